@@ -0,0 +1,205 @@
+//! Throughput benchmarks for the hash functions, MACs, AEADs and X25519.
+//!
+//! AES and GHASH pick between the hardware instructions and software at runtime, so the path in
+//! use is part of each benchmark's name, and the paths are compared by running the suite with
+//! different features into the same report:
+//!
+//! ```text
+//! cargo bench
+//! cargo bench --no-default-features --features alloc
+//! cargo bench --no-default-features --features alloc,aes-tables
+//! ```
+//!
+//! Without `std` there is no runtime detection, so the software paths are measured unless the
+//! instructions are enabled at compile time. Criterion's `--save-baseline` and `--baseline`
+//! options compare a run against an earlier one to catch regressions.
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use crypto_pure::aead::Aead;
+use crypto_pure::aes::{Aes128, Aes256};
+use crypto_pure::curve25519::{gen_pk, x25519};
+use crypto_pure::gcm::Gcm;
+use crypto_pure::ghash::GHash;
+use crypto_pure::hmac::Hmac;
+use crypto_pure::poly1305::ChaCha20Poly1305;
+use crypto_pure::sha2::{self, multi, HashFunction, Sha256, Sha512};
+use std::hint::black_box;
+
+const SIZES: [usize; 5] = [16, 64, 1024, 8192, 65536];
+
+/// Gets the path AES takes, following the selection in the `aes` module.
+fn aes_path() -> &'static str {
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    let hardware = if cfg!(feature = "std") {
+        is_x86_feature_detected!("aes") && is_x86_feature_detected!("sse2")
+    } else {
+        cfg!(all(target_feature = "aes", target_feature = "sse2"))
+    };
+    #[cfg(target_arch = "aarch64")]
+    let hardware = if cfg!(feature = "std") {
+        std::arch::is_aarch64_feature_detected!("aes")
+    } else {
+        cfg!(target_feature = "aes")
+    };
+    #[cfg(not(any(target_arch = "aarch64", target_arch = "x86", target_arch = "x86_64")))]
+    let hardware = false;
+    if hardware {
+        "hardware"
+    } else if cfg!(feature = "aes-tables") {
+        "tables"
+    } else {
+        "software"
+    }
+}
+
+/// Gets the path GHASH takes, following the selection in the `ghash` module.
+fn ghash_path() -> &'static str {
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    let hardware = if cfg!(feature = "std") {
+        is_x86_feature_detected!("pclmulqdq") && is_x86_feature_detected!("sse2")
+    } else {
+        cfg!(all(target_feature = "pclmulqdq", target_feature = "sse2"))
+    };
+    #[cfg(target_arch = "aarch64")]
+    let hardware = if cfg!(feature = "std") {
+        std::arch::is_aarch64_feature_detected!("pmull")
+    } else {
+        cfg!(target_feature = "aes")
+    };
+    #[cfg(not(any(target_arch = "aarch64", target_arch = "x86", target_arch = "x86_64")))]
+    let hardware = false;
+    if hardware {
+        "hardware"
+    } else {
+        "software"
+    }
+}
+
+fn bench_hash<H: HashFunction>(c: &mut Criterion, name: &str) {
+    let mut group = c.benchmark_group(name);
+    for &size in &SIZES {
+        let message = vec![0x42; size];
+        group.throughput(Throughput::Bytes(size as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(size), &message, |b, message| {
+            b.iter(|| {
+                let mut hash_function = H::default();
+                hash_function.update(black_box(message));
+                let digest = &mut [0; 64][..H::DIGEST_SIZE];
+                hash_function.write_digest(digest);
+                black_box(digest[0])
+            })
+        });
+    }
+    group.finish();
+}
+
+fn bench_sha2(c: &mut Criterion) {
+    bench_hash::<Sha256>(c, "sha256");
+    bench_hash::<Sha512>(c, "sha512");
+
+    // Many short messages, one at a time against four at once with AVX2 or NEON when available.
+    let mut group = c.benchmark_group("sha512_batch");
+    let messages = vec![[0x42; 64]; 64];
+    let messages: Vec<_> = messages.iter().map(|message| &message[..]).collect();
+    let digests = &mut vec![[0; 64]; messages.len()];
+    group.throughput(Throughput::Bytes(64 * messages.len() as u64));
+    group.bench_function("scalar", |b| {
+        b.iter(|| {
+            for (message, digest) in messages.iter().zip(digests.iter_mut()) {
+                *digest = sha2::sha512(black_box(message));
+            }
+        })
+    });
+    group.bench_function("multi", |b| {
+        b.iter(|| multi::sha512(black_box(&messages), digests))
+    });
+    group.finish();
+}
+
+fn bench_mac<H: HashFunction>(c: &mut Criterion, name: &str) {
+    let mut group = c.benchmark_group(name);
+    for &size in &SIZES {
+        let message = vec![0x42; size];
+        group.throughput(Throughput::Bytes(size as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(size), &message, |b, message| {
+            b.iter(|| {
+                let mut hmac = Hmac::<H>::new(&[0x24; 32]);
+                hmac.update(black_box(message));
+                black_box(hmac.tag()[0])
+            })
+        });
+    }
+    group.finish();
+}
+
+fn bench_hmac(c: &mut Criterion) {
+    bench_mac::<Sha256>(c, "hmac_sha256");
+    bench_mac::<Sha512>(c, "hmac_sha512");
+}
+
+fn bench_ghash(c: &mut Criterion) {
+    let mut group = c.benchmark_group("ghash");
+    for &size in &SIZES {
+        let message = vec![0x42; size];
+        group.throughput(Throughput::Bytes(size as u64));
+        let id = BenchmarkId::new(ghash_path(), size);
+        group.bench_with_input(id, &message, |b, message| {
+            b.iter(|| {
+                let mut ghash = GHash::new(&[0x24; 16]);
+                ghash.update_ciphertext(black_box(message));
+                ghash.finalize()
+            })
+        });
+    }
+    group.finish();
+}
+
+fn bench_aead<A: Aead>(c: &mut Criterion, name: &str, path: &str) {
+    let aead = A::new(&[0x24; 32][..A::KEY_LEN]).unwrap();
+    let nonce = &[0; 24][..A::NONCE_LEN];
+    let mut group = c.benchmark_group(name);
+    for &size in &SIZES {
+        let buffer = &mut vec![0x42; size];
+        group.throughput(Throughput::Bytes(size as u64));
+        group.bench_function(BenchmarkId::new(format!("{}/seal", path), size), |b| {
+            b.iter(|| aead.seal_detached(nonce, b"data", black_box(buffer)))
+        });
+        let tag = aead.seal_detached(nonce, b"data", buffer).unwrap();
+        let sealed = buffer.clone();
+        group.bench_function(BenchmarkId::new(format!("{}/open", path), size), |b| {
+            b.iter(|| {
+                buffer.copy_from_slice(&sealed);
+                aead.open_detached(nonce, b"data", black_box(buffer), &tag)
+                    .unwrap()
+            })
+        });
+    }
+    group.finish();
+}
+
+fn bench_aeads(c: &mut Criterion) {
+    let path = &format!("aes_{}+ghash_{}", aes_path(), ghash_path());
+    bench_aead::<Gcm<Aes128>>(c, "aes128_gcm", path);
+    bench_aead::<Gcm<Aes256>>(c, "aes256_gcm", path);
+    bench_aead::<ChaCha20Poly1305>(c, "chacha20_poly1305", "software");
+}
+
+fn bench_x25519(c: &mut Criterion) {
+    let mut group = c.benchmark_group("x25519");
+    let public_key = &gen_pk(&[0x24; 32]).unwrap();
+    group.throughput(Throughput::Elements(1));
+    group.bench_function("gen_pk", |b| b.iter(|| gen_pk(black_box(&[0x42; 32]))));
+    group.bench_function("x25519", |b| {
+        b.iter(|| x25519(black_box(&[0x42; 32]), public_key))
+    });
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_sha2,
+    bench_hmac,
+    bench_ghash,
+    bench_aeads,
+    bench_x25519
+);
+criterion_main!(benches);
@@ -0,0 +1,76 @@
+//! Parses DER of any structure, checking that it re-encodes to the same bytes.
+#![no_main]
+use crypto_pure::asn1::{Reader, Writer};
+use libfuzzer_sys::fuzz_target;
+
+const BOOLEAN: u8 = 0x01;
+const INTEGER: u8 = 0x02;
+const BIT_STRING: u8 = 0x03;
+const OID: u8 = 0x06;
+const CONSTRUCTED: u8 = 0x20;
+
+fuzz_target!(|input: &[u8]| {
+    let mut writer = Writer::new();
+    if reencode(Reader::new(input), &mut writer).is_some() {
+        assert_eq!(input, &writer.into_vec()[..]);
+    }
+});
+
+/// Re-encodes every encoding of `reader`, recursing into constructed ones, or outputs `None` if
+/// any fails to parse.
+fn reencode(mut reader: Reader, writer: &mut Writer) -> Option<()> {
+    while !reader.is_empty() {
+        let (tag, contents) = reader.read_any().ok()?;
+        if tag & CONSTRUCTED != 0 {
+            let mut result = Some(());
+            writer.write_constructed(tag, |writer| {
+                result = reencode(Reader::new(contents), writer);
+            });
+            result?;
+            continue;
+        }
+        check_primitive(tag, contents);
+        writer.write(tag, contents);
+    }
+    Some(())
+}
+
+/// Runs the parser of a primitive type on contents with its tag, checking that what parses
+/// re-encodes the same way.
+fn check_primitive(tag: u8, contents: &[u8]) {
+    let mut encoding = Writer::new();
+    encoding.write(tag, contents);
+    let encoding = &encoding.into_vec();
+    let mut reader = Reader::new(encoding);
+    let mut writer = Writer::new();
+    match tag {
+        BOOLEAN => match reader.read_boolean() {
+            Ok(value) => writer.write_boolean(value),
+            Err(_) => return,
+        },
+        INTEGER => match reader.read_unsigned_integer() {
+            Ok(magnitude) => writer.write_unsigned_integer(magnitude),
+            Err(_) => {
+                let _ = Reader::new(encoding).read_integer();
+                return;
+            }
+        },
+        BIT_STRING => match reader.read_bit_string() {
+            Ok(bits) => {
+                let _ = bits.octets();
+                writer.write_bit_string(bits);
+            }
+            Err(_) => return,
+        },
+        OID => match reader.read_oid() {
+            Ok(oid) => {
+                let _ = oid.arcs().count();
+                let _ = oid.to_string();
+                writer.write_oid(oid);
+            }
+            Err(_) => return,
+        },
+        _ => return,
+    }
+    assert_eq!(encoding, &writer.into_vec());
+}
@@ -0,0 +1,38 @@
+//! Opens sealed messages with AES-GCM, checking that the allocating and in-place paths agree,
+//! that a failure leaves the buffer unchanged, and that what opens seals back to the input.
+#![no_main]
+use crypto_pure::aead::Aead;
+use crypto_pure::aes::{Aes128, Aes192, Aes256, BlockCipher};
+use crypto_pure::gcm::Gcm;
+use crypto_pure_fuzz::GcmInput;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|input: &[u8]| {
+    if let Some(input) = GcmInput::parse(input) {
+        match input.key.len() {
+            16 => check::<Aes128>(&input),
+            24 => check::<Aes192>(&input),
+            _ => check::<Aes256>(&input),
+        }
+    }
+});
+
+fn check<E: BlockCipher>(input: &GcmInput) {
+    let gcm = match Gcm::<E>::new(input.key) {
+        Ok(gcm) => gcm,
+        Err(_) => return,
+    };
+    let opened = gcm.open(input.nonce, input.data, input.sealed);
+    let buffer = &mut input.sealed.to_vec();
+    match gcm.open_in_place(input.nonce, input.data, buffer) {
+        Ok(message) => {
+            assert_eq!(Ok(&message[..]), opened.as_deref());
+            let sealed = gcm.seal(input.nonce, input.data, message).unwrap();
+            assert_eq!(input.sealed, &sealed[..]);
+        }
+        Err(error) => {
+            assert_eq!(Err(error), opened);
+            assert_eq!(input.sealed, &buffer[..]);
+        }
+    }
+}
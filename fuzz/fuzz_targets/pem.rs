@@ -0,0 +1,22 @@
+//! Parses PEM in both modes, checking that what parses re-encodes to strict PEM with the same
+//! label and contents.
+#![no_main]
+use crypto_pure::encoding::pem::{self, Mode};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|input: &[u8]| {
+    let input = match std::str::from_utf8(input) {
+        Ok(input) => input,
+        Err(_) => return,
+    };
+    for &mode in &[Mode::Strict, Mode::Lenient] {
+        if let Ok(blocks) = pem::parse_all(input, mode) {
+            for block in &blocks {
+                let reparsed = pem::parse(&pem::encode(block), Mode::Strict).unwrap();
+                assert_eq!(block.label(), reparsed.label());
+                assert_eq!(block.contents(), reparsed.contents());
+            }
+        }
+        let _ = pem::parse(input, mode);
+    }
+});
@@ -0,0 +1,19 @@
+//! Decodes PKCS#8 private keys and SubjectPublicKeyInfo public keys, checking that what decodes
+//! re-encodes to DER that decodes to the same encoding.
+#![no_main]
+use crypto_pure::pkcs8;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|input: &[u8]| {
+    if let Ok(key) = pkcs8::decode_private_key(input) {
+        let der = pkcs8::encode_private_key(&key).unwrap();
+        let key = pkcs8::decode_private_key(&der).unwrap();
+        assert_eq!(&der[..], &pkcs8::encode_private_key(&key).unwrap()[..]);
+        let _ = key.public_key();
+    }
+    if let Ok(key) = pkcs8::decode_public_key(input) {
+        let der = pkcs8::encode_public_key(&key);
+        let key = pkcs8::decode_public_key(&der).unwrap();
+        assert_eq!(der, pkcs8::encode_public_key(&key));
+    }
+});
@@ -0,0 +1,34 @@
+//! Unprotects a stream of TLS 1.3 records with one of the cipher suites, updating the traffic
+//! secret after each KeyUpdate message.
+#![no_main]
+use crypto_pure::sha2::HashFunction;
+use crypto_pure::tls13::record::{ContentType, KeyUpdate, RecordProtection};
+use crypto_pure::tls13::{Aes128GcmSha256, Aes256GcmSha384, ChaCha20Poly1305Sha256, CipherSuite};
+use crypto_pure_fuzz::{next_record, TRAFFIC_SECRET};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|input: &[u8]| {
+    let input = &mut input.to_vec();
+    if let Some((suite, records)) = input.split_first_mut() {
+        match *suite % 3 {
+            0 => check::<Aes128GcmSha256>(records),
+            1 => check::<Aes256GcmSha384>(records),
+            _ => check::<ChaCha20Poly1305Sha256>(records),
+        }
+    }
+});
+
+fn check<S: CipherSuite>(mut records: &mut [u8]) {
+    let traffic_secret = &TRAFFIC_SECRET[..S::Hash::DIGEST_SIZE];
+    let mut receiver = RecordProtection::<S>::new(traffic_secret).unwrap();
+    while let Some(record) = next_record(&mut records) {
+        let sequence = receiver.sequence();
+        match receiver.unprotect(record) {
+            Ok((ContentType::Handshake, content)) if KeyUpdate::decode(content).is_ok() => {
+                receiver.update_traffic_secret();
+            }
+            Ok(_) => assert_eq!(sequence + 1, receiver.sequence()),
+            Err(_) => return,
+        }
+    }
+}
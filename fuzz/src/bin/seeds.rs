@@ -0,0 +1,149 @@
+//! Writes corpus seeds for each fuzz target into `corpus/<target>`, built with the crate's own
+//! encoders:
+//!
+//! ```text
+//! cargo run --release --bin seeds
+//! cargo +nightly fuzz run pkcs8
+//! ```
+use crypto_pure::aead::Aead;
+use crypto_pure::aes::{Aes128, Aes192, Aes256};
+use crypto_pure::asn1::{BitString, Oid, Writer};
+use crypto_pure::encoding::pem::{self, Pem};
+use crypto_pure::gcm::Gcm;
+use crypto_pure::pkcs8::{self, PrivateKey};
+use crypto_pure::rand::SystemRandom;
+use crypto_pure::rsa;
+use crypto_pure::secret::SecretKey;
+use crypto_pure::sha2::HashFunction;
+use crypto_pure::tls13::record::{ContentType, KeyUpdate, RecordProtection};
+use crypto_pure::tls13::{Aes128GcmSha256, Aes256GcmSha384, ChaCha20Poly1305Sha256, CipherSuite};
+use crypto_pure_fuzz::{GcmInput, TRAFFIC_SECRET};
+use std::fs;
+use std::path::Path;
+
+fn main() {
+    let corpus = Path::new(env!("CARGO_MANIFEST_DIR")).join("corpus");
+    let write = |target: &str, name: &str, seed: &[u8]| {
+        let dir = corpus.join(target);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join(name), seed).unwrap();
+    };
+
+    let keys = [
+        ("ed25519", PrivateKey::Ed25519(SecretKey::new([0x42; 32]))),
+        ("x25519", PrivateKey::X25519(SecretKey::new([0x42; 32]))),
+        ("p256", PrivateKey::P256(SecretKey::new([0x42; 32]))),
+        ("p384", PrivateKey::P384(SecretKey::new([0x42; 48]))),
+        (
+            "rsa",
+            PrivateKey::Rsa(Box::new(rsa::PrivateKey::generate(
+                2048,
+                &SystemRandom::new(),
+            ))),
+        ),
+    ];
+    let mut blocks = Vec::new();
+    for (name, key) in &keys {
+        let private_key = pkcs8::encode_private_key(key).unwrap();
+        let public_key = pkcs8::encode_public_key(&key.public_key().unwrap());
+        for (kind, der, label) in [
+            ("private", &private_key[..], "PRIVATE KEY"),
+            ("public", &public_key[..], "PUBLIC KEY"),
+        ] {
+            let name = &format!("{}_{}", name, kind);
+            write("asn1", name, der);
+            write("pkcs8", name, der);
+            let block = pem::encode(&Pem::new(label, der.to_vec()).unwrap());
+            write("pem", name, block.as_bytes());
+            blocks.push(block);
+        }
+    }
+    write("pem", "all", blocks.concat().as_bytes());
+    write("asn1", "types", &asn1_types());
+
+    for (name, key_len) in [("aes128", 16), ("aes192", 24), ("aes256", 32)] {
+        let key = &[0x42; 32][..key_len];
+        for (message, data) in [(&b""[..], &b""[..]), (b"message", b"data")] {
+            let sealed = &match key_len {
+                16 => Gcm::<Aes128>::new(key)
+                    .unwrap()
+                    .seal(&[0x24; 12], data, message),
+                24 => Gcm::<Aes192>::new(key)
+                    .unwrap()
+                    .seal(&[0x24; 12], data, message),
+                _ => Gcm::<Aes256>::new(key)
+                    .unwrap()
+                    .seal(&[0x24; 12], data, message),
+            }
+            .unwrap();
+            let input = GcmInput {
+                key,
+                nonce: &[0x24; 12],
+                data,
+                sealed,
+            };
+            let name = &format!("{}_{}", name, message.len());
+            write("gcm_open", name, &input.to_bytes());
+        }
+    }
+
+    write(
+        "tls_record",
+        "aes128gcm",
+        &tls_records::<Aes128GcmSha256>(0),
+    );
+    write(
+        "tls_record",
+        "aes256gcm",
+        &tls_records::<Aes256GcmSha384>(1),
+    );
+    write(
+        "tls_record",
+        "chacha20",
+        &tls_records::<ChaCha20Poly1305Sha256>(2),
+    );
+}
+
+/// Encodes a structure with every type the `asn1` module writes.
+fn asn1_types() -> Vec<u8> {
+    let mut writer = Writer::new();
+    writer.write_sequence(|writer| {
+        writer.write_boolean(true);
+        writer.write_null();
+        writer.write_u64(0);
+        writer.write_unsigned_integer(&[0x80; 200]);
+        writer.write_octet_string(b"octets");
+        writer.write_bit_string(BitString::from_contents(&[3, 0xf8]).unwrap());
+        writer.write_oid(Oid::new(&[0x2b, 0x65, 0x70]).unwrap());
+        writer.write_explicit(0, |writer| {
+            writer.write_set(|writer| writer.write_boolean(false));
+        });
+    });
+    writer.into_vec()
+}
+
+/// Protects a stream of records with different types and padding, including a KeyUpdate, after
+/// the index of the cipher suite.
+fn tls_records<S: CipherSuite>(suite: u8) -> Vec<u8> {
+    let traffic_secret = &TRAFFIC_SECRET[..S::Hash::DIGEST_SIZE];
+    let mut sender = RecordProtection::<S>::new(traffic_secret).unwrap();
+    let mut records = vec![suite];
+    let key_update = &KeyUpdate::UpdateNotRequested.encode();
+    let contents = [
+        (ContentType::ApplicationData, &b"message"[..], 0),
+        (ContentType::Handshake, key_update, 0),
+        (ContentType::ApplicationData, b"after the update", 10),
+        (ContentType::Alert, &[1, 0], 0),
+    ];
+    for (content_type, content, padding) in contents {
+        let record = &mut vec![0; RecordProtection::<S>::record_len(content.len(), padding)];
+        sender
+            .protect(content_type, content, padding, record)
+            .unwrap();
+        records.extend_from_slice(record);
+        if content_type == ContentType::Handshake {
+            sender.update_traffic_secret();
+        }
+    }
+    records
+}
@@ -0,0 +1,72 @@
+//! Input layouts shared by the fuzz targets and the `seeds` binary, which writes corpus seeds
+//! built with the crate's own encoders so that fuzzing starts from valid inputs.
+use crypto_pure::tls13::record::{parse_header, HEADER_LEN};
+
+/// The traffic secret of the records of the `tls_record` target, truncated to the digest size of
+/// the cipher suite.
+pub const TRAFFIC_SECRET: [u8; 48] = [0x42; 48];
+
+/// The input of the `gcm_open` target.
+pub struct GcmInput<'a> {
+    pub key: &'a [u8],
+    pub nonce: &'a [u8],
+    pub data: &'a [u8],
+    pub sealed: &'a [u8],
+}
+
+impl<'a> GcmInput<'a> {
+    /// Splits an input that starts with the lengths of the key, the nonce and the data, followed
+    /// by each of them and then the sealed message.
+    pub fn parse(input: &'a [u8]) -> Option<Self> {
+        let (lens, rest) = split(input, 3)?;
+        let (key, rest) = split(rest, lens[0].into())?;
+        let (nonce, rest) = split(rest, lens[1].into())?;
+        let (data, sealed) = split(rest, lens[2].into())?;
+        Some(Self {
+            key,
+            nonce,
+            data,
+            sealed,
+        })
+    }
+
+    /// Outputs the input that `parse` splits into this one.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the key, the nonce or the data is longer than 255 bytes.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let lens = [self.key.len(), self.nonce.len(), self.data.len()];
+        assert!(lens.iter().all(|&len| len <= 255));
+        let mut input: Vec<_> = lens.iter().map(|&len| len as u8).collect();
+        for part in &[self.key, self.nonce, self.data, self.sealed] {
+            input.extend_from_slice(part);
+        }
+        input
+    }
+}
+
+/// Splits the next record off the start of the records of the `tls_record` target, which follow
+/// the index of the cipher suite. Outputs `None` if the header doesn't parse or the record is
+/// cut short.
+pub fn next_record<'a>(records: &mut &'a mut [u8]) -> Option<&'a mut [u8]> {
+    if records.len() < HEADER_LEN {
+        return None;
+    }
+    let mut header = [0; HEADER_LEN];
+    header.copy_from_slice(&records[..HEADER_LEN]);
+    let (_, len) = parse_header(&header).ok()?;
+    if records.len() < HEADER_LEN + len {
+        return None;
+    }
+    let (record, rest) = std::mem::take(records).split_at_mut(HEADER_LEN + len);
+    *records = rest;
+    Some(record)
+}
+
+fn split(input: &[u8], len: usize) -> Option<(&[u8], &[u8])> {
+    if input.len() < len {
+        return None;
+    }
+    Some(input.split_at(len))
+}
@@ -0,0 +1,338 @@
+//! Module for the interface shared by authenticated encryption with associated data (AEAD)
+//! ciphers, such as AES-GCM and ChaCha20-Poly1305.
+use self::nonce::{Nonce, NonceSequence};
+use crate::error::Error;
+#[cfg(feature = "alloc")]
+use alloc::{vec, vec::Vec};
+
+pub mod nonce;
+pub mod stream;
+
+/// An AEAD cipher with tags of up to 16 bytes.
+///
+/// Tags are passed as 16-byte arrays. A tag shorter than 16 bytes is stored at the start of the
+/// array, followed by zeros.
+///
+/// Methods fail with `Error::InvalidLength` if a nonce has the wrong length or a buffer or the
+/// data are too long for the cipher, and openings fail with `Error::TagMismatch` if verification
+/// fails.
+///
+/// # Examples
+///
+/// ```
+/// use crypto_pure::aead::Aead;
+/// use crypto_pure::aes::Aes128;
+/// use crypto_pure::gcm::Gcm;
+/// use crypto_pure::poly1305::ChaCha20Poly1305;
+///
+/// fn round_trip<A: Aead>() {
+///     let cipher = A::new(&vec![0x42; A::KEY_LEN]).unwrap();
+///     let nonce = &vec![0x24; A::NONCE_LEN];
+///     let buffer = &mut [b"message" as &[u8], &[0; 16]].concat();
+///     cipher.seal_in_place(nonce, b"header", buffer).unwrap();
+///     let opened = cipher.open_in_place(nonce, b"header", buffer);
+///     assert_eq!(Ok(&mut b"message".to_vec()[..]), opened);
+/// }
+///
+/// round_trip::<Gcm<Aes128>>();
+/// round_trip::<ChaCha20Poly1305>();
+/// ```
+pub trait Aead {
+    /// The length of a key in bytes.
+    const KEY_LEN: usize;
+
+    /// The length of a nonce in bytes.
+    const NONCE_LEN: usize;
+
+    /// The length of a tag in bytes.
+    const TAG_LEN: usize = 16;
+
+    /// Initializes an AEAD cipher given a key, or fails with `Error::InvalidKey` if `key.len()`
+    /// is not `KEY_LEN`.
+    fn new(key: &[u8]) -> Result<Self, Error>
+    where
+        Self: Sized;
+
+    /// Encrypts a message into a ciphertext and outputs a tag authenticating it and provided data.
+    /// Fails with `Error::InvalidLength` if `input.len()` is not equal to `output.len()`.
+    fn encrypt(
+        &self,
+        input: &[u8],
+        nonce: &[u8],
+        data: &[u8],
+        output: &mut [u8],
+    ) -> Result<[u8; 16], Error> {
+        if input.len() != output.len() {
+            return Err(Error::InvalidLength);
+        }
+        output.copy_from_slice(input);
+        self.seal_detached(nonce, data, output)
+    }
+
+    /// Decrypts a ciphertext into a message if tag verification passes. Fails with
+    /// `Error::InvalidLength` if `input.len()` is not equal to `output.len()`, and `output` is
+    /// zeroed if verification fails.
+    fn decrypt(
+        &self,
+        input: &[u8],
+        nonce: &[u8],
+        data: &[u8],
+        tag: &[u8],
+        output: &mut [u8],
+    ) -> Result<(), Error> {
+        if input.len() != output.len() {
+            return Err(Error::InvalidLength);
+        }
+        if tag.len() != Self::TAG_LEN {
+            return Err(Error::TagMismatch);
+        }
+        let mut tag_copy = [0; 16];
+        tag_copy[..Self::TAG_LEN].copy_from_slice(tag);
+        output.copy_from_slice(input);
+        let result = self.open_detached(nonce, data, output, &tag_copy);
+        if result.is_err() {
+            output.iter_mut().for_each(|byte| *byte = 0);
+        }
+        result
+    }
+
+    /// Encrypts a message in place and outputs a tag authenticating it and provided data.
+    fn seal_detached(
+        &self,
+        nonce: &[u8],
+        data: &[u8],
+        buffer: &mut [u8],
+    ) -> Result<[u8; 16], Error>;
+
+    /// Verifies `tag` and decrypts a ciphertext in place. On failure, `buffer` is left unchanged.
+    fn open_detached(
+        &self,
+        nonce: &[u8],
+        data: &[u8],
+        buffer: &mut [u8],
+        tag: &[u8; 16],
+    ) -> Result<(), Error>;
+
+    /// Encrypts the message at the start of `buffer` in place and writes the tag into the last
+    /// `TAG_LEN` bytes, which must be reserved for it. Fails with `Error::InvalidLength` without
+    /// changing `buffer` if `buffer.len()` is less than `TAG_LEN`.
+    fn seal_in_place(&self, nonce: &[u8], data: &[u8], buffer: &mut [u8]) -> Result<(), Error> {
+        if buffer.len() < Self::TAG_LEN {
+            return Err(Error::InvalidLength);
+        }
+        let (message, tag) = buffer.split_at_mut(buffer.len() - Self::TAG_LEN);
+        tag.copy_from_slice(&self.seal_detached(nonce, data, message)?[..Self::TAG_LEN]);
+        Ok(())
+    }
+
+    /// Verifies the tag in the last `TAG_LEN` bytes of `buffer` and decrypts the ciphertext
+    /// before it in place, outputting the message part of `buffer`. On failure, `buffer` is left
+    /// unchanged.
+    fn open_in_place<'a>(
+        &self,
+        nonce: &[u8],
+        data: &[u8],
+        buffer: &'a mut [u8],
+    ) -> Result<&'a mut [u8], Error> {
+        if buffer.len() < Self::TAG_LEN {
+            return Err(Error::InvalidLength);
+        }
+        let (ciphertext, tag) = buffer.split_at_mut(buffer.len() - Self::TAG_LEN);
+        let mut tag_copy = [0; 16];
+        tag_copy[..Self::TAG_LEN].copy_from_slice(tag);
+        self.open_detached(nonce, data, ciphertext, &tag_copy)?;
+        Ok(ciphertext)
+    }
+
+    /// Encrypts a message and outputs the ciphertext with the tag appended.
+    #[cfg(feature = "alloc")]
+    fn seal(&self, nonce: &[u8], data: &[u8], message: &[u8]) -> Result<Vec<u8>, Error> {
+        let mut sealed = vec![0; message.len() + Self::TAG_LEN];
+        sealed[..message.len()].copy_from_slice(message);
+        self.seal_in_place(nonce, data, &mut sealed)?;
+        Ok(sealed)
+    }
+
+    /// Decrypts the output of `seal`, verifying its tag in constant time.
+    #[cfg(feature = "alloc")]
+    fn open(&self, nonce: &[u8], data: &[u8], sealed: &[u8]) -> Result<Vec<u8>, Error> {
+        let mut buffer = sealed.to_vec();
+        let len = self.open_in_place(nonce, data, &mut buffer)?.len();
+        buffer.truncate(len);
+        Ok(buffer)
+    }
+}
+
+/// An AEAD key for sealing messages, which takes each nonce from a sequence so that no nonce is
+/// used twice.
+///
+/// # Examples
+///
+/// ```
+/// use crypto_pure::aead::nonce::Counter;
+/// use crypto_pure::aead::{OpeningKey, SealingKey};
+/// use crypto_pure::poly1305::ChaCha20Poly1305;
+/// # let key = &[0x42; 32];
+/// let mut sealing_key = SealingKey::<ChaCha20Poly1305, _>::new(key, Counter::new()).unwrap();
+/// let mut opening_key = OpeningKey::<ChaCha20Poly1305, _>::new(key, Counter::new()).unwrap();
+/// for message in &[&b"first"[..], b"second"] {
+///     let buffer = &mut [message, &[0; 16][..]].concat();
+///     sealing_key.seal_in_place(b"header", buffer).unwrap();
+///     let opened = opening_key.open_in_place(b"header", buffer);
+///     assert_eq!(Ok(&mut message.to_vec()[..]), opened);
+/// }
+/// ```
+pub struct SealingKey<A, N> {
+    cipher: A,
+    nonces: N,
+}
+
+impl<A: Aead, N: NonceSequence> SealingKey<A, N> {
+    /// Initializes a sealing key given a key and the sequence of nonces to use with it, or fails
+    /// with `Error::InvalidKey` if `key.len()` is not `A::KEY_LEN`.
+    pub fn new(key: &[u8], nonces: N) -> Result<Self, Error> {
+        Ok(Self {
+            cipher: A::new(key)?,
+            nonces,
+        })
+    }
+
+    /// Encrypts a message in place with the next nonce, outputting the nonce and a tag
+    /// authenticating the message and provided data. Fails without changing `buffer` if the
+    /// nonce sequence is exhausted.
+    pub fn seal_detached(
+        &mut self,
+        data: &[u8],
+        buffer: &mut [u8],
+    ) -> Result<(Nonce, [u8; 16]), Error> {
+        let nonce = Nonce::next(&mut self.nonces, A::NONCE_LEN)?;
+        let tag = self.cipher.seal_detached(&nonce, data, buffer)?;
+        Ok((nonce, tag))
+    }
+
+    /// Encrypts the message at the start of `buffer` in place with the next nonce, writing the tag
+    /// into the last `A::TAG_LEN` bytes, and outputs the nonce. Fails without changing `buffer`
+    /// if `buffer.len()` is less than `A::TAG_LEN` or the nonce sequence is exhausted.
+    pub fn seal_in_place(&mut self, data: &[u8], buffer: &mut [u8]) -> Result<Nonce, Error> {
+        if buffer.len() < A::TAG_LEN {
+            return Err(Error::InvalidLength);
+        }
+        let nonce = Nonce::next(&mut self.nonces, A::NONCE_LEN)?;
+        self.cipher.seal_in_place(&nonce, data, buffer)?;
+        Ok(nonce)
+    }
+}
+
+/// An AEAD key for opening messages sealed by a `SealingKey` with the same nonce sequence.
+///
+/// Random nonces can't be reproduced, so messages sealed with them should be opened with
+/// `Aead::open_in_place` and the nonce sent alongside the message.
+pub struct OpeningKey<A, N> {
+    cipher: A,
+    nonces: N,
+}
+
+impl<A: Aead, N: NonceSequence> OpeningKey<A, N> {
+    /// Initializes an opening key given a key and the sequence of nonces used with it, or fails
+    /// with `Error::InvalidKey` if `key.len()` is not `A::KEY_LEN`.
+    pub fn new(key: &[u8], nonces: N) -> Result<Self, Error> {
+        Ok(Self {
+            cipher: A::new(key)?,
+            nonces,
+        })
+    }
+
+    /// Verifies the tag in the last `A::TAG_LEN` bytes of `buffer` and decrypts the ciphertext
+    /// before it in place with the next nonce, outputting the message part of `buffer`. On
+    /// failure, `buffer` is left unchanged. The nonce is used up either way, and opening fails
+    /// if the nonce sequence is exhausted.
+    pub fn open_in_place<'a>(
+        &mut self,
+        data: &[u8],
+        buffer: &'a mut [u8],
+    ) -> Result<&'a mut [u8], Error> {
+        let nonce = Nonce::next(&mut self.nonces, A::NONCE_LEN)?;
+        self.cipher.open_in_place(&nonce, data, buffer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::nonce::{NonceError, XorIv};
+    use super::*;
+    use crate::aes::Aes128;
+    use crate::gcm::Gcm;
+
+    struct Once(bool);
+
+    impl NonceSequence for Once {
+        fn advance(&mut self, nonce: &mut [u8]) -> Result<(), NonceError> {
+            if self.0 {
+                return Err(NonceError);
+            }
+            nonce.iter_mut().for_each(|byte| *byte = 0);
+            self.0 = true;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_keys() {
+        let key = &[0x42; 16];
+        let iv = &[0x24; 12];
+        let nonces = XorIv::new(iv).unwrap();
+        let mut sealing_key = SealingKey::<Gcm<Aes128>, _>::new(key, nonces).unwrap();
+        let nonces = XorIv::new(iv).unwrap();
+        let mut opening_key = OpeningKey::<Gcm<Aes128>, _>::new(key, nonces).unwrap();
+        let cipher = Gcm::<Aes128>::new(key).unwrap();
+
+        let first = &mut [1; 21];
+        assert_eq!(iv, &*sealing_key.seal_in_place(b"", first).unwrap());
+        let second = &mut [2; 5];
+        let (nonce, tag) = sealing_key.seal_detached(b"", second).unwrap();
+        assert_eq!(0x25, nonce[11]);
+        assert_eq!(Ok(tag), cipher.seal_detached(&nonce, b"", &mut [2; 5]));
+
+        assert_eq!(Ok(&mut [1; 5][..]), opening_key.open_in_place(b"", first));
+        let sealed = &mut [&second[..], &tag].concat();
+        let sealed_copy = &mut sealed.clone();
+        let error = Err(Error::TagMismatch);
+        assert_eq!(error, opening_key.open_in_place(b"x", sealed));
+        assert_eq!(sealed_copy, sealed);
+        // The failed attempt used up the nonce for the second message.
+        assert_eq!(error, opening_key.open_in_place(b"", sealed));
+
+        let short = &mut [3; 15];
+        assert_eq!(
+            Err(Error::InvalidLength),
+            sealing_key.seal_in_place(b"", short)
+        );
+        assert_eq!(&[3; 15], short);
+        // The failed attempt did not use up a nonce.
+        assert_eq!(
+            0x26,
+            sealing_key.seal_in_place(b"", &mut [3; 16]).unwrap()[11]
+        );
+    }
+
+    #[test]
+    fn test_exhausted() {
+        let key = &[0x42; 16];
+        let mut sealing_key = SealingKey::<Gcm<Aes128>, _>::new(key, Once(false)).unwrap();
+        let mut opening_key = OpeningKey::<Gcm<Aes128>, _>::new(key, Once(false)).unwrap();
+        let sealed = &mut [1; 20];
+        sealing_key.seal_in_place(b"", sealed).unwrap();
+        let buffer = &mut [1; 20];
+        let error = Err(Error::NonceExhausted);
+        assert_eq!(error, sealing_key.seal_in_place(b"", buffer).map(|_| ()));
+        assert_eq!(error, sealing_key.seal_detached(b"", buffer).map(|_| ()));
+        assert_eq!(&[1; 20], buffer);
+
+        let sealed_copy = &mut sealed.clone();
+        assert_eq!(Ok(&mut [1; 4][..]), opening_key.open_in_place(b"", sealed));
+        assert_eq!(
+            Err(Error::NonceExhausted),
+            opening_key.open_in_place(b"", sealed_copy)
+        );
+    }
+}
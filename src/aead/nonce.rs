@@ -0,0 +1,310 @@
+//! Module for generating AEAD nonces, so that a nonce is never reused with the same key.
+use crate::error::Error;
+use crate::rand::SecureRandom;
+use core::fmt;
+use core::ops::Deref;
+
+pub(crate) const MAX_NONCE_LEN: usize = 24;
+
+/// A nonce that derefs into a slice of bytes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Nonce {
+    buffer: [u8; MAX_NONCE_LEN],
+    len: usize,
+}
+
+impl Nonce {
+    /// Obtains the next nonce of length `len` from a sequence.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::InvalidLength` if `len` is greater than 24, or with
+    /// `Error::NonceExhausted` if the sequence is exhausted.
+    pub fn next<N: NonceSequence>(nonces: &mut N, len: usize) -> Result<Self, Error> {
+        if len > MAX_NONCE_LEN {
+            return Err(Error::InvalidLength);
+        }
+        let mut buffer = [0; MAX_NONCE_LEN];
+        nonces.advance(&mut buffer[..len])?;
+        Ok(Self { buffer, len })
+    }
+}
+
+impl Deref for Nonce {
+    type Target = [u8];
+
+    fn deref(&self) -> &Self::Target {
+        &self.buffer[..self.len]
+    }
+}
+
+impl AsRef<[u8]> for Nonce {
+    fn as_ref(&self) -> &[u8] {
+        self
+    }
+}
+
+/// An error returned when a nonce sequence has no nonces left.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NonceError;
+
+impl fmt::Display for NonceError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("nonce sequence exhausted")
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for NonceError {}
+
+/// A source of nonces that are distinct from each other, at least with overwhelming probability.
+pub trait NonceSequence {
+    /// Writes the next nonce into `nonce`, or fails without changing `nonce` if the sequence is
+    /// exhausted.
+    fn advance(&mut self, nonce: &mut [u8]) -> Result<(), NonceError>;
+}
+
+/// A sequence of nonces that are big-endian integers counting up from zero.
+///
+/// # Examples
+///
+/// ```
+/// use crypto_pure::aead::nonce::{Counter, NonceSequence};
+/// let mut nonces = Counter::new();
+/// let nonce = &mut [0xff; 12];
+/// nonces.advance(nonce).unwrap();
+/// assert_eq!(&[0; 12], nonce);
+/// nonces.advance(nonce).unwrap();
+/// assert_eq!(1, nonce[11]);
+/// ```
+pub struct Counter {
+    next: Option<u64>,
+}
+
+impl Counter {
+    /// Initializes a sequence starting at zero.
+    pub fn new() -> Self {
+        Self { next: Some(0) }
+    }
+}
+
+impl Default for Counter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl NonceSequence for Counter {
+    /// Fails once the counter no longer fits in `nonce` or 2^64 nonces have been generated.
+    fn advance(&mut self, nonce: &mut [u8]) -> Result<(), NonceError> {
+        let value = self.next.ok_or(NonceError)?;
+        let len = nonce.len().min(8);
+        if len < 8 && value >> (8 * len) != 0 {
+            return Err(NonceError);
+        }
+        let (zeros, counter) = nonce.split_at_mut(nonce.len() - len);
+        zeros.iter_mut().for_each(|byte| *byte = 0);
+        counter.copy_from_slice(&value.to_be_bytes()[8 - len..]);
+        self.next = value.checked_add(1);
+        Ok(())
+    }
+}
+
+/// A sequence of random nonces, which stops while the probability of any two nonces colliding is
+/// still below 2^-32.
+///
+/// For 12-byte nonces, this allows 2^32 nonces, as recommended for GCM by NIST SP 800-38D.
+/// Longer nonces, such as the 24-byte nonces of XChaCha20-Poly1305, allow many more.
+///
+/// # Examples
+///
+/// ```
+/// use crypto_pure::aead::nonce::{NonceSequence, Random};
+/// use crypto_pure::rand::SystemRandom;
+/// let mut nonces = Random::new(SystemRandom::new());
+/// let nonce = &mut [0; 24];
+/// nonces.advance(nonce).unwrap();
+/// ```
+pub struct Random<R> {
+    rng: R,
+    count: u64,
+}
+
+impl<R: SecureRandom> Random<R> {
+    /// Initializes a sequence drawing nonces from `rng`.
+    pub fn new(rng: R) -> Self {
+        Self { rng, count: 0 }
+    }
+}
+
+impl<R: SecureRandom> NonceSequence for Random<R> {
+    /// Fails once the number of nonces generated reaches the limit for `nonce.len()`.
+    fn advance(&mut self, nonce: &mut [u8]) -> Result<(), NonceError> {
+        if self.count >= random_limit(nonce.len()) {
+            return Err(NonceError);
+        }
+        self.rng.fill(nonce);
+        self.count += 1;
+        Ok(())
+    }
+}
+
+/// Outputs the number of random `len`-byte nonces for which the probability of a collision,
+/// which is at most n^2 / 2^(8 len + 1), stays below 2^-32.
+fn random_limit(len: usize) -> u64 {
+    let bits = 8 * len;
+    if bits <= 32 {
+        1
+    } else if bits >= 160 {
+        u64::MAX
+    } else {
+        1 << ((bits - 32) / 2)
+    }
+}
+
+/// A sequence of nonces formed by XORing a fixed IV with a big-endian sequence number counting up
+/// from zero, as in TLS 1.3 (RFC 8446).
+///
+/// # Examples
+///
+/// ```
+/// use crypto_pure::aead::nonce::{NonceSequence, XorIv};
+/// let mut nonces = XorIv::new(&[0x42; 12])?;
+/// let nonce = &mut [0; 12];
+/// nonces.advance(nonce).unwrap();
+/// assert_eq!(&[0x42; 12], nonce);
+/// nonces.advance(nonce).unwrap();
+/// assert_eq!(0x43, nonce[11]);
+/// # Ok::<(), crypto_pure::error::Error>(())
+/// ```
+pub struct XorIv {
+    iv: [u8; MAX_NONCE_LEN],
+    len: usize,
+    sequence: Counter,
+}
+
+impl XorIv {
+    /// Initializes a sequence given an IV as long as the nonces to generate.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::InvalidLength` if `iv.len()` is less than 8 or greater than 24.
+    pub fn new(iv: &[u8]) -> Result<Self, Error> {
+        if !(8..=MAX_NONCE_LEN).contains(&iv.len()) {
+            return Err(Error::InvalidLength);
+        }
+        let mut buffer = [0; MAX_NONCE_LEN];
+        buffer[..iv.len()].copy_from_slice(iv);
+        Ok(Self {
+            iv: buffer,
+            len: iv.len(),
+            sequence: Counter::new(),
+        })
+    }
+}
+
+impl NonceSequence for XorIv {
+    /// Fails once 2^64 nonces have been generated. The sequence has no nonces of any length other
+    /// than that of the IV, so it also fails if `nonce.len()` differs.
+    fn advance(&mut self, nonce: &mut [u8]) -> Result<(), NonceError> {
+        if nonce.len() != self.len {
+            return Err(NonceError);
+        }
+        let sequence = &mut [0; MAX_NONCE_LEN][..self.len];
+        self.sequence.advance(sequence)?;
+        for ((byte, iv_byte), sequence_byte) in nonce.iter_mut().zip(&self.iv).zip(&*sequence) {
+            *byte = iv_byte ^ sequence_byte;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::*;
+    use core::cell::Cell;
+
+    #[test]
+    fn test_counter() {
+        let mut nonces = Counter::new();
+        let nonce = &mut [0; 12];
+        for i in 0..300_u32 {
+            nonces.advance(nonce).unwrap();
+            assert_eq!(&[0; 8], &nonce[..8]);
+            assert_eq!(i.to_be_bytes(), nonce[8..]);
+        }
+
+        nonces.next = Some(u64::MAX);
+        nonces.advance(nonce).unwrap();
+        assert_eq!(&[0xff; 8], &nonce[4..]);
+        assert_eq!(Err(NonceError), nonces.advance(nonce));
+        assert_eq!(&[0xff; 8], &nonce[4..]);
+
+        let mut nonces = Counter::new();
+        let nonce = &mut [0; 1];
+        for i in 0..=255 {
+            nonces.advance(nonce).unwrap();
+            assert_eq!(i, nonce[0]);
+        }
+        assert_eq!(Err(NonceError), nonces.advance(nonce));
+    }
+
+    #[test]
+    fn test_random() {
+        assert_eq!(1 << 32, random_limit(12));
+        assert_eq!(1 << 20, random_limit(9));
+        assert_eq!(u64::MAX, random_limit(24));
+
+        let next = Cell::new(0);
+        let mut nonces = Random::new(|buffer: &mut [u8]| {
+            buffer.iter_mut().for_each(|byte| *byte = next.get());
+            next.set(next.get() + 1);
+        });
+        let nonce = &mut [0; 12];
+        nonces.advance(nonce).unwrap();
+        assert_eq!(&[0; 12], nonce);
+        nonces.advance(nonce).unwrap();
+        assert_eq!(&[1; 12], nonce);
+
+        nonces.count = (1 << 32) - 1;
+        nonces.advance(nonce).unwrap();
+        assert_eq!(Err(NonceError), nonces.advance(nonce));
+        assert_eq!(&[2; 12], nonce);
+        nonces.advance(&mut [0; 24]).unwrap();
+    }
+
+    #[test]
+    fn test_xor_iv() {
+        let iv = &h2b("5b78923dee08579033e523d9");
+        let mut nonces = XorIv::new(iv).unwrap();
+        let nonce = &mut [0; 12];
+        nonces.advance(nonce).unwrap();
+        assert_eq!(iv, nonce);
+        nonces.advance(nonce).unwrap();
+        assert_eq!(h2b("5b78923dee08579033e523d8"), &nonce[..]);
+        nonces.advance(nonce).unwrap();
+        assert_eq!(h2b("5b78923dee08579033e523db"), &nonce[..]);
+
+        nonces.sequence.next = Some(u64::MAX);
+        nonces.advance(nonce).unwrap();
+        assert_eq!(h2b("5b78923d11f7a86fcc1adc26"), &nonce[..]);
+        assert_eq!(Err(NonceError), nonces.advance(nonce));
+
+        let mut nonces = XorIv::new(iv).unwrap();
+        assert_eq!(Err(NonceError), nonces.advance(&mut [0; 11]));
+        assert_eq!(Err(Error::InvalidLength), XorIv::new(&[0; 7]).map(|_| ()));
+        assert_eq!(Err(Error::InvalidLength), XorIv::new(&[0; 25]).map(|_| ()));
+        assert!(XorIv::new(&[0; 24]).is_ok());
+    }
+
+    #[test]
+    fn test_nonce() {
+        let mut nonces = Counter::new();
+        nonces.next = Some(0x0102);
+        let nonce = Nonce::next(&mut nonces, 24).unwrap();
+        assert_eq!(24, nonce.len());
+        assert_eq!(&[1, 2], &nonce[22..]);
+        assert_eq!(Err(Error::InvalidLength), Nonce::next(&mut nonces, 25));
+    }
+}
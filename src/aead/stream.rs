@@ -0,0 +1,252 @@
+//! Module for the STREAM construction, which encrypts a long message as a sequence of segments
+//! using an AEAD cipher (Hoang, Reyhanitabar, Rogaway and Vizár, "Online Authenticated-Encryption
+//! and its Nonce-Reuse Misuse-Resistance").
+//!
+//! Each segment is sealed with a nonce made of a prefix chosen for the whole message, a 32-bit
+//! big-endian segment counter, and a byte that is 1 for the last segment and 0 otherwise. This
+//! lets segments be processed one at a time, while reordering, dropping or truncating segments is
+//! detected. The segment length is up to the caller, but using a fixed length for all segments
+//! but the last lets a reader split the ciphertext without any extra framing.
+use super::nonce::MAX_NONCE_LEN;
+use super::Aead;
+use crate::error::Error;
+
+const SUFFIX_LEN: usize = 5;
+
+/// Encrypts a message segment by segment with the STREAM construction.
+///
+/// # Examples
+///
+/// ```
+/// use crypto_pure::aead::stream::{Decryptor, Encryptor};
+/// use crypto_pure::poly1305::ChaCha20Poly1305;
+/// # let key = &[0x42; 32];
+/// # let nonce_prefix = &[0x24; 7];
+/// let mut encryptor = Encryptor::<ChaCha20Poly1305>::new(key, nonce_prefix).unwrap();
+/// let first = &mut [b"first segment" as &[u8], &[0; 16]].concat();
+/// encryptor.encrypt_next(b"", first).unwrap();
+/// let last = &mut [b"last" as &[u8], &[0; 16]].concat();
+/// encryptor.encrypt_last(b"", last).unwrap();
+///
+/// let mut decryptor = Decryptor::<ChaCha20Poly1305>::new(key, nonce_prefix).unwrap();
+/// assert_eq!(b"first segment", decryptor.decrypt_next(b"", first).unwrap());
+/// assert_eq!(b"last", decryptor.decrypt_last(b"", last).unwrap());
+/// ```
+pub struct Encryptor<A>(Stream<A>);
+
+impl<A: Aead> Encryptor<A> {
+    /// Initializes an encryptor given a key and a nonce prefix, which must never be used for
+    /// another message with the same key. Fails with `Error::InvalidKey` if `key.len()` is not
+    /// equal to `A::KEY_LEN`, or with `Error::InvalidLength` if `nonce_prefix.len()` is not equal
+    /// to `A::NONCE_LEN - 5`.
+    pub fn new(key: &[u8], nonce_prefix: &[u8]) -> Result<Self, Error> {
+        Ok(Self(Stream::new(key, nonce_prefix)?))
+    }
+
+    /// Encrypts a segment other than the last at the start of `buffer` in place and writes the
+    /// tag into the last `A::TAG_LEN` bytes, which must be reserved for it. Fails with
+    /// `Error::InvalidLength` if `buffer.len()` is less than `A::TAG_LEN`, or with
+    /// `Error::NonceExhausted` if 2^32 segments have been encrypted.
+    pub fn encrypt_next(&mut self, data: &[u8], buffer: &mut [u8]) -> Result<(), Error> {
+        self.0.seal(false, data, buffer)?;
+        self.0.counter += 1;
+        Ok(())
+    }
+
+    /// Encrypts the last segment like `encrypt_next`, finishing the message.
+    pub fn encrypt_last(self, data: &[u8], buffer: &mut [u8]) -> Result<(), Error> {
+        self.0.seal(true, data, buffer)
+    }
+}
+
+/// Decrypts a message encrypted segment by segment with the STREAM construction.
+pub struct Decryptor<A>(Stream<A>);
+
+impl<A: Aead> Decryptor<A> {
+    /// Initializes a decryptor given a key and the nonce prefix used to encrypt the message,
+    /// failing like `Encryptor::new`.
+    pub fn new(key: &[u8], nonce_prefix: &[u8]) -> Result<Self, Error> {
+        Ok(Self(Stream::new(key, nonce_prefix)?))
+    }
+
+    /// Verifies the tag at the end of a segment other than the last and decrypts the segment in
+    /// place, outputting the plaintext part of `buffer`. On failure, `buffer` is left unchanged
+    /// and the same segment can be retried. Fails with `Error::NonceExhausted` if 2^32 segments
+    /// have been decrypted.
+    pub fn decrypt_next<'a>(
+        &mut self,
+        data: &[u8],
+        buffer: &'a mut [u8],
+    ) -> Result<&'a mut [u8], Error> {
+        let plaintext = self.0.open(false, data, buffer)?;
+        self.0.counter += 1;
+        Ok(plaintext)
+    }
+
+    /// Decrypts the last segment like `decrypt_next`, finishing the message. Fails if the segment
+    /// was not encrypted as the last one, so a truncated message is detected.
+    pub fn decrypt_last<'a>(
+        self,
+        data: &[u8],
+        buffer: &'a mut [u8],
+    ) -> Result<&'a mut [u8], Error> {
+        self.0.open(true, data, buffer)
+    }
+}
+
+struct Stream<A> {
+    cipher: A,
+    nonce: [u8; MAX_NONCE_LEN],
+    counter: u64,
+}
+
+impl<A: Aead> Stream<A> {
+    fn new(key: &[u8], nonce_prefix: &[u8]) -> Result<Self, Error> {
+        if A::NONCE_LEN > MAX_NONCE_LEN || A::NONCE_LEN != nonce_prefix.len() + SUFFIX_LEN {
+            return Err(Error::InvalidLength);
+        }
+        let mut nonce = [0; MAX_NONCE_LEN];
+        nonce[..nonce_prefix.len()].copy_from_slice(nonce_prefix);
+        Ok(Self {
+            cipher: A::new(key)?,
+            nonce,
+            counter: 0,
+        })
+    }
+
+    fn seal(&self, last: bool, data: &[u8], buffer: &mut [u8]) -> Result<(), Error> {
+        let nonce = &self.nonce(last)?;
+        self.cipher
+            .seal_in_place(&nonce[..A::NONCE_LEN], data, buffer)
+    }
+
+    fn open<'a>(
+        &self,
+        last: bool,
+        data: &[u8],
+        buffer: &'a mut [u8],
+    ) -> Result<&'a mut [u8], Error> {
+        let nonce = &self.nonce(last)?;
+        self.cipher
+            .open_in_place(&nonce[..A::NONCE_LEN], data, buffer)
+    }
+
+    fn nonce(&self, last: bool) -> Result<[u8; MAX_NONCE_LEN], Error> {
+        if self.counter >> 32 != 0 {
+            return Err(Error::NonceExhausted);
+        }
+        let mut nonce = self.nonce;
+        let suffix = &mut nonce[A::NONCE_LEN - SUFFIX_LEN..A::NONCE_LEN];
+        suffix[..4].copy_from_slice(&(self.counter as u32).to_be_bytes());
+        suffix[4] = last as u8;
+        Ok(nonce)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aes::Aes128;
+    use crate::gcm::Gcm;
+    use crate::poly1305::XChaCha20Poly1305;
+    use std::vec::Vec;
+
+    fn segments<A: Aead>(message: &[u8], segment_len: usize) -> Vec<Vec<u8>> {
+        let mut segments: Vec<_> = message
+            .chunks(segment_len)
+            .map(|chunk| [chunk, &vec![0; A::TAG_LEN]].concat())
+            .collect();
+        if segments.is_empty() {
+            segments.push(vec![0; A::TAG_LEN]);
+        }
+        segments
+    }
+
+    fn check<A: Aead>(message_len: usize, segment_len: usize) {
+        let key = &vec![0x42; A::KEY_LEN];
+        let nonce_prefix = &vec![0x24; A::NONCE_LEN - SUFFIX_LEN];
+        let message: &Vec<_> = &(0..message_len).map(|i| i as u8).collect();
+        let segments = &mut segments::<A>(message, segment_len);
+        let last_index = segments.len() - 1;
+
+        let mut encryptor = Encryptor::<A>::new(key, nonce_prefix).unwrap();
+        for segment in &mut segments[..last_index] {
+            encryptor.encrypt_next(b"data", segment).unwrap();
+        }
+        encryptor
+            .encrypt_last(b"data", &mut segments[last_index])
+            .unwrap();
+
+        let cipher = A::new(key).unwrap();
+        for (i, segment) in segments.iter().enumerate() {
+            let mut nonce = nonce_prefix.clone();
+            nonce.extend_from_slice(&(i as u32).to_be_bytes());
+            nonce.push((i == last_index) as u8);
+            assert!(cipher
+                .open_in_place(&nonce, b"data", &mut segment.clone())
+                .is_ok());
+        }
+
+        let mut decryptor = Decryptor::<A>::new(key, nonce_prefix).unwrap();
+        let mut decrypted = Vec::new();
+        let error = Err(Error::TagMismatch);
+        for segment in &mut segments[..last_index] {
+            let segment_copy = &mut segment.clone();
+            assert_eq!(error, decryptor.decrypt_next(b"other", segment));
+            assert_eq!(segment_copy, segment);
+            decrypted.extend_from_slice(decryptor.decrypt_next(b"data", segment).unwrap());
+        }
+        let last = &mut segments[last_index];
+        assert_eq!(error, decryptor.decrypt_next(b"data", last));
+        decrypted.extend_from_slice(decryptor.decrypt_last(b"data", last).unwrap());
+        assert_eq!(message, &decrypted);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for &(message_len, segment_len) in &[(0, 16), (10, 16), (64, 16), (100, 16), (100, 7)] {
+            check::<Gcm<Aes128>>(message_len, segment_len);
+            check::<XChaCha20Poly1305>(message_len, segment_len);
+        }
+    }
+
+    #[test]
+    fn test_truncation() {
+        let key = &[0x42; 16];
+        let nonce_prefix = &[0x24; 7];
+        let segments = &mut segments::<Gcm<Aes128>>(&[0; 48], 16);
+        let mut encryptor = Encryptor::<Gcm<Aes128>>::new(key, nonce_prefix).unwrap();
+        encryptor.encrypt_next(b"", &mut segments[0]).unwrap();
+        encryptor.encrypt_next(b"", &mut segments[1]).unwrap();
+        encryptor.encrypt_last(b"", &mut segments[2]).unwrap();
+
+        let error = Err(Error::TagMismatch);
+        let decryptor = Decryptor::<Gcm<Aes128>>::new(key, nonce_prefix).unwrap();
+        assert_eq!(error, decryptor.decrypt_last(b"", &mut segments[0]));
+        let mut decryptor = Decryptor::<Gcm<Aes128>>::new(key, nonce_prefix).unwrap();
+        assert!(decryptor.decrypt_next(b"", &mut segments[0]).is_ok());
+        assert_eq!(error, decryptor.decrypt_next(b"", &mut segments[2]));
+        assert_eq!(error, decryptor.decrypt_last(b"", &mut segments[1]));
+    }
+
+    #[test]
+    fn test_invalid_inputs() {
+        let error = Err(Error::InvalidLength);
+        assert_eq!(
+            error,
+            Encryptor::<Gcm<Aes128>>::new(&[0; 16], &[0; 8]).map(|_| ())
+        );
+        let error = Err(Error::InvalidKey);
+        assert_eq!(
+            error,
+            Decryptor::<Gcm<Aes128>>::new(&[0; 15], &[0; 7]).map(|_| ())
+        );
+
+        let mut encryptor = Encryptor::<Gcm<Aes128>>::new(&[0; 16], &[0; 7]).unwrap();
+        let error = Err(Error::InvalidLength);
+        assert_eq!(error, encryptor.encrypt_next(b"", &mut [0; 15]));
+        encryptor.0.counter = 1 << 32;
+        let error = Err(Error::NonceExhausted);
+        assert_eq!(error, encryptor.encrypt_next(b"", &mut [0; 16]));
+    }
+}
@@ -2,26 +2,110 @@
 //!
 //! Do not use these module directly for encryption. The AES structs should only be used as a
 //! parameter for an encryption mode of operation, such as GCM.
+//!
+//! The S-box is computed in constant time rather than looked up in a table, so that timing does
+//! not depend on the key or data. The `aes-tables` feature switches to lookup tables, which are
+//! faster but vulnerable to cache-timing attacks; it exists only for benchmarking.
+//!
+//! When the CPU supports them, the AES instructions (AES-NI on x86 and x86-64, or the
+//! cryptography extensions on AArch64) are used instead of the software implementation. Without
+//! the `std` feature, they are only used if enabled at compile time, e.g. with
+//! `-C target-feature=+aes`.
+#[cfg(target_arch = "aarch64")]
+use self::armv8 as hardware;
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+use self::x86 as hardware;
+use crate::error::Error;
+use crate::secret::SecretKey;
+
+pub mod cbc;
+pub mod ctr;
+pub mod key_wrap;
+pub mod xts;
+
+#[cfg(target_arch = "aarch64")]
+mod armv8;
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+mod x86;
+
+#[cfg(not(any(target_arch = "aarch64", target_arch = "x86", target_arch = "x86_64")))]
+mod hardware {
+    pub(super) fn available() -> bool {
+        false
+    }
+
+    pub(super) unsafe fn encrypt_block(_: &[u8], _: &[u8; 16]) -> [u8; 16] {
+        unreachable!()
+    }
+
+    pub(super) unsafe fn decrypt_block(_: &[u8], _: &[u8; 16]) -> [u8; 16] {
+        unreachable!()
+    }
+}
 
 /// A trait for block ciphers with a block size of 16 bytes.
 pub trait BlockCipher {
-    /// Initializes a block cipher given a key.
-    fn new(key: &[u8]) -> Self;
+    /// The length of a key in bytes.
+    const KEY_LEN: usize;
+
+    /// Initializes a block cipher given a key, or fails with `Error::InvalidKey` if `key.len()`
+    /// is not `KEY_LEN`.
+    fn new(key: &[u8]) -> Result<Self, Error>
+    where
+        Self: Sized;
 
-    /// Outputs a permutation of the input block.
-    fn permute(&self, input: &[u8; 16]) -> [u8; 16];
+    /// Encrypts a single block, the forward permutation of the cipher.
+    fn encrypt_block(&self, input: &[u8; 16]) -> [u8; 16];
+
+    /// Decrypts a single block, the inverse of `encrypt_block`.
+    fn decrypt_block(&self, input: &[u8; 16]) -> [u8; 16];
 }
 
 macro_rules! impl_cipher {
     ($cipher:ident, $nk:expr) => {
-        pub struct $cipher([u8; 16 * ($nk + 6 + 1)]);
+        #[derive(Clone)]
+        pub struct $cipher {
+            schedule: SecretKey<[u8; 16 * ($nk + 6 + 1)]>,
+            accelerated: bool,
+        }
 
         impl BlockCipher for $cipher {
-            fn new(key: &[u8]) -> Self {
-                Self(Self::key_expansion(key))
+            const KEY_LEN: usize = 4 * $nk;
+
+            fn new(key: &[u8]) -> Result<Self, Error> {
+                if key.len() != Self::KEY_LEN {
+                    return Err(Error::InvalidKey);
+                }
+                Ok(Self {
+                    schedule: SecretKey::new(Self::key_expansion(key)),
+                    accelerated: hardware::available(),
+                })
+            }
+
+            fn encrypt_block(&self, input: &[u8; 16]) -> [u8; 16] {
+                if self.accelerated {
+                    // Safe since `accelerated` is only set if the CPU supports the instructions.
+                    unsafe { hardware::encrypt_block(&self.schedule[..], input) }
+                } else {
+                    self.software_encrypt_block(input)
+                }
             }
 
-            fn permute(&self, input: &[u8; 16]) -> [u8; 16] {
+            fn decrypt_block(&self, input: &[u8; 16]) -> [u8; 16] {
+                if self.accelerated {
+                    // Safe since `accelerated` is only set if the CPU supports the instructions.
+                    unsafe { hardware::decrypt_block(&self.schedule[..], input) }
+                } else {
+                    self.software_decrypt_block(input)
+                }
+            }
+        }
+
+        impl $cipher {
+            const NK: usize = $nk;
+            const NR: usize = Self::NK + 6;
+
+            fn software_encrypt_block(&self, input: &[u8; 16]) -> [u8; 16] {
                 let mut output = *input;
                 self.add_round_key(&mut output, 0);
                 for round in 1..Self::NR {
@@ -35,11 +119,21 @@ macro_rules! impl_cipher {
                 self.add_round_key(&mut output, Self::NR);
                 output
             }
-        }
 
-        impl $cipher {
-            const NK: usize = $nk;
-            const NR: usize = Self::NK + 6;
+            fn software_decrypt_block(&self, input: &[u8; 16]) -> [u8; 16] {
+                let mut output = *input;
+                self.add_round_key(&mut output, Self::NR);
+                for round in (1..Self::NR).rev() {
+                    inv_shift_rows(&mut output);
+                    inv_sub_bytes(&mut output);
+                    self.add_round_key(&mut output, round);
+                    inv_mix_columns(&mut output);
+                }
+                inv_shift_rows(&mut output);
+                inv_sub_bytes(&mut output);
+                self.add_round_key(&mut output, 0);
+                output
+            }
 
             fn key_expansion(key: &[u8]) -> [u8; 16 * (Self::NR + 1)] {
                 assert_eq!(4 * Self::NK, key.len());
@@ -71,7 +165,7 @@ macro_rules! impl_cipher {
             }
 
             fn round_key(&self, round: usize) -> &[u8] {
-                &self.0[16 * round..16 * (round + 1)]
+                &self.schedule[16 * round..16 * (round + 1)]
             }
         }
     };
@@ -108,6 +202,18 @@ fn shift_rows(state: &mut [u8; 16]) {
     state[3] = temp;
 }
 
+fn inv_sub_bytes(state: &mut [u8; 16]) {
+    for byte in state {
+        *byte = inv_s_box(*byte);
+    }
+}
+
+fn inv_shift_rows(state: &mut [u8; 16]) {
+    for _ in 0..3 {
+        shift_rows(state);
+    }
+}
+
 fn mix_columns(state: &mut [u8; 16]) {
     for column in state.chunks_mut(4) {
         let mut c = [0; 4];
@@ -121,6 +227,22 @@ fn mix_columns(state: &mut [u8; 16]) {
     }
 }
 
+fn inv_mix_columns(state: &mut [u8; 16]) {
+    // InvMixColumns is MixColumns preceded by multiplying each column by 4x^2 + 5.
+    for column in state.chunks_mut(4) {
+        let mut c = [0; 4];
+        c.copy_from_slice(column);
+        let x4 = xtime_column(xtime_column(c));
+        let u = x4[0] ^ x4[2];
+        let v = x4[1] ^ x4[3];
+        column[0] = c[0] ^ u;
+        column[1] = c[1] ^ v;
+        column[2] = c[2] ^ u;
+        column[3] = c[3] ^ v;
+    }
+    mix_columns(state);
+}
+
 fn xor_column(a: [u8; 4], b: [u8; 4]) -> [u8; 4] {
     [a[0] ^ b[0], a[1] ^ b[1], a[2] ^ b[2], a[3] ^ b[3]]
 }
@@ -137,28 +259,80 @@ fn xtime(byte: u8) -> u8 {
 // S-box implementation from
 // David Canright. A very compact Rijndael S-box. 2004.
 const A2X: [u8; 8] = [0x98, 0xF3, 0xF2, 0x48, 0x09, 0x81, 0xA9, 0xFF];
+const X2A: [u8; 8] = [0x64, 0x78, 0x6E, 0x8C, 0x68, 0x29, 0xDE, 0x60];
 const X2S: [u8; 8] = [0x58, 0x2D, 0x9E, 0x0B, 0xDC, 0x04, 0x03, 0x24];
+const S2X: [u8; 8] = [0x8C, 0x79, 0x05, 0xEB, 0x12, 0x04, 0x51, 0x53];
+
+// Lookup tables are faster, but the memory access pattern depends on secret data, which leaks
+// through cache timing. They are only for benchmarking against the constant-time S-box.
+#[cfg(feature = "aes-tables")]
+const S_BOX: [u8; 256] = s_box_table(false);
+#[cfg(feature = "aes-tables")]
+const INV_S_BOX: [u8; 256] = s_box_table(true);
+
+#[cfg(feature = "aes-tables")]
+const fn s_box_table(inverse: bool) -> [u8; 256] {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        table[i] = if inverse {
+            compute_inv_s_box(i as u8)
+        } else {
+            compute_s_box(i as u8)
+        };
+        i += 1;
+    }
+    table
+}
+
+#[cfg(feature = "aes-tables")]
+fn s_box(n: u8) -> u8 {
+    S_BOX[n as usize]
+}
+
+#[cfg(feature = "aes-tables")]
+fn inv_s_box(n: u8) -> u8 {
+    INV_S_BOX[n as usize]
+}
 
+#[cfg(not(feature = "aes-tables"))]
 fn s_box(n: u8) -> u8 {
+    compute_s_box(n)
+}
+
+#[cfg(not(feature = "aes-tables"))]
+fn inv_s_box(n: u8) -> u8 {
+    compute_inv_s_box(n)
+}
+
+const fn compute_s_box(n: u8) -> u8 {
     let mut t = g256_newbasis(n, A2X);
     t = g256_inv(t);
     t = g256_newbasis(t, X2S);
     t ^ 0x63
 }
 
-fn g256_newbasis(x: u8, b: [u8; 8]) -> u8 {
+const fn compute_inv_s_box(n: u8) -> u8 {
+    let mut t = g256_newbasis(n ^ 0x63, S2X);
+    t = g256_inv(t);
+    g256_newbasis(t, X2A)
+}
+
+const fn g256_newbasis(x: u8, b: [u8; 8]) -> u8 {
     let mut x = x;
     let mut y = 0;
-    for &b_i in b.iter().rev() {
+    let mut i = b.len();
+    while i > 0 {
+        i -= 1;
         let h = x << 7;
         let m = (h as i8 >> 7) as u8;
-        y ^= b_i & m;
+        y ^= b[i] & m;
         x >>= 1;
     }
     y
 }
 
-fn g256_inv(x: u8) -> u8 {
+const fn g256_inv(x: u8) -> u8 {
     let a = (x & 0xf0) >> 4;
     let b = x & 0x0f;
     let c = g16_sq_scl(a ^ b);
@@ -169,7 +343,7 @@ fn g256_inv(x: u8) -> u8 {
     (p << 4) | q
 }
 
-fn g16_inv(x: u8) -> u8 {
+const fn g16_inv(x: u8) -> u8 {
     let a = (x & 0xc) >> 2;
     let b = x & 0x3;
     let c = g4_scl_n(g4_sq(a ^ b));
@@ -180,7 +354,7 @@ fn g16_inv(x: u8) -> u8 {
     (p << 2) | q
 }
 
-fn g16_mul(x: u8, y: u8) -> u8 {
+const fn g16_mul(x: u8, y: u8) -> u8 {
     let a = (x & 0xc) >> 2;
     let b = x & 0x3;
     let c = (y & 0xc) >> 2;
@@ -192,7 +366,7 @@ fn g16_mul(x: u8, y: u8) -> u8 {
     (p << 2) | q
 }
 
-fn g16_sq_scl(x: u8) -> u8 {
+const fn g16_sq_scl(x: u8) -> u8 {
     let a = (x & 0xc) >> 2;
     let b = x & 0x3;
     let p = g4_sq(a ^ b);
@@ -200,7 +374,7 @@ fn g16_sq_scl(x: u8) -> u8 {
     (p << 2) | q
 }
 
-fn g4_mul(x: u8, y: u8) -> u8 {
+const fn g4_mul(x: u8, y: u8) -> u8 {
     let a = (x & 0x2) >> 1;
     let b = x & 0x1;
     let c = (y & 0x2) >> 1;
@@ -211,7 +385,7 @@ fn g4_mul(x: u8, y: u8) -> u8 {
     (p << 1) | q
 }
 
-fn g4_scl_n(x: u8) -> u8 {
+const fn g4_scl_n(x: u8) -> u8 {
     let a = (x & 0x2) >> 1;
     let b = x & 0x1;
     let p = b;
@@ -219,7 +393,7 @@ fn g4_scl_n(x: u8) -> u8 {
     (p << 1) | q
 }
 
-fn g4_scl_n2(x: u8) -> u8 {
+const fn g4_scl_n2(x: u8) -> u8 {
     let a = (x & 0x2) >> 1;
     let b = x & 0x1;
     let p = a ^ b;
@@ -227,7 +401,7 @@ fn g4_scl_n2(x: u8) -> u8 {
     (p << 1) | q
 }
 
-fn g4_sq(x: u8) -> u8 {
+const fn g4_sq(x: u8) -> u8 {
     let a = (x & 0x2) >> 1;
     let b = x & 0x1;
     (b << 1) | a
@@ -237,6 +411,7 @@ fn g4_sq(x: u8) -> u8 {
 mod tests {
     use super::*;
     use crate::test_helpers::*;
+    use std::vec::Vec;
 
     fn create_s_box() -> [u8; 256] {
         let mut s_box = [0; 256];
@@ -324,24 +499,52 @@ mod tests {
         "2c21a820306f154ab712c75eee0da04f",
     ];
 
-    #[test]
-    fn test_permute() {
+    fn check_cipher<E: BlockCipher>(key: &str, output: &str) {
         let input = &mut [0; 16];
         input.copy_from_slice(&h2b(INPUT));
-        let key = &h2b(KEY);
-        let output = h2b(OUTPUT);
-        let aes = Aes256::new(key);
-        assert_eq!(output, aes.permute(input));
-
-        let key = &h2b("000102030405060708090a0b0c0d0e0f1011121314151617");
-        let output = h2b("dda97ca4864cdfe06eaf70a0ec0d7191");
-        let aes = Aes192::new(key);
-        assert_eq!(output, aes.permute(input));
-
-        let key = &h2b("000102030405060708090a0b0c0d0e0f");
-        let output = h2b("69c4e0d86a7b0430d8cdb78070b4c55a");
-        let aes = Aes128::new(key);
-        assert_eq!(output, aes.permute(input));
+        let output_block = &mut [0; 16];
+        output_block.copy_from_slice(&h2b(output));
+        let aes = E::new(&h2b(key)).unwrap();
+        assert_eq!(output_block, &aes.encrypt_block(input));
+        assert_eq!(input, &aes.decrypt_block(output_block));
+    }
+
+    macro_rules! check_paths {
+        ($cipher:ident, $key_len:expr) => {{
+            let key: Vec<u8> = (0..$key_len).map(|x| 7 * x as u8).collect();
+            let aes = $cipher::new(&key).unwrap();
+            let mut block = [0; 16];
+            for _ in 0..100 {
+                let encrypted = aes.software_encrypt_block(&block);
+                assert_eq!(encrypted, aes.encrypt_block(&block));
+                assert_eq!(block, aes.software_decrypt_block(&encrypted));
+                assert_eq!(block, aes.decrypt_block(&encrypted));
+                block = encrypted;
+            }
+        }};
+    }
+
+    #[test]
+    fn test_hardware() {
+        check_paths!(Aes128, 16);
+        check_paths!(Aes192, 24);
+        check_paths!(Aes256, 32);
+    }
+
+    #[test]
+    fn test_cipher() {
+        check_cipher::<Aes256>(KEY, OUTPUT);
+        let key = "000102030405060708090a0b0c0d0e0f1011121314151617";
+        check_cipher::<Aes192>(key, "dda97ca4864cdfe06eaf70a0ec0d7191");
+        let key = "000102030405060708090a0b0c0d0e0f";
+        check_cipher::<Aes128>(key, "69c4e0d86a7b0430d8cdb78070b4c55a");
+    }
+
+    #[test]
+    fn test_invalid_key() {
+        assert!(matches!(Aes128::new(&[0; 15]), Err(Error::InvalidKey)));
+        assert!(matches!(Aes192::new(&[0; 32]), Err(Error::InvalidKey)));
+        assert!(matches!(Aes256::new(&[0; 24]), Err(Error::InvalidKey)));
     }
 
     #[test]
@@ -359,11 +562,21 @@ mod tests {
         );
         let key_schedule = Aes256::key_expansion(key);
         assert_eq!(schedule, key_schedule.to_vec());
+
+        let key = &h2b("2b7e151628aed2a6abf7158809cf4f3c");
+        let key_schedule = Aes128::key_expansion(key);
+        let last_round_key = h2b("d014f9a8c9ee2589e13f0cc8b6630ca6");
+        assert_eq!(last_round_key, key_schedule[16 * 10..].to_vec());
+
+        let key = &h2b("8e73b0f7da0e6452c810f32b809079e562f8ead2522c6b7b");
+        let key_schedule = Aes192::key_expansion(key);
+        let last_round_key = h2b("e98ba06f448c773c8ecc720401002202");
+        assert_eq!(last_round_key, key_schedule[16 * 12..].to_vec());
     }
 
     #[test]
     fn test_add_round_key() {
-        let aes = Aes256::new(&h2b(KEY));
+        let aes = Aes256::new(&h2b(KEY)).unwrap();
         let state = &mut [0; 16];
         state.copy_from_slice(&h2b(INPUT));
         aes.add_round_key(state, 0);
@@ -426,6 +639,26 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_inverse_steps() {
+        let state = &mut [0; 16];
+        for (before, after) in SHIFT_ROWS.iter().map(|x| h2b(x)).zip(MIX_COLUMNS) {
+            state.copy_from_slice(&h2b(after));
+            inv_mix_columns(state);
+            assert_eq!(&before, state);
+        }
+        for (before, after) in SUB_BYTES.iter().map(|x| h2b(x)).zip(SHIFT_ROWS) {
+            state.copy_from_slice(&h2b(after));
+            inv_shift_rows(state);
+            assert_eq!(&before, state);
+        }
+        for (before, after) in START.iter().map(|x| h2b(x)).zip(SUB_BYTES) {
+            state.copy_from_slice(&h2b(after));
+            inv_sub_bytes(state);
+            assert_eq!(&before, state);
+        }
+    }
+
     #[test]
     fn test_xtime() {
         let powers = [0x57, 0xae, 0x47, 0x8e, 0x07];
@@ -440,6 +673,9 @@ mod tests {
         for (i, &y) in s_box_array.iter().enumerate() {
             let x = i as u8;
             assert_eq!(y, s_box(x));
+            assert_eq!(x, inv_s_box(y));
+            assert_eq!(y, compute_s_box(x));
+            assert_eq!(x, compute_inv_s_box(y));
         }
     }
 }
@@ -0,0 +1,56 @@
+//! AES using the ARMv8 cryptography extensions.
+use core::arch::aarch64::*;
+
+pub(super) fn available() -> bool {
+    #[cfg(feature = "std")]
+    {
+        std::arch::is_aarch64_feature_detected!("aes")
+    }
+    #[cfg(not(feature = "std"))]
+    {
+        cfg!(target_feature = "aes")
+    }
+}
+
+#[target_feature(enable = "aes,neon")]
+pub(super) unsafe fn encrypt_block(schedule: &[u8], input: &[u8; 16]) -> [u8; 16] {
+    // AESE adds the round key before SubBytes and ShiftRows, so each round key is applied one
+    // instruction earlier than in FIPS 197 and the last one is added separately.
+    let rounds = schedule.len() / 16 - 1;
+    let mut state = load(input);
+    for round_key in schedule[..16 * (rounds - 1)].chunks(16) {
+        state = vaesmcq_u8(vaeseq_u8(state, load(round_key)));
+    }
+    let round_key = load(&schedule[16 * (rounds - 1)..16 * rounds]);
+    state = vaeseq_u8(state, round_key);
+    state = veorq_u8(state, load(&schedule[16 * rounds..]));
+    store(state)
+}
+
+#[target_feature(enable = "aes,neon")]
+pub(super) unsafe fn decrypt_block(schedule: &[u8], input: &[u8; 16]) -> [u8; 16] {
+    // AESD adds the round key before InvShiftRows and InvSubBytes. Moving InvMixColumns in front
+    // of each middle round key addition requires applying it to those round keys too.
+    let rounds = schedule.len() / 16 - 1;
+    let mut state = vaesdq_u8(load(input), load(&schedule[16 * rounds..]));
+    for round_key in schedule[16..16 * rounds].chunks(16).rev() {
+        state = vaesdq_u8(vaesimcq_u8(state), vaesimcq_u8(load(round_key)));
+    }
+    state = veorq_u8(state, load(&schedule[..16]));
+    store(state)
+}
+
+#[inline]
+#[target_feature(enable = "neon")]
+unsafe fn load(bytes: &[u8]) -> uint8x16_t {
+    debug_assert_eq!(16, bytes.len());
+    vld1q_u8(bytes.as_ptr())
+}
+
+#[inline]
+#[target_feature(enable = "neon")]
+unsafe fn store(state: uint8x16_t) -> [u8; 16] {
+    let mut output = [0; 16];
+    vst1q_u8(output.as_mut_ptr(), state);
+    output
+}
@@ -0,0 +1,184 @@
+//! Module for the cipher block chaining (CBC) mode of operation for block ciphers (NIST SP
+//! 800-38A).
+//!
+//! CBC mode provides confidentiality only. Combine it with a MAC, or use an AEAD such as GCM.
+//! Reporting padding errors for unauthenticated ciphertexts can reveal the message to an attacker
+//! (a padding oracle).
+use super::BlockCipher;
+use crate::error::Error;
+use crate::padding::{pkcs7, PaddingError};
+
+/// A block cipher in CBC mode.
+///
+/// # Examples
+///
+/// ```
+/// use crypto_pure::aes::cbc::Cbc;
+/// use crypto_pure::aes::Aes128;
+/// # let key = &[0x42; 16];
+/// # let iv = &[0x24; 16];
+/// let cbc = Cbc::<Aes128>::new(key)?;
+/// let buffer = &mut [0; 16];
+/// buffer[..7].copy_from_slice(b"message");
+/// let ciphertext = cbc.encrypt_padded(iv, buffer, 7)?;
+/// assert_eq!(Ok(&b"message"[..]), cbc.decrypt_padded(iv, ciphertext));
+/// # Ok::<(), crypto_pure::error::Error>(())
+/// ```
+pub struct Cbc<E> {
+    block_cipher: E,
+}
+
+impl<E: BlockCipher> Cbc<E> {
+    /// Initializes CBC mode given a key.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::InvalidKey` if `key.len()` is not appropriate for the block cipher.
+    pub fn new(key: &[u8]) -> Result<Self, Error> {
+        Ok(Self {
+            block_cipher: E::new(key)?,
+        })
+    }
+
+    /// Encrypts a message consisting of whole blocks in place.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::InvalidLength` if `buffer.len()` is not a multiple of 16.
+    pub fn encrypt(&self, iv: &[u8; 16], buffer: &mut [u8]) -> Result<(), Error> {
+        if !buffer.len().is_multiple_of(16) {
+            return Err(Error::InvalidLength);
+        }
+        let mut previous = *iv;
+        for chunk in buffer.chunks_mut(16) {
+            for (byte, previous_byte) in previous.iter_mut().zip(chunk.iter()) {
+                *byte ^= previous_byte;
+            }
+            previous = self.block_cipher.encrypt_block(&previous);
+            chunk.copy_from_slice(&previous);
+        }
+        Ok(())
+    }
+
+    /// Decrypts a ciphertext consisting of whole blocks in place.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::InvalidLength` if `buffer.len()` is not a multiple of 16.
+    pub fn decrypt(&self, iv: &[u8; 16], buffer: &mut [u8]) -> Result<(), Error> {
+        if !buffer.len().is_multiple_of(16) {
+            return Err(Error::InvalidLength);
+        }
+        let mut previous = *iv;
+        let mut block = [0; 16];
+        for chunk in buffer.chunks_mut(16) {
+            block.copy_from_slice(chunk);
+            let decrypted = self.block_cipher.decrypt_block(&block);
+            for ((byte, decrypted_byte), previous_byte) in
+                chunk.iter_mut().zip(&decrypted).zip(&previous)
+            {
+                *byte = decrypted_byte ^ previous_byte;
+            }
+            previous = block;
+        }
+        Ok(())
+    }
+
+    /// Pads the message in the first `len` bytes of `buffer` with PKCS #7 padding and encrypts it
+    /// in place, outputting the ciphertext part of `buffer`.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::InvalidLength` if `buffer.len()` is less than `len` rounded up to the
+    /// next multiple of 16, or `len` plus 16 if `len` is already a multiple.
+    pub fn encrypt_padded<'a>(
+        &self,
+        iv: &[u8; 16],
+        buffer: &'a mut [u8],
+        len: usize,
+    ) -> Result<&'a mut [u8], Error> {
+        let padded = pkcs7::pad(buffer, len, 16)?;
+        self.encrypt(iv, padded)?;
+        Ok(padded)
+    }
+
+    /// Decrypts a ciphertext in place and removes its PKCS #7 padding, outputting the message
+    /// part of `buffer`.
+    pub fn decrypt_padded<'a>(
+        &self,
+        iv: &[u8; 16],
+        buffer: &'a mut [u8],
+    ) -> Result<&'a [u8], PaddingError> {
+        if buffer.is_empty() || !buffer.len().is_multiple_of(16) {
+            return Err(PaddingError);
+        }
+        self.decrypt(iv, buffer).map_err(|_| PaddingError)?;
+        pkcs7::unpad(buffer, 16)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aes::{Aes128, Aes256};
+    use crate::test_helpers::*;
+
+    const IV: [u8; 16] = [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15];
+    const MESSAGE: &str = "6bc1bee22e409f96e93d7e117393172aae2d8a571e03ac9c9eb76fac45af8e51\
+                           30c81c46a35ce411e5fbc1191a0a52eff69f2445df4f9b17ad2b417be66c3710";
+
+    fn check<E: BlockCipher>(key: &str, ciphertext: &str) {
+        let key = &h2b(key);
+        let cbc = Cbc::<E>::new(key).unwrap();
+        let message = &h2b(MESSAGE);
+        let ciphertext = &h2b(ciphertext);
+        let buffer = &mut message.clone();
+        cbc.encrypt(&IV, buffer).unwrap();
+        assert_eq!(ciphertext, buffer);
+        cbc.decrypt(&IV, buffer).unwrap();
+        assert_eq!(message, buffer);
+
+        let error = Err(Error::InvalidLength);
+        assert_eq!(error, cbc.encrypt(&IV, &mut buffer[1..]));
+        assert_eq!(error, cbc.decrypt(&IV, &mut buffer[1..]));
+        assert_eq!(Err(Error::InvalidKey), Cbc::<E>::new(&key[1..]).map(|_| ()));
+    }
+
+    #[test]
+    fn test_aes128_cbc() {
+        let ciphertext = "7649abac8119b246cee98e9b12e9197d5086cb9b507219ee95db113a917678b2\
+                          73bed6b8e3c1743b7116e69e222295163ff1caa1681fac09120eca307586e1a7";
+        check::<Aes128>("2b7e151628aed2a6abf7158809cf4f3c", ciphertext);
+    }
+
+    #[test]
+    fn test_aes256_cbc() {
+        let key = "603deb1015ca71be2b73aef0857d77811f352c073b6108d72d9810a30914dff4";
+        let ciphertext = "f58c4c04d6e5f1ba779eabfb5f7bfbd69cfc4e967edb808d679f777bc6702c7d\
+                          39f23369a9d9bacfa530e26304231461b2eb05e2c39be9fcda6c19078c6a9d1b";
+        check::<Aes256>(key, ciphertext);
+    }
+
+    #[test]
+    fn test_padded() {
+        let cbc = Cbc::<Aes128>::new(&h2b("2b7e151628aed2a6abf7158809cf4f3c")).unwrap();
+        let message = &h2b(MESSAGE)[..20];
+        let buffer = &mut [0; 48];
+        buffer[..20].copy_from_slice(message);
+        let error = Err(Error::InvalidLength);
+        assert_eq!(
+            error,
+            cbc.encrypt_padded(&IV, &mut buffer[..31], 20).map(|_| ())
+        );
+        let ciphertext = cbc.encrypt_padded(&IV, buffer, 20).unwrap();
+        let expected = h2b("7649abac8119b246cee98e9b12e9197d2e013f890472d82217b17f45f6e7f539");
+        assert_eq!(expected, ciphertext.to_vec());
+        assert_eq!(Ok(message), cbc.decrypt_padded(&IV, ciphertext));
+
+        let ciphertext = &mut expected.clone();
+        ciphertext[31] ^= 1;
+        assert_eq!(Err(PaddingError), cbc.decrypt_padded(&IV, ciphertext));
+        assert_eq!(Err(PaddingError), cbc.decrypt_padded(&IV, &mut []));
+        assert_eq!(Err(PaddingError), cbc.decrypt_padded(&IV, &mut [0; 17]));
+    }
+}
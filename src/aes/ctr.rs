@@ -0,0 +1,169 @@
+//! Module for the counter (CTR) mode of operation for block ciphers (NIST SP 800-38A).
+//!
+//! CTR mode provides confidentiality only. Combine it with a MAC, or use an AEAD such as GCM.
+use super::BlockCipher;
+use crate::error::Error;
+
+/// A block cipher in CTR mode, used as a stream cipher.
+///
+/// The whole 16-byte counter block is incremented as a big-endian integer, wrapping around after
+/// the maximum value. A counter block must never be used twice with the same key.
+///
+/// # Examples
+///
+/// ```
+/// use crypto_pure::aes::ctr::Ctr;
+/// use crypto_pure::aes::Aes128;
+/// # let key = &[0x42; 16];
+/// # let nonce = &[0x24; 12];
+/// let mut buffer = b"message".to_vec();
+/// Ctr::<Aes128>::with_nonce(key, nonce)?.apply_keystream(&mut buffer);
+/// Ctr::<Aes128>::with_nonce(key, nonce)?.apply_keystream(&mut buffer);
+/// assert_eq!(b"message", &buffer[..]);
+/// # Ok::<(), crypto_pure::error::Error>(())
+/// ```
+pub struct Ctr<E> {
+    block_cipher: E,
+    counter: u128,
+    block: [u8; 16],
+    offset: usize,
+}
+
+impl<E: BlockCipher> Ctr<E> {
+    /// Initializes CTR mode given a key and the initial counter block.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::InvalidKey` if `key.len()` is not appropriate for the block cipher.
+    pub fn new(key: &[u8], counter: &[u8; 16]) -> Result<Self, Error> {
+        Ok(Self {
+            block_cipher: E::new(key)?,
+            counter: u128::from_be_bytes(*counter),
+            block: [0; 16],
+            offset: 16,
+        })
+    }
+
+    /// Initializes CTR mode with an initial counter block consisting of the nonce followed by
+    /// zeros, leaving the remaining bytes to count blocks.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::InvalidKey` if `key.len()` is not appropriate for the block cipher, or
+    /// with `Error::InvalidLength` if `nonce.len()` is greater than 16.
+    pub fn with_nonce(key: &[u8], nonce: &[u8]) -> Result<Self, Error> {
+        if nonce.len() > 16 {
+            return Err(Error::InvalidLength);
+        }
+        let mut counter = [0; 16];
+        counter[..nonce.len()].copy_from_slice(nonce);
+        Self::new(key, &counter)
+    }
+
+    /// Outputs the counter block that the next block of keystream will be generated from.
+    pub fn counter(&self) -> [u8; 16] {
+        self.counter.to_be_bytes()
+    }
+
+    /// Sets the counter block that the next block of keystream will be generated from, discarding
+    /// any unused keystream from the current block.
+    pub fn set_counter(&mut self, counter: &[u8; 16]) {
+        self.counter = u128::from_be_bytes(*counter);
+        self.offset = 16;
+    }
+
+    /// Encrypts or decrypts a buffer in place by XORing it with the next bytes of keystream.
+    pub fn apply_keystream(&mut self, buffer: &mut [u8]) {
+        for byte in buffer {
+            if self.offset == 16 {
+                self.block = self.block_cipher.encrypt_block(&self.counter.to_be_bytes());
+                self.counter = self.counter.wrapping_add(1);
+                self.offset = 0;
+            }
+            *byte ^= self.block[self.offset];
+            self.offset += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aes::{Aes128, Aes256};
+    use crate::test_helpers::*;
+
+    const COUNTER: [u8; 16] = [
+        0xf0, 0xf1, 0xf2, 0xf3, 0xf4, 0xf5, 0xf6, 0xf7, 0xf8, 0xf9, 0xfa, 0xfb, 0xfc, 0xfd, 0xfe,
+        0xff,
+    ];
+    const MESSAGE: &str = "6bc1bee22e409f96e93d7e117393172aae2d8a571e03ac9c9eb76fac45af8e51\
+                           30c81c46a35ce411e5fbc1191a0a52eff69f2445df4f9b17ad2b417be66c3710";
+
+    fn check<E: BlockCipher>(key: &str, ciphertext: &str) {
+        let key = &h2b(key);
+        let message = &h2b(MESSAGE);
+        let ciphertext = &h2b(ciphertext);
+        for chunk_size in &[1, 15, 16, 17, 64] {
+            let buffer = &mut message.clone();
+            let mut ctr = Ctr::<E>::new(key, &COUNTER).unwrap();
+            for chunk in buffer.chunks_mut(*chunk_size) {
+                ctr.apply_keystream(chunk);
+            }
+            assert_eq!(ciphertext, buffer);
+        }
+
+        let buffer = &mut ciphertext.clone();
+        let mut ctr = Ctr::<E>::new(key, &COUNTER).unwrap();
+        ctr.apply_keystream(&mut buffer[..5]);
+        let mut counter = COUNTER;
+        counter[14] = 0xff;
+        counter[15] = 0x00;
+        assert_eq!(counter, ctr.counter());
+        counter[15] = 0x01;
+        ctr.set_counter(&counter);
+        ctr.apply_keystream(&mut buffer[32..]);
+        ctr.set_counter(&COUNTER);
+        ctr.apply_keystream(&mut buffer[..32]);
+        buffer[..5].copy_from_slice(&message[..5]);
+        assert_eq!(message, buffer);
+    }
+
+    #[test]
+    fn test_aes128_ctr() {
+        let ciphertext = "874d6191b620e3261bef6864990db6ce9806f66b7970fdff8617187bb9fffdff\
+                          5ae4df3edbd5d35e5b4f09020db03eab1e031dda2fbe03d1792170a0f3009cee";
+        check::<Aes128>("2b7e151628aed2a6abf7158809cf4f3c", ciphertext);
+    }
+
+    #[test]
+    fn test_aes256_ctr() {
+        let key = "603deb1015ca71be2b73aef0857d77811f352c073b6108d72d9810a30914dff4";
+        let ciphertext = "601ec313775789a5b7a7f504bbf3d228f443e3ca4d62b59aca84e990cacaf5c5\
+                          2b0930daa23de94ce87017ba2d84988ddfc9c58db67aada613c2dd08457941a6";
+        check::<Aes256>(key, ciphertext);
+    }
+
+    #[test]
+    fn test_counter_wrap() {
+        let key = &h2b("2b7e151628aed2a6abf7158809cf4f3c");
+        let mut ctr = Ctr::<Aes128>::new(key, &[0xff; 16]).unwrap();
+        let buffer = &mut [0; 32];
+        ctr.apply_keystream(buffer);
+        let expected = h2b("8af2860142f786f409307c1a3f7eaaac7df76b0c1ab899b33e42f047b91b546f");
+        assert_eq!(expected, &buffer[..]);
+        assert_eq!(
+            [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1],
+            ctr.counter()
+        );
+    }
+
+    #[test]
+    fn test_invalid_lengths() {
+        let key = &[0; 16];
+        let error = Err(Error::InvalidKey);
+        assert_eq!(error, Ctr::<Aes128>::new(&key[1..], &COUNTER).map(|_| ()));
+        let error = Err(Error::InvalidLength);
+        assert_eq!(error, Ctr::<Aes128>::with_nonce(key, &[0; 17]).map(|_| ()));
+        assert!(Ctr::<Aes128>::with_nonce(key, &[0; 16]).is_ok());
+    }
+}
@@ -0,0 +1,343 @@
+//! Module for the AES key wrap (KW) and key wrap with padding (KWP) modes (RFC 3394, RFC 5649,
+//! NIST SP 800-38F), which encrypt and authenticate key material under a key-encryption key.
+use super::BlockCipher;
+use crate::ct::{self, lt_mask};
+use crate::error::Error;
+
+const IV: [u8; 8] = [0xa6; 8];
+const PADDING_IV: [u8; 4] = [0xa6, 0x59, 0x59, 0xa6];
+
+/// Wraps a key with KW, writing the result, which is 8 bytes longer, into `output`.
+///
+/// # Errors
+///
+/// Fails with `Error::InvalidKey` if `kek.len()` is not appropriate for the block cipher, or with
+/// `Error::InvalidLength` if `key.len()` is not a multiple of 8 or is less than 16, or if
+/// `output.len()` is not equal to `key.len() + 8`.
+///
+/// # Examples
+///
+/// ```
+/// use crypto_pure::aes::key_wrap;
+/// use crypto_pure::aes::Aes256;
+/// # let kek = &[0x42; 32];
+/// # let key = &[0x24; 32];
+/// let wrapped = &mut [0; 40];
+/// key_wrap::wrap::<Aes256>(kek, key, wrapped)?;
+/// let unwrapped = &mut [0; 32];
+/// key_wrap::unwrap::<Aes256>(kek, wrapped, unwrapped)?;
+/// assert_eq!(key, unwrapped);
+/// # Ok::<(), crypto_pure::error::Error>(())
+/// ```
+pub fn wrap<E: BlockCipher>(kek: &[u8], key: &[u8], output: &mut [u8]) -> Result<(), Error> {
+    let block_cipher = E::new(kek)?;
+    if key.len() < 16 || !key.len().is_multiple_of(8) || output.len() != key.len() + 8 {
+        return Err(Error::InvalidLength);
+    }
+    output[8..].copy_from_slice(key);
+    wrap_in_place(&block_cipher, IV, output);
+    Ok(())
+}
+
+/// Unwraps a key wrapped with KW into `output`, checking its integrity. On failure, `output` is
+/// zeroed.
+///
+/// # Errors
+///
+/// Fails with `Error::InvalidKey` if `kek.len()` is not appropriate for the block cipher, with
+/// `Error::InvalidLength` if `output.len()` is not equal to `wrapped.len() - 8`, or with
+/// `Error::TagMismatch` if `wrapped` is not a valid KW ciphertext under `kek`.
+pub fn unwrap<E: BlockCipher>(kek: &[u8], wrapped: &[u8], output: &mut [u8]) -> Result<(), Error> {
+    let block_cipher = E::new(kek)?;
+    check_unwrap_lengths(wrapped, output)?;
+    if wrapped.len() < 24 || !wrapped.len().is_multiple_of(8) {
+        return Err(Error::TagMismatch);
+    }
+    let a = unwrap_in_place(&block_cipher, wrapped, output);
+    if ct::constant_time_eq(&a, &IV) {
+        Ok(())
+    } else {
+        output.iter_mut().for_each(|byte| *byte = 0);
+        Err(Error::TagMismatch)
+    }
+}
+
+/// Outputs the length of a key of `len` bytes after wrapping it with KWP.
+pub fn padded_wrapped_len(len: usize) -> usize {
+    len.div_ceil(8) * 8 + 8
+}
+
+/// Wraps a key of any nonzero length with KWP, writing the result into `output`.
+///
+/// # Errors
+///
+/// Fails with `Error::InvalidKey` if `kek.len()` is not appropriate for the block cipher, or with
+/// `Error::InvalidLength` if `key.len()` is zero or not less than 2^32, or if `output.len()` is
+/// not equal to `padded_wrapped_len(key.len())`.
+///
+/// # Examples
+///
+/// ```
+/// use crypto_pure::aes::key_wrap;
+/// use crypto_pure::aes::Aes128;
+/// # let kek = &[0x42; 16];
+/// let wrapped = &mut [0; 16];
+/// key_wrap::wrap_with_padding::<Aes128>(kek, b"short", wrapped)?;
+/// let buffer = &mut [0; 8];
+/// let unwrapped = key_wrap::unwrap_with_padding::<Aes128>(kek, wrapped, buffer);
+/// assert_eq!(Ok(&mut b"short".clone()[..]), unwrapped);
+/// # Ok::<(), crypto_pure::error::Error>(())
+/// ```
+pub fn wrap_with_padding<E: BlockCipher>(
+    kek: &[u8],
+    key: &[u8],
+    output: &mut [u8],
+) -> Result<(), Error> {
+    let block_cipher = E::new(kek)?;
+    if key.is_empty() || key.len() as u64 >> 32 != 0 {
+        return Err(Error::InvalidLength);
+    }
+    if output.len() != padded_wrapped_len(key.len()) {
+        return Err(Error::InvalidLength);
+    }
+    let mut iv = [0; 8];
+    iv[..4].copy_from_slice(&PADDING_IV);
+    iv[4..].copy_from_slice(&(key.len() as u32).to_be_bytes());
+    output[8..8 + key.len()].copy_from_slice(key);
+    output[8 + key.len()..]
+        .iter_mut()
+        .for_each(|byte| *byte = 0);
+    if output.len() == 16 {
+        let mut block = [0; 16];
+        block[..8].copy_from_slice(&iv);
+        block[8..].copy_from_slice(&output[8..]);
+        output.copy_from_slice(&block_cipher.encrypt_block(&block));
+    } else {
+        wrap_in_place(&block_cipher, iv, output);
+    }
+    Ok(())
+}
+
+/// Unwraps a key wrapped with KWP, checking its integrity and outputting the key part of
+/// `output`. On failure, `output` is zeroed.
+///
+/// # Errors
+///
+/// Fails like `unwrap`, for KWP ciphertexts.
+pub fn unwrap_with_padding<'a, E: BlockCipher>(
+    kek: &[u8],
+    wrapped: &[u8],
+    output: &'a mut [u8],
+) -> Result<&'a mut [u8], Error> {
+    let block_cipher = E::new(kek)?;
+    check_unwrap_lengths(wrapped, output)?;
+    if wrapped.len() < 16 || !wrapped.len().is_multiple_of(8) {
+        return Err(Error::TagMismatch);
+    }
+    let a = if wrapped.len() == 16 {
+        let mut block = [0; 16];
+        block.copy_from_slice(wrapped);
+        let block = block_cipher.decrypt_block(&block);
+        output.copy_from_slice(&block[8..]);
+        let mut a = [0; 8];
+        a.copy_from_slice(&block[..8]);
+        a
+    } else {
+        unwrap_in_place(&block_cipher, wrapped, output)
+    };
+
+    let len = u32::from_be_bytes([a[4], a[5], a[6], a[7]]);
+    let padding_len = output.len() as i64 - i64::from(len);
+    // Each of these is nonzero if the check fails.
+    let mut invalid = u32::from(!ct::constant_time_eq(&a[..4], &PADDING_IV));
+    invalid |= ((padding_len >> 63) | !((padding_len - 8) >> 63)) as u32;
+    let padding_len = padding_len as u32;
+    for (i, &byte) in (0..).zip(output.iter().rev().take(8)) {
+        invalid |= lt_mask(i, padding_len) & u32::from(byte);
+    }
+    if invalid == 0 {
+        Ok(&mut output[..len as usize])
+    } else {
+        output.iter_mut().for_each(|byte| *byte = 0);
+        Err(Error::TagMismatch)
+    }
+}
+
+fn check_unwrap_lengths(wrapped: &[u8], output: &[u8]) -> Result<(), Error> {
+    if wrapped.len() != output.len() + 8 {
+        return Err(Error::InvalidLength);
+    }
+    Ok(())
+}
+
+/// Applies the wrapping function W to `buffer`, whose first 8 bytes are overwritten by `iv`.
+fn wrap_in_place<E: BlockCipher>(block_cipher: &E, iv: [u8; 8], buffer: &mut [u8]) {
+    let n = buffer.len() / 8 - 1;
+    let (a, r) = buffer.split_at_mut(8);
+    a.copy_from_slice(&iv);
+    let mut block = [0; 16];
+    for j in 0..6 {
+        for (i, r_i) in r.chunks_mut(8).enumerate() {
+            block[..8].copy_from_slice(a);
+            block[8..].copy_from_slice(r_i);
+            let b = block_cipher.encrypt_block(&block);
+            let t = (n * j + i + 1) as u64;
+            a.copy_from_slice(&(u64::from_be_bytes(first_half(&b)) ^ t).to_be_bytes());
+            r_i.copy_from_slice(&b[8..]);
+        }
+    }
+}
+
+/// Applies the unwrapping function W^-1 to `wrapped`, writing all but the first 8 bytes into
+/// `output` and returning the first 8 bytes, which must be checked.
+fn unwrap_in_place<E: BlockCipher>(block_cipher: &E, wrapped: &[u8], output: &mut [u8]) -> [u8; 8] {
+    let n = output.len() / 8;
+    let mut a = [0; 8];
+    a.copy_from_slice(&wrapped[..8]);
+    output.copy_from_slice(&wrapped[8..]);
+    let mut block = [0; 16];
+    for j in (0..6).rev() {
+        for (i, r_i) in output.chunks_mut(8).enumerate().rev() {
+            let t = (n * j + i + 1) as u64;
+            block[..8].copy_from_slice(&(u64::from_be_bytes(a) ^ t).to_be_bytes());
+            block[8..].copy_from_slice(r_i);
+            let b = block_cipher.decrypt_block(&block);
+            a = first_half(&b);
+            r_i.copy_from_slice(&b[8..]);
+        }
+    }
+    a
+}
+
+fn first_half(block: &[u8; 16]) -> [u8; 8] {
+    let mut half = [0; 8];
+    half.copy_from_slice(&block[..8]);
+    half
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aes::{Aes128, Aes192, Aes256};
+    use crate::test_helpers::*;
+
+    fn check<E: BlockCipher>(kek: &str, key: &str, expected: &str) {
+        let kek = &h2b(kek);
+        let key = &h2b(key);
+        let expected = &h2b(expected);
+        let wrapped = &mut vec![0; key.len() + 8];
+        wrap::<E>(kek, key, wrapped).unwrap();
+        assert_eq!(expected, wrapped);
+        let unwrapped = &mut vec![0; key.len()];
+        assert_eq!(Ok(()), unwrap::<E>(kek, wrapped, unwrapped));
+        assert_eq!(key, unwrapped);
+
+        wrapped[0] ^= 1;
+        assert_eq!(
+            Err(Error::TagMismatch),
+            unwrap::<E>(kek, wrapped, unwrapped)
+        );
+        assert_eq!(&vec![0; key.len()], unwrapped);
+    }
+
+    fn check_with_padding<E: BlockCipher>(kek: &str, key: &str, expected: &str) {
+        let kek = &h2b(kek);
+        let key = &h2b(key);
+        let expected = &h2b(expected);
+        let wrapped = &mut vec![0; padded_wrapped_len(key.len())];
+        wrap_with_padding::<E>(kek, key, wrapped).unwrap();
+        assert_eq!(expected, wrapped);
+        let buffer = &mut vec![0; wrapped.len() - 8];
+        let unwrapped = unwrap_with_padding::<E>(kek, wrapped, buffer);
+        assert_eq!(Ok(&mut key.clone()[..]), unwrapped);
+
+        let last = wrapped.len() - 1;
+        wrapped[last] ^= 1;
+        let unwrapped = unwrap_with_padding::<E>(kek, wrapped, buffer);
+        assert_eq!(Err(Error::TagMismatch), unwrapped);
+    }
+
+    #[test]
+    fn test_wrap() {
+        let key = "00112233445566778899aabbccddeeff";
+        let expected = "1fa68b0a8112b447aef34bd8fb5a7b829d3e862371d2cfe5";
+        check::<Aes128>("000102030405060708090a0b0c0d0e0f", key, expected);
+
+        let kek = "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f";
+        let key = "00112233445566778899aabbccddeeff000102030405060708090a0b0c0d0e0f";
+        let expected = "28c9f404c4b810f4cbccb35cfb87f8263f5786e2d80ed326\
+                        cbc7f0e71a99f43bfb988b9b7a02dd21";
+        check::<Aes256>(kek, key, expected);
+    }
+
+    #[test]
+    fn test_wrap_with_padding() {
+        let kek = "5840df6e29b02af1ab493b705bf16ea1ae8338f4dcc176a8";
+        let key = "c37b7e6492584340bed12207808941155068f738";
+        let expected = "138bdeaa9b8fa7fc61f97742e72248ee5ae6ae5360d1ae6a5f54f373fa543b6a";
+        check_with_padding::<Aes192>(kek, key, expected);
+        let expected = "afbeb0f07dfbf5419200f2ccb50bb24f";
+        check_with_padding::<Aes192>(kek, "466f7250617369", expected);
+    }
+
+    #[test]
+    fn test_unwrap_invalid_lengths() {
+        let kek = &[0; 16];
+        assert_eq!(
+            Err(Error::TagMismatch),
+            unwrap::<Aes128>(kek, &[0; 16], &mut [0; 8])
+        );
+        assert_eq!(
+            Err(Error::TagMismatch),
+            unwrap::<Aes128>(kek, &[0; 25], &mut [0; 17])
+        );
+        let buffer = &mut [];
+        let unwrapped = unwrap_with_padding::<Aes128>(kek, &[0; 8], buffer);
+        assert_eq!(Err(Error::TagMismatch), unwrapped);
+
+        let error = Err(Error::InvalidLength);
+        assert_eq!(error, unwrap::<Aes128>(kek, &[0; 24], &mut [0; 24]));
+        let buffer = &mut [0; 16];
+        let unwrapped = unwrap_with_padding::<Aes128>(kek, &[0; 16], buffer);
+        assert_eq!(error, unwrapped.map(|_| ()));
+        let error = Err(Error::InvalidKey);
+        assert_eq!(error, unwrap::<Aes128>(&kek[1..], &[0; 24], &mut [0; 16]));
+    }
+
+    #[test]
+    fn test_wrap_invalid_lengths() {
+        let kek = &[0; 16];
+        let error = Err(Error::InvalidLength);
+        assert_eq!(error, wrap::<Aes128>(kek, &[0; 8], &mut [0; 16]));
+        assert_eq!(error, wrap::<Aes128>(kek, &[0; 20], &mut [0; 28]));
+        assert_eq!(error, wrap::<Aes128>(kek, &[0; 16], &mut [0; 16]));
+        assert_eq!(error, wrap_with_padding::<Aes128>(kek, &[], &mut [0; 8]));
+        assert_eq!(
+            error,
+            wrap_with_padding::<Aes128>(kek, &[0; 5], &mut [0; 24])
+        );
+        let error = Err(Error::InvalidKey);
+        assert_eq!(error, wrap::<Aes128>(&kek[1..], &[0; 16], &mut [0; 24]));
+        assert_eq!(
+            error,
+            wrap_with_padding::<Aes128>(&kek[1..], &[0; 5], &mut [0; 16])
+        );
+    }
+
+    #[test]
+    fn test_unwrap_with_invalid_padding() {
+        let kek = &[0x42; 16];
+        let block_cipher = Aes128::new(kek).unwrap();
+        // Lengths that don't match the padded length, and nonzero padding.
+        for (len, last_byte) in &[(0, 0), (9, 0), (16, 0), (7, 1)] {
+            let mut block = [0; 16];
+            block[..4].copy_from_slice(&PADDING_IV);
+            block[4..8].copy_from_slice(&(*len as u32).to_be_bytes());
+            block[15] = *last_byte;
+            let wrapped = &block_cipher.encrypt_block(&block);
+            let buffer = &mut [0; 8];
+            let unwrapped = unwrap_with_padding::<Aes128>(kek, wrapped, buffer);
+            assert_eq!(Err(Error::TagMismatch), unwrapped);
+        }
+    }
+}
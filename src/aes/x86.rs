@@ -0,0 +1,55 @@
+//! AES using the AES-NI instructions.
+#[cfg(target_arch = "x86")]
+use core::arch::x86::*;
+#[cfg(target_arch = "x86_64")]
+use core::arch::x86_64::*;
+
+pub(super) fn available() -> bool {
+    #[cfg(feature = "std")]
+    {
+        is_x86_feature_detected!("aes") && is_x86_feature_detected!("sse2")
+    }
+    #[cfg(not(feature = "std"))]
+    {
+        cfg!(all(target_feature = "aes", target_feature = "sse2"))
+    }
+}
+
+#[target_feature(enable = "aes,sse2")]
+pub(super) unsafe fn encrypt_block(schedule: &[u8], input: &[u8; 16]) -> [u8; 16] {
+    let rounds = schedule.len() / 16 - 1;
+    let mut state = _mm_xor_si128(load(input), load(&schedule[..16]));
+    for round_key in schedule[16..16 * rounds].chunks(16) {
+        state = _mm_aesenc_si128(state, load(round_key));
+    }
+    state = _mm_aesenclast_si128(state, load(&schedule[16 * rounds..]));
+    store(state)
+}
+
+#[target_feature(enable = "aes,sse2")]
+pub(super) unsafe fn decrypt_block(schedule: &[u8], input: &[u8; 16]) -> [u8; 16] {
+    // AESDEC implements the equivalent inverse cipher, which needs InvMixColumns applied to the
+    // middle round keys.
+    let rounds = schedule.len() / 16 - 1;
+    let mut state = _mm_xor_si128(load(input), load(&schedule[16 * rounds..]));
+    for round_key in schedule[16..16 * rounds].chunks(16).rev() {
+        state = _mm_aesdec_si128(state, _mm_aesimc_si128(load(round_key)));
+    }
+    state = _mm_aesdeclast_si128(state, load(&schedule[..16]));
+    store(state)
+}
+
+#[inline]
+#[target_feature(enable = "sse2")]
+unsafe fn load(bytes: &[u8]) -> __m128i {
+    debug_assert_eq!(16, bytes.len());
+    _mm_loadu_si128(bytes.as_ptr() as *const __m128i)
+}
+
+#[inline]
+#[target_feature(enable = "sse2")]
+unsafe fn store(state: __m128i) -> [u8; 16] {
+    let mut output = [0; 16];
+    _mm_storeu_si128(output.as_mut_ptr() as *mut __m128i, state);
+    output
+}
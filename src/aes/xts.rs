@@ -0,0 +1,230 @@
+//! Module for the XTS mode of operation for block ciphers (IEEE 1619, NIST SP 800-38E), used to
+//! encrypt storage devices one data unit, such as a 512-byte or 4096-byte sector, at a time.
+//!
+//! XTS does not authenticate data, and equal plaintexts in the same position encrypt to equal
+//! ciphertexts.
+use super::BlockCipher;
+use crate::error::Error;
+
+/// A block cipher in XTS mode.
+///
+/// The tweak for a data unit is usually its sequence number, as a little-endian integer.
+///
+/// # Examples
+///
+/// ```
+/// use crypto_pure::aes::xts::Xts;
+/// use crypto_pure::aes::Aes256;
+/// # let key = &[[0x42; 32], [0x24; 32]].concat();
+/// let xts = Xts::<Aes256>::new(key)?;
+/// let tweak = &7u128.to_le_bytes();
+/// let sector = &mut [0x55; 512];
+/// xts.encrypt_sector(tweak, sector)?;
+/// xts.decrypt_sector(tweak, sector)?;
+/// assert_eq!(&[0x55; 512][..], &sector[..]);
+/// # Ok::<(), crypto_pure::error::Error>(())
+/// ```
+pub struct Xts<E> {
+    data_cipher: E,
+    tweak_cipher: E,
+}
+
+impl<E: BlockCipher> Xts<E> {
+    /// Initializes XTS mode given a key consisting of the data key followed by the tweak key.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::InvalidKey` if `key.len()` is not twice the block cipher's key length or
+    /// the two halves are equal.
+    pub fn new(key: &[u8]) -> Result<Self, Error> {
+        if key.len() != 2 * E::KEY_LEN {
+            return Err(Error::InvalidKey);
+        }
+        let (data_key, tweak_key) = key.split_at(E::KEY_LEN);
+        if data_key == tweak_key {
+            return Err(Error::InvalidKey);
+        }
+        Ok(Self {
+            data_cipher: E::new(data_key)?,
+            tweak_cipher: E::new(tweak_key)?,
+        })
+    }
+
+    /// Encrypts a data unit in place, using ciphertext stealing if its length is not a multiple
+    /// of 16.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::InvalidLength` if `data.len()` is less than 16 or greater than 2^24.
+    pub fn encrypt_sector(&self, tweak: &[u8; 16], data: &mut [u8]) -> Result<(), Error> {
+        check_bounds(data)?;
+        let mut t = self.tweak_cipher.encrypt_block(tweak);
+        let remainder = data.len() % 16;
+        let full_len = data.len() - remainder;
+        for block in data[..full_len].chunks_mut(16) {
+            self.encrypt_block(&t, block);
+            t = double(&t);
+        }
+        if remainder != 0 {
+            // Swap the partial block with the start of the last full block, and fill out the
+            // partial block with the rest of the last full block's ciphertext.
+            let (last_full, partial) = data[full_len - 16..].split_at_mut(16);
+            let mut stolen = [0; 16];
+            stolen.copy_from_slice(last_full);
+            stolen[..remainder].copy_from_slice(partial);
+            partial.copy_from_slice(&last_full[..remainder]);
+            self.encrypt_block(&t, &mut stolen);
+            last_full.copy_from_slice(&stolen);
+        }
+        Ok(())
+    }
+
+    /// Decrypts a data unit in place, using ciphertext stealing if its length is not a multiple
+    /// of 16.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::InvalidLength` if `data.len()` is less than 16 or greater than 2^24.
+    pub fn decrypt_sector(&self, tweak: &[u8; 16], data: &mut [u8]) -> Result<(), Error> {
+        check_bounds(data)?;
+        let mut t = self.tweak_cipher.encrypt_block(tweak);
+        let remainder = data.len() % 16;
+        let full_len = data.len() - remainder;
+        // With ciphertext stealing, the last full block was encrypted with the final tweak.
+        let stealing_len = if remainder == 0 { 0 } else { 16 };
+        for block in data[..full_len - stealing_len].chunks_mut(16) {
+            self.decrypt_block(&t, block);
+            t = double(&t);
+        }
+        if remainder != 0 {
+            let (last_full, partial) = data[full_len - 16..].split_at_mut(16);
+            let mut stolen = [0; 16];
+            stolen.copy_from_slice(last_full);
+            self.decrypt_block(&double(&t), &mut stolen);
+            last_full.copy_from_slice(&stolen);
+            last_full[..remainder].copy_from_slice(partial);
+            partial.copy_from_slice(&stolen[..remainder]);
+            self.decrypt_block(&t, last_full);
+        }
+        Ok(())
+    }
+
+    fn encrypt_block(&self, t: &[u8; 16], block: &mut [u8]) {
+        let mut input = [0; 16];
+        for ((x, y), z) in input.iter_mut().zip(block.iter()).zip(t) {
+            *x = y ^ z;
+        }
+        let output = self.data_cipher.encrypt_block(&input);
+        for ((x, y), z) in block.iter_mut().zip(&output).zip(t) {
+            *x = y ^ z;
+        }
+    }
+
+    fn decrypt_block(&self, t: &[u8; 16], block: &mut [u8]) {
+        let mut input = [0; 16];
+        for ((x, y), z) in input.iter_mut().zip(block.iter()).zip(t) {
+            *x = y ^ z;
+        }
+        let output = self.data_cipher.decrypt_block(&input);
+        for ((x, y), z) in block.iter_mut().zip(&output).zip(t) {
+            *x = y ^ z;
+        }
+    }
+}
+
+/// Multiplies the tweak by x in GF(2^128), with bytes in little-endian order, in constant time.
+fn double(t: &[u8; 16]) -> [u8; 16] {
+    let t = u128::from_le_bytes(*t);
+    let carry = ((t >> 127) as u8).wrapping_neg() & 0x87;
+    let mut output = (t << 1).to_le_bytes();
+    output[0] ^= carry;
+    output
+}
+
+fn check_bounds(data: &[u8]) -> Result<(), Error> {
+    if data.len() < 16 || data.len() > 1 << 24 {
+        return Err(Error::InvalidLength);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aes::{Aes128, Aes256};
+    use crate::sha2::sha256;
+    use crate::test_helpers::*;
+    use std::vec::Vec;
+
+    const KEY: &str = "fffefdfcfbfaf9f8f7f6f5f4f3f2f1f0bfbebdbcbbbab9b8b7b6b5b4b3b2b1b0";
+
+    fn check<E: BlockCipher>(key: &[u8], tweak: u128, plaintext: &[u8], ciphertext: &[u8]) {
+        let xts = Xts::<E>::new(key).unwrap();
+        let tweak = &tweak.to_le_bytes();
+        let buffer = &mut plaintext.to_vec();
+        xts.encrypt_sector(tweak, buffer).unwrap();
+        assert_eq!(ciphertext, &buffer[..]);
+        xts.decrypt_sector(tweak, buffer).unwrap();
+        assert_eq!(plaintext, &buffer[..]);
+    }
+
+    fn check_hash<E: BlockCipher>(key: &[u8], tweak: u128, len: usize, expected: &str) {
+        let xts = Xts::<E>::new(key).unwrap();
+        let plaintext: &Vec<_> = &(0..len).map(|i| i as u8).collect();
+        let buffer = &mut plaintext.clone();
+        xts.encrypt_sector(&tweak.to_le_bytes(), buffer).unwrap();
+        assert_eq!(h2b(expected), sha256(buffer));
+        xts.decrypt_sector(&tweak.to_le_bytes(), buffer).unwrap();
+        assert_eq!(plaintext, buffer);
+    }
+
+    #[test]
+    fn test_ieee_1619_vectors() {
+        let key = &h2b(KEY);
+        let tweak = 0x12_3456_789a;
+        let plaintext: &Vec<_> = &(0..32).collect();
+        let ciphertext = "edbf9dace45d6f6a7306e64be5dd824b2538f5724fcf24249ac111ab45ad3923";
+        check::<Aes128>(key, tweak, plaintext, &h2b(ciphertext));
+        let ciphertext = &h2b("6c1625db4671522d3d7599601de7ca09ed");
+        check::<Aes128>(key, tweak, &plaintext[..17], ciphertext);
+        let ciphertext = &h2b("9d84c813f719aa2c7be3f66171c7c5c2edbf9dac");
+        check::<Aes128>(key, tweak, &plaintext[..20], ciphertext);
+    }
+
+    #[test]
+    fn test_sectors() {
+        let key = &h2b(KEY);
+        let expected = "5107f6c5d7c6e63caa7e13224a79e6585cd86fbbf74333e586cad9cfea2575db";
+        check_hash::<Aes128>(key, 7, 512, expected);
+        let expected = "5c33a93a86f9a96e64f3df938bb0fb3a96882f47570ccc47598067d17a1403d6";
+        check_hash::<Aes128>(key, 7, 519, expected);
+
+        let key: &Vec<_> = &(0..64).collect();
+        let expected = "9315e2bea1ce37967acbd8997d4a81c0cf85034643721f4c1da135194fc28e1f";
+        check_hash::<Aes256>(key, 5, 4096, expected);
+    }
+
+    #[test]
+    fn test_invalid_lengths() {
+        let key = &h2b(KEY);
+        let error = Err(Error::InvalidKey);
+        assert_eq!(error, Xts::<Aes128>::new(&key[1..]).map(|_| ()));
+        assert_eq!(error, Xts::<Aes128>::new(&[0x42; 32]).map(|_| ()));
+        let xts = Xts::<Aes128>::new(key).unwrap();
+        let error = Err(Error::InvalidLength);
+        assert_eq!(error, xts.encrypt_sector(&[0; 16], &mut [0; 15]));
+        assert_eq!(error, xts.decrypt_sector(&[0; 16], &mut [0; 15]));
+        let data = &mut vec![0; (1 << 24) + 1];
+        assert_eq!(error, xts.encrypt_sector(&[0; 16], data));
+    }
+
+    #[test]
+    fn test_double() {
+        let mut t = [0; 16];
+        t[15] = 0x80;
+        t[0] = 0x01;
+        let mut expected = [0; 16];
+        expected[0] = 0x87 ^ 0x02;
+        assert_eq!(expected, double(&t));
+    }
+}
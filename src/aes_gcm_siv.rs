@@ -0,0 +1,243 @@
+//! Module for AES-GCM-SIV (RFC 8452), a nonce misuse-resistant AEAD.
+//!
+//! Repeating a nonce only reveals whether the same message and data were encrypted, unlike GCM,
+//! where it reveals the authentication key.
+use crate::aead::Aead;
+use crate::aes::BlockCipher;
+use crate::ct;
+use crate::error::Error;
+use crate::ghash;
+use byteorder::{ByteOrder as _, LittleEndian};
+
+/// An AES-GCM-SIV AEAD cipher, using `Aes128` or `Aes256`.
+///
+/// # Examples
+///
+/// ```
+/// use crypto_pure::aead::Aead;
+/// use crypto_pure::aes::Aes256;
+/// use crypto_pure::aes_gcm_siv::AesGcmSiv;
+/// # let key = &[0x42; 32];
+/// # let nonce = &[0x24; 12];
+/// let cipher = AesGcmSiv::<Aes256>::new(key).unwrap();
+/// let buffer = &mut b"message".to_vec();
+/// let tag = cipher.seal_detached(nonce, b"header", buffer).unwrap();
+/// assert_eq!(Ok(()), cipher.open_detached(nonce, b"header", buffer, &tag));
+/// assert_eq!(b"message", &buffer[..]);
+/// ```
+pub struct AesGcmSiv<E> {
+    key_generating_cipher: E,
+}
+
+impl<E: BlockCipher> Aead for AesGcmSiv<E> {
+    const KEY_LEN: usize = E::KEY_LEN;
+    const NONCE_LEN: usize = 12;
+
+    /// Initializes an AES-GCM-SIV cipher given a key-generating key. Fails with
+    /// `Error::InvalidKey` if `key.len()` is not equal to `E::KEY_LEN` or the block cipher's key
+    /// length is not 16 or 32.
+    fn new(key: &[u8]) -> Result<Self, Error> {
+        if E::KEY_LEN != 16 && E::KEY_LEN != 32 {
+            return Err(Error::InvalidKey);
+        }
+        Ok(Self {
+            key_generating_cipher: E::new(key)?,
+        })
+    }
+
+    /// Encrypts a message in place and outputs a tag authenticating it and provided data. Fails
+    /// with `Error::InvalidLength` if `nonce.len()` is not equal to 12 or `buffer.len()` or
+    /// `data.len()` is greater than 2^36.
+    fn seal_detached(
+        &self,
+        nonce: &[u8],
+        data: &[u8],
+        buffer: &mut [u8],
+    ) -> Result<[u8; 16], Error> {
+        check_bounds(buffer, nonce, data)?;
+        let (authentication_key, encryption_cipher) = self.derive_keys(nonce)?;
+        let tag = tag(&authentication_key, &encryption_cipher, nonce, data, buffer);
+        process(&encryption_cipher, &tag, buffer);
+        Ok(tag)
+    }
+
+    /// Verifies `tag` and decrypts a ciphertext in place. On failure, `buffer` is left unchanged.
+    /// Fails with `Error::InvalidLength` if `nonce.len()` is not equal to 12 or `buffer.len()` or
+    /// `data.len()` is greater than 2^36.
+    fn open_detached(
+        &self,
+        nonce: &[u8],
+        data: &[u8],
+        buffer: &mut [u8],
+        tag: &[u8; 16],
+    ) -> Result<(), Error> {
+        check_bounds(buffer, nonce, data)?;
+        let (authentication_key, encryption_cipher) = self.derive_keys(nonce)?;
+        process(&encryption_cipher, tag, buffer);
+        let expected_tag = self::tag(&authentication_key, &encryption_cipher, nonce, data, buffer);
+        if ct::constant_time_eq(&expected_tag, tag) {
+            Ok(())
+        } else {
+            process(&encryption_cipher, tag, buffer);
+            Err(Error::TagMismatch)
+        }
+    }
+}
+
+impl<E: BlockCipher> AesGcmSiv<E> {
+    fn derive_keys(&self, nonce: &[u8]) -> Result<([u8; 16], E), Error> {
+        let mut input = [0; 16];
+        input[4..].copy_from_slice(nonce);
+        let mut keys = [0; 48];
+        let keys = &mut keys[..16 + E::KEY_LEN];
+        for (i, chunk) in (0..).zip(keys.chunks_mut(8)) {
+            LittleEndian::write_u32(&mut input[..4], i);
+            chunk.copy_from_slice(&self.key_generating_cipher.encrypt_block(&input)[..8]);
+        }
+        let mut authentication_key = [0; 16];
+        authentication_key.copy_from_slice(&keys[..16]);
+        Ok((authentication_key, E::new(&keys[16..])?))
+    }
+}
+
+fn tag<E: BlockCipher>(
+    authentication_key: &[u8; 16],
+    encryption_cipher: &E,
+    nonce: &[u8],
+    data: &[u8],
+    message: &[u8],
+) -> [u8; 16] {
+    let mut s = ghash::polyval(authentication_key, data, message);
+    for (s_byte, nonce_byte) in s.iter_mut().zip(nonce) {
+        *s_byte ^= nonce_byte;
+    }
+    s[15] &= 0x7f;
+    encryption_cipher.encrypt_block(&s)
+}
+
+fn process<E: BlockCipher>(encryption_cipher: &E, tag: &[u8; 16], buffer: &mut [u8]) {
+    let mut counter = *tag;
+    counter[15] |= 0x80;
+    let initial = LittleEndian::read_u32(&counter[..4]);
+    for (i, chunk) in (0..).zip(buffer.chunks_mut(16)) {
+        LittleEndian::write_u32(&mut counter[..4], initial.wrapping_add(i));
+        let block = encryption_cipher.encrypt_block(&counter);
+        for (byte, block_byte) in chunk.iter_mut().zip(&block) {
+            *byte ^= block_byte;
+        }
+    }
+}
+
+fn check_bounds(message: &[u8], nonce: &[u8], data: &[u8]) -> Result<(), Error> {
+    if nonce.len() != 12 || 1 << 36 < message.len() as u64 || 1 << 36 < data.len() as u64 {
+        return Err(Error::InvalidLength);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aes::{Aes128, Aes192, Aes256};
+    use crate::test_helpers::*;
+
+    fn check<E: BlockCipher>(key: &str, nonce: &str, data: &str, msg: &str, sealed: &str) {
+        let key = &h2b(key);
+        let nonce = &h2b(nonce);
+        let data = &h2b(data);
+        let message = &h2b(msg);
+        let sealed = &h2b(sealed);
+        let (ciphertext, tag) = sealed.split_at(message.len());
+        let cipher = AesGcmSiv::<E>::new(key).unwrap();
+
+        let output = &mut vec![0; message.len()];
+        assert_eq!(tag, cipher.encrypt(message, nonce, data, output).unwrap());
+        assert_eq!(ciphertext, &output[..]);
+        assert_eq!(Ok(()), cipher.decrypt(ciphertext, nonce, data, tag, output));
+        assert_eq!(message, output);
+        let bad_nonce = &mut nonce.clone();
+        bad_nonce[0] ^= 1;
+        let error = Err(Error::TagMismatch);
+        assert_eq!(
+            error,
+            cipher.decrypt(ciphertext, bad_nonce, data, tag, output)
+        );
+        assert_eq!(
+            error,
+            cipher.decrypt(ciphertext, nonce, data, &tag[1..], output)
+        );
+        let error = Err(Error::InvalidLength);
+        assert_eq!(
+            error,
+            cipher.decrypt(ciphertext, &nonce[1..], data, tag, output)
+        );
+
+        let buffer = &mut [&message[..], &[0; 16]].concat();
+        cipher.seal_in_place(nonce, data, buffer).unwrap();
+        assert_eq!(sealed, buffer);
+        buffer[0] ^= 1;
+        assert_eq!(
+            Err(Error::TagMismatch),
+            cipher.open_in_place(nonce, data, buffer)
+        );
+        buffer[0] ^= 1;
+        assert_eq!(sealed, buffer);
+        let opened = cipher.open_in_place(nonce, data, buffer);
+        assert_eq!(Ok(&message[..]), opened.map(|x| &*x));
+    }
+
+    #[test]
+    fn test_aes128_gcm_siv() {
+        let key = "01000000000000000000000000000000";
+        let nonce = "030000000000000000000000";
+        check::<Aes128>(key, nonce, "", "", "dc20e2d83f25705bb49e439eca56de25");
+        let sealed = "b5d839330ac7b786578782fff6013b815b287c22493a364c";
+        check::<Aes128>(key, nonce, "", "0100000000000000", sealed);
+        let msg = "0100000000000000000000000000000002000000000000000000000000000000\
+                   0300000000000000000000000000000004000000000000000000000000000000";
+        let sealed = "0f22130eb2767406e95f84371dafbb533c0ad1bd55bd134c57f0851f50a412f3\
+                      f6033fcdd2bf04b653ebdd9129525ad73307d8a2b8c58bd64df01cf07d7bfbe1\
+                      aa2d83bc9a88c557bf5f997c8b83e1ba";
+        check::<Aes128>(key, nonce, "01", msg, sealed);
+        let data = "010000000000000000000000000000000200";
+        let msg = "0200000000000000000000000000000003000000";
+        let sealed = "493c00f98ff9c772b28099ff861567f6f6925029276388847e1dd576392c0a13\
+                      0e7bac6a";
+        check::<Aes128>(key, nonce, data, msg, sealed);
+
+        let key = "e66021d5eb8e4f4066d4adb9c33560e4";
+        let nonce = "f46e44bb3da0015c94f70887";
+        check::<Aes128>(key, nonce, "", "", "a4194b79071b01a87d65f706e3949578");
+    }
+
+    #[test]
+    fn test_aes256_gcm_siv() {
+        let key = "0100000000000000000000000000000000000000000000000000000000000000";
+        let nonce = "030000000000000000000000";
+        check::<Aes256>(key, nonce, "", "", "07f5f4169bbf55a8400cd47ea6fd400f");
+        let sealed = "c2ef328e5c71c83b843122130f7364b761e0b97427e3df28";
+        check::<Aes256>(key, nonce, "", "0100000000000000", sealed);
+        let data = "010000000000000000000000000000000200";
+        let msg = "0200000000000000000000000000000003000000";
+        let sealed = "91e487c3639df1358327662462bb1652ea0c3e990a8d839904854aac19587bb4\
+                      09e34bbf";
+        check::<Aes256>(key, nonce, data, msg, sealed);
+    }
+
+    #[test]
+    fn test_counter_wrap() {
+        let key = "0000000000000000000000000000000000000000000000000000000000000000";
+        let nonce = "000000000000000000000000";
+        let msg = "000000000000000000000000000000004db923dc793ee6497c76dcc03a98e108";
+        let sealed = "f3f80f2cf0cb2dd9c5984fcda908456cc537703b5ba70324a6793a7bf218d3ea\
+                      ffffffff000000000000000000000000";
+        check::<Aes256>(key, nonce, "", msg, sealed);
+    }
+
+    #[test]
+    fn test_invalid_key() {
+        let error = Err(Error::InvalidKey);
+        assert_eq!(error, AesGcmSiv::<Aes128>::new(&[0; 32]).map(|_| ()));
+        assert_eq!(error, AesGcmSiv::<Aes192>::new(&[0; 24]).map(|_| ()));
+    }
+}
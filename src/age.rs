@@ -0,0 +1,948 @@
+//! Module for the age file encryption format (age-encryption.org/v1).
+//!
+//! A file is encrypted under a random file key, which is wrapped in the header once for each
+//! recipient: either for X25519 recipients, given as `age1...` public keys, or with a passphrase
+//! stretched with scrypt, which must then be the only recipient. The header is authenticated
+//! with HMAC-SHA-256 under a key derived from the file key, and the payload is split into 64 KiB
+//! chunks, each encrypted with ChaCha20-Poly1305 using the STREAM construction, so truncation
+//! and reordering are detected.
+//!
+//! `Encryptor` and `Decryptor` work as streams: the `Encryptor` writes the header when it is
+//! created and each chunk once it is full, and must be finished with `Encryptor::finish`. The
+//! `Decryptor` reads and verifies the header when it is created, and each chunk as it is read.
+//! Errors other than I/O errors are reported with `io::ErrorKind::InvalidData` and an `Error`.
+//!
+//! # Examples
+//!
+//! ```
+//! use crypto_pure::age::{Decryptor, Encryptor, Identity};
+//! use crypto_pure::rand::SystemRandom;
+//! use std::io::{Read, Write};
+//! # fn main() -> std::io::Result<()> {
+//! let rng = &SystemRandom::new();
+//! let identity = Identity::generate(rng);
+//! let recipient = identity.recipient();
+//! assert!(recipient.encode().starts_with("age1"));
+//!
+//! let mut encryptor = Encryptor::new(Vec::new(), &[recipient], rng)?;
+//! encryptor.write_all(b"message")?;
+//! let file = encryptor.finish()?;
+//!
+//! let mut decryptor = Decryptor::new(&file[..], &[identity])?;
+//! let mut message = Vec::new();
+//! decryptor.read_to_end(&mut message)?;
+//! assert_eq!(b"message", &message[..]);
+//! # Ok(())
+//! # }
+//! ```
+use crate::aead::stream;
+use crate::aead::Aead;
+use crate::ct;
+use crate::curve25519;
+use crate::encoding::base64;
+use crate::error::Error;
+use crate::hkdf::Hkdf;
+use crate::hmac::Hmac;
+use crate::poly1305::ChaCha20Poly1305;
+use crate::rand::SecureRandom;
+use crate::scrypt::{self, Params};
+use crate::secret::SecretKey;
+use crate::sha2::Sha256;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::str;
+
+/// The base 2 logarithm of the scrypt work factor that `age` uses for passphrases.
+pub const DEFAULT_LOG_N: u8 = 18;
+
+const VERSION_LINE: &[u8] = b"age-encryption.org/v1";
+const X25519_LABEL: &[u8] = b"age-encryption.org/v1/X25519";
+const SCRYPT_LABEL: &[u8] = b"age-encryption.org/v1/scrypt";
+const X25519: &str = "X25519";
+const SCRYPT: &str = "scrypt";
+const RECIPIENT_HRP: &str = "age";
+const IDENTITY_HRP: &str = "age-secret-key-";
+
+const FILE_KEY_LEN: usize = 16;
+const NONCE_LEN: usize = 16;
+const SALT_LEN: usize = 16;
+const WRAPPED_KEY_LEN: usize = FILE_KEY_LEN + 16;
+const CHUNK_LEN: usize = 64 * 1024;
+const TAG_LEN: usize = 16;
+const BODY_LINE_LEN: usize = 64;
+const MAX_LINE_LEN: usize = 4096;
+
+/// An X25519 recipient, encoded as an `age1...` Bech32 string.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Recipient([u8; 32]);
+
+impl Recipient {
+    /// Uses an X25519 public key as a recipient.
+    pub fn new(public_key: &[u8; 32]) -> Self {
+        Recipient(*public_key)
+    }
+
+    /// Outputs the X25519 public key.
+    pub fn public_key(&self) -> &[u8; 32] {
+        &self.0
+    }
+
+    /// Parses an `age1...` string.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::InvalidEncoding` if `recipient` is not a valid recipient.
+    pub fn parse(recipient: &str) -> Result<Self, Error> {
+        if recipient.bytes().any(|c| c.is_ascii_uppercase()) {
+            return Err(Error::InvalidEncoding);
+        }
+        let mut public_key = [0; 32];
+        bech32_decode(RECIPIENT_HRP, recipient, &mut public_key)?;
+        Ok(Recipient(public_key))
+    }
+
+    /// Outputs the `age1...` encoding of the recipient.
+    pub fn encode(&self) -> String {
+        bech32_encode(RECIPIENT_HRP, &self.0)
+    }
+}
+
+/// An X25519 identity, encoded as an `AGE-SECRET-KEY-1...` Bech32 string.
+pub struct Identity(SecretKey<[u8; 32]>);
+
+impl Identity {
+    /// Uses an X25519 secret key as an identity.
+    pub fn new(secret_key: &[u8; 32]) -> Self {
+        Identity(SecretKey::new(*secret_key))
+    }
+
+    /// Generates an identity with a secret key drawn from `rng`.
+    pub fn generate(rng: &dyn SecureRandom) -> Self {
+        let mut secret_key = SecretKey::new([0; 32]);
+        rng.fill(&mut *secret_key);
+        Identity(secret_key)
+    }
+
+    /// Parses an `AGE-SECRET-KEY-1...` string.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::InvalidEncoding` if `identity` is not a valid identity.
+    pub fn parse(identity: &str) -> Result<Self, Error> {
+        if identity.bytes().any(|c| c.is_ascii_lowercase()) {
+            return Err(Error::InvalidEncoding);
+        }
+        let lowercase = SecretKey::new(identity.to_ascii_lowercase().into_bytes());
+        let lowercase = str::from_utf8(&lowercase).unwrap();
+        let mut secret_key = SecretKey::new([0; 32]);
+        bech32_decode(IDENTITY_HRP, lowercase, &mut *secret_key)?;
+        Ok(Identity(secret_key))
+    }
+
+    /// Outputs the `AGE-SECRET-KEY-1...` encoding of the identity.
+    pub fn encode(&self) -> String {
+        bech32_encode(IDENTITY_HRP, &*self.0).to_ascii_uppercase()
+    }
+
+    /// Outputs the recipient that files are encrypted to for this identity.
+    pub fn recipient(&self) -> Recipient {
+        Recipient(curve25519::gen_pk(&*self.0).expect("identity is not 32 bytes"))
+    }
+}
+
+/// Encrypts a file into a writer.
+pub struct Encryptor<W: Write> {
+    output: W,
+    stream: stream::Encryptor<ChaCha20Poly1305>,
+    buffer: Vec<u8>,
+}
+
+impl<W: Write> Encryptor<W> {
+    /// Starts encrypting a file to `recipients`, writing the header into `output`. The file key,
+    /// the ephemeral keys and the payload nonce are drawn from `rng`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `recipients` is empty.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::InvalidKey`, wrapped in an `io::Error`, if a recipient is a low-order
+    /// point. Also fails with the errors of `output`.
+    pub fn new(output: W, recipients: &[Recipient], rng: &dyn SecureRandom) -> io::Result<Self> {
+        assert!(!recipients.is_empty());
+        let file_key = &mut SecretKey::new([0; FILE_KEY_LEN]);
+        rng.fill(&mut **file_key);
+        let mut stanzas = Vec::new();
+        for recipient in recipients {
+            let mut ephemeral_key = SecretKey::new([0; 32]);
+            rng.fill(&mut *ephemeral_key);
+            let share = curve25519::gen_pk(&*ephemeral_key)?;
+            let shared_secret = curve25519::x25519(&*ephemeral_key, &recipient.0)?;
+            let key = x25519_wrap_key(shared_secret, &share, &recipient.0);
+            stanzas.push(Stanza {
+                tag: X25519.into(),
+                args: vec![base64::STANDARD_NO_PAD.encode(&share)],
+                body: wrap_file_key(&key, file_key)?,
+            });
+        }
+        Self::start(output, file_key, &stanzas, rng)
+    }
+
+    /// Starts encrypting a file with a passphrase, stretched with scrypt with a work factor of
+    /// `2^log_n`, writing the header into `output`. The file key, the salt and the payload nonce
+    /// are drawn from `rng`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `log_n` is 0 or more than 63.
+    ///
+    /// # Errors
+    ///
+    /// Fails with the errors of `output`.
+    pub fn with_passphrase(
+        output: W,
+        passphrase: &[u8],
+        log_n: u8,
+        rng: &dyn SecureRandom,
+    ) -> io::Result<Self> {
+        let file_key = &mut SecretKey::new([0; FILE_KEY_LEN]);
+        rng.fill(&mut **file_key);
+        let salt = &mut [0; SALT_LEN];
+        rng.fill(salt);
+        let key = scrypt_wrap_key(passphrase, salt, log_n);
+        let stanza = Stanza {
+            tag: SCRYPT.into(),
+            args: vec![base64::STANDARD_NO_PAD.encode(salt), log_n.to_string()],
+            body: wrap_file_key(&key, file_key)?,
+        };
+        Self::start(output, file_key, &[stanza], rng)
+    }
+
+    fn start(
+        mut output: W,
+        file_key: &[u8; FILE_KEY_LEN],
+        stanzas: &[Stanza],
+        rng: &dyn SecureRandom,
+    ) -> io::Result<Self> {
+        let mut header = VERSION_LINE.to_vec();
+        header.push(b'\n');
+        for stanza in stanzas {
+            stanza.encode(&mut header);
+        }
+        header.extend_from_slice(b"---");
+        let mac = header_mac(file_key, &header);
+        header.push(b' ');
+        header.extend_from_slice(base64::STANDARD_NO_PAD.encode(&mac).as_bytes());
+        header.push(b'\n');
+        let nonce = &mut [0; NONCE_LEN];
+        rng.fill(nonce);
+        header.extend_from_slice(nonce);
+        output.write_all(&header)?;
+        Ok(Self {
+            output,
+            stream: payload_stream(file_key, nonce, stream::Encryptor::new)?,
+            buffer: Vec::with_capacity(CHUNK_LEN + TAG_LEN),
+        })
+    }
+
+    /// Encrypts and writes the last chunk, outputting the writer.
+    ///
+    /// # Errors
+    ///
+    /// Fails with the errors of the writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        let len = self.buffer.len();
+        self.buffer.resize(len + TAG_LEN, 0);
+        self.stream.encrypt_last(b"", &mut self.buffer)?;
+        self.output.write_all(&self.buffer)?;
+        self.output.flush()?;
+        Ok(self.output)
+    }
+}
+
+impl<W: Write> Write for Encryptor<W> {
+    /// Buffers plaintext, encrypting and writing a full chunk once more plaintext follows it.
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        // A full chunk is only written once it's known not to be the last one.
+        if self.buffer.len() == CHUNK_LEN {
+            self.buffer.resize(CHUNK_LEN + TAG_LEN, 0);
+            self.stream.encrypt_next(b"", &mut self.buffer)?;
+            self.output.write_all(&self.buffer)?;
+            self.buffer.clear();
+        }
+        let len = buf.len().min(CHUNK_LEN - self.buffer.len());
+        self.buffer.extend_from_slice(&buf[..len]);
+        Ok(len)
+    }
+
+    /// Flushes the writer, without writing a partial chunk.
+    fn flush(&mut self) -> io::Result<()> {
+        self.output.flush()
+    }
+}
+
+/// Decrypts a file from a reader.
+pub struct Decryptor<R> {
+    input: BufReader<R>,
+    stream: Option<stream::Decryptor<ChaCha20Poly1305>>,
+    buffer: Vec<u8>,
+    offset: usize,
+    first: bool,
+    eof: bool,
+}
+
+impl<R: Read> Decryptor<R> {
+    /// Reads the header of a file from `input`, unwrapping the file key with one of
+    /// `identities`.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::InvalidEncoding` if the header is malformed or is for a passphrase,
+    /// or with `Error::DecryptionFailed` if none of `identities` is a recipient or the header was
+    /// modified, all wrapped in an `io::Error`. Also fails with the errors of `input`.
+    pub fn new(input: R, identities: &[Identity]) -> io::Result<Self> {
+        Self::start(input, |stanzas| {
+            for stanza in stanzas {
+                if stanza.tag == SCRYPT {
+                    return Err(Error::InvalidEncoding.into());
+                }
+            }
+            for stanza in stanzas.iter().filter(|stanza| stanza.tag == X25519) {
+                let share = &mut [0; 32];
+                if stanza.args.len() != 1
+                    || decode_exact(&stanza.args[0], share).is_err()
+                    || stanza.body.len() != WRAPPED_KEY_LEN
+                {
+                    return Err(Error::InvalidEncoding.into());
+                }
+                for identity in identities {
+                    let recipient = identity.recipient();
+                    let shared_secret = curve25519::x25519(&*identity.0, share)
+                        .map_err(|_| Error::InvalidEncoding)?;
+                    let key = x25519_wrap_key(shared_secret, share, &recipient.0);
+                    if let Some(file_key) = unwrap_file_key(&key, &stanza.body) {
+                        return Ok(file_key);
+                    }
+                }
+            }
+            Err(Error::DecryptionFailed.into())
+        })
+    }
+
+    /// Reads the header of a file encrypted with a passphrase from `input`, and unwraps the file
+    /// key with `passphrase` if the scrypt work factor is at most `2^max_log_n`.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::InvalidEncoding` if the header is malformed or is not for a passphrase,
+    /// or with `Error::DecryptionFailed` if the passphrase is wrong or the header was modified,
+    /// all wrapped in an `io::Error`, or with `io::ErrorKind::Unsupported` if the work factor is
+    /// too high. Also fails with the errors of `input`.
+    pub fn with_passphrase(input: R, passphrase: &[u8], max_log_n: u8) -> io::Result<Self> {
+        Self::start(input, |stanzas| {
+            let stanza = match stanzas {
+                [stanza] if stanza.tag == SCRYPT && stanza.args.len() == 2 => stanza,
+                _ => return Err(Error::InvalidEncoding.into()),
+            };
+            let salt = &mut [0; SALT_LEN];
+            decode_exact(&stanza.args[0], salt)?;
+            let log_n = &stanza.args[1];
+            if log_n.starts_with('0') || stanza.body.len() != WRAPPED_KEY_LEN {
+                return Err(Error::InvalidEncoding.into());
+            }
+            let log_n = log_n
+                .parse::<u8>()
+                .ok()
+                .filter(|&log_n| 0 < log_n && log_n < 64)
+                .ok_or(Error::InvalidEncoding)?;
+            if log_n > max_log_n {
+                return Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    "the scrypt work factor is too high",
+                ));
+            }
+            let key = scrypt_wrap_key(passphrase, salt, log_n);
+            unwrap_file_key(&key, &stanza.body).ok_or_else(|| Error::DecryptionFailed.into())
+        })
+    }
+
+    fn start(
+        input: R,
+        unwrap: impl FnOnce(&[Stanza]) -> io::Result<SecretKey<[u8; FILE_KEY_LEN]>>,
+    ) -> io::Result<Self> {
+        let mut input = BufReader::new(input);
+        let (header, stanzas, mac) = read_header(&mut input)?;
+        let file_key = unwrap(&stanzas)?;
+        if !ct::constant_time_eq(&header_mac(&file_key, &header), &mac) {
+            return Err(Error::DecryptionFailed.into());
+        }
+        let nonce = &mut [0; NONCE_LEN];
+        input.read_exact(nonce)?;
+        Ok(Self {
+            input,
+            stream: Some(payload_stream(&file_key, nonce, stream::Decryptor::new)?),
+            buffer: Vec::with_capacity(CHUNK_LEN + TAG_LEN),
+            offset: 0,
+            first: true,
+            eof: false,
+        })
+    }
+
+    /// Reads, verifies and decrypts the next chunk into the buffer.
+    fn read_chunk(&mut self) -> io::Result<()> {
+        let stream = match self.stream.as_mut() {
+            Some(stream) => stream,
+            None => return Err(Error::DecryptionFailed.into()),
+        };
+        self.buffer.resize(CHUNK_LEN + TAG_LEN, 0);
+        let mut len = 0;
+        while len < self.buffer.len() {
+            match self.input.read(&mut self.buffer[len..]) {
+                Ok(0) => break,
+                Ok(n) => len += n,
+                Err(error) if error.kind() == io::ErrorKind::Interrupted => {}
+                Err(error) => return Err(error),
+            }
+        }
+        let last = len < self.buffer.len() || self.input.fill_buf()?.is_empty();
+        let plaintext_len = if last {
+            let stream = self.stream.take().unwrap();
+            let plaintext = stream
+                .decrypt_last(b"", &mut self.buffer[..len])
+                .map_err(|_| Error::DecryptionFailed)?;
+            // Only an empty file may end with an empty chunk.
+            if plaintext.is_empty() && !self.first {
+                return Err(Error::InvalidEncoding.into());
+            }
+            self.eof = true;
+            plaintext.len()
+        } else {
+            stream
+                .decrypt_next(b"", &mut self.buffer)
+                .map_err(|_| Error::DecryptionFailed)?
+                .len()
+        };
+        self.buffer.truncate(plaintext_len);
+        self.offset = 0;
+        self.first = false;
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for Decryptor<R> {
+    /// Reads plaintext, outputting 0 once the last chunk has been read.
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.offset == self.buffer.len() {
+            if self.eof {
+                return Ok(0);
+            }
+            // After an error, every later read fails too.
+            if let Err(error) = self.read_chunk() {
+                self.stream = None;
+                self.buffer.clear();
+                self.offset = 0;
+                return Err(error);
+            }
+        }
+        let len = buf.len().min(self.buffer.len() - self.offset);
+        buf[..len].copy_from_slice(&self.buffer[self.offset..self.offset + len]);
+        self.offset += len;
+        Ok(len)
+    }
+}
+
+/// A recipient stanza in the header: a tag, its arguments, and a body.
+struct Stanza {
+    tag: String,
+    args: Vec<String>,
+    body: Vec<u8>,
+}
+
+impl Stanza {
+    fn encode(&self, output: &mut Vec<u8>) {
+        output.extend_from_slice(b"-> ");
+        output.extend_from_slice(self.tag.as_bytes());
+        for arg in &self.args {
+            output.push(b' ');
+            output.extend_from_slice(arg.as_bytes());
+        }
+        output.push(b'\n');
+        let body = base64::STANDARD_NO_PAD.encode(&self.body);
+        // The last line is shorter than a full line, even if it has to be empty.
+        for line in body.as_bytes().chunks(BODY_LINE_LEN) {
+            output.extend_from_slice(line);
+            output.push(b'\n');
+        }
+        if body.len().is_multiple_of(BODY_LINE_LEN) {
+            output.push(b'\n');
+        }
+    }
+}
+
+/// Reads the header, outputting the part authenticated by the MAC, the stanzas and the MAC.
+fn read_header(input: &mut impl BufRead) -> io::Result<(Vec<u8>, Vec<Stanza>, [u8; 32])> {
+    let mut header = Vec::new();
+    if read_line(input, &mut header)? != VERSION_LINE {
+        return Err(Error::InvalidEncoding.into());
+    }
+    let mut stanzas = Vec::new();
+    loop {
+        let start = header.len();
+        let line = read_line(input, &mut header)?;
+        if let Some(mac) = line.strip_prefix(b"--- ") {
+            let mac_str = str::from_utf8(mac).map_err(|_| Error::InvalidEncoding)?;
+            let mut mac = [0; 32];
+            decode_exact(mac_str, &mut mac)?;
+            if stanzas.is_empty() {
+                return Err(Error::InvalidEncoding.into());
+            }
+            // The MAC covers the header up to and including the "---".
+            header.truncate(start + 3);
+            return Ok((header, stanzas, mac));
+        }
+        let line = line.strip_prefix(b"-> ").ok_or(Error::InvalidEncoding)?;
+        let line = str::from_utf8(line).map_err(|_| Error::InvalidEncoding)?;
+        let mut args = line.split(' ').map(String::from);
+        let tag = args.next().unwrap();
+        let args: Vec<_> = args.collect();
+        if tag.is_empty() || args.iter().any(|arg| arg.is_empty()) {
+            return Err(Error::InvalidEncoding.into());
+        }
+        let mut body = Vec::new();
+        loop {
+            let line = read_line(input, &mut header)?;
+            let line = str::from_utf8(line).map_err(|_| Error::InvalidEncoding)?;
+            if line.len() > BODY_LINE_LEN {
+                return Err(Error::InvalidEncoding.into());
+            }
+            let decoded = base64::STANDARD_NO_PAD
+                .decode(line)
+                .map_err(|_| Error::InvalidEncoding)?;
+            body.extend_from_slice(&decoded);
+            if line.len() < BODY_LINE_LEN {
+                break;
+            }
+        }
+        stanzas.push(Stanza { tag, args, body });
+    }
+}
+
+/// Reads a line into the end of `header`, outputting it without the line feed.
+fn read_line<'a>(input: &mut impl BufRead, header: &'a mut Vec<u8>) -> io::Result<&'a [u8]> {
+    let start = header.len();
+    input.take(MAX_LINE_LEN as u64).read_until(b'\n', header)?;
+    if header.last() != Some(&b'\n') {
+        return Err(Error::InvalidEncoding.into());
+    }
+    let line = &header[start..header.len() - 1];
+    // Only printable ASCII characters are allowed.
+    if !line.iter().all(|&c| (0x20..0x7f).contains(&c)) {
+        return Err(Error::InvalidEncoding.into());
+    }
+    Ok(line)
+}
+
+/// Decodes unpadded base64 that must decode to exactly `output.len()` bytes.
+fn decode_exact(encoded: &str, output: &mut [u8]) -> Result<(), Error> {
+    match base64::STANDARD_NO_PAD.decoded_len(encoded) {
+        Ok(len) if len == output.len() => {
+            base64::STANDARD_NO_PAD.decode_to_slice(encoded, output)?;
+            Ok(())
+        }
+        _ => Err(Error::InvalidEncoding),
+    }
+}
+
+fn x25519_wrap_key(
+    shared_secret: [u8; 32],
+    share: &[u8; 32],
+    recipient: &[u8; 32],
+) -> SecretKey<[u8; 32]> {
+    let shared_secret = SecretKey::new(shared_secret);
+    let salt = &mut [0; 64];
+    salt[..32].copy_from_slice(share);
+    salt[32..].copy_from_slice(recipient);
+    let mut key = SecretKey::new([0; 32]);
+    Hkdf::<Sha256>::extract(salt, &*shared_secret)
+        .expand(X25519_LABEL, &mut *key)
+        .expect("output too long");
+    key
+}
+
+fn scrypt_wrap_key(passphrase: &[u8], salt: &[u8; SALT_LEN], log_n: u8) -> SecretKey<[u8; 32]> {
+    let mut scrypt_salt = SCRYPT_LABEL.to_vec();
+    scrypt_salt.extend_from_slice(salt);
+    let params = Params { log_n, r: 8, p: 1 };
+    let mut key = SecretKey::new([0; 32]);
+    scrypt::derive(passphrase, &scrypt_salt, params, &mut *key);
+    key
+}
+
+fn wrap_file_key(key: &[u8; 32], file_key: &[u8; FILE_KEY_LEN]) -> Result<Vec<u8>, Error> {
+    let mut body = file_key.to_vec();
+    body.resize(WRAPPED_KEY_LEN, 0);
+    ChaCha20Poly1305::new(key)?.seal_in_place(&[0; 12], b"", &mut body)?;
+    Ok(body)
+}
+
+fn unwrap_file_key(key: &[u8; 32], body: &[u8]) -> Option<SecretKey<[u8; FILE_KEY_LEN]>> {
+    if body.len() != WRAPPED_KEY_LEN {
+        return None;
+    }
+    let mut buffer = SecretKey::new([0; WRAPPED_KEY_LEN]);
+    buffer.copy_from_slice(body);
+    let file_key = ChaCha20Poly1305::new(key)
+        .ok()?
+        .open_in_place(&[0; 12], b"", &mut *buffer)
+        .ok()?;
+    let mut output = SecretKey::new([0; FILE_KEY_LEN]);
+    output.copy_from_slice(file_key);
+    Some(output)
+}
+
+fn header_mac(file_key: &[u8; FILE_KEY_LEN], header: &[u8]) -> [u8; 32] {
+    let mut key = SecretKey::new([0; 32]);
+    Hkdf::<Sha256>::extract(b"", file_key)
+        .expand(b"header", &mut *key)
+        .expect("output too long");
+    let mut hmac = Hmac::<Sha256>::new(&*key);
+    hmac.update(header);
+    let mut mac = [0; 32];
+    mac.copy_from_slice(&hmac.tag());
+    mac
+}
+
+/// Initializes the STREAM encryptor or decryptor for the payload. The nonces of age's STREAM
+/// are an 11-byte big-endian counter and a last-chunk flag, which is STREAM with a zero prefix.
+fn payload_stream<T>(
+    file_key: &[u8; FILE_KEY_LEN],
+    nonce: &[u8; NONCE_LEN],
+    new: impl FnOnce(&[u8], &[u8]) -> Result<T, Error>,
+) -> Result<T, Error> {
+    let mut key = SecretKey::new([0; 32]);
+    Hkdf::<Sha256>::extract(nonce, file_key).expand(b"payload", &mut *key)?;
+    new(&*key, &[0; 7])
+}
+
+const BECH32_CHARSET: &[u8; 32] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+
+fn bech32_polymod(values: impl Iterator<Item = u8>) -> u32 {
+    const GENERATOR: [u32; 5] = [0x3b6a57b2, 0x26508e6d, 0x1ea119fa, 0x3d4233dd, 0x2a1462b3];
+    let mut checksum = 1;
+    for value in values {
+        let top = checksum >> 25;
+        checksum = (checksum & 0x1ffffff) << 5 ^ u32::from(value);
+        for (i, generator) in GENERATOR.iter().enumerate() {
+            if (top >> i) & 1 == 1 {
+                checksum ^= generator;
+            }
+        }
+    }
+    checksum
+}
+
+fn bech32_hrp_values(hrp: &str) -> impl Iterator<Item = u8> + '_ {
+    let hrp = hrp.bytes();
+    hrp.clone()
+        .map(|c| c >> 5)
+        .chain(Some(0))
+        .chain(hrp.map(|c| c & 31))
+}
+
+/// Encodes `data` as lowercase Bech32 (BIP 173) with a human-readable part, without the usual
+/// length limit.
+fn bech32_encode(hrp: &str, data: &[u8]) -> String {
+    let mut values = Vec::with_capacity((data.len() * 8).div_ceil(5));
+    let mut acc = 0u32;
+    let mut bits = 0;
+    for &byte in data {
+        acc = acc << 8 | u32::from(byte);
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            values.push((acc >> bits) as u8 & 31);
+        }
+    }
+    if bits > 0 {
+        values.push((acc << (5 - bits)) as u8 & 31);
+    }
+    let checksum = bech32_polymod(
+        bech32_hrp_values(hrp)
+            .chain(values.iter().copied())
+            .chain([0; 6].iter().copied()),
+    ) ^ 1;
+    let mut output = String::with_capacity(hrp.len() + 1 + values.len() + 6);
+    output.push_str(hrp);
+    output.push('1');
+    for i in 0..6 {
+        values.push((checksum >> (5 * (5 - i))) as u8 & 31);
+    }
+    for value in values {
+        output.push(BECH32_CHARSET[value as usize] as char);
+    }
+    output
+}
+
+/// Decodes lowercase Bech32 with the human-readable part `hrp` into exactly `output`.
+fn bech32_decode(hrp: &str, input: &str, output: &mut [u8]) -> Result<(), Error> {
+    let data = input
+        .strip_prefix(hrp)
+        .and_then(|rest| rest.strip_prefix('1'))
+        .ok_or(Error::InvalidEncoding)?;
+    let mut values = SecretKey::new(Vec::with_capacity(data.len()));
+    for c in data.bytes() {
+        let value = BECH32_CHARSET
+            .iter()
+            .position(|&d| d == c)
+            .ok_or(Error::InvalidEncoding)?;
+        values.push(value as u8);
+    }
+    if values.len() < 6 || bech32_polymod(bech32_hrp_values(hrp).chain(values.iter().copied())) != 1
+    {
+        return Err(Error::InvalidEncoding);
+    }
+    let values = &values[..values.len() - 6];
+    if values.len() * 5 / 8 != output.len() {
+        return Err(Error::InvalidEncoding);
+    }
+    let mut acc = 0u32;
+    let mut bits = 0;
+    let mut len = 0;
+    for &value in values {
+        acc = acc << 5 | u32::from(value);
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            output[len] = (acc >> bits) as u8;
+            len += 1;
+        }
+    }
+    // The padding must be fewer than 5 zero bits.
+    if bits >= 5 || acc & ((1 << bits) - 1) != 0 {
+        return Err(Error::InvalidEncoding);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sha2::sha256;
+    use crate::test_helpers::*;
+
+    const IDENTITY: &str =
+        "AGE-SECRET-KEY-1QQQSYQCYQ5RQWZQFPG9SCRGWPUGPZYSNZS23V9CCRYDPK8QARC0SWRYDWG";
+    const RECIPIENT: &str = "age13aqvttdk3ujkyjh9kg2w5an6dmy5mq5a84a4uxk3hfhnugfc9p0sy5p2wh";
+    const HEADER: &[u8] = b"age-encryption.org/v1\n\
+        -> X25519 EyxEK+AQ+9V+cmAzKKp25x/MwVA6riGTJ9FNnJmT9HI\n\
+        JiXweXCxerUDidHpXOwBtnPlyuD7avws7tBtW6WWYkk\n\
+        --- BmvBUkAaitiWLL1xbaOgKK1y55FIyJzdJShzK8WGDP8\n";
+    const SCRYPT_HEADER: &[u8] = b"age-encryption.org/v1\n\
+        -> scrypt QkJCQkJCQkJCQkJCQkJCQg 10\n\
+        XjL7SFbYOEno5WYuqU0QMX7/eDs+8QfKBFHGMCLuaag\n\
+        --- 0pifrTQ4BlAEP4iDRiuN6AdHT2zxwOkns2SDdp3NpRQ\n";
+    const PAYLOAD: &str = "a7acfc4cc07fef2975529ea890579b142178988966c31832a445ab4f583a";
+
+    fn identity() -> Identity {
+        let mut secret_key = [0; 32];
+        for (i, byte) in secret_key.iter_mut().enumerate() {
+            *byte = i as u8;
+        }
+        Identity::new(&secret_key)
+    }
+
+    fn fixed_rng(dest: &mut [u8]) {
+        dest.iter_mut().for_each(|byte| *byte = 0x42);
+    }
+
+    fn file(header: &[u8], payload: &str) -> Vec<u8> {
+        [header, &[0x42; NONCE_LEN], &h2b(payload)].concat()
+    }
+
+    fn encrypt(recipients: &[Recipient], plaintext: &[u8]) -> Vec<u8> {
+        let mut encryptor = Encryptor::new(Vec::new(), recipients, &fixed_rng).unwrap();
+        encryptor.write_all(plaintext).unwrap();
+        encryptor.finish().unwrap()
+    }
+
+    fn decrypt(file: &[u8], identities: &[Identity]) -> io::Result<Vec<u8>> {
+        let mut decryptor = Decryptor::new(file, identities)?;
+        let mut plaintext = Vec::new();
+        decryptor.read_to_end(&mut plaintext)?;
+        Ok(plaintext)
+    }
+
+    fn error(result: io::Result<impl Sized>) -> Error {
+        let error = result.err().unwrap();
+        assert_eq!(io::ErrorKind::InvalidData, error.kind());
+        *error.into_inner().unwrap().downcast::<Error>().unwrap()
+    }
+
+    // Generated with a Python implementation of the format, checked against the age test kit
+    #[test]
+    fn test_keys() {
+        let identity = identity();
+        assert_eq!(IDENTITY, identity.encode());
+        assert_eq!(RECIPIENT, identity.recipient().encode());
+        let parsed = Identity::parse(IDENTITY).unwrap();
+        assert_eq!(identity.recipient(), parsed.recipient());
+        assert_eq!(identity.recipient(), Recipient::parse(RECIPIENT).unwrap());
+
+        for invalid in &[
+            "",
+            "age1",
+            "age13aqvttdk3ujkyjh9kg2w5an6dmy5mq5a84a4uxk3hfhnugfc9p0sy5p2wg",
+            "AGE13AQVTTDK3UJKYJH9KG2W5AN6DMY5MQ5A84A4UXK3HFHNUGFC9P0SY5P2WH",
+            "age13aqvttdk3ujkyjh9kg2w5an6dmy5mq5a84a4uxk3hfhnugfc9p0sy5p2wh ",
+            "age1qqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqq",
+        ] {
+            assert_eq!(Err(Error::InvalidEncoding), Recipient::parse(invalid));
+        }
+        assert!(Identity::parse(&IDENTITY.to_ascii_lowercase()).is_err());
+        assert!(Identity::parse(RECIPIENT).is_err());
+    }
+
+    #[test]
+    fn test_encrypt() {
+        let recipient = identity().recipient();
+        let expected = file(HEADER, PAYLOAD);
+        assert_eq!(expected, encrypt(&[recipient], b"hello, world!\n"));
+        assert_eq!(
+            b"hello, world!\n",
+            &decrypt(&expected, &[identity()]).unwrap()[..]
+        );
+    }
+
+    #[test]
+    fn test_empty() {
+        let recipient = identity().recipient();
+        let expected = file(HEADER, "63652191621ea601126ab731b49a407e");
+        assert_eq!(expected, encrypt(&[recipient], b""));
+        assert!(decrypt(&expected, &[identity()]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_passphrase() {
+        let mut encryptor =
+            Encryptor::with_passphrase(Vec::new(), b"passphrase", 10, &fixed_rng).unwrap();
+        encryptor.write_all(b"hello, world!\n").unwrap();
+        let expected = file(SCRYPT_HEADER, PAYLOAD);
+        assert_eq!(expected, encryptor.finish().unwrap());
+
+        let mut decryptor = Decryptor::with_passphrase(&expected[..], b"passphrase", 10).unwrap();
+        let mut plaintext = Vec::new();
+        decryptor.read_to_end(&mut plaintext).unwrap();
+        assert_eq!(b"hello, world!\n", &plaintext[..]);
+
+        let result = Decryptor::with_passphrase(&expected[..], b"wrong", 10);
+        assert_eq!(Error::DecryptionFailed, error(result));
+        let result = Decryptor::with_passphrase(&expected[..], b"passphrase", 9);
+        assert_eq!(io::ErrorKind::Unsupported, result.err().unwrap().kind());
+        assert_eq!(
+            Error::InvalidEncoding,
+            error(decrypt(&expected, &[identity()]))
+        );
+        let x25519 = file(HEADER, PAYLOAD);
+        let result = Decryptor::with_passphrase(&x25519[..], b"passphrase", 10);
+        assert_eq!(Error::InvalidEncoding, error(result));
+    }
+
+    #[test]
+    fn test_multiple_chunks() {
+        let other = Identity::new(&[7; 32]);
+        let recipients = &[identity().recipient(), other.recipient()];
+        let plaintext: Vec<u8> = (0..2 * CHUNK_LEN).map(|i| (i % 251) as u8).collect();
+        let mut encryptor = Encryptor::new(Vec::new(), recipients, &fixed_rng).unwrap();
+        for part in plaintext.chunks(1000) {
+            encryptor.write_all(part).unwrap();
+        }
+        let encrypted = encryptor.finish().unwrap();
+        assert_eq!(
+            &h2b("caba6f264d91a4c5ea421da36ba61c9113d4ed974b50bd9765ec3dc0647cd115")[..],
+            &sha256(&encrypted)[..]
+        );
+        assert_eq!(plaintext, decrypt(&encrypted, &[other]).unwrap());
+        assert_eq!(plaintext, decrypt(&encrypted, &[identity()]).unwrap());
+
+        // Dropping the last chunk leaves a full chunk that wasn't encrypted as the last one.
+        let truncated = &encrypted[..encrypted.len() - CHUNK_LEN - TAG_LEN];
+        assert_eq!(
+            Error::DecryptionFailed,
+            error(decrypt(truncated, &[identity()]))
+        );
+        let mut decryptor = Decryptor::new(truncated, &[identity()]).unwrap();
+        let buf = &mut [0; 100];
+        assert!(decryptor.read(buf).is_err());
+        assert!(decryptor.read(buf).is_err());
+    }
+
+    #[test]
+    fn test_invalid() {
+        let valid = file(HEADER, PAYLOAD);
+        let result = decrypt(&valid, &[Identity::new(&[7; 32])]);
+        assert_eq!(Error::DecryptionFailed, error(result));
+
+        // The payload is authenticated.
+        for i in HEADER.len()..valid.len() {
+            let mut invalid = valid.clone();
+            invalid[i] ^= 1;
+            assert_eq!(
+                Error::DecryptionFailed,
+                error(decrypt(&invalid, &[identity()]))
+            );
+        }
+        let truncated = &valid[..valid.len() - 1];
+        assert_eq!(
+            Error::DecryptionFailed,
+            error(decrypt(truncated, &[identity()]))
+        );
+        let truncated = &valid[..HEADER.len() + NONCE_LEN];
+        assert_eq!(
+            Error::DecryptionFailed,
+            error(decrypt(truncated, &[identity()]))
+        );
+        let truncated = &valid[..HEADER.len() + 4];
+        let result = decrypt(truncated, &[identity()]);
+        assert_eq!(io::ErrorKind::UnexpectedEof, result.err().unwrap().kind());
+
+        // So is the header, including the stanza arguments.
+        let header = str::from_utf8(HEADER).unwrap();
+        let modified = header.replace("-> X25519", "-> X25519 extra");
+        let invalid = file(modified.as_bytes(), PAYLOAD);
+        assert_eq!(
+            Error::InvalidEncoding,
+            error(decrypt(&invalid, &[identity()]))
+        );
+        let modified = header.replace("--- ", "-> unknown\n\n--- ");
+        let invalid = file(modified.as_bytes(), PAYLOAD);
+        assert_eq!(
+            Error::DecryptionFailed,
+            error(decrypt(&invalid, &[identity()]))
+        );
+        let modified = header.replace("--- B", "--- C");
+        let invalid = file(modified.as_bytes(), PAYLOAD);
+        assert_eq!(
+            Error::DecryptionFailed,
+            error(decrypt(&invalid, &[identity()]))
+        );
+
+        for (from, to) in &[
+            ("age-encryption.org/v1", "age-encryption.org/v2"),
+            ("\n", "\r\n"),
+            ("-> ", "->  "),
+            ("Ykk\n", "Ykk=\n"),
+            ("Ykk\n", "Ykk\n\n"),
+            ("--- BmvBUkAaitiWLL1xbaOgKK1y55FIyJzdJShzK8WGDP8", "---"),
+        ] {
+            let modified = header.replacen(from, to, 1);
+            let invalid = file(modified.as_bytes(), PAYLOAD);
+            assert_eq!(
+                Error::InvalidEncoding,
+                error(decrypt(&invalid, &[identity()]))
+            );
+        }
+    }
+}
@@ -0,0 +1,622 @@
+//! Module for the Argon2 memory-hard password hashing function (RFC 9106).
+//!
+//! Argon2id is the recommended variant for password hashing. Argon2d and Argon2i are provided for
+//! completeness.
+//!
+//! # Examples
+//!
+//! ```
+//! use crypto_pure::argon2::{Argon2, Block, Params, Variant};
+//! # let password = b"correct horse battery staple";
+//! # let salt = b"A random value stored alongside.";
+//! let params = Params {
+//!     memory_cost: 64,
+//!     time_cost: 2,
+//!     parallelism: 1,
+//! };
+//! let argon2 = Argon2::new(Variant::Argon2id, params);
+//! let memory = &mut [Block::default(); 64];
+//! let key = &mut [0; 32];
+//! argon2.hash_with_memory(password, salt, memory, key);
+//! ```
+use crate::blake2b::Blake2b;
+#[cfg(feature = "alloc")]
+use alloc::{string::String, vec, vec::Vec};
+use byteorder::{ByteOrder as _, LittleEndian};
+#[cfg(feature = "alloc")]
+use core::fmt;
+
+const VERSION: u32 = 0x13;
+const SYNC_POINTS: usize = 4;
+const ADDRESSES_PER_BLOCK: usize = 128;
+
+/// The Argon2 variant, which determines how reference blocks are chosen.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Variant {
+    /// Data-dependent addressing, for uses without side-channel concerns.
+    Argon2d = 0,
+    /// Data-independent addressing.
+    Argon2i = 1,
+    /// Data-independent addressing for the first half of the first pass, then data-dependent.
+    Argon2id = 2,
+}
+
+/// The cost parameters for Argon2.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Params {
+    /// The amount of memory to use in KiB.
+    pub memory_cost: u32,
+    /// The number of passes over memory.
+    pub time_cost: u32,
+    /// The number of lanes.
+    pub parallelism: u32,
+}
+
+/// A 1 KiB block of Argon2 memory.
+#[derive(Clone, Copy)]
+pub struct Block([u64; 128]);
+
+impl Default for Block {
+    fn default() -> Self {
+        Block([0; 128])
+    }
+}
+
+/// An Argon2 instance with fixed parameters and an optional secret and associated data.
+pub struct Argon2<'a> {
+    variant: Variant,
+    params: Params,
+    secret: &'a [u8],
+    associated_data: &'a [u8],
+}
+
+impl<'a> Argon2<'a> {
+    /// Initializes an Argon2 instance given a variant and cost parameters.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `params.parallelism` is 0 or more than 2^24 - 1, `params.memory_cost` is less than
+    /// 8 times `params.parallelism`, or `params.time_cost` is 0.
+    pub fn new(variant: Variant, params: Params) -> Self {
+        assert!(0 < params.parallelism && params.parallelism < 1 << 24);
+        assert!(params.memory_cost / 8 >= params.parallelism);
+        assert!(params.time_cost > 0);
+        Self {
+            variant,
+            params,
+            secret: &[],
+            associated_data: &[],
+        }
+    }
+
+    /// Adds a secret value (a "pepper") to be mixed into the hash.
+    pub fn with_secret(mut self, secret: &'a [u8]) -> Self {
+        self.secret = secret;
+        self
+    }
+
+    /// Adds associated data to be mixed into the hash.
+    pub fn with_associated_data(mut self, associated_data: &'a [u8]) -> Self {
+        self.associated_data = associated_data;
+        self
+    }
+
+    /// Outputs the number of blocks of memory that hashing uses.
+    pub fn memory_blocks(&self) -> usize {
+        let lanes = self.params.parallelism as usize;
+        let blocks_per_group = SYNC_POINTS * lanes;
+        blocks_per_group * (self.params.memory_cost as usize / blocks_per_group)
+    }
+
+    /// Hashes a password with a salt into `output`, allocating the memory needed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `salt.len()` is less than 8 or `output.len()` is less than 4.
+    #[cfg(feature = "alloc")]
+    pub fn hash(&self, password: &[u8], salt: &[u8], output: &mut [u8]) {
+        let memory = &mut vec![Block::default(); self.memory_blocks()];
+        self.hash_with_memory(password, salt, memory, output);
+    }
+
+    /// Hashes a password with a salt into `output`, using caller-provided memory.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `salt.len()` is less than 8, `output.len()` is less than 4, or `memory.len()` is
+    /// less than `self.memory_blocks()`.
+    pub fn hash_with_memory(
+        &self,
+        password: &[u8],
+        salt: &[u8],
+        memory: &mut [Block],
+        output: &mut [u8],
+    ) {
+        assert!(salt.len() >= 8);
+        assert!(output.len() >= 4);
+        let memory = &mut memory[..self.memory_blocks()];
+        let h0 = &self.initial_hash(password, salt, output.len());
+
+        let lane_len = memory.len() / self.params.parallelism as usize;
+        let bytes = &mut [0; 1024];
+        for (lane, lane_blocks) in memory.chunks_mut(lane_len).enumerate() {
+            for (i, block) in lane_blocks.iter_mut().take(2).enumerate() {
+                hprime(
+                    &[h0, &(i as u32).to_le_bytes(), &(lane as u32).to_le_bytes()],
+                    bytes,
+                );
+                LittleEndian::read_u64_into(bytes, &mut block.0);
+            }
+        }
+
+        for pass in 0..self.params.time_cost {
+            for slice in 0..SYNC_POINTS {
+                for lane in 0..self.params.parallelism as usize {
+                    self.fill_segment(memory, pass, slice, lane);
+                }
+            }
+        }
+
+        let mut last = Block::default();
+        for lane_blocks in memory.chunks(lane_len) {
+            last.xor(&lane_blocks[lane_len - 1]);
+        }
+        LittleEndian::write_u64_into(&last.0, bytes);
+        hprime(&[bytes], output);
+    }
+
+    fn initial_hash(&self, password: &[u8], salt: &[u8], tag_len: usize) -> [u8; 64] {
+        let mut blake = Blake2b::new(64);
+        for value in &[
+            self.params.parallelism,
+            tag_len as u32,
+            self.params.memory_cost,
+            self.params.time_cost,
+            VERSION,
+            self.variant as u32,
+        ] {
+            blake.update(&value.to_le_bytes());
+        }
+        for input in &[password, salt, self.secret, self.associated_data] {
+            blake.update(&(input.len() as u32).to_le_bytes());
+            blake.update(input);
+        }
+        let mut h0 = [0; 64];
+        blake.write_digest(&mut h0);
+        h0
+    }
+
+    fn fill_segment(&self, memory: &mut [Block], pass: u32, slice: usize, lane: usize) {
+        let lanes = self.params.parallelism as usize;
+        let lane_len = memory.len() / lanes;
+        let segment_len = lane_len / SYNC_POINTS;
+        let data_independent = match self.variant {
+            Variant::Argon2d => false,
+            Variant::Argon2i => true,
+            Variant::Argon2id => pass == 0 && slice < SYNC_POINTS / 2,
+        };
+
+        let mut input_block = Block::default();
+        let mut address_block = Block::default();
+        if data_independent {
+            input_block.0[..6].copy_from_slice(&[
+                u64::from(pass),
+                lane as u64,
+                slice as u64,
+                memory.len() as u64,
+                u64::from(self.params.time_cost),
+                self.variant as u64,
+            ]);
+        }
+
+        let first_segment = pass == 0 && slice == 0;
+        let start = if first_segment { 2 } else { 0 };
+        for index in start..segment_len {
+            let column = slice * segment_len + index;
+            let current = lane * lane_len + column;
+            let previous = if column == 0 {
+                current + lane_len - 1
+            } else {
+                current - 1
+            };
+
+            let pseudo_random = if data_independent {
+                if index % ADDRESSES_PER_BLOCK == 0 || index == start {
+                    input_block.0[6] += 1;
+                    address_block = compress(&Block::default(), &input_block);
+                    address_block = compress(&Block::default(), &address_block);
+                }
+                address_block.0[index % ADDRESSES_PER_BLOCK]
+            } else {
+                memory[previous].0[0]
+            };
+
+            let ref_lane = if first_segment {
+                lane
+            } else {
+                (pseudo_random >> 32) as usize % lanes
+            };
+            let ref_column = reference_column(
+                pass,
+                slice,
+                index,
+                ref_lane == lane,
+                pseudo_random & 0xffff_ffff,
+                segment_len,
+            );
+            let block = compress(&memory[previous], &memory[ref_lane * lane_len + ref_column]);
+            if pass == 0 {
+                memory[current] = block;
+            } else {
+                memory[current].xor(&block);
+            }
+        }
+    }
+}
+
+/// Maps the 32-bit `j1` onto a block in the reference area for the current position.
+fn reference_column(
+    pass: u32,
+    slice: usize,
+    index: usize,
+    same_lane: bool,
+    j1: u64,
+    segment_len: usize,
+) -> usize {
+    let lane_len = SYNC_POINTS * segment_len;
+    let finished = if pass == 0 {
+        slice * segment_len
+    } else {
+        lane_len - segment_len
+    };
+    let area_size = if same_lane {
+        finished + index - 1
+    } else if index == 0 {
+        finished - 1
+    } else {
+        finished
+    } as u64;
+    let x = (j1 * j1) >> 32;
+    let relative_position = area_size - 1 - ((area_size * x) >> 32);
+    let start = if pass == 0 || slice == SYNC_POINTS - 1 {
+        0
+    } else {
+        (slice + 1) * segment_len
+    };
+    (start + relative_position as usize) % lane_len
+}
+
+impl Block {
+    fn xor(&mut self, other: &Block) {
+        for (x, y) in self.0.iter_mut().zip(other.0.iter()) {
+            *x ^= y;
+        }
+    }
+}
+
+/// The compression function G, built from the BLAKE2b round function.
+fn compress(x: &Block, y: &Block) -> Block {
+    let mut r = *x;
+    r.xor(y);
+    let mut q = r;
+    for row in q.0.chunks_mut(16) {
+        let v = &mut [0; 16];
+        v.copy_from_slice(row);
+        permute(v);
+        row.copy_from_slice(v);
+    }
+    for column in 0..8 {
+        let v = &mut [0; 16];
+        for (i, pair) in v.chunks_mut(2).enumerate() {
+            pair.copy_from_slice(&q.0[16 * i + 2 * column..16 * i + 2 * column + 2]);
+        }
+        permute(v);
+        for (i, pair) in v.chunks(2).enumerate() {
+            q.0[16 * i + 2 * column..16 * i + 2 * column + 2].copy_from_slice(pair);
+        }
+    }
+    q.xor(&r);
+    q
+}
+
+fn permute(v: &mut [u64; 16]) {
+    mix(v, 0, 4, 8, 12);
+    mix(v, 1, 5, 9, 13);
+    mix(v, 2, 6, 10, 14);
+    mix(v, 3, 7, 11, 15);
+    mix(v, 0, 5, 10, 15);
+    mix(v, 1, 6, 11, 12);
+    mix(v, 2, 7, 8, 13);
+    mix(v, 3, 4, 9, 14);
+}
+
+fn mix(v: &mut [u64; 16], a: usize, b: usize, c: usize, d: usize) {
+    v[a] = multiply_add(v[a], v[b]);
+    v[d] = (v[d] ^ v[a]).rotate_right(32);
+    v[c] = multiply_add(v[c], v[d]);
+    v[b] = (v[b] ^ v[c]).rotate_right(24);
+    v[a] = multiply_add(v[a], v[b]);
+    v[d] = (v[d] ^ v[a]).rotate_right(16);
+    v[c] = multiply_add(v[c], v[d]);
+    v[b] = (v[b] ^ v[c]).rotate_right(63);
+}
+
+fn multiply_add(x: u64, y: u64) -> u64 {
+    let product = (x & 0xffff_ffff) * (y & 0xffff_ffff);
+    x.wrapping_add(y).wrapping_add(product.wrapping_mul(2))
+}
+
+/// The variable-length hash function H', which extends BLAKE2b to outputs of any size.
+fn hprime(inputs: &[&[u8]], output: &mut [u8]) {
+    let len = output.len();
+    let mut blake = Blake2b::new(len.min(64));
+    blake.update(&(len as u32).to_le_bytes());
+    for input in inputs {
+        blake.update(input);
+    }
+    if len <= 64 {
+        blake.write_digest(output);
+        return;
+    }
+    let mut v = [0; 64];
+    blake.write_digest(&mut v);
+    output[..32].copy_from_slice(&v[..32]);
+    let r = len.div_ceil(32) - 2;
+    for i in 1..r {
+        let mut blake = Blake2b::new(64);
+        blake.update(&v);
+        blake.write_digest(&mut v);
+        output[32 * i..32 * (i + 1)].copy_from_slice(&v[..32]);
+    }
+    let mut blake = Blake2b::new(len - 32 * r);
+    blake.update(&v);
+    blake.write_digest(&mut output[32 * r..]);
+}
+
+/// An Argon2 password hash along with the parameters needed to verify it, which can be stored as
+/// a PHC string such as `$argon2id$v=19$m=65536,t=3,p=4$c29tZXNhbHQ$...`.
+#[cfg(feature = "alloc")]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PasswordHash {
+    pub variant: Variant,
+    pub params: Params,
+    pub salt: Vec<u8>,
+    pub hash: Vec<u8>,
+}
+
+#[cfg(feature = "alloc")]
+impl PasswordHash {
+    /// Hashes a password with Argon2id, producing a 32-byte hash.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the parameters are invalid or `salt.len()` is less than 8.
+    pub fn new(params: Params, password: &[u8], salt: &[u8]) -> Self {
+        let mut hash = vec![0; 32];
+        Argon2::new(Variant::Argon2id, params).hash(password, salt, &mut hash);
+        Self {
+            variant: Variant::Argon2id,
+            params,
+            salt: salt.to_vec(),
+            hash,
+        }
+    }
+
+    /// Parses a PHC string, returning `None` if it is malformed or uses an unsupported version.
+    pub fn parse(phc: &str) -> Option<Self> {
+        let mut fields = phc.split('$');
+        if !fields.next()?.is_empty() {
+            return None;
+        }
+        let variant = match fields.next()? {
+            "argon2d" => Variant::Argon2d,
+            "argon2i" => Variant::Argon2i,
+            "argon2id" => Variant::Argon2id,
+            _ => return None,
+        };
+        if fields.next()? != "v=19" {
+            return None;
+        }
+        let mut params = fields.next()?.split(',');
+        let mut param = |name: &str| -> Option<u32> {
+            let value = params.next()?.strip_prefix(name)?.strip_prefix('=')?;
+            if value.starts_with('+') || (value.starts_with('0') && value != "0") {
+                return None;
+            }
+            value.parse().ok()
+        };
+        let params = Params {
+            memory_cost: param("m")?,
+            time_cost: param("t")?,
+            parallelism: param("p")?,
+        };
+        let salt = base64_decode(fields.next()?)?;
+        let hash = base64_decode(fields.next()?)?;
+        let valid_params = 0 < params.parallelism
+            && params.parallelism < 1 << 24
+            && params.memory_cost / 8 >= params.parallelism
+            && params.time_cost > 0;
+        if fields.next().is_some() || !valid_params || salt.len() < 8 || hash.len() < 4 {
+            return None;
+        }
+        Some(Self {
+            variant,
+            params,
+            salt,
+            hash,
+        })
+    }
+
+    /// Checks whether `password` hashes to this hash, comparing in constant time.
+    pub fn verify(&self, password: &[u8]) -> bool {
+        let mut hash = vec![0; self.hash.len()];
+        Argon2::new(self.variant, self.params).hash(password, &self.salt, &mut hash);
+        crate::ct::constant_time_eq(&hash, &self.hash)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl fmt::Display for PasswordHash {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let variant = match self.variant {
+            Variant::Argon2d => "argon2d",
+            Variant::Argon2i => "argon2i",
+            Variant::Argon2id => "argon2id",
+        };
+        write!(
+            f,
+            "${}$v=19$m={},t={},p={}${}${}",
+            variant,
+            self.params.memory_cost,
+            self.params.time_cost,
+            self.params.parallelism,
+            base64_encode(&self.salt),
+            base64_encode(&self.hash),
+        )
+    }
+}
+
+#[cfg(feature = "alloc")]
+const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Encodes bytes as unpadded standard base64, as used in PHC strings.
+#[cfg(feature = "alloc")]
+fn base64_encode(input: &[u8]) -> String {
+    let mut output = Vec::with_capacity(input.len().div_ceil(3) * 4);
+    for chunk in input.chunks(3) {
+        let mut bits = 0u32;
+        for (i, &byte) in chunk.iter().enumerate() {
+            bits |= u32::from(byte) << (16 - 8 * i);
+        }
+        for i in 0..=chunk.len() {
+            output.push(BASE64_ALPHABET[(bits >> (18 - 6 * i)) as usize & 63]);
+        }
+    }
+    String::from_utf8(output).unwrap()
+}
+
+#[cfg(feature = "alloc")]
+fn base64_decode(input: &str) -> Option<Vec<u8>> {
+    let mut output = Vec::with_capacity(input.len() * 3 / 4);
+    for chunk in input.as_bytes().chunks(4) {
+        if chunk.len() == 1 {
+            return None;
+        }
+        let mut bits = 0u32;
+        for (i, &c) in chunk.iter().enumerate() {
+            let value = BASE64_ALPHABET.iter().position(|&x| x == c)? as u32;
+            bits |= value << (18 - 6 * i);
+        }
+        let len = chunk.len() - 1;
+        // Reject non-canonical encodings with nonzero trailing bits.
+        if bits & (0x00ff_ffff >> (8 * len)) != 0 {
+            return None;
+        }
+        for i in 0..len {
+            output.push((bits >> (16 - 8 * i)) as u8);
+        }
+    }
+    Some(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::*;
+    #[cfg(feature = "alloc")]
+    use std::string::ToString;
+
+    fn check(variant: Variant, expected: &str) {
+        let params = Params {
+            memory_cost: 32,
+            time_cost: 3,
+            parallelism: 4,
+        };
+        let argon2 = Argon2::new(variant, params)
+            .with_secret(&[3; 8])
+            .with_associated_data(&[4; 12]);
+        let memory = &mut [Block::default(); 32];
+        let actual = &mut [0; 32];
+        argon2.hash_with_memory(&[1; 32], &[2; 16], memory, actual);
+        assert_eq!(h2b(expected), actual.to_vec());
+    }
+
+    #[test]
+    fn test_rfc_9106() {
+        let expected = "512b391b6f1162975371d30919734294f868e3be3984f3c1a13a4db9fabe4acb";
+        check(Variant::Argon2d, expected);
+        let expected = "c814d9d1dc7f37aa13f0d77f2494bda1c8de6b016dd388d29952a4c4672b6ce8";
+        check(Variant::Argon2i, expected);
+        let expected = "0d640df58d78766c08c037a34a8b53c9d01ef0452d75b65eb52520e96b01e659";
+        check(Variant::Argon2id, expected);
+    }
+
+    #[test]
+    fn test_long_output() {
+        let params = Params {
+            memory_cost: 16,
+            time_cost: 1,
+            parallelism: 2,
+        };
+        let expected = h2b(
+            "7db589f070b5640761a4f652c4a63ca7c980618770a66177b8f1a049fe8c1e8c\
+             827a9b9ed069877e6edf59d015223e2e325742e99a024d2233941f738b82c988\
+             7ff8a5c633a3ef3c350d29b9967ee995cfa78384986a43b9e947fb947160fcd2\
+             a2aa2c79",
+        );
+        let actual = &mut [0; 100];
+        let memory = &mut [Block::default(); 16];
+        let argon2 = Argon2::new(Variant::Argon2id, params);
+        argon2.hash_with_memory(b"password", b"somesalt", memory, actual);
+        assert_eq!(expected, actual.to_vec());
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_password_hash() {
+        let phc =
+            "$argon2id$v=19$m=64,t=2,p=1$c29tZXNhbHQ$FqGkmHNGCd0BRW2kBt6fPZ2pPmyGwwChL8FGUhTOSSI";
+        let params = Params {
+            memory_cost: 64,
+            time_cost: 2,
+            parallelism: 1,
+        };
+        let hash = PasswordHash::new(params, b"password", b"somesalt");
+        assert_eq!(phc, hash.to_string());
+        assert_eq!(Some(hash), PasswordHash::parse(phc));
+
+        let hash = PasswordHash::parse(phc).unwrap();
+        assert!(hash.verify(b"password"));
+        assert!(!hash.verify(b"Password"));
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_parse_invalid() {
+        for phc in &[
+            "",
+            "$argon2id$v=19$m=64,t=2,p=1$c29tZXNhbHQ",
+            "$argon2x$v=19$m=64,t=2,p=1$c29tZXNhbHQ$FqGkmHNGCd0BRW2kBt6fPZ2pPmyGwwChL8FGUhTOSSI",
+            "$argon2id$v=16$m=64,t=2,p=1$c29tZXNhbHQ$FqGkmHNGCd0BRW2kBt6fPZ2pPmyGwwChL8FGUhTOSSI",
+            "$argon2id$v=19$t=2,m=64,p=1$c29tZXNhbHQ$FqGkmHNGCd0BRW2kBt6fPZ2pPmyGwwChL8FGUhTOSSI",
+            "$argon2id$v=19$m=064,t=2,p=1$c29tZXNhbHQ$FqGkmHNGCd0BRW2kBt6fPZ2pPmyGwwChL8FGUhTOSSI",
+            "$argon2id$v=19$m=4,t=2,p=1$c29tZXNhbHQ$FqGkmHNGCd0BRW2kBt6fPZ2pPmyGwwChL8FGUhTOSSI",
+            "$argon2id$v=19$m=64,t=2,p=1$c29tZXNhbHR$FqGkmHNGCd0BRW2kBt6fPZ2pPmyGwwChL8FGUhTOSSI",
+            "$argon2id$v=19$m=64,t=2,p=1$c29tZXNhbHQ=$FqGkmHNGCd0BRW2kBt6fPZ2pPmyGwwChL8FGUhTOSSI",
+            "$argon2id$v=19$m=64,t=2,p=1$c29tZXNhbHQ$FqGkmHNGCd0BRW2kBt6fPZ2pPmyGwwChL8FGUhTOSSI$",
+        ] {
+            assert_eq!(None, PasswordHash::parse(phc));
+        }
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_base64() {
+        for len in 0..10 {
+            let input: Vec<u8> = (0..len).map(|x| (37 * x) as u8).collect();
+            assert_eq!(Some(input.clone()), base64_decode(&base64_encode(&input)));
+        }
+        assert_eq!("c29tZXNhbHQ", base64_encode(b"somesalt"));
+    }
+}
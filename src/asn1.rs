@@ -0,0 +1,946 @@
+//! Module for reading and writing the subset of ASN.1 DER used by key and certificate formats.
+//!
+//! `Reader` parses without copying, outputting slices of its input, and rejects anything that is
+//! not valid DER, such as non-minimal lengths or integers, so each value has one encoding. Only
+//! tag numbers below 31 are supported, which covers the universal types and the context-specific
+//! tags used in practice. `Writer` outputs DER and needs the `alloc` feature.
+//!
+//! # Examples
+//!
+//! ```
+//! use crypto_pure::asn1;
+//! // SEQUENCE { INTEGER 5, OCTET STRING 0x42 }
+//! let der = [0x30, 0x06, 0x02, 0x01, 0x05, 0x04, 0x01, 0x42];
+//! let (version, octets) = asn1::parse(&der, |reader| {
+//!     let mut sequence = reader.read_sequence()?;
+//!     let version = sequence.read_u64()?;
+//!     let octets = sequence.read_octet_string()?;
+//!     sequence.finish()?;
+//!     Ok((version, octets))
+//! })
+//! .unwrap();
+//! assert_eq!(5, version);
+//! assert_eq!(&[0x42], octets);
+//! ```
+use crate::error::Error;
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+use core::fmt;
+use core::mem;
+
+/// The tag of a BOOLEAN.
+pub const BOOLEAN: u8 = 0x01;
+/// The tag of an INTEGER.
+pub const INTEGER: u8 = 0x02;
+/// The tag of a BIT STRING.
+pub const BIT_STRING: u8 = 0x03;
+/// The tag of an OCTET STRING.
+pub const OCTET_STRING: u8 = 0x04;
+/// The tag of a NULL.
+pub const NULL: u8 = 0x05;
+/// The tag of an OBJECT IDENTIFIER.
+pub const OBJECT_IDENTIFIER: u8 = 0x06;
+/// The tag of a UTF8String.
+pub const UTF8_STRING: u8 = 0x0c;
+/// The tag of a PrintableString.
+pub const PRINTABLE_STRING: u8 = 0x13;
+/// The tag of an IA5String, which holds ASCII.
+pub const IA5_STRING: u8 = 0x16;
+/// The tag of a UTCTime.
+pub const UTC_TIME: u8 = 0x17;
+/// The tag of a GeneralizedTime.
+pub const GENERALIZED_TIME: u8 = 0x18;
+/// The tag of a SEQUENCE or SEQUENCE OF.
+pub const SEQUENCE: u8 = 0x30;
+/// The tag of a SET or SET OF.
+pub const SET: u8 = 0x31;
+
+const CONSTRUCTED: u8 = 0x20;
+const CONTEXT_SPECIFIC: u8 = 0x80;
+const HIGH_TAG_NUMBER: u8 = 0x1f;
+
+/// Outputs the tag `[number]` of an explicitly tagged value, which wraps another encoding.
+///
+/// # Panics
+///
+/// Panics if `number` is not less than 31.
+pub const fn explicit(number: u8) -> u8 {
+    assert!(number < HIGH_TAG_NUMBER);
+    CONTEXT_SPECIFIC | CONSTRUCTED | number
+}
+
+/// Outputs the tag `[number]` of an implicitly tagged primitive value, such as an OCTET STRING.
+///
+/// # Panics
+///
+/// Panics if `number` is not less than 31.
+pub const fn implicit(number: u8) -> u8 {
+    assert!(number < HIGH_TAG_NUMBER);
+    CONTEXT_SPECIFIC | number
+}
+
+/// Parses `input` with `f`, which must read all of it.
+///
+/// # Errors
+///
+/// Fails with the error of `f`, or with `Error::InvalidEncoding` if input is left over.
+pub fn parse<'a, T, F>(input: &'a [u8], f: F) -> Result<T, Error>
+where
+    F: FnOnce(&mut Reader<'a>) -> Result<T, Error>,
+{
+    let mut reader = Reader::new(input);
+    let value = f(&mut reader)?;
+    reader.finish()?;
+    Ok(value)
+}
+
+/// A parser of a series of DER encodings, such as the contents of a SEQUENCE.
+///
+/// Each method reads the next encoding and fails with `Error::InvalidEncoding` if it is malformed
+/// or doesn't have the expected tag. After a failure, the reader should be discarded.
+#[derive(Clone, Debug)]
+pub struct Reader<'a> {
+    input: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    /// Initializes a reader of `input`.
+    pub fn new(input: &'a [u8]) -> Self {
+        Self { input }
+    }
+
+    /// Checks whether all of the input has been read.
+    pub fn is_empty(&self) -> bool {
+        self.input.is_empty()
+    }
+
+    /// Outputs the tag of the next encoding, if there is one, without reading it.
+    pub fn peek_tag(&self) -> Option<u8> {
+        self.input.first().copied()
+    }
+
+    /// Checks that all of the input has been read.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::InvalidEncoding` if input is left over.
+    pub fn finish(self) -> Result<(), Error> {
+        if self.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::InvalidEncoding)
+        }
+    }
+
+    /// Reads an encoding with any tag, outputting the tag and the contents.
+    pub fn read_any(&mut self) -> Result<(u8, &'a [u8]), Error> {
+        let (&tag, rest) = self.input.split_first().ok_or(Error::InvalidEncoding)?;
+        if tag & HIGH_TAG_NUMBER == HIGH_TAG_NUMBER {
+            return Err(Error::InvalidEncoding);
+        }
+        let (&first, mut rest) = rest.split_first().ok_or(Error::InvalidEncoding)?;
+        let len = if first < 0x80 {
+            usize::from(first)
+        } else {
+            // The long form has the number of length bytes, which can't be 0 since DER doesn't
+            // allow the indefinite form.
+            let len_len = usize::from(first & 0x7f);
+            if len_len == 0 || len_len > mem::size_of::<usize>() || len_len > rest.len() {
+                return Err(Error::InvalidEncoding);
+            }
+            let (len_bytes, after) = rest.split_at(len_len);
+            rest = after;
+            let len = len_bytes
+                .iter()
+                .fold(0, |len, &byte| (len << 8) | usize::from(byte));
+            // The length must be encoded in as few bytes as possible.
+            if len_bytes[0] == 0 || len < 0x80 {
+                return Err(Error::InvalidEncoding);
+            }
+            len
+        };
+        if len > rest.len() {
+            return Err(Error::InvalidEncoding);
+        }
+        let (contents, rest) = rest.split_at(len);
+        self.input = rest;
+        Ok((tag, contents))
+    }
+
+    /// Reads an encoding with the tag `tag`, outputting the contents.
+    pub fn read(&mut self, tag: u8) -> Result<&'a [u8], Error> {
+        match self.read_any()? {
+            (actual, contents) if actual == tag => Ok(contents),
+            _ => Err(Error::InvalidEncoding),
+        }
+    }
+
+    /// Reads an encoding with the tag `tag`, outputting all of it rather than only the contents,
+    /// such as to verify a signature over it.
+    pub fn read_raw(&mut self, tag: u8) -> Result<&'a [u8], Error> {
+        let input = self.input;
+        self.read(tag)?;
+        Ok(&input[..input.len() - self.input.len()])
+    }
+
+    /// Reads an encoding with the tag `tag` if it is next, outputting the contents.
+    pub fn read_optional(&mut self, tag: u8) -> Result<Option<&'a [u8]>, Error> {
+        if self.peek_tag() == Some(tag) {
+            self.read(tag).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Reads a SEQUENCE, outputting a reader of its contents.
+    pub fn read_sequence(&mut self) -> Result<Reader<'a>, Error> {
+        self.read(SEQUENCE).map(Reader::new)
+    }
+
+    /// Reads a SET, outputting a reader of its contents.
+    pub fn read_set(&mut self) -> Result<Reader<'a>, Error> {
+        self.read(SET).map(Reader::new)
+    }
+
+    /// Reads an explicitly tagged value if it is next, outputting a reader of its contents.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `number` is not less than 31.
+    pub fn read_explicit(&mut self, number: u8) -> Result<Option<Reader<'a>>, Error> {
+        Ok(self.read_optional(explicit(number))?.map(Reader::new))
+    }
+
+    /// Reads a BOOLEAN.
+    pub fn read_boolean(&mut self) -> Result<bool, Error> {
+        match self.read(BOOLEAN)? {
+            [0x00] => Ok(false),
+            [0xff] => Ok(true),
+            _ => Err(Error::InvalidEncoding),
+        }
+    }
+
+    /// Reads a NULL.
+    pub fn read_null(&mut self) -> Result<(), Error> {
+        match self.read(NULL)? {
+            [] => Ok(()),
+            _ => Err(Error::InvalidEncoding),
+        }
+    }
+
+    /// Reads an INTEGER, outputting its big-endian two's complement contents.
+    pub fn read_integer(&mut self) -> Result<&'a [u8], Error> {
+        let contents = self.read(INTEGER)?;
+        match contents {
+            [] => Err(Error::InvalidEncoding),
+            // The first nine bits can't all be the same, since then the first byte is redundant.
+            [0x00, next, ..] if next & 0x80 == 0 => Err(Error::InvalidEncoding),
+            [0xff, next, ..] if next & 0x80 != 0 => Err(Error::InvalidEncoding),
+            _ => Ok(contents),
+        }
+    }
+
+    /// Reads a non-negative INTEGER, outputting its big-endian magnitude, which doesn't have
+    /// leading zeros unless the integer is 0.
+    pub fn read_unsigned_integer(&mut self) -> Result<&'a [u8], Error> {
+        match self.read_integer()? {
+            [first, ..] if first & 0x80 != 0 => Err(Error::InvalidEncoding),
+            [0x00, rest @ ..] if !rest.is_empty() => Ok(rest),
+            contents => Ok(contents),
+        }
+    }
+
+    /// Reads a non-negative INTEGER that fits in a `u64`, such as a version number.
+    pub fn read_u64(&mut self) -> Result<u64, Error> {
+        let magnitude = self.read_unsigned_integer()?;
+        if magnitude.len() > 8 {
+            return Err(Error::InvalidEncoding);
+        }
+        Ok(magnitude
+            .iter()
+            .fold(0, |value, &byte| (value << 8) | u64::from(byte)))
+    }
+
+    /// Reads an OCTET STRING.
+    pub fn read_octet_string(&mut self) -> Result<&'a [u8], Error> {
+        self.read(OCTET_STRING)
+    }
+
+    /// Reads a BIT STRING.
+    pub fn read_bit_string(&mut self) -> Result<BitString<'a>, Error> {
+        BitString::from_contents(self.read(BIT_STRING)?)
+    }
+
+    /// Reads an OBJECT IDENTIFIER.
+    pub fn read_oid(&mut self) -> Result<Oid<'a>, Error> {
+        Oid::new(self.read(OBJECT_IDENTIFIER)?)
+    }
+}
+
+/// The contents of a BIT STRING.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BitString<'a> {
+    unused_bits: u8,
+    bytes: &'a [u8],
+}
+
+impl<'a> BitString<'a> {
+    /// Initializes a bit string of whole bytes, such as an encoded key.
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Self {
+            unused_bits: 0,
+            bytes,
+        }
+    }
+
+    /// Parses the DER contents of a bit string, such as one with an implicit tag.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::InvalidEncoding` if `contents` is malformed.
+    pub fn from_contents(contents: &'a [u8]) -> Result<Self, Error> {
+        let (&unused_bits, bytes) = contents.split_first().ok_or(Error::InvalidEncoding)?;
+        // DER requires the unused bits to be 0.
+        let unused_mask = match (unused_bits, bytes.last()) {
+            (0, _) => 0,
+            (1..=7, Some(last)) => last & ((1 << unused_bits) - 1),
+            _ => return Err(Error::InvalidEncoding),
+        };
+        if unused_mask != 0 {
+            return Err(Error::InvalidEncoding);
+        }
+        Ok(Self { unused_bits, bytes })
+    }
+
+    /// Gets the number of low bits of the last byte that are not part of the string.
+    pub fn unused_bits(&self) -> u8 {
+        self.unused_bits
+    }
+
+    /// Gets the bytes holding the bits, starting from the most significant bit of the first.
+    pub fn bytes(&self) -> &'a [u8] {
+        self.bytes
+    }
+
+    /// Gets the bytes if the length of the string is a multiple of 8, as for keys.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::InvalidEncoding` if the last byte has unused bits.
+    pub fn octets(&self) -> Result<&'a [u8], Error> {
+        if self.unused_bits == 0 {
+            Ok(self.bytes)
+        } else {
+            Err(Error::InvalidEncoding)
+        }
+    }
+
+    /// Checks whether bit `index` is set, where bit 0 is the most significant bit of the first
+    /// byte. Bits past the end of the string are not set.
+    pub fn is_set(&self, index: usize) -> bool {
+        let bit_len = 8 * self.bytes.len() - usize::from(self.unused_bits);
+        index < bit_len && self.bytes[index / 8] & (0x80 >> (index % 8)) != 0
+    }
+}
+
+/// The contents of an OBJECT IDENTIFIER, which `Display` formats in dotted decimal notation.
+///
+/// # Examples
+///
+/// ```
+/// use crypto_pure::asn1::Oid;
+/// let oid = Oid::new(&[0x2b, 0x65, 0x70]).unwrap();
+/// assert_eq!("1.3.101.112", format!("{}", oid));
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Oid<'a>(&'a [u8]);
+
+impl<'a> Oid<'a> {
+    /// Initializes an object identifier from its DER contents.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::InvalidEncoding` if `contents` is empty, a subidentifier is not
+    /// minimally encoded or is truncated, or an arc doesn't fit in a `u64`.
+    pub fn new(contents: &'a [u8]) -> Result<Self, Error> {
+        if contents.last().is_none_or(|last| last & 0x80 != 0) {
+            return Err(Error::InvalidEncoding);
+        }
+        for subidentifier in contents.split_inclusive(|byte| byte & 0x80 == 0) {
+            // Nine bytes hold 63 bits, and a leading 0x80 adds nothing.
+            if subidentifier.len() > 9 || subidentifier[0] == 0x80 {
+                return Err(Error::InvalidEncoding);
+            }
+        }
+        Ok(Self(contents))
+    }
+
+    /// Gets the DER contents.
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.0
+    }
+
+    /// Outputs the arcs, such as 1, 3, 101 and 112 for `1.3.101.112`.
+    pub fn arcs(&self) -> impl Iterator<Item = u64> + 'a {
+        let mut subidentifiers = self
+            .0
+            .split_inclusive(|byte| byte & 0x80 == 0)
+            .map(|bytes| {
+                bytes
+                    .iter()
+                    .fold(0, |value, &byte| (value << 7) | u64::from(byte & 0x7f))
+            });
+        // The first subidentifier combines the first two arcs, where only the second can be more
+        // than 39 if the first is 2.
+        let first = subidentifiers.next().unwrap_or(0);
+        let (root, second) = match first {
+            0..=39 => (0, first),
+            40..=79 => (1, first - 40),
+            _ => (2, first - 80),
+        };
+        IntoIterator::into_iter([root, second]).chain(subidentifiers)
+    }
+}
+
+impl fmt::Display for Oid<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, arc) in self.arcs().enumerate() {
+            if i > 0 {
+                f.write_str(".")?;
+            }
+            write!(f, "{}", arc)?;
+        }
+        Ok(())
+    }
+}
+
+/// A builder of DER encodings, which are appended in order.
+///
+/// # Examples
+///
+/// ```
+/// use crypto_pure::asn1::Writer;
+/// let mut writer = Writer::new();
+/// writer.write_sequence(|sequence| {
+///     sequence.write_u64(5);
+///     sequence.write_octet_string(&[0x42]);
+/// });
+/// assert_eq!(vec![0x30, 0x06, 0x02, 0x01, 0x05, 0x04, 0x01, 0x42], writer.into_vec());
+/// ```
+#[cfg(feature = "alloc")]
+#[derive(Clone, Debug, Default)]
+pub struct Writer {
+    output: Vec<u8>,
+}
+
+#[cfg(feature = "alloc")]
+impl Writer {
+    /// Initializes an empty writer.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Outputs the encodings written so far.
+    pub fn into_vec(self) -> Vec<u8> {
+        self.output
+    }
+
+    /// Writes an encoding with the tag `tag` and contents `contents`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `tag` has the tag number 31, which would start a longer tag.
+    pub fn write(&mut self, tag: u8, contents: &[u8]) {
+        self.write_header(tag, contents.len());
+        self.output.extend_from_slice(contents);
+    }
+
+    /// Writes an encoding with the tag `tag` whose contents are written by `f`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `tag` has the tag number 31, which would start a longer tag.
+    pub fn write_constructed<F: FnOnce(&mut Writer)>(&mut self, tag: u8, f: F) {
+        let start = self.output.len();
+        f(self);
+        // The contents are moved after the header once their length is known.
+        let contents = self.output.split_off(start);
+        self.write(tag, &contents);
+    }
+
+    /// Writes a SEQUENCE whose contents are written by `f`.
+    pub fn write_sequence<F: FnOnce(&mut Writer)>(&mut self, f: F) {
+        self.write_constructed(SEQUENCE, f);
+    }
+
+    /// Writes a SET whose contents are written by `f`, which must write them in sorted order.
+    pub fn write_set<F: FnOnce(&mut Writer)>(&mut self, f: F) {
+        self.write_constructed(SET, f);
+    }
+
+    /// Writes an explicitly tagged value whose contents are written by `f`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `number` is not less than 31.
+    pub fn write_explicit<F: FnOnce(&mut Writer)>(&mut self, number: u8, f: F) {
+        self.write_constructed(explicit(number), f);
+    }
+
+    /// Writes a BOOLEAN.
+    pub fn write_boolean(&mut self, value: bool) {
+        self.write(BOOLEAN, &[if value { 0xff } else { 0x00 }]);
+    }
+
+    /// Writes a NULL.
+    pub fn write_null(&mut self) {
+        self.write(NULL, &[]);
+    }
+
+    /// Writes a non-negative INTEGER given its big-endian magnitude, which may have leading
+    /// zeros.
+    pub fn write_unsigned_integer(&mut self, magnitude: &[u8]) {
+        let start = magnitude
+            .iter()
+            .position(|&byte| byte != 0)
+            .unwrap_or(magnitude.len());
+        let magnitude = &magnitude[start..];
+        // A zero byte is needed if the magnitude is empty or its high bit would be the sign.
+        let padding = magnitude.first().is_none_or(|first| first & 0x80 != 0);
+        self.write_header(INTEGER, magnitude.len() + usize::from(padding));
+        if padding {
+            self.output.push(0);
+        }
+        self.output.extend_from_slice(magnitude);
+    }
+
+    /// Writes a non-negative INTEGER.
+    pub fn write_u64(&mut self, value: u64) {
+        self.write_unsigned_integer(&value.to_be_bytes());
+    }
+
+    /// Writes an OCTET STRING.
+    pub fn write_octet_string(&mut self, bytes: &[u8]) {
+        self.write(OCTET_STRING, bytes);
+    }
+
+    /// Writes a BIT STRING.
+    pub fn write_bit_string(&mut self, bits: BitString) {
+        self.write_header(BIT_STRING, 1 + bits.bytes.len());
+        self.output.push(bits.unused_bits);
+        self.output.extend_from_slice(bits.bytes);
+    }
+
+    /// Writes an OBJECT IDENTIFIER.
+    pub fn write_oid(&mut self, oid: Oid) {
+        self.write(OBJECT_IDENTIFIER, oid.as_bytes());
+    }
+
+    fn write_header(&mut self, tag: u8, len: usize) {
+        assert_ne!(HIGH_TAG_NUMBER, tag & HIGH_TAG_NUMBER);
+        self.output.push(tag);
+        if len < 0x80 {
+            self.output.push(len as u8);
+        } else {
+            let len_bytes = len.to_be_bytes();
+            let start = len_bytes.iter().position(|&byte| byte != 0).unwrap();
+            self.output.push(0x80 | (len_bytes.len() - start) as u8);
+            self.output.extend_from_slice(&len_bytes[start..]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::*;
+    use std::vec::Vec;
+
+    // RFC 8410, Section 10.3
+    const PRIVATE_KEY: &str =
+        "302e020100300506032b657004220420d4ee72dbf913584ad5b6d8f1f769f8ad3afe7c28cbf1d4fbe097a88f44755842";
+
+    fn parse_private_key(der: &[u8]) -> Result<(u64, Oid<'_>, &[u8]), Error> {
+        parse(der, |reader| {
+            let mut key_info = reader.read_sequence()?;
+            let version = key_info.read_u64()?;
+            let mut algorithm = key_info.read_sequence()?;
+            let oid = algorithm.read_oid()?;
+            algorithm.finish()?;
+            let key = key_info.read_octet_string()?;
+            key_info.finish()?;
+            Ok((version, oid, key))
+        })
+    }
+
+    #[test]
+    fn test_private_key() {
+        let der = h2b(PRIVATE_KEY);
+        let (version, oid, key) = parse_private_key(&der).unwrap();
+        assert_eq!(0, version);
+        assert_eq!(&[0x2b, 0x65, 0x70], oid.as_bytes());
+        assert_eq!(&der[14..], key);
+
+        #[cfg(feature = "alloc")]
+        {
+            let mut writer = Writer::new();
+            writer.write_sequence(|key_info| {
+                key_info.write_u64(version);
+                key_info.write_sequence(|algorithm| algorithm.write_oid(oid));
+                key_info.write_octet_string(key);
+            });
+            assert_eq!(der, writer.into_vec());
+        }
+
+        // Every truncation and every extension fails.
+        for len in 0..der.len() {
+            assert_eq!(Err(Error::InvalidEncoding), parse_private_key(&der[..len]));
+        }
+        let mut extended = der.clone();
+        extended.push(0);
+        assert_eq!(Err(Error::InvalidEncoding), parse_private_key(&extended));
+    }
+
+    #[test]
+    fn test_lengths() {
+        let mut contents = [0; 0x1_0000];
+        contents[0x1_0000 - 1] = 0x42;
+        for &(header, len) in &[
+            (&[0x04, 0x00][..], 0),
+            (&[0x04, 0x7f], 0x7f),
+            (&[0x04, 0x81, 0x80], 0x80),
+            (&[0x04, 0x81, 0xff], 0xff),
+            (&[0x04, 0x82, 0x01, 0x00], 0x100),
+            (&[0x04, 0x82, 0xff, 0xff], 0xffff),
+        ] {
+            let mut input = header.to_vec();
+            input.extend_from_slice(&contents[..len]);
+            let output = parse(&input, |reader| reader.read_octet_string()).unwrap();
+            assert_eq!(&contents[..len], output);
+
+            #[cfg(feature = "alloc")]
+            {
+                let mut writer = Writer::new();
+                writer.write_octet_string(&contents[..len]);
+                assert_eq!(input, writer.into_vec());
+            }
+        }
+    }
+
+    #[test]
+    fn test_malformed() {
+        let cases: &[&[u8]] = &[
+            // Missing tag, length or contents
+            &[],
+            &[0x04],
+            &[0x04, 0x01],
+            &[0x04, 0x81],
+            &[0x04, 0x82, 0x01],
+            &[0x04, 0x81, 0x80],
+            // Indefinite, reserved and non-minimal lengths
+            &[0x04, 0x80, 0x00, 0x00],
+            &[0x04, 0xff, 0x00],
+            &[0x04, 0x81, 0x00],
+            &[0x04, 0x81, 0x7f],
+            &[0x04, 0x82, 0x00, 0x80],
+            &[
+                0x04, 0x89, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            ],
+            // High tag number
+            &[0x1f, 0x04, 0x00],
+            &[0x9f, 0x81, 0x00, 0x00],
+        ];
+        for input in cases {
+            assert_eq!(Err(Error::InvalidEncoding), parse(input, |r| r.read_any()));
+        }
+        // Wrong tags
+        assert_eq!(
+            Err(Error::InvalidEncoding),
+            parse(&[0x24, 0x00], |r| r.read_octet_string())
+        );
+        assert_eq!(
+            Err(Error::InvalidEncoding),
+            parse(&[0x10, 0x00], |r| r.read_sequence().map(|_| ()))
+        );
+        assert_eq!(
+            Err(Error::InvalidEncoding),
+            parse(&[0x0a, 0x01, 0x00], |r| r.read_u64())
+        );
+    }
+
+    #[test]
+    fn test_integers() {
+        let valid: &[(&[u8], &[u8], Option<u64>)] = &[
+            (&[0x00], &[0x00], Some(0)),
+            (&[0x7f], &[0x7f], Some(0x7f)),
+            (&[0x00, 0x80], &[0x80], Some(0x80)),
+            (&[0x01, 0x00], &[0x01, 0x00], Some(0x100)),
+            (
+                &[0x00, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff],
+                &[0xff; 8],
+                Some(u64::MAX),
+            ),
+            (
+                &[0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00],
+                &[0x01, 0, 0, 0, 0, 0, 0, 0, 0],
+                None,
+            ),
+        ];
+        for &(contents, magnitude, value) in valid {
+            let input = &[&[INTEGER, contents.len() as u8], contents].concat();
+            assert_eq!(Ok(contents), parse(input, |r| r.read_integer()));
+            assert_eq!(Ok(magnitude), parse(input, |r| r.read_unsigned_integer()));
+            assert_eq!(
+                value.ok_or(Error::InvalidEncoding),
+                parse(input, |r| r.read_u64())
+            );
+
+            #[cfg(feature = "alloc")]
+            {
+                let mut writer = Writer::new();
+                writer.write_unsigned_integer(magnitude);
+                writer.write_unsigned_integer(&[&[0, 0], magnitude].concat());
+                assert_eq!([&input[..], input].concat(), writer.into_vec());
+                if let Some(value) = value {
+                    let mut writer = Writer::new();
+                    writer.write_u64(value);
+                    assert_eq!(input, &writer.into_vec());
+                }
+            }
+        }
+
+        let negative: &[&[u8]] = &[&[0xff], &[0x80], &[0xff, 0x7f], &[0x80, 0x00]];
+        for contents in negative {
+            let input = &[&[INTEGER, contents.len() as u8], *contents].concat();
+            assert_eq!(Ok(*contents), parse(input, |r| r.read_integer()));
+            assert_eq!(
+                Err(Error::InvalidEncoding),
+                parse(input, |r| r.read_unsigned_integer())
+            );
+        }
+
+        let malformed: &[&[u8]] = &[
+            &[],
+            &[0x00, 0x00],
+            &[0x00, 0x7f],
+            &[0xff, 0x80],
+            &[0xff, 0xff],
+        ];
+        for contents in malformed {
+            let input = &[&[INTEGER, contents.len() as u8], *contents].concat();
+            assert_eq!(
+                Err(Error::InvalidEncoding),
+                parse(input, |r| r.read_integer())
+            );
+            assert_eq!(
+                Err(Error::InvalidEncoding),
+                parse(input, |r| r.read_unsigned_integer())
+            );
+        }
+
+        #[cfg(feature = "alloc")]
+        {
+            let mut writer = Writer::new();
+            writer.write_unsigned_integer(&[]);
+            assert_eq!(vec![0x02, 0x01, 0x00], writer.into_vec());
+        }
+    }
+
+    #[test]
+    fn test_bit_strings() {
+        let input = [0x03, 0x03, 0x06, 0x6e, 0x40];
+        let bits = parse(&input, |r| r.read_bit_string()).unwrap();
+        assert_eq!(6, bits.unused_bits());
+        assert_eq!(&[0x6e, 0x40], bits.bytes());
+        assert_eq!(Err(Error::InvalidEncoding), bits.octets());
+        let set: Vec<_> = (0..16).filter(|&i| bits.is_set(i)).collect();
+        assert_eq!(vec![1, 2, 4, 5, 6, 9], set);
+
+        let input = [0x03, 0x03, 0x00, 0x12, 0x34];
+        let bits = parse(&input, |r| r.read_bit_string()).unwrap();
+        assert_eq!(BitString::new(&[0x12, 0x34]), bits);
+        assert_eq!(Ok(&[0x12, 0x34][..]), bits.octets());
+
+        let empty = parse(&[0x03, 0x01, 0x00], |r| r.read_bit_string()).unwrap();
+        assert_eq!(Ok(&[][..]), empty.octets());
+        assert!(!empty.is_set(0));
+
+        let malformed: &[&[u8]] = &[
+            &[0x03, 0x00],
+            &[0x03, 0x01, 0x01],
+            &[0x03, 0x02, 0x08, 0x00],
+            &[0x03, 0x02, 0x01, 0x01],
+            &[0x03, 0x02, 0x07, 0x40],
+        ];
+        for input in malformed {
+            assert_eq!(
+                Err(Error::InvalidEncoding),
+                parse(input, |r| r.read_bit_string())
+            );
+        }
+
+        #[cfg(feature = "alloc")]
+        {
+            let mut writer = Writer::new();
+            writer.write_bit_string(bits);
+            writer.write_bit_string(
+                parse(&[0x03, 0x02, 0x07, 0x80], |r| r.read_bit_string()).unwrap(),
+            );
+            assert_eq!(
+                vec![0x03, 0x03, 0x00, 0x12, 0x34, 0x03, 0x02, 0x07, 0x80],
+                writer.into_vec()
+            );
+        }
+    }
+
+    #[test]
+    fn test_oids() {
+        let cases: &[(&[u8], &[u64], &str)] = &[
+            (&[0x2b, 0x65, 0x70], &[1, 3, 101, 112], "1.3.101.112"),
+            (
+                &[0x2a, 0x86, 0x48, 0xce, 0x3d, 0x03, 0x01, 0x07],
+                &[1, 2, 840, 10045, 3, 1, 7],
+                "1.2.840.10045.3.1.7",
+            ),
+            (&[0x00], &[0, 0], "0.0"),
+            (&[0x27], &[0, 39], "0.39"),
+            (&[0x50], &[2, 0], "2.0"),
+            (&[0x88, 0x37, 0x03], &[2, 999, 3], "2.999.3"),
+            (
+                &[0x2b, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x7f],
+                &[1, 3, (1 << 63) - 1],
+                "1.3.9223372036854775807",
+            ),
+        ];
+        for &(contents, arcs, dotted) in cases {
+            let input = &[&[OBJECT_IDENTIFIER, contents.len() as u8], contents].concat();
+            let oid = parse(input, |r| r.read_oid()).unwrap();
+            assert_eq!(contents, oid.as_bytes());
+            assert_eq!(arcs, &oid.arcs().collect::<Vec<_>>()[..]);
+            assert_eq!(dotted, format!("{}", oid));
+
+            #[cfg(feature = "alloc")]
+            {
+                let mut writer = Writer::new();
+                writer.write_oid(oid);
+                assert_eq!(input, &writer.into_vec());
+            }
+        }
+
+        let malformed: &[&[u8]] = &[
+            &[],
+            &[0x80],
+            &[0x2b, 0x86],
+            &[0x2b, 0x80, 0x01],
+            &[0x80, 0x2b],
+            &[
+                0x2b, 0x81, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x00,
+            ],
+        ];
+        for contents in malformed {
+            assert_eq!(Err(Error::InvalidEncoding), Oid::new(contents));
+        }
+    }
+
+    #[test]
+    fn test_simple_types() {
+        assert_eq!(Ok(true), parse(&[0x01, 0x01, 0xff], |r| r.read_boolean()));
+        assert_eq!(Ok(false), parse(&[0x01, 0x01, 0x00], |r| r.read_boolean()));
+        assert_eq!(Ok(()), parse(&[0x05, 0x00], |r| r.read_null()));
+        let malformed: &[&[u8]] = &[
+            &[0x01, 0x00],
+            &[0x01, 0x01, 0x01],
+            &[0x01, 0x02, 0xff, 0xff],
+        ];
+        for input in malformed {
+            assert_eq!(
+                Err(Error::InvalidEncoding),
+                parse(input, |r| r.read_boolean())
+            );
+        }
+        assert_eq!(
+            Err(Error::InvalidEncoding),
+            parse(&[0x05, 0x01, 0x00], |r| r.read_null())
+        );
+
+        #[cfg(feature = "alloc")]
+        {
+            let mut writer = Writer::new();
+            writer.write_boolean(true);
+            writer.write_boolean(false);
+            writer.write_null();
+            assert_eq!(
+                vec![0x01, 0x01, 0xff, 0x01, 0x01, 0x00, 0x05, 0x00],
+                writer.into_vec()
+            );
+        }
+    }
+
+    #[test]
+    fn test_context_tags() {
+        // SEQUENCE { [0] { INTEGER 2 }, [1] IMPLICIT OCTET STRING, [3] { NULL } }
+        let input = [
+            0x30, 0x0c, 0xa0, 0x03, 0x02, 0x01, 0x02, 0x81, 0x01, 0x42, 0xa3, 0x02, 0x05, 0x00,
+        ];
+        parse(&input, |reader| {
+            let mut sequence = reader.read_sequence()?;
+            let mut version = sequence.read_explicit(0)?.unwrap();
+            assert_eq!(2, version.read_u64()?);
+            version.finish()?;
+            assert_eq!(None, sequence.read_explicit(1)?.map(|_| ()));
+            assert_eq!(Some(&[0x42][..]), sequence.read_optional(implicit(1))?);
+            assert_eq!(None, sequence.read_optional(implicit(2))?);
+            assert_eq!(None, sequence.read_explicit(2)?.map(|_| ()));
+            let mut extensions = sequence.read_explicit(3)?.unwrap();
+            extensions.read_null()?;
+            extensions.finish()?;
+            assert_eq!(None, sequence.peek_tag());
+            sequence.finish()
+        })
+        .unwrap();
+        let raw = parse(&input, |reader| reader.read_raw(SEQUENCE)).unwrap();
+        assert_eq!(&input, raw);
+
+        #[cfg(feature = "alloc")]
+        {
+            let mut writer = Writer::new();
+            writer.write_sequence(|sequence| {
+                sequence.write_explicit(0, |version| version.write_u64(2));
+                sequence.write(implicit(1), &[0x42]);
+                sequence.write_explicit(3, Writer::write_null);
+            });
+            assert_eq!(&input, &writer.into_vec()[..]);
+        }
+    }
+
+    /// Checks that if `input` starts with an encoding, the writer outputs it the same way.
+    fn check_canonical(input: &[u8]) {
+        let mut reader = Reader::new(input);
+        if let Ok((tag, contents)) = reader.read_any() {
+            let len = input.len() - reader.input.len();
+            assert_eq!(2 + contents.len(), len);
+
+            #[cfg(feature = "alloc")]
+            {
+                let mut writer = Writer::new();
+                writer.write(tag, contents);
+                assert_eq!(&input[..len], &writer.into_vec()[..]);
+            }
+            #[cfg(not(feature = "alloc"))]
+            let _ = tag;
+        }
+    }
+
+    #[test]
+    fn test_all_short_inputs() {
+        for a in 0..=255 {
+            check_canonical(&[a]);
+            for b in 0..=255 {
+                check_canonical(&[a, b]);
+                for c in 0..=255 {
+                    check_canonical(&[a, b, c]);
+                }
+            }
+        }
+    }
+}
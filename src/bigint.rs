@@ -0,0 +1,361 @@
+//! Module for arithmetic on integers of any number of little-endian 64-bit limbs, as needed for
+//! RSA and finite-field Diffie-Hellman, whose sizes are only known at run time.
+//!
+//! Arithmetic modulo an odd integer shares its Montgomery multiplication with the `field` module.
+//! All operations on values run in time depending only on their numbers of limbs, except
+//! `bit_len`, `gcd` and `small_rem`, which are only used on public values or during key
+//! generation.
+use crate::field::{montgomery_mul, mul_add, neg_inverse};
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// Arithmetic modulo an odd `m`, where values have as many limbs as `m` and are kept below it,
+/// and multiplication works on the Montgomery form a R mod m, with R = 2^(64 n) for n limbs.
+#[derive(Clone)]
+pub(crate) struct Modulus {
+    m: Vec<u64>,
+    /// -m^-1 modulo 2^64
+    m_inv: u64,
+    /// R^2 mod m
+    r2: Vec<u64>,
+}
+
+impl Modulus {
+    /// # Panics
+    ///
+    /// Panics if `m` is even.
+    pub(crate) fn new(m: Vec<u64>) -> Self {
+        assert_eq!(1, m[0] & 1, "modulus must be odd");
+        let len = m.len();
+        let mut modulus = Self {
+            m_inv: neg_inverse(m[0]),
+            m,
+            r2: vec![0; len],
+        };
+        let mut r2 = modulus.reduce(&[1]);
+        for _ in 0..128 * len {
+            r2 = modulus.add(&r2, &r2);
+        }
+        modulus.r2 = r2;
+        modulus
+    }
+
+    /// Outputs the modulus.
+    pub(crate) fn modulus(&self) -> &[u64] {
+        &self.m
+    }
+
+    /// Outputs the number of limbs of values.
+    pub(crate) fn len(&self) -> usize {
+        self.m.len()
+    }
+
+    pub(crate) fn add(&self, a: &[u64], b: &[u64]) -> Vec<u64> {
+        let (sum, carry) = add_with_carry(a, b);
+        self.subtract_if_needed(sum, carry)
+    }
+
+    pub(crate) fn sub(&self, a: &[u64], b: &[u64]) -> Vec<u64> {
+        let (difference, borrow) = sub_with_borrow(a, b);
+        let correction = select(&self.m, &vec![0; self.len()], borrow.wrapping_neg());
+        add_with_carry(&difference, &correction).0
+    }
+
+    /// Computes a b R^-1 mod m.
+    pub(crate) fn mul(&self, a: &[u64], b: &[u64]) -> Vec<u64> {
+        let mut t = vec![0; self.len()];
+        let t_high = montgomery_mul(a, b, &self.m, self.m_inv, &mut t);
+        self.subtract_if_needed(t, t_high)
+    }
+
+    pub(crate) fn square(&self, a: &[u64]) -> Vec<u64> {
+        self.mul(a, a)
+    }
+
+    /// Converts a value into Montgomery form.
+    pub(crate) fn to_montgomery(&self, a: &[u64]) -> Vec<u64> {
+        self.mul(a, &self.r2)
+    }
+
+    /// Converts a value out of Montgomery form.
+    pub(crate) fn to_normal(&self, a: &[u64]) -> Vec<u64> {
+        let mut one = vec![0; self.len()];
+        one[0] = 1;
+        self.mul(a, &one)
+    }
+
+    /// Outputs R mod m, the Montgomery form of 1.
+    pub(crate) fn one(&self) -> Vec<u64> {
+        let mut one = vec![0; self.len()];
+        one[0] = 1;
+        self.to_montgomery(&one)
+    }
+
+    /// Raises a Montgomery-form value to an exponent using 4-bit windows, reading every table entry
+    /// for each window so that the memory access pattern is independent of the exponent.
+    pub(crate) fn pow(&self, a: &[u64], exponent: &[u64]) -> Vec<u64> {
+        let mut table = vec![self.one(), a.to_vec()];
+        for i in 2..16 {
+            table.push(self.mul(&table[i - 1], a));
+        }
+        let mut result = self.one();
+        for i in (0..16 * exponent.len()).rev() {
+            for _ in 0..4 {
+                result = self.square(&result);
+            }
+            let window = (exponent[i / 16] >> (4 * (i % 16))) & 0xf;
+            let mut entry = vec![0; self.len()];
+            for (j, value) in (0..).zip(&table) {
+                let mask = u64::from(j == window).wrapping_neg();
+                entry = select(value, &entry, mask);
+            }
+            result = self.mul(&result, &entry);
+        }
+        result
+    }
+
+    /// Reduces an integer with any number of limbs modulo m.
+    pub(crate) fn reduce(&self, a: &[u64]) -> Vec<u64> {
+        div_rem(a, &self.m).1
+    }
+
+    /// Checks whether a value with as many limbs as m is below m.
+    pub(crate) fn contains(&self, a: &[u64]) -> bool {
+        sub_with_borrow(a, &self.m).1 == 1
+    }
+
+    /// Reduces `high` 2^(64 n) + `low` below m like the `field` module's `Modulus`.
+    fn subtract_if_needed(&self, low: Vec<u64>, high: u64) -> Vec<u64> {
+        let (difference, borrow) = sub_with_borrow(&low, &self.m);
+        let keep = (borrow & !high & 1).wrapping_neg();
+        select(&low, &difference, keep)
+    }
+}
+
+/// Adds two integers with the same number of limbs, outputting the carry separately.
+pub(crate) fn add_with_carry(a: &[u64], b: &[u64]) -> (Vec<u64>, u64) {
+    assert_eq!(a.len(), b.len());
+    let mut carry = 0;
+    let sum = a
+        .iter()
+        .zip(b)
+        .map(|(a, b)| {
+            let (value, carry_1) = a.overflowing_add(*b);
+            let (value, carry_2) = value.overflowing_add(carry);
+            carry = u64::from(carry_1 | carry_2);
+            value
+        })
+        .collect();
+    (sum, carry)
+}
+
+/// Subtracts two integers with the same number of limbs, outputting the borrow separately.
+pub(crate) fn sub_with_borrow(a: &[u64], b: &[u64]) -> (Vec<u64>, u64) {
+    assert_eq!(a.len(), b.len());
+    let mut borrow = 0;
+    let difference = a
+        .iter()
+        .zip(b)
+        .map(|(a, b)| {
+            let (value, borrow_1) = a.overflowing_sub(*b);
+            let (value, borrow_2) = value.overflowing_sub(borrow);
+            borrow = u64::from(borrow_1 | borrow_2);
+            value
+        })
+        .collect();
+    (difference, borrow)
+}
+
+/// Outputs `a` if `mask` is all ones and `b` if it is zero.
+pub(crate) fn select(a: &[u64], b: &[u64], mask: u64) -> Vec<u64> {
+    a.iter()
+        .zip(b)
+        .map(|(a, b)| (a & mask) | (b & !mask))
+        .collect()
+}
+
+pub(crate) fn is_zero(a: &[u64]) -> bool {
+    a.iter().fold(0, |acc, limb| acc | limb) == 0
+}
+
+/// Multiplies two integers, outputting all limbs of the product.
+pub(crate) fn mul(a: &[u64], b: &[u64]) -> Vec<u64> {
+    let mut product = vec![0; a.len() + b.len()];
+    for (i, b_i) in b.iter().enumerate() {
+        let mut carry = 0;
+        for (j, a_j) in a.iter().enumerate() {
+            (product[i + j], carry) = mul_add(*a_j, *b_i, product[i + j], carry);
+        }
+        product[i + a.len()] = carry;
+    }
+    product
+}
+
+/// Divides an integer by a nonzero `m` one bit at a time, outputting the quotient with as many
+/// limbs as `a` and the remainder with as many limbs as `m`.
+pub(crate) fn div_rem(a: &[u64], m: &[u64]) -> (Vec<u64>, Vec<u64>) {
+    let mut quotient = vec![0; a.len()];
+    let mut remainder = vec![0; m.len()];
+    for i in (0..64 * a.len()).rev() {
+        // The remainder is below m, so doubling it and adding a bit stays below 2 m.
+        let mut carry = (a[i / 64] >> (i % 64)) & 1;
+        for limb in remainder.iter_mut() {
+            let top = *limb >> 63;
+            *limb = (*limb << 1) | carry;
+            carry = top;
+        }
+        let (difference, borrow) = sub_with_borrow(&remainder, m);
+        let subtract = (!borrow | carry) & 1;
+        remainder = select(&difference, &remainder, subtract.wrapping_neg());
+        quotient[i / 64] |= subtract << (i % 64);
+    }
+    (quotient, remainder)
+}
+
+/// Outputs the remainder of an integer modulo a small nonzero divisor.
+pub(crate) fn small_rem(a: &[u64], divisor: u64) -> u64 {
+    a.iter().rev().fold(0, |remainder, limb| {
+        ((u128::from(remainder) << 64 | u128::from(*limb)) % u128::from(divisor)) as u64
+    })
+}
+
+/// Outputs the number of significant bits of an integer.
+pub(crate) fn bit_len(a: &[u64]) -> usize {
+    a.iter()
+        .rposition(|limb| *limb != 0)
+        .map_or(0, |i| 64 * (i + 1) - a[i].leading_zeros() as usize)
+}
+
+/// Computes the greatest common divisor of two integers with the same number of limbs using the
+/// binary algorithm.
+pub(crate) fn gcd(a: &[u64], b: &[u64]) -> Vec<u64> {
+    let (mut a, mut b) = (a.to_vec(), b.to_vec());
+    if is_zero(&a) {
+        return b;
+    }
+    let mut shift = 0;
+    while (a[0] | b[0]) & 1 == 0 && !is_zero(&b) {
+        shr1(&mut a);
+        shr1(&mut b);
+        shift += 1;
+    }
+    while a[0] & 1 == 0 {
+        shr1(&mut a);
+    }
+    // Now a is odd, and each step replaces the larger of a and b by their even difference.
+    while !is_zero(&b) {
+        while b[0] & 1 == 0 {
+            shr1(&mut b);
+        }
+        let (difference, borrow) = sub_with_borrow(&b, &a);
+        if borrow == 1 {
+            let difference = sub_with_borrow(&a, &b).0;
+            a = core::mem::replace(&mut b, difference);
+        } else {
+            b = difference;
+        }
+    }
+    for _ in 0..shift {
+        a = add_with_carry(&a, &a).0;
+    }
+    a
+}
+
+pub(crate) fn shr1(a: &mut [u64]) {
+    for i in 0..a.len() {
+        let high = a.get(i + 1).map_or(0, |next| next << 63);
+        a[i] = (a[i] >> 1) | high;
+    }
+}
+
+/// Reads a big-endian integer into as few limbs as fit it, with at least one limb.
+pub(crate) fn from_be_bytes(bytes: &[u8]) -> Vec<u64> {
+    let mut limbs = vec![0; bytes.len().div_ceil(8).max(1)];
+    for (limb, chunk) in limbs.iter_mut().zip(bytes.rchunks(8)) {
+        let mut buffer = [0; 8];
+        buffer[8 - chunk.len()..].copy_from_slice(chunk);
+        *limb = u64::from_be_bytes(buffer);
+    }
+    limbs
+}
+
+/// Writes the low `bytes.len()` bytes of an integer in big-endian order.
+pub(crate) fn to_be_bytes(limbs: &[u64], bytes: &mut [u8]) {
+    for (i, byte) in bytes.iter_mut().rev().enumerate() {
+        *byte = limbs
+            .get(i / 8)
+            .map_or(0, |limb| (limb >> (8 * (i % 8))) as u8);
+    }
+}
+
+/// Outputs an integer with its limbs truncated or padded with zeros to `len` limbs.
+pub(crate) fn resize(a: &[u64], len: usize) -> Vec<u64> {
+    let mut resized = a.to_vec();
+    resized.resize(len, 0);
+    resized
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_arithmetic() {
+        // Compare with 128-bit arithmetic on values of two limbs.
+        let to_u128 = |a: &[u64]| u128::from(a[0]) | u128::from(a[1]) << 64;
+        let limbs = |a: u128| vec![a as u64, (a >> 64) as u64];
+        let m = 0x53c2_1bcf_cf7b_8c29_0c16_fbc5_4bb2_1a37;
+        let a = 0x1b4b_c0ee_3a46_6e55_f83e_e64a_3b3f_c4c5;
+        let b = 0x0f2a_0c43_2d2b_5a8a_f1d2_4193_2e70_9c81;
+        let (quotient, remainder) = div_rem(&limbs(a), &limbs(b >> 40));
+        assert_eq!(a / (b >> 40), to_u128(&quotient));
+        assert_eq!(a % (b >> 40), to_u128(&remainder));
+        assert_eq!(vec![5, 0], gcd(&limbs(35 << 70), &limbs(45)));
+        assert_eq!(limbs(6 << 70), gcd(&limbs(18 << 70), &limbs(12 << 80)));
+        assert_eq!((a % 65537) as u64, small_rem(&limbs(a), 65537));
+        assert_eq!(125, bit_len(&limbs(a)));
+        let product = mul(&limbs(a), &[3]);
+        assert_eq!(limbs(a.wrapping_mul(3)), &product[..2]);
+        assert_eq!(0, product[2]);
+
+        let modulus = Modulus::new(limbs(m));
+        let (a_m, b_m) = (
+            modulus.to_montgomery(&limbs(a)),
+            modulus.to_montgomery(&limbs(b)),
+        );
+        assert_eq!(limbs(a), modulus.to_normal(&a_m));
+        let sum = (a + b) % m;
+        assert_eq!(limbs(sum), modulus.add(&limbs(a), &limbs(b)));
+        assert_eq!(limbs(a), modulus.sub(&limbs(sum), &limbs(b)));
+        let mul_mod = |x: u128, y: u128| {
+            (0..128).rev().fold(0, |acc, i| {
+                let acc = (acc + acc) % m;
+                if (y >> i) & 1 == 1 {
+                    (acc + x) % m
+                } else {
+                    acc
+                }
+            })
+        };
+        let product = mul_mod(a, b);
+        assert_eq!(limbs(product), modulus.to_normal(&modulus.mul(&a_m, &b_m)));
+        let power = (0..17).fold(1, |acc, _| mul_mod(acc, a));
+        let power_m = modulus.pow(&a_m, &[17, 0]);
+        assert_eq!(limbs(power), modulus.to_normal(&power_m));
+        assert_eq!(limbs(a % m), modulus.reduce(&limbs(a)));
+        // 2^192 - 1 mod m
+        let expected = 0x0844_90a0_9e01_424f_48ed_781a_36ad_12cb;
+        assert_eq!(limbs(expected), modulus.reduce(&[u64::MAX; 3]));
+    }
+
+    #[test]
+    fn test_bytes() {
+        let bytes = [1, 2, 3, 4, 5, 6, 7, 8, 9];
+        let limbs = from_be_bytes(&bytes);
+        assert_eq!(vec![0x0203_0405_0607_0809, 1], limbs);
+        let mut output = [0; 12];
+        to_be_bytes(&limbs, &mut output);
+        assert_eq!([0, 0, 0, 1, 2, 3, 4, 5, 6, 7, 8, 9], output);
+        assert_eq!(vec![0], from_be_bytes(&[]));
+    }
+}
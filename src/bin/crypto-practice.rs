@@ -0,0 +1,347 @@
+//! A command-line tool for checksums, HMACs, age encryption and signatures.
+//!
+//! Inputs are files, or standard input if none are given or a file is `-`. Signatures are
+//! written raw in the formats `openssl` uses: Ed25519 signatures as is, ECDSA signatures in DER
+//! with SHA-256 for P-256 and SHA-384 for P-384, and RSA signatures with PKCS #1 v1.5 and
+//! SHA-256.
+use crypto_pure::age::{self, Decryptor, Encryptor, Identity, Recipient};
+use crypto_pure::encoding::hex;
+use crypto_pure::encoding::pem::{self, Mode};
+use crypto_pure::hmac::Hmac;
+use crypto_pure::io::DigestWriter;
+use crypto_pure::pkcs8::{self, PrivateKey, PublicKey};
+use crypto_pure::rand::SystemRandom;
+use crypto_pure::sha2::{HashFunction, Sha224, Sha256, Sha384, Sha512};
+use crypto_pure::sha3::{Sha3_224, Sha3_256, Sha3_384, Sha3_512};
+use crypto_pure::{ed25519, p256, p384};
+use std::env;
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::process;
+
+const USAGE: &str = "\
+usage: crypto-practice <command> [options] [file...]
+
+commands:
+    hash [--algorithm <name>] [file...]
+        Prints the digest of each file, as sha256sum does.
+    hmac (--key <hex> | --key-file <path>) [--algorithm <name>] [file...]
+        Prints the HMAC tag of each file.
+    encrypt (--recipient <age1...>... | --passphrase-file <path>) [--output <path>] [file]
+        Encrypts a file in the age format.
+    decrypt (--identity <path>... | --passphrase-file <path>) [--output <path>] [file]
+        Decrypts a file in the age format.
+    sign --key <private key PEM> [--output <path>] [file]
+        Signs a file with an Ed25519, P-256, P-384 or RSA key.
+    verify --key <public key PEM> --signature <path> [file]
+        Verifies the signature of a file, exiting with status 1 if it is not valid.
+
+algorithms: sha224, sha256 (default), sha384, sha512, sha3-224, sha3-256, sha3-384, sha3-512";
+
+type Result<T> = std::result::Result<T, Box<dyn Error>>;
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let (command, args) = match args.split_first() {
+        Some((command, args)) => (command.as_str(), args),
+        None => usage(),
+    };
+    let result = match command {
+        "hash" => hash(&Options::parse(args, &["algorithm"])),
+        "hmac" => hmac(&Options::parse(args, &["algorithm", "key", "key-file"])),
+        "encrypt" => encrypt(&Options::parse(
+            args,
+            &["recipient", "passphrase-file", "output"],
+        )),
+        "decrypt" => decrypt(&Options::parse(
+            args,
+            &["identity", "passphrase-file", "output"],
+        )),
+        "sign" => sign(&Options::parse(args, &["key", "output"])),
+        "verify" => verify(&Options::parse(args, &["key", "signature"])),
+        "help" | "--help" | "-h" => {
+            println!("{}", USAGE);
+            return;
+        }
+        _ => usage(),
+    };
+    if let Err(error) = result {
+        eprintln!("crypto-practice: {}", error);
+        process::exit(2);
+    }
+}
+
+fn usage() -> ! {
+    eprintln!("{}", USAGE);
+    process::exit(2);
+}
+
+/// The options of a command, each of which takes a value, and its files.
+struct Options {
+    values: Vec<(String, String)>,
+    files: Vec<String>,
+}
+
+impl Options {
+    /// Parses the arguments after the command, exiting with the usage if an option isn't one of
+    /// `names` or is missing its value. Arguments after `--` are all files.
+    fn parse(args: &[String], names: &[&str]) -> Self {
+        let mut values = Vec::new();
+        let mut files = Vec::new();
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            if arg == "--" {
+                files.extend(args.by_ref().cloned());
+            } else if let Some(name) = arg.strip_prefix("--") {
+                match args.next() {
+                    Some(value) if names.contains(&name) => {
+                        values.push((name.to_string(), value.clone()))
+                    }
+                    _ => usage(),
+                }
+            } else if arg.starts_with('-') && arg != "-" {
+                usage();
+            } else {
+                files.push(arg.clone());
+            }
+        }
+        Self { values, files }
+    }
+
+    /// Gets every value of an option, in order.
+    fn all(&self, name: &str) -> Vec<&str> {
+        self.values
+            .iter()
+            .filter(|(option, _)| option == name)
+            .map(|(_, value)| value.as_str())
+            .collect()
+    }
+
+    /// Gets the last value of an option, if it was given.
+    fn get(&self, name: &str) -> Option<&str> {
+        self.all(name).pop()
+    }
+
+    /// Gets the last value of an option that must be given.
+    fn required(&self, name: &str) -> Result<&str> {
+        self.get(name)
+            .ok_or_else(|| format!("missing --{}", name).into())
+    }
+
+    /// Gets the files, or `-` for standard input if there are none.
+    fn files(&self) -> Vec<&str> {
+        match self.files.len() {
+            0 => vec!["-"],
+            _ => self.files.iter().map(String::as_str).collect(),
+        }
+    }
+
+    /// Gets the only file, or `-` for standard input if there is none.
+    fn file(&self) -> Result<&str> {
+        match self.files()[..] {
+            [file] => Ok(file),
+            _ => Err("expected at most one file".into()),
+        }
+    }
+}
+
+fn open(path: &str) -> Result<Box<dyn Read>> {
+    Ok(match path {
+        "-" => Box::new(io::stdin()),
+        _ => Box::new(File::open(path).map_err(|error| format!("{}: {}", path, error))?),
+    })
+}
+
+fn create(path: Option<&str>) -> Result<Box<dyn Write>> {
+    Ok(match path {
+        None | Some("-") => Box::new(io::stdout()),
+        Some(path) => Box::new(File::create(path).map_err(|error| format!("{}: {}", path, error))?),
+    })
+}
+
+fn read(path: &str) -> Result<Vec<u8>> {
+    let mut contents = Vec::new();
+    open(path)?.read_to_end(&mut contents)?;
+    Ok(contents)
+}
+
+fn read_to_string(path: &str) -> Result<String> {
+    fs::read_to_string(path).map_err(|error| format!("{}: {}", path, error).into())
+}
+
+/// Reads a passphrase from the first line of a file.
+fn read_passphrase(path: &str) -> Result<String> {
+    let contents = read_to_string(path)?;
+    Ok(contents.lines().next().unwrap_or("").to_string())
+}
+
+fn digest<H: HashFunction>(input: &mut dyn Read) -> Result<Vec<u8>> {
+    let mut writer = DigestWriter::<H>::new();
+    io::copy(input, &mut writer)?;
+    Ok(writer.finalize().to_vec())
+}
+
+fn tag<H: HashFunction>(key: &[u8], input: &mut dyn Read) -> Result<Vec<u8>> {
+    let mut hmac = Hmac::<H>::new(key);
+    let buffer = &mut [0; 64 * 1024];
+    loop {
+        match input.read(buffer) {
+            Ok(0) => return Ok(hmac.tag().to_vec()),
+            Ok(len) => hmac.update(&buffer[..len]),
+            Err(error) if error.kind() == io::ErrorKind::Interrupted => {}
+            Err(error) => return Err(error.into()),
+        }
+    }
+}
+
+/// Prints the output of `f` for each file in the format of `sha256sum`.
+fn print_each(options: &Options, f: impl Fn(&mut dyn Read) -> Result<Vec<u8>>) -> Result<()> {
+    for file in options.files() {
+        let output = f(&mut open(file)?)?;
+        println!("{}  {}", hex::encode(&output), file);
+    }
+    Ok(())
+}
+
+fn hash(options: &Options) -> Result<()> {
+    let digest = match options.get("algorithm").unwrap_or("sha256") {
+        "sha224" => digest::<Sha224>,
+        "sha256" => digest::<Sha256>,
+        "sha384" => digest::<Sha384>,
+        "sha512" => digest::<Sha512>,
+        "sha3-224" => digest::<Sha3_224>,
+        "sha3-256" => digest::<Sha3_256>,
+        "sha3-384" => digest::<Sha3_384>,
+        "sha3-512" => digest::<Sha3_512>,
+        algorithm => return Err(format!("unknown algorithm {}", algorithm).into()),
+    };
+    print_each(options, digest)
+}
+
+fn hmac(options: &Options) -> Result<()> {
+    let tag = match options.get("algorithm").unwrap_or("sha256") {
+        "sha224" => tag::<Sha224>,
+        "sha256" => tag::<Sha256>,
+        "sha384" => tag::<Sha384>,
+        "sha512" => tag::<Sha512>,
+        "sha3-224" => tag::<Sha3_224>,
+        "sha3-256" => tag::<Sha3_256>,
+        "sha3-384" => tag::<Sha3_384>,
+        "sha3-512" => tag::<Sha3_512>,
+        algorithm => return Err(format!("unknown algorithm {}", algorithm).into()),
+    };
+    let key = match (options.get("key"), options.get("key-file")) {
+        (Some(key), None) => hex::decode_secret(key).map_err(|_| "--key is not hexadecimal")?,
+        (None, Some(path)) => read(path)?.into(),
+        _ => return Err("expected one of --key and --key-file".into()),
+    };
+    print_each(options, |input| tag(&key, input))
+}
+
+fn encrypt(options: &Options) -> Result<()> {
+    let rng = &SystemRandom::new();
+    let output = create(options.get("output"))?;
+    let mut encryptor = match (options.all("recipient"), options.get("passphrase-file")) {
+        (recipients, None) if !recipients.is_empty() => {
+            let recipients = recipients
+                .into_iter()
+                .map(|recipient| {
+                    Recipient::parse(recipient)
+                        .map_err(|_| format!("invalid recipient {}", recipient))
+                })
+                .collect::<std::result::Result<Vec<_>, _>>()?;
+            Encryptor::new(output, &recipients, rng)?
+        }
+        (recipients, Some(path)) if recipients.is_empty() => {
+            let passphrase = read_passphrase(path)?;
+            Encryptor::with_passphrase(output, passphrase.as_bytes(), age::DEFAULT_LOG_N, rng)?
+        }
+        _ => return Err("expected either --recipient or --passphrase-file".into()),
+    };
+    io::copy(&mut open(options.file()?)?, &mut encryptor)?;
+    encryptor.finish()?.flush()?;
+    Ok(())
+}
+
+fn decrypt(options: &Options) -> Result<()> {
+    let input = open(options.file()?)?;
+    let mut decryptor = match (options.all("identity"), options.get("passphrase-file")) {
+        (paths, None) if !paths.is_empty() => {
+            let mut identities = Vec::new();
+            for path in paths {
+                identities.extend(read_identities(path)?);
+            }
+            Decryptor::new(input, &identities)?
+        }
+        (paths, Some(path)) if paths.is_empty() => {
+            let passphrase = read_passphrase(path)?;
+            Decryptor::with_passphrase(input, passphrase.as_bytes(), age::DEFAULT_LOG_N)?
+        }
+        _ => return Err("expected either --identity or --passphrase-file".into()),
+    };
+    // Decrypted chunks are authenticated before they are written, but an error partway leaves
+    // the output truncated.
+    let mut output = create(options.get("output"))?;
+    io::copy(&mut decryptor, &mut output)?;
+    output.flush()?;
+    Ok(())
+}
+
+/// Reads the identities of an identity file, one per line with comments starting with `#`.
+fn read_identities(path: &str) -> Result<Vec<Identity>> {
+    let contents = read_to_string(path)?;
+    let lines = contents.lines().map(str::trim);
+    lines
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| Identity::parse(line).map_err(|_| format!("{}: invalid identity", path).into()))
+        .collect()
+}
+
+fn sign(options: &Options) -> Result<()> {
+    let key = pem::parse(&read_to_string(options.required("key")?)?, Mode::Lenient)?;
+    let private_key = pkcs8::decode_private_key(key.contents())?;
+    let message = &read(options.file()?)?;
+    let rng = &SystemRandom::new();
+    // Computing the public key also checks that an elliptic curve key is in range.
+    let signature = match (&private_key, private_key.public_key()?) {
+        (PrivateKey::Ed25519(secret_key), PublicKey::Ed25519(public_key)) => {
+            ed25519::sign(message, &secret_key[..], &public_key)?.to_vec()
+        }
+        (PrivateKey::P256(secret_key), _) => {
+            p256::signature_to_der(&p256::sign(message, &secret_key[..], rng)?)?
+        }
+        (PrivateKey::P384(secret_key), _) => {
+            p384::signature_to_der(&p384::sign(message, &secret_key[..], rng)?)?
+        }
+        (PrivateKey::Rsa(private_key), _) => private_key.sign_pkcs1v15::<Sha256>(message, rng)?,
+        _ => return Err("X25519 keys can't sign".into()),
+    };
+    let mut output = create(options.get("output"))?;
+    output.write_all(&signature)?;
+    output.flush()?;
+    Ok(())
+}
+
+fn verify(options: &Options) -> Result<()> {
+    let key = pem::parse(&read_to_string(options.required("key")?)?, Mode::Lenient)?;
+    let public_key = pkcs8::decode_public_key(key.contents())?;
+    let signature = &read(options.required("signature")?)?;
+    let message = &read(options.file()?)?;
+    let valid = match &public_key {
+        PublicKey::Ed25519(public_key) => ed25519::verify(message, signature, public_key),
+        PublicKey::P256(public_key) => p256::signature_from_der(signature)
+            .is_ok_and(|signature| p256::verify(message, &signature, public_key)),
+        PublicKey::P384(public_key) => p384::signature_from_der(signature)
+            .is_ok_and(|signature| p384::verify(message, &signature, public_key)),
+        PublicKey::Rsa(public_key) => public_key.verify_pkcs1v15::<Sha256>(message, signature),
+        PublicKey::X25519(_) => return Err("X25519 keys can't verify".into()),
+    };
+    if valid {
+        println!("Verified OK");
+        Ok(())
+    } else {
+        println!("Verification failure");
+        process::exit(1);
+    }
+}
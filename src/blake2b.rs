@@ -0,0 +1,257 @@
+//! Module for the BLAKE2b hash function (RFC 7693). Internally, the digest size is variable and
+//! an optional key is supported, as needed by Argon2 and PASETO.
+use crate::sha2::HashFunction;
+use byteorder::{ByteOrder as _, LittleEndian};
+
+/// The BLAKE2b hash function with a 64-byte digest.
+///
+/// # Examples
+///
+/// ```
+/// use crypto_pure::blake2b::Blake2b512;
+/// use crypto_pure::sha2::HashFunction;
+/// let mut digest = [0; Blake2b512::DIGEST_SIZE];
+/// let mut blake = Blake2b512::default();
+/// blake.update(b"part one");
+/// blake.update(b"part two");
+/// blake.write_digest(&mut digest);
+/// ```
+#[derive(Clone)]
+pub struct Blake2b512(Blake2b);
+
+impl Default for Blake2b512 {
+    fn default() -> Self {
+        Blake2b512(Blake2b::new(64))
+    }
+}
+
+impl HashFunction for Blake2b512 {
+    const DIGEST_SIZE: usize = 64;
+    const BLOCK_SIZE: usize = 128;
+
+    fn update(&mut self, input: &[u8]) {
+        self.0.update(input);
+    }
+
+    /// Writes the hash function digest into an output buffer.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `output.len()` is not equal to the digest size.
+    fn write_digest(self, output: &mut [u8]) {
+        self.0.write_digest(output);
+    }
+}
+
+const IV: [u64; 8] = [
+    0x6a09_e667_f3bc_c908,
+    0xbb67_ae85_84ca_a73b,
+    0x3c6e_f372_fe94_f82b,
+    0xa54f_f53a_5f1d_36f1,
+    0x510e_527f_ade6_82d1,
+    0x9b05_688c_2b3e_6c1f,
+    0x1f83_d9ab_fb41_bd6b,
+    0x5be0_cd19_137e_2179,
+];
+
+const SIGMA: [[usize; 16]; 12] = [
+    [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15],
+    [14, 10, 4, 8, 9, 15, 13, 6, 1, 12, 0, 2, 11, 7, 5, 3],
+    [11, 8, 12, 0, 5, 2, 15, 13, 10, 14, 3, 6, 7, 1, 9, 4],
+    [7, 9, 3, 1, 13, 12, 11, 14, 2, 6, 5, 10, 4, 0, 15, 8],
+    [9, 0, 5, 7, 2, 4, 10, 15, 14, 1, 11, 12, 6, 8, 3, 13],
+    [2, 12, 6, 10, 0, 11, 8, 3, 4, 13, 7, 5, 15, 14, 1, 9],
+    [12, 5, 1, 15, 14, 13, 4, 10, 0, 7, 6, 3, 9, 2, 8, 11],
+    [13, 11, 7, 14, 12, 1, 3, 9, 5, 0, 15, 4, 8, 6, 2, 10],
+    [6, 15, 14, 9, 11, 3, 0, 8, 12, 2, 13, 7, 1, 4, 10, 5],
+    [10, 2, 8, 4, 7, 6, 1, 5, 15, 11, 9, 14, 3, 12, 13, 0],
+    [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15],
+    [14, 10, 4, 8, 9, 15, 13, 6, 1, 12, 0, 2, 11, 7, 5, 3],
+];
+
+#[derive(Clone)]
+pub(crate) struct Blake2b {
+    state: [u64; 8],
+    buffer: [u8; 128],
+    offset: usize,
+    len: u128,
+    digest_size: usize,
+}
+
+impl Blake2b {
+    /// # Panics
+    ///
+    /// Panics if `digest_size` is 0 or more than 64.
+    pub(crate) fn new(digest_size: usize) -> Self {
+        Self::new_keyed(&[], digest_size)
+    }
+
+    /// Initializes BLAKE2b as a MAC, which is unkeyed if `key` is empty.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `key.len()` is more than 64, or if `digest_size` is 0 or more than 64.
+    pub(crate) fn new_keyed(key: &[u8], digest_size: usize) -> Self {
+        assert!(key.len() <= 64);
+        assert!(0 < digest_size && digest_size <= 64);
+        let mut state = IV;
+        state[0] ^= 0x0101_0000 ^ (key.len() << 8) as u64 ^ digest_size as u64;
+        // The key is padded to a full block, which is processed like any other.
+        let mut buffer = [0; 128];
+        buffer[..key.len()].copy_from_slice(key);
+        Self {
+            state,
+            buffer,
+            offset: if key.is_empty() { 0 } else { 128 },
+            len: 0,
+            digest_size,
+        }
+    }
+
+    pub(crate) fn update(&mut self, input: &[u8]) {
+        let mut input = input;
+        // The last block is held back, since it is compressed with the final flag set.
+        while !input.is_empty() {
+            if self.offset == 128 {
+                self.len += 128;
+                compress(&mut self.state, &self.buffer, self.len, false);
+                self.offset = 0;
+            }
+            let len = input.len().min(128 - self.offset);
+            self.buffer[self.offset..self.offset + len].copy_from_slice(&input[..len]);
+            self.offset += len;
+            input = &input[len..];
+        }
+    }
+
+    /// # Panics
+    ///
+    /// Panics if `output.len()` is not equal to the digest size.
+    pub(crate) fn write_digest(mut self, output: &mut [u8]) {
+        assert_eq!(self.digest_size, output.len());
+        self.len += self.offset as u128;
+        for byte in self.buffer.iter_mut().skip(self.offset) {
+            *byte = 0;
+        }
+        compress(&mut self.state, &self.buffer, self.len, true);
+        let digest = &mut [0; 64];
+        LittleEndian::write_u64_into(&self.state, digest);
+        output.copy_from_slice(&digest[..self.digest_size]);
+    }
+}
+
+fn compress(state: &mut [u64; 8], block: &[u8; 128], len: u128, last: bool) {
+    let m = &mut [0; 16];
+    LittleEndian::read_u64_into(block, m);
+    let mut v = [0; 16];
+    v[..8].copy_from_slice(state);
+    v[8..].copy_from_slice(&IV);
+    v[12] ^= len as u64;
+    v[13] ^= (len >> 64) as u64;
+    if last {
+        v[14] = !v[14];
+    }
+    for s in &SIGMA {
+        mix(&mut v, 0, 4, 8, 12, m[s[0]], m[s[1]]);
+        mix(&mut v, 1, 5, 9, 13, m[s[2]], m[s[3]]);
+        mix(&mut v, 2, 6, 10, 14, m[s[4]], m[s[5]]);
+        mix(&mut v, 3, 7, 11, 15, m[s[6]], m[s[7]]);
+        mix(&mut v, 0, 5, 10, 15, m[s[8]], m[s[9]]);
+        mix(&mut v, 1, 6, 11, 12, m[s[10]], m[s[11]]);
+        mix(&mut v, 2, 7, 8, 13, m[s[12]], m[s[13]]);
+        mix(&mut v, 3, 4, 9, 14, m[s[14]], m[s[15]]);
+    }
+    for (i, h) in state.iter_mut().enumerate() {
+        *h ^= v[i] ^ v[i + 8];
+    }
+}
+
+fn mix(v: &mut [u64; 16], a: usize, b: usize, c: usize, d: usize, x: u64, y: u64) {
+    v[a] = v[a].wrapping_add(v[b]).wrapping_add(x);
+    v[d] = (v[d] ^ v[a]).rotate_right(32);
+    v[c] = v[c].wrapping_add(v[d]);
+    v[b] = (v[b] ^ v[c]).rotate_right(24);
+    v[a] = v[a].wrapping_add(v[b]).wrapping_add(y);
+    v[d] = (v[d] ^ v[a]).rotate_right(16);
+    v[c] = v[c].wrapping_add(v[d]);
+    v[b] = (v[b] ^ v[c]).rotate_right(63);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::*;
+    use std::vec::Vec;
+
+    fn check(expected: &str, message: &[u8]) {
+        check_keyed(expected, &[], message);
+    }
+
+    fn check_keyed(expected: &str, key: &[u8], message: &[u8]) {
+        let expected = h2b(expected);
+        for chunk_size in &[1, 128, 129] {
+            let mut blake = Blake2b::new_keyed(key, expected.len());
+            for chunk in message.chunks(*chunk_size) {
+                blake.update(chunk);
+            }
+            let actual = &mut vec![0; expected.len()];
+            blake.write_digest(actual);
+            assert_eq!(&expected, actual);
+        }
+    }
+
+    #[test]
+    fn test_digest() {
+        check(
+            "ba80a53f981c4d0d6a2797b69f12f6e94c212f14685ac4b74b12bb6fdbffa2d1\
+             7d87c5392aab792dc252d5de4533cc9518d38aa8dbf1925ab92386edd4009923",
+            b"abc",
+        );
+        check(
+            "0e5751c026e543b2e8ab2eb06099daa1d1e5df47778f7787faab45cdf12fe3a8",
+            b"",
+        );
+        let message: Vec<u8> = (0..=255).chain(0..=255).collect();
+        check("e1695d971d2357c55fe6824cf175e915700ea2f2", &message);
+    }
+
+    #[test]
+    fn test_blake2b512() {
+        let mut blake = Blake2b512::default();
+        blake.update(b"a");
+        blake.update(b"bc");
+        let actual = &mut [0; 64];
+        blake.write_digest(actual);
+        assert_eq!(
+            &h2b(
+                "ba80a53f981c4d0d6a2797b69f12f6e94c212f14685ac4b74b12bb6fdbffa2d1\
+                 7d87c5392aab792dc252d5de4533cc9518d38aa8dbf1925ab92386edd4009923"
+            )[..],
+            &actual[..]
+        );
+    }
+
+    #[test]
+    fn test_keyed() {
+        let key: Vec<u8> = (0..64).collect();
+        // From the BLAKE2 known-answer tests
+        check_keyed(
+            "10ebb67700b1868efb4417987acf4690ae9d972fb7a590c2f02871799aaa4786\
+             b5e996e8f0f4eb981fc214b005f42d2ff4233499391653df7aefcbc13fc51568",
+            &key,
+            b"",
+        );
+        let message: Vec<u8> = (0..=255).chain(0..=255).collect();
+        check_keyed(
+            "31952478e1b6229b6bca296c643a3fdbe4aa2c2f7fea466675453d5f7f094270\
+             21a77b8625c78070c0f7ce564d8d257d7eb36495be76950c31a1a7d80ff8b1b4",
+            &key,
+            &message,
+        );
+        check_keyed(
+            "0330531d097355a3f72e80d55c1245ccf79f1704431c6e3887938320442c23c0",
+            b"key",
+            b"abc",
+        );
+    }
+}
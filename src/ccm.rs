@@ -0,0 +1,306 @@
+//! Module for the Counter with CBC-MAC (CCM) mode of operation for block ciphers (NIST SP 800-38C).
+use crate::aead::Aead;
+use crate::aes::BlockCipher;
+use crate::ct;
+use crate::error::Error;
+
+/// An AEAD cipher in CCM mode with a `T`-byte tag and an `N`-byte nonce.
+///
+/// `T` must be an even number from 4 to 16 and `N` must be from 7 to 13. A shorter nonce allows
+/// longer messages, up to 2^(120 - 8N) bytes.
+///
+/// # Examples
+///
+/// ```
+/// use crypto_pure::aead::Aead;
+/// use crypto_pure::aes::Aes128;
+/// use crypto_pure::ccm::Ccm;
+/// # let key = &[0x42; 16];
+/// # let nonce = &[0x24; 13];
+/// let cipher = Ccm::<Aes128, 8, 13>::new(key).unwrap();
+/// let buffer = &mut [b"message" as &[u8], &[0; 8]].concat();
+/// cipher.seal_in_place(nonce, b"header", buffer).unwrap();
+/// let opened = cipher.open_in_place(nonce, b"header", buffer);
+/// assert_eq!(Ok(&mut b"message".to_vec()[..]), opened);
+/// ```
+pub struct Ccm<E, const T: usize, const N: usize> {
+    block_cipher: E,
+}
+
+impl<E: BlockCipher, const T: usize, const N: usize> Aead for Ccm<E, T, N> {
+    const KEY_LEN: usize = E::KEY_LEN;
+    const NONCE_LEN: usize = N;
+    const TAG_LEN: usize = T;
+
+    /// Initializes an AEAD block cipher in CCM mode given a key. Fails with `Error::InvalidKey` if
+    /// `key.len()` is not equal to `E::KEY_LEN`, or with `Error::InvalidLength` if `T` or `N` is
+    /// not supported.
+    fn new(key: &[u8]) -> Result<Self, Error> {
+        if !(4..=16).contains(&T) || !T.is_multiple_of(2) || !(7..=13).contains(&N) {
+            return Err(Error::InvalidLength);
+        }
+        Ok(Self {
+            block_cipher: E::new(key)?,
+        })
+    }
+
+    /// Encrypts a message in place and outputs a tag authenticating it and provided data. Fails
+    /// with `Error::InvalidLength` if `nonce.len()` is not equal to `N` or `buffer.len()` is too
+    /// long for `N`.
+    fn seal_detached(
+        &self,
+        nonce: &[u8],
+        data: &[u8],
+        buffer: &mut [u8],
+    ) -> Result<[u8; 16], Error> {
+        check_bounds::<N>(buffer, nonce)?;
+        let tag = self.tag(nonce, data, buffer);
+        self.process(nonce, buffer);
+        Ok(tag)
+    }
+
+    /// Verifies `tag` and decrypts a ciphertext in place. On failure, `buffer` is left unchanged.
+    /// Fails with `Error::InvalidLength` if `nonce.len()` is not equal to `N` or `buffer.len()`
+    /// is too long for `N`.
+    fn open_detached(
+        &self,
+        nonce: &[u8],
+        data: &[u8],
+        buffer: &mut [u8],
+        tag: &[u8; 16],
+    ) -> Result<(), Error> {
+        check_bounds::<N>(buffer, nonce)?;
+        self.process(nonce, buffer);
+        let expected_tag = self.tag(nonce, data, buffer);
+        if ct::constant_time_eq(&expected_tag, tag) {
+            Ok(())
+        } else {
+            self.process(nonce, buffer);
+            Err(Error::TagMismatch)
+        }
+    }
+}
+
+impl<E: BlockCipher, const T: usize, const N: usize> Ccm<E, T, N> {
+    fn process(&self, nonce: &[u8], buffer: &mut [u8]) {
+        for (i, chunk) in (1..).zip(buffer.chunks_mut(16)) {
+            let block = self.block_cipher.encrypt_block(&counter::<N>(nonce, i));
+            for (byte, block_byte) in chunk.iter_mut().zip(&block) {
+                *byte ^= block_byte;
+            }
+        }
+    }
+
+    fn tag(&self, nonce: &[u8], data: &[u8], message: &[u8]) -> [u8; 16] {
+        let mut mac = CbcMac::new(&self.block_cipher);
+        let mut b0 = counter::<N>(nonce, message.len() as u64);
+        b0[0] |= ((T as u8 - 2) / 2) << 3;
+        if !data.is_empty() {
+            b0[0] |= 0x40;
+        }
+        mac.update(&b0);
+        if !data.is_empty() {
+            let len = data.len() as u64;
+            if len < 0xff00 {
+                mac.update(&(len as u16).to_be_bytes());
+            } else if len >> 32 == 0 {
+                mac.update(&[0xff, 0xfe]);
+                mac.update(&(len as u32).to_be_bytes());
+            } else {
+                mac.update(&[0xff, 0xff]);
+                mac.update(&len.to_be_bytes());
+            }
+            mac.update(data);
+            mac.pad();
+        }
+        mac.update(message);
+        mac.pad();
+
+        let mut tag = [0; 16];
+        let s0 = self.block_cipher.encrypt_block(&counter::<N>(nonce, 0));
+        for ((t, m), s) in tag.iter_mut().zip(&mac.state).zip(&s0).take(T) {
+            *t = m ^ s;
+        }
+        tag
+    }
+}
+
+/// Formats a block with the flags for the length field size, the nonce, and `value` in the
+/// length field, as used for both the first CBC-MAC block and the counter blocks.
+fn counter<const N: usize>(nonce: &[u8], value: u64) -> [u8; 16] {
+    let q = 15 - N;
+    let mut block = [0; 16];
+    block[0] = q as u8 - 1;
+    // The nonce overwrites all but the last `q` bytes of `value`, which `check_bounds` ensures
+    // are zero when `value` is a message length.
+    block[8..].copy_from_slice(&value.to_be_bytes());
+    block[1..=N].copy_from_slice(nonce);
+    block
+}
+
+fn check_bounds<const N: usize>(message: &[u8], nonce: &[u8]) -> Result<(), Error> {
+    let q = 15 - N;
+    if nonce.len() != N || (q < 8 && (message.len() as u64) >> (8 * q) != 0) {
+        return Err(Error::InvalidLength);
+    }
+    Ok(())
+}
+
+struct CbcMac<'a, E> {
+    block_cipher: &'a E,
+    state: [u8; 16],
+    offset: usize,
+}
+
+impl<'a, E: BlockCipher> CbcMac<'a, E> {
+    fn new(block_cipher: &'a E) -> Self {
+        Self {
+            block_cipher,
+            state: [0; 16],
+            offset: 0,
+        }
+    }
+
+    fn update(&mut self, input: &[u8]) {
+        for byte in input {
+            self.state[self.offset] ^= byte;
+            self.offset += 1;
+            if self.offset == 16 {
+                self.state = self.block_cipher.encrypt_block(&self.state);
+                self.offset = 0;
+            }
+        }
+    }
+
+    /// Finishes the current block as if it were padded with zeros.
+    fn pad(&mut self) {
+        if self.offset != 0 {
+            self.state = self.block_cipher.encrypt_block(&self.state);
+            self.offset = 0;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aes::{Aes128, Aes256};
+    use crate::test_helpers::*;
+    use std::vec::Vec;
+
+    fn check<E: BlockCipher, const T: usize, const N: usize>(
+        key: &[u8],
+        nonce: &str,
+        data: &[u8],
+        msg: &str,
+        sealed: &str,
+    ) {
+        let nonce = &h2b(nonce);
+        let message = &h2b(msg);
+        let sealed = &h2b(sealed);
+        let (ciphertext, tag) = sealed.split_at(message.len());
+        let cipher = Ccm::<E, T, N>::new(key).unwrap();
+
+        let output = &mut vec![0; message.len()];
+        assert_eq!(
+            tag,
+            &cipher.encrypt(message, nonce, data, output).unwrap()[..T]
+        );
+        assert_eq!(ciphertext, &output[..]);
+        assert_eq!(Ok(()), cipher.decrypt(ciphertext, nonce, data, tag, output));
+        assert_eq!(message, output);
+        let error = Err(Error::TagMismatch);
+        assert_eq!(
+            error,
+            cipher.decrypt(ciphertext, nonce, data, &tag[1..], output)
+        );
+
+        let buffer = &mut [&message[..], &[0; T]].concat();
+        cipher.seal_in_place(nonce, data, buffer).unwrap();
+        assert_eq!(sealed, buffer);
+        buffer[0] ^= 1;
+        assert_eq!(
+            Err(Error::TagMismatch),
+            cipher.open_in_place(nonce, data, buffer)
+        );
+        buffer[0] ^= 1;
+        assert_eq!(sealed, buffer);
+        let opened = cipher.open_in_place(nonce, data, buffer);
+        assert_eq!(Ok(&message[..]), opened.map(|x| &*x));
+
+        let mut padded_tag = [0; 16];
+        padded_tag[..T].copy_from_slice(tag);
+        if T < 16 {
+            padded_tag[15] = 1;
+            assert!(cipher
+                .open_detached(nonce, data, buffer, &padded_tag)
+                .is_err());
+        }
+    }
+
+    #[test]
+    fn test_sp800_38c_examples() {
+        let key = &h2b("404142434445464748494a4b4c4d4e4f");
+        let data = &h2b("0001020304050607");
+        let sealed = "7162015b4dac255d";
+        check::<Aes128, 4, 7>(key, "10111213141516", data, "20212223", sealed);
+
+        let data = &h2b("000102030405060708090a0b0c0d0e0f");
+        let msg = "202122232425262728292a2b2c2d2e2f";
+        let sealed = "d2a1f0e051ea5f62081a7792073d593d1fc64fbfaccd";
+        check::<Aes128, 6, 8>(key, "1011121314151617", data, msg, sealed);
+
+        let nonce = "101112131415161718191a1b";
+        let data = &h2b("000102030405060708090a0b0c0d0e0f10111213");
+        let msg = "202122232425262728292a2b2c2d2e2f3031323334353637";
+        let sealed = "e3b201a9f5b71a7a9b1ceaeccd97e70b6176aad9a4428aa5484392fbc1b09951";
+        check::<Aes128, 8, 12>(key, nonce, data, msg, sealed);
+    }
+
+    #[test]
+    fn test_rfc3610_packet_vector_1() {
+        let key = &h2b("c0c1c2c3c4c5c6c7c8c9cacbcccdcecf");
+        let nonce = "00000003020100a0a1a2a3a4a5";
+        let data = &h2b("0001020304050607");
+        let msg = "08090a0b0c0d0e0f101112131415161718191a1b1c1d1e";
+        let sealed = "588c979a61c663d2f066d0c2c0f989806d5f6b61dac38417e8d12cfdf926e0";
+        check::<Aes128, 8, 13>(key, nonce, data, msg, sealed);
+    }
+
+    #[test]
+    fn test_data_lengths() {
+        let key: &Vec<_> = &(0..32).collect();
+        let nonce = "000102030405060708090a0b0c";
+        let sealed = "5ca387835c0d63b16110f45241370da8";
+        check::<Aes256, 16, 13>(key, nonce, &[], "", sealed);
+
+        let data: &Vec<_> = &(0..0xff00).map(|i| i as u8).collect();
+        let sealed = "7d65c87f0b03657ed1a5d847f4238e7be68a69";
+        check::<Aes256, 16, 13>(key, nonce, data, "616263", sealed);
+    }
+
+    #[test]
+    fn test_invalid_lengths() {
+        let cipher = Ccm::<Aes128, 8, 13>::new(&[0; 16]).unwrap();
+        let error = Err(Error::InvalidLength);
+        assert_eq!(
+            error,
+            cipher.seal_detached(&[0; 12], b"", &mut []).map(|_| ())
+        );
+        // With a 13-byte nonce, the length field holds lengths of less than 2^16 bytes.
+        let buffer = &mut [0; 1 << 16];
+        assert_eq!(
+            error,
+            cipher.seal_detached(&[0; 13], b"", buffer).map(|_| ())
+        );
+        let opened = cipher.open_in_place(&[0; 13], b"", &mut [0; 7]).map(|_| ());
+        assert_eq!(error, opened);
+        let error = Err(Error::InvalidKey);
+        assert_eq!(error, Ccm::<Aes128, 8, 13>::new(&[0; 32]).map(|_| ()));
+        let error = Err(Error::InvalidLength);
+        assert_eq!(error, Ccm::<Aes128, 7, 13>::new(&[0; 16]).map(|_| ()));
+        assert_eq!(error, Ccm::<Aes128, 18, 13>::new(&[0; 16]).map(|_| ()));
+        assert_eq!(error, Ccm::<Aes128, 8, 6>::new(&[0; 16]).map(|_| ()));
+        assert_eq!(error, Ccm::<Aes128, 8, 14>::new(&[0; 16]).map(|_| ()));
+    }
+}
@@ -1,4 +1,5 @@
 //! Module for the ChaCha20 stream cipher.
+use crate::error::Error;
 use byteorder::{ByteOrder as _, LittleEndian};
 
 /// A ChaCha20 iterator that can be used as a stream cipher or pseudorandom generator.
@@ -8,7 +9,8 @@ use byteorder::{ByteOrder as _, LittleEndian};
 ///
 /// # Overflow Behavior
 ///
-/// Iterating past 256 GB of the bytestream will cause the block counter to overflow.
+/// The stream ends after 256 GiB, when the block counter would overflow: the iterator then
+/// outputs `None`, and `apply_keystream` fails rather than leave part of a buffer unprocessed.
 pub struct Stream {
     chacha20: ChaCha20,
     counter: u32,
@@ -19,43 +21,83 @@ pub struct Stream {
 impl Stream {
     /// Initializes a ChaCha20 stream given a key and nonce.
     ///
-    /// # Panics
+    /// # Errors
     ///
-    /// Panics if `key.len()` is not equal to 32 or if `nonce.len()` is not equal to 12.
-    pub fn new(key: &[u8], nonce: &[u8]) -> Self {
+    /// Fails with `Error::InvalidKey` if `key.len()` is not equal to 32, or with
+    /// `Error::InvalidLength` if `nonce.len()` is not equal to 12.
+    pub fn new(key: &[u8], nonce: &[u8]) -> Result<Self, Error> {
+        if key.len() != 32 {
+            return Err(Error::InvalidKey);
+        }
+        if nonce.len() != 12 {
+            return Err(Error::InvalidLength);
+        }
         let chacha20 = ChaCha20::new(key, nonce);
         let block = chacha20.block(0);
-        Self {
+        Ok(Self {
             chacha20,
             counter: 0,
             block,
             block_index: 0,
-        }
+        })
     }
 
     /// Encrypts a message into a ciphertext.
     ///
-    /// # Panics
+    /// # Errors
     ///
-    /// Panics if `input.len()` is not equal to `output.len()`.
-    pub fn encrypt(&mut self, input: &[u8], output: &mut [u8]) {
+    /// Fails with `Error::InvalidLength` if `input.len()` is not equal to `output.len()`, or
+    /// otherwise like `apply_keystream`.
+    pub fn encrypt(&mut self, input: &[u8], output: &mut [u8]) -> Result<(), Error> {
         self.process(input, output)
     }
 
     /// Decrypts a ciphertext into a message.
     ///
-    /// # Panics
+    /// # Errors
     ///
-    /// Panics if `input.len()` is not equal to `output.len()`.
-    pub fn decrypt(&mut self, input: &[u8], output: &mut [u8]) {
+    /// Fails with `Error::InvalidLength` if `input.len()` is not equal to `output.len()`, or
+    /// otherwise like `apply_keystream`.
+    pub fn decrypt(&mut self, input: &[u8], output: &mut [u8]) -> Result<(), Error> {
         self.process(input, output)
     }
 
-    fn process(&mut self, input: &[u8], output: &mut [u8]) {
-        assert_eq!(input.len(), output.len());
-        for (x, (y, z)) in output.iter_mut().zip(self.take(input.len()).zip(input)) {
-            *x = y ^ z;
+    /// Encrypts or decrypts a buffer in place by XORing it with the next bytes of the stream.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::NonceExhausted`, leaving `buffer` unchanged, if it is longer than the
+    /// rest of the stream.
+    pub fn apply_keystream(&mut self, buffer: &mut [u8]) -> Result<(), Error> {
+        let remaining = u64::from(u32::MAX - self.counter) * 64 + u64::from(64 - self.block_index);
+        if buffer.len() as u64 > remaining {
+            return Err(Error::NonceExhausted);
+        }
+        for (x, y) in buffer.iter_mut().zip(self.by_ref()) {
+            *x ^= y;
+        }
+        Ok(())
+    }
+
+    /// Moves to the start of the block with the given counter, as if `64 * counter` bytes of the
+    /// stream had been consumed since initialization.
+    pub fn seek(&mut self, counter: u32) {
+        self.counter = counter;
+        self.block = self.chacha20.block(counter);
+        self.block_index = 0;
+    }
+
+    fn process(&mut self, input: &[u8], output: &mut [u8]) -> Result<(), Error> {
+        if input.len() != output.len() {
+            return Err(Error::InvalidLength);
+        }
+        output.copy_from_slice(input);
+        let result = self.apply_keystream(output);
+        if result.is_err() {
+            // Don't leave the input in place of a ciphertext or message.
+            output.fill(0);
         }
+        result
     }
 }
 
@@ -64,7 +106,7 @@ impl Iterator for Stream {
 
     fn next(&mut self) -> Option<Self::Item> {
         if self.block_index == 64 {
-            self.counter += 1; // could overflow
+            self.counter = self.counter.checked_add(1)?;
             self.block = self.chacha20.block(self.counter);
             self.block_index = 0;
         }
@@ -74,6 +116,35 @@ impl Iterator for Stream {
     }
 }
 
+/// Derives a 32-byte subkey from a 32-byte key and the first 16 bytes of a nonce, as used by
+/// XChaCha20 to extend the nonce to 24 bytes.
+///
+/// # Errors
+///
+/// Fails with `Error::InvalidKey` if `key.len()` is not equal to 32, or with
+/// `Error::InvalidLength` if `nonce.len()` is not equal to 16.
+pub fn hchacha20(key: &[u8], nonce: &[u8]) -> Result<[u8; 32], Error> {
+    if key.len() != 32 {
+        return Err(Error::InvalidKey);
+    }
+    if nonce.len() != 16 {
+        return Err(Error::InvalidLength);
+    }
+    let mut state = [0; 16];
+    state[..4].copy_from_slice(&CONSTANTS);
+    LittleEndian::read_u32_into(key, &mut state[4..12]);
+    LittleEndian::read_u32_into(nonce, &mut state[12..]);
+    for _ in 0..10 {
+        ChaCha20::inner_block(&mut state);
+    }
+    let mut subkey = [0; 32];
+    LittleEndian::write_u32_into(&state[..4], &mut subkey[..16]);
+    LittleEndian::write_u32_into(&state[12..], &mut subkey[16..]);
+    Ok(subkey)
+}
+
+const CONSTANTS: [u32; 4] = [0x6170_7865, 0x3320_646e, 0x7962_2d32, 0x6b20_6574];
+
 pub(crate) struct ChaCha20 {
     state: [u32; 16],
 }
@@ -94,10 +165,7 @@ impl ChaCha20 {
     }
 
     fn setup_state(&mut self, key: &[u8], nonce: &[u8]) {
-        self.state[0] = 0x6170_7865;
-        self.state[1] = 0x3320_646e;
-        self.state[2] = 0x7962_2d32;
-        self.state[3] = 0x6b20_6574;
+        self.state[..4].copy_from_slice(&CONSTANTS);
         LittleEndian::read_u32_into(key, &mut self.state[4..12]);
         LittleEndian::read_u32_into(nonce, &mut self.state[13..]);
     }
@@ -188,15 +256,85 @@ mod tests {
         let encrypted_message = &mut vec![0; message.len()];
         let decrypted_ciphertext = &mut vec![0; ciphertext.len()];
 
-        let mut stream = Stream::new(key, nonce);
+        let mut stream = Stream::new(key, nonce).unwrap();
         stream.nth(64 - 1);
-        stream.encrypt(message.as_bytes(), encrypted_message);
+        stream
+            .encrypt(message.as_bytes(), encrypted_message)
+            .unwrap();
         assert_eq!(&ciphertext, encrypted_message);
 
-        stream = Stream::new(key, nonce);
+        stream = Stream::new(key, nonce).unwrap();
         stream.nth(64 - 1);
-        stream.decrypt(&ciphertext, decrypted_ciphertext);
+        stream.decrypt(&ciphertext, decrypted_ciphertext).unwrap();
         assert_eq!(message.as_bytes(), decrypted_ciphertext.as_slice());
+
+        for chunk_size in &[1, 17, 64, 65] {
+            let buffer = &mut message.as_bytes().to_vec();
+            stream = Stream::new(key, nonce).unwrap();
+            stream.seek(1);
+            for chunk in buffer.chunks_mut(*chunk_size) {
+                stream.apply_keystream(chunk).unwrap();
+            }
+            assert_eq!(&ciphertext, buffer);
+        }
+    }
+
+    #[test]
+    fn test_invalid_lengths() {
+        let key = &h2b(KEY);
+        assert!(Stream::new(key, NONCE).is_ok());
+        assert_eq!(Some(Error::InvalidKey), Stream::new(&key[1..], NONCE).err());
+        assert_eq!(
+            Some(Error::InvalidLength),
+            Stream::new(key, &NONCE[1..]).err()
+        );
+    }
+
+    #[test]
+    fn test_seek() {
+        let key = &h2b(KEY);
+        let mut expected = Stream::new(key, NONCE).unwrap();
+        let expected = &mut expected.by_ref().skip(3 * 64 + 5).take(100);
+        let mut stream = Stream::new(key, NONCE).unwrap();
+        stream.nth(10);
+        stream.seek(3);
+        stream.nth(4);
+        assert!(expected.eq(stream.take(100)));
+    }
+
+    #[test]
+    fn test_end_of_stream() {
+        let key = &h2b(KEY);
+        let mut stream = Stream::new(key, NONCE).unwrap();
+        stream.seek(u32::MAX);
+        let last_block = stream.chacha20.block(u32::MAX);
+        assert!(last_block.iter().copied().eq(stream.by_ref().take(64)));
+        assert_eq!(None, stream.next());
+        assert_eq!(None, stream.next());
+
+        stream.seek(u32::MAX);
+        stream.nth(9);
+        let buffer = &mut [0; 55];
+        assert_eq!(Err(Error::NonceExhausted), stream.apply_keystream(buffer));
+        assert_eq!(&[0; 55], buffer);
+        let output = &mut [0x42; 55];
+        assert_eq!(Err(Error::NonceExhausted), stream.encrypt(&[0; 55], output));
+        assert_eq!(&[0; 55], output);
+        let error = Err(Error::InvalidLength);
+        assert_eq!(error, stream.decrypt(&[0; 54], output));
+        stream.apply_keystream(&mut buffer[1..]).unwrap();
+        assert_eq!(&last_block[10..], &buffer[1..]);
+        assert_eq!(Ok(()), stream.apply_keystream(&mut []));
+        assert_eq!(Err(Error::NonceExhausted), stream.apply_keystream(&mut [0]));
+    }
+
+    #[test]
+    fn test_hchacha20() {
+        let nonce = &h2b("000000090000004a0000000031415927");
+        let expected = h2b("82413b4227b27bfed30e42508a877d73a0f9e4d58a74a853c12ec41326d3ecdc");
+        assert_eq!(expected, hchacha20(&h2b(KEY), nonce).unwrap());
+        assert_eq!(Err(Error::InvalidKey), hchacha20(&h2b(KEY)[1..], nonce));
+        assert_eq!(Err(Error::InvalidLength), hchacha20(&h2b(KEY), &nonce[1..]));
     }
 
     #[test]
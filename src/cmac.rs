@@ -0,0 +1,192 @@
+//! Module for the CMAC message authentication code (NIST SP 800-38B), such as AES-CMAC.
+use crate::aes::BlockCipher;
+use crate::ct;
+use crate::error::Error;
+use crate::hmac::MacError;
+
+/// A CMAC message authentication code using a 16-byte block cipher.
+///
+/// # Examples
+///
+/// ```
+/// use crypto_pure::aes::Aes128;
+/// use crypto_pure::cmac::{cmac, Cmac};
+/// # let key = &[0x42; 16];
+/// let mut mac = Cmac::<Aes128>::new(key).unwrap();
+/// mac.update(b"part one");
+/// mac.update(b"part two");
+/// let tag = mac.finalize();
+/// assert_eq!(cmac::<Aes128>(key, b"part onepart two"), Ok(tag));
+/// ```
+#[derive(Clone)]
+pub struct Cmac<E> {
+    block_cipher: E,
+    k1: [u8; 16],
+    k2: [u8; 16],
+    state: [u8; 16],
+    buffer: [u8; 16],
+    offset: usize,
+}
+
+/// Wrapper for obtaining the CMAC tag for a complete message.
+///
+/// # Errors
+///
+/// Fails like `Cmac::new`.
+pub fn cmac<E: BlockCipher>(key: &[u8], message: &[u8]) -> Result<[u8; 16], Error> {
+    let mut mac = Cmac::<E>::new(key)?;
+    mac.update(message);
+    Ok(mac.finalize())
+}
+
+impl<E: BlockCipher> Cmac<E> {
+    /// Initializes a CMAC function given a key, deriving its subkeys.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::InvalidKey` if `key.len()` is not appropriate for the block cipher.
+    pub fn new(key: &[u8]) -> Result<Self, Error> {
+        let block_cipher = E::new(key)?;
+        let k1 = double(&block_cipher.encrypt_block(&[0; 16]));
+        let k2 = double(&k1);
+        Ok(Self {
+            block_cipher,
+            k1,
+            k2,
+            state: [0; 16],
+            buffer: [0; 16],
+            offset: 0,
+        })
+    }
+
+    /// Feeds input into the CMAC function to update its state.
+    pub fn update(&mut self, input: &[u8]) {
+        let mut input = input;
+        // The last block is held back, since it is treated differently by `finalize`.
+        while !input.is_empty() {
+            if self.offset == 16 {
+                self.process_buffer();
+                self.offset = 0;
+            }
+            let len = input.len().min(16 - self.offset);
+            self.buffer[self.offset..self.offset + len].copy_from_slice(&input[..len]);
+            self.offset += len;
+            input = &input[len..];
+        }
+    }
+
+    /// Outputs the tag for the input fed so far.
+    pub fn finalize(mut self) -> [u8; 16] {
+        let subkey = if self.offset == 16 {
+            self.k1
+        } else {
+            self.buffer[self.offset] = 0x80;
+            for byte in self.buffer.iter_mut().skip(self.offset + 1) {
+                *byte = 0;
+            }
+            self.k2
+        };
+        for (byte, subkey_byte) in self.buffer.iter_mut().zip(&subkey) {
+            *byte ^= subkey_byte;
+        }
+        self.process_buffer();
+        self.state
+    }
+
+    /// Verifies in constant time that `expected_tag` is the tag for the input fed so far.
+    pub fn verify(self, expected_tag: &[u8]) -> Result<(), MacError> {
+        if ct::constant_time_eq(&self.finalize(), expected_tag) {
+            Ok(())
+        } else {
+            Err(MacError)
+        }
+    }
+
+    fn process_buffer(&mut self) {
+        for (state_byte, byte) in self.state.iter_mut().zip(&self.buffer) {
+            *state_byte ^= byte;
+        }
+        self.state = self.block_cipher.encrypt_block(&self.state);
+    }
+}
+
+/// Multiplies by x in GF(2^128) with the polynomial used by CMAC, in constant time.
+fn double(input: &[u8; 16]) -> [u8; 16] {
+    let mut output = [0; 16];
+    for i in 0..15 {
+        output[i] = (input[i] << 1) | (input[i + 1] >> 7);
+    }
+    output[15] = input[15] << 1;
+    output[15] ^= (input[0] >> 7).wrapping_neg() & 0x87;
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aes::{Aes128, Aes192, Aes256};
+    use crate::test_helpers::*;
+
+    const MESSAGE: &str = "6bc1bee22e409f96e93d7e117393172aae2d8a571e03ac9c9eb76fac45af8e51\
+                           30c81c46a35ce411e5fbc1191a0a52eff69f2445df4f9b17ad2b417be66c3710";
+
+    fn check<E: BlockCipher>(key: &str, len: usize, expected: &str) {
+        let key = &h2b(key);
+        let message = &h2b(MESSAGE)[..len];
+        let expected = &h2b(expected);
+        assert_eq!(expected, &cmac::<E>(key, message).unwrap());
+        assert_eq!(Err(Error::InvalidKey), cmac::<E>(&key[1..], message));
+
+        for chunk_size in &[1, 7, 16, 17] {
+            let mut mac = Cmac::<E>::new(key).unwrap();
+            for chunk in message.chunks(*chunk_size) {
+                mac.update(chunk);
+            }
+            assert_eq!(expected, &mac.finalize());
+        }
+
+        let mut mac = Cmac::<E>::new(key).unwrap();
+        mac.update(message);
+        assert_eq!(Ok(()), mac.verify(expected));
+        let bad_tag = &mut expected.clone();
+        bad_tag[0] ^= 1;
+        let mut mac = Cmac::<E>::new(key).unwrap();
+        mac.update(message);
+        assert_eq!(Err(MacError), mac.verify(bad_tag));
+    }
+
+    #[test]
+    fn test_subkeys() {
+        let mac = Cmac::<Aes128>::new(&h2b("2b7e151628aed2a6abf7158809cf4f3c")).unwrap();
+        assert_eq!(h2b("fbeed618357133667c85e08f7236a8de"), mac.k1);
+        assert_eq!(h2b("f7ddac306ae266ccf90bc11ee46d513b"), mac.k2);
+    }
+
+    #[test]
+    fn test_aes128_cmac() {
+        let key = "2b7e151628aed2a6abf7158809cf4f3c";
+        check::<Aes128>(key, 0, "bb1d6929e95937287fa37d129b756746");
+        check::<Aes128>(key, 16, "070a16b46b4d4144f79bdd9dd04a287c");
+        check::<Aes128>(key, 20, "7d85449ea6ea19c823a7bf78837dfade");
+        check::<Aes128>(key, 40, "dfa66747de9ae63030ca32611497c827");
+        check::<Aes128>(key, 64, "51f0bebf7e3b9d92fc49741779363cfe");
+    }
+
+    #[test]
+    fn test_aes192_cmac() {
+        let key = "8e73b0f7da0e6452c810f32b809079e562f8ead2522c6b7b";
+        check::<Aes192>(key, 0, "d17ddf46adaacde531cac483de7a9367");
+        check::<Aes192>(key, 16, "9e99a7bf31e710900662f65e617c5184");
+        check::<Aes192>(key, 40, "8a1de5be2eb31aad089a82e6ee908b0e");
+        check::<Aes192>(key, 64, "a1d5df0eed790f794d77589659f39a11");
+    }
+
+    #[test]
+    fn test_aes256_cmac() {
+        let key = "603deb1015ca71be2b73aef0857d77811f352c073b6108d72d9810a30914dff4";
+        check::<Aes256>(key, 0, "028962f61b7bf89efc6b551f4667d983");
+        check::<Aes256>(key, 16, "28a7023f452e8f82bd4bf28d8c37c35c");
+        check::<Aes256>(key, 40, "aaf3d8f1de5640c232f5b169b9c911e6");
+        check::<Aes256>(key, 64, "e1992190549f6ed5696a2c056c315410");
+    }
+}
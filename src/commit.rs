@@ -0,0 +1,209 @@
+//! Module for commitments, which fix a value now without revealing it until the commitment is
+//! opened later.
+//!
+//! Hash commitments are SHA-512(nonce || message) for a random 32-byte nonce, which opens the
+//! commitment together with the message. They commit to messages of any length and are binding
+//! as long as SHA-512 is collision resistant.
+//!
+//! Pedersen commitments are v G + r H over ristretto255 for a scalar value v and a random blinding
+//! scalar r, where G is the generator and H is hashed to the group, so no one knows its discrete
+//! logarithm. They hide v perfectly and bind under the discrete logarithm assumption. Adding
+//! commitments to v_1 and v_2 with blinding scalars r_1 and r_2 gives a commitment to v_1 + v_2
+//! with blinding scalar r_1 + r_2, so a sum can be opened without opening its terms.
+//!
+//! # Examples
+//!
+//! ```
+//! use crypto_pure::commit::PedersenCommitment;
+//! use crypto_pure::rand::SystemRandom;
+//! use crypto_pure::ristretto255::Scalar;
+//! let rng = &SystemRandom::new();
+//! let (bid_1, bid_2) = (Scalar::from(100), Scalar::from(250));
+//! let (commitment_1, blinding_1) = PedersenCommitment::commit(&bid_1, rng);
+//! let (commitment_2, blinding_2) = PedersenCommitment::commit(&bid_2, rng);
+//! let total = commitment_1 + commitment_2;
+//! assert!(total.verify(&Scalar::from(350), &(blinding_1 + blinding_2)));
+//! ```
+use crate::ct;
+use crate::rand::SecureRandom;
+use crate::ristretto255::{Point, Scalar, POINT_LEN};
+use crate::sha2::{HashFunction, Sha512};
+use core::ops::{Add, Sub};
+
+/// The length of the nonce that opens a hash commitment.
+pub const NONCE_LEN: usize = 32;
+
+/// The length of a hash commitment.
+pub const HASH_COMMITMENT_LEN: usize = 64;
+
+/// The domain separation tag for hashing to the generator H.
+const H_DST: &[u8] = b"CRYPTO_PURE-PEDERSEN-V01-CS01-with-ristretto255_XMD:SHA-512_R255MAP_RO_";
+
+/// Commits to a message with a nonce drawn from `rng`, outputting the commitment and the nonce
+/// that opens it.
+///
+/// # Examples
+///
+/// ```
+/// use crypto_pure::commit::{hash_commit, hash_verify};
+/// use crypto_pure::rand::SystemRandom;
+/// let (commitment, nonce) = hash_commit(b"message", &SystemRandom::new());
+/// assert!(hash_verify(&commitment, b"message", &nonce));
+/// ```
+pub fn hash_commit(
+    message: &[u8],
+    rng: &dyn SecureRandom,
+) -> ([u8; HASH_COMMITMENT_LEN], [u8; NONCE_LEN]) {
+    let mut nonce = [0; NONCE_LEN];
+    rng.fill(&mut nonce);
+    (hash(message, &nonce), nonce)
+}
+
+/// Checks whether a hash commitment opens to a message with a nonce.
+pub fn hash_verify(commitment: &[u8], message: &[u8], nonce: &[u8]) -> bool {
+    nonce.len() == NONCE_LEN && ct::constant_time_eq(commitment, &hash(message, nonce))
+}
+
+fn hash(message: &[u8], nonce: &[u8]) -> [u8; HASH_COMMITMENT_LEN] {
+    let mut hash_function = Sha512::default();
+    hash_function.update(nonce);
+    hash_function.update(message);
+    let mut commitment = [0; HASH_COMMITMENT_LEN];
+    hash_function.write_digest(&mut commitment);
+    commitment
+}
+
+/// A Pedersen commitment v G + r H to a scalar value v with a blinding scalar r.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct PedersenCommitment(Point);
+
+impl PedersenCommitment {
+    /// Commits to a value with a given blinding scalar, which must be uniformly random and secret
+    /// for the commitment to hide the value.
+    pub fn new(value: &Scalar, blinding: &Scalar) -> Self {
+        Self(Point::mul_base(value) + generator_h() * *blinding)
+    }
+
+    /// Commits to a value with a blinding scalar drawn from `rng`, outputting the commitment and
+    /// the blinding scalar that opens it.
+    pub fn commit(value: &Scalar, rng: &dyn SecureRandom) -> (Self, Scalar) {
+        let blinding = Scalar::random(rng);
+        (Self::new(value, &blinding), blinding)
+    }
+
+    /// Checks whether the commitment opens to a value with a blinding scalar, in time independent
+    /// of them.
+    pub fn verify(&self, value: &Scalar, blinding: &Scalar) -> bool {
+        self.0.ct_eq(&Self::new(value, blinding).0).into()
+    }
+
+    /// Decodes a commitment, or returns `None` if `bytes` is not the canonical encoding of a
+    /// ristretto255 element.
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        Point::from_bytes(bytes).map(Self)
+    }
+
+    /// Outputs the encoding of the commitment.
+    pub fn to_bytes(&self) -> [u8; POINT_LEN] {
+        self.0.to_bytes()
+    }
+}
+
+impl Add for PedersenCommitment {
+    type Output = Self;
+
+    /// Outputs a commitment to the sum of the values with the sum of the blinding scalars.
+    fn add(self, other: Self) -> Self {
+        Self(self.0 + other.0)
+    }
+}
+
+impl Sub for PedersenCommitment {
+    type Output = Self;
+
+    /// Outputs a commitment to the difference of the values with the difference of the blinding
+    /// scalars.
+    fn sub(self, other: Self) -> Self {
+        Self(self.0 - other.0)
+    }
+}
+
+/// Outputs the generator H, whose discrete logarithm with respect to G is unknown.
+fn generator_h() -> Point {
+    Point::hash_to_group(b"H", H_DST).expect("domain separation tag too long")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rand::SystemRandom;
+    use crate::test_helpers::*;
+
+    #[test]
+    fn test_hash_commit() {
+        let nonce = [0x24; NONCE_LEN];
+        let (commitment, opening) = hash_commit(b"message", &|buffer: &mut [u8]| {
+            buffer.copy_from_slice(&nonce)
+        });
+        // Checked against Python's hashlib
+        let expected = "19979ec6dd6519f0780d4c2e292de8ae3b08c6a1b0842df77a139b7056ca8677\
+                        fc4efc2ae20d0991fd80ad1940de01542c17f668834305495a14986bc7ed5d3f";
+        assert_eq!(h2b(expected), &commitment[..]);
+        assert_eq!(nonce, opening);
+        assert!(hash_verify(&commitment, b"message", &nonce));
+        assert!(!hash_verify(&commitment, b"massage", &nonce));
+        assert!(!hash_verify(
+            &commitment,
+            b"message",
+            &[0x24; NONCE_LEN - 1]
+        ));
+        assert!(!hash_verify(&commitment[1..], b"message", &nonce));
+        let commitment = &mut commitment.clone();
+        commitment[63] ^= 1;
+        assert!(!hash_verify(commitment, b"message", &nonce));
+
+        let rng = &SystemRandom::new();
+        let (commitment_1, nonce_1) = hash_commit(b"message", rng);
+        let (commitment_2, _) = hash_commit(b"message", rng);
+        assert_ne!(commitment_1[..], commitment_2[..]);
+        assert!(hash_verify(&commitment_1, b"message", &nonce_1));
+    }
+
+    #[test]
+    fn test_generator_h() {
+        // Checked against a Python implementation of RFC 9380 and RFC 9496
+        let expected = "36356e0c39fc45e6dac8987d517c877df140114f259c3fd4f9cf3ab9e4d2b362";
+        assert_eq!(h2b(expected), &generator_h().to_bytes()[..]);
+    }
+
+    #[test]
+    fn test_pedersen_commit() {
+        let commitment = PedersenCommitment::new(&Scalar::from(3), &Scalar::from(5));
+        let expected = "2eabfedec8c4c14be3e6bd18eea9d65126fd7a09642adf200cc8644e2a663927";
+        assert_eq!(h2b(expected), &commitment.to_bytes()[..]);
+        assert!(commitment.verify(&Scalar::from(3), &Scalar::from(5)));
+        assert!(!commitment.verify(&Scalar::from(3), &Scalar::from(6)));
+        assert!(!commitment.verify(&Scalar::from(4), &Scalar::from(5)));
+        // Swapping the value and blinding scalar gives a different commitment.
+        assert!(!commitment.verify(&Scalar::from(5), &Scalar::from(3)));
+
+        let decoded = PedersenCommitment::from_bytes(&commitment.to_bytes()).unwrap();
+        assert!(decoded == commitment);
+        assert!(PedersenCommitment::from_bytes(&[0xff; POINT_LEN]).is_none());
+    }
+
+    #[test]
+    fn test_pedersen_homomorphism() {
+        let rng = &SystemRandom::new();
+        let (v_1, v_2) = (Scalar::random(rng), Scalar::random(rng));
+        let (commitment_1, r_1) = PedersenCommitment::commit(&v_1, rng);
+        let (commitment_2, r_2) = PedersenCommitment::commit(&v_2, rng);
+        assert!(commitment_1.verify(&v_1, &r_1));
+        assert!(!commitment_1.verify(&v_1, &r_2));
+        assert!((commitment_1 + commitment_2).verify(&(v_1 + v_2), &(r_1 + r_2)));
+        assert!((commitment_1 - commitment_2).verify(&(v_1 - v_2), &(r_1 - r_2)));
+        assert!(
+            (commitment_1 + commitment_2) == PedersenCommitment::new(&(v_1 + v_2), &(r_1 + r_2))
+        );
+    }
+}
@@ -9,8 +9,7 @@ pub(crate) const D2: [i32; 10] = [
 ];
 
 pub(crate) const SQRTM1: [i32; 10] = [
-    -32595792, -7943725, 9377950, 3500415, 12389472, -272473, -25146209, -2005654, 326686,
-    11406482,
+    -32595792, -7943725, 9377950, 3500415, 12389472, -272473, -25146209, -2005654, 326686, 11406482,
 ];
 
 pub(crate) const BI: [[[i32; 10]; 3]; 8] = [
@@ -128,7 +127,7 @@ pub(crate) const BI: [[[i32; 10]; 3]; 8] = [
     ],
 ];
 
-pub(crate) const BASE: [[[[i32; 10]; 3]; 8]; 32] = [
+pub(crate) static BASE: [[[[i32; 10]; 3]; 8]; 32] = [
     [
         [
             [
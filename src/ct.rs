@@ -0,0 +1,254 @@
+//! Module for constant-time operations on secret data.
+//!
+//! Secret conditions are held in a `Choice` rather than a `bool`, and combined with bitwise
+//! operators, so that the compiler cannot see that they only take two values and turn the code
+//! using them into branches. A `Choice` should only become a `bool` once the result is public,
+//! such as whether a tag was valid.
+//!
+//! # Examples
+//!
+//! ```
+//! use crypto_pure::ct::{ct_eq, ConditionallySelectable};
+//! let choice = ct_eq(b"secret", b"secret") & !ct_eq(b"secret", b"public");
+//! assert!(bool::from(choice));
+//! assert_eq!(2, u32::conditional_select(&1, &2, choice));
+//! ```
+use core::hint::black_box;
+use core::ops::{BitAnd, BitAndAssign, BitOr, BitOrAssign, BitXor, Not};
+
+/// A secret boolean, stored as 0 or 1.
+#[derive(Clone, Copy, Debug)]
+pub struct Choice(u8);
+
+impl Choice {
+    /// Outputs the choice as 0 or 1.
+    pub fn unwrap_u8(self) -> u8 {
+        self.0
+    }
+
+    /// Outputs all ones for 1 and zero for 0.
+    pub(crate) fn mask(self) -> u32 {
+        u32::from(self.0).wrapping_neg()
+    }
+}
+
+impl From<u8> for Choice {
+    /// # Panics
+    ///
+    /// Panics in debug builds if `value` is not 0 or 1.
+    fn from(value: u8) -> Self {
+        debug_assert!(value <= 1);
+        Choice(black_box(value))
+    }
+}
+
+impl From<Choice> for bool {
+    fn from(choice: Choice) -> Self {
+        black_box(choice.0) != 0
+    }
+}
+
+impl BitAnd for Choice {
+    type Output = Self;
+
+    fn bitand(self, rhs: Self) -> Self {
+        Choice::from(self.0 & rhs.0)
+    }
+}
+
+impl BitAndAssign for Choice {
+    fn bitand_assign(&mut self, rhs: Self) {
+        *self = *self & rhs;
+    }
+}
+
+impl BitOr for Choice {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Choice::from(self.0 | rhs.0)
+    }
+}
+
+impl BitOrAssign for Choice {
+    fn bitor_assign(&mut self, rhs: Self) {
+        *self = *self | rhs;
+    }
+}
+
+impl BitXor for Choice {
+    type Output = Self;
+
+    fn bitxor(self, rhs: Self) -> Self {
+        Choice::from(self.0 ^ rhs.0)
+    }
+}
+
+impl Not for Choice {
+    type Output = Self;
+
+    fn not(self) -> Self {
+        Choice::from(self.0 ^ 1)
+    }
+}
+
+/// Checks whether two byte slices are equal in time independent of their contents.
+///
+/// Only the lengths of the slices, which are not treated as secret, affect the running time.
+///
+/// # Examples
+///
+/// ```
+/// use crypto_pure::ct::ct_eq;
+/// assert!(bool::from(ct_eq(b"tag", b"tag")));
+/// assert!(!bool::from(ct_eq(b"tag", b"taG")));
+/// assert!(!bool::from(ct_eq(b"tag", b"ta")));
+/// ```
+#[inline(never)]
+pub fn ct_eq(x: &[u8], y: &[u8]) -> Choice {
+    if x.len() != y.len() {
+        return Choice::from(0);
+    }
+    let difference = x.iter().zip(y).fold(0, |acc, (x, y)| acc | (x ^ y));
+    let difference = u32::from(black_box(difference));
+    Choice::from((difference.wrapping_sub(1) >> 8) as u8 & 1)
+}
+
+/// Checks whether two byte slices are equal in time independent of their contents, outputting
+/// the result as a `bool`.
+///
+/// # Examples
+///
+/// ```
+/// use crypto_pure::ct::constant_time_eq;
+/// assert!(constant_time_eq(b"tag", b"tag"));
+/// assert!(!constant_time_eq(b"tag", b"taG"));
+/// assert!(!constant_time_eq(b"tag", b"ta"));
+/// ```
+pub fn constant_time_eq(x: &[u8], y: &[u8]) -> bool {
+    ct_eq(x, y).into()
+}
+
+/// Checks whether `x` is less than `y`, for values less than 2^31, in time independent of the
+/// values.
+pub fn ct_lt(x: u32, y: u32) -> Choice {
+    Choice::from((black_box(x.wrapping_sub(y)) >> 31) as u8)
+}
+
+/// Outputs all ones if `x` is less than `y` and zero otherwise, for values less than 2^31, in time
+/// independent of the values.
+pub(crate) fn lt_mask(x: u32, y: u32) -> u32 {
+    ct_lt(x, y).mask()
+}
+
+/// Types whose values can be selected between in time independent of a `Choice`.
+pub trait ConditionallySelectable: Copy {
+    /// Outputs `a` if `choice` is 0 and `b` if it is 1.
+    fn conditional_select(a: &Self, b: &Self, choice: Choice) -> Self;
+
+    /// Replaces `self` with `other` if `choice` is 1.
+    fn conditional_assign(&mut self, other: &Self, choice: Choice) {
+        *self = Self::conditional_select(self, other, choice);
+    }
+
+    /// Swaps `a` and `b` if `choice` is 1.
+    fn conditional_swap(a: &mut Self, b: &mut Self, choice: Choice) {
+        let t = *a;
+        a.conditional_assign(b, choice);
+        b.conditional_assign(&t, choice);
+    }
+}
+
+macro_rules! impl_conditionally_selectable {
+    ($($t:ty),*) => {$(
+        impl ConditionallySelectable for $t {
+            fn conditional_select(a: &Self, b: &Self, choice: Choice) -> Self {
+                let mask = <$t>::from(choice.0).wrapping_neg();
+                a ^ (mask & (a ^ b))
+            }
+        }
+    )*};
+}
+
+impl_conditionally_selectable!(u8, u16, u32, u64, u128);
+
+impl<T: ConditionallySelectable, const N: usize> ConditionallySelectable for [T; N] {
+    fn conditional_select(a: &Self, b: &Self, choice: Choice) -> Self {
+        core::array::from_fn(|i| T::conditional_select(&a[i], &b[i], choice))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_constant_time_eq() {
+        assert!(constant_time_eq(b"", b""));
+        assert!(constant_time_eq(b"Hello!", b"Hello!"));
+        assert!(constant_time_eq(&[0x80, 0xff], &[0x80, 0xff]));
+        assert!(constant_time_eq(&[0; 64], &[0; 64]));
+
+        assert!(!constant_time_eq(b"ok", b"ko"));
+        assert!(!constant_time_eq(&[0x80, 0xff], &[0xff, 0x80]));
+        assert!(!constant_time_eq(&[0x01], &[0x00]));
+        assert!(!constant_time_eq(b"Hello!", b"Hello"));
+        assert!(!constant_time_eq(b"", b"\0"));
+    }
+
+    #[test]
+    fn test_choice() {
+        let (f, t) = (Choice::from(0), Choice::from(1));
+        assert!(!bool::from(f) && bool::from(t));
+        assert_eq!(1, (!f).unwrap_u8());
+        assert_eq!(0, (!t).unwrap_u8());
+        assert_eq!(
+            [0, 0, 0, 1],
+            [f & f, f & t, t & f, t & t].map(Choice::unwrap_u8)
+        );
+        assert_eq!(
+            [0, 1, 1, 1],
+            [f | f, f | t, t | f, t | t].map(Choice::unwrap_u8)
+        );
+        assert_eq!(
+            [0, 1, 1, 0],
+            [f ^ f, f ^ t, t ^ f, t ^ t].map(Choice::unwrap_u8)
+        );
+        assert_eq!(0, f.mask());
+        assert_eq!(!0, t.mask());
+    }
+
+    #[test]
+    fn test_ct_lt() {
+        assert!(bool::from(ct_lt(0, 1)));
+        assert!(!bool::from(ct_lt(1, 1)));
+        assert!(!bool::from(ct_lt(1 << 30, 0)));
+        assert!(bool::from(ct_lt(0, 1 << 30)));
+    }
+
+    #[test]
+    fn test_conditional_select() {
+        let (f, t) = (Choice::from(0), Choice::from(1));
+        assert_eq!(0x12, u8::conditional_select(&0x12, &0xfe, f));
+        assert_eq!(0xfe, u8::conditional_select(&0x12, &0xfe, t));
+        assert_eq!(u64::MAX, u64::conditional_select(&0, &u64::MAX, t));
+        assert_eq!([1, 2], <[u32; 2]>::conditional_select(&[1, 2], &[3, 4], f));
+        assert_eq!([3, 4], <[u32; 2]>::conditional_select(&[1, 2], &[3, 4], t));
+
+        let (mut a, mut b) = ([1_u16; 3], [2_u16; 3]);
+        ConditionallySelectable::conditional_swap(&mut a, &mut b, f);
+        assert_eq!(([1; 3], [2; 3]), (a, b));
+        ConditionallySelectable::conditional_swap(&mut a, &mut b, t);
+        assert_eq!(([2; 3], [1; 3]), (a, b));
+        a.conditional_assign(&[5; 3], t);
+        assert_eq!([5; 3], a);
+    }
+
+    #[test]
+    fn test_lt_mask() {
+        assert_eq!(!0, lt_mask(0, 1));
+        assert_eq!(0, lt_mask(1, 1));
+        assert_eq!(0, lt_mask(2, 1));
+        assert_eq!(!0, lt_mask(255, 256));
+    }
+}
@@ -0,0 +1,248 @@
+//! Module for CTR_DRBG with AES-256 and the derivation function, a deterministic random bit
+//! generator from NIST SP 800-90A Rev. 1.
+//!
+//! The DRBG expands entropy from an external source into any amount of pseudorandom output, with
+//! a security strength of 256 bits. Prediction resistance is obtained by calling `reseed` with
+//! fresh entropy before `generate`.
+//!
+//! # Examples
+//!
+//! ```
+//! use crypto_pure::ctr_drbg::CtrDrbg;
+//! # let entropy_input = &[0x42; 32];
+//! # let nonce = &[0x24; 16];
+//! let mut drbg = CtrDrbg::new(entropy_input, nonce, b"personalization string")?;
+//! let key = &mut [0; 32];
+//! drbg.generate(key, b"")?;
+//! # Ok::<(), crypto_pure::error::Error>(())
+//! ```
+use crate::aes::{Aes256, BlockCipher};
+use crate::error::Error;
+
+/// The length of the key and V together in bytes.
+const SEED_LEN: usize = 48;
+
+/// The maximum number of bytes per call to `generate`, 2^19 bits.
+pub const MAX_REQUEST_LEN: usize = 1 << 16;
+
+/// The number of calls to `generate` allowed between reseeds.
+pub(crate) const RESEED_INTERVAL: u64 = 1 << 48;
+
+/// An instance of AES-256 CTR_DRBG with the derivation function.
+pub struct CtrDrbg {
+    cipher: Aes256,
+    v: [u8; 16],
+    pub(crate) reseed_counter: u64,
+}
+
+impl CtrDrbg {
+    /// Instantiates the DRBG from an entropy input, a nonce and an optional personalization
+    /// string. The nonce should be at least 16 bytes that are unique per instantiation, such as
+    /// a timestamp or more entropy.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::InvalidLength` if `entropy_input.len()` is less than 32.
+    pub fn new(
+        entropy_input: &[u8],
+        nonce: &[u8],
+        personalization_string: &[u8],
+    ) -> Result<Self, Error> {
+        if entropy_input.len() < 32 {
+            return Err(Error::InvalidLength);
+        }
+        let mut drbg = Self {
+            cipher: Aes256::new(&[0; 32]).expect("invalid key length"),
+            v: [0; 16],
+            reseed_counter: 1,
+        };
+        drbg.update(&derive(&[entropy_input, nonce, personalization_string]));
+        Ok(drbg)
+    }
+
+    /// Reseeds the DRBG with a fresh entropy input and optional additional input.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::InvalidLength` if `entropy_input.len()` is less than 32.
+    pub fn reseed(&mut self, entropy_input: &[u8], additional_input: &[u8]) -> Result<(), Error> {
+        if entropy_input.len() < 32 {
+            return Err(Error::InvalidLength);
+        }
+        self.update(&derive(&[entropy_input, additional_input]));
+        self.reseed_counter = 1;
+        Ok(())
+    }
+
+    /// Fills a buffer with pseudorandom bytes given optional additional input.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::InvalidLength` if `output.len()` is more than `MAX_REQUEST_LEN`, or
+    /// with `Error::ReseedRequired` if the DRBG must be reseeded first.
+    pub fn generate(&mut self, output: &mut [u8], additional_input: &[u8]) -> Result<(), Error> {
+        if output.len() > MAX_REQUEST_LEN {
+            return Err(Error::InvalidLength);
+        }
+        if self.reseed_counter > RESEED_INTERVAL {
+            return Err(Error::ReseedRequired);
+        }
+        let additional_input = if additional_input.is_empty() {
+            [0; SEED_LEN]
+        } else {
+            let additional_input = derive(&[additional_input]);
+            self.update(&additional_input);
+            additional_input
+        };
+        for chunk in output.chunks_mut(16) {
+            let block = self.next_block();
+            chunk.copy_from_slice(&block[..chunk.len()]);
+        }
+        self.update(&additional_input);
+        self.reseed_counter += 1;
+        Ok(())
+    }
+
+    /// Increments V and encrypts it.
+    fn next_block(&mut self) -> [u8; 16] {
+        self.v = u128::from_be_bytes(self.v).wrapping_add(1).to_be_bytes();
+        self.cipher.encrypt_block(&self.v)
+    }
+
+    /// Replaces the key and V with the next output blocks XORed with the provided data.
+    fn update(&mut self, provided_data: &[u8; SEED_LEN]) {
+        let mut temp = [0; SEED_LEN];
+        for (chunk, data) in temp.chunks_mut(16).zip(provided_data.chunks(16)) {
+            let block = self.next_block();
+            for ((byte, block), data) in chunk.iter_mut().zip(&block).zip(data) {
+                *byte = block ^ data;
+            }
+        }
+        self.cipher = Aes256::new(&temp[..32]).expect("invalid key length");
+        self.v.copy_from_slice(&temp[32..]);
+    }
+}
+
+/// Derives seed material from the concatenation of several inputs with Block_Cipher_df.
+fn derive(inputs: &[&[u8]]) -> [u8; SEED_LEN] {
+    let len: usize = inputs.iter().map(|input| input.len()).sum();
+    let mut header = [0; 8];
+    header[..4].copy_from_slice(&(len as u32).to_be_bytes());
+    header[4..].copy_from_slice(&(SEED_LEN as u32).to_be_bytes());
+
+    let key: [u8; 32] = core::array::from_fn(|i| i as u8);
+    let cipher = Aes256::new(&key).expect("invalid key length");
+    let mut temp = [0; SEED_LEN];
+    for (i, chunk) in (0_u32..).zip(temp.chunks_mut(16)) {
+        // BCC of the counter block followed by S = L || N || inputs || 0x80, padded with zeros
+        let mut bcc = Bcc::new(&cipher);
+        let mut iv = [0; 16];
+        iv[..4].copy_from_slice(&i.to_be_bytes());
+        bcc.update(&iv);
+        bcc.update(&header);
+        inputs.iter().for_each(|input| bcc.update(input));
+        bcc.update(&[0x80]);
+        chunk.copy_from_slice(&bcc.finalize());
+    }
+
+    let cipher = Aes256::new(&temp[..32]).expect("invalid key length");
+    let mut x = [0; 16];
+    x.copy_from_slice(&temp[32..]);
+    let mut seed = [0; SEED_LEN];
+    for chunk in seed.chunks_mut(16) {
+        x = cipher.encrypt_block(&x);
+        chunk.copy_from_slice(&x);
+    }
+    seed
+}
+
+/// The BCC function, i.e. CBC-MAC, computed over input fed in pieces without copying it.
+struct Bcc<'a> {
+    cipher: &'a Aes256,
+    chain: [u8; 16],
+    offset: usize,
+}
+
+impl<'a> Bcc<'a> {
+    fn new(cipher: &'a Aes256) -> Self {
+        Self {
+            cipher,
+            chain: [0; 16],
+            offset: 0,
+        }
+    }
+
+    fn update(&mut self, input: &[u8]) {
+        for byte in input {
+            self.chain[self.offset] ^= byte;
+            self.offset += 1;
+            if self.offset == 16 {
+                self.chain = self.cipher.encrypt_block(&self.chain);
+                self.offset = 0;
+            }
+        }
+    }
+
+    /// Pads the input with zeros to a multiple of the block size and outputs the chaining value.
+    fn finalize(mut self) -> [u8; 16] {
+        if self.offset > 0 {
+            self.chain = self.cipher.encrypt_block(&self.chain);
+        }
+        self.chain
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::*;
+
+    fn check(entropy_input: &str, nonce: &str, entropy_input_reseed: &str, expected: &str) {
+        let mut drbg = CtrDrbg::new(&h2b(entropy_input), &h2b(nonce), b"").unwrap();
+        if !entropy_input_reseed.is_empty() {
+            drbg.reseed(&h2b(entropy_input_reseed), b"").unwrap();
+        }
+        let output = &mut [0; 64];
+        drbg.generate(output, b"").unwrap();
+        drbg.generate(output, b"").unwrap();
+        assert_eq!(h2b(expected), &output[..]);
+    }
+
+    #[test]
+    fn test_cavp() {
+        // CAVP CTR_DRBG, AES-256 use df, no prediction resistance, no reseed, COUNT = 0
+        let entropy_input = "36401940fa8b1fba91a1661f211d78a0b9389a74e5bccfece8d766af1a6d3b14";
+        let nonce = "496f25b0f1301b4f501be30380a137eb";
+        let expected = "5862eb38bd558dd978a696e6df164782ddd887e7e9a6c9f3f1fbafb78941b535\
+                        a64912dfd224c6dc7454e5250b3d97165e16260c2faf1cc7735cb75fb4f07e1d";
+        check(entropy_input, nonce, "", expected);
+
+        // CAVP CTR_DRBG, AES-256 use df, no prediction resistance, COUNT = 0
+        let entropy_input = "2d4c9f46b981c6a0b2b5d8c69391e569ff13851437ebc0fc00d616340252fed5";
+        let nonce = "0bf814b411f65ec4866be1abb59d3c32";
+        let entropy_input_reseed =
+            "93500fae4fa32b86033b7a7bac9d37e710dcc67ca266bc8607d665937766d207";
+        let expected = "322dd28670e75c0ea638f3cb68d6a9d6e50ddfd052b772a7b1d78263a7b8978b\
+                        6740c2b65a9550c3a76325866fa97e16d74006bc96f26249b9f0a90d076f08e5";
+        check(entropy_input, nonce, entropy_input_reseed, expected);
+    }
+
+    #[test]
+    fn test_reseed_required() {
+        let mut drbg = CtrDrbg::new(&[0; 32], &[0; 16], b"").unwrap();
+        drbg.reseed_counter = RESEED_INTERVAL;
+        drbg.generate(&mut [0; 16], b"").unwrap();
+        assert_eq!(Err(Error::ReseedRequired), drbg.generate(&mut [0; 16], b""));
+        drbg.reseed(&[0; 32], b"").unwrap();
+        drbg.generate(&mut [0; MAX_REQUEST_LEN], b"").unwrap();
+    }
+
+    #[test]
+    fn test_invalid_lengths() {
+        let error = Err(Error::InvalidLength);
+        assert_eq!(error, CtrDrbg::new(&[0; 31], &[0; 16], b"").map(|_| ()));
+        let mut drbg = CtrDrbg::new(&[0; 32], &[0; 16], b"").unwrap();
+        assert_eq!(error, drbg.reseed(&[0; 31], b""));
+        assert_eq!(error, drbg.generate(&mut [0; MAX_REQUEST_LEN + 1], b""));
+    }
+}
@@ -1,30 +1,73 @@
 //! Module for Curve25519 ECDH.
 //!
 //! Translated to Rust from Daniel J. Bernstein's public domain SUPERCOP `ref10` implementation.
+use crate::ct;
+use crate::error::Error;
 use core::ops::{AddAssign, MulAssign, SubAssign};
 
 /// Computes a public key for use in Curve25519 Diffie-Hellman key exchange.
 ///
-/// # Panics
+/// # Errors
 ///
-/// Panics if `secret_key.len()` is not equal to 32.
-pub fn gen_pk(secret_key: &[u8]) -> [u8; 32] {
+/// Fails with `Error::InvalidKey` if `secret_key.len()` is not equal to 32.
+pub fn gen_pk(secret_key: &[u8]) -> Result<[u8; 32], Error> {
+    if secret_key.len() != 32 {
+        return Err(Error::InvalidKey);
+    }
     let mut public_key = [0; 32];
     let mut basepoint = [0; 32];
     basepoint[0] = 9;
     scalarmult(&mut public_key, secret_key, &basepoint);
-    public_key
+    Ok(public_key)
 }
 
 /// Computes a Curve25519 Diffie-Hellman shared secret given a secret key and another's public key.
 ///
-/// # Panics
+/// # Errors
 ///
-/// Panics if `public_key.len()` or `secret_key.len()` is not equal to 32.
-pub fn dh(public_key: &[u8], secret_key: &[u8]) -> [u8; 32] {
+/// Fails with `Error::InvalidKey` if `public_key.len()` or `secret_key.len()` is not equal to 32.
+pub fn dh(public_key: &[u8], secret_key: &[u8]) -> Result<[u8; 32], Error> {
+    if public_key.len() != 32 || secret_key.len() != 32 {
+        return Err(Error::InvalidKey);
+    }
     let mut secret = [0; 32];
     scalarmult(&mut secret, secret_key, public_key);
-    secret
+    Ok(secret)
+}
+
+/// Computes the X25519 function (RFC 7748) given a secret key and another's public key, outputting
+/// the shared secret unless it is all zeros.
+///
+/// The secret key is clamped before use. An all-zero result means the public key was a point of
+/// small order, so the shared secret would not depend on the secret key.
+///
+/// # Errors
+///
+/// Fails with `Error::InvalidKey` if `secret_key.len()` or `public_key.len()` is not equal to 32,
+/// or if the shared secret is all zeros.
+///
+/// # Examples
+///
+/// ```
+/// use crypto_pure::curve25519::{gen_pk, x25519};
+/// use crypto_pure::error::Error;
+/// # let alice_secret_key = &[0x42; 32];
+/// # let bob_secret_key = &[0x24; 32];
+/// let alice_public_key = &gen_pk(alice_secret_key)?;
+/// let bob_public_key = &gen_pk(bob_secret_key)?;
+/// let alice_shared_secret = x25519(alice_secret_key, bob_public_key)?;
+/// let bob_shared_secret = x25519(bob_secret_key, alice_public_key)?;
+/// assert_eq!(alice_shared_secret, bob_shared_secret);
+/// assert_eq!(Err(Error::InvalidKey), x25519(alice_secret_key, &[0; 32]));
+/// # Ok::<(), Error>(())
+/// ```
+pub fn x25519(secret_key: &[u8], public_key: &[u8]) -> Result<[u8; 32], Error> {
+    let shared_secret = dh(public_key, secret_key)?;
+    if ct::constant_time_eq(&shared_secret, &[0; 32]) {
+        Err(Error::InvalidKey)
+    } else {
+        Ok(shared_secret)
+    }
 }
 
 #[inline(never)]
@@ -996,7 +1039,7 @@ impl<'a> AddAssign<&'a Fe> for Fe {
     }
 }
 
-impl<'a, 'b> AddAssign<&'a Fe> for &'b mut Fe {
+impl<'a> AddAssign<&'a Fe> for &mut Fe {
     fn add_assign(&mut self, rhs: &'a Fe) {
         for (l, r) in self.0.iter_mut().zip(&rhs.0) {
             *l += r;
@@ -1010,7 +1053,7 @@ impl<'a> MulAssign<&'a Fe> for Fe {
     }
 }
 
-impl<'a, 'b> MulAssign<&'a Fe> for &'b mut Fe {
+impl<'a> MulAssign<&'a Fe> for &mut Fe {
     fn mul_assign(&mut self, rhs: &'a Fe) {
         fe_mul!(&mut self.0, self.0, rhs.0);
     }
@@ -1024,7 +1067,7 @@ impl<'a> SubAssign<&'a Fe> for Fe {
     }
 }
 
-impl<'a, 'b> SubAssign<&'a Fe> for &'b mut Fe {
+impl<'a> SubAssign<&'a Fe> for &mut Fe {
     fn sub_assign(&mut self, rhs: &'a Fe) {
         for (l, r) in self.0.iter_mut().zip(&rhs.0) {
             *l -= r;
@@ -1086,8 +1129,8 @@ mod tests {
         ));
         let u = &mut k.clone();
         let x = &mut [0; 32];
-        // slow to do 1 mil iterations, or 1000 without --release
-        for i in 0..1 {
+        // slow to do 1 mil iterations
+        for i in 0..1000 {
             scalarmult(x, k, u);
             if i == 0 {
                 assert_eq!(
@@ -1117,9 +1160,39 @@ mod tests {
         let sk_b = "5dab087e624a8a4b79e17f8b83800ee66f3bb1292618b6fd1c2f8b27ff88e0eb";
         let pk_b = "de9edb7d7b7dc1b4d35b61c2ece435373f8343c85b78674dadfc7e146f882b4f";
         let k = "4a5d9d5ba4ce2de1728e3bf480350f25e07e21c947d19e3376f09b3c1e161742";
-        assert_eq!(h2b(pk_a), gen_pk(&h2b(sk_a)));
-        assert_eq!(h2b(pk_b), gen_pk(&h2b(sk_b)));
+        assert_eq!(h2b(pk_a), gen_pk(&h2b(sk_a)).unwrap());
+        assert_eq!(h2b(pk_b), gen_pk(&h2b(sk_b)).unwrap());
         check(k, sk_a, pk_b);
         check(k, sk_b, pk_a);
+        assert_eq!(
+            Ok(&h2b(k)[..]),
+            x25519(&h2b(sk_a), &h2b(pk_b)).as_ref().map(|x| &x[..])
+        );
+    }
+
+    #[test]
+    fn test_invalid_lengths() {
+        let sk = &[0x42; 32];
+        let pk = &gen_pk(&[0x24; 32]).unwrap();
+        assert_eq!(Err(Error::InvalidKey), gen_pk(&sk[1..]));
+        assert_eq!(Err(Error::InvalidKey), dh(&pk[1..], sk));
+        assert_eq!(Err(Error::InvalidKey), dh(pk, &[0x42; 33]));
+        assert_eq!(Err(Error::InvalidKey), x25519(&sk[1..], pk));
+        assert_eq!(Err(Error::InvalidKey), x25519(sk, b""));
+    }
+
+    #[test]
+    fn test_small_order() {
+        let sk = &h2b("77076d0a7318a57d3c16c17251b26645df4c2f87ebc0992ab177fba51db92c2a");
+        for u in &[
+            "0000000000000000000000000000000000000000000000000000000000000000",
+            "0100000000000000000000000000000000000000000000000000000000000000",
+            "e0eb7a7c3b41b8ae1656e3faf19fc46ada098deb9c32b1fd866205165f49b800",
+            "5f9c95bca3508c24b1d0b1559c83ef5b04445cc4581c8e86d8224eddd09f1157",
+            "ecffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff7f",
+        ] {
+            assert_eq!(Ok([0; 32]), dh(&h2b(u), sk));
+            assert_eq!(Err(Error::InvalidKey), x25519(sk, &h2b(u)));
+        }
     }
 }
@@ -0,0 +1,403 @@
+//! Module for X448 ECDH (RFC 7748) and arithmetic in the field of integers modulo
+//! p = 2^448 - 2^224 - 1, which is shared with Ed448.
+use crate::error::Error;
+use core::ops::{Add, Mul, Neg, Sub};
+
+/// Computes a public key for use in X448 Diffie-Hellman key exchange.
+///
+/// # Errors
+///
+/// Fails with `Error::InvalidKey` if `secret_key.len()` is not equal to 56.
+pub fn gen_pk(secret_key: &[u8]) -> Result<[u8; 56], Error> {
+    if secret_key.len() != 56 {
+        return Err(Error::InvalidKey);
+    }
+    let mut base = [0; 56];
+    base[0] = 5;
+    Ok(scalarmult(secret_key, &base))
+}
+
+/// Computes the X448 function (RFC 7748) given a secret key and another's public key, outputting
+/// the shared secret unless it is all zeros.
+///
+/// The secret key is clamped before use. An all-zero result means the public key was a point of
+/// small order, so the shared secret would not depend on the secret key.
+///
+/// # Errors
+///
+/// Fails with `Error::InvalidKey` if `secret_key.len()` or `public_key.len()` is not equal to 56,
+/// or if the shared secret is all zeros.
+///
+/// # Examples
+///
+/// ```
+/// use crypto_pure::curve448::{gen_pk, x448};
+/// use crypto_pure::error::Error;
+/// # let alice_secret_key = &[0x42; 56];
+/// # let bob_secret_key = &[0x24; 56];
+/// let alice_public_key = &gen_pk(alice_secret_key)?;
+/// let bob_public_key = &gen_pk(bob_secret_key)?;
+/// let alice_shared_secret = x448(alice_secret_key, bob_public_key)?;
+/// let bob_shared_secret = x448(bob_secret_key, alice_public_key)?;
+/// assert_eq!(&alice_shared_secret[..], &bob_shared_secret[..]);
+/// assert_eq!(Err(Error::InvalidKey), x448(alice_secret_key, &[0; 56]));
+/// # Ok::<(), Error>(())
+/// ```
+pub fn x448(secret_key: &[u8], public_key: &[u8]) -> Result<[u8; 56], Error> {
+    if secret_key.len() != 56 || public_key.len() != 56 {
+        return Err(Error::InvalidKey);
+    }
+    let shared_secret = scalarmult(secret_key, public_key);
+    let difference = shared_secret.iter().fold(0, |acc, byte| acc | byte);
+    if difference == 0 {
+        Err(Error::InvalidKey)
+    } else {
+        Ok(shared_secret)
+    }
+}
+
+/// The Montgomery ladder of RFC 7748, Section 5.
+fn scalarmult(secret_key: &[u8], u: &[u8]) -> [u8; 56] {
+    assert_eq!(56, secret_key.len());
+    assert_eq!(56, u.len());
+    let mut k = [0; 56];
+    k.copy_from_slice(secret_key);
+    k[0] &= 252;
+    k[55] |= 128;
+
+    let x_1 = Fe::from_bytes(u);
+    let mut x_2 = Fe::ONE;
+    let mut z_2 = Fe::ZERO;
+    let mut x_3 = x_1;
+    let mut z_3 = Fe::ONE;
+    let mut swap = 0;
+    for t in (0..448).rev() {
+        let k_t = u64::from(k[t / 8] >> (t % 8)) & 1;
+        swap ^= k_t;
+        Fe::cswap(&mut x_2, &mut x_3, swap);
+        Fe::cswap(&mut z_2, &mut z_3, swap);
+        swap = k_t;
+
+        let a = x_2 + z_2;
+        let aa = a.square();
+        let b = x_2 - z_2;
+        let bb = b.square();
+        let e = aa - bb;
+        let c = x_3 + z_3;
+        let d = x_3 - z_3;
+        let da = d * a;
+        let cb = c * b;
+        x_3 = (da + cb).square();
+        z_3 = x_1 * (da - cb).square();
+        x_2 = aa * bb;
+        z_2 = e * (aa + A24 * e);
+    }
+    Fe::cswap(&mut x_2, &mut x_3, swap);
+    Fe::cswap(&mut z_2, &mut z_3, swap);
+    (x_2 * z_2.invert()).to_bytes()
+}
+
+const MASK: u64 = (1 << 56) - 1;
+
+const A24: Fe = Fe([39081, 0, 0, 0, 0, 0, 0, 0]);
+
+/// An element of the field, stored as eight 56-bit limbs in little-endian order.
+///
+/// The limbs may exceed 56 bits by a few bits between operations, so an element is only reduced
+/// to its canonical form when encoded.
+#[derive(Clone, Copy)]
+pub(crate) struct Fe(pub(crate) [u64; 8]);
+
+impl Fe {
+    pub(crate) const ZERO: Self = Fe([0; 8]);
+    pub(crate) const ONE: Self = Fe([1, 0, 0, 0, 0, 0, 0, 0]);
+    const P: Self = Fe([MASK, MASK, MASK, MASK, MASK - 1, MASK, MASK, MASK]);
+
+    /// Decodes 56 little-endian bytes, reducing the value modulo p if needed.
+    pub(crate) fn from_bytes(bytes: &[u8]) -> Self {
+        let mut limbs = [0; 8];
+        for (limb, chunk) in limbs.iter_mut().zip(bytes.chunks(7)) {
+            let mut buffer = [0; 8];
+            buffer[..7].copy_from_slice(chunk);
+            *limb = u64::from_le_bytes(buffer);
+        }
+        Fe(limbs)
+    }
+
+    /// Encodes the canonical value as 56 little-endian bytes.
+    pub(crate) fn to_bytes(self) -> [u8; 56] {
+        // After three passes, all limbs are below 2^56, so the value is below 2^448 and subtracting
+        // p once makes it canonical.
+        let mut limbs = self.carry().carry().carry().0;
+
+        let mut difference = [0; 8];
+        let mut borrow = 0;
+        for i in 0..8 {
+            let limb = limbs[i].wrapping_sub(Self::P.0[i]).wrapping_sub(borrow);
+            borrow = limb >> 63;
+            difference[i] = limb & MASK;
+        }
+        let keep = borrow.wrapping_neg();
+        for (limb, difference) in limbs.iter_mut().zip(&difference) {
+            *limb = (*limb & keep) | (difference & !keep);
+        }
+
+        let mut bytes = [0; 56];
+        for (chunk, limb) in bytes.chunks_mut(7).zip(&limbs) {
+            chunk.copy_from_slice(&limb.to_le_bytes()[..7]);
+        }
+        bytes
+    }
+
+    /// Outputs whether the canonical value is odd, which is used as the sign of a coordinate.
+    pub(crate) fn is_negative(self) -> bool {
+        self.to_bytes()[0] & 1 == 1
+    }
+
+    pub(crate) fn is_zero(self) -> bool {
+        self.to_bytes().iter().fold(0, |acc, byte| acc | byte) == 0
+    }
+
+    /// Swaps `f` and `g` if `swap` is 1 and leaves them unchanged if it is 0, in constant time.
+    pub(crate) fn cswap(f: &mut Self, g: &mut Self, swap: u64) {
+        let mask = swap.wrapping_neg();
+        for (f, g) in f.0.iter_mut().zip(g.0.iter_mut()) {
+            let x = (*f ^ *g) & mask;
+            *f ^= x;
+            *g ^= x;
+        }
+    }
+
+    pub(crate) fn square(self) -> Self {
+        self * self
+    }
+
+    fn square_times(self, n: usize) -> Self {
+        (0..n).fold(self, |f, _| f.square())
+    }
+
+    /// Outputs this element raised to the power 2^222 - 1, the building block of inversion and
+    /// square roots.
+    fn pow_chain(self) -> Self {
+        let x2 = self.square() * self;
+        let x3 = x2.square() * self;
+        let x6 = x3.square_times(3) * x3;
+        let x12 = x6.square_times(6) * x6;
+        let x24 = x12.square_times(12) * x12;
+        let x30 = x24.square_times(6) * x6;
+        let x48 = x24.square_times(24) * x24;
+        let x96 = x48.square_times(48) * x48;
+        let x192 = x96.square_times(96) * x96;
+        x192.square_times(30) * x30
+    }
+
+    /// Computes the inverse as f^(p - 2), where p - 2 is 223 ones, a zero, 222 ones, a zero and a
+    /// one in binary. The inverse of zero is zero.
+    pub(crate) fn invert(self) -> Self {
+        let x222 = self.pow_chain();
+        let x223 = x222.square() * self;
+        (x223.square_times(223) * x222).square_times(2) * self
+    }
+
+    /// Computes a candidate square root as f^((p + 1) / 4) = f^((2^224 - 1) 2^222), which is a
+    /// square root if and only if one exists, since p is 3 modulo 4.
+    pub(crate) fn sqrt(self) -> Self {
+        let x223 = self.pow_chain().square() * self;
+        let x224 = x223.square() * self;
+        x224.square_times(222)
+    }
+
+    /// Propagates the carries so that all limbs are at most 56 bits, apart from a small excess in
+    /// limbs 0 and 4.
+    fn carry(self) -> Self {
+        let mut limbs = self.0;
+        for i in 0..7 {
+            limbs[i + 1] += limbs[i] >> 56;
+            limbs[i] &= MASK;
+        }
+        // 2^448 is congruent to 2^224 + 1.
+        let top = limbs[7] >> 56;
+        limbs[7] &= MASK;
+        limbs[0] += top;
+        limbs[4] += top;
+        Fe(limbs)
+    }
+}
+
+impl Add for Fe {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        let mut limbs = self.0;
+        for (limb, other) in limbs.iter_mut().zip(&other.0) {
+            *limb += other;
+        }
+        Fe(limbs).carry()
+    }
+}
+
+impl Sub for Fe {
+    type Output = Self;
+
+    /// Subtracts by adding 2p first, which is larger than any carried limb, so no limb underflows.
+    fn sub(self, other: Self) -> Self {
+        let mut limbs = self.0;
+        for ((limb, other), p) in limbs.iter_mut().zip(&other.0).zip(&Self::P.0) {
+            *limb = *limb + 2 * p - other;
+        }
+        Fe(limbs).carry()
+    }
+}
+
+impl Neg for Fe {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Self::ZERO - self
+    }
+}
+
+impl Mul for Fe {
+    type Output = Self;
+
+    fn mul(self, other: Self) -> Self {
+        let mut product = [0_u128; 15];
+        for (i, f) in self.0.iter().enumerate() {
+            for (j, g) in other.0.iter().enumerate() {
+                product[i + j] += u128::from(*f) * u128::from(*g);
+            }
+        }
+        // Fold the limbs from 2^448 up using 2^448 = 2^224 + 1, from the top down so that limbs
+        // folded into positions 8 and up are themselves folded.
+        for k in (8..15).rev() {
+            let limb = product[k];
+            product[k - 8] += limb;
+            product[k - 4] += limb;
+        }
+        let mut limbs = [0; 8];
+        let mut carry = 0;
+        for (limb, product) in limbs.iter_mut().zip(&product[..8]) {
+            let value = product + carry;
+            *limb = (value as u64) & MASK;
+            carry = value >> 56;
+        }
+        limbs[0] += (carry as u64) & MASK;
+        limbs[1] += (carry >> 56) as u64;
+        limbs[4] += (carry as u64) & MASK;
+        limbs[5] += (carry >> 56) as u64;
+        Fe(limbs).carry()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::*;
+
+    fn check(secret_key: &str, public_key: &str, expected: &str) {
+        let shared_secret = x448(&h2b(secret_key), &h2b(public_key)).unwrap();
+        assert_eq!(h2b(expected), &shared_secret[..]);
+    }
+
+    #[test]
+    fn test_x448() {
+        check(
+            "3d262fddf9ec8e88495266fea19a34d28882acef045104d0d1aae121700a779c\
+             984c24f8cdd78fbff44943eba368f54b29259a4f1c600ad3",
+            "06fce640fa3487bfda5f6cf2d5263f8aad88334cbd07437f020f08f9814dc031\
+             ddbdc38c19c6da2583fa5429db94ada18aa7a7fb4ef8a086",
+            "ce3e4ff95a60dc6697da1db1d85e6afbdf79b50a2412d7546d5f239fe14fbaad\
+             eb445fc66a01b0779d98223961111e21766282f73dd96b6f",
+        );
+        check(
+            "203d494428b8399352665ddca42f9de8fef600908e0d461cb021f8c538345dd7\
+             7c3e4806e25f46d3315c44e0a5b4371282dd2c8d5be3095f",
+            "0fbcc2f993cd56d3305b0b7d9e55d4c1a8fb5dbb52f8e9a1e9b6201b165d0158\
+             94e56c4d3570bee52fe205e28a78b91cdfbde71ce8d157db",
+            "884a02576239ff7a2f2f63b2db6a9ff37047ac13568e1e30fe63c4a7ad1b3ee3\
+             a5700df34321d62077e63633c575c1c954514e99da7c179d",
+        );
+    }
+
+    #[test]
+    fn test_iterations() {
+        let mut k = [0; 56];
+        k[0] = 5;
+        let mut u = k;
+        for i in 0..1000 {
+            let result = scalarmult(&k, &u);
+            u = k;
+            k = result;
+            if i == 0 {
+                let expected = "3f482c8a9f19b01e6c46ee9711d9dc14fd4bf67af30765c2ae2b846a\
+                                4d23a8cd0db897086239492caf350b51f833868b9bc2b3bca9cf4113";
+                assert_eq!(h2b(expected), &k[..]);
+            }
+        }
+        let expected = "aa3b4749d55b9daf1e5b00288826c467274ce3ebbdd5c17b975e09d4\
+                        af6c67cf10d087202db88286e2b79fceea3ec353ef54faa26e219f38";
+        assert_eq!(h2b(expected), &k[..]);
+    }
+
+    #[test]
+    fn test_gen_pk() {
+        let alice_secret_key = &h2b("9a8f4925d1519f5775cf46b04b5800d4ee9ee8bae8bc5565d498c28d\
+             d9c9baf574a9419744897391006382a6f127ab1d9ac2d8c0a598726b");
+        let alice_public_key = &gen_pk(alice_secret_key).unwrap();
+        let expected = "9b08f7cc31b7e3e67d22d5aea121074a273bd2b83de09c63faa73d2c\
+                        22c5d9bbc836647241d953d40c5b12da88120d53177f80e532c41fa0";
+        assert_eq!(h2b(expected), &alice_public_key[..]);
+
+        let bob_secret_key = &h2b("1c306a7ac2a0e2e0990b294470cba339e6453772b075811d8fad0d1d\
+             6927c120bb5ee8972b0d3e21374c9c921b09d1b0366f10b65173992d");
+        let bob_public_key = &gen_pk(bob_secret_key).unwrap();
+        let expected = "3eb7a829b0cd20f5bcfc0b599b6feccf6da4627107bdb0d4f345b430\
+                        27d8b972fc3e34fb4232a13ca706dcb57aec3dae07bdc1c67bf33609";
+        assert_eq!(h2b(expected), &bob_public_key[..]);
+
+        let expected = "07fff4181ac6cc95ec1c16a94a0f74d12da232ce40a77552281d282b\
+                        b60c0b56fd2464c335543936521c24403085d59a449a5037514a879d";
+        let shared_secret = x448(alice_secret_key, bob_public_key).unwrap();
+        assert_eq!(h2b(expected), &shared_secret[..]);
+        let shared_secret = x448(bob_secret_key, alice_public_key).unwrap();
+        assert_eq!(h2b(expected), &shared_secret[..]);
+    }
+
+    #[test]
+    fn test_small_order() {
+        let secret_key = &[0x42; 56];
+        let mut minus_one = [0xff; 56];
+        minus_one[0] = 0xfe;
+        minus_one[28] = 0xfe;
+        let mut one = [0; 56];
+        one[0] = 1;
+        for public_key in &[[0; 56], one, minus_one] {
+            assert_eq!(Err(Error::InvalidKey), x448(secret_key, public_key));
+        }
+    }
+
+    #[test]
+    fn test_invalid_lengths() {
+        let key = &[0x42; 56];
+        let error = Err(Error::InvalidKey);
+        assert_eq!(error, gen_pk(&key[1..]));
+        assert_eq!(error, x448(&key[1..], key));
+        assert_eq!(error, x448(key, &key[1..]));
+    }
+
+    #[test]
+    fn test_field() {
+        let minus_one = -Fe::ONE;
+        assert_eq!(
+            Fe::ONE.to_bytes()[..],
+            (minus_one * minus_one).to_bytes()[..]
+        );
+        let mut p = [0xff; 56];
+        p[28] = 0xfe;
+        assert!(Fe::from_bytes(&p).is_zero());
+        let f = Fe::from_bytes(&[0xab; 56]);
+        assert_eq!(Fe::ONE.to_bytes()[..], (f * f.invert()).to_bytes()[..]);
+        let root = f.square().sqrt();
+        assert_eq!(f.square().to_bytes()[..], root.square().to_bytes()[..]);
+    }
+}
@@ -0,0 +1,287 @@
+//! Module for finite-field Diffie-Hellman over the ffdhe2048, ffdhe3072 and ffdhe4096 groups of
+//! RFC 7919, whose moduli are safe primes p = 2 q + 1 with generator 2 of the subgroup of prime
+//! order q.
+//!
+//! Public keys and shared secrets are big-endian integers as long as p, with leading zeros kept
+//! as in TLS 1.3.
+//!
+//! # Examples
+//!
+//! ```
+//! use crypto_pure::dh::{agree, Group, KeyPair};
+//! use crypto_pure::rand::SystemRandom;
+//! let rng = &SystemRandom::new();
+//! let alice = KeyPair::generate(Group::Ffdhe2048, rng);
+//! let bob = KeyPair::generate(Group::Ffdhe2048, rng);
+//! let alice_shared_secret = agree(&alice, bob.public_key()).unwrap();
+//! let bob_shared_secret = agree(&bob, alice.public_key()).unwrap();
+//! assert_eq!(alice_shared_secret, bob_shared_secret);
+//! ```
+use crate::bigint::{from_be_bytes, resize, shr1, to_be_bytes, Modulus};
+use crate::rand::SecureRandom;
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// A finite-field group from RFC 7919.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Group {
+    Ffdhe2048,
+    Ffdhe3072,
+    Ffdhe4096,
+}
+
+impl Group {
+    /// Outputs the length of p, public keys and shared secrets in bytes.
+    pub fn size(self) -> usize {
+        self.prime().len()
+    }
+
+    fn prime(self) -> &'static [u8] {
+        match self {
+            Group::Ffdhe2048 => &FFDHE2048,
+            Group::Ffdhe3072 => &FFDHE3072,
+            Group::Ffdhe4096 => &FFDHE4096,
+        }
+    }
+
+    /// Outputs the length of secret exponents in bits, which RFC 7919 suggests as at least twice
+    /// the security level of the group.
+    fn exponent_bits(self) -> usize {
+        match self {
+            Group::Ffdhe2048 => 225,
+            Group::Ffdhe3072 => 275,
+            Group::Ffdhe4096 => 325,
+        }
+    }
+}
+
+/// A secret exponent x together with the public key 2^x mod p.
+#[derive(Clone)]
+pub struct KeyPair {
+    group: Group,
+    exponent: Vec<u64>,
+    public_key: Vec<u8>,
+}
+
+impl KeyPair {
+    /// Generates a key pair with a random secret exponent drawn from `rng`.
+    pub fn generate(group: Group, rng: &dyn SecureRandom) -> Self {
+        let bits = group.exponent_bits();
+        let len = bits.div_ceil(64);
+        let mut bytes = vec![0; 8 * len];
+        // Exponents of 0 and 1 are rejected, though they are unlikely to ever be drawn.
+        let exponent = loop {
+            rng.fill(&mut bytes);
+            let mut exponent = from_be_bytes(&bytes);
+            exponent[len - 1] &= u64::MAX >> (64 * len - bits);
+            if exponent[1..].iter().any(|limb| *limb != 0) || exponent[0] > 1 {
+                break exponent;
+            }
+        };
+        let p = Modulus::new(from_be_bytes(group.prime()));
+        let mut two = vec![0; p.len()];
+        two[0] = 2;
+        let mut public_key = vec![0; group.size()];
+        to_be_bytes(&exp(&p, &two, &exponent), &mut public_key);
+        Self {
+            group,
+            exponent,
+            public_key,
+        }
+    }
+
+    /// Outputs the group of the key pair.
+    pub fn group(&self) -> Group {
+        self.group
+    }
+
+    /// Outputs the public key.
+    pub fn public_key(&self) -> &[u8] {
+        &self.public_key
+    }
+}
+
+/// Computes the shared secret y^x mod p given a key pair and another's public key y, or outputs
+/// `None` if y is not an element of order q, i.e. if its length is wrong, it is not between 2
+/// and p - 2, or y^q is not 1.
+pub fn agree(key_pair: &KeyPair, public_key: &[u8]) -> Option<Vec<u8>> {
+    let group = key_pair.group;
+    if public_key.len() != group.size() {
+        return None;
+    }
+    let p = Modulus::new(from_be_bytes(group.prime()));
+    let y = resize(&from_be_bytes(public_key), p.len());
+    let mut one = vec![0; p.len()];
+    one[0] = 1;
+    // The power check also rules out 0 and p - 1, since q is odd.
+    if !p.contains(&y) || y == one {
+        return None;
+    }
+    let mut q = p.modulus().to_vec();
+    shr1(&mut q);
+    if exp(&p, &y, &q) != one {
+        return None;
+    }
+    let mut shared_secret = vec![0; group.size()];
+    to_be_bytes(&exp(&p, &y, &key_pair.exponent), &mut shared_secret);
+    Some(shared_secret)
+}
+
+/// Computes a^exponent mod p for a value a below p.
+fn exp(p: &Modulus, a: &[u64], exponent: &[u64]) -> Vec<u64> {
+    p.to_normal(&p.pow(&p.to_montgomery(a), exponent))
+}
+
+const FFDHE2048: [u8; 256] = [
+    0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xad, 0xf8, 0x54, 0x58, 0xa2, 0xbb, 0x4a, 0x9a,
+    0xaf, 0xdc, 0x56, 0x20, 0x27, 0x3d, 0x3c, 0xf1, 0xd8, 0xb9, 0xc5, 0x83, 0xce, 0x2d, 0x36, 0x95,
+    0xa9, 0xe1, 0x36, 0x41, 0x14, 0x64, 0x33, 0xfb, 0xcc, 0x93, 0x9d, 0xce, 0x24, 0x9b, 0x3e, 0xf9,
+    0x7d, 0x2f, 0xe3, 0x63, 0x63, 0x0c, 0x75, 0xd8, 0xf6, 0x81, 0xb2, 0x02, 0xae, 0xc4, 0x61, 0x7a,
+    0xd3, 0xdf, 0x1e, 0xd5, 0xd5, 0xfd, 0x65, 0x61, 0x24, 0x33, 0xf5, 0x1f, 0x5f, 0x06, 0x6e, 0xd0,
+    0x85, 0x63, 0x65, 0x55, 0x3d, 0xed, 0x1a, 0xf3, 0xb5, 0x57, 0x13, 0x5e, 0x7f, 0x57, 0xc9, 0x35,
+    0x98, 0x4f, 0x0c, 0x70, 0xe0, 0xe6, 0x8b, 0x77, 0xe2, 0xa6, 0x89, 0xda, 0xf3, 0xef, 0xe8, 0x72,
+    0x1d, 0xf1, 0x58, 0xa1, 0x36, 0xad, 0xe7, 0x35, 0x30, 0xac, 0xca, 0x4f, 0x48, 0x3a, 0x79, 0x7a,
+    0xbc, 0x0a, 0xb1, 0x82, 0xb3, 0x24, 0xfb, 0x61, 0xd1, 0x08, 0xa9, 0x4b, 0xb2, 0xc8, 0xe3, 0xfb,
+    0xb9, 0x6a, 0xda, 0xb7, 0x60, 0xd7, 0xf4, 0x68, 0x1d, 0x4f, 0x42, 0xa3, 0xde, 0x39, 0x4d, 0xf4,
+    0xae, 0x56, 0xed, 0xe7, 0x63, 0x72, 0xbb, 0x19, 0x0b, 0x07, 0xa7, 0xc8, 0xee, 0x0a, 0x6d, 0x70,
+    0x9e, 0x02, 0xfc, 0xe1, 0xcd, 0xf7, 0xe2, 0xec, 0xc0, 0x34, 0x04, 0xcd, 0x28, 0x34, 0x2f, 0x61,
+    0x91, 0x72, 0xfe, 0x9c, 0xe9, 0x85, 0x83, 0xff, 0x8e, 0x4f, 0x12, 0x32, 0xee, 0xf2, 0x81, 0x83,
+    0xc3, 0xfe, 0x3b, 0x1b, 0x4c, 0x6f, 0xad, 0x73, 0x3b, 0xb5, 0xfc, 0xbc, 0x2e, 0xc2, 0x20, 0x05,
+    0xc5, 0x8e, 0xf1, 0x83, 0x7d, 0x16, 0x83, 0xb2, 0xc6, 0xf3, 0x4a, 0x26, 0xc1, 0xb2, 0xef, 0xfa,
+    0x88, 0x6b, 0x42, 0x38, 0x61, 0x28, 0x5c, 0x97, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+];
+
+const FFDHE3072: [u8; 384] = [
+    0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xad, 0xf8, 0x54, 0x58, 0xa2, 0xbb, 0x4a, 0x9a,
+    0xaf, 0xdc, 0x56, 0x20, 0x27, 0x3d, 0x3c, 0xf1, 0xd8, 0xb9, 0xc5, 0x83, 0xce, 0x2d, 0x36, 0x95,
+    0xa9, 0xe1, 0x36, 0x41, 0x14, 0x64, 0x33, 0xfb, 0xcc, 0x93, 0x9d, 0xce, 0x24, 0x9b, 0x3e, 0xf9,
+    0x7d, 0x2f, 0xe3, 0x63, 0x63, 0x0c, 0x75, 0xd8, 0xf6, 0x81, 0xb2, 0x02, 0xae, 0xc4, 0x61, 0x7a,
+    0xd3, 0xdf, 0x1e, 0xd5, 0xd5, 0xfd, 0x65, 0x61, 0x24, 0x33, 0xf5, 0x1f, 0x5f, 0x06, 0x6e, 0xd0,
+    0x85, 0x63, 0x65, 0x55, 0x3d, 0xed, 0x1a, 0xf3, 0xb5, 0x57, 0x13, 0x5e, 0x7f, 0x57, 0xc9, 0x35,
+    0x98, 0x4f, 0x0c, 0x70, 0xe0, 0xe6, 0x8b, 0x77, 0xe2, 0xa6, 0x89, 0xda, 0xf3, 0xef, 0xe8, 0x72,
+    0x1d, 0xf1, 0x58, 0xa1, 0x36, 0xad, 0xe7, 0x35, 0x30, 0xac, 0xca, 0x4f, 0x48, 0x3a, 0x79, 0x7a,
+    0xbc, 0x0a, 0xb1, 0x82, 0xb3, 0x24, 0xfb, 0x61, 0xd1, 0x08, 0xa9, 0x4b, 0xb2, 0xc8, 0xe3, 0xfb,
+    0xb9, 0x6a, 0xda, 0xb7, 0x60, 0xd7, 0xf4, 0x68, 0x1d, 0x4f, 0x42, 0xa3, 0xde, 0x39, 0x4d, 0xf4,
+    0xae, 0x56, 0xed, 0xe7, 0x63, 0x72, 0xbb, 0x19, 0x0b, 0x07, 0xa7, 0xc8, 0xee, 0x0a, 0x6d, 0x70,
+    0x9e, 0x02, 0xfc, 0xe1, 0xcd, 0xf7, 0xe2, 0xec, 0xc0, 0x34, 0x04, 0xcd, 0x28, 0x34, 0x2f, 0x61,
+    0x91, 0x72, 0xfe, 0x9c, 0xe9, 0x85, 0x83, 0xff, 0x8e, 0x4f, 0x12, 0x32, 0xee, 0xf2, 0x81, 0x83,
+    0xc3, 0xfe, 0x3b, 0x1b, 0x4c, 0x6f, 0xad, 0x73, 0x3b, 0xb5, 0xfc, 0xbc, 0x2e, 0xc2, 0x20, 0x05,
+    0xc5, 0x8e, 0xf1, 0x83, 0x7d, 0x16, 0x83, 0xb2, 0xc6, 0xf3, 0x4a, 0x26, 0xc1, 0xb2, 0xef, 0xfa,
+    0x88, 0x6b, 0x42, 0x38, 0x61, 0x1f, 0xcf, 0xdc, 0xde, 0x35, 0x5b, 0x3b, 0x65, 0x19, 0x03, 0x5b,
+    0xbc, 0x34, 0xf4, 0xde, 0xf9, 0x9c, 0x02, 0x38, 0x61, 0xb4, 0x6f, 0xc9, 0xd6, 0xe6, 0xc9, 0x07,
+    0x7a, 0xd9, 0x1d, 0x26, 0x91, 0xf7, 0xf7, 0xee, 0x59, 0x8c, 0xb0, 0xfa, 0xc1, 0x86, 0xd9, 0x1c,
+    0xae, 0xfe, 0x13, 0x09, 0x85, 0x13, 0x92, 0x70, 0xb4, 0x13, 0x0c, 0x93, 0xbc, 0x43, 0x79, 0x44,
+    0xf4, 0xfd, 0x44, 0x52, 0xe2, 0xd7, 0x4d, 0xd3, 0x64, 0xf2, 0xe2, 0x1e, 0x71, 0xf5, 0x4b, 0xff,
+    0x5c, 0xae, 0x82, 0xab, 0x9c, 0x9d, 0xf6, 0x9e, 0xe8, 0x6d, 0x2b, 0xc5, 0x22, 0x36, 0x3a, 0x0d,
+    0xab, 0xc5, 0x21, 0x97, 0x9b, 0x0d, 0xea, 0xda, 0x1d, 0xbf, 0x9a, 0x42, 0xd5, 0xc4, 0x48, 0x4e,
+    0x0a, 0xbc, 0xd0, 0x6b, 0xfa, 0x53, 0xdd, 0xef, 0x3c, 0x1b, 0x20, 0xee, 0x3f, 0xd5, 0x9d, 0x7c,
+    0x25, 0xe4, 0x1d, 0x2b, 0x66, 0xc6, 0x2e, 0x37, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+];
+
+const FFDHE4096: [u8; 512] = [
+    0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xad, 0xf8, 0x54, 0x58, 0xa2, 0xbb, 0x4a, 0x9a,
+    0xaf, 0xdc, 0x56, 0x20, 0x27, 0x3d, 0x3c, 0xf1, 0xd8, 0xb9, 0xc5, 0x83, 0xce, 0x2d, 0x36, 0x95,
+    0xa9, 0xe1, 0x36, 0x41, 0x14, 0x64, 0x33, 0xfb, 0xcc, 0x93, 0x9d, 0xce, 0x24, 0x9b, 0x3e, 0xf9,
+    0x7d, 0x2f, 0xe3, 0x63, 0x63, 0x0c, 0x75, 0xd8, 0xf6, 0x81, 0xb2, 0x02, 0xae, 0xc4, 0x61, 0x7a,
+    0xd3, 0xdf, 0x1e, 0xd5, 0xd5, 0xfd, 0x65, 0x61, 0x24, 0x33, 0xf5, 0x1f, 0x5f, 0x06, 0x6e, 0xd0,
+    0x85, 0x63, 0x65, 0x55, 0x3d, 0xed, 0x1a, 0xf3, 0xb5, 0x57, 0x13, 0x5e, 0x7f, 0x57, 0xc9, 0x35,
+    0x98, 0x4f, 0x0c, 0x70, 0xe0, 0xe6, 0x8b, 0x77, 0xe2, 0xa6, 0x89, 0xda, 0xf3, 0xef, 0xe8, 0x72,
+    0x1d, 0xf1, 0x58, 0xa1, 0x36, 0xad, 0xe7, 0x35, 0x30, 0xac, 0xca, 0x4f, 0x48, 0x3a, 0x79, 0x7a,
+    0xbc, 0x0a, 0xb1, 0x82, 0xb3, 0x24, 0xfb, 0x61, 0xd1, 0x08, 0xa9, 0x4b, 0xb2, 0xc8, 0xe3, 0xfb,
+    0xb9, 0x6a, 0xda, 0xb7, 0x60, 0xd7, 0xf4, 0x68, 0x1d, 0x4f, 0x42, 0xa3, 0xde, 0x39, 0x4d, 0xf4,
+    0xae, 0x56, 0xed, 0xe7, 0x63, 0x72, 0xbb, 0x19, 0x0b, 0x07, 0xa7, 0xc8, 0xee, 0x0a, 0x6d, 0x70,
+    0x9e, 0x02, 0xfc, 0xe1, 0xcd, 0xf7, 0xe2, 0xec, 0xc0, 0x34, 0x04, 0xcd, 0x28, 0x34, 0x2f, 0x61,
+    0x91, 0x72, 0xfe, 0x9c, 0xe9, 0x85, 0x83, 0xff, 0x8e, 0x4f, 0x12, 0x32, 0xee, 0xf2, 0x81, 0x83,
+    0xc3, 0xfe, 0x3b, 0x1b, 0x4c, 0x6f, 0xad, 0x73, 0x3b, 0xb5, 0xfc, 0xbc, 0x2e, 0xc2, 0x20, 0x05,
+    0xc5, 0x8e, 0xf1, 0x83, 0x7d, 0x16, 0x83, 0xb2, 0xc6, 0xf3, 0x4a, 0x26, 0xc1, 0xb2, 0xef, 0xfa,
+    0x88, 0x6b, 0x42, 0x38, 0x61, 0x1f, 0xcf, 0xdc, 0xde, 0x35, 0x5b, 0x3b, 0x65, 0x19, 0x03, 0x5b,
+    0xbc, 0x34, 0xf4, 0xde, 0xf9, 0x9c, 0x02, 0x38, 0x61, 0xb4, 0x6f, 0xc9, 0xd6, 0xe6, 0xc9, 0x07,
+    0x7a, 0xd9, 0x1d, 0x26, 0x91, 0xf7, 0xf7, 0xee, 0x59, 0x8c, 0xb0, 0xfa, 0xc1, 0x86, 0xd9, 0x1c,
+    0xae, 0xfe, 0x13, 0x09, 0x85, 0x13, 0x92, 0x70, 0xb4, 0x13, 0x0c, 0x93, 0xbc, 0x43, 0x79, 0x44,
+    0xf4, 0xfd, 0x44, 0x52, 0xe2, 0xd7, 0x4d, 0xd3, 0x64, 0xf2, 0xe2, 0x1e, 0x71, 0xf5, 0x4b, 0xff,
+    0x5c, 0xae, 0x82, 0xab, 0x9c, 0x9d, 0xf6, 0x9e, 0xe8, 0x6d, 0x2b, 0xc5, 0x22, 0x36, 0x3a, 0x0d,
+    0xab, 0xc5, 0x21, 0x97, 0x9b, 0x0d, 0xea, 0xda, 0x1d, 0xbf, 0x9a, 0x42, 0xd5, 0xc4, 0x48, 0x4e,
+    0x0a, 0xbc, 0xd0, 0x6b, 0xfa, 0x53, 0xdd, 0xef, 0x3c, 0x1b, 0x20, 0xee, 0x3f, 0xd5, 0x9d, 0x7c,
+    0x25, 0xe4, 0x1d, 0x2b, 0x66, 0x9e, 0x1e, 0xf1, 0x6e, 0x6f, 0x52, 0xc3, 0x16, 0x4d, 0xf4, 0xfb,
+    0x79, 0x30, 0xe9, 0xe4, 0xe5, 0x88, 0x57, 0xb6, 0xac, 0x7d, 0x5f, 0x42, 0xd6, 0x9f, 0x6d, 0x18,
+    0x77, 0x63, 0xcf, 0x1d, 0x55, 0x03, 0x40, 0x04, 0x87, 0xf5, 0x5b, 0xa5, 0x7e, 0x31, 0xcc, 0x7a,
+    0x71, 0x35, 0xc8, 0x86, 0xef, 0xb4, 0x31, 0x8a, 0xed, 0x6a, 0x1e, 0x01, 0x2d, 0x9e, 0x68, 0x32,
+    0xa9, 0x07, 0x60, 0x0a, 0x91, 0x81, 0x30, 0xc4, 0x6d, 0xc7, 0x78, 0xf9, 0x71, 0xad, 0x00, 0x38,
+    0x09, 0x29, 0x99, 0xa3, 0x33, 0xcb, 0x8b, 0x7a, 0x1a, 0x1d, 0xb9, 0x3d, 0x71, 0x40, 0x00, 0x3c,
+    0x2a, 0x4e, 0xce, 0xa9, 0xf9, 0x8d, 0x0a, 0xcc, 0x0a, 0x82, 0x91, 0xcd, 0xce, 0xc9, 0x7d, 0xcf,
+    0x8e, 0xc9, 0xb5, 0x5a, 0x7f, 0x88, 0xa4, 0x6b, 0x4d, 0xb5, 0xa8, 0x51, 0xf4, 0x41, 0x82, 0xe1,
+    0xc6, 0x8a, 0x00, 0x7e, 0x5e, 0x65, 0x5f, 0x6a, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::*;
+
+    fn counting(buffer: &mut [u8]) {
+        (0..).zip(buffer).for_each(|(i, byte)| *byte = i as u8);
+    }
+
+    fn counting_down(buffer: &mut [u8]) {
+        (0..)
+            .zip(buffer)
+            .for_each(|(i, byte)| *byte = 255 - i as u8);
+    }
+
+    #[test]
+    fn test_agree() {
+        // Checked against Python's built-in modular exponentiation
+        let alice = KeyPair::generate(Group::Ffdhe2048, &counting);
+        let bob = KeyPair::generate(Group::Ffdhe2048, &counting_down);
+        let expected = "cf0aab49953b4b841d4e62c151e5e6027b2366492031053b458acd3d7b74247d\
+            dec77276a5c27d0393c9d42668cf9df43eebef5e39cc90cc88ab8ca98bad767d\
+            fea8e339ffc6d41bc270d8e32f6b3b08ed1ffaa368f55ca9ff211bcf85e92163\
+            9f6794a98c60e44340c95d58b9531dc63a593f4f2ac8f8ee5d41f68bd783f832\
+            ccb6522146e33011cd9fdb3546be5e4272ef2fb99f17c2ffc6e8ad2061c581bd\
+            47b5f9408b0257dcca3bae8756b32c9fc688269a142e6cee70c50a77c28f7066\
+            adf3143707936e7329842849db3cedbe0a0bd6e4a5dc875c86dea1811caa9692\
+            a58162d10afbfc020a77905e6544decad761060d1848c113406ae53bd534b101";
+        assert_eq!(h2b(expected), alice.public_key());
+        let expected = "6b57fb24c970fd4d9663400d720e271ee523d7625da7b3b8f20ce8cbf764b47a\
+            892953beb46a9d57d2bcb8512faf8834097009942fc0da42ad98a89f37b00381\
+            95119efb32c36c28e91f2c750a8d45e1ee4c6a65fd33d46c839ce1233a0cbf7c\
+            cb92f61d39ada1c54af6ceef437f4a58cd1578302ddca82ab9781969af4af2b9\
+            cf7b8a3b644810bf1839d81fdd4b3010b3b7eb7ef7b33699e0b12cd327c439f5\
+            5a013bec6932a350fe82c0a67234a45b9754a26b144e6e2126aeb7f7d2793732\
+            3f01637c649b9a63e64e30048d70db29922b15fd97d573ad70b256801c4fb02c\
+            374da8dd8a5a0b31cb4aade41f22d70969bb0bd2cf367d2cb144240ea6cc83c3";
+        let shared_secret = agree(&alice, bob.public_key()).unwrap();
+        assert_eq!(h2b(expected), shared_secret);
+        assert_eq!(shared_secret, agree(&bob, alice.public_key()).unwrap());
+
+        for &group in &[Group::Ffdhe3072, Group::Ffdhe4096] {
+            let alice = KeyPair::generate(group, &counting);
+            let bob = KeyPair::generate(group, &counting_down);
+            assert_eq!(group, alice.group());
+            assert_eq!(group.size(), alice.public_key().len());
+            let shared_secret = agree(&alice, bob.public_key()).unwrap();
+            assert_eq!(shared_secret, agree(&bob, alice.public_key()).unwrap());
+            assert!(agree(&alice, &bob.public_key()[1..]).is_none());
+        }
+    }
+
+    #[test]
+    fn test_invalid_public_keys() {
+        let key_pair = KeyPair::generate(Group::Ffdhe2048, &counting);
+        let p = &FFDHE2048;
+        let mut p_1 = p.to_vec();
+        p_1[255] -= 1;
+        let mut p_2 = p_1.clone();
+        p_2[255] -= 1;
+        let mut two = [0; 256];
+        two[255] = 2;
+        assert!(agree(&key_pair, &two).is_some());
+        // -2 is not a square modulo p, so it is outside the subgroup of order q.
+        for public_key in &[&[0; 256][..], &two[..255], p, &p_1, &p_2, &[0xff; 256]] {
+            assert!(agree(&key_pair, public_key).is_none());
+        }
+        let mut one = [0; 256];
+        one[255] = 1;
+        assert!(agree(&key_pair, &one).is_none());
+    }
+}
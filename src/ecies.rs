@@ -0,0 +1,269 @@
+//! Module for the Elliptic Curve Integrated Encryption Scheme, encrypting a message to a
+//! recipient's public key with an ephemeral ECDH key pair and AES-256-GCM.
+//!
+//! A ciphertext is the ephemeral public key followed by the sealed message, so it is
+//! `Curve::public_key_len()` + 16 bytes longer than the message:
+//!
+//! ```text
+//! ephemeral public key || AES-256-GCM ciphertext || tag
+//! ```
+//!
+//! The 32-byte AES key and 12-byte nonce are derived from the ECDH shared secret Z:
+//!
+//! * With P-256, public keys use the 65-byte uncompressed encoding, and the key and nonce are the
+//!   44-byte output of the ANSI X9.63 KDF with SHA-256 given Z, the x-coordinate of the shared
+//!   point, and the ephemeral public key as SharedInfo.
+//! * With X25519, public keys are 32 bytes, and the key and nonce are the 44-byte output of
+//!   HKDF-SHA256 given Z, no salt, and the ephemeral public key followed by the recipient's
+//!   public key as info.
+//!
+//! Since each message has a fresh ephemeral key, the derived key is used only once. The scheme
+//! doesn't authenticate the sender.
+//!
+//! # Examples
+//!
+//! ```
+//! use crypto_pure::curve25519;
+//! use crypto_pure::ecies::{self, Curve};
+//! use crypto_pure::rand::SystemRandom;
+//! # let secret_key = &[0x42; 32];
+//! let public_key = &curve25519::gen_pk(secret_key)?;
+//! let ciphertext = ecies::encrypt(Curve::X25519, public_key, b"message", &SystemRandom::new())?;
+//! assert_eq!(b"message", &ecies::decrypt(Curve::X25519, secret_key, &ciphertext)?[..]);
+//! # Ok::<(), crypto_pure::error::Error>(())
+//! ```
+use crate::aead::Aead;
+use crate::aes::Aes256;
+use crate::curve25519;
+use crate::error::Error;
+use crate::gcm::Gcm;
+use crate::hkdf::Hkdf;
+use crate::p256;
+use crate::rand::SecureRandom;
+use crate::secret::SecretKey;
+use crate::sha2::Sha256;
+use crate::x963;
+use alloc::vec::Vec;
+
+const SECRET_KEY_LEN: usize = 32;
+const KEY_LEN: usize = 32;
+const NONCE_LEN: usize = 12;
+
+/// The curve used for ECDH.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Curve {
+    /// NIST P-256, with the X9.63 KDF.
+    P256,
+    /// X25519, with HKDF.
+    X25519,
+}
+
+impl Curve {
+    /// Outputs the length in bytes of a public key, which starts each ciphertext.
+    pub fn public_key_len(self) -> usize {
+        match self {
+            Curve::P256 => 65,
+            Curve::X25519 => 32,
+        }
+    }
+
+    fn gen_pk(self, secret_key: &[u8], public_key: &mut [u8]) -> Result<(), Error> {
+        match self {
+            Curve::P256 => public_key.copy_from_slice(&p256::gen_pk(secret_key)?),
+            Curve::X25519 => public_key.copy_from_slice(&curve25519::gen_pk(secret_key)?),
+        }
+        Ok(())
+    }
+
+    fn ecdh(
+        self,
+        secret_key: &[u8],
+        public_key: &[u8],
+    ) -> Result<SecretKey<[u8; SECRET_KEY_LEN]>, Error> {
+        let shared_secret = match self {
+            Curve::P256 => p256::ecdh(secret_key, public_key)?,
+            Curve::X25519 => curve25519::x25519(secret_key, public_key)?,
+        };
+        Ok(SecretKey::new(shared_secret))
+    }
+}
+
+/// Encrypts a message to a recipient's public key, drawing the ephemeral secret key from `rng`.
+///
+/// # Errors
+///
+/// Fails with `Error::InvalidKey` if `recipient_public_key` is not a valid public key for `curve`.
+pub fn encrypt(
+    curve: Curve,
+    recipient_public_key: &[u8],
+    message: &[u8],
+    rng: &dyn SecureRandom,
+) -> Result<Vec<u8>, Error> {
+    let public_key_len = curve.public_key_len();
+    if recipient_public_key.len() != public_key_len {
+        return Err(Error::InvalidKey);
+    }
+    let mut ephemeral_secret_key = SecretKey::new([0; SECRET_KEY_LEN]);
+    let mut ciphertext =
+        Vec::with_capacity(public_key_len + message.len() + Gcm::<Aes256>::TAG_LEN);
+    ciphertext.resize(public_key_len, 0);
+    // Only P-256 rejects secret keys, with negligible probability for random ones.
+    loop {
+        rng.fill(&mut *ephemeral_secret_key);
+        if curve
+            .gen_pk(&*ephemeral_secret_key, &mut ciphertext)
+            .is_ok()
+        {
+            break;
+        }
+    }
+    let shared_secret = curve.ecdh(&*ephemeral_secret_key, recipient_public_key)?;
+    let okm = derive(curve, &*shared_secret, &ciphertext, recipient_public_key);
+    let (key, nonce) = okm.split_at(KEY_LEN);
+    ciphertext.extend_from_slice(&Gcm::<Aes256>::new(key)?.seal(nonce, b"", message)?);
+    Ok(ciphertext)
+}
+
+/// Decrypts a ciphertext with the recipient's secret key, verifying its tag in constant time.
+///
+/// # Errors
+///
+/// Fails with `Error::InvalidKey` if `recipient_secret_key` or the ephemeral public key is not
+/// valid, with `Error::InvalidLength` if `ciphertext` is too short, or with `Error::TagMismatch`
+/// if verification fails.
+pub fn decrypt(
+    curve: Curve,
+    recipient_secret_key: &[u8],
+    ciphertext: &[u8],
+) -> Result<Vec<u8>, Error> {
+    if recipient_secret_key.len() != SECRET_KEY_LEN {
+        return Err(Error::InvalidKey);
+    }
+    let public_key_len = curve.public_key_len();
+    if ciphertext.len() < public_key_len + Gcm::<Aes256>::TAG_LEN {
+        return Err(Error::InvalidLength);
+    }
+    let (ephemeral_public_key, sealed) = ciphertext.split_at(public_key_len);
+    let recipient_public_key = &mut [0; 65][..public_key_len];
+    curve.gen_pk(recipient_secret_key, recipient_public_key)?;
+    let shared_secret = curve.ecdh(recipient_secret_key, ephemeral_public_key)?;
+    let okm = derive(
+        curve,
+        &*shared_secret,
+        ephemeral_public_key,
+        recipient_public_key,
+    );
+    let (key, nonce) = okm.split_at(KEY_LEN);
+    Gcm::<Aes256>::new(key)?.open(nonce, b"", sealed)
+}
+
+/// Derives the AES key and nonce from the shared secret.
+fn derive(
+    curve: Curve,
+    shared_secret: &[u8],
+    ephemeral_public_key: &[u8],
+    recipient_public_key: &[u8],
+) -> SecretKey<[u8; KEY_LEN + NONCE_LEN]> {
+    let mut okm = SecretKey::new([0; KEY_LEN + NONCE_LEN]);
+    match curve {
+        Curve::P256 => x963::derive::<Sha256>(shared_secret, ephemeral_public_key, &mut *okm),
+        Curve::X25519 => {
+            let info = &[ephemeral_public_key, recipient_public_key].concat();
+            Hkdf::<Sha256>::extract(b"", shared_secret)
+                .expand(info, &mut *okm)
+                .expect("output too long");
+        }
+    }
+    okm
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::*;
+
+    const SECRET_KEY: [u8; 32] = [0x42; 32];
+
+    fn fixed_rng(dest: &mut [u8]) {
+        dest.copy_from_slice(&[0x24; 32]);
+    }
+
+    fn check(curve: Curve, public_key: &str, expected: &str) {
+        let public_key = &h2b(public_key);
+        let expected = h2b(expected);
+        let ciphertext = encrypt(curve, public_key, b"attack at dawn", &fixed_rng).unwrap();
+        assert_eq!(expected, ciphertext);
+        assert_eq!(
+            b"attack at dawn",
+            &decrypt(curve, &SECRET_KEY, &ciphertext).unwrap()[..]
+        );
+    }
+
+    // Generated with Python's cryptography
+    #[test]
+    fn test_vectors() {
+        check(
+            Curve::P256,
+            "043ad3861a95621392516bb593ef05583ed2e5866f5cb6260a3017237fd89b90af\
+             d0961c7e37075a6791a39c61f56295b02b6d26567b615e60aa41ee1c8e83388d",
+            "045e0b88955478752f20b52c86f7cf76a6a2f65e350293b09b5bc48afe12ef6d88\
+             88fce0bddd9dac2d7a522ca845cb400a11b415028a1f83e36d97104bd3505e29\
+             eb32a99706d1d50f62d0b33f55ec8b63179e76c3e24f754337c69ea76736",
+        );
+        check(
+            Curve::X25519,
+            "132c442be010fbd57e72603328aa76e71fccc1503aae219327d14d9c9993f472",
+            "04bcd2e0d00f2cce5fe8f1c6c2fbec5c07fa56e3aa5c88a5689975d88b3fce05\
+             4b434f35101fba786d48316408c9195699d003e6366e6c738985992ddde5",
+        );
+    }
+
+    #[test]
+    fn test_invalid() {
+        for &curve in &[Curve::P256, Curve::X25519] {
+            let public_key = &mut [0; 65][..curve.public_key_len()];
+            curve.gen_pk(&SECRET_KEY, public_key).unwrap();
+            let rng = &fixed_rng;
+            let mut ciphertext = encrypt(curve, public_key, b"", rng).unwrap();
+            assert_eq!(curve.public_key_len() + 16, ciphertext.len());
+            assert_eq!(
+                Err(Error::InvalidKey),
+                encrypt(curve, &public_key[1..], b"", rng)
+            );
+            assert_eq!(
+                Err(Error::InvalidKey),
+                decrypt(curve, &SECRET_KEY[1..], &ciphertext)
+            );
+            assert_eq!(
+                Err(Error::InvalidLength),
+                decrypt(curve, &SECRET_KEY, &ciphertext[1..])
+            );
+            assert_eq!(
+                Err(Error::TagMismatch),
+                decrypt(curve, &[0x43; 32], &ciphertext)
+            );
+            let last = ciphertext.len() - 1;
+            ciphertext[last] ^= 1;
+            assert_eq!(
+                Err(Error::TagMismatch),
+                decrypt(curve, &SECRET_KEY, &ciphertext)
+            );
+            // An ephemeral key that isn't a point, or has small order, is rejected.
+            for byte in &mut ciphertext[..curve.public_key_len()] {
+                *byte = 0;
+            }
+            assert_eq!(
+                Err(Error::InvalidKey),
+                decrypt(curve, &SECRET_KEY, &ciphertext)
+            );
+        }
+        assert_eq!(
+            Err(Error::InvalidKey),
+            encrypt(Curve::X25519, &[0; 32], b"", &fixed_rng)
+        );
+        assert_eq!(
+            Err(Error::InvalidKey),
+            decrypt(Curve::P256, &[0; 32], &[4; 81])
+        );
+    }
+}
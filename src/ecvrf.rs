@@ -0,0 +1,349 @@
+//! Module for ECVRF-EDWARDS25519-SHA512-TAI, the verifiable random function of RFC 9381 on the
+//! Ed25519 curve.
+//!
+//! A VRF is a hash keyed by a secret key whose outputs anyone can check with the public key:
+//! `prove` gives a proof for an input alpha, `proof_to_hash` turns the proof into the output beta,
+//! and `verify` checks the proof under the public key before doing the same. Without the secret
+//! key, beta looks random, yet each public key and input have only one valid beta. Keys are
+//! Ed25519 keys, as from `ed25519::gen_sk` and `ed25519::gen_pk`, but a key should not be used
+//! for both signatures and the VRF.
+//!
+//! # Examples
+//!
+//! ```
+//! use crypto_pure::ecvrf::{proof_to_hash, prove, verify};
+//! use crypto_pure::ed25519::{gen_pk, gen_sk};
+//! use crypto_pure::rand::SystemRandom;
+//! let secret_key = &gen_sk(&SystemRandom::new());
+//! let public_key = &gen_pk(secret_key)?;
+//! let proof = &prove(secret_key, b"alpha")?;
+//! let beta = proof_to_hash(proof)?;
+//! assert_eq!(Ok(beta), verify(public_key, b"alpha", proof));
+//! # Ok::<(), crypto_pure::error::Error>(())
+//! ```
+use crate::ct;
+use crate::ed25519::{
+    ge_add, ge_p1p1_to_p3, ge_p3_dbl, ge_p3_to_cached, ge_p3_tobytes, sc_muladd, sc_reduce,
+    GeCached, GeP1p1, GeP2, GeP3,
+};
+use crate::error::Error;
+use crate::sha2::{sha512, HashFunction, Sha512};
+
+/// The length of a proof Gamma || c || s.
+pub const PROOF_LEN: usize = 80;
+
+/// The length of an output beta.
+pub const OUTPUT_LEN: usize = 64;
+
+/// The suite_string identifying ECVRF-EDWARDS25519-SHA512-TAI.
+const SUITE: u8 = 3;
+
+/// The length of the challenge c.
+const C_LEN: usize = 16;
+
+/// Computes the proof for an input alpha under a secret key, as in RFC 9381, Section 5.1.
+///
+/// # Errors
+///
+/// Fails with `Error::InvalidKey` if `secret_key.len()` is not equal to 32, or with
+/// `Error::InvalidEncoding` in the negligibly likely case that no point is found when hashing
+/// alpha to the curve.
+pub fn prove(secret_key: &[u8], alpha: &[u8]) -> Result<[u8; PROOF_LEN], Error> {
+    if secret_key.len() != 32 {
+        return Err(Error::InvalidKey);
+    }
+    let mut az = sha512(secret_key);
+    az[0] &= 248;
+    az[31] &= 63;
+    az[31] |= 64;
+    let x = &az[..32];
+    let public_key = &mut [0; 32];
+    ge_p3_tobytes(public_key, &GeP3::from_scalarmult_base(&az));
+
+    let h = &encode_to_curve(public_key, alpha).ok_or(Error::InvalidEncoding)?;
+    let h_string = &mut [0; 32];
+    ge_p3_tobytes(h_string, h);
+    let gamma = &mut [0; 32];
+    ge_p3_tobytes(gamma, &GeP3::from_scalarmult(x, h));
+
+    let k = &mut [0; Sha512::DIGEST_SIZE];
+    let mut hash_function = Sha512::default();
+    hash_function.update(&az[32..]);
+    hash_function.update(h_string);
+    hash_function.write_digest(k);
+    sc_reduce(k);
+    let u = &mut [0; 32];
+    ge_p3_tobytes(u, &GeP3::from_scalarmult_base(k));
+    let v = &mut [0; 32];
+    ge_p3_tobytes(v, &GeP3::from_scalarmult(&k[..32], h));
+
+    let c = &challenge(&[public_key, h_string, gamma, u, v]);
+    let mut proof = [0; PROOF_LEN];
+    proof[..32].copy_from_slice(gamma);
+    proof[32..48].copy_from_slice(&c[..C_LEN]);
+    sc_muladd(&mut proof[48..], c, x, &k[..32]);
+    Ok(proof)
+}
+
+/// Computes the output beta of a proof, as in RFC 9381, Section 5.2. This doesn't check the
+/// proof, so the output is only trustworthy for proofs that passed `verify`.
+///
+/// # Errors
+///
+/// Fails with `Error::InvalidEncoding` if the proof is malformed.
+pub fn proof_to_hash(proof: &[u8]) -> Result<[u8; OUTPUT_LEN], Error> {
+    let (gamma, _, _) = decode_proof(proof).ok_or(Error::InvalidEncoding)?;
+    let gamma_string = &mut [0; 32];
+    ge_p3_tobytes(gamma_string, &mul_by_cofactor(&gamma));
+    let mut beta = [0; OUTPUT_LEN];
+    let mut hash_function = Sha512::default();
+    hash_function.update(&[SUITE, 3]);
+    hash_function.update(gamma_string);
+    hash_function.update(&[0]);
+    hash_function.write_digest(&mut beta);
+    Ok(beta)
+}
+
+/// Verifies a proof for an input alpha under a public key, as in RFC 9381, Section 5.3, and
+/// outputs beta. Public keys of small order are rejected, as with validate_key set.
+///
+/// # Errors
+///
+/// Fails with `Error::InvalidProof` if verification fails.
+pub fn verify(public_key: &[u8], alpha: &[u8], proof: &[u8]) -> Result<[u8; OUTPUT_LEN], Error> {
+    let y = decode_point(public_key).ok_or(Error::InvalidProof)?;
+    let mut small_order = [0; 32];
+    small_order[0] = 1;
+    let cofactor_y = &mut [0; 32];
+    ge_p3_tobytes(cofactor_y, &mul_by_cofactor(&y));
+    if cofactor_y == &small_order {
+        return Err(Error::InvalidProof);
+    }
+    let (gamma, c, s) = decode_proof(proof).ok_or(Error::InvalidProof)?;
+    let h = &encode_to_curve(public_key, alpha).ok_or(Error::InvalidProof)?;
+
+    // U = s B - c Y and V = s H - c Gamma
+    let minus_y = &negate(&y);
+    let u = GeP2::from_double_scalarmult_vartime(&c, minus_y, &s[..32]).to_bytes();
+    let t = &mut GeP1p1::default();
+    let minus_c_gamma = &mut GeCached::default();
+    ge_p3_to_cached(
+        minus_c_gamma,
+        &GeP3::from_scalarmult(&c[..32], &negate(&gamma)),
+    );
+    ge_add(t, &GeP3::from_scalarmult(&s[..32], h), minus_c_gamma);
+    let v_point = &mut GeP3::default();
+    ge_p1p1_to_p3(v_point, t);
+    let v = &mut [0; 32];
+    ge_p3_tobytes(v, v_point);
+
+    let h_string = &mut [0; 32];
+    ge_p3_tobytes(h_string, h);
+    let expected = challenge(&[public_key, h_string, &proof[..32], &u, v]);
+    if !ct::constant_time_eq(&expected[..C_LEN], &proof[32..48]) {
+        return Err(Error::InvalidProof);
+    }
+    proof_to_hash(proof)
+}
+
+/// Hashes an input alpha to a point with the try-and-increment method of RFC 9381, Section
+/// 5.4.1.1, salted with the public key, or outputs `None` if all 256 attempts fail.
+fn encode_to_curve(public_key: &[u8], alpha: &[u8]) -> Option<GeP3> {
+    let hash_string = &mut [0; Sha512::DIGEST_SIZE];
+    for counter in 0..=255 {
+        let mut hash_function = Sha512::default();
+        hash_function.update(&[SUITE, 1]);
+        hash_function.update(public_key);
+        hash_function.update(alpha);
+        hash_function.update(&[counter, 0]);
+        hash_function.write_digest(hash_string);
+        if let Some(h) = decode_point(&hash_string[..32]) {
+            return Some(mul_by_cofactor(&h));
+        }
+    }
+    None
+}
+
+/// Computes the challenge of RFC 9381, Section 5.4.3, from five encoded points, with the first
+/// C_LEN bytes of the output holding c and the rest zero.
+fn challenge(points: &[&[u8]; 5]) -> [u8; 32] {
+    let digest = &mut [0; Sha512::DIGEST_SIZE];
+    let mut hash_function = Sha512::default();
+    hash_function.update(&[SUITE, 2]);
+    points.iter().for_each(|point| hash_function.update(point));
+    hash_function.update(&[0]);
+    hash_function.write_digest(digest);
+    let mut c = [0; 32];
+    c[..C_LEN].copy_from_slice(&digest[..C_LEN]);
+    c
+}
+
+/// Splits a proof into Gamma, c and s, with the scalars zero-extended to 64 bytes, or outputs
+/// `None` if Gamma is not a point or s is not below the group order.
+fn decode_proof(proof: &[u8]) -> Option<(GeP3, [u8; 64], [u8; 64])> {
+    if proof.len() != PROOF_LEN {
+        return None;
+    }
+    let gamma = decode_point(&proof[..32])?;
+    let mut c = [0; 64];
+    c[..C_LEN].copy_from_slice(&proof[32..48]);
+    let mut s = [0; 64];
+    s[..32].copy_from_slice(&proof[48..]);
+    let reduced = &mut s.clone();
+    sc_reduce(reduced);
+    if reduced[..32] != s[..32] {
+        return None;
+    }
+    Some((gamma, c, s))
+}
+
+/// Decodes a point as in RFC 8032, Section 5.1.3, rejecting non-canonical encodings.
+fn decode_point(s: &[u8]) -> Option<GeP3> {
+    if s.len() != 32 {
+        return None;
+    }
+    let minus_point = GeP3::from_bytes_negate_vartime(s)?;
+    if minus_point.is_encoded_by(s) {
+        Some(negate(&minus_point))
+    } else {
+        None
+    }
+}
+
+fn negate(p: &GeP3) -> GeP3 {
+    let mut minus_p = *p;
+    minus_p.x.neg();
+    minus_p.t.neg();
+    minus_p
+}
+
+fn mul_by_cofactor(p: &GeP3) -> GeP3 {
+    let t = &mut GeP1p1::default();
+    let mut q = *p;
+    for _ in 0..3 {
+        ge_p3_dbl(t, &q);
+        ge_p1p1_to_p3(&mut q, t);
+    }
+    q
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ed25519::gen_pk;
+    use crate::test_helpers::*;
+
+    fn check(sk: &str, pk: &str, alpha: &str, pi: &str, beta: &str) {
+        let secret_key = &h2b(sk);
+        let public_key = &gen_pk(secret_key).unwrap();
+        assert_eq!(h2b(pk), public_key);
+        let alpha = &h2b(alpha);
+        let proof = &prove(secret_key, alpha).unwrap();
+        assert_eq!(h2b(pi), &proof[..]);
+        let beta = &h2b(beta)[..];
+        assert_eq!(beta, proof_to_hash(proof).unwrap());
+        assert_eq!(beta, verify(public_key, alpha, proof).unwrap());
+        assert_eq!(
+            Err(Error::InvalidProof),
+            verify(public_key, b"other", proof)
+        );
+    }
+
+    // Examples 16 to 18 from RFC 9381, Appendix B.3
+    #[test]
+    fn test_vectors() {
+        check(
+            "9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60",
+            "d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a",
+            "",
+            "8657106690b5526245a92b003bb079ccd1a92130477671f6fc01ad16f26f723f\
+             26f8a57ccaed74ee1b190bed1f479d9727d2d0f9b005a6e456a35d4fb0daab12\
+             68a1b0db10836d9826a528ca76567805",
+            "90cf1df3b703cce59e2a35b925d411164068269d7b2d29f3301c03dd757876ff\
+             66b71dda49d2de59d03450451af026798e8f81cd2e333de5cdf4f3e140fdd8ae",
+        );
+        check(
+            "4ccd089b28ff96da9db6c346ec114e0f5b8a319f35aba624da8cf6ed4fb8a6fb",
+            "3d4017c3e843895a92b70aa74d1b7ebc9c982ccf2ec4968cc0cd55f12af4660c",
+            "72",
+            "f3141cd382dc42909d19ec5110469e4feae18300e94f304590abdced48aed593\
+             3bf0864a62558b3ed7f2fea45c92a465301b3bbf5e3e54ddf2d935be3b67926d\
+             a3ef39226bbc355bdc9850112c8f4b02",
+            "eb4440665d3891d668e7e0fcaf587f1b4bd7fbfe99d0eb2211ccec90496310eb\
+             5e33821bc613efb94db5e5b54c70a848a0bef4553a41befc57663b56373a5031",
+        );
+        check(
+            "c5aa8df43f9f837bedb7442f31dcb7b166d38535076f094b85ce3a2e0b4458f7",
+            "fc51cd8e6218a1a38da47ed00230f0580816ed13ba3303ac5deb911548908025",
+            "af82",
+            "9bc0f79119cc5604bf02d23b4caede71393cedfbb191434dd016d30177ccbf80\
+             96bb474e53895c362d8628ee9f9ea3c0e52c7a5c691b6c18c9979866568add7a\
+             2d41b00b05081ed0f58ee5e31b3a970e",
+            "645427e5d00c62a23fb703732fa5d892940935942101e456ecca7bb217c61c45\
+             2118fec1219202a0edcf038bb6373241578be7217ba85a2687f7a0310b2df19f",
+        );
+    }
+
+    #[test]
+    fn test_verify_invalid() {
+        let secret_key = &[0x42; 32];
+        let public_key = &gen_pk(secret_key).unwrap();
+        let proof = &prove(secret_key, b"alpha").unwrap();
+        assert!(verify(public_key, b"alpha", proof).is_ok());
+        for secret_key in [&secret_key[1..], &[0x42; 33]] {
+            assert_eq!(Err(Error::InvalidKey), prove(secret_key, b"alpha"));
+        }
+        for i in [0, 31, 32, 47, 48, 79] {
+            let mut bad_proof = *proof;
+            bad_proof[i] ^= 1;
+            assert_eq!(
+                Err(Error::InvalidProof),
+                verify(public_key, b"alpha", &bad_proof)
+            );
+        }
+        assert_eq!(
+            Err(Error::InvalidProof),
+            verify(public_key, b"alpha", &proof[..79])
+        );
+        let other_key = &gen_pk(&[0x24; 32]).unwrap();
+        assert_eq!(Err(Error::InvalidProof), verify(other_key, b"alpha", proof));
+
+        // s + L satisfies the equations but is not below the group order
+        let l = h2b("edd3f55c1a631258d69cf7a2def9de1400000000000000000000000000000010");
+        let mut bad_proof = *proof;
+        let mut carry = 0;
+        for (s, l) in bad_proof[48..].iter_mut().zip(&l) {
+            let sum = u16::from(*s) + u16::from(*l) + carry;
+            *s = sum as u8;
+            carry = sum >> 8;
+        }
+        assert_eq!(
+            Err(Error::InvalidProof),
+            verify(public_key, b"alpha", &bad_proof)
+        );
+        assert_eq!(Err(Error::InvalidEncoding), proof_to_hash(&bad_proof));
+    }
+
+    #[test]
+    fn test_small_order_public_key() {
+        // The identity and a point of order 4, which any proof could otherwise be forged for
+        let identity = &h2b("0100000000000000000000000000000000000000000000000000000000000000");
+        let order_4 = &h2b("0000000000000000000000000000000000000000000000000000000000000000");
+        let proof = &prove(&[0x42; 32], b"alpha").unwrap();
+        for public_key in [identity, order_4] {
+            assert_eq!(
+                Err(Error::InvalidProof),
+                verify(public_key, b"alpha", proof)
+            );
+        }
+        // y = p, a non-canonical encoding of the order 4 point with y = 0
+        let mut non_canonical = [0xff; 32];
+        non_canonical[0] = 0xed;
+        non_canonical[31] = 0x7f;
+        assert_eq!(
+            Err(Error::InvalidProof),
+            verify(&non_canonical, b"alpha", proof)
+        );
+        assert_eq!(Err(Error::InvalidEncoding), proof_to_hash(&proof[..79]));
+    }
+}
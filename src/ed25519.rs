@@ -3,15 +3,41 @@
 //! Translated to Rust from Daniel J. Bernstein's public domain SUPERCOP `ref10` implementation.
 use crate::const_curve25519::{BASE, BI, D, D2, SQRTM1};
 use crate::curve25519::{load_3, load_4, verify_32, Fe};
+use crate::error::Error;
+use crate::rand::SecureRandom;
 use crate::sha2::{sha512, HashFunction as _, Sha512};
+use crate::sig::{self, Signer, Verifier};
+#[cfg(feature = "alloc")]
+use alloc::{vec, vec::Vec};
+
+/// Generates a secret key for use in the Ed25519 signature scheme, drawing 32 bytes from `rng`.
+///
+/// # Examples
+///
+/// ```
+/// use crypto_pure::ed25519::{gen_pk, gen_sk, sign, verify};
+/// use crypto_pure::rand::SystemRandom;
+/// let secret_key = &gen_sk(&SystemRandom::new());
+/// let public_key = &gen_pk(secret_key)?;
+/// let signature = &sign(b"message", secret_key, public_key)?;
+/// assert!(verify(b"message", signature, public_key));
+/// # Ok::<(), crypto_pure::error::Error>(())
+/// ```
+pub fn gen_sk(rng: &dyn SecureRandom) -> [u8; 32] {
+    let mut secret_key = [0; 32];
+    rng.fill(&mut secret_key);
+    secret_key
+}
 
 /// Computes a public key for use in the Ed25519 signature scheme.
 ///
-/// # Panics
+/// # Errors
 ///
-/// Panics if `secret_key.len()` is not equal to 32.
-pub fn gen_pk(secret_key: &[u8]) -> [u8; 32] {
-    assert_eq!(32, secret_key.len());
+/// Fails with `Error::InvalidKey` if `secret_key.len()` is not equal to 32.
+pub fn gen_pk(secret_key: &[u8]) -> Result<[u8; 32], Error> {
+    if secret_key.len() != 32 {
+        return Err(Error::InvalidKey);
+    }
     let mut public_key = [0; 32];
     let az = &mut sha512(secret_key);
     az[0] &= 248;
@@ -20,17 +46,18 @@ pub fn gen_pk(secret_key: &[u8]) -> [u8; 32] {
 
     let a = &GeP3::from_scalarmult_base(az);
     ge_p3_tobytes(&mut public_key, a);
-    public_key
+    Ok(public_key)
 }
 
 /// Signs a message using the Ed25519 signature scheme.
 ///
-/// # Panics
+/// # Errors
 ///
-/// Panics if `public_key.len()` or `secret_key.len()` is not equal to 32.
-pub fn sign(message: &[u8], secret_key: &[u8], public_key: &[u8]) -> [u8; 64] {
-    assert_eq!(32, secret_key.len());
-    assert_eq!(32, public_key.len());
+/// Fails with `Error::InvalidKey` if `public_key.len()` or `secret_key.len()` is not equal to 32.
+pub fn sign(message: &[u8], secret_key: &[u8], public_key: &[u8]) -> Result<[u8; 64], Error> {
+    if secret_key.len() != 32 || public_key.len() != 32 {
+        return Err(Error::InvalidKey);
+    }
     let mut az = sha512(secret_key);
     az[0] &= 248;
     az[31] &= 63;
@@ -57,17 +84,14 @@ pub fn sign(message: &[u8], secret_key: &[u8], public_key: &[u8]) -> [u8; 64] {
     sc_reduce(hram);
 
     sc_muladd(&mut signature[32..], hram, &az, nonce);
-    signature
+    Ok(signature)
 }
 
 /// Verifies whether a message was signed using the given Ed25519 public key.
 ///
-/// # Panics
-///
-/// Panics if `public_key.len()` is not equal to 32.
+/// Outputs `false` if the signature or public key has the wrong length.
 pub fn verify(message: &[u8], signature: &[u8], public_key: &[u8]) -> bool {
-    assert_eq!(32, public_key.len());
-    if signature.len() != 64 || (signature[63] & 224 != 0) {
+    if public_key.len() != 32 || signature.len() != 64 || !sc_is_canonical(&signature[32..]) {
         return false;
     }
     let a = match GeP3::from_bytes_negate_vartime(public_key) {
@@ -91,10 +115,147 @@ pub fn verify(message: &[u8], signature: &[u8], public_key: &[u8]) -> bool {
     verify_32(&rcheck, rcopy) == 0
 }
 
+/// Verifies whether every message was signed using the corresponding Ed25519 public key, about
+/// twice as fast as calling `verify` on each one.
+///
+/// The signatures are checked together as a random linear combination, with coefficients derived
+/// by hashing the whole batch. Unlike `verify`, this uses the cofactored verification equation, so
+/// the two may disagree on maliciously crafted signatures involving points of small order, but
+/// never on honestly generated ones. If the batch fails, `verify` can find the bad signatures.
+///
+/// Outputs `false` if any signature or public key has the wrong length, or if `messages`,
+/// `signatures` and `public_keys` have different lengths.
+///
+/// # Examples
+///
+/// ```
+/// use crypto_pure::ed25519::{gen_pk, gen_sk, sign, verify_batch};
+/// use crypto_pure::rand::SystemRandom;
+/// let rng = &SystemRandom::new();
+/// let (sk1, sk2) = (&gen_sk(rng), &gen_sk(rng));
+/// let (pk1, pk2) = (&gen_pk(sk1)?, &gen_pk(sk2)?);
+/// let sig1 = &sign(b"first", sk1, pk1)?;
+/// let sig2 = &sign(b"second", sk2, pk2)?;
+/// assert!(verify_batch(
+///     &[b"first", b"second"],
+///     &[sig1, sig2],
+///     &[pk1, pk2],
+/// ));
+/// # Ok::<(), crypto_pure::error::Error>(())
+/// ```
+#[cfg(feature = "alloc")]
+pub fn verify_batch(messages: &[&[u8]], signatures: &[&[u8]], public_keys: &[&[u8]]) -> bool {
+    if signatures.len() != messages.len() || public_keys.len() != messages.len() {
+        return false;
+    }
+    let mut points = Vec::with_capacity(2 * messages.len());
+    let mut hrams = Vec::with_capacity(messages.len());
+    let mut transcript = Sha512::default();
+    for ((message, signature), public_key) in messages.iter().zip(signatures).zip(public_keys) {
+        if public_key.len() != 32 || signature.len() != 64 || !sc_is_canonical(&signature[32..]) {
+            return false;
+        }
+        let (r, a) = match (
+            GeP3::from_bytes_negate_vartime(&signature[..32]),
+            GeP3::from_bytes_negate_vartime(public_key),
+        ) {
+            (Some(r), Some(a)) => (r, a),
+            _ => return false,
+        };
+        // `verify` compares against the canonical encoding of R, so reject any other
+        if !r.is_encoded_by(&signature[..32]) {
+            return false;
+        }
+
+        let h = &mut [0; Sha512::DIGEST_SIZE];
+        let mut hash_function = Sha512::default();
+        hash_function.update(&signature[..32]);
+        hash_function.update(public_key);
+        hash_function.update(message);
+        hash_function.write_digest(h);
+        sc_reduce(h);
+
+        transcript.update(signature);
+        transcript.update(public_key);
+        transcript.update(&h[..32]);
+        points.push(r);
+        points.push(a);
+        hrams.push(*h);
+    }
+    let seed = &mut [0; Sha512::DIGEST_SIZE];
+    transcript.write_digest(seed);
+
+    // Check that [8](sum(z_i * s_i) * B - sum(z_i * R_i) - sum(z_i * h_i * A_i)) is the identity
+    let mut b = [0; 64];
+    let mut scalars = Vec::with_capacity(points.len());
+    for (i, (signature, h)) in signatures.iter().zip(&hrams).enumerate() {
+        let z = &mut [0; Sha512::DIGEST_SIZE];
+        let mut hash_function = Sha512::default();
+        hash_function.update(seed);
+        hash_function.update(&(i as u64).to_le_bytes());
+        hash_function.write_digest(z);
+        z[16..].fill(0);
+
+        let s = &mut [0; 64];
+        s[..32].copy_from_slice(&signature[32..]);
+        let sum = &b.clone();
+        sc_muladd(&mut b[..32], z, s, sum);
+
+        let zh = &mut [0; 64];
+        sc_muladd(&mut zh[..32], z, h, &[0; 64]);
+        scalars.push(*z);
+        scalars.push(*zh);
+    }
+
+    let t = &mut GeP1p1::default();
+    let mut r = GeP2::from_multi_scalarmult_vartime(&b, &scalars, &points);
+    for _ in 0..3 {
+        ge_p2_dbl(t, &r);
+        ge_p1p1_to_p2(&mut r, t);
+    }
+    let mut identity = ZERO;
+    identity[0] = 1;
+    verify_32(&r.to_bytes(), &identity) == 0
+}
+
+/// The Ed25519 signature scheme.
+pub struct Ed25519;
+
+impl Verifier for Ed25519 {
+    const MAX_CONTEXT_LEN: usize = 0;
+
+    type PublicKey = [u8];
+
+    fn verify(
+        message: &[u8],
+        context: &[u8],
+        signature: &[u8],
+        public_key: &[u8],
+    ) -> Result<(), Error> {
+        sig::check_lengths::<Self>(context, public_key, 32)?;
+        sig::check_valid(verify(message, signature, public_key))
+    }
+}
+
+impl Signer for Ed25519 {
+    type SecretKey = [u8];
+    type Signature = [u8; 64];
+
+    fn sign(
+        message: &[u8],
+        context: &[u8],
+        secret_key: &[u8],
+        _rng: &dyn SecureRandom,
+    ) -> Result<[u8; 64], Error> {
+        sig::check_lengths::<Self>(context, secret_key, 32)?;
+        sign(message, secret_key, &gen_pk(secret_key)?)
+    }
+}
+
 const ZERO: [u8; 32] = [0; 32];
 
 impl Fe {
-    fn cmov_from(&mut self, g: &Fe, b: u32) {
+    pub(crate) fn cmov_from(&mut self, g: &Fe, b: u32) {
         let f = &mut self.0;
         let g = g.0;
         let f0 = f[0];
@@ -150,25 +311,25 @@ impl Fe {
         f[9] = f9 ^ x9;
     }
 
-    fn is_negative(&self) -> i32 {
+    pub(crate) fn is_negative(&self) -> i32 {
         let s = &mut [0; 32];
         self.write_bytes(s);
         i32::from(s[0] & 1)
     }
 
-    fn is_nonzero(&self) -> i32 {
+    pub(crate) fn is_nonzero(&self) -> i32 {
         let s = &mut [0; 32];
         self.write_bytes(s);
         verify_32(s, &ZERO)
     }
 
-    fn assign_neg(&mut self, f: &Fe) {
+    pub(crate) fn assign_neg(&mut self, f: &Fe) {
         for (l, &r) in self.0.iter_mut().zip(&f.0) {
             *l = -r;
         }
     }
 
-    fn neg(&mut self) {
+    pub(crate) fn neg(&mut self) {
         for l in &mut self.0 {
             *l = -*l;
         }
@@ -332,7 +493,7 @@ impl Fe {
     }
 }
 
-fn sc_muladd(s: &mut [u8], a: &[u8; 64], b: &[u8; 64], c: &[u8; 64]) {
+pub(crate) fn sc_muladd(s: &mut [u8], a: &[u8], b: &[u8], c: &[u8]) {
     assert_eq!(32, s.len());
     let a0 = 2097151 & load_3(a) as i64;
     let a1 = 2097151 & (load_4(&a[2..]) >> 5) as i64;
@@ -411,12 +572,6 @@ fn sc_muladd(s: &mut [u8], a: &[u8; 64], b: &[u8; 64], c: &[u8; 64]) {
     let mut carry14;
     let mut carry15;
     let mut carry16;
-    let carry17;
-    let carry18;
-    let carry19;
-    let carry20;
-    let carry21;
-    let carry22;
 
     s0 = c0 + a0 * b0;
     s1 = c1 + a0 * b1 + a1 * b0;
@@ -532,13 +687,13 @@ fn sc_muladd(s: &mut [u8], a: &[u8; 64], b: &[u8; 64], c: &[u8; 64]) {
     carry16 = (s16 + (1 << 20)) >> 21;
     s17 += carry16;
     s16 -= carry16 << 21;
-    carry18 = (s18 + (1 << 20)) >> 21;
+    let carry18 = (s18 + (1 << 20)) >> 21;
     s19 += carry18;
     s18 -= carry18 << 21;
-    carry20 = (s20 + (1 << 20)) >> 21;
+    let carry20 = (s20 + (1 << 20)) >> 21;
     s21 += carry20;
     s20 -= carry20 << 21;
-    carry22 = (s22 + (1 << 20)) >> 21;
+    let carry22 = (s22 + (1 << 20)) >> 21;
     s23 += carry22;
     s22 -= carry22 << 21;
 
@@ -566,13 +721,13 @@ fn sc_muladd(s: &mut [u8], a: &[u8; 64], b: &[u8; 64], c: &[u8; 64]) {
     carry15 = (s15 + (1 << 20)) >> 21;
     s16 += carry15;
     s15 -= carry15 << 21;
-    carry17 = (s17 + (1 << 20)) >> 21;
+    let carry17 = (s17 + (1 << 20)) >> 21;
     s18 += carry17;
     s17 -= carry17 << 21;
-    carry19 = (s19 + (1 << 20)) >> 21;
+    let carry19 = (s19 + (1 << 20)) >> 21;
     s20 += carry19;
     s19 -= carry19 << 21;
-    carry21 = (s21 + (1 << 20)) >> 21;
+    let carry21 = (s21 + (1 << 20)) >> 21;
     s22 += carry21;
     s21 -= carry21 << 21;
 
@@ -866,7 +1021,19 @@ fn sc_muladd(s: &mut [u8], a: &[u8; 64], b: &[u8; 64], c: &[u8; 64]) {
     s[31] = (s11 >> 17) as u8;
 }
 
-fn sc_reduce(s: &mut [u8; 64]) {
+/// The group order L, little-endian.
+const L: [u8; 32] = [
+    0xed, 0xd3, 0xf5, 0x5c, 0x1a, 0x63, 0x12, 0x58, 0xd6, 0x9c, 0xf7, 0xa2, 0xde, 0xf9, 0xde, 0x14,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x10,
+];
+
+/// Checks whether a little-endian scalar is less than L, as RFC 8032 requires of S so that
+/// signatures are not malleable. The scalar is public, so the comparison needn't be constant-time.
+fn sc_is_canonical(s: &[u8]) -> bool {
+    s.iter().rev().lt(L.iter().rev())
+}
+
+pub(crate) fn sc_reduce(s: &mut [u8; 64]) {
     let mut s0 = 2097151 & load_3(s) as i64;
     let mut s1 = 2097151 & (load_4(&s[2..]) >> 5) as i64;
     let mut s2 = 2097151 & (load_3(&s[5..]) >> 2) as i64;
@@ -903,11 +1070,6 @@ fn sc_reduce(s: &mut [u8; 64]) {
     let mut carry9;
     let mut carry10;
     let mut carry11;
-    let carry12;
-    let carry13;
-    let carry14;
-    let carry15;
-    let carry16;
 
     s11 += s23 * 666643;
     s12 += s23 * 470296;
@@ -966,13 +1128,13 @@ fn sc_reduce(s: &mut [u8; 64]) {
     carry10 = (s10 + (1 << 20)) >> 21;
     s11 += carry10;
     s10 -= carry10 << 21;
-    carry12 = (s12 + (1 << 20)) >> 21;
+    let carry12 = (s12 + (1 << 20)) >> 21;
     s13 += carry12;
     s12 -= carry12 << 21;
-    carry14 = (s14 + (1 << 20)) >> 21;
+    let carry14 = (s14 + (1 << 20)) >> 21;
     s15 += carry14;
     s14 -= carry14 << 21;
-    carry16 = (s16 + (1 << 20)) >> 21;
+    let carry16 = (s16 + (1 << 20)) >> 21;
     s17 += carry16;
     s16 -= carry16 << 21;
 
@@ -985,10 +1147,10 @@ fn sc_reduce(s: &mut [u8; 64]) {
     carry11 = (s11 + (1 << 20)) >> 21;
     s12 += carry11;
     s11 -= carry11 << 21;
-    carry13 = (s13 + (1 << 20)) >> 21;
+    let carry13 = (s13 + (1 << 20)) >> 21;
     s14 += carry13;
     s13 -= carry13 << 21;
-    carry15 = (s15 + (1 << 20)) >> 21;
+    let carry15 = (s15 + (1 << 20)) >> 21;
     s16 += carry15;
     s15 -= carry15 << 21;
 
@@ -1200,13 +1362,13 @@ fn sc_reduce(s: &mut [u8; 64]) {
 }
 
 #[derive(Default)]
-struct GeP2 {
-    x: Fe,
-    y: Fe,
-    z: Fe,
+pub(crate) struct GeP2 {
+    pub(crate) x: Fe,
+    pub(crate) y: Fe,
+    pub(crate) z: Fe,
 }
 
-impl<'a> From<&'a GeP3> for GeP2 {
+impl From<&GeP3> for GeP2 {
     fn from(p: &GeP3) -> Self {
         Self {
             x: p.x,
@@ -1217,43 +1379,19 @@ impl<'a> From<&'a GeP3> for GeP2 {
 }
 
 impl GeP2 {
-    fn from_double_scalarmult_vartime(a: &[u8; 64], ga: &GeP3, b: &[u8]) -> Self {
+    pub(crate) fn from_double_scalarmult_vartime(a: &[u8; 64], ga: &GeP3, b: &[u8]) -> Self {
         assert_eq!(32, b.len());
         let aslide = &mut [0; 256];
         let bslide = &mut [0; 256];
         let mut ai = [GeCached::default(); 8];
         let t = &mut GeP1p1::default();
         let u = &mut GeP3::default();
-        let a2 = &mut GeP3::default();
         let mut i: i16 = 255;
 
         slide(aslide, a);
         slide(bslide, b);
 
-        ge_p3_to_cached(&mut ai[0], ga);
-        ge_p3_dbl(t, ga);
-        ge_p1p1_to_p3(a2, t);
-        ge_add(t, a2, &ai[0]);
-        ge_p1p1_to_p3(u, t);
-        ge_p3_to_cached(&mut ai[1], u);
-        ge_add(t, a2, &ai[1]);
-        ge_p1p1_to_p3(u, t);
-        ge_p3_to_cached(&mut ai[2], u);
-        ge_add(t, a2, &ai[2]);
-        ge_p1p1_to_p3(u, t);
-        ge_p3_to_cached(&mut ai[3], u);
-        ge_add(t, a2, &ai[3]);
-        ge_p1p1_to_p3(u, t);
-        ge_p3_to_cached(&mut ai[4], u);
-        ge_add(t, a2, &ai[4]);
-        ge_p1p1_to_p3(u, t);
-        ge_p3_to_cached(&mut ai[5], u);
-        ge_add(t, a2, &ai[5]);
-        ge_p1p1_to_p3(u, t);
-        ge_p3_to_cached(&mut ai[6], u);
-        ge_add(t, a2, &ai[6]);
-        ge_p1p1_to_p3(u, t);
-        ge_p3_to_cached(&mut ai[7], u);
+        odd_multiples(&mut ai, ga);
 
         let mut r = Self::zero();
 
@@ -1293,7 +1431,159 @@ impl GeP2 {
         r
     }
 
-    fn to_bytes(&self) -> [u8; 32] {
+    /// Computes `b * B + sum(scalars[i] * points[i])`, where `B` is the base point and each scalar
+    /// is less than 2^253.
+    #[cfg(feature = "alloc")]
+    pub(crate) fn from_multi_scalarmult_vartime(
+        b: &[u8; 64],
+        scalars: &[[u8; 64]],
+        points: &[GeP3],
+    ) -> Self {
+        assert_eq!(scalars.len(), points.len());
+        if points.len() < 128 {
+            Self::straus_vartime(b, scalars, points)
+        } else {
+            Self::pippenger_vartime(b, scalars, points)
+        }
+    }
+
+    /// Computes `b * B + sum(scalars[i] * points[i])` using Straus's method, interleaving sliding
+    /// windows over every scalar.
+    #[cfg(feature = "alloc")]
+    fn straus_vartime(b: &[u8; 64], scalars: &[[u8; 64]], points: &[GeP3]) -> Self {
+        let bslide = &mut [0; 256];
+        let mut slides = vec![[0; 256]; points.len()];
+        let mut tables = vec![[GeCached::default(); 8]; points.len()];
+        let t = &mut GeP1p1::default();
+        let u = &mut GeP3::default();
+
+        slide(bslide, &b[..32]);
+        for (((aslide, ai), a), ga) in slides.iter_mut().zip(&mut tables).zip(scalars).zip(points) {
+            slide(aslide, &a[..32]);
+            odd_multiples(ai, ga);
+        }
+
+        let mut r = Self::zero();
+        let top = match (0..256)
+            .rev()
+            .find(|&i| bslide[i] != 0 || slides.iter().any(|aslide| aslide[i] != 0))
+        {
+            Some(top) => top,
+            None => return r,
+        };
+
+        for i in (0..=top).rev() {
+            ge_p2_dbl(t, &r);
+
+            for (aslide, ai) in slides.iter().zip(&tables) {
+                if aslide[i] > 0 {
+                    ge_p1p1_to_p3(u, t);
+                    ge_add(t, u, &ai[aslide[i] as usize / 2]);
+                } else if aslide[i] < 0 {
+                    ge_p1p1_to_p3(u, t);
+                    ge_sub(t, u, &ai[(-aslide[i]) as usize / 2]);
+                }
+            }
+
+            if bslide[i] > 0 {
+                ge_p1p1_to_p3(u, t);
+                ge_madd(t, u, &GePrecomp::from(BI[bslide[i] as usize / 2]));
+            } else if bslide[i] < 0 {
+                ge_p1p1_to_p3(u, t);
+                ge_msub(t, u, &GePrecomp::from(BI[(-bslide[i]) as usize / 2]));
+            }
+
+            ge_p1p1_to_p2(&mut r, t);
+        }
+        r
+    }
+
+    /// Computes `b * B + sum(scalars[i] * points[i])` using Pippenger's bucket method, which is
+    /// faster than Straus's method for many points.
+    #[cfg(feature = "alloc")]
+    fn pippenger_vartime(b: &[u8; 64], scalars: &[[u8; 64]], points: &[GeP3]) -> Self {
+        let c = match points.len() {
+            0..=191 => 5,
+            192..=511 => 6,
+            512..=1535 => 7,
+            _ => 8,
+        };
+        let windows = 253 / c + 1;
+
+        // Recode each scalar into signed digits in [-2^(c - 1), 2^(c - 1))
+        let mut digits = vec![0i16; windows * points.len()];
+        for (scalar, digits) in scalars.iter().zip(digits.chunks_mut(windows)) {
+            let mut carry = 0;
+            for (w, digit) in digits.iter_mut().enumerate() {
+                let bit = w * c;
+                let mut bits = u32::from(scalar[bit / 8]) | u32::from(scalar[bit / 8 + 1]) << 8;
+                bits = (bits >> (bit % 8)) & ((1 << c) - 1);
+                let d = bits as i16 + carry;
+                carry = (d + (1 << (c - 1))) >> c;
+                *digit = d - (carry << c);
+            }
+        }
+
+        let mut cached = vec![GeCached::default(); points.len()];
+        for (cached, point) in cached.iter_mut().zip(points) {
+            ge_p3_to_cached(cached, point);
+        }
+
+        let mut buckets = Vec::with_capacity(1 << (c - 1));
+        buckets.resize_with(1 << (c - 1), GeP3::zero);
+        let t = &mut GeP1p1::default();
+        let s = &mut GeP2::default();
+        let q = &mut GeCached::default();
+        let running = &mut GeP3::zero();
+        let sum = &mut GeP3::zero();
+        let mut h = GeP3::zero();
+
+        for w in (0..windows).rev() {
+            ge_p3_dbl(t, &h);
+            for _ in 1..c {
+                ge_p1p1_to_p2(s, t);
+                ge_p2_dbl(t, s);
+            }
+            ge_p1p1_to_p3(&mut h, t);
+
+            buckets.iter_mut().for_each(|bucket| *bucket = GeP3::zero());
+            for (j, cached) in cached.iter().enumerate() {
+                let d = digits[j * windows + w];
+                if d > 0 {
+                    let bucket = &mut buckets[d as usize - 1];
+                    ge_add(t, bucket, cached);
+                    ge_p1p1_to_p3(bucket, t);
+                } else if d < 0 {
+                    let bucket = &mut buckets[(-d) as usize - 1];
+                    ge_sub(t, bucket, cached);
+                    ge_p1p1_to_p3(bucket, t);
+                }
+            }
+
+            // sum(k * buckets[k - 1]) as a sum of running sums
+            *running = GeP3::zero();
+            *sum = GeP3::zero();
+            for bucket in buckets.iter().rev() {
+                ge_p3_to_cached(q, bucket);
+                ge_add(t, running, q);
+                ge_p1p1_to_p3(running, t);
+                ge_p3_to_cached(q, running);
+                ge_add(t, sum, q);
+                ge_p1p1_to_p3(sum, t);
+            }
+            ge_p3_to_cached(q, sum);
+            ge_add(t, &h, q);
+            ge_p1p1_to_p3(&mut h, t);
+        }
+
+        let mut r = Self::default();
+        ge_p3_to_cached(q, &GeP3::from_scalarmult_base(b));
+        ge_add(t, &h, q);
+        ge_p1p1_to_p2(&mut r, t);
+        r
+    }
+
+    pub(crate) fn to_bytes(&self) -> [u8; 32] {
         let mut s = [0; 32];
         let x = &mut Fe::default();
         let y = &mut Fe::default();
@@ -1315,16 +1605,16 @@ impl GeP2 {
     }
 }
 
-#[derive(Default)]
-struct GeP3 {
-    x: Fe,
-    y: Fe,
-    z: Fe,
-    t: Fe,
+#[derive(Clone, Copy, Default)]
+pub(crate) struct GeP3 {
+    pub(crate) x: Fe,
+    pub(crate) y: Fe,
+    pub(crate) z: Fe,
+    pub(crate) t: Fe,
 }
 
 impl GeP3 {
-    fn from_bytes_negate_vartime(s: &[u8]) -> Option<Self> {
+    pub(crate) fn from_bytes_negate_vartime(s: &[u8]) -> Option<Self> {
         let mut h = Self::default();
         let mut u = &mut Fe::default();
         let mut v = &mut Fe::default();
@@ -1368,26 +1658,22 @@ impl GeP3 {
         Some(h)
     }
 
-    fn from_scalarmult_base(a: &[u8; 64]) -> Self {
-        let mut e = [0; 64];
+    /// Returns whether `s` is the canonical encoding of this point or its negation.
+    pub(crate) fn is_encoded_by(&self, s: &[u8]) -> bool {
+        let canonical = &mut [0; 32];
+        self.y.write_bytes(canonical);
+        if self.x.is_nonzero() != 0 {
+            canonical[31] |= s[31] & 128;
+        }
+        verify_32(canonical, s) == 0
+    }
+
+    pub(crate) fn from_scalarmult_base(a: &[u8; 64]) -> Self {
+        let e = signed_radix16(a);
         let r = &mut GeP1p1::default();
         let s = &mut GeP2::default();
         let t = &mut GePrecomp::default();
 
-        for i in 0..32 {
-            e[2 * i] = a[i] as i8 & 15;
-            e[2 * i + 1] = (a[i] >> 4) as i8 & 15;
-        }
-
-        let mut carry = 0;
-        for e_i in e[..63].iter_mut() {
-            *e_i += carry;
-            carry = *e_i + 8;
-            carry >>= 4;
-            *e_i -= carry << 4;
-        }
-        e[63] += carry;
-
         let mut h = Self::zero();
         let mut i = 1;
         while i < 64 {
@@ -1416,7 +1702,36 @@ impl GeP3 {
         h
     }
 
-    fn zero() -> Self {
+    /// Computes `a * p` in constant time, for a scalar with `a[31] <= 127`.
+    pub(crate) fn from_scalarmult(a: &[u8], p: &GeP3) -> Self {
+        assert_eq!(32, a.len());
+        let e = signed_radix16(a);
+        let r = &mut GeP1p1::default();
+        let t = &mut GeCached::default();
+        let u = &mut GeP3::default();
+
+        let mut pi = [GeCached::default(); 8];
+        ge_p3_to_cached(&mut pi[0], p);
+        for i in 1..8 {
+            ge_add(r, p, &pi[i - 1]);
+            ge_p1p1_to_p3(u, r);
+            ge_p3_to_cached(&mut pi[i], u);
+        }
+
+        let mut h = Self::zero();
+        for &e_i in e.iter().rev() {
+            for _ in 0..4 {
+                ge_p3_dbl(r, &h);
+                ge_p1p1_to_p3(&mut h, r);
+            }
+            select_cached(t, &pi, e_i);
+            ge_add(r, &h, t);
+            ge_p1p1_to_p3(&mut h, r);
+        }
+        h
+    }
+
+    pub(crate) fn zero() -> Self {
         let mut h = Self::default();
         h.x.assign_zero();
         h.y.assign_one();
@@ -1426,8 +1741,27 @@ impl GeP3 {
     }
 }
 
+/// Recodes a scalar with `a[31] <= 127` into 64 signed radix-16 digits between -8 and 8.
+fn signed_radix16(a: &[u8]) -> [i8; 64] {
+    let mut e = [0; 64];
+    for i in 0..32 {
+        e[2 * i] = a[i] as i8 & 15;
+        e[2 * i + 1] = (a[i] >> 4) as i8 & 15;
+    }
+
+    let mut carry = 0;
+    for e_i in e[..63].iter_mut() {
+        *e_i += carry;
+        carry = *e_i + 8;
+        carry >>= 4;
+        *e_i -= carry << 4;
+    }
+    e[63] += carry;
+    e
+}
+
 #[derive(Default)]
-struct GeP1p1 {
+pub(crate) struct GeP1p1 {
     x: Fe,
     y: Fe,
     z: Fe,
@@ -1452,14 +1786,14 @@ impl From<[[i32; 10]; 3]> for GePrecomp {
 }
 
 #[derive(Clone, Copy, Default)]
-struct GeCached {
+pub(crate) struct GeCached {
     yplusx: Fe,
     yminusx: Fe,
     z: Fe,
     t2d: Fe,
 }
 
-fn ge_add(r: &mut GeP1p1, p: &GeP3, q: &GeCached) {
+pub(crate) fn ge_add(r: &mut GeP1p1, p: &GeP3, q: &GeCached) {
     let t0 = &mut Fe::default();
     r.x.assign_sum(&p.y, &p.x);
 
@@ -1484,6 +1818,22 @@ fn ge_add(r: &mut GeP1p1, p: &GeP3, q: &GeCached) {
     r.t.subtract_from(t0);
 }
 
+/// Fills `ai` with the cached forms of `a`, `3a`, `5a`, ..., `15a`.
+fn odd_multiples(ai: &mut [GeCached; 8], a: &GeP3) {
+    let t = &mut GeP1p1::default();
+    let u = &mut GeP3::default();
+    let a2 = &mut GeP3::default();
+
+    ge_p3_to_cached(&mut ai[0], a);
+    ge_p3_dbl(t, a);
+    ge_p1p1_to_p3(a2, t);
+    for i in 1..8 {
+        ge_add(t, a2, &ai[i - 1]);
+        ge_p1p1_to_p3(u, t);
+        ge_p3_to_cached(&mut ai[i], u);
+    }
+}
+
 fn slide(r: &mut [i8; 256], a: &[u8]) {
     let mut b;
 
@@ -1570,7 +1920,7 @@ fn ge_p1p1_to_p2(r: &mut GeP2, p: &GeP1p1) {
     r.z.assign_product(&p.z, &p.t);
 }
 
-fn ge_p1p1_to_p3(r: &mut GeP3, p: &GeP1p1) {
+pub(crate) fn ge_p1p1_to_p3(r: &mut GeP3, p: &GeP1p1) {
     r.x.assign_product(&p.x, &p.t);
     r.y.assign_product(&p.y, &p.z);
     r.z.assign_product(&p.z, &p.t);
@@ -1598,12 +1948,12 @@ fn ge_p2_dbl(r: &mut GeP1p1, p: &GeP2) {
     r.t -= &r.z;
 }
 
-fn ge_p3_dbl(r: &mut GeP1p1, p: &GeP3) {
+pub(crate) fn ge_p3_dbl(r: &mut GeP1p1, p: &GeP3) {
     let q = &GeP2::from(p);
     ge_p2_dbl(r, q);
 }
 
-fn ge_p3_tobytes(s: &mut [u8], h: &GeP3) {
+pub(crate) fn ge_p3_tobytes(s: &mut [u8], h: &GeP3) {
     let x = &mut Fe::default();
     let y = &mut Fe::default();
     let recip = &h.z.inverse();
@@ -1613,7 +1963,7 @@ fn ge_p3_tobytes(s: &mut [u8], h: &GeP3) {
     s[31] ^= (x.is_negative() << 7) as u8;
 }
 
-fn ge_p3_to_cached(r: &mut GeCached, p: &GeP3) {
+pub(crate) fn ge_p3_to_cached(r: &mut GeCached, p: &GeP3) {
     r.yplusx.assign_sum(&p.y, &p.x);
     r.yminusx.assign_difference(&p.y, &p.x);
     r.z = p.z;
@@ -1668,7 +2018,34 @@ fn select(t: &mut GePrecomp, pos: usize, b: i8) {
     cmov(t, &minust, bnegative);
 }
 
-fn ge_sub(r: &mut GeP1p1, p: &GeP3, q: &GeCached) {
+fn cmov_cached(t: &mut GeCached, u: &GeCached, b: u8) {
+    t.yplusx.cmov_from(&u.yplusx, u32::from(b));
+    t.yminusx.cmov_from(&u.yminusx, u32::from(b));
+    t.z.cmov_from(&u.z, u32::from(b));
+    t.t2d.cmov_from(&u.t2d, u32::from(b));
+}
+
+/// Sets `t` to `b` times the point whose multiples `pi` holds, in time independent of `b`.
+fn select_cached(t: &mut GeCached, pi: &[GeCached; 8], b: i8) {
+    let mut minust = GeCached::default();
+    let bnegative = negative(b);
+    let babs = b - ((bnegative.wrapping_neg() as i8 & b) << 1);
+
+    t.yplusx.assign_one();
+    t.yminusx.assign_one();
+    t.z.assign_one();
+    t.t2d.assign_zero();
+    for (i, pi_i) in pi.iter().enumerate() {
+        cmov_cached(t, pi_i, equal(babs, i as i8 + 1));
+    }
+    minust.yplusx = t.yminusx;
+    minust.yminusx = t.yplusx;
+    minust.z = t.z;
+    minust.t2d.assign_neg(&t.t2d);
+    cmov_cached(t, &minust, bnegative);
+}
+
+pub(crate) fn ge_sub(r: &mut GeP1p1, p: &GeP3, q: &GeCached) {
     let t0 = &mut Fe::default();
     r.x.assign_sum(&p.y, &p.x);
 
@@ -1703,7 +2080,7 @@ mod tests {
         let pk = &h2b(pk);
         let msg = &h2b(msg);
         let sig = h2b(sig);
-        let signature = sign(msg, sk, pk);
+        let signature = sign(msg, sk, pk).unwrap();
         assert_eq!(sig, signature.to_vec());
         assert!(verify(msg, &signature, pk))
         // TODO: check that a bad signature causes verification to fail
@@ -1778,4 +2155,190 @@ mod tests {
                    09351fc9ac90b3ecfdfbc7c66431e0303dca179c138ac17ad9bef1177331a704";
         check(sk, pk, msg, sig);
     }
+
+    #[cfg(feature = "alloc")]
+    type Batch = (Vec<Vec<u8>>, Vec<[u8; 64]>, Vec<[u8; 32]>);
+
+    #[cfg(feature = "alloc")]
+    fn batch(n: u8) -> Batch {
+        let mut messages = Vec::new();
+        let mut signatures = Vec::new();
+        let mut public_keys = Vec::new();
+        for i in 0..n {
+            let sk = &[i; 32];
+            let pk = gen_pk(sk).unwrap();
+            let message = vec![i; usize::from(i)];
+            signatures.push(sign(&message, sk, &pk).unwrap());
+            messages.push(message);
+            public_keys.push(pk);
+        }
+        (messages, signatures, public_keys)
+    }
+
+    #[cfg(feature = "alloc")]
+    fn check_batch(
+        messages: &[Vec<u8>],
+        signatures: &[[u8; 64]],
+        public_keys: &[[u8; 32]],
+    ) -> bool {
+        let messages: Vec<&[u8]> = messages.iter().map(|m| &m[..]).collect();
+        let signatures: Vec<&[u8]> = signatures.iter().map(|s| &s[..]).collect();
+        let public_keys: Vec<&[u8]> = public_keys.iter().map(|pk| &pk[..]).collect();
+        verify_batch(&messages, &signatures, &public_keys)
+    }
+
+    #[test]
+    fn test_non_canonical_s() {
+        let secret_key = &[0x42; 32];
+        let public_key = &gen_pk(secret_key).unwrap();
+        let mut signature = sign(b"message", secret_key, public_key).unwrap();
+        assert!(verify(b"message", &signature, public_key));
+        // S + L satisfies the verification equation too, but must be rejected so that signatures
+        // are not malleable.
+        let mut carry = 0;
+        for (s, l) in signature[32..].iter_mut().zip(&L) {
+            let sum = u16::from(*s) + u16::from(*l) + carry;
+            *s = sum as u8;
+            carry = sum >> 8;
+        }
+        assert_eq!(0, signature[63] & 224);
+        assert!(!verify(b"message", &signature, public_key));
+        #[cfg(feature = "alloc")]
+        assert!(!verify_batch(&[b"message"], &[&signature], &[public_key]));
+    }
+
+    #[test]
+    fn test_invalid_lengths() {
+        let secret_key = &[0x42; 32];
+        let public_key = &gen_pk(secret_key).unwrap();
+        let signature = &sign(b"message", secret_key, public_key).unwrap();
+        assert!(!verify(b"message", &signature[..63], public_key));
+        assert!(!verify(b"message", signature, &public_key[..31]));
+        assert!(!verify(
+            b"message",
+            signature,
+            &[public_key, &[0][..]].concat()
+        ));
+        assert!(!verify(b"message", b"", b""));
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn test_verify_batch() {
+        let (messages, signatures, public_keys) = batch(70);
+        assert!(check_batch(&messages, &signatures, &public_keys));
+        assert!(check_batch(
+            &messages[..20],
+            &signatures[..20],
+            &public_keys[..20]
+        ));
+        assert!(check_batch(
+            &messages[..1],
+            &signatures[..1],
+            &public_keys[..1]
+        ));
+        assert!(verify_batch(&[], &[], &[]));
+
+        let pk = &h2b("3d4017c3e843895a92b70aa74d1b7ebc9c982ccf2ec4968cc0cd55f12af4660c");
+        let sig = &h2b(
+            "92a009a9f0d4cab8720e820b5f642540a2b27b5416503f8fb3762223ebdb69da\
+             085ac1e43e15996e458f3613d0f11d8c387b2eaeb4302aeeb00d291612bb0c00",
+        );
+        assert!(verify_batch(
+            &[&[0x72], b""],
+            &[sig, &signatures[0]],
+            &[pk, &public_keys[0]]
+        ));
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn test_verify_batch_invalid() {
+        for n in [20, 70] {
+            let (messages, signatures, public_keys) = batch(n);
+            for i in [0, 7, usize::from(n) - 1] {
+                let mut bad_messages = messages.clone();
+                bad_messages[i].push(0);
+                assert!(!check_batch(&bad_messages, &signatures, &public_keys));
+
+                for j in [0, 31, 32, 62] {
+                    let mut bad_signatures = signatures.clone();
+                    bad_signatures[i][j] ^= 1;
+                    assert!(!check_batch(&messages, &bad_signatures, &public_keys));
+                }
+
+                let mut bad_public_keys = public_keys.clone();
+                bad_public_keys[i] = public_keys[(i + 1) % public_keys.len()];
+                assert!(!check_batch(&messages, &signatures, &bad_public_keys));
+            }
+        }
+
+        let (messages, signatures, public_keys) = batch(20);
+        // Swapping signatures between messages is caught
+        let mut bad_signatures = signatures.clone();
+        bad_signatures.swap(3, 4);
+        assert!(!check_batch(&messages, &bad_signatures, &public_keys));
+
+        // s must not have its top three bits set
+        let mut bad_signatures = signatures.clone();
+        bad_signatures[5][63] |= 32;
+        assert!(!check_batch(&messages, &bad_signatures, &public_keys));
+
+        // R must be canonically encoded
+        let mut bad_signatures = signatures.clone();
+        bad_signatures[5][..32].copy_from_slice(&[0xff; 32]);
+        assert!(!check_batch(&messages, &bad_signatures, &public_keys));
+        let identity = &h2b("0100000000000000000000000000000000000000000000000000000000000000");
+        let p_plus_one = &h2b("eeffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff7f");
+        let negative_zero =
+            &h2b("0100000000000000000000000000000000000000000000000000000000000080");
+        let point = GeP3::from_bytes_negate_vartime(identity).unwrap();
+        assert!(point.is_encoded_by(identity));
+        assert!(!point.is_encoded_by(p_plus_one));
+        assert!(!point.is_encoded_by(negative_zero));
+        let point = GeP3::from_bytes_negate_vartime(&public_keys[0]).unwrap();
+        assert!(point.is_encoded_by(&public_keys[0]));
+
+        let short = &[0; 63];
+        assert!(!verify_batch(&[b""], &[short], &[&public_keys[0]]));
+        let short = &public_keys[0][..31];
+        assert!(!verify_batch(&[&messages[0]], &[&signatures[0]], &[short]));
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn test_verify_batch_length_mismatch() {
+        let (messages, signatures, public_keys) = batch(2);
+        assert!(!check_batch(&messages[1..], &signatures, &public_keys));
+        assert!(!check_batch(&messages, &signatures[1..], &public_keys));
+        assert!(!check_batch(&messages, &signatures, &public_keys[1..]));
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn test_multi_scalarmult() {
+        let mut scalars = Vec::new();
+        let mut points = Vec::new();
+        for i in 0..70u8 {
+            let scalar = &mut sha512(&[i]);
+            sc_reduce(scalar);
+            scalar[32..].fill(0);
+            scalars.push(*scalar);
+            points.push(GeP3::from_bytes_negate_vartime(&gen_pk(&[i; 32]).unwrap()).unwrap());
+        }
+        let b = &mut sha512(b"base");
+        sc_reduce(b);
+        b[32..].fill(0);
+        for n in [0, 1, 2, 10, 64, 70] {
+            let straus = GeP2::straus_vartime(b, &scalars[..n], &points[..n]).to_bytes();
+            let pippenger = GeP2::pippenger_vartime(b, &scalars[..n], &points[..n]).to_bytes();
+            assert_eq!(straus, pippenger);
+        }
+        let expected = &mut [0; 32];
+        ge_p3_tobytes(expected, &GeP3::from_scalarmult_base(b));
+        assert_eq!(expected, &GeP2::pippenger_vartime(b, &[], &[]).to_bytes());
+        let double = &GeP2::from_double_scalarmult_vartime(&scalars[0], &points[0], &b[..32]);
+        let straus = &GeP2::straus_vartime(b, &scalars[..1], &points[..1]);
+        assert_eq!(double.to_bytes(), straus.to_bytes());
+    }
 }
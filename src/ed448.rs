@@ -0,0 +1,496 @@
+//! Module for Ed448 EdDSA (RFC 8032), on the untwisted Edwards curve x^2 + y^2 = 1 - 39081 x^2 y^2
+//! over the Curve448 field.
+use crate::ct;
+use crate::curve448::Fe;
+use crate::error::Error;
+use crate::rand::SecureRandom;
+use crate::sha3::Shake256;
+use crate::sig::{self, Signer, Verifier};
+use core::ops::Add;
+
+/// Computes a public key for use in the Ed448 signature scheme.
+///
+/// # Errors
+///
+/// Fails with `Error::InvalidKey` if `secret_key.len()` is not equal to 57.
+pub fn gen_pk(secret_key: &[u8]) -> Result<[u8; 57], Error> {
+    if secret_key.len() != 57 {
+        return Err(Error::InvalidKey);
+    }
+    let (s, _) = expand(secret_key);
+    Ok(Point::BASE.scalarmult(&s).to_bytes())
+}
+
+/// Signs a message using the Ed448 signature scheme with an empty context.
+///
+/// # Errors
+///
+/// Fails with `Error::InvalidKey` if `secret_key.len()` or `public_key.len()` is not equal to 57.
+///
+/// # Examples
+///
+/// ```
+/// use crypto_pure::ed448::{gen_pk, sign, verify};
+/// # let secret_key = &[0x42; 57];
+/// let public_key = &gen_pk(secret_key)?;
+/// let signature = &sign(b"message", secret_key, public_key)?;
+/// assert!(verify(b"message", signature, public_key));
+/// assert!(!verify(b"other message", signature, public_key));
+/// # Ok::<(), crypto_pure::error::Error>(())
+/// ```
+pub fn sign(message: &[u8], secret_key: &[u8], public_key: &[u8]) -> Result<[u8; 114], Error> {
+    sign_with_context(message, b"", secret_key, public_key)
+}
+
+/// Signs a message using the Ed448 signature scheme with a context, which binds the signature to
+/// a particular use so it can't be verified under another context.
+///
+/// # Errors
+///
+/// Fails with `Error::InvalidKey` if `secret_key.len()` or `public_key.len()` is not equal to 57,
+/// or with `Error::InvalidLength` if `context.len()` is greater than 255.
+pub fn sign_with_context(
+    message: &[u8],
+    context: &[u8],
+    secret_key: &[u8],
+    public_key: &[u8],
+) -> Result<[u8; 114], Error> {
+    if secret_key.len() != 57 || public_key.len() != 57 {
+        return Err(Error::InvalidKey);
+    }
+    if context.len() > 255 {
+        return Err(Error::InvalidLength);
+    }
+    let (s, prefix) = expand(secret_key);
+    let r = hash_to_scalar(context, &[&prefix, message]);
+
+    let mut signature = [0; 114];
+    signature[..57].copy_from_slice(&Point::BASE.scalarmult(&r).to_bytes());
+    let k = hash_to_scalar(context, &[&signature[..57], public_key, message]);
+    signature[57..].copy_from_slice(&scalar_muladd(&k, &s, &r));
+    Ok(signature)
+}
+
+/// Verifies whether a message was signed using the given Ed448 public key with an empty context.
+pub fn verify(message: &[u8], signature: &[u8], public_key: &[u8]) -> bool {
+    verify_with_context(message, b"", signature, public_key)
+}
+
+/// Verifies whether a message was signed using the given Ed448 public key and context. Outputs
+/// `false` if `public_key.len()` is not equal to 57 or `context.len()` is greater than 255.
+pub fn verify_with_context(
+    message: &[u8],
+    context: &[u8],
+    signature: &[u8],
+    public_key: &[u8],
+) -> bool {
+    if public_key.len() != 57 || context.len() > 255 || signature.len() != 114 {
+        return false;
+    }
+    let (r, s) = signature.split_at(57);
+    if scalar_reduce(s)[..] != s[..] {
+        return false;
+    }
+    let a = match Point::from_bytes(public_key) {
+        Some(a) => a,
+        None => return false,
+    };
+    let k = hash_to_scalar(context, &[r, public_key, message]);
+
+    let check = Point::BASE.scalarmult(s) + a.negate().scalarmult(&k);
+    ct::constant_time_eq(&check.to_bytes(), r)
+}
+
+/// The Ed448 signature scheme, with contexts of up to 255 bytes.
+pub struct Ed448;
+
+impl Verifier for Ed448 {
+    const MAX_CONTEXT_LEN: usize = 255;
+
+    type PublicKey = [u8];
+
+    fn verify(
+        message: &[u8],
+        context: &[u8],
+        signature: &[u8],
+        public_key: &[u8],
+    ) -> Result<(), Error> {
+        sig::check_lengths::<Self>(context, public_key, 57)?;
+        sig::check_valid(verify_with_context(message, context, signature, public_key))
+    }
+}
+
+impl Signer for Ed448 {
+    type SecretKey = [u8];
+    type Signature = [u8; 114];
+
+    fn sign(
+        message: &[u8],
+        context: &[u8],
+        secret_key: &[u8],
+        _rng: &dyn SecureRandom,
+    ) -> Result<[u8; 114], Error> {
+        sig::check_lengths::<Self>(context, secret_key, 57)?;
+        let public_key = &gen_pk(secret_key)?;
+        sign_with_context(message, context, secret_key, public_key)
+    }
+}
+
+/// Hashes the secret key, outputting the clamped secret scalar and the prefix used to derive
+/// nonces.
+fn expand(secret_key: &[u8]) -> ([u8; 57], [u8; 57]) {
+    assert_eq!(57, secret_key.len());
+    let mut shake = Shake256::default();
+    shake.update(secret_key);
+    let mut reader = shake.finalize_xof();
+    let mut s = [0; 57];
+    let mut prefix = [0; 57];
+    reader.read(&mut s);
+    reader.read(&mut prefix);
+    s[0] &= 0xfc;
+    s[55] |= 0x80;
+    s[56] = 0;
+    (s, prefix)
+}
+
+/// Computes SHAKE256(dom4(0, context) || parts) with 114 bytes of output, reduced modulo L.
+fn hash_to_scalar(context: &[u8], parts: &[&[u8]]) -> [u8; 57] {
+    assert!(context.len() <= 255);
+    let mut shake = Shake256::default();
+    shake.update(b"SigEd448");
+    shake.update(&[0, context.len() as u8]);
+    shake.update(context);
+    for part in parts {
+        shake.update(part);
+    }
+    let digest = &mut [0; 114];
+    shake.finalize_xof().read(digest);
+    scalar_reduce(digest)
+}
+
+/// The order L of the base point, in 64-bit little-endian limbs.
+const L: [u64; 7] = [
+    0x2378_c292_ab58_44f3,
+    0x216c_c272_8dc5_8f55,
+    0xc44e_db49_aed6_3690,
+    0xffff_ffff_7cca_23e9,
+    0xffff_ffff_ffff_ffff,
+    0xffff_ffff_ffff_ffff,
+    0x3fff_ffff_ffff_ffff,
+];
+
+/// Reduces a little-endian integer modulo L in time that only depends on its length, by shifting
+/// in one bit at a time and conditionally subtracting L.
+fn scalar_reduce(input: &[u8]) -> [u8; 57] {
+    let mut acc = [0_u64; 7];
+    for i in (0..8 * input.len()).rev() {
+        let mut bit = u64::from(input[i / 8] >> (i % 8)) & 1;
+        for limb in acc.iter_mut() {
+            let top = *limb >> 63;
+            *limb = (*limb << 1) | bit;
+            bit = top;
+        }
+
+        let mut difference = [0; 7];
+        let mut borrow = 0;
+        for (j, limb) in acc.iter().enumerate() {
+            let (value, borrow_1) = limb.overflowing_sub(L[j]);
+            let (value, borrow_2) = value.overflowing_sub(borrow);
+            difference[j] = value;
+            borrow = u64::from(borrow_1 | borrow_2);
+        }
+        let keep = borrow.wrapping_neg();
+        for (limb, difference) in acc.iter_mut().zip(&difference) {
+            *limb = (*limb & keep) | (difference & !keep);
+        }
+    }
+    let mut output = [0; 57];
+    for (chunk, limb) in output.chunks_mut(8).zip(&acc) {
+        chunk.copy_from_slice(&limb.to_le_bytes()[..chunk.len()]);
+    }
+    output
+}
+
+/// Computes (a b + c) modulo L.
+fn scalar_muladd(a: &[u8; 57], b: &[u8; 57], c: &[u8; 57]) -> [u8; 57] {
+    let load = |bytes: &[u8; 57]| {
+        let mut limbs = [0_u64; 8];
+        for (limb, chunk) in limbs.iter_mut().zip(bytes.chunks(8)) {
+            let mut buffer = [0; 8];
+            buffer[..chunk.len()].copy_from_slice(chunk);
+            *limb = u64::from_le_bytes(buffer);
+        }
+        limbs
+    };
+    let (a, b, c) = (load(a), load(b), load(c));
+    let mut product = [0_u64; 16];
+    product[..8].copy_from_slice(&c);
+    for (i, a) in a.iter().enumerate() {
+        let mut carry = 0;
+        for (j, b) in b.iter().enumerate() {
+            let value = u128::from(*a) * u128::from(*b) + u128::from(product[i + j]) + carry;
+            product[i + j] = value as u64;
+            carry = value >> 64;
+        }
+        product[i + 8] = carry as u64;
+    }
+    let mut bytes = [0; 128];
+    for (chunk, limb) in bytes.chunks_mut(8).zip(&product) {
+        chunk.copy_from_slice(&limb.to_le_bytes());
+    }
+    scalar_reduce(&bytes)
+}
+
+/// The curve constant d = -39081.
+const D: Fe = Fe([
+    0x00ff_ffff_ffff_6756,
+    0x00ff_ffff_ffff_ffff,
+    0x00ff_ffff_ffff_ffff,
+    0x00ff_ffff_ffff_ffff,
+    0x00ff_ffff_ffff_fffe,
+    0x00ff_ffff_ffff_ffff,
+    0x00ff_ffff_ffff_ffff,
+    0x00ff_ffff_ffff_ffff,
+]);
+
+/// A point in projective coordinates (X : Y : Z), representing (X / Z, Y / Z).
+#[derive(Clone, Copy)]
+struct Point {
+    x: Fe,
+    y: Fe,
+    z: Fe,
+}
+
+impl Point {
+    const IDENTITY: Self = Point {
+        x: Fe::ZERO,
+        y: Fe::ONE,
+        z: Fe::ONE,
+    };
+
+    const BASE: Self = Point {
+        x: Fe([
+            0x0026_a82b_c70c_c05e,
+            0x0080_e18b_0093_8e26,
+            0x00f7_2ab6_6511_433b,
+            0x00a3_d3a4_6412_ae1a,
+            0x000f_1767_ea6d_e324,
+            0x0036_da9e_1465_7047,
+            0x00ed_221d_15a6_22bf,
+            0x004f_1970_c66b_ed0d,
+        ]),
+        y: Fe([
+            0x0008_795b_f230_fa14,
+            0x0013_2c4e_d7c8_ad98,
+            0x001c_e67c_39c4_fdbd,
+            0x0005_a0c2_d73a_d3ff,
+            0x00a3_9840_8778_9c1e,
+            0x00c7_624b_ea73_736c,
+            0x0024_8876_2037_56c9,
+            0x0069_3f46_716e_b6bc,
+        ]),
+        z: Fe::ONE,
+    };
+
+    /// Decodes a point as in RFC 8032, Section 5.2.3, rejecting non-canonical encodings and
+    /// encodings of points not on the curve.
+    fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes[56] & 0x7f != 0 {
+            return None;
+        }
+        let y = Fe::from_bytes(&bytes[..56]);
+        if y.to_bytes()[..] != bytes[..56] {
+            return None;
+        }
+        let x_0 = bytes[56] >> 7 == 1;
+
+        let yy = y.square();
+        let u = yy - Fe::ONE;
+        let v = D * yy - Fe::ONE;
+        let xx = u * v.invert();
+        let mut x = xx.sqrt();
+        if (x.square() - xx).is_zero() {
+            if x.is_zero() && x_0 {
+                return None;
+            }
+            if x.is_negative() != x_0 {
+                x = -x;
+            }
+            Some(Point { x, y, z: Fe::ONE })
+        } else {
+            None
+        }
+    }
+
+    fn to_bytes(self) -> [u8; 57] {
+        let z_inverse = self.z.invert();
+        let x = self.x * z_inverse;
+        let y = self.y * z_inverse;
+        let mut bytes = [0; 57];
+        bytes[..56].copy_from_slice(&y.to_bytes());
+        bytes[56] = (x.is_negative() as u8) << 7;
+        bytes
+    }
+
+    fn negate(self) -> Self {
+        Point { x: -self.x, ..self }
+    }
+
+    /// Doubles the point using the formulas of RFC 8032, Section 5.2.4.
+    fn double(self) -> Self {
+        let b = (self.x + self.y).square();
+        let c = self.x.square();
+        let d = self.y.square();
+        let e = c + d;
+        let h = self.z.square();
+        let j = e - (h + h);
+        Point {
+            x: (b - e) * j,
+            y: e * (c - d),
+            z: e * j,
+        }
+    }
+
+    /// Computes `scalar` times the point, for a little-endian scalar below 2^456, by doubling and
+    /// adding at every bit with a constant-time selection.
+    fn scalarmult(self, scalar: &[u8]) -> Self {
+        let mut result = Self::IDENTITY;
+        for i in (0..8 * scalar.len()).rev() {
+            result = result.double();
+            let mut sum = result + self;
+            let bit = u64::from(scalar[i / 8] >> (i % 8)) & 1;
+            Fe::cswap(&mut result.x, &mut sum.x, bit);
+            Fe::cswap(&mut result.y, &mut sum.y, bit);
+            Fe::cswap(&mut result.z, &mut sum.z, bit);
+        }
+        result
+    }
+}
+
+impl Add for Point {
+    type Output = Self;
+
+    /// Adds two points using the formulas of RFC 8032, Section 5.2.4, which are complete, so they
+    /// also hold for doubling and the identity.
+    fn add(self, other: Self) -> Self {
+        let a = self.z * other.z;
+        let b = a.square();
+        let c = self.x * other.x;
+        let d = self.y * other.y;
+        let e = D * c * d;
+        let f = b - e;
+        let g = b + e;
+        let h = (self.x + self.y) * (other.x + other.y);
+        Point {
+            x: a * f * (h - c - d),
+            y: a * g * (d - c),
+            z: f * g,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::*;
+
+    fn check(sk: &str, pk: &str, msg: &str, context: &[u8], sig: &str) {
+        let sk = &h2b(sk);
+        let pk = &h2b(pk);
+        let msg = &h2b(msg);
+        let sig = h2b(sig);
+        assert_eq!(pk, &gen_pk(sk).unwrap()[..]);
+        let signature = &sign_with_context(msg, context, sk, pk).unwrap();
+        assert_eq!(sig, &signature[..]);
+        assert!(verify_with_context(msg, context, signature, pk));
+        assert!(!verify_with_context(msg, b"other", signature, pk));
+        assert!(!verify_with_context(b"other", context, signature, pk));
+        for &i in &[0, 56, 57, 113] {
+            let signature = &mut signature.clone();
+            signature[i] ^= 1;
+            assert!(!verify_with_context(msg, context, signature, pk));
+        }
+    }
+
+    #[test]
+    fn test_eddsa() {
+        let sk = "6c82a562cb808d10d632be89c8513ebf6c929f34ddfa8c9f63c9960ef6e348a3\
+                  528c8a3fcc2f044e39a3fc5b94492f8f032e7549a20098f95b";
+        let pk = "5fd7449b59b461fd2ce787ec616ad46a1da1342485a70e1f8a0ea75d80e96778\
+                  edf124769b46c7061bd6783df1e50f6cd1fa1abeafe8256180";
+        let sig = "533a37f6bbe457251f023c0d88f976ae2dfb504a843e34d2074fd823d41a591f\
+                   2b233f034f628281f2fd7a22ddd47d7828c59bd0a21bfd3980ff0d2028d4b18a\
+                   9df63e006c5d1c2d345b925d8dc00b4104852db99ac5c7cdda8530a113a0f4db\
+                   b61149f05a7363268c71d95808ff2e652600";
+        check(sk, pk, "", b"", sig);
+        assert!(verify(b"", &h2b(sig), &h2b(pk)));
+
+        let sk = "c4eab05d357007c632f3dbb48489924d552b08fe0c353a0d4a1f00acda2c463a\
+                  fbea67c5e8d2877c5e3bc397a659949ef8021e954e0a12274e";
+        let pk = "43ba28f430cdff456ae531545f7ecd0ac834a55d9358c0372bfa0c6c6798c086\
+                  6aea01eb00742802b8438ea4cb82169c235160627b4c3a9480";
+        let sig = "26b8f91727bd62897af15e41eb43c377efb9c610d48f2335cb0bd0087810f435\
+                   2541b143c4b981b7e18f62de8ccdf633fc1bf037ab7cd779805e0dbcc0aae1cb\
+                   cee1afb2e027df36bc04dcecbf154336c19f0af7e0a6472905e799f1953d2a0f\
+                   f3348ab21aa4adafd1d234441cf807c03a00";
+        check(sk, pk, "03", b"", sig);
+        let sig = "d4f8f6131770dd46f40867d6fd5d5055de43541f8c5e35abbcd001b32a89f7d2\
+                   151f7647f11d8ca2ae279fb842d607217fce6e042f6815ea000c85741de5c8da\
+                   1144a6a1aba7f96de42505d7a7298524fda538fccbbb754f578c1cad10d54d0d\
+                   5428407e85dcbc98a49155c13764e66c3c00";
+        check(sk, pk, "03", b"foo", sig);
+    }
+
+    #[test]
+    fn test_malleability() {
+        let sk = &[0x42; 57];
+        let pk = &gen_pk(sk).unwrap();
+        let signature = &mut sign(b"message", sk, pk).unwrap();
+
+        // Adding L to S gives an equivalent but non-canonical signature, which must be rejected.
+        let mut carry = 0;
+        for (i, byte) in signature[57..].iter_mut().enumerate() {
+            let l_byte = L.get(i / 8).map_or(0, |limb| (limb >> (8 * (i % 8))) as u8);
+            let sum = u16::from(*byte) + u16::from(l_byte) + carry;
+            *byte = sum as u8;
+            carry = sum >> 8;
+        }
+        assert!(!verify(b"message", signature, pk));
+        assert!(!verify(b"message", &signature[..113], pk));
+    }
+
+    #[test]
+    fn test_invalid_lengths() {
+        let sk = &[0x42; 57];
+        let pk = &gen_pk(sk).unwrap();
+        assert_eq!(Err(Error::InvalidKey), gen_pk(&sk[1..]));
+        let error = Err(Error::InvalidKey);
+        assert_eq!(error, sign(b"message", &sk[1..], pk));
+        assert_eq!(error, sign(b"message", sk, &pk[1..]));
+        let result = sign_with_context(b"message", &[0; 256], sk, pk);
+        assert_eq!(Err(Error::InvalidLength), result);
+
+        let signature = &sign(b"message", sk, pk).unwrap();
+        assert!(!verify(b"message", signature, &pk[1..]));
+        assert!(!verify_with_context(b"message", &[0; 256], signature, pk));
+    }
+
+    #[test]
+    fn test_scalar() {
+        let mut l = [0; 57];
+        for (chunk, limb) in l.chunks_mut(8).zip(&L) {
+            chunk.copy_from_slice(&limb.to_le_bytes()[..chunk.len()]);
+        }
+        assert_eq!([0; 57][..], scalar_reduce(&l)[..]);
+        let mut one = [0; 57];
+        one[0] = 1;
+        let mut l_minus_one = l;
+        l_minus_one[0] -= 1;
+        assert_eq!(l_minus_one[..], scalar_reduce(&l_minus_one)[..]);
+        assert_eq!(
+            one[..],
+            scalar_muladd(&l_minus_one, &l_minus_one, &[0; 57])[..]
+        );
+        assert_eq!([0; 57][..], scalar_muladd(&l_minus_one, &one, &one)[..]);
+    }
+}
@@ -0,0 +1,7 @@
+//! Module for text encodings of binary data, such as keys and digests.
+pub mod base64;
+pub mod hex;
+#[cfg(feature = "alloc")]
+pub mod json;
+#[cfg(feature = "alloc")]
+pub mod pem;
@@ -0,0 +1,625 @@
+//! Module for base64 encoding (RFC 4648), with the standard and URL-safe alphabets, each with or
+//! without padding.
+//!
+//! Decoding is strict: characters outside the alphabet (including whitespace and line breaks),
+//! misplaced or missing padding, and nonzero bits after the last byte are all rejected, so each
+//! byte string has exactly one accepted encoding. Neither encoding nor decoding branches on the
+//! values of the bytes or characters, so keys can be encoded and decoded too.
+//!
+//! # Examples
+//!
+//! ```
+//! use crypto_pure::encoding::base64;
+//! let bytes = &[0xfb, 0xff, 0x00];
+//! let output = &mut [0; 4];
+//! assert_eq!(Ok("+/8A"), base64::STANDARD.encode_to_slice(bytes, output));
+//! assert_eq!(Ok("-_8A"), base64::URL_SAFE.encode_to_slice(bytes, output));
+//! assert_eq!(Ok("aGk="), base64::STANDARD.encode_to_slice(b"hi", output));
+//! assert_eq!(Ok("aGk"), base64::STANDARD_NO_PAD.encode_to_slice(b"hi", &mut output[..3]));
+//!
+//! let decoded = base64::STANDARD.decode_to_slice("aGk=", output);
+//! assert_eq!(Ok(&mut b"hi".to_vec()[..]), decoded);
+//! ```
+use crate::ct::{self, Choice};
+use crate::error::Error;
+#[cfg(feature = "alloc")]
+use alloc::{string::String, vec, vec::Vec};
+use core::str;
+#[cfg(feature = "std")]
+use std::io::{self, Read, Write};
+
+/// A base64 alphabet along with whether encodings are padded with `=` to a multiple of 4
+/// characters.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Config {
+    url_safe: bool,
+    padding: bool,
+}
+
+/// The standard alphabet, ending in `+` and `/`, with padding.
+pub const STANDARD: Config = Config {
+    url_safe: false,
+    padding: true,
+};
+
+/// The standard alphabet without padding.
+pub const STANDARD_NO_PAD: Config = Config {
+    url_safe: false,
+    padding: false,
+};
+
+/// The URL and filename safe alphabet, ending in `-` and `_`, with padding.
+pub const URL_SAFE: Config = Config {
+    url_safe: true,
+    padding: true,
+};
+
+/// The URL and filename safe alphabet without padding, as used by JWS and PASETO.
+pub const URL_SAFE_NO_PAD: Config = Config {
+    url_safe: true,
+    padding: false,
+};
+
+impl Config {
+    /// Outputs the length of the encoding of `len` bytes.
+    pub fn encoded_len(self, len: usize) -> usize {
+        let partial = match (len % 3, self.padding) {
+            (0, _) => 0,
+            (_, true) => 4,
+            (remainder, false) => remainder + 1,
+        };
+        len / 3 * 4 + partial
+    }
+
+    /// Outputs the encoding of `bytes`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crypto_pure::encoding::base64;
+    /// let encoded = base64::URL_SAFE_NO_PAD.encode(b"message");
+    /// assert_eq!("bWVzc2FnZQ", encoded);
+    /// assert_eq!(Ok(b"message".to_vec()), base64::URL_SAFE_NO_PAD.decode(&encoded));
+    /// ```
+    #[cfg(feature = "alloc")]
+    pub fn encode(self, bytes: &[u8]) -> String {
+        let mut encoded = vec![0; self.encoded_len(bytes.len())];
+        self.encode_into(bytes, &mut encoded);
+        // Only characters of the alphabet and padding are written.
+        String::from_utf8(encoded).unwrap()
+    }
+
+    /// Writes the encoding of `bytes` into `output`, outputting it as a string.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::InvalidLength` if `output.len()` is not `self.encoded_len(bytes.len())`.
+    pub fn encode_to_slice<'a>(self, bytes: &[u8], output: &'a mut [u8]) -> Result<&'a str, Error> {
+        if output.len() != self.encoded_len(bytes.len()) {
+            return Err(Error::InvalidLength);
+        }
+        self.encode_into(bytes, output);
+        // Only characters of the alphabet and padding are written.
+        Ok(str::from_utf8(output).unwrap())
+    }
+
+    fn encode_into(self, bytes: &[u8], output: &mut [u8]) {
+        for (chunk, group) in bytes.chunks(3).zip(output.chunks_mut(4)) {
+            let mut block = [0; 4];
+            block[1..=chunk.len()].copy_from_slice(chunk);
+            let block = u32::from_be_bytes(block);
+            for (i, c) in group.iter_mut().enumerate() {
+                *c = if i <= chunk.len() {
+                    encode_char((block >> (18 - 6 * i)) as u8 & 0x3f, self.url_safe)
+                } else {
+                    b'='
+                };
+            }
+        }
+    }
+
+    /// Outputs the length of the bytes encoded by `encoded`.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::InvalidLength` if no encoding has the length of `encoded`, or with
+    /// `Error::InvalidEncoding` if it has misplaced padding. The other characters are checked by
+    /// decoding.
+    pub fn decoded_len(self, encoded: &str) -> Result<usize, Error> {
+        let data = self.strip_padding(encoded.as_bytes())?;
+        let partial = match data.len() % 4 {
+            0 => 0,
+            1 => return Err(Error::InvalidLength),
+            remainder => remainder - 1,
+        };
+        Ok(data.len() / 4 * 3 + partial)
+    }
+
+    /// Decodes `encoded`.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::InvalidLength` if no encoding has the length of `encoded`, or with
+    /// `Error::InvalidEncoding` if it contains a character outside the alphabet, misplaced
+    /// padding, or nonzero bits after the last byte.
+    #[cfg(feature = "alloc")]
+    pub fn decode(self, encoded: &str) -> Result<Vec<u8>, Error> {
+        let mut bytes = vec![0; self.decoded_len(encoded)?];
+        self.decode_to_slice(encoded, &mut bytes)?;
+        Ok(bytes)
+    }
+
+    /// Decodes `encoded` like `decode` into the start of `output`, outputting the decoded part
+    /// of `output`. If decoding fails, the part that would have been output is zeroed.
+    ///
+    /// # Errors
+    ///
+    /// Fails like `decode`, or with `Error::InvalidLength` if `output.len()` is less than
+    /// `self.decoded_len(encoded)`.
+    pub fn decode_to_slice<'a>(
+        self,
+        encoded: &str,
+        output: &'a mut [u8],
+    ) -> Result<&'a mut [u8], Error> {
+        let len = self.decoded_len(encoded)?;
+        if output.len() < len {
+            return Err(Error::InvalidLength);
+        }
+        let data = self.strip_padding(encoded.as_bytes())?;
+        let output = &mut output[..len];
+        decode_into(data, output, self.url_safe)?;
+        Ok(output)
+    }
+
+    /// Checks the length and padding of an encoding, outputting the part before the padding.
+    fn strip_padding(self, encoded: &[u8]) -> Result<&[u8], Error> {
+        if !self.padding {
+            // Any `=` will be rejected as a character outside the alphabet.
+            return Ok(encoded);
+        }
+        if !encoded.len().is_multiple_of(4) {
+            return Err(Error::InvalidLength);
+        }
+        let padding_len = encoded
+            .iter()
+            .rev()
+            .take(2)
+            .take_while(|&&c| c == b'=')
+            .count();
+        let data = &encoded[..encoded.len() - padding_len];
+        if data.last() == Some(&b'=') {
+            return Err(Error::InvalidEncoding);
+        }
+        Ok(data)
+    }
+}
+
+/// Decodes unpadded groups of characters into `output`, which must have the decoded length.
+fn decode_into(data: &[u8], output: &mut [u8], url_safe: bool) -> Result<(), Error> {
+    let mut valid = Choice::from(1);
+    for (group, chunk) in data.chunks(4).zip(output.chunks_mut(3)) {
+        let mut block = 0;
+        for (i, &c) in group.iter().enumerate() {
+            let (value, char_valid) = decode_char(c, url_safe);
+            block |= u32::from(value) << (18 - 6 * i);
+            valid &= char_valid;
+        }
+        chunk.copy_from_slice(&block.to_be_bytes()[1..=chunk.len()]);
+        // The bits after the last byte must be zero, or another encoding would decode the same.
+        let trailing_bits = block & ((1 << (24 - 8 * chunk.len())) - 1);
+        valid &= !ct::ct_lt(0, trailing_bits);
+    }
+    if bool::from(valid) {
+        Ok(())
+    } else {
+        output.iter_mut().for_each(|byte| *byte = 0);
+        Err(Error::InvalidEncoding)
+    }
+}
+
+/// Maps a 6-bit value to a character of the alphabet without branching on `value`.
+fn encode_char(value: u8, url_safe: bool) -> u8 {
+    let value = i16::from(value);
+    let (offset_62, offset_63) = if url_safe { (13, 49) } else { (15, 3) };
+    // Each `(n - value) >> 8` is all ones exactly when `value` is greater than `n`, shifting the
+    // character from the start of one range to the start of the next.
+    let mut c = value + i16::from(b'A');
+    c += ((25 - value) >> 8) & 6;
+    c -= ((51 - value) >> 8) & 75;
+    c -= ((61 - value) >> 8) & offset_62;
+    c += ((62 - value) >> 8) & offset_63;
+    c as u8
+}
+
+/// Maps a character of the alphabet to its value without branching on `c`, outputting whether it
+/// was in the alphabet.
+fn decode_char(c: u8, url_safe: bool) -> (u8, Choice) {
+    let (c_62, c_63) = if url_safe { (b'-', b'_') } else { (b'+', b'/') };
+    let c = i16::from(c);
+    let upper = in_range(c, b'A', b'Z');
+    let lower = in_range(c, b'a', b'z');
+    let digit = in_range(c, b'0', b'9');
+    let is_62 = in_range(c, c_62, c_62);
+    let is_63 = in_range(c, c_63, c_63);
+    let value = (upper & (c - 65)) | (lower & (c - 71)) | (digit & (c + 4)) | (is_62 & 62);
+    let value = value | (is_63 & 63);
+    let valid = (upper | lower | digit | is_62 | is_63) & 1;
+    (value as u8, Choice::from(valid as u8))
+}
+
+/// Outputs all ones if `c` is from `low` to `high` and zero otherwise, since both differences are
+/// then negative and the shift extends the sign bit.
+fn in_range(c: i16, low: u8, high: u8) -> i16 {
+    ((i16::from(low) - 1 - c) & (c - i16::from(high) - 1)) >> 8
+}
+
+/// A writer that base64-encodes everything written through it into another writer.
+///
+/// `finish` must be called after the last write to output the final group of characters.
+///
+/// # Examples
+///
+/// ```
+/// use crypto_pure::encoding::base64::{self, EncoderWriter};
+/// use std::io::{self, Write};
+/// # fn main() -> io::Result<()> {
+/// let mut writer = EncoderWriter::new(Vec::new(), base64::STANDARD);
+/// writer.write_all(b"part one, ")?;
+/// writer.write_all(b"part two")?;
+/// let encoded = writer.finish()?;
+/// assert_eq!(base64::STANDARD.encode(b"part one, part two").as_bytes(), &encoded[..]);
+/// # Ok(())
+/// # }
+/// ```
+#[cfg(feature = "std")]
+pub struct EncoderWriter<W> {
+    inner: W,
+    config: Config,
+    pending: [u8; 3],
+    pending_len: usize,
+}
+
+#[cfg(feature = "std")]
+impl<W: Write> EncoderWriter<W> {
+    /// Initializes a writer that encodes its input with `config` before passing it on to `inner`.
+    pub fn new(inner: W, config: Config) -> Self {
+        Self {
+            inner,
+            config,
+            pending: [0; 3],
+            pending_len: 0,
+        }
+    }
+
+    /// Writes the encoding of any bytes left over from the last complete group of three, with
+    /// padding if the configuration has it, and outputs the underlying writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        let output = &mut [0; 4];
+        let len = self.config.encoded_len(self.pending_len);
+        self.config
+            .encode_into(&self.pending[..self.pending_len], &mut output[..len]);
+        self.inner.write_all(&output[..len])?;
+        Ok(self.inner)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<W: Write> Write for EncoderWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut consumed = 0;
+        if self.pending_len > 0 {
+            consumed = buf.len().min(3 - self.pending_len);
+            self.pending[self.pending_len..self.pending_len + consumed]
+                .copy_from_slice(&buf[..consumed]);
+            self.pending_len += consumed;
+            if self.pending_len < 3 {
+                return Ok(consumed);
+            }
+            let output = &mut [0; 4];
+            self.config.encode_into(&self.pending, output);
+            self.inner.write_all(output)?;
+            self.pending_len = 0;
+        }
+
+        let output = &mut [0; 1024];
+        let len = (buf.len() - consumed).min(768) / 3 * 3;
+        let chunk = &buf[consumed..consumed + len];
+        self.config.encode_into(chunk, &mut output[..len / 3 * 4]);
+        self.inner.write_all(&output[..len / 3 * 4])?;
+        consumed += len;
+
+        let rest = &buf[consumed..];
+        if rest.len() < 3 {
+            self.pending[..rest.len()].copy_from_slice(rest);
+            self.pending_len = rest.len();
+            consumed += rest.len();
+        }
+        Ok(consumed)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// A reader that decodes base64 read from another reader.
+///
+/// A decoding error is returned as an `io::Error` of kind `InvalidData`.
+///
+/// # Examples
+///
+/// ```
+/// use crypto_pure::encoding::base64::{self, DecoderReader};
+/// use std::io::{self, Read};
+/// # fn main() -> io::Result<()> {
+/// let encoded = base64::URL_SAFE.encode(b"message");
+/// let mut reader = DecoderReader::new(encoded.as_bytes(), base64::URL_SAFE);
+/// let mut decoded = Vec::new();
+/// reader.read_to_end(&mut decoded)?;
+/// assert_eq!(b"message", &decoded[..]);
+/// # Ok(())
+/// # }
+/// ```
+#[cfg(feature = "std")]
+pub struct DecoderReader<R> {
+    inner: R,
+    config: Config,
+    encoded: [u8; 1024],
+    encoded_len: usize,
+    decoded: [u8; 768],
+    decoded_start: usize,
+    decoded_end: usize,
+    finished: bool,
+}
+
+#[cfg(feature = "std")]
+impl<R: Read> DecoderReader<R> {
+    /// Initializes a reader that decodes what it reads from `inner` with `config`.
+    pub fn new(inner: R, config: Config) -> Self {
+        Self {
+            inner,
+            config,
+            encoded: [0; 1024],
+            encoded_len: 0,
+            decoded: [0; 768],
+            decoded_start: 0,
+            decoded_end: 0,
+            finished: false,
+        }
+    }
+
+    /// Decodes the buffered characters once the underlying reader is exhausted.
+    fn decode_last(&mut self) -> Result<(), Error> {
+        // The buffered characters came from bytes, so they are valid UTF-8 only if they are
+        // ASCII, and anything else is rejected anyway.
+        let encoded = str::from_utf8(&self.encoded[..self.encoded_len])
+            .map_err(|_| Error::InvalidEncoding)?;
+        self.decoded_end = self
+            .config
+            .decode_to_slice(encoded, &mut self.decoded)?
+            .len();
+        Ok(())
+    }
+
+    /// Decodes all complete groups of buffered characters but the last, which might be padded.
+    fn decode_buffered(&mut self) -> Result<(), Error> {
+        let data_len = (self.encoded_len - 1) / 4 * 4;
+        self.decoded_end = data_len / 4 * 3;
+        let (data, output) = (&self.encoded[..data_len], &mut self.decoded);
+        decode_into(data, &mut output[..data_len / 4 * 3], self.config.url_safe)?;
+        self.encoded.copy_within(data_len..self.encoded_len, 0);
+        self.encoded_len -= data_len;
+        Ok(())
+    }
+}
+
+#[cfg(feature = "std")]
+impl<R: Read> Read for DecoderReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.decoded_start == self.decoded_end && !self.finished {
+            self.decoded_start = 0;
+            let read = self.inner.read(&mut self.encoded[self.encoded_len..])?;
+            let result = if read == 0 {
+                self.finished = true;
+                self.decode_last()
+            } else {
+                self.encoded_len += read;
+                self.decode_buffered()
+            };
+            if let Err(error) = result {
+                self.finished = true;
+                self.decoded_end = 0;
+                return Err(io::Error::new(io::ErrorKind::InvalidData, error));
+            }
+        }
+        let available = &self.decoded[self.decoded_start..self.decoded_end];
+        let len = available.len().min(buf.len());
+        buf[..len].copy_from_slice(&available[..len]);
+        self.decoded_start += len;
+        Ok(len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789";
+
+    const VECTORS: &[(&str, &str)] = &[
+        ("", ""),
+        ("f", "Zg=="),
+        ("fo", "Zm8="),
+        ("foo", "Zm9v"),
+        ("foob", "Zm9vYg=="),
+        ("fooba", "Zm9vYmE="),
+        ("foobar", "Zm9vYmFy"),
+    ];
+
+    #[test]
+    fn test_rfc4648() {
+        for &(bytes, encoded) in VECTORS {
+            let bytes = bytes.as_bytes();
+            let output = &mut [0; 8];
+            let len = encoded.len();
+            assert_eq!(
+                Ok(encoded),
+                STANDARD.encode_to_slice(bytes, &mut output[..len])
+            );
+            let unpadded = encoded.trim_end_matches('=');
+            let len = unpadded.len();
+            let encoded_no_pad = STANDARD_NO_PAD.encode_to_slice(bytes, &mut output[..len]);
+            assert_eq!(Ok(unpadded), encoded_no_pad);
+
+            let output = &mut [0xff; 8];
+            assert_eq!(Ok(bytes.len()), STANDARD.decoded_len(encoded));
+            let decoded = STANDARD.decode_to_slice(encoded, output);
+            assert_eq!(Ok(bytes), decoded.map(|x| &*x));
+            let decoded = URL_SAFE_NO_PAD.decode_to_slice(unpadded, output);
+            assert_eq!(Ok(bytes), decoded.map(|x| &*x));
+
+            #[cfg(feature = "alloc")]
+            {
+                assert_eq!(encoded, STANDARD.encode(bytes));
+                assert_eq!(unpadded, URL_SAFE_NO_PAD.encode(bytes));
+                assert_eq!(Ok(bytes), URL_SAFE.decode(encoded).as_deref());
+                assert_eq!(Ok(bytes), STANDARD_NO_PAD.decode(unpadded).as_deref());
+            }
+        }
+    }
+
+    #[test]
+    fn test_alphabets() {
+        for value in 0..64 {
+            let (standard, url_safe) = match ALPHABET.get(value as usize) {
+                Some(&c) => (c, c),
+                None if value == 62 => (b'+', b'-'),
+                None => (b'/', b'_'),
+            };
+            assert_eq!(standard, encode_char(value, false));
+            assert_eq!(url_safe, encode_char(value, true));
+        }
+        for c in 0..=255 {
+            let expected = ALPHABET.iter().position(|&x| x == c);
+            let check = |url_safe, c_62, c_63| {
+                let (value, valid) = decode_char(c, url_safe);
+                let expected = match c {
+                    _ if c == c_62 => Some(62),
+                    _ if c == c_63 => Some(63),
+                    _ => expected,
+                };
+                assert_eq!(expected.is_some(), bool::from(valid));
+                if let Some(expected) = expected {
+                    assert_eq!(expected as u8, value);
+                }
+            };
+            check(false, b'+', b'/');
+            check(true, b'-', b'_');
+        }
+    }
+
+    #[test]
+    fn test_invalid() {
+        let invalid_length = Err(Error::InvalidLength);
+        let invalid_encoding = Err(Error::InvalidEncoding);
+        let cases = &[
+            (STANDARD, "Zg=", invalid_length),
+            (STANDARD, "Zg", invalid_length),
+            (STANDARD, "Zm9vY", invalid_length),
+            (STANDARD, "Z===", invalid_encoding),
+            (STANDARD, "Zg=a", invalid_encoding),
+            (STANDARD, "=Zg=", invalid_encoding),
+            (STANDARD, "Zh==", invalid_encoding),
+            (STANDARD, "Zm9=", invalid_encoding),
+            (STANDARD, "Zm9v\nYg==", invalid_length),
+            (STANDARD, "Zm9 Yg==", invalid_encoding),
+            (STANDARD, "-_8A", invalid_encoding),
+            (URL_SAFE, "+/8A", invalid_encoding),
+            (STANDARD_NO_PAD, "Zg==", invalid_encoding),
+            (STANDARD_NO_PAD, "Zm9vY", invalid_length),
+            (STANDARD_NO_PAD, "Zh", invalid_encoding),
+            (URL_SAFE_NO_PAD, "Zm9vYé", invalid_encoding),
+        ];
+        for &(config, encoded, expected) in cases {
+            let output = &mut [0xff; 8];
+            let decoded = config.decode_to_slice(encoded, output).map(|_| ());
+            assert_eq!(expected, decoded, "{}", encoded);
+            #[cfg(feature = "alloc")]
+            assert_eq!(expected, config.decode(encoded).map(|_| ()));
+        }
+
+        let output = &mut [0xff; 3];
+        assert_eq!(
+            invalid_length,
+            STANDARD.decode_to_slice("Zm9vYg==", output).map(|_| ())
+        );
+        let encoded = STANDARD.encode_to_slice(b"foo", output).map(|_| ());
+        assert_eq!(invalid_length, encoded);
+        let decoded = STANDARD
+            .decode_to_slice("Zm9vYg=a", &mut [0xff; 6])
+            .map(|_| ());
+        assert_eq!(invalid_encoding, decoded);
+        let output = &mut [0xff; 2];
+        assert_eq!(
+            invalid_encoding,
+            STANDARD_NO_PAD.decode_to_slice("Zm$", output).map(|_| ())
+        );
+        assert_eq!(&[0, 0], output);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_streaming() {
+        use std::vec::Vec;
+
+        let message: &Vec<_> = &(0..3000).map(|i| (i * 7) as u8).collect();
+        let expected = &URL_SAFE.encode(message);
+        for &chunk_size in &[1, 2, 3, 5, 1000, 3000] {
+            let mut writer = EncoderWriter::new(Vec::new(), URL_SAFE);
+            for chunk in message.chunks(chunk_size) {
+                writer.write_all(chunk).unwrap();
+            }
+            writer.flush().unwrap();
+            assert_eq!(expected.as_bytes(), &writer.finish().unwrap()[..]);
+
+            let mut reader = DecoderReader::new(expected.as_bytes(), URL_SAFE);
+            let mut decoded = Vec::new();
+            let buf = &mut vec![0; chunk_size];
+            loop {
+                let read = reader.read(buf).unwrap();
+                if read == 0 {
+                    break;
+                }
+                decoded.extend_from_slice(&buf[..read]);
+            }
+            assert_eq!(message, &decoded);
+        }
+
+        for message in &[&b""[..], b"f", b"fo", b"foo"] {
+            let mut writer = EncoderWriter::new(Vec::new(), STANDARD_NO_PAD);
+            writer.write_all(message).unwrap();
+            let encoded = writer.finish().unwrap();
+            assert_eq!(STANDARD_NO_PAD.encode(message).as_bytes(), &encoded[..]);
+            let mut reader = DecoderReader::new(&encoded[..], STANDARD_NO_PAD);
+            let mut decoded = Vec::new();
+            reader.read_to_end(&mut decoded).unwrap();
+            assert_eq!(message, &decoded);
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_streaming_invalid() {
+        use std::vec::Vec;
+
+        let valid = STANDARD.encode(&[0x42; 2000]);
+        let padded_early = [&valid[..1000], "Zg==", &valid[1000..]].concat();
+        let invalid_char = [&valid[..1500], "*", &valid[1501..]].concat();
+        for encoded in &[padded_early, invalid_char, [&valid[..], "A"].concat()] {
+            let mut reader = DecoderReader::new(encoded.as_bytes(), STANDARD);
+            let error = reader.read_to_end(&mut Vec::new()).unwrap_err();
+            assert_eq!(io::ErrorKind::InvalidData, error.kind());
+            assert_eq!(0, reader.read(&mut [0; 16]).unwrap());
+        }
+    }
+}
@@ -0,0 +1,283 @@
+//! Module for hexadecimal (base 16) encoding, with two characters per byte.
+//!
+//! Encoding takes the same time for all bytes, so it can be used on secrets. `decode` stops at
+//! the first invalid character, which is fine for public data. `decode_secret` and
+//! `decode_secret_to_slice` instead take the same time for all inputs of a given length, so they
+//! don't reveal anything about a key being loaded besides whether it is valid.
+//!
+//! # Examples
+//!
+//! ```
+//! use crypto_pure::encoding::hex;
+//! let output = &mut [0; 6];
+//! assert_eq!(Ok("00ff2a"), hex::encode_to_slice(&[0x00, 0xff, 0x2a], output));
+//! let bytes = &mut [0; 3];
+//! hex::decode_to_slice("00fF2a", bytes).unwrap();
+//! assert_eq!(&[0x00, 0xff, 0x2a], bytes);
+//! ```
+use crate::ct::Choice;
+use crate::error::Error;
+#[cfg(feature = "alloc")]
+use crate::secret::SecretVec;
+#[cfg(feature = "alloc")]
+use alloc::{string::String, vec, vec::Vec};
+use core::str;
+
+/// The offsets from `'0' + 10` to `'a'` and to `'A'`.
+const LOWER: i16 = 39;
+const UPPER: i16 = 7;
+
+/// Outputs the lowercase hex encoding of `bytes`.
+///
+/// # Examples
+///
+/// ```
+/// use crypto_pure::encoding::hex;
+/// assert_eq!("00ff2a", hex::encode(&[0x00, 0xff, 0x2a]));
+/// assert_eq!("00FF2A", hex::encode_upper(&[0x00, 0xff, 0x2a]));
+/// assert_eq!(Ok(vec![0x00, 0xff, 0x2a]), hex::decode("00fF2a"));
+/// ```
+#[cfg(feature = "alloc")]
+pub fn encode(bytes: &[u8]) -> String {
+    encode_with(bytes, LOWER)
+}
+
+/// Outputs the uppercase hex encoding of `bytes`.
+#[cfg(feature = "alloc")]
+pub fn encode_upper(bytes: &[u8]) -> String {
+    encode_with(bytes, UPPER)
+}
+
+#[cfg(feature = "alloc")]
+fn encode_with(bytes: &[u8], offset: i16) -> String {
+    let mut encoded = vec![0; 2 * bytes.len()];
+    encode_into(bytes, &mut encoded, offset);
+    // Only ASCII digits and letters are written.
+    String::from_utf8(encoded).unwrap()
+}
+
+/// Writes the lowercase hex encoding of `bytes` into `output`, outputting it as a string.
+///
+/// # Errors
+///
+/// Fails with `Error::InvalidLength` if `output.len()` is not `2 * bytes.len()`.
+pub fn encode_to_slice<'a>(bytes: &[u8], output: &'a mut [u8]) -> Result<&'a str, Error> {
+    encode_to_slice_with(bytes, output, LOWER)
+}
+
+/// Writes the uppercase hex encoding of `bytes` into `output`, outputting it as a string.
+///
+/// # Errors
+///
+/// Fails with `Error::InvalidLength` if `output.len()` is not `2 * bytes.len()`.
+pub fn encode_upper_to_slice<'a>(bytes: &[u8], output: &'a mut [u8]) -> Result<&'a str, Error> {
+    encode_to_slice_with(bytes, output, UPPER)
+}
+
+fn encode_to_slice_with<'a>(
+    bytes: &[u8],
+    output: &'a mut [u8],
+    offset: i16,
+) -> Result<&'a str, Error> {
+    if output.len() != 2 * bytes.len() {
+        return Err(Error::InvalidLength);
+    }
+    encode_into(bytes, output, offset);
+    // Only ASCII digits and letters are written.
+    Ok(str::from_utf8(output).unwrap())
+}
+
+fn encode_into(bytes: &[u8], output: &mut [u8], offset: i16) {
+    for (byte, pair) in bytes.iter().zip(output.chunks_mut(2)) {
+        pair[0] = encode_nibble(byte >> 4, offset);
+        pair[1] = encode_nibble(byte & 0xf, offset);
+    }
+}
+
+/// Maps 0 to 9 to `'0'` to `'9'` and 10 to 15 to letters without branching on `nibble`.
+fn encode_nibble(nibble: u8, offset: i16) -> u8 {
+    let nibble = i16::from(nibble);
+    // `(9 - nibble) >> 8` is all ones exactly when `nibble` is greater than 9.
+    (nibble + i16::from(b'0') + (((9 - nibble) >> 8) & offset)) as u8
+}
+
+/// Decodes hex in either case, failing at the first invalid character.
+///
+/// # Errors
+///
+/// Fails with `Error::InvalidLength` if `hex.len()` is odd, or with `Error::InvalidEncoding` if
+/// `hex` contains a character other than a hex digit.
+#[cfg(feature = "alloc")]
+pub fn decode(hex: &str) -> Result<Vec<u8>, Error> {
+    let mut bytes = vec![0; hex.len() / 2];
+    decode_to_slice(hex, &mut bytes)?;
+    Ok(bytes)
+}
+
+/// Decodes hex like `decode` into `output`.
+///
+/// # Errors
+///
+/// Fails like `decode`, or with `Error::InvalidLength` if `output.len()` is not
+/// `hex.len() / 2`.
+pub fn decode_to_slice(hex: &str, output: &mut [u8]) -> Result<(), Error> {
+    check_lengths(hex, output)?;
+    for (pair, byte) in hex.as_bytes().chunks(2).zip(output.iter_mut()) {
+        *byte = (decode_digit(pair[0])? << 4) | decode_digit(pair[1])?;
+    }
+    Ok(())
+}
+
+fn decode_digit(c: u8) -> Result<u8, Error> {
+    match c {
+        b'0'..=b'9' => Ok(c - b'0'),
+        b'a'..=b'f' => Ok(c - b'a' + 10),
+        b'A'..=b'F' => Ok(c - b'A' + 10),
+        _ => Err(Error::InvalidEncoding),
+    }
+}
+
+/// Decodes hex in either case in constant time, for secret material such as keys.
+///
+/// # Errors
+///
+/// Fails like `decode`, but only after the whole input has been processed.
+#[cfg(feature = "alloc")]
+pub fn decode_secret(hex: &str) -> Result<SecretVec, Error> {
+    let mut bytes = SecretVec::new(vec![0; hex.len() / 2]);
+    decode_secret_to_slice(hex, &mut bytes)?;
+    Ok(bytes)
+}
+
+/// Decodes hex like `decode_secret` into `output`, which is zeroed if decoding fails.
+///
+/// # Errors
+///
+/// Fails like `decode_to_slice`.
+pub fn decode_secret_to_slice(hex: &str, output: &mut [u8]) -> Result<(), Error> {
+    check_lengths(hex, output)?;
+    let mut valid = Choice::from(1);
+    for (pair, byte) in hex.as_bytes().chunks(2).zip(output.iter_mut()) {
+        let (high, high_valid) = decode_digit_ct(pair[0]);
+        let (low, low_valid) = decode_digit_ct(pair[1]);
+        *byte = (high << 4) | low;
+        valid &= high_valid & low_valid;
+    }
+    if bool::from(valid) {
+        Ok(())
+    } else {
+        output.iter_mut().for_each(|byte| *byte = 0);
+        Err(Error::InvalidEncoding)
+    }
+}
+
+/// Decodes a hex digit without branching on `c`, outputting whether it was valid.
+fn decode_digit_ct(c: u8) -> (u8, Choice) {
+    let c = i16::from(c);
+    // Each mask is all ones exactly when `c` is in the range, since both differences are then
+    // negative and the shift extends the sign bit.
+    let digit = ((0x2f - c) & (c - 0x3a)) >> 8;
+    let upper = ((0x40 - c) & (c - 0x47)) >> 8;
+    let lower = ((0x60 - c) & (c - 0x67)) >> 8;
+    let value = (digit & (c - 0x30)) | (upper & (c - 0x37)) | (lower & (c - 0x57));
+    let valid = (digit | upper | lower) & 1;
+    (value as u8, Choice::from(valid as u8))
+}
+
+fn check_lengths(hex: &str, output: &[u8]) -> Result<(), Error> {
+    if !hex.len().is_multiple_of(2) || output.len() != hex.len() / 2 {
+        return Err(Error::InvalidLength);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode() {
+        let bytes: &[u8] = &[0x01, 0x23, 0x45, 0x67, 0x89, 0xab, 0xcd, 0xef];
+        let output = &mut [0; 16];
+        assert_eq!(Ok("0123456789abcdef"), encode_to_slice(bytes, output));
+        assert_eq!(Ok("0123456789ABCDEF"), encode_upper_to_slice(bytes, output));
+        assert_eq!(Ok(""), encode_to_slice(&[], &mut []));
+        let error = Err(Error::InvalidLength);
+        assert_eq!(error, encode_to_slice(bytes, &mut output[1..]));
+        assert_eq!(error, encode_upper_to_slice(&bytes[1..], output));
+
+        #[cfg(feature = "alloc")]
+        {
+            assert_eq!("0123456789abcdef", encode(bytes));
+            assert_eq!("0123456789ABCDEF", encode_upper(bytes));
+            assert_eq!("", encode(&[]));
+        }
+    }
+
+    #[test]
+    fn test_decode() {
+        let expected = [
+            0x01, 0x23, 0x45, 0x67, 0x89, 0xab, 0xcd, 0xef, 0xab, 0xcd, 0xef,
+        ];
+        let hex = "0123456789abcdefABCDEF";
+        let output = &mut [0; 11];
+        decode_to_slice(hex, output).unwrap();
+        assert_eq!(&expected, output);
+        let output = &mut [0; 11];
+        decode_secret_to_slice(hex, output).unwrap();
+        assert_eq!(&expected, output);
+
+        #[cfg(feature = "alloc")]
+        {
+            assert_eq!(Ok(&expected[..]), decode(hex).as_deref());
+            assert_eq!(&expected, &decode_secret(hex).unwrap()[..]);
+            assert_eq!(Ok(&[][..]), decode("").as_deref());
+        }
+    }
+
+    #[test]
+    fn test_decode_invalid() {
+        for hex in &[
+            "0", "012", "0g", "g0", " 00", "0x", "/0", ":0", "@0", "G0", "`0", "ff\n",
+        ] {
+            let output = &mut [0xff; 1];
+            let expected = if hex.len().is_multiple_of(2) {
+                Error::InvalidEncoding
+            } else {
+                Error::InvalidLength
+            };
+            if hex.len() == 2 {
+                assert_eq!(Err(expected), decode_to_slice(hex, output));
+                assert_eq!(Err(expected), decode_secret_to_slice(hex, output));
+                assert_eq!(&[0], output);
+            }
+            #[cfg(feature = "alloc")]
+            {
+                assert_eq!(Err(expected), decode(hex));
+                assert_eq!(Err(expected), decode_secret(hex).map(|_| ()));
+            }
+        }
+        let error = Err(Error::InvalidLength);
+        assert_eq!(error, decode_to_slice("0000", &mut [0; 1]));
+        assert_eq!(error, decode_secret_to_slice("00", &mut [0; 2]));
+    }
+
+    #[test]
+    fn test_digits() {
+        for c in 0..=255 {
+            let expected = decode_digit(c);
+            let (value, valid) = decode_digit_ct(c);
+            assert_eq!(expected.is_ok(), bool::from(valid));
+            if let Ok(expected) = expected {
+                assert_eq!(expected, value);
+            }
+        }
+        for nibble in 0..16 {
+            let c = encode_nibble(nibble, LOWER);
+            assert_eq!(b"0123456789abcdef"[nibble as usize], c);
+            assert_eq!(Ok(nibble), decode_digit(c));
+            let c = encode_nibble(nibble, UPPER);
+            assert_eq!(b"0123456789ABCDEF"[nibble as usize], c);
+        }
+    }
+}
@@ -0,0 +1,490 @@
+//! Module for JSON (RFC 8259), as used by JOSE headers and token claims.
+//!
+//! Parsing is strict: it accepts exactly the grammar of RFC 8259, so that trailing commas,
+//! comments, unescaped control characters and unpaired surrogate escapes are rejected. Objects
+//! with duplicate member names are rejected too, as RFC 7515 allows, so that no two parsers can
+//! disagree on which member counts. Nesting is limited to a depth of 64.
+//!
+//! Objects keep their members in order, and formatting with `Display` outputs the compact form
+//! with no whitespace.
+//!
+//! # Examples
+//!
+//! ```
+//! use crypto_pure::encoding::json::{self, Value};
+//! let value = json::parse(r#" {"alg": "HS256", "exp": 1300819380, "admin": true} "#)?;
+//! assert_eq!(Some("HS256"), value.get("alg").and_then(Value::as_str));
+//! assert_eq!(Some(1300819380.0), value.get("exp").and_then(Value::as_f64));
+//! assert_eq!(r#"{"alg":"HS256","exp":1300819380,"admin":true}"#, value.to_string());
+//! # Ok::<(), crypto_pure::error::Error>(())
+//! ```
+use crate::error::Error;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::{self, Write};
+
+const MAX_DEPTH: usize = 64;
+
+/// A JSON value.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    /// `null`
+    Null,
+    /// `true` or `false`
+    Bool(bool),
+    /// A number, which is formatted as `null` if it is not finite
+    Number(f64),
+    /// A string
+    String(String),
+    /// An array
+    Array(Vec<Value>),
+    /// An object, given by its members in order
+    Object(Vec<(String, Value)>),
+}
+
+impl Value {
+    /// Gets the value of the member named `name` if this is an object with one.
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        self.as_object()?
+            .iter()
+            .find(|(member, _)| member == name)
+            .map(|(_, value)| value)
+    }
+
+    /// Gets the boolean if this is one.
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Value::Bool(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// Gets the number if this is one.
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Value::Number(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// Gets the string if this is one.
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Gets the elements if this is an array.
+    pub fn as_array(&self) -> Option<&[Value]> {
+        match self {
+            Value::Array(elements) => Some(elements),
+            _ => None,
+        }
+    }
+
+    /// Gets the members if this is an object.
+    pub fn as_object(&self) -> Option<&[(String, Value)]> {
+        match self {
+            Value::Object(members) => Some(members),
+            _ => None,
+        }
+    }
+}
+
+impl From<bool> for Value {
+    fn from(value: bool) -> Self {
+        Value::Bool(value)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(value: f64) -> Self {
+        Value::Number(value)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(value: &str) -> Self {
+        Value::String(value.into())
+    }
+}
+
+impl From<String> for Value {
+    fn from(value: String) -> Self {
+        Value::String(value)
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Null => f.write_str("null"),
+            Value::Bool(value) => write!(f, "{}", value),
+            Value::Number(value) if value.is_finite() => write!(f, "{}", value),
+            Value::Number(_) => f.write_str("null"),
+            Value::String(value) => write_string(f, value),
+            Value::Array(elements) => {
+                f.write_char('[')?;
+                for (i, element) in elements.iter().enumerate() {
+                    if i > 0 {
+                        f.write_char(',')?;
+                    }
+                    write!(f, "{}", element)?;
+                }
+                f.write_char(']')
+            }
+            Value::Object(members) => {
+                f.write_char('{')?;
+                for (i, (name, value)) in members.iter().enumerate() {
+                    if i > 0 {
+                        f.write_char(',')?;
+                    }
+                    write_string(f, name)?;
+                    write!(f, ":{}", value)?;
+                }
+                f.write_char('}')
+            }
+        }
+    }
+}
+
+/// Parses a JSON text, which may have whitespace around the value.
+///
+/// # Errors
+///
+/// Fails with `Error::InvalidEncoding` if `input` is not valid JSON, has an object with duplicate
+/// member names, is nested too deeply, or has a number too large for `f64`.
+pub fn parse(input: &str) -> Result<Value, Error> {
+    let mut parser = Parser {
+        input: input.as_bytes(),
+        position: 0,
+    };
+    let value = parser.parse_value(0)?;
+    parser.skip_whitespace();
+    if parser.position != parser.input.len() {
+        return Err(Error::InvalidEncoding);
+    }
+    Ok(value)
+}
+
+fn write_string(f: &mut fmt::Formatter, value: &str) -> fmt::Result {
+    f.write_char('"')?;
+    for c in value.chars() {
+        match c {
+            '"' => f.write_str("\\\"")?,
+            '\\' => f.write_str("\\\\")?,
+            '\n' => f.write_str("\\n")?,
+            '\r' => f.write_str("\\r")?,
+            '\t' => f.write_str("\\t")?,
+            '\u{0}'..='\u{1f}' => write!(f, "\\u{:04x}", c as u32)?,
+            _ => f.write_char(c)?,
+        }
+    }
+    f.write_char('"')
+}
+
+struct Parser<'a> {
+    input: &'a [u8],
+    position: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<u8> {
+        self.input.get(self.position).copied()
+    }
+
+    fn next(&mut self) -> Result<u8, Error> {
+        let byte = self.peek().ok_or(Error::InvalidEncoding)?;
+        self.position += 1;
+        Ok(byte)
+    }
+
+    fn expect(&mut self, expected: &[u8]) -> Result<(), Error> {
+        let end = self.position + expected.len();
+        if self.input.get(self.position..end) != Some(expected) {
+            return Err(Error::InvalidEncoding);
+        }
+        self.position = end;
+        Ok(())
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(b' ' | b'\t' | b'\n' | b'\r') = self.peek() {
+            self.position += 1;
+        }
+    }
+
+    fn parse_value(&mut self, depth: usize) -> Result<Value, Error> {
+        self.skip_whitespace();
+        match self.peek().ok_or(Error::InvalidEncoding)? {
+            b'n' => self.expect(b"null").map(|_| Value::Null),
+            b't' => self.expect(b"true").map(|_| Value::Bool(true)),
+            b'f' => self.expect(b"false").map(|_| Value::Bool(false)),
+            b'"' => self.parse_string().map(Value::String),
+            b'-' | b'0'..=b'9' => self.parse_number().map(Value::Number),
+            b'[' | b'{' if depth == MAX_DEPTH => Err(Error::InvalidEncoding),
+            b'[' => self.parse_array(depth + 1).map(Value::Array),
+            b'{' => self.parse_object(depth + 1).map(Value::Object),
+            _ => Err(Error::InvalidEncoding),
+        }
+    }
+
+    fn parse_array(&mut self, depth: usize) -> Result<Vec<Value>, Error> {
+        self.expect(b"[")?;
+        let mut elements = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some(b']') {
+            self.position += 1;
+            return Ok(elements);
+        }
+        loop {
+            elements.push(self.parse_value(depth)?);
+            self.skip_whitespace();
+            match self.next()? {
+                b',' => {}
+                b']' => return Ok(elements),
+                _ => return Err(Error::InvalidEncoding),
+            }
+        }
+    }
+
+    fn parse_object(&mut self, depth: usize) -> Result<Vec<(String, Value)>, Error> {
+        self.expect(b"{")?;
+        let mut members: Vec<(String, Value)> = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some(b'}') {
+            self.position += 1;
+            return Ok(members);
+        }
+        loop {
+            self.skip_whitespace();
+            let name = self.parse_string()?;
+            if members.iter().any(|(member, _)| *member == name) {
+                return Err(Error::InvalidEncoding);
+            }
+            self.skip_whitespace();
+            self.expect(b":")?;
+            let value = self.parse_value(depth)?;
+            members.push((name, value));
+            self.skip_whitespace();
+            match self.next()? {
+                b',' => {}
+                b'}' => return Ok(members),
+                _ => return Err(Error::InvalidEncoding),
+            }
+        }
+    }
+
+    fn parse_string(&mut self) -> Result<String, Error> {
+        self.expect(b"\"")?;
+        let mut output = String::new();
+        loop {
+            // Runs of unescaped characters are copied at once, and are valid UTF-8 since they
+            // end at ASCII characters.
+            let start = self.position;
+            while let Some(byte) = self.peek() {
+                if byte == b'"' || byte == b'\\' || byte < 0x20 {
+                    break;
+                }
+                self.position += 1;
+            }
+            let run = &self.input[start..self.position];
+            output.push_str(core::str::from_utf8(run).map_err(|_| Error::InvalidEncoding)?);
+            match self.next()? {
+                b'"' => return Ok(output),
+                b'\\' => output.push(self.parse_escape()?),
+                _ => return Err(Error::InvalidEncoding),
+            }
+        }
+    }
+
+    fn parse_escape(&mut self) -> Result<char, Error> {
+        Ok(match self.next()? {
+            b'"' => '"',
+            b'\\' => '\\',
+            b'/' => '/',
+            b'b' => '\u{8}',
+            b'f' => '\u{c}',
+            b'n' => '\n',
+            b'r' => '\r',
+            b't' => '\t',
+            b'u' => {
+                let unit = self.parse_hex4()?;
+                let code_point = match unit {
+                    // A high surrogate must be followed by an escaped low surrogate.
+                    0xd800..=0xdbff => {
+                        self.expect(b"\\u")?;
+                        let low = self.parse_hex4()?;
+                        if !(0xdc00..=0xdfff).contains(&low) {
+                            return Err(Error::InvalidEncoding);
+                        }
+                        0x10000 + ((unit - 0xd800) << 10) + (low - 0xdc00)
+                    }
+                    _ => unit,
+                };
+                char::from_u32(code_point).ok_or(Error::InvalidEncoding)?
+            }
+            _ => return Err(Error::InvalidEncoding),
+        })
+    }
+
+    fn parse_hex4(&mut self) -> Result<u32, Error> {
+        (0..4).try_fold(0, |value, _| {
+            let digit = char::from(self.next()?)
+                .to_digit(16)
+                .ok_or(Error::InvalidEncoding)?;
+            Ok(value << 4 | digit)
+        })
+    }
+
+    fn parse_number(&mut self) -> Result<f64, Error> {
+        let start = self.position;
+        if self.peek() == Some(b'-') {
+            self.position += 1;
+        }
+        // The integer part has no leading zeros.
+        match self.next()? {
+            b'0' => {}
+            b'1'..=b'9' => self.skip_digits(),
+            _ => return Err(Error::InvalidEncoding),
+        }
+        if self.peek() == Some(b'.') {
+            self.position += 1;
+            self.expect_digits()?;
+        }
+        if let Some(b'e' | b'E') = self.peek() {
+            self.position += 1;
+            if let Some(b'+' | b'-') = self.peek() {
+                self.position += 1;
+            }
+            self.expect_digits()?;
+        }
+        let text = core::str::from_utf8(&self.input[start..self.position])
+            .map_err(|_| Error::InvalidEncoding)?;
+        match text.parse::<f64>() {
+            Ok(value) if value.is_finite() => Ok(value),
+            _ => Err(Error::InvalidEncoding),
+        }
+    }
+
+    fn skip_digits(&mut self) {
+        while let Some(b'0'..=b'9') = self.peek() {
+            self.position += 1;
+        }
+    }
+
+    fn expect_digits(&mut self) -> Result<(), Error> {
+        match self.peek() {
+            Some(b'0'..=b'9') => {
+                self.skip_digits();
+                Ok(())
+            }
+            _ => Err(Error::InvalidEncoding),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::string::ToString;
+    use std::vec;
+
+    #[test]
+    fn test_parse() {
+        let value = parse(" [null, true, false, -0.5e1, 0, \"\", [], {}, {\"a\": [1]}]\n").unwrap();
+        let expected = Value::Array(vec![
+            Value::Null,
+            Value::Bool(true),
+            Value::Bool(false),
+            Value::Number(-5.0),
+            Value::Number(0.0),
+            Value::String(String::new()),
+            Value::Array(vec![]),
+            Value::Object(vec![]),
+            Value::Object(vec![("a".into(), Value::Array(vec![Value::Number(1.0)]))]),
+        ]);
+        assert_eq!(expected, value);
+        assert_eq!(
+            "[null,true,false,-5,0,\"\",[],{},{\"a\":[1]}]",
+            value.to_string()
+        );
+        assert_eq!(None, value.get("a"));
+        assert_eq!(
+            Some(&[Value::Number(1.0)][..]),
+            value.as_array().unwrap()[8]
+                .get("a")
+                .and_then(Value::as_array)
+        );
+    }
+
+    #[test]
+    fn test_strings() {
+        let value = parse(r#""a\"\\\/\b\f\n\r\t\u0041\u00e9\ud83d\ude00é""#).unwrap();
+        assert_eq!(Some("a\"\\/\u{8}\u{c}\n\r\t\u{41}é😀é"), value.as_str());
+        assert_eq!(r#""a\"\\/\u0008\u000c\n\r\tAé😀é""#, value.to_string());
+        for input in &[
+            "\"",
+            "\"\\\"",
+            "\"\\x\"",
+            "\"\\u12\"",
+            "\"\\u12g4\"",
+            "\"\\ud83d\"",
+            "\"\\ud83d\\u0041\"",
+            "\"\\ude00\"",
+            "\"\t\"",
+            "\"\u{0}\"",
+            "'a'",
+        ] {
+            assert_eq!(Err(Error::InvalidEncoding), parse(input), "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_numbers() {
+        for &(input, expected) in &[
+            ("0", 0.0),
+            ("-0", -0.0),
+            ("1300819380", 1_300_819_380.0),
+            ("1.5", 1.5),
+            ("1E2", 100.0),
+            ("1e+2", 100.0),
+            ("25e-2", 0.25),
+            ("9007199254740993", 9_007_199_254_740_992.0),
+        ] {
+            assert_eq!(Ok(Value::Number(expected)), parse(input), "{}", input);
+        }
+        for input in &[
+            "01", "-", "+1", "1.", ".5", "1e", "1e+", "0x10", "1e999", "NaN", "-01",
+        ] {
+            assert_eq!(Err(Error::InvalidEncoding), parse(input), "{}", input);
+        }
+        assert_eq!("1000000000000000000000", Value::Number(1e21).to_string());
+        assert_eq!("null", Value::Number(f64::NAN).to_string());
+    }
+
+    #[test]
+    fn test_invalid() {
+        for input in &[
+            "",
+            " ",
+            "nul",
+            "True",
+            "[1,]",
+            "[1 2]",
+            "{\"a\":1,}",
+            "{\"a\" 1}",
+            "{a:1}",
+            "{\"a\":1,\"a\":2}",
+            "{\"a\":1}{}",
+            "[1]]",
+            "/* */ 1",
+        ] {
+            assert_eq!(Err(Error::InvalidEncoding), parse(input), "{}", input);
+        }
+        let nested = |depth| "[".repeat(depth) + &"]".repeat(depth);
+        assert!(parse(&nested(MAX_DEPTH)).is_ok());
+        assert_eq!(Err(Error::InvalidEncoding), parse(&nested(MAX_DEPTH + 1)));
+    }
+}
@@ -0,0 +1,153 @@
+//! Module for the error type shared by fallible public APIs.
+//!
+//! The errors of individual modules, such as `hmac::MacError` or `padding::PaddingError`, convert
+//! into `Error`, so `?` can combine them. Panics are reserved for violations of internal
+//! invariants, not for malformed input.
+//!
+//! # Examples
+//!
+//! ```
+//! use crypto_pure::aead::Aead;
+//! use crypto_pure::error::Error;
+//! use crypto_pure::poly1305::ChaCha20Poly1305;
+//! assert_eq!(Err(Error::InvalidKey), ChaCha20Poly1305::new(&[0x42; 16]).map(|_| ()));
+//!
+//! let cipher = ChaCha20Poly1305::new(&[0x42; 32]).unwrap();
+//! let buffer = &mut [b"message" as &[u8], &[0; 16]].concat();
+//! cipher.seal_in_place(&[0x24; 12], b"", buffer)?;
+//! let opened = cipher.open_in_place(&[0x24; 8], b"", buffer);
+//! assert_eq!(Err(Error::InvalidLength), opened.map(|_| ()));
+//! let opened = cipher.open_in_place(&[0x24; 12], b"x", buffer);
+//! assert_eq!(Err(Error::TagMismatch), opened.map(|_| ()));
+//! # Ok::<(), Error>(())
+//! ```
+use crate::aead::nonce::NonceError;
+use crate::hmac::MacError;
+use crate::padding::PaddingError;
+use crate::rand::RandomError;
+#[cfg(feature = "alloc")]
+use crate::rsa::DecryptionError;
+use core::fmt;
+
+/// An error returned by a public API given input it can't process.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Error {
+    /// An input, such as a nonce, buffer or associated data, has a length that is not allowed.
+    InvalidLength,
+    /// A key has the wrong length or is otherwise not valid.
+    InvalidKey,
+    /// A tag failed verification.
+    TagMismatch,
+    /// Padding is malformed.
+    InvalidPadding,
+    /// A nonce sequence is exhausted.
+    NonceExhausted,
+    /// A DRBG must be reseeded before producing more output.
+    ReseedRequired,
+    /// The operating system failed to provide random bytes.
+    RandomFailure,
+    /// A ciphertext failed to decrypt, for a reason that is deliberately not given.
+    DecryptionFailed,
+    /// Encoded data, such as hex or DER, is malformed.
+    InvalidEncoding,
+    /// A signature failed verification.
+    InvalidSignature,
+    /// The claims of a token, such as its expiration time, failed validation.
+    InvalidClaims,
+    /// A certificate chain failed validation, such as by not leading to a trust anchor.
+    InvalidCertificate,
+    /// A proof, such as a Merkle inclusion proof, failed verification.
+    InvalidProof,
+    /// Cost parameters read from an input, such as those of a key derivation function, exceed
+    /// the limits set for them.
+    CostTooHigh,
+    /// A method was called when the state doesn't allow it, such as out of turn in a handshake.
+    InvalidState,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Error::InvalidLength => "invalid length",
+            Error::InvalidKey => "invalid key",
+            Error::TagMismatch => "MAC verification failed",
+            Error::InvalidPadding => "invalid padding",
+            Error::NonceExhausted => "nonce sequence exhausted",
+            Error::ReseedRequired => "DRBG reseed required",
+            Error::RandomFailure => "random number generation failed",
+            Error::DecryptionFailed => "decryption failed",
+            Error::InvalidEncoding => "invalid encoding",
+            Error::InvalidSignature => "signature verification failed",
+            Error::InvalidClaims => "token claims failed validation",
+            Error::InvalidCertificate => "certificate validation failed",
+            Error::InvalidProof => "proof verification failed",
+            Error::CostTooHigh => "cost parameters exceed limits",
+            Error::InvalidState => "invalid state",
+        })
+    }
+}
+
+impl core::error::Error for Error {}
+
+/// Errors from parsing or verifying data read from a stream are reported as invalid data.
+#[cfg(feature = "std")]
+impl From<Error> for std::io::Error {
+    fn from(error: Error) -> Self {
+        std::io::Error::new(std::io::ErrorKind::InvalidData, error)
+    }
+}
+
+impl From<MacError> for Error {
+    fn from(_: MacError) -> Self {
+        Error::TagMismatch
+    }
+}
+
+impl From<PaddingError> for Error {
+    fn from(_: PaddingError) -> Self {
+        Error::InvalidPadding
+    }
+}
+
+impl From<NonceError> for Error {
+    fn from(_: NonceError) -> Self {
+        Error::NonceExhausted
+    }
+}
+
+impl From<RandomError> for Error {
+    fn from(_: RandomError) -> Self {
+        Error::RandomFailure
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl From<DecryptionError> for Error {
+    fn from(_: DecryptionError) -> Self {
+        Error::DecryptionFailed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::string::ToString;
+
+    fn check<E: Into<Error> + fmt::Display>(error: E, expected: Error) {
+        let message = error.to_string();
+        let error = error.into();
+        assert_eq!(expected, error);
+        assert_eq!(message, error.to_string());
+    }
+
+    #[test]
+    fn test_conversions() {
+        check(MacError, Error::TagMismatch);
+        check(PaddingError, Error::InvalidPadding);
+        check(NonceError, Error::NonceExhausted);
+        check(RandomError, Error::RandomFailure);
+        #[cfg(feature = "alloc")]
+        check(DecryptionError, Error::DecryptionFailed);
+    }
+}
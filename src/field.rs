@@ -0,0 +1,345 @@
+//! Module for constant-time arithmetic modulo odd integers of `N` little-endian 64-bit limbs,
+//! shared by the elliptic curves over prime fields and their scalars.
+//!
+//! Multiplication uses Montgomery reduction, so it works for any odd modulus without special
+//! reduction code per prime. Apart from `pow` and `invert`, whose exponents are public, all
+//! operations run in time independent of the values.
+
+/// Arithmetic modulo an odd `m` with `N` little-endian 64-bit limbs, where values are kept below
+/// `m` and multiplication works on the Montgomery form a R mod m, with R = 2^(64 N).
+pub(crate) struct Modulus<const N: usize> {
+    m: [u64; N],
+    /// -m^-1 modulo 2^64
+    m_inv: u64,
+    /// R^2 mod m
+    r2: [u64; N],
+}
+
+impl<const N: usize> Modulus<N> {
+    pub(crate) fn new(m: [u64; N]) -> Self {
+        let mut modulus = Self {
+            m,
+            m_inv: neg_inverse(m[0]),
+            r2: [0; N],
+        };
+        let mut r2 = [0; N];
+        r2[0] = 1;
+        for _ in 0..128 * N {
+            r2 = modulus.add(&r2, &r2);
+        }
+        modulus.r2 = r2;
+        modulus
+    }
+
+    pub(crate) fn add(&self, a: &[u64; N], b: &[u64; N]) -> [u64; N] {
+        let mut sum = [0; N];
+        let mut carry = 0;
+        for i in 0..N {
+            let (value, carry_1) = a[i].overflowing_add(b[i]);
+            let (value, carry_2) = value.overflowing_add(carry);
+            sum[i] = value;
+            carry = u64::from(carry_1 | carry_2);
+        }
+        self.subtract_if_needed(sum, carry)
+    }
+
+    pub(crate) fn sub(&self, a: &[u64; N], b: &[u64; N]) -> [u64; N] {
+        let (mut difference, borrow) = sub_with_borrow(a, b);
+        let mask = borrow.wrapping_neg();
+        let mut carry = 0;
+        for (limb, m) in difference.iter_mut().zip(&self.m) {
+            let (value, carry_1) = limb.overflowing_add(m & mask);
+            let (value, carry_2) = value.overflowing_add(carry);
+            *limb = value;
+            carry = u64::from(carry_1 | carry_2);
+        }
+        difference
+    }
+
+    /// Computes a b R^-1 mod m.
+    pub(crate) fn mul(&self, a: &[u64; N], b: &[u64; N]) -> [u64; N] {
+        let mut t = [0; N];
+        let t_high = montgomery_mul(a, b, &self.m, self.m_inv, &mut t);
+        self.subtract_if_needed(t, t_high)
+    }
+
+    pub(crate) fn square(&self, a: &[u64; N]) -> [u64; N] {
+        self.mul(a, a)
+    }
+
+    /// Converts a value into Montgomery form.
+    pub(crate) fn to_montgomery(&self, a: &[u64; N]) -> [u64; N] {
+        self.mul(a, &self.r2)
+    }
+
+    /// Converts a value out of Montgomery form.
+    pub(crate) fn to_normal(&self, a: &[u64; N]) -> [u64; N] {
+        let mut one = [0; N];
+        one[0] = 1;
+        self.mul(a, &one)
+    }
+
+    /// Outputs R mod m, the Montgomery form of 1.
+    pub(crate) fn one(&self) -> [u64; N] {
+        let mut one = [0; N];
+        one[0] = 1;
+        self.to_montgomery(&one)
+    }
+
+    /// Raises a Montgomery-form value to a public exponent.
+    pub(crate) fn pow(&self, a: &[u64; N], exponent: &[u64; N]) -> [u64; N] {
+        let mut result = self.one();
+        for i in (0..64 * N).rev() {
+            result = self.square(&result);
+            if (exponent[i / 64] >> (i % 64)) & 1 == 1 {
+                result = self.mul(&result, a);
+            }
+        }
+        result
+    }
+
+    /// Computes the inverse of a Montgomery-form value as a^(m - 2), so m must be prime. The
+    /// inverse of zero is zero.
+    pub(crate) fn invert(&self, a: &[u64; N]) -> [u64; N] {
+        let mut two = [0; N];
+        two[0] = 2;
+        let (exponent, _) = sub_with_borrow(&self.m, &two);
+        self.pow(a, &exponent)
+    }
+
+    /// Outputs the modulus.
+    pub(crate) fn modulus(&self) -> &[u64; N] {
+        &self.m
+    }
+
+    /// Reduces a value below 2 m.
+    pub(crate) fn reduce_once(&self, a: &[u64; N]) -> [u64; N] {
+        self.subtract_if_needed(*a, 0)
+    }
+
+    /// Checks whether a value is below m.
+    pub(crate) fn contains(&self, a: &[u64; N]) -> bool {
+        sub_with_borrow(a, &self.m).1 == 1
+    }
+
+    /// Subtracts m from `high` 2^(64 N) + `low` if the result is not negative, where the value is
+    /// below 2 m.
+    fn subtract_if_needed(&self, low: [u64; N], high: u64) -> [u64; N] {
+        let (difference, borrow) = sub_with_borrow(&low, &self.m);
+        // Keep the original value only if the subtraction borrowed past the high word.
+        let keep = (borrow & !high & 1).wrapping_neg();
+        select(&low, &difference, keep)
+    }
+}
+
+/// Outputs -m^-1 modulo 2^64 for an odd m, given its lowest limb.
+pub(crate) fn neg_inverse(m_0: u64) -> u64 {
+    // Newton's iteration doubles the number of correct low bits of m^-1 each time, starting from
+    // 1 correct bit, so six iterations give all 64.
+    let mut inv = 1_u64;
+    for _ in 0..6 {
+        inv = inv.wrapping_mul(2_u64.wrapping_sub(m_0.wrapping_mul(inv)));
+    }
+    inv.wrapping_neg()
+}
+
+/// Computes a b R^-1 with the CIOS method into `t`, which must be zero and have as many limbs as
+/// `m`, and outputs the limb above `t`. The result is below 2 m for `a` and `b` below m, where
+/// `m_inv` is `neg_inverse(m[0])`. This is the core of both this module's `Modulus` and the
+/// run-time sized one of the `bigint` module.
+pub(crate) fn montgomery_mul(a: &[u64], b: &[u64], m: &[u64], m_inv: u64, t: &mut [u64]) -> u64 {
+    let n = m.len();
+    let mut t_high = 0_u64;
+    for b_i in b {
+        let mut carry = 0;
+        for j in 0..n {
+            (t[j], carry) = mul_add(a[j], *b_i, t[j], carry);
+        }
+        let (value, overflow) = t_high.overflowing_add(carry);
+        t_high = value;
+        let t_top = u64::from(overflow);
+
+        let q = t[0].wrapping_mul(m_inv);
+        let (_, mut carry) = mul_add(q, m[0], t[0], 0);
+        for j in 1..n {
+            (t[j - 1], carry) = mul_add(q, m[j], t[j], carry);
+        }
+        let (value, overflow) = t_high.overflowing_add(carry);
+        t[n - 1] = value;
+        t_high = t_top + u64::from(overflow);
+    }
+    t_high
+}
+
+/// Computes a b + c + d, outputting the low and high limbs.
+pub(crate) fn mul_add(a: u64, b: u64, c: u64, d: u64) -> (u64, u64) {
+    let value = u128::from(a) * u128::from(b) + u128::from(c) + u128::from(d);
+    (value as u64, (value >> 64) as u64)
+}
+
+pub(crate) fn sub_with_borrow<const N: usize>(a: &[u64; N], b: &[u64; N]) -> ([u64; N], u64) {
+    let mut difference = [0; N];
+    let mut borrow = 0;
+    for i in 0..N {
+        let (value, borrow_1) = a[i].overflowing_sub(b[i]);
+        let (value, borrow_2) = value.overflowing_sub(borrow);
+        difference[i] = value;
+        borrow = u64::from(borrow_1 | borrow_2);
+    }
+    (difference, borrow)
+}
+
+/// Outputs `a` if `mask` is all ones and `b` if it is zero.
+pub(crate) fn select<const N: usize>(a: &[u64; N], b: &[u64; N], mask: u64) -> [u64; N] {
+    let mut result = [0; N];
+    for i in 0..N {
+        result[i] = (a[i] & mask) | (b[i] & !mask);
+    }
+    result
+}
+
+pub(crate) fn is_zero<const N: usize>(a: &[u64; N]) -> bool {
+    a.iter().fold(0, |acc, limb| acc | limb) == 0
+}
+
+/// Reads a big-endian integer of exactly 8 N bytes.
+pub(crate) fn from_be_bytes<const N: usize>(bytes: &[u8]) -> [u64; N] {
+    assert_eq!(8 * N, bytes.len());
+    let mut limbs = [0; N];
+    for (limb, chunk) in limbs.iter_mut().zip(bytes.rchunks(8)) {
+        let mut buffer = [0; 8];
+        buffer.copy_from_slice(chunk);
+        *limb = u64::from_be_bytes(buffer);
+    }
+    limbs
+}
+
+/// Writes an integer as exactly 8 N big-endian bytes.
+pub(crate) fn to_be_bytes<const N: usize>(limbs: &[u64; N], bytes: &mut [u8]) {
+    assert_eq!(8 * N, bytes.len());
+    for (limb, chunk) in limbs.iter().zip(bytes.rchunks_mut(8)) {
+        chunk.copy_from_slice(&limb.to_be_bytes());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_modulus() {
+        // 2^127 - 1 split into two limbs
+        let modulus = Modulus::new([u64::MAX, (1 << 63) - 1]);
+        let a = [0x1234_5678_9abc_def0, 0x0fed_cba9_8765_4321];
+        let b = [u64::MAX - 6, (1 << 63) - 1];
+        let a_m = modulus.to_montgomery(&a);
+        let b_m = modulus.to_montgomery(&b);
+        assert_eq!(a, modulus.to_normal(&a_m));
+
+        // b = -6, so a b = -6 a and a + b = a - 6
+        let product = modulus.to_normal(&modulus.mul(&a_m, &b_m));
+        let six_a = (0..6).fold([0; 2], |acc, _| modulus.add(&acc, &a));
+        assert_eq!(modulus.sub(&[0; 2], &six_a), product);
+        assert_eq!(modulus.sub(&a, &[6, 0]), modulus.add(&a, &b));
+        assert_eq!(modulus.one(), modulus.mul(&a_m, &modulus.invert(&a_m)));
+        assert_eq!([0; 2], modulus.invert(&[0; 2]));
+        assert!(modulus.contains(&b));
+        assert!(!modulus.contains(&[u64::MAX, (1 << 63) - 1]));
+    }
+
+    /// A xorshift generator for reproducible pseudorandom test inputs.
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        /// Outputs a value below the modulus, or one of the edge cases 0, 1 and m - 1.
+        fn element<const N: usize>(&mut self, modulus: &Modulus<N>) -> [u64; N] {
+            let mut one = [0; N];
+            one[0] = 1;
+            match self.next() % 8 {
+                0 => [0; N],
+                1 => one,
+                2 => sub_with_borrow(modulus.modulus(), &one).0,
+                _ => loop {
+                    let mut a = [0; N];
+                    a.iter_mut().for_each(|limb| *limb = self.next());
+                    a[N - 1] &= u64::MAX >> modulus.modulus()[N - 1].leading_zeros();
+                    if modulus.contains(&a) {
+                        break a;
+                    }
+                },
+            }
+        }
+    }
+
+    /// Multiplies by doubling and adding, independently of Montgomery reduction.
+    fn reference_mul<const N: usize>(modulus: &Modulus<N>, a: &[u64; N], b: &[u64; N]) -> [u64; N] {
+        let mut product = [0; N];
+        for i in (0..64 * N).rev() {
+            product = modulus.add(&product, &product);
+            if (b[i / 64] >> (i % 64)) & 1 == 1 {
+                product = modulus.add(&product, a);
+            }
+        }
+        product
+    }
+
+    fn check_properties<const N: usize>(m: [u64; N], prime: bool) {
+        let modulus = &Modulus::new(m);
+        let rng = &mut Rng(0x0123_4567_89ab_cdef ^ m[0]);
+        for _ in 0..100 {
+            let (a, b, c) = (
+                rng.element(modulus),
+                rng.element(modulus),
+                rng.element(modulus),
+            );
+            let sum = modulus.add(&a, &b);
+            assert!(modulus.contains(&sum));
+            assert_eq!(sum, modulus.add(&b, &a));
+            assert_eq!(a, modulus.sub(&sum, &b));
+            assert_eq!([0; N], modulus.sub(&a, &a));
+
+            let (a_m, b_m, c_m) = (
+                modulus.to_montgomery(&a),
+                modulus.to_montgomery(&b),
+                modulus.to_montgomery(&c),
+            );
+            assert_eq!(a, modulus.to_normal(&a_m));
+            let product = modulus.mul(&a_m, &b_m);
+            assert!(modulus.contains(&product));
+            assert_eq!(reference_mul(modulus, &a, &b), modulus.to_normal(&product));
+            assert_eq!(product, modulus.mul(&b_m, &a_m));
+            assert_eq!(
+                modulus.mul(&product, &c_m),
+                modulus.mul(&a_m, &modulus.mul(&b_m, &c_m))
+            );
+            assert_eq!(
+                modulus.mul(&a_m, &modulus.add(&b_m, &c_m)),
+                modulus.add(&product, &modulus.mul(&a_m, &c_m))
+            );
+            if prime && a != [0; N] {
+                assert_eq!(modulus.one(), modulus.mul(&a_m, &modulus.invert(&a_m)));
+            }
+            assert_eq!(a, modulus.reduce_once(&a));
+        }
+    }
+
+    #[test]
+    fn test_properties() {
+        for params in &[&crate::p256::PARAMS, &crate::secp256k1::PARAMS] {
+            check_properties(params.p, true);
+            check_properties(params.n, true);
+        }
+        check_properties(crate::p384::PARAMS.p, true);
+        check_properties(crate::p384::PARAMS.n, true);
+        check_properties([u64::MAX, (1 << 63) - 1], true);
+        check_properties([0xffff_ffff_ffff_ffc5], true);
+        check_properties([0x1234_5678_9abc_def1, 0xff, 0, 1 << 40], false);
+    }
+}
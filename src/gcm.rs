@@ -1,106 +1,132 @@
 //! Module for the Galois/Counter Mode (GCM) mode of operation for block ciphers.
+use crate::aead::Aead;
 use crate::aes::BlockCipher;
-use crate::ghash;
-use crate::util;
+use crate::ct;
+use crate::error::Error;
+use crate::ghash::GHash;
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
 use byteorder::{BigEndian, ByteOrder as _};
 
-pub trait AeadCipher {
-    /// Initializes an AEAD Cipher given a key.
-    fn new(key: &[u8]) -> Self;
+/// An AEAD cipher in GCM mode.
+pub struct Gcm<E>(Processor<E>);
 
-    /// Encrypts a message into a ciphertext and outputs a tag authenticating it and provided data.
-    fn encrypt(&self, input: &[u8], nonce: &[u8], data: &[u8], output: &mut [u8]) -> [u8; 16];
+impl<E: BlockCipher> Aead for Gcm<E> {
+    const KEY_LEN: usize = E::KEY_LEN;
+    const NONCE_LEN: usize = 12;
 
-    /// Decrypts a ciphertext into a message if tag verification passes.
-    fn decrypt(
+    /// Initializes an AEAD block cipher in GCM mode given a key.
+    fn new(key: &[u8]) -> Result<Self, Error> {
+        Ok(Self(Processor::new(key)?))
+    }
+
+    /// Encrypts a message in place and outputs a tag authenticating it and provided data. Fails
+    /// with `Error::InvalidLength` if `nonce.len()` is not equal to 12, `buffer.len()` is not
+    /// less than 2^36 - 32, or `data.len()` is not less than 2^61.
+    fn seal_detached(
         &self,
-        input: &[u8],
         nonce: &[u8],
         data: &[u8],
-        tag: &[u8],
-        output: &mut [u8],
-    ) -> bool;
-}
-
-/// An AEAD cipher in GCM mode.
-pub struct Gcm<E>(Processor<E>);
-
-impl<E: BlockCipher> AeadCipher for Gcm<E> {
-    /// Initializes an AEAD block cipher in GCM mode given a key.
-    ///
-    /// # Panics
-    ///
-    /// Panics if `key.len()` is not appropriate for the block cipher.
-    fn new(key: &[u8]) -> Self {
-        Self(Processor::new(key))
-    }
-
-    /// Encrypts a message into a ciphertext and outputs a tag authenticating it and provided data.
-    ///
-    /// # Panics
-    ///
-    /// Panics if `input.len()` is not equal to `output.len()`, `nonce.len()` is not equal to 12,
-    /// `message.len()` is not less than 2^36 - 32, or `data.len()` is not less than 2^61.
-    fn encrypt(&self, input: &[u8], nonce: &[u8], data: &[u8], output: &mut [u8]) -> [u8; 16] {
-        check_bounds(input, output, nonce, data);
+        buffer: &mut [u8],
+    ) -> Result<[u8; 16], Error> {
+        check_bounds(buffer, nonce, data)?;
         let counter = &mut counter(nonce);
-        self.0.process(counter, input, output);
-        self.0.tag(output, data, counter)
+        self.0.process(counter, buffer);
+        Ok(self.0.tag(buffer, data, counter))
     }
 
-    /// Decrypts a ciphertext into a message if tag verification passes.
-    ///
-    /// # Panics
-    ///
-    /// Panics if `input.len()` is not equal to `output.len()`, `nonce.len()` is not equal to 12,
-    /// `message.len()` is not less than 2^36 - 32, or `data.len()` is not less than 2^61.
-    fn decrypt(
+    /// Verifies `tag` and decrypts a ciphertext in place. On failure, `buffer` is left unchanged.
+    /// Fails with `Error::InvalidLength` if `nonce.len()` is not equal to 12, `buffer.len()` is
+    /// not less than 2^36 - 32, or `data.len()` is not less than 2^61.
+    fn open_detached(
         &self,
-        input: &[u8],
         nonce: &[u8],
         data: &[u8],
-        tag: &[u8],
-        output: &mut [u8],
-    ) -> bool {
-        check_bounds(output, input, nonce, data);
+        buffer: &mut [u8],
+        tag: &[u8; 16],
+    ) -> Result<(), Error> {
+        check_bounds(buffer, nonce, data)?;
         let counter = &mut counter(nonce);
-        let expected_tag = self.0.tag(input, data, counter);
-        if util::verify_16(&expected_tag, tag) {
-            self.0.process(counter, input, output);
-            true
-        } else {
-            false
+        let expected_tag = self.0.tag(buffer, data, counter);
+        if !bool::from(ct::ct_eq(&expected_tag, tag)) {
+            return Err(Error::TagMismatch);
         }
+        self.0.process(counter, buffer);
+        Ok(())
     }
 }
 
+/// Encrypts a message with GCM and outputs the ciphertext with the 16-byte tag appended.
+///
+/// # Errors
+///
+/// Fails with `Error::InvalidKey` if `key.len()` is not appropriate for the block cipher, or with
+/// `Error::InvalidLength` if `nonce.len()` is not equal to 12 or the message or data are too long
+/// for GCM.
+///
+/// # Examples
+///
+/// ```
+/// use crypto_pure::aes::Aes256;
+/// use crypto_pure::gcm;
+/// # let key = &[0x42; 32];
+/// # let nonce = &[0x24; 12];
+/// let sealed = gcm::seal::<Aes256>(key, nonce, b"header", b"message").unwrap();
+/// let opened = gcm::open::<Aes256>(key, nonce, b"header", &sealed);
+/// assert_eq!(Ok(b"message".to_vec()), opened);
+/// ```
+#[cfg(feature = "alloc")]
+pub fn seal<E: BlockCipher>(
+    key: &[u8],
+    nonce: &[u8],
+    data: &[u8],
+    message: &[u8],
+) -> Result<Vec<u8>, Error> {
+    Gcm::<E>::new(key)?.seal(nonce, data, message)
+}
+
+/// Decrypts the output of `seal`, verifying its tag in constant time.
+///
+/// # Errors
+///
+/// Fails with `Error::TagMismatch` if verification fails, and otherwise as `seal` does.
+#[cfg(feature = "alloc")]
+pub fn open<E: BlockCipher>(
+    key: &[u8],
+    nonce: &[u8],
+    data: &[u8],
+    sealed: &[u8],
+) -> Result<Vec<u8>, Error> {
+    Gcm::<E>::new(key)?.open(nonce, data, sealed)
+}
+
 struct Processor<E> {
     block_cipher: E,
+    ghash: GHash,
 }
 
 impl<E: BlockCipher> Processor<E> {
-    fn new(key: &[u8]) -> Self {
-        Self {
-            block_cipher: E::new(key),
-        }
+    fn new(key: &[u8]) -> Result<Self, Error> {
+        let block_cipher = E::new(key)?;
+        let ghash = GHash::new(&block_cipher.encrypt_block(&[0; 16]));
+        Ok(Self {
+            block_cipher,
+            ghash,
+        })
     }
 
-    fn process(&self, counter: &mut [u8; 16], input: &[u8], output: &mut [u8]) {
-        for (i, (input_chunk, output_chunk)) in
-            (2..).zip(input.chunks(16).zip(output.chunks_mut(16)))
-        {
+    fn process(&self, counter: &mut [u8; 16], buffer: &mut [u8]) {
+        for (i, chunk) in (2..).zip(buffer.chunks_mut(16)) {
             let block = self.block(counter, i);
-            for (block_byte, (input_byte, output_byte)) in
-                block.iter().zip(input_chunk.iter().zip(output_chunk))
-            {
-                *output_byte = input_byte ^ block_byte;
+            for (byte, block_byte) in chunk.iter_mut().zip(&block) {
+                *byte ^= block_byte;
             }
         }
     }
 
     fn block(&self, counter: &mut [u8; 16], i: u32) -> [u8; 16] {
         BigEndian::write_u32(&mut counter[12..], i);
-        self.block_cipher.permute(counter)
+        self.block_cipher.encrypt_block(counter)
     }
 
     fn tag(&self, ciphertext: &[u8], data: &[u8], counter: &mut [u8; 16]) -> [u8; 16] {
@@ -113,8 +139,10 @@ impl<E: BlockCipher> Processor<E> {
     }
 
     fn ghash(&self, a: &[u8], c: &[u8]) -> [u8; 16] {
-        let key = &self.block_cipher.permute(&[0; 16]);
-        ghash::ghash(key, a, c)
+        let mut ghash = self.ghash.clone();
+        ghash.update_aad(a);
+        ghash.update_ciphertext(c);
+        ghash.finalize()
     }
 }
 
@@ -124,11 +152,11 @@ fn counter(nonce: &[u8]) -> [u8; 16] {
     counter
 }
 
-fn check_bounds(message: &[u8], ciphertext: &[u8], nonce: &[u8], data: &[u8]) {
-    assert_eq!(12, nonce.len());
-    assert_eq!(message.len(), ciphertext.len());
-    assert!(1 << 36 > message.len() + 32);
-    assert!(1 << 61 > data.len());
+fn check_bounds(message: &[u8], nonce: &[u8], data: &[u8]) -> Result<(), Error> {
+    if nonce.len() != 12 || 1 << 36 <= message.len() as u64 + 32 || 1 << 61 <= data.len() as u64 {
+        return Err(Error::InvalidLength);
+    }
+    Ok(())
 }
 
 #[cfg(test)]
@@ -144,15 +172,107 @@ mod tests {
         let data = &h2b(data);
         let tag = &h2b(tag);
         let ciphertext = &h2b(ct);
-        let gcm = Gcm::<E>::new(key);
+        let gcm = Gcm::<E>::new(key).unwrap();
         let encrypted_message = &mut vec![0; message.len()];
         let decrypted_ciphertext = &mut vec![0; ciphertext.len()];
         let actual_tag = gcm.encrypt(message, nonce, data, encrypted_message);
         assert_eq!(ciphertext, encrypted_message);
-        assert_eq!(tag, &actual_tag);
-        assert!(gcm.decrypt(ciphertext, nonce, data, tag, decrypted_ciphertext));
+        assert_eq!(tag, &actual_tag.unwrap());
+        let decrypted = gcm.decrypt(ciphertext, nonce, data, tag, decrypted_ciphertext);
+        assert_eq!(Ok(()), decrypted);
         assert_eq!(message, decrypted_ciphertext);
-        // TODO: check that bad tags cause decryption to fail
+
+        let bad_tag = &mut tag.clone();
+        bad_tag[15] ^= 0x80;
+        let error = Err(Error::TagMismatch);
+        assert_eq!(
+            error,
+            gcm.decrypt(ciphertext, nonce, data, bad_tag, decrypted_ciphertext)
+        );
+        let short_tag = &tag[..15];
+        assert_eq!(
+            error,
+            gcm.decrypt(ciphertext, nonce, data, short_tag, decrypted_ciphertext)
+        );
+        let error = Err(Error::InvalidLength);
+        assert_eq!(
+            error,
+            gcm.decrypt(ciphertext, nonce, data, tag, &mut [0; 100])
+        );
+        assert_eq!(
+            error,
+            gcm.encrypt(message, &nonce[1..], data, encrypted_message)
+                .map(|_| ())
+        );
+
+        let buffer = &mut [&message[..], &[0; 16]].concat();
+        gcm.seal_in_place(nonce, data, buffer).unwrap();
+        let sealed = &[&ciphertext[..], tag].concat();
+        assert_eq!(sealed, buffer);
+        assert_eq!(
+            Ok(&mut message.clone()[..]),
+            gcm.open_in_place(nonce, data, buffer)
+        );
+        buffer.copy_from_slice(sealed);
+        buffer[0] ^= 1;
+        assert_eq!(
+            Err(Error::TagMismatch),
+            gcm.open_in_place(nonce, data, buffer)
+        );
+        buffer[0] ^= 1;
+        assert_eq!(sealed, buffer);
+        // Dropping a byte leaves too few for the tag if the message is empty.
+        let truncated = if message.is_empty() {
+            Err(Error::InvalidLength)
+        } else {
+            Err(Error::TagMismatch)
+        };
+        assert_eq!(
+            truncated,
+            gcm.open_in_place(nonce, data, &mut buffer[1..]).map(|_| ())
+        );
+        assert_eq!(
+            Err(Error::InvalidLength),
+            gcm.open_in_place(&[0; 16], data, buffer)
+        );
+        assert_eq!(sealed, buffer);
+
+        let buffer = &mut message.clone();
+        let detached_tag = gcm.seal_detached(nonce, data, buffer).unwrap();
+        assert_eq!(ciphertext, buffer);
+        assert_eq!(tag, &detached_tag);
+        let bad_tag = &mut detached_tag.clone();
+        bad_tag[0] ^= 1;
+        assert_eq!(
+            Err(Error::TagMismatch),
+            gcm.open_detached(nonce, data, buffer, bad_tag)
+        );
+        assert_eq!(ciphertext, buffer);
+        assert_eq!(
+            Ok(()),
+            gcm.open_detached(nonce, data, buffer, &detached_tag)
+        );
+        assert_eq!(message, buffer);
+
+        #[cfg(feature = "alloc")]
+        {
+            let sealed = seal::<E>(key, nonce, data, message).unwrap();
+            assert_eq!(&[&ciphertext[..], tag].concat(), &sealed);
+            assert_eq!(Ok(message.clone()), open::<E>(key, nonce, data, &sealed));
+            let bad_sealed = &mut sealed.clone();
+            bad_sealed[0] ^= 1;
+            let error = Err(Error::TagMismatch);
+            assert_eq!(error, open::<E>(key, nonce, data, bad_sealed));
+            assert_eq!(
+                truncated,
+                open::<E>(key, nonce, data, &sealed[1..]).map(|_| ())
+            );
+            let error = Err(Error::InvalidLength);
+            assert_eq!(error, open::<E>(key, nonce, data, &tag[1..]));
+            let error = Err(Error::InvalidKey);
+            assert_eq!(error, seal::<E>(&key[1..], nonce, data, message));
+            assert_eq!(error, open::<E>(&key[1..], nonce, data, &sealed));
+        }
     }
 
     #[test]
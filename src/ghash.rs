@@ -1,100 +1,293 @@
+//! Module for the GHASH universal hash function used by GCM (NIST SP 800-38D).
+//!
+//! GHASH is only secure as a MAC when its output is encrypted, as GCM and GMAC do.
+//!
+//! When the CPU supports them, carry-less multiplication instructions (PCLMULQDQ on x86 and
+//! x86-64, or PMULL on AArch64) are used for the field multiplication. Otherwise, a key-specific
+//! precomputation lets integer multiplication stand in for carry-less multiplication, still in
+//! constant time.
+#[cfg(target_arch = "aarch64")]
+use self::armv8 as hardware;
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+use self::x86 as hardware;
+use crate::secret::SecretKey;
 use byteorder::{BigEndian, ByteOrder as _};
 
-pub(crate) fn ghash(key: &[u8; 16], data: &[u8], ciphertext: &[u8]) -> [u8; 16] {
-    let mut tag = [0; 16];
-    let mut mac = GHash::new(key, data);
-    mac.update(ciphertext);
-    mac.write_tag(&mut tag);
-    tag
+#[cfg(target_arch = "aarch64")]
+mod armv8;
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+mod x86;
+
+#[cfg(not(any(target_arch = "aarch64", target_arch = "x86", target_arch = "x86_64")))]
+mod hardware {
+    pub(super) fn available() -> bool {
+        false
+    }
+
+    pub(super) unsafe fn clmul(_: u128, _: u128) -> (u128, u128) {
+        unreachable!()
+    }
+}
+
+/// Computes POLYVAL from AES-GCM-SIV (RFC 8452) over padded data, padded plaintext and their bit
+/// lengths. POLYVAL is GHASH on byte-reversed blocks with the key multiplied by x.
+pub(crate) fn polyval(key: &[u8; 16], data: &[u8], plaintext: &[u8]) -> [u8; 16] {
+    let mut function = PolyFunction::new(mul_x(u128::from_le_bytes(*key)));
+    function.process_padded(data, u128::from_le_bytes);
+    function.process_padded(plaintext, u128::from_le_bytes);
+    let data_bits = u128::from(8 * data.len() as u64);
+    let plaintext_bits = u128::from(8 * plaintext.len() as u64);
+    function.process_block(data_bits | plaintext_bits << 64);
+    function.state.to_le_bytes()
 }
 
 const R0: u128 = 0xe1 << 120;
 
-struct GHash {
+/// The GHASH function, computed incrementally over additional data followed by a ciphertext.
+///
+/// # Examples
+///
+/// ```
+/// use crypto_pure::ghash::GHash;
+/// # let key = &[0x42; 16];
+/// let mut mac = GHash::new(key);
+/// mac.update_aad(b"header");
+/// mac.update_ciphertext(b"part one");
+/// mac.update_ciphertext(b"part two");
+/// let output = mac.finalize();
+/// # assert_eq!(16, output.len());
+/// ```
+#[derive(Clone)]
+pub struct GHash {
     function: PolyFunction,
+    buffer: [u8; 16],
+    offset: usize,
     data_len: u64,
     ciphertext_len: u64,
+    in_ciphertext: bool,
 }
 
 impl GHash {
-    fn new(key: &[u8; 16], data: &[u8]) -> Self {
-        let mut ghash = Self {
-            function: PolyFunction::new(key),
-            data_len: data.len() as u64,
+    /// Initializes GHASH given a key, which for GCM is the encryption of the zero block.
+    pub fn new(key: &[u8; 16]) -> Self {
+        Self {
+            function: PolyFunction::new(u128::from_be_bytes(*key)),
+            buffer: [0; 16],
+            offset: 0,
+            data_len: 0,
             ciphertext_len: 0,
-        };
-        ghash.process(data);
-        ghash
+            in_ciphertext: false,
+        }
     }
 
-    fn update(&mut self, input: &[u8]) {
-        self.ciphertext_len += input.len() as u64;
-        self.process(input);
+    /// Feeds additional data into the function.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `update_ciphertext` has already been called.
+    pub fn update_aad(&mut self, data: &[u8]) {
+        assert!(!self.in_ciphertext);
+        self.data_len += data.len() as u64;
+        self.process(data);
     }
 
-    fn write_tag(mut self, output: &mut [u8; 16]) {
-        BigEndian::write_u64(&mut output[..8], 8 * self.data_len);
-        BigEndian::write_u64(&mut output[8..], 8 * self.ciphertext_len);
-        self.function.process(output);
-        self.function.write_value(output);
+    /// Feeds ciphertext into the function. The additional data must all be fed first.
+    pub fn update_ciphertext(&mut self, ciphertext: &[u8]) {
+        if !self.in_ciphertext {
+            self.pad();
+            self.in_ciphertext = true;
+        }
+        self.ciphertext_len += ciphertext.len() as u64;
+        self.process(ciphertext);
+    }
+
+    /// Outputs the value of the function for the input fed so far.
+    pub fn finalize(mut self) -> [u8; 16] {
+        self.pad();
+        let lengths = u128::from(8 * self.data_len) << 64 | u128::from(8 * self.ciphertext_len);
+        self.function.process_block(lengths);
+        self.function.state.to_be_bytes()
     }
 
     fn process(&mut self, input: &[u8]) {
-        for chunk in input.chunks(16) {
-            if chunk.len() < 16 {
-                let buffer = &mut [0; 16];
-                buffer[..chunk.len()].copy_from_slice(chunk);
-                self.function.process(buffer);
-            } else {
-                self.function.process(chunk);
+        let mut input = input;
+        if self.offset != 0 {
+            let len = input.len().min(16 - self.offset);
+            self.buffer[self.offset..self.offset + len].copy_from_slice(&input[..len]);
+            self.offset += len;
+            input = &input[len..];
+            if self.offset < 16 {
+                return;
             }
+            self.function
+                .process_block(BigEndian::read_u128(&self.buffer));
+            self.offset = 0;
+        }
+        let full_len = input.len() - input.len() % 16;
+        self.function
+            .process_padded(&input[..full_len], u128::from_be_bytes);
+        let remainder = &input[full_len..];
+        self.buffer[..remainder.len()].copy_from_slice(remainder);
+        self.offset = remainder.len();
+    }
+
+    fn pad(&mut self) {
+        if self.offset != 0 {
+            for byte in self.buffer.iter_mut().skip(self.offset) {
+                *byte = 0;
+            }
+            self.function
+                .process_block(BigEndian::read_u128(&self.buffer));
+            self.offset = 0;
         }
     }
 }
 
 type GFBlock = u128;
 
+#[derive(Clone)]
 struct PolyFunction {
-    key_block: GFBlock,
+    key_block: SecretKey<GFBlock>,
+    software_key: Option<SoftwareKey>,
     state: GFBlock,
 }
 
 impl PolyFunction {
-    fn new(key: &[u8; 16]) -> Self {
+    fn new(key_block: GFBlock) -> Self {
+        let software_key = if hardware::available() {
+            None
+        } else {
+            Some(SoftwareKey::new(key_block))
+        };
         Self {
-            key_block: u128::from_be_bytes(*key),
+            key_block: SecretKey::new(key_block),
+            software_key,
             state: 0,
         }
     }
 
-    fn process(&mut self, input: &[u8]) {
-        self.state ^= BigEndian::read_u128(input);
+    fn process_padded(&mut self, input: &[u8], read: fn([u8; 16]) -> GFBlock) {
+        for chunk in input.chunks(16) {
+            let mut buffer = [0; 16];
+            buffer[..chunk.len()].copy_from_slice(chunk);
+            self.process_block(read(buffer));
+        }
+    }
 
-        let mut x = self.state;
-        let mut v = self.key_block;
-        self.state = 0;
-        for _ in 0..128 {
-            let mut h = x & (1 << 127);
-            let mut m = (h as i128 >> 127) as u128;
-            self.state ^= v & m;
-            h = v << 127;
-            m = (h as i128 >> 127) as u128;
-            v >>= 1;
-            v ^= R0 & m;
-            x <<= 1;
+    fn process_block(&mut self, block: GFBlock) {
+        self.state ^= block;
+        let (high, low) = match &self.software_key {
+            Some(software_key) => software_key.clmul(self.state),
+            // Safe since there is only no software key if the CPU supports the instructions.
+            None => unsafe { hardware::clmul(self.state, *self.key_block) },
+        };
+        self.state = reduce(high, low);
+    }
+}
+
+const MASKS: [u64; 4] = [
+    0x1111_1111_1111_1111,
+    0x2222_2222_2222_2222,
+    0x4444_4444_4444_4444,
+    0x8888_8888_8888_8888,
+];
+
+/// A key block prepared for carry-less multiplication without hardware support.
+///
+/// The key's 64-bit halves, their sum (for Karatsuba multiplication), and the bit reversals of
+/// all three are each split into four parts with bits spaced four apart. Splitting the other
+/// operand the same way, integer products of the parts leave enough room between bits that
+/// carries never reach a bit that is kept. Unlike multiplying with lookup tables, this does not
+/// access memory at addresses that depend on secret data.
+#[derive(Clone)]
+struct SoftwareKey {
+    parts: SecretKey<[[u64; 4]; 6]>,
+}
+
+impl SoftwareKey {
+    fn new(key_block: GFBlock) -> Self {
+        let (high, low) = ((key_block >> 64) as u64, key_block as u64);
+        let mut parts = [[0; 4]; 6];
+        for (pair, half) in parts.chunks_mut(2).zip(&[low, high, low ^ high]) {
+            pair[0] = split(*half);
+            pair[1] = split(half.reverse_bits());
+        }
+        Self {
+            parts: SecretKey::new(parts),
         }
     }
 
-    fn write_value(self, output: &mut [u8; 16]) {
-        BigEndian::write_u128(output, self.state);
+    /// Outputs the high and low halves of the carry-less product of `x` and the key block.
+    fn clmul(&self, x: GFBlock) -> (u128, u128) {
+        let (x_high, x_low) = ((x >> 64) as u64, x as u64);
+        let parts = &self.parts;
+        let low = clmul64(x_low, &parts[0], &parts[1]);
+        let high = clmul64(x_high, &parts[2], &parts[3]);
+        let middle = clmul64(x_low ^ x_high, &parts[4], &parts[5]) ^ low ^ high;
+        (high ^ middle >> 64, low ^ middle << 64)
     }
 }
 
+fn split(x: u64) -> [u64; 4] {
+    [x & MASKS[0], x & MASKS[1], x & MASKS[2], x & MASKS[3]]
+}
+
+/// Outputs the carry-less product of `x` and `y`, given the split parts of `y` and of its bit
+/// reversal.
+fn clmul64(x: u64, y: &[u64; 4], y_reversed: &[u64; 4]) -> u128 {
+    let low = bmul64(x, y);
+    let high = bmul64(x.reverse_bits(), y_reversed).reverse_bits() >> 1;
+    u128::from(high) << 64 | u128::from(low)
+}
+
+/// Outputs the low 64 bits of the carry-less product of `x` and `y`, given the split parts of
+/// `y`. At most 16 terms land on any bit of a product of parts, and only when there is no higher
+/// bit left to carry into, so the bits that are kept are unaffected by carries.
+fn bmul64(x: u64, y: &[u64; 4]) -> u64 {
+    let x = split(x);
+    let mut product = 0;
+    for (i, mask) in MASKS.iter().enumerate() {
+        let mut z = 0;
+        for (j, x_part) in x.iter().enumerate() {
+            z ^= x_part.wrapping_mul(y[(4 + i - j) % 4]);
+        }
+        product |= z & mask;
+    }
+    product
+}
+
+/// Reduces the carry-less product of two field elements, given as its high and low halves.
+///
+/// Since bits are ordered from x^0 down to x^127, the product's coefficients are shifted by one
+/// bit, after which the high half holds the terms below x^128 and the low half the terms above.
+fn reduce(high: u128, low: u128) -> GFBlock {
+    let lower_terms = high << 1 | low >> 127;
+    let upper_terms = low << 1;
+    // Fold the upper terms with x^128 = x^7 + x^2 + x + 1, and then fold again the few terms
+    // that this pushes past x^127.
+    let overflow = upper_terms << 127 ^ upper_terms << 126 ^ upper_terms << 121;
+    let folded = upper_terms ^ overflow;
+    lower_terms ^ folded ^ folded >> 1 ^ folded >> 2 ^ folded >> 7
+}
+
+/// Multiplies by x in constant time, where bits are ordered from x^0 down to x^127.
+fn mul_x(block: GFBlock) -> GFBlock {
+    let m = ((block << 127) as i128 >> 127) as u128;
+    (block >> 1) ^ (R0 & m)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::test_helpers::*;
 
+    fn ghash(key: &[u8; 16], data: &[u8], ciphertext: &[u8]) -> [u8; 16] {
+        let mut mac = GHash::new(key);
+        mac.update_aad(data);
+        mac.update_ciphertext(ciphertext);
+        mac.finalize()
+    }
+
     fn check(expected: &str, h: &str, a: &str, c: &str) {
         let h_vec = &h2b(h);
         let h = &mut [0; 16];
@@ -103,6 +296,88 @@ mod tests {
         let c = &h2b(c);
         let expected = h2b(expected);
         assert_eq!(expected, ghash(h, a, c));
+
+        for chunk_size in &[1, 15, 16, 17] {
+            let mut mac = GHash::new(h);
+            for chunk in a.chunks(*chunk_size) {
+                mac.update_aad(chunk);
+            }
+            for chunk in c.chunks(*chunk_size) {
+                mac.update_ciphertext(chunk);
+            }
+            assert_eq!(expected, mac.finalize());
+        }
+
+        let mut mac = GHash::new(h);
+        mac.function.software_key = Some(SoftwareKey::new(*mac.function.key_block));
+        mac.update_aad(a);
+        mac.update_ciphertext(c);
+        assert_eq!(expected, mac.finalize());
+    }
+
+    /// Multiplies two field elements one bit at a time.
+    fn bitwise_multiply(x: GFBlock, y: GFBlock) -> GFBlock {
+        let mut x = x;
+        let mut v = y;
+        let mut product = 0;
+        for _ in 0..128 {
+            let h = x & (1 << 127);
+            let m = (h as i128 >> 127) as u128;
+            product ^= v & m;
+            v = mul_x(v);
+            x <<= 1;
+        }
+        product
+    }
+
+    fn check_multiply(clmul: impl Fn(GFBlock, GFBlock) -> (u128, u128)) {
+        let mut x = 0x0123_4567_89ab_cdef_fedc_ba98_7654_3210_u128;
+        let mut y = 0x66e9_4bd4_ef8a_2c3b_884c_fa59_ca34_2b2e_u128;
+        for _ in 0..64 {
+            let (high, low) = clmul(x, y);
+            assert_eq!(bitwise_multiply(x, y), reduce(high, low));
+            x = bitwise_multiply(x, x ^ y);
+            y = mul_x(y) ^ x;
+        }
+        for &(x, y) in &[(0, 0), (!0, !0), (!0, 1), (1 << 127, !0)] {
+            let (high, low) = clmul(x, y);
+            assert_eq!(bitwise_multiply(x, y), reduce(high, low));
+        }
+    }
+
+    #[test]
+    fn test_software() {
+        check_multiply(|x, y| SoftwareKey::new(y).clmul(x));
+    }
+
+    #[test]
+    fn test_hardware() {
+        if !hardware::available() {
+            return;
+        }
+        check_multiply(|x, y| unsafe { hardware::clmul(x, y) });
+        assert_eq!(0, reduce(0, 0));
+        assert_eq!(1 << 127, reduce(1 << 126, 0));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_aad_after_ciphertext() {
+        let mut mac = GHash::new(&[0; 16]);
+        mac.update_ciphertext(b"ciphertext");
+        mac.update_aad(b"data");
+    }
+
+    #[test]
+    fn test_polyval() {
+        let h = &h2b("25629347589242761d31f826ba4b757b");
+        let x = &h2b("4f4f95668c83dfb6401762bb2d01a262d1a24ddd2721d006bbe45f20d3c9f362");
+        let mut key = [0; 16];
+        key.copy_from_slice(h);
+        let mut function = PolyFunction::new(mul_x(u128::from_le_bytes(key)));
+        function.process_padded(x, u128::from_le_bytes);
+        let expected = h2b("f7a3b47b846119fae5b7866cf5e5b77e");
+        assert_eq!(expected, function.state.to_le_bytes());
     }
 
     #[test]
@@ -0,0 +1,24 @@
+//! Carry-less multiplication using the PMULL instruction.
+use core::arch::aarch64::*;
+
+pub(super) fn available() -> bool {
+    #[cfg(feature = "std")]
+    {
+        std::arch::is_aarch64_feature_detected!("pmull")
+    }
+    #[cfg(not(feature = "std"))]
+    {
+        cfg!(target_feature = "aes")
+    }
+}
+
+/// Outputs the high and low halves of the carry-less product of `a` and `b`.
+#[target_feature(enable = "aes,neon")]
+pub(super) unsafe fn clmul(a: u128, b: u128) -> (u128, u128) {
+    let (a_high, a_low) = ((a >> 64) as u64, a as u64);
+    let (b_high, b_low) = ((b >> 64) as u64, b as u64);
+    let low = vmull_p64(a_low, b_low);
+    let high = vmull_p64(a_high, b_high);
+    let middle = vmull_p64(a_low, b_high) ^ vmull_p64(a_high, b_low);
+    (high ^ middle >> 64, low ^ middle << 64)
+}
@@ -0,0 +1,44 @@
+//! Carry-less multiplication using the PCLMULQDQ instruction.
+#[cfg(target_arch = "x86")]
+use core::arch::x86::*;
+#[cfg(target_arch = "x86_64")]
+use core::arch::x86_64::*;
+
+pub(super) fn available() -> bool {
+    #[cfg(feature = "std")]
+    {
+        is_x86_feature_detected!("pclmulqdq") && is_x86_feature_detected!("sse2")
+    }
+    #[cfg(not(feature = "std"))]
+    {
+        cfg!(all(target_feature = "pclmulqdq", target_feature = "sse2"))
+    }
+}
+
+/// Outputs the high and low halves of the carry-less product of `a` and `b`.
+#[target_feature(enable = "pclmulqdq,sse2")]
+pub(super) unsafe fn clmul(a: u128, b: u128) -> (u128, u128) {
+    let a = load(a);
+    let b = load(b);
+    let low = store(_mm_clmulepi64_si128(a, b, 0x00));
+    let high = store(_mm_clmulepi64_si128(a, b, 0x11));
+    let middle = store(_mm_xor_si128(
+        _mm_clmulepi64_si128(a, b, 0x01),
+        _mm_clmulepi64_si128(a, b, 0x10),
+    ));
+    (high ^ middle >> 64, low ^ middle << 64)
+}
+
+#[inline]
+#[target_feature(enable = "sse2")]
+unsafe fn load(x: u128) -> __m128i {
+    _mm_loadu_si128(x.to_le_bytes().as_ptr() as *const __m128i)
+}
+
+#[inline]
+#[target_feature(enable = "sse2")]
+unsafe fn store(x: __m128i) -> u128 {
+    let mut output = [0; 16];
+    _mm_storeu_si128(output.as_mut_ptr() as *mut __m128i, x);
+    u128::from_le_bytes(output)
+}
@@ -0,0 +1,151 @@
+//! Module for the GMAC message authentication code, which is GCM with an empty message.
+//!
+//! Unlike CMAC or HMAC, GMAC needs a unique nonce for each tag created with a key.
+use crate::aes::BlockCipher;
+use crate::ct;
+use crate::error::Error;
+use crate::ghash::GHash;
+use crate::hmac::MacError;
+use core::marker::PhantomData;
+
+/// A GMAC message authentication code using a 16-byte block cipher.
+///
+/// # Examples
+///
+/// ```
+/// use crypto_pure::aes::Aes128;
+/// use crypto_pure::gmac::{gmac, Gmac};
+/// # let key = &[0x42; 16];
+/// # let nonce = &[0x24; 12];
+/// let mut mac = Gmac::<Aes128>::new(key, nonce).unwrap();
+/// mac.update(b"part one");
+/// mac.update(b"part two");
+/// let tag = mac.finalize();
+/// assert_eq!(gmac::<Aes128>(key, nonce, b"part onepart two"), Ok(tag));
+/// ```
+pub struct Gmac<E> {
+    ghash: GHash,
+    mask: [u8; 16],
+    block_cipher: PhantomData<E>,
+}
+
+/// Wrapper for obtaining the GMAC tag for a complete message.
+///
+/// # Errors
+///
+/// Fails like `Gmac::new`.
+pub fn gmac<E: BlockCipher>(key: &[u8], nonce: &[u8], message: &[u8]) -> Result<[u8; 16], Error> {
+    let mut mac = Gmac::<E>::new(key, nonce)?;
+    mac.update(message);
+    Ok(mac.finalize())
+}
+
+impl<E: BlockCipher> Gmac<E> {
+    /// Initializes a GMAC function given a key and a nonce.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::InvalidKey` if `key.len()` is not appropriate for the block cipher, or
+    /// with `Error::InvalidLength` if `nonce.len()` is not equal to 12.
+    pub fn new(key: &[u8], nonce: &[u8]) -> Result<Self, Error> {
+        let block_cipher = E::new(key)?;
+        if nonce.len() != 12 {
+            return Err(Error::InvalidLength);
+        }
+        let mut counter = [0; 16];
+        counter[..12].copy_from_slice(nonce);
+        counter[15] = 1;
+        Ok(Self {
+            ghash: GHash::new(&block_cipher.encrypt_block(&[0; 16])),
+            mask: block_cipher.encrypt_block(&counter),
+            block_cipher: PhantomData,
+        })
+    }
+
+    /// Feeds input into the GMAC function to update its state.
+    pub fn update(&mut self, input: &[u8]) {
+        self.ghash.update_aad(input);
+    }
+
+    /// Outputs the tag for the input fed so far.
+    pub fn finalize(self) -> [u8; 16] {
+        let mut tag = self.ghash.finalize();
+        for (byte, mask_byte) in tag.iter_mut().zip(&self.mask) {
+            *byte ^= mask_byte;
+        }
+        tag
+    }
+
+    /// Verifies in constant time that `expected_tag` is the tag for the input fed so far.
+    pub fn verify(self, expected_tag: &[u8]) -> Result<(), MacError> {
+        if ct::ct_eq(&self.finalize(), expected_tag).into() {
+            Ok(())
+        } else {
+            Err(MacError)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aead::Aead;
+    use crate::aes::{Aes128, Aes256};
+    use crate::gcm::Gcm;
+    use crate::test_helpers::*;
+
+    fn check<E: BlockCipher>(key: &str, nonce: &str, message: &str, expected: &str) {
+        let key = &h2b(key);
+        let nonce = &h2b(nonce);
+        let message = &h2b(message);
+        let expected = &h2b(expected);
+        assert_eq!(expected, &gmac::<E>(key, nonce, message).unwrap());
+        let gcm = Gcm::<E>::new(key).unwrap();
+        assert_eq!(
+            expected,
+            &gcm.seal_detached(nonce, message, &mut []).unwrap()
+        );
+
+        for chunk_size in &[1, 7, 16, 17] {
+            let mut mac = Gmac::<E>::new(key, nonce).unwrap();
+            for chunk in message.chunks(*chunk_size) {
+                mac.update(chunk);
+            }
+            assert_eq!(expected, &mac.finalize());
+        }
+
+        let mut mac = Gmac::<E>::new(key, nonce).unwrap();
+        mac.update(message);
+        assert_eq!(Ok(()), mac.verify(expected));
+        let bad_tag = &mut expected.clone();
+        bad_tag[0] ^= 1;
+        let mut mac = Gmac::<E>::new(key, nonce).unwrap();
+        mac.update(message);
+        assert_eq!(Err(MacError), mac.verify(bad_tag));
+
+        let error = Err(Error::InvalidLength);
+        assert_eq!(error, gmac::<E>(key, &nonce[1..], message));
+        let error = Err(Error::InvalidKey);
+        assert_eq!(error, gmac::<E>(&key[1..], nonce, message));
+    }
+
+    #[test]
+    fn test_aes128_gmac() {
+        let key = "00000000000000000000000000000000";
+        let nonce = "000000000000000000000000";
+        check::<Aes128>(key, nonce, "", "58e2fccefa7e3061367f1d57a4e7455a");
+
+        let key = "feffe9928665731c6d6a8f9467308308";
+        let nonce = "cafebabefacedbaddecaf888";
+        let message = "feedfacedeadbeeffeedfacedeadbeefabaddad2";
+        check::<Aes128>(key, nonce, message, "346434fd51d5cd0c5887ec63e39b907a");
+    }
+
+    #[test]
+    fn test_aes256_gmac() {
+        let key = "feffe9928665731c6d6a8f9467308308feffe9928665731c6d6a8f9467308308";
+        let nonce = "cafebabefacedbaddecaf888";
+        let message = "feedfacedeadbeeffeedfacedeadbeefabaddad2";
+        check::<Aes256>(key, nonce, message, "9f6be07603c0b0bd1272854063e9c9ba");
+    }
+}
@@ -0,0 +1,173 @@
+//! Module for the building blocks of hashing to elliptic curves (RFC 9380).
+//!
+//! `expand_message_xmd` stretches a message into any number of uniformly random bytes with a hash
+//! function, which the curve modules then map to field elements, scalars or points. Distinct
+//! protocols must use distinct domain separation tags.
+//!
+//! # Examples
+//!
+//! ```
+//! use crypto_pure::hash_to_curve::expand_message_xmd;
+//! use crypto_pure::sha2::Sha256;
+//! let uniform_bytes = &mut [0; 48];
+//! expand_message_xmd::<Sha256>(b"message", b"MyApp-V01-CS01-with-expander", uniform_bytes)?;
+//! # Ok::<(), crypto_pure::error::Error>(())
+//! ```
+use crate::error::Error;
+use crate::sha2::{HashFunction, MAX_BLOCK_SIZE, MAX_DIGEST_SIZE};
+
+/// Fills `output` with uniformly random bytes derived from a message and a domain separation tag
+/// with hash function `H`, as in RFC 9380, Section 5.3.1.
+///
+/// # Errors
+///
+/// Fails with `Error::InvalidLength` if `dst.len()` is more than 255, or if `output.len()` is
+/// more than 65535 or more than 255 times the digest size of `H`.
+pub fn expand_message_xmd<H: HashFunction>(
+    message: &[u8],
+    dst: &[u8],
+    output: &mut [u8],
+) -> Result<(), Error> {
+    if dst.len() > 255 || output.len() > 65535 || output.len().div_ceil(H::DIGEST_SIZE) > 255 {
+        return Err(Error::InvalidLength);
+    }
+    expand_message_xmd_concat::<H>(&[message], dst, output);
+    Ok(())
+}
+
+/// Like `expand_message_xmd`, for the concatenation of several parts of a message, with lengths
+/// that the caller has already checked.
+pub(crate) fn expand_message_xmd_concat<H: HashFunction>(
+    message: &[&[u8]],
+    dst: &[u8],
+    output: &mut [u8],
+) {
+    assert!(dst.len() <= 255);
+    assert!(output.len() <= 65535 && output.len().div_ceil(H::DIGEST_SIZE) <= 255);
+    let dst_len = &[dst.len() as u8];
+
+    let mut hash_function = H::default();
+    hash_function.update(&[0; MAX_BLOCK_SIZE][..H::BLOCK_SIZE]);
+    for part in message {
+        hash_function.update(part);
+    }
+    hash_function.update(&(output.len() as u16).to_be_bytes());
+    hash_function.update(&[0]);
+    hash_function.update(dst);
+    hash_function.update(dst_len);
+    let b_0 = &mut [0; MAX_DIGEST_SIZE][..H::DIGEST_SIZE];
+    hash_function.write_digest(b_0);
+
+    // b_1 hashes b_0 itself, and each later block hashes b_0 XOR the previous block
+    let b_i = &mut [0; MAX_DIGEST_SIZE][..H::DIGEST_SIZE];
+    for (i, chunk) in output.chunks_mut(H::DIGEST_SIZE).enumerate() {
+        for (b, b_0) in b_i.iter_mut().zip(b_0.iter()) {
+            *b ^= b_0;
+        }
+        let mut hash_function = H::default();
+        hash_function.update(b_i);
+        hash_function.update(&[i as u8 + 1]);
+        hash_function.update(dst);
+        hash_function.update(dst_len);
+        hash_function.write_digest(b_i);
+        chunk.copy_from_slice(&b_i[..chunk.len()]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sha2::{Sha256, Sha512};
+    use crate::test_helpers::*;
+
+    fn check<H: HashFunction>(dst: &[u8], message: &[u8], expected: &str) {
+        let expected = h2b(expected);
+        let output = &mut [0; 128][..expected.len()];
+        expand_message_xmd::<H>(message, dst, output).unwrap();
+        assert_eq!(&expected[..], &output[..]);
+    }
+
+    #[test]
+    fn test_expand_message_xmd_sha256() {
+        let dst = b"QUUX-V01-CS02-with-expander-SHA256-128";
+        check::<Sha256>(
+            dst,
+            b"",
+            "68a985b87eb6b46952128911f2a4412bbc302a9d759667f87f7a21d803f07235",
+        );
+        check::<Sha256>(
+            dst,
+            b"abc",
+            "d8ccab23b5985ccea865c6c97b6e5b8350e794e603b4b97902f53a8a0d605615",
+        );
+        check::<Sha256>(
+            dst,
+            b"",
+            "af84c27ccfd45d41914fdff5df25293e221afc53d8ad2ac06d5e3e29485dadbe\
+             e0d121587713a3e0dd4d5e69e93eb7cd4f5df4cd103e188cf60cb02edc3edf18\
+             eda8576c412b18ffb658e3dd6ec849469b979d444cf7b26911a08e63cf31f9dc\
+             c541708d3491184472c2c29bb749d4286b004ceb5ee6b9a7fa5b646c993f0ced",
+        );
+        check::<Sha256>(
+            dst,
+            b"abc",
+            "abba86a6129e366fc877aab32fc4ffc70120d8996c88aee2fe4b32d6c7b6437a\
+             647e6c3163d40b76a73cf6a5674ef1d890f95b664ee0afa5359a5c4e07985635\
+             bbecbac65d747d3d2da7ec2b8221b17b0ca9dc8a1ac1c07ea6a1e60583e2cb00\
+             058e77b7b72a298425cd1b941ad4ec65e8afc50303a22c0f99b0509b4c895f40",
+        );
+    }
+
+    #[test]
+    fn test_expand_message_xmd_sha512() {
+        let dst = b"QUUX-V01-CS02-with-expander-SHA512-256";
+        check::<Sha512>(
+            dst,
+            b"",
+            "6b9a7312411d92f921c6f68ca0b6380730a1a4d982c507211a90964c394179ba",
+        );
+        check::<Sha512>(
+            dst,
+            b"abc",
+            "0da749f12fbe5483eb066a5f595055679b976e93abe9be6f0f6318bce7aca8dc",
+        );
+        check::<Sha512>(
+            dst,
+            b"",
+            "41b037d1734a5f8df225dd8c7de38f851efdb45c372887be655212d07251b921\
+             b052b62eaed99b46f72f2ef4cc96bfaf254ebbbec091e1a3b9e4fb5e5b619d2e\
+             0c5414800a1d882b62bb5cd1778f098b8eb6cb399d5d9d18f5d5842cf5d13d7e\
+             b00a7cff859b605da678b318bd0e65ebff70bec88c753b159a805d2c89c55961",
+        );
+        check::<Sha512>(
+            dst,
+            b"abc",
+            "7f1dddd13c08b543f2e2037b14cefb255b44c83cc397c1786d975653e36a6b11\
+             bdd7732d8b38adb4a0edc26a0cef4bb45217135456e58fbca1703cd6032cb134\
+             7ee720b87972d63fbf232587043ed2901bce7f22610c0419751c065922b48843\
+             1851041310ad659e4b23520e1772ab29dcdeb2002222a363f0c2b1c972b3efe1",
+        );
+    }
+
+    #[test]
+    fn test_max_output() {
+        let output = &mut [0; 255 * 32];
+        expand_message_xmd::<Sha256>(b"abc", &[b'X'; 255], output).unwrap();
+        assert_eq!(
+            &h2b("cf98ed16dbce613a9b81c24ee946483855d376e7a39a215a96363960259991c9")[..],
+            &output[255 * 32 - 32..]
+        );
+    }
+
+    #[test]
+    fn test_output_too_long() {
+        let result = expand_message_xmd::<Sha256>(b"abc", b"dst", &mut [0; 255 * 32 + 1]);
+        assert_eq!(Err(Error::InvalidLength), result);
+    }
+
+    #[test]
+    fn test_dst_too_long() {
+        let result = expand_message_xmd::<Sha256>(b"abc", &[b'X'; 256], &mut [0; 32]);
+        assert_eq!(Err(Error::InvalidLength), result);
+    }
+}
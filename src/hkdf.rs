@@ -1,4 +1,4 @@
-//! Module for for the HMAC-based Key Derivation Function (HKDF).
+//! Module for the HMAC-based Key Derivation Function (HKDF).
 //!
 //! # Examples
 //!
@@ -10,49 +10,115 @@
 //! # let info = b"independent of the ikm";
 //! let prk = &mut [0; Sha512::DIGEST_SIZE];
 //! let okm = &mut [0; 64];
-//! extract::<Sha512>(salt, ikm, prk);
-//! expand::<Sha512>(prk, info, okm);
+//! extract::<Sha512>(salt, ikm, prk)?;
+//! expand::<Sha512>(prk, info, okm)?;
+//! # Ok::<(), crypto_pure::error::Error>(())
 //! ```
+use crate::error::Error;
 use crate::hmac::Hmac;
 use crate::sha2::HashFunction;
 
+/// An HKDF instance holding a pseudorandom key, from which any number of output keys can be
+/// expanded.
+///
+/// # Examples
+///
+/// ```
+/// use crypto_pure::hkdf::Hkdf;
+/// use crypto_pure::sha2::Sha256;
+/// # let ikm = b"password";
+/// # let salt = b"A non-secret random value.";
+/// let hkdf = Hkdf::<Sha256>::extract(salt, ikm);
+/// let key: [u8; 32] = hkdf.expand_to_array(b"key");
+/// let iv: [u8; 12] = hkdf.expand_to_array(b"iv");
+/// ```
+#[derive(Clone)]
+pub struct Hkdf<H> {
+    keyed_hmac: Hmac<H>,
+}
+
+impl<H: HashFunction> Hkdf<H> {
+    /// Extracts input keying material into a pseudorandom key using a salt.
+    pub fn extract(salt: &[u8], ikm: &[u8]) -> Self {
+        let mut hmac = Hmac::<H>::new(salt);
+        hmac.update(ikm);
+        Self {
+            keyed_hmac: Hmac::new(&hmac.tag()),
+        }
+    }
+
+    /// Initializes an HKDF instance from an existing pseudorandom key, skipping extraction, or
+    /// fails with `Error::InvalidLength` if `prk.len()` is less than the digest size for `H`.
+    pub fn from_prk(prk: &[u8]) -> Result<Self, Error> {
+        if prk.len() < H::DIGEST_SIZE {
+            return Err(Error::InvalidLength);
+        }
+        Ok(Self {
+            keyed_hmac: Hmac::new(prk),
+        })
+    }
+
+    /// Expands the pseudorandom key into output keying material given optional information, or
+    /// fails with `Error::InvalidLength` if `okm.len()` is more than 255 times the digest size for
+    /// `H`.
+    pub fn expand(&self, info: &[u8], okm: &mut [u8]) -> Result<(), Error> {
+        let digest_size = H::DIGEST_SIZE;
+        if okm.len() > 255 * digest_size {
+            return Err(Error::InvalidLength);
+        }
+        let mut hmac = self.keyed_hmac.clone();
+        for (i, chunk) in (1..=255).zip(okm.chunks_mut(digest_size)) {
+            hmac.update(info);
+            hmac.update(&[i]);
+            let tag = hmac.tag();
+            let chunk_len = chunk.len();
+            if chunk_len < digest_size {
+                chunk.copy_from_slice(&tag[..chunk_len]);
+                break;
+            }
+            chunk.copy_from_slice(&tag);
+            hmac = self.keyed_hmac.clone();
+            hmac.update(chunk);
+        }
+        Ok(())
+    }
+
+    /// Expands the pseudorandom key into a fixed-size array given optional information.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `N` is more than 255 times the digest size for `H`.
+    pub fn expand_to_array<const N: usize>(&self, info: &[u8]) -> [u8; N] {
+        let mut okm = [0; N];
+        self.expand(info, &mut okm).expect("output too long");
+        okm
+    }
+}
+
 /// Extracts input keying material into a pseudorandom key using a salt.
 ///
-/// # Panics
+/// # Errors
 ///
-/// Panics if `prk.len()` is not equal to the digest size for `H`.
-pub fn extract<H: HashFunction>(salt: &[u8], ikm: &[u8], prk: &mut [u8]) {
-    assert_eq!(H::DIGEST_SIZE, prk.len());
+/// Fails with `Error::InvalidLength` if `prk.len()` is not equal to the digest size for `H`.
+pub fn extract<H: HashFunction>(salt: &[u8], ikm: &[u8], prk: &mut [u8]) -> Result<(), Error> {
+    if prk.len() != H::DIGEST_SIZE {
+        return Err(Error::InvalidLength);
+    }
     let mut hmac = Hmac::<H>::new(salt);
     hmac.update(ikm);
     let tag = hmac.tag();
     prk.copy_from_slice(&tag);
+    Ok(())
 }
 
 /// Expands a pseudorandom key into output keying material given optional information.
 ///
-/// # Panics
+/// # Errors
 ///
-/// Panics if `prk.len()` is less than the digest size for `H`, or if `okm.len()` is more than 255
-/// times the digest size for `H`.
-pub fn expand<H: HashFunction>(prk: &[u8], info: &[u8], okm: &mut [u8]) {
-    let digest_size = H::DIGEST_SIZE;
-    assert!(digest_size <= prk.len());
-    assert!(255 * digest_size >= okm.len());
-    let mut hmac: Hmac<H> = Hmac::new(prk);
-    for (i, chunk) in (1..).zip(okm.chunks_mut(digest_size)) {
-        hmac.update(info);
-        hmac.update(&[i]);
-        let tag = hmac.tag();
-        let chunk_len = chunk.len();
-        if chunk_len < digest_size {
-            chunk.copy_from_slice(&tag[..chunk_len]);
-            return;
-        }
-        chunk.copy_from_slice(&tag);
-        hmac = Hmac::new(prk);
-        hmac.update(chunk);
-    }
+/// Fails with `Error::InvalidLength` if `prk.len()` is less than the digest size for `H`, or if
+/// `okm.len()` is more than 255 times the digest size for `H`.
+pub fn expand<H: HashFunction>(prk: &[u8], info: &[u8], okm: &mut [u8]) -> Result<(), Error> {
+    Hkdf::<H>::from_prk(prk)?.expand(info, okm)
 }
 
 #[cfg(test)]
@@ -68,12 +134,44 @@ mod tests {
         let prk = h2b(prk);
         let okm = h2b(okm);
         let mut actual = prk.clone();
-        extract::<Sha256>(&salt, &ikm, &mut actual);
+        extract::<Sha256>(&salt, &ikm, &mut actual).unwrap();
         assert_eq!(prk, actual);
 
         let mut actual = okm.clone();
-        expand::<Sha256>(&prk, &info, &mut actual);
+        expand::<Sha256>(&prk, &info, &mut actual).unwrap();
+        assert_eq!(okm, actual);
+
+        let hkdf = Hkdf::<Sha256>::extract(&salt, &ikm);
+        let mut actual = okm.clone();
+        hkdf.expand(&info, &mut actual).unwrap();
         assert_eq!(okm, actual);
+        let actual: [u8; 42] = hkdf.expand_to_array(&info);
+        assert_eq!(okm[..42], actual[..]);
+    }
+
+    #[test]
+    fn test_max_len() {
+        let hkdf = Hkdf::<Sha256>::extract(b"salt", b"ikm");
+        let okm = &mut [0; 255 * Sha256::DIGEST_SIZE];
+        hkdf.expand(b"info", okm).unwrap();
+        let expected: [u8; 32] = hkdf.expand_to_array(b"info");
+        assert_eq!(expected, okm[..32]);
+
+        let okm = &mut [0; 255 * Sha256::DIGEST_SIZE + 1];
+        assert_eq!(Err(Error::InvalidLength), hkdf.expand(b"info", okm));
+        assert_eq!([0; 255 * Sha256::DIGEST_SIZE + 1], *okm);
+    }
+
+    #[test]
+    fn test_short_prk() {
+        let prk = &[0; Sha256::DIGEST_SIZE];
+        assert!(Hkdf::<Sha256>::from_prk(prk).is_ok());
+        let result = Hkdf::<Sha256>::from_prk(&prk[1..]);
+        assert_eq!(Some(Error::InvalidLength), result.err());
+        let result = expand::<Sha256>(&prk[1..], b"", &mut [0; 32]);
+        assert_eq!(Err(Error::InvalidLength), result);
+        let result = extract::<Sha256>(b"salt", b"ikm", &mut [0; 31]);
+        assert_eq!(Err(Error::InvalidLength), result);
     }
 
     #[test]
@@ -1,6 +1,10 @@
 //! Module for creating and verifying HMAC tags.
-use crate::sha2::{HashFunction, Sha224, Sha256, Sha384, Sha512, MAX_DIGEST_SIZE};
-use crate::util;
+use crate::ct;
+use crate::secret::SecretKey;
+use crate::sha2::{
+    CheckSizes, HashFunction, Sha224, Sha256, Sha384, Sha512, MAX_BLOCK_SIZE, MAX_DIGEST_SIZE,
+};
+use core::fmt;
 use core::ops::Deref;
 
 /// A function for creating and verifying HMAC tags given a hash function `H`.
@@ -19,14 +23,43 @@ use core::ops::Deref;
 /// assert!(!verify::<Sha512>(key, b"forged message", &tag));
 /// assert!(verify::<Sha512>(key, b"signed message", &tag));
 /// ```
+///
+/// A keyed state can be cloned to authenticate several messages without rehashing the key:
+///
+/// ```
+/// use crypto_pure::hmac::Hmac;
+/// use crypto_pure::sha2::Sha256;
+/// # let key = b"This should be generated securely.";
+/// let keyed = Hmac::<Sha256>::new(key);
+/// for message in &[&b"first"[..], b"second"] {
+///     let mut hmac = keyed.clone();
+///     hmac.update(message);
+///     let tag = hmac.tag();
+/// }
+/// ```
+#[derive(Clone)]
 pub struct Hmac<H> {
     inner_hash_function: H,
     outer_hash_function: H,
+    keyed_inner_hash_function: H,
 }
 
+/// An error returned when a tag fails verification.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MacError;
+
+impl fmt::Display for MacError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("MAC verification failed")
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for MacError {}
+
 /// A tag that derefs into a slice of bytes.
 pub struct Tag {
-    buffer: [u8; MAX_DIGEST_SIZE],
+    buffer: SecretKey<[u8; MAX_DIGEST_SIZE]>,
     size: usize,
 }
 
@@ -62,10 +95,28 @@ pub fn hmac_sha224(key: &[u8], message: &[u8]) -> [u8; Sha224::DIGEST_SIZE] {
 
 impl<H: HashFunction> Hmac<H> {
     /// Initializes an HMAC function given a key.
+    ///
+    /// Hash functions with a digest size over 64 bytes or a block size over 144 bytes are rejected
+    /// at compile time:
+    ///
+    /// ```compile_fail
+    /// use crypto_pure::hmac::Hmac;
+    /// use crypto_pure::sha2::HashFunction;
+    /// #[derive(Clone, Default)]
+    /// struct Wide;
+    /// impl HashFunction for Wide {
+    ///     const DIGEST_SIZE: usize = 72;
+    ///     const BLOCK_SIZE: usize = 128;
+    ///     fn update(&mut self, _: &[u8]) {}
+    ///     fn write_digest(self, _: &mut [u8]) {}
+    /// }
+    /// let hmac = Hmac::<Wide>::new(b"key");
+    /// ```
     pub fn new(key: &[u8]) -> Self {
+        let () = CheckSizes::<H>::VALID;
         let mut hashed_key;
         let new_key = if key.len() > H::BLOCK_SIZE {
-            hashed_key = [0; MAX_DIGEST_SIZE];
+            hashed_key = SecretKey::new([0; MAX_DIGEST_SIZE]);
             let mut hash_function = H::default();
             hash_function.update(key);
             hash_function.write_digest(&mut hashed_key[..H::DIGEST_SIZE]);
@@ -73,8 +124,10 @@ impl<H: HashFunction> Hmac<H> {
         } else {
             key
         };
+        let inner_hash_function = Self::keyed_hash_function(new_key, 0x36);
         Self {
-            inner_hash_function: Self::keyed_hash_function(new_key, 0x36),
+            keyed_inner_hash_function: inner_hash_function.clone(),
+            inner_hash_function,
             outer_hash_function: Self::keyed_hash_function(new_key, 0x5c),
         }
     }
@@ -84,9 +137,22 @@ impl<H: HashFunction> Hmac<H> {
         self.inner_hash_function.update(input);
     }
 
+    /// Resets the function to its freshly keyed state, discarding any input fed so far.
+    pub fn reset(&mut self) {
+        self.inner_hash_function = self.keyed_inner_hash_function.clone();
+    }
+
+    /// Outputs a `Tag` containing the HMAC result and resets the function, so that the same
+    /// instance can authenticate another message.
+    pub fn tag_reset(&mut self) -> Tag {
+        let tag = self.clone().tag();
+        self.reset();
+        tag
+    }
+
     /// Outputs a `Tag` containing the HMAC result.
     pub fn tag(self) -> Tag {
-        let mut buffer = [0; MAX_DIGEST_SIZE];
+        let mut buffer = SecretKey::new([0; MAX_DIGEST_SIZE]);
         self.write_tag(&mut buffer[..H::DIGEST_SIZE]);
         Tag {
             buffer,
@@ -94,6 +160,15 @@ impl<H: HashFunction> Hmac<H> {
         }
     }
 
+    /// Verifies in constant time that `expected_tag` is the tag for the input fed so far.
+    pub fn verify(self, expected_tag: &[u8]) -> Result<(), MacError> {
+        if ct::ct_eq(&self.tag(), expected_tag).into() {
+            Ok(())
+        } else {
+            Err(MacError)
+        }
+    }
+
     fn write_tag(mut self, output: &mut [u8]) {
         assert_eq!(H::DIGEST_SIZE, output.len());
         self.inner_hash_function.write_digest(output);
@@ -102,13 +177,12 @@ impl<H: HashFunction> Hmac<H> {
     }
 
     fn keyed_hash_function(key: &[u8], pad: u8) -> H {
-        let mut hash_function = H::default();
-        for byte in key {
-            hash_function.update(&[byte ^ pad]);
-        }
-        for _ in key.len()..H::BLOCK_SIZE {
-            hash_function.update(&[pad]);
+        let mut block = SecretKey::new([pad; MAX_BLOCK_SIZE]);
+        for (byte, k) in block.iter_mut().zip(key) {
+            *byte ^= k;
         }
+        let mut hash_function = H::default();
+        hash_function.update(&block[..H::BLOCK_SIZE]);
         hash_function
     }
 }
@@ -117,8 +191,7 @@ impl<H: HashFunction> Hmac<H> {
 pub fn verify<H: HashFunction>(key: &[u8], message: &[u8], tag: &[u8]) -> bool {
     let mut hmac = Hmac::<H>::new(key);
     hmac.update(message);
-    let expected_tag = hmac.tag();
-    expected_tag.len() == tag.len() && util::verify_inner(&expected_tag, tag) == 0
+    hmac.verify(tag).is_ok()
 }
 
 impl Deref for Tag {
@@ -131,7 +204,7 @@ impl Deref for Tag {
 
 impl AsRef<[u8]> for Tag {
     fn as_ref(&self) -> &[u8] {
-        &self
+        self
     }
 }
 
@@ -156,7 +229,35 @@ mod tests {
                 assert_eq!(expected, actual.to_vec());
 
                 assert!(verify::<$function>(key, data, &actual));
-                // TODO: check that bad tags cause verification to fail
+                let mut hmac = Hmac::<$function>::new(key);
+                hmac.update(data);
+                assert_eq!(Ok(()), hmac.verify(&actual));
+
+                let bad_tag = &mut actual.to_vec();
+                bad_tag[0] ^= 1;
+                assert!(!verify::<$function>(key, data, bad_tag));
+                assert!(!verify::<$function>(key, data, &actual[1..]));
+                let mut hmac = Hmac::<$function>::new(key);
+                hmac.update(data);
+                assert_eq!(Err(MacError), hmac.verify(bad_tag));
+
+                let mut hmac = Hmac::<$function>::new(key);
+                hmac.update(b"discarded");
+                hmac.reset();
+                let forked = hmac.clone();
+                hmac.update(data);
+                assert_eq!(expected, hmac.tag().to_vec());
+                let mut hmac = forked;
+                hmac.update(data);
+                assert_eq!(expected, hmac.tag().to_vec());
+
+                let mut hmac = Hmac::<$function>::new(key);
+                for _ in 0..2 {
+                    hmac.update(data);
+                    assert_eq!(expected, hmac.tag_reset().to_vec());
+                }
+                hmac.update(b"other");
+                assert_eq!($wrapper(key, b"other").to_vec(), hmac.tag_reset().to_vec());
             };
         }
 
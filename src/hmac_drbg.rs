@@ -0,0 +1,205 @@
+//! Module for HMAC_DRBG, a deterministic random bit generator from NIST SP 800-90A Rev. 1, with a
+//! hash function chosen by its `HashFunction` type.
+//!
+//! The DRBG expands entropy from an external source into any amount of pseudorandom output, with
+//! a security strength of up to 256 bits depending on the hash function. Prediction resistance is
+//! obtained by calling `reseed` with fresh entropy before `generate`. It also underlies the
+//! deterministic ECDSA nonces of RFC 6979.
+//!
+//! # Examples
+//!
+//! ```
+//! use crypto_pure::hmac_drbg::HmacDrbg;
+//! use crypto_pure::sha2::Sha256;
+//! # let entropy_input = &[0x42; 32];
+//! # let nonce = &[0x24; 16];
+//! let mut drbg = HmacDrbg::<Sha256>::new(entropy_input, nonce, b"personalization string")?;
+//! let key = &mut [0; 32];
+//! drbg.generate(key, b"")?;
+//! # Ok::<(), crypto_pure::error::Error>(())
+//! ```
+use crate::error::Error;
+use crate::hmac::Hmac;
+use crate::secret::SecretKey;
+use crate::sha2::{HashFunction, MAX_DIGEST_SIZE};
+
+/// The maximum number of bytes per call to `generate`, 2^19 bits.
+pub const MAX_REQUEST_LEN: usize = 1 << 16;
+
+/// The number of calls to `generate` allowed between reseeds.
+pub(crate) const RESEED_INTERVAL: u64 = 1 << 48;
+
+/// An instance of HMAC_DRBG with hash function `H`.
+pub struct HmacDrbg<H> {
+    key: Hmac<H>,
+    v: SecretKey<[u8; MAX_DIGEST_SIZE]>,
+    pub(crate) reseed_counter: u64,
+}
+
+impl<H: HashFunction> HmacDrbg<H> {
+    /// Instantiates the DRBG from an entropy input, a nonce and an optional personalization
+    /// string. The nonce should be unique per instantiation, such as a timestamp or more entropy.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::InvalidLength` if `entropy_input.len()` is less than 32, or less than the
+    /// digest size for `H` if that is smaller.
+    pub fn new(
+        entropy_input: &[u8],
+        nonce: &[u8],
+        personalization_string: &[u8],
+    ) -> Result<Self, Error> {
+        if entropy_input.len() < H::DIGEST_SIZE.min(32) {
+            return Err(Error::InvalidLength);
+        }
+        let mut v = SecretKey::new([0; MAX_DIGEST_SIZE]);
+        v[..H::DIGEST_SIZE].fill(1);
+        let mut drbg = Self {
+            key: Hmac::new(&[0; MAX_DIGEST_SIZE][..H::DIGEST_SIZE]),
+            v,
+            reseed_counter: 1,
+        };
+        drbg.update(&[entropy_input, nonce, personalization_string]);
+        Ok(drbg)
+    }
+
+    /// Reseeds the DRBG with a fresh entropy input and optional additional input.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::InvalidLength` if `entropy_input.len()` is less than 32, or less than the
+    /// digest size for `H` if that is smaller.
+    pub fn reseed(&mut self, entropy_input: &[u8], additional_input: &[u8]) -> Result<(), Error> {
+        if entropy_input.len() < H::DIGEST_SIZE.min(32) {
+            return Err(Error::InvalidLength);
+        }
+        self.update(&[entropy_input, additional_input]);
+        self.reseed_counter = 1;
+        Ok(())
+    }
+
+    /// Fills a buffer with pseudorandom bytes given optional additional input.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::InvalidLength` if `output.len()` is more than `MAX_REQUEST_LEN`, or
+    /// with `Error::ReseedRequired` if the DRBG must be reseeded first.
+    pub fn generate(&mut self, output: &mut [u8], additional_input: &[u8]) -> Result<(), Error> {
+        if output.len() > MAX_REQUEST_LEN {
+            return Err(Error::InvalidLength);
+        }
+        if self.reseed_counter > RESEED_INTERVAL {
+            return Err(Error::ReseedRequired);
+        }
+        if !additional_input.is_empty() {
+            self.update(&[additional_input]);
+        }
+        for chunk in output.chunks_mut(H::DIGEST_SIZE) {
+            self.next_block();
+            chunk.copy_from_slice(&self.v[..chunk.len()]);
+        }
+        self.update(&[additional_input]);
+        self.reseed_counter += 1;
+        Ok(())
+    }
+
+    /// Replaces V with HMAC(K, V).
+    fn next_block(&mut self) {
+        let v = &mut self.v[..H::DIGEST_SIZE];
+        let mut hmac = self.key.clone();
+        hmac.update(v);
+        v.copy_from_slice(&hmac.tag());
+    }
+
+    /// Updates the key and V with the concatenation of the provided data, which may be empty.
+    fn update(&mut self, provided_data: &[&[u8]]) {
+        let empty = provided_data.iter().all(|data| data.is_empty());
+        for round in 0..2 {
+            if round == 1 && empty {
+                break;
+            }
+            let mut hmac = self.key.clone();
+            hmac.update(&self.v[..H::DIGEST_SIZE]);
+            hmac.update(&[round]);
+            provided_data.iter().for_each(|data| hmac.update(data));
+            self.key = Hmac::new(&hmac.tag());
+            self.next_block();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sha2::{Sha256, Sha512};
+    use crate::test_helpers::*;
+
+    // Checked against a Python implementation of SP 800-90A
+    #[test]
+    fn test_generate() {
+        let entropy_input = &(0..32).collect::<std::vec::Vec<u8>>();
+        let nonce = &(32..48).collect::<std::vec::Vec<u8>>();
+        let mut drbg = HmacDrbg::<Sha256>::new(entropy_input, nonce, b"personalization").unwrap();
+        let output = &mut [0; 40];
+        drbg.generate(output, b"").unwrap();
+        assert_eq!(
+            &h2b(
+                "ef0543a6a18f8f9620a08e17ccb950f715502b66d82514fcd376c4841ffc863d\
+                 2204d6d1f20df976"
+            )[..],
+            &output[..]
+        );
+        drbg.generate(output, b"additional").unwrap();
+        assert_eq!(
+            &h2b(
+                "46afd3feb5dfd3a698bab869085b2ee3c042eee714a5273d8d7d17ce2a391c91\
+                 1018ab0c69560218"
+            )[..],
+            &output[..]
+        );
+        let entropy_input = &(64..96).collect::<std::vec::Vec<u8>>();
+        drbg.reseed(entropy_input, b"").unwrap();
+        let output = &mut [0; 100];
+        drbg.generate(output, b"").unwrap();
+        assert_eq!(
+            &h2b(
+                "15adec75997d4abbc98ec9c424440546016462e6e2afd7ad539a901c5ed00e6b\
+                 6b752790f76020c76aaed4bcaeae35d7cc966a4420a14f490a5c0028181a24c2\
+                 df827bd413d2559e30b7194a3e286a9608a862bcc7dc20d6c8bebb2bce42b1e7\
+                 2fc9a5ec"
+            )[..],
+            &output[..]
+        );
+
+        let mut drbg = HmacDrbg::<Sha512>::new(&[0x42; 32], b"", b"").unwrap();
+        let output = &mut [0; 64];
+        drbg.generate(output, b"").unwrap();
+        assert_eq!(
+            &h2b(
+                "09391b725f08eafafc6c8ee1c198de99e5be29431cf96a887d503f2225361e74\
+                 fa45fc4ea3493d7f2e496f164281a3d9bed3fd119c9a5544183cf79ce88f2729"
+            )[..],
+            &output[..]
+        );
+    }
+
+    #[test]
+    fn test_reseed_required() {
+        let mut drbg = HmacDrbg::<Sha256>::new(&[0x42; 32], &[0x24; 16], b"").unwrap();
+        drbg.reseed_counter = RESEED_INTERVAL;
+        drbg.generate(&mut [0; 16], b"").unwrap();
+        assert_eq!(Err(Error::ReseedRequired), drbg.generate(&mut [0; 16], b""));
+        drbg.reseed(&[0x42; 32], b"").unwrap();
+        drbg.generate(&mut [0; 16], b"").unwrap();
+    }
+
+    #[test]
+    fn test_invalid_lengths() {
+        let error = Err(Error::InvalidLength);
+        let drbg = HmacDrbg::<Sha256>::new(&[0x42; 31], &[0x24; 16], b"");
+        assert_eq!(error, drbg.map(|_| ()));
+        let mut drbg = HmacDrbg::<Sha256>::new(&[0x42; 32], &[0x24; 16], b"").unwrap();
+        assert_eq!(error, drbg.reseed(&[0x42; 31], b""));
+        assert_eq!(error, drbg.generate(&mut [0; MAX_REQUEST_LEN + 1], b""));
+    }
+}
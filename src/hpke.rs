@@ -0,0 +1,1248 @@
+//! Module for Hybrid Public Key Encryption (RFC 9180).
+//!
+//! A sender sets up a context by encapsulating a shared secret to the recipient's public key,
+//! outputting the encapsulated key to send along with the messages it seals. The recipient sets
+//! up the matching context from the encapsulated key and its secret key. Both sides can also
+//! export secrets from their context.
+//!
+//! The KEM is chosen by its type, which must implement `KemId`: `DhKemX25519`, `DhKemP256`, or
+//! one of the ML-KEM parameter sets (draft-ietf-hpke-pq). The KDF is chosen by its hash
+//! function, which must implement `KdfId`, and the AEAD by its type, which must implement
+//! `AeadId`. The four modes differ in how the sender is authenticated: not at all in the base
+//! mode, by a pre-shared key in the PSK mode, by its static X25519 key in the auth mode, and by
+//! both in the auth-PSK mode. The auth modes need a Diffie-Hellman KEM and are only provided for
+//! DHKEM(X25519, HKDF-SHA256).
+//!
+//! # Examples
+//!
+//! ```
+//! use crypto_pure::hpke::{setup_base_recipient, setup_base_sender};
+//! use crypto_pure::kem::Kem;
+//! use crypto_pure::mlkem::mlkem768::MlKem768;
+//! use crypto_pure::poly1305::ChaCha20Poly1305;
+//! use crypto_pure::rand::SystemRandom;
+//! use crypto_pure::sha2::Sha256;
+//! let rng = &SystemRandom::new();
+//! let (secret_key, public_key) = MlKem768::generate(rng);
+//! let (enc, mut sender) =
+//!     setup_base_sender::<MlKem768, Sha256, ChaCha20Poly1305>(&public_key, b"info", rng)?;
+//! let buffer = &mut [&b"message"[..], &[0; 16]].concat();
+//! sender.seal_in_place(b"header", buffer)?;
+//!
+//! let mut recipient =
+//!     setup_base_recipient::<MlKem768, Sha256, ChaCha20Poly1305>(&enc, &secret_key, b"info")?;
+//! assert_eq!(b"message", recipient.open_in_place(b"header", buffer)?);
+//! # Ok::<(), crypto_pure::error::Error>(())
+//! ```
+use crate::aead::Aead;
+use crate::aes::{Aes128, Aes256};
+use crate::curve25519;
+use crate::error::Error;
+use crate::gcm::Gcm;
+use crate::hmac::{Hmac, Tag};
+use crate::kem::Kem;
+use crate::mlkem::mlkem1024::MlKem1024;
+use crate::mlkem::mlkem512::MlKem512;
+use crate::mlkem::mlkem768::MlKem768;
+use crate::p256;
+use crate::poly1305::ChaCha20Poly1305;
+use crate::rand::SecureRandom;
+use crate::secret::SecretKey;
+use crate::sha2::{HashFunction, Sha256, Sha384, Sha512, MAX_DIGEST_SIZE};
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+use core::marker::PhantomData;
+
+const NONCE_LEN: usize = 12;
+const MIN_PSK_LEN: usize = 32;
+
+const MODE_BASE: u8 = 0;
+const MODE_PSK: u8 = 1;
+const MODE_AUTH: u8 = 2;
+const MODE_AUTH_PSK: u8 = 3;
+
+/// A KEM with an HPKE identifier.
+pub trait KemId: Kem {
+    /// The identifier of the KEM.
+    const KEM_ID: u16;
+}
+
+/// DHKEM(X25519, HKDF-SHA256), whose key pairs can be derived with `derive_key_pair`.
+pub struct DhKemX25519;
+
+impl KemId for DhKemX25519 {
+    const KEM_ID: u16 = 0x0020;
+}
+
+/// DHKEM(P-256, HKDF-SHA256), whose public keys and encapsulated keys are uncompressed points.
+pub struct DhKemP256;
+
+impl KemId for DhKemP256 {
+    const KEM_ID: u16 = 0x0010;
+}
+
+impl KemId for MlKem512 {
+    const KEM_ID: u16 = 0x0040;
+}
+
+impl KemId for MlKem768 {
+    const KEM_ID: u16 = 0x0041;
+}
+
+impl KemId for MlKem1024 {
+    const KEM_ID: u16 = 0x0042;
+}
+
+/// A hash function whose HKDF is an HPKE KDF.
+pub trait KdfId: HashFunction {
+    /// The identifier of the KDF.
+    const KDF_ID: u16;
+}
+
+impl KdfId for Sha256 {
+    const KDF_ID: u16 = 0x0001;
+}
+
+impl KdfId for Sha384 {
+    const KDF_ID: u16 = 0x0002;
+}
+
+impl KdfId for Sha512 {
+    const KDF_ID: u16 = 0x0003;
+}
+
+/// An AEAD cipher with 12-byte nonces that is an HPKE AEAD.
+pub trait AeadId: Aead {
+    /// The identifier of the AEAD.
+    const AEAD_ID: u16;
+}
+
+impl AeadId for Gcm<Aes128> {
+    const AEAD_ID: u16 = 0x0001;
+}
+
+impl AeadId for Gcm<Aes256> {
+    const AEAD_ID: u16 = 0x0002;
+}
+
+impl AeadId for ChaCha20Poly1305 {
+    const AEAD_ID: u16 = 0x0003;
+}
+
+/// The sender's side of a context, for sealing messages to the recipient.
+pub struct SenderContext<H, A>(Context<H, A>);
+
+/// The recipient's side of a context, for opening messages from the sender.
+pub struct RecipientContext<H, A>(Context<H, A>);
+
+struct Context<H, A> {
+    suite_id: [u8; 10],
+    cipher: A,
+    base_nonce: [u8; NONCE_LEN],
+    sequence: u64,
+    exporter_secret: SecretKey<[u8; MAX_DIGEST_SIZE]>,
+    hash: PhantomData<H>,
+}
+
+impl<H: KdfId, A: AeadId> SenderContext<H, A> {
+    /// Encrypts the message at the start of `buffer` in place and writes the tag into the last
+    /// `A::TAG_LEN` bytes, which must be reserved for it.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::NonceExhausted` if 2^64 messages have been sealed, or like
+    /// `Aead::seal_in_place`.
+    pub fn seal_in_place(&mut self, aad: &[u8], buffer: &mut [u8]) -> Result<(), Error> {
+        let nonce = self.0.nonce()?;
+        self.0.cipher.seal_in_place(&nonce, aad, buffer)?;
+        self.0.sequence += 1;
+        Ok(())
+    }
+
+    /// Encrypts a message and outputs the ciphertext with the tag appended.
+    ///
+    /// # Errors
+    ///
+    /// Fails like `seal_in_place`.
+    #[cfg(feature = "alloc")]
+    pub fn seal(&mut self, aad: &[u8], message: &[u8]) -> Result<Vec<u8>, Error> {
+        let nonce = self.0.nonce()?;
+        let sealed = self.0.cipher.seal(&nonce, aad, message)?;
+        self.0.sequence += 1;
+        Ok(sealed)
+    }
+
+    /// Exports a secret for `exporter_context` into `output`.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::InvalidLength` if `output.len()` is more than 255 times the digest size
+    /// for `H`.
+    pub fn export(&self, exporter_context: &[u8], output: &mut [u8]) -> Result<(), Error> {
+        self.0.export(exporter_context, output)
+    }
+}
+
+impl<H: KdfId, A: AeadId> RecipientContext<H, A> {
+    /// Verifies the tag in the last `A::TAG_LEN` bytes of `buffer` and decrypts the ciphertext
+    /// before it in place, outputting the message part of `buffer`. Messages must be opened in
+    /// the order they were sealed, and a message that fails to open doesn't count.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::NonceExhausted` if 2^64 messages have been opened, or like
+    /// `Aead::open_in_place`.
+    pub fn open_in_place<'a>(
+        &mut self,
+        aad: &[u8],
+        buffer: &'a mut [u8],
+    ) -> Result<&'a mut [u8], Error> {
+        let nonce = self.0.nonce()?;
+        let message = self.0.cipher.open_in_place(&nonce, aad, buffer)?;
+        self.0.sequence += 1;
+        Ok(message)
+    }
+
+    /// Decrypts the output of `SenderContext::seal`.
+    ///
+    /// # Errors
+    ///
+    /// Fails like `open_in_place`.
+    #[cfg(feature = "alloc")]
+    pub fn open(&mut self, aad: &[u8], sealed: &[u8]) -> Result<Vec<u8>, Error> {
+        let nonce = self.0.nonce()?;
+        let message = self.0.cipher.open(&nonce, aad, sealed)?;
+        self.0.sequence += 1;
+        Ok(message)
+    }
+
+    /// Exports a secret for `exporter_context` into `output`.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::InvalidLength` if `output.len()` is more than 255 times the digest size
+    /// for `H`.
+    pub fn export(&self, exporter_context: &[u8], output: &mut [u8]) -> Result<(), Error> {
+        self.0.export(exporter_context, output)
+    }
+}
+
+impl<H: KdfId, A: AeadId> Context<H, A> {
+    fn new(
+        kem_id: u16,
+        mode: u8,
+        shared_secret: &[u8],
+        info: &[u8],
+        psk: &[u8],
+        psk_id: &[u8],
+    ) -> Result<Self, Error> {
+        let suite_id = &hpke_suite_id::<H, A>(kem_id);
+        let key_schedule_context = &mut [0; 1 + 2 * MAX_DIGEST_SIZE];
+        key_schedule_context[0] = mode;
+        let (psk_id_hash, info_hash) =
+            key_schedule_context[1..1 + 2 * H::DIGEST_SIZE].split_at_mut(H::DIGEST_SIZE);
+        psk_id_hash.copy_from_slice(
+            &labeled_extract::<H>(suite_id, b"", b"psk_id_hash", psk_id)[..H::DIGEST_SIZE],
+        );
+        info_hash.copy_from_slice(
+            &labeled_extract::<H>(suite_id, b"", b"info_hash", info)[..H::DIGEST_SIZE],
+        );
+        let key_schedule_context = &key_schedule_context[..1 + 2 * H::DIGEST_SIZE];
+
+        let secret =
+            &labeled_extract::<H>(suite_id, shared_secret, b"secret", psk)[..H::DIGEST_SIZE];
+        let mut key = SecretKey::new([0; 32]);
+        let key = &mut key[..A::KEY_LEN];
+        labeled_expand::<H>(suite_id, secret, b"key", key_schedule_context, key);
+        let mut base_nonce = [0; NONCE_LEN];
+        labeled_expand::<H>(
+            suite_id,
+            secret,
+            b"base_nonce",
+            key_schedule_context,
+            &mut base_nonce,
+        );
+        let mut exporter_secret = SecretKey::new([0; MAX_DIGEST_SIZE]);
+        labeled_expand::<H>(
+            suite_id,
+            secret,
+            b"exp",
+            key_schedule_context,
+            &mut exporter_secret[..H::DIGEST_SIZE],
+        );
+        Ok(Self {
+            suite_id: *suite_id,
+            cipher: A::new(key)?,
+            base_nonce,
+            sequence: 0,
+            exporter_secret,
+            hash: PhantomData,
+        })
+    }
+
+    fn nonce(&self) -> Result<[u8; NONCE_LEN], Error> {
+        if self.sequence == u64::MAX {
+            return Err(Error::NonceExhausted);
+        }
+        let mut nonce = self.base_nonce;
+        for (byte, s) in nonce[NONCE_LEN - 8..]
+            .iter_mut()
+            .zip(&self.sequence.to_be_bytes())
+        {
+            *byte ^= s;
+        }
+        Ok(nonce)
+    }
+
+    fn export(&self, exporter_context: &[u8], output: &mut [u8]) -> Result<(), Error> {
+        if output.len() > 255 * H::DIGEST_SIZE {
+            return Err(Error::InvalidLength);
+        }
+        labeled_expand::<H>(
+            &self.suite_id,
+            &self.exporter_secret[..H::DIGEST_SIZE],
+            b"sec",
+            exporter_context,
+            output,
+        );
+        Ok(())
+    }
+}
+
+impl Kem for DhKemX25519 {
+    const ENCAPSULATION_KEY_LEN: usize = 32;
+    const CIPHERTEXT_LEN: usize = 32;
+    const SHARED_SECRET_LEN: usize = 32;
+
+    type DecapsulationKey = [u8; 32];
+    type EncapsulationKey = [u8; 32];
+    type Ciphertext = [u8; 32];
+    type SharedSecret = [u8; 32];
+
+    fn generate(rng: &dyn SecureRandom) -> ([u8; 32], [u8; 32]) {
+        let mut ikm = SecretKey::new([0; 32]);
+        rng.fill(&mut *ikm);
+        derive_key_pair(&*ikm)
+    }
+
+    fn encapsulate(
+        encapsulation_key: &[u8],
+        rng: &dyn SecureRandom,
+    ) -> Result<([u8; 32], [u8; 32]), Error> {
+        let recipient_key = <&[u8; 32]>::try_from(encapsulation_key).or(Err(Error::InvalidKey))?;
+        let ephemeral_key = SecretKey::new(Self::generate(rng).0);
+        let (enc, shared_secret) = encap(recipient_key, &ephemeral_key, None)?;
+        Ok((enc, *shared_secret))
+    }
+
+    fn decapsulate(decapsulation_key: &[u8; 32], ciphertext: &[u8]) -> Result<[u8; 32], Error> {
+        let enc = <&[u8; 32]>::try_from(ciphertext).or(Err(Error::InvalidLength))?;
+        Ok(*decap(enc, decapsulation_key, None)?)
+    }
+}
+
+impl DhKemP256 {
+    /// Derives a P-256 key pair from input keying material, which should have at least 32 bytes
+    /// of entropy, outputting the secret key and public key.
+    pub fn derive_key_pair(ikm: &[u8]) -> ([u8; 32], [u8; 65]) {
+        let suite_id = &kem_suite_id(Self::KEM_ID);
+        let dkp_prk =
+            &labeled_extract::<Sha256>(suite_id, b"", b"dkp_prk", ikm)[..Sha256::DIGEST_SIZE];
+        // Candidates are rejected with probability about 2^-32 each, so 256 always suffice.
+        (0..=255)
+            .find_map(|counter| {
+                let mut secret_key = [0; 32];
+                labeled_expand::<Sha256>(
+                    suite_id,
+                    dkp_prk,
+                    b"candidate",
+                    &[counter],
+                    &mut secret_key,
+                );
+                p256::gen_pk(&secret_key)
+                    .ok()
+                    .map(|public_key| (secret_key, public_key))
+            })
+            .expect("no valid candidate")
+    }
+}
+
+impl Kem for DhKemP256 {
+    const ENCAPSULATION_KEY_LEN: usize = 65;
+    const CIPHERTEXT_LEN: usize = 65;
+    const SHARED_SECRET_LEN: usize = 32;
+
+    type DecapsulationKey = [u8; 32];
+    type EncapsulationKey = [u8; 65];
+    type Ciphertext = [u8; 65];
+    type SharedSecret = [u8; 32];
+
+    fn generate(rng: &dyn SecureRandom) -> ([u8; 32], [u8; 65]) {
+        let mut ikm = SecretKey::new([0; 32]);
+        rng.fill(&mut *ikm);
+        Self::derive_key_pair(&*ikm)
+    }
+
+    fn encapsulate(
+        encapsulation_key: &[u8],
+        rng: &dyn SecureRandom,
+    ) -> Result<([u8; 65], [u8; 32]), Error> {
+        if encapsulation_key.len() != Self::ENCAPSULATION_KEY_LEN {
+            return Err(Error::InvalidKey);
+        }
+        let (ephemeral_key, enc) = Self::generate(rng);
+        let ephemeral_key = SecretKey::new(ephemeral_key);
+        let dh = SecretKey::new(p256::ecdh(&*ephemeral_key, encapsulation_key)?);
+        let kem_context = &mut [0; 130];
+        kem_context[..65].copy_from_slice(&enc);
+        kem_context[65..].copy_from_slice(encapsulation_key);
+        let shared_secret = extract_and_expand(Self::KEM_ID, &*dh, kem_context);
+        Ok((enc, *shared_secret))
+    }
+
+    fn decapsulate(decapsulation_key: &[u8; 32], ciphertext: &[u8]) -> Result<[u8; 32], Error> {
+        if ciphertext.len() != Self::CIPHERTEXT_LEN {
+            return Err(Error::InvalidLength);
+        }
+        let dh = SecretKey::new(p256::ecdh(decapsulation_key, ciphertext)?);
+        let kem_context = &mut [0; 130];
+        kem_context[..65].copy_from_slice(ciphertext);
+        kem_context[65..].copy_from_slice(&p256::gen_pk(decapsulation_key)?);
+        let shared_secret = extract_and_expand(Self::KEM_ID, &*dh, kem_context);
+        Ok(*shared_secret)
+    }
+}
+
+/// Derives an X25519 key pair from input keying material, which should have at least 32 bytes of
+/// entropy, outputting the secret key and public key.
+pub fn derive_key_pair(ikm: &[u8]) -> ([u8; 32], [u8; 32]) {
+    let suite_id = &kem_suite_id(DhKemX25519::KEM_ID);
+    let dkp_prk = &labeled_extract::<Sha256>(suite_id, b"", b"dkp_prk", ikm)[..Sha256::DIGEST_SIZE];
+    let mut secret_key = [0; 32];
+    labeled_expand::<Sha256>(suite_id, dkp_prk, b"sk", b"", &mut secret_key);
+    let public_key = curve25519::gen_pk(&secret_key).expect("secret key is not 32 bytes");
+    (secret_key, public_key)
+}
+
+/// Sets up a context for sealing messages to the holder of `recipient_key` with no sender
+/// authentication, outputting the encapsulated key along with it. The KEM's randomness is drawn
+/// from `rng`.
+///
+/// # Errors
+///
+/// Fails with `Error::InvalidKey` if `recipient_key` is not a valid public key for `K`.
+pub fn setup_base_sender<K: KemId, H: KdfId, A: AeadId>(
+    recipient_key: &[u8],
+    info: &[u8],
+    rng: &dyn SecureRandom,
+) -> Result<(K::Ciphertext, SenderContext<H, A>), Error> {
+    setup_sender::<K, H, A>(MODE_BASE, recipient_key, info, b"", b"", rng)
+}
+
+/// Sets up a context for opening messages sealed to `secret_key` with no sender authentication.
+///
+/// # Errors
+///
+/// Fails with `Error::InvalidLength` if `enc` has the wrong length for `K`, or with
+/// `Error::InvalidKey` if it is not a valid encapsulated key otherwise.
+pub fn setup_base_recipient<K: KemId, H: KdfId, A: AeadId>(
+    enc: &[u8],
+    secret_key: &K::DecapsulationKey,
+    info: &[u8],
+) -> Result<RecipientContext<H, A>, Error> {
+    setup_recipient::<K, H, A>(MODE_BASE, enc, secret_key, info, b"", b"")
+}
+
+/// Sets up a context for sealing messages to the holder of `recipient_key`, authenticated by a
+/// pre-shared key and its identifier, outputting the encapsulated key along with it. The KEM's
+/// randomness is drawn from `rng`.
+///
+/// # Errors
+///
+/// Fails with `Error::InvalidKey` if `recipient_key` is not a valid public key for `K` or
+/// `psk.len()` is less than 32.
+pub fn setup_psk_sender<K: KemId, H: KdfId, A: AeadId>(
+    recipient_key: &[u8],
+    info: &[u8],
+    psk: &[u8],
+    psk_id: &[u8],
+    rng: &dyn SecureRandom,
+) -> Result<(K::Ciphertext, SenderContext<H, A>), Error> {
+    check_psk(psk)?;
+    setup_sender::<K, H, A>(MODE_PSK, recipient_key, info, psk, psk_id, rng)
+}
+
+/// Sets up a context for opening messages sealed to `secret_key` by a holder of a pre-shared
+/// key.
+///
+/// # Errors
+///
+/// Fails with `Error::InvalidLength` if `enc` has the wrong length for `K`, or with
+/// `Error::InvalidKey` if it is not a valid encapsulated key otherwise or `psk.len()` is less
+/// than 32.
+pub fn setup_psk_recipient<K: KemId, H: KdfId, A: AeadId>(
+    enc: &[u8],
+    secret_key: &K::DecapsulationKey,
+    info: &[u8],
+    psk: &[u8],
+    psk_id: &[u8],
+) -> Result<RecipientContext<H, A>, Error> {
+    check_psk(psk)?;
+    setup_recipient::<K, H, A>(MODE_PSK, enc, secret_key, info, psk, psk_id)
+}
+
+/// Sets up a DHKEM(X25519) context for sealing messages to the holder of `recipient_key`,
+/// authenticated by the sender's static secret key, outputting the encapsulated key along with
+/// it. The ephemeral key is drawn from `rng`.
+///
+/// # Errors
+///
+/// Fails with `Error::InvalidKey` if `recipient_key` is not a valid public key.
+pub fn setup_auth_sender<H: KdfId, A: AeadId>(
+    recipient_key: &[u8; 32],
+    info: &[u8],
+    sender_secret_key: &[u8; 32],
+    rng: &dyn SecureRandom,
+) -> Result<([u8; 32], SenderContext<H, A>), Error> {
+    setup_authenticated_sender(
+        MODE_AUTH,
+        recipient_key,
+        info,
+        b"",
+        b"",
+        sender_secret_key,
+        rng,
+    )
+}
+
+/// Sets up a DHKEM(X25519) context for opening messages sealed to `secret_key` by the holder of
+/// `sender_public_key`.
+///
+/// # Errors
+///
+/// Fails with `Error::InvalidKey` if `enc` is not a valid encapsulated key or
+/// `sender_public_key` is not a valid public key.
+pub fn setup_auth_recipient<H: KdfId, A: AeadId>(
+    enc: &[u8; 32],
+    secret_key: &[u8; 32],
+    info: &[u8],
+    sender_public_key: &[u8; 32],
+) -> Result<RecipientContext<H, A>, Error> {
+    setup_authenticated_recipient(
+        MODE_AUTH,
+        enc,
+        secret_key,
+        info,
+        b"",
+        b"",
+        sender_public_key,
+    )
+}
+
+/// Sets up a DHKEM(X25519) context for sealing messages to the holder of `recipient_key`,
+/// authenticated by both a pre-shared key and the sender's static secret key, outputting the
+/// encapsulated key along with it. The ephemeral key is drawn from `rng`.
+///
+/// # Errors
+///
+/// Fails with `Error::InvalidKey` if `recipient_key` is not a valid public key or `psk.len()` is
+/// less than 32.
+pub fn setup_auth_psk_sender<H: KdfId, A: AeadId>(
+    recipient_key: &[u8; 32],
+    info: &[u8],
+    psk: &[u8],
+    psk_id: &[u8],
+    sender_secret_key: &[u8; 32],
+    rng: &dyn SecureRandom,
+) -> Result<([u8; 32], SenderContext<H, A>), Error> {
+    check_psk(psk)?;
+    setup_authenticated_sender(
+        MODE_AUTH_PSK,
+        recipient_key,
+        info,
+        psk,
+        psk_id,
+        sender_secret_key,
+        rng,
+    )
+}
+
+/// Sets up a DHKEM(X25519) context for opening messages sealed to `secret_key` by the holder of
+/// `sender_public_key` and a pre-shared key.
+///
+/// # Errors
+///
+/// Fails with `Error::InvalidKey` if `enc` is not a valid encapsulated key,
+/// `sender_public_key` is not a valid public key or `psk.len()` is less than 32.
+pub fn setup_auth_psk_recipient<H: KdfId, A: AeadId>(
+    enc: &[u8; 32],
+    secret_key: &[u8; 32],
+    info: &[u8],
+    psk: &[u8],
+    psk_id: &[u8],
+    sender_public_key: &[u8; 32],
+) -> Result<RecipientContext<H, A>, Error> {
+    check_psk(psk)?;
+    setup_authenticated_recipient(
+        MODE_AUTH_PSK,
+        enc,
+        secret_key,
+        info,
+        psk,
+        psk_id,
+        sender_public_key,
+    )
+}
+
+fn check_psk(psk: &[u8]) -> Result<(), Error> {
+    if psk.len() < MIN_PSK_LEN {
+        return Err(Error::InvalidKey);
+    }
+    Ok(())
+}
+
+fn setup_sender<K: KemId, H: KdfId, A: AeadId>(
+    mode: u8,
+    recipient_key: &[u8],
+    info: &[u8],
+    psk: &[u8],
+    psk_id: &[u8],
+    rng: &dyn SecureRandom,
+) -> Result<(K::Ciphertext, SenderContext<H, A>), Error> {
+    let (enc, shared_secret) = K::encapsulate(recipient_key, rng)?;
+    let context = Context::new(K::KEM_ID, mode, shared_secret.as_ref(), info, psk, psk_id)?;
+    Ok((enc, SenderContext(context)))
+}
+
+fn setup_recipient<K: KemId, H: KdfId, A: AeadId>(
+    mode: u8,
+    enc: &[u8],
+    secret_key: &K::DecapsulationKey,
+    info: &[u8],
+    psk: &[u8],
+    psk_id: &[u8],
+) -> Result<RecipientContext<H, A>, Error> {
+    let shared_secret = K::decapsulate(secret_key, enc)?;
+    let context = Context::new(K::KEM_ID, mode, shared_secret.as_ref(), info, psk, psk_id)?;
+    Ok(RecipientContext(context))
+}
+
+fn setup_authenticated_sender<H: KdfId, A: AeadId>(
+    mode: u8,
+    recipient_key: &[u8; 32],
+    info: &[u8],
+    psk: &[u8],
+    psk_id: &[u8],
+    sender_key: &[u8; 32],
+    rng: &dyn SecureRandom,
+) -> Result<([u8; 32], SenderContext<H, A>), Error> {
+    let ephemeral_key = SecretKey::new(DhKemX25519::generate(rng).0);
+    let (enc, shared_secret) = encap(recipient_key, &ephemeral_key, Some(sender_key))?;
+    let kem_id = DhKemX25519::KEM_ID;
+    let context = Context::new(kem_id, mode, &*shared_secret, info, psk, psk_id)?;
+    Ok((enc, SenderContext(context)))
+}
+
+fn setup_authenticated_recipient<H: KdfId, A: AeadId>(
+    mode: u8,
+    enc: &[u8; 32],
+    secret_key: &[u8; 32],
+    info: &[u8],
+    psk: &[u8],
+    psk_id: &[u8],
+    sender_key: &[u8; 32],
+) -> Result<RecipientContext<H, A>, Error> {
+    let shared_secret = decap(enc, secret_key, Some(sender_key))?;
+    let kem_id = DhKemX25519::KEM_ID;
+    let context = Context::new(kem_id, mode, &*shared_secret, info, psk, psk_id)?;
+    Ok(RecipientContext(context))
+}
+
+/// Encapsulates a shared secret to `recipient_key` with the given ephemeral secret key, and with
+/// the sender's static secret key in the auth modes, outputting the encapsulated key and the
+/// shared secret.
+fn encap(
+    recipient_key: &[u8; 32],
+    ephemeral_key: &[u8; 32],
+    sender_key: Option<&[u8; 32]>,
+) -> Result<([u8; 32], SecretKey<[u8; 32]>), Error> {
+    let enc = curve25519::gen_pk(ephemeral_key)?;
+    let dh = &mut SecretKey::new([0; 64]);
+    dh[..32].copy_from_slice(&curve25519::x25519(ephemeral_key, recipient_key)?);
+    let kem_context = &mut [0; 96];
+    kem_context[..32].copy_from_slice(&enc);
+    kem_context[32..64].copy_from_slice(recipient_key);
+    let len = match sender_key {
+        Some(sender_key) => {
+            let static_dh = curve25519::x25519(sender_key, recipient_key)?;
+            dh[32..].copy_from_slice(&static_dh);
+            kem_context[64..].copy_from_slice(&curve25519::gen_pk(sender_key)?);
+            64
+        }
+        None => 32,
+    };
+    let kem_id = DhKemX25519::KEM_ID;
+    let shared_secret = extract_and_expand(kem_id, &dh[..len], &kem_context[..len + 32]);
+    Ok((enc, shared_secret))
+}
+
+/// Decapsulates the shared secret from `enc` with the recipient's secret key, and with the
+/// sender's public key in the auth modes.
+fn decap(
+    enc: &[u8; 32],
+    secret_key: &[u8; 32],
+    sender_key: Option<&[u8; 32]>,
+) -> Result<SecretKey<[u8; 32]>, Error> {
+    let dh = &mut SecretKey::new([0; 64]);
+    dh[..32].copy_from_slice(&curve25519::x25519(secret_key, enc)?);
+    let kem_context = &mut [0; 96];
+    kem_context[..32].copy_from_slice(enc);
+    kem_context[32..64].copy_from_slice(&curve25519::gen_pk(secret_key)?);
+    let len = match sender_key {
+        Some(sender_key) => {
+            let static_dh = curve25519::x25519(secret_key, sender_key)?;
+            dh[32..].copy_from_slice(&static_dh);
+            kem_context[64..].copy_from_slice(sender_key);
+            64
+        }
+        None => 32,
+    };
+    let kem_id = DhKemX25519::KEM_ID;
+    Ok(extract_and_expand(
+        kem_id,
+        &dh[..len],
+        &kem_context[..len + 32],
+    ))
+}
+
+fn extract_and_expand(kem_id: u16, dh: &[u8], kem_context: &[u8]) -> SecretKey<[u8; 32]> {
+    let suite_id = &kem_suite_id(kem_id);
+    let eae_prk = &labeled_extract::<Sha256>(suite_id, b"", b"eae_prk", dh)[..Sha256::DIGEST_SIZE];
+    let mut shared_secret = SecretKey::new([0; 32]);
+    labeled_expand::<Sha256>(
+        suite_id,
+        eae_prk,
+        b"shared_secret",
+        kem_context,
+        &mut *shared_secret,
+    );
+    shared_secret
+}
+
+fn kem_suite_id(kem_id: u16) -> [u8; 5] {
+    let mut suite_id = *b"KEM\0\0";
+    suite_id[3..].copy_from_slice(&kem_id.to_be_bytes());
+    suite_id
+}
+
+fn hpke_suite_id<H: KdfId, A: AeadId>(kem_id: u16) -> [u8; 10] {
+    let mut suite_id = *b"HPKE\0\0\0\0\0\0";
+    suite_id[4..6].copy_from_slice(&kem_id.to_be_bytes());
+    suite_id[6..8].copy_from_slice(&H::KDF_ID.to_be_bytes());
+    suite_id[8..].copy_from_slice(&A::AEAD_ID.to_be_bytes());
+    suite_id
+}
+
+/// HKDF-Extract with the input keying material prefixed by the version, suite and label. The
+/// output is the first `H::DIGEST_SIZE` bytes.
+fn labeled_extract<H: HashFunction>(
+    suite_id: &[u8],
+    salt: &[u8],
+    label: &[u8],
+    ikm: &[u8],
+) -> SecretKey<[u8; MAX_DIGEST_SIZE]> {
+    let mut hmac = Hmac::<H>::new(salt);
+    for part in &[&b"HPKE-v1"[..], suite_id, label, ikm] {
+        hmac.update(part);
+    }
+    let mut prk = SecretKey::new([0; MAX_DIGEST_SIZE]);
+    prk[..H::DIGEST_SIZE].copy_from_slice(&hmac.tag());
+    prk
+}
+
+/// HKDF-Expand with the info prefixed by the output length, version, suite and label. The info
+/// is fed to HMAC in parts, so that it needn't be copied into a buffer.
+fn labeled_expand<H: HashFunction>(
+    suite_id: &[u8],
+    prk: &[u8],
+    label: &[u8],
+    info: &[u8],
+    output: &mut [u8],
+) {
+    assert!(output.len() <= 255 * H::DIGEST_SIZE);
+    let len = (output.len() as u16).to_be_bytes();
+    let hmac = Hmac::<H>::new(prk);
+    let mut previous: Option<Tag> = None;
+    for (i, chunk) in output.chunks_mut(H::DIGEST_SIZE).enumerate() {
+        let mut hmac = hmac.clone();
+        if let Some(previous) = &previous {
+            hmac.update(previous);
+        }
+        for part in &[&len[..], b"HPKE-v1", suite_id, label, info, &[i as u8 + 1]] {
+            hmac.update(part);
+        }
+        let block = hmac.tag();
+        chunk.copy_from_slice(&block[..chunk.len()]);
+        previous = Some(block);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mlkem::mlkem768;
+    use crate::rand::SystemRandom;
+    use crate::sha3::sha3_256;
+    use crate::test_helpers::*;
+
+    const INFO: &[u8] = b"Ode on a Grecian Urn";
+    const PLAINTEXT: &[u8] = b"Beauty is truth, truth beauty";
+    const PSK: &str = "0247fd33b913760fa1fa51e1892d9f307fbe65eb171e8132c2af18555a738b82";
+    const PSK_ID: &[u8] = b"Ennyn Durin aran Moria";
+
+    fn array(hex: &str) -> [u8; 32] {
+        let mut bytes = [0; 32];
+        bytes.copy_from_slice(&h2b(hex));
+        bytes
+    }
+
+    struct Vector {
+        mode: u8,
+        ikm_e: &'static str,
+        ikm_r: &'static str,
+        ikm_s: Option<&'static str>,
+        enc: &'static str,
+        ciphertexts: [&'static str; 2],
+        exports: [&'static str; 3],
+    }
+
+    fn check<H: KdfId, A: AeadId>(vector: &Vector) {
+        const KEM_ID: u16 = DhKemX25519::KEM_ID;
+        let (ephemeral_key, _) = derive_key_pair(&h2b(vector.ikm_e));
+        let (secret_key, public_key) = derive_key_pair(&h2b(vector.ikm_r));
+        let sender = vector.ikm_s.map(|ikm| derive_key_pair(&h2b(ikm)));
+        let psk = &h2b(PSK);
+        let (psk, psk_id) = match vector.mode {
+            MODE_PSK | MODE_AUTH_PSK => (&psk[..], PSK_ID),
+            _ => (&b""[..], &b""[..]),
+        };
+
+        let sender_secret_key = sender.as_ref().map(|(secret_key, _)| secret_key);
+        let (enc, shared_secret) = encap(&public_key, &ephemeral_key, sender_secret_key).unwrap();
+        assert_eq!(&h2b(vector.enc)[..], &enc);
+        let context =
+            Context::<H, A>::new(KEM_ID, vector.mode, &*shared_secret, INFO, psk, psk_id).unwrap();
+        let mut sender_context = SenderContext(context);
+
+        let sender_public_key = sender.as_ref().map(|(_, public_key)| public_key);
+        let shared_secret = decap(&enc, &secret_key, sender_public_key).unwrap();
+        let context =
+            Context::<H, A>::new(KEM_ID, vector.mode, &*shared_secret, INFO, psk, psk_id).unwrap();
+        let mut recipient_context = RecipientContext(context);
+
+        for (i, ciphertext) in vector.ciphertexts.iter().enumerate() {
+            let aad = &[b"Count-", &[b'0' + i as u8][..]].concat();
+            let buffer = &mut [PLAINTEXT, &[0; 16]].concat();
+            sender_context.seal_in_place(aad, buffer).unwrap();
+            assert_eq!(&h2b(ciphertext), buffer);
+            let opened = recipient_context.open_in_place(aad, buffer).unwrap();
+            assert_eq!(PLAINTEXT, opened);
+        }
+        for (exporter_context, expected) in [&b""[..], b"\0", b"TestContext"]
+            .iter()
+            .zip(&vector.exports)
+        {
+            let exported = &mut [0; 32];
+            sender_context.export(exporter_context, exported).unwrap();
+            assert_eq!(&h2b(expected)[..], exported);
+            recipient_context
+                .export(exporter_context, exported)
+                .unwrap();
+            assert_eq!(&h2b(expected)[..], exported);
+        }
+    }
+
+    #[test]
+    fn test_derive_key_pair() {
+        // RFC 9180, A.1.1
+        let (secret_key, public_key) = derive_key_pair(&h2b(
+            "6db9df30aa07dd42ee5e8181afdb977e538f5e1fec8a06223f33f7013e525037",
+        ));
+        assert_eq!(
+            array("4612c550263fc8ad58375df3f557aac531d26850903e55a9f23f21d8534e8ac8"),
+            secret_key
+        );
+        assert_eq!(
+            array("3948cfe0ad1ddb695d780e59077195da6c56506b027329794ab02bca80815c4d"),
+            public_key
+        );
+    }
+
+    #[test]
+    fn test_derive_p256_key_pair() {
+        // RFC 9180, A.3.1
+        let (secret_key, public_key) = DhKemP256::derive_key_pair(&h2b(
+            "668b37171f1072f3cf12ea8a236a45df23fc13b82af3609ad1e354f6ef817550",
+        ));
+        assert_eq!(
+            array("f3ce7fdae57e1a310d87f1ebbde6f328be0a99cdbcadf4d6589cf29de4b8ffd2"),
+            secret_key
+        );
+        assert_eq!(
+            h2b(
+                "04fe8c19ce0905191ebc298a9245792531f26f0cece2460639e8bc39cb7f706a\
+                 826a779b4cf969b8a0e539c7f62fb3d30ad6aa8f80e30f1d128aafd68a2ce72ea0"
+            ),
+            public_key
+        );
+        let (_, public_key) = DhKemP256::derive_key_pair(&h2b(
+            "4270e54ffd08d79d5928020af4686d8f6b7d35dbe470265f1f5aa22816ce860e",
+        ));
+        assert_eq!(
+            h2b(
+                "04a92719c6195d5085104f469a8b9814d5838ff72b60501e2c4466e5e67b325a\
+                 c98536d7b61a1af4b78e5b7f951c0900be863c403ce65c9bfcb9382657222d18c4"
+            ),
+            public_key
+        );
+    }
+
+    #[test]
+    fn test_vectors() {
+        // RFC 9180, A.1.1
+        check::<Sha256, Gcm<Aes128>>(&Vector {
+            mode: MODE_BASE,
+            ikm_e: "7268600d403fce431561aef583ee1613527cff655c1343f29812e66706df3234",
+            ikm_r: "6db9df30aa07dd42ee5e8181afdb977e538f5e1fec8a06223f33f7013e525037",
+            ikm_s: None,
+            enc: "37fda3567bdbd628e88668c3c8d7e97d1d1253b6d4ea6d44c150f741f1bf4431",
+            ciphertexts: [
+                "f938558b5d72f1a23810b4be2ab4f84331acc02fc97babc53a52ae8218a355a96d8770ac83d07bea87e13c512a",
+                "af2d7e9ac9ae7e270f46ba1f975be53c09f8d875bdc8535458c2494e8a6eab251c03d0c22a56b8ca42c2063b84",
+            ],
+            exports: [
+                "3853fe2b4035195a573ffc53856e77058e15d9ea064de3e59f4961d0095250ee",
+                "2e8f0b54673c7029649d4eb9d5e33bf1872cf76d623ff164ac185da9e88c21a5",
+                "e9e43065102c3836401bed8c3c3c75ae46be1639869391d62c61f1ec7af54931",
+            ],
+        });
+
+        // RFC 9180, A.1.2
+        check::<Sha256, Gcm<Aes128>>(&Vector {
+            mode: MODE_PSK,
+            ikm_e: "78628c354e46f3e169bd231be7b2ff1c77aa302460a26dbfa15515684c00130b",
+            ikm_r: "d4a09d09f575fef425905d2ab396c1449141463f698f8efdb7accfaff8995098",
+            ikm_s: None,
+            enc: "0ad0950d9fb9588e59690b74f1237ecdf1d775cd60be2eca57af5a4b0471c91b",
+            ciphertexts: [
+                "e52c6fed7f758d0cf7145689f21bc1be6ec9ea097fef4e959440012f4feb73fb611b946199e681f4cfc34db8ea",
+                "49f3b19b28a9ea9f43e8c71204c00d4a490ee7f61387b6719db765e948123b45b61633ef059ba22cd62437c8ba",
+            ],
+            exports: [
+                "dff17af354c8b41673567db6259fd6029967b4e1aad13023c2ae5df8f4f43bf6",
+                "6a847261d8207fe596befb52928463881ab493da345b10e1dcc645e3b94e2d95",
+                "8aff52b45a1be3a734bc7a41e20b4e055ad4c4d22104b0c20285a7c4302401cd",
+            ],
+        });
+
+        // RFC 9180, A.1.3
+        check::<Sha256, Gcm<Aes128>>(&Vector {
+            mode: MODE_AUTH,
+            ikm_e: "6e6d8f200ea2fb20c30b003a8b4f433d2f4ed4c2658d5bc8ce2fef718059c9f7",
+            ikm_r: "f1d4a30a4cef8d6d4e3b016e6fd3799ea057db4f345472ed302a67ce1c20cdec",
+            ikm_s: Some("94b020ce91d73fca4649006c7e7329a67b40c55e9e93cc907d282bbbff386f58"),
+            enc: "23fb952571a14a25e3d678140cd0e5eb47a0961bb18afcf85896e5453c312e76",
+            ciphertexts: [
+                "5fd92cc9d46dbf8943e72a07e42f363ed5f721212cd90bcfd072bfd9f44e06b80fd17824947496e21b680c141b",
+                "d3736bb256c19bfa93d79e8f80b7971262cb7c887e35c26370cfed62254369a1b52e3d505b79dd699f002bc8ed",
+            ],
+            exports: [
+                "28c70088017d70c896a8420f04702c5a321d9cbf0279fba899b59e51bac72c85",
+                "25dfc004b0892be1888c3914977aa9c9bbaf2c7471708a49e1195af48a6f29ce",
+                "5a0131813abc9a522cad678eb6bafaabc43389934adb8097d23c5ff68059eb64",
+            ],
+        });
+
+        // RFC 9180, A.2.1
+        check::<Sha256, ChaCha20Poly1305>(&Vector {
+            mode: MODE_BASE,
+            ikm_e: "909a9b35d3dc4713a5e72a4da274b55d3d3821a37e5d099e74a647db583a904b",
+            ikm_r: "1ac01f181fdf9f352797655161c58b75c656a6cc2716dcb66372da835542e1df",
+            ikm_s: None,
+            enc: "1afa08d3dec047a643885163f1180476fa7ddb54c6a8029ea33f95796bf2ac4a",
+            ciphertexts: [
+                "1c5250d8034ec2b784ba2cfd69dbdb8af406cfe3ff938e131f0def8c8b60b4db21993c62ce81883d2dd1b51a28",
+                "6b53c051e4199c518de79594e1c4ab18b96f081549d45ce015be002090bb119e85285337cc95ba5f59992dc98c",
+            ],
+            exports: [
+                "4bbd6243b8bb54cec311fac9df81841b6fd61f56538a775e7c80a9f40160606e",
+                "8c1df14732580e5501b00f82b10a1647b40713191b7c1240ac80e2b68808ba69",
+                "5acb09211139c43b3090489a9da433e8a30ee7188ba8b0a9a1ccf0c229283e53",
+            ],
+        });
+
+        // Generated with a Python implementation checked against the RFC's vectors
+        check::<Sha384, Gcm<Aes256>>(&Vector {
+            mode: MODE_AUTH_PSK,
+            ikm_e: "0000000000000000000000000000000000000000000000000000000000000001",
+            ikm_r: "0000000000000000000000000000000000000000000000000000000000000002",
+            ikm_s: Some("0000000000000000000000000000000000000000000000000000000000000003"),
+            enc: "a59fa8886f6f6a302db37b18359b677db1304990a9d976e467a9ff97bad8ce48",
+            ciphertexts: [
+                "917e410bbf545c5e52dd9f043d76402248e67514ae2886c0b610a9a060349d4e878fc4943aaf70aa04434bd77b",
+                "2d0f2c1c9f5f6171dec3a89023d0fa6a20193e904a181b71c3258c1e2666408b814c3d9ec3e0c97b4e74dcdff7",
+            ],
+            exports: [
+                "dc265c57d6efbb5d1f286ad2e9bb5163e56ca35c4de6ecd26e3b8852cba6daff",
+                "8de2627d713df6b7229249ddd40a2c54a224b9af4c01d61c912ae1d6be752891",
+                "071965efa3c3e415253a8e54ce4b2527fb2a45178d4991ee83ec8d265d1e1fd3",
+            ],
+        });
+
+        // Generated likewise
+        check::<Sha512, ChaCha20Poly1305>(&Vector {
+            mode: MODE_PSK,
+            ikm_e: "0000000000000000000000000000000000000000000000000000000000000004",
+            ikm_r: "0000000000000000000000000000000000000000000000000000000000000005",
+            ikm_s: None,
+            enc: "6b1ab32a231d83fd3fc4d01a9254a1d05e35e44310a67e2d638da0086fdef14c",
+            ciphertexts: [
+                "e7c8376c600164f44acc91c88a24fa87aaf296165e115f55cc8d6040b949162a5add288726d2d83075765b907c",
+                "319046c3cdb47021d3ab3f69f64c06e568e6a73972a4694b6affd2562cebdae572666c1ef230b0f6a885d4208d",
+            ],
+            exports: [
+                "a217444d66dd7b4da61c02c348d3ae91e195bf0e9aac41c49963d438bbe688b5",
+                "193087fc386bbc2febebf76f08e4796dadab5430a7d110f5784a1ebf2f242b65",
+                "560268fecdecaed6c0d148736ff838bc122ad49b6552d90d09101a0aff92e25d",
+            ],
+        });
+    }
+
+    #[test]
+    fn test_interoperability() {
+        // Sealed with a single-shot base-mode context by Python's cryptography to the recipient
+        // key of RFC 9180, A.1.1
+        let secret_key = &array("4612c550263fc8ad58375df3f557aac531d26850903e55a9f23f21d8534e8ac8");
+        let sealed = &mut h2b(
+            "83e6629da683011af88e58dc790c59868b7413ecba7a56280112e5ee0bf85062e1abd5025b68b3de1bf2f9fa6d\
+             4e3bde93979282940d87885ebcb0cd9d332a779ac431a73e1bdfb0634d20718e",
+        );
+        let (enc, buffer) = sealed.split_at_mut(32);
+        let mut context =
+            setup_base_recipient::<DhKemX25519, Sha384, Gcm<Aes256>>(enc, secret_key, INFO)
+                .unwrap();
+        assert_eq!(PLAINTEXT, context.open_in_place(b"", buffer).unwrap());
+
+        // Likewise with DHKEM(P-256) to the recipient key of RFC 9180, A.3.1
+        let secret_key = &array("f3ce7fdae57e1a310d87f1ebbde6f328be0a99cdbcadf4d6589cf29de4b8ffd2");
+        let sealed = &mut h2b(
+            "04776d3d379d06426bb48d063ccb493cfc49b2c82e8491d8d3021a35e01de4d5099777d7f8e43d7baa7e72\
+             128cbad6cc9581a8a3dfa94a271f1464a84a3b4c1a501deb190349e689902439d90e5e363d37acaefe7f9d\
+             2a9e5fa441b5be046f4dabed0411f4c1a4cd363ff892139e",
+        );
+        let (enc, buffer) = sealed.split_at_mut(65);
+        let mut context =
+            setup_base_recipient::<DhKemP256, Sha256, Gcm<Aes128>>(enc, secret_key, INFO).unwrap();
+        assert_eq!(PLAINTEXT, context.open_in_place(b"", buffer).unwrap());
+
+        // Sealed to the ML-KEM-768 key with seed 0, 1, ..., 63, and opened by Python's
+        // cryptography
+        let mut seed = [0; 64];
+        for (i, byte) in seed.iter_mut().enumerate() {
+            *byte = i as u8;
+        }
+        let (secret_key, public_key) = &mlkem768::derive_key_pair(&seed);
+        let rng = &|dest: &mut [u8]| dest.fill(0x42);
+        let (enc, mut sender) =
+            setup_base_sender::<MlKem768, Sha256, Gcm<Aes128>>(public_key, INFO, rng).unwrap();
+        let buffer = &mut [PLAINTEXT, &[0; 16]].concat();
+        sender.seal_in_place(b"", buffer).unwrap();
+        assert_eq!(
+            h2b("869b10b4b123402f191ece89221d578ab1e284fe6315494578996e7067a2a889"),
+            sha3_256(&[&enc[..], buffer].concat())
+        );
+        let mut context =
+            setup_base_recipient::<MlKem768, Sha256, Gcm<Aes128>>(&enc, secret_key, INFO).unwrap();
+        assert_eq!(PLAINTEXT, context.open_in_place(b"", buffer).unwrap());
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let rng = &SystemRandom::new();
+        let (secret_key, public_key) = &derive_key_pair(&[1; 32]);
+        let (sender_secret_key, sender_public_key) = &derive_key_pair(&[2; 32]);
+        let psk = &[3; 32];
+
+        let (enc, mut sender) =
+            setup_base_sender::<DhKemX25519, Sha256, Gcm<Aes128>>(public_key, INFO, rng).unwrap();
+        let mut recipient =
+            setup_base_recipient::<DhKemX25519, _, _>(&enc, secret_key, INFO).unwrap();
+        check_contexts(&mut sender, &mut recipient);
+
+        let (enc, mut sender) = setup_psk_sender::<DhKemX25519, Sha384, ChaCha20Poly1305>(
+            public_key, INFO, psk, PSK_ID, rng,
+        )
+        .unwrap();
+        let mut recipient =
+            setup_psk_recipient::<DhKemX25519, _, _>(&enc, secret_key, INFO, psk, PSK_ID).unwrap();
+        check_contexts(&mut sender, &mut recipient);
+        recipient =
+            setup_psk_recipient::<DhKemX25519, _, _>(&enc, secret_key, INFO, &[4; 32], PSK_ID)
+                .unwrap();
+        let buffer = &mut [PLAINTEXT, &[0; 16]].concat();
+        sender.seal_in_place(b"", buffer).unwrap();
+        assert_eq!(
+            Err(Error::TagMismatch),
+            recipient.open_in_place(b"", buffer)
+        );
+
+        let (enc, mut sender) =
+            setup_auth_sender::<Sha512, Gcm<Aes256>>(public_key, INFO, sender_secret_key, rng)
+                .unwrap();
+        let mut recipient =
+            setup_auth_recipient(&enc, secret_key, INFO, sender_public_key).unwrap();
+        check_contexts(&mut sender, &mut recipient);
+        recipient = setup_auth_recipient(&enc, secret_key, INFO, public_key).unwrap();
+        let buffer = &mut [PLAINTEXT, &[0; 16]].concat();
+        sender.seal_in_place(b"", buffer).unwrap();
+        assert_eq!(
+            Err(Error::TagMismatch),
+            recipient.open_in_place(b"", buffer)
+        );
+
+        let (enc, mut sender) = setup_auth_psk_sender::<Sha256, ChaCha20Poly1305>(
+            public_key,
+            INFO,
+            psk,
+            PSK_ID,
+            sender_secret_key,
+            rng,
+        )
+        .unwrap();
+        let mut recipient =
+            setup_auth_psk_recipient(&enc, secret_key, INFO, psk, PSK_ID, sender_public_key)
+                .unwrap();
+        check_contexts(&mut sender, &mut recipient);
+    }
+
+    fn check_kem<K: KemId>() {
+        let rng = &SystemRandom::new();
+        let (secret_key, public_key) = K::generate(rng);
+        let (enc, mut sender) =
+            setup_base_sender::<K, Sha256, Gcm<Aes128>>(public_key.as_ref(), INFO, rng).unwrap();
+        let mut recipient =
+            setup_base_recipient::<K, _, _>(enc.as_ref(), &secret_key, INFO).unwrap();
+        check_contexts(&mut sender, &mut recipient);
+
+        let psk = &[3; 32];
+        let (enc, mut sender) = setup_psk_sender::<K, Sha512, ChaCha20Poly1305>(
+            public_key.as_ref(),
+            INFO,
+            psk,
+            PSK_ID,
+            rng,
+        )
+        .unwrap();
+        let mut recipient =
+            setup_psk_recipient::<K, _, _>(enc.as_ref(), &secret_key, INFO, psk, PSK_ID).unwrap();
+        check_contexts(&mut sender, &mut recipient);
+    }
+
+    #[test]
+    fn test_kems() {
+        check_kem::<DhKemX25519>();
+        check_kem::<DhKemP256>();
+        check_kem::<MlKem512>();
+        check_kem::<MlKem768>();
+        check_kem::<MlKem1024>();
+    }
+
+    fn check_contexts<H: KdfId, A: AeadId>(
+        sender: &mut SenderContext<H, A>,
+        recipient: &mut RecipientContext<H, A>,
+    ) {
+        let (exported, expected) = (&mut [0; 100], &mut [0; 100]);
+        sender.export(b"context", exported).unwrap();
+        recipient.export(b"context", expected).unwrap();
+        assert_eq!(&expected[..], &exported[..]);
+        let too_long = &mut vec![0; 255 * H::DIGEST_SIZE + 1];
+        assert_eq!(Err(Error::InvalidLength), sender.export(b"", too_long));
+        assert_eq!(Err(Error::InvalidLength), recipient.export(b"", too_long));
+        let longest = &mut too_long[1..];
+        sender.export(b"", longest).unwrap();
+
+        let first = &mut [PLAINTEXT, &[0; 16]].concat();
+        sender.seal_in_place(b"first", first).unwrap();
+        let second = &mut [PLAINTEXT, &[0; 16]].concat();
+        sender.seal_in_place(b"second", second).unwrap();
+        // Messages are opened in order, and a failure doesn't advance the sequence.
+        let opened = recipient.open_in_place(b"second", second).map(|_| ());
+        assert_eq!(Err(Error::TagMismatch), opened);
+        let opened = recipient.open_in_place(b"", first).map(|_| ());
+        assert_eq!(Err(Error::TagMismatch), opened);
+        assert_eq!(PLAINTEXT, recipient.open_in_place(b"first", first).unwrap());
+        assert_eq!(
+            PLAINTEXT,
+            recipient.open_in_place(b"second", second).unwrap()
+        );
+
+        #[cfg(feature = "alloc")]
+        {
+            let sealed = &sender.seal(b"third", PLAINTEXT).unwrap();
+            assert_eq!(PLAINTEXT, &recipient.open(b"third", sealed).unwrap()[..]);
+        }
+    }
+
+    #[test]
+    fn test_invalid() {
+        let rng = &SystemRandom::new();
+        let (secret_key, public_key) = &derive_key_pair(&[1; 32]);
+        let error = Err(Error::InvalidKey);
+        let sender = setup_base_sender::<DhKemX25519, Sha256, Gcm<Aes128>>(&[0; 32], INFO, rng);
+        assert_eq!(error, sender.map(|_| ()));
+        let sender = setup_base_sender::<DhKemX25519, Sha256, Gcm<Aes128>>(&[9; 31], INFO, rng);
+        assert_eq!(error, sender.map(|_| ()));
+        let recipient =
+            setup_base_recipient::<DhKemX25519, Sha256, Gcm<Aes128>>(&[0; 32], secret_key, INFO);
+        assert_eq!(error, recipient.map(|_| ()));
+        let recipient =
+            setup_base_recipient::<DhKemX25519, Sha256, Gcm<Aes128>>(&[9; 31], secret_key, INFO);
+        assert_eq!(Err(Error::InvalidLength), recipient.map(|_| ()));
+        let sender = setup_psk_sender::<DhKemX25519, Sha256, Gcm<Aes128>>(
+            public_key, INFO, &[3; 31], b"", rng,
+        );
+        assert_eq!(error, sender.map(|_| ()));
+        let (p256_secret_key, p256_public_key) = &DhKemP256::derive_key_pair(&[1; 32]);
+        let sender =
+            setup_base_sender::<DhKemP256, Sha256, Gcm<Aes128>>(&p256_public_key[1..], INFO, rng);
+        assert_eq!(error, sender.map(|_| ()));
+        let recipient =
+            setup_base_recipient::<DhKemP256, Sha256, Gcm<Aes128>>(&[4; 65], p256_secret_key, INFO);
+        assert_eq!(error, recipient.map(|_| ()));
+        let recipient =
+            setup_auth_recipient::<Sha256, Gcm<Aes128>>(public_key, secret_key, INFO, &[0; 32]);
+        assert_eq!(error, recipient.map(|_| ()));
+
+        let (enc, mut sender) =
+            setup_base_sender::<DhKemX25519, Sha256, Gcm<Aes128>>(public_key, INFO, rng).unwrap();
+        let mut recipient =
+            setup_base_recipient::<DhKemX25519, Sha256, Gcm<Aes128>>(&enc, secret_key, INFO)
+                .unwrap();
+        assert_eq!(
+            Err(Error::InvalidLength),
+            sender.seal_in_place(b"", &mut [0; 15])
+        );
+        sender.0.sequence = u64::MAX - 1;
+        recipient.0.sequence = u64::MAX - 1;
+        let buffer = &mut [0; 16];
+        sender.seal_in_place(b"", buffer).unwrap();
+        recipient.open_in_place(b"", buffer).unwrap();
+        assert_eq!(
+            Err(Error::NonceExhausted),
+            sender.seal_in_place(b"", buffer)
+        );
+        let opened = recipient.open_in_place(b"", buffer).map(|_| ());
+        assert_eq!(Err(Error::NonceExhausted), opened);
+    }
+}
@@ -0,0 +1,234 @@
+//! Module for the X25519MLKEM768 hybrid KEM, which combines X25519 with ML-KEM-768 as in the TLS
+//! key exchange of the same name (draft-ietf-tls-ecdhe-mlkem).
+//!
+//! An encapsulation key is an ML-KEM-768 encapsulation key followed by an X25519 public key, and
+//! a ciphertext is an ML-KEM-768 ciphertext followed by an ephemeral X25519 public key. The
+//! 64-byte shared secret is the ML-KEM-768 shared secret followed by the X25519 one. It must be
+//! combined with a KDF such as HKDF, as the TLS 1.3 key schedule does with HKDF-Extract, and then
+//! stays secret as long as either X25519 or ML-KEM-768 is secure: a quantum computer that breaks
+//! X25519 doesn't break the exchange, and neither does a flaw in the newer ML-KEM.
+//!
+//! # Examples
+//!
+//! ```
+//! use crypto_pure::hkdf::Hkdf;
+//! use crypto_pure::hybrid::X25519MlKem768;
+//! use crypto_pure::kem::Kem;
+//! use crypto_pure::rand::SystemRandom;
+//! use crypto_pure::sha2::Sha256;
+//! let rng = &SystemRandom::new();
+//! let (decapsulation_key, encapsulation_key) = X25519MlKem768::generate(rng);
+//! let (ciphertext, sender_secret) = X25519MlKem768::encapsulate(&encapsulation_key, rng)?;
+//! let recipient_secret = X25519MlKem768::decapsulate(&decapsulation_key, &ciphertext)?;
+//! assert_eq!(sender_secret, recipient_secret);
+//! let key: [u8; 32] = Hkdf::<Sha256>::extract(&[], &sender_secret).expand_to_array(b"key");
+//! # Ok::<(), crypto_pure::error::Error>(())
+//! ```
+use crate::curve25519;
+use crate::error::Error;
+use crate::kem::Kem;
+use crate::mlkem::{self, mlkem768};
+use crate::rand::SecureRandom;
+use crate::secret::SecretKey;
+
+/// The length of an encapsulation key.
+pub const ENCAPSULATION_KEY_LEN: usize = mlkem768::ENCAPSULATION_KEY_LEN + 32;
+
+/// The length of a ciphertext.
+pub const CIPHERTEXT_LEN: usize = mlkem768::CIPHERTEXT_LEN + 32;
+
+/// The length of a shared secret.
+pub const SHARED_SECRET_LEN: usize = mlkem::SHARED_SECRET_LEN + 32;
+
+/// The seed of an X25519MLKEM768 key pair: an ML-KEM-768 seed d || z followed by an X25519
+/// secret key.
+pub const SEED_LEN: usize = mlkem::SEED_LEN + 32;
+
+/// The X25519MLKEM768 hybrid KEM.
+pub struct X25519MlKem768;
+
+/// An X25519MLKEM768 decapsulation key, which is erased when dropped.
+pub struct DecapsulationKey {
+    ml_kem: SecretKey<[u8; mlkem768::DECAPSULATION_KEY_LEN]>,
+    x25519: SecretKey<[u8; 32]>,
+}
+
+impl X25519MlKem768 {
+    /// Derives a key pair from a seed, outputting the decapsulation key and the encapsulation
+    /// key.
+    pub fn derive_key_pair(
+        seed: &[u8; SEED_LEN],
+    ) -> (DecapsulationKey, [u8; ENCAPSULATION_KEY_LEN]) {
+        let (ml_kem_bytes, x25519_key) = seed.split_at(mlkem::SEED_LEN);
+        let mut ml_kem_seed = SecretKey::new([0; mlkem::SEED_LEN]);
+        ml_kem_seed.copy_from_slice(ml_kem_bytes);
+        let (ml_kem, ml_kem_public) = mlkem768::derive_key_pair(&ml_kem_seed);
+        let mut x25519 = SecretKey::new([0; 32]);
+        x25519.copy_from_slice(x25519_key);
+        let mut encapsulation_key = [0; ENCAPSULATION_KEY_LEN];
+        let (ml_kem_bytes, x25519_bytes) =
+            encapsulation_key.split_at_mut(mlkem768::ENCAPSULATION_KEY_LEN);
+        ml_kem_bytes.copy_from_slice(&ml_kem_public);
+        x25519_bytes.copy_from_slice(&curve25519::gen_pk(&*x25519).expect("key is not 32 bytes"));
+        let decapsulation_key = DecapsulationKey {
+            ml_kem: SecretKey::new(ml_kem),
+            x25519,
+        };
+        (decapsulation_key, encapsulation_key)
+    }
+}
+
+impl Kem for X25519MlKem768 {
+    const ENCAPSULATION_KEY_LEN: usize = ENCAPSULATION_KEY_LEN;
+    const CIPHERTEXT_LEN: usize = CIPHERTEXT_LEN;
+    const SHARED_SECRET_LEN: usize = SHARED_SECRET_LEN;
+
+    type DecapsulationKey = DecapsulationKey;
+    type EncapsulationKey = [u8; ENCAPSULATION_KEY_LEN];
+    type Ciphertext = [u8; CIPHERTEXT_LEN];
+    type SharedSecret = [u8; SHARED_SECRET_LEN];
+
+    fn generate(rng: &dyn SecureRandom) -> (DecapsulationKey, [u8; ENCAPSULATION_KEY_LEN]) {
+        let mut seed = SecretKey::new([0; SEED_LEN]);
+        rng.fill(&mut seed[..]);
+        Self::derive_key_pair(&seed)
+    }
+
+    fn encapsulate(
+        encapsulation_key: &[u8],
+        rng: &dyn SecureRandom,
+    ) -> Result<([u8; CIPHERTEXT_LEN], [u8; SHARED_SECRET_LEN]), Error> {
+        if encapsulation_key.len() != ENCAPSULATION_KEY_LEN {
+            return Err(Error::InvalidKey);
+        }
+        let (ml_kem_key, x25519_key) = encapsulation_key.split_at(mlkem768::ENCAPSULATION_KEY_LEN);
+        let (ml_kem_ciphertext, ml_kem_secret) = mlkem768::encapsulate(ml_kem_key, rng)?;
+        let mut ephemeral_key = SecretKey::new([0; 32]);
+        rng.fill(&mut *ephemeral_key);
+        let x25519_secret = curve25519::x25519(&*ephemeral_key, x25519_key)?;
+
+        let mut ciphertext = [0; CIPHERTEXT_LEN];
+        ciphertext[..mlkem768::CIPHERTEXT_LEN].copy_from_slice(&ml_kem_ciphertext);
+        ciphertext[mlkem768::CIPHERTEXT_LEN..]
+            .copy_from_slice(&curve25519::gen_pk(&*ephemeral_key)?);
+        Ok((ciphertext, concat(&ml_kem_secret, &x25519_secret)))
+    }
+
+    fn decapsulate(
+        decapsulation_key: &DecapsulationKey,
+        ciphertext: &[u8],
+    ) -> Result<[u8; SHARED_SECRET_LEN], Error> {
+        if ciphertext.len() != CIPHERTEXT_LEN {
+            return Err(Error::InvalidLength);
+        }
+        let (ml_kem_ciphertext, x25519_key) = ciphertext.split_at(mlkem768::CIPHERTEXT_LEN);
+        let ml_kem_secret = mlkem768::decapsulate(&*decapsulation_key.ml_kem, ml_kem_ciphertext)?;
+        let x25519_secret = curve25519::x25519(&*decapsulation_key.x25519, x25519_key)?;
+        Ok(concat(&ml_kem_secret, &x25519_secret))
+    }
+}
+
+fn concat(ml_kem_secret: &[u8], x25519_secret: &[u8]) -> [u8; SHARED_SECRET_LEN] {
+    let mut shared_secret = [0; SHARED_SECRET_LEN];
+    shared_secret[..mlkem::SHARED_SECRET_LEN].copy_from_slice(ml_kem_secret);
+    shared_secret[mlkem::SHARED_SECRET_LEN..].copy_from_slice(x25519_secret);
+    shared_secret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rand::SystemRandom;
+    use crate::sha3::sha3_256;
+    use crate::test_helpers::*;
+
+    #[test]
+    fn test_kat() {
+        let mut seed = [0; SEED_LEN];
+        for (i, byte) in seed.iter_mut().enumerate() {
+            *byte = i as u8;
+        }
+        let (decapsulation_key, encapsulation_key) = X25519MlKem768::derive_key_pair(&seed);
+        let (ml_kem_key, x25519_key) = encapsulation_key.split_at(mlkem768::ENCAPSULATION_KEY_LEN);
+        let mut ml_kem_seed = [0; mlkem::SEED_LEN];
+        ml_kem_seed.copy_from_slice(&seed[..mlkem::SEED_LEN]);
+        assert_eq!(mlkem768::derive_key_pair(&ml_kem_seed).1, ml_kem_key);
+        assert_eq!(curve25519::gen_pk(&seed[64..]).unwrap(), x25519_key);
+
+        let rng = &|dest: &mut [u8]| dest.fill(0x42);
+        let (ciphertext, shared_secret) =
+            X25519MlKem768::encapsulate(&encapsulation_key, rng).unwrap();
+        assert_eq!(
+            h2b("29a6852fdafa759215bbaef6162a2516a6835b5d3f174207884d88972f8b672e"),
+            sha3_256(&ciphertext)
+        );
+        assert_eq!(
+            h2b(
+                "b83e7f23b33f909715c7a50b0d4b1f6684d53e1f4b9056f803b29f058ccb5566\
+                 c9824ec12d123f830ff21ae5dec33afb8494ed3702d849f53bc65b5cfe6c6c34"
+            ),
+            shared_secret
+        );
+        let (ml_kem_ciphertext, ml_kem_secret) = mlkem768::encapsulate(ml_kem_key, rng).unwrap();
+        assert_eq!(ml_kem_ciphertext, ciphertext[..mlkem768::CIPHERTEXT_LEN]);
+        assert_eq!(ml_kem_secret, shared_secret[..32]);
+        let x25519_secret =
+            curve25519::x25519(&seed[64..], &ciphertext[mlkem768::CIPHERTEXT_LEN..]).unwrap();
+        assert_eq!(x25519_secret, shared_secret[32..]);
+        assert_eq!(
+            shared_secret,
+            X25519MlKem768::decapsulate(&decapsulation_key, &ciphertext).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let rng = &SystemRandom::new();
+        let (decapsulation_key, encapsulation_key) = X25519MlKem768::generate(rng);
+        let (mut ciphertext, shared_secret) =
+            X25519MlKem768::encapsulate(&encapsulation_key, rng).unwrap();
+        assert_eq!(
+            shared_secret,
+            X25519MlKem768::decapsulate(&decapsulation_key, &ciphertext).unwrap()
+        );
+
+        // Either half of a modified ciphertext changes the corresponding half of the secret.
+        ciphertext[0] ^= 1;
+        let other_secret = X25519MlKem768::decapsulate(&decapsulation_key, &ciphertext).unwrap();
+        assert_ne!(shared_secret[..32], other_secret[..32]);
+        assert_eq!(shared_secret[32..], other_secret[32..]);
+        ciphertext[0] ^= 1;
+        ciphertext[CIPHERTEXT_LEN - 1] ^= 1;
+        let other_secret = X25519MlKem768::decapsulate(&decapsulation_key, &ciphertext).unwrap();
+        assert_eq!(shared_secret[..32], other_secret[..32]);
+        assert_ne!(shared_secret[32..], other_secret[32..]);
+    }
+
+    #[test]
+    fn test_invalid() {
+        let rng = &SystemRandom::new();
+        let (decapsulation_key, mut encapsulation_key) = X25519MlKem768::generate(rng);
+        assert_eq!(
+            Err(Error::InvalidKey),
+            X25519MlKem768::encapsulate(&encapsulation_key[1..], rng)
+        );
+        let (ciphertext, _) = X25519MlKem768::encapsulate(&encapsulation_key, rng).unwrap();
+        assert_eq!(
+            Err(Error::InvalidLength),
+            X25519MlKem768::decapsulate(&decapsulation_key, &ciphertext[1..])
+        );
+
+        // A low-order X25519 public key gives an all-zero shared secret.
+        let mut ciphertext = ciphertext;
+        ciphertext[mlkem768::CIPHERTEXT_LEN..].fill(0);
+        assert_eq!(
+            Err(Error::InvalidKey),
+            X25519MlKem768::decapsulate(&decapsulation_key, &ciphertext)
+        );
+        encapsulation_key[mlkem768::ENCAPSULATION_KEY_LEN..].fill(0);
+        assert_eq!(
+            Err(Error::InvalidKey),
+            X25519MlKem768::encapsulate(&encapsulation_key, rng)
+        );
+    }
+}
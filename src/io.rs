@@ -0,0 +1,111 @@
+//! Module for adapters between hash functions and `std::io`.
+use crate::sha2::{Digest, HashFunction};
+use std::io::{self, Sink, Write};
+
+/// A writer that feeds everything written through it into a hash function.
+///
+/// By default the data is discarded after being hashed. Use `DigestWriter::wrap` to hash data as
+/// it flows into another writer.
+///
+/// # Examples
+///
+/// ```
+/// use crypto_pure::io::DigestWriter;
+/// use crypto_pure::sha2::{sha256, Sha256};
+/// use std::io;
+/// # fn main() -> io::Result<()> {
+/// # let mut file: &[u8] = b"file contents";
+/// let mut writer = DigestWriter::<Sha256>::new();
+/// io::copy(&mut file, &mut writer)?;
+/// let digest = writer.finalize();
+/// assert_eq!(&sha256(b"file contents")[..], &digest[..]);
+/// # Ok(())
+/// # }
+/// ```
+pub struct DigestWriter<H, W = Sink> {
+    hash_function: H,
+    inner: W,
+}
+
+impl<H: HashFunction> DigestWriter<H> {
+    /// Initializes a writer that hashes and then discards its input.
+    pub fn new() -> Self {
+        Self::wrap(io::sink())
+    }
+}
+
+impl<H: HashFunction> Default for DigestWriter<H> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<H: HashFunction, W: Write> DigestWriter<H, W> {
+    /// Initializes a writer that hashes its input before passing it on to `inner`.
+    pub fn wrap(inner: W) -> Self {
+        Self {
+            hash_function: H::default(),
+            inner,
+        }
+    }
+
+    /// Gets a reference to the underlying writer.
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    /// Outputs the digest of everything written so far.
+    pub fn finalize(self) -> Digest {
+        self.finalize_into_inner().0
+    }
+
+    /// Outputs the digest of everything written so far along with the underlying writer.
+    pub fn finalize_into_inner(self) -> (Digest, W) {
+        (Digest::new(self.hash_function), self.inner)
+    }
+}
+
+impl<H: HashFunction, W: Write> Write for DigestWriter<H, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.hash_function.update(&buf[..written]);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sha2::{sha224, sha512, Sha224, Sha512};
+    use std::vec::Vec;
+
+    const MESSAGE: &[u8] = b"abcdefghbcdefghicdefghijdefghijkefghijklfghijklmghijklmn\
+        hijklmnoijklmnopjklmnopqklmnopqrlmnopqrsmnopqrstnopqrstu";
+
+    #[test]
+    fn test_copy() {
+        let mut writer = DigestWriter::<Sha512>::new();
+        io::copy(&mut &MESSAGE[..], &mut writer).unwrap();
+        assert_eq!(sha512(MESSAGE).to_vec(), writer.finalize().to_vec());
+
+        let writer = DigestWriter::<Sha512>::default();
+        assert_eq!(sha512(b"").to_vec(), writer.finalize().to_vec());
+    }
+
+    #[test]
+    fn test_wrap() {
+        let mut writer = DigestWriter::<Sha224, _>::wrap(Vec::new());
+        for chunk in MESSAGE.chunks(5) {
+            writer.write_all(chunk).unwrap();
+        }
+        writer.flush().unwrap();
+        assert_eq!(MESSAGE, writer.get_ref().as_slice());
+        let (digest, inner) = writer.finalize_into_inner();
+        assert_eq!(sha224(MESSAGE).to_vec(), digest.to_vec());
+        assert_eq!(MESSAGE, inner.as_slice());
+    }
+}
@@ -0,0 +1,687 @@
+//! Module for JSON Web Encryption (RFC 7516) in the compact serialization
+//! `header.encrypted_key.iv.ciphertext.tag`.
+//!
+//! Content is encrypted with AES-GCM under a content encryption key that is either derived with
+//! ECDH-ES, direct key agreement with an ephemeral key and the Concat KDF, or chosen at random and
+//! encrypted with RSAES-OAEP. The header is authenticated as associated data.
+//!
+//! # Examples
+//!
+//! ```
+//! use crypto_pure::curve25519;
+//! use crypto_pure::jose::jwe::{self, ContentEncryption, DecryptionKey, EncryptionKey};
+//! use crypto_pure::rand::SystemRandom;
+//! let rng = &SystemRandom::new();
+//! # let secret_key = &[0x42; 32];
+//! let public_key = &curve25519::gen_pk(secret_key)?;
+//! let key = &EncryptionKey::EcdhEsX25519(public_key);
+//! let token = jwe::encrypt(b"message", key, ContentEncryption::A256Gcm, rng)?;
+//! let key = &DecryptionKey::EcdhEsX25519(secret_key);
+//! assert_eq!(b"message", &jwe::decrypt(&token, key, rng)?[..]);
+//! # Ok::<(), crypto_pure::error::Error>(())
+//! ```
+use super::{decode_header, decode_part, encode_part, MIN_RSA_SIZE};
+use crate::aead::Aead;
+use crate::aes::{Aes128, Aes256};
+use crate::curve25519;
+use crate::encoding::json::Value;
+use crate::error::Error;
+use crate::gcm::Gcm;
+use crate::p256;
+use crate::rand::SecureRandom;
+use crate::rsa::{PrivateKey, PublicKey};
+use crate::secret::SecretVec;
+#[cfg(feature = "legacy-hashes")]
+use crate::sha1::InsecureSha1;
+use crate::sha2::{HashFunction, Sha256};
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+
+const IV_LEN: usize = 12;
+const TAG_LEN: usize = 16;
+
+/// A content encryption algorithm, given by the `enc` header.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ContentEncryption {
+    /// AES-128 in GCM mode
+    A128Gcm,
+    /// AES-256 in GCM mode
+    A256Gcm,
+}
+
+impl ContentEncryption {
+    /// Gets the name of the algorithm for the `enc` header.
+    pub fn name(self) -> &'static str {
+        match self {
+            ContentEncryption::A128Gcm => "A128GCM",
+            ContentEncryption::A256Gcm => "A256GCM",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "A128GCM" => Some(ContentEncryption::A128Gcm),
+            "A256GCM" => Some(ContentEncryption::A256Gcm),
+            _ => None,
+        }
+    }
+
+    fn key_len(self) -> usize {
+        match self {
+            ContentEncryption::A128Gcm => 16,
+            ContentEncryption::A256Gcm => 32,
+        }
+    }
+
+    fn seal(
+        self,
+        key: &[u8],
+        iv: &[u8],
+        data: &[u8],
+        buffer: &mut [u8],
+    ) -> Result<[u8; TAG_LEN], Error> {
+        match self {
+            ContentEncryption::A128Gcm => Gcm::<Aes128>::new(key)?.seal_detached(iv, data, buffer),
+            ContentEncryption::A256Gcm => Gcm::<Aes256>::new(key)?.seal_detached(iv, data, buffer),
+        }
+    }
+
+    fn open(
+        self,
+        key: &[u8],
+        iv: &[u8],
+        data: &[u8],
+        buffer: &mut [u8],
+        tag: &[u8; TAG_LEN],
+    ) -> bool {
+        match self {
+            ContentEncryption::A128Gcm => Gcm::<Aes128>::new(key)
+                .and_then(|cipher| cipher.open_detached(iv, data, buffer, tag))
+                .is_ok(),
+            ContentEncryption::A256Gcm => Gcm::<Aes256>::new(key)
+                .and_then(|cipher| cipher.open_detached(iv, data, buffer, tag))
+                .is_ok(),
+        }
+    }
+}
+
+/// A recipient's key for encrypting, which determines the key management algorithm.
+#[derive(Clone, Copy)]
+pub enum EncryptionKey<'a> {
+    /// An ECDH-ES public key on P-256
+    EcdhEsP256(&'a [u8; 65]),
+    /// An ECDH-ES public key for X25519
+    EcdhEsX25519(&'a [u8; 32]),
+    /// An RSA-OAEP-256 public key, for RSAES-OAEP with SHA-256, of at least 2048 bits
+    RsaOaep256(&'a PublicKey),
+    /// An RSA-OAEP public key, for RSAES-OAEP with SHA-1, of at least 2048 bits
+    #[cfg(feature = "legacy-hashes")]
+    RsaOaep(&'a PublicKey),
+}
+
+impl EncryptionKey<'_> {
+    /// Gets the name of the algorithm for the `alg` header.
+    pub fn algorithm(&self) -> &'static str {
+        match self {
+            EncryptionKey::EcdhEsP256(_) | EncryptionKey::EcdhEsX25519(_) => "ECDH-ES",
+            EncryptionKey::RsaOaep256(_) => "RSA-OAEP-256",
+            #[cfg(feature = "legacy-hashes")]
+            EncryptionKey::RsaOaep(_) => "RSA-OAEP",
+        }
+    }
+}
+
+/// A recipient's key for decrypting, which determines the key management algorithm.
+#[derive(Clone, Copy)]
+pub enum DecryptionKey<'a> {
+    /// An ECDH-ES secret key on P-256
+    EcdhEsP256(&'a [u8; 32]),
+    /// An ECDH-ES secret key for X25519
+    EcdhEsX25519(&'a [u8; 32]),
+    /// An RSA-OAEP-256 private key, for RSAES-OAEP with SHA-256, of at least 2048 bits
+    RsaOaep256(&'a PrivateKey),
+    /// An RSA-OAEP private key, for RSAES-OAEP with SHA-1, of at least 2048 bits
+    #[cfg(feature = "legacy-hashes")]
+    RsaOaep(&'a PrivateKey),
+}
+
+impl DecryptionKey<'_> {
+    /// Gets the name of the algorithm for the `alg` header.
+    pub fn algorithm(&self) -> &'static str {
+        match self {
+            DecryptionKey::EcdhEsP256(_) | DecryptionKey::EcdhEsX25519(_) => "ECDH-ES",
+            DecryptionKey::RsaOaep256(_) => "RSA-OAEP-256",
+            #[cfg(feature = "legacy-hashes")]
+            DecryptionKey::RsaOaep(_) => "RSA-OAEP",
+        }
+    }
+}
+
+/// Encrypts a plaintext, outputting a token whose header only has the members the algorithms
+/// need. The keys and IV are drawn from `rng`.
+///
+/// # Errors
+///
+/// Fails with `Error::InvalidKey` if the key is not valid or too short.
+pub fn encrypt(
+    plaintext: &[u8],
+    key: &EncryptionKey,
+    enc: ContentEncryption,
+    rng: &dyn SecureRandom,
+) -> Result<String, Error> {
+    encrypt_with_header(&[], plaintext, key, enc, rng)
+}
+
+/// Encrypts a plaintext, outputting a token whose header has the members the algorithms need
+/// followed by the members of `header`, such as `kid`. For ECDH-ES, the `apu` and `apv` members,
+/// if present, are base64url-encoded information about the parties that is bound to the key.
+/// The keys and IV are drawn from `rng`.
+///
+/// # Errors
+///
+/// Fails with `Error::InvalidKey` if the key is not valid or too short, or with
+/// `Error::InvalidEncoding` if `header` has an `alg`, `enc`, `epk`, `zip` or `crit` member, two
+/// members with the same name, or an `apu` or `apv` member that isn't a base64url string.
+pub fn encrypt_with_header(
+    header: &[(&str, Value)],
+    plaintext: &[u8],
+    key: &EncryptionKey,
+    enc: ContentEncryption,
+    rng: &dyn SecureRandom,
+) -> Result<String, Error> {
+    let mut members = vec![
+        ("alg".into(), key.algorithm().into()),
+        ("enc".into(), enc.name().into()),
+    ];
+    let (cek, encrypted_key) = match key {
+        EncryptionKey::EcdhEsP256(public_key) => {
+            let secret_key = &mut SecretVec::new(vec![0; 32]);
+            let ephemeral = loop {
+                rng.fill(secret_key);
+                if let Ok(ephemeral) = p256::gen_pk(secret_key) {
+                    break ephemeral;
+                }
+            };
+            let z = p256::ecdh(secret_key, *public_key)?;
+            members.push(("epk".into(), p256_jwk(&ephemeral)));
+            (agree(&z, enc, header)?, Vec::new())
+        }
+        EncryptionKey::EcdhEsX25519(public_key) => {
+            let secret_key = &mut SecretVec::new(vec![0; 32]);
+            rng.fill(secret_key);
+            let ephemeral = curve25519::gen_pk(secret_key)?;
+            let z = curve25519::x25519(secret_key, *public_key)?;
+            members.push(("epk".into(), x25519_jwk(&ephemeral)));
+            (agree(&z, enc, header)?, Vec::new())
+        }
+        EncryptionKey::RsaOaep256(public_key) => wrap::<Sha256>(public_key, enc, rng)?,
+        #[cfg(feature = "legacy-hashes")]
+        EncryptionKey::RsaOaep(public_key) => wrap::<InsecureSha1>(public_key, enc, rng)?,
+    };
+    for (name, value) in header {
+        let reserved = ["enc", "epk", "zip", "crit"].contains(name);
+        if reserved || members.iter().any(|(member, _)| member == name) {
+            return Err(Error::InvalidEncoding);
+        }
+        members.push((String::from(*name), value.clone()));
+    }
+
+    let header = encode_part(Value::Object(members).to_string().as_bytes());
+    let iv = &mut [0; IV_LEN];
+    rng.fill(iv);
+    let buffer = &mut plaintext.to_vec();
+    let tag = enc.seal(&cek, iv, header.as_bytes(), buffer)?;
+    Ok([
+        &header[..],
+        &encode_part(&encrypted_key),
+        &encode_part(iv),
+        &encode_part(buffer),
+        &encode_part(&tag),
+    ]
+    .join("."))
+}
+
+/// Decrypts a token, outputting its plaintext. RSA private-key operations are blinded with
+/// values drawn from `rng`.
+///
+/// # Errors
+///
+/// Fails with `Error::InvalidEncoding` if the token is malformed, including if it has an
+/// unsupported `enc` or `zip` header or an `epk` header that isn't a valid public key, with
+/// `Error::InvalidKey` if the `alg` header doesn't match the key or the key is not valid or too
+/// short, or with `Error::DecryptionFailed` if the token was not encrypted for the key or was
+/// modified.
+pub fn decrypt(token: &str, key: &DecryptionKey, rng: &dyn SecureRandom) -> Result<Vec<u8>, Error> {
+    let mut parts = token.split('.');
+    let mut next = || parts.next().ok_or(Error::InvalidEncoding);
+    let (encoded_header, encrypted_key) = (next()?, decode_part(next()?)?);
+    let (iv, ciphertext, tag) = (
+        decode_part(next()?)?,
+        decode_part(next()?)?,
+        decode_part(next()?)?,
+    );
+    if parts.next().is_some() || iv.len() != IV_LEN || tag.len() != TAG_LEN {
+        return Err(Error::InvalidEncoding);
+    }
+    let header = decode_header(encoded_header)?;
+    if header.get("alg").and_then(Value::as_str) != Some(key.algorithm()) {
+        return Err(Error::InvalidKey);
+    }
+    let enc = header
+        .get("enc")
+        .and_then(Value::as_str)
+        .and_then(ContentEncryption::from_name)
+        .ok_or(Error::InvalidEncoding)?;
+    if header.get("zip").is_some() {
+        return Err(Error::InvalidEncoding);
+    }
+
+    let cek = match key {
+        DecryptionKey::EcdhEsP256(secret_key) => {
+            p256::gen_pk(*secret_key)?;
+            let public_key = read_p256_jwk(&header)?;
+            let z = p256::ecdh(*secret_key, &public_key).map_err(|_| Error::InvalidEncoding)?;
+            direct(&z, enc, &header, &encrypted_key)?
+        }
+        DecryptionKey::EcdhEsX25519(secret_key) => {
+            let public_key = read_x25519_jwk(&header)?;
+            let z =
+                curve25519::x25519(*secret_key, &public_key).map_err(|_| Error::InvalidEncoding)?;
+            direct(&z, enc, &header, &encrypted_key)?
+        }
+        DecryptionKey::RsaOaep256(private_key) => {
+            unwrap::<Sha256>(private_key, enc, &encrypted_key, rng)?
+        }
+        #[cfg(feature = "legacy-hashes")]
+        DecryptionKey::RsaOaep(private_key) => {
+            unwrap::<InsecureSha1>(private_key, enc, &encrypted_key, rng)?
+        }
+    };
+    let mut buffer = ciphertext;
+    let mut tag_array = [0; TAG_LEN];
+    tag_array.copy_from_slice(&tag);
+    if !enc.open(
+        &cek,
+        &iv,
+        encoded_header.as_bytes(),
+        &mut buffer,
+        &tag_array,
+    ) {
+        return Err(Error::DecryptionFailed);
+    }
+    Ok(buffer)
+}
+
+/// Derives the content encryption key for ECDH-ES when encrypting, from the shared secret and
+/// the `apu` and `apv` members of `header`.
+fn agree(z: &[u8], enc: ContentEncryption, header: &[(&str, Value)]) -> Result<SecretVec, Error> {
+    let party_info = |name| match header.iter().find(|(member, _)| *member == name) {
+        Some((_, value)) => decode_party_info(value),
+        None => Ok(Vec::new()),
+    };
+    Ok(concat_kdf(z, enc, &party_info("apu")?, &party_info("apv")?))
+}
+
+/// Derives the content encryption key for ECDH-ES when decrypting, for which the encrypted key
+/// must be empty.
+fn direct(
+    z: &[u8],
+    enc: ContentEncryption,
+    header: &Value,
+    encrypted_key: &[u8],
+) -> Result<SecretVec, Error> {
+    if !encrypted_key.is_empty() {
+        return Err(Error::InvalidEncoding);
+    }
+    let party_info = |name| header.get(name).map_or(Ok(Vec::new()), decode_party_info);
+    Ok(concat_kdf(z, enc, &party_info("apu")?, &party_info("apv")?))
+}
+
+fn decode_party_info(value: &Value) -> Result<Vec<u8>, Error> {
+    decode_part(value.as_str().ok_or(Error::InvalidEncoding)?)
+}
+
+/// Derives a content encryption key with the Concat KDF of NIST SP 800-56A and SHA-256, as
+/// RFC 7518 specifies for ECDH-ES. A single block suffices for the key lengths used here.
+fn concat_kdf(z: &[u8], enc: ContentEncryption, apu: &[u8], apv: &[u8]) -> SecretVec {
+    let mut hash_function = Sha256::default();
+    hash_function.update(&1u32.to_be_bytes());
+    hash_function.update(z);
+    for field in &[enc.name().as_bytes(), apu, apv] {
+        hash_function.update(&(field.len() as u32).to_be_bytes());
+        hash_function.update(field);
+    }
+    hash_function.update(&(8 * enc.key_len() as u32).to_be_bytes());
+    let mut key = SecretVec::new(vec![0; Sha256::DIGEST_SIZE]);
+    hash_function.write_digest(&mut key);
+    SecretVec::new(key[..enc.key_len()].to_vec())
+}
+
+/// Generates a random content encryption key and encrypts it with RSAES-OAEP.
+fn wrap<H: HashFunction>(
+    public_key: &PublicKey,
+    enc: ContentEncryption,
+    rng: &dyn SecureRandom,
+) -> Result<(SecretVec, Vec<u8>), Error> {
+    if public_key.size() < MIN_RSA_SIZE {
+        return Err(Error::InvalidKey);
+    }
+    let mut cek = SecretVec::new(vec![0; enc.key_len()]);
+    rng.fill(&mut cek);
+    let encrypted_key = public_key.encrypt_oaep::<H>(&cek, b"", rng)?;
+    Ok((cek, encrypted_key))
+}
+
+/// Decrypts a content encryption key with RSAES-OAEP. If that fails, a random key is used
+/// instead, so that the failure is only detected along with a modified ciphertext, as RFC 7516
+/// recommends against timing attacks.
+fn unwrap<H: HashFunction>(
+    private_key: &PrivateKey,
+    enc: ContentEncryption,
+    encrypted_key: &[u8],
+    rng: &dyn SecureRandom,
+) -> Result<SecretVec, Error> {
+    if private_key.public_key().size() < MIN_RSA_SIZE {
+        return Err(Error::InvalidKey);
+    }
+    let mut cek = SecretVec::new(vec![0; enc.key_len()]);
+    rng.fill(&mut cek);
+    match private_key.decrypt_oaep::<H>(encrypted_key, b"", rng) {
+        Ok(decrypted) if decrypted.len() == enc.key_len() => Ok(SecretVec::new(decrypted)),
+        _ => Ok(cek),
+    }
+}
+
+fn p256_jwk(public_key: &[u8; 65]) -> Value {
+    Value::Object(vec![
+        ("kty".into(), "EC".into()),
+        ("crv".into(), "P-256".into()),
+        ("x".into(), encode_part(&public_key[1..33]).into()),
+        ("y".into(), encode_part(&public_key[33..]).into()),
+    ])
+}
+
+fn x25519_jwk(public_key: &[u8; 32]) -> Value {
+    Value::Object(vec![
+        ("kty".into(), "OKP".into()),
+        ("crv".into(), "X25519".into()),
+        ("x".into(), encode_part(public_key).into()),
+    ])
+}
+
+/// Reads the `epk` header as a P-256 JWK (RFC 7518), outputting the uncompressed public key.
+fn read_p256_jwk(header: &Value) -> Result<[u8; 65], Error> {
+    let epk = read_jwk(header, "EC", "P-256")?;
+    let mut public_key = [0x04; 65];
+    read_coordinate(epk, "x", &mut public_key[1..33])?;
+    read_coordinate(epk, "y", &mut public_key[33..])?;
+    Ok(public_key)
+}
+
+/// Reads the `epk` header as an X25519 JWK (RFC 8037).
+fn read_x25519_jwk(header: &Value) -> Result<[u8; 32], Error> {
+    let epk = read_jwk(header, "OKP", "X25519")?;
+    let mut public_key = [0; 32];
+    read_coordinate(epk, "x", &mut public_key)?;
+    Ok(public_key)
+}
+
+fn read_jwk<'a>(header: &'a Value, kty: &str, crv: &str) -> Result<&'a Value, Error> {
+    let epk = header.get("epk").ok_or(Error::InvalidEncoding)?;
+    let member = |name| epk.get(name).and_then(Value::as_str);
+    if member("kty") != Some(kty) || member("crv") != Some(crv) {
+        return Err(Error::InvalidEncoding);
+    }
+    Ok(epk)
+}
+
+fn read_coordinate(jwk: &Value, name: &str, output: &mut [u8]) -> Result<(), Error> {
+    let encoded = jwk.get(name).and_then(Value::as_str);
+    let decoded = decode_part(encoded.ok_or(Error::InvalidEncoding)?)?;
+    if decoded.len() != output.len() {
+        return Err(Error::InvalidEncoding);
+    }
+    output.copy_from_slice(&decoded);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encoding::json;
+    use crate::jose::jws;
+    use crate::jose::tests::rsa_key;
+    use crate::rand::SystemRandom;
+    use crate::test_helpers::*;
+
+    const PLAINTEXT: &[u8] = b"Live long and prosper.";
+
+    // Encrypted by the joserfc Python package to the P-256 and RSA keys used in other tests and
+    // the X25519 key below
+    const P256_TOKEN: &str = "eyJhbGciOiJFQ0RILUVTIiwiZW5jIjoiQTEyOEdDTSIsImVwayI6eyJjcnYiOiJQLTI1NiIsIngiOiJFeU5JekhwTmpGNlEtSnZ3RXJUeWM1WWRzN2pEOXduSDR4dE0yOWpjajFZIiwieSI6IkQwR2NKRmtiSTNMUUgwZFRIaVZZVTZFRmxnTlJGRUdLcDVPMHlVc1Y4SWMiLCJrdHkiOiJFQyJ9fQ..86wLMeR6e3QRGi_6.9taRorLbYHIfvcEVxdPdqGRjtafhCw.qKFQ0-hltCN-jhdd2d9DYA";
+    const X25519_TOKEN: &str = "eyJhbGciOiJFQ0RILUVTIiwiZW5jIjoiQTI1NkdDTSIsImVwayI6eyJjcnYiOiJYMjU1MTkiLCJ4IjoiZ0NnbXFJX3RUU3BvVE1ueEpKX210QVBRNDFfNjdlVEw3NHlHa25FZzNYOCIsImt0eSI6Ik9LUCJ9fQ..HtTD9UN6zFziowtY.Bhf9oPf4-IRCMt-T_zJZttzRXMBD2w.O-g2kTVbUTnrZKFC2eY0gg";
+    const RSA_OAEP_256_TOKEN: &str = "eyJhbGciOiJSU0EtT0FFUC0yNTYiLCJlbmMiOiJBMjU2R0NNIn0.YdTuhZPUyhSGyIHlMynEFqT1RPq3gHwEPdu3AYB3JK0FrSL0xdmpShD5ZAk4YYSt8N9RkJ9QptatyuljYTTWrhyf5J66SFZrgbjk6KfuqU2IQ17a6IDOtfSrj7Fb8NW-Rd9lp9sWarPre4KVbNdhxtGfwPr6KFw-NDQRSwzzcLbvRrjNSAEHoDMeJJhhO9HJeRm38ECZq3_sAy7fQ5GPyO1R9OkrfUH7rwMFTrmFMORmb1VcnqQvdgnQWJ93teNhpGRtQNhJmIgK5_DEicWTQ__1_s5tjCbi2QYX5WKx0VDtADAtx4PzyVSO69_kw7p5TXqAStHb21YRBWIMFKXLcA.0pVfNPnDRfSck-5m.rIa-5dCtRAoLM4L-O8JB2CcQ5C5uYQ.iGtfPmZEl2LJuKPhvAgz6A";
+    #[cfg(feature = "legacy-hashes")]
+    const RSA_OAEP_TOKEN: &str = "eyJhbGciOiJSU0EtT0FFUCIsImVuYyI6IkExMjhHQ00ifQ.e6-3AIYrhfYPQ657mMuku5KKSoXxiZT1i7NQyHITMRBTliY_WB8Ybi15LSDdmmcipJSVk8pqLCHP-hM6j5Qrw44cpJcVHcGC1L_IMgL5K85dPmZV8Fblw6kYsG9pBJHbU4ZUQ6SJ4MyOCaipOT0GB1iq26UWBBCDpOkWGBW26uRf3ycpMG5KjzFcAsmcu25ADOallakRtjA6cu4jOww49Yu3dNREeu2eaxKKM23h4eluIy1tVNbjCAmKrIvMLWB6lxndXhgyMDq7mQnNk7eSNkZLLdNVTZj-elR5rRfPo03TWvoflqM25rUNDmsJ7a7qM6tn7bEE4J1DIKC5M-k7mw.-21fD2_Hc6jvJHGO.-uSiokaDbqGv9szEXTMtKRnuP0Xa1g.UYTwUUu9kCAT8eIQRDvzNw";
+    const P256_SECRET_KEY: &str =
+        "564c01ed94637b4c192abb3d38ba8b81d30e22934d5337243a64ed9ba31e5401";
+    const X25519_SECRET_KEY: &str =
+        "00e7218bb1f7e66421c3e4c095f032b590813771221a059b8c7a0bedef6f625e";
+
+    fn array<const N: usize>(hex: &str) -> [u8; N] {
+        let mut bytes = [0; N];
+        bytes.copy_from_slice(&h2b(hex));
+        bytes
+    }
+
+    #[test]
+    fn test_concat_kdf() {
+        // RFC 7518, Appendix C
+        let alice = h2b("d3f3716913d4310a0026de741b3f18893afc8114f0c84682ba677e313a13988a");
+        let bob = h2b("5449836690d75caf29f0dd029ddb31b3ddb8aba9d2d515c5012465e817d4a9dc");
+        let z = p256::ecdh(&alice, &p256::gen_pk(&bob).unwrap()).unwrap();
+        let key = concat_kdf(&z, ContentEncryption::A128Gcm, b"Alice", b"Bob");
+        assert_eq!(decode_part("VqqN6vgjbSBcIijNcacQGg").unwrap(), &key[..]);
+        let header = [("apu", "QWxpY2U".into()), ("apv", "Qm9i".into())];
+        assert_eq!(
+            &key[..],
+            &agree(&z, ContentEncryption::A128Gcm, &header).unwrap()[..]
+        );
+    }
+
+    #[test]
+    fn test_decrypt() {
+        let rng = &SystemRandom::new();
+        let rsa_key = &rsa_key();
+        let p256_key = &array(P256_SECRET_KEY);
+        let x25519_key = &array(X25519_SECRET_KEY);
+        let tokens = vec![
+            (P256_TOKEN, DecryptionKey::EcdhEsP256(p256_key)),
+            (X25519_TOKEN, DecryptionKey::EcdhEsX25519(x25519_key)),
+            (RSA_OAEP_256_TOKEN, DecryptionKey::RsaOaep256(rsa_key)),
+        ];
+        #[cfg(feature = "legacy-hashes")]
+        let tokens = [
+            tokens,
+            vec![(RSA_OAEP_TOKEN, DecryptionKey::RsaOaep(rsa_key))],
+        ]
+        .concat();
+        for (token, key) in &tokens {
+            assert_eq!(PLAINTEXT, &decrypt(token, key, rng).unwrap()[..]);
+
+            // Modifying any part except the empty encrypted key of ECDH-ES makes decryption fail.
+            let parts: Vec<_> = token.split('.').collect();
+            for (i, part) in parts.iter().enumerate() {
+                let mut bytes = decode_part(part).unwrap();
+                if bytes.is_empty() {
+                    continue;
+                }
+                let mut modified = parts.clone();
+                let encoded = if i == 0 {
+                    // Reordering the header keeps it valid but changes the associated data.
+                    let header = json::parse(std::str::from_utf8(&bytes).unwrap()).unwrap();
+                    let mut members = header.as_object().unwrap().to_vec();
+                    members.reverse();
+                    encode_part(Value::Object(members).to_string().as_bytes())
+                } else {
+                    bytes[0] ^= 1;
+                    encode_part(&bytes)
+                };
+                modified[i] = &encoded;
+                let error = Err(Error::DecryptionFailed);
+                assert_eq!(error, decrypt(&modified.join("."), key, rng), "{}", i);
+            }
+        }
+
+        // The algorithm must match the key.
+        let key = DecryptionKey::EcdhEsX25519(x25519_key);
+        assert_eq!(
+            Err(Error::InvalidKey),
+            decrypt(RSA_OAEP_256_TOKEN, &key, rng)
+        );
+        // An X25519 key can't decrypt a token for P-256.
+        assert_eq!(Err(Error::InvalidEncoding), decrypt(P256_TOKEN, &key, rng));
+        // The other key isn't the recipient.
+        let key = DecryptionKey::EcdhEsX25519(&[0x42; 32]);
+        assert_eq!(
+            Err(Error::DecryptionFailed),
+            decrypt(X25519_TOKEN, &key, rng)
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let rng = &SystemRandom::new();
+        let rsa_key = &rsa_key();
+        let p256_key = &array(P256_SECRET_KEY);
+        let p256_public_key = &p256::gen_pk(p256_key).unwrap();
+        let x25519_key = &array(X25519_SECRET_KEY);
+        let x25519_public_key = &curve25519::gen_pk(x25519_key).unwrap();
+        let keys = vec![
+            (
+                EncryptionKey::EcdhEsP256(p256_public_key),
+                DecryptionKey::EcdhEsP256(p256_key),
+            ),
+            (
+                EncryptionKey::EcdhEsX25519(x25519_public_key),
+                DecryptionKey::EcdhEsX25519(x25519_key),
+            ),
+            (
+                EncryptionKey::RsaOaep256(rsa_key.public_key()),
+                DecryptionKey::RsaOaep256(rsa_key),
+            ),
+        ];
+        #[cfg(feature = "legacy-hashes")]
+        let keys = [
+            keys,
+            vec![(
+                EncryptionKey::RsaOaep(rsa_key.public_key()),
+                DecryptionKey::RsaOaep(rsa_key),
+            )],
+        ]
+        .concat();
+        let header = [("kid", "1".into()), ("apu", "QWxpY2U".into())];
+        for (encryption_key, decryption_key) in &keys {
+            for &enc in &[ContentEncryption::A128Gcm, ContentEncryption::A256Gcm] {
+                for &plaintext in &[&b""[..], PLAINTEXT] {
+                    let token = encrypt(plaintext, encryption_key, enc, rng).unwrap();
+                    assert_eq!(
+                        plaintext,
+                        &decrypt(&token, decryption_key, rng).unwrap()[..]
+                    );
+                    let token =
+                        encrypt_with_header(&header, plaintext, encryption_key, enc, rng).unwrap();
+                    assert_eq!(
+                        plaintext,
+                        &decrypt(&token, decryption_key, rng).unwrap()[..]
+                    );
+                }
+            }
+        }
+
+        let token = encrypt_with_header(
+            &header,
+            PLAINTEXT,
+            &keys[0].0,
+            ContentEncryption::A128Gcm,
+            rng,
+        );
+        let header = jws::decode_unverified_header(&token.unwrap()).unwrap();
+        let names: Vec<_> = header
+            .as_object()
+            .unwrap()
+            .iter()
+            .map(|(name, _)| &name[..])
+            .collect();
+        assert_eq!(vec!["alg", "enc", "epk", "kid", "apu"], names);
+        assert_eq!(
+            Some("EC"),
+            header
+                .get("epk")
+                .and_then(|epk| epk.get("kty"))
+                .and_then(Value::as_str)
+        );
+    }
+
+    #[test]
+    fn test_invalid() {
+        let rng = &SystemRandom::new();
+        let enc = ContentEncryption::A128Gcm;
+        let x25519_key = &array(X25519_SECRET_KEY);
+        let public_key = &curve25519::gen_pk(x25519_key).unwrap();
+        let key = &EncryptionKey::EcdhEsX25519(public_key);
+        for header in &[
+            [("alg", "dir".into()), ("kid", "1".into())],
+            [("zip", "DEF".into()), ("kid", "1".into())],
+            [("kid", "1".into()), ("kid", "2".into())],
+            [("apu", Value::Null), ("kid", "1".into())],
+            [("apv", "Qm9i=".into()), ("kid", "1".into())],
+        ] {
+            let encrypted = encrypt_with_header(header, PLAINTEXT, key, enc, rng);
+            assert_eq!(Err(Error::InvalidEncoding), encrypted);
+        }
+        let error = Err(Error::InvalidKey);
+        assert_eq!(
+            error,
+            encrypt(PLAINTEXT, &EncryptionKey::EcdhEsX25519(&[0; 32]), enc, rng)
+        );
+        assert_eq!(
+            error,
+            encrypt(PLAINTEXT, &EncryptionKey::EcdhEsP256(&[4; 65]), enc, rng)
+        );
+
+        let decryption_key = &DecryptionKey::EcdhEsX25519(x25519_key);
+        let token = encrypt(PLAINTEXT, key, enc, rng).unwrap();
+        let parts: Vec<_> = token.split('.').collect();
+        let header = json::parse(std::str::from_utf8(&decode_part(parts[0]).unwrap()).unwrap());
+        let mut members = header.unwrap().as_object().unwrap().to_vec();
+        let with_header = |members: &[(String, Value)]| {
+            let header = encode_part(Value::Object(members.to_vec()).to_string().as_bytes());
+            [&header, &parts[1..].join(".")[..]].join(".")
+        };
+        let error = Err(Error::InvalidEncoding);
+        members[1].1 = "A192GCM".into();
+        assert_eq!(error, decrypt(&with_header(&members), decryption_key, rng));
+        members[1].1 = "A128GCM".into();
+        members.push(("zip".into(), "DEF".into()));
+        assert_eq!(error, decrypt(&with_header(&members), decryption_key, rng));
+        members.pop();
+        members[2].1 = x25519_jwk(&[0; 32]);
+        assert_eq!(error, decrypt(&with_header(&members), decryption_key, rng));
+        members[2].1 = p256_jwk(&[4; 65]);
+        assert_eq!(error, decrypt(&with_header(&members), decryption_key, rng));
+
+        for token in &[
+            "",
+            "....",
+            "e30....",
+            &[parts[0], "AA", parts[2], parts[3], parts[4]].join("."),
+            &[parts[0], "", "AAAA", parts[3], parts[4]].join("."),
+            &[parts[0], "", parts[2], parts[3], "AAAA"].join("."),
+            &[&token[..], "."].concat(),
+        ] {
+            assert_eq!(error, decrypt(token, decryption_key, rng), "{}", token);
+        }
+    }
+}
@@ -0,0 +1,409 @@
+//! Module for JSON Web Signatures (RFC 7515) in the compact serialization
+//! `header.payload.signature`.
+//!
+//! # Examples
+//!
+//! ```
+//! use crypto_pure::jose::jws::{self, SigningKey, VerifyingKey};
+//! use crypto_pure::rand::SystemRandom;
+//! # let key = &[0x42; 32];
+//! let token = jws::sign(b"payload", &SigningKey::Hs256(key), &SystemRandom::new())?;
+//! assert_eq!(b"payload", &jws::verify(&token, &VerifyingKey::Hs256(key))?[..]);
+//! # Ok::<(), crypto_pure::error::Error>(())
+//! ```
+use super::{decode_header, decode_part, encode_part, MIN_RSA_SIZE};
+use crate::ed25519::Ed25519;
+use crate::encoding::json::Value;
+use crate::error::Error;
+use crate::hmac::{self, hmac_sha256, hmac_sha384, hmac_sha512};
+use crate::p256::EcdsaP256;
+use crate::rand::SecureRandom;
+use crate::rsa::{Pkcs1v15, PrivateKey, PublicKey};
+use crate::sha2::{HashFunction, Sha256, Sha384, Sha512};
+use crate::sig::{self, Signer, Verifier};
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+/// A key for signing, which determines the algorithm.
+#[derive(Clone, Copy)]
+pub enum SigningKey<'a> {
+    /// An HS256 key, for HMAC with SHA-256, of at least 32 bytes
+    Hs256(&'a [u8]),
+    /// An HS384 key, for HMAC with SHA-384, of at least 48 bytes
+    Hs384(&'a [u8]),
+    /// An HS512 key, for HMAC with SHA-512, of at least 64 bytes
+    Hs512(&'a [u8]),
+    /// An ES256 secret key, for ECDSA with P-256 and SHA-256
+    Es256(&'a [u8; 32]),
+    /// An EdDSA secret key, for Ed25519
+    EdDsa(&'a [u8; 32]),
+    /// An RS256 private key, for RSASSA-PKCS1-v1_5 with SHA-256, of at least 2048 bits
+    Rs256(&'a PrivateKey),
+}
+
+impl SigningKey<'_> {
+    /// Gets the name of the algorithm for the `alg` header.
+    pub fn algorithm(&self) -> &'static str {
+        match self {
+            SigningKey::Hs256(_) => "HS256",
+            SigningKey::Hs384(_) => "HS384",
+            SigningKey::Hs512(_) => "HS512",
+            SigningKey::Es256(_) => "ES256",
+            SigningKey::EdDsa(_) => "EdDSA",
+            SigningKey::Rs256(_) => "RS256",
+        }
+    }
+}
+
+/// A key for verifying, which determines the algorithm.
+#[derive(Clone, Copy)]
+pub enum VerifyingKey<'a> {
+    /// An HS256 key, for HMAC with SHA-256, of at least 32 bytes
+    Hs256(&'a [u8]),
+    /// An HS384 key, for HMAC with SHA-384, of at least 48 bytes
+    Hs384(&'a [u8]),
+    /// An HS512 key, for HMAC with SHA-512, of at least 64 bytes
+    Hs512(&'a [u8]),
+    /// An ES256 public key, for ECDSA with P-256 and SHA-256
+    Es256(&'a [u8; 65]),
+    /// An EdDSA public key, for Ed25519
+    EdDsa(&'a [u8; 32]),
+    /// An RS256 public key, for RSASSA-PKCS1-v1_5 with SHA-256, of at least 2048 bits
+    Rs256(&'a PublicKey),
+}
+
+impl VerifyingKey<'_> {
+    /// Gets the name of the algorithm for the `alg` header.
+    pub fn algorithm(&self) -> &'static str {
+        match self {
+            VerifyingKey::Hs256(_) => "HS256",
+            VerifyingKey::Hs384(_) => "HS384",
+            VerifyingKey::Hs512(_) => "HS512",
+            VerifyingKey::Es256(_) => "ES256",
+            VerifyingKey::EdDsa(_) => "EdDSA",
+            VerifyingKey::Rs256(_) => "RS256",
+        }
+    }
+}
+
+/// Signs a payload, outputting a token whose header only has the `alg` member. Any randomness
+/// the algorithm needs is drawn from `rng`.
+///
+/// # Errors
+///
+/// Fails with `Error::InvalidKey` if the key is too short or out of range.
+pub fn sign(payload: &[u8], key: &SigningKey, rng: &dyn SecureRandom) -> Result<String, Error> {
+    sign_with_header(&[], payload, key, rng)
+}
+
+/// Signs a payload, outputting a token whose header has the `alg` member followed by the members
+/// of `header`, such as `typ` or `kid`. Any randomness the algorithm needs is drawn from `rng`.
+///
+/// # Errors
+///
+/// Fails with `Error::InvalidKey` if the key is too short or out of range, or with
+/// `Error::InvalidEncoding` if `header` has an `alg` member, a `crit` member, which would make
+/// the token unverifiable here, or two members with the same name.
+pub fn sign_with_header(
+    header: &[(&str, Value)],
+    payload: &[u8],
+    key: &SigningKey,
+    rng: &dyn SecureRandom,
+) -> Result<String, Error> {
+    let mut members = Vec::with_capacity(header.len() + 1);
+    members.push(("alg".into(), key.algorithm().into()));
+    for (name, value) in header {
+        if *name == "crit" || members.iter().any(|(member, _)| member == name) {
+            return Err(Error::InvalidEncoding);
+        }
+        members.push((String::from(*name), value.clone()));
+    }
+    let header = Value::Object(members).to_string();
+    let mut token = encode_part(header.as_bytes());
+    token.push('.');
+    token.push_str(&encode_part(payload));
+    let signature = sign_input(token.as_bytes(), key, rng)?;
+    token.push('.');
+    token.push_str(&encode_part(&signature));
+    Ok(token)
+}
+
+/// Verifies a token, outputting its payload.
+///
+/// # Errors
+///
+/// Fails with `Error::InvalidEncoding` if the token is malformed or its header has a `crit`
+/// member, with `Error::InvalidKey` if the `alg` header doesn't match the key or the key is too
+/// short, or with `Error::InvalidSignature` if the signature is not valid.
+pub fn verify(token: &str, key: &VerifyingKey) -> Result<Vec<u8>, Error> {
+    let (header, payload, signature) = split(token)?;
+    if decode_header(header)?.get("alg").and_then(Value::as_str) != Some(key.algorithm()) {
+        return Err(Error::InvalidKey);
+    }
+    let signature = decode_part(signature)?;
+    let message = &token.as_bytes()[..header.len() + 1 + payload.len()];
+    match key {
+        VerifyingKey::Hs256(key) => {
+            sig::check_valid(verify_hmac::<Sha256>(key, message, &signature)?)?
+        }
+        VerifyingKey::Hs384(key) => {
+            sig::check_valid(verify_hmac::<Sha384>(key, message, &signature)?)?
+        }
+        VerifyingKey::Hs512(key) => {
+            sig::check_valid(verify_hmac::<Sha512>(key, message, &signature)?)?
+        }
+        VerifyingKey::Es256(key) => EcdsaP256::verify(message, b"", &signature, *key)?,
+        VerifyingKey::EdDsa(key) => Ed25519::verify(message, b"", &signature, *key)?,
+        VerifyingKey::Rs256(key) if key.size() < MIN_RSA_SIZE => return Err(Error::InvalidKey),
+        VerifyingKey::Rs256(key) => Pkcs1v15::<Sha256>::verify(message, b"", &signature, key)?,
+    }
+    decode_part(payload)
+}
+
+/// Decodes the header of a token without verifying it, such as to choose a key by its `kid`
+/// member.
+///
+/// # Errors
+///
+/// Fails with `Error::InvalidEncoding` if the header is malformed or has a `crit` member.
+pub fn decode_unverified_header(token: &str) -> Result<Value, Error> {
+    decode_header(token.split('.').next().unwrap_or_default())
+}
+
+/// Splits a token into its encoded header, payload and signature.
+fn split(token: &str) -> Result<(&str, &str, &str), Error> {
+    let mut parts = token.split('.');
+    match (parts.next(), parts.next(), parts.next(), parts.next()) {
+        (Some(header), Some(payload), Some(signature), None) => Ok((header, payload, signature)),
+        _ => Err(Error::InvalidEncoding),
+    }
+}
+
+fn sign_input(message: &[u8], key: &SigningKey, rng: &dyn SecureRandom) -> Result<Vec<u8>, Error> {
+    Ok(match key {
+        SigningKey::Hs256(key) => {
+            check_hmac_key::<Sha256>(key)?;
+            hmac_sha256(key, message).to_vec()
+        }
+        SigningKey::Hs384(key) => {
+            check_hmac_key::<Sha384>(key)?;
+            hmac_sha384(key, message).to_vec()
+        }
+        SigningKey::Hs512(key) => {
+            check_hmac_key::<Sha512>(key)?;
+            hmac_sha512(key, message).to_vec()
+        }
+        SigningKey::Es256(key) => EcdsaP256::sign(message, b"", *key, rng)?.to_vec(),
+        SigningKey::EdDsa(key) => Ed25519::sign(message, b"", *key, rng)?.to_vec(),
+        SigningKey::Rs256(key) if key.public_key().size() < MIN_RSA_SIZE => {
+            return Err(Error::InvalidKey)
+        }
+        SigningKey::Rs256(key) => Pkcs1v15::<Sha256>::sign(message, b"", key, rng)?,
+    })
+}
+
+/// Checks that an HMAC key is at least as long as the digest, which RFC 7518 requires.
+fn check_hmac_key<H: HashFunction>(key: &[u8]) -> Result<(), Error> {
+    if key.len() < H::DIGEST_SIZE {
+        return Err(Error::InvalidKey);
+    }
+    Ok(())
+}
+
+fn verify_hmac<H: HashFunction>(key: &[u8], message: &[u8], tag: &[u8]) -> Result<bool, Error> {
+    check_hmac_key::<H>(key)?;
+    Ok(hmac::verify::<H>(key, message, tag))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rand::SystemRandom;
+    use crate::test_helpers::*;
+    use crate::{ed25519, p256};
+
+    // RFC 7515, A.1
+    const HS256_KEY: &str =
+        "AyM1SysPpbyDfgZld3umj1qzKObwVMkoqQ-EstJQLr_T-1qS0gZH75aKtMN3Yj0iPS4hcgUuTwjAzZr1Z9CAow";
+    const HS256_TOKEN: &str = "eyJ0eXAiOiJKV1QiLA0KICJhbGciOiJIUzI1NiJ9.\
+        eyJpc3MiOiJqb2UiLA0KICJleHAiOjEzMDA4MTkzODAsDQogImh0dHA6Ly9leGFtcGxlLmNvbS9pc19yb290Ijp0cnVlfQ.\
+        dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk";
+    const PAYLOAD: &[u8] =
+        b"{\"iss\":\"joe\",\r\n \"exp\":1300819380,\r\n \"http://example.com/is_root\":true}";
+
+    // Signed by Python's cryptography with the key of `jose::tests::rsa_key`
+    const RS256_TOKEN: &str = "eyJhbGciOiJSUzI1NiJ9.\
+        eyJpc3MiOiJqb2UiLA0KICJleHAiOjEzMDA4MTkzODAsDQogImh0dHA6Ly9leGFtcGxlLmNvbS9pc19yb290Ijp0cnVlfQ.\
+        Dy93ma2NtDjp0GFm9G-bqyiJ0iAWsGh7422uPnfFD6_-r1ZptAL3QPNLs0_eM8HteO0GQp1wgU7_4-n6ZirDnkXHGIbH-X\
+        xEu0j3iT_zqSevgj8nG_vTVwUW49YRue6xatHBW6Q_nhn8t2z1xloh4UpGN9Do8Mbvxkdwm3Dea97DUvA5DaNCTLQO4Wk9\
+        V4deEGrSn6wZqyMm_VEh9yy03rCaZV2UjQwEyXCKHyahVoBAZbZMhhT02y7CrREkbIAGtmwAgksy4wthYoo3DLW7rExz-_\
+        JbyS_dRo70NvFkM9PC18al5o7rwoR7MobX-z-o7C9nlMFyAArysUf_6q_a8g";
+    const ES256_SECRET_KEY: &str =
+        "564c01ed94637b4c192abb3d38ba8b81d30e22934d5337243a64ed9ba31e5401";
+    const ES256_TOKEN: &str = "eyJhbGciOiJFUzI1NiJ9.\
+        eyJpc3MiOiJqb2UiLA0KICJleHAiOjEzMDA4MTkzODAsDQogImh0dHA6Ly9leGFtcGxlLmNvbS9pc19yb290Ijp0cnVlfQ.\
+        r-uOO0eC-vnk01K_C6e1CMWzgniZ6-SGw92a-aU2Js7x-FNHMHbFCgz0WTx49MaGRaV21XCVqIPhN7PMRo3G9A";
+
+    // RFC 8037, A.4 and A.5
+    const ED25519_SECRET_KEY: &str =
+        "9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60";
+    const ED25519_TOKEN: &str = "eyJhbGciOiJFZERTQSJ9.RXhhbXBsZSBvZiBFZDI1NTE5IHNpZ25pbmc.\
+        hgyY0il_MGCjP0JzlnLWG1PPOt7-09PGcvMg3AIbQR6dWbhijcNR4ki4iylGjg5BhVsPt9g7sVvpAr_MuM0KAg";
+
+    fn array<const N: usize>(hex: &str) -> [u8; N] {
+        let mut bytes = [0; N];
+        bytes.copy_from_slice(&h2b(hex));
+        bytes
+    }
+
+    #[test]
+    fn test_hmac() {
+        let key = &decode_part(HS256_KEY).unwrap();
+        assert_eq!(
+            PAYLOAD,
+            &verify(HS256_TOKEN, &VerifyingKey::Hs256(key)).unwrap()[..]
+        );
+        let header = decode_unverified_header(HS256_TOKEN).unwrap();
+        assert_eq!(Some("JWT"), header.get("typ").and_then(Value::as_str));
+
+        let rng = &SystemRandom::new();
+        let keys = [
+            (SigningKey::Hs256(key), VerifyingKey::Hs256(key)),
+            (
+                SigningKey::Hs384(&key[..48]),
+                VerifyingKey::Hs384(&key[..48]),
+            ),
+            (SigningKey::Hs512(key), VerifyingKey::Hs512(key)),
+        ];
+        for (signing_key, verifying_key) in &keys {
+            let token = sign(PAYLOAD, signing_key, rng).unwrap();
+            assert_eq!(PAYLOAD, &verify(&token, verifying_key).unwrap()[..]);
+        }
+        // HMAC is deterministic.
+        let token = sign(PAYLOAD, &SigningKey::Hs256(key), rng).unwrap();
+        let expected = "eyJhbGciOiJIUzI1NiJ9.\
+            eyJpc3MiOiJqb2UiLA0KICJleHAiOjEzMDA4MTkzODAsDQogImh0dHA6Ly9leGFtcGxlLmNvbS9pc19yb290Ijp0cnVlfQ.\
+            dCfJaSBBMSnC8CXslIf5orCzS7AboBan4qE7aXuYSDs";
+        assert_eq!(expected, token);
+
+        // Keys must be at least as long as the digest.
+        let short = &key[..31];
+        assert_eq!(
+            Err(Error::InvalidKey),
+            sign(PAYLOAD, &SigningKey::Hs256(short), rng)
+        );
+        assert_eq!(
+            Err(Error::InvalidKey),
+            verify(HS256_TOKEN, &VerifyingKey::Hs256(short))
+        );
+        let error = Err(Error::InvalidKey);
+        assert_eq!(error, sign(PAYLOAD, &SigningKey::Hs384(&key[..47]), rng));
+    }
+
+    #[test]
+    fn test_signatures() {
+        let rsa_key = &crate::jose::tests::rsa_key();
+        let rng = &SystemRandom::new();
+        // RSASSA-PKCS1-v1_5 and Ed25519 are deterministic.
+        let token = sign(PAYLOAD, &SigningKey::Rs256(rsa_key), rng).unwrap();
+        assert_eq!(RS256_TOKEN, token);
+        let verifying_key = VerifyingKey::Rs256(rsa_key.public_key());
+        assert_eq!(PAYLOAD, &verify(RS256_TOKEN, &verifying_key).unwrap()[..]);
+
+        let secret_key = &array(ED25519_SECRET_KEY);
+        let token = sign(
+            b"Example of Ed25519 signing",
+            &SigningKey::EdDsa(secret_key),
+            rng,
+        );
+        assert_eq!(ED25519_TOKEN, token.unwrap());
+        let verifying_key = VerifyingKey::EdDsa(&ed25519::gen_pk(secret_key).unwrap());
+        let payload = verify(ED25519_TOKEN, &verifying_key).unwrap();
+        assert_eq!(b"Example of Ed25519 signing", &payload[..]);
+
+        let secret_key = &array(ES256_SECRET_KEY);
+        let public_key = &p256::gen_pk(secret_key).unwrap();
+        let verifying_key = VerifyingKey::Es256(public_key);
+        assert_eq!(PAYLOAD, &verify(ES256_TOKEN, &verifying_key).unwrap()[..]);
+        let token = sign(PAYLOAD, &SigningKey::Es256(secret_key), rng).unwrap();
+        assert_eq!(PAYLOAD, &verify(&token, &verifying_key).unwrap()[..]);
+        let error = Err(Error::InvalidKey);
+        assert_eq!(error, sign(PAYLOAD, &SigningKey::Es256(&[0; 32]), rng));
+
+        // Changing any part invalidates the signature.
+        for (token, key) in &[
+            (RS256_TOKEN, VerifyingKey::Rs256(rsa_key.public_key())),
+            (ES256_TOKEN, verifying_key),
+        ] {
+            let (header, rest) = token.split_once('.').unwrap();
+            let (_, signature) = rest.split_once('.').unwrap();
+            let forged = [header, "eyJpc3MiOiJqb2UifQ", signature].join(".");
+            assert_eq!(Err(Error::InvalidSignature), verify(&forged, key));
+            let mut signature = decode_part(signature).unwrap();
+            signature[0] ^= 1;
+            let forged = [
+                header,
+                &rest[..rest.find('.').unwrap()],
+                &encode_part(&signature),
+            ];
+            assert_eq!(Err(Error::InvalidSignature), verify(&forged.join("."), key));
+        }
+    }
+
+    #[test]
+    fn test_headers() {
+        let key = &decode_part(HS256_KEY).unwrap();
+        let rng = &SystemRandom::new();
+        let header = [("typ", "JWT".into()), ("kid", "1".into())];
+        let token = sign_with_header(&header, PAYLOAD, &SigningKey::Hs256(key), rng).unwrap();
+        let decoded = decode_unverified_header(&token).unwrap();
+        assert_eq!(
+            r#"{"alg":"HS256","typ":"JWT","kid":"1"}"#,
+            decoded.to_string()
+        );
+        assert_eq!(
+            PAYLOAD,
+            &verify(&token, &VerifyingKey::Hs256(key)).unwrap()[..]
+        );
+        for header in &[
+            [("alg", "none".into()), ("kid", "1".into())],
+            [("kid", "1".into()), ("kid", "2".into())],
+            [("crit", Value::Array(Vec::new())), ("kid", "1".into())],
+        ] {
+            let signed = sign_with_header(header, PAYLOAD, &SigningKey::Hs256(key), rng);
+            assert_eq!(Err(Error::InvalidEncoding), signed);
+        }
+
+        // The algorithm must match the key.
+        let error = Err(Error::InvalidKey);
+        assert_eq!(error, verify(HS256_TOKEN, &VerifyingKey::Hs512(&[0; 64])));
+        assert_eq!(error, verify(ES256_TOKEN, &VerifyingKey::EdDsa(&[0; 32])));
+        let mut unsigned = encode_part(br#"{"alg":"none"}"#);
+        unsigned.push_str(".eyJpc3MiOiJqb2UifQ.");
+        assert_eq!(error, verify(&unsigned, &VerifyingKey::Hs256(key)));
+
+        // Signed headers with critical extensions are rejected.
+        let mut critical = encode_part(br#"{"alg":"HS256","crit":["exp"],"exp":0}"#);
+        critical.push_str(".eyJpc3MiOiJqb2UifQ");
+        let signature = hmac_sha256(key, critical.as_bytes());
+        critical = [&critical, ".", &encode_part(&signature)].concat();
+        let error = Err(Error::InvalidEncoding);
+        assert_eq!(error, verify(&critical, &VerifyingKey::Hs256(key)));
+        assert_eq!(
+            Err(Error::InvalidEncoding),
+            decode_unverified_header(&critical)
+        );
+
+        for token in &[
+            "",
+            "..",
+            "eyJhbGciOiJIUzI1NiJ9",
+            "eyJhbGciOiJIUzI1NiJ9.e30",
+            "eyJhbGciOiJIUzI1NiJ9.e30.AAAA.AAAA",
+            "eyJhbGciOiJIUzI1NiJ9=.e30.AAAA",
+            "WyJIUzI1NiJd.e30.AAAA",
+        ] {
+            assert_eq!(error, verify(token, &VerifyingKey::Hs256(key)), "{}", token);
+        }
+    }
+}
@@ -0,0 +1,276 @@
+//! Module for JSON Web Tokens (RFC 7519): claims signed as JWS payloads.
+//!
+//! Decoding checks the signature, then the `exp` and `nbf` claims against a time given by the
+//! caller, since the crate has no clock, and then an optional hook for any other claims, such as
+//! `iss` or `aud`.
+//!
+//! # Examples
+//!
+//! ```
+//! use crypto_pure::error::Error;
+//! use crypto_pure::jose::jws::{SigningKey, VerifyingKey};
+//! use crypto_pure::jose::jwt::{self, Claims, Validation};
+//! use crypto_pure::rand::SystemRandom;
+//! # let key = &[0x42; 32];
+//! let mut claims = Claims::new();
+//! claims.set("sub", "alice");
+//! claims.set("exp", 1_700_000_600.0);
+//! let token = jwt::encode(&claims, &SigningKey::Hs256(key), &SystemRandom::new())?;
+//!
+//! let key = &VerifyingKey::Hs256(key);
+//! let decoded = jwt::decode(&token, key, &Validation::new(1_700_000_000))?;
+//! assert_eq!(claims, decoded);
+//! let expired = jwt::decode(&token, key, &Validation::new(1_700_000_600));
+//! assert_eq!(Err(Error::InvalidClaims), expired);
+//! # Ok::<(), Error>(())
+//! ```
+use super::jws::{self, SigningKey, VerifyingKey};
+use crate::encoding::json::{self, Value};
+use crate::error::Error;
+use crate::rand::SecureRandom;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+use core::str;
+
+/// The claims of a token, a JSON object whose members are kept in order.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Claims {
+    members: Vec<(String, Value)>,
+}
+
+impl Claims {
+    /// Initializes an empty set of claims.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Gets the claim named `name`.
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        self.members
+            .iter()
+            .find(|(member, _)| member == name)
+            .map(|(_, value)| value)
+    }
+
+    /// Sets the claim named `name`, replacing any existing one in place.
+    pub fn set<V: Into<Value>>(&mut self, name: &str, value: V) {
+        let value = value.into();
+        match self.members.iter_mut().find(|(member, _)| member == name) {
+            Some((_, existing)) => *existing = value,
+            None => self.members.push((name.into(), value)),
+        }
+    }
+
+    /// Gets the claims in order.
+    pub fn members(&self) -> &[(String, Value)] {
+        &self.members
+    }
+}
+
+/// A check of claims beyond the time checks, such as of the issuer or audience.
+pub type Hook = dyn Fn(&Claims) -> Result<(), Error>;
+
+/// How the claims of a token are validated, given the time at which it is being decoded.
+#[derive(Clone, Copy)]
+pub struct Validation<'a> {
+    time: i64,
+    leeway: u32,
+    require_expiration: bool,
+    hook: Option<&'a Hook>,
+}
+
+impl<'a> Validation<'a> {
+    /// Initializes a validation at `time`, in seconds since the Unix epoch, with no leeway.
+    pub fn new(time: i64) -> Self {
+        Self {
+            time,
+            leeway: 0,
+            require_expiration: false,
+            hook: None,
+        }
+    }
+
+    /// Allows the `exp` and `nbf` claims to be off by up to `seconds` to account for clock skew.
+    pub fn with_leeway(mut self, seconds: u32) -> Self {
+        self.leeway = seconds;
+        self
+    }
+
+    /// Rejects tokens without an `exp` claim.
+    pub fn require_expiration(mut self) -> Self {
+        self.require_expiration = true;
+        self
+    }
+
+    /// Runs `hook` on the claims after the time checks pass, failing with its error if it fails.
+    pub fn with_hook(mut self, hook: &'a Hook) -> Self {
+        self.hook = Some(hook);
+        self
+    }
+}
+
+impl fmt::Debug for Validation<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Validation")
+            .field("time", &self.time)
+            .field("leeway", &self.leeway)
+            .field("require_expiration", &self.require_expiration)
+            .field("hook", &self.hook.is_some())
+            .finish()
+    }
+}
+
+/// Signs claims, outputting a token whose header has the `typ` member `JWT`. Any randomness the
+/// algorithm needs is drawn from `rng`.
+///
+/// # Errors
+///
+/// Fails like `jws::sign`.
+pub fn encode(claims: &Claims, key: &SigningKey, rng: &dyn SecureRandom) -> Result<String, Error> {
+    let payload = Value::Object(claims.members.clone()).to_string();
+    jws::sign_with_header(&[("typ", "JWT".into())], payload.as_bytes(), key, rng)
+}
+
+/// Verifies a token and validates its claims, outputting them.
+///
+/// The token is expired from its `exp` time on, and is not yet valid before its `nbf` time,
+/// with both times extended by the leeway.
+///
+/// # Errors
+///
+/// Fails like `jws::verify` if the signature is not valid, with `Error::InvalidEncoding` if the
+/// payload is not a JSON object or the `exp` or `nbf` claim is not a number, with
+/// `Error::InvalidClaims` if the token is expired, not yet valid or missing a required `exp`
+/// claim, or with the error of the hook.
+pub fn decode(token: &str, key: &VerifyingKey, validation: &Validation) -> Result<Claims, Error> {
+    let payload = jws::verify(token, key)?;
+    let payload = str::from_utf8(&payload).map_err(|_| Error::InvalidEncoding)?;
+    let claims = match json::parse(payload)? {
+        Value::Object(members) => Claims { members },
+        _ => return Err(Error::InvalidEncoding),
+    };
+    let time = validation.time as f64;
+    let leeway = f64::from(validation.leeway);
+    match numeric_date(&claims, "exp")? {
+        Some(expiration) if time >= expiration + leeway => return Err(Error::InvalidClaims),
+        None if validation.require_expiration => return Err(Error::InvalidClaims),
+        _ => {}
+    }
+    if let Some(not_before) = numeric_date(&claims, "nbf")? {
+        if time + leeway < not_before {
+            return Err(Error::InvalidClaims);
+        }
+    }
+    if let Some(hook) = validation.hook {
+        hook(&claims)?;
+    }
+    Ok(claims)
+}
+
+/// Gets a NumericDate claim, which is a number of seconds since the Unix epoch.
+fn numeric_date(claims: &Claims, name: &str) -> Result<Option<f64>, Error> {
+    claims
+        .get(name)
+        .map(|value| value.as_f64().ok_or(Error::InvalidEncoding))
+        .transpose()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rand::SystemRandom;
+
+    // RFC 7515, A.1
+    const KEY: &str =
+        "AyM1SysPpbyDfgZld3umj1qzKObwVMkoqQ-EstJQLr_T-1qS0gZH75aKtMN3Yj0iPS4hcgUuTwjAzZr1Z9CAow";
+    const TOKEN: &str = "eyJ0eXAiOiJKV1QiLA0KICJhbGciOiJIUzI1NiJ9.\
+        eyJpc3MiOiJqb2UiLA0KICJleHAiOjEzMDA4MTkzODAsDQogImh0dHA6Ly9leGFtcGxlLmNvbS9pc19yb290Ijp0cnVlfQ.\
+        dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk";
+    const EXPIRATION: i64 = 1_300_819_380;
+
+    #[test]
+    fn test_decode() {
+        let key = &crate::jose::decode_part(KEY).unwrap();
+        let key = &VerifyingKey::Hs256(key);
+        let claims = decode(TOKEN, key, &Validation::new(EXPIRATION - 1)).unwrap();
+        assert_eq!(Some("joe"), claims.get("iss").and_then(Value::as_str));
+        assert_eq!(
+            Some(EXPIRATION as f64),
+            claims.get("exp").and_then(Value::as_f64)
+        );
+        let is_root = claims.get("http://example.com/is_root");
+        assert_eq!(Some(true), is_root.and_then(Value::as_bool));
+        assert_eq!(3, claims.members().len());
+
+        let error = Err(Error::InvalidClaims);
+        assert_eq!(error, decode(TOKEN, key, &Validation::new(EXPIRATION)));
+        let validation = Validation::new(EXPIRATION + 59).with_leeway(60);
+        assert!(decode(TOKEN, key, &validation).is_ok());
+        let validation = Validation::new(EXPIRATION + 60).with_leeway(60);
+        assert_eq!(error, decode(TOKEN, key, &validation));
+
+        let issuer_is = |expected: &'static str| {
+            move |claims: &Claims| match claims.get("iss").and_then(Value::as_str) {
+                Some(issuer) if issuer == expected => Ok(()),
+                _ => Err(Error::InvalidClaims),
+            }
+        };
+        let (joe, eve) = (issuer_is("joe"), issuer_is("eve"));
+        let validation = Validation::new(0).with_hook(&joe);
+        assert!(decode(TOKEN, key, &validation).is_ok());
+        let validation = Validation::new(0).with_hook(&eve);
+        assert_eq!(error, decode(TOKEN, key, &validation));
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let key = &[0x42; 32];
+        let rng = &SystemRandom::new();
+        let mut claims = Claims::new();
+        claims.set("sub", "alice");
+        claims.set("nbf", 1000.0);
+        claims.set("admin", false);
+        claims.set("sub", "bob");
+        let token = encode(&claims, &SigningKey::Hs256(key), rng).unwrap();
+        let header = jws::decode_unverified_header(&token).unwrap();
+        assert_eq!(r#"{"alg":"HS256","typ":"JWT"}"#, header.to_string());
+        let payload = jws::verify(&token, &VerifyingKey::Hs256(key)).unwrap();
+        assert_eq!(br#"{"sub":"bob","nbf":1000,"admin":false}"#, &payload[..]);
+
+        let key = &VerifyingKey::Hs256(key);
+        let error = Err(Error::InvalidClaims);
+        assert_eq!(
+            Ok(&claims),
+            decode(&token, key, &Validation::new(1000)).as_ref()
+        );
+        assert_eq!(error, decode(&token, key, &Validation::new(999)));
+        let validation = Validation::new(990).with_leeway(10);
+        assert_eq!(Ok(&claims), decode(&token, key, &validation).as_ref());
+        let validation = Validation::new(1000).require_expiration();
+        assert_eq!(error, decode(&token, key, &validation));
+    }
+
+    #[test]
+    fn test_invalid_claims() {
+        let key = &[0x42; 32];
+        let rng = &SystemRandom::new();
+        for payload in &[
+            &br#"["sub"]"#[..],
+            br#"{"exp":"tomorrow"}"#,
+            br#"{"nbf":null}"#,
+            b"\xff",
+        ] {
+            let token = jws::sign(payload, &SigningKey::Hs256(key), rng).unwrap();
+            let decoded = decode(&token, &VerifyingKey::Hs256(key), &Validation::new(0));
+            assert_eq!(Err(Error::InvalidEncoding), decoded);
+        }
+        let token = jws::sign(b"{}", &SigningKey::Hs256(key), rng).unwrap();
+        let decoded = decode(
+            &token,
+            &VerifyingKey::Hs256(&[0x24; 32]),
+            &Validation::new(0),
+        );
+        assert_eq!(Err(Error::InvalidSignature), decoded);
+    }
+}
@@ -0,0 +1,375 @@
+//! Module for the key-based key derivation functions of NIST SP 800-108 in counter and feedback
+//! modes, with HMAC or CMAC as the pseudorandom function.
+//!
+//! Each block of output is the PRF, keyed with the key derivation key, of a block counter and the
+//! fixed input data Label || 0x00 || Context || L, where L is the output length in bits. In
+//! feedback mode, each block also takes the previous block, starting from an IV. The counter and
+//! L are big-endian integers whose widths, along with the counter's position and the separator
+//! byte, are set by `Encoding`, since applications such as TPM 2.0 and payment specifications
+//! differ.
+//!
+//! # Examples
+//!
+//! ```
+//! use crypto_pure::aes::Aes128;
+//! use crypto_pure::cmac::Cmac;
+//! use crypto_pure::hmac::Hmac;
+//! use crypto_pure::kbkdf::{self, CounterLocation, Encoding};
+//! use crypto_pure::sha2::Sha256;
+//! # let key = &[0x42; 32];
+//! let output = &mut [0; 32];
+//! kbkdf::derive_counter::<Hmac<Sha256>>(key, b"label", b"context", &Encoding::default(), output)?;
+//!
+//! let encoding = Encoding {
+//!     counter_len: 1,
+//!     counter_location: CounterLocation::AfterFixed,
+//!     separator: false,
+//!     length_len: 2,
+//! };
+//! let iv = &[0; 16];
+//! kbkdf::derive_feedback::<Cmac<Aes128>>(&key[..16], iv, b"label", b"", &encoding, output)?;
+//! # Ok::<(), crypto_pure::error::Error>(())
+//! ```
+use crate::aes::BlockCipher;
+use crate::cmac::Cmac;
+use crate::error::Error;
+use crate::hmac::Hmac;
+use crate::secret::SecretKey;
+use crate::sha2::{HashFunction, MAX_DIGEST_SIZE};
+
+/// A pseudorandom function for key derivation, implemented for `Hmac` and `Cmac`.
+pub trait Prf: Clone {
+    /// Output size in bytes, which is at most 64.
+    const OUTPUT_SIZE: usize;
+
+    /// Initializes the function given a key.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::InvalidKey` if `key.len()` is not appropriate for the function.
+    fn new(key: &[u8]) -> Result<Self, Error>;
+
+    /// Feeds input into the function to update its state.
+    fn update(&mut self, input: &[u8]);
+
+    /// Writes the output into a buffer of length `OUTPUT_SIZE`.
+    fn write_output(self, output: &mut [u8]);
+}
+
+impl<H: HashFunction> Prf for Hmac<H> {
+    const OUTPUT_SIZE: usize = H::DIGEST_SIZE;
+
+    fn new(key: &[u8]) -> Result<Self, Error> {
+        Ok(Hmac::new(key))
+    }
+
+    fn update(&mut self, input: &[u8]) {
+        Hmac::update(self, input);
+    }
+
+    fn write_output(self, output: &mut [u8]) {
+        output.copy_from_slice(&self.tag());
+    }
+}
+
+impl<E: BlockCipher + Clone> Prf for Cmac<E> {
+    const OUTPUT_SIZE: usize = 16;
+
+    fn new(key: &[u8]) -> Result<Self, Error> {
+        Cmac::new(key)
+    }
+
+    fn update(&mut self, input: &[u8]) {
+        Cmac::update(self, input);
+    }
+
+    fn write_output(self, output: &mut [u8]) {
+        output.copy_from_slice(&self.finalize());
+    }
+}
+
+/// The position of the block counter relative to the fixed input data.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CounterLocation {
+    /// The counter precedes the fixed input data, after any previous block in feedback mode.
+    BeforeFixed,
+    /// The counter follows the fixed input data.
+    AfterFixed,
+}
+
+/// The encoding of the block counter and fixed input data.
+///
+/// The default is a 4-byte counter before the fixed input data, a separator byte and a 4-byte
+/// output length, as used by most applications.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Encoding {
+    /// The length in bytes of the counter, from 1 to 4, or 0 to omit it in feedback mode.
+    pub counter_len: usize,
+    /// The position of the counter.
+    pub counter_location: CounterLocation,
+    /// Whether a zero byte separates the label and context.
+    pub separator: bool,
+    /// The length in bytes of the output length L in bits, from 1 to 4, or 0 to omit it.
+    pub length_len: usize,
+}
+
+impl Default for Encoding {
+    fn default() -> Self {
+        Self {
+            counter_len: 4,
+            counter_location: CounterLocation::BeforeFixed,
+            separator: true,
+            length_len: 4,
+        }
+    }
+}
+
+/// Derives keying material into `output` with KBKDF in counter mode.
+///
+/// # Errors
+///
+/// Fails with `Error::InvalidKey` if `key.len()` is not appropriate for `P`, or with
+/// `Error::InvalidLength` if `output` is too long for the counter or for its length in bits to be
+/// encoded.
+///
+/// # Panics
+///
+/// Panics if `encoding.counter_len` is 0 or more than 4, or if `encoding.length_len` is more than
+/// 4.
+pub fn derive_counter<P: Prf>(
+    key: &[u8],
+    label: &[u8],
+    context: &[u8],
+    encoding: &Encoding,
+    output: &mut [u8],
+) -> Result<(), Error> {
+    assert_ne!(0, encoding.counter_len);
+    derive::<P>(key, None, label, context, encoding, output)
+}
+
+/// Derives keying material into `output` with KBKDF in feedback mode, where the first block is
+/// computed from `iv`, which may be empty.
+///
+/// # Errors
+///
+/// Fails with `Error::InvalidKey` if `key.len()` is not appropriate for `P`, or with
+/// `Error::InvalidLength` if `iv.len()` is more than the output size of `P` or if `output` is too
+/// long for the counter or for its length in bits to be encoded.
+///
+/// # Panics
+///
+/// Panics if `encoding.counter_len` or `encoding.length_len` is more than 4.
+pub fn derive_feedback<P: Prf>(
+    key: &[u8],
+    iv: &[u8],
+    label: &[u8],
+    context: &[u8],
+    encoding: &Encoding,
+    output: &mut [u8],
+) -> Result<(), Error> {
+    if iv.len() > P::OUTPUT_SIZE {
+        return Err(Error::InvalidLength);
+    }
+    derive::<P>(key, Some(iv), label, context, encoding, output)
+}
+
+fn derive<P: Prf>(
+    key: &[u8],
+    iv: Option<&[u8]>,
+    label: &[u8],
+    context: &[u8],
+    encoding: &Encoding,
+    output: &mut [u8],
+) -> Result<(), Error> {
+    let counter_len = encoding.counter_len;
+    let length_len = encoding.length_len;
+    assert!(counter_len <= 4 && length_len <= 4);
+    let blocks = (output.len() as u64).div_ceil(P::OUTPUT_SIZE as u64);
+    let bits = 8 * output.len() as u64;
+    if (counter_len > 0 && blocks >= 1 << (8 * counter_len))
+        || (length_len > 0 && bits >= 1 << (8 * length_len))
+    {
+        return Err(Error::InvalidLength);
+    }
+    let length = &bits.to_be_bytes()[8 - length_len..];
+    let keyed_prf = P::new(key)?;
+    let mut previous = SecretKey::new([0; MAX_DIGEST_SIZE]);
+    let mut previous_len = 0;
+    if let Some(iv) = iv {
+        previous[..iv.len()].copy_from_slice(iv);
+        previous_len = iv.len();
+    }
+    for (i, chunk) in (1u32..).zip(output.chunks_mut(P::OUTPUT_SIZE)) {
+        let counter = &i.to_be_bytes()[4 - counter_len..];
+        let mut prf = keyed_prf.clone();
+        prf.update(&previous[..previous_len]);
+        if encoding.counter_location == CounterLocation::BeforeFixed {
+            prf.update(counter);
+        }
+        prf.update(label);
+        if encoding.separator {
+            prf.update(&[0]);
+        }
+        prf.update(context);
+        prf.update(length);
+        if encoding.counter_location == CounterLocation::AfterFixed {
+            prf.update(counter);
+        }
+        let block = &mut previous[..P::OUTPUT_SIZE];
+        prf.write_output(block);
+        chunk.copy_from_slice(&block[..chunk.len()]);
+        if iv.is_some() {
+            previous_len = P::OUTPUT_SIZE;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aes::{Aes128, Aes256};
+    use crate::sha2::{Sha256, Sha512};
+    use crate::test_helpers::*;
+    use std::vec;
+
+    const KEY: &str = "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f";
+
+    fn check_counter<P: Prf>(key: &str, encoding: Encoding, expected: &str) {
+        let expected = h2b(expected);
+        let output = &mut vec![0; expected.len()];
+        derive_counter::<P>(&h2b(key), b"label", b"context", &encoding, output).unwrap();
+        assert_eq!(&expected, output);
+    }
+
+    fn check_feedback<P: Prf>(iv: &[u8], encoding: Encoding, expected: &str) {
+        let expected = h2b(expected);
+        let output = &mut vec![0; expected.len()];
+        derive_feedback::<P>(&h2b(KEY), iv, b"label", b"context", &encoding, output).unwrap();
+        assert_eq!(&expected, output);
+    }
+
+    // Generated with Python's cryptography
+    #[test]
+    fn test_counter_mode() {
+        check_counter::<Hmac<Sha256>>(
+            KEY,
+            Encoding::default(),
+            "b9cd5f6323f01f4680650855f1ebea9b4c54c08131b506fc28c856364a38a2f4\
+             fb680c12ea51696887d9",
+        );
+        check_counter::<Hmac<Sha512>>(
+            KEY,
+            Encoding {
+                counter_len: 1,
+                counter_location: CounterLocation::AfterFixed,
+                separator: true,
+                length_len: 2,
+            },
+            "064cee11fe17448305462da14c71933463416df1bf37d60e5a5d98c507229033\
+             0fff58f1c2ef175d2f17a3bf73df88a65ca625e373ba0710c86deddf6db289c8\
+             dec087ed4102ee0ab621fe2f57b26a08dc06bb30b27f323a3538bf5e811349e1\
+             ae1c1209",
+        );
+        check_counter::<Cmac<Aes128>>(
+            &KEY[..32],
+            Encoding::default(),
+            "3fc9b552ad320ef843abf45fe0209ce553353235b587ffa35dfd387b410da1c1\
+             a60066f8b9f805ce",
+        );
+        check_counter::<Hmac<Sha256>>(
+            KEY,
+            Encoding {
+                separator: false,
+                length_len: 0,
+                ..Encoding::default()
+            },
+            "5fbe62c62af6a793f057c9af9e825a9bde3c7c65926751ad49e026a82d49b5be\
+             240f82ed2307639e7283",
+        );
+    }
+
+    // Generated with a Python implementation checked against cryptography's counter mode
+    #[test]
+    fn test_feedback_mode() {
+        check_feedback::<Hmac<Sha256>>(
+            &[0x11; 32],
+            Encoding::default(),
+            "1d4dad78012da69c4e895ae065a239c9b19ba31a92560ea92a5837c705bfdfb3\
+             3caa02abd5cf15b9ab92105aa9712ce5a9f417acf276e20f9a6ad08d8f57fa4e\
+             d80a85de49a12534d4660d6c2c3b33a7",
+        );
+        check_feedback::<Hmac<Sha256>>(
+            &[],
+            Encoding {
+                counter_len: 0,
+                ..Encoding::default()
+            },
+            "bf7a94f8892e2812e783e3358ac8d9edf75be78b2c1e30ee04d98f4421a1097b\
+             f88db212d7566536c733dedee7975d334ea8d4f7b2276ebc8da52ccdf9fcb791\
+             f351002a794ca4205eba74bccff56c30",
+        );
+        check_feedback::<Cmac<Aes256>>(
+            &[0; 16],
+            Encoding {
+                counter_len: 1,
+                counter_location: CounterLocation::AfterFixed,
+                ..Encoding::default()
+            },
+            "0bb81e13bb79a2189e190adb0ac0ec3c5f5c4bafcfac434c27b5da89c30444cc\
+             cd0542484139dd8f",
+        );
+    }
+
+    #[test]
+    fn test_max_output() {
+        let encoding = Encoding {
+            counter_len: 1,
+            ..Encoding::default()
+        };
+        let output = &mut [0; 255 * 16];
+        derive_counter::<Cmac<Aes128>>(&[0; 16], b"", b"", &encoding, output).unwrap();
+    }
+
+    #[test]
+    fn test_counter_overflow() {
+        let encoding = Encoding {
+            counter_len: 1,
+            ..Encoding::default()
+        };
+        let output = &mut [0; 255 * 16 + 1];
+        let result = derive_counter::<Cmac<Aes128>>(&[0; 16], b"", b"", &encoding, output);
+        assert_eq!(Err(Error::InvalidLength), result);
+    }
+
+    #[test]
+    fn test_length_overflow() {
+        let encoding = Encoding {
+            length_len: 1,
+            ..Encoding::default()
+        };
+        let result = derive_counter::<Hmac<Sha256>>(&[0; 32], b"", b"", &encoding, &mut [0; 32]);
+        assert_eq!(Err(Error::InvalidLength), result);
+    }
+
+    #[test]
+    fn test_invalid_lengths() {
+        let encoding = Encoding::default();
+        let output = &mut [0; 32];
+        let result = derive_counter::<Cmac<Aes128>>(&[0; 15], b"", b"", &encoding, output);
+        assert_eq!(Err(Error::InvalidKey), result);
+        let result =
+            derive_feedback::<Cmac<Aes128>>(&[0; 16], &[0; 17], b"", b"", &encoding, output);
+        assert_eq!(Err(Error::InvalidLength), result);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_counter_mode_without_counter() {
+        let encoding = Encoding {
+            counter_len: 0,
+            ..Encoding::default()
+        };
+        let _ = derive_counter::<Hmac<Sha256>>(&[0; 32], b"", b"", &encoding, &mut [0; 32]);
+    }
+}
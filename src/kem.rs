@@ -0,0 +1,70 @@
+//! Module for the `Kem` trait shared by key encapsulation mechanisms.
+//!
+//! A recipient generates a key pair and publishes the encapsulation key. A sender encapsulates a
+//! fresh shared secret to it, outputting a ciphertext from which the recipient decapsulates the
+//! same shared secret. The trait is implemented by the ML-KEM parameter sets, the X25519MLKEM768
+//! hybrid KEM and the HPKE DHKEMs, so that protocols such as `hpke` are written once against it.
+//!
+//! # Examples
+//!
+//! ```
+//! use crypto_pure::hybrid::X25519MlKem768;
+//! use crypto_pure::kem::Kem;
+//! use crypto_pure::rand::SystemRandom;
+//! fn exchange<K: Kem>() -> Result<bool, crypto_pure::error::Error> {
+//!     let rng = &SystemRandom::new();
+//!     let (decapsulation_key, encapsulation_key) = K::generate(rng);
+//!     let (ciphertext, sender_secret) = K::encapsulate(encapsulation_key.as_ref(), rng)?;
+//!     let recipient_secret = K::decapsulate(&decapsulation_key, ciphertext.as_ref())?;
+//!     Ok(sender_secret.as_ref() == recipient_secret.as_ref())
+//! }
+//! assert!(exchange::<X25519MlKem768>()?);
+//! # Ok::<(), crypto_pure::error::Error>(())
+//! ```
+use crate::error::Error;
+use crate::rand::SecureRandom;
+
+/// A key encapsulation mechanism.
+pub trait Kem {
+    /// The length of an encapsulation key.
+    const ENCAPSULATION_KEY_LEN: usize;
+    /// The length of a ciphertext.
+    const CIPHERTEXT_LEN: usize;
+    /// The length of a shared secret.
+    const SHARED_SECRET_LEN: usize;
+
+    /// A decapsulation key.
+    type DecapsulationKey;
+    /// An encoded encapsulation key.
+    type EncapsulationKey: AsRef<[u8]>;
+    /// An encoded ciphertext.
+    type Ciphertext: AsRef<[u8]>;
+    /// A shared secret.
+    type SharedSecret: AsRef<[u8]>;
+
+    /// Generates a key pair with randomness drawn from `rng`, outputting the decapsulation key
+    /// and the encapsulation key.
+    fn generate(rng: &dyn SecureRandom) -> (Self::DecapsulationKey, Self::EncapsulationKey);
+
+    /// Encapsulates a shared secret to an encapsulation key with randomness drawn from `rng`,
+    /// outputting the ciphertext and the shared secret.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::InvalidKey` if the encapsulation key is not valid.
+    fn encapsulate(
+        encapsulation_key: &[u8],
+        rng: &dyn SecureRandom,
+    ) -> Result<(Self::Ciphertext, Self::SharedSecret), Error>;
+
+    /// Decapsulates the shared secret from a ciphertext.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::InvalidLength` if the ciphertext has the wrong length, or with
+    /// `Error::InvalidKey` if it is not valid otherwise.
+    fn decapsulate(
+        decapsulation_key: &Self::DecapsulationKey,
+        ciphertext: &[u8],
+    ) -> Result<Self::SharedSecret, Error>;
+}
@@ -0,0 +1,399 @@
+//! Module for storing private keys encrypted under a passphrase.
+//!
+//! A keystore holds the PKCS #8 encoding of a private key, encrypted with AES-256-GCM under a
+//! key derived from the passphrase with scrypt or Argon2id. It starts with a header, which is
+//! authenticated as the associated data so that the parameters can't be changed:
+//!
+//! ```text
+//! magic      "CPKSTORE"
+//! version    1 byte, currently 1
+//! kdf        1 byte: 1 for scrypt, 2 for Argon2id
+//! params     scrypt: log_n (1 byte), r and p (4 bytes each)
+//!            Argon2id: memory cost, time cost and parallelism (4 bytes each)
+//! salt       16 bytes
+//! nonce      12 bytes
+//! ```
+//!
+//! Integers are big-endian, and the ciphertext and 16-byte tag follow. Since the cost of
+//! decrypting is set by the file, `decrypt` refuses parameters beyond `Limits`.
+//!
+//! # Examples
+//!
+//! ```
+//! use crypto_pure::keystore::{self, Kdf, Limits};
+//! use crypto_pure::pkcs8::PrivateKey;
+//! use crypto_pure::rand::SystemRandom;
+//! use crypto_pure::secret::SecretKey;
+//! # let seed = [0x42; 32];
+//! let private_key = PrivateKey::Ed25519(SecretKey::new(seed));
+//! let passphrase = b"correct horse battery staple";
+//! let rng = &SystemRandom::new();
+//! let keystore = keystore::encrypt(&private_key, passphrase, Kdf::default(), rng)?;
+//! let decrypted = keystore::decrypt(&keystore, passphrase, &Limits::default())?;
+//! assert!(matches!(decrypted, PrivateKey::Ed25519(_)));
+//! # Ok::<(), crypto_pure::error::Error>(())
+//! ```
+use crate::aead::Aead;
+use crate::aes::Aes256;
+use crate::argon2::{self, Argon2, Variant};
+use crate::error::Error;
+use crate::gcm::Gcm;
+use crate::pkcs8::{self, PrivateKey};
+use crate::rand::SecureRandom;
+use crate::scrypt;
+use crate::secret::{SecretKey, SecretVec};
+use alloc::vec::Vec;
+use byteorder::{BigEndian, ByteOrder as _};
+
+/// The version of the format that `encrypt` writes.
+pub const VERSION: u8 = 1;
+
+const MAGIC: &[u8] = b"CPKSTORE";
+const SCRYPT: u8 = 1;
+const ARGON2ID: u8 = 2;
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+const KEY_LEN: usize = 32;
+
+/// The key derivation function that stretches the passphrase, with its cost parameters.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Kdf {
+    /// scrypt.
+    Scrypt(scrypt::Params),
+    /// Argon2id.
+    Argon2id(argon2::Params),
+}
+
+impl Default for Kdf {
+    /// Argon2id with 19 MiB of memory and 2 passes, the minimum OWASP recommends.
+    fn default() -> Self {
+        Kdf::Argon2id(argon2::Params {
+            memory_cost: 19 * 1024,
+            time_cost: 2,
+            parallelism: 1,
+        })
+    }
+}
+
+impl Kdf {
+    fn encode(&self, header: &mut Vec<u8>) {
+        let mut u32s = [0; 12];
+        match self {
+            Kdf::Scrypt(params) => {
+                header.extend_from_slice(&[SCRYPT, params.log_n]);
+                BigEndian::write_u32_into(&[params.r, params.p], &mut u32s[..8]);
+                header.extend_from_slice(&u32s[..8]);
+            }
+            Kdf::Argon2id(params) => {
+                header.push(ARGON2ID);
+                let params = [params.memory_cost, params.time_cost, params.parallelism];
+                BigEndian::write_u32_into(&params, &mut u32s);
+                header.extend_from_slice(&u32s);
+            }
+        }
+    }
+
+    /// Parses the parameters after the KDF byte, outputting the rest of the input.
+    fn parse(input: &[u8]) -> Result<(Self, &[u8]), Error> {
+        let (&kdf, input) = input.split_first().ok_or(Error::InvalidEncoding)?;
+        let (kdf, len) = match kdf {
+            SCRYPT if input.len() >= 9 => {
+                let params = scrypt::Params {
+                    log_n: input[0],
+                    r: BigEndian::read_u32(&input[1..5]),
+                    p: BigEndian::read_u32(&input[5..9]),
+                };
+                (Kdf::Scrypt(params), 9)
+            }
+            ARGON2ID if input.len() >= 12 => {
+                let params = argon2::Params {
+                    memory_cost: BigEndian::read_u32(&input[..4]),
+                    time_cost: BigEndian::read_u32(&input[4..8]),
+                    parallelism: BigEndian::read_u32(&input[8..12]),
+                };
+                (Kdf::Argon2id(params), 12)
+            }
+            _ => return Err(Error::InvalidEncoding),
+        };
+        if !kdf.is_valid() {
+            return Err(Error::InvalidEncoding);
+        }
+        Ok((kdf, &input[len..]))
+    }
+
+    /// Checks the parameters as `scrypt::Params::memory_len` and `Argon2::new` do, without
+    /// panicking.
+    fn is_valid(&self) -> bool {
+        match self {
+            Kdf::Scrypt(params) => {
+                0 < params.log_n
+                    && params.log_n < 64
+                    && params.r > 0
+                    && params.p > 0
+                    && u64::from(params.r) * u64::from(params.p) < 1 << 30
+            }
+            Kdf::Argon2id(params) => {
+                0 < params.parallelism
+                    && params.parallelism < 1 << 24
+                    && params.memory_cost / 8 >= params.parallelism
+                    && params.time_cost > 0
+            }
+        }
+    }
+
+    /// Outputs the number of bytes of memory that deriving a key uses, without overflowing.
+    fn memory_len(&self) -> u128 {
+        match self {
+            Kdf::Scrypt(params) => {
+                let blocks = (1 << params.log_n) + u128::from(params.p) + 1;
+                blocks * 128 * u128::from(params.r)
+            }
+            Kdf::Argon2id(params) => 1024 * u128::from(params.memory_cost),
+        }
+    }
+
+    /// Outputs the number of passes over memory: p for scrypt, or the time cost for Argon2id.
+    fn passes(&self) -> u32 {
+        match self {
+            Kdf::Scrypt(params) => params.p,
+            Kdf::Argon2id(params) => params.time_cost,
+        }
+    }
+
+    fn derive_key(&self, passphrase: &[u8], salt: &[u8]) -> SecretKey<[u8; KEY_LEN]> {
+        let mut key = SecretKey::new([0; KEY_LEN]);
+        match self {
+            Kdf::Scrypt(params) => scrypt::derive(passphrase, salt, *params, &mut *key),
+            Kdf::Argon2id(params) => {
+                Argon2::new(Variant::Argon2id, *params).hash(passphrase, salt, &mut *key)
+            }
+        }
+        key
+    }
+}
+
+/// The highest cost that `decrypt` accepts from a keystore.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Limits {
+    /// The most memory, in bytes, that deriving the key may use.
+    pub max_memory_len: usize,
+    /// The most passes over memory: p for scrypt, or the time cost for Argon2id.
+    pub max_passes: u32,
+}
+
+impl Default for Limits {
+    /// Allows 1 GiB of memory and 16 passes.
+    fn default() -> Self {
+        Self {
+            max_memory_len: 1 << 30,
+            max_passes: 16,
+        }
+    }
+}
+
+/// Encrypts a private key under a passphrase, drawing the salt and nonce from `rng`.
+///
+/// # Panics
+///
+/// Panics if the parameters of `kdf` are not valid, as described for `scrypt::Params::memory_len`
+/// and `argon2::Argon2::new`.
+///
+/// # Errors
+///
+/// Fails with the errors of `pkcs8::encode_private_key`.
+pub fn encrypt(
+    private_key: &PrivateKey,
+    passphrase: &[u8],
+    kdf: Kdf,
+    rng: &dyn SecureRandom,
+) -> Result<Vec<u8>, Error> {
+    assert!(kdf.is_valid());
+    let encoded = pkcs8::encode_private_key(private_key)?;
+    let mut header = MAGIC.to_vec();
+    header.push(VERSION);
+    kdf.encode(&mut header);
+    let salt_start = header.len();
+    header.resize(salt_start + SALT_LEN + NONCE_LEN, 0);
+    rng.fill(&mut header[salt_start..]);
+    let (salt, nonce) = header[salt_start..].split_at(SALT_LEN);
+    let key = kdf.derive_key(passphrase, salt);
+    let sealed = Gcm::<Aes256>::new(&*key)?.seal(nonce, &header, &encoded)?;
+    header.extend_from_slice(&sealed);
+    Ok(header)
+}
+
+/// Decrypts a private key from a keystore with a passphrase.
+///
+/// # Errors
+///
+/// Fails with `Error::InvalidEncoding` if the keystore is malformed or has an unknown version,
+/// with `Error::CostTooHigh` if its parameters exceed `limits`, or with `Error::DecryptionFailed`
+/// if the passphrase is wrong or the keystore was modified.
+pub fn decrypt(keystore: &[u8], passphrase: &[u8], limits: &Limits) -> Result<PrivateKey, Error> {
+    let input = match keystore.strip_prefix(MAGIC) {
+        Some(input) => input,
+        None => return Err(Error::InvalidEncoding),
+    };
+    let input = match input.split_first() {
+        Some((&VERSION, input)) => input,
+        _ => return Err(Error::InvalidEncoding),
+    };
+    let (kdf, input) = Kdf::parse(input)?;
+    if input.len() < SALT_LEN + NONCE_LEN {
+        return Err(Error::InvalidEncoding);
+    }
+    let (salt, input) = input.split_at(SALT_LEN);
+    let (nonce, sealed) = input.split_at(NONCE_LEN);
+    if kdf.memory_len() > limits.max_memory_len as u128 || kdf.passes() > limits.max_passes {
+        return Err(Error::CostTooHigh);
+    }
+    let key = kdf.derive_key(passphrase, salt);
+    let header = &keystore[..keystore.len() - sealed.len()];
+    let encoded = Gcm::<Aes256>::new(&*key)?
+        .open(nonce, header, sealed)
+        .map_err(|_| Error::DecryptionFailed)?;
+    pkcs8::decode_private_key(&SecretVec::new(encoded))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pkcs8::PublicKey;
+
+    const SCRYPT_KDF: Kdf = Kdf::Scrypt(scrypt::Params {
+        log_n: 4,
+        r: 8,
+        p: 1,
+    });
+    const ARGON2ID_KDF: Kdf = Kdf::Argon2id(argon2::Params {
+        memory_cost: 64,
+        time_cost: 2,
+        parallelism: 1,
+    });
+
+    fn fixed_rng(dest: &mut [u8]) {
+        dest.iter_mut().for_each(|byte| *byte = 0x42);
+    }
+
+    fn private_key() -> PrivateKey {
+        PrivateKey::Ed25519(SecretKey::new([0x24; 32]))
+    }
+
+    fn public_key(private_key: &PrivateKey) -> [u8; 32] {
+        match private_key.public_key().unwrap() {
+            PublicKey::Ed25519(public_key) => public_key,
+            _ => panic!("expected an Ed25519 key"),
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let keys = [
+            private_key(),
+            PrivateKey::X25519(SecretKey::new([0x24; 32])),
+            PrivateKey::P256(SecretKey::new([0x24; 32])),
+            PrivateKey::P384(SecretKey::new([0x24; 48])),
+        ];
+        for kdf in &[SCRYPT_KDF, ARGON2ID_KDF] {
+            for key in &keys {
+                let keystore = encrypt(key, b"passphrase", *kdf, &fixed_rng).unwrap();
+                let decrypted = decrypt(&keystore, b"passphrase", &Limits::default()).unwrap();
+                assert_eq!(
+                    pkcs8::encode_private_key(key).unwrap()[..],
+                    pkcs8::encode_private_key(&decrypted).unwrap()[..]
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_header() {
+        let keystore = encrypt(&private_key(), b"passphrase", SCRYPT_KDF, &fixed_rng).unwrap();
+        let header = [
+            &b"CPKSTORE\x01\x01\x04\x00\x00\x00\x08\x00\x00\x00\x01"[..],
+            &[0x42; SALT_LEN + NONCE_LEN],
+        ]
+        .concat();
+        assert_eq!(header[..], keystore[..header.len()]);
+        let encoded_len = pkcs8::encode_private_key(&private_key()).unwrap().len();
+        assert_eq!(header.len() + encoded_len + 16, keystore.len());
+
+        let keystore = encrypt(&private_key(), b"passphrase", ARGON2ID_KDF, &fixed_rng).unwrap();
+        let params = b"\x02\x00\x00\x00\x40\x00\x00\x00\x02\x00\x00\x00\x01";
+        assert_eq!(&params[..], &keystore[9..22]);
+    }
+
+    #[test]
+    fn test_wrong_passphrase() {
+        let keystore = encrypt(&private_key(), b"passphrase", ARGON2ID_KDF, &fixed_rng).unwrap();
+        let result = decrypt(&keystore, b"passphrasf", &Limits::default());
+        assert_eq!(Some(Error::DecryptionFailed), result.err());
+    }
+
+    #[test]
+    fn test_modified() {
+        let keystore = encrypt(&private_key(), b"passphrase", SCRYPT_KDF, &fixed_rng).unwrap();
+        let expected = public_key(&decrypt(&keystore, b"passphrase", &Limits::default()).unwrap());
+        assert_eq!(expected, public_key(&private_key()));
+        // Changing r, the salt, the nonce, the ciphertext or the tag fails authentication.
+        for &i in &[14, 20, 40, 60, keystore.len() - 1] {
+            let mut keystore = keystore.clone();
+            keystore[i] ^= 1;
+            let result = decrypt(&keystore, b"passphrase", &Limits::default());
+            assert_eq!(Some(Error::DecryptionFailed), result.err(), "{}", i);
+        }
+    }
+
+    #[test]
+    fn test_malformed() {
+        let keystore = encrypt(&private_key(), b"passphrase", SCRYPT_KDF, &fixed_rng).unwrap();
+        let limits = &Limits::default();
+        for len in 0..47 {
+            let result = decrypt(&keystore[..len], b"passphrase", limits);
+            assert_eq!(Some(Error::InvalidEncoding), result.err(), "{}", len);
+        }
+        // The magic, an unknown version, an unknown KDF and log_n = 0
+        for &(i, byte) in &[(0, b'c'), (8, 2), (9, 3), (10, 0)] {
+            let mut keystore = keystore.clone();
+            keystore[i] = byte;
+            let result = decrypt(&keystore, b"passphrase", limits);
+            assert_eq!(Some(Error::InvalidEncoding), result.err(), "{}", i);
+        }
+
+        let mut keystore =
+            encrypt(&private_key(), b"passphrase", ARGON2ID_KDF, &fixed_rng).unwrap();
+        // A parallelism of 0
+        keystore[21] = 0;
+        let result = decrypt(&keystore, b"passphrase", limits);
+        assert_eq!(Some(Error::InvalidEncoding), result.err());
+    }
+
+    #[test]
+    fn test_limits() {
+        let keystore = encrypt(&private_key(), b"passphrase", ARGON2ID_KDF, &fixed_rng).unwrap();
+        let limits = Limits {
+            max_memory_len: 64 * 1024,
+            max_passes: 2,
+        };
+        assert!(decrypt(&keystore, b"passphrase", &limits).is_ok());
+        for limits in &[
+            Limits {
+                max_memory_len: 64 * 1024 - 1,
+                ..limits
+            },
+            Limits {
+                max_passes: 1,
+                ..limits
+            },
+        ] {
+            let result = decrypt(&keystore, b"passphrase", limits);
+            assert_eq!(Some(Error::CostTooHigh), result.err());
+        }
+
+        // 2^63 blocks of memory can't be allocated, but are rejected before any is.
+        let mut keystore = encrypt(&private_key(), b"passphrase", SCRYPT_KDF, &fixed_rng).unwrap();
+        keystore[10] = 63;
+        let result = decrypt(&keystore, b"passphrase", &Limits::default());
+        assert_eq!(Some(Error::CostTooHigh), result.err());
+    }
+}
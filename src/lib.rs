@@ -2,33 +2,120 @@
 #[cfg(feature = "std")]
 extern crate core;
 
-#[cfg(not(feature = "std"))]
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+#[cfg(all(test, not(feature = "std")))]
 #[macro_use]
-pub(crate) extern crate std;
+extern crate std;
 
+pub mod aead;
 pub mod aes;
+pub mod aes_gcm_siv;
+#[cfg(feature = "std")]
+pub mod age;
+#[cfg(feature = "alloc")]
+pub mod argon2;
+pub mod asn1;
+#[cfg(feature = "alloc")]
+pub(crate) mod bigint;
+pub mod blake2b;
+pub mod ccm;
 pub mod chacha20;
+pub mod cmac;
+pub mod commit;
 pub(crate) mod const_curve25519;
+pub mod ct;
+pub mod ctr_drbg;
 pub mod curve25519;
+pub mod curve448;
+#[cfg(feature = "alloc")]
+pub mod dh;
+#[cfg(feature = "alloc")]
+pub mod ecies;
+pub mod ecvrf;
 pub mod ed25519;
+pub mod ed448;
+pub mod encoding;
+pub mod error;
+pub(crate) mod field;
 pub mod gcm;
-pub(crate) mod ghash;
+pub mod ghash;
+pub mod gmac;
+pub mod hash_to_curve;
 pub mod hkdf;
 pub mod hmac;
+pub mod hmac_drbg;
+pub mod hpke;
+pub mod hybrid;
+#[cfg(feature = "std")]
+pub mod io;
+#[cfg(feature = "alloc")]
+pub mod jose;
+pub mod kbkdf;
+pub mod kem;
+#[cfg(feature = "alloc")]
+pub mod keystore;
+#[cfg(feature = "legacy-hashes")]
+pub mod md5;
+pub mod merkle;
+pub mod mgf1;
+pub mod mldsa;
+pub mod mlkem;
+pub mod nacl;
+pub mod noise;
+pub mod p256;
+pub mod p384;
+pub mod padding;
+#[cfg(feature = "alloc")]
+pub mod paseto;
+pub mod pbkdf2;
+#[cfg(feature = "alloc")]
+pub mod pkcs8;
 pub mod poly1305;
+pub mod rand;
+#[cfg(feature = "rand-core-compat")]
+pub mod rand_core_compat;
+#[cfg(feature = "alloc")]
+pub mod ratchet;
+pub mod ristretto255;
+#[cfg(feature = "alloc")]
+pub mod rsa;
+#[cfg(feature = "rustcrypto-compat")]
+pub mod rustcrypto;
+pub mod salsa20;
+pub mod schnorr;
+pub mod scrypt;
+pub mod secp256k1;
+pub mod secret;
+#[cfg(feature = "legacy-hashes")]
+pub mod sha1;
 pub mod sha2;
+pub mod sha3;
+pub mod sig;
+pub mod slhdsa;
+pub mod spake2;
+#[cfg(feature = "alloc")]
+pub mod srp;
+#[cfg(feature = "alloc")]
+pub mod ssh;
+pub mod tls12;
+pub mod tls13;
 pub mod util;
+pub mod voprf;
+pub(crate) mod weierstrass;
+#[cfg(feature = "alloc")]
+pub mod x509;
+pub mod x963;
 
 #[cfg(test)]
 pub mod test_helpers {
-    use std::str;
+    use crate::encoding::hex;
     use std::vec::Vec;
 
-    // ew
     pub fn h2b(s: &str) -> Vec<u8> {
-        s.as_bytes()
-            .chunks(2)
-            .map(|x| u8::from_str_radix(str::from_utf8(x).unwrap(), 16).unwrap())
-            .collect()
+        let mut bytes = vec![0; s.len() / 2];
+        hex::decode_to_slice(s, &mut bytes).unwrap();
+        bytes
     }
 }
@@ -0,0 +1,252 @@
+//! Module for the MD5 hash function.
+//!
+//! MD5 is broken with respect to collision resistance and is only provided for verifying digests
+//! produced by legacy systems. Do not use it in new designs.
+use crate::sha2::HashFunction;
+use byteorder::{ByteOrder as _, LittleEndian};
+
+/// The MD5 hash function, for legacy interoperability only.
+///
+/// # Examples
+///
+/// ```
+/// use crypto_pure::md5::InsecureMd5;
+/// use crypto_pure::sha2::HashFunction;
+/// let mut digest = [0; InsecureMd5::DIGEST_SIZE];
+/// let mut md5 = InsecureMd5::default();
+/// md5.update(b"part one");
+/// md5.update(b"part two");
+/// md5.write_digest(&mut digest);
+/// ```
+#[derive(Clone)]
+pub struct InsecureMd5 {
+    state: [u32; 4],
+    buffer: [u8; 64],
+    offset: usize,
+    len: u64,
+}
+
+/// Wrapper for obtaining the MD5 digest for a complete message, for legacy interoperability only.
+pub fn insecure_md5(message: &[u8]) -> [u8; InsecureMd5::DIGEST_SIZE] {
+    let mut digest = [0; InsecureMd5::DIGEST_SIZE];
+    let mut md5 = InsecureMd5::default();
+    md5.update(message);
+    md5.write_digest(&mut digest);
+    digest
+}
+
+const INITIAL_STATE: [u32; 4] = [0x6745_2301, 0xefcd_ab89, 0x98ba_dcfe, 0x1032_5476];
+
+const SHIFTS: [[u32; 4]; 4] = [
+    [7, 12, 17, 22],
+    [5, 9, 14, 20],
+    [4, 11, 16, 23],
+    [6, 10, 15, 21],
+];
+
+const K: [u32; 64] = [
+    0xd76a_a478,
+    0xe8c7_b756,
+    0x2420_70db,
+    0xc1bd_ceee,
+    0xf57c_0faf,
+    0x4787_c62a,
+    0xa830_4613,
+    0xfd46_9501,
+    0x6980_98d8,
+    0x8b44_f7af,
+    0xffff_5bb1,
+    0x895c_d7be,
+    0x6b90_1122,
+    0xfd98_7193,
+    0xa679_438e,
+    0x49b4_0821,
+    0xf61e_2562,
+    0xc040_b340,
+    0x265e_5a51,
+    0xe9b6_c7aa,
+    0xd62f_105d,
+    0x0244_1453,
+    0xd8a1_e681,
+    0xe7d3_fbc8,
+    0x21e1_cde6,
+    0xc337_07d6,
+    0xf4d5_0d87,
+    0x455a_14ed,
+    0xa9e3_e905,
+    0xfcef_a3f8,
+    0x676f_02d9,
+    0x8d2a_4c8a,
+    0xfffa_3942,
+    0x8771_f681,
+    0x6d9d_6122,
+    0xfde5_380c,
+    0xa4be_ea44,
+    0x4bde_cfa9,
+    0xf6bb_4b60,
+    0xbebf_bc70,
+    0x289b_7ec6,
+    0xeaa1_27fa,
+    0xd4ef_3085,
+    0x0488_1d05,
+    0xd9d4_d039,
+    0xe6db_99e5,
+    0x1fa2_7cf8,
+    0xc4ac_5665,
+    0xf429_2244,
+    0x432a_ff97,
+    0xab94_23a7,
+    0xfc93_a039,
+    0x655b_59c3,
+    0x8f0c_cc92,
+    0xffef_f47d,
+    0x8584_5dd1,
+    0x6fa8_7e4f,
+    0xfe2c_e6e0,
+    0xa301_4314,
+    0x4e08_11a1,
+    0xf753_7e82,
+    0xbd3a_f235,
+    0x2ad7_d2bb,
+    0xeb86_d391,
+];
+
+impl Default for InsecureMd5 {
+    fn default() -> Self {
+        Self {
+            state: INITIAL_STATE,
+            buffer: [0; 64],
+            offset: 0,
+            len: 0,
+        }
+    }
+}
+
+impl HashFunction for InsecureMd5 {
+    const DIGEST_SIZE: usize = 16;
+    const BLOCK_SIZE: usize = 64;
+
+    fn update(&mut self, input: &[u8]) {
+        self.len += input.len() as u64;
+        let mut input_offset = 0;
+        let buffer_space = self.buffer.len() - self.offset;
+        if self.offset > 0 {
+            if input.len() < buffer_space {
+                self.buffer[self.offset..self.offset + input.len()].copy_from_slice(input);
+                self.offset += input.len();
+                return;
+            }
+            self.buffer[self.offset..].copy_from_slice(&input[..buffer_space]);
+            self.offset = 0;
+            process(&mut self.state, &self.buffer);
+            input_offset = buffer_space;
+        }
+        for chunk in input[input_offset..].chunks(self.buffer.len()) {
+            if chunk.len() < self.buffer.len() {
+                self.buffer[..chunk.len()].copy_from_slice(chunk);
+                self.offset = chunk.len();
+            } else {
+                process(&mut self.state, chunk);
+            }
+        }
+    }
+
+    /// Writes the hash function digest into an output buffer.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `output.len()` is not equal to the digest size.
+    fn write_digest(mut self, output: &mut [u8]) {
+        assert_eq!(Self::DIGEST_SIZE, output.len());
+        self.pad();
+        process(&mut self.state, &self.buffer);
+        LittleEndian::write_u32_into(&self.state, output);
+    }
+}
+
+impl InsecureMd5 {
+    fn pad(&mut self) {
+        self.buffer[self.offset] = 0x80;
+        self.offset += 1;
+        if self.offset > 64 - 8 {
+            for byte in self.buffer.iter_mut().skip(self.offset) {
+                *byte = 0;
+            }
+            self.offset = 0;
+            process(&mut self.state, &self.buffer);
+        }
+        for byte in self.buffer.iter_mut().take(64 - 8).skip(self.offset) {
+            *byte = 0;
+        }
+        LittleEndian::write_u64(&mut self.buffer[64 - 8..], 8 * self.len);
+    }
+}
+
+fn process(state: &mut [u32; 4], input: &[u8]) {
+    let mut m = [0; 16];
+    LittleEndian::read_u32_into(input, &mut m);
+    let mut a = state[0];
+    let mut b = state[1];
+    let mut c = state[2];
+    let mut d = state[3];
+    for (i, &ki) in K.iter().enumerate() {
+        let (f, g) = match i / 16 {
+            0 => ((b & c) | (!b & d), i),
+            1 => ((d & b) | (!d & c), (5 * i + 1) % 16),
+            2 => (b ^ c ^ d, (3 * i + 5) % 16),
+            _ => (c ^ (b | !d), (7 * i) % 16),
+        };
+        let temp = a
+            .wrapping_add(f)
+            .wrapping_add(ki)
+            .wrapping_add(m[g])
+            .rotate_left(SHIFTS[i / 16][i % 4]);
+        a = d;
+        d = c;
+        c = b;
+        b = b.wrapping_add(temp);
+    }
+    state[0] = state[0].wrapping_add(a);
+    state[1] = state[1].wrapping_add(b);
+    state[2] = state[2].wrapping_add(c);
+    state[3] = state[3].wrapping_add(d);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::*;
+
+    fn check(expected: &str, message: &[u8]) {
+        let expected = h2b(expected);
+        assert_eq!(expected, insecure_md5(message).to_vec());
+
+        let actual = &mut [0; InsecureMd5::DIGEST_SIZE];
+        let mut md5 = InsecureMd5::default();
+        for word in message.chunks(3) {
+            md5.update(word);
+        }
+        md5.write_digest(actual);
+        assert_eq!(expected, actual.to_vec());
+    }
+
+    #[test]
+    fn test_digest() {
+        check("d41d8cd98f00b204e9800998ecf8427e", b"");
+        check("0cc175b9c0f1b6a831c399e269772661", b"a");
+        check("900150983cd24fb0d6963f7d28e17f72", b"abc");
+        check("f96b697d7cb7938d525a2f31aaf161d0", b"message digest");
+        check(
+            "c3fcd3d76192e4007dfb496cca67e13b",
+            b"abcdefghijklmnopqrstuvwxyz",
+        );
+        check(
+            "d174ab98d277d9f5a5611c2c9f419d9f",
+            b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789",
+        );
+        check(
+            "57edf4a22be3c955ac49da2e2107b67a",
+            b"12345678901234567890123456789012345678901234567890123456789012345678901234567890",
+        );
+    }
+}
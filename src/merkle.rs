@@ -0,0 +1,592 @@
+//! Module for Merkle trees with inclusion and consistency proofs, as used by Certificate
+//! Transparency (RFC 6962), with a hash function chosen by its `HashFunction` type.
+//!
+//! A leaf is hashed as H(0x00 || entry) and an interior node as H(0x01 || left || right), so a
+//! leaf can't be passed off as a node. A tree of n leaves splits into a left subtree of the
+//! largest power of 2 less than n leaves and a right subtree of the rest. Proofs are the
+//! concatenation of the hashes they contain, in the order of RFC 6962.
+use crate::error::Error;
+use crate::sha2::{Digest, HashFunction};
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+#[cfg(feature = "alloc")]
+use core::marker::PhantomData;
+
+/// Hashes an entry into a leaf hash.
+pub fn leaf_hash<H: HashFunction>(entry: &[u8]) -> Digest {
+    let mut hash_function = H::default();
+    hash_function.update(&[0]);
+    hash_function.update(entry);
+    Digest::new(hash_function)
+}
+
+fn node_hash<H: HashFunction>(left: &[u8], right: &[u8]) -> Digest {
+    let mut hash_function = H::default();
+    hash_function.update(&[1]);
+    hash_function.update(left);
+    hash_function.update(right);
+    Digest::new(hash_function)
+}
+
+/// Verifies that `leaf_hash` is the hash of the leaf at `index` in the tree of `tree_size` leaves
+/// with root `root`.
+///
+/// # Errors
+///
+/// Fails with `Error::InvalidProof` if verification fails.
+pub fn verify_inclusion<H: HashFunction>(
+    leaf_hash: &[u8],
+    index: u64,
+    tree_size: u64,
+    proof: &[u8],
+    root: &[u8],
+) -> Result<(), Error> {
+    if leaf_hash.len() != H::DIGEST_SIZE
+        || index >= tree_size
+        || !proof.len().is_multiple_of(H::DIGEST_SIZE)
+    {
+        return Err(Error::InvalidProof);
+    }
+    let mut node = index;
+    let mut last_node = tree_size - 1;
+    let mut hash = Digest::from_slice(leaf_hash);
+    for sibling in proof.chunks(H::DIGEST_SIZE) {
+        if last_node == 0 {
+            return Err(Error::InvalidProof);
+        }
+        if node & 1 == 1 || node == last_node {
+            hash = node_hash::<H>(sibling, &hash);
+            // Skip the levels where the node has no sibling to its right.
+            while node & 1 == 0 && node != 0 {
+                node >>= 1;
+                last_node >>= 1;
+            }
+        } else {
+            hash = node_hash::<H>(&hash, sibling);
+        }
+        node >>= 1;
+        last_node >>= 1;
+    }
+    if last_node != 0 || *hash != *root {
+        return Err(Error::InvalidProof);
+    }
+    Ok(())
+}
+
+/// Verifies that the tree of `old_size` leaves with root `old_root` is a prefix of the tree of
+/// `new_size` leaves with root `new_root`.
+///
+/// # Errors
+///
+/// Fails with `Error::InvalidProof` if verification fails, including if `old_size` is zero.
+pub fn verify_consistency<H: HashFunction>(
+    old_size: u64,
+    new_size: u64,
+    old_root: &[u8],
+    new_root: &[u8],
+    proof: &[u8],
+) -> Result<(), Error> {
+    let digest_size = H::DIGEST_SIZE;
+    if old_root.len() != digest_size
+        || old_size == 0
+        || old_size > new_size
+        || !proof.len().is_multiple_of(digest_size)
+    {
+        return Err(Error::InvalidProof);
+    }
+    if old_size == new_size {
+        if !proof.is_empty() || old_root != new_root {
+            return Err(Error::InvalidProof);
+        }
+        return Ok(());
+    }
+    let mut node = old_size - 1;
+    let mut last_node = new_size - 1;
+    // Skip the levels where the old tree's last node is a complete right subtree.
+    while node & 1 == 1 {
+        node >>= 1;
+        last_node >>= 1;
+    }
+    // The proof omits the old root when the old tree is a complete subtree of the new one.
+    let (first, rest) = if old_size.is_power_of_two() {
+        (old_root, proof)
+    } else if proof.is_empty() {
+        return Err(Error::InvalidProof);
+    } else {
+        proof.split_at(digest_size)
+    };
+    let mut old_hash = Digest::from_slice(first);
+    let mut new_hash = Digest::from_slice(first);
+    for sibling in rest.chunks(digest_size) {
+        if last_node == 0 {
+            return Err(Error::InvalidProof);
+        }
+        if node & 1 == 1 || node == last_node {
+            old_hash = node_hash::<H>(sibling, &old_hash);
+            new_hash = node_hash::<H>(sibling, &new_hash);
+            while node & 1 == 0 && node != 0 {
+                node >>= 1;
+                last_node >>= 1;
+            }
+        } else {
+            new_hash = node_hash::<H>(&new_hash, sibling);
+        }
+        node >>= 1;
+        last_node >>= 1;
+    }
+    if last_node != 0 || *old_hash != *old_root || *new_hash != *new_root {
+        return Err(Error::InvalidProof);
+    }
+    Ok(())
+}
+
+/// An append-only Merkle tree that stores its leaf hashes and can produce proofs for any of its
+/// earlier sizes.
+///
+/// # Examples
+///
+/// ```
+/// use crypto_pure::merkle::{self, MerkleTree};
+/// use crypto_pure::sha2::Sha256;
+/// let mut tree = MerkleTree::<Sha256>::new();
+/// for entry in &[b"one", b"two", b"six"] {
+///     tree.push(*entry);
+/// }
+/// let old_root = tree.root();
+/// tree.push(b"ten");
+/// let root = tree.root();
+///
+/// let proof = tree.inclusion_proof(1, 4)?;
+/// let leaf_hash = merkle::leaf_hash::<Sha256>(b"two");
+/// merkle::verify_inclusion::<Sha256>(&leaf_hash, 1, 4, &proof, &root)?;
+/// let proof = tree.consistency_proof(3, 4)?;
+/// merkle::verify_consistency::<Sha256>(3, 4, &old_root, &root, &proof)?;
+/// # Ok::<(), crypto_pure::error::Error>(())
+/// ```
+#[cfg(feature = "alloc")]
+#[derive(Clone)]
+pub struct MerkleTree<H> {
+    leaf_hashes: Vec<u8>,
+    hash_function: PhantomData<H>,
+}
+
+#[cfg(feature = "alloc")]
+impl<H: HashFunction> MerkleTree<H> {
+    /// Initializes an empty tree.
+    pub fn new() -> Self {
+        Self {
+            leaf_hashes: Vec::new(),
+            hash_function: PhantomData,
+        }
+    }
+
+    /// Appends a leaf for an entry.
+    pub fn push(&mut self, entry: &[u8]) {
+        self.leaf_hashes.extend_from_slice(&leaf_hash::<H>(entry));
+    }
+
+    /// Appends a leaf given its leaf hash.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::InvalidLength` if `leaf_hash.len()` is not equal to the digest size for
+    /// `H`.
+    pub fn push_leaf_hash(&mut self, leaf_hash: &[u8]) -> Result<(), Error> {
+        if leaf_hash.len() != H::DIGEST_SIZE {
+            return Err(Error::InvalidLength);
+        }
+        self.leaf_hashes.extend_from_slice(leaf_hash);
+        Ok(())
+    }
+
+    /// Outputs the number of leaves.
+    pub fn len(&self) -> u64 {
+        (self.leaf_hashes.len() / H::DIGEST_SIZE) as u64
+    }
+
+    /// Outputs whether the tree has no leaves.
+    pub fn is_empty(&self) -> bool {
+        self.leaf_hashes.is_empty()
+    }
+
+    /// Outputs the root of the tree, which for an empty tree is the hash of the empty string.
+    pub fn root(&self) -> Digest {
+        subtree_root::<H>(&self.leaf_hashes)
+    }
+
+    /// Outputs the root of the tree when it had `tree_size` leaves.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::InvalidLength` if `tree_size` is more than the number of leaves.
+    pub fn root_at(&self, tree_size: u64) -> Result<Digest, Error> {
+        Ok(subtree_root::<H>(self.leaves(tree_size)?))
+    }
+
+    /// Outputs the proof that the leaf at `index` is in the tree of `tree_size` leaves.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::InvalidLength` if `index` is not less than `tree_size` or `tree_size` is
+    /// more than the number of leaves.
+    pub fn inclusion_proof(&self, index: u64, tree_size: u64) -> Result<Vec<u8>, Error> {
+        if index >= tree_size {
+            return Err(Error::InvalidLength);
+        }
+        let mut proof = Vec::new();
+        inclusion_path::<H>(index as usize, self.leaves(tree_size)?, &mut proof);
+        Ok(proof)
+    }
+
+    /// Outputs the proof that the tree of `old_size` leaves is a prefix of the tree of `new_size`
+    /// leaves.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::InvalidLength` if `old_size` is zero or more than `new_size`, or if
+    /// `new_size` is more than the number of leaves.
+    pub fn consistency_proof(&self, old_size: u64, new_size: u64) -> Result<Vec<u8>, Error> {
+        if old_size == 0 || old_size > new_size {
+            return Err(Error::InvalidLength);
+        }
+        let mut proof = Vec::new();
+        let leaves = self.leaves(new_size)?;
+        consistency_path::<H>(old_size as usize, leaves, true, &mut proof);
+        Ok(proof)
+    }
+
+    /// Outputs the leaf hashes of the tree when it had `tree_size` leaves.
+    fn leaves(&self, tree_size: u64) -> Result<&[u8], Error> {
+        if tree_size > self.len() {
+            return Err(Error::InvalidLength);
+        }
+        Ok(&self.leaf_hashes[..tree_size as usize * H::DIGEST_SIZE])
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<H: HashFunction> Default for MerkleTree<H> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Outputs the number of leaves in the left subtree of a tree of `n` leaves, for `n` at least 2.
+#[cfg(feature = "alloc")]
+fn split(n: usize) -> usize {
+    1 << (usize::BITS - 1 - (n - 1).leading_zeros())
+}
+
+#[cfg(feature = "alloc")]
+fn subtree_root<H: HashFunction>(leaves: &[u8]) -> Digest {
+    let n = leaves.len() / H::DIGEST_SIZE;
+    match n {
+        0 => Digest::new(H::default()),
+        1 => Digest::from_slice(leaves),
+        _ => {
+            let (left, right) = leaves.split_at(split(n) * H::DIGEST_SIZE);
+            node_hash::<H>(&subtree_root::<H>(left), &subtree_root::<H>(right))
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+fn inclusion_path<H: HashFunction>(index: usize, leaves: &[u8], proof: &mut Vec<u8>) {
+    let n = leaves.len() / H::DIGEST_SIZE;
+    if n <= 1 {
+        return;
+    }
+    let k = split(n);
+    let (left, right) = leaves.split_at(k * H::DIGEST_SIZE);
+    if index < k {
+        inclusion_path::<H>(index, left, proof);
+        proof.extend_from_slice(&subtree_root::<H>(right));
+    } else {
+        inclusion_path::<H>(index - k, right, proof);
+        proof.extend_from_slice(&subtree_root::<H>(left));
+    }
+}
+
+/// Appends SUBPROOF(m, D[n], b) of RFC 6962, where `complete` is b, which is whether the subtree
+/// of `m` leaves is a subtree of the original old tree, whose root the verifier already has.
+#[cfg(feature = "alloc")]
+fn consistency_path<H: HashFunction>(m: usize, leaves: &[u8], complete: bool, proof: &mut Vec<u8>) {
+    let n = leaves.len() / H::DIGEST_SIZE;
+    if m == n {
+        if !complete {
+            proof.extend_from_slice(&subtree_root::<H>(leaves));
+        }
+        return;
+    }
+    let k = split(n);
+    let (left, right) = leaves.split_at(k * H::DIGEST_SIZE);
+    if m <= k {
+        consistency_path::<H>(m, left, complete, proof);
+        proof.extend_from_slice(&subtree_root::<H>(right));
+    } else {
+        consistency_path::<H>(m - k, right, false, proof);
+        proof.extend_from_slice(&subtree_root::<H>(left));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sha2::Sha256;
+    #[cfg(feature = "alloc")]
+    use crate::sha2::Sha512;
+    use crate::test_helpers::*;
+    use std::vec::Vec;
+
+    // The test vectors of the Certificate Transparency reference implementation
+    const ENTRIES: [&str; 8] = [
+        "",
+        "00",
+        "10",
+        "2021",
+        "3031",
+        "40414243",
+        "5051525354555657",
+        "606162636465666768696a6b6c6d6e6f",
+    ];
+
+    const ROOTS: [&str; 9] = [
+        "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855",
+        "6e340b9cffb37a989ca544e6bb780a2c78901d3fb33738768511a30617afa01d",
+        "fac54203e7cc696cf0dfcb42c92a1d9dbaf70ad9e621f4bd8d98662f00e3c125",
+        "aeb6bcfe274b70a14fb067a5e5578264db0fa9b51af5e0ba159158f329e06e77",
+        "d37ee418976dd95753c1c73862b9398fa2a2cf9b4ff0fdfe8b30cd95209614b7",
+        "4e3bbb1f7b478dcfe71fb631631519a3bca12c9aefca1612bfce4c13a86264d4",
+        "76e67dadbcdf1e10e1b74ddc608abd2f98dfb16fbce75277b5232a127f2087ef",
+        "ddb89be403809e325750d3d263cd78929c2942b7942a34b77e122c9594a74c8c",
+        "5dc9da79a70659a9ad559cb701ded9a2ab9d823aad2f4960cfe370eff4604328",
+    ];
+
+    #[cfg(feature = "alloc")]
+    fn tree() -> MerkleTree<Sha256> {
+        let mut tree = MerkleTree::new();
+        for entry in &ENTRIES {
+            tree.push(&h2b(entry));
+        }
+        tree
+    }
+
+    fn proof(hashes: &[&str]) -> Vec<u8> {
+        hashes.iter().flat_map(|hash| h2b(hash)).collect()
+    }
+
+    fn check_inclusion(index: u64, tree_size: u64, expected: &[&str]) {
+        let expected = proof(expected);
+        #[cfg(feature = "alloc")]
+        assert_eq!(expected, tree().inclusion_proof(index, tree_size).unwrap());
+        let leaf_hash = leaf_hash::<Sha256>(&h2b(ENTRIES[index as usize]));
+        let root = h2b(ROOTS[tree_size as usize]);
+        verify_inclusion::<Sha256>(&leaf_hash, index, tree_size, &expected, &root).unwrap();
+    }
+
+    fn check_consistency(old_size: u64, new_size: u64, expected: &[&str]) {
+        let expected = proof(expected);
+        #[cfg(feature = "alloc")]
+        assert_eq!(
+            expected,
+            tree().consistency_proof(old_size, new_size).unwrap()
+        );
+        let old_root = h2b(ROOTS[old_size as usize]);
+        let new_root = h2b(ROOTS[new_size as usize]);
+        verify_consistency::<Sha256>(old_size, new_size, &old_root, &new_root, &expected).unwrap();
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_roots() {
+        let tree = tree();
+        assert_eq!(8, tree.len());
+        for (size, root) in ROOTS.iter().enumerate() {
+            assert_eq!(&h2b(root)[..], &tree.root_at(size as u64).unwrap()[..]);
+        }
+        assert_eq!(&h2b(ROOTS[8])[..], &tree.root()[..]);
+        assert!(MerkleTree::<Sha256>::new().is_empty());
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_out_of_range() {
+        let mut tree = tree();
+        let error = Err(Error::InvalidLength);
+        assert_eq!(Some(Error::InvalidLength), tree.root_at(9).err());
+        assert_eq!(error, tree.inclusion_proof(8, 8));
+        assert_eq!(error, tree.inclusion_proof(0, 9));
+        assert_eq!(error, tree.consistency_proof(0, 8));
+        assert_eq!(error, tree.consistency_proof(7, 6));
+        assert_eq!(error, tree.consistency_proof(8, 9));
+        assert_eq!(Err(Error::InvalidLength), tree.push_leaf_hash(&[0; 31]));
+        assert_eq!(8, tree.len());
+        tree.push_leaf_hash(&leaf_hash::<Sha256>(b"")).unwrap();
+        assert_eq!(9, tree.len());
+        assert_eq!(&tree.root()[..], &tree.root_at(9).unwrap()[..]);
+    }
+
+    #[test]
+    fn test_inclusion_proofs() {
+        check_inclusion(0, 1, &[]);
+        check_inclusion(
+            0,
+            8,
+            &[
+                "96a296d224f285c67bee93c30f8a309157f0daa35dc5b87e410b78630a09cfc7",
+                "5f083f0a1a33ca076a95279832580db3e0ef4584bdff1f54c8a360f50de3031e",
+                "6b47aaf29ee3c2af9af889bc1fb9254dabd31177f16232dd6aab035ca39bf6e4",
+            ],
+        );
+        check_inclusion(
+            5,
+            8,
+            &[
+                "bc1a0643b12e4d2d7c77918f44e0f4f79a838b6cf9ec5b5c283e1f4d88599e6b",
+                "ca854ea128ed050b41b35ffc1b87b8eb2bde461e9e3b5596ece6b9d5975a0ae0",
+                "d37ee418976dd95753c1c73862b9398fa2a2cf9b4ff0fdfe8b30cd95209614b7",
+            ],
+        );
+        check_inclusion(
+            2,
+            3,
+            &["fac54203e7cc696cf0dfcb42c92a1d9dbaf70ad9e621f4bd8d98662f00e3c125"],
+        );
+        check_inclusion(
+            1,
+            5,
+            &[
+                "6e340b9cffb37a989ca544e6bb780a2c78901d3fb33738768511a30617afa01d",
+                "5f083f0a1a33ca076a95279832580db3e0ef4584bdff1f54c8a360f50de3031e",
+                "bc1a0643b12e4d2d7c77918f44e0f4f79a838b6cf9ec5b5c283e1f4d88599e6b",
+            ],
+        );
+    }
+
+    #[test]
+    fn test_consistency_proofs() {
+        check_consistency(1, 1, &[]);
+        check_consistency(
+            1,
+            8,
+            &[
+                "96a296d224f285c67bee93c30f8a309157f0daa35dc5b87e410b78630a09cfc7",
+                "5f083f0a1a33ca076a95279832580db3e0ef4584bdff1f54c8a360f50de3031e",
+                "6b47aaf29ee3c2af9af889bc1fb9254dabd31177f16232dd6aab035ca39bf6e4",
+            ],
+        );
+        check_consistency(
+            6,
+            8,
+            &[
+                "0ebc5d3437fbe2db158b9f126a1d118e308181031d0a949f8dededebc558ef6a",
+                "ca854ea128ed050b41b35ffc1b87b8eb2bde461e9e3b5596ece6b9d5975a0ae0",
+                "d37ee418976dd95753c1c73862b9398fa2a2cf9b4ff0fdfe8b30cd95209614b7",
+            ],
+        );
+        check_consistency(
+            2,
+            5,
+            &[
+                "5f083f0a1a33ca076a95279832580db3e0ef4584bdff1f54c8a360f50de3031e",
+                "bc1a0643b12e4d2d7c77918f44e0f4f79a838b6cf9ec5b5c283e1f4d88599e6b",
+            ],
+        );
+        check_consistency(
+            4,
+            8,
+            &["6b47aaf29ee3c2af9af889bc1fb9254dabd31177f16232dd6aab035ca39bf6e4"],
+        );
+        check_consistency(
+            3,
+            7,
+            &[
+                "0298d122906dcfc10892cb53a73992fc5b9f493ea4c9badb27b791b4127a7fe7",
+                "07506a85fd9dd2f120eb694f86011e5bb4662e5c415a62917033d4a9624487e7",
+                "fac54203e7cc696cf0dfcb42c92a1d9dbaf70ad9e621f4bd8d98662f00e3c125",
+                "837dbb152e9b079010717e84e865da4ebc0fa198a806d59d31bf15accef22d0e",
+            ],
+        );
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_all_sizes() {
+        let mut tree = MerkleTree::<Sha512>::default();
+        for i in 0..33u8 {
+            tree.push(&[i]);
+        }
+        for new_size in 1..=tree.len() {
+            let new_root = tree.root_at(new_size).unwrap();
+            for index in 0..new_size {
+                let proof = tree.inclusion_proof(index, new_size).unwrap();
+                let leaf_hash = leaf_hash::<Sha512>(&[index as u8]);
+                verify_inclusion::<Sha512>(&leaf_hash, index, new_size, &proof, &new_root).unwrap();
+                let old_size = index + 1;
+                let old_root = tree.root_at(old_size).unwrap();
+                let proof = tree.consistency_proof(old_size, new_size).unwrap();
+                verify_consistency::<Sha512>(old_size, new_size, &old_root, &new_root, &proof)
+                    .unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn test_invalid_inclusion_proofs() {
+        let root = &h2b(ROOTS[8]);
+        let leaf_hash = leaf_hash::<Sha256>(&h2b(ENTRIES[5]));
+        let proof = proof(&[
+            "bc1a0643b12e4d2d7c77918f44e0f4f79a838b6cf9ec5b5c283e1f4d88599e6b",
+            "ca854ea128ed050b41b35ffc1b87b8eb2bde461e9e3b5596ece6b9d5975a0ae0",
+            "d37ee418976dd95753c1c73862b9398fa2a2cf9b4ff0fdfe8b30cd95209614b7",
+        ]);
+        let verify = |index, tree_size, proof: &[u8]| {
+            verify_inclusion::<Sha256>(&leaf_hash, index, tree_size, proof, root)
+        };
+        verify(5, 8, &proof).unwrap();
+        assert_eq!(Err(Error::InvalidProof), verify(4, 8, &proof));
+        assert_eq!(Err(Error::InvalidProof), verify(5, 6, &proof));
+        assert_eq!(Err(Error::InvalidProof), verify(8, 8, &proof));
+        assert_eq!(Err(Error::InvalidProof), verify(5, 8, &proof[..64]));
+        assert_eq!(Err(Error::InvalidProof), verify(5, 8, &proof[1..]));
+        let extended = &[&proof[..], root].concat();
+        assert_eq!(Err(Error::InvalidProof), verify(5, 8, extended));
+        let mut corrupted = proof;
+        corrupted[40] ^= 1;
+        assert_eq!(Err(Error::InvalidProof), verify(5, 8, &corrupted));
+        let result = verify_inclusion::<Sha256>(&leaf_hash[1..], 5, 8, &corrupted, root);
+        assert_eq!(Err(Error::InvalidProof), result);
+    }
+
+    #[test]
+    fn test_invalid_consistency_proofs() {
+        let old_root = &h2b(ROOTS[6]);
+        let new_root = &h2b(ROOTS[8]);
+        let proof = proof(&[
+            "0ebc5d3437fbe2db158b9f126a1d118e308181031d0a949f8dededebc558ef6a",
+            "ca854ea128ed050b41b35ffc1b87b8eb2bde461e9e3b5596ece6b9d5975a0ae0",
+            "d37ee418976dd95753c1c73862b9398fa2a2cf9b4ff0fdfe8b30cd95209614b7",
+        ]);
+        let verify = |old_size, new_size, old_root: &[u8], proof: &[u8]| {
+            verify_consistency::<Sha256>(old_size, new_size, old_root, new_root, proof)
+        };
+        verify(6, 8, old_root, &proof).unwrap();
+        assert_eq!(Err(Error::InvalidProof), verify(5, 8, old_root, &proof));
+        assert_eq!(Err(Error::InvalidProof), verify(0, 8, old_root, &proof));
+        assert_eq!(Err(Error::InvalidProof), verify(8, 6, old_root, &proof));
+        assert_eq!(Err(Error::InvalidProof), verify(8, 8, old_root, &[]));
+        assert_eq!(Err(Error::InvalidProof), verify(6, 8, old_root, &[]));
+        assert_eq!(
+            Err(Error::InvalidProof),
+            verify(6, 8, old_root, &proof[32..])
+        );
+        assert_eq!(Err(Error::InvalidProof), verify(6, 8, new_root, &proof));
+        assert_eq!(
+            Err(Error::InvalidProof),
+            verify(6, 8, &old_root[1..], &proof)
+        );
+        let mut corrupted = proof;
+        corrupted[70] ^= 1;
+        assert_eq!(Err(Error::InvalidProof), verify(6, 8, old_root, &corrupted));
+    }
+}
@@ -0,0 +1,97 @@
+//! Module for the mask generation function MGF1 (RFC 8017, Appendix B.2.1), with a hash function
+//! chosen by its `HashFunction` type.
+//!
+//! MGF1 stretches a seed to any length by hashing it with a 4-byte big-endian counter, and is
+//! used by RSA-OAEP and RSA-PSS as well as constructions such as full-domain hashing.
+//!
+//! # Examples
+//!
+//! ```
+//! use crypto_pure::mgf1::{mgf1, mgf1_xor};
+//! use crypto_pure::sha2::Sha256;
+//! let mask = &mut [0; 100];
+//! mgf1::<Sha256>(b"seed", mask);
+//!
+//! let data = &mut [0x42; 100];
+//! mgf1_xor::<Sha256>(b"seed", data);
+//! mgf1_xor::<Sha256>(b"seed", data);
+//! assert_eq!(&[0x42; 100], data);
+//! ```
+use crate::sha2::{Digest, HashFunction};
+
+/// Writes the output of MGF1 with hash function `H` for `seed` into `output`.
+///
+/// # Panics
+///
+/// Panics if `output.len()` is more than 2^32 times the digest size for `H`.
+pub fn mgf1<H: HashFunction>(seed: &[u8], output: &mut [u8]) {
+    for byte in output.iter_mut() {
+        *byte = 0;
+    }
+    mgf1_xor::<H>(seed, output);
+}
+
+/// XORs the output of MGF1 with hash function `H` for `seed` into `output`, which is how masks are
+/// applied.
+///
+/// # Panics
+///
+/// Panics if `output.len()` is more than 2^32 times the digest size for `H`.
+pub fn mgf1_xor<H: HashFunction>(seed: &[u8], output: &mut [u8]) {
+    assert!((output.len() as u64).div_ceil(H::DIGEST_SIZE as u64) <= 1 << 32);
+    for (counter, chunk) in (0_u32..).zip(output.chunks_mut(H::DIGEST_SIZE)) {
+        let mut hash_function = H::default();
+        hash_function.update(seed);
+        hash_function.update(&counter.to_be_bytes());
+        let mask = Digest::new(hash_function);
+        chunk
+            .iter_mut()
+            .zip(mask.iter())
+            .for_each(|(byte, mask)| *byte ^= mask);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "legacy-hashes")]
+    use crate::sha1::InsecureSha1;
+    use crate::sha2::{Sha256, Sha512};
+    use crate::test_helpers::*;
+    use std::vec;
+
+    fn check<H: HashFunction>(seed: &[u8], expected: &str) {
+        let expected = h2b(expected);
+        let output = &mut vec![0xff; expected.len()];
+        mgf1::<H>(seed, output);
+        assert_eq!(&expected, output);
+        mgf1_xor::<H>(seed, output);
+        assert!(output.iter().all(|&byte| byte == 0));
+    }
+
+    // Generated with Python's hashlib
+    #[test]
+    fn test_vectors() {
+        check::<Sha256>(b"", "");
+        check::<Sha256>(
+            b"bar",
+            "382576a7841021cc28fc4c0948753fb8312090cea942ea4c4e735d10dc724b15\
+             5f9f6069f289d61daca0cb814502ef04eae1",
+        );
+        check::<Sha512>(
+            b"seed",
+            "b76f0d507aafecd10f1a1f9893059f9d691de22082c56b9057c38ea555a50614\
+             8fda313e51515d18522c4e70066f8adfc773cde314d480b9521773495e3069ad\
+             24cb16e3eebfe8444aca93a80cfd96b16a5f0ab3d71fb4c3956089cbb89d9288\
+             f2f11ca8",
+        );
+    }
+
+    #[cfg(feature = "legacy-hashes")]
+    #[test]
+    fn test_sha1() {
+        check::<InsecureSha1>(b"foo", "1ac907");
+        check::<InsecureSha1>(b"foo", "1ac9075cd4");
+        check::<InsecureSha1>(b"bar", "bc0c655e01");
+    }
+}
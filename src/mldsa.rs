@@ -0,0 +1,764 @@
+//! Module for ML-DSA (FIPS 204), the post-quantum signature scheme based on the Module-LWE and
+//! Module-SIS problems and standardized from CRYSTALS-Dilithium.
+//!
+//! A signer derives a key pair from a 32-byte seed and signs messages together with a context
+//! string of at most 255 bytes, which binds a signature to a particular use so it can't be
+//! verified under another context. Signing is hedged by default, mixing fresh randomness into the
+//! nonce so that fault attacks and side channels on repeated signatures are harder to exploit; the
+//! deterministic variant gives the same signature for the same message every time. ML-DSA-44,
+//! ML-DSA-65 and ML-DSA-87 target security comparable to AES-128, AES-192 and AES-256.
+//!
+//! # Examples
+//!
+//! ```
+//! use crypto_pure::mldsa::mldsa65::{gen_key_pair, sign, verify};
+//! use crypto_pure::rand::SystemRandom;
+//! let rng = &SystemRandom::new();
+//! let (secret_key, public_key) = gen_key_pair(rng);
+//! let signature = &sign(b"message", b"context", &secret_key, rng)?;
+//! assert!(verify(b"message", b"context", signature, &public_key));
+//! assert!(!verify(b"message", b"other context", signature, &public_key));
+//! # Ok::<(), crypto_pure::error::Error>(())
+//! ```
+use crate::ct;
+use crate::error::Error;
+use crate::secret::SecretKey;
+use crate::sha3::{Shake128, Shake256};
+
+pub mod mldsa44;
+pub mod mldsa65;
+pub mod mldsa87;
+
+/// The length of a seed for deterministic key generation.
+pub const SEED_LEN: usize = 32;
+
+const N: usize = 256;
+const Q: u32 = 8_380_417;
+
+/// The number of dropped bits of t.
+const D: u32 = 13;
+
+/// 1753^BitRev8(i) mod q, the powers of the primitive 512th root of unity used by the NTT.
+const ZETAS: [u32; N] = {
+    let mut zetas = [0; N];
+    let mut i = 0;
+    while i < N {
+        zetas[i] = pow_1753((i as u32).reverse_bits() >> 24);
+        i += 1;
+    }
+    zetas
+};
+
+const fn pow_1753(exponent: u32) -> u32 {
+    let mut result = 1;
+    let mut i = 0;
+    while i < exponent {
+        result = result * 1753 % Q as u64;
+        i += 1;
+    }
+    result as u32
+}
+
+/// A polynomial with coefficients in [0, q).
+type Poly = [u32; N];
+
+/// The parameters of an ML-DSA parameter set other than the dimensions k and l of the matrix A.
+pub(crate) struct Params {
+    pub(crate) eta: u32,
+    pub(crate) tau: usize,
+    pub(crate) lambda: usize,
+    pub(crate) gamma_1: u32,
+    pub(crate) gamma_2: u32,
+    pub(crate) omega: usize,
+}
+
+impl Params {
+    fn beta(&self) -> u32 {
+        self.tau as u32 * self.eta
+    }
+
+    fn eta_bits(&self) -> usize {
+        bit_len(2 * self.eta)
+    }
+
+    fn gamma_1_bits(&self) -> usize {
+        1 + bit_len(self.gamma_1 - 1)
+    }
+
+    fn w_1_bits(&self) -> usize {
+        bit_len((Q - 1) / (2 * self.gamma_2) - 1)
+    }
+}
+
+/// Writes the secret key and the public key derived from a seed, as in FIPS 204, Algorithm 6.
+pub(crate) fn derive_key_pair<const K: usize, const L: usize>(
+    params: &Params,
+    seed: &[u8; SEED_LEN],
+    secret_key: &mut [u8],
+    public_key: &mut [u8],
+) {
+    let mut h = Shake256::default();
+    h.update(seed);
+    h.update(&[K as u8, L as u8]);
+    let mut expanded = SecretKey::new([0; 128]);
+    h.finalize_xof().read(&mut expanded[..]);
+    let (rho, rest) = expanded.split_at(32);
+    let (rho_prime, key) = rest.split_at(64);
+
+    let a_hat = expand_a::<K, L>(rho);
+    let mut s_1 = SecretKey::new([[0; N]; L]);
+    for (r, s_1_r) in s_1.iter_mut().enumerate() {
+        *s_1_r = rej_bounded_poly(rho_prime, r as u16, params.eta);
+    }
+    let mut s_2 = SecretKey::new([[0; N]; K]);
+    for (r, s_2_r) in s_2.iter_mut().enumerate() {
+        *s_2_r = rej_bounded_poly(rho_prime, (L + r) as u16, params.eta);
+    }
+    let mut s_1_hat = SecretKey::new(*s_1);
+    for s_1_r in s_1_hat.iter_mut() {
+        ntt(s_1_r);
+    }
+    let mut t = SecretKey::new(multiply_matrix(&a_hat, &s_1_hat));
+    let mut t_0 = SecretKey::new([[0; N]; K]);
+    let mut t_1 = [[0; N]; K];
+    for i in 0..K {
+        inverse_ntt(&mut t[i]);
+        t[i] = add(&t[i], &s_2[i]);
+        for j in 0..N {
+            let (r_1, r_0) = power_2_round(t[i][j]);
+            t_1[i][j] = r_1;
+            t_0[i][j] = r_0;
+        }
+    }
+
+    let (rho_bytes, t_1_bytes) = public_key.split_at_mut(32);
+    rho_bytes.copy_from_slice(rho);
+    for (t_1_i, chunk) in t_1.iter().zip(t_1_bytes.chunks_mut(32 * 10)) {
+        pack(t_1_i, 10, chunk);
+    }
+
+    let eta_len = 32 * params.eta_bits();
+    let (rho_bytes, rest) = secret_key.split_at_mut(32);
+    let (key_bytes, rest) = rest.split_at_mut(32);
+    let (tr, rest) = rest.split_at_mut(64);
+    let (s_1_bytes, rest) = rest.split_at_mut(L * eta_len);
+    let (s_2_bytes, t_0_bytes) = rest.split_at_mut(K * eta_len);
+    rho_bytes.copy_from_slice(rho);
+    key_bytes.copy_from_slice(key);
+    let mut h = Shake256::default();
+    h.update(public_key);
+    h.finalize_xof().read(tr);
+    for (s_1_r, chunk) in s_1.iter().zip(s_1_bytes.chunks_mut(eta_len)) {
+        pack_centered(s_1_r, params.eta, chunk);
+    }
+    for (s_2_r, chunk) in s_2.iter().zip(s_2_bytes.chunks_mut(eta_len)) {
+        pack_centered(s_2_r, params.eta, chunk);
+    }
+    for (t_0_i, chunk) in t_0.iter().zip(t_0_bytes.chunks_mut(32 * D as usize)) {
+        pack_centered(t_0_i, 1 << (D - 1), chunk);
+    }
+}
+
+/// Writes the signature of a message under a context with 32 bytes of randomness rnd, as in
+/// FIPS 204, Algorithms 2 and 7, after checking the lengths of the secret key and the context.
+pub(crate) fn sign<const K: usize, const L: usize>(
+    params: &Params,
+    message: &[u8],
+    context: &[u8],
+    secret_key: &[u8],
+    rnd: &[u8],
+    signature: &mut [u8],
+) -> Result<(), Error> {
+    let eta_len = 32 * params.eta_bits();
+    if secret_key.len() != 128 + (K + L) * eta_len + K * 32 * D as usize {
+        return Err(Error::InvalidKey);
+    }
+    if context.len() > 255 {
+        return Err(Error::InvalidLength);
+    }
+    let (rho, rest) = secret_key.split_at(32);
+    let (key, rest) = rest.split_at(32);
+    let (tr, rest) = rest.split_at(64);
+    let (s_1_bytes, rest) = rest.split_at(L * eta_len);
+    let (s_2_bytes, t_0_bytes) = rest.split_at(K * eta_len);
+    let mut s_1_hat = SecretKey::new([[0; N]; L]);
+    for (s_1_r, chunk) in s_1_hat.iter_mut().zip(s_1_bytes.chunks(eta_len)) {
+        *s_1_r = unpack_centered(chunk, params.eta);
+        ntt(s_1_r);
+    }
+    let mut s_2_hat = SecretKey::new([[0; N]; K]);
+    for (s_2_r, chunk) in s_2_hat.iter_mut().zip(s_2_bytes.chunks(eta_len)) {
+        *s_2_r = unpack_centered(chunk, params.eta);
+        ntt(s_2_r);
+    }
+    let mut t_0_hat = SecretKey::new([[0; N]; K]);
+    for (t_0_i, chunk) in t_0_hat.iter_mut().zip(t_0_bytes.chunks(32 * D as usize)) {
+        *t_0_i = unpack_centered(chunk, 1 << (D - 1));
+        ntt(t_0_i);
+    }
+    let a_hat = expand_a::<K, L>(rho);
+    let mu = message_representative(tr, context, message);
+    let mut h = Shake256::default();
+    h.update(key);
+    h.update(rnd);
+    h.update(&mu);
+    let mut rho_prime = SecretKey::new([0; 64]);
+    h.finalize_xof().read(&mut rho_prime[..]);
+
+    let beta = params.beta();
+    let mut kappa = 0;
+    loop {
+        let mut y = SecretKey::new([[0; N]; L]);
+        for (r, y_r) in y.iter_mut().enumerate() {
+            *y_r = expand_mask(params, &rho_prime[..], kappa + r as u16);
+        }
+        kappa += L as u16;
+        let mut y_hat = SecretKey::new(*y);
+        for y_r in y_hat.iter_mut() {
+            ntt(y_r);
+        }
+        let mut w = SecretKey::new(multiply_matrix(&a_hat, &y_hat));
+        let mut w_1 = [[0; N]; K];
+        for (w_i, w_1_i) in w.iter_mut().zip(w_1.iter_mut()) {
+            inverse_ntt(w_i);
+            for (w_ij, w_1_ij) in w_i.iter().zip(w_1_i.iter_mut()) {
+                *w_1_ij = decompose(*w_ij, params.gamma_2).0;
+            }
+        }
+        let mut c_tilde = [0; 64];
+        let c_tilde = &mut c_tilde[..params.lambda / 4];
+        commitment_hash(params, &mu, &w_1, c_tilde);
+        let mut c_hat = sample_in_ball(c_tilde, params.tau);
+        ntt(&mut c_hat);
+
+        let mut z = SecretKey::new([[0; N]; L]);
+        for ((z_r, y_r), s_1_r) in z.iter_mut().zip(y.iter()).zip(s_1_hat.iter()) {
+            let mut c_s_1 = pointwise(&c_hat, s_1_r);
+            inverse_ntt(&mut c_s_1);
+            *z_r = add(y_r, &c_s_1);
+        }
+        if exceeds_norm(&z[..], params.gamma_1 - beta) {
+            continue;
+        }
+        let mut r = SecretKey::new([[0; N]; K]);
+        let mut c_t_0 = SecretKey::new([[0; N]; K]);
+        for i in 0..K {
+            let mut c_s_2 = pointwise(&c_hat, &s_2_hat[i]);
+            inverse_ntt(&mut c_s_2);
+            r[i] = sub(&w[i], &c_s_2);
+            c_t_0[i] = pointwise(&c_hat, &t_0_hat[i]);
+            inverse_ntt(&mut c_t_0[i]);
+        }
+        let low_bits_too_large = r
+            .iter()
+            .flatten()
+            .any(|&r_ij| decompose(r_ij, params.gamma_2).1.unsigned_abs() >= params.gamma_2 - beta);
+        if low_bits_too_large || exceeds_norm(&c_t_0[..], params.gamma_2) {
+            continue;
+        }
+        let mut hint = [[false; N]; K];
+        let mut ones = 0;
+        for i in 0..K {
+            let r_plus_c_t_0 = add(&r[i], &c_t_0[i]);
+            for j in 0..N {
+                hint[i][j] = decompose(r_plus_c_t_0[j], params.gamma_2).0
+                    != decompose(r[i][j], params.gamma_2).0;
+                ones += usize::from(hint[i][j]);
+            }
+        }
+        if ones > params.omega {
+            continue;
+        }
+
+        let (c_tilde_bytes, rest) = signature.split_at_mut(c_tilde.len());
+        let z_len = 32 * params.gamma_1_bits();
+        let (z_bytes, hint_bytes) = rest.split_at_mut(L * z_len);
+        c_tilde_bytes.copy_from_slice(c_tilde);
+        for (z_r, chunk) in z.iter().zip(z_bytes.chunks_mut(z_len)) {
+            pack_centered(z_r, params.gamma_1, chunk);
+        }
+        hint_bit_pack(&hint, params.omega, hint_bytes);
+        return Ok(());
+    }
+}
+
+/// Checks whether a signature of a message under a context is valid, as in FIPS 204,
+/// Algorithms 3 and 8. Outputs `false` if the signature or public key has the wrong length.
+pub(crate) fn verify<const K: usize, const L: usize>(
+    params: &Params,
+    message: &[u8],
+    context: &[u8],
+    signature: &[u8],
+    public_key: &[u8],
+) -> bool {
+    let z_len = 32 * params.gamma_1_bits();
+    let c_tilde_len = params.lambda / 4;
+    if public_key.len() != 32 + K * 32 * 10
+        || context.len() > 255
+        || signature.len() != c_tilde_len + L * z_len + params.omega + K
+    {
+        return false;
+    }
+    let (c_tilde, rest) = signature.split_at(c_tilde_len);
+    let (z_bytes, hint_bytes) = rest.split_at(L * z_len);
+    let hint = match hint_bit_unpack::<K>(hint_bytes, params.omega) {
+        Some(hint) => hint,
+        None => return false,
+    };
+    let mut z_hat = [[0; N]; L];
+    for (z_r, chunk) in z_hat.iter_mut().zip(z_bytes.chunks(z_len)) {
+        *z_r = unpack_centered(chunk, params.gamma_1);
+    }
+    if exceeds_norm(&z_hat[..], params.gamma_1 - params.beta()) {
+        return false;
+    }
+    for z_r in z_hat.iter_mut() {
+        ntt(z_r);
+    }
+
+    let (rho, t_1_bytes) = public_key.split_at(32);
+    let a_hat = expand_a::<K, L>(rho);
+    let mut tr = [0; 64];
+    let mut h = Shake256::default();
+    h.update(public_key);
+    h.finalize_xof().read(&mut tr);
+    let mu = message_representative(&tr, context, message);
+    let mut c_hat = sample_in_ball(c_tilde, params.tau);
+    ntt(&mut c_hat);
+
+    let mut w_1 = multiply_matrix(&a_hat, &z_hat);
+    for ((w_i, chunk), hint_i) in w_1.iter_mut().zip(t_1_bytes.chunks(32 * 10)).zip(&hint) {
+        let mut t_1_i = unpack(chunk, 10);
+        for coefficient in t_1_i.iter_mut() {
+            *coefficient <<= D;
+        }
+        ntt(&mut t_1_i);
+        *w_i = sub(w_i, &pointwise(&c_hat, &t_1_i));
+        inverse_ntt(w_i);
+        for (w_ij, hint_ij) in w_i.iter_mut().zip(hint_i) {
+            *w_ij = use_hint(*hint_ij, *w_ij, params.gamma_2);
+        }
+    }
+    let mut expected = [0; 64];
+    let expected = &mut expected[..c_tilde_len];
+    commitment_hash(params, &mu, &w_1, expected);
+    ct::constant_time_eq(c_tilde, expected)
+}
+
+/// Outputs mu = H(tr || M'), where M' = 0 || |ctx| || ctx || M is the formatted message of
+/// FIPS 204, Algorithm 2.
+fn message_representative(tr: &[u8], context: &[u8], message: &[u8]) -> [u8; 64] {
+    let mut h = Shake256::default();
+    h.update(tr);
+    h.update(&[0, context.len() as u8]);
+    h.update(context);
+    h.update(message);
+    let mut mu = [0; 64];
+    h.finalize_xof().read(&mut mu);
+    mu
+}
+
+/// Writes the commitment hash H(mu || w1Encode(w_1)) into `c_tilde`.
+fn commitment_hash<const K: usize>(
+    params: &Params,
+    mu: &[u8],
+    w_1: &[Poly; K],
+    c_tilde: &mut [u8],
+) {
+    let bits = params.w_1_bits();
+    let mut h = Shake256::default();
+    h.update(mu);
+    for w_1_i in w_1 {
+        let mut encoded = [0; 32 * 6];
+        let encoded = &mut encoded[..32 * bits];
+        pack(w_1_i, bits, encoded);
+        h.update(encoded);
+    }
+    h.finalize_xof().read(c_tilde);
+}
+
+/// Samples the matrix A in the NTT domain from the seed rho, as in FIPS 204, Algorithm 32.
+fn expand_a<const K: usize, const L: usize>(rho: &[u8]) -> [[Poly; L]; K] {
+    let mut a_hat = [[[0; N]; L]; K];
+    for (r, row) in a_hat.iter_mut().enumerate() {
+        for (s, a_rs) in row.iter_mut().enumerate() {
+            *a_rs = rej_ntt_poly(rho, s as u8, r as u8);
+        }
+    }
+    a_hat
+}
+
+/// Samples a uniformly random polynomial in the NTT domain from SHAKE128(rho || s || r), as in
+/// FIPS 204, Algorithm 30.
+fn rej_ntt_poly(rho: &[u8], s: u8, r: u8) -> Poly {
+    let mut g = Shake128::default();
+    g.update(rho);
+    g.update(&[s, r]);
+    let mut reader = g.finalize_xof();
+    let mut a = [0; N];
+    let mut count = 0;
+    let mut block = [0; 168];
+    while count < N {
+        reader.read(&mut block);
+        for c in block.chunks(3) {
+            let d = u32::from(c[0]) | (u32::from(c[1]) << 8) | (u32::from(c[2] & 0x7f) << 16);
+            if d < Q && count < N {
+                a[count] = d;
+                count += 1;
+            }
+        }
+    }
+    a
+}
+
+/// Samples a polynomial with coefficients in [-eta, eta] from SHAKE256(rho' || index), as in
+/// FIPS 204, Algorithm 31.
+fn rej_bounded_poly(rho_prime: &[u8], index: u16, eta: u32) -> Poly {
+    let mut h = Shake256::default();
+    h.update(rho_prime);
+    h.update(&index.to_le_bytes());
+    let mut reader = h.finalize_xof();
+    let mut a = [0; N];
+    let mut count = 0;
+    let mut block = SecretKey::new([0; 136]);
+    while count < N {
+        reader.read(&mut block[..]);
+        for &byte in block.iter() {
+            for half in [u32::from(byte & 0xf), u32::from(byte >> 4)] {
+                // CoefFromHalfByte of FIPS 204, Algorithm 15
+                let coefficient = match eta {
+                    2 if half < 15 => Some(Q + 2 - half % 5),
+                    4 if half < 9 => Some(Q + 4 - half),
+                    _ => None,
+                };
+                if let Some(coefficient) = coefficient {
+                    if count < N {
+                        a[count] = coefficient % Q;
+                        count += 1;
+                    }
+                }
+            }
+        }
+    }
+    a
+}
+
+/// Samples the mask polynomial y_r with coefficients in (-gamma_1, gamma_1] from
+/// SHAKE256(rho'' || kappa + r), as in FIPS 204, Algorithm 34.
+fn expand_mask(params: &Params, rho_prime: &[u8], index: u16) -> Poly {
+    let mut h = Shake256::default();
+    h.update(rho_prime);
+    h.update(&index.to_le_bytes());
+    let mut bytes = SecretKey::new([0; 32 * 20]);
+    let bytes = &mut bytes[..32 * params.gamma_1_bits()];
+    h.finalize_xof().read(bytes);
+    unpack_centered(bytes, params.gamma_1)
+}
+
+/// Samples the challenge polynomial with tau coefficients in {-1, 1} and the rest 0 from
+/// SHAKE256(c_tilde), as in FIPS 204, Algorithm 29.
+fn sample_in_ball(c_tilde: &[u8], tau: usize) -> Poly {
+    let mut h = Shake256::default();
+    h.update(c_tilde);
+    let mut reader = h.finalize_xof();
+    let mut signs = [0; 8];
+    reader.read(&mut signs);
+    let signs = u64::from_le_bytes(signs);
+    let mut c = [0; N];
+    for (k, i) in (N - tau..N).enumerate() {
+        let j = loop {
+            let mut j = [0];
+            reader.read(&mut j);
+            if usize::from(j[0]) <= i {
+                break usize::from(j[0]);
+            }
+        };
+        c[i] = c[j];
+        c[j] = if (signs >> k) & 1 == 1 { Q - 1 } else { 1 };
+    }
+    c
+}
+
+/// Splits r into r_1 2^d + r_0 with r_0 in (-2^(d - 1), 2^(d - 1)], outputting r_1 and r_0 mod q,
+/// as in FIPS 204, Algorithm 35.
+fn power_2_round(r: u32) -> (u32, u32) {
+    let mut r_0 = r & ((1 << D) - 1);
+    if r_0 > 1 << (D - 1) {
+        r_0 = r_0.wrapping_sub(1 << D);
+    }
+    let r_1 = r.wrapping_sub(r_0) >> D;
+    (r_1, r_0.wrapping_add(Q) % Q)
+}
+
+/// Splits r into r_1 (2 gamma_2) + r_0 with r_0 in (-gamma_2, gamma_2], handling the wraparound at
+/// q - 1, as in FIPS 204, Algorithm 36.
+fn decompose(r: u32, gamma_2: u32) -> (u32, i32) {
+    let mut r_0 = (r % (2 * gamma_2)) as i32;
+    if r_0 > gamma_2 as i32 {
+        r_0 -= 2 * gamma_2 as i32;
+    }
+    let r_1 = (r as i32 - r_0) as u32;
+    if r_1 == Q - 1 {
+        (0, r_0 - 1)
+    } else {
+        (r_1 / (2 * gamma_2), r_0)
+    }
+}
+
+/// Outputs the high bits of r adjusted by a hint, as in FIPS 204, Algorithm 40.
+fn use_hint(hint: bool, r: u32, gamma_2: u32) -> u32 {
+    let m = (Q - 1) / (2 * gamma_2);
+    let (r_1, r_0) = decompose(r, gamma_2);
+    if !hint {
+        r_1
+    } else if r_0 > 0 {
+        (r_1 + 1) % m
+    } else {
+        (r_1 + m - 1) % m
+    }
+}
+
+/// Checks whether any coefficient x has |x mod± q| at least `bound`.
+fn exceeds_norm(v: &[Poly], bound: u32) -> bool {
+    v.iter()
+        .flatten()
+        .any(|&x| core::cmp::min(x, Q - x) >= bound)
+}
+
+/// Computes the NTT of a polynomial in place, as in FIPS 204, Algorithm 41.
+fn ntt(w: &mut Poly) {
+    let mut m = 0;
+    let mut len = 128;
+    while len >= 1 {
+        for start in (0..N).step_by(2 * len) {
+            m += 1;
+            for j in start..start + len {
+                let t = mul(ZETAS[m], w[j + len]);
+                w[j + len] = (w[j] + Q - t) % Q;
+                w[j] = (w[j] + t) % Q;
+            }
+        }
+        len /= 2;
+    }
+}
+
+/// Computes the inverse NTT of a polynomial in place, as in FIPS 204, Algorithm 42.
+fn inverse_ntt(w: &mut Poly) {
+    let mut m = N;
+    let mut len = 1;
+    while len < N {
+        for start in (0..N).step_by(2 * len) {
+            m -= 1;
+            let zeta = Q - ZETAS[m];
+            for j in start..start + len {
+                let t = w[j];
+                w[j] = (t + w[j + len]) % Q;
+                w[j + len] = mul(zeta, t + Q - w[j + len]);
+            }
+        }
+        len *= 2;
+    }
+    // 8347681 is 256^-1 mod q.
+    for coefficient in w.iter_mut() {
+        *coefficient = mul(*coefficient, 8_347_681);
+    }
+}
+
+/// Multiplies a matrix by a vector in the NTT domain.
+fn multiply_matrix<const K: usize, const L: usize>(
+    a_hat: &[[Poly; L]; K],
+    v: &[Poly; L],
+) -> [Poly; K] {
+    let mut w = [[0; N]; K];
+    for (w_i, row) in w.iter_mut().zip(a_hat) {
+        for (a_ij, v_j) in row.iter().zip(v) {
+            *w_i = add(w_i, &pointwise(a_ij, v_j));
+        }
+    }
+    w
+}
+
+fn mul(a: u32, b: u32) -> u32 {
+    (u64::from(a) * u64::from(b) % u64::from(Q)) as u32
+}
+
+fn pointwise(f: &Poly, g: &Poly) -> Poly {
+    core::array::from_fn(|i| mul(f[i], g[i]))
+}
+
+fn add(f: &Poly, g: &Poly) -> Poly {
+    core::array::from_fn(|i| (f[i] + g[i]) % Q)
+}
+
+fn sub(f: &Poly, g: &Poly) -> Poly {
+    core::array::from_fn(|i| (f[i] + Q - g[i]) % Q)
+}
+
+fn bit_len(x: u32) -> usize {
+    (32 - x.leading_zeros()) as usize
+}
+
+/// Writes the coefficients of a polynomial in [0, 2^bits) as 32 bits bytes, least significant bits
+/// first, as in FIPS 204, Algorithm 16.
+fn pack(f: &Poly, bits: usize, output: &mut [u8]) {
+    let mut buffer = 0_u64;
+    let mut buffered = 0;
+    let mut bytes = output.iter_mut();
+    for &coefficient in f {
+        buffer |= u64::from(coefficient) << buffered;
+        buffered += bits;
+        while buffered >= 8 {
+            *bytes.next().expect("output too short") = buffer as u8;
+            buffer >>= 8;
+            buffered -= 8;
+        }
+    }
+}
+
+/// Writes the coefficients of a polynomial in [-(b - 1), b] or [-b, b] as b - x, using as many
+/// bits as `output` has room for, as in FIPS 204, Algorithm 17.
+fn pack_centered(f: &Poly, b: u32, output: &mut [u8]) {
+    let shifted = core::array::from_fn(|i| (b + Q - f[i]) % Q);
+    pack(&shifted, output.len() / 32, output);
+}
+
+/// Reads 256 coefficients in [0, 2^bits) from 32 bits bytes, as in FIPS 204, Algorithm 18.
+fn unpack(bytes: &[u8], bits: usize) -> Poly {
+    let mut f = [0; N];
+    let mut buffer = 0_u64;
+    let mut buffered = 0;
+    let mut coefficients = f.iter_mut();
+    for &byte in bytes {
+        buffer |= u64::from(byte) << buffered;
+        buffered += 8;
+        while buffered >= bits {
+            *coefficients.next().expect("input too long") = (buffer & ((1 << bits) - 1)) as u32;
+            buffer >>= bits;
+            buffered -= bits;
+        }
+    }
+    f
+}
+
+/// Reads 256 coefficients packed by `pack_centered`, as in FIPS 204, Algorithm 19.
+fn unpack_centered(bytes: &[u8], b: u32) -> Poly {
+    let mut f = unpack(bytes, bytes.len() / 32);
+    for coefficient in f.iter_mut() {
+        *coefficient = (b + Q - *coefficient) % Q;
+    }
+    f
+}
+
+/// Writes the positions of the ones of a hint, followed by the running count for each
+/// polynomial, as in FIPS 204, Algorithm 20.
+fn hint_bit_pack<const K: usize>(hint: &[[bool; N]; K], omega: usize, output: &mut [u8]) {
+    output.fill(0);
+    let mut index = 0;
+    for (i, hint_i) in hint.iter().enumerate() {
+        for (j, &one) in hint_i.iter().enumerate() {
+            if one {
+                output[index] = j as u8;
+                index += 1;
+            }
+        }
+        output[omega + i] = index as u8;
+    }
+}
+
+/// Reads a hint written by `hint_bit_pack`, or returns `None` if it is not the unique encoding of
+/// a hint with at most omega ones, as in FIPS 204, Algorithm 21.
+fn hint_bit_unpack<const K: usize>(bytes: &[u8], omega: usize) -> Option<[[bool; N]; K]> {
+    let mut hint = [[false; N]; K];
+    let mut index = 0;
+    for (i, hint_i) in hint.iter_mut().enumerate() {
+        let end = usize::from(bytes[omega + i]);
+        if end < index || end > omega {
+            return None;
+        }
+        let first = index;
+        while index < end {
+            if index > first && bytes[index - 1] >= bytes[index] {
+                return None;
+            }
+            hint_i[usize::from(bytes[index])] = true;
+            index += 1;
+        }
+    }
+    if bytes[index..omega].iter().any(|&byte| byte != 0) {
+        return None;
+    }
+    Some(hint)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_zetas() {
+        assert_eq!(1, ZETAS[0]);
+        assert_eq!(4_808_194, ZETAS[1]);
+        assert_eq!(3_765_607, ZETAS[2]);
+        assert_eq!(1_753, ZETAS[128]);
+        assert_eq!(Q - 1, mul(ZETAS[1], ZETAS[1]));
+    }
+
+    #[test]
+    fn test_ntt() {
+        let mut f = [0; N];
+        for (i, coefficient) in f.iter_mut().enumerate() {
+            *coefficient = (i as u32 * 7_919) % Q;
+        }
+        let mut g = f;
+        ntt(&mut g);
+        assert_ne!(f, g);
+        inverse_ntt(&mut g);
+        assert_eq!(f, g);
+
+        // (1 + x) (1 - x) = 1 - x^2
+        let mut a = [0; N];
+        let mut b = [0; N];
+        a[0] = 1;
+        a[1] = 1;
+        b[0] = 1;
+        b[1] = Q - 1;
+        ntt(&mut a);
+        ntt(&mut b);
+        let mut product = pointwise(&a, &b);
+        inverse_ntt(&mut product);
+        let mut expected = [0; N];
+        expected[0] = 1;
+        expected[2] = Q - 1;
+        assert_eq!(expected, product);
+    }
+
+    #[test]
+    fn test_rounding() {
+        assert_eq!((0, 0), power_2_round(0));
+        assert_eq!((0, 4096), power_2_round(4096));
+        assert_eq!((1, Q - 4095), power_2_round(4097));
+        let gamma_2 = (Q - 1) / 32;
+        assert_eq!((0, -1), decompose(Q - 1, gamma_2));
+        assert_eq!((1, -(gamma_2 as i32) + 1), decompose(gamma_2 + 1, gamma_2));
+        assert_eq!(15, use_hint(true, Q - 1, gamma_2));
+        assert_eq!(0, use_hint(false, Q - 1, gamma_2));
+        assert_eq!(15, use_hint(true, 0, gamma_2));
+        assert_eq!(1, use_hint(true, 1, gamma_2));
+    }
+
+    #[test]
+    fn test_hint_bit_pack() {
+        let mut hint = [[false; N]; 2];
+        hint[0][3] = true;
+        hint[0][200] = true;
+        hint[1][7] = true;
+        let mut bytes = [0; 6];
+        hint_bit_pack(&hint, 4, &mut bytes);
+        assert_eq!([3, 200, 7, 0, 2, 3], bytes);
+        assert_eq!(Some(hint), hint_bit_unpack::<2>(&bytes, 4));
+        // Out of order, too many ones, and nonzero padding
+        assert_eq!(None, hint_bit_unpack::<2>(&[200, 3, 7, 0, 2, 3], 4));
+        assert_eq!(None, hint_bit_unpack::<2>(&[3, 200, 7, 0, 2, 5], 4));
+        assert_eq!(None, hint_bit_unpack::<2>(&[3, 200, 7, 1, 2, 3], 4));
+    }
+}
@@ -0,0 +1,218 @@
+//! Module for ML-DSA-87, the parameter set with security comparable to AES-256.
+use super::{Params, Q, SEED_LEN};
+use crate::error::Error;
+use crate::rand::SecureRandom;
+use crate::secret::SecretKey;
+use crate::sig::{self, Signer, Verifier};
+
+/// The length of a public key.
+pub const PUBLIC_KEY_LEN: usize = 2592;
+
+/// The length of a secret key.
+pub const SECRET_KEY_LEN: usize = 4896;
+
+/// The length of a signature.
+pub const SIGNATURE_LEN: usize = 4627;
+
+const K: usize = 8;
+const L: usize = 7;
+
+const PARAMS: Params = Params {
+    eta: 2,
+    tau: 60,
+    lambda: 256,
+    gamma_1: 1 << 19,
+    gamma_2: (Q - 1) / 32,
+    omega: 75,
+};
+
+/// Generates a key pair with a seed drawn from `rng`, outputting the secret key and the public
+/// key.
+pub fn gen_key_pair(rng: &dyn SecureRandom) -> ([u8; SECRET_KEY_LEN], [u8; PUBLIC_KEY_LEN]) {
+    let mut seed = SecretKey::new([0; SEED_LEN]);
+    rng.fill(&mut seed[..]);
+    derive_key_pair(&seed)
+}
+
+/// Derives a key pair from a seed, outputting the secret key and the public key. The seed is a
+/// compact form of the secret key.
+pub fn derive_key_pair(seed: &[u8; SEED_LEN]) -> ([u8; SECRET_KEY_LEN], [u8; PUBLIC_KEY_LEN]) {
+    let mut secret_key = [0; SECRET_KEY_LEN];
+    let mut public_key = [0; PUBLIC_KEY_LEN];
+    super::derive_key_pair::<K, L>(&PARAMS, seed, &mut secret_key, &mut public_key);
+    (secret_key, public_key)
+}
+
+/// Signs a message under a context with hedged randomness drawn from `rng`.
+///
+/// # Errors
+///
+/// Fails with `Error::InvalidKey` if `secret_key.len()` is not equal to `SECRET_KEY_LEN`, or
+/// with `Error::InvalidLength` if `context.len()` is greater than 255.
+pub fn sign(
+    message: &[u8],
+    context: &[u8],
+    secret_key: &[u8],
+    rng: &dyn SecureRandom,
+) -> Result<[u8; SIGNATURE_LEN], Error> {
+    let mut rnd = SecretKey::new([0; 32]);
+    rng.fill(&mut rnd[..]);
+    let mut signature = [0; SIGNATURE_LEN];
+    super::sign::<K, L>(
+        &PARAMS,
+        message,
+        context,
+        secret_key,
+        &rnd[..],
+        &mut signature,
+    )?;
+    Ok(signature)
+}
+
+/// Signs a message under a context deterministically, so signing the same message twice gives
+/// the same signature.
+///
+/// # Errors
+///
+/// Fails like `sign`.
+pub fn sign_deterministic(
+    message: &[u8],
+    context: &[u8],
+    secret_key: &[u8],
+) -> Result<[u8; SIGNATURE_LEN], Error> {
+    let mut signature = [0; SIGNATURE_LEN];
+    super::sign::<K, L>(
+        &PARAMS,
+        message,
+        context,
+        secret_key,
+        &[0; 32],
+        &mut signature,
+    )?;
+    Ok(signature)
+}
+
+/// Verifies whether a message was signed under a context using the given public key.
+///
+/// Outputs `false` if the signature or public key has the wrong length.
+pub fn verify(message: &[u8], context: &[u8], signature: &[u8], public_key: &[u8]) -> bool {
+    super::verify::<K, L>(&PARAMS, message, context, signature, public_key)
+}
+
+/// The ML-DSA-87 parameter set, signing with hedged randomness.
+pub struct MlDsa87;
+
+impl Verifier for MlDsa87 {
+    const MAX_CONTEXT_LEN: usize = 255;
+
+    type PublicKey = [u8];
+
+    fn verify(
+        message: &[u8],
+        context: &[u8],
+        signature: &[u8],
+        public_key: &[u8],
+    ) -> Result<(), Error> {
+        sig::check_lengths::<Self>(context, public_key, PUBLIC_KEY_LEN)?;
+        sig::check_valid(verify(message, context, signature, public_key))
+    }
+}
+
+impl Signer for MlDsa87 {
+    type SecretKey = [u8];
+    type Signature = [u8; SIGNATURE_LEN];
+
+    fn sign(
+        message: &[u8],
+        context: &[u8],
+        secret_key: &[u8],
+        rng: &dyn SecureRandom,
+    ) -> Result<[u8; SIGNATURE_LEN], Error> {
+        sign(message, context, secret_key, rng)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rand::SystemRandom;
+    use crate::sha3::sha3_256;
+    use crate::test_helpers::*;
+
+    #[test]
+    fn test_kat() {
+        let mut seed = [0; SEED_LEN];
+        for (i, byte) in seed.iter_mut().enumerate() {
+            *byte = i as u8;
+        }
+        let (secret_key, public_key) = derive_key_pair(&seed);
+        // Checked against OpenSSL 3.5
+        let expected = "db6218a2eeae53e567d25222055251eab0ba85d1c69aeb8a62253d2096fd96a2";
+        assert_eq!(h2b(expected), sha3_256(&secret_key));
+        let expected = "e6cf50a9c2fa5234f59949ff61f8161db4d629532127f4aefa8bb10811ecfb1e";
+        assert_eq!(h2b(expected), sha3_256(&public_key));
+
+        let signature = sign_deterministic(b"message", b"context", &secret_key).unwrap();
+        let expected = "8bc8906da470106d6a61eca92b359cab2e805975ffbb85e4f468ef0dce891453";
+        assert_eq!(h2b(expected), sha3_256(&signature));
+        assert!(verify(b"message", b"context", &signature, &public_key));
+
+        let rng = &|buffer: &mut [u8]| buffer.fill(0x42);
+        let signature = sign(b"message", b"context", &secret_key, rng).unwrap();
+        let expected = "b0c0912a95806dfc39ace4225f5d0af76a4b9d2b8a34b7043a87fe34a52c244e";
+        assert_eq!(h2b(expected), sha3_256(&signature));
+        assert!(verify(b"message", b"context", &signature, &public_key));
+    }
+
+    #[test]
+    fn test_sign_verify() {
+        let rng = &SystemRandom::new();
+        let (secret_key, public_key) = gen_key_pair(rng);
+        let signature = sign(b"message", b"", &secret_key, rng).unwrap();
+        assert_ne!(signature, sign(b"message", b"", &secret_key, rng).unwrap());
+        assert_eq!(
+            sign_deterministic(b"message", b"", &secret_key).unwrap(),
+            sign_deterministic(b"message", b"", &secret_key).unwrap()
+        );
+        assert!(verify(b"message", b"", &signature, &public_key));
+        assert!(!verify(b"massage", b"", &signature, &public_key));
+        assert!(!verify(b"message", b"context", &signature, &public_key));
+        assert!(!verify(b"message", &[0; 256], &signature, &public_key));
+        assert!(!verify(b"message", b"", &signature[1..], &public_key));
+        assert!(!verify(b"message", b"", &signature, &public_key[1..]));
+        let long_key = &[&public_key[..], &[0]].concat();
+        assert!(!verify(b"message", b"", &signature, long_key));
+        let (_, other_key) = gen_key_pair(rng);
+        assert!(!verify(b"message", b"", &signature, &other_key));
+        for &i in &[
+            0,
+            SIGNATURE_LEN / 2,
+            SIGNATURE_LEN - PARAMS.omega - K,
+            SIGNATURE_LEN - 1,
+        ] {
+            let signature = &mut signature.clone();
+            signature[i] ^= 1;
+            assert!(!verify(b"message", b"", signature, &public_key));
+        }
+    }
+
+    #[test]
+    fn test_invalid_lengths() {
+        let (secret_key, _) = derive_key_pair(&[0; SEED_LEN]);
+        let rng = &SystemRandom::new();
+        let error = Err(Error::InvalidLength);
+        assert_eq!(
+            error,
+            sign(b"message", &[0; 256], &secret_key, rng).map(|_| ())
+        );
+        let signature = sign_deterministic(b"message", &[0; 256], &secret_key);
+        assert_eq!(error, signature.map(|_| ()));
+        let error = Err(Error::InvalidKey);
+        assert_eq!(
+            error,
+            sign(b"message", b"", &secret_key[1..], rng).map(|_| ())
+        );
+        let signature = sign_deterministic(b"message", b"", &secret_key[1..]);
+        assert_eq!(error, signature.map(|_| ()));
+    }
+}
@@ -0,0 +1,533 @@
+//! Module for ML-KEM (FIPS 203), the post-quantum key encapsulation mechanism based on the
+//! Module-LWE problem and standardized from CRYSTALS-Kyber.
+//!
+//! A recipient generates a key pair, and a sender encapsulates a fresh 32-byte shared secret to
+//! the encapsulation key, producing a ciphertext from which only the holder of the decapsulation
+//! key can recover the shared secret. Decapsulation never fails on a well-formed ciphertext: a
+//! tampered one gives a pseudorandom shared secret instead ("implicit rejection"), so the mismatch
+//! only shows when the secret is used. ML-KEM-512, ML-KEM-768 and ML-KEM-1024 target security
+//! comparable to AES-128, AES-192 and AES-256, and ML-KEM-768 is the usual choice.
+//!
+//! # Examples
+//!
+//! ```
+//! use crypto_pure::mlkem::mlkem768::{decapsulate, encapsulate, gen_key_pair};
+//! use crypto_pure::rand::SystemRandom;
+//! let rng = &SystemRandom::new();
+//! let (decapsulation_key, encapsulation_key) = gen_key_pair(rng);
+//! let (ciphertext, sender_secret) = encapsulate(&encapsulation_key, rng)?;
+//! let recipient_secret = decapsulate(&decapsulation_key, &ciphertext)?;
+//! assert_eq!(sender_secret, recipient_secret);
+//! # Ok::<(), crypto_pure::error::Error>(())
+//! ```
+use crate::ct::{self, ConditionallySelectable};
+use crate::error::Error;
+use crate::secret::SecretKey;
+use crate::sha2::HashFunction;
+use crate::sha3::{sha3_256, Sha3_512, Shake128, Shake256};
+
+pub mod mlkem1024;
+pub mod mlkem512;
+pub mod mlkem768;
+
+/// The length of a shared secret.
+pub const SHARED_SECRET_LEN: usize = 32;
+
+/// The length of a seed d || z for deterministic key generation.
+pub const SEED_LEN: usize = 64;
+
+const N: usize = 256;
+const Q: u32 = 3329;
+
+/// 17^BitRev7(i) mod q, the powers of the primitive 256th root of unity used by the NTT.
+const ZETAS: [u16; 128] = {
+    let mut zetas = [0; 128];
+    let mut i = 0;
+    while i < 128 {
+        zetas[i] = pow_17(bit_rev_7(i));
+        i += 1;
+    }
+    zetas
+};
+
+/// 17^(2 BitRev7(i) + 1) mod q, the roots of the quadratic factors for multiplication in the NTT
+/// domain.
+const GAMMAS: [u16; 128] = {
+    let mut gammas = [0; 128];
+    let mut i = 0;
+    while i < 128 {
+        gammas[i] = pow_17(2 * bit_rev_7(i) + 1);
+        i += 1;
+    }
+    gammas
+};
+
+const fn bit_rev_7(i: usize) -> u32 {
+    (i as u32).reverse_bits() >> 25
+}
+
+const fn pow_17(exponent: u32) -> u16 {
+    let mut result = 1;
+    let mut i = 0;
+    while i < exponent {
+        result = result * 17 % Q;
+        i += 1;
+    }
+    result as u16
+}
+
+/// A polynomial with coefficients in [0, q).
+type Poly = [u16; N];
+
+/// The parameters of an ML-KEM parameter set other than the module rank k.
+pub(crate) struct Params {
+    pub(crate) eta_1: usize,
+    pub(crate) d_u: u32,
+    pub(crate) d_v: u32,
+}
+
+const ETA_2: usize = 2;
+
+/// The length of an encoded polynomial with 12-bit coefficients.
+const POLY_LEN: usize = 32 * 12;
+
+/// Writes the decapsulation key and the encapsulation key derived from a seed d || z, as in
+/// FIPS 203, Algorithm 16.
+pub(crate) fn derive_key_pair<const K: usize>(
+    params: &Params,
+    seed: &[u8; SEED_LEN],
+    decapsulation_key: &mut [u8],
+    encapsulation_key: &mut [u8],
+) {
+    let (d, z) = seed.split_at(32);
+    let (dk_pke, rest) = decapsulation_key.split_at_mut(K * POLY_LEN);
+    kpke_gen_key::<K>(params, d, dk_pke, encapsulation_key);
+    let (ek, rest) = rest.split_at_mut(encapsulation_key.len());
+    ek.copy_from_slice(encapsulation_key);
+    rest[..32].copy_from_slice(&sha3_256(encapsulation_key));
+    rest[32..].copy_from_slice(z);
+}
+
+/// Writes the ciphertext encapsulating a shared secret for message m, as in FIPS 203,
+/// Algorithm 17, after checking the encapsulation key as in Section 7.2.
+pub(crate) fn encapsulate<const K: usize>(
+    params: &Params,
+    encapsulation_key: &[u8],
+    m: &[u8],
+    ciphertext: &mut [u8],
+) -> Result<[u8; SHARED_SECRET_LEN], Error> {
+    if encapsulation_key.len() != K * POLY_LEN + 32 {
+        return Err(Error::InvalidKey);
+    }
+    // Every coefficient of t must be below q, so that the key has a unique encoding.
+    for chunk in encapsulation_key[..K * POLY_LEN].chunks(POLY_LEN) {
+        let mut encoded = [0; POLY_LEN];
+        byte_encode(&byte_decode(chunk, 12), 12, &mut encoded);
+        if encoded[..] != *chunk {
+            return Err(Error::InvalidKey);
+        }
+    }
+    let mut g = Sha3_512::default();
+    g.update(m);
+    g.update(&sha3_256(encapsulation_key));
+    let mut k_r = SecretKey::new([0; 64]);
+    g.write_digest(&mut k_r[..]);
+    kpke_encrypt::<K>(params, encapsulation_key, m, &k_r[32..], ciphertext);
+    let mut shared_secret = [0; SHARED_SECRET_LEN];
+    shared_secret.copy_from_slice(&k_r[..32]);
+    Ok(shared_secret)
+}
+
+/// Outputs the shared secret for a ciphertext, as in FIPS 203, Algorithm 18, after checking the
+/// lengths and the hash of the encapsulation key in the decapsulation key as in Section 7.3.
+pub(crate) fn decapsulate<const K: usize>(
+    params: &Params,
+    decapsulation_key: &[u8],
+    ciphertext: &[u8],
+) -> Result<[u8; SHARED_SECRET_LEN], Error> {
+    let ek_len = K * POLY_LEN + 32;
+    let ciphertext_len = 32 * (params.d_u as usize * K + params.d_v as usize);
+    if decapsulation_key.len() != K * POLY_LEN + ek_len + 64 {
+        return Err(Error::InvalidKey);
+    }
+    if ciphertext.len() != ciphertext_len {
+        return Err(Error::InvalidLength);
+    }
+    let (dk_pke, rest) = decapsulation_key.split_at(K * POLY_LEN);
+    let (ek, rest) = rest.split_at(ek_len);
+    let (h, z) = rest.split_at(32);
+    if !ct::constant_time_eq(&sha3_256(ek), h) {
+        return Err(Error::InvalidKey);
+    }
+
+    let m = SecretKey::new(kpke_decrypt::<K>(params, dk_pke, ciphertext));
+    let mut g = Sha3_512::default();
+    g.update(&m[..]);
+    g.update(h);
+    let mut k_r = SecretKey::new([0; 64]);
+    g.write_digest(&mut k_r[..]);
+    let mut j = Shake256::default();
+    j.update(z);
+    j.update(ciphertext);
+    let mut rejection_secret = [0; SHARED_SECRET_LEN];
+    j.finalize_xof().read(&mut rejection_secret);
+
+    let mut reencrypted = [0; 1568];
+    let reencrypted = &mut reencrypted[..ciphertext_len];
+    kpke_encrypt::<K>(params, ek, &m[..], &k_r[32..], reencrypted);
+    let mut shared_secret = [0; SHARED_SECRET_LEN];
+    shared_secret.copy_from_slice(&k_r[..32]);
+    let rejected = !ct::ct_eq(ciphertext, reencrypted);
+    Ok(<[u8; SHARED_SECRET_LEN]>::conditional_select(
+        &shared_secret,
+        &rejection_secret,
+        rejected,
+    ))
+}
+
+/// Writes the K-PKE decryption key and encryption key for a seed d, as in FIPS 203,
+/// Algorithm 13.
+fn kpke_gen_key<const K: usize>(
+    params: &Params,
+    d: &[u8],
+    decryption_key: &mut [u8],
+    encryption_key: &mut [u8],
+) {
+    let mut g = Sha3_512::default();
+    g.update(d);
+    g.update(&[K as u8]);
+    let mut rho_sigma = SecretKey::new([0; 64]);
+    g.write_digest(&mut rho_sigma[..]);
+    let (rho, sigma) = rho_sigma.split_at(32);
+
+    let a_hat = sample_matrix::<K>(rho);
+    let mut s = SecretKey::new([[0; N]; K]);
+    let mut e = SecretKey::new([[0; N]; K]);
+    for (i, s_i) in s.iter_mut().enumerate() {
+        *s_i = sample_poly_cbd(sigma, i as u8, params.eta_1);
+        ntt(s_i);
+    }
+    for (i, e_i) in e.iter_mut().enumerate() {
+        *e_i = sample_poly_cbd(sigma, (K + i) as u8, params.eta_1);
+        ntt(e_i);
+    }
+
+    let (t_bytes, rho_bytes) = encryption_key.split_at_mut(K * POLY_LEN);
+    for (i, chunk) in t_bytes.chunks_mut(POLY_LEN).enumerate() {
+        let mut t_i = e[i];
+        for (a_ij, s_j) in a_hat[i].iter().zip(s.iter()) {
+            t_i = add(&t_i, &multiply_ntts(a_ij, s_j));
+        }
+        byte_encode(&t_i, 12, chunk);
+    }
+    rho_bytes.copy_from_slice(rho);
+    for (s_i, chunk) in s.iter().zip(decryption_key.chunks_mut(POLY_LEN)) {
+        byte_encode(s_i, 12, chunk);
+    }
+}
+
+/// Writes the K-PKE encryption of a 32-byte message with 32 bytes of randomness r, as in
+/// FIPS 203, Algorithm 14.
+fn kpke_encrypt<const K: usize>(
+    params: &Params,
+    encryption_key: &[u8],
+    m: &[u8],
+    r: &[u8],
+    ciphertext: &mut [u8],
+) {
+    let (t_bytes, rho) = encryption_key.split_at(K * POLY_LEN);
+    let mut t_hat = [[0; N]; K];
+    for (t_i, chunk) in t_hat.iter_mut().zip(t_bytes.chunks(POLY_LEN)) {
+        *t_i = byte_decode(chunk, 12);
+    }
+    let a_hat = sample_matrix::<K>(rho);
+    let mut y = SecretKey::new([[0; N]; K]);
+    for (i, y_i) in y.iter_mut().enumerate() {
+        *y_i = sample_poly_cbd(r, i as u8, params.eta_1);
+        ntt(y_i);
+    }
+
+    let (u_bytes, v_bytes) = ciphertext.split_at_mut(32 * params.d_u as usize * K);
+    for (i, chunk) in u_bytes.chunks_mut(32 * params.d_u as usize).enumerate() {
+        // u_i is the product of column i of A with y, since u = A^T y + e_1.
+        let mut u_i = [0; N];
+        for (a_j, y_j) in a_hat.iter().zip(y.iter()) {
+            u_i = add(&u_i, &multiply_ntts(&a_j[i], y_j));
+        }
+        inverse_ntt(&mut u_i);
+        let e_1 = sample_poly_cbd(r, (K + i) as u8, ETA_2);
+        byte_encode(&compress(&add(&u_i, &e_1), params.d_u), params.d_u, chunk);
+    }
+    let mut v = [0; N];
+    for (t_i, y_i) in t_hat.iter().zip(y.iter()) {
+        v = add(&v, &multiply_ntts(t_i, y_i));
+    }
+    inverse_ntt(&mut v);
+    let e_2 = sample_poly_cbd(r, (2 * K) as u8, ETA_2);
+    let mu = decompress(&byte_decode(m, 1), 1);
+    let v = add(&add(&v, &e_2), &mu);
+    byte_encode(&compress(&v, params.d_v), params.d_v, v_bytes);
+}
+
+/// Outputs the K-PKE decryption of a ciphertext, as in FIPS 203, Algorithm 15.
+fn kpke_decrypt<const K: usize>(
+    params: &Params,
+    decryption_key: &[u8],
+    ciphertext: &[u8],
+) -> [u8; 32] {
+    let (u_bytes, v_bytes) = ciphertext.split_at(32 * params.d_u as usize * K);
+    let mut w = [0; N];
+    let u_chunks = u_bytes.chunks(32 * params.d_u as usize);
+    for (u_chunk, s_chunk) in u_chunks.zip(decryption_key.chunks(POLY_LEN)) {
+        let mut u_i = decompress(&byte_decode(u_chunk, params.d_u), params.d_u);
+        ntt(&mut u_i);
+        let s_i = SecretKey::new(byte_decode(s_chunk, 12));
+        w = add(&w, &multiply_ntts(&s_i, &u_i));
+    }
+    inverse_ntt(&mut w);
+    let v = decompress(&byte_decode(v_bytes, params.d_v), params.d_v);
+    let w = SecretKey::new(sub(&v, &w));
+    let mut m = [0; 32];
+    byte_encode(&compress(&w, 1), 1, &mut m);
+    m
+}
+
+/// Samples the matrix A in the NTT domain from the seed rho, as in FIPS 203, Algorithm 13,
+/// lines 3 to 7.
+fn sample_matrix<const K: usize>(rho: &[u8]) -> [[Poly; K]; K] {
+    let mut a_hat = [[[0; N]; K]; K];
+    for (i, row) in a_hat.iter_mut().enumerate() {
+        for (j, a_ij) in row.iter_mut().enumerate() {
+            *a_ij = sample_ntt(rho, j as u8, i as u8);
+        }
+    }
+    a_hat
+}
+
+/// Samples a uniformly random polynomial in the NTT domain from SHAKE128(rho || j || i), as in
+/// FIPS 203, Algorithm 7.
+fn sample_ntt(rho: &[u8], j: u8, i: u8) -> Poly {
+    let mut xof = Shake128::default();
+    xof.update(rho);
+    xof.update(&[j, i]);
+    let mut reader = xof.finalize_xof();
+    let mut a = [0; N];
+    let mut count = 0;
+    let mut block = [0; 168];
+    while count < N {
+        reader.read(&mut block);
+        for c in block.chunks(3) {
+            let d_1 = u16::from(c[0]) | (u16::from(c[1] & 0xf) << 8);
+            let d_2 = u16::from(c[1] >> 4) | (u16::from(c[2]) << 4);
+            for d in [d_1, d_2] {
+                if u32::from(d) < Q && count < N {
+                    a[count] = d;
+                    count += 1;
+                }
+            }
+        }
+    }
+    a
+}
+
+/// Samples a polynomial from the centered binomial distribution with parameter eta, using the
+/// bytes PRF_eta(seed, b) = SHAKE256(seed || b), as in FIPS 203, Algorithm 8.
+fn sample_poly_cbd(seed: &[u8], b: u8, eta: usize) -> Poly {
+    let mut prf = Shake256::default();
+    prf.update(seed);
+    prf.update(&[b]);
+    let mut bytes = SecretKey::new([0; 64 * 3]);
+    let bytes = &mut bytes[..64 * eta];
+    prf.finalize_xof().read(bytes);
+    let bit = |i: usize| u32::from((bytes[i / 8] >> (i % 8)) & 1);
+    let mut f = [0; N];
+    for (i, f_i) in f.iter_mut().enumerate() {
+        let x: u32 = (0..eta).map(|j| bit(2 * i * eta + j)).sum();
+        let y: u32 = (0..eta).map(|j| bit(2 * i * eta + eta + j)).sum();
+        *f_i = ((x + Q - y) % Q) as u16;
+    }
+    f
+}
+
+/// Computes the NTT of a polynomial in place, as in FIPS 203, Algorithm 9.
+fn ntt(f: &mut Poly) {
+    let mut i = 1;
+    let mut len = 128;
+    while len >= 2 {
+        for start in (0..N).step_by(2 * len) {
+            let zeta = u32::from(ZETAS[i]);
+            i += 1;
+            for j in start..start + len {
+                let t = zeta * u32::from(f[j + len]) % Q;
+                f[j + len] = ((u32::from(f[j]) + Q - t) % Q) as u16;
+                f[j] = ((u32::from(f[j]) + t) % Q) as u16;
+            }
+        }
+        len /= 2;
+    }
+}
+
+/// Computes the inverse NTT of a polynomial in place, as in FIPS 203, Algorithm 10.
+fn inverse_ntt(f: &mut Poly) {
+    let mut i = 127;
+    let mut len = 2;
+    while len <= 128 {
+        for start in (0..N).step_by(2 * len) {
+            let zeta = u32::from(ZETAS[i]);
+            i -= 1;
+            for j in start..start + len {
+                let t = u32::from(f[j]);
+                f[j] = ((t + u32::from(f[j + len])) % Q) as u16;
+                f[j + len] = (zeta * (u32::from(f[j + len]) + Q - t) % Q) as u16;
+            }
+        }
+        len *= 2;
+    }
+    // 3303 is 128^-1 mod q.
+    for coefficient in f.iter_mut() {
+        *coefficient = (u32::from(*coefficient) * 3303 % Q) as u16;
+    }
+}
+
+/// Multiplies two polynomials in the NTT domain, as in FIPS 203, Algorithms 11 and 12.
+fn multiply_ntts(f: &Poly, g: &Poly) -> Poly {
+    let mut h = [0; N];
+    for (i, gamma) in GAMMAS.iter().enumerate() {
+        let (a_0, a_1) = (u32::from(f[2 * i]), u32::from(f[2 * i + 1]));
+        let (b_0, b_1) = (u32::from(g[2 * i]), u32::from(g[2 * i + 1]));
+        h[2 * i] = ((a_0 * b_0 + a_1 * b_1 % Q * u32::from(*gamma)) % Q) as u16;
+        h[2 * i + 1] = ((a_0 * b_1 + a_1 * b_0) % Q) as u16;
+    }
+    h
+}
+
+fn add(f: &Poly, g: &Poly) -> Poly {
+    core::array::from_fn(|i| ((u32::from(f[i]) + u32::from(g[i])) % Q) as u16)
+}
+
+fn sub(f: &Poly, g: &Poly) -> Poly {
+    core::array::from_fn(|i| ((u32::from(f[i]) + Q - u32::from(g[i])) % Q) as u16)
+}
+
+/// Maps each coefficient x to round(2^d x / q) mod 2^d. Division by the constant q compiles to a
+/// multiplication, so this runs in constant time.
+fn compress(f: &Poly, d: u32) -> Poly {
+    core::array::from_fn(|i| {
+        let x = u32::from(f[i]);
+        ((((x << d) + Q / 2) / Q) & ((1 << d) - 1)) as u16
+    })
+}
+
+/// Maps each coefficient y to round(q y / 2^d).
+fn decompress(f: &Poly, d: u32) -> Poly {
+    core::array::from_fn(|i| ((u32::from(f[i]) * Q + (1 << (d - 1))) >> d) as u16)
+}
+
+/// Writes the d-bit coefficients of a polynomial as 32 d bytes, least significant bits first, as
+/// in FIPS 203, Algorithm 5.
+fn byte_encode(f: &Poly, d: u32, output: &mut [u8]) {
+    let mut buffer = 0_u32;
+    let mut bits = 0;
+    let mut bytes = output.iter_mut();
+    for &coefficient in f {
+        buffer |= u32::from(coefficient) << bits;
+        bits += d;
+        while bits >= 8 {
+            *bytes.next().expect("output too short") = buffer as u8;
+            buffer >>= 8;
+            bits -= 8;
+        }
+    }
+}
+
+/// Reads 256 d-bit coefficients from 32 d bytes, reducing them modulo q if d is 12, as in
+/// FIPS 203, Algorithm 6.
+fn byte_decode(bytes: &[u8], d: u32) -> Poly {
+    let mut f = [0; N];
+    let mut buffer = 0_u32;
+    let mut bits = 0;
+    let mut coefficients = f.iter_mut();
+    for &byte in bytes {
+        buffer |= u32::from(byte) << bits;
+        bits += 8;
+        while bits >= d {
+            let coefficient = coefficients.next().expect("input too long");
+            *coefficient = (buffer & ((1 << d) - 1)) as u16;
+            if d == 12 {
+                *coefficient = (u32::from(*coefficient) % Q) as u16;
+            }
+            buffer >>= d;
+            bits -= d;
+        }
+    }
+    f
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tables() {
+        assert_eq!([1, 1729, 2580, 3289], ZETAS[..4]);
+        assert_eq!([17, 3312, 2761, 568], GAMMAS[..4]);
+    }
+
+    #[test]
+    fn test_ntt() {
+        let mut f = [0; N];
+        for (i, coefficient) in f.iter_mut().enumerate() {
+            *coefficient = (i * 13 % 3329) as u16;
+        }
+        let mut g = f;
+        ntt(&mut g);
+        inverse_ntt(&mut g);
+        assert_eq!(f[..], g[..]);
+
+        // Multiplying by x in the NTT domain shifts the coefficients, negating the top one.
+        let mut x = [0; N];
+        x[1] = 1;
+        ntt(&mut x);
+        let mut product = f;
+        ntt(&mut product);
+        product = multiply_ntts(&product, &x);
+        inverse_ntt(&mut product);
+        assert_eq!((3329 - u32::from(f[255])) % 3329, u32::from(product[0]));
+        assert_eq!(f[..255], product[1..]);
+    }
+
+    #[test]
+    fn test_compress() {
+        for d in [1, 4, 5, 10, 11] {
+            let f: Poly = core::array::from_fn(|i| (i * 13) as u16);
+            let g = decompress(&compress(&f, d), d);
+            for (x, y) in f.iter().zip(&g) {
+                // The error is at most round(q / 2^(d + 1)) modulo q.
+                let error = (u32::from(*x) + Q - u32::from(*y)) % Q;
+                let error = error.min(Q - error);
+                assert!(error <= (Q + (1 << d)) >> (d + 1), "{} {} {}", d, x, y);
+            }
+        }
+    }
+
+    #[test]
+    fn test_byte_encode() {
+        for d in [1, 4, 5, 10, 11, 12] {
+            let f: Poly = core::array::from_fn(|i| ((i * 2741) % (1 << d).min(3329)) as u16);
+            let mut bytes = [0; POLY_LEN];
+            byte_encode(&f, d, &mut bytes[..32 * d as usize]);
+            assert_eq!(f[..], byte_decode(&bytes[..32 * d as usize], d)[..]);
+        }
+        assert_eq!([0xff, 0x0f, 0, 0], byte_encode_first(&[4095, 0]));
+        // 12-bit coefficients are reduced modulo q.
+        assert_eq!(4095 - 3329, byte_decode(&[0xff; POLY_LEN], 12)[0]);
+    }
+
+    fn byte_encode_first(coefficients: &[u16]) -> [u8; 4] {
+        let mut f = [0; N];
+        f[..coefficients.len()].copy_from_slice(coefficients);
+        let mut bytes = [0; POLY_LEN];
+        byte_encode(&f, 12, &mut bytes);
+        [bytes[0], bytes[1], bytes[2], bytes[3]]
+    }
+}
@@ -0,0 +1,205 @@
+//! Module for ML-KEM-1024, the parameter set with security comparable to AES-256.
+use super::{Params, SEED_LEN, SHARED_SECRET_LEN};
+use crate::error::Error;
+use crate::kem::Kem;
+use crate::rand::SecureRandom;
+use crate::secret::SecretKey;
+
+/// The length of an encapsulation key.
+pub const ENCAPSULATION_KEY_LEN: usize = 1568;
+
+/// The length of a decapsulation key.
+pub const DECAPSULATION_KEY_LEN: usize = 3168;
+
+/// The length of a ciphertext.
+pub const CIPHERTEXT_LEN: usize = 1568;
+
+const K: usize = 4;
+
+const PARAMS: Params = Params {
+    eta_1: 2,
+    d_u: 11,
+    d_v: 5,
+};
+
+/// Generates a key pair with a seed drawn from `rng`, outputting the decapsulation key and the
+/// encapsulation key.
+pub fn gen_key_pair(
+    rng: &dyn SecureRandom,
+) -> ([u8; DECAPSULATION_KEY_LEN], [u8; ENCAPSULATION_KEY_LEN]) {
+    let mut seed = SecretKey::new([0; SEED_LEN]);
+    rng.fill(&mut seed[..]);
+    derive_key_pair(&seed)
+}
+
+/// Derives a key pair from a seed d || z, outputting the decapsulation key and the encapsulation
+/// key. The seed is a compact form of the decapsulation key.
+pub fn derive_key_pair(
+    seed: &[u8; SEED_LEN],
+) -> ([u8; DECAPSULATION_KEY_LEN], [u8; ENCAPSULATION_KEY_LEN]) {
+    let mut decapsulation_key = [0; DECAPSULATION_KEY_LEN];
+    let mut encapsulation_key = [0; ENCAPSULATION_KEY_LEN];
+    super::derive_key_pair::<K>(
+        &PARAMS,
+        seed,
+        &mut decapsulation_key,
+        &mut encapsulation_key,
+    );
+    (decapsulation_key, encapsulation_key)
+}
+
+/// Encapsulates a shared secret to an encapsulation key with randomness drawn from `rng`,
+/// outputting the ciphertext and the shared secret.
+///
+/// # Errors
+///
+/// Fails with `Error::InvalidKey` if the encapsulation key has the wrong length or encodes a
+/// coefficient that is not below q.
+pub fn encapsulate(
+    encapsulation_key: &[u8],
+    rng: &dyn SecureRandom,
+) -> Result<([u8; CIPHERTEXT_LEN], [u8; SHARED_SECRET_LEN]), Error> {
+    let mut m = SecretKey::new([0; 32]);
+    rng.fill(&mut m[..]);
+    let mut ciphertext = [0; CIPHERTEXT_LEN];
+    let shared_secret =
+        super::encapsulate::<K>(&PARAMS, encapsulation_key, &m[..], &mut ciphertext)?;
+    Ok((ciphertext, shared_secret))
+}
+
+/// Decapsulates the shared secret from a ciphertext. A ciphertext that was not produced by
+/// `encapsulate` for the matching encapsulation key gives an unrelated pseudorandom secret
+/// rather than an error.
+///
+/// # Errors
+///
+/// Fails with `Error::InvalidKey` if the decapsulation key has the wrong length or an
+/// inconsistent hash of the encapsulation key, or with `Error::InvalidLength` if the ciphertext
+/// has the wrong length.
+pub fn decapsulate(
+    decapsulation_key: &[u8],
+    ciphertext: &[u8],
+) -> Result<[u8; SHARED_SECRET_LEN], Error> {
+    super::decapsulate::<K>(&PARAMS, decapsulation_key, ciphertext)
+}
+
+/// The ML-KEM-1024 KEM, whose key pairs can be derived from a seed with `derive_key_pair`.
+pub struct MlKem1024;
+
+impl Kem for MlKem1024 {
+    const ENCAPSULATION_KEY_LEN: usize = ENCAPSULATION_KEY_LEN;
+    const CIPHERTEXT_LEN: usize = CIPHERTEXT_LEN;
+    const SHARED_SECRET_LEN: usize = SHARED_SECRET_LEN;
+
+    type DecapsulationKey = [u8; DECAPSULATION_KEY_LEN];
+    type EncapsulationKey = [u8; ENCAPSULATION_KEY_LEN];
+    type Ciphertext = [u8; CIPHERTEXT_LEN];
+    type SharedSecret = [u8; SHARED_SECRET_LEN];
+
+    fn generate(
+        rng: &dyn SecureRandom,
+    ) -> ([u8; DECAPSULATION_KEY_LEN], [u8; ENCAPSULATION_KEY_LEN]) {
+        gen_key_pair(rng)
+    }
+
+    fn encapsulate(
+        encapsulation_key: &[u8],
+        rng: &dyn SecureRandom,
+    ) -> Result<([u8; CIPHERTEXT_LEN], [u8; SHARED_SECRET_LEN]), Error> {
+        encapsulate(encapsulation_key, rng)
+    }
+
+    fn decapsulate(
+        decapsulation_key: &[u8; DECAPSULATION_KEY_LEN],
+        ciphertext: &[u8],
+    ) -> Result<[u8; SHARED_SECRET_LEN], Error> {
+        decapsulate(decapsulation_key, ciphertext)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rand::SystemRandom;
+    use crate::sha3::sha3_256;
+    use crate::test_helpers::*;
+
+    #[test]
+    fn test_kat() {
+        let mut seed = [0; SEED_LEN];
+        for (i, byte) in seed.iter_mut().enumerate() {
+            *byte = i as u8;
+        }
+        let (decapsulation_key, encapsulation_key) = derive_key_pair(&seed);
+        let (ciphertext, shared_secret) =
+            encapsulate(&encapsulation_key, &|buffer: &mut [u8]| buffer.fill(0x42)).unwrap();
+        // Checked against OpenSSL 3.5
+        let expected = "f0db5d938027fcd9bad87847d52c14cf0c4abcf0703b749793f212111ffb303b";
+        assert_eq!(h2b(expected), sha3_256(&decapsulation_key));
+        let expected = "61349e5c131a7e116a0463861d7d18663c5627c38c7147ddaadfd48acd7a4535";
+        assert_eq!(h2b(expected), sha3_256(&encapsulation_key));
+        let expected = "332ad96959499e023f53a96dcb106525deb6a3fbcb674324fe432acca9b3d545";
+        assert_eq!(h2b(expected), sha3_256(&ciphertext));
+        let expected = "dde5bda1124813a06cdcc735df4926b412e014ec3271a3a2fb0340e667183279";
+        assert_eq!(h2b(expected), shared_secret);
+        assert_eq!(
+            shared_secret,
+            decapsulate(&decapsulation_key, &ciphertext).unwrap()
+        );
+
+        // Implicit rejection
+        let ciphertext = &mut ciphertext.clone();
+        ciphertext[0] ^= 1;
+        let expected = "3cf76b5d88b6b780cb21a8724e3cdc32c1a79c23fb59f401f80f13cedb19fd1a";
+        assert_eq!(
+            h2b(expected),
+            decapsulate(&decapsulation_key, ciphertext).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_invalid_keys() {
+        let (decapsulation_key, encapsulation_key) = gen_key_pair(&SystemRandom::new());
+        let rng = &SystemRandom::new();
+        let (ciphertext, _) = encapsulate(&encapsulation_key, rng).unwrap();
+        assert_eq!(
+            Error::InvalidKey,
+            encapsulate(&encapsulation_key[1..], rng).unwrap_err()
+        );
+        let encapsulation_key = &mut encapsulation_key.clone();
+        encapsulation_key[0] = 0xff;
+        encapsulation_key[1] |= 0x0f;
+        assert_eq!(
+            Error::InvalidKey,
+            encapsulate(encapsulation_key, rng).unwrap_err()
+        );
+
+        assert_eq!(
+            Error::InvalidKey,
+            decapsulate(&decapsulation_key[1..], &ciphertext).unwrap_err()
+        );
+        assert_eq!(
+            Error::InvalidLength,
+            decapsulate(&decapsulation_key, &ciphertext[1..]).unwrap_err()
+        );
+        let decapsulation_key = &mut decapsulation_key.clone();
+        decapsulation_key[DECAPSULATION_KEY_LEN - 64] ^= 1;
+        assert_eq!(
+            Error::InvalidKey,
+            decapsulate(decapsulation_key, &ciphertext).unwrap_err()
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let rng = &SystemRandom::new();
+        let (decapsulation_key, encapsulation_key) = gen_key_pair(rng);
+        let (ciphertext, shared_secret) = encapsulate(&encapsulation_key, rng).unwrap();
+        assert_eq!(
+            shared_secret,
+            decapsulate(&decapsulation_key, &ciphertext).unwrap()
+        );
+        let (other_key, _) = gen_key_pair(rng);
+        assert_ne!(shared_secret, decapsulate(&other_key, &ciphertext).unwrap());
+    }
+}
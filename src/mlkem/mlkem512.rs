@@ -0,0 +1,205 @@
+//! Module for ML-KEM-512, the parameter set with security comparable to AES-128.
+use super::{Params, SEED_LEN, SHARED_SECRET_LEN};
+use crate::error::Error;
+use crate::kem::Kem;
+use crate::rand::SecureRandom;
+use crate::secret::SecretKey;
+
+/// The length of an encapsulation key.
+pub const ENCAPSULATION_KEY_LEN: usize = 800;
+
+/// The length of a decapsulation key.
+pub const DECAPSULATION_KEY_LEN: usize = 1632;
+
+/// The length of a ciphertext.
+pub const CIPHERTEXT_LEN: usize = 768;
+
+const K: usize = 2;
+
+const PARAMS: Params = Params {
+    eta_1: 3,
+    d_u: 10,
+    d_v: 4,
+};
+
+/// Generates a key pair with a seed drawn from `rng`, outputting the decapsulation key and the
+/// encapsulation key.
+pub fn gen_key_pair(
+    rng: &dyn SecureRandom,
+) -> ([u8; DECAPSULATION_KEY_LEN], [u8; ENCAPSULATION_KEY_LEN]) {
+    let mut seed = SecretKey::new([0; SEED_LEN]);
+    rng.fill(&mut seed[..]);
+    derive_key_pair(&seed)
+}
+
+/// Derives a key pair from a seed d || z, outputting the decapsulation key and the encapsulation
+/// key. The seed is a compact form of the decapsulation key.
+pub fn derive_key_pair(
+    seed: &[u8; SEED_LEN],
+) -> ([u8; DECAPSULATION_KEY_LEN], [u8; ENCAPSULATION_KEY_LEN]) {
+    let mut decapsulation_key = [0; DECAPSULATION_KEY_LEN];
+    let mut encapsulation_key = [0; ENCAPSULATION_KEY_LEN];
+    super::derive_key_pair::<K>(
+        &PARAMS,
+        seed,
+        &mut decapsulation_key,
+        &mut encapsulation_key,
+    );
+    (decapsulation_key, encapsulation_key)
+}
+
+/// Encapsulates a shared secret to an encapsulation key with randomness drawn from `rng`,
+/// outputting the ciphertext and the shared secret.
+///
+/// # Errors
+///
+/// Fails with `Error::InvalidKey` if the encapsulation key has the wrong length or encodes a
+/// coefficient that is not below q.
+pub fn encapsulate(
+    encapsulation_key: &[u8],
+    rng: &dyn SecureRandom,
+) -> Result<([u8; CIPHERTEXT_LEN], [u8; SHARED_SECRET_LEN]), Error> {
+    let mut m = SecretKey::new([0; 32]);
+    rng.fill(&mut m[..]);
+    let mut ciphertext = [0; CIPHERTEXT_LEN];
+    let shared_secret =
+        super::encapsulate::<K>(&PARAMS, encapsulation_key, &m[..], &mut ciphertext)?;
+    Ok((ciphertext, shared_secret))
+}
+
+/// Decapsulates the shared secret from a ciphertext. A ciphertext that was not produced by
+/// `encapsulate` for the matching encapsulation key gives an unrelated pseudorandom secret
+/// rather than an error.
+///
+/// # Errors
+///
+/// Fails with `Error::InvalidKey` if the decapsulation key has the wrong length or an
+/// inconsistent hash of the encapsulation key, or with `Error::InvalidLength` if the ciphertext
+/// has the wrong length.
+pub fn decapsulate(
+    decapsulation_key: &[u8],
+    ciphertext: &[u8],
+) -> Result<[u8; SHARED_SECRET_LEN], Error> {
+    super::decapsulate::<K>(&PARAMS, decapsulation_key, ciphertext)
+}
+
+/// The ML-KEM-512 KEM, whose key pairs can be derived from a seed with `derive_key_pair`.
+pub struct MlKem512;
+
+impl Kem for MlKem512 {
+    const ENCAPSULATION_KEY_LEN: usize = ENCAPSULATION_KEY_LEN;
+    const CIPHERTEXT_LEN: usize = CIPHERTEXT_LEN;
+    const SHARED_SECRET_LEN: usize = SHARED_SECRET_LEN;
+
+    type DecapsulationKey = [u8; DECAPSULATION_KEY_LEN];
+    type EncapsulationKey = [u8; ENCAPSULATION_KEY_LEN];
+    type Ciphertext = [u8; CIPHERTEXT_LEN];
+    type SharedSecret = [u8; SHARED_SECRET_LEN];
+
+    fn generate(
+        rng: &dyn SecureRandom,
+    ) -> ([u8; DECAPSULATION_KEY_LEN], [u8; ENCAPSULATION_KEY_LEN]) {
+        gen_key_pair(rng)
+    }
+
+    fn encapsulate(
+        encapsulation_key: &[u8],
+        rng: &dyn SecureRandom,
+    ) -> Result<([u8; CIPHERTEXT_LEN], [u8; SHARED_SECRET_LEN]), Error> {
+        encapsulate(encapsulation_key, rng)
+    }
+
+    fn decapsulate(
+        decapsulation_key: &[u8; DECAPSULATION_KEY_LEN],
+        ciphertext: &[u8],
+    ) -> Result<[u8; SHARED_SECRET_LEN], Error> {
+        decapsulate(decapsulation_key, ciphertext)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rand::SystemRandom;
+    use crate::sha3::sha3_256;
+    use crate::test_helpers::*;
+
+    #[test]
+    fn test_kat() {
+        let mut seed = [0; SEED_LEN];
+        for (i, byte) in seed.iter_mut().enumerate() {
+            *byte = i as u8;
+        }
+        let (decapsulation_key, encapsulation_key) = derive_key_pair(&seed);
+        let (ciphertext, shared_secret) =
+            encapsulate(&encapsulation_key, &|buffer: &mut [u8]| buffer.fill(0x42)).unwrap();
+        // Checked against OpenSSL 3.5
+        let expected = "0bd3f5df01098ac9c29d687c7f1bd0588a5573feeef8f1e3b4573fa7f6ab57c8";
+        assert_eq!(h2b(expected), sha3_256(&decapsulation_key));
+        let expected = "82f101ff648063b376e2bb6c5b7455f655a50c2feadade150efa0e0e6f365aea";
+        assert_eq!(h2b(expected), sha3_256(&encapsulation_key));
+        let expected = "a87df500822d8fae370255aa3b71c132756c4804b98b8ae6634a5a4bee685c12";
+        assert_eq!(h2b(expected), sha3_256(&ciphertext));
+        let expected = "f5efd3b124fd64aa955dfccd56085bfa7dd633957a583ef2425c4faf7799d972";
+        assert_eq!(h2b(expected), shared_secret);
+        assert_eq!(
+            shared_secret,
+            decapsulate(&decapsulation_key, &ciphertext).unwrap()
+        );
+
+        // Implicit rejection
+        let ciphertext = &mut ciphertext.clone();
+        ciphertext[0] ^= 1;
+        let expected = "c1d81e9fb7422e549742297d2e03f030c542116cd4bb4cb7668c121d8f8befce";
+        assert_eq!(
+            h2b(expected),
+            decapsulate(&decapsulation_key, ciphertext).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_invalid_keys() {
+        let (decapsulation_key, encapsulation_key) = gen_key_pair(&SystemRandom::new());
+        let rng = &SystemRandom::new();
+        let (ciphertext, _) = encapsulate(&encapsulation_key, rng).unwrap();
+        assert_eq!(
+            Error::InvalidKey,
+            encapsulate(&encapsulation_key[1..], rng).unwrap_err()
+        );
+        let encapsulation_key = &mut encapsulation_key.clone();
+        encapsulation_key[0] = 0xff;
+        encapsulation_key[1] |= 0x0f;
+        assert_eq!(
+            Error::InvalidKey,
+            encapsulate(encapsulation_key, rng).unwrap_err()
+        );
+
+        assert_eq!(
+            Error::InvalidKey,
+            decapsulate(&decapsulation_key[1..], &ciphertext).unwrap_err()
+        );
+        assert_eq!(
+            Error::InvalidLength,
+            decapsulate(&decapsulation_key, &ciphertext[1..]).unwrap_err()
+        );
+        let decapsulation_key = &mut decapsulation_key.clone();
+        decapsulation_key[DECAPSULATION_KEY_LEN - 64] ^= 1;
+        assert_eq!(
+            Error::InvalidKey,
+            decapsulate(decapsulation_key, &ciphertext).unwrap_err()
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let rng = &SystemRandom::new();
+        let (decapsulation_key, encapsulation_key) = gen_key_pair(rng);
+        let (ciphertext, shared_secret) = encapsulate(&encapsulation_key, rng).unwrap();
+        assert_eq!(
+            shared_secret,
+            decapsulate(&decapsulation_key, &ciphertext).unwrap()
+        );
+        let (other_key, _) = gen_key_pair(rng);
+        assert_ne!(shared_secret, decapsulate(&other_key, &ciphertext).unwrap());
+    }
+}
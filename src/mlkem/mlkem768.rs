@@ -0,0 +1,205 @@
+//! Module for ML-KEM-768, the parameter set with security comparable to AES-192.
+use super::{Params, SEED_LEN, SHARED_SECRET_LEN};
+use crate::error::Error;
+use crate::kem::Kem;
+use crate::rand::SecureRandom;
+use crate::secret::SecretKey;
+
+/// The length of an encapsulation key.
+pub const ENCAPSULATION_KEY_LEN: usize = 1184;
+
+/// The length of a decapsulation key.
+pub const DECAPSULATION_KEY_LEN: usize = 2400;
+
+/// The length of a ciphertext.
+pub const CIPHERTEXT_LEN: usize = 1088;
+
+const K: usize = 3;
+
+const PARAMS: Params = Params {
+    eta_1: 2,
+    d_u: 10,
+    d_v: 4,
+};
+
+/// Generates a key pair with a seed drawn from `rng`, outputting the decapsulation key and the
+/// encapsulation key.
+pub fn gen_key_pair(
+    rng: &dyn SecureRandom,
+) -> ([u8; DECAPSULATION_KEY_LEN], [u8; ENCAPSULATION_KEY_LEN]) {
+    let mut seed = SecretKey::new([0; SEED_LEN]);
+    rng.fill(&mut seed[..]);
+    derive_key_pair(&seed)
+}
+
+/// Derives a key pair from a seed d || z, outputting the decapsulation key and the encapsulation
+/// key. The seed is a compact form of the decapsulation key.
+pub fn derive_key_pair(
+    seed: &[u8; SEED_LEN],
+) -> ([u8; DECAPSULATION_KEY_LEN], [u8; ENCAPSULATION_KEY_LEN]) {
+    let mut decapsulation_key = [0; DECAPSULATION_KEY_LEN];
+    let mut encapsulation_key = [0; ENCAPSULATION_KEY_LEN];
+    super::derive_key_pair::<K>(
+        &PARAMS,
+        seed,
+        &mut decapsulation_key,
+        &mut encapsulation_key,
+    );
+    (decapsulation_key, encapsulation_key)
+}
+
+/// Encapsulates a shared secret to an encapsulation key with randomness drawn from `rng`,
+/// outputting the ciphertext and the shared secret.
+///
+/// # Errors
+///
+/// Fails with `Error::InvalidKey` if the encapsulation key has the wrong length or encodes a
+/// coefficient that is not below q.
+pub fn encapsulate(
+    encapsulation_key: &[u8],
+    rng: &dyn SecureRandom,
+) -> Result<([u8; CIPHERTEXT_LEN], [u8; SHARED_SECRET_LEN]), Error> {
+    let mut m = SecretKey::new([0; 32]);
+    rng.fill(&mut m[..]);
+    let mut ciphertext = [0; CIPHERTEXT_LEN];
+    let shared_secret =
+        super::encapsulate::<K>(&PARAMS, encapsulation_key, &m[..], &mut ciphertext)?;
+    Ok((ciphertext, shared_secret))
+}
+
+/// Decapsulates the shared secret from a ciphertext. A ciphertext that was not produced by
+/// `encapsulate` for the matching encapsulation key gives an unrelated pseudorandom secret
+/// rather than an error.
+///
+/// # Errors
+///
+/// Fails with `Error::InvalidKey` if the decapsulation key has the wrong length or an
+/// inconsistent hash of the encapsulation key, or with `Error::InvalidLength` if the ciphertext
+/// has the wrong length.
+pub fn decapsulate(
+    decapsulation_key: &[u8],
+    ciphertext: &[u8],
+) -> Result<[u8; SHARED_SECRET_LEN], Error> {
+    super::decapsulate::<K>(&PARAMS, decapsulation_key, ciphertext)
+}
+
+/// The ML-KEM-768 KEM, whose key pairs can be derived from a seed with `derive_key_pair`.
+pub struct MlKem768;
+
+impl Kem for MlKem768 {
+    const ENCAPSULATION_KEY_LEN: usize = ENCAPSULATION_KEY_LEN;
+    const CIPHERTEXT_LEN: usize = CIPHERTEXT_LEN;
+    const SHARED_SECRET_LEN: usize = SHARED_SECRET_LEN;
+
+    type DecapsulationKey = [u8; DECAPSULATION_KEY_LEN];
+    type EncapsulationKey = [u8; ENCAPSULATION_KEY_LEN];
+    type Ciphertext = [u8; CIPHERTEXT_LEN];
+    type SharedSecret = [u8; SHARED_SECRET_LEN];
+
+    fn generate(
+        rng: &dyn SecureRandom,
+    ) -> ([u8; DECAPSULATION_KEY_LEN], [u8; ENCAPSULATION_KEY_LEN]) {
+        gen_key_pair(rng)
+    }
+
+    fn encapsulate(
+        encapsulation_key: &[u8],
+        rng: &dyn SecureRandom,
+    ) -> Result<([u8; CIPHERTEXT_LEN], [u8; SHARED_SECRET_LEN]), Error> {
+        encapsulate(encapsulation_key, rng)
+    }
+
+    fn decapsulate(
+        decapsulation_key: &[u8; DECAPSULATION_KEY_LEN],
+        ciphertext: &[u8],
+    ) -> Result<[u8; SHARED_SECRET_LEN], Error> {
+        decapsulate(decapsulation_key, ciphertext)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rand::SystemRandom;
+    use crate::sha3::sha3_256;
+    use crate::test_helpers::*;
+
+    #[test]
+    fn test_kat() {
+        let mut seed = [0; SEED_LEN];
+        for (i, byte) in seed.iter_mut().enumerate() {
+            *byte = i as u8;
+        }
+        let (decapsulation_key, encapsulation_key) = derive_key_pair(&seed);
+        let (ciphertext, shared_secret) =
+            encapsulate(&encapsulation_key, &|buffer: &mut [u8]| buffer.fill(0x42)).unwrap();
+        // Checked against OpenSSL 3.5
+        let expected = "1149f17c3c4ac6ab1e3e2d9d8bd0171355ac0fa31bb8855c48ceade874c0864b";
+        assert_eq!(h2b(expected), sha3_256(&decapsulation_key));
+        let expected = "a24e16d8f8f9383a95b77050f4d9fd2f5733eec1d63ef3c23ebf9918173669a7";
+        assert_eq!(h2b(expected), sha3_256(&encapsulation_key));
+        let expected = "e9a0824664dba3f8f3c86ecb43a0c889030947ff01d276d04d46c204b62fc221";
+        assert_eq!(h2b(expected), sha3_256(&ciphertext));
+        let expected = "b83e7f23b33f909715c7a50b0d4b1f6684d53e1f4b9056f803b29f058ccb5566";
+        assert_eq!(h2b(expected), shared_secret);
+        assert_eq!(
+            shared_secret,
+            decapsulate(&decapsulation_key, &ciphertext).unwrap()
+        );
+
+        // Implicit rejection
+        let ciphertext = &mut ciphertext.clone();
+        ciphertext[0] ^= 1;
+        let expected = "3816af13752429d4e8b800fd2c691b3254d09ed953cf287c99453d3d8057b41e";
+        assert_eq!(
+            h2b(expected),
+            decapsulate(&decapsulation_key, ciphertext).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_invalid_keys() {
+        let (decapsulation_key, encapsulation_key) = gen_key_pair(&SystemRandom::new());
+        let rng = &SystemRandom::new();
+        let (ciphertext, _) = encapsulate(&encapsulation_key, rng).unwrap();
+        assert_eq!(
+            Error::InvalidKey,
+            encapsulate(&encapsulation_key[1..], rng).unwrap_err()
+        );
+        let encapsulation_key = &mut encapsulation_key.clone();
+        encapsulation_key[0] = 0xff;
+        encapsulation_key[1] |= 0x0f;
+        assert_eq!(
+            Error::InvalidKey,
+            encapsulate(encapsulation_key, rng).unwrap_err()
+        );
+
+        assert_eq!(
+            Error::InvalidKey,
+            decapsulate(&decapsulation_key[1..], &ciphertext).unwrap_err()
+        );
+        assert_eq!(
+            Error::InvalidLength,
+            decapsulate(&decapsulation_key, &ciphertext[1..]).unwrap_err()
+        );
+        let decapsulation_key = &mut decapsulation_key.clone();
+        decapsulation_key[DECAPSULATION_KEY_LEN - 64] ^= 1;
+        assert_eq!(
+            Error::InvalidKey,
+            decapsulate(decapsulation_key, &ciphertext).unwrap_err()
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let rng = &SystemRandom::new();
+        let (decapsulation_key, encapsulation_key) = gen_key_pair(rng);
+        let (ciphertext, shared_secret) = encapsulate(&encapsulation_key, rng).unwrap();
+        assert_eq!(
+            shared_secret,
+            decapsulate(&decapsulation_key, &ciphertext).unwrap()
+        );
+        let (other_key, _) = gen_key_pair(rng);
+        assert_ne!(shared_secret, decapsulate(&other_key, &ciphertext).unwrap());
+    }
+}
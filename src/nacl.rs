@@ -0,0 +1,13 @@
+//! Module for NaCl-compatible authenticated encryption, producing the same bytes as libsodium's
+//! `crypto_secretbox_easy` and `crypto_box_easy`.
+//!
+//! A sealed box is the 16-byte Poly1305 tag followed by the XSalsa20 ciphertext, which is as long
+//! as the message.
+pub mod box_;
+pub mod secretbox;
+
+/// The length of a tag in bytes.
+pub const MAC_LEN: usize = 16;
+
+/// The length of a nonce in bytes.
+pub const NONCE_LEN: usize = 24;
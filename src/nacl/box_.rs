@@ -0,0 +1,155 @@
+//! Module for NaCl's `crypto_box`, which is XSalsa20-Poly1305 with a key agreed on using
+//! Curve25519 Diffie-Hellman.
+//!
+//! Public keys are computed from secret keys with `curve25519::gen_pk`. When exchanging several
+//! messages with the same party, `precompute` can be used once, and the result passed as the key
+//! to the `secretbox` functions.
+use super::secretbox;
+use crate::curve25519;
+use crate::error::Error;
+use crate::salsa20::hsalsa20;
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+/// Computes the secret key shared by the owners of `secret_key` and the secret key corresponding
+/// to `public_key`, like `crypto_box_beforenm`.
+///
+/// # Errors
+///
+/// Fails with `Error::InvalidKey` if `public_key.len()` or `secret_key.len()` is not equal to 32.
+pub fn precompute(public_key: &[u8], secret_key: &[u8]) -> Result<[u8; 32], Error> {
+    hsalsa20(&curve25519::dh(public_key, secret_key)?, &[0; 16])
+}
+
+/// Encrypts the message following the first `MAC_LEN` bytes of `buffer` in place for the owner
+/// of `public_key` and writes the tag into those bytes, which must be reserved for it.
+///
+/// # Errors
+///
+/// Fails with `Error::InvalidKey` if `public_key.len()` or `secret_key.len()` is not equal to 32,
+/// or with `Error::InvalidLength` if `nonce.len()` is not equal to 24 or `buffer.len()` is less
+/// than `MAC_LEN`.
+pub fn seal_in_place(
+    public_key: &[u8],
+    secret_key: &[u8],
+    nonce: &[u8],
+    buffer: &mut [u8],
+) -> Result<(), Error> {
+    secretbox::seal_in_place(&precompute(public_key, secret_key)?, nonce, buffer)
+}
+
+/// Verifies the tag in the first `MAC_LEN` bytes of `buffer` and decrypts the ciphertext from the
+/// owner of `public_key` after it in place, outputting the message part of `buffer`. On failure,
+/// `buffer` is left unchanged.
+///
+/// # Errors
+///
+/// Fails with `Error::InvalidKey` if `public_key.len()` or `secret_key.len()` is not equal to 32,
+/// with `Error::InvalidLength` if `nonce.len()` is not equal to 24 or `buffer.len()` is less than
+/// `MAC_LEN`, and with `Error::TagMismatch` if verification fails.
+pub fn open_in_place<'a>(
+    public_key: &[u8],
+    secret_key: &[u8],
+    nonce: &[u8],
+    buffer: &'a mut [u8],
+) -> Result<&'a mut [u8], Error> {
+    secretbox::open_in_place(&precompute(public_key, secret_key)?, nonce, buffer)
+}
+
+/// Encrypts a message for the owner of `public_key` and outputs the tag followed by the
+/// ciphertext, like `crypto_box_easy`.
+///
+/// # Errors
+///
+/// Fails with `Error::InvalidKey` if `public_key.len()` or `secret_key.len()` is not equal to 32,
+/// or with `Error::InvalidLength` if `nonce.len()` is not equal to 24.
+///
+/// # Examples
+///
+/// ```
+/// use crypto_pure::curve25519::gen_pk;
+/// use crypto_pure::nacl::box_;
+/// # let alice_secret_key = &[0x42; 32];
+/// # let bob_secret_key = &[0x24; 32];
+/// # let nonce = &[0; 24];
+/// let alice_public_key = &gen_pk(alice_secret_key)?;
+/// let bob_public_key = &gen_pk(bob_secret_key)?;
+/// let sealed = box_::seal(bob_public_key, alice_secret_key, nonce, b"message")?;
+/// let opened = box_::open(alice_public_key, bob_secret_key, nonce, &sealed);
+/// assert_eq!(Ok(b"message".to_vec()), opened);
+/// # Ok::<(), crypto_pure::error::Error>(())
+/// ```
+#[cfg(feature = "alloc")]
+pub fn seal(
+    public_key: &[u8],
+    secret_key: &[u8],
+    nonce: &[u8],
+    message: &[u8],
+) -> Result<Vec<u8>, Error> {
+    secretbox::seal(&precompute(public_key, secret_key)?, nonce, message)
+}
+
+/// Decrypts the output of `seal` from the owner of `public_key`, like `crypto_box_open_easy`.
+///
+/// # Errors
+///
+/// Fails with `Error::InvalidKey` if `public_key.len()` or `secret_key.len()` is not equal to 32,
+/// with `Error::InvalidLength` if `nonce.len()` is not equal to 24 or `sealed.len()` is less than
+/// `MAC_LEN`, and with `Error::TagMismatch` if verification fails.
+#[cfg(feature = "alloc")]
+pub fn open(
+    public_key: &[u8],
+    secret_key: &[u8],
+    nonce: &[u8],
+    sealed: &[u8],
+) -> Result<Vec<u8>, Error> {
+    secretbox::open(&precompute(public_key, secret_key)?, nonce, sealed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::curve25519::gen_pk;
+    use crate::nacl::MAC_LEN;
+    use crate::test_helpers::*;
+
+    #[test]
+    fn test_box() {
+        let alice_secret_key =
+            &h2b("77076d0a7318a57d3c16c17251b26645df4c2f87ebc0992ab177fba51db92c2a");
+        let bob_secret_key =
+            &h2b("5dab087e624a8a4b79e17f8b83800ee66f3bb1292618b6fd1c2f8b27ff88e0eb");
+        let alice_public_key = &gen_pk(alice_secret_key).unwrap();
+        let bob_public_key = &gen_pk(bob_secret_key).unwrap();
+        let expected = h2b("1b27556473e985d462cd51197a9a46c76009549eac6474f206c4ee0844f68389");
+        assert_eq!(
+            &expected[..],
+            &precompute(bob_public_key, alice_secret_key).unwrap()[..]
+        );
+        assert_eq!(
+            &expected[..],
+            &precompute(alice_public_key, bob_secret_key).unwrap()[..]
+        );
+
+        let nonce = &[0; 24];
+        let message = b"hello, bob";
+        let buffer = &mut [&[0; MAC_LEN], &message[..]].concat();
+        seal_in_place(bob_public_key, alice_secret_key, nonce, buffer).unwrap();
+        assert_eq!(
+            h2b("a7a6aeb0a627228ddabf1158817d1a4b6eb54e0b07ef3ea6465e"),
+            *buffer
+        );
+        let bad_buffer = &mut buffer.clone();
+        bad_buffer[MAC_LEN] ^= 1;
+        let result = open_in_place(alice_public_key, bob_secret_key, nonce, bad_buffer);
+        assert_eq!(Some(Error::TagMismatch), result.err());
+        let result = open_in_place(bob_public_key, bob_secret_key, nonce, buffer);
+        assert_eq!(Some(Error::TagMismatch), result.err());
+        let result = open_in_place(&alice_public_key[1..], bob_secret_key, nonce, buffer);
+        assert_eq!(Some(Error::InvalidKey), result.err());
+        let result = open_in_place(alice_public_key, bob_secret_key, &nonce[1..], buffer);
+        assert_eq!(Some(Error::InvalidLength), result.err());
+        let opened = open_in_place(alice_public_key, bob_secret_key, nonce, buffer);
+        assert_eq!(Ok(&message[..]), opened.map(|x| &*x));
+    }
+}
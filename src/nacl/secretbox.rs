@@ -0,0 +1,174 @@
+//! Module for NaCl's `crypto_secretbox`, which is XSalsa20-Poly1305 with a secret key.
+//!
+//! Functions fail with `Error::InvalidKey` if the key is not `KEY_LEN` bytes, with
+//! `Error::InvalidLength` if the nonce is not `NONCE_LEN` bytes or a buffer is too short for the
+//! tag, and openings fail with `Error::TagMismatch` if verification fails.
+use super::MAC_LEN;
+use crate::error::Error;
+use crate::poly1305::{poly1305, Poly1305};
+use crate::salsa20::Stream;
+#[cfg(feature = "alloc")]
+use alloc::{vec, vec::Vec};
+
+/// The length of a key in bytes.
+pub const KEY_LEN: usize = 32;
+
+/// Encrypts a message in place and outputs a tag authenticating it.
+pub fn seal_detached(key: &[u8], nonce: &[u8], buffer: &mut [u8]) -> Result<[u8; 16], Error> {
+    let (mac_key, mut stream) = setup(key, nonce)?;
+    stream.apply_keystream(buffer);
+    poly1305(&mac_key, buffer)
+}
+
+/// Verifies `tag` and decrypts a ciphertext in place. On failure, `buffer` is left unchanged.
+pub fn open_detached(
+    key: &[u8],
+    nonce: &[u8],
+    buffer: &mut [u8],
+    tag: &[u8; 16],
+) -> Result<(), Error> {
+    let (mac_key, mut stream) = setup(key, nonce)?;
+    let mut mac = Poly1305::new(&mac_key)?;
+    mac.update(buffer);
+    mac.verify(tag)?;
+    stream.apply_keystream(buffer);
+    Ok(())
+}
+
+/// Encrypts the message following the first `MAC_LEN` bytes of `buffer` in place and writes the
+/// tag into those bytes, which must be reserved for it.
+pub fn seal_in_place(key: &[u8], nonce: &[u8], buffer: &mut [u8]) -> Result<(), Error> {
+    if buffer.len() < MAC_LEN {
+        return Err(Error::InvalidLength);
+    }
+    let (tag, message) = buffer.split_at_mut(MAC_LEN);
+    tag.copy_from_slice(&seal_detached(key, nonce, message)?);
+    Ok(())
+}
+
+/// Verifies the tag in the first `MAC_LEN` bytes of `buffer` and decrypts the ciphertext after it
+/// in place, outputting the message part of `buffer`. On failure, `buffer` is left unchanged.
+pub fn open_in_place<'a>(
+    key: &[u8],
+    nonce: &[u8],
+    buffer: &'a mut [u8],
+) -> Result<&'a mut [u8], Error> {
+    if buffer.len() < MAC_LEN {
+        return Err(Error::InvalidLength);
+    }
+    let (tag, ciphertext) = buffer.split_at_mut(MAC_LEN);
+    let mut tag_copy = [0; MAC_LEN];
+    tag_copy.copy_from_slice(tag);
+    open_detached(key, nonce, ciphertext, &tag_copy)?;
+    Ok(ciphertext)
+}
+
+/// Encrypts a message and outputs the tag followed by the ciphertext, like
+/// `crypto_secretbox_easy`.
+///
+/// # Examples
+///
+/// ```
+/// use crypto_pure::nacl::secretbox;
+/// # let key = &[0x42; 32];
+/// # let nonce = &[0x24; 24];
+/// let sealed = secretbox::seal(key, nonce, b"message")?;
+/// assert_eq!(Ok(b"message".to_vec()), secretbox::open(key, nonce, &sealed));
+/// # Ok::<(), crypto_pure::error::Error>(())
+/// ```
+#[cfg(feature = "alloc")]
+pub fn seal(key: &[u8], nonce: &[u8], message: &[u8]) -> Result<Vec<u8>, Error> {
+    let mut sealed = vec![0; MAC_LEN + message.len()];
+    sealed[MAC_LEN..].copy_from_slice(message);
+    seal_in_place(key, nonce, &mut sealed)?;
+    Ok(sealed)
+}
+
+/// Decrypts the output of `seal`, like `crypto_secretbox_open_easy`.
+#[cfg(feature = "alloc")]
+pub fn open(key: &[u8], nonce: &[u8], sealed: &[u8]) -> Result<Vec<u8>, Error> {
+    let mut buffer = sealed.to_vec();
+    open_in_place(key, nonce, &mut buffer)?;
+    Ok(buffer.split_off(MAC_LEN))
+}
+
+/// Outputs the Poly1305 key, which is the first 32 bytes of the XSalsa20 stream, along with the
+/// rest of the stream.
+fn setup(key: &[u8], nonce: &[u8]) -> Result<([u8; 32], Stream), Error> {
+    let mut stream = Stream::xsalsa20(key, nonce)?;
+    let mut mac_key = [0; 32];
+    stream.apply_keystream(&mut mac_key);
+    Ok((mac_key, stream))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::*;
+    use std::vec::Vec;
+
+    fn check(key: &[u8], nonce: &[u8], message: &[u8], sealed: &str) {
+        let sealed = &h2b(sealed);
+        let buffer = &mut [&[0; MAC_LEN], message].concat();
+        seal_in_place(key, nonce, buffer).unwrap();
+        assert_eq!(sealed, buffer);
+
+        let opened = open_in_place(key, nonce, buffer);
+        assert_eq!(Ok(message), opened.map(|x| &*x));
+        assert_eq!(&message, &&buffer[MAC_LEN..]);
+        seal_in_place(key, nonce, buffer).unwrap();
+
+        for i in &[0, MAC_LEN - 1, buffer.len() - 1] {
+            buffer[*i] ^= 1;
+            assert_eq!(Err(Error::TagMismatch), open_in_place(key, nonce, buffer));
+            buffer[*i] ^= 1;
+            assert_eq!(sealed, buffer);
+        }
+        assert!(open_in_place(key, nonce, &mut buffer[1..]).is_err());
+    }
+
+    #[test]
+    fn test_secretbox() {
+        let key: &Vec<_> = &(0..32).collect();
+        let nonce: &Vec<_> = &(0..24).collect();
+        check(key, nonce, b"", "28fd82cd7386c5471a24d8ad2a525b6e");
+        let message: &Vec<_> = &(0..100).collect();
+        let sealed = "330bcf3bf0f0d19cda2917bfb96164bd5efe3a4cc3cfa417b335853564824498\
+                      42b454cc983029f80fbb7d8f49dabffea3fad7f66195d32591a4cab2564b856c\
+                      13705aacc7a2ea7884261d86265ca9ca9cc1bd5a41538faa5cbbcb9a863ec6cd\
+                      a68280a8c64b78afc161c4517e66b9f1e13b66e5";
+        check(key, nonce, message, sealed);
+        let result = open_in_place(key, nonce, &mut [0; 15]).err();
+        assert_eq!(Some(Error::InvalidLength), result);
+    }
+
+    #[test]
+    fn test_invalid_lengths() {
+        let key = &[0x42; KEY_LEN];
+        let nonce = &[0x24; 24];
+        let buffer = &mut [0; MAC_LEN + 10];
+        assert_eq!(
+            Err(Error::InvalidKey),
+            seal_in_place(&key[1..], nonce, buffer)
+        );
+        assert_eq!(
+            Err(Error::InvalidLength),
+            seal_in_place(key, &nonce[1..], buffer)
+        );
+        assert_eq!([0; MAC_LEN + 10], *buffer);
+        let result = seal_in_place(key, nonce, &mut [0; MAC_LEN - 1]);
+        assert_eq!(Err(Error::InvalidLength), result);
+
+        seal_in_place(key, nonce, buffer).unwrap();
+        let sealed = *buffer;
+        assert_eq!(
+            Some(Error::InvalidKey),
+            open_in_place(b"", nonce, buffer).err()
+        );
+        assert_eq!(
+            Some(Error::InvalidLength),
+            open_in_place(key, b"", buffer).err()
+        );
+        assert_eq!(sealed, *buffer);
+    }
+}
@@ -0,0 +1,946 @@
+//! Module for the Noise protocol framework (revision 34) with the XX and IK handshake patterns
+//! and the 25519 DH functions.
+//!
+//! A handshake is run by a `HandshakeState` on each side, which alternately writes and reads
+//! handshake messages, with the initiator writing first. The cipher functions are chosen by
+//! their `Aead` type, which must implement `Cipher`, and the hash functions by their
+//! `HashFunction` type, which must implement `Hash`. Once the handshake is finished, the state
+//! is turned into a `TransportState` for encrypting transport messages in both directions.
+//!
+//! In the XX pattern, both parties transmit their static public keys during the handshake. In
+//! the IK pattern, the initiator already knows the responder's static public key, which allows
+//! the first message's payload to be encrypted.
+//!
+//! # Examples
+//!
+//! ```
+//! use crypto_pure::blake2b::Blake2b512;
+//! use crypto_pure::noise::{HandshakeState, Pattern};
+//! use crypto_pure::poly1305::ChaCha20Poly1305;
+//! use crypto_pure::rand::SystemRandom;
+//! # let initiator_key = &[0x42; 32];
+//! # let responder_key = &[0x43; 32];
+//! type State = HandshakeState<ChaCha20Poly1305, Blake2b512>;
+//! let rng = &SystemRandom::new();
+//! let mut initiator = State::new(Pattern::Xx, true, b"prologue", initiator_key, None)?;
+//! let mut responder = State::new(Pattern::Xx, false, b"prologue", responder_key, None)?;
+//! let message = &mut [0; 1024];
+//! let payload = &mut [0; 1024];
+//! while !initiator.is_finished() {
+//!     let len = initiator.write_message(b"", message, rng)?;
+//!     responder.read_message(&message[..len], payload)?;
+//!     if responder.is_finished() {
+//!         break;
+//!     }
+//!     let len = responder.write_message(b"", message, rng)?;
+//!     initiator.read_message(&message[..len], payload)?;
+//! }
+//!
+//! let mut initiator = initiator.into_transport()?;
+//! let mut responder = responder.into_transport()?;
+//! let len = initiator.write_message(b"message", message)?;
+//! let len = responder.read_message(&message[..len], payload)?;
+//! assert_eq!(b"message", &payload[..len]);
+//! # Ok::<(), crypto_pure::error::Error>(())
+//! ```
+use crate::aead::Aead;
+use crate::aes::Aes256;
+use crate::blake2b::Blake2b512;
+use crate::curve25519;
+use crate::error::Error;
+use crate::gcm::Gcm;
+use crate::hkdf::Hkdf;
+use crate::poly1305::ChaCha20Poly1305;
+use crate::rand::SecureRandom;
+use crate::secret::SecretKey;
+use crate::sha2::{HashFunction, Sha256, Sha512, MAX_DIGEST_SIZE};
+use core::marker::PhantomData;
+
+const DH_LEN: usize = 32;
+const KEY_LEN: usize = 32;
+const MAX_MESSAGE_LEN: usize = 65535;
+
+/// An AEAD cipher with 32-byte keys and 12-byte nonces that is a Noise cipher function.
+pub trait Cipher: Aead {
+    /// The name of the cipher function in protocol names.
+    const NAME: &'static str;
+
+    /// Encodes a counter as a nonce.
+    fn nonce(n: u64) -> [u8; 12];
+}
+
+impl Cipher for Gcm<Aes256> {
+    const NAME: &'static str = "AESGCM";
+
+    fn nonce(n: u64) -> [u8; 12] {
+        let mut nonce = [0; 12];
+        nonce[4..].copy_from_slice(&n.to_be_bytes());
+        nonce
+    }
+}
+
+impl Cipher for ChaCha20Poly1305 {
+    const NAME: &'static str = "ChaChaPoly";
+
+    fn nonce(n: u64) -> [u8; 12] {
+        let mut nonce = [0; 12];
+        nonce[4..].copy_from_slice(&n.to_le_bytes());
+        nonce
+    }
+}
+
+/// A hash function that is a Noise hash function.
+pub trait Hash: HashFunction {
+    /// The name of the hash function in protocol names.
+    const NAME: &'static str;
+}
+
+impl Hash for Sha256 {
+    const NAME: &'static str = "SHA256";
+}
+
+impl Hash for Sha512 {
+    const NAME: &'static str = "SHA512";
+}
+
+impl Hash for Blake2b512 {
+    const NAME: &'static str = "BLAKE2b";
+}
+
+/// A handshake pattern.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Pattern {
+    /// The XX pattern, in which static keys are transmitted during the handshake.
+    Xx,
+    /// The IK pattern, in which the initiator knows the responder's static key beforehand.
+    Ik,
+}
+
+#[derive(Clone, Copy)]
+enum Token {
+    E,
+    S,
+    Ee,
+    Es,
+    Se,
+    Ss,
+}
+
+impl Pattern {
+    fn name(self) -> &'static str {
+        match self {
+            Pattern::Xx => "XX",
+            Pattern::Ik => "IK",
+        }
+    }
+
+    fn messages(self) -> &'static [&'static [Token]] {
+        use Token::*;
+        match self {
+            Pattern::Xx => &[&[E], &[E, Ee, S, Es], &[S, Se]],
+            Pattern::Ik => &[&[E, Es, S, Ss], &[E, Ee, Se]],
+        }
+    }
+}
+
+struct CipherState<C> {
+    cipher: Option<C>,
+    n: u64,
+}
+
+impl<C: Cipher> CipherState<C> {
+    fn new(key: &[u8]) -> Result<Self, Error> {
+        Ok(Self {
+            cipher: Some(C::new(key)?),
+            n: 0,
+        })
+    }
+
+    fn overhead(&self) -> usize {
+        if self.cipher.is_some() {
+            C::TAG_LEN
+        } else {
+            0
+        }
+    }
+
+    /// Encrypts `plaintext` into the start of `output`, or copies it if there is no key yet,
+    /// outputting the number of bytes written.
+    fn encrypt_with_ad(
+        &mut self,
+        ad: &[u8],
+        plaintext: &[u8],
+        output: &mut [u8],
+    ) -> Result<usize, Error> {
+        let len = plaintext.len() + self.overhead();
+        if output.len() < len {
+            return Err(Error::InvalidLength);
+        }
+        let (ciphertext, tag) = output[..len].split_at_mut(plaintext.len());
+        match &self.cipher {
+            Some(cipher) => {
+                if self.n == u64::MAX {
+                    return Err(Error::NonceExhausted);
+                }
+                let computed_tag = cipher.encrypt(plaintext, &C::nonce(self.n), ad, ciphertext)?;
+                tag.copy_from_slice(&computed_tag[..C::TAG_LEN]);
+                self.n += 1;
+            }
+            None => ciphertext.copy_from_slice(plaintext),
+        }
+        Ok(len)
+    }
+
+    /// Decrypts `ciphertext` into the start of `output`, or copies it if there is no key yet,
+    /// outputting the number of bytes written.
+    fn decrypt_with_ad(
+        &mut self,
+        ad: &[u8],
+        ciphertext: &[u8],
+        output: &mut [u8],
+    ) -> Result<usize, Error> {
+        let len = ciphertext
+            .len()
+            .checked_sub(self.overhead())
+            .ok_or(Error::InvalidLength)?;
+        if output.len() < len {
+            return Err(Error::InvalidLength);
+        }
+        let (ciphertext, tag) = ciphertext.split_at(len);
+        match &self.cipher {
+            Some(cipher) => {
+                if self.n == u64::MAX {
+                    return Err(Error::NonceExhausted);
+                }
+                cipher.decrypt(ciphertext, &C::nonce(self.n), ad, tag, &mut output[..len])?;
+                self.n += 1;
+            }
+            None => output[..len].copy_from_slice(ciphertext),
+        }
+        Ok(len)
+    }
+
+    fn rekey(&mut self) -> Result<(), Error> {
+        let cipher = self.cipher.as_ref().expect("no key to rekey");
+        let mut key = SecretKey::new([0; KEY_LEN]);
+        cipher.encrypt(&[0; KEY_LEN], &C::nonce(u64::MAX), b"", &mut *key)?;
+        self.cipher = Some(C::new(&*key)?);
+        Ok(())
+    }
+}
+
+struct SymmetricState<C, H> {
+    cipher_state: CipherState<C>,
+    ck: SecretKey<[u8; MAX_DIGEST_SIZE]>,
+    h: [u8; MAX_DIGEST_SIZE],
+    hash: PhantomData<H>,
+}
+
+impl<C: Cipher, H: Hash> SymmetricState<C, H> {
+    /// Initializes the state from the protocol name, which is fed in parts so that it needn't be
+    /// copied into a buffer.
+    fn new(protocol_name: &[&str]) -> Self {
+        let mut h = [0; MAX_DIGEST_SIZE];
+        let len: usize = protocol_name.iter().map(|part| part.len()).sum();
+        if len <= H::DIGEST_SIZE {
+            let mut offset = 0;
+            for part in protocol_name {
+                h[offset..offset + part.len()].copy_from_slice(part.as_bytes());
+                offset += part.len();
+            }
+        } else {
+            let mut hash = H::default();
+            for part in protocol_name {
+                hash.update(part.as_bytes());
+            }
+            hash.write_digest(&mut h[..H::DIGEST_SIZE]);
+        }
+        Self {
+            cipher_state: CipherState { cipher: None, n: 0 },
+            ck: SecretKey::new(h),
+            h,
+            hash: PhantomData,
+        }
+    }
+
+    /// Derives two outputs from the chaining key and `ikm` with HKDF.
+    fn hkdf(&self, ikm: &[u8]) -> SecretKey<[u8; 2 * MAX_DIGEST_SIZE]> {
+        let mut okm = SecretKey::new([0; 2 * MAX_DIGEST_SIZE]);
+        Hkdf::<H>::extract(&self.ck[..H::DIGEST_SIZE], ikm)
+            .expand(b"", &mut okm[..2 * H::DIGEST_SIZE])
+            .expect("output too long");
+        okm
+    }
+
+    fn mix_key(&mut self, ikm: &[u8]) -> Result<(), Error> {
+        let okm = self.hkdf(ikm);
+        self.ck[..H::DIGEST_SIZE].copy_from_slice(&okm[..H::DIGEST_SIZE]);
+        self.cipher_state = CipherState::new(&okm[H::DIGEST_SIZE..H::DIGEST_SIZE + KEY_LEN])?;
+        Ok(())
+    }
+
+    fn mix_hash(&mut self, data: &[u8]) {
+        let mut hash = H::default();
+        hash.update(&self.h[..H::DIGEST_SIZE]);
+        hash.update(data);
+        hash.write_digest(&mut self.h[..H::DIGEST_SIZE]);
+    }
+
+    fn encrypt_and_hash(&mut self, plaintext: &[u8], output: &mut [u8]) -> Result<usize, Error> {
+        let len =
+            self.cipher_state
+                .encrypt_with_ad(&self.h[..H::DIGEST_SIZE], plaintext, output)?;
+        self.mix_hash(&output[..len]);
+        Ok(len)
+    }
+
+    fn decrypt_and_hash(&mut self, ciphertext: &[u8], output: &mut [u8]) -> Result<usize, Error> {
+        let len =
+            self.cipher_state
+                .decrypt_with_ad(&self.h[..H::DIGEST_SIZE], ciphertext, output)?;
+        self.mix_hash(ciphertext);
+        Ok(len)
+    }
+
+    fn split(&self) -> Result<(CipherState<C>, CipherState<C>), Error> {
+        let okm = self.hkdf(b"");
+        Ok((
+            CipherState::new(&okm[..KEY_LEN])?,
+            CipherState::new(&okm[H::DIGEST_SIZE..H::DIGEST_SIZE + KEY_LEN])?,
+        ))
+    }
+}
+
+/// The state of one party during a handshake.
+pub struct HandshakeState<C, H> {
+    symmetric_state: SymmetricState<C, H>,
+    pattern: Pattern,
+    initiator: bool,
+    message: usize,
+    s: SecretKey<[u8; DH_LEN]>,
+    e: Option<SecretKey<[u8; DH_LEN]>>,
+    rs: Option<[u8; DH_LEN]>,
+    re: Option<[u8; DH_LEN]>,
+}
+
+impl<C: Cipher, H: Hash> HandshakeState<C, H> {
+    /// Initializes a handshake with the given pattern and role, and the party's static secret
+    /// key. Both parties must use the same prologue, or the handshake will fail.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::InvalidKey` if `remote_static_key` is not given exactly when the
+    /// pattern requires it to be known beforehand, which is only for the initiator in the IK
+    /// pattern.
+    pub fn new(
+        pattern: Pattern,
+        initiator: bool,
+        prologue: &[u8],
+        static_key: &[u8; DH_LEN],
+        remote_static_key: Option<&[u8; DH_LEN]>,
+    ) -> Result<Self, Error> {
+        if (pattern == Pattern::Ik && initiator) != remote_static_key.is_some() {
+            return Err(Error::InvalidKey);
+        }
+        let mut symmetric_state =
+            SymmetricState::new(&["Noise_", pattern.name(), "_25519_", C::NAME, "_", H::NAME]);
+        symmetric_state.mix_hash(prologue);
+        let s = SecretKey::new(*static_key);
+        if pattern == Pattern::Ik {
+            // The responder's static key is the pre-message.
+            match remote_static_key {
+                Some(rs) => symmetric_state.mix_hash(rs),
+                None => {
+                    symmetric_state.mix_hash(&curve25519::gen_pk(&*s).expect("key is not 32 bytes"))
+                }
+            }
+        }
+        Ok(Self {
+            symmetric_state,
+            pattern,
+            initiator,
+            message: 0,
+            s,
+            e: None,
+            rs: remote_static_key.copied(),
+            re: None,
+        })
+    }
+
+    /// Writes the next handshake message with `payload` into `message`, outputting its length.
+    /// The ephemeral key is drawn from `rng`.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::InvalidState` if it is not this party's turn to write, with
+    /// `Error::InvalidLength` if `message` is too short or the message would be longer than
+    /// 65535 bytes, or with `Error::InvalidKey` if the remote party's key is invalid.
+    pub fn write_message(
+        &mut self,
+        payload: &[u8],
+        message: &mut [u8],
+        rng: &dyn SecureRandom,
+    ) -> Result<usize, Error> {
+        if self.is_finished() || !self.is_writing() {
+            return Err(Error::InvalidState);
+        }
+        let len = self.message_len(payload.len());
+        if message.len() < len || len > MAX_MESSAGE_LEN {
+            return Err(Error::InvalidLength);
+        }
+        let mut offset = 0;
+        for &token in self.pattern.messages()[self.message] {
+            match token {
+                Token::E => {
+                    let e = self.e.get_or_insert_with(|| {
+                        let mut e = SecretKey::new([0; DH_LEN]);
+                        rng.fill(&mut *e);
+                        e
+                    });
+                    let public_key = curve25519::gen_pk(&**e)?;
+                    message[offset..offset + DH_LEN].copy_from_slice(&public_key);
+                    self.symmetric_state.mix_hash(&public_key);
+                    offset += DH_LEN;
+                }
+                Token::S => {
+                    let public_key = curve25519::gen_pk(&*self.s)?;
+                    offset += self
+                        .symmetric_state
+                        .encrypt_and_hash(&public_key, &mut message[offset..])?;
+                }
+                _ => self.mix_dh(token)?,
+            }
+        }
+        offset += self
+            .symmetric_state
+            .encrypt_and_hash(payload, &mut message[offset..])?;
+        self.message += 1;
+        Ok(offset)
+    }
+
+    /// Reads the next handshake message and writes its payload into `payload`, outputting the
+    /// payload's length. If reading fails, the handshake must be abandoned.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::InvalidState` if it is not this party's turn to read, with
+    /// `Error::InvalidLength` if `message` is too short or longer than 65535 bytes, or if
+    /// `payload` is too short, with `Error::InvalidKey` if a key in the message is invalid, or
+    /// with `Error::TagMismatch` if decryption fails.
+    pub fn read_message(&mut self, message: &[u8], payload: &mut [u8]) -> Result<usize, Error> {
+        if self.is_finished() || self.is_writing() {
+            return Err(Error::InvalidState);
+        }
+        let overhead = self.message_len(0);
+        if message.len() < overhead
+            || message.len() > MAX_MESSAGE_LEN
+            || payload.len() < message.len() - overhead
+        {
+            return Err(Error::InvalidLength);
+        }
+        let mut message = message;
+        for &token in self.pattern.messages()[self.message] {
+            match token {
+                Token::E => {
+                    let (public_key, rest) = message.split_at(DH_LEN);
+                    let mut re = [0; DH_LEN];
+                    re.copy_from_slice(public_key);
+                    self.symmetric_state.mix_hash(&re);
+                    self.re = Some(re);
+                    message = rest;
+                }
+                Token::S => {
+                    let len = DH_LEN + self.symmetric_state.cipher_state.overhead();
+                    let (ciphertext, rest) = message.split_at(len);
+                    let mut rs = [0; DH_LEN];
+                    self.symmetric_state.decrypt_and_hash(ciphertext, &mut rs)?;
+                    self.rs = Some(rs);
+                    message = rest;
+                }
+                _ => self.mix_dh(token)?,
+            }
+        }
+        let len = self.symmetric_state.decrypt_and_hash(message, payload)?;
+        self.message += 1;
+        Ok(len)
+    }
+
+    /// Returns whether all handshake messages have been written or read.
+    pub fn is_finished(&self) -> bool {
+        self.message == self.pattern.messages().len()
+    }
+
+    /// Returns the remote party's static public key, once it is known.
+    pub fn remote_static_key(&self) -> Option<&[u8; DH_LEN]> {
+        self.rs.as_ref()
+    }
+
+    /// Returns the handshake hash, which uniquely identifies the handshake once it is finished
+    /// and can be used for channel binding.
+    pub fn handshake_hash(&self) -> &[u8] {
+        &self.symmetric_state.h[..H::DIGEST_SIZE]
+    }
+
+    /// Finishes the handshake, outputting the state for transport messages.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::InvalidState` if the handshake is not finished, or with
+    /// `Error::InvalidKey` if `C` does not accept the derived 32-byte keys.
+    pub fn into_transport(self) -> Result<TransportState<C>, Error> {
+        if !self.is_finished() {
+            return Err(Error::InvalidState);
+        }
+        let (initiator_to_responder, responder_to_initiator) = self.symmetric_state.split()?;
+        if self.initiator {
+            Ok(TransportState {
+                sending: initiator_to_responder,
+                receiving: responder_to_initiator,
+            })
+        } else {
+            Ok(TransportState {
+                sending: responder_to_initiator,
+                receiving: initiator_to_responder,
+            })
+        }
+    }
+
+    fn is_writing(&self) -> bool {
+        self.message.is_multiple_of(2) == self.initiator
+    }
+
+    /// Computes the length of the next message with a payload of `payload_len` bytes.
+    fn message_len(&self, payload_len: usize) -> usize {
+        let mut has_key = self.symmetric_state.cipher_state.cipher.is_some();
+        let mut len = 0;
+        for token in self.pattern.messages()[self.message] {
+            match token {
+                Token::E => len += DH_LEN,
+                Token::S => len += DH_LEN + if has_key { C::TAG_LEN } else { 0 },
+                _ => has_key = true,
+            }
+        }
+        len + payload_len + if has_key { C::TAG_LEN } else { 0 }
+    }
+
+    fn mix_dh(&mut self, token: Token) -> Result<(), Error> {
+        let (secret_key, public_key) = match (token, self.initiator) {
+            (Token::Ee, _) => (self.e.as_ref(), self.re.as_ref()),
+            (Token::Es, true) | (Token::Se, false) => (self.e.as_ref(), self.rs.as_ref()),
+            (Token::Es, false) | (Token::Se, true) => (Some(&self.s), self.re.as_ref()),
+            (Token::Ss, _) => (Some(&self.s), self.rs.as_ref()),
+            _ => unreachable!(),
+        };
+        let secret_key = secret_key.expect("missing secret key");
+        let public_key = public_key.expect("missing public key");
+        let shared_secret = SecretKey::new(curve25519::x25519(&**secret_key, public_key)?);
+        self.symmetric_state.mix_key(&*shared_secret)
+    }
+}
+
+/// The state of one party after a handshake, for encrypting transport messages.
+pub struct TransportState<C> {
+    sending: CipherState<C>,
+    receiving: CipherState<C>,
+}
+
+impl<C: Cipher> TransportState<C> {
+    /// Encrypts `payload` into `message`, outputting the message's length, which is
+    /// `payload.len() + C::TAG_LEN`.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::InvalidLength` if `message` is too short or the message would be
+    /// longer than 65535 bytes, or with `Error::NonceExhausted` if 2^64 - 1 messages have been
+    /// written.
+    pub fn write_message(&mut self, payload: &[u8], message: &mut [u8]) -> Result<usize, Error> {
+        if payload.len() + C::TAG_LEN > MAX_MESSAGE_LEN {
+            return Err(Error::InvalidLength);
+        }
+        self.sending.encrypt_with_ad(b"", payload, message)
+    }
+
+    /// Decrypts `message` into `payload`, outputting the payload's length. Messages must be read
+    /// in the order they were written, and a message that fails to decrypt doesn't count.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::InvalidLength` if `message` is too short or longer than 65535 bytes,
+    /// or if `payload` is too short, with `Error::NonceExhausted` if 2^64 - 1 messages have been
+    /// read, or with `Error::TagMismatch` if decryption fails.
+    pub fn read_message(&mut self, message: &[u8], payload: &mut [u8]) -> Result<usize, Error> {
+        if message.len() > MAX_MESSAGE_LEN {
+            return Err(Error::InvalidLength);
+        }
+        self.receiving.decrypt_with_ad(b"", message, payload)
+    }
+
+    /// Updates the key for writing messages, which the other party must match by calling
+    /// `rekey_receiving` at the same point.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::InvalidKey` if `C` does not accept the derived 32-byte key.
+    pub fn rekey_sending(&mut self) -> Result<(), Error> {
+        self.sending.rekey()
+    }
+
+    /// Updates the key for reading messages.
+    ///
+    /// # Errors
+    ///
+    /// Fails like `rekey_sending`.
+    pub fn rekey_receiving(&mut self) -> Result<(), Error> {
+        self.receiving.rekey()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rand::SystemRandom;
+    use crate::test_helpers::*;
+
+    const PROLOGUE: &[u8] = b"prologue";
+
+    struct Vector {
+        messages: &'static [&'static str],
+        handshake_hash: &'static str,
+        transport: [&'static str; 2],
+        rekeyed: &'static str,
+    }
+
+    fn key(start: u8) -> [u8; 32] {
+        let mut key = [0; 32];
+        for (i, byte) in key.iter_mut().enumerate() {
+            *byte = start + i as u8;
+        }
+        key
+    }
+
+    fn states<C: Cipher, H: Hash>(
+        pattern: Pattern,
+    ) -> (HandshakeState<C, H>, HandshakeState<C, H>) {
+        let responder_public_key = curve25519::gen_pk(&key(65)).unwrap();
+        let remote_static_key = match pattern {
+            Pattern::Xx => None,
+            Pattern::Ik => Some(&responder_public_key),
+        };
+        let initiator =
+            HandshakeState::new(pattern, true, PROLOGUE, &key(1), remote_static_key).unwrap();
+        let responder = HandshakeState::new(pattern, false, PROLOGUE, &key(65), None).unwrap();
+        (initiator, responder)
+    }
+
+    fn handshake<C: Cipher, H: Hash>(
+        initiator: &mut HandshakeState<C, H>,
+        responder: &mut HandshakeState<C, H>,
+    ) {
+        let rng = &SystemRandom::new();
+        let message = &mut [0; 256];
+        let payload = &mut [0; 256];
+        let (mut writer, mut reader) = (initiator, responder);
+        while !writer.is_finished() {
+            let len = writer.write_message(b"payload", message, rng).unwrap();
+            let len = reader.read_message(&message[..len], payload).unwrap();
+            assert_eq!(b"payload", &payload[..len]);
+            core::mem::swap(&mut writer, &mut reader);
+        }
+        assert!(reader.is_finished());
+    }
+
+    fn check<C: Cipher, H: Hash>(pattern: Pattern, vector: &Vector) {
+        let (mut initiator, mut responder) = states::<C, H>(pattern);
+        initiator.e = Some(SecretKey::new(key(33)));
+        responder.e = Some(SecretKey::new(key(97)));
+        let rng = &|_: &mut [u8]| unreachable!();
+        let message = &mut [0; 256];
+        let payload = &mut [0; 256];
+        for (i, expected) in vector.messages.iter().enumerate() {
+            let (writer, reader) = if i % 2 == 0 {
+                (&mut initiator, &mut responder)
+            } else {
+                (&mut responder, &mut initiator)
+            };
+            let expected_payload = &[&b"payload "[..], &[b'0' + i as u8]].concat();
+            let len = writer
+                .write_message(expected_payload, message, rng)
+                .unwrap();
+            assert_eq!(&h2b(expected)[..], &message[..len]);
+            let len = reader.read_message(&message[..len], payload).unwrap();
+            assert_eq!(&expected_payload[..], &payload[..len]);
+        }
+        assert_eq!(
+            Some(&curve25519::gen_pk(&key(1)).unwrap()),
+            responder.remote_static_key()
+        );
+        assert_eq!(
+            Some(&curve25519::gen_pk(&key(65)).unwrap()),
+            initiator.remote_static_key()
+        );
+        assert_eq!(&h2b(vector.handshake_hash)[..], initiator.handshake_hash());
+        assert_eq!(&h2b(vector.handshake_hash)[..], responder.handshake_hash());
+
+        let mut initiator = initiator.into_transport().unwrap();
+        let mut responder = responder.into_transport().unwrap();
+        let len = initiator.write_message(b"transport 0", message).unwrap();
+        assert_eq!(&h2b(vector.transport[0])[..], &message[..len]);
+        let len = responder.read_message(&message[..len], payload).unwrap();
+        assert_eq!(b"transport 0", &payload[..len]);
+        let len = responder.write_message(b"transport 1", message).unwrap();
+        assert_eq!(&h2b(vector.transport[1])[..], &message[..len]);
+        let len = initiator.read_message(&message[..len], payload).unwrap();
+        assert_eq!(b"transport 1", &payload[..len]);
+
+        initiator.rekey_sending().unwrap();
+        responder.rekey_receiving().unwrap();
+        let len = initiator.write_message(b"rekeyed", message).unwrap();
+        assert_eq!(&h2b(vector.rekeyed)[..], &message[..len]);
+        let len = responder.read_message(&message[..len], payload).unwrap();
+        assert_eq!(b"rekeyed", &payload[..len]);
+    }
+
+    #[test]
+    fn test_vectors() {
+        // Generated with a Python implementation of the specification, using the primitives of
+        // the cryptography package and hashlib
+        check::<Gcm<Aes256>, Sha512>(
+            Pattern::Xx,
+            &Vector {
+                messages: &[
+                    "5869aff450549732cbaaed5e5df9b30a6da31cb0e5742bad5ad4a1a768f1a67b\
+                     7061796c6f61642030",
+                    "244fe3b963e899dd295baffce248d3530f3a9a7479ba063002680ebfe7adad49\
+                     ed63521be27dc8486bf17d3e66b7b700c7400507588ce01d8762f0225faaac61\
+                     b3d61e1338795bea342aff5af5bcc423d99b054ea636c5b00bd850a9790a465b\
+                     b41e313890708c81b8",
+                    "cd14b9a9c04a05f0c3214f5d5665d6c39d9c24496958af42df80a40255ee76ff\
+                     aa9f2ca118a28b79527689ef1d11cb5d685e9b89d4510efebbc570e5ca596f8f\
+                     5493d1d2063fd862d2",
+                ],
+                handshake_hash: "6997e264d8faa1f7323e5a84f19d4533ecbbc6d3f6ab898ad45dce4f74efe7fc\
+                                 129223e708b2399fa8a624496edee4dc2044e7bcf6efdc424f4dc431f574507a",
+                transport: [
+                    "f78d76fb893394762e60c593c5717aad5c6d510df38081278236d5",
+                    "b89531735a8190ca16ca95432a097835eaa79137a048d78bcee276",
+                ],
+                rekeyed: "f389b7347b73e40a0ed70d698d97f8f597ab58b2cdff30",
+            },
+        );
+        check::<ChaCha20Poly1305, Blake2b512>(
+            Pattern::Xx,
+            &Vector {
+                messages: &[
+                    "5869aff450549732cbaaed5e5df9b30a6da31cb0e5742bad5ad4a1a768f1a67b\
+                     7061796c6f61642030",
+                    "244fe3b963e899dd295baffce248d3530f3a9a7479ba063002680ebfe7adad49\
+                     c8135a92e0830925f2df8199a059bf3248e2cbfff8e0655f87ee503323392011\
+                     44ce649be33b7d892230f30214ff557a5f110329fafa5932c3e8b008fbbb55fa\
+                     f5c34adc0c384a0832",
+                    "0d77afa79fe222d90f0b687a5d3c8b0d4b3a2f4b63db0a380dbeecc429a22eb4\
+                     12894ef65eb43876fcf4edda07664c3d243f8f2a6ca035d665425ef312964695\
+                     b08b4d2ac0e0ceeea3",
+                ],
+                handshake_hash: "68b1e0c622eca7f2056dc89d78d8ac4eb2764aa114ba51f6fb2b879fedb6f90c\
+                                 fbc4e60f321897238a6d2e273a753d8593250ee3189b9b415cf7556e7d35881f",
+                transport: [
+                    "790bfe158f7597e15ca87cb96136cf6df10a7c19768396d99f194e",
+                    "74bc2a202afd4dff40283db2452ac869a067c8582367fca9a4e45d",
+                ],
+                rekeyed: "3a5ad48149c1cd57fb0362a14d492dca9c6c73740cb554",
+            },
+        );
+        check::<Gcm<Aes256>, Sha512>(
+            Pattern::Ik,
+            &Vector {
+                messages: &[
+                    "5869aff450549732cbaaed5e5df9b30a6da31cb0e5742bad5ad4a1a768f1a67b\
+                     effe61d6f54911beea133241f276cde62c14ed66dd4da37b68f0095de19e16d5\
+                     28d0276e9d97dd6619d5406c09a86c6fe4dda3057fbe5a004bde55ed95799277\
+                     bd53bf50361e00b758",
+                    "244fe3b963e899dd295baffce248d3530f3a9a7479ba063002680ebfe7adad49\
+                     2965144c557400e728445ac55096a6c758826e490226635ebe",
+                ],
+                handshake_hash: "7b22c3a06d1297ca6d433825b3907417f99622e6d605ae5f04e54db13ade1551\
+                                 2c47d2bb2da2271294048bd90caa2920448f681ec94ba6060df14f85e8e532c4",
+                transport: [
+                    "f8ff706bb465ff40b828164eb7f6221d72be569a9f1ffd1dd9b2b0",
+                    "aeb79bb1c8a4ad582e1fbf4656418886dfeefa6e0da71603b57e2d",
+                ],
+                rekeyed: "c84da6a7db8db3d7754f7c3016bd7afa3a5e79f0d51ea1",
+            },
+        );
+        check::<ChaCha20Poly1305, Blake2b512>(
+            Pattern::Ik,
+            &Vector {
+                messages: &[
+                    "5869aff450549732cbaaed5e5df9b30a6da31cb0e5742bad5ad4a1a768f1a67b\
+                     8821c0e2b0064fa2097add2b2cb2e7ab8288762667e63505ff4e33b48d517401\
+                     0c90d4909337cdf10db426d5e0a7a758af04ed235854e0f46b7cfc730e6650e1\
+                     aea5233a8465c28153",
+                    "244fe3b963e899dd295baffce248d3530f3a9a7479ba063002680ebfe7adad49\
+                     16314ff1e7068a30f90ea68c0818b3c02fbb54e62228c6ccf7",
+                ],
+                handshake_hash: "167fcfe979d4782ea10a537f1ae56f9e54dd727ef9d954239589d791ce97b29a\
+                                 87948e69f0dca557f8954a4428958bf6990973356725188c7248e1abf9bac154",
+                transport: [
+                    "cdee88c441696939c479e75cbbf0fdc7422c30791d0c02ad9258a6",
+                    "8013154381b330ecb31778ca281b6d8234625a0c28b7d0c540bf9f",
+                ],
+                rekeyed: "eba40e2b61da101044878c17e78c6325edc0d299c0671a",
+            },
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for &pattern in &[Pattern::Xx, Pattern::Ik] {
+            let (mut initiator, mut responder) = states::<Gcm<Aes256>, Sha256>(pattern);
+            handshake(&mut initiator, &mut responder);
+            assert_eq!(initiator.handshake_hash(), responder.handshake_hash());
+            let mut initiator = initiator.into_transport().unwrap();
+            let mut responder = responder.into_transport().unwrap();
+            let message = &mut [0; 64];
+            let payload = &mut [0; 64];
+            for i in 0..3 {
+                let len = responder.write_message(&[i; 48], message).unwrap();
+                assert_eq!(64, len);
+                let len = initiator.read_message(&message[..len], payload).unwrap();
+                assert_eq!(&[i; 48][..], &payload[..len]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_invalid() {
+        let rng = &SystemRandom::new();
+        let message = &mut [0; 256];
+        let payload = &mut [0; 256];
+
+        // The prologues differ
+        let (mut initiator, _) = states::<ChaCha20Poly1305, Blake2b512>(Pattern::Xx);
+        let mut responder = HandshakeState::<ChaCha20Poly1305, Blake2b512>::new(
+            Pattern::Xx,
+            false,
+            b"other",
+            &key(65),
+            None,
+        )
+        .unwrap();
+        let len = initiator.write_message(b"", message, rng).unwrap();
+        responder.read_message(&message[..len], payload).unwrap();
+        let len = responder.write_message(b"", message, rng).unwrap();
+        assert_eq!(
+            Err(Error::TagMismatch),
+            initiator.read_message(&message[..len], payload)
+        );
+
+        // The initiator has the wrong static key for the responder
+        let mut initiator = HandshakeState::<ChaCha20Poly1305, Blake2b512>::new(
+            Pattern::Ik,
+            true,
+            PROLOGUE,
+            &key(1),
+            Some(&curve25519::gen_pk(&key(2)).unwrap()),
+        )
+        .unwrap();
+        let (_, mut responder) = states::<ChaCha20Poly1305, Blake2b512>(Pattern::Ik);
+        let len = initiator.write_message(b"", message, rng).unwrap();
+        assert_eq!(
+            Err(Error::TagMismatch),
+            responder.read_message(&message[..len], payload)
+        );
+
+        // Messages are tampered with or too short
+        let (mut initiator, mut responder) = states::<Gcm<Aes256>, Sha512>(Pattern::Ik);
+        assert_eq!(
+            Err(Error::InvalidLength),
+            initiator.write_message(b"", &mut message[..95], rng)
+        );
+        let len = initiator.write_message(b"payload", message, rng).unwrap();
+        assert_eq!(32 + 48 + 7 + 16, len);
+        assert_eq!(
+            Err(Error::InvalidLength),
+            responder.read_message(&message[..32 + 48 + 15], payload)
+        );
+        assert_eq!(
+            Err(Error::InvalidLength),
+            responder.read_message(&message[..len], &mut payload[..6])
+        );
+        message[40] ^= 1;
+        assert_eq!(
+            Err(Error::TagMismatch),
+            responder.read_message(&message[..len], payload)
+        );
+
+        // A low-order ephemeral key
+        let (_, mut responder) = states::<Gcm<Aes256>, Sha512>(Pattern::Xx);
+        responder.read_message(&[0; 32], payload).unwrap();
+        let (mut initiator, _) = states::<Gcm<Aes256>, Sha512>(Pattern::Xx);
+        initiator.write_message(b"", message, rng).unwrap();
+        assert_eq!(
+            Err(Error::InvalidKey),
+            initiator.read_message(&[0; 96], payload)
+        );
+
+        // Transport messages are tampered with or replayed
+        let (mut initiator, mut responder) = states::<Gcm<Aes256>, Sha512>(Pattern::Xx);
+        handshake(&mut initiator, &mut responder);
+        let mut initiator = initiator.into_transport().unwrap();
+        let mut responder = responder.into_transport().unwrap();
+        let len = initiator.write_message(b"message", message).unwrap();
+        message[0] ^= 1;
+        assert_eq!(
+            Err(Error::TagMismatch),
+            responder.read_message(&message[..len], payload)
+        );
+        message[0] ^= 1;
+        responder.read_message(&message[..len], payload).unwrap();
+        assert_eq!(
+            Err(Error::TagMismatch),
+            responder.read_message(&message[..len], payload)
+        );
+        assert_eq!(
+            Err(Error::InvalidLength),
+            responder.read_message(&message[..15], payload)
+        );
+        assert_eq!(
+            Err(Error::InvalidLength),
+            initiator.write_message(&[0; MAX_MESSAGE_LEN - 15], &mut [0; MAX_MESSAGE_LEN + 1])
+        );
+    }
+
+    #[test]
+    fn test_invalid_state() {
+        let rng = &SystemRandom::new();
+        let message = &mut [0; 256];
+        let payload = &mut [0; 256];
+        let error = Err(Error::InvalidState);
+        let (mut initiator, mut responder) = states::<Gcm<Aes256>, Sha512>(Pattern::Xx);
+        assert_eq!(error, responder.write_message(b"", message, rng));
+        assert_eq!(error, initiator.read_message(&[0; 32], payload));
+        let len = initiator.write_message(b"", message, rng).unwrap();
+        assert_eq!(error, initiator.write_message(b"", message, rng));
+        responder.read_message(&message[..len], payload).unwrap();
+        assert_eq!(error, responder.read_message(&message[..len], payload));
+        let result = responder.into_transport().err();
+        assert_eq!(Some(Error::InvalidState), result);
+
+        let (mut initiator, mut responder) = states::<Gcm<Aes256>, Sha512>(Pattern::Ik);
+        handshake(&mut initiator, &mut responder);
+        assert_eq!(error, initiator.write_message(b"", message, rng));
+        assert_eq!(error, responder.read_message(&[0; 48], payload));
+
+        // The remote static key is given exactly for the IK initiator.
+        let remote_static_key = Some(&[0x42; 32]);
+        type State = HandshakeState<Gcm<Aes256>, Sha512>;
+        for (pattern, initiator, remote_static_key) in [
+            (Pattern::Ik, true, None),
+            (Pattern::Ik, false, remote_static_key),
+            (Pattern::Xx, true, remote_static_key),
+        ] {
+            let state = State::new(pattern, initiator, PROLOGUE, &key(1), remote_static_key);
+            assert_eq!(Some(Error::InvalidKey), state.err());
+        }
+    }
+}
@@ -0,0 +1,447 @@
+//! Module for ECDH and ECDSA with SHA-256 on the NIST P-256 curve (FIPS 186-4, SEC 1).
+//!
+//! Secret keys are 32-byte big-endian integers between 1 and n - 1, public keys use the 65-byte
+//! uncompressed encoding 0x04 || x || y, and signatures use the 64-byte encoding r || s, which
+//! can be converted to and from the DER encoding used by X.509 and TLS.
+use crate::error::Error;
+use crate::rand::SecureRandom;
+use crate::sha2::{sha256, Sha256};
+use crate::sig::{self, Signer, Verifier};
+use crate::weierstrass::{self, Curve, Params};
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+pub(crate) const PARAMS: Params<4> = Params {
+    p: [
+        0xffff_ffff_ffff_ffff,
+        0x0000_0000_ffff_ffff,
+        0x0000_0000_0000_0000,
+        0xffff_ffff_0000_0001,
+    ],
+    n: [
+        0xf3b9_cac2_fc63_2551,
+        0xbce6_faad_a717_9e84,
+        0xffff_ffff_ffff_ffff,
+        0xffff_ffff_0000_0000,
+    ],
+    a: [
+        0xffff_ffff_ffff_fffc,
+        0x0000_0000_ffff_ffff,
+        0x0000_0000_0000_0000,
+        0xffff_ffff_0000_0001,
+    ],
+    b: [
+        0x3bce_3c3e_27d2_604b,
+        0x651d_06b0_cc53_b0f6,
+        0xb3eb_bd55_7698_86bc,
+        0x5ac6_35d8_aa3a_93e7,
+    ],
+    gx: [
+        0xf4a1_3945_d898_c296,
+        0x7703_7d81_2deb_33a0,
+        0xf8bc_e6e5_63a4_40f2,
+        0x6b17_d1f2_e12c_4247,
+    ],
+    gy: [
+        0xcbb6_4068_37bf_51f5,
+        0x2bce_3357_6b31_5ece,
+        0x8ee7_eb4a_7c0f_9e16,
+        0x4fe3_42e2_fe1a_7f9b,
+    ],
+};
+
+/// Computes the public key for a secret key.
+///
+/// # Errors
+///
+/// Fails with `Error::InvalidKey` if `secret_key.len()` is not equal to 32 or the secret key is
+/// out of range.
+pub fn gen_pk(secret_key: &[u8]) -> Result<[u8; 65], Error> {
+    let mut public_key = [0; 65];
+    if Curve::new(&PARAMS).gen_pk(secret_key, &mut public_key) {
+        Ok(public_key)
+    } else {
+        Err(Error::InvalidKey)
+    }
+}
+
+/// Computes an ECDH shared secret, the x-coordinate of the shared point, given a secret key and
+/// another's public key.
+///
+/// # Errors
+///
+/// Fails with `Error::InvalidKey` if `secret_key.len()` is not equal to 32, the secret key is out
+/// of range, or the public key is not a point on the curve.
+///
+/// # Examples
+///
+/// ```
+/// use crypto_pure::p256::{ecdh, gen_pk};
+/// # let alice_secret_key = &[0x42; 32];
+/// # let bob_secret_key = &[0x24; 32];
+/// let alice_public_key = &gen_pk(alice_secret_key)?;
+/// let bob_public_key = &gen_pk(bob_secret_key)?;
+/// let alice_shared_secret = ecdh(alice_secret_key, bob_public_key)?;
+/// let bob_shared_secret = ecdh(bob_secret_key, alice_public_key)?;
+/// assert_eq!(alice_shared_secret, bob_shared_secret);
+/// # Ok::<(), crypto_pure::error::Error>(())
+/// ```
+pub fn ecdh(secret_key: &[u8], public_key: &[u8]) -> Result<[u8; 32], Error> {
+    let mut shared_secret = [0; 32];
+    if Curve::new(&PARAMS).ecdh(secret_key, public_key, &mut shared_secret) {
+        Ok(shared_secret)
+    } else {
+        Err(Error::InvalidKey)
+    }
+}
+
+/// Signs a message with ECDSA and SHA-256, outputting the signature r || s. The per-signature
+/// nonce is drawn from `rng`.
+///
+/// # Errors
+///
+/// Fails with `Error::InvalidKey` if `secret_key.len()` is not equal to 32 or the secret key is
+/// out of range.
+///
+/// # Examples
+///
+/// ```
+/// use crypto_pure::p256::{gen_pk, sign, verify};
+/// use crypto_pure::rand::SystemRandom;
+/// # let secret_key = &[0x42; 32];
+/// let public_key = &gen_pk(secret_key)?;
+/// let signature = &sign(b"message", secret_key, &SystemRandom::new())?;
+/// assert!(verify(b"message", signature, public_key));
+/// # Ok::<(), crypto_pure::error::Error>(())
+/// ```
+pub fn sign(message: &[u8], secret_key: &[u8], rng: &dyn SecureRandom) -> Result<[u8; 64], Error> {
+    let mut signature = [0; 64];
+    if !Curve::new(&PARAMS).sign(&sha256(message), secret_key, rng, &mut signature) {
+        return Err(Error::InvalidKey);
+    }
+    Ok(signature)
+}
+
+/// Signs a message with ECDSA and SHA-256, outputting the signature r || s. The
+/// per-signature nonce is derived from the secret key and message as in RFC 6979, so signing
+/// needs no randomness and the same message always gives the same signature.
+///
+/// # Errors
+///
+/// Fails with `Error::InvalidKey` if `secret_key.len()` is not equal to 32 or the secret key is
+/// out of range.
+pub fn sign_deterministic(message: &[u8], secret_key: &[u8]) -> Result<[u8; 64], Error> {
+    let mut signature = [0; 64];
+    let curve = Curve::new(&PARAMS);
+    if !curve.sign_deterministic::<Sha256>(&sha256(message), secret_key, b"", &mut signature) {
+        return Err(Error::InvalidKey);
+    }
+    Ok(signature)
+}
+
+/// Signs a message with ECDSA and SHA-256, outputting the signature r || s. The
+/// per-signature nonce is derived as in RFC 6979 with 32 bytes from `rng` as additional data, so
+/// a weak `rng` doesn't leak the secret key, while fresh randomness makes each signature differ,
+/// defeating fault attacks that rely on signing the same message twice.
+///
+/// # Errors
+///
+/// Fails with `Error::InvalidKey` if `secret_key.len()` is not equal to 32 or the secret key is
+/// out of range.
+pub fn sign_hedged(
+    message: &[u8],
+    secret_key: &[u8],
+    rng: &dyn SecureRandom,
+) -> Result<[u8; 64], Error> {
+    let extra = &mut [0; 32];
+    rng.fill(extra);
+    let mut signature = [0; 64];
+    let curve = Curve::new(&PARAMS);
+    if !curve.sign_deterministic::<Sha256>(&sha256(message), secret_key, extra, &mut signature) {
+        return Err(Error::InvalidKey);
+    }
+    Ok(signature)
+}
+
+/// Verifies an ECDSA signature r || s of a message with SHA-256 under a public key.
+pub fn verify(message: &[u8], signature: &[u8], public_key: &[u8]) -> bool {
+    Curve::new(&PARAMS).verify(&sha256(message), signature, public_key)
+}
+
+/// Hashes a message to a point with the P256_XMD:SHA-256_SSWU_RO_ suite of RFC 9380, outputting
+/// its uncompressed encoding. Distinct protocols must use distinct domain separation tags.
+///
+/// # Errors
+///
+/// Fails with `Error::InvalidLength` if `dst.len()` is more than 255.
+pub fn hash_to_curve(message: &[u8], dst: &[u8]) -> Result<[u8; 65], Error> {
+    if dst.len() > 255 {
+        return Err(Error::InvalidLength);
+    }
+    let mut point = [0; 65];
+    let hashed = Curve::new(&PARAMS).hash_to_curve::<Sha256>(message, dst, 10, &mut point);
+    // The point at infinity would take finding a preimage of the hash.
+    assert!(hashed, "hashed to the point at infinity");
+    Ok(point)
+}
+
+/// ECDSA with SHA-256 on P-256, signing with hedged nonces as in `sign_hedged`.
+pub struct EcdsaP256;
+
+impl Verifier for EcdsaP256 {
+    const MAX_CONTEXT_LEN: usize = 0;
+
+    type PublicKey = [u8];
+
+    fn verify(
+        message: &[u8],
+        context: &[u8],
+        signature: &[u8],
+        public_key: &[u8],
+    ) -> Result<(), Error> {
+        sig::check_lengths::<Self>(context, public_key, 65)?;
+        sig::check_valid(verify(message, signature, public_key))
+    }
+}
+
+impl Signer for EcdsaP256 {
+    type SecretKey = [u8];
+    type Signature = [u8; 64];
+
+    fn sign(
+        message: &[u8],
+        context: &[u8],
+        secret_key: &[u8],
+        rng: &dyn SecureRandom,
+    ) -> Result<[u8; 64], Error> {
+        sig::check_lengths::<Self>(context, secret_key, 32)?;
+        sign_hedged(message, secret_key, rng)
+    }
+}
+
+/// Converts a signature r || s to the DER encoding used by X.509 and TLS, a SEQUENCE of the
+/// INTEGERs r and s.
+///
+/// # Errors
+///
+/// Fails with `Error::InvalidLength` if `signature.len()` is not equal to 64.
+#[cfg(feature = "alloc")]
+pub fn signature_to_der(signature: &[u8]) -> Result<Vec<u8>, Error> {
+    if signature.len() != 64 {
+        return Err(Error::InvalidLength);
+    }
+    Ok(weierstrass::signature_to_der(signature))
+}
+
+/// Converts a DER-encoded signature, a SEQUENCE of the INTEGERs r and s, to r || s.
+///
+/// # Errors
+///
+/// Fails with `Error::InvalidEncoding` if `der` is not the DER encoding of two non-negative
+/// integers below 2^256, including if it is only valid BER, such as with redundant leading
+/// zeros or long-form lengths.
+pub fn signature_from_der(der: &[u8]) -> Result<[u8; 64], Error> {
+    let mut signature = [0; 64];
+    weierstrass::signature_from_der(der, &mut signature)?;
+    Ok(signature)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::*;
+
+    const SECRET_KEY: &str = "c9afa9d845ba75166b5c215767b1d6934e50c3db36e89b127b8a622b120f6721";
+    const PUBLIC_KEY: &str = "0460fed4ba255a9d31c961eb74c6356d68c049b8923b61fa6ce669622e60f29fb6\
+                              7903fe1008b8bc99a41ae9e95628bc64f2f1b20c2d7e9f5177a3c294d4462299";
+
+    #[test]
+    fn test_gen_pk() {
+        let public_key = gen_pk(&h2b(SECRET_KEY)).unwrap();
+        assert_eq!(h2b(PUBLIC_KEY), &public_key[..]);
+        assert_eq!(Err(Error::InvalidKey), gen_pk(&[0; 32]));
+        let n = "ffffffff00000000ffffffffffffffffbce6faada7179e84f3b9cac2fc632551";
+        assert_eq!(Err(Error::InvalidKey), gen_pk(&h2b(n)));
+        assert_eq!(Err(Error::InvalidKey), gen_pk(&h2b(SECRET_KEY)[1..]));
+    }
+
+    #[test]
+    fn test_sign() {
+        // RFC 6979, A.2.5, with the nonce supplied directly
+        let k = h2b("a6e3c57dd01abe90086538398355dd4c3b17aa873382b0f24d6129493d8aad60");
+        let secret_key = &h2b(SECRET_KEY);
+        let public_key = &h2b(PUBLIC_KEY);
+        let signature = sign(b"sample", secret_key, &|buffer: &mut [u8]| {
+            buffer.copy_from_slice(&k)
+        })
+        .unwrap();
+        let expected = "efd48b2aacb6a8fd1140dd9cd45e81d69d2c877b56aaf991c34d0ea84eaf3716\
+                        f7cb1c942d657c41d436c7a1b6e29f65f3e900dbb9aff4064dc4ab2f843acda8";
+        assert_eq!(h2b(expected), &signature[..]);
+        assert!(verify(b"sample", &signature, public_key));
+        assert!(!verify(b"other", &signature, public_key));
+        assert!(!verify(b"sample", &signature[..63], public_key));
+        for &i in &[0, 31, 32, 63] {
+            let signature = &mut signature.clone();
+            signature[i] ^= 1;
+            assert!(!verify(b"sample", signature, public_key));
+        }
+        let public_key = &mut public_key.clone();
+        public_key[64] ^= 1;
+        assert!(!verify(b"sample", &signature, public_key));
+    }
+
+    #[test]
+    fn test_sign_invalid_key() {
+        let rng = &|buffer: &mut [u8]| buffer.fill(0x24);
+        for secret_key in [&[0; 32][..], &h2b(SECRET_KEY)[1..]] {
+            assert_eq!(Err(Error::InvalidKey), sign(b"sample", secret_key, rng));
+            assert_eq!(
+                Err(Error::InvalidKey),
+                sign_deterministic(b"sample", secret_key)
+            );
+            assert_eq!(
+                Err(Error::InvalidKey),
+                sign_hedged(b"sample", secret_key, rng)
+            );
+        }
+    }
+
+    #[test]
+    fn test_sign_deterministic() {
+        // RFC 6979, A.2.5
+        let secret_key = &h2b(SECRET_KEY);
+        let expected = "efd48b2aacb6a8fd1140dd9cd45e81d69d2c877b56aaf991c34d0ea84eaf3716\
+                        f7cb1c942d657c41d436c7a1b6e29f65f3e900dbb9aff4064dc4ab2f843acda8";
+        assert_eq!(
+            h2b(expected),
+            &sign_deterministic(b"sample", secret_key).unwrap()[..]
+        );
+        let expected = "f1abb023518351cd71d881567b1ea663ed3efcf6c5132b354f28d3b0b7d38367\
+                        019f4113742a2b14bd25926b49c649155f267e60d3814b4c0cc84250e46f0083";
+        assert_eq!(
+            h2b(expected),
+            &sign_deterministic(b"test", secret_key).unwrap()[..]
+        );
+    }
+
+    #[test]
+    fn test_hash_to_curve() {
+        // RFC 9380, J.1.1
+        let dst = b"QUUX-V01-CS02-with-P256_XMD:SHA-256_SSWU_RO_";
+        let check = |message: &[u8], expected: &str| {
+            assert_eq!(h2b(expected), &hash_to_curve(message, dst).unwrap()[..]);
+        };
+        check(
+            b"",
+            "042c15230b26dbc6fc9a37051158c95b79656e17a1a920b11394ca91c44247d3e4\
+             8a7a74985cc5c776cdfe4b1f19884970453912e9d31528c060be9ab5c43e8415",
+        );
+        check(
+            b"abc",
+            "040bb8b87485551aa43ed54f009230450b492fead5f1cc91658775dac4a3388a0f\
+             5c41b3d0731a27a7b14bc0bf0ccded2d8751f83493404c84a88e71ffd424212e",
+        );
+        check(
+            b"abcdef0123456789",
+            "0465038ac8f2b1def042a5df0b33b1f4eca6bff7cb0f9c6c1526811864e544ed80\
+             cad44d40a656e7aff4002a8de287abc8ae0482b5ae825822bb870d6df9b56ca3",
+        );
+        let message = &mut b"q128_".to_vec();
+        message.extend_from_slice(&[b'q'; 128]);
+        check(
+            message,
+            "044be61ee205094282ba8a2042bcb48d88dfbb609301c49aa8b078533dc65a0b5d\
+             98f8df449a072c4721d241a3b1236d3caccba603f916ca680f4539d2bfb3c29e",
+        );
+        assert_eq!(Err(Error::InvalidLength), hash_to_curve(b"", &[0; 256]));
+    }
+
+    #[test]
+    fn test_sign_hedged() {
+        // The nonce with k' = 0x24..., checked against a Python implementation of RFC 6979
+        let k = h2b("c7cb835ee69a0ff918806e6b91b4859f202f085048e54e35a510d182093e647e");
+        let secret_key = &h2b(SECRET_KEY);
+        let signature = sign_hedged(b"sample", secret_key, &|buffer: &mut [u8]| {
+            buffer.copy_from_slice(&[0x24; 32])
+        })
+        .unwrap();
+        let expected = sign(b"sample", secret_key, &|buffer: &mut [u8]| {
+            buffer.copy_from_slice(&k)
+        })
+        .unwrap();
+        assert_eq!(expected, signature);
+        assert!(verify(b"sample", &signature, &h2b(PUBLIC_KEY)));
+    }
+
+    #[test]
+    fn test_signature_der() {
+        // The signature of RFC 6979, A.2.5, whose halves both need a zero byte for the sign
+        let signature = h2b(
+            "efd48b2aacb6a8fd1140dd9cd45e81d69d2c877b56aaf991c34d0ea84eaf3716\
+             f7cb1c942d657c41d436c7a1b6e29f65f3e900dbb9aff4064dc4ab2f843acda8",
+        );
+        let der = h2b(
+            "3046022100efd48b2aacb6a8fd1140dd9cd45e81d69d2c877b56aaf991c34d0ea84eaf3716\
+             022100f7cb1c942d657c41d436c7a1b6e29f65f3e900dbb9aff4064dc4ab2f843acda8",
+        );
+        assert_eq!(signature, signature_from_der(&der).unwrap());
+        #[cfg(feature = "alloc")]
+        assert_eq!(Ok(der), signature_to_der(&signature));
+
+        // Short integers are padded to 32 bytes.
+        let mut signature = [0; 64];
+        signature[31] = 0x01;
+        signature[63] = 0x80;
+        let der = h2b("300702010102020080");
+        assert_eq!(signature, signature_from_der(&der).unwrap());
+        #[cfg(feature = "alloc")]
+        assert_eq!(Ok(der), signature_to_der(&signature));
+        let der = h2b("3006020100020100");
+        assert_eq!([0; 64], signature_from_der(&der).unwrap());
+        #[cfg(feature = "alloc")]
+        assert_eq!(Ok(der), signature_to_der(&[0; 64]));
+        #[cfg(feature = "alloc")]
+        assert_eq!(Err(Error::InvalidLength), signature_to_der(&[0; 63]));
+
+        for der in &[
+            "",
+            "3000",
+            "3003020101",
+            // Long-form length
+            "30810702010102020080",
+            // Redundant leading zero
+            "30080202000102020080",
+            // Negative integer
+            "3006020101020180",
+            // Trailing data inside and after the sequence
+            "3009020101020200800500",
+            "30070201010202008000",
+            // Wrong tags
+            "310702010102020080",
+            "300702010104020080",
+            // 33-byte integer
+            "30260201010223010000000000000000000000000000000000000000000000000000000000000000",
+        ] {
+            let der = h2b(der);
+            assert_eq!(Err(Error::InvalidEncoding), signature_from_der(&der));
+        }
+    }
+
+    #[test]
+    fn test_ecdh() {
+        // NIST CAVS ECC CDH Primitive, P-256, COUNT = 0
+        let secret_key = &h2b("7d7dc5f71eb29ddaf80d6214632eeae03d9058af1fb6d22ed80badb62bc1a534");
+        let public_key = &h2b(
+            "04700c48f77f56584c5cc632ca65640db91b6bacce3a4df6b42ce7cc838833d287\
+             db71e509e3fd9b060ddb20ba5c51dcc5948d46fbf640dfe0441782cab85fa4ac",
+        );
+        let expected = "46fc62106420ff012e54a434fbdd2d25ccc5852060561e68040dd7778997bd7b";
+        assert_eq!(h2b(expected), &ecdh(secret_key, public_key).unwrap()[..]);
+        let mut off_curve = public_key.clone();
+        off_curve[64] ^= 1;
+        assert_eq!(Err(Error::InvalidKey), ecdh(secret_key, &off_curve));
+        assert_eq!(Err(Error::InvalidKey), ecdh(secret_key, &public_key[..64]));
+        assert_eq!(Err(Error::InvalidKey), ecdh(&secret_key[1..], public_key));
+    }
+}
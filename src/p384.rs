@@ -0,0 +1,355 @@
+//! Module for ECDH and ECDSA with SHA-384 on the NIST P-384 curve (FIPS 186-4, SEC 1).
+//!
+//! Secret keys are 48-byte big-endian integers between 1 and n - 1, public keys use the 97-byte
+//! uncompressed encoding 0x04 || x || y, and signatures use the 96-byte encoding r || s, which
+//! can be converted to and from the DER encoding used by X.509 and TLS.
+use crate::error::Error;
+use crate::rand::SecureRandom;
+use crate::sha2::{sha384, Sha384};
+use crate::sig::{self, Signer, Verifier};
+use crate::weierstrass::{self, Curve, Params};
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+pub(crate) const PARAMS: Params<6> = Params {
+    p: [
+        0x0000_0000_ffff_ffff,
+        0xffff_ffff_0000_0000,
+        0xffff_ffff_ffff_fffe,
+        0xffff_ffff_ffff_ffff,
+        0xffff_ffff_ffff_ffff,
+        0xffff_ffff_ffff_ffff,
+    ],
+    n: [
+        0xecec_196a_ccc5_2973,
+        0x581a_0db2_48b0_a77a,
+        0xc763_4d81_f437_2ddf,
+        0xffff_ffff_ffff_ffff,
+        0xffff_ffff_ffff_ffff,
+        0xffff_ffff_ffff_ffff,
+    ],
+    a: [
+        0x0000_0000_ffff_fffc,
+        0xffff_ffff_0000_0000,
+        0xffff_ffff_ffff_fffe,
+        0xffff_ffff_ffff_ffff,
+        0xffff_ffff_ffff_ffff,
+        0xffff_ffff_ffff_ffff,
+    ],
+    b: [
+        0x2a85_c8ed_d3ec_2aef,
+        0xc656_398d_8a2e_d19d,
+        0x0314_088f_5013_875a,
+        0x181d_9c6e_fe81_4112,
+        0x988e_056b_e3f8_2d19,
+        0xb331_2fa7_e23e_e7e4,
+    ],
+    gx: [
+        0x3a54_5e38_7276_0ab7,
+        0x5502_f25d_bf55_296c,
+        0x59f7_41e0_8254_2a38,
+        0x6e1d_3b62_8ba7_9b98,
+        0x8eb1_c71e_f320_ad74,
+        0xaa87_ca22_be8b_0537,
+    ],
+    gy: [
+        0x7a43_1d7c_90ea_0e5f,
+        0x0a60_b1ce_1d7e_819d,
+        0xe9da_3113_b5f0_b8c0,
+        0xf8f4_1dbd_289a_147c,
+        0x5d9e_98bf_9292_dc29,
+        0x3617_de4a_9626_2c6f,
+    ],
+};
+
+/// Computes the public key for a secret key.
+///
+/// # Errors
+///
+/// Fails with `Error::InvalidKey` if `secret_key.len()` is not equal to 48 or the secret key is
+/// out of range.
+pub fn gen_pk(secret_key: &[u8]) -> Result<[u8; 97], Error> {
+    let mut public_key = [0; 97];
+    if Curve::new(&PARAMS).gen_pk(secret_key, &mut public_key) {
+        Ok(public_key)
+    } else {
+        Err(Error::InvalidKey)
+    }
+}
+
+/// Computes an ECDH shared secret, the x-coordinate of the shared point, given a secret key and
+/// another's public key.
+///
+/// # Errors
+///
+/// Fails with `Error::InvalidKey` if `secret_key.len()` is not equal to 48, the secret key is out
+/// of range, or the public key is not a point on the curve.
+pub fn ecdh(secret_key: &[u8], public_key: &[u8]) -> Result<[u8; 48], Error> {
+    let mut shared_secret = [0; 48];
+    if Curve::new(&PARAMS).ecdh(secret_key, public_key, &mut shared_secret) {
+        Ok(shared_secret)
+    } else {
+        Err(Error::InvalidKey)
+    }
+}
+
+/// Signs a message with ECDSA and SHA-384, outputting the signature r || s. The per-signature
+/// nonce is drawn from `rng`.
+///
+/// # Errors
+///
+/// Fails with `Error::InvalidKey` if `secret_key.len()` is not equal to 48 or the secret key is
+/// out of range.
+///
+/// # Examples
+///
+/// ```
+/// use crypto_pure::p384::{gen_pk, sign, verify};
+/// use crypto_pure::rand::SystemRandom;
+/// # let secret_key = &[0x42; 48];
+/// let public_key = &gen_pk(secret_key)?;
+/// let signature = &sign(b"message", secret_key, &SystemRandom::new())?;
+/// assert!(verify(b"message", signature, public_key));
+/// # Ok::<(), crypto_pure::error::Error>(())
+/// ```
+pub fn sign(message: &[u8], secret_key: &[u8], rng: &dyn SecureRandom) -> Result<[u8; 96], Error> {
+    let mut signature = [0; 96];
+    if !Curve::new(&PARAMS).sign(&sha384(message), secret_key, rng, &mut signature) {
+        return Err(Error::InvalidKey);
+    }
+    Ok(signature)
+}
+
+/// Signs a message with ECDSA and SHA-384, outputting the signature r || s. The
+/// per-signature nonce is derived from the secret key and message as in RFC 6979, so signing
+/// needs no randomness and the same message always gives the same signature.
+///
+/// # Errors
+///
+/// Fails with `Error::InvalidKey` if `secret_key.len()` is not equal to 48 or the secret key is
+/// out of range.
+pub fn sign_deterministic(message: &[u8], secret_key: &[u8]) -> Result<[u8; 96], Error> {
+    let mut signature = [0; 96];
+    let curve = Curve::new(&PARAMS);
+    if !curve.sign_deterministic::<Sha384>(&sha384(message), secret_key, b"", &mut signature) {
+        return Err(Error::InvalidKey);
+    }
+    Ok(signature)
+}
+
+/// Signs a message with ECDSA and SHA-384, outputting the signature r || s. The
+/// per-signature nonce is derived as in RFC 6979 with 32 bytes from `rng` as additional data, so
+/// a weak `rng` doesn't leak the secret key, while fresh randomness makes each signature differ,
+/// defeating fault attacks that rely on signing the same message twice.
+///
+/// # Errors
+///
+/// Fails with `Error::InvalidKey` if `secret_key.len()` is not equal to 48 or the secret key is
+/// out of range.
+pub fn sign_hedged(
+    message: &[u8],
+    secret_key: &[u8],
+    rng: &dyn SecureRandom,
+) -> Result<[u8; 96], Error> {
+    let extra = &mut [0; 32];
+    rng.fill(extra);
+    let mut signature = [0; 96];
+    let curve = Curve::new(&PARAMS);
+    if !curve.sign_deterministic::<Sha384>(&sha384(message), secret_key, extra, &mut signature) {
+        return Err(Error::InvalidKey);
+    }
+    Ok(signature)
+}
+
+/// Verifies an ECDSA signature r || s of a message with SHA-384 under a public key.
+pub fn verify(message: &[u8], signature: &[u8], public_key: &[u8]) -> bool {
+    Curve::new(&PARAMS).verify(&sha384(message), signature, public_key)
+}
+
+/// ECDSA with SHA-384 on P-384, signing with hedged nonces as in `sign_hedged`.
+pub struct EcdsaP384;
+
+impl Verifier for EcdsaP384 {
+    const MAX_CONTEXT_LEN: usize = 0;
+
+    type PublicKey = [u8];
+
+    fn verify(
+        message: &[u8],
+        context: &[u8],
+        signature: &[u8],
+        public_key: &[u8],
+    ) -> Result<(), Error> {
+        sig::check_lengths::<Self>(context, public_key, 97)?;
+        sig::check_valid(verify(message, signature, public_key))
+    }
+}
+
+impl Signer for EcdsaP384 {
+    type SecretKey = [u8];
+    type Signature = [u8; 96];
+
+    fn sign(
+        message: &[u8],
+        context: &[u8],
+        secret_key: &[u8],
+        rng: &dyn SecureRandom,
+    ) -> Result<[u8; 96], Error> {
+        sig::check_lengths::<Self>(context, secret_key, 48)?;
+        sign_hedged(message, secret_key, rng)
+    }
+}
+
+/// Converts a signature r || s to the DER encoding used by X.509 and TLS, a SEQUENCE of the
+/// INTEGERs r and s.
+///
+/// # Errors
+///
+/// Fails with `Error::InvalidLength` if `signature.len()` is not equal to 96.
+#[cfg(feature = "alloc")]
+pub fn signature_to_der(signature: &[u8]) -> Result<Vec<u8>, Error> {
+    if signature.len() != 96 {
+        return Err(Error::InvalidLength);
+    }
+    Ok(weierstrass::signature_to_der(signature))
+}
+
+/// Converts a DER-encoded signature, a SEQUENCE of the INTEGERs r and s, to r || s.
+///
+/// # Errors
+///
+/// Fails with `Error::InvalidEncoding` if `der` is not the DER encoding of two non-negative
+/// integers below 2^384, including if it is only valid BER, such as with redundant leading
+/// zeros or long-form lengths.
+pub fn signature_from_der(der: &[u8]) -> Result<[u8; 96], Error> {
+    let mut signature = [0; 96];
+    weierstrass::signature_from_der(der, &mut signature)?;
+    Ok(signature)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::*;
+    use std::vec::Vec;
+
+    const SECRET_KEY: &str = "6b9d3dad2e1b8c1c05b19875b6659f4de23c3b667bf297ba\
+                              9aa47740787137d896d5724e4c70a825f872c9ea60d2edf5";
+    const PUBLIC_KEY: &str = "04ec3a4e415b4e19a4568618029f427fa5da9a8bc4ae92e02e\
+                              06aae5286b300c64def8f0ea9055866064a254515480bc13\
+                              8015d9b72d7d57244ea8ef9ac0c621896708a59367f9dfb9\
+                              f54ca84b3f1c9db1288b231c3ae0d4fe7344fd2533264720";
+
+    #[test]
+    fn test_gen_pk() {
+        let public_key = gen_pk(&h2b(SECRET_KEY)).unwrap();
+        assert_eq!(h2b(PUBLIC_KEY), &public_key[..]);
+        assert_eq!(Err(Error::InvalidKey), gen_pk(&[0; 48]));
+        assert_eq!(Err(Error::InvalidKey), gen_pk(&[0xff; 48]));
+        assert_eq!(Err(Error::InvalidKey), gen_pk(&h2b(SECRET_KEY)[1..]));
+    }
+
+    #[test]
+    fn test_sign_deterministic() {
+        // RFC 6979, A.2.6
+        let secret_key = &h2b(SECRET_KEY);
+        let expected = "94edbb92a5ecb8aad4736e56c691916b3f88140666ce9fa7\
+                        3d64c4ea95ad133c81a648152e44acf96e36dd1e80fabe46\
+                        99ef4aeb15f178cea1fe40db2603138f130e740a19624526\
+                        203b6351d0a3a94fa329c145786e679e7b82c71a38628ac8";
+        assert_eq!(
+            h2b(expected),
+            &sign_deterministic(b"sample", secret_key).unwrap()[..]
+        );
+        let expected = "8203b63d3c853e8d77227fb377bcf7b7b772e97892a80f36\
+                        ab775d509d7a5feb0542a7f0812998da8f1dd3ca3cf023db\
+                        ddd0760448d42d8a43af45af836fce4de8be06b485e9b61b\
+                        827c2f13173923e06a739f040649a667bf3b828246baa5a5";
+        assert_eq!(
+            h2b(expected),
+            &sign_deterministic(b"test", secret_key).unwrap()[..]
+        );
+
+        let signature = sign_hedged(b"test", secret_key, &|buffer: &mut [u8]| {
+            buffer.copy_from_slice(&[0x24; 32])
+        })
+        .unwrap();
+        assert_ne!(h2b(expected), &signature[..]);
+        assert!(verify(b"test", &signature, &h2b(PUBLIC_KEY)));
+    }
+
+    #[test]
+    fn test_sign() {
+        // RFC 6979, A.2.6, with the nonce supplied directly
+        let k = h2b("94ed910d1a099dad3254e9242ae85abde4ba15168eaf0ca8\
+             7a555fd56d10fbca2907e3e83ba95368623b8c4686915cf9");
+        let secret_key = &h2b(SECRET_KEY);
+        let public_key = &h2b(PUBLIC_KEY);
+        let signature = sign(b"sample", secret_key, &|buffer: &mut [u8]| {
+            buffer.copy_from_slice(&k)
+        })
+        .unwrap();
+        let expected = "94edbb92a5ecb8aad4736e56c691916b3f88140666ce9fa7\
+                        3d64c4ea95ad133c81a648152e44acf96e36dd1e80fabe46\
+                        99ef4aeb15f178cea1fe40db2603138f130e740a19624526\
+                        203b6351d0a3a94fa329c145786e679e7b82c71a38628ac8";
+        assert_eq!(h2b(expected), &signature[..]);
+        let der = [
+            &h2b("3066023100"),
+            &signature[..48],
+            &[0x02, 0x31, 0x00],
+            &signature[48..],
+        ]
+        .concat();
+        assert_eq!(signature, signature_from_der(&der).unwrap());
+        #[cfg(feature = "alloc")]
+        assert_eq!(Ok(der), signature_to_der(&signature));
+        #[cfg(feature = "alloc")]
+        assert_eq!(Err(Error::InvalidLength), signature_to_der(&signature[1..]));
+        assert!(verify(b"sample", &signature, public_key));
+        assert!(!verify(b"other", &signature, public_key));
+        for &i in &[0, 47, 48, 95] {
+            let signature = &mut signature.clone();
+            signature[i] ^= 1;
+            assert!(!verify(b"sample", signature, public_key));
+        }
+        let rng = &|buffer: &mut [u8]| buffer.fill(0x24);
+        assert_eq!(Err(Error::InvalidKey), sign(b"sample", &[0; 48], rng));
+        assert_eq!(
+            Err(Error::InvalidKey),
+            sign(b"sample", &secret_key[1..], rng)
+        );
+        assert_eq!(
+            Err(Error::InvalidKey),
+            sign_deterministic(b"sample", &[0; 48])
+        );
+        assert_eq!(
+            Err(Error::InvalidKey),
+            sign_hedged(b"sample", &[0; 48], rng)
+        );
+    }
+
+    #[test]
+    fn test_ecdh() {
+        let secret_key = &h2b(SECRET_KEY);
+        let other_secret_key: &Vec<_> = &(1..=48).collect();
+        let other_public_key = &gen_pk(other_secret_key).unwrap();
+        let expected = "04c76f2283dda95cd49b0ed9e733d2904474e37216f124e13d\
+                        2c9ab4cf01021c49ad9cabb3d0b97499aef2f0ab313fa028\
+                        26bc1f83451b5c8962a75caff73588d4400a6296436154fb\
+                        343c393e91048a6c7bcbadc83cd8a5f26feae883156f92a1";
+        assert_eq!(h2b(expected), &other_public_key[..]);
+
+        let expected = "742f5f3f59859c087997e89caa7dca6bc7294688e11f1424\
+                        059c5a9083cd4f20b9bdd9c68e6ff7b709004be95806155f";
+        let shared_secret = ecdh(secret_key, other_public_key).unwrap();
+        assert_eq!(h2b(expected), &shared_secret[..]);
+        let shared_secret = ecdh(other_secret_key, &h2b(PUBLIC_KEY)).unwrap();
+        assert_eq!(h2b(expected), &shared_secret[..]);
+        assert_eq!(Err(Error::InvalidKey), ecdh(secret_key, &[4; 97]));
+        assert_eq!(
+            Err(Error::InvalidKey),
+            ecdh(&secret_key[1..], other_public_key)
+        );
+    }
+}
@@ -0,0 +1,17 @@
+//! Module for padding schemes that fill out the last block of a block cipher mode of operation.
+use core::fmt;
+
+pub mod pkcs7;
+
+/// An error returned when padding is malformed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PaddingError;
+
+impl fmt::Display for PaddingError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("invalid padding")
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for PaddingError {}
@@ -0,0 +1,116 @@
+//! Module for PKCS #7 padding (RFC 5652), where each padding byte is the number of padding bytes.
+use super::PaddingError;
+use crate::ct::{ct_eq, ct_lt};
+use crate::error::Error;
+
+/// Outputs the length of a message of `len` bytes after padding to a multiple of `block_size`.
+///
+/// A full block of padding is added if `len` is already a multiple of `block_size`.
+///
+/// # Panics
+///
+/// Panics if `block_size` is not from 1 to 255.
+pub fn padded_len(len: usize, block_size: usize) -> usize {
+    assert!((1..=255).contains(&block_size));
+    len - len % block_size + block_size
+}
+
+/// Pads the message in the first `len` bytes of `buffer`, outputting the padded part of `buffer`.
+///
+/// # Errors
+///
+/// Fails with `Error::InvalidLength` if `buffer.len()` is less than `padded_len(len, block_size)`.
+///
+/// # Panics
+///
+/// Panics if `block_size` is not from 1 to 255.
+///
+/// # Examples
+///
+/// ```
+/// use crypto_pure::padding::pkcs7;
+/// let buffer = &mut [0; 16];
+/// buffer[..5].copy_from_slice(b"hello");
+/// let padded = pkcs7::pad(buffer, 5, 8)?;
+/// assert_eq!(b"hello\x03\x03\x03", padded);
+/// assert_eq!(Ok(&b"hello"[..]), pkcs7::unpad(padded, 8));
+/// # Ok::<(), crypto_pure::error::Error>(())
+/// ```
+pub fn pad(buffer: &mut [u8], len: usize, block_size: usize) -> Result<&mut [u8], Error> {
+    let padded_len = padded_len(len, block_size);
+    if buffer.len() < padded_len {
+        return Err(Error::InvalidLength);
+    }
+    let padding = &mut buffer[len..padded_len];
+    for byte in padding.iter_mut() {
+        *byte = (padded_len - len) as u8;
+    }
+    Ok(&mut buffer[..padded_len])
+}
+
+/// Removes the padding from `buffer`, outputting the message part of it.
+///
+/// The padding is checked in time that only depends on `buffer.len()` and `block_size`, so that
+/// this can be used after decryption without revealing why a ciphertext was rejected.
+///
+/// # Panics
+///
+/// Panics if `block_size` is not from 1 to 255.
+pub fn unpad(buffer: &[u8], block_size: usize) -> Result<&[u8], PaddingError> {
+    assert!((1..=255).contains(&block_size));
+    if buffer.is_empty() || !buffer.len().is_multiple_of(block_size) {
+        return Err(PaddingError);
+    }
+    let last_block = &buffer[buffer.len() - block_size..];
+    let padding_len = u32::from(last_block[block_size - 1]);
+    let mut valid = !ct_lt(padding_len, 1) & ct_lt(padding_len, block_size as u32 + 1);
+    for (i, &byte) in (0..).zip(last_block.iter().rev()) {
+        let in_padding = ct_lt(i, padding_len);
+        valid &= !in_padding | ct_eq(&[byte], &[padding_len as u8]);
+    }
+    if valid.into() {
+        Ok(&buffer[..buffer.len() - padding_len as usize])
+    } else {
+        Err(PaddingError)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pad() {
+        for len in 0..=32 {
+            let buffer = &mut [0xaa; 48];
+            let padded = pad(buffer, len, 16).unwrap();
+            let padded_len = padded_len(len, 16);
+            assert_eq!(padded_len, padded.len());
+            assert_eq!(0, padded_len % 16);
+            assert!(padded_len > len && padded_len <= len + 16);
+            for &byte in &padded[len..] {
+                assert_eq!((padded_len - len) as u8, byte);
+            }
+            assert_eq!(Ok(&[0xaa; 48][..len]), unpad(padded, 16));
+        }
+        assert_eq!(b"\x01", pad(&mut [0], 0, 1).unwrap());
+        assert_eq!(b"ab\x02\x02", pad(&mut b"ab\0\0".clone(), 2, 2).unwrap());
+        assert_eq!(Err(Error::InvalidLength), pad(&mut [0; 16], 16, 16));
+    }
+
+    #[test]
+    fn test_unpad() {
+        assert_eq!(Ok(&b"abc"[..]), unpad(b"abc\x01", 4));
+        assert_eq!(Ok(&b""[..]), unpad(b"\x04\x04\x04\x04", 4));
+        assert_eq!(Ok(&b"abcd"[..]), unpad(b"abcd\x04\x04\x04\x04", 4));
+
+        assert_eq!(Err(PaddingError), unpad(b"", 4));
+        assert_eq!(Err(PaddingError), unpad(b"abc\x01x", 4));
+        assert_eq!(Err(PaddingError), unpad(b"abc\x00", 4));
+        assert_eq!(Err(PaddingError), unpad(b"abc\x05", 4));
+        assert_eq!(Err(PaddingError), unpad(b"a\x03\x02\x03", 4));
+        assert_eq!(Err(PaddingError), unpad(b"\x03\x04\x04\x04", 4));
+        assert_eq!(Err(PaddingError), unpad(b"abcd\x05\x05\x05\x05", 4));
+        assert_eq!(Err(PaddingError), unpad(b"abc\xff", 4));
+    }
+}
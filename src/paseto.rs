@@ -0,0 +1,416 @@
+//! Module for version 4 of PASETO, platform-agnostic security tokens: `v4.local` tokens, which
+//! are encrypted with XChaCha20 and authenticated with keyed BLAKE2b, and `v4.public` tokens,
+//! which are signed with Ed25519.
+//!
+//! Unlike JOSE, the algorithms are fixed by the version and purpose in the token's header, so
+//! there is nothing for an attacker to choose. Both kinds of token can carry an unencrypted
+//! footer, such as a key identifier, and be bound to an implicit assertion, which is
+//! authenticated but not part of the token, such as the audience it is meant for. Both must be
+//! given exactly when decoding; `decode_unverified_footer` reads the footer beforehand.
+//!
+//! # Examples
+//!
+//! ```
+//! use crypto_pure::paseto;
+//! use crypto_pure::rand::SystemRandom;
+//! # let key = &[0x42; 32];
+//! let token = paseto::encrypt(b"message", b"footer", b"", key, &SystemRandom::new());
+//! assert_eq!(b"footer", &paseto::decode_unverified_footer(&token)?[..]);
+//! assert_eq!(b"message", &paseto::decrypt(&token, b"footer", b"", key)?[..]);
+//! # Ok::<(), crypto_pure::error::Error>(())
+//! ```
+use crate::blake2b::Blake2b;
+use crate::chacha20;
+use crate::ct;
+use crate::ed25519;
+use crate::encoding::base64;
+use crate::error::Error;
+use crate::rand::SecureRandom;
+use crate::secret::SecretKey;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+const LOCAL_HEADER: &str = "v4.local.";
+const PUBLIC_HEADER: &str = "v4.public.";
+const NONCE_LEN: usize = 32;
+const TAG_LEN: usize = 32;
+const SIGNATURE_LEN: usize = 64;
+
+/// Encrypts a message into a `v4.local` token with a random nonce drawn from `rng`.
+pub fn encrypt(
+    message: &[u8],
+    footer: &[u8],
+    implicit_assertion: &[u8],
+    key: &[u8; 32],
+    rng: &dyn SecureRandom,
+) -> String {
+    let nonce = &mut [0; NONCE_LEN];
+    rng.fill(nonce);
+    encrypt_with_nonce(message, footer, implicit_assertion, key, nonce)
+}
+
+fn encrypt_with_nonce(
+    message: &[u8],
+    footer: &[u8],
+    implicit_assertion: &[u8],
+    key: &[u8; 32],
+    nonce: &[u8; NONCE_LEN],
+) -> String {
+    let (encryption_key, stream_nonce, authentication_key) = derive_keys(key, nonce);
+    let mut payload = nonce.to_vec();
+    payload.extend_from_slice(message);
+    chacha20::Stream::new(&encryption_key[..], &stream_nonce)
+        .expect("invalid key or nonce length")
+        .apply_keystream(&mut payload[NONCE_LEN..])
+        .expect("message too long");
+    let tag = authenticate(&authentication_key, &payload, footer, implicit_assertion);
+    payload.extend_from_slice(&tag);
+    encode(LOCAL_HEADER, &payload, footer)
+}
+
+/// Decrypts a `v4.local` token, outputting its message.
+///
+/// # Errors
+///
+/// Fails with `Error::InvalidEncoding` if the token is malformed or not a `v4.local` token, or
+/// with `Error::DecryptionFailed` if its footer is not `footer`, it is not bound to
+/// `implicit_assertion`, it was not encrypted with the key or it was modified.
+pub fn decrypt(
+    token: &str,
+    footer: &[u8],
+    implicit_assertion: &[u8],
+    key: &[u8; 32],
+) -> Result<Vec<u8>, Error> {
+    let (payload, token_footer) = decode(LOCAL_HEADER, token)?;
+    if payload.len() < NONCE_LEN + TAG_LEN {
+        return Err(Error::InvalidEncoding);
+    }
+    if !ct::constant_time_eq(footer, &token_footer) {
+        return Err(Error::DecryptionFailed);
+    }
+    let (authenticated, tag) = payload.split_at(payload.len() - TAG_LEN);
+    let nonce = &authenticated[..NONCE_LEN];
+    let (encryption_key, stream_nonce, authentication_key) = derive_keys(key, nonce);
+    let expected = authenticate(
+        &authentication_key,
+        authenticated,
+        footer,
+        implicit_assertion,
+    );
+    if !ct::constant_time_eq(&expected, tag) {
+        return Err(Error::DecryptionFailed);
+    }
+    let mut message = authenticated[NONCE_LEN..].to_vec();
+    chacha20::Stream::new(&encryption_key[..], &stream_nonce)
+        .expect("invalid key or nonce length")
+        .apply_keystream(&mut message)
+        .expect("message too long");
+    Ok(message)
+}
+
+/// Signs a message into a `v4.public` token. The message itself is not encrypted.
+pub fn sign(
+    message: &[u8],
+    footer: &[u8],
+    implicit_assertion: &[u8],
+    secret_key: &[u8; 32],
+    public_key: &[u8; 32],
+) -> String {
+    let signed = pae(&[
+        PUBLIC_HEADER.as_bytes(),
+        message,
+        footer,
+        implicit_assertion,
+    ]);
+    let mut payload = message.to_vec();
+    let signature = ed25519::sign(&signed, secret_key, public_key).expect("keys are 32 bytes");
+    payload.extend_from_slice(&signature);
+    encode(PUBLIC_HEADER, &payload, footer)
+}
+
+/// Verifies a `v4.public` token, outputting its message.
+///
+/// # Errors
+///
+/// Fails with `Error::InvalidEncoding` if the token is malformed or not a `v4.public` token, or
+/// with `Error::InvalidSignature` if its footer is not `footer`, it is not bound to
+/// `implicit_assertion` or its signature is not valid for the key.
+pub fn verify(
+    token: &str,
+    footer: &[u8],
+    implicit_assertion: &[u8],
+    public_key: &[u8; 32],
+) -> Result<Vec<u8>, Error> {
+    let (mut payload, token_footer) = decode(PUBLIC_HEADER, token)?;
+    if payload.len() < SIGNATURE_LEN {
+        return Err(Error::InvalidEncoding);
+    }
+    if !ct::constant_time_eq(footer, &token_footer) {
+        return Err(Error::InvalidSignature);
+    }
+    let signature = payload.split_off(payload.len() - SIGNATURE_LEN);
+    let signed = pae(&[
+        PUBLIC_HEADER.as_bytes(),
+        &payload,
+        footer,
+        implicit_assertion,
+    ]);
+    if !ed25519::verify(&signed, &signature, public_key) {
+        return Err(Error::InvalidSignature);
+    }
+    Ok(payload)
+}
+
+/// Decodes the footer of a version 4 token without decrypting or verifying it, such as to find
+/// the key identifier. The footer is empty if the token has none.
+///
+/// # Errors
+///
+/// Fails with `Error::InvalidEncoding` if the token is malformed or not a version 4 token.
+pub fn decode_unverified_footer(token: &str) -> Result<Vec<u8>, Error> {
+    decode(LOCAL_HEADER, token)
+        .or_else(|_| decode(PUBLIC_HEADER, token))
+        .map(|(_, footer)| footer)
+}
+
+fn encode(header: &str, payload: &[u8], footer: &[u8]) -> String {
+    let mut token = String::from(header);
+    token.push_str(&base64::URL_SAFE_NO_PAD.encode(payload));
+    if !footer.is_empty() {
+        token.push('.');
+        token.push_str(&base64::URL_SAFE_NO_PAD.encode(footer));
+    }
+    token
+}
+
+/// Decodes the payload and footer of a token with the given header. A footer that is present
+/// must not be empty, so that each token has a single encoding.
+fn decode(header: &str, token: &str) -> Result<(Vec<u8>, Vec<u8>), Error> {
+    if !token.starts_with(header) {
+        return Err(Error::InvalidEncoding);
+    }
+    let mut parts = token[header.len()..].split('.');
+    let decode_part = |part: &str| {
+        base64::URL_SAFE_NO_PAD
+            .decode(part)
+            .map_err(|_| Error::InvalidEncoding)
+    };
+    let payload = decode_part(parts.next().ok_or(Error::InvalidEncoding)?)?;
+    let footer = match (parts.next(), parts.next()) {
+        (None, _) => Vec::new(),
+        (Some(footer), None) if !footer.is_empty() => decode_part(footer)?,
+        _ => return Err(Error::InvalidEncoding),
+    };
+    Ok((payload, footer))
+}
+
+/// The XChaCha20 key reduced to a ChaCha20 key and nonce, and the BLAKE2b-MAC key.
+type Keys = (SecretKey<[u8; 32]>, [u8; 12], SecretKey<[u8; 32]>);
+
+/// Derives the keys for a token from the key and the token's nonce.
+fn derive_keys(key: &[u8; 32], nonce: &[u8]) -> Keys {
+    let mut blake = Blake2b::new_keyed(key, 56);
+    blake.update(b"paseto-encryption-key");
+    blake.update(nonce);
+    let mut derived = SecretKey::new([0; 56]);
+    blake.write_digest(&mut *derived);
+    let mut stream_nonce = [0; 12];
+    stream_nonce[4..].copy_from_slice(&derived[48..]);
+    let encryption_key =
+        chacha20::hchacha20(&derived[..32], &derived[32..48]).expect("invalid key or nonce length");
+    let encryption_key = SecretKey::new(encryption_key);
+
+    let mut blake = Blake2b::new_keyed(key, 32);
+    blake.update(b"paseto-auth-key-for-aead");
+    blake.update(nonce);
+    let mut authentication_key = SecretKey::new([0; 32]);
+    blake.write_digest(&mut *authentication_key);
+    (encryption_key, stream_nonce, authentication_key)
+}
+
+fn authenticate(
+    key: &[u8; 32],
+    payload: &[u8],
+    footer: &[u8],
+    implicit_assertion: &[u8],
+) -> [u8; 32] {
+    let (nonce, ciphertext) = payload.split_at(NONCE_LEN);
+    let mut blake = Blake2b::new_keyed(key, TAG_LEN);
+    blake.update(&pae(&[
+        LOCAL_HEADER.as_bytes(),
+        nonce,
+        ciphertext,
+        footer,
+        implicit_assertion,
+    ]));
+    let mut tag = [0; TAG_LEN];
+    blake.write_digest(&mut tag);
+    tag
+}
+
+/// Pre-authentication encoding, which encodes a list of strings unambiguously by prefixing the
+/// list and each string with its length as a 64-bit little-endian integer.
+fn pae(pieces: &[&[u8]]) -> Vec<u8> {
+    let mut output = (pieces.len() as u64).to_le_bytes().to_vec();
+    for piece in pieces {
+        output.extend_from_slice(&(piece.len() as u64).to_le_bytes());
+        output.extend_from_slice(piece);
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rand::SystemRandom;
+    use crate::test_helpers::*;
+
+    const MESSAGE: &[u8] =
+        br#"{"data":"this is a secret message","exp":"2022-01-01T00:00:00+00:00"}"#;
+    const FOOTER: &[u8] = br#"{"kid":"1"}"#;
+    const IMPLICIT_ASSERTION: &[u8] = br#"{"aud":"example.com"}"#;
+
+    fn array(hex: &str) -> [u8; 32] {
+        let mut bytes = [0; 32];
+        bytes.copy_from_slice(&h2b(hex));
+        bytes
+    }
+
+    #[test]
+    fn test_local() {
+        // Test vector 4-E-1 of the PASETO specification
+        let key = &array("707172737475767778797a7b7c7d7e7f808182838485868788898a8b8c8d8e8f");
+        let token = "v4.local.AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAQAr68PS4AXe7If_ZgesdkUMvSwscFlAl1\
+                     pk5HC0e8kApeaqMfGo_7OpBnwJOAbY9V7WU6abu74MmcUE8YWAiaArVI8XJ5hOb_4v9RmDkneN0S92dx0OW4p\
+                     gy7omxgf3S8c3LlQg";
+        assert_eq!(token, encrypt_with_nonce(MESSAGE, b"", b"", key, &[0; 32]));
+        assert_eq!(MESSAGE, &decrypt(token, b"", b"", key).unwrap()[..]);
+
+        // Generated with Python's hashlib and cryptography, following the specification
+        let nonce = &array("df654812bac492663825520ba2f6e67cf5ca5bdc13d4e7507a98cc4c2fcc3ad8");
+        let token = "v4.local.32VIErrEkmY4JVILovbmfPXKW9wT1OdQepjMTC_MOtjA4kiqw7_tcaOM5GNEcnTxl60WkwM\
+                     sYXw6FSNb_UdJPXjpzm0KW9ojM5f4O2mRvE2IcweP-PRdoHjd5-RHCiExR1IK6t4Fa34F7Wh517awB2qPqAx\
+                     EeWoWTUgdtuoNT8row13qlw.eyJraWQiOiIxIn0";
+        let encrypted = encrypt_with_nonce(MESSAGE, FOOTER, IMPLICIT_ASSERTION, key, nonce);
+        assert_eq!(token, encrypted);
+        let decrypted = decrypt(token, FOOTER, IMPLICIT_ASSERTION, key).unwrap();
+        assert_eq!(MESSAGE, &decrypted[..]);
+        assert_eq!(FOOTER, &decode_unverified_footer(token).unwrap()[..]);
+
+        let error = Err(Error::DecryptionFailed);
+        assert_eq!(error, decrypt(token, b"", IMPLICIT_ASSERTION, key));
+        assert_eq!(error, decrypt(token, FOOTER, b"", key));
+        assert_eq!(error, decrypt(token, FOOTER, IMPLICIT_ASSERTION, &[0; 32]));
+        let mut payload = decode(LOCAL_HEADER, token).unwrap().0;
+        for i in &[0, NONCE_LEN, payload.len() - 1] {
+            payload[*i] ^= 1;
+            let modified = encode(LOCAL_HEADER, &payload, FOOTER);
+            assert_eq!(error, decrypt(&modified, FOOTER, IMPLICIT_ASSERTION, key));
+            payload[*i] ^= 1;
+        }
+        let modified = encode(LOCAL_HEADER, &payload, b"{}");
+        assert_eq!(error, decrypt(&modified, b"{}", IMPLICIT_ASSERTION, key));
+    }
+
+    #[test]
+    fn test_public() {
+        // The key of the PASETO test vectors, with tokens generated with Python's cryptography
+        let secret_key = &array("b4cbfb43df4ce210727d953e4a713307fa19bb7d9f85041438d9e11b942a3774");
+        let public_key = &ed25519::gen_pk(secret_key).unwrap();
+        assert_eq!(
+            &array("1eb9dbbbbc047c03fd70604e0071f0987e16b28b757225c11f00415d0e20b1a2"),
+            public_key
+        );
+        let token = "v4.public.eyJkYXRhIjoidGhpcyBpcyBhIHNlY3JldCBtZXNzYWdlIiwiZXhwIjoiMjAyMi0wMS0wMVQ\
+                     wMDowMDowMCswMDowMCJ9xNYW5UtXhROUjF_KN8pOY986BLzsaoblneTb5ncJmXCOMQIIYnGJET9g3_XiiaQeV\
+                     rFWyVRDKcdl7b6sxMLxCw";
+        assert_eq!(token, sign(MESSAGE, b"", b"", secret_key, public_key));
+        assert_eq!(MESSAGE, &verify(token, b"", b"", public_key).unwrap()[..]);
+        assert!(decode_unverified_footer(token).unwrap().is_empty());
+
+        let token = "v4.public.eyJkYXRhIjoidGhpcyBpcyBhIHNlY3JldCBtZXNzYWdlIiwiZXhwIjoiMjAyMi0wMS0wMVQ\
+                     wMDowMDowMCswMDowMCJ9A69d4ZIlnp2YVAi_Zag1rxOXL3UL8OdEPXtJVzTBYS2WuL91XnyMhJOQf0TgAlMIt\
+                     nx1kzpKKXDU4YB2bfnMDg.eyJraWQiOiIxIn0";
+        let signed = sign(MESSAGE, FOOTER, IMPLICIT_ASSERTION, secret_key, public_key);
+        assert_eq!(token, signed);
+        let verified = verify(token, FOOTER, IMPLICIT_ASSERTION, public_key).unwrap();
+        assert_eq!(MESSAGE, &verified[..]);
+
+        let error = Err(Error::InvalidSignature);
+        assert_eq!(error, verify(token, b"", IMPLICIT_ASSERTION, public_key));
+        assert_eq!(error, verify(token, FOOTER, b"", public_key));
+        let other_key = &ed25519::gen_pk(&[0x42; 32]).unwrap();
+        assert_eq!(error, verify(token, FOOTER, IMPLICIT_ASSERTION, other_key));
+        let mut payload = decode(PUBLIC_HEADER, token).unwrap().0;
+        payload[0] ^= 1;
+        let modified = encode(PUBLIC_HEADER, &payload, FOOTER);
+        assert_eq!(
+            error,
+            verify(&modified, FOOTER, IMPLICIT_ASSERTION, public_key)
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let rng = &SystemRandom::new();
+        let key = &[0x42; 32];
+        let public_key = &ed25519::gen_pk(key).unwrap();
+        for message in &[&b""[..], MESSAGE] {
+            for footer in &[&b""[..], FOOTER] {
+                let token = encrypt(message, footer, IMPLICIT_ASSERTION, key, rng);
+                assert!(token.starts_with(LOCAL_HEADER));
+                let decrypted = decrypt(&token, footer, IMPLICIT_ASSERTION, key).unwrap();
+                assert_eq!(message, &&decrypted[..]);
+                assert_ne!(
+                    token,
+                    encrypt(message, footer, IMPLICIT_ASSERTION, key, rng)
+                );
+
+                let token = sign(message, footer, IMPLICIT_ASSERTION, key, public_key);
+                assert!(token.starts_with(PUBLIC_HEADER));
+                let verified = verify(&token, footer, IMPLICIT_ASSERTION, public_key).unwrap();
+                assert_eq!(message, &&verified[..]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_invalid() {
+        let rng = &SystemRandom::new();
+        let key = &[0x42; 32];
+        let public_key = &ed25519::gen_pk(key).unwrap();
+        let local = encrypt(MESSAGE, FOOTER, b"", key, rng);
+        let public = sign(MESSAGE, FOOTER, b"", key, public_key);
+        let error = Err(Error::InvalidEncoding);
+
+        // A token of one purpose can't be decoded as the other.
+        assert_eq!(error, decrypt(&public, FOOTER, b"", key));
+        assert_eq!(error, verify(&local, FOOTER, b"", public_key));
+        let (local_payload, public_payload) = (&local[9..local.len() - 16], &public[10..]);
+        let short = &base64::URL_SAFE_NO_PAD.encode(&[0; NONCE_LEN + TAG_LEN - 1]);
+        for token in &[
+            "",
+            "v4.local.",
+            "v3.local.AAAA",
+            "v4.local.AA",
+            &["v4.local.", short].concat(),
+            &["v4.local.", local_payload, "."].concat(),
+            &["v4.local.", local_payload, ".e30.e30"].concat(),
+            &["v4.local.", local_payload, ".e30="].concat(),
+            &["v4.local.", local_payload, "="].concat(),
+            &["V4.local.", local_payload].concat(),
+        ] {
+            assert_eq!(error, decrypt(token, b"", b"", key), "{}", token);
+        }
+        for token in &[
+            "v4.public.",
+            &["v4.public.", &short[..84]].concat(),
+            &["v4.public.", public_payload, "."].concat(),
+            &["v4.local.", public_payload].concat(),
+        ] {
+            assert_eq!(error, verify(token, b"", b"", public_key), "{}", token);
+        }
+        assert_eq!(error, decode_unverified_footer("v2.local.AAAA.e30"));
+        assert_eq!(error, decode_unverified_footer("v4.public.AAAA."));
+    }
+}
@@ -0,0 +1,102 @@
+//! Module for the PBKDF2 password-based key derivation function (RFC 8018) using HMAC.
+//!
+//! # Examples
+//!
+//! ```
+//! use crypto_pure::pbkdf2::derive;
+//! use crypto_pure::sha2::Sha256;
+//! # let password = b"correct horse battery staple";
+//! # let salt = b"A random value stored alongside.";
+//! let key = &mut [0; 32];
+//! derive::<Sha256>(password, salt, 100_000, key);
+//! ```
+use crate::hmac::Hmac;
+use crate::sha2::{HashFunction, MAX_DIGEST_SIZE};
+
+/// Derives a key from a password and salt by applying HMAC with `H` for `iterations` rounds.
+///
+/// The HMAC is keyed with the password once, and the keyed state is copied for each iteration.
+///
+/// # Panics
+///
+/// Panics if `iterations` is 0, or if `output.len()` is more than (2^32 - 1) times the digest size
+/// for `H`.
+pub fn derive<H: HashFunction>(password: &[u8], salt: &[u8], iterations: u32, output: &mut [u8]) {
+    assert!(iterations > 0);
+    let digest_size = H::DIGEST_SIZE;
+    assert!((output.len() as u64).div_ceil(digest_size as u64) <= u64::from(u32::MAX));
+    let keyed_hmac = Hmac::<H>::new(password);
+    for (i, chunk) in (1u32..).zip(output.chunks_mut(digest_size)) {
+        let mut hmac = keyed_hmac.clone();
+        hmac.update(salt);
+        hmac.update(&i.to_be_bytes());
+        let mut u = [0; MAX_DIGEST_SIZE];
+        u[..digest_size].copy_from_slice(&hmac.tag());
+        let mut block = u;
+        for _ in 1..iterations {
+            let mut hmac = keyed_hmac.clone();
+            hmac.update(&u[..digest_size]);
+            u[..digest_size].copy_from_slice(&hmac.tag());
+            for (block_byte, u_byte) in block.iter_mut().zip(&u[..digest_size]) {
+                *block_byte ^= u_byte;
+            }
+        }
+        chunk.copy_from_slice(&block[..chunk.len()]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sha2::{Sha256, Sha512};
+    use crate::test_helpers::*;
+
+    fn check<H: HashFunction>(password: &[u8], salt: &[u8], iterations: u32, expected: &str) {
+        let expected = h2b(expected);
+        let actual = &mut vec![0; expected.len()];
+        derive::<H>(password, salt, iterations, actual);
+        assert_eq!(&expected, actual);
+    }
+
+    #[test]
+    fn test_sha256() {
+        let expected = "120fb6cffcf8b32c43e7225256c4f837a86548c92ccc35480805987cb70be17b";
+        check::<Sha256>(b"password", b"salt", 1, expected);
+        let expected = "ae4d0c95af6b46d32d0adff928f06dd02a303f8ef3c251dfd6e2d85a95474c43";
+        check::<Sha256>(b"password", b"salt", 2, expected);
+        let expected = "c5e478d59288c841aa530db6845c4c8d962893a001ce4e11a4963873aa98134a";
+        check::<Sha256>(b"password", b"salt", 4096, expected);
+        let expected = "348c89dbcbd32b2f32d814b8116e84cf2b17347ebc1800181c4e2a1fb8dd53e1\
+                        c635518c7dac47e9";
+        let password = b"passwordPASSWORDpassword";
+        let salt = b"saltSALTsaltSALTsaltSALTsaltSALTsalt";
+        check::<Sha256>(password, salt, 4096, expected);
+        let expected = "89b69d0516f829893c696226650a8687";
+        check::<Sha256>(b"pass\0word", b"sa\0lt", 4096, expected);
+    }
+
+    #[test]
+    fn test_sha512() {
+        let expected = "867f70cf1ade02cff3752599a3a53dc4af34c7a669815ae5d513554e1c8cf252\
+                        c02d470a285a0501bad999bfe943c08f050235d7d68b1da55e63f73b60a57fce";
+        check::<Sha512>(b"password", b"salt", 1, expected);
+        let expected = "e1d9c16aa681708a45f5c7c4e215ceb66e011a2e9f0040713f18aefdb866d53c\
+                        f76cab2868a39b9f7840edce4fef5a82be67335c77a6068e04112754f27ccf4e";
+        check::<Sha512>(b"password", b"salt", 2, expected);
+        let expected = "8c0511f4c6e597c6ac6315d8f0362e225f3c501495ba23b868c005174dc4ee71\
+                        115b59f9e60cd9532fa33e0f75aefe30225c583a186cd82bd4daea9724a3d3b8\
+                        04f75bdd41494fa324cab24bcc680fb3";
+        let password = b"passwordPASSWORDpassword";
+        let salt = b"saltSALTsaltSALTsaltSALTsaltSALTsalt";
+        check::<Sha512>(password, salt, 4096, expected);
+    }
+
+    #[test]
+    fn test_prefix() {
+        let long = &mut [0; 100];
+        derive::<Sha256>(b"password", b"salt", 3, long);
+        let short = &mut [0; 33];
+        derive::<Sha256>(b"password", b"salt", 3, short);
+        assert_eq!(&long[..33], &short[..]);
+    }
+}
@@ -1,81 +1,271 @@
-//! Module for ChaCha20-Poly1305 authenticated encryption with associated data (AEAD).
-use crate::chacha20::ChaCha20;
-use crate::gcm::AeadCipher;
-use crate::util;
+//! Module for the Poly1305 one-time authenticator and ChaCha20-Poly1305 authenticated encryption
+//! with associated data (AEAD).
+use crate::aead::Aead;
+use crate::chacha20::{self, ChaCha20};
+use crate::ct;
+use crate::error::Error;
+use crate::hmac::MacError;
 use byteorder::{ByteOrder as _, LittleEndian};
 
-/// A ChaCha20-Poly1305 AEAD cipher.
+/// The Poly1305 one-time authenticator.
+///
+/// A key must only ever be used to authenticate a single message. Poly1305 is usually combined
+/// with a cipher that derives a fresh key for each message, as in ChaCha20-Poly1305.
+///
+/// # Examples
+///
+/// ```
+/// use crypto_pure::poly1305::{poly1305, Poly1305};
+/// # let key = &[0x42; 32];
+/// let mut mac = Poly1305::new(key).unwrap();
+/// mac.update(b"part one");
+/// mac.update(b"part two");
+/// let tag = mac.finalize();
+/// assert_eq!(poly1305(key, b"part onepart two"), Ok(tag));
+/// ```
+pub struct Poly1305 {
+    function: PolyFunction,
+    buffer: [u8; 16],
+    offset: usize,
+}
+
+/// Wrapper for obtaining the Poly1305 tag for a complete message.
+///
+/// # Errors
+///
+/// Fails like `Poly1305::new`.
+pub fn poly1305(key: &[u8], message: &[u8]) -> Result<[u8; 16], Error> {
+    let mut mac = Poly1305::new(key)?;
+    mac.update(message);
+    Ok(mac.finalize())
+}
+
+impl Poly1305 {
+    /// Initializes a Poly1305 authenticator given a one-time key.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::InvalidKey` if `key.len()` is not equal to 32.
+    pub fn new(key: &[u8]) -> Result<Self, Error> {
+        if key.len() != 32 {
+            return Err(Error::InvalidKey);
+        }
+        let mut key_copy = [0; 32];
+        key_copy.copy_from_slice(key);
+        Ok(Self {
+            function: PolyFunction::new(&key_copy),
+            buffer: [0; 16],
+            offset: 0,
+        })
+    }
+
+    /// Feeds input into the authenticator to update its state.
+    pub fn update(&mut self, input: &[u8]) {
+        let mut input = input;
+        if self.offset > 0 {
+            let buffer_space = 16 - self.offset;
+            if input.len() < buffer_space {
+                self.buffer[self.offset..self.offset + input.len()].copy_from_slice(input);
+                self.offset += input.len();
+                return;
+            }
+            self.buffer[self.offset..].copy_from_slice(&input[..buffer_space]);
+            self.function.process(&self.buffer);
+            self.offset = 0;
+            input = &input[buffer_space..];
+        }
+        for chunk in input.chunks(16) {
+            if chunk.len() < 16 {
+                self.buffer[..chunk.len()].copy_from_slice(chunk);
+                self.offset = chunk.len();
+            } else {
+                self.function.process(chunk);
+            }
+        }
+    }
+
+    /// Outputs the tag for the input fed so far.
+    pub fn finalize(mut self) -> [u8; 16] {
+        if self.offset > 0 {
+            self.function.process_partial(&self.buffer[..self.offset]);
+        }
+        let mut tag = [0; 16];
+        self.function.write_value(&mut tag);
+        tag
+    }
+
+    /// Verifies in constant time that `expected_tag` is the tag for the input fed so far.
+    pub fn verify(self, expected_tag: &[u8]) -> Result<(), MacError> {
+        if ct::constant_time_eq(&self.finalize(), expected_tag) {
+            Ok(())
+        } else {
+            Err(MacError)
+        }
+    }
+}
+
+/// A ChaCha20-Poly1305 AEAD cipher (RFC 8439).
+///
+/// # Examples
+///
+/// ```
+/// use crypto_pure::aead::Aead;
+/// use crypto_pure::poly1305::ChaCha20Poly1305;
+/// # let key = &[0x42; 32];
+/// # let nonce = &[0x24; 12];
+/// let cipher = ChaCha20Poly1305::new(key).unwrap();
+/// let buffer = &mut b"message".to_vec();
+/// let tag = cipher.seal_detached(nonce, b"header", buffer).unwrap();
+/// assert_eq!(Ok(()), cipher.open_detached(nonce, b"header", buffer, &tag));
+/// assert_eq!(b"message", &buffer[..]);
+/// ```
 pub struct ChaCha20Poly1305 {
     key: [u8; 32],
 }
 
-impl AeadCipher for ChaCha20Poly1305 {
+impl Aead for ChaCha20Poly1305 {
+    const KEY_LEN: usize = 32;
+    const NONCE_LEN: usize = 12;
+
     /// Initializes a ChaCha20-Poly1305 cipher given a key.
-    ///
-    /// # Panics
-    ///
-    /// Panics if `key.len()` is not equal to 32.
-    fn new(key: &[u8]) -> Self {
-        assert_eq!(32, key.len());
+    fn new(key: &[u8]) -> Result<Self, Error> {
+        if key.len() != 32 {
+            return Err(Error::InvalidKey);
+        }
         let mut key_copy = [0; 32];
         key_copy.copy_from_slice(key);
-        Self { key: key_copy }
+        Ok(Self { key: key_copy })
     }
 
-    /// Encrypts a message into a ciphertext and outputs a tag authenticating it and provided data.
-    ///
-    /// # Panics
-    ///
-    /// Panics if `input.len()` is not equal to `output.len()` or `nonce.len()` is not equal to 12.
-    fn encrypt(&self, input: &[u8], nonce: &[u8], data: &[u8], output: &mut [u8]) -> [u8; 16] {
-        assert_eq!(input.len(), output.len());
-        let cipher = &ChaCha20::new(&self.key, nonce);
-        self.process(cipher, input, output);
-        self.tag(cipher, output, data)
+    /// Encrypts a message in place and outputs a tag authenticating it and provided data. Fails
+    /// with `Error::InvalidLength` if `nonce.len()` is not equal to 12 or `buffer.len()` is
+    /// greater than 2^38 - 64.
+    fn seal_detached(
+        &self,
+        nonce: &[u8],
+        data: &[u8],
+        buffer: &mut [u8],
+    ) -> Result<[u8; 16], Error> {
+        let cipher = &self.cipher(nonce, buffer)?;
+        self.process(cipher, buffer);
+        Ok(self.tag(cipher, buffer, data))
     }
 
-    /// Decrypts a ciphertext into a message if tag verification passes.
-    ///
-    /// # Panics
-    ///
-    /// Panics if `input.len()` is not equal to `output.len()` or `nonce.len()` is not equal to 12.
-    fn decrypt(
+    /// Verifies `tag` and decrypts a ciphertext in place. On failure, `buffer` is left unchanged.
+    /// Fails with `Error::InvalidLength` if `nonce.len()` is not equal to 12 or `buffer.len()` is
+    /// greater than 2^38 - 64.
+    fn open_detached(
         &self,
-        input: &[u8],
         nonce: &[u8],
         data: &[u8],
-        tag: &[u8],
-        output: &mut [u8],
-    ) -> bool {
-        assert_eq!(output.len(), input.len());
-        let cipher = &ChaCha20::new(&self.key, nonce);
-        let expected_tag = self.tag(cipher, input, data);
-        if util::verify_16(&expected_tag, tag) {
-            self.process(cipher, input, output);
-            true
-        } else {
-            false
+        buffer: &mut [u8],
+        tag: &[u8; 16],
+    ) -> Result<(), Error> {
+        let cipher = &self.cipher(nonce, buffer)?;
+        let expected_tag = self.tag(cipher, buffer, data);
+        if !ct::constant_time_eq(&expected_tag, tag) {
+            return Err(Error::TagMismatch);
+        }
+        self.process(cipher, buffer);
+        Ok(())
+    }
+}
+
+/// An XChaCha20-Poly1305 AEAD cipher, which extends ChaCha20-Poly1305 to 24-byte nonces that are
+/// long enough to be chosen at random.
+///
+/// # Examples
+///
+/// ```
+/// use crypto_pure::aead::Aead;
+/// use crypto_pure::poly1305::XChaCha20Poly1305;
+/// # let key = &[0x42; 32];
+/// # let nonce = &[0x24; 24];
+/// let cipher = XChaCha20Poly1305::new(key).unwrap();
+/// let buffer = &mut b"message".to_vec();
+/// let tag = cipher.seal_detached(nonce, b"header", buffer).unwrap();
+/// assert_eq!(Ok(()), cipher.open_detached(nonce, b"header", buffer, &tag));
+/// assert_eq!(b"message", &buffer[..]);
+/// ```
+pub struct XChaCha20Poly1305 {
+    key: [u8; 32],
+}
+
+impl Aead for XChaCha20Poly1305 {
+    const KEY_LEN: usize = 32;
+    const NONCE_LEN: usize = 24;
+
+    /// Initializes an XChaCha20-Poly1305 cipher given a key.
+    fn new(key: &[u8]) -> Result<Self, Error> {
+        if key.len() != 32 {
+            return Err(Error::InvalidKey);
+        }
+        let mut key_copy = [0; 32];
+        key_copy.copy_from_slice(key);
+        Ok(Self { key: key_copy })
+    }
+
+    /// Encrypts a message in place and outputs a tag authenticating it and provided data. Fails
+    /// with `Error::InvalidLength` if `nonce.len()` is not equal to 24 or `buffer.len()` is
+    /// greater than 2^38 - 64.
+    fn seal_detached(
+        &self,
+        nonce: &[u8],
+        data: &[u8],
+        buffer: &mut [u8],
+    ) -> Result<[u8; 16], Error> {
+        let (cipher, nonce) = self.subcipher(nonce)?;
+        cipher.seal_detached(&nonce, data, buffer)
+    }
+
+    /// Verifies `tag` and decrypts a ciphertext in place. On failure, `buffer` is left unchanged.
+    /// Fails with `Error::InvalidLength` if `nonce.len()` is not equal to 24 or `buffer.len()` is
+    /// greater than 2^38 - 64.
+    fn open_detached(
+        &self,
+        nonce: &[u8],
+        data: &[u8],
+        buffer: &mut [u8],
+        tag: &[u8; 16],
+    ) -> Result<(), Error> {
+        let (cipher, nonce) = self.subcipher(nonce)?;
+        cipher.open_detached(&nonce, data, buffer, tag)
+    }
+}
+
+impl XChaCha20Poly1305 {
+    fn subcipher(&self, nonce: &[u8]) -> Result<(ChaCha20Poly1305, [u8; 12]), Error> {
+        if nonce.len() != 24 {
+            return Err(Error::InvalidLength);
         }
+        let subkey = chacha20::hchacha20(&self.key, &nonce[..16])?;
+        let mut subnonce = [0; 12];
+        subnonce[4..].copy_from_slice(&nonce[16..]);
+        Ok((ChaCha20Poly1305 { key: subkey }, subnonce))
     }
 }
 
 impl ChaCha20Poly1305 {
-    fn process(&self, cipher: &ChaCha20, input: &[u8], output: &mut [u8]) {
-        for (i, (input_chunk, output_chunk)) in
-            (1..).zip(input.chunks(64).zip(output.chunks_mut(64)))
-        {
+    fn cipher(&self, nonce: &[u8], message: &[u8]) -> Result<ChaCha20, Error> {
+        // The block counter starts at 1 for the message and must not wrap around.
+        if nonce.len() != 12 || 1 << 38 < message.len() as u64 + 64 {
+            return Err(Error::InvalidLength);
+        }
+        Ok(ChaCha20::new(&self.key, nonce))
+    }
+
+    fn process(&self, cipher: &ChaCha20, buffer: &mut [u8]) {
+        for (i, chunk) in (1..).zip(buffer.chunks_mut(64)) {
             let block = cipher.block(i);
-            for (block_byte, (input_byte, output_byte)) in
-                block.iter().zip(input_chunk.iter().zip(output_chunk))
-            {
-                *output_byte = input_byte ^ block_byte;
+            for (byte, block_byte) in chunk.iter_mut().zip(block.iter()) {
+                *byte ^= block_byte;
             }
         }
     }
 
     fn tag(&self, cipher: &ChaCha20, ciphertext: &[u8], data: &[u8]) -> [u8; 16] {
         let poly_key = &Self::poly_key_gen(cipher);
-        poly1305(poly_key, data, ciphertext)
+        aead_poly1305(poly_key, data, ciphertext)
     }
 
     fn poly_key_gen(cipher: &ChaCha20) -> [u8; 32] {
@@ -86,29 +276,29 @@ impl ChaCha20Poly1305 {
     }
 }
 
-fn poly1305(key: &[u8; 32], data: &[u8], ciphertext: &[u8]) -> [u8; 16] {
+fn aead_poly1305(key: &[u8; 32], data: &[u8], ciphertext: &[u8]) -> [u8; 16] {
     let mut tag = [0; 16];
-    let mut mac = Poly1305::new(key, data);
+    let mut mac = AeadPoly1305::new(key, data);
     mac.update(ciphertext);
     mac.write_tag(&mut tag);
     tag
 }
 
-struct Poly1305 {
+struct AeadPoly1305 {
     function: PolyFunction,
     data_len: u64,
     ciphertext_len: u64,
 }
 
-impl Poly1305 {
+impl AeadPoly1305 {
     fn new(key: &[u8; 32], data: &[u8]) -> Self {
-        let mut poly1305 = Self {
+        let mut mac = Self {
             function: PolyFunction::new(key),
             data_len: data.len() as u64,
             ciphertext_len: 0,
         };
-        poly1305.process(data);
-        poly1305
+        mac.process(data);
+        mac
     }
 
     fn update(&mut self, input: &[u8]) {
@@ -162,15 +352,24 @@ impl PolyFunction {
         }
     }
 
-    /// Does not support arbitrary input; assumes padding to 16 bytes for AEAD.
+    /// Processes a full 16-byte block.
     fn process(&mut self, input: &[u8]) {
         add(&mut self.h, input, 1);
         self.mulmod();
     }
 
+    /// Processes a final block of fewer than 16 bytes.
+    fn process_partial(&mut self, input: &[u8]) {
+        let mut block = [0; 16];
+        block[..input.len()].copy_from_slice(input);
+        block[input.len()] = 1;
+        add(&mut self.h, &block, 0);
+        self.mulmod();
+    }
+
     fn mulmod(&mut self) {
         let h_r = &mut [0; 17];
-        for i in 0..17 {
+        for (i, h_r_i) in h_r.iter_mut().enumerate() {
             let mut u = 0;
             for j in 0..i + 1 {
                 u += self.h[j] * u32::from(self.r[i - j]);
@@ -178,7 +377,7 @@ impl PolyFunction {
             for j in i + 1..17 {
                 u += 320 * self.h[j] * u32::from(self.r[i + 17 - j]);
             }
-            h_r[i] = u;
+            *h_r_i = u;
         }
         self.h.copy_from_slice(h_r);
         self.squeeze();
@@ -223,7 +422,8 @@ fn add(h: &mut [u32; 17], c: &[u8], last_byte: u8) {
         *h_j = u & 255;
         u >>= 8;
     }
-    h[16] += u + u32::from(last_byte);
+    u += h[16] + u32::from(last_byte);
+    h[16] = u & 255;
 }
 
 fn load_r(key: &[u8; 32]) -> [u8; 17] {
@@ -273,15 +473,57 @@ mod tests {
         let data = &h2b(data);
         let tag = &h2b(tag);
         let ciphertext = &h2b(ciphertext);
-        let chacha_poly = ChaCha20Poly1305::new(key);
+        let chacha_poly = ChaCha20Poly1305::new(key).unwrap();
         let encrypted_message = &mut vec![0; message.len()];
         let decrypted_ciphertext = &mut vec![0; ciphertext.len()];
         let actual_tag = chacha_poly.encrypt(message.as_bytes(), nonce, data, encrypted_message);
         assert_eq!(ciphertext, encrypted_message);
-        assert_eq!(tag, &actual_tag);
-        assert!(chacha_poly.decrypt(ciphertext, nonce, data, tag, decrypted_ciphertext,));
+        assert_eq!(tag, &actual_tag.unwrap());
+        let decrypted = chacha_poly.decrypt(ciphertext, nonce, data, tag, decrypted_ciphertext);
+        assert_eq!(Ok(()), decrypted);
         assert_eq!(message.as_bytes(), decrypted_ciphertext.as_slice());
-        // TODO: check that bad tags cause decryption to fail
+
+        let bad_tag = &mut tag.clone();
+        bad_tag[0] ^= 1;
+        let error = Err(Error::TagMismatch);
+        let decrypted = chacha_poly.decrypt(ciphertext, nonce, data, bad_tag, decrypted_ciphertext);
+        assert_eq!(error, decrypted);
+        let short_tag = &tag[1..];
+        let decrypted =
+            chacha_poly.decrypt(ciphertext, nonce, data, short_tag, decrypted_ciphertext);
+        assert_eq!(error, decrypted);
+
+        let buffer = &mut [message.as_bytes(), &[0; 16]].concat();
+        chacha_poly.seal_in_place(nonce, data, buffer).unwrap();
+        let sealed = &[&ciphertext[..], tag].concat();
+        assert_eq!(sealed, buffer);
+        let opened = chacha_poly.open_in_place(nonce, data, buffer);
+        assert_eq!(Ok(message.as_bytes()), opened.map(|x| &*x));
+        buffer.copy_from_slice(sealed);
+        buffer[0] ^= 1;
+        let opened = chacha_poly.open_in_place(nonce, data, buffer);
+        assert_eq!(error, opened.map(|_| ()));
+        buffer[0] ^= 1;
+        assert_eq!(sealed, buffer);
+        let opened = chacha_poly.open_in_place(nonce, data, &mut buffer[1..]);
+        assert_eq!(error, opened.map(|_| ()));
+        assert_eq!(
+            Err(Error::InvalidLength),
+            chacha_poly.open_in_place(&nonce[1..], data, buffer)
+        );
+
+        let buffer = &mut message.as_bytes().to_vec();
+        let detached_tag = chacha_poly.seal_detached(nonce, data, buffer).unwrap();
+        assert_eq!(ciphertext, buffer);
+        assert_eq!(tag, &detached_tag);
+        let bad_tag = &mut detached_tag.clone();
+        bad_tag[0] ^= 1;
+        let opened = chacha_poly.open_detached(nonce, data, buffer, bad_tag);
+        assert_eq!(Err(Error::TagMismatch), opened);
+        assert_eq!(ciphertext, buffer);
+        let opened = chacha_poly.open_detached(nonce, data, buffer, &detached_tag);
+        assert_eq!(Ok(()), opened);
+        assert_eq!(message.as_bytes(), buffer.as_slice());
 
         check_poly_key_gen(poly_key, key, nonce);
     }
@@ -322,6 +564,43 @@ mod tests {
         check_encrypt(key, nonce, data, message, ciphertext, tag, poly_key);
     }
 
+    #[test]
+    fn test_xchacha20_poly1305() {
+        let key: &Vec<_> = &(0x80..0xa0).collect();
+        let nonce = &h2b("404142434445464748494a4b4c4d4e4f5051525354555657");
+        let data = &h2b("50515253c0c1c2c3c4c5c6c7");
+        let message = b"Ladies and Gentlemen of the class of '99: If I could offer you only one \
+                        tip for the future, sunscreen would be it.";
+        let ciphertext = &h2b(
+            "bd6d179d3e83d43b9576579493c0e939572a1700252bfaccbed2902c21396cbb\
+             731c7f1b0b4aa6440bf3a82f4eda7e39ae64c6708c54c216cb96b72e1213b452\
+             2f8c9ba40db5d945b11b69b982c1bb9e3f3fac2bc369488f76b2383565d3fff9\
+             21f9664c97637da9768812f615c68b13b52e",
+        );
+        let tag = &h2b("c0875924c1c7987947deafd8780acf49");
+        let cipher = XChaCha20Poly1305::new(key).unwrap();
+
+        let output = &mut vec![0; message.len()];
+        assert_eq!(tag, &cipher.encrypt(message, nonce, data, output).unwrap());
+        assert_eq!(ciphertext, output);
+        assert_eq!(Ok(()), cipher.decrypt(ciphertext, nonce, data, tag, output));
+        assert_eq!(&message[..], &output[..]);
+
+        let buffer = &mut [&message[..], &[0; 16]].concat();
+        cipher.seal_in_place(nonce, data, buffer).unwrap();
+        assert_eq!(&[&ciphertext[..], tag].concat(), buffer);
+        let bad_nonce = &mut nonce.clone();
+        bad_nonce[0] ^= 1;
+        let opened = cipher.open_in_place(bad_nonce, data, buffer);
+        assert_eq!(Err(Error::TagMismatch), opened.map(|x| &*x));
+        let opened = cipher.open_in_place(&nonce[..12], data, buffer);
+        assert_eq!(Err(Error::InvalidLength), opened.map(|x| &*x));
+        let error = Err(Error::InvalidKey);
+        assert_eq!(error, XChaCha20Poly1305::new(&key[1..]).map(|_| ()));
+        let opened = cipher.open_in_place(nonce, data, buffer);
+        assert_eq!(Ok(&message[..]), opened.map(|x| &*x));
+    }
+
     #[test]
     fn test_poly_key_gen() {
         let key: &Vec<_> = &(0x80..0xa0).collect();
@@ -346,6 +625,92 @@ mod tests {
         check_poly_key_gen(expected, key, nonce);
     }
 
+    fn check_poly1305(key: &str, message: &[u8], tag: &str) {
+        let key = &h2b(key);
+        let tag = &h2b(tag);
+        assert_eq!(tag, &poly1305(key, message).unwrap());
+        let error = Err(Error::InvalidKey);
+        assert_eq!(error, poly1305(&key[1..], message));
+
+        for chunk_size in &[1, 3, 16, 17] {
+            let mut mac = Poly1305::new(key).unwrap();
+            for chunk in message.chunks(*chunk_size) {
+                mac.update(chunk);
+            }
+            assert_eq!(tag, &mac.finalize());
+        }
+
+        let mut mac = Poly1305::new(key).unwrap();
+        mac.update(message);
+        assert_eq!(Ok(()), mac.verify(tag));
+        let bad_tag = &mut tag.clone();
+        bad_tag[15] ^= 1;
+        let mut mac = Poly1305::new(key).unwrap();
+        mac.update(message);
+        assert_eq!(Err(MacError), mac.verify(bad_tag));
+    }
+
+    #[test]
+    fn test_poly1305() {
+        let key = "85d6be7857556d337f4452fe42d506a80103808afb0db2fd4abff6af4149f51b";
+        let message = b"Cryptographic Forum Research Group";
+        let tag = "a8061dc1305136c6c22b8baf0c0127a9";
+        check_poly1305(key, message, tag);
+
+        let key = "0000000000000000000000000000000000000000000000000000000000000000";
+        let tag = "00000000000000000000000000000000";
+        check_poly1305(key, &[0; 64], tag);
+
+        let message = b"Any submission to the IETF intended by the Contributor for publication as \
+            all or part of an IETF Internet-Draft or RFC and any statement made within the \
+            context of an IETF activity is considered an \"IETF Contribution\". Such statements \
+            include oral statements in IETF sessions, as well as written and electronic \
+            communications made at any time or place, which are addressed to";
+        let key = "0000000000000000000000000000000036e5f6b5c5e06070f0efca96227a863e";
+        let tag = "36e5f6b5c5e06070f0efca96227a863e";
+        check_poly1305(key, message, tag);
+
+        let key = "36e5f6b5c5e06070f0efca96227a863e00000000000000000000000000000000";
+        let tag = "f3477e7cd95417af89a6b8794c310cf0";
+        check_poly1305(key, message, tag);
+
+        let key = "1c9240a5eb55d38af333888604f6b5f0473917c1402b80099dca5cbc207075c0";
+        let message = b"'Twas brillig, and the slithy toves\nDid gyre and gimble in the wabe:\n\
+            All mimsy were the borogoves,\nAnd the mome raths outgrabe.";
+        let tag = "4541669a7eaaee61e708dc7cbcc5eb62";
+        check_poly1305(key, message, tag);
+    }
+
+    #[test]
+    fn test_poly1305_edge_cases() {
+        let key = "0200000000000000000000000000000000000000000000000000000000000000";
+        let message = &h2b("ffffffffffffffffffffffffffffffff");
+        let tag = "03000000000000000000000000000000";
+        check_poly1305(key, message, tag);
+
+        let key = "02000000000000000000000000000000ffffffffffffffffffffffffffffffff";
+        let message = &h2b("02000000000000000000000000000000");
+        check_poly1305(key, message, tag);
+
+        let key = "0100000000000000000000000000000000000000000000000000000000000000";
+        let message = &h2b("ffffffffffffffffffffffffffffffff\
+             f0ffffffffffffffffffffffffffffff\
+             11000000000000000000000000000000");
+        let tag = "05000000000000000000000000000000";
+        check_poly1305(key, message, tag);
+
+        let message = &h2b("ffffffffffffffffffffffffffffffff\
+             fbfefefefefefefefefefefefefefefe\
+             01010101010101010101010101010101");
+        let tag = "00000000000000000000000000000000";
+        check_poly1305(key, message, tag);
+
+        let key = "0200000000000000000000000000000000000000000000000000000000000000";
+        let message = &h2b("fdffffffffffffffffffffffffffffff");
+        let tag = "faffffffffffffffffffffffffffffff";
+        check_poly1305(key, message, tag);
+    }
+
     #[test]
     fn test_poly() {
         let key = &mut [0; 32];
@@ -360,7 +725,7 @@ mod tests {
              3ff4def08e4b7a9de576d26586cec64b6116",
         );
         let tag = &h2b("1ae10b594f09e26a7e902ecbd0600691");
-        let actual = poly1305(key, data, ciphertext);
+        let actual = aead_poly1305(key, data, ciphertext);
         assert_eq!(tag, &actual);
 
         let input = h2b(
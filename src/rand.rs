@@ -0,0 +1,416 @@
+//! Module for sources of cryptographically secure random bytes.
+//!
+//! Functions in this crate that need randomness, such as key generation, randomized signatures
+//! and random nonces, take a `&dyn SecureRandom`. `SystemRandom` reads from the operating system,
+//! `thread_csprng` expands seeds from it in userspace for bulk use, and any `Fn(&mut [u8])` closure
+//! can be used in their place, for example to supply fixed bytes in tests.
+//!
+//! # Examples
+//!
+//! ```
+//! use crypto_pure::rand::{SecureRandom, SystemRandom};
+//! let rng = SystemRandom::new();
+//! let key = &mut [0; 32];
+//! rng.fill(key);
+//! ```
+#[cfg(feature = "std")]
+use crate::chacha20::Stream;
+#[cfg(feature = "std")]
+use core::cell::RefCell;
+use core::fmt;
+#[cfg(feature = "std")]
+use std::process;
+
+/// A source of cryptographically secure random bytes.
+pub trait SecureRandom {
+    /// Fills a buffer with random bytes.
+    ///
+    /// # Panics
+    ///
+    /// Implementations may panic if no random bytes can be obtained.
+    fn fill(&self, dest: &mut [u8]);
+}
+
+impl<F: Fn(&mut [u8])> SecureRandom for F {
+    fn fill(&self, dest: &mut [u8]) {
+        self(dest)
+    }
+}
+
+/// An error returned when the operating system fails to provide random bytes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RandomError;
+
+impl fmt::Display for RandomError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("random number generation failed")
+    }
+}
+
+impl core::error::Error for RandomError {}
+
+/// The random number generator of the operating system: `getrandom(2)` on Linux and Android,
+/// `getentropy` on macOS, iOS, the BSDs and illumos, and `BCryptGenRandom` on Windows.
+///
+/// There is no state to keep, so an instance can be created wherever it is needed. On other
+/// platforms, `try_fill` always fails.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemRandom(());
+
+impl SystemRandom {
+    /// Creates a handle to the random number generator of the operating system.
+    pub fn new() -> Self {
+        Self(())
+    }
+
+    /// Fills a buffer with random bytes, or fails if the operating system cannot provide them.
+    pub fn try_fill(&self, dest: &mut [u8]) -> Result<(), RandomError> {
+        sys::fill(dest)
+    }
+}
+
+impl SecureRandom for SystemRandom {
+    /// # Panics
+    ///
+    /// Panics if the operating system cannot provide random bytes.
+    fn fill(&self, dest: &mut [u8]) {
+        self.try_fill(dest)
+            .expect("random number generation failed")
+    }
+}
+
+/// The number of bytes a thread's CSPRNG outputs before it is reseeded from the operating system.
+#[cfg(feature = "std")]
+const RESEED_LEN: u64 = 1 << 20;
+
+/// The number of bytes of keystream buffered for small requests.
+#[cfg(feature = "std")]
+const BUFFER_LEN: usize = 256;
+
+#[cfg(feature = "std")]
+std::thread_local! {
+    static THREAD_CSPRNG: RefCell<ChaChaRng> = RefCell::new(ChaChaRng::new());
+}
+
+/// Outputs a handle to the CSPRNG of the current thread, which is much faster than
+/// `SystemRandom` for generating many small values such as nonces and salts.
+///
+/// Each thread has its own ChaCha20 generator, seeded from `SystemRandom` when first used and
+/// reseeded after every mebibyte of output. After a `fork`, the child reseeds before its next
+/// output, so it never repeats the parent's. The key is replaced as soon as it is used ("fast key
+/// erasure"), and output bytes are erased from the buffer, so the current state reveals nothing
+/// about earlier outputs.
+///
+/// # Panics
+///
+/// The handle panics when used if the operating system cannot provide a seed.
+///
+/// # Examples
+///
+/// ```
+/// use crypto_pure::rand::{thread_csprng, SecureRandom};
+/// let salt = &mut [0; 16];
+/// thread_csprng().fill(salt);
+/// ```
+#[cfg(feature = "std")]
+pub fn thread_csprng() -> ThreadCsprng {
+    ThreadCsprng(())
+}
+
+/// A handle to the CSPRNG of the current thread, returned by `thread_csprng`.
+#[cfg(feature = "std")]
+#[derive(Clone, Copy, Debug)]
+pub struct ThreadCsprng(());
+
+#[cfg(feature = "std")]
+impl SecureRandom for ThreadCsprng {
+    fn fill(&self, dest: &mut [u8]) {
+        THREAD_CSPRNG.with(|rng| rng.borrow_mut().fill(dest))
+    }
+}
+
+/// A ChaCha20 generator with fast key erasure: every use of the key starts by replacing it with
+/// the first 32 bytes of keystream.
+#[cfg(feature = "std")]
+struct ChaChaRng {
+    key: [u8; 32],
+    buffer: [u8; BUFFER_LEN],
+    index: usize,
+    output_len: u64,
+    pid: u32,
+}
+
+#[cfg(feature = "std")]
+impl ChaChaRng {
+    fn new() -> Self {
+        let mut rng = Self {
+            key: [0; 32],
+            buffer: [0; BUFFER_LEN],
+            index: BUFFER_LEN,
+            output_len: 0,
+            pid: 0,
+        };
+        rng.reseed();
+        rng
+    }
+
+    fn reseed(&mut self) {
+        SystemRandom::new().fill(&mut self.key);
+        self.buffer = [0; BUFFER_LEN];
+        self.index = BUFFER_LEN;
+        self.output_len = 0;
+        self.pid = process::id();
+    }
+
+    fn fill(&mut self, dest: &mut [u8]) {
+        // Chunks keep the keystream of a single key well short of the ChaCha20 counter limit.
+        for chunk in dest.chunks_mut(RESEED_LEN as usize) {
+            if self.output_len >= RESEED_LEN || self.pid != process::id() {
+                self.reseed();
+            }
+            self.output_len += chunk.len() as u64;
+            if chunk.len() > BUFFER_LEN {
+                chunk.iter_mut().for_each(|byte| *byte = 0);
+                self.next_stream()
+                    .apply_keystream(chunk)
+                    .expect("keystream exhausted");
+            } else {
+                self.fill_from_buffer(chunk);
+            }
+        }
+    }
+
+    fn fill_from_buffer(&mut self, mut dest: &mut [u8]) {
+        while !dest.is_empty() {
+            if self.index == BUFFER_LEN {
+                self.buffer = [0; BUFFER_LEN];
+                self.next_stream()
+                    .apply_keystream(&mut self.buffer)
+                    .expect("keystream exhausted");
+                self.index = 0;
+            }
+            let len = dest.len().min(BUFFER_LEN - self.index);
+            let output = &mut self.buffer[self.index..self.index + len];
+            dest[..len].copy_from_slice(output);
+            output.iter_mut().for_each(|byte| *byte = 0);
+            self.index += len;
+            dest = &mut dest[len..];
+        }
+    }
+
+    /// Outputs a stream under the current key after replacing the key with its first 32 bytes.
+    fn next_stream(&mut self) -> Stream {
+        let mut stream = Stream::new(&self.key, &[0; 12]).expect("invalid key or nonce length");
+        self.key = [0; 32];
+        stream
+            .apply_keystream(&mut self.key)
+            .expect("keystream exhausted");
+        stream
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+mod sys {
+    use super::RandomError;
+    use core::ffi::{c_int, c_uint, c_void};
+
+    const EINTR: c_int = 4;
+
+    extern "C" {
+        fn getrandom(buf: *mut c_void, buflen: usize, flags: c_uint) -> isize;
+        #[cfg_attr(target_os = "android", link_name = "__errno")]
+        fn __errno_location() -> *mut c_int;
+    }
+
+    pub(super) fn fill(mut dest: &mut [u8]) -> Result<(), RandomError> {
+        // Reads of more than 256 bytes can be cut short by signals.
+        while !dest.is_empty() {
+            let result = unsafe { getrandom(dest.as_mut_ptr().cast(), dest.len(), 0) };
+            if result < 0 {
+                if unsafe { *__errno_location() } == EINTR {
+                    continue;
+                }
+                return Err(RandomError);
+            }
+            dest = &mut dest[result as usize..];
+        }
+        Ok(())
+    }
+}
+
+#[cfg(any(
+    target_os = "macos",
+    target_os = "ios",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd",
+    target_os = "dragonfly",
+    target_os = "illumos",
+    target_os = "solaris"
+))]
+mod sys {
+    use super::RandomError;
+    use core::ffi::{c_int, c_void};
+
+    extern "C" {
+        fn getentropy(buf: *mut c_void, buflen: usize) -> c_int;
+    }
+
+    pub(super) fn fill(dest: &mut [u8]) -> Result<(), RandomError> {
+        // At most 256 bytes can be requested at a time.
+        for chunk in dest.chunks_mut(256) {
+            if unsafe { getentropy(chunk.as_mut_ptr().cast(), chunk.len()) } != 0 {
+                return Err(RandomError);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(windows)]
+mod sys {
+    use super::RandomError;
+    use core::ffi::c_void;
+    use core::ptr;
+
+    const BCRYPT_USE_SYSTEM_PREFERRED_RNG: u32 = 2;
+
+    #[link(name = "bcrypt")]
+    extern "system" {
+        fn BCryptGenRandom(
+            algorithm: *mut c_void,
+            buffer: *mut u8,
+            buffer_len: u32,
+            flags: u32,
+        ) -> i32;
+    }
+
+    pub(super) fn fill(dest: &mut [u8]) -> Result<(), RandomError> {
+        for chunk in dest.chunks_mut(u32::MAX as usize) {
+            let status = unsafe {
+                BCryptGenRandom(
+                    ptr::null_mut(),
+                    chunk.as_mut_ptr(),
+                    chunk.len() as u32,
+                    BCRYPT_USE_SYSTEM_PREFERRED_RNG,
+                )
+            };
+            if status < 0 {
+                return Err(RandomError);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(not(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "macos",
+    target_os = "ios",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd",
+    target_os = "dragonfly",
+    target_os = "illumos",
+    target_os = "solaris",
+    windows
+)))]
+mod sys {
+    use super::RandomError;
+
+    pub(super) fn fill(_dest: &mut [u8]) -> Result<(), RandomError> {
+        Err(RandomError)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_system_random() {
+        let rng = SystemRandom::new();
+        let a = &mut [0; 64];
+        let b = &mut [0; 64];
+        rng.fill(a);
+        rng.fill(b);
+        assert_ne!(a, b);
+        assert_ne!(&[0; 64], a);
+
+        // Longer than a single getentropy call
+        let long = &mut [0; 1000];
+        rng.try_fill(long).unwrap();
+        assert!(long[900..].iter().any(|byte| *byte != 0));
+        rng.try_fill(&mut []).unwrap();
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_thread_csprng() {
+        let rng = thread_csprng();
+        let a = &mut [0; 16];
+        let b = &mut [0; 16];
+        rng.fill(a);
+        rng.fill(b);
+        assert_ne!(a, b);
+        let long = &mut [0; 1000];
+        rng.fill(long);
+        assert!(long[900..].iter().any(|byte| *byte != 0));
+
+        let other = std::thread::spawn(move || {
+            let c = &mut [0; 16];
+            thread_csprng().fill(c);
+            *c
+        });
+        assert_ne!(a, &other.join().unwrap());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_chacha_rng() {
+        let key = [0x42; 32];
+        let keystream = &mut [0; 32 + 1000];
+        Stream::new(&key, &[0; 12])
+            .unwrap()
+            .apply_keystream(keystream)
+            .unwrap();
+
+        // Small requests come from the buffer, and large ones directly from a new stream.
+        let mut rng = ChaChaRng::new();
+        rng.key = key;
+        let output = &mut [0; 100];
+        rng.fill(&mut output[..40]);
+        rng.fill(&mut output[40..]);
+        assert_eq!(&keystream[32..132], &output[..]);
+        assert_eq!(&keystream[..32], &rng.key);
+        assert!(rng.buffer[..100].iter().all(|byte| *byte == 0));
+
+        rng.key = key;
+        let output = &mut [0; 1000];
+        rng.fill(output);
+        assert_eq!(&keystream[32..], &output[..]);
+        assert_eq!(&keystream[..32], &rng.key);
+        assert_eq!(1100, rng.output_len);
+
+        // A child process after a fork has a different ID.
+        rng.pid = rng.pid.wrapping_add(1);
+        rng.fill(&mut [0; 1]);
+        assert_eq!(process::id(), rng.pid);
+        assert_eq!(1, rng.output_len);
+
+        rng.output_len = RESEED_LEN;
+        rng.key = key;
+        rng.fill(output);
+        assert_ne!(&keystream[32..], &output[..]);
+        assert_eq!(1000, rng.output_len);
+    }
+
+    #[test]
+    fn test_closure() {
+        let rng: &dyn SecureRandom = &|buffer: &mut [u8]| buffer.iter_mut().for_each(|b| *b = 7);
+        let buffer = &mut [0; 3];
+        rng.fill(buffer);
+        assert_eq!(&[7; 3], buffer);
+    }
+}
@@ -0,0 +1,189 @@
+//! Module implementing the traits of the `rand_core` crate for the crate's random number
+//! generators, so they can be passed to third-party code that takes an `Rng` or `CryptoRng`.
+//!
+//! `SystemRandom` and the DRBGs implement `TryRng` and `TryCryptoRng` with `Error` as the error
+//! type, failing with `Error::RandomFailure` if the operating system cannot provide random bytes
+//! and with `Error::ReseedRequired` once a DRBG must be reseeded, which takes 2^48 requests.
+//! `UnwrapErr` turns them into an `Rng` and `CryptoRng` that panic instead. The thread CSPRNG,
+//! which already panics if it cannot be seeded, implements `Rng` and `CryptoRng` directly. The
+//! `rand_core` crate is re-exported so that its version matches.
+//!
+//! # Examples
+//!
+//! ```
+//! use crypto_pure::rand::SystemRandom;
+//! use crypto_pure::rand_core_compat::rand_core::{CryptoRng, UnwrapErr};
+//! fn generate_key(rng: &mut impl CryptoRng) -> [u8; 32] {
+//!     let mut key = [0; 32];
+//!     rng.fill_bytes(&mut key);
+//!     key
+//! }
+//! let key = generate_key(&mut UnwrapErr(SystemRandom::new()));
+//! ```
+use crate::ctr_drbg::{self, CtrDrbg};
+use crate::error::Error;
+use crate::hmac_drbg::{self, HmacDrbg};
+use crate::rand::SystemRandom;
+#[cfg(feature = "std")]
+use crate::rand::{SecureRandom, ThreadCsprng};
+use crate::sha2::HashFunction;
+#[cfg(feature = "std")]
+use core::convert::Infallible;
+use rand_core::{TryCryptoRng, TryRng};
+
+pub use rand_core;
+
+macro_rules! impl_rng {
+    ($rng:ty, $error:ty, |$self:ident, $dest:ident| $fill:expr) => {
+        impl TryRng for $rng {
+            type Error = $error;
+
+            fn try_next_u32(&mut self) -> Result<u32, $error> {
+                let mut bytes = [0; 4];
+                self.try_fill_bytes(&mut bytes)?;
+                Ok(u32::from_le_bytes(bytes))
+            }
+
+            fn try_next_u64(&mut self) -> Result<u64, $error> {
+                let mut bytes = [0; 8];
+                self.try_fill_bytes(&mut bytes)?;
+                Ok(u64::from_le_bytes(bytes))
+            }
+
+            fn try_fill_bytes(&mut $self, $dest: &mut [u8]) -> Result<(), $error> {
+                $fill
+            }
+        }
+
+        impl TryCryptoRng for $rng {}
+    };
+}
+
+impl_rng!(SystemRandom, Error, |self, dest| Ok(self.try_fill(dest)?));
+#[cfg(feature = "std")]
+impl_rng!(ThreadCsprng, Infallible, |self, dest| {
+    self.fill(dest);
+    Ok(())
+});
+impl_rng!(CtrDrbg, Error, |self, dest| {
+    for chunk in dest.chunks_mut(ctr_drbg::MAX_REQUEST_LEN) {
+        self.generate(chunk, b"")?;
+    }
+    Ok(())
+});
+
+impl<H: HashFunction> TryRng for HmacDrbg<H> {
+    type Error = Error;
+
+    fn try_next_u32(&mut self) -> Result<u32, Error> {
+        let mut bytes = [0; 4];
+        self.try_fill_bytes(&mut bytes)?;
+        Ok(u32::from_le_bytes(bytes))
+    }
+
+    fn try_next_u64(&mut self) -> Result<u64, Error> {
+        let mut bytes = [0; 8];
+        self.try_fill_bytes(&mut bytes)?;
+        Ok(u64::from_le_bytes(bytes))
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        for chunk in dest.chunks_mut(hmac_drbg::MAX_REQUEST_LEN) {
+            self.generate(chunk, b"")?;
+        }
+        Ok(())
+    }
+}
+
+impl<H: HashFunction> TryCryptoRng for HmacDrbg<H> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "std")]
+    use crate::rand::thread_csprng;
+    use crate::sha2::{Sha256, Sha512};
+    use rand_core::{CryptoRng, UnwrapErr};
+
+    fn generate(rng: &mut impl CryptoRng) -> ([u8; 100], u32, u64) {
+        let mut bytes = [0; 100];
+        rng.fill_bytes(&mut bytes);
+        (bytes, rng.next_u32(), rng.next_u64())
+    }
+
+    #[test]
+    fn test_ctr_drbg() {
+        let mut expected = CtrDrbg::new(&[0x42; 32], &[0x24; 16], b"").unwrap();
+        let drbg = CtrDrbg::new(&[0x42; 32], &[0x24; 16], b"").unwrap();
+        let (bytes, word, double_word) = generate(&mut UnwrapErr(drbg));
+        let expected_bytes = &mut [0; 100];
+        expected.generate(expected_bytes, b"").unwrap();
+        assert_eq!(expected_bytes, &bytes);
+        let expected_word = &mut [0; 4];
+        expected.generate(expected_word, b"").unwrap();
+        assert_eq!(u32::from_le_bytes(*expected_word), word);
+        let expected_double_word = &mut [0; 8];
+        expected.generate(expected_double_word, b"").unwrap();
+        assert_eq!(u64::from_le_bytes(*expected_double_word), double_word);
+    }
+
+    #[test]
+    fn test_ctr_drbg_long() {
+        // Longer requests are split to stay within the limit of `generate`.
+        let drbg = &mut CtrDrbg::new(&[0x42; 32], &[0x24; 16], b"").unwrap();
+        let long = &mut [0; ctr_drbg::MAX_REQUEST_LEN + 1];
+        drbg.try_fill_bytes(long).unwrap();
+        let expected = &mut CtrDrbg::new(&[0x42; 32], &[0x24; 16], b"").unwrap();
+        let expected_long = &mut [0; ctr_drbg::MAX_REQUEST_LEN];
+        expected.generate(expected_long, b"").unwrap();
+        assert_eq!(expected_long[..], long[..ctr_drbg::MAX_REQUEST_LEN]);
+    }
+
+    #[test]
+    fn test_ctr_drbg_reseed_required() {
+        let mut drbg = CtrDrbg::new(&[0x42; 32], &[0x24; 16], b"").unwrap();
+        drbg.reseed_counter = ctr_drbg::RESEED_INTERVAL + 1;
+        assert_eq!(Err(Error::ReseedRequired), drbg.try_next_u32());
+        drbg.reseed(&[0x42; 32], b"").unwrap();
+        assert!(drbg.try_next_u32().is_ok());
+    }
+
+    #[test]
+    fn test_hmac_drbg() {
+        fn check<H: HashFunction>() {
+            let mut expected = HmacDrbg::<H>::new(&[0x42; 32], &[0x24; 16], b"").unwrap();
+            let drbg = HmacDrbg::<H>::new(&[0x42; 32], &[0x24; 16], b"").unwrap();
+            let (bytes, word, double_word) = generate(&mut UnwrapErr(drbg));
+            let expected_bytes = &mut [0; 100];
+            expected.generate(expected_bytes, b"").unwrap();
+            assert_eq!(expected_bytes, &bytes);
+            let expected_word = &mut [0; 4];
+            expected.generate(expected_word, b"").unwrap();
+            assert_eq!(u32::from_le_bytes(*expected_word), word);
+            let expected_double_word = &mut [0; 8];
+            expected.generate(expected_double_word, b"").unwrap();
+            assert_eq!(u64::from_le_bytes(*expected_double_word), double_word);
+
+            let mut drbg = HmacDrbg::<H>::new(&[0x42; 32], &[0x24; 16], b"").unwrap();
+            drbg.reseed_counter = hmac_drbg::RESEED_INTERVAL + 1;
+            assert_eq!(Err(Error::ReseedRequired), drbg.try_next_u64());
+        }
+        check::<Sha256>();
+        check::<Sha512>();
+    }
+
+    #[test]
+    fn test_system_random() {
+        let (first, _, _) = generate(&mut UnwrapErr(SystemRandom::new()));
+        let (second, _, _) = generate(&mut UnwrapErr(SystemRandom::new()));
+        assert_ne!(first, second);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_thread_csprng() {
+        let (first, _, _) = generate(&mut thread_csprng());
+        let (second, _, _) = generate(&mut thread_csprng());
+        assert_ne!(first, second);
+    }
+}
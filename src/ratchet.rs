@@ -0,0 +1,774 @@
+//! Module for the X3DH key agreement and the Double Ratchet algorithm, as specified by Signal.
+//!
+//! X3DH lets an initiator agree on a shared secret with a responder who may be offline, using a
+//! `PreKeyBundle` the responder published: their identity key, a signed prekey, and optionally a
+//! one-time prekey. The initiator sends an `InitialMessage` along with their first ratchet
+//! message so that the responder can derive the same secret.
+//!
+//! Identity keys are Ed25519 keys, which sign the prekeys and are converted to X25519 keys for
+//! key agreement, rather than XEdDSA keys. A `Session` then encrypts messages with the Double
+//! Ratchet using X25519, HKDF-SHA-256, HMAC-SHA-256 chain keys and AES-256-GCM, starting from the
+//! shared secret and the responder's signed prekey as their first ratchet key. Messages may be
+//! received out of order: the keys of skipped messages are cached until they arrive.
+//!
+//! # Examples
+//!
+//! ```
+//! use crypto_pure::curve25519;
+//! use crypto_pure::ratchet::{self, IdentityKey, PreKeyBundle, Session};
+//! use crypto_pure::rand::SystemRandom;
+//! # let signed_prekey = &[0x42; 32];
+//! let rng = &SystemRandom::new();
+//! let alice = IdentityKey::generate(rng);
+//! let bob = IdentityKey::generate(rng);
+//! let prekey = curve25519::gen_pk(signed_prekey)?;
+//! let bundle = PreKeyBundle {
+//!     identity_key: *bob.public_key(),
+//!     signed_prekey: prekey,
+//!     signature: bob.sign_prekey(&prekey),
+//!     one_time_prekey: None,
+//! };
+//!
+//! let (agreement, initial_message) = ratchet::x3dh_initiate(&alice, &bundle, b"example", rng)?;
+//! let mut alice_session = Session::initiate(&agreement, &bundle.signed_prekey, rng)?;
+//! let message = alice_session.encrypt(b"hello")?;
+//!
+//! let agreement =
+//!     ratchet::x3dh_respond(&bob, signed_prekey, None, &initial_message, b"example")?;
+//! let mut bob_session = Session::respond(&agreement, signed_prekey);
+//! assert_eq!(b"hello", &bob_session.decrypt(&message, rng)?[..]);
+//! let reply = bob_session.encrypt(b"hi")?;
+//! assert_eq!(b"hi", &alice_session.decrypt(&reply, rng)?[..]);
+//! # Ok::<(), crypto_pure::error::Error>(())
+//! ```
+use crate::aead::Aead;
+use crate::aes::Aes256;
+use crate::curve25519::{self, Fe};
+use crate::ed25519;
+use crate::error::Error;
+use crate::gcm::Gcm;
+use crate::hkdf::Hkdf;
+use crate::hmac::Hmac;
+use crate::rand::SecureRandom;
+use crate::secret::SecretKey;
+use crate::sha2::{sha512, Sha256};
+use alloc::vec::Vec;
+use byteorder::{BigEndian, ByteOrder as _};
+
+/// The length of the header at the start of each ratchet message.
+pub const HEADER_LEN: usize = KEY_LEN + 8;
+
+/// The most message keys skipped in one chain, and the most kept in the cache.
+pub const MAX_SKIP: usize = 1000;
+
+const KEY_LEN: usize = 32;
+const TAG_LEN: usize = 16;
+const RATCHET_INFO: &[u8] = b"DoubleRatchet";
+const MESSAGE_KEYS_INFO: &[u8] = b"MessageKeys";
+
+/// An Ed25519 identity key pair.
+pub struct IdentityKey {
+    secret_key: SecretKey<[u8; KEY_LEN]>,
+    public_key: [u8; KEY_LEN],
+}
+
+impl IdentityKey {
+    /// Uses an Ed25519 secret key as an identity key.
+    pub fn new(secret_key: &[u8; KEY_LEN]) -> Self {
+        Self {
+            secret_key: SecretKey::new(*secret_key),
+            public_key: ed25519::gen_pk(secret_key).expect("secret key is not 32 bytes"),
+        }
+    }
+
+    /// Generates an identity key with a secret key drawn from `rng`.
+    pub fn generate(rng: &dyn SecureRandom) -> Self {
+        let secret_key = SecretKey::new(ed25519::gen_sk(rng));
+        Self::new(&secret_key)
+    }
+
+    /// Outputs the Ed25519 public key.
+    pub fn public_key(&self) -> &[u8; KEY_LEN] {
+        &self.public_key
+    }
+
+    /// Signs an X25519 public key to be published as a signed prekey.
+    pub fn sign_prekey(&self, prekey: &[u8; KEY_LEN]) -> [u8; 64] {
+        ed25519::sign(prekey, &*self.secret_key, &self.public_key).expect("keys are not 32 bytes")
+    }
+
+    /// Outputs the X25519 secret key with the same scalar as the Ed25519 secret key.
+    fn x25519_secret_key(&self) -> SecretKey<[u8; KEY_LEN]> {
+        let hash = SecretKey::new(sha512(&*self.secret_key));
+        let mut secret_key = SecretKey::new([0; KEY_LEN]);
+        secret_key.copy_from_slice(&hash[..KEY_LEN]);
+        secret_key
+    }
+}
+
+/// The keys a responder publishes so that initiators can start sessions with them.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PreKeyBundle {
+    /// The responder's Ed25519 identity key.
+    pub identity_key: [u8; KEY_LEN],
+    /// The X25519 signed prekey.
+    pub signed_prekey: [u8; KEY_LEN],
+    /// The signature of `signed_prekey` by `identity_key`.
+    pub signature: [u8; 64],
+    /// An X25519 one-time prekey, which the responder deletes after it is used.
+    pub one_time_prekey: Option<[u8; KEY_LEN]>,
+}
+
+/// The keys an initiator sends to the responder with their first message.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InitialMessage {
+    /// The initiator's Ed25519 identity key.
+    pub identity_key: [u8; KEY_LEN],
+    /// The initiator's X25519 ephemeral key.
+    pub ephemeral_key: [u8; KEY_LEN],
+    /// The responder's one-time prekey that was used, if any.
+    pub one_time_prekey: Option<[u8; KEY_LEN]>,
+}
+
+/// The outcome of X3DH.
+pub struct Agreement {
+    /// The shared secret.
+    pub shared_secret: SecretKey<[u8; KEY_LEN]>,
+    /// The initiator's identity key followed by the responder's, to be authenticated with every
+    /// message.
+    pub associated_data: [u8; 2 * KEY_LEN],
+}
+
+/// Runs X3DH as the initiator with a responder's prekey bundle, outputting the agreement and the
+/// message to send to the responder. The ephemeral key is drawn from `rng`, and `info` identifies
+/// the application.
+///
+/// # Errors
+///
+/// Fails with `Error::InvalidSignature` if the signed prekey's signature is not valid, or with
+/// `Error::InvalidKey` if a key in the bundle is not valid.
+pub fn x3dh_initiate(
+    identity_key: &IdentityKey,
+    bundle: &PreKeyBundle,
+    info: &[u8],
+    rng: &dyn SecureRandom,
+) -> Result<(Agreement, InitialMessage), Error> {
+    if !ed25519::verify(
+        &bundle.signed_prekey,
+        &bundle.signature,
+        &bundle.identity_key,
+    ) {
+        return Err(Error::InvalidSignature);
+    }
+    let mut ephemeral_key = SecretKey::new([0; KEY_LEN]);
+    rng.fill(&mut *ephemeral_key);
+    let remote_identity_key = x25519_public_key(&bundle.identity_key);
+    let dh1 = x25519(&*identity_key.x25519_secret_key(), &bundle.signed_prekey)?;
+    let dh2 = x25519(&*ephemeral_key, &remote_identity_key)?;
+    let dh3 = x25519(&*ephemeral_key, &bundle.signed_prekey)?;
+    let dh4 = match &bundle.one_time_prekey {
+        Some(one_time_prekey) => Some(x25519(&*ephemeral_key, one_time_prekey)?),
+        None => None,
+    };
+    let agreement = agreement(
+        &identity_key.public_key,
+        &bundle.identity_key,
+        &[&dh1, &dh2, &dh3],
+        dh4.as_ref(),
+        info,
+    );
+    let message = InitialMessage {
+        identity_key: identity_key.public_key,
+        ephemeral_key: curve25519::gen_pk(&*ephemeral_key)?,
+        one_time_prekey: bundle.one_time_prekey,
+    };
+    Ok((agreement, message))
+}
+
+/// Runs X3DH as the responder with the initiator's message, given the secret keys of the signed
+/// prekey and of the one-time prekey the message names, if any. `info` must be the same as the
+/// initiator's.
+///
+/// # Errors
+///
+/// Fails with `Error::InvalidKey` if a key in the message is not valid, or if the one-time
+/// prekey is missing or doesn't match the message.
+pub fn x3dh_respond(
+    identity_key: &IdentityKey,
+    signed_prekey: &[u8; KEY_LEN],
+    one_time_prekey: Option<&[u8; KEY_LEN]>,
+    message: &InitialMessage,
+    info: &[u8],
+) -> Result<Agreement, Error> {
+    let remote_identity_key = x25519_public_key(&message.identity_key);
+    let dh1 = x25519(signed_prekey, &remote_identity_key)?;
+    let dh2 = x25519(&*identity_key.x25519_secret_key(), &message.ephemeral_key)?;
+    let dh3 = x25519(signed_prekey, &message.ephemeral_key)?;
+    let dh4 = match (one_time_prekey, &message.one_time_prekey) {
+        (None, None) => None,
+        (Some(secret_key), Some(public_key)) if curve25519::gen_pk(secret_key)? == *public_key => {
+            Some(x25519(secret_key, &message.ephemeral_key)?)
+        }
+        _ => return Err(Error::InvalidKey),
+    };
+    Ok(agreement(
+        &message.identity_key,
+        &identity_key.public_key,
+        &[&dh1, &dh2, &dh3],
+        dh4.as_ref(),
+        info,
+    ))
+}
+
+fn agreement(
+    initiator: &[u8; KEY_LEN],
+    responder: &[u8; KEY_LEN],
+    dh: &[&SecretKey<[u8; KEY_LEN]>; 3],
+    dh4: Option<&SecretKey<[u8; KEY_LEN]>>,
+    info: &[u8],
+) -> Agreement {
+    // The input starts with 32 0xff bytes, which separates it from inputs to XEdDSA.
+    let mut ikm = SecretKey::new([0xff; 5 * KEY_LEN]);
+    for (chunk, dh) in ikm[KEY_LEN..]
+        .chunks_mut(KEY_LEN)
+        .zip(dh.iter().copied().chain(dh4))
+    {
+        chunk.copy_from_slice(&**dh);
+    }
+    let len = if dh4.is_some() { 5 } else { 4 } * KEY_LEN;
+    let mut shared_secret = SecretKey::new([0; KEY_LEN]);
+    Hkdf::<Sha256>::extract(&[0; KEY_LEN], &ikm[..len])
+        .expand(info, &mut *shared_secret)
+        .expect("output too long");
+    let mut associated_data = [0; 2 * KEY_LEN];
+    associated_data[..KEY_LEN].copy_from_slice(initiator);
+    associated_data[KEY_LEN..].copy_from_slice(responder);
+    Agreement {
+        shared_secret,
+        associated_data,
+    }
+}
+
+/// Converts an Ed25519 public key to the X25519 public key of the same point, u = (1 + y) / (1 -
+/// y).
+fn x25519_public_key(public_key: &[u8; KEY_LEN]) -> [u8; KEY_LEN] {
+    let mut one = Fe::default();
+    one.assign_one();
+    let mut y = Fe::default();
+    y.assign_from_bytes(public_key);
+    let mut numerator = Fe::default();
+    numerator.assign_sum(&one, &y);
+    let mut denominator = Fe::default();
+    denominator.assign_difference(&one, &y);
+    let mut u = Fe::default();
+    u.assign_product(&numerator, &denominator.inverse());
+    let mut output = [0; KEY_LEN];
+    u.write_bytes(&mut output);
+    output
+}
+
+fn x25519(secret_key: &[u8], public_key: &[u8]) -> Result<SecretKey<[u8; KEY_LEN]>, Error> {
+    curve25519::x25519(secret_key, public_key).map(SecretKey::new)
+}
+
+/// A Double Ratchet session with one other party.
+pub struct Session(State);
+
+#[derive(Clone)]
+struct State {
+    associated_data: Vec<u8>,
+    root_key: SecretKey<[u8; KEY_LEN]>,
+    sending_key: SecretKey<[u8; KEY_LEN]>,
+    sending_public_key: [u8; KEY_LEN],
+    receiving_public_key: Option<[u8; KEY_LEN]>,
+    sending_chain: Option<SecretKey<[u8; KEY_LEN]>>,
+    receiving_chain: Option<SecretKey<[u8; KEY_LEN]>>,
+    sent: u32,
+    received: u32,
+    previous_sent: u32,
+    skipped: Vec<([u8; KEY_LEN], u32, SecretKey<[u8; KEY_LEN]>)>,
+}
+
+impl Session {
+    /// Starts a session as the initiator after X3DH, with the responder's signed prekey as their
+    /// ratchet key. The first ratchet key is drawn from `rng`.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::InvalidKey` if `remote_ratchet_key` is not valid.
+    pub fn initiate(
+        agreement: &Agreement,
+        remote_ratchet_key: &[u8; KEY_LEN],
+        rng: &dyn SecureRandom,
+    ) -> Result<Self, Error> {
+        Self::initiate_with_secret(
+            &agreement.shared_secret,
+            &agreement.associated_data,
+            remote_ratchet_key,
+            rng,
+        )
+    }
+
+    /// Starts a session as the initiator from a shared secret and associated data agreed on some
+    /// other way, like `initiate`.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::InvalidKey` if `remote_ratchet_key` is not valid.
+    pub fn initiate_with_secret(
+        shared_secret: &[u8; KEY_LEN],
+        associated_data: &[u8],
+        remote_ratchet_key: &[u8; KEY_LEN],
+        rng: &dyn SecureRandom,
+    ) -> Result<Self, Error> {
+        let mut sending_key = SecretKey::new([0; KEY_LEN]);
+        rng.fill(&mut *sending_key);
+        let mut state = State::new(shared_secret, associated_data, sending_key);
+        let dh = x25519(&*state.sending_key, remote_ratchet_key)?;
+        let sending_chain = state.ratchet_root_key(&*dh);
+        state.receiving_public_key = Some(*remote_ratchet_key);
+        state.sending_chain = Some(sending_chain);
+        Ok(Self(state))
+    }
+
+    /// Starts a session as the responder after X3DH, with the secret key of the signed prekey as
+    /// the ratchet key. The responder can only encrypt once it has decrypted a message.
+    pub fn respond(agreement: &Agreement, ratchet_key: &[u8; KEY_LEN]) -> Self {
+        Self::respond_with_secret(
+            &agreement.shared_secret,
+            &agreement.associated_data,
+            ratchet_key,
+        )
+    }
+
+    /// Starts a session as the responder from a shared secret and associated data agreed on some
+    /// other way, like `respond`.
+    pub fn respond_with_secret(
+        shared_secret: &[u8; KEY_LEN],
+        associated_data: &[u8],
+        ratchet_key: &[u8; KEY_LEN],
+    ) -> Self {
+        let sending_key = SecretKey::new(*ratchet_key);
+        Self(State::new(shared_secret, associated_data, sending_key))
+    }
+
+    /// Encrypts a message, outputting the header followed by the ciphertext and tag.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::InvalidState` if this is the responder and it hasn't decrypted a message
+    /// yet, or with `Error::NonceExhausted` if 2^32 - 1 messages have been sent in the current
+    /// chain.
+    pub fn encrypt(&mut self, plaintext: &[u8]) -> Result<Vec<u8>, Error> {
+        let state = &mut self.0;
+        let chain = state.sending_chain.as_mut().ok_or(Error::InvalidState)?;
+        if state.sent == u32::MAX {
+            return Err(Error::NonceExhausted);
+        }
+        let message_key = ratchet_chain_key(chain);
+        let mut message = Vec::with_capacity(HEADER_LEN + plaintext.len() + TAG_LEN);
+        message.extend_from_slice(&state.sending_public_key);
+        message.extend_from_slice(&state.previous_sent.to_be_bytes());
+        message.extend_from_slice(&state.sent.to_be_bytes());
+        message.extend_from_slice(plaintext);
+        message.resize(HEADER_LEN + plaintext.len() + TAG_LEN, 0);
+        let (header, buffer) = message.split_at_mut(HEADER_LEN);
+        seal(&message_key, &state.associated_data, header, buffer);
+        state.sent += 1;
+        Ok(message)
+    }
+
+    /// Decrypts a message, performing a DH ratchet step with a new ratchet key drawn from `rng`
+    /// if the other party's ratchet key changed. If decryption fails, the session is unchanged.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::InvalidLength` if `message` is too short, with `Error::TagMismatch` if
+    /// the message was modified, with `Error::DecryptionFailed` if the message was already
+    /// decrypted, its key is no longer cached, or more than `MAX_SKIP` messages would be skipped,
+    /// or with `Error::InvalidKey` if the header's ratchet key is not valid.
+    pub fn decrypt(&mut self, message: &[u8], rng: &dyn SecureRandom) -> Result<Vec<u8>, Error> {
+        if message.len() < HEADER_LEN + TAG_LEN {
+            return Err(Error::InvalidLength);
+        }
+        let (header, ciphertext) = message.split_at(HEADER_LEN);
+        let mut public_key = [0; KEY_LEN];
+        public_key.copy_from_slice(&header[..KEY_LEN]);
+        let previous_sent = BigEndian::read_u32(&header[KEY_LEN..]);
+        let n = BigEndian::read_u32(&header[KEY_LEN + 4..]);
+        let mut plaintext = ciphertext.to_vec();
+
+        if let Some(i) = self
+            .0
+            .skipped
+            .iter()
+            .position(|(skipped_key, skipped_n, _)| *skipped_key == public_key && *skipped_n == n)
+        {
+            let len = open(
+                &self.0.skipped[i].2,
+                &self.0.associated_data,
+                header,
+                &mut plaintext,
+            )?;
+            self.0.skipped.remove(i);
+            plaintext.truncate(len);
+            return Ok(plaintext);
+        }
+
+        // Changes are made to a copy that replaces the state only if decryption succeeds.
+        let mut state = self.0.clone();
+        if state.receiving_public_key != Some(public_key) {
+            state.skip_message_keys(previous_sent)?;
+            state.ratchet_dh(&public_key, rng)?;
+        }
+        state.skip_message_keys(n)?;
+        let chain = state.receiving_chain.as_mut().unwrap();
+        let message_key = ratchet_chain_key(chain);
+        let len = open(&message_key, &state.associated_data, header, &mut plaintext)?;
+        state.received += 1;
+        self.0 = state;
+        plaintext.truncate(len);
+        Ok(plaintext)
+    }
+}
+
+impl State {
+    fn new(
+        shared_secret: &[u8; KEY_LEN],
+        associated_data: &[u8],
+        sending_key: SecretKey<[u8; KEY_LEN]>,
+    ) -> Self {
+        Self {
+            associated_data: associated_data.to_vec(),
+            root_key: SecretKey::new(*shared_secret),
+            sending_public_key: curve25519::gen_pk(&*sending_key).expect("key is not 32 bytes"),
+            sending_key,
+            receiving_public_key: None,
+            sending_chain: None,
+            receiving_chain: None,
+            sent: 0,
+            received: 0,
+            previous_sent: 0,
+            skipped: Vec::new(),
+        }
+    }
+
+    /// Mixes a DH output into the root key, outputting a new chain key.
+    fn ratchet_root_key(&mut self, dh: &[u8]) -> SecretKey<[u8; KEY_LEN]> {
+        let mut okm = SecretKey::new([0; 2 * KEY_LEN]);
+        Hkdf::<Sha256>::extract(&*self.root_key, dh)
+            .expand(RATCHET_INFO, &mut *okm)
+            .expect("output too long");
+        self.root_key.copy_from_slice(&okm[..KEY_LEN]);
+        let mut chain_key = SecretKey::new([0; KEY_LEN]);
+        chain_key.copy_from_slice(&okm[KEY_LEN..]);
+        chain_key
+    }
+
+    fn ratchet_dh(
+        &mut self,
+        public_key: &[u8; KEY_LEN],
+        rng: &dyn SecureRandom,
+    ) -> Result<(), Error> {
+        let dh = x25519(&*self.sending_key, public_key)?;
+        self.receiving_chain = Some(self.ratchet_root_key(&*dh));
+        rng.fill(&mut *self.sending_key);
+        self.sending_public_key = curve25519::gen_pk(&*self.sending_key)?;
+        let dh = x25519(&*self.sending_key, public_key)?;
+        self.sending_chain = Some(self.ratchet_root_key(&*dh));
+        self.receiving_public_key = Some(*public_key);
+        self.previous_sent = self.sent;
+        self.sent = 0;
+        self.received = 0;
+        Ok(())
+    }
+
+    /// Caches the keys of the messages in the receiving chain before message `until`.
+    fn skip_message_keys(&mut self, until: u32) -> Result<(), Error> {
+        let (public_key, chain) = match (&self.receiving_public_key, &mut self.receiving_chain) {
+            (Some(public_key), Some(chain)) => (public_key, chain),
+            _ => return Ok(()),
+        };
+        match until.checked_sub(self.received) {
+            Some(skipped) if skipped as usize <= MAX_SKIP => {}
+            _ => return Err(Error::DecryptionFailed),
+        }
+        while self.received < until {
+            let message_key = ratchet_chain_key(chain);
+            self.skipped.push((*public_key, self.received, message_key));
+            self.received += 1;
+        }
+        // The oldest keys are dropped once the cache is full.
+        if self.skipped.len() > MAX_SKIP {
+            self.skipped.drain(..self.skipped.len() - MAX_SKIP);
+        }
+        Ok(())
+    }
+}
+
+/// Advances a chain key, outputting the next message key.
+fn ratchet_chain_key(chain_key: &mut SecretKey<[u8; KEY_LEN]>) -> SecretKey<[u8; KEY_LEN]> {
+    let mut message_key = SecretKey::new([0; KEY_LEN]);
+    let mut hmac = Hmac::<Sha256>::new(&**chain_key);
+    hmac.update(&[1]);
+    message_key.copy_from_slice(&hmac.tag());
+    let mut hmac = Hmac::<Sha256>::new(&**chain_key);
+    hmac.update(&[2]);
+    chain_key.copy_from_slice(&hmac.tag());
+    message_key
+}
+
+/// Derives the AES-256-GCM key and nonce for a message key, which is only used once.
+fn message_cipher(message_key: &[u8; KEY_LEN]) -> (Gcm<Aes256>, [u8; 12]) {
+    let mut okm = SecretKey::new([0; KEY_LEN + 12]);
+    Hkdf::<Sha256>::extract(b"", message_key)
+        .expand(MESSAGE_KEYS_INFO, &mut *okm)
+        .expect("output too long");
+    let cipher = Gcm::new(&okm[..KEY_LEN]).expect("invalid key length");
+    let mut nonce = [0; 12];
+    nonce.copy_from_slice(&okm[KEY_LEN..]);
+    (cipher, nonce)
+}
+
+fn seal(message_key: &[u8; KEY_LEN], associated_data: &[u8], header: &[u8], buffer: &mut [u8]) {
+    let (cipher, nonce) = message_cipher(message_key);
+    let data = [associated_data, header].concat();
+    cipher
+        .seal_in_place(&nonce, &data, buffer)
+        .expect("invalid buffer length");
+}
+
+/// Opens a ciphertext in place, outputting the length of the plaintext.
+fn open(
+    message_key: &[u8; KEY_LEN],
+    associated_data: &[u8],
+    header: &[u8],
+    buffer: &mut [u8],
+) -> Result<usize, Error> {
+    let (cipher, nonce) = message_cipher(message_key);
+    let data = [associated_data, header].concat();
+    Ok(cipher.open_in_place(&nonce, &data, buffer)?.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::*;
+    use std::vec;
+
+    const INFO: &[u8] = b"example";
+
+    fn fixed_rng(byte: u8) -> impl Fn(&mut [u8]) {
+        move |dest: &mut [u8]| dest.iter_mut().for_each(|x| *x = byte)
+    }
+
+    fn bundle(one_time_prekey: bool) -> PreKeyBundle {
+        let bob = IdentityKey::new(&[2; 32]);
+        let signed_prekey = curve25519::gen_pk(&[3; 32]).unwrap();
+        PreKeyBundle {
+            identity_key: bob.public_key,
+            signed_prekey,
+            signature: bob.sign_prekey(&signed_prekey),
+            one_time_prekey: if one_time_prekey {
+                Some(curve25519::gen_pk(&[4; 32]).unwrap())
+            } else {
+                None
+            },
+        }
+    }
+
+    fn sessions() -> (Session, Session) {
+        let alice = IdentityKey::new(&[1; 32]);
+        let bob = IdentityKey::new(&[2; 32]);
+        let (agreement, message) =
+            x3dh_initiate(&alice, &bundle(true), INFO, &fixed_rng(5)).unwrap();
+        let alice = Session::initiate(
+            &agreement,
+            &curve25519::gen_pk(&[3; 32]).unwrap(),
+            &fixed_rng(6),
+        );
+        let agreement = x3dh_respond(&bob, &[3; 32], Some(&[4; 32]), &message, INFO).unwrap();
+        (alice.unwrap(), Session::respond(&agreement, &[3; 32]))
+    }
+
+    // libsodium's test of crypto_sign_ed25519_pk_to_curve25519
+    #[test]
+    fn test_x25519_public_key() {
+        let mut secret_key = [0; 32];
+        secret_key.copy_from_slice(&h2b(
+            "421151a459faeade3d247115f94aedae42318124095afabe4d1451a559faedee",
+        ));
+        let identity_key = IdentityKey::new(&secret_key);
+        assert_eq!(
+            &h2b("b5076a8474a832daee4dd5b4040983b6623b5f344aca57d4d6ee4baf3f259e6e")[..],
+            identity_key.public_key()
+        );
+        let public_key = x25519_public_key(identity_key.public_key());
+        assert_eq!(
+            &h2b("f1814f0e8ff1043d8a44d25babff3cedcae6c22c3edaa48f857ae70de2baae50")[..],
+            &public_key
+        );
+        assert_eq!(
+            public_key,
+            curve25519::gen_pk(&*identity_key.x25519_secret_key()).unwrap()
+        );
+    }
+
+    // Generated with Python's cryptography package
+    #[test]
+    fn test_x3dh() {
+        let alice = IdentityKey::new(&[1; 32]);
+        let bob = IdentityKey::new(&[2; 32]);
+        for &(one_time_prekey, expected) in &[
+            (
+                true,
+                "99220ca7ba2cebbd89ea0b700cfba92c9608b2e87b24105077ab8c84c813d413",
+            ),
+            (
+                false,
+                "002c1e42049da7318e62d561a8c918971bffe97ffd673aa0d8c0007391969da3",
+            ),
+        ] {
+            let bundle = bundle(one_time_prekey);
+            let (agreement, message) = x3dh_initiate(&alice, &bundle, INFO, &fixed_rng(5)).unwrap();
+            assert_eq!(&h2b(expected)[..], &*agreement.shared_secret);
+            assert_eq!(alice.public_key(), &agreement.associated_data[..32]);
+            assert_eq!(bob.public_key(), &agreement.associated_data[32..]);
+            assert_eq!(curve25519::gen_pk(&[5; 32]).unwrap(), message.ephemeral_key);
+
+            let one_time_prekey = if one_time_prekey {
+                Some(&[4; 32])
+            } else {
+                None
+            };
+            let other = x3dh_respond(&bob, &[3; 32], one_time_prekey, &message, INFO).unwrap();
+            assert_eq!(*agreement.shared_secret, *other.shared_secret);
+            assert_eq!(agreement.associated_data, other.associated_data);
+            let other = x3dh_respond(&bob, &[3; 32], one_time_prekey, &message, b"other").unwrap();
+            assert_ne!(*agreement.shared_secret, *other.shared_secret);
+        }
+        assert_eq!(
+            &h2b(
+                "3459651b33c0090f7ac190c8b219f406cf8acd25a344aa0b837b1e8607db79a6\
+                 fc7874ef700c843c064d522968361bb81fe31578d31879b33606c4d82b0aaa0e"
+            )[..],
+            &bundle(true).signature[..]
+        );
+    }
+
+    #[test]
+    fn test_x3dh_invalid() {
+        let alice = IdentityKey::new(&[1; 32]);
+        let bob = IdentityKey::new(&[2; 32]);
+        let mut bundle = bundle(true);
+        bundle.signed_prekey[0] ^= 1;
+        let result = x3dh_initiate(&alice, &bundle, INFO, &fixed_rng(5));
+        assert_eq!(Err(Error::InvalidSignature), result.map(|_| ()));
+        bundle.signed_prekey[0] ^= 1;
+        bundle.one_time_prekey = Some([0; 32]);
+        let result = x3dh_initiate(&alice, &bundle, INFO, &fixed_rng(5));
+        assert_eq!(Err(Error::InvalidKey), result.map(|_| ()));
+
+        bundle.one_time_prekey = Some(curve25519::gen_pk(&[4; 32]).unwrap());
+        let (_, message) = x3dh_initiate(&alice, &bundle, INFO, &fixed_rng(5)).unwrap();
+        for one_time_prekey in &[None, Some(&[5; 32])] {
+            let result = x3dh_respond(&bob, &[3; 32], *one_time_prekey, &message, INFO);
+            assert_eq!(Err(Error::InvalidKey), result.map(|_| ()));
+        }
+    }
+
+    // Generated with Python's cryptography package
+    #[test]
+    fn test_messages() {
+        let (mut alice, mut bob) = sessions();
+        let first = alice.encrypt(b"hello").unwrap();
+        assert_eq!(
+            h2b(
+                "f5b2d6e60f9477e310c2982daaa6c9136c108a1777c5947e448fa37d68174557\
+                 0000000000000000b0f78de05b7531032a39e1a9eb8ffddb7b3d602198"
+            ),
+            first
+        );
+        let second = alice.encrypt(b"world").unwrap();
+        assert_eq!(
+            h2b(
+                "f5b2d6e60f9477e310c2982daaa6c9136c108a1777c5947e448fa37d68174557\
+                 0000000000000001c3236c9c6b4c14348b399807de65f72304f5acb97b"
+            ),
+            second
+        );
+        assert_eq!(b"world", &bob.decrypt(&second, &fixed_rng(7)).unwrap()[..]);
+        assert_eq!(b"hello", &bob.decrypt(&first, &fixed_rng(7)).unwrap()[..]);
+    }
+
+    #[test]
+    fn test_out_of_order() {
+        let (mut alice, mut bob) = sessions();
+        let rng = &fixed_rng(7);
+        let a1 = alice.encrypt(b"a1").unwrap();
+        let a2 = alice.encrypt(b"a2").unwrap();
+        assert_eq!(b"a2", &bob.decrypt(&a2, rng).unwrap()[..]);
+        let b1 = bob.encrypt(b"b1").unwrap();
+        assert_eq!(b"b1", &alice.decrypt(&b1, &fixed_rng(8)).unwrap()[..]);
+        let a3 = alice.encrypt(b"a3").unwrap();
+        let a4 = alice.encrypt(b"a4").unwrap();
+        assert_eq!(b"a4", &bob.decrypt(&a4, &fixed_rng(9)).unwrap()[..]);
+        let b2 = bob.encrypt(b"b2").unwrap();
+
+        // Messages from earlier chains still decrypt after DH ratchet steps.
+        assert_eq!(b"a1", &bob.decrypt(&a1, rng).unwrap()[..]);
+        assert_eq!(b"a3", &bob.decrypt(&a3, rng).unwrap()[..]);
+        assert_eq!(b"b2", &alice.decrypt(&b2, rng).unwrap()[..]);
+        assert_eq!(
+            b"",
+            &alice.decrypt(&bob.encrypt(b"").unwrap(), rng).unwrap()[..]
+        );
+
+        // Each message decrypts only once.
+        for message in &[&a1, &a2, &a3, &a4] {
+            assert_eq!(Err(Error::DecryptionFailed), bob.decrypt(message, rng));
+        }
+        assert_eq!(Err(Error::DecryptionFailed), alice.decrypt(&b1, rng));
+    }
+
+    #[test]
+    fn test_invalid() {
+        let (mut alice, mut bob) = sessions();
+        let rng = &fixed_rng(7);
+        let message = alice.encrypt(b"message").unwrap();
+        for i in 0..message.len() {
+            let mut invalid = message.clone();
+            invalid[i] ^= 1;
+            assert!(bob.decrypt(&invalid, rng).is_err());
+        }
+        assert_eq!(Err(Error::InvalidLength), bob.decrypt(&message[..55], rng));
+        let mut invalid = message.clone();
+        invalid[..32].copy_from_slice(&[0; 32]);
+        assert_eq!(Err(Error::InvalidKey), bob.decrypt(&invalid, rng));
+        invalid[..32].copy_from_slice(&message[..32]);
+        invalid[HEADER_LEN] ^= 1;
+        assert_eq!(Err(Error::TagMismatch), bob.decrypt(&invalid, rng));
+        // The session is unchanged by failures.
+        assert_eq!(b"message", &bob.decrypt(&message, rng).unwrap()[..]);
+
+        let mut messages = vec![];
+        for _ in 0..=MAX_SKIP + 1 {
+            messages.push(alice.encrypt(b"").unwrap());
+        }
+        let last = messages.last().unwrap();
+        assert_eq!(Err(Error::DecryptionFailed), bob.decrypt(last, rng));
+        assert!(bob.decrypt(&messages[MAX_SKIP], rng).is_ok());
+        assert_eq!(MAX_SKIP, bob.0.skipped.len());
+        assert!(bob.decrypt(last, rng).is_ok());
+        assert!(bob.decrypt(&messages[0], rng).is_ok());
+    }
+
+    #[test]
+    fn test_respond_first() {
+        let (mut alice, mut bob) = sessions();
+        assert_eq!(Err(Error::InvalidState), bob.encrypt(b"message"));
+        let message = &alice.encrypt(b"message").unwrap();
+        bob.decrypt(message, &fixed_rng(7)).unwrap();
+        assert!(bob.encrypt(b"reply").is_ok());
+    }
+}
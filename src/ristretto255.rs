@@ -0,0 +1,791 @@
+//! Module for ristretto255 (RFC 9496), a group of prime order
+//! L = 2^252 + 27742317777372353535851937790883648493 built from the Edwards form of Curve25519.
+//!
+//! Curve25519 has a cofactor of 8, so protocols written for prime-order groups can go wrong on it
+//! in subtle ways. Ristretto255 encodes points so that each group element has exactly one
+//! encoding, and every decoded point is in the prime-order group. `Point` and `Scalar` support the
+//! usual arithmetic operators, which run in time independent of the values, as do encoding and
+//! comparison. Decoding is only constant-time for valid encodings.
+//!
+//! # Examples
+//!
+//! ```
+//! use crypto_pure::rand::SystemRandom;
+//! use crypto_pure::ristretto255::{Point, Scalar};
+//! let rng = &SystemRandom::new();
+//! let (a, b) = (Scalar::random(rng), Scalar::random(rng));
+//! let (public_a, public_b) = (Point::mul_base(&a), Point::mul_base(&b));
+//! let public_a = Point::from_bytes(&public_a.to_bytes()).unwrap();
+//! assert!(public_b * a == public_a * b);
+//! ```
+use crate::const_curve25519::{D, SQRTM1};
+use crate::ct::{self, Choice, ConditionallySelectable};
+use crate::curve25519::Fe;
+#[cfg(feature = "alloc")]
+use crate::ed25519::GeP2;
+use crate::ed25519::{
+    ge_add, ge_p1p1_to_p3, ge_p3_to_cached, ge_sub, sc_muladd, sc_reduce, GeCached, GeP1p1, GeP3,
+};
+use crate::error::Error;
+use crate::hash_to_curve::expand_message_xmd;
+use crate::rand::SecureRandom;
+use crate::sha2::Sha512;
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+use core::ops::{Add, Mul, Neg, Sub};
+
+/// The length of an encoded point.
+pub const POINT_LEN: usize = 32;
+
+/// The length of an encoded scalar.
+pub const SCALAR_LEN: usize = 32;
+
+/// An integer modulo the group order L, stored as its canonical little-endian encoding.
+#[derive(Clone, Copy, Debug)]
+pub struct Scalar([u8; SCALAR_LEN]);
+
+impl Scalar {
+    /// The scalar 0.
+    pub const ZERO: Self = Scalar([0; SCALAR_LEN]);
+
+    /// The scalar 1.
+    pub const ONE: Self = {
+        let mut bytes = [0; SCALAR_LEN];
+        bytes[0] = 1;
+        Scalar(bytes)
+    };
+
+    /// Draws a uniformly random scalar from `rng`.
+    pub fn random(rng: &dyn SecureRandom) -> Self {
+        let mut bytes = [0; 64];
+        rng.fill(&mut bytes);
+        Self::from_uniform_bytes(&bytes)
+    }
+
+    /// Reduces 64 bytes, read as a little-endian integer, modulo L. The result is uniformly
+    /// distributed if the bytes are.
+    pub fn from_uniform_bytes(bytes: &[u8; 64]) -> Self {
+        let mut wide = *bytes;
+        sc_reduce(&mut wide);
+        let mut scalar = Self::ZERO;
+        scalar.0.copy_from_slice(&wide[..SCALAR_LEN]);
+        scalar
+    }
+
+    /// Hashes a message to a scalar with `expand_message_xmd` using SHA-512 and the domain
+    /// separation tag `dst`, as in the `HashToScalar` function of the ristretto255 ciphersuites of
+    /// RFC 9497.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::InvalidLength` if `dst.len()` is more than 255.
+    pub fn hash_to_scalar(message: &[u8], dst: &[u8]) -> Result<Self, Error> {
+        let mut uniform_bytes = [0; 64];
+        expand_message_xmd::<Sha512>(message, dst, &mut uniform_bytes)?;
+        Ok(Self::from_uniform_bytes(&uniform_bytes))
+    }
+
+    /// Decodes a scalar from its little-endian encoding, or returns `None` if `bytes` is not 32
+    /// bytes long or encodes an integer that is not less than L.
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() != SCALAR_LEN {
+            return None;
+        }
+        let mut wide = [0; 64];
+        wide[..SCALAR_LEN].copy_from_slice(bytes);
+        let scalar = Self::from_uniform_bytes(&wide);
+        if ct::constant_time_eq(&scalar.0, bytes) {
+            Some(scalar)
+        } else {
+            None
+        }
+    }
+
+    /// Outputs the little-endian encoding of the scalar.
+    pub fn to_bytes(&self) -> [u8; SCALAR_LEN] {
+        self.0
+    }
+
+    /// Computes the multiplicative inverse of the scalar, or zero if it is zero.
+    pub fn invert(&self) -> Self {
+        // a^(L - 2) by square-and-multiply, since the exponent is public
+        let mut exponent = L;
+        exponent[0] -= 2;
+        let mut result = Self::ONE;
+        for i in (0..253).rev() {
+            result = result * result;
+            if (exponent[i / 8] >> (i % 8)) & 1 == 1 {
+                result = result * *self;
+            }
+        }
+        result
+    }
+
+    /// Checks whether the scalar is zero, in time independent of its value.
+    pub fn is_zero(&self) -> Choice {
+        ct::ct_eq(&self.0, &Self::ZERO.0)
+    }
+}
+
+impl From<u64> for Scalar {
+    fn from(value: u64) -> Self {
+        let mut scalar = Self::ZERO;
+        scalar.0[..8].copy_from_slice(&value.to_le_bytes());
+        scalar
+    }
+}
+
+impl ConditionallySelectable for Scalar {
+    fn conditional_select(a: &Self, b: &Self, choice: Choice) -> Self {
+        Scalar(<[u8; SCALAR_LEN]>::conditional_select(&a.0, &b.0, choice))
+    }
+}
+
+impl PartialEq for Scalar {
+    /// Compares scalars in time independent of their values.
+    fn eq(&self, other: &Self) -> bool {
+        ct::constant_time_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for Scalar {}
+
+impl Add for Scalar {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        muladd(&self, &Self::ONE, &other)
+    }
+}
+
+impl Sub for Scalar {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        muladd(&other, &L_MINUS_ONE, &self)
+    }
+}
+
+impl Mul for Scalar {
+    type Output = Self;
+
+    fn mul(self, other: Self) -> Self {
+        muladd(&self, &other, &Self::ZERO)
+    }
+}
+
+impl Neg for Scalar {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        muladd(&self, &L_MINUS_ONE, &Self::ZERO)
+    }
+}
+
+/// Computes (a b + c) modulo L.
+fn muladd(a: &Scalar, b: &Scalar, c: &Scalar) -> Scalar {
+    let mut s = Scalar::ZERO;
+    sc_muladd(&mut s.0, &a.0, &b.0, &c.0);
+    s
+}
+
+/// An element of the ristretto255 group.
+#[derive(Clone, Copy)]
+pub struct Point(GeP3);
+
+impl Point {
+    /// Returns the identity element.
+    pub fn identity() -> Self {
+        Point(GeP3::zero())
+    }
+
+    /// Returns the generator of the group, which is the image of the Ed25519 base point.
+    pub fn generator() -> Self {
+        Self::mul_base(&Scalar::ONE)
+    }
+
+    /// Computes a multiple of the generator, faster than multiplying `generator()` by `scalar`.
+    pub fn mul_base(scalar: &Scalar) -> Self {
+        let mut a = [0; 64];
+        a[..SCALAR_LEN].copy_from_slice(&scalar.0);
+        Point(GeP3::from_scalarmult_base(&a))
+    }
+
+    /// Decodes a point as in RFC 9496, Section 4.3.1, or returns `None` if `bytes` is not the
+    /// canonical encoding of a group element.
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() != POINT_LEN {
+            return None;
+        }
+        let s = &mut Fe::default();
+        s.assign_from_bytes(bytes);
+        let canonical = &mut [0; POINT_LEN];
+        s.write_bytes(canonical);
+        if !ct::constant_time_eq(canonical, bytes) || s.is_negative() == 1 {
+            return None;
+        }
+
+        let ss = &mut Fe::default();
+        let u1 = &mut Fe::default();
+        let u2 = &mut Fe::default();
+        let u2_sqr = &mut Fe::default();
+        let v = &mut Fe::default();
+        let t0 = &mut Fe::default();
+        ss.assign_square(s);
+        u1.assign_difference(&ONE, ss);
+        u2.assign_sum(&ONE, ss);
+        u2_sqr.assign_square(u2);
+        t0.assign_square(u1);
+        v.assign_product(t0, &Fe::from(D));
+        *v += u2_sqr;
+        v.neg();
+
+        t0.assign_product(v, u2_sqr);
+        let (was_square, invsqrt) = sqrt_ratio_m1(&ONE, t0);
+        let den_x = &mut Fe::default();
+        let den_y = &mut Fe::default();
+        den_x.assign_product(&invsqrt, u2);
+        den_y.assign_product(&invsqrt, den_x);
+        *den_y *= v;
+
+        let mut h = GeP3::default();
+        t0.assign_product(s, den_x);
+        h.x.assign_sum(t0, t0);
+        ct_abs(&mut h.x);
+        h.y.assign_product(u1, den_y);
+        h.z.assign_one();
+        h.t.assign_product(&h.x, &h.y);
+
+        if !bool::from(was_square) || h.t.is_negative() == 1 || h.y.is_nonzero() == 0 {
+            return None;
+        }
+        Some(Point(h))
+    }
+
+    /// Encodes the point as in RFC 9496, Section 4.3.2.
+    pub fn to_bytes(&self) -> [u8; POINT_LEN] {
+        let p = &self.0;
+        let u1 = &mut Fe::default();
+        let u2 = &mut Fe::default();
+        let t0 = &mut Fe::default();
+        let t1 = &mut Fe::default();
+        t0.assign_sum(&p.z, &p.y);
+        t1.assign_difference(&p.z, &p.y);
+        u1.assign_product(t0, t1);
+        u2.assign_product(&p.x, &p.y);
+
+        t0.assign_square(u2);
+        *t0 *= u1;
+        let (_, invsqrt) = sqrt_ratio_m1(&ONE, t0);
+        let den1 = &mut Fe::default();
+        let den2 = &mut Fe::default();
+        let z_inv = &mut Fe::default();
+        den1.assign_product(&invsqrt, u1);
+        den2.assign_product(&invsqrt, u2);
+        z_inv.assign_product(den1, den2);
+        *z_inv *= &p.t;
+
+        let mut x = p.x;
+        let mut y = p.y;
+        let mut den_inv = *den2;
+        let ix0 = &mut Fe::default();
+        let iy0 = &mut Fe::default();
+        let enchanted_denominator = &mut Fe::default();
+        ix0.assign_product(&p.x, &Fe::from(SQRTM1));
+        iy0.assign_product(&p.y, &Fe::from(SQRTM1));
+        enchanted_denominator.assign_product(den1, &Fe::from(INVSQRT_A_MINUS_D));
+        t0.assign_product(&p.t, z_inv);
+        let rotate = t0.is_negative() as u32;
+        x.cmov_from(iy0, rotate);
+        y.cmov_from(ix0, rotate);
+        den_inv.cmov_from(enchanted_denominator, rotate);
+
+        t0.assign_product(&x, z_inv);
+        conditional_negate(&mut y, Choice::from(t0.is_negative() as u8));
+        t1.assign_difference(&p.z, &y);
+        let s = &mut Fe::default();
+        s.assign_product(&den_inv, t1);
+        ct_abs(s);
+        let mut bytes = [0; POINT_LEN];
+        s.write_bytes(&mut bytes);
+        bytes
+    }
+
+    /// Maps 64 bytes to a point as in RFC 9496, Section 4.3.4. If the bytes are uniformly random,
+    /// so is the point, and its discrete logarithm is unknown.
+    pub fn from_uniform_bytes(bytes: &[u8; 64]) -> Self {
+        let p1 = map(&bytes[..32]);
+        let p2 = map(&bytes[32..]);
+        Point(p1) + Point(p2)
+    }
+
+    /// Hashes a message to a point with `expand_message_xmd` using SHA-512 and the domain
+    /// separation tag `dst`, as in the `ristretto255_XMD:SHA-512_R255MAP_RO_` suite of RFC 9380.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::InvalidLength` if `dst.len()` is more than 255.
+    pub fn hash_to_group(message: &[u8], dst: &[u8]) -> Result<Self, Error> {
+        let mut uniform_bytes = [0; 64];
+        expand_message_xmd::<Sha512>(message, dst, &mut uniform_bytes)?;
+        Ok(Self::from_uniform_bytes(&uniform_bytes))
+    }
+
+    /// Checks whether the point is the identity, in time independent of its value.
+    pub fn is_identity(&self) -> Choice {
+        self.ct_eq(&Self::identity())
+    }
+
+    /// Checks whether two points are equal as in RFC 9496, Section 4.3.3, in time independent of
+    /// their values.
+    pub fn ct_eq(&self, other: &Self) -> Choice {
+        let (p, q) = (&self.0, &other.0);
+        let t0 = &mut Fe::default();
+        let t1 = &mut Fe::default();
+        t0.assign_product(&p.x, &q.y);
+        t1.assign_product(&p.y, &q.x);
+        let x_y_equal = fe_ct_eq(t0, t1);
+        t0.assign_product(&p.y, &q.y);
+        t1.assign_product(&p.x, &q.x);
+        x_y_equal | fe_ct_eq(t0, t1)
+    }
+}
+
+/// Checks whether `b` times the generator plus `sum(scalars[i] * points[i])` is the identity. This
+/// runs in variable time, so it is only for public values, as in batch verification.
+///
+/// # Panics
+///
+/// Panics if `scalars.len()` is not equal to `points.len()`.
+#[cfg(feature = "alloc")]
+pub(crate) fn is_identity_multiscalar_vartime(
+    b: &Scalar,
+    scalars: &[Scalar],
+    points: &[Point],
+) -> bool {
+    let widen = |scalar: &Scalar| {
+        let mut wide = [0; 64];
+        wide[..SCALAR_LEN].copy_from_slice(&scalar.0);
+        wide
+    };
+    let scalars: Vec<_> = scalars.iter().map(widen).collect();
+    let points: Vec<_> = points.iter().map(|point| point.0).collect();
+    let sum = GeP2::from_multi_scalarmult_vartime(&widen(b), &scalars, &points);
+    // As in `ct_eq`, (X : Y : Z) is equivalent to the identity (0 : 1 : 1) if X or Y is zero.
+    sum.x.is_nonzero() == 0 || sum.y.is_nonzero() == 0
+}
+
+impl ConditionallySelectable for Point {
+    fn conditional_select(a: &Self, b: &Self, choice: Choice) -> Self {
+        let mut p = *a;
+        let mask = u32::from(choice.unwrap_u8());
+        p.0.x.cmov_from(&b.0.x, mask);
+        p.0.y.cmov_from(&b.0.y, mask);
+        p.0.z.cmov_from(&b.0.z, mask);
+        p.0.t.cmov_from(&b.0.t, mask);
+        p
+    }
+}
+
+impl PartialEq for Point {
+    /// Compares points in time independent of their values.
+    fn eq(&self, other: &Self) -> bool {
+        self.ct_eq(other).into()
+    }
+}
+
+impl Eq for Point {}
+
+impl Add for Point {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        let q = &mut GeCached::default();
+        let r = &mut GeP1p1::default();
+        let mut sum = GeP3::default();
+        ge_p3_to_cached(q, &other.0);
+        ge_add(r, &self.0, q);
+        ge_p1p1_to_p3(&mut sum, r);
+        Point(sum)
+    }
+}
+
+impl Sub for Point {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        let q = &mut GeCached::default();
+        let r = &mut GeP1p1::default();
+        let mut difference = GeP3::default();
+        ge_p3_to_cached(q, &other.0);
+        ge_sub(r, &self.0, q);
+        ge_p1p1_to_p3(&mut difference, r);
+        Point(difference)
+    }
+}
+
+impl Neg for Point {
+    type Output = Self;
+
+    fn neg(mut self) -> Self {
+        self.0.x.neg();
+        self.0.t.neg();
+        self
+    }
+}
+
+impl Mul<Scalar> for Point {
+    type Output = Self;
+
+    /// Multiplies the point by a scalar in time independent of both.
+    fn mul(self, scalar: Scalar) -> Self {
+        Point(GeP3::from_scalarmult(&scalar.0, &self.0))
+    }
+}
+
+/// Computes the square root of u / v as in RFC 9496, Section 4.2, outputting whether u / v is
+/// square and the nonnegative square root of either u / v or SQRT_M1 u / v.
+fn sqrt_ratio_m1(u: &Fe, v: &Fe) -> (Choice, Fe) {
+    let v3 = &mut Fe::default();
+    let v7 = &mut Fe::default();
+    v3.assign_square(v);
+    *v3 *= v;
+    v7.assign_square(v3);
+    *v7 *= v;
+
+    // r = (u v^3) (u v^7)^((p - 5) / 8)
+    let mut r = Fe::default();
+    r.assign_product(u, v7);
+    r.pow22523();
+    r *= v3;
+    r *= u;
+
+    let check = &mut Fe::default();
+    let neg_u = &mut Fe::default();
+    let neg_u_i = &mut Fe::default();
+    check.assign_square(&r);
+    *check *= v;
+    neg_u.assign_neg(u);
+    neg_u_i.assign_product(neg_u, &Fe::from(SQRTM1));
+    let correct_sign_sqrt = fe_ct_eq(check, u);
+    let flipped_sign_sqrt = fe_ct_eq(check, neg_u);
+    let flipped_sign_sqrt_i = fe_ct_eq(check, neg_u_i);
+
+    let r_prime = &mut Fe::default();
+    r_prime.assign_product(&r, &Fe::from(SQRTM1));
+    let flipped = flipped_sign_sqrt | flipped_sign_sqrt_i;
+    r.cmov_from(r_prime, u32::from(flipped.unwrap_u8()));
+    ct_abs(&mut r);
+    (correct_sign_sqrt | flipped_sign_sqrt, r)
+}
+
+/// The one-way map from 32 bytes to a point of RFC 9496, Section 4.3.4, which is based on
+/// Elligator 2.
+fn map(bytes: &[u8]) -> GeP3 {
+    let d = &Fe::from(D);
+    let r0 = &mut Fe::default();
+    let r = &mut Fe::default();
+    let u = &mut Fe::default();
+    let v = &mut Fe::default();
+    let t0 = &mut Fe::default();
+    let t1 = &mut Fe::default();
+    r0.assign_from_bytes(bytes);
+    t0.assign_square(r0);
+    r.assign_product(t0, &Fe::from(SQRTM1));
+    t0.assign_sum(r, &ONE);
+    u.assign_product(t0, &Fe::from(ONE_MINUS_D_SQ));
+    t0.assign_product(r, d);
+    t1.assign_sum(t0, &ONE);
+    t1.neg();
+    t0.assign_sum(r, d);
+    v.assign_product(t1, t0);
+
+    let (was_square, mut s) = sqrt_ratio_m1(u, v);
+    let s_prime = &mut Fe::default();
+    s_prime.assign_product(&s, r0);
+    ct_abs(s_prime);
+    s_prime.neg();
+    s.cmov_from(s_prime, u32::from((!was_square).unwrap_u8()));
+    let mut c = Fe::default();
+    c.assign_neg(&ONE);
+    c.cmov_from(r, u32::from((!was_square).unwrap_u8()));
+
+    let n = &mut Fe::default();
+    t0.assign_difference(r, &ONE);
+    n.assign_product(&c, t0);
+    *n *= &Fe::from(D_MINUS_ONE_SQ);
+    *n -= v;
+
+    let w0 = &mut Fe::default();
+    let w1 = &mut Fe::default();
+    let w2 = &mut Fe::default();
+    let w3 = &mut Fe::default();
+    t0.assign_product(&s, v);
+    w0.assign_sum(t0, t0);
+    w1.assign_product(n, &Fe::from(SQRT_AD_MINUS_ONE));
+    t0.assign_square(&s);
+    w2.assign_difference(&ONE, t0);
+    w3.assign_sum(&ONE, t0);
+
+    let mut h = GeP3::default();
+    h.x.assign_product(w0, w3);
+    h.y.assign_product(w2, w1);
+    h.z.assign_product(w1, w3);
+    h.t.assign_product(w0, w2);
+    h
+}
+
+/// Checks whether two field elements are equal in time independent of their values.
+fn fe_ct_eq(f: &Fe, g: &Fe) -> Choice {
+    let f_bytes = &mut [0; 32];
+    let g_bytes = &mut [0; 32];
+    f.write_bytes(f_bytes);
+    g.write_bytes(g_bytes);
+    ct::ct_eq(f_bytes, g_bytes)
+}
+
+/// Replaces a field element with its absolute value, the one of it and its negation that is even.
+fn ct_abs(f: &mut Fe) {
+    let negative = Choice::from(f.is_negative() as u8);
+    conditional_negate(f, negative);
+}
+
+fn conditional_negate(f: &mut Fe, choice: Choice) {
+    let mut neg_f = Fe::default();
+    neg_f.assign_neg(f);
+    f.cmov_from(&neg_f, u32::from(choice.unwrap_u8()));
+}
+
+const ONE: Fe = Fe([1, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+
+/// The group order L, little-endian.
+const L: [u8; SCALAR_LEN] = [
+    0xed, 0xd3, 0xf5, 0x5c, 0x1a, 0x63, 0x12, 0x58, 0xd6, 0x9c, 0xf7, 0xa2, 0xde, 0xf9, 0xde, 0x14,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x10,
+];
+
+/// L - 1, which is -1 modulo L.
+const L_MINUS_ONE: Scalar = Scalar([
+    0xec, 0xd3, 0xf5, 0x5c, 0x1a, 0x63, 0x12, 0x58, 0xd6, 0x9c, 0xf7, 0xa2, 0xde, 0xf9, 0xde, 0x14,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x10,
+]);
+
+/// sqrt(a d - 1), where a = -1.
+const SQRT_AD_MINUS_ONE: [i32; 10] = [
+    24849947, 33400850, 43495378, 6347714, 46036536, 32887293, 41837720, 18186727, 66238516,
+    14525638,
+];
+
+/// 1 / sqrt(a - d), where a = -1.
+const INVSQRT_A_MINUS_D: [i32; 10] = [
+    6111466, 4156064, 39310137, 12243467, 41204824, 120896, 20826367, 26493656, 6093567, 31568420,
+];
+
+/// 1 - d^2.
+const ONE_MINUS_D_SQ: [i32; 10] = [
+    6275446, 16937061, 44170319, 29780721, 11667076, 7397348, 39186143, 1766194, 42675006, 672202,
+];
+
+/// (d - 1)^2.
+const D_MINUS_ONE_SQ: [i32; 10] = [
+    15551776, 22456977, 53683765, 23429360, 55212328, 10178283, 40474537, 4729243, 61826754,
+    23438029,
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sha2::sha512;
+    use crate::test_helpers::*;
+
+    // RFC 9496, Appendix A.1
+    const MULTIPLES: [&str; 16] = [
+        "0000000000000000000000000000000000000000000000000000000000000000",
+        "e2f2ae0a6abc4e71a884a961c500515f58e30b6aa582dd8db6a65945e08d2d76",
+        "6a493210f7499cd17fecb510ae0cea23a110e8d5b901f8acadd3095c73a3b919",
+        "94741f5d5d52755ece4f23f044ee27d5d1ea1e2bd196b462166b16152a9d0259",
+        "da80862773358b466ffadfe0b3293ab3d9fd53c5ea6c955358f568322daf6a57",
+        "e882b131016b52c1d3337080187cf768423efccbb517bb495ab812c4160ff44e",
+        "f64746d3c92b13050ed8d80236a7f0007c3b3f962f5ba793d19a601ebb1df403",
+        "44f53520926ec81fbd5a387845beb7df85a96a24ece18738bdcfa6a7822a176d",
+        "903293d8f2287ebe10e2374dc1a53e0bc887e592699f02d077d5263cdd55601c",
+        "02622ace8f7303a31cafc63f8fc48fdc16e1c8c8d234b2f0d6685282a9076031",
+        "20706fd788b2720a1ed2a5dad4952b01f413bcf0e7564de8cdc816689e2db95f",
+        "bce83f8ba5dd2fa572864c24ba1810f9522bc6004afe95877ac73241cafdab42",
+        "e4549ee16b9aa03099ca208c67adafcafa4c3f3e4e5303de6026e3ca8ff84460",
+        "aa52e000df2e16f55fb1032fc33bc42742dad6bd5a8fc0be0167436c5948501f",
+        "46376b80f409b29dc2b5f6f0c52591990896e5716f41477cd30085ab7f10301e",
+        "e0c418f7c8d9c4cdd7395b93ea124f3ad99021bb681dfc3302a9d99a2e53e64e",
+    ];
+
+    #[test]
+    fn test_multiples() {
+        let generator = Point::generator();
+        let mut point = Point::identity();
+        for (i, &expected) in MULTIPLES.iter().enumerate() {
+            let expected = &h2b(expected)[..];
+            assert_eq!(expected, &point.to_bytes()[..]);
+            assert_eq!(
+                expected,
+                &Point::mul_base(&Scalar::from(i as u64)).to_bytes()[..]
+            );
+            assert_eq!(
+                expected,
+                &(generator * Scalar::from(i as u64)).to_bytes()[..]
+            );
+            let decoded = Point::from_bytes(expected).unwrap();
+            assert!(decoded == point);
+            assert_eq!(expected, &decoded.to_bytes()[..]);
+            point = point + generator;
+        }
+        assert!(bool::from(Point::identity().is_identity()));
+        assert!(!bool::from(generator.is_identity()));
+    }
+
+    #[test]
+    fn test_bad_encodings() {
+        for &encoding in &[
+            // Non-canonical field elements
+            "edffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff7f",
+            "f3ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff7f",
+            "0100000000000000000000000000000000000000000000000000000000000080",
+            // Negative field elements
+            "0100000000000000000000000000000000000000000000000000000000000000",
+            "0300000000000000000000000000000000000000000000000000000000000000",
+            // Failing the checks after the square root, with y = 0 for the last
+            "0200000000000000000000000000000000000000000000000000000000000000",
+            "0800000000000000000000000000000000000000000000000000000000000000",
+            "0a00000000000000000000000000000000000000000000000000000000000000",
+            "26948d35ca62e643e26a83177332e6b6afeb9d08e4268b650f1f5bbd8d81d371",
+            "f6a6ad5f5a3f18f0b6d94fd00d5e5184da1ad8d5c3fa30a3f50d8d0f19d5d84b",
+            "ecffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff7f",
+        ] {
+            assert!(Point::from_bytes(&h2b(encoding)).is_none(), "{}", encoding);
+        }
+        assert!(Point::from_bytes(&[0; 31]).is_none());
+        assert!(Point::from_bytes(&[0; 33]).is_none());
+    }
+
+    #[test]
+    fn test_from_uniform_bytes() {
+        // RFC 9496, Appendix A.3
+        for &(label, expected) in &[
+            (
+                "Ristretto is traditionally a short shot of espresso coffee",
+                "3066f82a1a747d45120d1740f14358531a8f04bbffe6a819f86dfe50f44a0a46",
+            ),
+            (
+                "made with the normal amount of ground coffee but extracted with",
+                "f26e5b6f7d362d2d2a94c5d0e7602cb4773c95a2e5c31a64f133189fa76ed61b",
+            ),
+            (
+                "about half the amount of water in the same amount of time",
+                "006ccd2a9e6867e6a2c5cea83d3302cc9de128dd2a9a57dd8ee7b9d7ffe02826",
+            ),
+            (
+                "by using a finer grind.",
+                "f8f0c87cf237953c5890aec3998169005dae3eca1fbb04548c635953c817f92a",
+            ),
+            (
+                "This produces a concentrated shot of coffee per volume.",
+                "ae81e7dedf20a497e10c304a765c1767a42d6e06029758d2d7e8ef7cc4c41179",
+            ),
+            (
+                "Just pulling a normal shot short will produce a weaker shot",
+                "e2705652ff9f5e44d3e841bf1c251cf7dddb77d140870d1ab2ed64f1a9ce8628",
+            ),
+            (
+                "and is not a Ristretto as some believe.",
+                "80bd07262511cdde4863f8a7434cef696750681cb9510eea557088f76d9e5065",
+            ),
+        ] {
+            let point = Point::from_uniform_bytes(&sha512(label.as_bytes()));
+            assert_eq!(&h2b(expected)[..], &point.to_bytes()[..]);
+        }
+    }
+
+    // Checked against a Python implementation of RFC 9380 and RFC 9496
+    #[test]
+    fn test_hash_to_group() {
+        let dst = b"QUUX-V01-CS02-with-ristretto255_XMD:SHA-512_R255MAP_RO_";
+        assert_eq!(
+            &h2b("bed61e1ee1966329962880e236dfdc83afd52fd1ce116f64fb806f1e8acea926")[..],
+            &Point::hash_to_group(b"", dst).unwrap().to_bytes()[..]
+        );
+        assert_eq!(
+            &h2b("627b997b104ee62543358e22576c75a98dff9dc5f348d5ab228689735d77b258")[..],
+            &Point::hash_to_group(b"abc", dst).unwrap().to_bytes()[..]
+        );
+        assert_eq!(
+            &h2b("b38553af69c2102b0d64b51c319d71360ca942050d2a799a568d770141a4d80b")[..],
+            &Scalar::hash_to_scalar(b"", b"HashToScalar-test")
+                .unwrap()
+                .to_bytes()[..]
+        );
+        assert_eq!(
+            &h2b("3f640227568ac7351b0e69fff608cd01b4a1c30f3af3149ca769248d0fb2770f")[..],
+            &Scalar::hash_to_scalar(b"abc", b"HashToScalar-test")
+                .unwrap()
+                .to_bytes()[..]
+        );
+
+        let error = Err(Error::InvalidLength);
+        assert_eq!(error, Point::hash_to_group(b"", &[0; 256]).map(|_| ()));
+        assert_eq!(error, Scalar::hash_to_scalar(b"", &[0; 256]).map(|_| ()));
+    }
+
+    #[test]
+    fn test_point_arithmetic() {
+        let a = Scalar::hash_to_scalar(b"a", b"test").unwrap();
+        let b = Scalar::hash_to_scalar(b"b", b"test").unwrap();
+        let p = Point::hash_to_group(b"p", b"test").unwrap();
+        let q = Point::hash_to_group(b"q", b"test").unwrap();
+        assert!(p * a + p * b == p * (a + b));
+        assert!(p * a - p * b == p * (a - b));
+        assert!((p * a) * b == p * (a * b));
+        assert!(p * a + q * a == (p + q) * a);
+        assert!(-p == p * -Scalar::ONE);
+        assert!(bool::from((p - p).is_identity()));
+        assert!(bool::from((p * Scalar::ZERO).is_identity()));
+        assert!(Point::mul_base(&a) == Point::generator() * a);
+        assert!(p != q);
+
+        let p_or_q = Point::conditional_select(&p, &q, Choice::from(1));
+        assert!(p_or_q == q);
+        let p_or_q = Point::conditional_select(&p, &q, Choice::from(0));
+        assert!(p_or_q == p);
+
+        // Adding a point of order 4 on the curve gives another representative of the same
+        // element, which must compare equal and encode identically.
+        let mut torsion = GeP3::zero();
+        torsion.x = Fe::from(SQRTM1);
+        torsion.y.assign_zero();
+        let equivalent = p + Point(torsion);
+        assert!(equivalent == p);
+        assert_eq!(p.to_bytes(), equivalent.to_bytes());
+    }
+
+    #[test]
+    fn test_scalar_arithmetic() {
+        let a = Scalar::hash_to_scalar(b"a", b"test").unwrap();
+        let b = Scalar::hash_to_scalar(b"b", b"test").unwrap();
+        assert!(a * a.invert() == Scalar::ONE);
+        assert!(Scalar::ZERO.invert() == Scalar::ZERO);
+        assert!(a + b - b == a);
+        assert!(a + -a == Scalar::ZERO);
+        assert!(Scalar::from(6) * Scalar::from(7) == Scalar::from(42));
+        assert!(Scalar::from(1) - Scalar::from(2) == L_MINUS_ONE);
+        assert!(bool::from(Scalar::ZERO.is_zero()));
+        assert!(!bool::from(a.is_zero()));
+        assert!(Scalar::conditional_select(&a, &b, Choice::from(1)) == b);
+
+        assert!(Scalar::from_bytes(&a.to_bytes()).unwrap() == a);
+        assert!(Scalar::from_bytes(&L_MINUS_ONE.to_bytes()).is_some());
+        assert!(Scalar::from_bytes(&L).is_none());
+        assert!(Scalar::from_bytes(&[0xff; 32]).is_none());
+        assert!(Scalar::from_bytes(&[0; 31]).is_none());
+        let mut wide = [0; 64];
+        wide[..32].copy_from_slice(&L);
+        assert!(Scalar::from_uniform_bytes(&wide) == Scalar::ZERO);
+    }
+}
@@ -0,0 +1,1158 @@
+//! Module for RSA (RFC 8017): key generation, RSASSA-PKCS1-v1_5 and RSASSA-PSS signatures, and
+//! RSAES-OAEP encryption.
+//!
+//! Private-key operations use the Chinese remainder theorem with constant-time modular
+//! exponentiation. Their input is blinded with a fresh random value, and their output is checked
+//! against the public key before it is released, so that a faulty computation cannot leak the
+//! factors of the modulus.
+//!
+//! # Examples
+//!
+//! ```
+//! use crypto_pure::rsa::PrivateKey;
+//! use crypto_pure::sha2::Sha256;
+//! use crypto_pure::rand::SystemRandom;
+//! let rng = &SystemRandom::new();
+//! let private_key = PrivateKey::generate(1024, rng);
+//! let signature = private_key.sign_pss::<Sha256>(b"message", rng)?;
+//! let public_key = private_key.public_key();
+//! assert!(public_key.verify_pss::<Sha256>(b"message", &signature));
+//! # Ok::<(), crypto_pure::error::Error>(())
+//! ```
+use crate::bigint::{
+    add_with_carry, bit_len, div_rem, from_be_bytes, gcd, is_zero, mul, resize, shr1, small_rem,
+    sub_with_borrow, to_be_bytes, Modulus,
+};
+use crate::ct::{constant_time_eq, lt_mask};
+use crate::error::Error;
+use crate::mgf1::mgf1_xor;
+use crate::rand::SecureRandom;
+use crate::secret::SecretVec;
+use crate::sha2::{Digest, HashFunction, Sha224, Sha256, Sha384, Sha512};
+use crate::sig::{self, Signer, Verifier};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+use core::marker::PhantomData;
+
+/// The public exponent of generated keys.
+const PUBLIC_EXPONENT: u64 = 65537;
+
+/// A hash function with the DER encoding of its DigestInfo prefix for PKCS #1 v1.5 signatures.
+pub trait Pkcs1Hash: HashFunction {
+    /// The DER-encoded DigestInfo structure up to the digest itself.
+    const DIGEST_INFO_PREFIX: &'static [u8];
+}
+
+impl Pkcs1Hash for Sha224 {
+    const DIGEST_INFO_PREFIX: &'static [u8] = &[
+        0x30, 0x2d, 0x30, 0x0d, 0x06, 0x09, 0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x04,
+        0x05, 0x00, 0x04, 0x1c,
+    ];
+}
+
+impl Pkcs1Hash for Sha256 {
+    const DIGEST_INFO_PREFIX: &'static [u8] = &[
+        0x30, 0x31, 0x30, 0x0d, 0x06, 0x09, 0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x01,
+        0x05, 0x00, 0x04, 0x20,
+    ];
+}
+
+impl Pkcs1Hash for Sha384 {
+    const DIGEST_INFO_PREFIX: &'static [u8] = &[
+        0x30, 0x41, 0x30, 0x0d, 0x06, 0x09, 0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x02,
+        0x05, 0x00, 0x04, 0x30,
+    ];
+}
+
+impl Pkcs1Hash for Sha512 {
+    const DIGEST_INFO_PREFIX: &'static [u8] = &[
+        0x30, 0x51, 0x30, 0x0d, 0x06, 0x09, 0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x03,
+        0x05, 0x00, 0x04, 0x40,
+    ];
+}
+
+/// An error returned when a ciphertext fails to decrypt, which does not tell why, so that it
+/// cannot be used as a padding oracle.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DecryptionError;
+
+impl fmt::Display for DecryptionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("decryption failed")
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for DecryptionError {}
+
+/// An RSA public key.
+#[derive(Clone)]
+pub struct PublicKey {
+    n: Modulus,
+    e: Vec<u64>,
+}
+
+impl PublicKey {
+    /// Creates a public key from its big-endian modulus and public exponent, or outputs `None` if
+    /// the modulus is even or the exponent is not an odd integer between 3 and the modulus.
+    pub fn new(n: &[u8], e: &[u8]) -> Option<Self> {
+        let n = from_be_bytes(trim(n));
+        let e = from_be_bytes(trim(e));
+        if n[0] & 1 == 0 || e[0] & 1 == 0 || bit_len(&e) < 2 || e.len() > n.len() {
+            return None;
+        }
+        let n = Modulus::new(n);
+        let e = resize(&e, n.len());
+        if !n.contains(&e) {
+            return None;
+        }
+        Some(Self { n, e })
+    }
+
+    /// Outputs the length of the modulus and of signatures in bytes.
+    pub fn size(&self) -> usize {
+        bit_len(self.n.modulus()).div_ceil(8)
+    }
+
+    /// Outputs the big-endian modulus.
+    pub fn modulus(&self) -> Vec<u8> {
+        let mut bytes = vec![0; self.size()];
+        to_be_bytes(self.n.modulus(), &mut bytes);
+        bytes
+    }
+
+    /// Outputs the big-endian public exponent.
+    pub fn public_exponent(&self) -> Vec<u8> {
+        let mut bytes = vec![0; bit_len(&self.e).div_ceil(8)];
+        to_be_bytes(&self.e, &mut bytes);
+        bytes
+    }
+
+    /// Verifies an RSASSA-PKCS1-v1_5 signature of a message with hash function `H`.
+    pub fn verify_pkcs1v15<H: Pkcs1Hash>(&self, message: &[u8], signature: &[u8]) -> bool {
+        let encoded = match self.recover(signature) {
+            Some(encoded) => encoded,
+            None => return false,
+        };
+        let digest = hash::<H>(&[message]);
+        pkcs1v15_encode::<H>(&digest, self.size()).is_some_and(|expected| encoded == expected)
+    }
+
+    /// Verifies an RSASSA-PSS signature of a message with hash function `H`, using MGF1 with `H`
+    /// and a salt as long as the digest.
+    pub fn verify_pss<H: HashFunction>(&self, message: &[u8], signature: &[u8]) -> bool {
+        let encoded = match self.recover(signature) {
+            Some(encoded) => encoded,
+            None => return false,
+        };
+        // The encoded message has one bit less than the modulus, so it may be a byte shorter.
+        let em_bits = bit_len(self.n.modulus()) - 1;
+        let (zeros, encoded) = encoded.split_at(self.size() - em_bits.div_ceil(8));
+        zeros.iter().all(|byte| *byte == 0)
+            && pss_verify::<H>(&hash::<H>(&[message]), encoded, em_bits)
+    }
+
+    /// Encrypts a message with RSAES-OAEP and hash function `H`, using MGF1 with `H` and a label,
+    /// which may be empty. The seed is drawn from `rng`.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::InvalidLength` if the message is longer than the modulus minus two
+    /// digests of `H` and two more bytes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crypto_pure::rsa::PrivateKey;
+    /// use crypto_pure::sha2::Sha256;
+    /// use crypto_pure::rand::SystemRandom;
+    /// let rng = &SystemRandom::new();
+    /// # let private_key = PrivateKey::generate(1024, rng);
+    /// let public_key = private_key.public_key();
+    /// let ciphertext = public_key.encrypt_oaep::<Sha256>(b"message", b"", rng)?;
+    /// let message = private_key.decrypt_oaep::<Sha256>(&ciphertext, b"", rng);
+    /// assert_eq!(b"message", &message.unwrap()[..]);
+    /// # Ok::<(), crypto_pure::error::Error>(())
+    /// ```
+    pub fn encrypt_oaep<H: HashFunction>(
+        &self,
+        message: &[u8],
+        label: &[u8],
+        rng: &dyn SecureRandom,
+    ) -> Result<Vec<u8>, Error> {
+        let seed = &mut vec![0; H::DIGEST_SIZE];
+        rng.fill(seed);
+        let encoded =
+            oaep_encode::<H>(message, label, seed, self.size()).ok_or(Error::InvalidLength)?;
+        Ok(self.encrypt_encoded(&encoded))
+    }
+
+    fn encrypt_encoded(&self, encoded: &[u8]) -> Vec<u8> {
+        let m = resize(&from_be_bytes(encoded), self.n.len());
+        let mut ciphertext = vec![0; self.size()];
+        to_be_bytes(&exp(&self.n, &m, &self.e), &mut ciphertext);
+        ciphertext
+    }
+
+    /// Applies the public-key operation to a signature, outputting the encoded message or `None`
+    /// if the signature is not an integer below the modulus of the right length.
+    fn recover(&self, signature: &[u8]) -> Option<Vec<u8>> {
+        if signature.len() != self.size() {
+            return None;
+        }
+        let s = resize(&from_be_bytes(signature), self.n.len());
+        if !self.n.contains(&s) {
+            return None;
+        }
+        let mut encoded = vec![0; self.size()];
+        to_be_bytes(&exp(&self.n, &s, &self.e), &mut encoded);
+        Some(encoded)
+    }
+}
+
+/// An RSA private key, holding the prime factors of the modulus.
+#[derive(Clone)]
+pub struct PrivateKey {
+    public_key: PublicKey,
+    p: Modulus,
+    q: Modulus,
+    d: Vec<u64>,
+    /// d mod (p - 1)
+    dp: Vec<u64>,
+    /// d mod (q - 1)
+    dq: Vec<u64>,
+    /// q^-1 mod p in Montgomery form
+    q_inv: Vec<u64>,
+}
+
+impl PrivateKey {
+    /// Generates a key with a modulus of `bits` bits and public exponent 65537 as in FIPS 186-4,
+    /// drawing from `rng`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bits` is odd or less than 1024.
+    pub fn generate(bits: usize, rng: &dyn SecureRandom) -> Self {
+        assert!(bits >= 1024 && bits.is_multiple_of(2), "invalid key size");
+        let e = PUBLIC_EXPONENT;
+        loop {
+            let p = generate_prime(bits / 2, rng);
+            let q = generate_prime(bits / 2, rng);
+            let (difference, borrow) = sub_with_borrow(&p, &q);
+            let difference = if borrow == 1 {
+                sub_with_borrow(&q, &p).0
+            } else {
+                difference
+            };
+            if bit_len(&difference) <= bits / 2 - 100 {
+                continue;
+            }
+
+            // d = e^-1 mod lcm(p - 1, q - 1). With l = lcm(p - 1, q - 1) and u = l^-1 mod e,
+            // l (e - u) + 1 is divisible by e, and the quotient is d.
+            let (p_1, q_1) = (minus_one(&p), minus_one(&q));
+            let lcm = div_rem(&mul(&p_1, &q_1), &gcd(&p_1, &q_1)).0;
+            let u = pow_small(small_rem(&lcm, e), e - 2, e);
+            let product = mul(&lcm, &[e - u]);
+            let mut one = vec![0; product.len()];
+            one[0] = 1;
+            let d = div_rem(&add_with_carry(&product, &one).0, &[e]).0;
+            // FIPS 186-4 also requires d > 2^(bits / 2).
+            if bit_len(&d) <= bits / 2 {
+                continue;
+            }
+
+            let n = Modulus::new(resize(&mul(&p, &q), bits.div_ceil(64)));
+            let public_key = PublicKey {
+                e: resize(&[e], n.len()),
+                n,
+            };
+            let d = resize(&d, public_key.n.len());
+            return Self::from_primes(public_key, &d, p, q).expect("invalid generated key");
+        }
+    }
+
+    /// Creates a private key from its big-endian modulus, public exponent, private exponent and
+    /// prime factors, or outputs `None` if they are inconsistent.
+    pub fn from_components(n: &[u8], e: &[u8], d: &[u8], p: &[u8], q: &[u8]) -> Option<Self> {
+        let public_key = PublicKey::new(n, e)?;
+        let n = public_key.n.modulus();
+        let (d, p, q) = (
+            from_be_bytes(trim(d)),
+            from_be_bytes(trim(p)),
+            from_be_bytes(trim(q)),
+        );
+        if d.len() > n.len() || p[0] & 1 == 0 || q[0] & 1 == 0 || bit_len(&p) < 2 {
+            return None;
+        }
+        let product = mul(&p, &q);
+        if bit_len(&product) != bit_len(n) || resize(&product, n.len()) != n {
+            return None;
+        }
+        let d = resize(&d, n.len());
+        Self::from_primes(public_key, &d, p, q)
+    }
+
+    /// Outputs the public key.
+    pub fn public_key(&self) -> &PublicKey {
+        &self.public_key
+    }
+
+    /// Signs a message with RSASSA-PKCS1-v1_5 and hash function `H`. The value blinding the
+    /// private-key operation is drawn from `rng`.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::InvalidKey` if the modulus is too short for the DigestInfo of `H`.
+    pub fn sign_pkcs1v15<H: Pkcs1Hash>(
+        &self,
+        message: &[u8],
+        rng: &dyn SecureRandom,
+    ) -> Result<Vec<u8>, Error> {
+        let digest = hash::<H>(&[message]);
+        let encoded =
+            pkcs1v15_encode::<H>(&digest, self.public_key.size()).ok_or(Error::InvalidKey)?;
+        Ok(self.sign_encoded(&encoded, rng))
+    }
+
+    /// Signs a message with RSASSA-PSS and hash function `H`, using MGF1 with `H` and a salt as
+    /// long as the digest. The salt and the value blinding the private-key operation are drawn
+    /// from `rng`.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::InvalidKey` if the modulus is too short to hold two digests of `H` and
+    /// two more bytes.
+    pub fn sign_pss<H: HashFunction>(
+        &self,
+        message: &[u8],
+        rng: &dyn SecureRandom,
+    ) -> Result<Vec<u8>, Error> {
+        let salt = &mut vec![0; H::DIGEST_SIZE];
+        rng.fill(salt);
+        let em_bits = bit_len(self.public_key.n.modulus()) - 1;
+        let encoded =
+            pss_encode::<H>(&hash::<H>(&[message]), salt, em_bits).ok_or(Error::InvalidKey)?;
+        Ok(self.sign_encoded(&encoded, rng))
+    }
+
+    /// Decrypts a ciphertext with RSAES-OAEP and hash function `H`, using MGF1 with `H` and the
+    /// label used for encryption. The value blinding the private-key operation is drawn from
+    /// `rng`.
+    ///
+    /// All checks of the decoded message are done in constant time and combined, so that failures
+    /// are indistinguishable, as needed to resist Manger's attack.
+    pub fn decrypt_oaep<H: HashFunction>(
+        &self,
+        ciphertext: &[u8],
+        label: &[u8],
+        rng: &dyn SecureRandom,
+    ) -> Result<Vec<u8>, DecryptionError> {
+        let n = &self.public_key.n;
+        let (k, h_len) = (self.public_key.size(), H::DIGEST_SIZE);
+        if ciphertext.len() != k || k < 2 * h_len + 2 {
+            return Err(DecryptionError);
+        }
+        let c = resize(&from_be_bytes(ciphertext), n.len());
+        if !n.contains(&c) {
+            return Err(DecryptionError);
+        }
+        let mut encoded = vec![0; k];
+        to_be_bytes(&self.private_op(&c, rng), &mut encoded);
+
+        let (y, rest) = encoded.split_at_mut(1);
+        let (seed, db) = rest.split_at_mut(h_len);
+        mgf1_xor::<H>(db, seed);
+        mgf1_xor::<H>(seed, db);
+        let (label_hash, padded) = db.split_at(h_len);
+        let label_matches = u32::from(constant_time_eq(label_hash, &hash::<H>(&[label])));
+        let mut valid = lt_mask(y[0].into(), 1) & label_matches.wrapping_neg();
+        // Find the 0x01 after the zero padding without branching on the bytes.
+        let mut looking = !0;
+        let mut index = 0;
+        for (i, byte) in padded.iter().enumerate() {
+            let is_zero = lt_mask((*byte).into(), 1);
+            let is_one = lt_mask((byte ^ 1).into(), 1);
+            index |= (looking & is_one) as usize & i;
+            valid &= !looking | is_zero | is_one;
+            looking &= is_zero;
+        }
+        valid &= !looking;
+        if valid == 0 {
+            return Err(DecryptionError);
+        }
+        Ok(padded[index + 1..].to_vec())
+    }
+
+    /// Outputs the big-endian private exponent, prime factors, CRT exponents and CRT coefficient
+    /// in the order of the PKCS #1 RSAPrivateKey structure.
+    pub(crate) fn private_components(&self) -> [SecretVec; 6] {
+        let secret_bytes = |a: &[u64]| {
+            let mut bytes = SecretVec::new(vec![0; 8 * a.len()]);
+            to_be_bytes(a, &mut bytes);
+            bytes
+        };
+        [
+            secret_bytes(&self.d),
+            secret_bytes(self.p.modulus()),
+            secret_bytes(self.q.modulus()),
+            secret_bytes(&self.dp),
+            secret_bytes(&self.dq),
+            secret_bytes(&self.p.to_normal(&self.q_inv)),
+        ]
+    }
+
+    fn from_primes(public_key: PublicKey, d: &[u64], p: Vec<u64>, q: Vec<u64>) -> Option<Self> {
+        let e = &public_key.e;
+        let (p_1, q_1) = (minus_one(&p), minus_one(&q));
+        let dp = div_rem(d, &p_1).1;
+        let dq = div_rem(d, &q_1).1;
+        // Check e d = 1 modulo p - 1 and q - 1, so that signatures verify.
+        for (exponent, order) in &[(&dp, &p_1), (&dq, &q_1)] {
+            let remainder = div_rem(&mul(e, exponent), order).1;
+            if bit_len(&remainder) != 1 {
+                return None;
+            }
+        }
+        let p = Modulus::new(p);
+        let q_mod_p = p.reduce(&q);
+        if is_zero(&q_mod_p) {
+            return None;
+        }
+        let q_inv = p.pow(&p.to_montgomery(&q_mod_p), &minus_two(p.modulus()));
+        Some(Self {
+            public_key,
+            q: Modulus::new(q),
+            p,
+            d: d.to_vec(),
+            dp,
+            dq,
+            q_inv,
+        })
+    }
+
+    fn sign_encoded(&self, encoded: &[u8], rng: &dyn SecureRandom) -> Vec<u8> {
+        let m = resize(&from_be_bytes(encoded), self.public_key.n.len());
+        let mut signature = vec![0; self.public_key.size()];
+        to_be_bytes(&self.private_op(&m, rng), &mut signature);
+        signature
+    }
+
+    /// Computes m^d mod n for a value m below n.
+    fn private_op(&self, m: &[u64], rng: &dyn SecureRandom) -> Vec<u64> {
+        let n = &self.public_key.n;
+        // Blind the input as m r^e for a random r, so the exponentiation gives m^d r.
+        let mut bytes = vec![0; 8 * n.len()];
+        let r = loop {
+            rng.fill(&mut bytes);
+            let mut r = from_be_bytes(&bytes);
+            r[n.len() - 1] &= u64::MAX >> n.modulus()[n.len() - 1].leading_zeros();
+            if n.contains(&r) && !is_zero(&r) {
+                break r;
+            }
+        };
+        let r_e = n.pow(&n.to_montgomery(&r), &self.public_key.e);
+        let blinded = n.mul(&n.to_montgomery(m), &r_e);
+        let blinded = n.to_normal(&blinded);
+
+        let s_1 = exp(&self.p, &blinded, &self.dp);
+        let s_2 = exp(&self.q, &blinded, &self.dq);
+        let s = self.combine(&s_1, &s_2);
+        let r_inv = self.combine(
+            &exp(&self.p, &r, &minus_two(self.p.modulus())),
+            &exp(&self.q, &r, &minus_two(self.q.modulus())),
+        );
+        let s = n.mul(&n.to_montgomery(&s), &r_inv);
+        assert!(
+            exp(n, &s, &self.public_key.e) == m,
+            "RSA private-key operation failed"
+        );
+        s
+    }
+
+    /// Combines residues modulo p and q into the residue modulo n, using Garner's formula.
+    fn combine(&self, mod_p: &[u64], mod_q: &[u64]) -> Vec<u64> {
+        let p = &self.p;
+        let h = p.mul(&p.sub(mod_p, &p.reduce(mod_q)), &self.q_inv);
+        let len = self.public_key.n.len();
+        let hq = resize(&mul(&h, self.q.modulus()), len);
+        add_with_carry(&hq, &resize(mod_q, len)).0
+    }
+}
+
+/// RSASSA-PKCS1-v1_5 with hash function `H`.
+pub struct Pkcs1v15<H>(PhantomData<H>);
+
+impl<H: Pkcs1Hash> Verifier for Pkcs1v15<H> {
+    const MAX_CONTEXT_LEN: usize = 0;
+
+    type PublicKey = PublicKey;
+
+    fn verify(
+        message: &[u8],
+        context: &[u8],
+        signature: &[u8],
+        public_key: &PublicKey,
+    ) -> Result<(), Error> {
+        sig::check_context::<Self>(context)?;
+        sig::check_valid(public_key.verify_pkcs1v15::<H>(message, signature))
+    }
+}
+
+impl<H: Pkcs1Hash> Signer for Pkcs1v15<H> {
+    type SecretKey = PrivateKey;
+    type Signature = Vec<u8>;
+
+    fn sign(
+        message: &[u8],
+        context: &[u8],
+        secret_key: &PrivateKey,
+        rng: &dyn SecureRandom,
+    ) -> Result<Vec<u8>, Error> {
+        sig::check_context::<Self>(context)?;
+        secret_key.sign_pkcs1v15::<H>(message, rng)
+    }
+}
+
+/// RSASSA-PSS with hash function `H` and the parameters of `sign_pss`.
+pub struct Pss<H>(PhantomData<H>);
+
+impl<H: HashFunction> Verifier for Pss<H> {
+    const MAX_CONTEXT_LEN: usize = 0;
+
+    type PublicKey = PublicKey;
+
+    fn verify(
+        message: &[u8],
+        context: &[u8],
+        signature: &[u8],
+        public_key: &PublicKey,
+    ) -> Result<(), Error> {
+        sig::check_context::<Self>(context)?;
+        sig::check_valid(public_key.verify_pss::<H>(message, signature))
+    }
+}
+
+impl<H: HashFunction> Signer for Pss<H> {
+    type SecretKey = PrivateKey;
+    type Signature = Vec<u8>;
+
+    fn sign(
+        message: &[u8],
+        context: &[u8],
+        secret_key: &PrivateKey,
+        rng: &dyn SecureRandom,
+    ) -> Result<Vec<u8>, Error> {
+        sig::check_context::<Self>(context)?;
+        secret_key.sign_pss::<H>(message, rng)
+    }
+}
+
+/// Strips leading zero bytes from a big-endian integer.
+fn trim(bytes: &[u8]) -> &[u8] {
+    let start = bytes.iter().position(|byte| *byte != 0);
+    &bytes[start.unwrap_or(bytes.len())..]
+}
+
+/// Outputs a - 1 for an odd integer a.
+fn minus_one(a: &[u64]) -> Vec<u64> {
+    let mut result = a.to_vec();
+    result[0] ^= 1;
+    result
+}
+
+fn minus_two(a: &[u64]) -> Vec<u64> {
+    let mut two = vec![0; a.len()];
+    two[0] = 2;
+    sub_with_borrow(a, &two).0
+}
+
+/// Computes a^exponent mod m for an integer a with any number of limbs.
+fn exp(modulus: &Modulus, a: &[u64], exponent: &[u64]) -> Vec<u64> {
+    let a = modulus.to_montgomery(&modulus.reduce(a));
+    modulus.to_normal(&modulus.pow(&a, exponent))
+}
+
+fn pow_small(a: u64, exponent: u64, m: u64) -> u64 {
+    let mul = |a: u64, b: u64| (u128::from(a) * u128::from(b) % u128::from(m)) as u64;
+    (0..64).rev().fold(1, |result, i| {
+        let result = mul(result, result);
+        if (exponent >> i) & 1 == 1 {
+            mul(result, a)
+        } else {
+            result
+        }
+    })
+}
+
+/// Generates a random prime of exactly `bits` bits whose top two bits are set, so that the product
+/// of two such primes has exactly twice as many bits, and which is not 1 modulo the public
+/// exponent.
+fn generate_prime(bits: usize, rng: &dyn SecureRandom) -> Vec<u64> {
+    let len = bits.div_ceil(64);
+    let mut bytes = vec![0; 8 * len];
+    loop {
+        rng.fill(&mut bytes);
+        let mut p = from_be_bytes(&bytes);
+        p[len - 1] &= u64::MAX >> (64 * len - bits);
+        for bit in &[bits - 1, bits - 2, 0] {
+            p[bit / 64] |= 1 << (bit % 64);
+        }
+        if small_rem(&p, PUBLIC_EXPONENT) == 1 {
+            continue;
+        }
+        // Dividing by small odd numbers quickly rules out most composites.
+        if (3..1000)
+            .step_by(2)
+            .any(|divisor| small_rem(&p, divisor) == 0)
+        {
+            continue;
+        }
+        let rounds = match bits {
+            0..=767 => 7,
+            768..=1279 => 5,
+            _ => 4,
+        };
+        if is_probable_prime(&p, rounds, rng) {
+            return p;
+        }
+    }
+}
+
+/// Runs the Miller-Rabin test on an odd integer with its top bit set, with random bases.
+fn is_probable_prime(p: &[u64], rounds: usize, rng: &dyn SecureRandom) -> bool {
+    let modulus = Modulus::new(p.to_vec());
+    let len = modulus.len();
+    let mut t = minus_one(p);
+    let mut s = 0;
+    while t[0] & 1 == 0 {
+        shr1(&mut t);
+        s += 1;
+    }
+    let one = modulus.one();
+    let minus_one = modulus.sub(&vec![0; len], &one);
+    let mut bytes = vec![0; 8 * len];
+    for _ in 0..rounds {
+        // A base with the top bit of p cleared is below p - 1, and it is retried until above 1.
+        let base = loop {
+            rng.fill(&mut bytes);
+            let mut base = from_be_bytes(&bytes);
+            base[len - 1] &= (u64::MAX >> 1) >> p[len - 1].leading_zeros();
+            if bit_len(&base) > 1 {
+                break base;
+            }
+        };
+        let mut x = modulus.pow(&modulus.to_montgomery(&base), &t);
+        if x == one || x == minus_one {
+            continue;
+        }
+        let mut witness = true;
+        for _ in 1..s {
+            x = modulus.square(&x);
+            if x == minus_one {
+                witness = false;
+                break;
+            }
+        }
+        if witness {
+            return false;
+        }
+    }
+    true
+}
+
+/// Hashes the concatenation of several inputs.
+fn hash<H: HashFunction>(inputs: &[&[u8]]) -> Digest {
+    let mut hash_function = H::default();
+    inputs.iter().for_each(|input| hash_function.update(input));
+    Digest::new(hash_function)
+}
+
+/// Encodes a digest as 0x00 || 0x01 || 0xff... || 0x00 || DigestInfo of length `len`, or outputs
+/// `None` if it would need fewer than 8 bytes of 0xff.
+fn pkcs1v15_encode<H: Pkcs1Hash>(digest: &[u8], len: usize) -> Option<Vec<u8>> {
+    let prefix = H::DIGEST_INFO_PREFIX;
+    let t_len = prefix.len() + digest.len();
+    if len < t_len + 11 {
+        return None;
+    }
+    let mut encoded = vec![0xff; len];
+    encoded[0] = 0;
+    encoded[1] = 1;
+    encoded[len - t_len - 1] = 0;
+    encoded[len - t_len..len - digest.len()].copy_from_slice(prefix);
+    encoded[len - digest.len()..].copy_from_slice(digest);
+    Some(encoded)
+}
+
+/// Encodes a message with EME-OAEP into `len` bytes, given the seed, or outputs `None` if the
+/// message is too long.
+fn oaep_encode<H: HashFunction>(
+    message: &[u8],
+    label: &[u8],
+    seed: &[u8],
+    len: usize,
+) -> Option<Vec<u8>> {
+    let h_len = H::DIGEST_SIZE;
+    if len < 2 * h_len + 2 + message.len() {
+        return None;
+    }
+    let mut encoded = vec![0; len];
+    let (masked_seed, db) = encoded[1..].split_at_mut(h_len);
+    let db_len = db.len();
+    db[..h_len].copy_from_slice(&hash::<H>(&[label]));
+    db[db_len - message.len() - 1] = 1;
+    db[db_len - message.len()..].copy_from_slice(message);
+    masked_seed.copy_from_slice(seed);
+    mgf1_xor::<H>(masked_seed, db);
+    mgf1_xor::<H>(db, masked_seed);
+    Some(encoded)
+}
+
+/// Encodes a message digest with EMSA-PSS into `em_bits` bits, or outputs `None` if `em_bits` is
+/// too small for the digest and salt.
+fn pss_encode<H: HashFunction>(digest: &[u8], salt: &[u8], em_bits: usize) -> Option<Vec<u8>> {
+    let em_len = em_bits.div_ceil(8);
+    let h_len = H::DIGEST_SIZE;
+    if em_len < h_len + salt.len() + 2 {
+        return None;
+    }
+    let h = hash::<H>(&[&[0; 8], digest, salt]);
+    let mut encoded = vec![0; em_len];
+    let (db, rest) = encoded.split_at_mut(em_len - h_len - 1);
+    let db_len = db.len();
+    db[db_len - salt.len() - 1] = 1;
+    db[db_len - salt.len()..].copy_from_slice(salt);
+    mgf1_xor::<H>(&h, db);
+    db[0] &= 0xff >> (8 * em_len - em_bits);
+    rest[..h_len].copy_from_slice(&h);
+    rest[h_len] = 0xbc;
+    Some(encoded)
+}
+
+/// Checks an EMSA-PSS encoding of a message digest into `em_bits` bits, with a salt as long as
+/// the digest.
+fn pss_verify<H: HashFunction>(digest: &[u8], encoded: &[u8], em_bits: usize) -> bool {
+    let em_len = encoded.len();
+    let h_len = H::DIGEST_SIZE;
+    if em_len < 2 * h_len + 2 || encoded[em_len - 1] != 0xbc {
+        return false;
+    }
+    let top_mask = 0xff >> (8 * em_len - em_bits);
+    let (masked_db, h) = encoded[..em_len - 1].split_at(em_len - h_len - 1);
+    if masked_db[0] & !top_mask != 0 {
+        return false;
+    }
+    let db = &mut masked_db.to_vec();
+    mgf1_xor::<H>(h, db);
+    db[0] &= top_mask;
+    let (padding, salt) = db.split_at(db.len() - h_len);
+    let (zeros, one) = padding.split_at(padding.len() - 1);
+    zeros.iter().all(|byte| *byte == 0)
+        && one == [1]
+        && &hash::<H>(&[&[0; 8], digest, salt])[..] == h
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chacha20::Stream;
+    use crate::test_helpers::*;
+    use core::cell::{Cell, RefCell};
+
+    // A 2048-bit key with e = 65537, generated with the Python cryptography package
+    const N: &str = "b2167c14e87d6d9edf7b690b9a33caa10f3dab5c46538beed68432b4b810af4f\
+                     5545c4d3b643d3667a42f864ccc3a5c0e8c9346c62a37ce4b9c525826e2a318a\
+                     23f3464a8b2b18e6000f2928b5202d5057078437436bb1a30c4bbe85929599d1\
+                     24031c63a99f1f51d9ed711bce67ffe0fa088d938d6076d9d0977fedd118d96c\
+                     29705812f9f5385e3d75b28dcc02a9d6487f3c1230bb732efd4943e8aab1c13d\
+                     138164795390b4fb9ed48b8a2a186a48cd28b5dc4689bb3736354421ea8be041\
+                     ec19ff161ec80749dab41f9d254737edde2f99b907f8826f895230c5cf08d930\
+                     f565e2b4fa1395cdccc2f8ceab26e486c926b815344bdd7ee0412436b3169507";
+    const D: &str = "3190376d5c3d1846a89d00a1c583578816a54bad4d37505122ebbceef5f152d1\
+                     2a02d66233ae51c60a0ddc72b5585827c5c1c5b0ca8d04a3e59ddc301e37e8fc\
+                     b0bfb296e97f8f98e77850b4af202aec5aa451644e03c976fee38fbd3e0c1bb8\
+                     8ca4bf006fc344e4b359b56a8f39fb89621d024608094a5a9986a88887f2ba03\
+                     bc94d8c19b94edd59e2659a47377ec9a9c9cf477ebb348c79d7b9c644c115e65\
+                     e0361d5899470f7c165eb46565dedadfe8123187132de1cfae0b8333410ff72d\
+                     f3a2315fdcc61e2237bcf4f4b8260b78525674f08c3436429fd7a75d5fa33c18\
+                     0b9cd52610c6157d62fe47273b89d5154fe01912858021901dd17fc6fc866ec9";
+    const P: &str = "e72fa38397f02f472dbb353f6cec97147aabd5ae29753523647972550c80a5af\
+                     5e50602082d9115523af322f496cbf1a3dc2e043c8440cd987f9a22a7f6aeb93\
+                     51860fee51c71d06c7b45f487c517892e2030642403cc1c14c988c16abc758c6\
+                     73b114f9d4df2caa082be5fe5a06d1de15abaf801d23142b61fc8e1e37f2698f";
+    const Q: &str = "c533d9e18c7f5ce3620f9e16b9eb250f0d5d09a56571484cb42bb7b97e440c8a\
+                     076951c8df6e699ac3720e62112aa6b36d7e63560dae23e7b6e7772cf3fe1865\
+                     7ee4100699099ab6152a4147754aaadd9117f6072cd5941403953619279593ef\
+                     79d0225136b47212de94de2c6dd3b0ace25e0b42f0983f8cebc8fb48d99bb109";
+
+    fn private_key() -> PrivateKey {
+        PrivateKey::from_components(&h2b(N), &[1, 0, 1], &h2b(D), &h2b(P), &h2b(Q)).unwrap()
+    }
+
+    /// Fills buffers with 0, 1, 2, ..., so PSS salts are predictable.
+    fn counting(buffer: &mut [u8]) {
+        (0..).zip(buffer).for_each(|(i, byte)| *byte = i as u8);
+    }
+
+    #[test]
+    fn test_pkcs1v15() {
+        let private_key = &private_key();
+        let public_key = private_key.public_key();
+        let expected = "705b00a05612164c22a711a2ee4f2be1b8a5edae5a35f0b49badc44d97ebc64a\
+            ceec8f59201ea994e58fe39a83018a3b8e1a64ca17a4413fea533b11de61913e\
+            aac5fc9527692fa3d7794a4105aa24bfd59bce5492f910208dcfaaf5228c65ff\
+            f66e806995929a3e6a94014d3e7d9712dd145c8c67e886550552ce9f8c568617\
+            99719ea83896c81d32363b41c9f6ee7e5995c48de57f6bbb771f84d338f6c92a\
+            0662808711742cea100c7c8ca7bee82990fb5724f6bef8e4c5452693a126699a\
+            81d0cb85edd75c898cb525e54fc81a1a4967a3bd945364317b878f5194a93cd0\
+            d69e7bbdd4d988fc575f77f81a586de61d9f87aaa924def84fa797f809c97da8";
+        let signature = private_key
+            .sign_pkcs1v15::<Sha256>(b"sample", &counting)
+            .unwrap();
+        assert_eq!(h2b(expected), signature);
+        assert!(public_key.verify_pkcs1v15::<Sha256>(b"sample", &signature));
+        assert!(!public_key.verify_pkcs1v15::<Sha256>(b"other", &signature));
+        assert!(!public_key.verify_pkcs1v15::<Sha384>(b"sample", &signature));
+        assert!(!public_key.verify_pss::<Sha256>(b"sample", &signature));
+        assert!(!public_key.verify_pkcs1v15::<Sha256>(b"sample", &signature[1..]));
+        for &i in &[0, 128, 255] {
+            let signature = &mut signature.clone();
+            signature[i] ^= 1;
+            assert!(!public_key.verify_pkcs1v15::<Sha256>(b"sample", signature));
+        }
+        assert!(!public_key.verify_pkcs1v15::<Sha256>(b"sample", &h2b(N)));
+
+        // Blinding does not change the signature.
+        let byte = Cell::new(0);
+        let signature = private_key.sign_pkcs1v15::<Sha256>(b"sample", &|buffer: &mut [u8]| {
+            byte.set(byte.get() + 1);
+            buffer.iter_mut().for_each(|b| *b = byte.get());
+        });
+        assert_eq!(h2b(expected), signature.unwrap());
+
+        let expected = "19cad018e041b2bc3956266ec443d92350e9b5d9c5bb12a3dc78c8379f933923\
+            3290240eaeecbd9bdbf85c4481d54ee6e5c86d747de84b5837e526b0a774d21c\
+            f32634a7fd43295237d9d843058a49b962e3d8a2525c7e78e1cc618721ac05f8\
+            5830ac2ca7f16a26580b3c018e9af9c942e86e40371351be8ef88c2cb5663347\
+            7de3b054efb3dcfdc8f0f994a58cb90cbdcf26d6ef9c368fd385dc503ad6e1c9\
+            a3e87e8d7ad747540ae1d031dbec9ee6de11a78ee3ac3ac8ebf4eb38e0224b20\
+            0a80aa58e98f4356a81bf92a6304c28fed20322fa2fea604c522978f10a4540b\
+            74958a4f9548337fc0d94c7ed534cb68f4c0fd93d3098579ad2845d7bd05ea48";
+        let signature = private_key
+            .sign_pkcs1v15::<Sha512>(b"sample", &counting)
+            .unwrap();
+        assert_eq!(h2b(expected), signature);
+        assert!(public_key.verify_pkcs1v15::<Sha512>(b"sample", &signature));
+    }
+
+    #[test]
+    fn test_pss() {
+        let private_key = &private_key();
+        let public_key = private_key.public_key();
+        // Salts of 0, 1, 2, ...
+        let expected = "92d5748c0049b49ec40eeefbbb771fb4ace5cdb57e6c265a50a6c59f44d66ab5\
+            04c75c2355de6797390283c611f2ade26a2ede5b193c0dffe03b6b1ce98feec4\
+            5e80e5b66b48399a6602e8497fad4576e1148019a49ec9a6ae3375c686a76127\
+            ded28f80ddc5b37addce4b6cf4fc85ebf6350ebfbd7571d3fdab170953faa783\
+            2acdfa114c603c2e1d0ce6409a00fc4050d896749408babda7e1fff6a296bacf\
+            7023020a0a165a308d0ff3ccc971136582e8c7e5643c13b66fe986ac1ef6fd74\
+            130a5d9f124c801f0a9d131c271afb21f3161556f2df6c0301508ce065b6eec3\
+            4d0f5b25e954735a6aba532268d2548e5e0b3e3e25dced9090e6b7da7ca81e4b";
+        let signature = private_key
+            .sign_pss::<Sha256>(b"sample", &counting)
+            .unwrap();
+        assert_eq!(h2b(expected), signature);
+        assert!(public_key.verify_pss::<Sha256>(b"sample", &signature));
+        assert!(!public_key.verify_pss::<Sha256>(b"other", &signature));
+        assert!(!public_key.verify_pss::<Sha512>(b"sample", &signature));
+        assert!(!public_key.verify_pkcs1v15::<Sha256>(b"sample", &signature));
+        for &i in &[0, 128, 255] {
+            let signature = &mut signature.clone();
+            signature[i] ^= 1;
+            assert!(!public_key.verify_pss::<Sha256>(b"sample", signature));
+        }
+
+        let expected = "8c4e6e69a12b1bdd3088c1ccb56014a85de33485cbdb72f1caf72d189cff1bc7\
+            1bc5bb3480ddded45c41950fce42b60b01c2b744667ff0cfdcbc3004a955e6c0\
+            9ca08349e698e0e3278a6e836223bf5bf1f78d564e8fb9a4698a26497231930f\
+            1ed14bedf8a1709de147f3a2b14f2fe3b179608e35cb066ba33ccab1f19139e3\
+            f3d7154346ae27e49abc62051e2f66707bfd87540761a055a670346a1667ff6b\
+            127f134d2bde25756bf3be4f2dd76d6f5353d67b5f18c4708ad987550b52dcfd\
+            66317e04960ec743540ea801c6709dffa7dccfa3e907f614c67222cede2f5a1d\
+            a5583b39c205f8a72dbef018e1baa2b99f5cdd9d9a3def9b31e160b1bad25a81";
+        let signature = private_key
+            .sign_pss::<Sha384>(b"sample", &counting)
+            .unwrap();
+        assert_eq!(h2b(expected), signature);
+        assert!(public_key.verify_pss::<Sha384>(b"sample", &signature));
+
+        // A signature with a random salt from the Python cryptography package
+        let signature = "48a8aff70225d4959d51cda4a71a795ff130aef442198820b7bd18dd89c06c3c\
+            7b465b1591ad6c1a2f8dfbdb6f02649bf770bd22f119e5c2fda1b64bfc5cfa24\
+            3455122ca66a8f2a5ea71e96f2592936bc49e8a58f7d14b7401f2d4391c11120\
+            1b2e62df27fb6bf548fbf113c41e7fc438bf43a35697c9fec3505863bf856762\
+            ad7742130398feba065837cc0d29b42ec4d606db2b378acf231399ce5e5feaa0\
+            8f8795622fa05d445f762d1a5d5df8dac624a9e2347301f8833ef26e2528b1c1\
+            2aaa31e709aa3c660df820044a8a2b548f8050594dc4b62c0d15d55148b1ef54\
+            5e796b3822793179c3760e424047a278bb677bd71320632b5d2aa3b143fce489";
+        assert!(public_key.verify_pss::<Sha256>(b"sample", &h2b(signature)));
+    }
+
+    #[test]
+    fn test_oaep() {
+        let private_key = &private_key();
+        let public_key = private_key.public_key();
+        // Known-answer vectors are in the tests below, so these are round trips
+        let ciphertext = public_key
+            .encrypt_oaep::<Sha256>(b"sample", b"", &counting)
+            .unwrap();
+        let message = private_key.decrypt_oaep::<Sha256>(&ciphertext, b"", &counting);
+        assert_eq!(b"sample", &message.unwrap()[..]);
+        let ciphertext = public_key
+            .encrypt_oaep::<Sha384>(b"sample", b"label", &counting)
+            .unwrap();
+        let message = private_key.decrypt_oaep::<Sha384>(&ciphertext, b"label", &counting);
+        assert_eq!(b"sample", &message.unwrap()[..]);
+        let error = Err(DecryptionError);
+        assert_eq!(
+            error,
+            private_key.decrypt_oaep::<Sha384>(&ciphertext, b"", &counting)
+        );
+        assert_eq!(
+            error,
+            private_key.decrypt_oaep::<Sha256>(&ciphertext, b"label", &counting)
+        );
+
+        // A ciphertext with a random seed from the Python cryptography package
+        let ciphertext = &h2b(
+            "83163b24d89c3a14b3deef020310d0b029a487bd01c7dc0b55945eec3aa6c1ea\
+                86e9c3d84a75eee07c8b349cb202cda17c3c3cfba4e7d298759f8c679508f2ef\
+                0f685b8bf33c4d3b6c03bed9597321e3ae9aa1ac0cb096d13275d35ad5c19596\
+                33f1f13d76d7d6279a8287475b66d9d60d4beef24c0f5bca1aaf0d3d88b9a579\
+                bc695aff2203bb71f570fd2593735afa5dae8db20384e1eb8adf145ee8f72cbe\
+                0d94e73b1754f28dbff8a2186373bc002c9e8aed5d8571c2ea47bdf5e232c720\
+                2efa5f964703f803e01aa893e5b0a02619f6466d23fbbfbfc3e93857d6f2867c\
+                65bfc940363267f0c85391f52620cbaee5f3347e25474bbca3ba29ce6381b832",
+        );
+        let message = private_key.decrypt_oaep::<Sha256>(ciphertext, b"", &counting);
+        assert_eq!(b"sample", &message.unwrap()[..]);
+        for &i in &[0, 128, 255] {
+            let ciphertext = &mut ciphertext.clone();
+            ciphertext[i] ^= 1;
+            assert_eq!(
+                error,
+                private_key.decrypt_oaep::<Sha256>(ciphertext, b"", &counting)
+            );
+        }
+        let decrypt =
+            |ciphertext: &[u8]| private_key.decrypt_oaep::<Sha256>(ciphertext, b"", &counting);
+        assert_eq!(error, decrypt(&ciphertext[1..]));
+        assert_eq!(error, decrypt(&h2b(N)));
+
+        // Valid encodings except for a nonzero first byte or a missing 0x01 separator
+        let mut encoded = oaep_encode::<Sha256>(b"sample", b"", &[0; 32], 256).unwrap();
+        assert_eq!(
+            b"sample",
+            &decrypt(&public_key.encrypt_encoded(&encoded)).unwrap()[..]
+        );
+        encoded[0] = 1;
+        assert_eq!(error, decrypt(&public_key.encrypt_encoded(&encoded)));
+        encoded[0] = 0;
+        let (seed, db) = encoded[1..].split_at_mut(32);
+        mgf1_xor::<Sha256>(db, seed);
+        mgf1_xor::<Sha256>(seed, db);
+        assert_eq!([1, b's'], db[216..218]);
+        db[216] = 0;
+        mgf1_xor::<Sha256>(seed, db);
+        mgf1_xor::<Sha256>(db, seed);
+        assert_eq!(error, decrypt(&public_key.encrypt_encoded(&encoded)));
+
+        for len in &[0, 190] {
+            let message = &vec![0x42; *len];
+            let ciphertext = public_key
+                .encrypt_oaep::<Sha256>(message, b"", &counting)
+                .unwrap();
+            assert_eq!(message, &decrypt(&ciphertext).unwrap());
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "legacy-hashes")]
+    fn test_oaep_sha1_vector() {
+        use crate::sha1::InsecureSha1;
+        // Example 1.1 of oaep-vect.txt in the PKCS #1 v2.1 test vectors
+        let n = "a8b3b284af8eb50b387034a860f146c4919f318763cd6c5598c8ae4811a1e0ab\
+                  c4c7e0b082d693a5e7fced675cf4668512772c0cbc64a742c6c630f533c8cc72\
+                  f62ae833c40bf25842e984bb78bdbf97c0107d55bdb662f5c4e0fab9845cb514\
+                  8ef7392dd3aaff93ae1e6b667bb3d4247616d4f5ba10d4cfd226de88d39f16fb";
+        let d = "53339cfdb79fc8466a655c7316aca85c55fd8f6dd898fdaf119517ef4f52e8fd\
+                  8e258df93fee180fa0e4ab29693cd83b152a553d4ac4d1812b8b9fa5af0e7f55\
+                  fe7304df41570926f3311f15c4d65a732c483116ee3d3d2d0af3549ad9bf7cbf\
+                  b78ad884f84d5beb04724dc7369b31def37d0cf539e9cfcdd3de653729ead5d1";
+        let p = "d32737e7267ffe1341b2d5c0d150a81b586fb3132bed2f8d5262864a9cb9f30a\
+                  f38be448598d413a172efb802c21acf1c11c520c2f26a471dcad212eac7ca39d";
+        let q = "cc8853d1d54da630fac004f471f281c7b8982d8224a490edbeb33d3e3d5cc93c\
+                  4765703d1dd791642f1f116a0dd852be2419b2af72bfe9a030e860b0288b5d77";
+        let private_key =
+            &PrivateKey::from_components(&h2b(n), &[1, 0, 1], &h2b(d), &h2b(p), &h2b(q)).unwrap();
+        let message = &h2b("6628194e12073db03ba94cda9ef9532397d50dba79b987004afefe34");
+        let seed = &h2b("18b776ea21069d69776a33e96bad48e1dda0a5ef");
+        let expected = "354fe67b4a126d5d35fe36c777791a3f7ba13def484e2d3908aff722fad468fb\
+            21696de95d0be911c2d3174f8afcc201035f7b6d8e69402de5451618c21a535f\
+            a9d7bfc5b8dd9fc243f8cf927db31322d6e881eaa91a996170e657a05a266426\
+            d98c88003f8477c1227094a0d9fa1e8c4024309ce1ecccb5210035d47ac72e8a";
+        let rng = |buffer: &mut [u8]| buffer.copy_from_slice(seed);
+        let ciphertext = private_key
+            .public_key()
+            .encrypt_oaep::<InsecureSha1>(message, b"", &rng)
+            .unwrap();
+        assert_eq!(h2b(expected), ciphertext);
+        let decrypted = private_key.decrypt_oaep::<InsecureSha1>(&ciphertext, b"", &counting);
+        assert_eq!(message, &decrypted.unwrap());
+    }
+
+    #[test]
+    fn test_oaep_sha256_vector() {
+        // Test case 5 of rsa_oaep_2048_sha256_mgf1sha256_test.json in Wycheproof, since the
+        // PKCS #1 v2.1 test vectors only use SHA-1
+        let n = "a2b451a07d0aa5f96e455671513550514a8a5b462ebef717094fa1fee82224e6\
+                  37f9746d3f7cafd31878d80325b6ef5a1700f65903b469429e89d6eac8845097\
+                  b5ab393189db92512ed8a7711a1253facd20f79c15e8247f3d3e42e46e48c98e\
+                  254a2fe9765313a03eff8f17e1a029397a1fa26a8dce26f490ed81299615d981\
+                  4c22da610428e09c7d9658594266f5c021d0fceca08d945a12be82de4d1ece6b\
+                  4c03145b5d3495d4ed5411eb878daf05fd7afc3e09ada0f1126422f590975a19\
+                  69816f48698bcbba1b4d9cae79d460d8f9f85e7975005d9bc22c4e5ac0f7c1a4\
+                  5d12569a62807d3b9a02e5a530e773066f453d1f5b4c2e9cf7820283f742b9d5";
+        let d = "24cdc62317f5d72a6f6ba6cc9632899b01d1ff28867d72f61688995bc855a4e4\
+                  20a8405250089bdb13cf8e09543827b748b9d27fbb2b4d9e20af8c5a6a862796\
+                  d1a4cc18ad16ea678bc1bd4a83bbbe9c5e57453b5ce7388e41a3ba4ce2b77b44\
+                  38a229e954f720dae0353dc088ac8a76b26dc276f8e1b7851ddd6398ad16ff2e\
+                  78195123b9b036e945c38c9d12434f6df76fe22359eb3e1ac9c011678fc926fa\
+                  d3ae475a4fffff55feb2d147e9c894f4c0e29a599e762462482d968bf4278094\
+                  5fc0d2c31c573c4431b8f4fe8b8c67bec815abd44f7a86edca1c2308737358d2\
+                  c2ae5e2e0e2dadf730980262377e58b13b7d9992060a0bc870ccfdb4a9319ee1";
+        let p = "dc431050f782e894fb5248247d98cb7d58b8d1e24f3b55d041c56e4de086b0d5\
+                  bb028bda42eeb5d234d5681e5809d415e6a289ad4cfbf78f978f6c35814f50ee\
+                  bff1c5b80a69f788e81e6bab5ddaa78369d659d143ec6f17e79813a575cfad9c\
+                  569156b90113e2e9110ad9e7b48a1c9348a6e653321191290ea36cfb3a5b18f1";
+        let q = "bd1a81e7977f9898122273ae3222b598ea5fb19eb4eabc38308a5e32196603b2\
+                  e500ffb79f5b886816611debc472fac45544070beb057c941378a6868af3b7a0\
+                  3d3f9880ec47d5e089b94fbde542aba9ae8d72c57088d7abf5b131f39098f7bc\
+                  160f90536abc9492fd4e06f3ed7299d4b97bb03677207d95669f140cfbc20f25";
+        let private_key =
+            &PrivateKey::from_components(&h2b(n), &[1, 0, 1], &h2b(d), &h2b(p), &h2b(q)).unwrap();
+        let ciphertext = "121196e51a3f4476bfb6adddfdeb3a25dad72d1ea315d652f331a43631ad3672\
+            4b3d14532110dc44e407b1184618f115677b33751fb0e8786ba220cfa7fc3fce\
+            22822eabdd4fc2761c7f34a04e8f13c1021c31adc123a32d871f0da6cdacab9c\
+            020222da52afd5c307a6e55e4566944403fda426ee2c6c973ccaaafe2d081ed8\
+            c5b1dc00662424e395faed86c9ae19a3a95950c83d2a9ad5c7e7f670faeb123a\
+            cef07fe7795ad298aafe543504d7811336b3e2ecb1622bc90599a185b34700f8\
+            f4c52a651d73ea57e8cfa80e61d9da61f36951c7194ae4dee3c6e67b5757a396\
+            85dd3fe01cb87620a54666ff8132e93d7081d38ddc9f079431075e96cca78f59";
+        let message = private_key.decrypt_oaep::<Sha256>(&h2b(ciphertext), b"", &counting);
+        assert_eq!(b"Message", &message.unwrap()[..]);
+    }
+
+    #[test]
+    fn test_oaep_message_too_long() {
+        let message = &[0x42; 191];
+        let ciphertext = private_key()
+            .public_key()
+            .encrypt_oaep::<Sha256>(message, b"", &counting);
+        assert_eq!(Err(Error::InvalidLength), ciphertext);
+    }
+
+    #[test]
+    fn test_from_components() {
+        let private_key = private_key();
+        let public_key = private_key.public_key();
+        assert_eq!(h2b(N), public_key.modulus());
+        assert_eq!([1, 0, 1], &public_key.public_exponent()[..]);
+        assert_eq!(256, public_key.size());
+        assert!(PublicKey::new(&h2b(N), &[0, 0, 1, 0, 1]).is_some());
+        assert!(PublicKey::new(&h2b(N), &[1, 0, 0]).is_none());
+        assert!(PublicKey::new(&h2b(N), &[1]).is_none());
+        assert!(PublicKey::new(&h2b(P), &h2b(N)).is_none());
+        let mut even = h2b(N);
+        even[255] ^= 1;
+        assert!(PublicKey::new(&even, &[1, 0, 1]).is_none());
+
+        let (n, d, p, q) = (&h2b(N), &h2b(D), &h2b(P), &h2b(Q));
+        assert!(PrivateKey::from_components(n, &[1, 0, 1], d, q, p).is_some());
+        assert!(PrivateKey::from_components(n, &[3], d, p, q).is_none());
+        assert!(PrivateKey::from_components(n, &[1, 0, 1], d, p, p).is_none());
+        assert!(PrivateKey::from_components(n, &[1, 0, 1], d, n, &[1]).is_none());
+        let mut wrong_d = d.clone();
+        wrong_d[255] ^= 2;
+        assert!(PrivateKey::from_components(n, &[1, 0, 1], &wrong_d, p, q).is_none());
+    }
+
+    #[test]
+    fn test_generate() {
+        let stream = RefCell::new(Stream::new(&[0x42; 32], &[0; 12]).unwrap());
+        let rng = &|buffer: &mut [u8]| {
+            buffer.iter_mut().for_each(|byte| *byte = 0);
+            stream.borrow_mut().apply_keystream(buffer).unwrap();
+        };
+        let private_key = PrivateKey::generate(1024, rng);
+        let public_key = private_key.public_key();
+        assert_eq!(128, public_key.size());
+        assert!(public_key.modulus()[0] >= 0x80);
+        assert_eq!([1, 0, 1], &public_key.public_exponent()[..]);
+        let signature = private_key
+            .sign_pkcs1v15::<Sha256>(b"message", rng)
+            .unwrap();
+        assert!(public_key.verify_pkcs1v15::<Sha256>(b"message", &signature));
+        let signature = private_key.sign_pss::<Sha384>(b"message", rng).unwrap();
+        assert_eq!(128, signature.len());
+        assert!(public_key.verify_pss::<Sha384>(b"message", &signature));
+        // PSS with SHA-512 needs a 1040-bit modulus.
+        let signature = private_key.sign_pss::<Sha512>(b"message", rng);
+        assert_eq!(Err(Error::InvalidKey), signature);
+        assert_eq!(
+            Err(Error::InvalidKey),
+            Pss::<Sha512>::sign(b"message", b"", &private_key, rng)
+        );
+    }
+
+    #[test]
+    fn test_signer() {
+        let private_key = &private_key();
+        let public_key = private_key.public_key();
+        let signature = Pkcs1v15::<Sha256>::sign(b"message", b"", private_key, &counting).unwrap();
+        assert_eq!(
+            private_key
+                .sign_pkcs1v15::<Sha256>(b"message", &counting)
+                .unwrap(),
+            signature
+        );
+        assert_eq!(
+            Ok(()),
+            Pkcs1v15::<Sha256>::verify(b"message", b"", &signature, public_key)
+        );
+        assert_eq!(
+            Err(Error::InvalidSignature),
+            Pss::<Sha256>::verify(b"message", b"", &signature, public_key)
+        );
+
+        let signature = Pss::<Sha256>::sign(b"message", b"", private_key, &counting).unwrap();
+        assert_eq!(
+            Ok(()),
+            Pss::<Sha256>::verify(b"message", b"", &signature, public_key)
+        );
+        assert_eq!(
+            Err(Error::InvalidSignature),
+            Pss::<Sha256>::verify(b"other", b"", &signature, public_key)
+        );
+        assert_eq!(
+            Err(Error::InvalidLength),
+            Pss::<Sha256>::verify(b"message", b"context", &signature, public_key)
+        );
+        assert_eq!(
+            Err(Error::InvalidLength),
+            Pkcs1v15::<Sha256>::sign(b"message", b"context", private_key, &counting)
+        );
+    }
+}
@@ -0,0 +1,148 @@
+//! Module implementing the traits of the RustCrypto `digest` crate for the crate's hash
+//! functions, so they can be used with third-party code such as the `hmac` crate, for example to
+//! test the crate's own implementations against it.
+//!
+//! The implementations forward to `HashFunction`, and with them each hash function also
+//! implements `digest::Digest`. The `digest` crate is re-exported so that its version matches.
+//! Only `SimpleHmac` of the `hmac` crate is supported, since `Hmac` needs access to the
+//! block-level core of the hash function.
+//!
+//! # Examples
+//!
+//! ```
+//! use crypto_pure::rustcrypto::digest::Digest;
+//! use crypto_pure::sha2::{sha256, Sha256};
+//! assert_eq!(sha256(b"message"), Sha256::digest(b"message")[..]);
+//! ```
+use crate::blake2b::Blake2b512;
+#[cfg(feature = "legacy-hashes")]
+use crate::md5::InsecureMd5;
+#[cfg(feature = "legacy-hashes")]
+use crate::sha1::InsecureSha1;
+use crate::sha2::{HashFunction, Sha224, Sha256, Sha384, Sha512};
+use crate::sha3::{Sha3_224, Sha3_256, Sha3_384, Sha3_512};
+use digest::consts::{U104, U128, U136, U144, U28, U32, U48, U64, U72};
+#[cfg(feature = "legacy-hashes")]
+use digest::consts::{U16, U20};
+use digest::core_api::BlockSizeUser;
+use digest::{FixedOutput, FixedOutputReset, HashMarker, Output, OutputSizeUser, Reset, Update};
+
+pub use digest;
+
+macro_rules! impl_digest {
+    ($function:ty, $output_size:ty, $block_size:ty) => {
+        impl HashMarker for $function {}
+
+        impl OutputSizeUser for $function {
+            type OutputSize = $output_size;
+        }
+
+        impl BlockSizeUser for $function {
+            type BlockSize = $block_size;
+        }
+
+        impl Update for $function {
+            fn update(&mut self, data: &[u8]) {
+                HashFunction::update(self, data);
+            }
+        }
+
+        impl FixedOutput for $function {
+            fn finalize_into(self, out: &mut Output<Self>) {
+                self.write_digest(out);
+            }
+        }
+
+        impl Reset for $function {
+            fn reset(&mut self) {
+                HashFunction::reset(self);
+            }
+        }
+
+        impl FixedOutputReset for $function {
+            fn finalize_into_reset(&mut self, out: &mut Output<Self>) {
+                self.clone().write_digest(out);
+                HashFunction::reset(self);
+            }
+        }
+    };
+}
+
+impl_digest!(Sha512, U64, U128);
+impl_digest!(Sha384, U48, U128);
+impl_digest!(Sha256, U32, U64);
+impl_digest!(Sha224, U28, U64);
+impl_digest!(Sha3_512, U64, U72);
+impl_digest!(Sha3_384, U48, U104);
+impl_digest!(Sha3_256, U32, U136);
+impl_digest!(Sha3_224, U28, U144);
+impl_digest!(Blake2b512, U64, U128);
+#[cfg(feature = "legacy-hashes")]
+impl_digest!(InsecureSha1, U20, U64);
+#[cfg(feature = "legacy-hashes")]
+impl_digest!(InsecureMd5, U16, U64);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hmac::{hmac_sha256, hmac_sha512, Hmac};
+    use digest::Digest;
+    use hmac::{Mac, SimpleHmac};
+
+    fn check_digest<H: HashFunction + Digest + FixedOutputReset + BlockSizeUser>() {
+        assert_eq!(H::DIGEST_SIZE, <H as Digest>::output_size());
+        assert_eq!(H::BLOCK_SIZE, H::block_size());
+        let message = &[0x42; 1000];
+        for len in [0, 1, H::BLOCK_SIZE - 1, H::BLOCK_SIZE, message.len()] {
+            let mut expected = [0; 64];
+            let expected = &mut expected[..H::DIGEST_SIZE];
+            let mut hash_function = H::default();
+            HashFunction::update(&mut hash_function, &message[..len]);
+            hash_function.write_digest(expected);
+            assert_eq!(expected, &H::digest(&message[..len])[..]);
+
+            let mut digest = <H as Digest>::new();
+            Digest::update(&mut digest, &message[..len / 2]);
+            Digest::update(&mut digest, &message[len / 2..len]);
+            assert_eq!(expected, &digest.finalize_reset()[..]);
+            Digest::update(&mut digest, &message[..len]);
+            assert_eq!(expected, &digest.finalize()[..]);
+        }
+    }
+
+    #[test]
+    fn test_digest() {
+        check_digest::<Sha512>();
+        check_digest::<Sha384>();
+        check_digest::<Sha256>();
+        check_digest::<Sha224>();
+        check_digest::<Sha3_512>();
+        check_digest::<Sha3_384>();
+        check_digest::<Sha3_256>();
+        check_digest::<Sha3_224>();
+        check_digest::<Blake2b512>();
+        #[cfg(feature = "legacy-hashes")]
+        check_digest::<InsecureSha1>();
+        #[cfg(feature = "legacy-hashes")]
+        check_digest::<InsecureMd5>();
+    }
+
+    #[test]
+    fn test_hmac() {
+        let message = b"message";
+        for key in [&[0x42; 16][..], &[0x42; 200][..]] {
+            let mut mac = SimpleHmac::<Sha256>::new_from_slice(key).unwrap();
+            Mac::update(&mut mac, message);
+            assert_eq!(hmac_sha256(key, message), mac.finalize().into_bytes()[..]);
+            let mut mac = SimpleHmac::<Sha512>::new_from_slice(key).unwrap();
+            Mac::update(&mut mac, message);
+            assert_eq!(hmac_sha512(key, message), mac.finalize().into_bytes()[..]);
+
+            let mut mac = SimpleHmac::<Sha3_256>::new_from_slice(key).unwrap();
+            Mac::update(&mut mac, message);
+            let mut hmac = Hmac::<Sha3_256>::new(key);
+            hmac.update(message);
+            assert_eq!(hmac.tag()[..], mac.finalize().into_bytes()[..]);
+        }
+    }
+}
@@ -0,0 +1,358 @@
+//! Module for the Salsa20 and XSalsa20 stream ciphers.
+use crate::error::Error;
+use byteorder::{ByteOrder as _, LittleEndian};
+
+/// A Salsa20 or XSalsa20 iterator that can be used as a stream cipher or pseudorandom generator.
+///
+/// Salsa20 is initialized with a 16-byte or 32-byte key and an 8-byte nonce, and XSalsa20 with a
+/// 32-byte key and a 24-byte nonce, which is long enough to be chosen at random. If reusing a key
+/// for encryption, be sure to generate a unique nonce so that a given (key, nonce) pair is never
+/// used twice.
+///
+/// # Examples
+///
+/// ```
+/// use crypto_pure::salsa20::Stream;
+/// # let key = &[0x42; 32];
+/// # let nonce = &[0x24; 24];
+/// let mut buffer = b"message".to_vec();
+/// Stream::xsalsa20(key, nonce)?.apply_keystream(&mut buffer);
+/// Stream::xsalsa20(key, nonce)?.apply_keystream(&mut buffer);
+/// assert_eq!(b"message", &buffer[..]);
+/// # Ok::<(), crypto_pure::error::Error>(())
+/// ```
+pub struct Stream {
+    salsa20: Salsa20,
+    counter: u64,
+    block: [u8; 64],
+    block_index: u8,
+}
+
+impl Stream {
+    /// Initializes a Salsa20 stream given a key and nonce.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::InvalidKey` if `key.len()` is not equal to 16 or 32, or with
+    /// `Error::InvalidLength` if `nonce.len()` is not equal to 8.
+    pub fn new(key: &[u8], nonce: &[u8]) -> Result<Self, Error> {
+        if key.len() != 16 && key.len() != 32 {
+            return Err(Error::InvalidKey);
+        }
+        if nonce.len() != 8 {
+            return Err(Error::InvalidLength);
+        }
+        Ok(Self::with_cipher(Salsa20::new(key, nonce)))
+    }
+
+    /// Initializes an XSalsa20 stream given a key and nonce.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::InvalidKey` if `key.len()` is not equal to 32, or with
+    /// `Error::InvalidLength` if `nonce.len()` is not equal to 24.
+    pub fn xsalsa20(key: &[u8], nonce: &[u8]) -> Result<Self, Error> {
+        if key.len() != 32 {
+            return Err(Error::InvalidKey);
+        }
+        if nonce.len() != 24 {
+            return Err(Error::InvalidLength);
+        }
+        let subkey = hsalsa20(key, &nonce[..16])?;
+        Ok(Self::with_cipher(Salsa20::new(&subkey, &nonce[16..])))
+    }
+
+    /// Encrypts a message into a ciphertext.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::InvalidLength` if `input.len()` is not equal to `output.len()`.
+    pub fn encrypt(&mut self, input: &[u8], output: &mut [u8]) -> Result<(), Error> {
+        self.process(input, output)
+    }
+
+    /// Decrypts a ciphertext into a message.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::InvalidLength` if `input.len()` is not equal to `output.len()`.
+    pub fn decrypt(&mut self, input: &[u8], output: &mut [u8]) -> Result<(), Error> {
+        self.process(input, output)
+    }
+
+    /// Encrypts or decrypts a buffer in place by XORing it with the next bytes of the stream.
+    pub fn apply_keystream(&mut self, buffer: &mut [u8]) {
+        for (x, y) in buffer.iter_mut().zip(self.by_ref()) {
+            *x ^= y;
+        }
+    }
+
+    /// Moves to the start of the block with the given counter, as if `64 * counter` bytes of the
+    /// stream had been consumed since initialization.
+    pub fn seek(&mut self, counter: u64) {
+        self.counter = counter;
+        self.block = self.salsa20.block(counter);
+        self.block_index = 0;
+    }
+
+    fn with_cipher(salsa20: Salsa20) -> Self {
+        let block = salsa20.block(0);
+        Self {
+            salsa20,
+            counter: 0,
+            block,
+            block_index: 0,
+        }
+    }
+
+    fn process(&mut self, input: &[u8], output: &mut [u8]) -> Result<(), Error> {
+        if input.len() != output.len() {
+            return Err(Error::InvalidLength);
+        }
+        output.copy_from_slice(input);
+        self.apply_keystream(output);
+        Ok(())
+    }
+}
+
+impl Iterator for Stream {
+    type Item = u8;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.block_index == 64 {
+            self.counter = self.counter.wrapping_add(1);
+            self.block = self.salsa20.block(self.counter);
+            self.block_index = 0;
+        }
+        let byte = self.block[self.block_index as usize];
+        self.block_index += 1;
+        Some(byte)
+    }
+}
+
+/// Derives a 32-byte subkey from a 32-byte key and the first 16 bytes of a nonce, as used by
+/// XSalsa20 to extend the nonce to 24 bytes.
+///
+/// # Errors
+///
+/// Fails with `Error::InvalidKey` if `key.len()` is not equal to 32, or with
+/// `Error::InvalidLength` if `nonce.len()` is not equal to 16.
+pub fn hsalsa20(key: &[u8], nonce: &[u8]) -> Result<[u8; 32], Error> {
+    if key.len() != 32 {
+        return Err(Error::InvalidKey);
+    }
+    if nonce.len() != 16 {
+        return Err(Error::InvalidLength);
+    }
+    let mut state = [0; 16];
+    setup_state(&mut state, key);
+    LittleEndian::read_u32_into(nonce, &mut state[6..10]);
+    permute(&mut state, 10);
+    let mut subkey = [0; 32];
+    for (i, &j) in [0, 5, 10, 15, 6, 7, 8, 9].iter().enumerate() {
+        LittleEndian::write_u32(&mut subkey[4 * i..], state[j]);
+    }
+    Ok(subkey)
+}
+
+/// Computes the Salsa20 core function with the given number of double rounds, which is 10 for
+/// Salsa20 itself and 4 for the Salsa20/8 variant used by scrypt.
+pub(crate) fn core(input: &[u32; 16], double_rounds: usize) -> [u32; 16] {
+    let mut state = *input;
+    permute(&mut state, double_rounds);
+    for (x, &y) in state.iter_mut().zip(input) {
+        *x = x.wrapping_add(y);
+    }
+    state
+}
+
+const SIGMA: [u32; 4] = [0x6170_7865, 0x3320_646e, 0x7962_2d32, 0x6b20_6574];
+const TAU: [u32; 4] = [0x6170_7865, 0x3120_646e, 0x7962_2d36, 0x6b20_6574];
+
+struct Salsa20 {
+    state: [u32; 16],
+}
+
+impl Salsa20 {
+    fn new(key: &[u8], nonce: &[u8]) -> Self {
+        assert_eq!(8, nonce.len());
+        let mut state = [0; 16];
+        setup_state(&mut state, key);
+        LittleEndian::read_u32_into(nonce, &mut state[6..8]);
+        Self { state }
+    }
+
+    fn block(&self, counter: u64) -> [u8; 64] {
+        let mut input = self.state;
+        input[8] = counter as u32;
+        input[9] = (counter >> 32) as u32;
+        let mut output = [0; 64];
+        LittleEndian::write_u32_into(&core(&input, 10), &mut output);
+        output
+    }
+}
+
+/// Places the constants and key in the state, with a 16-byte key repeated to fill 32 bytes.
+fn setup_state(state: &mut [u32; 16], key: &[u8]) {
+    let constants = match key.len() {
+        16 => TAU,
+        32 => SIGMA,
+        _ => panic!("invalid key length"),
+    };
+    for (i, &constant) in [0, 5, 10, 15].iter().zip(&constants) {
+        state[*i] = constant;
+    }
+    let (first, second) = key.split_at(16);
+    let second = if second.is_empty() { first } else { second };
+    LittleEndian::read_u32_into(first, &mut state[1..5]);
+    LittleEndian::read_u32_into(second, &mut state[11..15]);
+}
+
+fn permute(state: &mut [u32; 16], double_rounds: usize) {
+    for _ in 0..double_rounds {
+        quarter_round(state, 0, 4, 8, 12);
+        quarter_round(state, 5, 9, 13, 1);
+        quarter_round(state, 10, 14, 2, 6);
+        quarter_round(state, 15, 3, 7, 11);
+        quarter_round(state, 0, 1, 2, 3);
+        quarter_round(state, 5, 6, 7, 4);
+        quarter_round(state, 10, 11, 8, 9);
+        quarter_round(state, 15, 12, 13, 14);
+    }
+}
+
+fn quarter_round(state: &mut [u32; 16], a: usize, b: usize, c: usize, d: usize) {
+    state[b] ^= state[a].wrapping_add(state[d]).rotate_left(7);
+    state[c] ^= state[b].wrapping_add(state[a]).rotate_left(9);
+    state[d] ^= state[c].wrapping_add(state[b]).rotate_left(13);
+    state[a] ^= state[d].wrapping_add(state[c]).rotate_left(18);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::*;
+    use std::vec::Vec;
+
+    fn expansion(key: &[u8], n: &[u8]) -> [u8; 64] {
+        Salsa20::new(key, &n[..8]).block(LittleEndian::read_u64(&n[8..]))
+    }
+
+    #[test]
+    fn test_expansion() {
+        // The examples from section 9 of the Salsa20 specification.
+        let n: Vec<u8> = (101..=116).collect();
+        let key: Vec<u8> = (1..=16).chain(201..=216).collect();
+        let expected = [
+            69, 37, 68, 39, 41, 15, 107, 193, 255, 139, 122, 6, 170, 233, 217, 98, 89, 144, 182,
+            106, 21, 51, 200, 65, 239, 49, 222, 34, 215, 114, 40, 126, 104, 197, 7, 225, 197, 153,
+            31, 2, 102, 78, 76, 176, 84, 245, 246, 184, 177, 160, 133, 130, 6, 72, 149, 119, 192,
+            195, 132, 236, 234, 103, 246, 74,
+        ];
+        assert_eq!(&expected[..], &expansion(&key, &n)[..]);
+
+        let expected = [
+            39, 173, 46, 248, 30, 200, 82, 17, 48, 67, 254, 239, 37, 18, 13, 247, 241, 200, 61,
+            144, 10, 55, 50, 185, 6, 47, 246, 253, 143, 86, 187, 225, 134, 85, 110, 246, 161, 163,
+            43, 235, 231, 94, 171, 51, 145, 214, 112, 29, 14, 232, 5, 16, 151, 140, 183, 141, 171,
+            9, 122, 181, 104, 182, 177, 193,
+        ];
+        assert_eq!(&expected[..], &expansion(&key[..16], &n)[..]);
+    }
+
+    #[test]
+    fn test_core() {
+        assert_eq!([0; 16], core(&[0; 16], 10));
+        let mut state = [0; 16];
+        setup_state(&mut state, &[7; 32]);
+        let mut permuted = state;
+        permute(&mut permuted, 4);
+        let output = core(&state, 4);
+        for ((&x, &y), &z) in output.iter().zip(&permuted).zip(&state) {
+            assert_eq!(x, y.wrapping_add(z));
+        }
+    }
+
+    #[test]
+    fn test_salsa20() {
+        // ECRYPT Set 1, vector 0.
+        let key = &h2b("80000000000000000000000000000000");
+        let mut stream = Stream::new(key, &[0; 8]).unwrap();
+        let output = &mut [0; 64];
+        stream.encrypt(&[0; 64], output).unwrap();
+        let expected = h2b(
+            "4dfa5e481da23ea09a31022050859936da52fcee218005164f267cb65f5cfd7f\
+             2b4f97e0ff16924a52df269515110a07f9e460bc65ef95da58f740b7d1dbb0aa",
+        );
+        assert_eq!(&expected[..], &output[..]);
+        let error = Err(Error::InvalidLength);
+        assert_eq!(error, stream.decrypt(&[0; 63], output));
+
+        let key: &Vec<_> = &(1..=32).collect();
+        let mut stream = Stream::new(key, &[3, 1, 4, 1, 5, 9, 2, 6]).unwrap();
+        stream.seek(7);
+        let expected = h2b(
+            "a305a2b950e195061a8894aa2cb1b7add442897916701026a4b1ed643f17272d\
+             faf1c7b1dc6e066223fa35e0046f49c4b3e6312128de0b8107b42cf63ddede6b",
+        );
+        assert_eq!(expected, stream.take(64).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_invalid_lengths() {
+        assert!(Stream::new(&[0; 16], &[0; 8]).is_ok());
+        assert_eq!(
+            Some(Error::InvalidKey),
+            Stream::new(&[0; 24], &[0; 8]).err()
+        );
+        assert_eq!(
+            Some(Error::InvalidLength),
+            Stream::new(&[0; 32], &[0; 24]).err()
+        );
+        assert_eq!(
+            Some(Error::InvalidKey),
+            Stream::xsalsa20(&[0; 16], &[0; 24]).err()
+        );
+        assert_eq!(
+            Some(Error::InvalidLength),
+            Stream::xsalsa20(&[0; 32], &[0; 8]).err()
+        );
+    }
+
+    #[test]
+    fn test_hsalsa20() {
+        let key: &Vec<_> = &(0..32).collect();
+        let nonce: &Vec<_> = &(0..16).collect();
+        let expected = h2b("f2a52d7cea2bb6babc32b07f89e22487a063c2481084ff41b8190fb7839d501c");
+        assert_eq!(&expected[..], &hsalsa20(key, nonce).unwrap()[..]);
+        assert_eq!(Err(Error::InvalidKey), hsalsa20(&key[1..], nonce));
+        assert_eq!(Err(Error::InvalidLength), hsalsa20(key, &nonce[1..]));
+    }
+
+    #[test]
+    fn test_xsalsa20() {
+        let key: &Vec<_> = &(0..32).collect();
+        let nonce: &Vec<_> = &(0..24).collect();
+        let expected = h2b(
+            "7cb660afdd9ec6468f57dd6d2433f93428fd82cd7386c5471a24d8ad2a525b6e\
+             5eff384fc7caa210bb3c8f3e688f4a9752a546df8c253fef17a2679455c7a1e1\
+             83dbf5d545b0f502b98de0997a66ab43",
+        );
+        for chunk_size in &[1, 17, 64, 65] {
+            let buffer = &mut vec![0; 80];
+            let mut stream = Stream::xsalsa20(key, nonce).unwrap();
+            for chunk in buffer.chunks_mut(*chunk_size) {
+                stream.apply_keystream(chunk);
+            }
+            assert_eq!(&expected, buffer);
+        }
+
+        let mut stream = Stream::xsalsa20(key, nonce).unwrap();
+        stream.seek(3);
+        let expected = h2b(
+            "57b625e7eab343d8eb2085b6f68209589935204422606061d28de98bea58afbf\
+             baad70039819a0c39aa86394475cd06194b017abc4bb28b7566d3c661c76f48a",
+        );
+        assert_eq!(expected, stream.take(64).collect::<Vec<_>>());
+    }
+}
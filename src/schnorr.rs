@@ -0,0 +1,9 @@
+//! Module for Schnorr signatures, in two standard variants: over ristretto255 as in RFC 9591, and
+//! over secp256k1 as in Bitcoin's BIP 340.
+//!
+//! A signature is a commitment R = k G to a nonce k and a response s = k + c x, where the
+//! challenge c hashes R, the public key and the message. Unlike ECDSA, verification needs no
+//! inversions and is linear in the signature, so many signatures can be verified at once with a
+//! random linear combination, which is much faster than verifying them one by one.
+pub mod bip340;
+pub mod ristretto255;
@@ -0,0 +1,523 @@
+//! Module for Schnorr signatures over secp256k1 as in Bitcoin's BIP 340.
+//!
+//! Secret keys are 32-byte big-endian integers between 1 and n - 1, and public keys are 32-byte
+//! x-coordinates, standing for the point with that x-coordinate and an even y-coordinate.
+//! Signatures are the x-coordinate of R followed by s. Messages are signed as they are rather
+//! than hashed first, and may have any length, as in the current revision of BIP 340.
+//!
+//! # Examples
+//!
+//! ```
+//! use crypto_pure::rand::SystemRandom;
+//! use crypto_pure::schnorr::bip340::{gen_pk, sign, verify};
+//! # let secret_key = &[0x42; 32];
+//! let public_key = &gen_pk(secret_key)?;
+//! let signature = &sign(b"message", secret_key, &SystemRandom::new())?;
+//! assert!(verify(b"message", signature, public_key));
+//! # Ok::<(), crypto_pure::error::Error>(())
+//! ```
+use crate::error::Error;
+use crate::rand::SecureRandom;
+use crate::secp256k1::PARAMS;
+use crate::sha2::{sha256, HashFunction, Sha256};
+use crate::sig::{self, Signer, Verifier};
+use crate::weierstrass::Curve;
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+/// Computes the x-only public key for a secret key.
+///
+/// # Errors
+///
+/// Fails with `Error::InvalidKey` if `secret_key.len()` is not equal to 32 or the secret key is
+/// out of range.
+pub fn gen_pk(secret_key: &[u8]) -> Result<[u8; 32], Error> {
+    let point = &mut [0; 65];
+    if !Curve::new(&PARAMS).gen_pk(secret_key, point) {
+        return Err(Error::InvalidKey);
+    }
+    let mut public_key = [0; 32];
+    public_key.copy_from_slice(&point[1..33]);
+    Ok(public_key)
+}
+
+/// Signs a message, with 32 bytes from `rng` as the auxiliary randomness that BIP 340 mixes into
+/// the nonce.
+///
+/// # Errors
+///
+/// Fails like `gen_pk`.
+pub fn sign(message: &[u8], secret_key: &[u8], rng: &dyn SecureRandom) -> Result<[u8; 64], Error> {
+    let aux_rand = &mut [0; 32];
+    rng.fill(aux_rand);
+    sign_with_aux_rand(message, secret_key, aux_rand)
+}
+
+/// Signs a message with the given auxiliary randomness, following the BIP 340 signing algorithm
+/// exactly. The nonce is derived from the secret key, the message and `aux_rand`, so all zeros
+/// gives deterministic signatures, while fresh randomness protects against side channels.
+///
+/// # Errors
+///
+/// Fails like `gen_pk`.
+pub fn sign_with_aux_rand(
+    message: &[u8],
+    secret_key: &[u8],
+    aux_rand: &[u8; 32],
+) -> Result<[u8; 64], Error> {
+    let curve = Curve::new(&PARAMS);
+    let public_point = &mut [0; 65];
+    if !curve.gen_pk(secret_key, public_point) {
+        return Err(Error::InvalidKey);
+    }
+    let public_key = &public_point[1..33];
+    // Use whichever of d and n - d gives the point with an even y-coordinate.
+    let d = &mut [0; 32];
+    d.copy_from_slice(secret_key);
+    if public_point[64] & 1 == 1 {
+        curve.negate_scalar(d);
+    }
+
+    let t = &mut tagged_hash(b"BIP0340/aux", &[aux_rand]);
+    for (t, d) in t.iter_mut().zip(d.iter()) {
+        *t ^= d;
+    }
+    let k = &mut [0; 32];
+    curve.reduce_scalar(&tagged_hash(b"BIP0340/nonce", &[t, public_key, message]), k);
+    let commitment = &mut [0; 65];
+    // k is zero with negligible probability, when BIP 340 also fails.
+    assert!(curve.gen_pk(k, commitment), "zero nonce");
+    if commitment[64] & 1 == 1 {
+        curve.negate_scalar(k);
+    }
+
+    let e = &challenge(&curve, &commitment[1..33], public_key, message);
+    let mut signature = [0; 64];
+    signature[..32].copy_from_slice(&commitment[1..33]);
+    curve.scalar_muladd(e, d, k, &mut signature[32..]);
+    Ok(signature)
+}
+
+/// Verifies a signature of a message under an x-only public key.
+pub fn verify(message: &[u8], signature: &[u8], public_key: &[u8]) -> bool {
+    if signature.len() != 64 {
+        return false;
+    }
+    let curve = Curve::new(&PARAMS);
+    let point = &mut [0; 65];
+    if !curve.lift_x(public_key, point) {
+        return false;
+    }
+    let minus_e = &mut challenge(&curve, &signature[..32], public_key, message);
+    curve.negate_scalar(minus_e);
+    // R = s G - e P must not be infinity, must have an even y-coordinate, and must have the
+    // x-coordinate r, which rules out r at least p.
+    let commitment = &mut [0; 65];
+    curve.mul_base_add(&signature[32..], minus_e, point, commitment)
+        && commitment[64] & 1 == 0
+        && commitment[1..33] == signature[..32]
+}
+
+/// Verifies a batch of signatures of messages under x-only public keys as in BIP 340, which is
+/// several times faster than verifying them one at a time. Outputs `true` only if every
+/// signature is valid, in which case each would pass `verify`.
+///
+/// The check is a random linear combination of the verification equations, with 128-bit
+/// coefficients derived from a hash of the whole batch, so an invalid batch passes with
+/// probability about 2^-128 and nobody can choose signatures that cancel each other out.
+///
+/// # Panics
+///
+/// Panics if the three slices differ in length.
+#[cfg(feature = "alloc")]
+pub fn verify_batch(messages: &[&[u8]], signatures: &[&[u8]], public_keys: &[&[u8]]) -> bool {
+    assert_eq!(messages.len(), signatures.len());
+    assert_eq!(messages.len(), public_keys.len());
+    let curve = Curve::new(&PARAMS);
+    let mut points = Vec::with_capacity(2 * messages.len());
+    let mut challenges = Vec::with_capacity(messages.len());
+    let mut transcript = Sha256::default();
+    for ((message, signature), public_key) in messages.iter().zip(signatures).zip(public_keys) {
+        if signature.len() != 64 {
+            return false;
+        }
+        let (commitment, point) = (&mut [0; 65], &mut [0; 65]);
+        if !curve.lift_x(&signature[..32], commitment) || !curve.lift_x(public_key, point) {
+            return false;
+        }
+        // Reduction leaves s unchanged exactly when it is below n.
+        let s = &mut [0; 32];
+        curve.reduce_scalar(&signature[32..], s);
+        if s[..] != signature[32..] {
+            return false;
+        }
+        let e = challenge(&curve, &signature[..32], public_key, message);
+        transcript.update(signature);
+        transcript.update(public_key);
+        transcript.update(&e);
+        points.push(*commitment);
+        points.push(*point);
+        challenges.push(e);
+    }
+    let seed = &mut [0; Sha256::DIGEST_SIZE];
+    transcript.write_digest(seed);
+
+    // Check that sum(a_i s_i) G - sum(a_i R_i) - sum(a_i e_i P_i) is infinity
+    let sum = &mut [0; 32];
+    let mut scalars = Vec::with_capacity(points.len());
+    for (i, (signature, e)) in signatures.iter().zip(&challenges).enumerate() {
+        let mut hash_function = Sha256::default();
+        hash_function.update(seed);
+        hash_function.update(&(i as u64).to_be_bytes());
+        let a = &mut [0; 32];
+        hash_function.write_digest(a);
+        a[..16].fill(0);
+
+        let previous = *sum;
+        curve.scalar_muladd(a, &signature[32..], &previous, sum);
+        let ae = &mut [0; 32];
+        curve.scalar_muladd(a, e, &[0; 32], ae);
+        curve.negate_scalar(a);
+        curve.negate_scalar(ae);
+        scalars.push(*a);
+        scalars.push(*ae);
+    }
+    let terms: Vec<(&[u8], &[u8])> = scalars
+        .iter()
+        .zip(&points)
+        .map(|(scalar, point)| (&scalar[..], &point[..]))
+        .collect();
+    curve.is_infinity_vartime(sum, &terms)
+}
+
+/// BIP 340 Schnorr signatures, signing with auxiliary randomness from the `SecureRandom`.
+pub struct Bip340;
+
+impl Verifier for Bip340 {
+    const MAX_CONTEXT_LEN: usize = 0;
+
+    type PublicKey = [u8];
+
+    fn verify(
+        message: &[u8],
+        context: &[u8],
+        signature: &[u8],
+        public_key: &[u8],
+    ) -> Result<(), Error> {
+        sig::check_lengths::<Self>(context, public_key, 32)?;
+        sig::check_valid(verify(message, signature, public_key))
+    }
+}
+
+impl Signer for Bip340 {
+    type SecretKey = [u8];
+    type Signature = [u8; 64];
+
+    fn sign(
+        message: &[u8],
+        context: &[u8],
+        secret_key: &[u8],
+        rng: &dyn SecureRandom,
+    ) -> Result<[u8; 64], Error> {
+        sig::check_context::<Self>(context)?;
+        sign(message, secret_key, rng)
+    }
+}
+
+/// Computes the challenge e, the tagged hash of r, the public key and the message modulo n.
+fn challenge(curve: &Curve<4>, r: &[u8], public_key: &[u8], message: &[u8]) -> [u8; 32] {
+    let mut e = [0; 32];
+    curve.reduce_scalar(
+        &tagged_hash(b"BIP0340/challenge", &[r, public_key, message]),
+        &mut e,
+    );
+    e
+}
+
+/// Computes the BIP 340 tagged hash SHA-256(SHA-256(tag) || SHA-256(tag) || data).
+fn tagged_hash(tag: &[u8], data: &[&[u8]]) -> [u8; 32] {
+    let tag_hash = sha256(tag);
+    let mut hash_function = Sha256::default();
+    hash_function.update(&tag_hash);
+    hash_function.update(&tag_hash);
+    data.iter().for_each(|data| hash_function.update(data));
+    let mut digest = [0; 32];
+    hash_function.write_digest(&mut digest);
+    digest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::*;
+    #[cfg(feature = "alloc")]
+    use alloc::vec;
+
+    fn check(secret_key: &str, aux_rand: &str, message: &str, pk: &str, sig: &str) {
+        let secret_key = &h2b(secret_key);
+        let message = &h2b(message);
+        let aux_rand = &h2b(aux_rand);
+        let public_key = &gen_pk(secret_key).unwrap();
+        assert_eq!(h2b(pk), &public_key[..]);
+        let signature = &sign(message, secret_key, &|buffer: &mut [u8]| {
+            buffer.copy_from_slice(aux_rand)
+        })
+        .unwrap();
+        assert_eq!(h2b(sig), &signature[..]);
+        assert!(verify(message, signature, public_key));
+        assert!(!verify(b"other", signature, public_key));
+    }
+
+    // Test vectors 0 to 3 and 15 to 18 from BIP 340
+    #[test]
+    fn test_sign() {
+        check(
+            "0000000000000000000000000000000000000000000000000000000000000003",
+            "0000000000000000000000000000000000000000000000000000000000000000",
+            "0000000000000000000000000000000000000000000000000000000000000000",
+            "f9308a019258c31049344f85f89d5229b531c845836f99b08601f113bce036f9",
+            "e907831f80848d1069a5371b402410364bdf1c5f8307b0084c55f1ce2dca8215\
+             25f66a4a85ea8b71e482a74f382d2ce5ebeee8fdb2172f477df4900d310536c0",
+        );
+        check(
+            "b7e151628aed2a6abf7158809cf4f3c762e7160f38b4da56a784d9045190cfef",
+            "0000000000000000000000000000000000000000000000000000000000000001",
+            "243f6a8885a308d313198a2e03707344a4093822299f31d0082efa98ec4e6c89",
+            "dff1d77f2a671c5f36183726db2341be58feae1da2deced843240f7b502ba659",
+            "6896bd60eeae296db48a229ff71dfe071bde413e6d43f917dc8dcf8c78de3341\
+             8906d11ac976abccb20b091292bff4ea897efcb639ea871cfa95f6de339e4b0a",
+        );
+        check(
+            "c90fdaa22168c234c4c6628b80dc1cd129024e088a67cc74020bbea63b14e5c9",
+            "c87aa53824b4d7ae2eb035a2b5bbbccc080e76cdc6d1692c4b0b62d798e6d906",
+            "7e2d58d8b3bcdf1abadec7829054f90dda9805aab56c77333024b9d0a508b75c",
+            "dd308afec5777e13121fa72b9cc1b7cc0139715309b086c960e18fd969774eb8",
+            "5831aaeed7b44bb74e5eab94ba9d4294c49bcf2a60728d8b4c200f50dd313c1b\
+             ab745879a5ad954a72c45a91c3a51d3c7adea98d82f8481e0e1e03674a6f3fb7",
+        );
+        // The public point has an odd y-coordinate, so the secret key is negated
+        check(
+            "0b432b2677937381aef05bb02a66ecd012773062cf3fa2549e44f58ed2401710",
+            "ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff",
+            "ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff",
+            "25d1dff95105f5253c4022f628a996ad3a0d95fbf21d468a1b33f8c160d8f517",
+            "7eb0509757e246f19449885651611cb965ecc1a187dd51b64fda1edc9637d5ec\
+             97582b9cb13db3933705b32ba982af5af25fd78881ebb32771fc5922efc66ea3",
+        );
+
+        let secret_key = "0340034003400340034003400340034003400340034003400340034003400340";
+        let aux_rand = "0000000000000000000000000000000000000000000000000000000000000000";
+        let pk = "778caa53b4393ac467774d09497a87224bf9fab6f6e68b23086497324d6fd117";
+        check(
+            secret_key,
+            aux_rand,
+            "",
+            pk,
+            "71535db165ecd9fbbc046e5ffaea61186bb6ad436732fccc25291a55895464cf\
+             6069ce26bf03466228f19a3a62db8a649f2d560fac652827d1af0574e427ab63",
+        );
+        check(
+            secret_key,
+            aux_rand,
+            "11",
+            pk,
+            "08a20a0afef64124649232e0693c583ab1b9934ae63b4c3511f3ae1134c6a303\
+             ea3173bfea6683bd101fa5aa5dbc1996fe7cacfc5a577d33ec14564cec2bacbf",
+        );
+        check(
+            secret_key,
+            aux_rand,
+            "0102030405060708090a0b0c0d0e0f1011",
+            pk,
+            "5130f39a4059b43bc7cac09a19ece52b5d8699d1a71e3c52da9afdb6b50ac370\
+             c4a482b77bf960f8681540e25b6771ece1e5a37fd80e5a51897c5566a97ea5a5",
+        );
+        check(
+            secret_key,
+            aux_rand,
+            &"99".repeat(100),
+            pk,
+            "403b12b0d8555a344175ea7ec746566303321e5dbfa8be6f091635163eca79a8\
+             585ed3e3170807e7c03b720fc54c7b23897fcba0e9d0b4a06894cfd249f22367",
+        );
+        assert_eq!(
+            sign_with_aux_rand(b"", &h2b(secret_key), &[0; 32]),
+            sign(b"", &h2b(secret_key), &|buffer: &mut [u8]| buffer.fill(0))
+        );
+    }
+
+    #[test]
+    fn test_invalid_secret_keys() {
+        let n = h2b("fffffffffffffffffffffffffffffffebaaedce6af48a03bbfd25e8cd0364141");
+        for secret_key in [&[0; 32][..], &n, &[1; 31], &[1; 33]] {
+            assert_eq!(Err(Error::InvalidKey), gen_pk(secret_key));
+            let signature = sign_with_aux_rand(b"", secret_key, &[0; 32]);
+            assert_eq!(Err(Error::InvalidKey), signature);
+            let signature = sign(b"", secret_key, &|buffer: &mut [u8]| buffer.fill(0));
+            assert_eq!(Err(Error::InvalidKey), signature);
+        }
+    }
+
+    fn vector_1() -> (std::vec::Vec<u8>, std::vec::Vec<u8>, std::vec::Vec<u8>) {
+        (
+            h2b("243f6a8885a308d313198a2e03707344a4093822299f31d0082efa98ec4e6c89"),
+            h2b(
+                "6896bd60eeae296db48a229ff71dfe071bde413e6d43f917dc8dcf8c78de3341\
+                 8906d11ac976abccb20b091292bff4ea897efcb639ea871cfa95f6de339e4b0a",
+            ),
+            h2b("dff1d77f2a671c5f36183726db2341be58feae1da2deced843240f7b502ba659"),
+        )
+    }
+
+    #[test]
+    fn test_verify() {
+        // Test vector 4 from BIP 340, where R has leading zeros
+        assert!(verify(
+            &h2b("4df3c3f68fcc83b27e9d42c90431a72499f17875c81a599b566c9889b9696703"),
+            &h2b(
+                "00000000000000000000003b78ce563f89a0ed9414f5aa28ad0d96d6795f9c63\
+                 76afb1548af603b3eb45c9f8207dee1060cb71c04e80f593060b07d28308d7f4"
+            ),
+            &h2b("d69c3509bb99e412e68b0fe8544e72837dfa30746d8be2aa65975f29d22dc7b9"),
+        ));
+
+        let (message, signature, public_key) = vector_1();
+        assert!(verify(&message, &signature, &public_key));
+        assert!(!verify(&message, &signature[..63], &public_key));
+        assert!(!verify(&message, &signature, &public_key[..31]));
+
+        // A public key that is not an x-coordinate on the curve, from BIP 340 test vector 5
+        let bad_public_key =
+            h2b("eefdea4cdb677750a420fee807eacf21eb9898ae79b9768766e4faa04a2d4a34");
+        assert!(!verify(&message, &signature, &bad_public_key));
+        // A public key equal to p
+        let p = h2b("fffffffffffffffffffffffffffffffffffffffffffffffffffffffefffffc2f");
+        assert!(!verify(&message, &signature, &p));
+
+        // r equal to p, and r not an x-coordinate on the curve
+        let mut bad_signature = signature.clone();
+        bad_signature[..32].copy_from_slice(&p);
+        assert!(!verify(&message, &bad_signature, &public_key));
+        bad_signature[..32].copy_from_slice(&[0; 32]);
+        bad_signature[31] = 5;
+        assert!(!verify(&message, &bad_signature, &public_key));
+
+        // s equal to n
+        let n = h2b("fffffffffffffffffffffffffffffffebaaedce6af48a03bbfd25e8cd0364141");
+        let mut bad_signature = signature.clone();
+        bad_signature[32..].copy_from_slice(&n);
+        assert!(!verify(&message, &bad_signature, &public_key));
+
+        // n - s gives -R, which has an odd y-coordinate
+        let curve = Curve::new(&PARAMS);
+        let mut bad_signature = signature.clone();
+        curve.negate_scalar(&mut bad_signature[32..]);
+        assert!(!verify(&message, &bad_signature, &public_key));
+    }
+
+    #[cfg(feature = "alloc")]
+    type Batch = (Vec<Vec<u8>>, Vec<[u8; 64]>, Vec<[u8; 32]>);
+
+    #[cfg(feature = "alloc")]
+    fn batch(n: u8) -> Batch {
+        let mut messages = Vec::new();
+        let mut signatures = Vec::new();
+        let mut public_keys = Vec::new();
+        for i in 0..n {
+            let sk = &[i + 1; 32];
+            let message = vec![i; usize::from(i)];
+            signatures.push(sign_with_aux_rand(&message, sk, &[i; 32]).unwrap());
+            messages.push(message);
+            public_keys.push(gen_pk(sk).unwrap());
+        }
+        (messages, signatures, public_keys)
+    }
+
+    #[cfg(feature = "alloc")]
+    fn check_batch(
+        messages: &[Vec<u8>],
+        signatures: &[[u8; 64]],
+        public_keys: &[[u8; 32]],
+    ) -> bool {
+        let messages: Vec<&[u8]> = messages.iter().map(|m| &m[..]).collect();
+        let signatures: Vec<&[u8]> = signatures.iter().map(|s| &s[..]).collect();
+        let public_keys: Vec<&[u8]> = public_keys.iter().map(|pk| &pk[..]).collect();
+        verify_batch(&messages, &signatures, &public_keys)
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn test_verify_batch() {
+        let (messages, signatures, public_keys) = batch(20);
+        assert!(check_batch(&messages, &signatures, &public_keys));
+        assert!(check_batch(
+            &messages[..1],
+            &signatures[..1],
+            &public_keys[..1]
+        ));
+        assert!(verify_batch(&[], &[], &[]));
+
+        let (message, signature, public_key) = vector_1();
+        assert!(verify_batch(
+            &[&message, &messages[0]],
+            &[&signature, &signatures[0]],
+            &[&public_key, &public_keys[0]]
+        ));
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn test_verify_batch_invalid() {
+        let (messages, signatures, public_keys) = batch(10);
+        for i in [0, 7, 9] {
+            let mut bad_messages = messages.clone();
+            bad_messages[i].push(0);
+            assert!(!check_batch(&bad_messages, &signatures, &public_keys));
+
+            for j in [0, 31, 32, 63] {
+                let mut bad_signatures = signatures.clone();
+                bad_signatures[i][j] ^= 1;
+                assert!(!check_batch(&messages, &bad_signatures, &public_keys));
+            }
+
+            let mut bad_public_keys = public_keys.clone();
+            bad_public_keys[i] = public_keys[(i + 1) % public_keys.len()];
+            assert!(!check_batch(&messages, &signatures, &bad_public_keys));
+        }
+
+        // Swapping signatures between messages is caught
+        let mut bad_signatures = signatures.clone();
+        bad_signatures.swap(3, 4);
+        assert!(!check_batch(&messages, &bad_signatures, &public_keys));
+
+        // s must be below n
+        let (message, signature, public_key) = vector_1();
+        let n = h2b("fffffffffffffffffffffffffffffffebaaedce6af48a03bbfd25e8cd0364141");
+        let mut bad_signature = [0; 64];
+        bad_signature[..32].copy_from_slice(&signature[..32]);
+        bad_signature[32..].copy_from_slice(&n);
+        assert!(!verify_batch(
+            &[&message],
+            &[&bad_signature],
+            &[&public_key]
+        ));
+
+        // n - s goes with -R, which has an odd y-coordinate
+        let curve = Curve::new(&PARAMS);
+        bad_signature.copy_from_slice(&signature);
+        curve.negate_scalar(&mut bad_signature[32..]);
+        assert!(!verify_batch(
+            &[&message],
+            &[&bad_signature],
+            &[&public_key]
+        ));
+    }
+
+    #[test]
+    #[should_panic]
+    #[cfg(feature = "alloc")]
+    fn test_verify_batch_length_mismatch() {
+        let (messages, signatures, public_keys) = batch(2);
+        check_batch(&messages[..1], &signatures, &public_keys);
+    }
+}
@@ -0,0 +1,399 @@
+//! Module for Schnorr signatures over ristretto255 as in RFC 9591, Appendix C, with the hash
+//! functions of the FROST(ristretto255, SHA-512) ciphersuite, so signatures made by a FROST
+//! threshold group verify here too.
+//!
+//! Secret keys are nonzero canonical little-endian scalars, public keys are encoded points, and
+//! signatures are the encoded commitment R followed by the scalar response z. Nonces hash fresh
+//! randomness together with the secret key as in RFC 9591, Section 4.1, so a weak `rng` doesn't
+//! leak the secret key.
+//!
+//! # Examples
+//!
+//! ```
+//! use crypto_pure::rand::SystemRandom;
+//! use crypto_pure::schnorr::ristretto255::{gen_pk, gen_sk, sign, verify};
+//! let rng = &SystemRandom::new();
+//! let secret_key = &gen_sk(rng);
+//! let public_key = &gen_pk(secret_key).unwrap();
+//! let signature = &sign(b"message", secret_key, rng)?;
+//! assert!(verify(b"message", signature, public_key));
+//! # Ok::<(), crypto_pure::error::Error>(())
+//! ```
+use crate::error::Error;
+use crate::rand::SecureRandom;
+#[cfg(feature = "alloc")]
+use crate::ristretto255::is_identity_multiscalar_vartime;
+use crate::ristretto255::{Point, Scalar, POINT_LEN, SCALAR_LEN};
+use crate::sha2::{HashFunction, Sha512};
+use crate::sig::{self, Signer, Verifier};
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+/// The length of a signature R || z.
+pub const SIGNATURE_LEN: usize = POINT_LEN + SCALAR_LEN;
+
+/// Draws a random secret key from `rng`.
+pub fn gen_sk(rng: &dyn SecureRandom) -> [u8; SCALAR_LEN] {
+    loop {
+        let scalar = Scalar::random(rng);
+        if !bool::from(scalar.is_zero()) {
+            return scalar.to_bytes();
+        }
+    }
+}
+
+/// Computes the public key for a secret key, or outputs `None` if the secret key is not a nonzero
+/// canonical scalar.
+pub fn gen_pk(secret_key: &[u8]) -> Option<[u8; POINT_LEN]> {
+    secret_scalar(secret_key).map(|x| Point::mul_base(&x).to_bytes())
+}
+
+/// Signs a message, outputting R || z. The nonce hashes 32 bytes from `rng` with the secret key.
+///
+/// # Errors
+///
+/// Fails with `Error::InvalidKey` if the secret key is not a nonzero canonical scalar.
+pub fn sign(
+    message: &[u8],
+    secret_key: &[u8],
+    rng: &dyn SecureRandom,
+) -> Result<[u8; SIGNATURE_LEN], Error> {
+    let x = secret_scalar(secret_key).ok_or(Error::InvalidKey)?;
+    let public_key = Point::mul_base(&x).to_bytes();
+    let random_bytes = &mut [0; 32];
+    rng.fill(random_bytes);
+    let k = hash_to_scalar(b"nonce", &[random_bytes, secret_key]);
+    let commitment = Point::mul_base(&k).to_bytes();
+    let c = challenge(&commitment, &public_key, message);
+    let mut signature = [0; SIGNATURE_LEN];
+    signature[..POINT_LEN].copy_from_slice(&commitment);
+    signature[POINT_LEN..].copy_from_slice(&(k + c * x).to_bytes());
+    Ok(signature)
+}
+
+/// Verifies a signature R || z of a message under a public key, checking that z G = R + c A.
+pub fn verify(message: &[u8], signature: &[u8], public_key: &[u8]) -> bool {
+    if signature.len() != SIGNATURE_LEN {
+        return false;
+    }
+    let (commitment, z, a) = match (
+        Point::from_bytes(&signature[..POINT_LEN]),
+        Scalar::from_bytes(&signature[POINT_LEN..]),
+        Point::from_bytes(public_key),
+    ) {
+        (Some(commitment), Some(z), Some(a)) => (commitment, z, a),
+        _ => return false,
+    };
+    let c = challenge(&signature[..POINT_LEN], public_key, message);
+    Point::mul_base(&z) - a * c == commitment
+}
+
+/// Verifies a batch of signatures of messages under public keys, which is several times faster
+/// than verifying them one at a time. Outputs `true` only if every signature is valid, in which
+/// case each would pass `verify`.
+///
+/// The check is a random linear combination of the verification equations, with 128-bit
+/// coefficients derived from a hash of the whole batch, so an invalid batch passes with
+/// probability about 2^-128 and nobody can choose signatures that cancel each other out.
+///
+/// # Panics
+///
+/// Panics if the three slices differ in length.
+#[cfg(feature = "alloc")]
+pub fn verify_batch(messages: &[&[u8]], signatures: &[&[u8]], public_keys: &[&[u8]]) -> bool {
+    assert_eq!(messages.len(), signatures.len());
+    assert_eq!(messages.len(), public_keys.len());
+    let mut points = Vec::with_capacity(2 * messages.len());
+    let mut responses = Vec::with_capacity(messages.len());
+    let mut challenges = Vec::with_capacity(messages.len());
+    let mut transcript = Sha512::default();
+    for ((message, signature), public_key) in messages.iter().zip(signatures).zip(public_keys) {
+        if signature.len() != SIGNATURE_LEN {
+            return false;
+        }
+        let (commitment, z, a) = match (
+            Point::from_bytes(&signature[..POINT_LEN]),
+            Scalar::from_bytes(&signature[POINT_LEN..]),
+            Point::from_bytes(public_key),
+        ) {
+            (Some(commitment), Some(z), Some(a)) => (commitment, z, a),
+            _ => return false,
+        };
+        let c = challenge(&signature[..POINT_LEN], public_key, message);
+        transcript.update(signature);
+        transcript.update(public_key);
+        transcript.update(&c.to_bytes());
+        points.push(-commitment);
+        points.push(-a);
+        responses.push(z);
+        challenges.push(c);
+    }
+    let seed = &mut [0; Sha512::DIGEST_SIZE];
+    transcript.write_digest(seed);
+
+    // Check that sum(w_i z_i) G - sum(w_i R_i) - sum(w_i c_i A_i) is the identity
+    let mut b = Scalar::ZERO;
+    let mut scalars = Vec::with_capacity(points.len());
+    for (i, (z, c)) in responses.iter().zip(&challenges).enumerate() {
+        let digest = &mut [0; Sha512::DIGEST_SIZE];
+        let mut hash_function = Sha512::default();
+        hash_function.update(seed);
+        hash_function.update(&(i as u64).to_le_bytes());
+        hash_function.write_digest(digest);
+        digest[16..SCALAR_LEN].fill(0);
+        let w = Scalar::from_bytes(&digest[..SCALAR_LEN]).expect("128-bit scalar");
+        b = b + w * *z;
+        scalars.push(w);
+        scalars.push(w * *c);
+    }
+    is_identity_multiscalar_vartime(&b, &scalars, &points)
+}
+
+/// Schnorr signatures over ristretto255.
+pub struct SchnorrRistretto255;
+
+impl Verifier for SchnorrRistretto255 {
+    const MAX_CONTEXT_LEN: usize = 0;
+
+    type PublicKey = [u8];
+
+    fn verify(
+        message: &[u8],
+        context: &[u8],
+        signature: &[u8],
+        public_key: &[u8],
+    ) -> Result<(), Error> {
+        sig::check_lengths::<Self>(context, public_key, POINT_LEN)?;
+        sig::check_valid(verify(message, signature, public_key))
+    }
+}
+
+impl Signer for SchnorrRistretto255 {
+    type SecretKey = [u8];
+    type Signature = [u8; SIGNATURE_LEN];
+
+    fn sign(
+        message: &[u8],
+        context: &[u8],
+        secret_key: &[u8],
+        rng: &dyn SecureRandom,
+    ) -> Result<[u8; SIGNATURE_LEN], Error> {
+        sig::check_context::<Self>(context)?;
+        sign(message, secret_key, rng)
+    }
+}
+
+fn secret_scalar(secret_key: &[u8]) -> Option<Scalar> {
+    Scalar::from_bytes(secret_key).filter(|x| !bool::from(x.is_zero()))
+}
+
+/// Computes the challenge H2(R || A || message) of RFC 9591.
+fn challenge(commitment: &[u8], public_key: &[u8], message: &[u8]) -> Scalar {
+    hash_to_scalar(b"chal", &[commitment, public_key, message])
+}
+
+/// Hashes the context string, a label and some data with SHA-512 to a scalar, as in the H2 and H3
+/// functions of FROST(ristretto255, SHA-512).
+fn hash_to_scalar(label: &[u8], data: &[&[u8]]) -> Scalar {
+    let mut hash_function = Sha512::default();
+    hash_function.update(b"FROST-RISTRETTO255-SHA512-v1");
+    hash_function.update(label);
+    data.iter().for_each(|data| hash_function.update(data));
+    let digest = &mut [0; Sha512::DIGEST_SIZE];
+    hash_function.write_digest(digest);
+    Scalar::from_uniform_bytes(digest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::*;
+    #[cfg(feature = "alloc")]
+    use alloc::vec;
+
+    // Checked against a Python implementation of RFC 9591, Appendix C
+    fn check(secret_key: &str, random_bytes: &str, message: &[u8], pk: &str, sig: &str) {
+        let secret_key = &h2b(secret_key);
+        let random_bytes = &h2b(random_bytes);
+        let public_key = &gen_pk(secret_key).unwrap();
+        assert_eq!(h2b(pk), &public_key[..]);
+        let signature = &sign(message, secret_key, &|buffer: &mut [u8]| {
+            buffer.copy_from_slice(random_bytes)
+        })
+        .unwrap();
+        assert_eq!(h2b(sig), &signature[..]);
+        assert!(verify(message, signature, public_key));
+        assert!(!verify(b"other", signature, public_key));
+        let other_key = &gen_pk(&[7; 32]).unwrap();
+        assert!(!verify(message, signature, other_key));
+    }
+
+    #[test]
+    fn test_sign() {
+        check(
+            "0100000000000000000000000000000000000000000000000000000000000000",
+            "4242424242424242424242424242424242424242424242424242424242424242",
+            b"",
+            "e2f2ae0a6abc4e71a884a961c500515f58e30b6aa582dd8db6a65945e08d2d76",
+            "5473f40ef9f295d8a1dddb323e2bc98df5a47cdefbff11ff4a9c2baea90a0230\
+             b5a4f79caeb818a704265814d5453b0b205bf7bf9be781a0e6578b23e5064e09",
+        );
+        check(
+            "b168de3a00000000000000000000000000000000000000c07bf32a249e158d04",
+            "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f",
+            b"message",
+            "a41fbc6b774d19ad853e7985ef975a1a4df2f183f727283477fe81c27cba3049",
+            "b4ccbddd65b1d7d3c394a4446cbcce4eab9cf2f89788eb63c01a9febb0ebc551\
+             50b652d5399dc930e9d86000384d938729f55ccfc1397fb70508b15b1f2aee02",
+        );
+        // L - 1
+        check(
+            "ecd3f55c1a631258d69cf7a2def9de1400000000000000000000000000000010",
+            "ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff",
+            &[b'x'; 100],
+            "eaffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff7f",
+            "7a3ccf2929f39958bea3073ffb8c5d9c45bcb183b31ad35b676e37dcc5e08804\
+             e739c3d89112da1cf0a13b0594c29bcd4d51571faea9bc55372aaa2b64752402",
+        );
+    }
+
+    #[test]
+    fn test_gen_sk() {
+        let counter = core::cell::Cell::new(0);
+        // The first draw gives zero, so it is redrawn
+        let rng = |buffer: &mut [u8]| {
+            buffer.fill(counter.get());
+            counter.set(1);
+        };
+        let secret_key = &gen_sk(&rng);
+        assert_ne!(&[0; 32], secret_key);
+        assert!(gen_pk(secret_key).is_some());
+    }
+
+    #[test]
+    fn test_invalid_secret_key() {
+        assert_eq!(None, gen_pk(&[0; 32]));
+        // L is not canonical
+        let l = h2b("edd3f55c1a631258d69cf7a2def9de1400000000000000000000000000000010");
+        assert_eq!(None, gen_pk(&l));
+        assert_eq!(None, gen_pk(&[1; 31]));
+
+        let rng = &|buffer: &mut [u8]| buffer.fill(0x42);
+        for secret_key in [&[0; 32][..], &l, &[1; 31]] {
+            let signature = sign(b"message", secret_key, rng);
+            assert_eq!(Err(Error::InvalidKey), signature);
+        }
+    }
+
+    #[test]
+    fn test_verify_invalid() {
+        let secret_key = &[0x0f; 32];
+        let public_key = &gen_pk(secret_key).unwrap();
+        let signature = &sign(b"message", secret_key, &|buffer: &mut [u8]| {
+            buffer.fill(0x42)
+        })
+        .unwrap();
+        assert!(verify(b"message", signature, public_key));
+        assert!(!verify(b"message", &signature[..63], public_key));
+        assert!(!verify(b"message", signature, &public_key[..31]));
+
+        // z + L is rejected even though it satisfies the equation
+        let l = h2b("edd3f55c1a631258d69cf7a2def9de1400000000000000000000000000000010");
+        let mut malleated = *signature;
+        let mut carry = 0;
+        for (z, l) in malleated[32..].iter_mut().zip(&l) {
+            let sum = u16::from(*z) + u16::from(*l) + carry;
+            *z = sum as u8;
+            carry = sum >> 8;
+        }
+        assert!(!verify(b"message", &malleated, public_key));
+
+        // A negative field element is not a valid encoding of R or the public key
+        let mut bad_r = *signature;
+        bad_r[..32].copy_from_slice(&[1; 32]);
+        assert!(!verify(b"message", &bad_r, public_key));
+        let identity = &[0; 32];
+        assert!(!verify(b"message", signature, identity));
+        let mut bad_public_key = *public_key;
+        bad_public_key[0] ^= 1;
+        assert!(!verify(b"message", signature, &bad_public_key));
+    }
+
+    #[cfg(feature = "alloc")]
+    type Batch = (Vec<Vec<u8>>, Vec<[u8; 64]>, Vec<[u8; 32]>);
+
+    #[cfg(feature = "alloc")]
+    fn batch(n: u8) -> Batch {
+        let mut messages = Vec::new();
+        let mut signatures = Vec::new();
+        let mut public_keys = Vec::new();
+        for i in 0..n {
+            let sk = &mut [i + 1; 32];
+            sk[31] = 0;
+            let message = vec![i; usize::from(i)];
+            signatures.push(sign(&message, sk, &|buffer: &mut [u8]| buffer.fill(i)).unwrap());
+            messages.push(message);
+            public_keys.push(gen_pk(sk).unwrap());
+        }
+        (messages, signatures, public_keys)
+    }
+
+    #[cfg(feature = "alloc")]
+    fn check_batch(
+        messages: &[Vec<u8>],
+        signatures: &[[u8; 64]],
+        public_keys: &[[u8; 32]],
+    ) -> bool {
+        let messages: Vec<&[u8]> = messages.iter().map(|m| &m[..]).collect();
+        let signatures: Vec<&[u8]> = signatures.iter().map(|s| &s[..]).collect();
+        let public_keys: Vec<&[u8]> = public_keys.iter().map(|pk| &pk[..]).collect();
+        verify_batch(&messages, &signatures, &public_keys)
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn test_verify_batch() {
+        let (messages, signatures, public_keys) = batch(40);
+        assert!(check_batch(&messages, &signatures, &public_keys));
+        assert!(check_batch(
+            &messages[..1],
+            &signatures[..1],
+            &public_keys[..1]
+        ));
+        assert!(verify_batch(&[], &[], &[]));
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn test_verify_batch_invalid() {
+        let (messages, signatures, public_keys) = batch(20);
+        for i in [0, 7, 19] {
+            let mut bad_messages = messages.clone();
+            bad_messages[i].push(0);
+            assert!(!check_batch(&bad_messages, &signatures, &public_keys));
+
+            for j in [0, 31, 32, 62] {
+                let mut bad_signatures = signatures.clone();
+                bad_signatures[i][j] ^= 2;
+                assert!(!check_batch(&messages, &bad_signatures, &public_keys));
+            }
+
+            let mut bad_public_keys = public_keys.clone();
+            bad_public_keys[i] = public_keys[(i + 1) % public_keys.len()];
+            assert!(!check_batch(&messages, &signatures, &bad_public_keys));
+        }
+
+        // Swapping signatures between messages is caught
+        let mut bad_signatures = signatures.clone();
+        bad_signatures.swap(3, 4);
+        assert!(!check_batch(&messages, &bad_signatures, &public_keys));
+    }
+
+    #[test]
+    #[should_panic]
+    #[cfg(feature = "alloc")]
+    fn test_verify_batch_length_mismatch() {
+        let (messages, signatures, public_keys) = batch(2);
+        check_batch(&messages[..1], &signatures, &public_keys);
+    }
+}
@@ -0,0 +1,233 @@
+//! Module for the scrypt memory-hard password-based key derivation function (RFC 7914).
+//!
+//! The cost is set by `Params`: the memory used grows with `2^log_n * r`, and the time with
+//! `2^log_n * r * p`.
+//!
+//! # Examples
+//!
+//! ```
+//! use crypto_pure::scrypt::{self, Params};
+//! # let password = b"correct horse battery staple";
+//! # let salt = b"A random value stored alongside.";
+//! let params = Params {
+//!     log_n: 10,
+//!     r: 8,
+//!     p: 1,
+//! };
+//! let memory = &mut vec![0; params.memory_len()];
+//! let key = &mut [0; 32];
+//! scrypt::derive_with_memory(password, salt, params, memory, key);
+//! ```
+use crate::pbkdf2;
+use crate::salsa20;
+use crate::sha2::Sha256;
+#[cfg(feature = "alloc")]
+use alloc::vec;
+use byteorder::{ByteOrder as _, LittleEndian};
+
+const BLOCK_LEN: usize = 64;
+
+/// The cost parameters for scrypt.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Params {
+    /// The base 2 logarithm of the CPU/memory cost N.
+    pub log_n: u8,
+    /// The block size, in units of 128 bytes.
+    pub r: u32,
+    /// The parallelization parameter.
+    pub p: u32,
+}
+
+impl Params {
+    /// Outputs the number of bytes of memory that deriving a key uses.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the parameters are not valid: `log_n` must be from 1 to 63, `r` and `p` must be
+    /// positive with `r * p` less than 2^30, and the memory used must fit in a `usize`.
+    pub fn memory_len(&self) -> usize {
+        assert!(0 < self.log_n && self.log_n < 64);
+        assert!(self.r > 0 && self.p > 0);
+        assert!(u64::from(self.r) * u64::from(self.p) < 1 << 30);
+        let blocks = 1usize
+            .checked_shl(u32::from(self.log_n))
+            .and_then(|n| n.checked_add(self.p as usize + 1))
+            .expect("N is too large");
+        self.block_len()
+            .checked_mul(blocks)
+            .expect("N * r is too large")
+    }
+
+    /// The length of a block mixed by BlockMix.
+    fn block_len(&self) -> usize {
+        2 * BLOCK_LEN * self.r as usize
+    }
+}
+
+/// Derives a key from a password and salt into `output`, allocating the memory needed.
+///
+/// # Panics
+///
+/// Panics if `params` are not valid, as described for `Params::memory_len`, or if
+/// `output.len()` is more than (2^32 - 1) * 32.
+#[cfg(feature = "alloc")]
+pub fn derive(password: &[u8], salt: &[u8], params: Params, output: &mut [u8]) {
+    let memory = &mut vec![0; params.memory_len()];
+    derive_with_memory(password, salt, params, memory, output);
+}
+
+/// Derives a key from a password and salt into `output`, using caller-provided memory.
+///
+/// # Panics
+///
+/// Panics if `params` are not valid, as described for `Params::memory_len`, if `memory.len()` is
+/// less than `params.memory_len()`, or if `output.len()` is more than (2^32 - 1) * 32.
+pub fn derive_with_memory(
+    password: &[u8],
+    salt: &[u8],
+    params: Params,
+    memory: &mut [u8],
+    output: &mut [u8],
+) {
+    let block_len = params.block_len();
+    let memory = &mut memory[..params.memory_len()];
+    let (blocks, rest) = memory.split_at_mut(block_len * params.p as usize);
+    let (scratch, v) = rest.split_at_mut(block_len);
+    pbkdf2::derive::<Sha256>(password, salt, 1, blocks);
+    for block in blocks.chunks_mut(block_len) {
+        ro_mix(block, v, scratch);
+    }
+    pbkdf2::derive::<Sha256>(password, blocks, 1, output);
+    for byte in memory.iter_mut() {
+        *byte = 0;
+    }
+}
+
+/// Mixes `block` with the sequential memory-hard function ROMix, using `v` for the 2^log_n
+/// copies of the block and `scratch` for BlockMix.
+fn ro_mix(block: &mut [u8], v: &mut [u8], scratch: &mut [u8]) {
+    let block_len = block.len();
+    let n = v.len() / block_len;
+    for v_i in v.chunks_mut(block_len) {
+        v_i.copy_from_slice(block);
+        block_mix(block, scratch);
+    }
+    for _ in 0..n {
+        let j = integerify(block) & (n as u64 - 1);
+        let v_j = &v[j as usize * block_len..][..block_len];
+        for (x, y) in block.iter_mut().zip(v_j) {
+            *x ^= y;
+        }
+        block_mix(block, scratch);
+    }
+}
+
+/// Mixes `block` in place with BlockMix using Salsa20/8.
+fn block_mix(block: &mut [u8], scratch: &mut [u8]) {
+    let half = block.len() / 2;
+    let mut x = [0; 16];
+    LittleEndian::read_u32_into(&block[block.len() - BLOCK_LEN..], &mut x);
+    for (i, chunk) in block.chunks(BLOCK_LEN).enumerate() {
+        let mut input = [0; 16];
+        LittleEndian::read_u32_into(chunk, &mut input);
+        for (word, x_word) in input.iter_mut().zip(&x) {
+            *word ^= x_word;
+        }
+        x = salsa20::core(&input, 4);
+        // The even-numbered outputs go in the first half and the odd-numbered in the second.
+        let offset = (i % 2) * half + (i / 2) * BLOCK_LEN;
+        LittleEndian::write_u32_into(&x, &mut scratch[offset..offset + BLOCK_LEN]);
+    }
+    block.copy_from_slice(scratch);
+}
+
+/// Interprets the start of the last 64-byte part of a block as a little-endian integer.
+fn integerify(block: &[u8]) -> u64 {
+    LittleEndian::read_u64(&block[block.len() - BLOCK_LEN..])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::*;
+    use std::vec;
+
+    fn check(password: &[u8], salt: &[u8], params: Params, expected: &str) {
+        let expected = h2b(expected);
+        let memory = &mut vec![0; params.memory_len()];
+        let output = &mut [0; 64];
+        derive_with_memory(password, salt, params, memory, output);
+        assert_eq!(&expected[..], &output[..]);
+    }
+
+    // RFC 7914, Section 12, except the last vector, which takes too long
+    #[test]
+    fn test_vectors() {
+        check(
+            b"",
+            b"",
+            Params {
+                log_n: 4,
+                r: 1,
+                p: 1,
+            },
+            "77d6576238657b203b19ca42c18a0497f16b4844e3074ae8dfdffa3fede21442\
+             fcd0069ded0948f8326a753a0fc81f17e8d3e0fb2e0d3628cf35e20c38d18906",
+        );
+        check(
+            b"password",
+            b"NaCl",
+            Params {
+                log_n: 10,
+                r: 8,
+                p: 16,
+            },
+            "fdbabe1c9d3472007856e7190d01e9fe7c6ad7cbc8237830e77376634b373162\
+             2eaf30d92e22a3886ff109279d9830dac727afb94a83ee6d8360cbdfa2cc0640",
+        );
+        check(
+            b"pleaseletmein",
+            b"SodiumChloride",
+            Params {
+                log_n: 14,
+                r: 8,
+                p: 1,
+            },
+            "7023bdcb3afd7348461c06cd81fd38ebfda8fbba904f8e3ea9b543f6545da1f2\
+             d5432955613f0fcf62d49705242a9af9e61e85dc0d651e40dfcf017b45575887",
+        );
+    }
+
+    #[test]
+    fn test_memory() {
+        let params = Params {
+            log_n: 4,
+            r: 1,
+            p: 2,
+        };
+        assert_eq!(128 * (16 + 2 + 1), params.memory_len());
+        let memory = &mut [0xff; 128 * 19];
+        let output = &mut [0; 32];
+        derive_with_memory(b"password", b"salt", params, memory, output);
+        assert!(memory.iter().all(|&byte| byte == 0));
+        // Generated with Python's hashlib
+        assert_eq!(
+            &h2b("d8daed18e86519e136e7b604855d9f22bdf9b29096706516b8f411740f003934")[..],
+            &output[..]
+        );
+        let other = &mut [0; 32];
+        derive_with_memory(b"password", b"salt", params, &mut [0; 128 * 20], other);
+        assert_eq!(output, other);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_invalid_params() {
+        let params = Params {
+            log_n: 0,
+            r: 1,
+            p: 1,
+        };
+        params.memory_len();
+    }
+}
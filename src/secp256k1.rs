@@ -0,0 +1,345 @@
+//! Module for ECDH and ECDSA with SHA-256 on the secp256k1 curve (SEC 2), as used by Bitcoin.
+//!
+//! Secret keys are 32-byte big-endian integers between 1 and n - 1, public keys use the 65-byte
+//! uncompressed encoding 0x04 || x || y, and signatures use the compact 64-byte encoding r || s.
+//!
+//! Since r || s and r || (n - s) are both valid signatures, signatures follow the low-S rule of
+//! Bitcoin's BIP 62 and BIP 146 to prevent malleability: signing always outputs the one with
+//! s at most (n - 1) / 2, and verification rejects the other one.
+use crate::error::Error;
+use crate::rand::SecureRandom;
+use crate::sha2::{sha256, Sha256};
+use crate::sig::{self, Signer, Verifier};
+use crate::weierstrass::{self, Curve, Params};
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+pub(crate) const PARAMS: Params<4> = Params {
+    p: [
+        0xffff_fffe_ffff_fc2f,
+        0xffff_ffff_ffff_ffff,
+        0xffff_ffff_ffff_ffff,
+        0xffff_ffff_ffff_ffff,
+    ],
+    n: [
+        0xbfd2_5e8c_d036_4141,
+        0xbaae_dce6_af48_a03b,
+        0xffff_ffff_ffff_fffe,
+        0xffff_ffff_ffff_ffff,
+    ],
+    a: [0; 4],
+    b: [7, 0, 0, 0],
+    gx: [
+        0x59f2_815b_16f8_1798,
+        0x029b_fcdb_2dce_28d9,
+        0x55a0_6295_ce87_0b07,
+        0x79be_667e_f9dc_bbac,
+    ],
+    gy: [
+        0x9c47_d08f_fb10_d4b8,
+        0xfd17_b448_a685_5419,
+        0x5da4_fbfc_0e11_08a8,
+        0x483a_da77_26a3_c465,
+    ],
+};
+
+/// Computes the public key for a secret key.
+///
+/// # Errors
+///
+/// Fails with `Error::InvalidKey` if `secret_key.len()` is not equal to 32 or the secret key is
+/// out of range.
+pub fn gen_pk(secret_key: &[u8]) -> Result<[u8; 65], Error> {
+    let mut public_key = [0; 65];
+    if Curve::new(&PARAMS).gen_pk(secret_key, &mut public_key) {
+        Ok(public_key)
+    } else {
+        Err(Error::InvalidKey)
+    }
+}
+
+/// Computes an ECDH shared secret, the x-coordinate of the shared point, given a secret key and
+/// another's public key.
+///
+/// # Errors
+///
+/// Fails with `Error::InvalidKey` if `secret_key.len()` is not equal to 32, the secret key is out
+/// of range, or the public key is not a point on the curve.
+pub fn ecdh(secret_key: &[u8], public_key: &[u8]) -> Result<[u8; 32], Error> {
+    let mut shared_secret = [0; 32];
+    if Curve::new(&PARAMS).ecdh(secret_key, public_key, &mut shared_secret) {
+        Ok(shared_secret)
+    } else {
+        Err(Error::InvalidKey)
+    }
+}
+
+/// Signs a message with ECDSA and SHA-256, outputting the low-S signature r || s. The
+/// per-signature nonce is drawn from `rng`.
+///
+/// # Errors
+///
+/// Fails with `Error::InvalidKey` if `secret_key.len()` is not equal to 32 or the secret key is
+/// out of range.
+///
+/// # Examples
+///
+/// ```
+/// use crypto_pure::secp256k1::{gen_pk, sign, verify};
+/// use crypto_pure::rand::SystemRandom;
+/// # let secret_key = &[0x42; 32];
+/// let public_key = &gen_pk(secret_key)?;
+/// let signature = &sign(b"message", secret_key, &SystemRandom::new())?;
+/// assert!(verify(b"message", signature, public_key));
+/// # Ok::<(), crypto_pure::error::Error>(())
+/// ```
+pub fn sign(message: &[u8], secret_key: &[u8], rng: &dyn SecureRandom) -> Result<[u8; 64], Error> {
+    let curve = Curve::new(&PARAMS);
+    let mut signature = [0; 64];
+    if !curve.sign(&sha256(message), secret_key, rng, &mut signature) {
+        return Err(Error::InvalidKey);
+    }
+    curve.normalize_s(&mut signature);
+    Ok(signature)
+}
+
+/// Signs a message with ECDSA and SHA-256, outputting the low-S signature r || s. The
+/// per-signature nonce is derived from the secret key and message as in RFC 6979, so signing
+/// needs no randomness and the same message always gives the same signature.
+///
+/// # Errors
+///
+/// Fails with `Error::InvalidKey` if `secret_key.len()` is not equal to 32 or the secret key is
+/// out of range.
+pub fn sign_deterministic(message: &[u8], secret_key: &[u8]) -> Result<[u8; 64], Error> {
+    let curve = Curve::new(&PARAMS);
+    let mut signature = [0; 64];
+    if !curve.sign_deterministic::<Sha256>(&sha256(message), secret_key, b"", &mut signature) {
+        return Err(Error::InvalidKey);
+    }
+    curve.normalize_s(&mut signature);
+    Ok(signature)
+}
+
+/// Signs a message with ECDSA and SHA-256, outputting the low-S signature r || s. The
+/// per-signature nonce is derived as in RFC 6979 with 32 bytes from `rng` as additional data, so
+/// a weak `rng` doesn't leak the secret key, while fresh randomness makes each signature differ,
+/// defeating fault attacks that rely on signing the same message twice.
+///
+/// # Errors
+///
+/// Fails with `Error::InvalidKey` if `secret_key.len()` is not equal to 32 or the secret key is
+/// out of range.
+pub fn sign_hedged(
+    message: &[u8],
+    secret_key: &[u8],
+    rng: &dyn SecureRandom,
+) -> Result<[u8; 64], Error> {
+    let extra = &mut [0; 32];
+    rng.fill(extra);
+    let curve = Curve::new(&PARAMS);
+    let mut signature = [0; 64];
+    if !curve.sign_deterministic::<Sha256>(&sha256(message), secret_key, extra, &mut signature) {
+        return Err(Error::InvalidKey);
+    }
+    curve.normalize_s(&mut signature);
+    Ok(signature)
+}
+
+/// Verifies a low-S ECDSA signature r || s of a message with SHA-256 under a public key.
+pub fn verify(message: &[u8], signature: &[u8], public_key: &[u8]) -> bool {
+    let curve = Curve::new(&PARAMS);
+    signature.len() == 64
+        && !curve.is_high_s(signature)
+        && curve.verify(&sha256(message), signature, public_key)
+}
+
+/// Converts a signature r || s to its low-S form in place, for signatures produced elsewhere
+/// without the low-S rule. Outputs whether the signature was changed.
+///
+/// # Errors
+///
+/// Fails with `Error::InvalidLength` if `signature.len()` is not equal to 64.
+pub fn normalize_s(signature: &mut [u8]) -> Result<bool, Error> {
+    if signature.len() != 64 {
+        return Err(Error::InvalidLength);
+    }
+    Ok(Curve::new(&PARAMS).normalize_s(signature))
+}
+
+/// ECDSA with SHA-256 on secp256k1, with low-S signatures and nonces hedged as in `sign_hedged`.
+pub struct EcdsaSecp256k1;
+
+impl Verifier for EcdsaSecp256k1 {
+    const MAX_CONTEXT_LEN: usize = 0;
+
+    type PublicKey = [u8];
+
+    fn verify(
+        message: &[u8],
+        context: &[u8],
+        signature: &[u8],
+        public_key: &[u8],
+    ) -> Result<(), Error> {
+        sig::check_lengths::<Self>(context, public_key, 65)?;
+        sig::check_valid(verify(message, signature, public_key))
+    }
+}
+
+impl Signer for EcdsaSecp256k1 {
+    type SecretKey = [u8];
+    type Signature = [u8; 64];
+
+    fn sign(
+        message: &[u8],
+        context: &[u8],
+        secret_key: &[u8],
+        rng: &dyn SecureRandom,
+    ) -> Result<[u8; 64], Error> {
+        sig::check_lengths::<Self>(context, secret_key, 32)?;
+        sign_hedged(message, secret_key, rng)
+    }
+}
+
+/// Converts a signature r || s to the DER encoding used by X.509 and TLS, a SEQUENCE of the
+/// INTEGERs r and s.
+///
+/// # Errors
+///
+/// Fails with `Error::InvalidLength` if `signature.len()` is not equal to 64.
+#[cfg(feature = "alloc")]
+pub fn signature_to_der(signature: &[u8]) -> Result<Vec<u8>, Error> {
+    if signature.len() != 64 {
+        return Err(Error::InvalidLength);
+    }
+    Ok(weierstrass::signature_to_der(signature))
+}
+
+/// Converts a DER-encoded signature, a SEQUENCE of the INTEGERs r and s, to r || s. Signatures
+/// produced elsewhere may need `normalize_s` before they verify.
+///
+/// # Errors
+///
+/// Fails with `Error::InvalidEncoding` if `der` is not the DER encoding of two non-negative
+/// integers below 2^256, including if it is only valid BER, such as with redundant leading
+/// zeros or long-form lengths.
+pub fn signature_from_der(der: &[u8]) -> Result<[u8; 64], Error> {
+    let mut signature = [0; 64];
+    weierstrass::signature_from_der(der, &mut signature)?;
+    Ok(signature)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::*;
+
+    fn check(secret_key: &str, message: &[u8], k: &str, expected: &str) {
+        let secret_key = &h2b(secret_key);
+        let public_key = &gen_pk(secret_key).unwrap();
+        let k = &h2b(k);
+        let signature = sign(message, secret_key, &|buffer: &mut [u8]| {
+            buffer.copy_from_slice(k)
+        })
+        .unwrap();
+        assert_eq!(h2b(expected), &signature[..]);
+        assert_eq!(Ok(signature), sign_deterministic(message, secret_key));
+        assert!(verify(message, &signature, public_key));
+        assert!(!verify(b"other", &signature, public_key));
+        let hedged = sign_hedged(message, secret_key, &|buffer: &mut [u8]| {
+            buffer.copy_from_slice(&[0x24; 32])
+        })
+        .unwrap();
+        assert_ne!(signature, hedged);
+        assert!(verify(message, &hedged, public_key));
+
+        // The high-S form is rejected until normalized.
+        let high_s = &mut signature.clone();
+        let n = h2b("fffffffffffffffffffffffffffffffebaaedce6af48a03bbfd25e8cd0364141");
+        let mut borrow = 0;
+        for i in (0..32).rev() {
+            let difference = i16::from(n[i]) - i16::from(signature[32 + i]) - borrow;
+            high_s[32 + i] = difference as u8;
+            borrow = (difference < 0) as i16;
+        }
+        assert!(!verify(message, high_s, public_key));
+        assert_eq!(Ok(true), normalize_s(high_s));
+        assert_eq!(&signature, high_s);
+        assert_eq!(Ok(false), normalize_s(high_s));
+        assert_eq!(Err(Error::InvalidLength), normalize_s(&mut high_s[1..]));
+
+        // Conversions to and from DER preserve either form.
+        #[cfg(feature = "alloc")]
+        for signature in &[signature, *high_s] {
+            assert_eq!(
+                Ok(*signature),
+                signature_from_der(&signature_to_der(signature).unwrap())
+            );
+        }
+        #[cfg(feature = "alloc")]
+        assert_eq!(Err(Error::InvalidLength), signature_to_der(&signature[1..]));
+    }
+
+    #[test]
+    fn test_sign() {
+        // Nonces from RFC 6979 with SHA-256, giving widely used deterministic test vectors
+        check(
+            "0000000000000000000000000000000000000000000000000000000000000001",
+            b"Satoshi Nakamoto",
+            "8f8a276c19f4149656b280621e358cce24f5f52542772691ee69063b74f15d15",
+            "934b1ea10a4b3c1757e2b0c017d0b6143ce3c9a7e6a4a49860d7a6ab210ee3d8\
+             2442ce9d2b916064108014783e923ec36b49743e2ffa1c4496f01a512aafd9e5",
+        );
+        check(
+            "fffffffffffffffffffffffffffffffebaaedce6af48a03bbfd25e8cd0364140",
+            b"Satoshi Nakamoto",
+            "33a19b60e25fb6f4435af53a3d42d493644827367e6453928554f43e49aa6f90",
+            "fd567d121db66e382991534ada77a6bd3106f0a1098c231e47993447cd6af2d0\
+             6b39cd0eb1bc8603e159ef5c20a5c8ad685a45b06ce9bebed3f153d10d93bed5",
+        );
+        check(
+            "f8b8af8ce3c7cca5e300d33939540c10d45ce001b8f252bfbc57ba0342904181",
+            b"Alan Turing",
+            "525a82b70e67874398067543fd84c83d30c175fdc45fdeee082fe13b1d7cfdf1",
+            "7063ae83e7f62bbb171798131b4a0564b956930092b33b07b395615d9ec7e15c\
+             58dfcc1e00a35e1572f366ffe34ba0fc47db1e7189759b9fb233c5b05ab388ea",
+        );
+    }
+
+    #[test]
+    fn test_gen_pk() {
+        let mut secret_key = [0; 32];
+        secret_key[31] = 3;
+        let expected = "04f9308a019258c31049344f85f89d5229b531c845836f99b08601f113bce036f9\
+                        388f7b0f632de8140fe337e62a37f3566500a99934c2231b6cb9fd7584b8e672";
+        assert_eq!(h2b(expected), &gen_pk(&secret_key).unwrap()[..]);
+        assert_eq!(Err(Error::InvalidKey), gen_pk(&[0; 32]));
+        assert_eq!(Err(Error::InvalidKey), gen_pk(&secret_key[1..]));
+        let rng = &|buffer: &mut [u8]| buffer.fill(0x24);
+        assert_eq!(Err(Error::InvalidKey), sign(b"message", &[0; 32], rng));
+        assert_eq!(
+            Err(Error::InvalidKey),
+            sign_deterministic(b"message", &[0; 32])
+        );
+        assert_eq!(
+            Err(Error::InvalidKey),
+            sign_hedged(b"message", &secret_key[1..], rng)
+        );
+    }
+
+    #[test]
+    fn test_ecdh() {
+        let alice_secret_key = &[0x42; 32];
+        let bob_secret_key = &[0x24; 32];
+        let alice_public_key = &gen_pk(alice_secret_key).unwrap();
+        let bob_public_key = &gen_pk(bob_secret_key).unwrap();
+        let shared_secret = ecdh(alice_secret_key, bob_public_key).unwrap();
+        assert_eq!(
+            shared_secret,
+            ecdh(bob_secret_key, alice_public_key).unwrap()
+        );
+        let expected = "19c227001629350835c0ce8689d71c643e53ba789a2e9d95eeb611d1e6a9ab3a";
+        assert_eq!(h2b(expected), &shared_secret[..]);
+        assert_eq!(Err(Error::InvalidKey), ecdh(alice_secret_key, &[4; 65]));
+    }
+}
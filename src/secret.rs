@@ -0,0 +1,211 @@
+//! Module for wrappers that erase secret data from memory when they are dropped.
+//!
+//! Erasing uses volatile writes followed by a compiler fence, so that the writes are not removed
+//! as dead stores to memory that is about to be released. Copies made before a value is wrapped,
+//! such as by moves of the unwrapped value, are not erased, so secrets should be wrapped where
+//! they are created.
+//!
+//! # Examples
+//!
+//! ```
+//! use crypto_pure::secret::SecretKey;
+//! let mut key = SecretKey::new([0_u8; 32]);
+//! key[0] = 0x42;
+//! assert_eq!(0x42, key[0]);
+//! ```
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+use core::fmt;
+use core::ops::{Deref, DerefMut};
+use core::ptr;
+use core::sync::atomic::{compiler_fence, Ordering};
+
+/// Types whose values can be erased.
+pub trait Zeroize {
+    /// Overwrites the value with zeros in a way that the compiler does not optimize away.
+    fn zeroize(&mut self);
+}
+
+macro_rules! impl_zeroize {
+    ($($t:ty),*) => {$(
+        impl Zeroize for $t {
+            fn zeroize(&mut self) {
+                // Safe since `self` is a valid reference.
+                unsafe { ptr::write_volatile(self, 0) };
+                compiler_fence(Ordering::SeqCst);
+            }
+        }
+    )*};
+}
+
+impl_zeroize!(u8, u16, u32, u64, u128, usize);
+
+impl<T: Zeroize> Zeroize for [T] {
+    fn zeroize(&mut self) {
+        self.iter_mut().for_each(Zeroize::zeroize);
+    }
+}
+
+impl<T: Zeroize, const N: usize> Zeroize for [T; N] {
+    fn zeroize(&mut self) {
+        self[..].zeroize();
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<T: Zeroize> Zeroize for Vec<T> {
+    /// Erases the elements and then clears the vector.
+    fn zeroize(&mut self) {
+        self[..].zeroize();
+        self.clear();
+    }
+}
+
+/// A secret value, such as a key or a key schedule, that is erased when dropped.
+///
+/// The value is accessed through `Deref` and `DerefMut`, and `Debug` does not show it.
+#[derive(Clone)]
+pub struct SecretKey<T: Zeroize>(T);
+
+impl<T: Zeroize> SecretKey<T> {
+    /// Wraps a secret value.
+    pub fn new(value: T) -> Self {
+        Self(value)
+    }
+}
+
+impl<T: Zeroize> Deref for SecretKey<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T: Zeroize> DerefMut for SecretKey<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
+
+impl<T: Zeroize> fmt::Debug for SecretKey<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("SecretKey(..)")
+    }
+}
+
+impl<T: Zeroize> Drop for SecretKey<T> {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+/// A secret byte vector, such as derived keying material, that is erased when dropped.
+///
+/// The bytes are accessed through `Deref` and `DerefMut`, and `Debug` does not show them.
+#[cfg(feature = "alloc")]
+#[derive(Clone, Default)]
+pub struct SecretVec(Vec<u8>);
+
+#[cfg(feature = "alloc")]
+impl SecretVec {
+    /// Wraps a secret vector.
+    pub fn new(bytes: Vec<u8>) -> Self {
+        Self(bytes)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl From<Vec<u8>> for SecretVec {
+    fn from(bytes: Vec<u8>) -> Self {
+        Self(bytes)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl Deref for SecretVec {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl DerefMut for SecretVec {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        &mut self.0
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl AsRef<[u8]> for SecretVec {
+    fn as_ref(&self) -> &[u8] {
+        self
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl fmt::Debug for SecretVec {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("SecretVec(..)")
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl Drop for SecretVec {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "alloc")]
+    use std::vec;
+
+    #[test]
+    fn test_zeroize() {
+        let mut array = [[0xff_u64; 4]; 6];
+        array.zeroize();
+        assert_eq!([[0; 4]; 6], array);
+
+        let mut x = u128::MAX;
+        x.zeroize();
+        assert_eq!(0, x);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_zeroize_vec() {
+        let mut bytes = vec![1_u8, 2, 3];
+        bytes[..].zeroize();
+        assert_eq!([0; 3], &bytes[..]);
+        bytes.zeroize();
+        assert!(bytes.is_empty());
+    }
+
+    #[test]
+    fn test_secret_key() {
+        let mut key = SecretKey::new([1_u8; 16]);
+        key[15] = 2;
+        assert_eq!(2, key[15]);
+        assert_eq!([1; 15], key[..15]);
+        assert_eq!("SecretKey(..)", format!("{:?}", key));
+
+        // Dropping in place leaves the erased storage behind to check.
+        let mut key = core::mem::ManuallyDrop::new(key);
+        unsafe { core::mem::ManuallyDrop::drop(&mut key) };
+        assert_eq!([0; 16], key.0);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_secret_vec() {
+        let mut bytes = SecretVec::from(vec![7; 5]);
+        bytes[0] = 1;
+        assert_eq!([1, 7, 7, 7, 7], &bytes[..]);
+        assert_eq!("SecretVec(..)", format!("{:?}", bytes));
+    }
+}
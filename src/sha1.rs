@@ -0,0 +1,187 @@
+//! Module for the SHA-1 hash function.
+//!
+//! SHA-1 is broken with respect to collision resistance and is only provided for verifying digests
+//! produced by legacy systems. Do not use it in new designs.
+use crate::sha2::HashFunction;
+use byteorder::{BigEndian, ByteOrder as _};
+
+/// The SHA-1 hash function, for legacy interoperability only.
+///
+/// # Examples
+///
+/// ```
+/// use crypto_pure::sha1::InsecureSha1;
+/// use crypto_pure::sha2::HashFunction;
+/// let mut digest = [0; InsecureSha1::DIGEST_SIZE];
+/// let mut sha = InsecureSha1::default();
+/// sha.update(b"part one");
+/// sha.update(b"part two");
+/// sha.write_digest(&mut digest);
+/// ```
+#[derive(Clone)]
+pub struct InsecureSha1 {
+    state: [u32; 5],
+    buffer: [u8; 64],
+    offset: usize,
+    len: u64,
+}
+
+/// Wrapper for obtaining the SHA-1 digest for a complete message, for legacy interoperability only.
+pub fn insecure_sha1(message: &[u8]) -> [u8; InsecureSha1::DIGEST_SIZE] {
+    let mut digest = [0; InsecureSha1::DIGEST_SIZE];
+    let mut sha = InsecureSha1::default();
+    sha.update(message);
+    sha.write_digest(&mut digest);
+    digest
+}
+
+const INITIAL_STATE: [u32; 5] = [
+    0x6745_2301,
+    0xefcd_ab89,
+    0x98ba_dcfe,
+    0x1032_5476,
+    0xc3d2_e1f0,
+];
+
+impl Default for InsecureSha1 {
+    fn default() -> Self {
+        Self {
+            state: INITIAL_STATE,
+            buffer: [0; 64],
+            offset: 0,
+            len: 0,
+        }
+    }
+}
+
+impl HashFunction for InsecureSha1 {
+    const DIGEST_SIZE: usize = 20;
+    const BLOCK_SIZE: usize = 64;
+
+    fn update(&mut self, input: &[u8]) {
+        self.len += input.len() as u64;
+        let mut input_offset = 0;
+        let buffer_space = self.buffer.len() - self.offset;
+        if self.offset > 0 {
+            if input.len() < buffer_space {
+                self.buffer[self.offset..self.offset + input.len()].copy_from_slice(input);
+                self.offset += input.len();
+                return;
+            }
+            self.buffer[self.offset..].copy_from_slice(&input[..buffer_space]);
+            self.offset = 0;
+            process(&mut self.state, &self.buffer);
+            input_offset = buffer_space;
+        }
+        for chunk in input[input_offset..].chunks(self.buffer.len()) {
+            if chunk.len() < self.buffer.len() {
+                self.buffer[..chunk.len()].copy_from_slice(chunk);
+                self.offset = chunk.len();
+            } else {
+                process(&mut self.state, chunk);
+            }
+        }
+    }
+
+    /// Writes the hash function digest into an output buffer.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `output.len()` is not equal to the digest size.
+    fn write_digest(mut self, output: &mut [u8]) {
+        assert_eq!(Self::DIGEST_SIZE, output.len());
+        self.pad();
+        process(&mut self.state, &self.buffer);
+        BigEndian::write_u32_into(&self.state, output);
+    }
+}
+
+impl InsecureSha1 {
+    fn pad(&mut self) {
+        self.buffer[self.offset] = 0x80;
+        self.offset += 1;
+        if self.offset > 64 - 8 {
+            for byte in self.buffer.iter_mut().skip(self.offset) {
+                *byte = 0;
+            }
+            self.offset = 0;
+            process(&mut self.state, &self.buffer);
+        }
+        for byte in self.buffer.iter_mut().take(64 - 8).skip(self.offset) {
+            *byte = 0;
+        }
+        BigEndian::write_u64(&mut self.buffer[64 - 8..], 8 * self.len);
+    }
+}
+
+fn process(state: &mut [u32; 5], input: &[u8]) {
+    let mut w = [0; 80];
+    BigEndian::read_u32_into(input, &mut w[..16]);
+    for t in 16..80 {
+        w[t] = (w[t - 3] ^ w[t - 8] ^ w[t - 14] ^ w[t - 16]).rotate_left(1);
+    }
+    let mut a = state[0];
+    let mut b = state[1];
+    let mut c = state[2];
+    let mut d = state[3];
+    let mut e = state[4];
+    for (t, &wt) in w.iter().enumerate() {
+        let (f, k) = match t {
+            0..=19 => ((b & c) | (!b & d), 0x5a82_7999),
+            20..=39 => (b ^ c ^ d, 0x6ed9_eba1),
+            40..=59 => ((b & c) | (b & d) | (c & d), 0x8f1b_bcdc),
+            _ => (b ^ c ^ d, 0xca62_c1d6),
+        };
+        let temp = a
+            .rotate_left(5)
+            .wrapping_add(f)
+            .wrapping_add(e)
+            .wrapping_add(k)
+            .wrapping_add(wt);
+        e = d;
+        d = c;
+        c = b.rotate_left(30);
+        b = a;
+        a = temp;
+    }
+    state[0] = state[0].wrapping_add(a);
+    state[1] = state[1].wrapping_add(b);
+    state[2] = state[2].wrapping_add(c);
+    state[3] = state[3].wrapping_add(d);
+    state[4] = state[4].wrapping_add(e);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::*;
+
+    fn check(expected: &str, message: &[u8]) {
+        let expected = h2b(expected);
+        assert_eq!(expected, insecure_sha1(message).to_vec());
+
+        let actual = &mut [0; InsecureSha1::DIGEST_SIZE];
+        let mut sha = InsecureSha1::default();
+        for word in message.chunks(3) {
+            sha.update(word);
+        }
+        sha.write_digest(actual);
+        assert_eq!(expected, actual.to_vec());
+    }
+
+    #[test]
+    fn test_digest() {
+        check("da39a3ee5e6b4b0d3255bfef95601890afd80709", b"");
+        check("a9993e364706816aba3e25717850c26c9cd0d89d", b"abc");
+        check(
+            "84983e441c3bd26ebaae4aa1f95129e5e54670f1",
+            b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq",
+        );
+        check(
+            "a49b2446a02c645bf419f995b67091253a04a259",
+            b"abcdefghbcdefghicdefghijdefghijkefghijklfghijklmghijklmn\
+              hijklmnoijklmnopjklmnopqklmnopqrlmnopqrsmnopqrstnopqrstu",
+        );
+        check("34aa973cd4c4daa4f61eeb2bdbad27316534016f", &[0x61; 1000000]);
+    }
+}
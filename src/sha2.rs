@@ -1,9 +1,15 @@
 //! Module for the SHA-2 family of hash functions.
+use crate::secret::Zeroize;
 use byteorder::{BigEndian, ByteOrder as _};
+use core::convert::TryFrom;
+use core::marker::PhantomData;
 use core::mem::size_of;
+use core::ops::Deref;
+
+pub mod multi;
 
 /// A trait for hash functions.
-pub trait HashFunction: Default {
+pub trait HashFunction: Clone + Default {
     /// Digest size in bytes.
     const DIGEST_SIZE: usize;
     /// Block size in bytes.
@@ -14,6 +20,11 @@ pub trait HashFunction: Default {
 
     /// Writes the hash function digest into an output buffer.
     fn write_digest(self, output: &mut [u8]);
+
+    /// Resets the hash function to its initial state, discarding any input fed so far.
+    fn reset(&mut self) {
+        *self = Self::default();
+    }
 }
 
 /// The SHA-512 hash function.
@@ -28,6 +39,7 @@ pub trait HashFunction: Default {
 /// sha.update(b"part two");
 /// sha.write_digest(&mut digest);
 /// ```
+#[derive(Clone)]
 pub struct Sha512(Processor512);
 
 /// The SHA-384 hash function.
@@ -42,6 +54,7 @@ pub struct Sha512(Processor512);
 /// sha.update(b"part two");
 /// sha.write_digest(&mut digest);
 /// ```
+#[derive(Clone)]
 pub struct Sha384(Processor512);
 
 /// The SHA-256 hash function.
@@ -56,6 +69,7 @@ pub struct Sha384(Processor512);
 /// sha.update(b"part two");
 /// sha.write_digest(&mut digest);
 /// ```
+#[derive(Clone)]
 pub struct Sha256(Processor256);
 
 /// The SHA-224 hash function.
@@ -70,6 +84,7 @@ pub struct Sha256(Processor256);
 /// sha.update(b"part two");
 /// sha.write_digest(&mut digest);
 /// ```
+#[derive(Clone)]
 pub struct Sha224(Processor256);
 
 macro_rules! impl_wrapper {
@@ -128,6 +143,24 @@ macro_rules! impl_function {
                 self.0.write_digest(output);
             }
         }
+
+        impl $function {
+            /// Exports a snapshot of the internal state, including any partially filled block.
+            pub fn export_state(&self) -> ShaState {
+                self.0.export_state($algorithm.id)
+            }
+
+            /// Resumes hashing from an exported state.
+            ///
+            /// Returns `None` if the state was exported from a different hash function or is
+            /// otherwise invalid.
+            pub fn from_state(state: &ShaState) -> Option<Self> {
+                if state.id != $algorithm.id {
+                    return None;
+                }
+                $processor::from_state(state).map($function)
+            }
+        }
     };
 }
 
@@ -137,14 +170,130 @@ impl_function!(Sha256, SHA256, Processor256);
 impl_function!(Sha224, SHA224, Processor256);
 
 pub(crate) const MAX_DIGEST_SIZE: usize = 64;
+pub(crate) const MAX_BLOCK_SIZE: usize = 144;
+
+/// A snapshot of the internal state of a SHA-2 hash function.
+///
+/// The state can be serialized so that hashing a long message can be checkpointed and resumed
+/// later, e.g. after a process restart. It contains the processed message length and partial
+/// block, i.e. data derived from the message, so it should be stored as carefully as the message.
+///
+/// # Examples
+///
+/// ```
+/// use crypto_pure::sha2::{sha256, HashFunction, Sha256, ShaState};
+/// let mut sha = Sha256::default();
+/// sha.update(b"part one");
+/// let checkpoint = sha.export_state().to_bytes();
+///
+/// let state = ShaState::from_bytes(&checkpoint).unwrap();
+/// let mut sha = Sha256::from_state(&state).unwrap();
+/// sha.update(b"part two");
+/// let mut digest = [0; Sha256::DIGEST_SIZE];
+/// sha.write_digest(&mut digest);
+/// assert_eq!(sha256(b"part onepart two"), digest);
+/// ```
+#[derive(Clone)]
+pub struct ShaState {
+    id: u8,
+    state: [u64; 8],
+    len: u128,
+    buffer: [u8; 128],
+}
+
+impl ShaState {
+    /// Size of a serialized state in bytes.
+    pub const SIZE: usize = 1 + 8 * 8 + 16 + 128;
+
+    /// Serializes the state.
+    pub fn to_bytes(&self) -> [u8; Self::SIZE] {
+        let mut output = [0; Self::SIZE];
+        output[0] = self.id;
+        BigEndian::write_u64_into(&self.state, &mut output[1..65]);
+        BigEndian::write_u128(&mut output[65..81], self.len);
+        output[81..].copy_from_slice(&self.buffer);
+        output
+    }
+
+    /// Deserializes a state, returning `None` if `input` is not a serialized state.
+    pub fn from_bytes(input: &[u8]) -> Option<Self> {
+        if input.len() != Self::SIZE {
+            return None;
+        }
+        let mut state = [0; 8];
+        BigEndian::read_u64_into(&input[1..65], &mut state);
+        let mut buffer = [0; 128];
+        buffer.copy_from_slice(&input[81..]);
+        Some(Self {
+            id: input[0],
+            state,
+            len: BigEndian::read_u128(&input[65..81]),
+            buffer,
+        })
+    }
+}
+
+/// A digest that derefs into a slice of bytes.
+pub struct Digest {
+    buffer: [u8; MAX_DIGEST_SIZE],
+    size: usize,
+}
+
+impl Digest {
+    pub(crate) fn new<H: HashFunction>(hash_function: H) -> Self {
+        let () = CheckSizes::<H>::VALID;
+        let mut buffer = [0; MAX_DIGEST_SIZE];
+        hash_function.write_digest(&mut buffer[..H::DIGEST_SIZE]);
+        Self {
+            buffer,
+            size: H::DIGEST_SIZE,
+        }
+    }
+
+    pub(crate) fn from_slice(digest: &[u8]) -> Self {
+        let mut buffer = [0; MAX_DIGEST_SIZE];
+        buffer[..digest.len()].copy_from_slice(digest);
+        Self {
+            buffer,
+            size: digest.len(),
+        }
+    }
+}
+
+/// Fails to compile for any hash function whose sizes exceed the fixed-size buffers used by
+/// generic code in this crate.
+pub(crate) struct CheckSizes<H>(PhantomData<H>);
+
+impl<H: HashFunction> CheckSizes<H> {
+    pub(crate) const VALID: () = assert!(
+        H::DIGEST_SIZE <= MAX_DIGEST_SIZE && H::BLOCK_SIZE <= MAX_BLOCK_SIZE,
+        "hash function sizes exceed MAX_DIGEST_SIZE or MAX_BLOCK_SIZE"
+    );
+}
+
+impl Deref for Digest {
+    type Target = [u8];
+
+    fn deref(&self) -> &Self::Target {
+        &self.buffer[..self.size]
+    }
+}
+
+impl AsRef<[u8]> for Digest {
+    fn as_ref(&self) -> &[u8] {
+        self
+    }
+}
 
 struct Algorithm<S> {
+    id: u8,
     digest_size: usize,
     block_size: usize,
     initial_state: S,
 }
 
 const SHA512: Algorithm<[u64; 8]> = Algorithm {
+    id: 0,
     digest_size: 64,
     block_size: 128,
     initial_state: [
@@ -160,6 +309,7 @@ const SHA512: Algorithm<[u64; 8]> = Algorithm {
 };
 
 const SHA384: Algorithm<[u64; 8]> = Algorithm {
+    id: 1,
     digest_size: 48,
     block_size: 128,
     initial_state: [
@@ -175,6 +325,7 @@ const SHA384: Algorithm<[u64; 8]> = Algorithm {
 };
 
 const SHA256: Algorithm<[u32; 8]> = Algorithm {
+    id: 2,
     digest_size: 32,
     block_size: 64,
     initial_state: [
@@ -190,6 +341,7 @@ const SHA256: Algorithm<[u32; 8]> = Algorithm {
 };
 
 const SHA224: Algorithm<[u32; 8]> = Algorithm {
+    id: 3,
     digest_size: 28,
     block_size: 64,
     initial_state: [
@@ -354,14 +506,15 @@ const K256: [u32; 64] = [
     0xc671_78f2,
 ];
 
+#[derive(Clone)]
 struct Processor512 {
     state: [u64; 8],
     buffer: [u8; 128],
     offset: usize,
-    /// Only supports messages with at most 2^64 - 8 bits for now.
-    len: u64,
+    len: u128,
 }
 
+#[derive(Clone)]
 struct Processor256 {
     state: [u32; 8],
     buffer: [u8; 64],
@@ -373,11 +526,13 @@ macro_rules! impl_processor {
     (
         $processor:ident,
         $word:ty,
+        $len:ty,
         $block_size:expr,
         $round_constants:expr,
         $rounds:expr,
         $read_into:path,
         $write_into:path,
+        $write_len:path,
         $b0:expr,
         $b1:expr,
         $s0:expr,
@@ -394,7 +549,7 @@ macro_rules! impl_processor {
             }
 
             fn update(&mut self, input: &[u8]) {
-                self.len += input.len() as u64;
+                self.len += input.len() as $len;
                 let mut input_offset = 0;
                 let buffer_space = self.buffer.len() - self.offset;
                 if self.offset > 0 {
@@ -418,6 +573,40 @@ macro_rules! impl_processor {
                 }
             }
 
+            fn export_state(&self, id: u8) -> ShaState {
+                let mut state = [0; 8];
+                for (x, &y) in state.iter_mut().zip(&self.state) {
+                    *x = u64::from(y);
+                }
+                let mut buffer = [0; 128];
+                buffer[..self.offset].copy_from_slice(&self.buffer[..self.offset]);
+                ShaState {
+                    id,
+                    state,
+                    len: u128::from(self.len),
+                    buffer,
+                }
+            }
+
+            fn from_state(state: &ShaState) -> Option<Self> {
+                let len = <$len>::try_from(state.len).ok()?;
+                let offset = (len % $block_size) as usize;
+                if state.buffer[offset..].iter().any(|&byte| byte != 0) {
+                    return None;
+                }
+                let mut processor = Self {
+                    state: [0; 8],
+                    buffer: [0; $block_size],
+                    offset,
+                    len,
+                };
+                for (x, &y) in processor.state.iter_mut().zip(&state.state) {
+                    *x = <$word>::try_from(y).ok()?;
+                }
+                processor.buffer[..offset].copy_from_slice(&state.buffer[..offset]);
+                Some(processor)
+            }
+
             fn write_digest(mut self, output: &mut [u8]) {
                 self.pad();
                 Self::process(&mut self.state, &self.buffer);
@@ -480,12 +669,15 @@ macro_rules! impl_processor {
                 for byte in self
                     .buffer
                     .iter_mut()
-                    .take($block_size - 8)
+                    .take($block_size - size_of::<$len>())
                     .skip(self.offset)
                 {
                     *byte = 0;
                 }
-                BigEndian::write_u64(&mut self.buffer[$block_size - 8..], 8 * self.len);
+                $write_len(
+                    &mut self.buffer[$block_size - size_of::<$len>()..],
+                    8 * self.len,
+                );
             }
 
             fn ch(x: $word, y: $word, z: $word) -> $word {
@@ -512,17 +704,27 @@ macro_rules! impl_processor {
                 x.rotate_right($s1.0) ^ x.rotate_right($s1.1) ^ (x >> $s1.2)
             }
         }
+
+        // The state and buffer can hold keys, such as the padded key in an HMAC state.
+        impl Drop for $processor {
+            fn drop(&mut self) {
+                self.state.zeroize();
+                self.buffer.zeroize();
+            }
+        }
     };
 }
 
 impl_processor!(
     Processor512,
     u64,
+    u128,
     128,
     K512,
     80,
     BigEndian::read_u64_into,
     BigEndian::write_u64_into,
+    BigEndian::write_u128,
     (28, 34, 39),
     (14, 18, 41),
     (1, 8, 7),
@@ -531,11 +733,13 @@ impl_processor!(
 impl_processor!(
     Processor256,
     u32,
+    u64,
     64,
     K256,
     64,
     BigEndian::read_u32_into,
     BigEndian::write_u32_into,
+    BigEndian::write_u64,
     (2, 13, 22),
     (6, 11, 25),
     (7, 18, 3),
@@ -547,6 +751,7 @@ mod tests {
     use super::*;
     use crate::test_helpers::*;
     use std::string::String;
+    use std::vec::Vec;
 
     const TEST1: &[u8] = b"abc";
     const TEST2_1: &[u8] = b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq";
@@ -578,6 +783,37 @@ mod tests {
         assert_eq!(expected, processor.buffer.to_vec());
     }
 
+    #[test]
+    fn test_pad_128_bit_length() {
+        let message = [0b01100001, 0b01100010, 0b01100011, 0b01100100, 0b01100101];
+        let check = |len: u128, expected_len: &str| {
+            let expected = h2b(&format!(
+                "6162636465800000000000000000000000000000000000000000000000000000\
+                 0000000000000000000000000000000000000000000000000000000000000000\
+                 0000000000000000000000000000000000000000000000000000000000000000\
+                 00000000000000000000000000000000{}",
+                expected_len
+            ));
+            let mut processor = Processor512::new(&SHA512);
+            processor.update(&message);
+            processor.len = len;
+            processor.pad();
+            assert_eq!(expected, processor.buffer.to_vec());
+        };
+        check((1 << 61) - 1, "0000000000000000fffffffffffffff8");
+        check(1 << 61, "00000000000000010000000000000000");
+        check((1 << 61) + 5, "00000000000000010000000000000028");
+        check((1 << 125) - 1, "fffffffffffffffffffffffffffffff8");
+    }
+
+    #[test]
+    fn test_update_128_bit_length() {
+        let mut processor = Processor512::new(&SHA512);
+        processor.len = u128::from(u64::MAX);
+        processor.update(&[0; 3]);
+        assert_eq!(u128::from(u64::MAX) + 3, processor.len);
+    }
+
     macro_rules! check {
         ($function:ident, $wrapper:path, $expected:expr, $message:expr) => {
             let expected = h2b($expected);
@@ -609,6 +845,69 @@ mod tests {
         check!(Sha224, sha224, exp224, message);
     }
 
+    #[test]
+    fn test_reset_and_clone() {
+        let mut sha = Sha256::default();
+        sha.update(TEST2_1);
+        sha.reset();
+        sha.update(b"ab");
+        let mut forked = sha.clone();
+        sha.update(b"c");
+        forked.update(b"d");
+
+        let actual = &mut [0; Sha256::DIGEST_SIZE];
+        sha.write_digest(actual);
+        assert_eq!(sha256(TEST1), *actual);
+        forked.write_digest(actual);
+        assert_eq!(sha256(b"abd"), *actual);
+    }
+
+    macro_rules! check_state {
+        ($function:ident, $wrapper:path) => {
+            let message: &Vec<_> = &(0..=255).collect();
+            for split in &[0, 1, 63, 64, 65, 127, 128, 129, 256] {
+                let mut sha = <$function>::default();
+                sha.update(&message[..*split]);
+                let bytes = sha.export_state().to_bytes();
+
+                let state = ShaState::from_bytes(&bytes).unwrap();
+                let mut sha = <$function>::from_state(&state).unwrap();
+                sha.update(&message[*split..]);
+                let actual = &mut [0; $function::DIGEST_SIZE];
+                sha.write_digest(actual);
+                assert_eq!($wrapper(message).to_vec(), actual.to_vec());
+            }
+        };
+    }
+
+    #[test]
+    fn test_export_state() {
+        check_state!(Sha512, sha512);
+        check_state!(Sha384, sha384);
+        check_state!(Sha256, sha256);
+        check_state!(Sha224, sha224);
+    }
+
+    #[test]
+    fn test_from_invalid_state() {
+        let mut sha = Sha256::default();
+        sha.update(TEST1);
+        let state = sha.export_state();
+        assert!(Sha256::from_state(&state).is_some());
+        assert!(Sha224::from_state(&state).is_none());
+        assert!(Sha512::from_state(&state).is_none());
+
+        let bytes = &mut state.to_bytes();
+        assert!(ShaState::from_bytes(&bytes[1..]).is_none());
+        bytes[81 + TEST1.len()] = 1;
+        assert!(Sha256::from_state(&ShaState::from_bytes(bytes).unwrap()).is_none());
+
+        let bytes = &mut Sha512::default().export_state().to_bytes();
+        bytes[0] = Sha256::default().export_state().id;
+        bytes[1] = 1;
+        assert!(Sha256::from_state(&ShaState::from_bytes(bytes).unwrap()).is_none());
+    }
+
     #[test]
     fn test_digest() {
         let exp512 = "cf83e1357eefb8bdf1542850d66d8007d620e4050b5715dc83f4a921d36ce9ce\
@@ -0,0 +1,378 @@
+//! Module for hashing many independent messages at once.
+//!
+//! Messages are hashed in groups of four, with each group's compression functions evaluated
+//! together using AVX2 when the CPU supports it, or NEON on AArch64, where it is part of the
+//! baseline so no detection is needed. On other targets, or on x86 without the `std` feature
+//! needed for runtime feature detection, the scalar implementation is used.
+use super::SHA512;
+use byteorder::{BigEndian, ByteOrder as _};
+use core::convert::TryInto;
+
+const LANES: usize = 4;
+const BLOCK_SIZE: usize = 128;
+
+/// Computes the SHA-512 digest of each message, writing the results to `digests` in order.
+///
+/// This is faster than calling `sha2::sha512` in a loop when hashing many short messages.
+///
+/// # Panics
+///
+/// Panics if `messages.len()` is not equal to `digests.len()`.
+///
+/// # Examples
+///
+/// ```
+/// use crypto_pure::sha2::{multi, sha512};
+/// let messages = [&b"first"[..], b"second", b"third"];
+/// let mut digests = [[0; 64]; 3];
+/// multi::sha512(&messages, &mut digests);
+/// assert_eq!(sha512(b"second")[..], digests[1][..]);
+/// ```
+pub fn sha512(messages: &[&[u8]], digests: &mut [[u8; 64]]) {
+    assert_eq!(messages.len(), digests.len());
+    for (messages, digests) in messages.chunks(LANES).zip(digests.chunks_mut(LANES)) {
+        hash_lanes(messages, digests);
+    }
+}
+
+fn hash_lanes(messages: &[&[u8]], digests: &mut [[u8; 64]]) {
+    let mut states = [SHA512.initial_state; LANES];
+    let mut block_counts = [0; LANES];
+    for (count, message) in block_counts.iter_mut().zip(messages) {
+        *count = (message.len() + 2 * 8 + 1).div_ceil(BLOCK_SIZE);
+    }
+    let max_count = block_counts.iter().cloned().max().unwrap_or(0);
+    let mut padded_blocks = [[0; BLOCK_SIZE]; LANES];
+    for i in 0..max_count {
+        let start = i * BLOCK_SIZE;
+        for ((block, message), &count) in padded_blocks.iter_mut().zip(messages).zip(&block_counts)
+        {
+            if i < count && start + BLOCK_SIZE > message.len() {
+                padded_block(block, message, i, count);
+            }
+        }
+        // Full blocks are read from the messages in place, and only padded ones are copied.
+        let blocks = core::array::from_fn(|lane| match messages.get(lane) {
+            Some(message) if start + BLOCK_SIZE <= message.len() => {
+                message[start..start + BLOCK_SIZE].try_into().unwrap()
+            }
+            _ => &padded_blocks[lane],
+        });
+        let mut next_states = states;
+        compress(&mut next_states, &blocks);
+        for ((state, next_state), &count) in states.iter_mut().zip(&next_states).zip(&block_counts)
+        {
+            if i < count {
+                *state = *next_state;
+            }
+        }
+    }
+    for (digest, state) in digests.iter_mut().zip(&states) {
+        BigEndian::write_u64_into(state, digest);
+    }
+}
+
+/// Writes block `i` of `count` of a padded message, given that it is not a full block of the
+/// message itself.
+fn padded_block(block: &mut [u8; BLOCK_SIZE], message: &[u8], i: usize, count: usize) {
+    let start = i * BLOCK_SIZE;
+    *block = [0; BLOCK_SIZE];
+    if start <= message.len() {
+        let remaining = &message[start..];
+        block[..remaining.len()].copy_from_slice(remaining);
+        block[remaining.len()] = 0x80;
+    }
+    if i + 1 == count {
+        BigEndian::write_u128(&mut block[BLOCK_SIZE - 16..], 8 * message.len() as u128);
+    }
+}
+
+fn compress(states: &mut [[u64; 8]; LANES], blocks: &[&[u8; BLOCK_SIZE]; LANES]) {
+    #[cfg(all(target_arch = "aarch64", target_feature = "neon"))]
+    {
+        unsafe { neon::compress(states, blocks) };
+    }
+    #[cfg(not(all(target_arch = "aarch64", target_feature = "neon")))]
+    {
+        #[cfg(all(feature = "std", any(target_arch = "x86", target_arch = "x86_64")))]
+        {
+            if is_x86_feature_detected!("avx2") {
+                unsafe { avx2::compress(states, blocks) };
+                return;
+            }
+        }
+        compress_scalar(states, blocks);
+    }
+}
+
+#[cfg(any(test, not(all(target_arch = "aarch64", target_feature = "neon"))))]
+fn compress_scalar(states: &mut [[u64; 8]; LANES], blocks: &[&[u8; BLOCK_SIZE]; LANES]) {
+    use super::Processor512;
+    for (state, block) in states.iter_mut().zip(blocks) {
+        Processor512::process(state, *block);
+    }
+}
+
+#[cfg(all(feature = "std", any(target_arch = "x86", target_arch = "x86_64")))]
+mod avx2 {
+    use super::{BLOCK_SIZE, LANES};
+    use crate::sha2::K512;
+    use byteorder::{BigEndian, ByteOrder as _};
+    #[cfg(target_arch = "x86")]
+    use core::arch::x86::*;
+    #[cfg(target_arch = "x86_64")]
+    use core::arch::x86_64::*;
+
+    macro_rules! rotr {
+        ($x:expr, $n:expr) => {
+            _mm256_or_si256(_mm256_srli_epi64($x, $n), _mm256_slli_epi64($x, 64 - $n))
+        };
+    }
+
+    macro_rules! add {
+        ($x:expr $(, $rest:expr)*) => {{
+            let sum = $x;
+            $(let sum = _mm256_add_epi64(sum, $rest);)*
+            sum
+        }};
+    }
+
+    #[target_feature(enable = "avx2")]
+    pub(super) unsafe fn compress(
+        states: &mut [[u64; 8]; LANES],
+        blocks: &[&[u8; BLOCK_SIZE]; LANES],
+    ) {
+        let mut w = [_mm256_setzero_si256(); 80];
+        for (t, w_t) in w.iter_mut().enumerate().take(16) {
+            let word = |lane: usize| BigEndian::read_u64(&blocks[lane][8 * t..]) as i64;
+            *w_t = _mm256_set_epi64x(word(3), word(2), word(1), word(0));
+        }
+        for t in 16..80 {
+            w[t] = add!(ssig1(w[t - 2]), w[t - 7], ssig0(w[t - 15]), w[t - 16]);
+        }
+
+        let mut v = [_mm256_setzero_si256(); 8];
+        for (i, v_i) in v.iter_mut().enumerate() {
+            let word = |lane: usize| states[lane][i] as i64;
+            *v_i = _mm256_set_epi64x(word(3), word(2), word(1), word(0));
+        }
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = v;
+        for (&kt, &wt) in K512.iter().zip(w.iter()) {
+            let t1 = add!(h, bsig1(e), ch(e, f, g), _mm256_set1_epi64x(kt as i64), wt);
+            let t2 = add!(bsig0(a), maj(a, b, c));
+            h = g;
+            g = f;
+            f = e;
+            e = add!(d, t1);
+            d = c;
+            c = b;
+            b = a;
+            a = add!(t1, t2);
+        }
+
+        for (i, &x) in [a, b, c, d, e, f, g, h].iter().enumerate() {
+            let mut lanes = [0u64; LANES];
+            _mm256_storeu_si256(lanes.as_mut_ptr() as *mut __m256i, add!(v[i], x));
+            for (state, &lane) in states.iter_mut().zip(&lanes) {
+                state[i] = lane;
+            }
+        }
+    }
+
+    #[inline]
+    #[target_feature(enable = "avx2")]
+    unsafe fn ch(x: __m256i, y: __m256i, z: __m256i) -> __m256i {
+        _mm256_xor_si256(_mm256_and_si256(x, y), _mm256_andnot_si256(x, z))
+    }
+
+    #[inline]
+    #[target_feature(enable = "avx2")]
+    unsafe fn maj(x: __m256i, y: __m256i, z: __m256i) -> __m256i {
+        _mm256_xor_si256(
+            _mm256_xor_si256(_mm256_and_si256(x, y), _mm256_and_si256(x, z)),
+            _mm256_and_si256(y, z),
+        )
+    }
+
+    #[inline]
+    #[target_feature(enable = "avx2")]
+    unsafe fn bsig0(x: __m256i) -> __m256i {
+        _mm256_xor_si256(_mm256_xor_si256(rotr!(x, 28), rotr!(x, 34)), rotr!(x, 39))
+    }
+
+    #[inline]
+    #[target_feature(enable = "avx2")]
+    unsafe fn bsig1(x: __m256i) -> __m256i {
+        _mm256_xor_si256(_mm256_xor_si256(rotr!(x, 14), rotr!(x, 18)), rotr!(x, 41))
+    }
+
+    #[inline]
+    #[target_feature(enable = "avx2")]
+    unsafe fn ssig0(x: __m256i) -> __m256i {
+        _mm256_xor_si256(
+            _mm256_xor_si256(rotr!(x, 1), rotr!(x, 8)),
+            _mm256_srli_epi64(x, 7),
+        )
+    }
+
+    #[inline]
+    #[target_feature(enable = "avx2")]
+    unsafe fn ssig1(x: __m256i) -> __m256i {
+        _mm256_xor_si256(
+            _mm256_xor_si256(rotr!(x, 19), rotr!(x, 61)),
+            _mm256_srli_epi64(x, 6),
+        )
+    }
+}
+
+#[cfg(all(target_arch = "aarch64", target_feature = "neon"))]
+mod neon {
+    use super::{BLOCK_SIZE, LANES};
+    use crate::sha2::K512;
+    use byteorder::{BigEndian, ByteOrder as _};
+    use core::arch::aarch64::*;
+
+    macro_rules! rotr {
+        ($x:expr, $n:expr) => {
+            vsriq_n_u64::<$n>(vshlq_n_u64::<{ 64 - $n }>($x), $x)
+        };
+    }
+
+    macro_rules! add {
+        ($x:expr $(, $rest:expr)*) => {{
+            let sum = $x;
+            $(let sum = vaddq_u64(sum, $rest);)*
+            sum
+        }};
+    }
+
+    /// Evaluates the four compression functions as two pairs, since a NEON register holds two
+    /// 64-bit lanes.
+    #[target_feature(enable = "neon")]
+    pub(super) unsafe fn compress(
+        states: &mut [[u64; 8]; LANES],
+        blocks: &[&[u8; BLOCK_SIZE]; LANES],
+    ) {
+        for (states, blocks) in states.chunks_exact_mut(2).zip(blocks.chunks_exact(2)) {
+            compress_pair(states, blocks);
+        }
+    }
+
+    #[inline]
+    #[target_feature(enable = "neon")]
+    unsafe fn compress_pair(states: &mut [[u64; 8]], blocks: &[&[u8; BLOCK_SIZE]]) {
+        let mut w = [vdupq_n_u64(0); 80];
+        for (t, w_t) in w.iter_mut().enumerate().take(16) {
+            let word = |lane: usize| BigEndian::read_u64(&blocks[lane][8 * t..]);
+            *w_t = vld1q_u64([word(0), word(1)].as_ptr());
+        }
+        for t in 16..80 {
+            w[t] = add!(ssig1(w[t - 2]), w[t - 7], ssig0(w[t - 15]), w[t - 16]);
+        }
+
+        let mut v = [vdupq_n_u64(0); 8];
+        for (i, v_i) in v.iter_mut().enumerate() {
+            *v_i = vld1q_u64([states[0][i], states[1][i]].as_ptr());
+        }
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = v;
+        for (&kt, &wt) in K512.iter().zip(w.iter()) {
+            let t1 = add!(h, bsig1(e), ch(e, f, g), vdupq_n_u64(kt), wt);
+            let t2 = add!(bsig0(a), maj(a, b, c));
+            h = g;
+            g = f;
+            f = e;
+            e = add!(d, t1);
+            d = c;
+            c = b;
+            b = a;
+            a = add!(t1, t2);
+        }
+
+        for (i, &x) in [a, b, c, d, e, f, g, h].iter().enumerate() {
+            let mut lanes = [0u64; 2];
+            vst1q_u64(lanes.as_mut_ptr(), add!(v[i], x));
+            for (state, &lane) in states.iter_mut().zip(&lanes) {
+                state[i] = lane;
+            }
+        }
+    }
+
+    #[inline]
+    #[target_feature(enable = "neon")]
+    unsafe fn ch(x: uint64x2_t, y: uint64x2_t, z: uint64x2_t) -> uint64x2_t {
+        veorq_u64(vandq_u64(x, y), vbicq_u64(z, x))
+    }
+
+    #[inline]
+    #[target_feature(enable = "neon")]
+    unsafe fn maj(x: uint64x2_t, y: uint64x2_t, z: uint64x2_t) -> uint64x2_t {
+        veorq_u64(veorq_u64(vandq_u64(x, y), vandq_u64(x, z)), vandq_u64(y, z))
+    }
+
+    #[inline]
+    #[target_feature(enable = "neon")]
+    unsafe fn bsig0(x: uint64x2_t) -> uint64x2_t {
+        veorq_u64(veorq_u64(rotr!(x, 28), rotr!(x, 34)), rotr!(x, 39))
+    }
+
+    #[inline]
+    #[target_feature(enable = "neon")]
+    unsafe fn bsig1(x: uint64x2_t) -> uint64x2_t {
+        veorq_u64(veorq_u64(rotr!(x, 14), rotr!(x, 18)), rotr!(x, 41))
+    }
+
+    #[inline]
+    #[target_feature(enable = "neon")]
+    unsafe fn ssig0(x: uint64x2_t) -> uint64x2_t {
+        veorq_u64(veorq_u64(rotr!(x, 1), rotr!(x, 8)), vshrq_n_u64::<7>(x))
+    }
+
+    #[inline]
+    #[target_feature(enable = "neon")]
+    unsafe fn ssig1(x: uint64x2_t) -> uint64x2_t {
+        veorq_u64(veorq_u64(rotr!(x, 19), rotr!(x, 61)), vshrq_n_u64::<6>(x))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sha2::sha512 as sha512_scalar;
+    use std::vec::Vec;
+
+    fn check(lengths: &[usize]) {
+        let data: Vec<u8> = (0..300).map(|x| x as u8).collect();
+        let messages: Vec<_> = lengths.iter().map(|&len| &data[..len]).collect();
+        let digests = &mut vec![[0; 64]; messages.len()];
+        sha512(&messages, digests);
+        for (message, digest) in messages.iter().zip(digests.iter()) {
+            assert_eq!(sha512_scalar(message)[..], digest[..]);
+        }
+    }
+
+    #[test]
+    fn test_sha512() {
+        check(&[]);
+        check(&[0]);
+        check(&[3, 111, 112, 113]);
+        check(&[127, 128, 129, 239, 240, 300]);
+        check(&[0, 1, 2, 3, 4, 5, 6, 7, 200]);
+        check(&[256, 0, 300, 255, 128, 257]);
+    }
+
+    #[test]
+    fn test_compress() {
+        let mut blocks = [[0; BLOCK_SIZE]; LANES];
+        for (i, block) in blocks.iter_mut().enumerate() {
+            for (j, byte) in block.iter_mut().enumerate() {
+                *byte = (i * j) as u8;
+            }
+        }
+        let blocks = blocks.each_ref();
+        let mut expected = [SHA512.initial_state; LANES];
+        compress_scalar(&mut expected, &blocks);
+        let mut actual = [SHA512.initial_state; LANES];
+        compress(&mut actual, &blocks);
+        assert_eq!(expected, actual);
+    }
+}
@@ -0,0 +1,400 @@
+//! Module for the SHA-3 family of hash functions and the SHAKE extendable-output functions
+//! (FIPS 202), which are built on the Keccak-f\[1600\] permutation.
+use crate::sha2::HashFunction;
+
+/// The SHA3-512 hash function.
+///
+/// # Examples
+///
+/// ```
+/// use crypto_pure::sha2::HashFunction;
+/// use crypto_pure::sha3::Sha3_512;
+/// let mut digest = [0; Sha3_512::DIGEST_SIZE];
+/// let mut sha = Sha3_512::default();
+/// sha.update(b"part one");
+/// sha.update(b"part two");
+/// sha.write_digest(&mut digest);
+/// ```
+#[derive(Clone)]
+pub struct Sha3_512(Sponge);
+
+/// The SHA3-384 hash function.
+#[derive(Clone)]
+pub struct Sha3_384(Sponge);
+
+/// The SHA3-256 hash function.
+#[derive(Clone)]
+pub struct Sha3_256(Sponge);
+
+/// The SHA3-224 hash function.
+#[derive(Clone)]
+pub struct Sha3_224(Sponge);
+
+macro_rules! impl_function {
+    ($function:ident, $digest_size:expr) => {
+        impl Default for $function {
+            fn default() -> Self {
+                $function(Sponge::new(200 - 2 * $digest_size))
+            }
+        }
+
+        impl HashFunction for $function {
+            const DIGEST_SIZE: usize = $digest_size;
+            const BLOCK_SIZE: usize = 200 - 2 * $digest_size;
+
+            fn update(&mut self, input: &[u8]) {
+                self.0.absorb(input);
+            }
+
+            /// Writes the hash function digest into an output buffer.
+            ///
+            /// # Panics
+            ///
+            /// Panics if `output.len()` is not equal to the digest size.
+            fn write_digest(mut self, output: &mut [u8]) {
+                assert_eq!(Self::DIGEST_SIZE, output.len());
+                self.0.pad(SHA3_SUFFIX);
+                self.0.squeeze(output);
+            }
+        }
+    };
+}
+
+impl_function!(Sha3_512, 64);
+impl_function!(Sha3_384, 48);
+impl_function!(Sha3_256, 32);
+impl_function!(Sha3_224, 28);
+
+macro_rules! impl_wrapper {
+    ($function:ident, $message:expr) => {{
+        let mut digest = [0; $function::DIGEST_SIZE];
+        let mut sha = $function::default();
+        sha.update($message);
+        sha.write_digest(&mut digest);
+        digest
+    }};
+}
+
+/// Wrapper for obtaining the SHA3-512 digest for a complete message.
+pub fn sha3_512(message: &[u8]) -> [u8; Sha3_512::DIGEST_SIZE] {
+    impl_wrapper!(Sha3_512, message)
+}
+
+/// Wrapper for obtaining the SHA3-384 digest for a complete message.
+pub fn sha3_384(message: &[u8]) -> [u8; Sha3_384::DIGEST_SIZE] {
+    impl_wrapper!(Sha3_384, message)
+}
+
+/// Wrapper for obtaining the SHA3-256 digest for a complete message.
+pub fn sha3_256(message: &[u8]) -> [u8; Sha3_256::DIGEST_SIZE] {
+    impl_wrapper!(Sha3_256, message)
+}
+
+/// Wrapper for obtaining the SHA3-224 digest for a complete message.
+pub fn sha3_224(message: &[u8]) -> [u8; Sha3_224::DIGEST_SIZE] {
+    impl_wrapper!(Sha3_224, message)
+}
+
+/// The SHAKE128 extendable-output function.
+///
+/// # Examples
+///
+/// ```
+/// use crypto_pure::sha3::Shake128;
+/// let mut shake = Shake128::default();
+/// shake.update(b"part one");
+/// shake.update(b"part two");
+/// let mut reader = shake.finalize_xof();
+/// let output = &mut [0; 100];
+/// reader.read(&mut output[..30]);
+/// reader.read(&mut output[30..]);
+/// ```
+#[derive(Clone)]
+pub struct Shake128(Sponge);
+
+/// The SHAKE256 extendable-output function.
+#[derive(Clone)]
+pub struct Shake256(Sponge);
+
+/// A reader for the output of a SHAKE function, which can be read in any number of pieces.
+#[derive(Clone)]
+pub struct XofReader(Sponge);
+
+macro_rules! impl_xof {
+    ($function:ident, $rate:expr) => {
+        impl Default for $function {
+            fn default() -> Self {
+                $function(Sponge::new($rate))
+            }
+        }
+
+        impl $function {
+            /// Feeds input into the function to update its state.
+            pub fn update(&mut self, input: &[u8]) {
+                self.0.absorb(input);
+            }
+
+            /// Finishes the input and outputs a reader for the output.
+            pub fn finalize_xof(mut self) -> XofReader {
+                self.0.pad(SHAKE_SUFFIX);
+                XofReader(self.0)
+            }
+        }
+    };
+}
+
+impl_xof!(Shake128, 168);
+impl_xof!(Shake256, 136);
+
+impl XofReader {
+    /// Fills `output` with the next bytes of output.
+    pub fn read(&mut self, output: &mut [u8]) {
+        self.0.squeeze(output);
+    }
+}
+
+/// Wrapper for filling `output` with the SHAKE128 output for a complete message.
+pub fn shake128(message: &[u8], output: &mut [u8]) {
+    let mut shake = Shake128::default();
+    shake.update(message);
+    shake.finalize_xof().read(output);
+}
+
+/// Wrapper for filling `output` with the SHAKE256 output for a complete message.
+pub fn shake256(message: &[u8], output: &mut [u8]) {
+    let mut shake = Shake256::default();
+    shake.update(message);
+    shake.finalize_xof().read(output);
+}
+
+const SHA3_SUFFIX: u8 = 0x06;
+const SHAKE_SUFFIX: u8 = 0x1f;
+
+/// The Keccak sponge with a rate of `rate` bytes, first absorbing input and then, once padded,
+/// squeezing output.
+#[derive(Clone)]
+struct Sponge {
+    state: [u64; 25],
+    rate: usize,
+    offset: usize,
+}
+
+impl Sponge {
+    fn new(rate: usize) -> Self {
+        Self {
+            state: [0; 25],
+            rate,
+            offset: 0,
+        }
+    }
+
+    fn absorb(&mut self, input: &[u8]) {
+        for byte in input {
+            self.xor_byte(self.offset, *byte);
+            self.offset += 1;
+            if self.offset == self.rate {
+                keccak_f(&mut self.state);
+                self.offset = 0;
+            }
+        }
+    }
+
+    /// Appends the domain separation suffix and the pad10*1 padding, permuting the last block.
+    fn pad(&mut self, suffix: u8) {
+        self.xor_byte(self.offset, suffix);
+        self.xor_byte(self.rate - 1, 0x80);
+        keccak_f(&mut self.state);
+        self.offset = 0;
+    }
+
+    fn squeeze(&mut self, output: &mut [u8]) {
+        for byte in output {
+            if self.offset == self.rate {
+                keccak_f(&mut self.state);
+                self.offset = 0;
+            }
+            *byte = (self.state[self.offset / 8] >> (8 * (self.offset % 8))) as u8;
+            self.offset += 1;
+        }
+    }
+
+    fn xor_byte(&mut self, i: usize, byte: u8) {
+        self.state[i / 8] ^= u64::from(byte) << (8 * (i % 8));
+    }
+}
+
+const ROUND_CONSTANTS: [u64; 24] = [
+    0x0000_0000_0000_0001,
+    0x0000_0000_0000_8082,
+    0x8000_0000_0000_808a,
+    0x8000_0000_8000_8000,
+    0x0000_0000_0000_808b,
+    0x0000_0000_8000_0001,
+    0x8000_0000_8000_8081,
+    0x8000_0000_0000_8009,
+    0x0000_0000_0000_008a,
+    0x0000_0000_0000_0088,
+    0x0000_0000_8000_8009,
+    0x0000_0000_8000_000a,
+    0x0000_0000_8000_808b,
+    0x8000_0000_0000_008b,
+    0x8000_0000_0000_8089,
+    0x8000_0000_0000_8003,
+    0x8000_0000_0000_8002,
+    0x8000_0000_0000_0080,
+    0x0000_0000_0000_800a,
+    0x8000_0000_8000_000a,
+    0x8000_0000_8000_8081,
+    0x8000_0000_0000_8080,
+    0x0000_0000_8000_0001,
+    0x8000_0000_8000_8008,
+];
+
+/// The rotation offsets and destinations of the combined rho and pi steps, following the lanes
+/// in the order that pi moves them, starting from lane 1.
+const ROTATIONS: [u32; 24] = [
+    1, 3, 6, 10, 15, 21, 28, 36, 45, 55, 2, 14, 27, 41, 56, 8, 25, 43, 62, 18, 39, 61, 20, 44,
+];
+const PI_LANES: [usize; 24] = [
+    10, 7, 11, 17, 18, 3, 5, 16, 8, 21, 24, 4, 15, 23, 19, 13, 12, 2, 20, 14, 22, 9, 6, 1,
+];
+
+/// The Keccak-f\[1600\] permutation, where lane (x, y) is `state[x + 5 * y]`.
+pub(crate) fn keccak_f(state: &mut [u64; 25]) {
+    for round_constant in &ROUND_CONSTANTS {
+        // theta
+        let mut parity = [0; 5];
+        for (x, p) in parity.iter_mut().enumerate() {
+            *p = state[x] ^ state[x + 5] ^ state[x + 10] ^ state[x + 15] ^ state[x + 20];
+        }
+        for x in 0..5 {
+            let d = parity[(x + 4) % 5] ^ parity[(x + 1) % 5].rotate_left(1);
+            for y in 0..5 {
+                state[x + 5 * y] ^= d;
+            }
+        }
+
+        // rho and pi
+        let mut lane = state[1];
+        for (&rotation, &destination) in ROTATIONS.iter().zip(&PI_LANES) {
+            let next = state[destination];
+            state[destination] = lane.rotate_left(rotation);
+            lane = next;
+        }
+
+        // chi
+        for y in 0..5 {
+            let row = [
+                state[5 * y],
+                state[5 * y + 1],
+                state[5 * y + 2],
+                state[5 * y + 3],
+                state[5 * y + 4],
+            ];
+            for x in 0..5 {
+                state[x + 5 * y] = row[x] ^ (!row[(x + 1) % 5] & row[(x + 2) % 5]);
+            }
+        }
+
+        // iota
+        state[0] ^= round_constant;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hmac::Hmac;
+    use crate::test_helpers::*;
+    use std::vec::Vec;
+
+    const MESSAGE: &[u8] = b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq";
+
+    fn check<H: HashFunction>(message: &[u8], expected: &str) {
+        let expected = &h2b(expected);
+        for chunk_size in &[1, 7, 135, 136, 137, 1000] {
+            let mut sha = H::default();
+            for chunk in message.chunks(*chunk_size) {
+                sha.update(chunk);
+            }
+            let digest = &mut vec![0; H::DIGEST_SIZE];
+            sha.write_digest(digest);
+            assert_eq!(expected, digest);
+        }
+    }
+
+    #[test]
+    fn test_sha3() {
+        let long: &Vec<_> = &(0..1000).map(|i| i as u8).collect();
+        check::<Sha3_224>(
+            b"",
+            "6b4e03423667dbb73b6e15454f0eb1abd4597f9a1b078e3f5b5a6bc7",
+        );
+        check::<Sha3_224>(
+            MESSAGE,
+            "8a24108b154ada21c9fd5574494479ba5c7e7ab76ef264ead0fcce33",
+        );
+        check::<Sha3_256>(
+            b"",
+            "a7ffc6f8bf1ed76651c14756a061d662f580ff4de43b49fa82d80a4b80f8434a",
+        );
+        check::<Sha3_256>(
+            MESSAGE,
+            "41c0dba2a9d6240849100376a8235e2c82e1b9998a999e21db32dd97496d3376",
+        );
+        check::<Sha3_256>(
+            long,
+            "14e5de35911194ddad95ac1572e2b6ce054ed2146cd0562280fcab04ccfecbd8",
+        );
+        check::<Sha3_384>(
+            MESSAGE,
+            "991c665755eb3a4b6bbdfb75c78a492e8c56a22c5c4d7e429bfdbc32b9d4ad5a\
+             a04a1f076e62fea19eef51acd0657c22",
+        );
+        check::<Sha3_512>(
+            MESSAGE,
+            "04a371e84ecfb5b8b77cb48610fca8182dd457ce6f326a0fd3d7ec2f1e91636d\
+             ee691fbe0c985302ba1b0d8dc78c086346b533b49c030d99a27daf1139d6e75e",
+        );
+        assert_eq!(h2b("a69f73cca23a9ac5c8b567dc185a756e97c982164fe25859e0d1dcc1475c80a615b2123af1f5f94c11e3e9402c3ac558f500199d95b6d3e301758586281dcd26"), &sha3_512(b"")[..]);
+    }
+
+    #[test]
+    fn test_shake() {
+        let output = &mut [0; 32];
+        shake128(b"", output);
+        let expected = "7f9c2ba4e88f827d616045507605853ed73b8093f6efbc88eb1a6eacfa66ef26";
+        assert_eq!(&h2b(expected), output);
+        let output = &mut [0; 64];
+        shake256(b"", output);
+        let expected = "46b9dd2b0ba88d13233b3feb743eeb243fcd52ea62b81b82b50c27646ed5762f\
+                        d75dc4ddd8c0f200cb05019d67b592f6fc821c49479ab48640292eacb3b7c4be";
+        assert_eq!(&h2b(expected), output);
+
+        let long: &Vec<_> = &(0..1000).map(|i| i as u8).collect();
+        let expected = &mut vec![0; 500];
+        shake256(long, expected);
+        let tail = "266b5bb1fdc7f9633c4b834baab078a86135375ae5849e7151c8004635275e71";
+        assert_eq!(h2b(tail), &expected[468..]);
+        for chunk_size in &[1, 7, 135, 136, 137, 1000] {
+            let mut shake = Shake256::default();
+            for chunk in long.chunks(*chunk_size) {
+                shake.update(chunk);
+            }
+            let mut reader = shake.finalize_xof();
+            let output = &mut vec![0; 500];
+            for chunk in output.chunks_mut(*chunk_size) {
+                reader.read(chunk);
+            }
+            assert_eq!(expected, output);
+        }
+    }
+
+    #[test]
+    fn test_hmac_sha3() {
+        let mut hmac = Hmac::<Sha3_256>::new(b"key");
+        hmac.update(b"The quick brown fox jumps over the lazy dog");
+        let expected = "8c6e0683409427f8931711b10ca92a506eb1fafa48fadd66d76126f47ac2c333";
+        assert_eq!(h2b(expected), &hmac.tag()[..]);
+    }
+}
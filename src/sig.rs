@@ -0,0 +1,225 @@
+//! Module for the `Signer` and `Verifier` traits shared by signature schemes.
+//!
+//! Each signature scheme in the crate is implemented by a type, such as `ed25519::Ed25519`,
+//! `p256::EcdsaP256`, `rsa::Pss<Sha256>` or `mldsa::mldsa65::MlDsa65`, so that protocols can
+//! sign and verify generically. Unlike the free functions of each module, the trait methods check
+//! the lengths of keys and report every failure as an `Error` instead of panicking.
+//!
+//! Schemes such as Ed448, ML-DSA and SLH-DSA can bind a signature to a context string, up to
+//! `MAX_CONTEXT_LEN` bytes long, so that it can't be verified under another context. The other
+//! schemes only accept the empty context.
+//!
+//! # Examples
+//!
+//! ```
+//! use crypto_pure::ed25519::Ed25519;
+//! use crypto_pure::error::Error;
+//! use crypto_pure::rand::SystemRandom;
+//! use crypto_pure::sig::{Signer, Verifier};
+//! fn round_trip<S: Signer<SecretKey = [u8], PublicKey = [u8]>>(
+//!     secret_key: &[u8],
+//!     public_key: &[u8],
+//! ) -> Result<(), Error> {
+//!     let signature = S::sign(b"message", b"", secret_key, &SystemRandom::new())?;
+//!     S::verify(b"message", b"", signature.as_ref(), public_key)
+//! }
+//! # let secret_key = &[0x42; 32];
+//! let public_key = &crypto_pure::ed25519::gen_pk(secret_key)?;
+//! round_trip::<Ed25519>(secret_key, public_key)?;
+//! # Ok::<(), Error>(())
+//! ```
+use crate::error::Error;
+use crate::rand::SecureRandom;
+
+/// A signature scheme that verifies signatures under public keys.
+pub trait Verifier {
+    /// The maximum length of a context string, which is 0 if the scheme doesn't support them.
+    const MAX_CONTEXT_LEN: usize;
+
+    /// A public key, which is an encoding for most schemes.
+    type PublicKey: ?Sized;
+
+    /// Verifies whether a message was signed under a context using the given public key.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::InvalidLength` if `context.len()` is greater than `MAX_CONTEXT_LEN`,
+    /// with `Error::InvalidKey` if the encoded public key has the wrong length, or with
+    /// `Error::InvalidSignature` if the signature is not valid.
+    fn verify(
+        message: &[u8],
+        context: &[u8],
+        signature: &[u8],
+        public_key: &Self::PublicKey,
+    ) -> Result<(), Error>;
+}
+
+/// A signature scheme that also signs messages with secret keys.
+pub trait Signer: Verifier {
+    /// A secret key, which is an encoding for most schemes.
+    type SecretKey: ?Sized;
+    /// A signature.
+    type Signature: AsRef<[u8]>;
+
+    /// Signs a message under a context. Any randomness the scheme needs is drawn from `rng`.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::InvalidLength` if `context.len()` is greater than `MAX_CONTEXT_LEN`, or
+    /// with `Error::InvalidKey` if the secret key is not valid.
+    fn sign(
+        message: &[u8],
+        context: &[u8],
+        secret_key: &Self::SecretKey,
+        rng: &dyn SecureRandom,
+    ) -> Result<Self::Signature, Error>;
+}
+
+/// Checks the length of a context for a trait method.
+pub(crate) fn check_context<S: Verifier + ?Sized>(context: &[u8]) -> Result<(), Error> {
+    if context.len() > S::MAX_CONTEXT_LEN {
+        return Err(Error::InvalidLength);
+    }
+    Ok(())
+}
+
+/// Checks the lengths of a context and an encoded key for a trait method.
+pub(crate) fn check_lengths<S: Verifier + ?Sized>(
+    context: &[u8],
+    key: &[u8],
+    key_len: usize,
+) -> Result<(), Error> {
+    check_context::<S>(context)?;
+    if key.len() != key_len {
+        return Err(Error::InvalidKey);
+    }
+    Ok(())
+}
+
+/// Converts the outcome of a verification into a result.
+pub(crate) fn check_valid(valid: bool) -> Result<(), Error> {
+    if valid {
+        Ok(())
+    } else {
+        Err(Error::InvalidSignature)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ed25519::{self, Ed25519};
+    use crate::ed448::{self, Ed448};
+    use crate::mldsa::mldsa44::{self, MlDsa44};
+    use crate::mldsa::mldsa65::{self, MlDsa65};
+    use crate::mldsa::mldsa87::{self, MlDsa87};
+    use crate::p256::{self, EcdsaP256};
+    use crate::p384::{self, EcdsaP384};
+    use crate::rand::SystemRandom;
+    use crate::schnorr::bip340::{self, Bip340};
+    use crate::schnorr::ristretto255::{self, SchnorrRistretto255};
+    use crate::secp256k1::{self, EcdsaSecp256k1};
+    use crate::slhdsa::sha2_128f::{self, SlhDsaSha2_128f};
+
+    fn check_scheme<S: Signer<SecretKey = [u8], PublicKey = [u8]>>(
+        secret_key: &[u8],
+        public_key: &[u8],
+    ) {
+        let rng = &SystemRandom::new();
+        let context = &[0x42; 256][..S::MAX_CONTEXT_LEN];
+        let signature = S::sign(b"message", context, secret_key, rng).unwrap();
+        let signature = signature.as_ref();
+        assert_eq!(
+            Ok(()),
+            S::verify(b"message", context, signature, public_key)
+        );
+        assert_eq!(
+            Err(Error::InvalidSignature),
+            S::verify(b"other message", context, signature, public_key)
+        );
+        assert_eq!(
+            Err(Error::InvalidSignature),
+            S::verify(b"message", context, &signature[1..], public_key)
+        );
+        if !context.is_empty() {
+            assert_eq!(
+                Err(Error::InvalidSignature),
+                S::verify(b"message", &context[1..], signature, public_key)
+            );
+        }
+
+        let long_context = &[0x42; 256][..=S::MAX_CONTEXT_LEN];
+        assert_eq!(
+            Err(Error::InvalidLength),
+            S::sign(b"message", long_context, secret_key, rng).map(|_| ())
+        );
+        assert_eq!(
+            Err(Error::InvalidLength),
+            S::verify(b"message", long_context, signature, public_key)
+        );
+        assert_eq!(
+            Err(Error::InvalidKey),
+            S::sign(b"message", context, &secret_key[1..], rng).map(|_| ())
+        );
+        assert_eq!(
+            Err(Error::InvalidKey),
+            S::verify(b"message", context, signature, &public_key[1..])
+        );
+    }
+
+    #[test]
+    fn test_schemes() {
+        let rng = &SystemRandom::new();
+        let secret_key = &[0x42; 57];
+        let public_key = &ed25519::gen_pk(&secret_key[..32]).unwrap();
+        check_scheme::<Ed25519>(&secret_key[..32], public_key);
+        check_scheme::<Ed448>(secret_key, &ed448::gen_pk(secret_key).unwrap());
+        let public_key = &p256::gen_pk(&secret_key[..32]).unwrap();
+        check_scheme::<EcdsaP256>(&secret_key[..32], public_key);
+        let public_key = &p384::gen_pk(&secret_key[..48]).unwrap();
+        check_scheme::<EcdsaP384>(&secret_key[..48], public_key);
+        let public_key = &secp256k1::gen_pk(&secret_key[..32]).unwrap();
+        check_scheme::<EcdsaSecp256k1>(&secret_key[..32], public_key);
+        let public_key = &bip340::gen_pk(&secret_key[..32]).unwrap();
+        check_scheme::<Bip340>(&secret_key[..32], public_key);
+        let secret_key = &ristretto255::gen_sk(rng);
+        let public_key = &ristretto255::gen_pk(secret_key).unwrap();
+        check_scheme::<SchnorrRistretto255>(secret_key, public_key);
+
+        let (secret_key, public_key) = mldsa44::gen_key_pair(rng);
+        check_scheme::<MlDsa44>(&secret_key, &public_key);
+        let (secret_key, public_key) = mldsa65::gen_key_pair(rng);
+        check_scheme::<MlDsa65>(&secret_key, &public_key);
+        let (secret_key, public_key) = mldsa87::gen_key_pair(rng);
+        check_scheme::<MlDsa87>(&secret_key, &public_key);
+        let (secret_key, public_key) = sha2_128f::gen_key_pair(rng);
+        check_scheme::<SlhDsaSha2_128f>(&secret_key, &public_key);
+    }
+
+    #[test]
+    fn test_invalid_secret_keys() {
+        let rng = &SystemRandom::new();
+        for secret_key in &[[0; 32], [0xff; 32]] {
+            assert_eq!(
+                Err(Error::InvalidKey),
+                EcdsaP256::sign(b"message", b"", secret_key, rng).map(|_| ())
+            );
+            assert_eq!(
+                Err(Error::InvalidKey),
+                EcdsaSecp256k1::sign(b"message", b"", secret_key, rng).map(|_| ())
+            );
+            assert_eq!(
+                Err(Error::InvalidKey),
+                Bip340::sign(b"message", b"", secret_key, rng).map(|_| ())
+            );
+            assert_eq!(
+                Err(Error::InvalidKey),
+                SchnorrRistretto255::sign(b"message", b"", secret_key, rng).map(|_| ())
+            );
+        }
+        assert_eq!(
+            Err(Error::InvalidKey),
+            EcdsaP384::sign(b"message", b"", &[0; 48], rng).map(|_| ())
+        );
+    }
+}
@@ -0,0 +1,629 @@
+//! Module for SLH-DSA (FIPS 205), the stateless hash-based signature scheme standardized from
+//! SPHINCS+, with the SHA2 parameter sets at security category 1.
+//!
+//! SLH-DSA relies only on the security of its hash function, which makes it the conservative
+//! choice among post-quantum signatures, at the cost of large signatures and slow signing. A
+//! signature is a FORS few-time signature of the message digest, authenticated by a hypertree of
+//! WOTS+ one-time signatures up to the root in the public key. The "s" parameter sets give
+//! smaller signatures and the "f" ones faster signing. As in `mldsa`, signing takes a context
+//! string of at most 255 bytes and is hedged by default.
+//!
+//! # Examples
+//!
+//! ```
+//! use crypto_pure::rand::SystemRandom;
+//! use crypto_pure::slhdsa::sha2_128f::{gen_key_pair, sign, verify};
+//! let rng = &SystemRandom::new();
+//! let (secret_key, public_key) = gen_key_pair(rng);
+//! let signature = &sign(b"message", b"context", &secret_key, rng)?;
+//! assert!(verify(b"message", b"context", signature, &public_key));
+//! assert!(!verify(b"message", b"other context", signature, &public_key));
+//! # Ok::<(), crypto_pure::error::Error>(())
+//! ```
+use crate::ct;
+use crate::error::Error;
+use crate::hmac::Hmac;
+use crate::mgf1::mgf1;
+use crate::sha2::{HashFunction, Sha256};
+
+pub mod sha2_128f;
+pub mod sha2_128s;
+
+/// The length of a seed SK.seed || SK.prf || PK.seed for deterministic key generation.
+pub const SEED_LEN: usize = 3 * N;
+
+/// The security parameter n, the length of every hash output.
+const N: usize = 16;
+
+/// The Winternitz parameter w.
+const W: usize = 16;
+
+/// The number of WOTS+ chains for the message, len_1.
+const LEN_1: usize = 2 * N;
+
+/// The number of WOTS+ chains, len_1 + len_2, where len_2 = 3 chains cover the checksum.
+const LEN: usize = LEN_1 + 3;
+
+// Address types
+const WOTS_HASH: u32 = 0;
+const WOTS_PK: u32 = 1;
+const TREE: u32 = 2;
+const FORS_TREE: u32 = 3;
+const FORS_ROOTS: u32 = 4;
+const WOTS_PRF: u32 = 5;
+const FORS_PRF: u32 = 6;
+
+/// The parameters of an SLH-DSA parameter set with n = 16 and w = 16.
+pub(crate) struct Params {
+    /// The total height h of the hypertree.
+    pub(crate) h: usize,
+    /// The number d of layers of the hypertree.
+    pub(crate) d: usize,
+    /// The height a of each FORS tree.
+    pub(crate) a: usize,
+    /// The number k of FORS trees.
+    pub(crate) k: usize,
+}
+
+impl Params {
+    /// The height h' of each XMSS tree.
+    fn h_prime(&self) -> usize {
+        self.h / self.d
+    }
+
+    fn signature_len(&self) -> usize {
+        N + self.k * (1 + self.a) * N + (self.h + self.d * LEN) * N
+    }
+}
+
+/// Writes the secret key and the public key derived from a seed SK.seed || SK.prf || PK.seed, as
+/// in FIPS 205, Algorithm 18.
+pub(crate) fn derive_key_pair(
+    params: &Params,
+    seed: &[u8; SEED_LEN],
+    secret_key: &mut [u8],
+    public_key: &mut [u8],
+) {
+    let (sk_seed, pk_seed) = (&seed[..N], &seed[2 * N..]);
+    let hash = Hash::new(pk_seed, sk_seed);
+    let mut adrs = Address::default();
+    adrs.set_layer(params.d - 1);
+    let pk_root = xmss_node(&hash, 0, params.h_prime(), &mut adrs);
+    secret_key[..SEED_LEN].copy_from_slice(seed);
+    secret_key[SEED_LEN..].copy_from_slice(&pk_root);
+    public_key.copy_from_slice(&secret_key[2 * N..]);
+}
+
+/// Writes the signature of a message under a context with n bytes of randomness opt_rand, as in
+/// FIPS 205, Algorithms 19 and 22, after checking the lengths of the secret key and the context.
+pub(crate) fn sign(
+    params: &Params,
+    message: &[u8],
+    context: &[u8],
+    secret_key: &[u8],
+    opt_rand: &[u8],
+    signature: &mut [u8],
+) -> Result<(), Error> {
+    if secret_key.len() != 4 * N {
+        return Err(Error::InvalidKey);
+    }
+    if context.len() > 255 {
+        return Err(Error::InvalidLength);
+    }
+    let (sk_seed, rest) = secret_key.split_at(N);
+    let (sk_prf, public_key) = rest.split_at(N);
+    let prefix = [0, context.len() as u8];
+
+    let mut prf_msg = Hmac::<Sha256>::new(sk_prf);
+    prf_msg.update(opt_rand);
+    prf_msg.update(&prefix);
+    prf_msg.update(context);
+    prf_msg.update(message);
+    let (r, rest) = signature.split_at_mut(N);
+    r.copy_from_slice(&prf_msg.tag()[..N]);
+    let (md, idx_tree, idx_leaf) =
+        hash_message(params, r, public_key, &[&prefix, context, message]);
+
+    let hash = Hash::new(&public_key[..N], sk_seed);
+    let mut adrs = Address::default();
+    adrs.set_tree(idx_tree);
+    adrs.set_type_and_clear(FORS_TREE);
+    adrs.set_key_pair(idx_leaf);
+    let (fors_signature, ht_signature) = rest.split_at_mut(params.k * (1 + params.a) * N);
+    let indices = &base_2b(&md, params.a, params.k)[..params.k];
+    fors_sign(params, &hash, indices, &mut adrs, fors_signature);
+    let fors_pk = fors_pk_from_sig(params, &hash, fors_signature, indices, &mut adrs);
+    ht_sign(params, &hash, &fors_pk, idx_tree, idx_leaf, ht_signature);
+    Ok(())
+}
+
+/// Checks whether a signature of a message under a context is valid, as in FIPS 205,
+/// Algorithms 20 and 24. Outputs `false` if the signature or public key has the wrong length.
+pub(crate) fn verify(
+    params: &Params,
+    message: &[u8],
+    context: &[u8],
+    signature: &[u8],
+    public_key: &[u8],
+) -> bool {
+    if public_key.len() != 2 * N || context.len() > 255 || signature.len() != params.signature_len()
+    {
+        return false;
+    }
+    let (r, rest) = signature.split_at(N);
+    let prefix = [0, context.len() as u8];
+    let (md, idx_tree, idx_leaf) =
+        hash_message(params, r, public_key, &[&prefix, context, message]);
+
+    let hash = Hash::new(&public_key[..N], &[]);
+    let mut adrs = Address::default();
+    adrs.set_tree(idx_tree);
+    adrs.set_type_and_clear(FORS_TREE);
+    adrs.set_key_pair(idx_leaf);
+    let (fors_signature, ht_signature) = rest.split_at(params.k * (1 + params.a) * N);
+    let indices = &base_2b(&md, params.a, params.k)[..params.k];
+    let fors_pk = fors_pk_from_sig(params, &hash, fors_signature, indices, &mut adrs);
+    ht_verify(
+        params,
+        &hash,
+        &fors_pk,
+        ht_signature,
+        idx_tree,
+        idx_leaf,
+        &public_key[N..],
+    )
+}
+
+/// Computes H_msg(R, PK.seed, PK.root, M') and splits it into the FORS message digest, the index
+/// of the XMSS tree and the index of the leaf, as in FIPS 205, Algorithm 19, lines 7 to 13.
+fn hash_message(
+    params: &Params,
+    r: &[u8],
+    public_key: &[u8],
+    message: &[&[u8]],
+) -> ([u8; 32], u64, usize) {
+    let mut sha = Sha256::default();
+    sha.update(r);
+    sha.update(public_key);
+    for part in message {
+        sha.update(part);
+    }
+    let mut seed = [0; 2 * N + 32];
+    seed[..N].copy_from_slice(r);
+    seed[N..2 * N].copy_from_slice(&public_key[..N]);
+    sha.write_digest(&mut seed[2 * N..]);
+
+    let md_len = (params.k * params.a).div_ceil(8);
+    let tree_bits = params.h - params.h_prime();
+    let tree_len = tree_bits.div_ceil(8);
+    let leaf_len = params.h_prime().div_ceil(8);
+    let mut digest = [0; 64];
+    let digest = &mut digest[..md_len + tree_len + leaf_len];
+    mgf1::<Sha256>(&seed, digest);
+    let (md_bytes, rest) = digest.split_at(md_len);
+    let (tree_bytes, leaf_bytes) = rest.split_at(tree_len);
+    let mut md = [0; 32];
+    md[..md_len].copy_from_slice(md_bytes);
+    let to_int = |bytes: &[u8]| bytes.iter().fold(0, |x, &byte| (x << 8) | u64::from(byte));
+    // tree_bits is at most 63 for these parameter sets, so the shift can't overflow.
+    let idx_tree = to_int(tree_bytes) & ((1 << tree_bits) - 1);
+    let idx_leaf = to_int(leaf_bytes) & ((1 << params.h_prime()) - 1);
+    (md, idx_tree, idx_leaf as usize)
+}
+
+/// The tweakable hash functions F, H, T_l and PRF, which for SHA2 at category 1 all hash
+/// PK.seed || toByte(0, 48) || ADRS^c || M with SHA-256, as in FIPS 205, Section 11.2.1.
+struct Hash<'a> {
+    /// SHA-256 after the first block PK.seed || toByte(0, 48), which every call shares.
+    seeded: Sha256,
+    sk_seed: &'a [u8],
+}
+
+impl<'a> Hash<'a> {
+    fn new(pk_seed: &[u8], sk_seed: &'a [u8]) -> Self {
+        let mut seeded = Sha256::default();
+        seeded.update(pk_seed);
+        seeded.update(&[0; 64 - N]);
+        Self { seeded, sk_seed }
+    }
+
+    /// Outputs T_l(PK.seed, ADRS, M) for l n-byte blocks of M, which is F for l = 1 and H for
+    /// l = 2.
+    fn t(&self, adrs: &Address, message: &[&[u8]]) -> [u8; N] {
+        let mut sha = self.seeded.clone();
+        sha.update(&adrs.compressed());
+        for part in message {
+            sha.update(part);
+        }
+        let mut digest = [0; 32];
+        sha.write_digest(&mut digest);
+        let mut output = [0; N];
+        output.copy_from_slice(&digest[..N]);
+        output
+    }
+
+    /// Outputs PRF(PK.seed, SK.seed, ADRS).
+    fn prf(&self, adrs: &Address) -> [u8; N] {
+        self.t(adrs, &[self.sk_seed])
+    }
+}
+
+/// A 32-byte hash address, as in FIPS 205, Section 4.2.
+#[derive(Clone, Default)]
+struct Address([u8; 32]);
+
+impl Address {
+    fn set_word(&mut self, offset: usize, word: usize) {
+        self.0[offset..offset + 4].copy_from_slice(&(word as u32).to_be_bytes());
+    }
+
+    fn set_layer(&mut self, layer: usize) {
+        self.set_word(0, layer);
+    }
+
+    fn set_tree(&mut self, tree: u64) {
+        self.0[4..8].fill(0);
+        self.0[8..16].copy_from_slice(&tree.to_be_bytes());
+    }
+
+    fn set_type_and_clear(&mut self, address_type: u32) {
+        self.0[16..20].copy_from_slice(&address_type.to_be_bytes());
+        self.0[20..].fill(0);
+    }
+
+    fn set_key_pair(&mut self, key_pair: usize) {
+        self.set_word(20, key_pair);
+    }
+
+    fn key_pair(&self) -> usize {
+        self.word(20)
+    }
+
+    /// Sets the chain address, which is the tree height for tree addresses.
+    fn set_chain(&mut self, chain: usize) {
+        self.set_word(24, chain);
+    }
+
+    /// Sets the hash address, which is the tree index for tree addresses.
+    fn set_hash(&mut self, hash: usize) {
+        self.set_word(28, hash);
+    }
+
+    fn hash(&self) -> usize {
+        self.word(28)
+    }
+
+    fn word(&self, offset: usize) -> usize {
+        let mut word = [0; 4];
+        word.copy_from_slice(&self.0[offset..offset + 4]);
+        u32::from_be_bytes(word) as usize
+    }
+
+    /// Outputs the 22-byte compressed address ADRS^c used by the SHA2 parameter sets.
+    fn compressed(&self) -> [u8; 22] {
+        let mut compressed = [0; 22];
+        compressed[0] = self.0[3];
+        compressed[1..9].copy_from_slice(&self.0[8..16]);
+        compressed[9] = self.0[19];
+        compressed[10..].copy_from_slice(&self.0[20..]);
+        compressed
+    }
+}
+
+/// Splits bytes into `out_len` b-bit integers, most significant bits first, as in FIPS 205,
+/// Algorithm 4. At most 35 integers are output.
+fn base_2b(bytes: &[u8], b: usize, out_len: usize) -> [usize; LEN] {
+    let mut output = [0; LEN];
+    let mut bytes = bytes.iter();
+    let mut total = 0;
+    let mut bits = 0;
+    for value in output.iter_mut().take(out_len) {
+        while bits < b {
+            total = (total << 8) | usize::from(*bytes.next().expect("input too short"));
+            bits += 8;
+        }
+        bits -= b;
+        *value = (total >> bits) & ((1 << b) - 1);
+        total &= (1 << bits) - 1;
+    }
+    output
+}
+
+/// Outputs the WOTS+ chain values for an n-byte message and its checksum, as in FIPS 205,
+/// Algorithm 7, lines 1 to 9.
+fn wots_chain_lengths(message: &[u8]) -> [usize; LEN] {
+    let mut lengths = base_2b(message, 4, LEN_1);
+    let checksum: usize = lengths[..LEN_1].iter().map(|&x| W - 1 - x).sum();
+    // The checksum is shifted so that its 12 bits fill 2 bytes.
+    let checksum = ((checksum << 4) as u16).to_be_bytes();
+    lengths[LEN_1..].copy_from_slice(&base_2b(&checksum, 4, LEN - LEN_1)[..LEN - LEN_1]);
+    lengths
+}
+
+/// Iterates F on an n-byte value `steps` times starting at step `start`, as in FIPS 205,
+/// Algorithm 5.
+fn chain(hash: &Hash, x: &[u8], start: usize, steps: usize, adrs: &mut Address) -> [u8; N] {
+    let mut tmp = [0; N];
+    tmp.copy_from_slice(x);
+    for j in start..start + steps {
+        adrs.set_hash(j);
+        tmp = hash.t(adrs, &[&tmp]);
+    }
+    tmp
+}
+
+/// Outputs the secret value of chain i of the WOTS+ key pair at `adrs`, as in FIPS 205,
+/// Algorithm 6, lines 5 to 7.
+fn wots_sk(hash: &Hash, i: usize, adrs: &Address) -> [u8; N] {
+    let mut sk_adrs = adrs.clone();
+    sk_adrs.set_type_and_clear(WOTS_PRF);
+    sk_adrs.set_key_pair(adrs.key_pair());
+    sk_adrs.set_chain(i);
+    hash.prf(&sk_adrs)
+}
+
+/// Compresses the ends of the WOTS+ chains into a public key, as in FIPS 205, Algorithm 6,
+/// lines 11 to 14.
+fn wots_pk(hash: &Hash, ends: &[[u8; N]; LEN], adrs: &Address) -> [u8; N] {
+    let mut pk_adrs = adrs.clone();
+    pk_adrs.set_type_and_clear(WOTS_PK);
+    pk_adrs.set_key_pair(adrs.key_pair());
+    let ends: [&[u8]; LEN] = core::array::from_fn(|i| &ends[i][..]);
+    hash.t(&pk_adrs, &ends)
+}
+
+/// Outputs the WOTS+ public key at `adrs`, as in FIPS 205, Algorithm 6.
+fn wots_pkgen(hash: &Hash, adrs: &mut Address) -> [u8; N] {
+    let mut ends = [[0; N]; LEN];
+    for (i, end) in ends.iter_mut().enumerate() {
+        let sk = wots_sk(hash, i, adrs);
+        adrs.set_chain(i);
+        *end = chain(hash, &sk, 0, W - 1, adrs);
+    }
+    wots_pk(hash, &ends, adrs)
+}
+
+/// Writes the WOTS+ signature of an n-byte message, as in FIPS 205, Algorithm 7.
+fn wots_sign(hash: &Hash, message: &[u8], adrs: &mut Address, signature: &mut [u8]) {
+    let lengths = wots_chain_lengths(message);
+    for (i, chunk) in signature.chunks_mut(N).enumerate() {
+        let sk = wots_sk(hash, i, adrs);
+        adrs.set_chain(i);
+        chunk.copy_from_slice(&chain(hash, &sk, 0, lengths[i], adrs));
+    }
+}
+
+/// Outputs the WOTS+ public key implied by a signature of an n-byte message, as in FIPS 205,
+/// Algorithm 8.
+fn wots_pk_from_sig(hash: &Hash, signature: &[u8], message: &[u8], adrs: &mut Address) -> [u8; N] {
+    let lengths = wots_chain_lengths(message);
+    let mut ends = [[0; N]; LEN];
+    for (i, (end, chunk)) in ends.iter_mut().zip(signature.chunks(N)).enumerate() {
+        adrs.set_chain(i);
+        *end = chain(hash, chunk, lengths[i], W - 1 - lengths[i], adrs);
+    }
+    wots_pk(hash, &ends, adrs)
+}
+
+/// Outputs the node at height z and index i of the XMSS tree at `adrs`, as in FIPS 205,
+/// Algorithm 9.
+fn xmss_node(hash: &Hash, i: usize, z: usize, adrs: &mut Address) -> [u8; N] {
+    if z == 0 {
+        adrs.set_type_and_clear(WOTS_HASH);
+        adrs.set_key_pair(i);
+        return wots_pkgen(hash, adrs);
+    }
+    let left = xmss_node(hash, 2 * i, z - 1, adrs);
+    let right = xmss_node(hash, 2 * i + 1, z - 1, adrs);
+    adrs.set_type_and_clear(TREE);
+    adrs.set_chain(z);
+    adrs.set_hash(i);
+    hash.t(adrs, &[&left, &right])
+}
+
+/// Writes the XMSS signature of an n-byte message with the WOTS+ key pair at index `idx`,
+/// followed by its authentication path, as in FIPS 205, Algorithm 10.
+fn xmss_sign(hash: &Hash, message: &[u8], idx: usize, adrs: &mut Address, signature: &mut [u8]) {
+    let (wots_signature, auth) = signature.split_at_mut(LEN * N);
+    for (j, node) in auth.chunks_mut(N).enumerate() {
+        node.copy_from_slice(&xmss_node(hash, (idx >> j) ^ 1, j, adrs));
+    }
+    adrs.set_type_and_clear(WOTS_HASH);
+    adrs.set_key_pair(idx);
+    wots_sign(hash, message, adrs, wots_signature);
+}
+
+/// Outputs the root of an XMSS tree implied by a signature, as in FIPS 205, Algorithm 11.
+fn xmss_pk_from_sig(
+    hash: &Hash,
+    idx: usize,
+    signature: &[u8],
+    message: &[u8],
+    adrs: &mut Address,
+) -> [u8; N] {
+    adrs.set_type_and_clear(WOTS_HASH);
+    adrs.set_key_pair(idx);
+    let (wots_signature, auth) = signature.split_at(LEN * N);
+    let node = wots_pk_from_sig(hash, wots_signature, message, adrs);
+    adrs.set_type_and_clear(TREE);
+    adrs.set_hash(idx);
+    climb(hash, node, idx, auth, adrs)
+}
+
+/// Hashes a leaf up its authentication path to the root, updating the tree height and index in
+/// `adrs`, as in FIPS 205, Algorithm 11, lines 8 to 18, and Algorithm 17, lines 12 to 21.
+fn climb(hash: &Hash, mut node: [u8; N], idx: usize, auth: &[u8], adrs: &mut Address) -> [u8; N] {
+    for (k, sibling) in auth.chunks(N).enumerate() {
+        adrs.set_chain(k + 1);
+        adrs.set_hash(adrs.hash() / 2);
+        node = if (idx >> k) & 1 == 0 {
+            hash.t(adrs, &[&node, sibling])
+        } else {
+            hash.t(adrs, &[sibling, &node])
+        };
+    }
+    node
+}
+
+/// Writes the hypertree signature of an n-byte message, as in FIPS 205, Algorithm 12.
+fn ht_sign(
+    params: &Params,
+    hash: &Hash,
+    message: &[u8],
+    mut idx_tree: u64,
+    mut idx_leaf: usize,
+    signature: &mut [u8],
+) {
+    let mut root = [0; N];
+    root.copy_from_slice(message);
+    let xmss_len = (LEN + params.h_prime()) * N;
+    for (j, xmss_signature) in signature.chunks_mut(xmss_len).enumerate() {
+        let mut adrs = Address::default();
+        adrs.set_layer(j);
+        adrs.set_tree(idx_tree);
+        xmss_sign(hash, &root, idx_leaf, &mut adrs, xmss_signature);
+        if j < params.d - 1 {
+            root = xmss_pk_from_sig(hash, idx_leaf, xmss_signature, &root, &mut adrs);
+        }
+        idx_leaf = (idx_tree & ((1 << params.h_prime()) - 1)) as usize;
+        idx_tree >>= params.h_prime();
+    }
+}
+
+/// Checks whether a hypertree signature of an n-byte message leads to the root PK.root, as in
+/// FIPS 205, Algorithm 13.
+fn ht_verify(
+    params: &Params,
+    hash: &Hash,
+    message: &[u8],
+    signature: &[u8],
+    mut idx_tree: u64,
+    mut idx_leaf: usize,
+    pk_root: &[u8],
+) -> bool {
+    let mut node = [0; N];
+    node.copy_from_slice(message);
+    let xmss_len = (LEN + params.h_prime()) * N;
+    for (j, xmss_signature) in signature.chunks(xmss_len).enumerate() {
+        let mut adrs = Address::default();
+        adrs.set_layer(j);
+        adrs.set_tree(idx_tree);
+        node = xmss_pk_from_sig(hash, idx_leaf, xmss_signature, &node, &mut adrs);
+        idx_leaf = (idx_tree & ((1 << params.h_prime()) - 1)) as usize;
+        idx_tree >>= params.h_prime();
+    }
+    ct::constant_time_eq(&node, pk_root)
+}
+
+/// Outputs the FORS secret value at index `idx`, as in FIPS 205, Algorithm 14.
+fn fors_sk_gen(hash: &Hash, adrs: &Address, idx: usize) -> [u8; N] {
+    let mut sk_adrs = adrs.clone();
+    sk_adrs.set_type_and_clear(FORS_PRF);
+    sk_adrs.set_key_pair(adrs.key_pair());
+    sk_adrs.set_hash(idx);
+    hash.prf(&sk_adrs)
+}
+
+/// Outputs the node at height z and index i of the FORS trees at `adrs`, as in FIPS 205,
+/// Algorithm 15.
+fn fors_node(hash: &Hash, i: usize, z: usize, adrs: &mut Address) -> [u8; N] {
+    if z == 0 {
+        let sk = fors_sk_gen(hash, adrs, i);
+        adrs.set_chain(0);
+        adrs.set_hash(i);
+        return hash.t(adrs, &[&sk]);
+    }
+    let left = fors_node(hash, 2 * i, z - 1, adrs);
+    let right = fors_node(hash, 2 * i + 1, z - 1, adrs);
+    adrs.set_chain(z);
+    adrs.set_hash(i);
+    hash.t(adrs, &[&left, &right])
+}
+
+/// Writes the FORS signature of the a-bit message indices, each secret value followed by its
+/// authentication path, as in FIPS 205, Algorithm 16.
+fn fors_sign(
+    params: &Params,
+    hash: &Hash,
+    indices: &[usize],
+    adrs: &mut Address,
+    signature: &mut [u8],
+) {
+    let chunks = signature.chunks_mut((1 + params.a) * N);
+    for (i, (&index, chunk)) in indices.iter().zip(chunks).enumerate() {
+        let (sk, auth) = chunk.split_at_mut(N);
+        sk.copy_from_slice(&fors_sk_gen(hash, adrs, (i << params.a) + index));
+        for (j, node) in auth.chunks_mut(N).enumerate() {
+            let s = (index >> j) ^ 1;
+            node.copy_from_slice(&fors_node(hash, (i << (params.a - j)) + s, j, adrs));
+        }
+    }
+}
+
+/// Outputs the FORS public key implied by a signature of the message indices, as in FIPS 205,
+/// Algorithm 17.
+fn fors_pk_from_sig(
+    params: &Params,
+    hash: &Hash,
+    signature: &[u8],
+    indices: &[usize],
+    adrs: &mut Address,
+) -> [u8; N] {
+    let mut roots = [[0; N]; 35];
+    let chunks = signature.chunks((1 + params.a) * N);
+    for (i, ((&index, chunk), root)) in indices.iter().zip(chunks).zip(&mut roots).enumerate() {
+        let (sk, auth) = chunk.split_at(N);
+        let leaf = (i << params.a) + index;
+        adrs.set_chain(0);
+        adrs.set_hash(leaf);
+        let node = hash.t(adrs, &[sk]);
+        *root = climb(hash, node, index, auth, adrs);
+    }
+    let mut pk_adrs = adrs.clone();
+    pk_adrs.set_type_and_clear(FORS_ROOTS);
+    pk_adrs.set_key_pair(adrs.key_pair());
+    let roots: [&[u8]; 35] = core::array::from_fn(|i| &roots[i][..]);
+    hash.t(&pk_adrs, &roots[..params.k])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_base_2b() {
+        let values = base_2b(&[0x12, 0x34, 0x56], 4, 6);
+        assert_eq!([1, 2, 3, 4, 5, 6], values[..6]);
+        let values = base_2b(&[0x12, 0x34, 0x56], 12, 2);
+        assert_eq!([0x123, 0x456], values[..2]);
+        let values = base_2b(&[0xff, 0x80], 6, 2);
+        assert_eq!([0x3f, 0x38], values[..2]);
+    }
+
+    #[test]
+    fn test_wots_chain_lengths() {
+        // The checksum of an all-zero message is 32 * 15 = 0x1e0.
+        let lengths = wots_chain_lengths(&[0; N]);
+        assert_eq!([0; LEN_1], lengths[..LEN_1]);
+        assert_eq!([1, 14, 0], lengths[LEN_1..]);
+        let lengths = wots_chain_lengths(&[0xff; N]);
+        assert_eq!([0; LEN - LEN_1], lengths[LEN_1..]);
+    }
+
+    #[test]
+    fn test_compressed_address() {
+        let mut adrs = Address::default();
+        adrs.set_layer(3);
+        adrs.set_tree(0x0102_0304_0506_0708);
+        adrs.set_type_and_clear(FORS_TREE);
+        adrs.set_key_pair(9);
+        adrs.set_chain(10);
+        adrs.set_hash(11);
+        let expected = [
+            3, 1, 2, 3, 4, 5, 6, 7, 8, 3, 0, 0, 0, 9, 0, 0, 0, 10, 0, 0, 0, 11,
+        ];
+        assert_eq!(expected, adrs.compressed());
+        assert_eq!(9, adrs.key_pair());
+        assert_eq!(11, adrs.hash());
+    }
+}
@@ -0,0 +1,183 @@
+//! Module for SLH-DSA-SHA2-128s, the parameter set at security category 1 for small signatures.
+use super::{Params, N, SEED_LEN};
+use crate::error::Error;
+use crate::rand::SecureRandom;
+use crate::secret::SecretKey;
+use crate::sig::{self, Signer, Verifier};
+
+/// The length of a public key.
+pub const PUBLIC_KEY_LEN: usize = 32;
+
+/// The length of a secret key.
+pub const SECRET_KEY_LEN: usize = 64;
+
+/// The length of a signature.
+pub const SIGNATURE_LEN: usize = 7856;
+
+const PARAMS: Params = Params {
+    h: 63,
+    d: 7,
+    a: 12,
+    k: 14,
+};
+
+/// Generates a key pair with seeds drawn from `rng`, outputting the secret key and the public
+/// key.
+pub fn gen_key_pair(rng: &dyn SecureRandom) -> ([u8; SECRET_KEY_LEN], [u8; PUBLIC_KEY_LEN]) {
+    let mut seed = SecretKey::new([0; SEED_LEN]);
+    rng.fill(&mut seed[..]);
+    derive_key_pair(&seed)
+}
+
+/// Derives a key pair from a seed SK.seed || SK.prf || PK.seed, outputting the secret key and
+/// the public key.
+pub fn derive_key_pair(seed: &[u8; SEED_LEN]) -> ([u8; SECRET_KEY_LEN], [u8; PUBLIC_KEY_LEN]) {
+    let mut secret_key = [0; SECRET_KEY_LEN];
+    let mut public_key = [0; PUBLIC_KEY_LEN];
+    super::derive_key_pair(&PARAMS, seed, &mut secret_key, &mut public_key);
+    (secret_key, public_key)
+}
+
+/// Signs a message under a context with hedged randomness drawn from `rng`.
+///
+/// # Errors
+///
+/// Fails with `Error::InvalidKey` if `secret_key.len()` is not equal to 64, or with
+/// `Error::InvalidLength` if `context.len()` is greater than 255.
+pub fn sign(
+    message: &[u8],
+    context: &[u8],
+    secret_key: &[u8],
+    rng: &dyn SecureRandom,
+) -> Result<[u8; SIGNATURE_LEN], Error> {
+    let mut opt_rand = [0; N];
+    rng.fill(&mut opt_rand);
+    let mut signature = [0; SIGNATURE_LEN];
+    super::sign(
+        &PARAMS,
+        message,
+        context,
+        secret_key,
+        &opt_rand,
+        &mut signature,
+    )?;
+    Ok(signature)
+}
+
+/// Signs a message under a context deterministically, so signing the same message twice gives
+/// the same signature.
+///
+/// # Errors
+///
+/// Fails like `sign`.
+pub fn sign_deterministic(
+    message: &[u8],
+    context: &[u8],
+    secret_key: &[u8],
+) -> Result<[u8; SIGNATURE_LEN], Error> {
+    if secret_key.len() != SECRET_KEY_LEN {
+        return Err(Error::InvalidKey);
+    }
+    // The deterministic variant uses PK.seed in place of the randomness.
+    let opt_rand = &secret_key[2 * N..3 * N];
+    let mut signature = [0; SIGNATURE_LEN];
+    super::sign(
+        &PARAMS,
+        message,
+        context,
+        secret_key,
+        opt_rand,
+        &mut signature,
+    )?;
+    Ok(signature)
+}
+
+/// Verifies whether a message was signed under a context using the given public key.
+///
+/// Outputs `false` if the signature or public key has the wrong length.
+pub fn verify(message: &[u8], context: &[u8], signature: &[u8], public_key: &[u8]) -> bool {
+    super::verify(&PARAMS, message, context, signature, public_key)
+}
+
+/// The SLH-DSA-SHA2-128s parameter set, with small signatures.
+pub struct SlhDsaSha2_128s;
+
+impl Verifier for SlhDsaSha2_128s {
+    const MAX_CONTEXT_LEN: usize = 255;
+
+    type PublicKey = [u8];
+
+    fn verify(
+        message: &[u8],
+        context: &[u8],
+        signature: &[u8],
+        public_key: &[u8],
+    ) -> Result<(), Error> {
+        sig::check_lengths::<Self>(context, public_key, PUBLIC_KEY_LEN)?;
+        sig::check_valid(verify(message, context, signature, public_key))
+    }
+}
+
+impl Signer for SlhDsaSha2_128s {
+    type SecretKey = [u8];
+    type Signature = [u8; SIGNATURE_LEN];
+
+    fn sign(
+        message: &[u8],
+        context: &[u8],
+        secret_key: &[u8],
+        rng: &dyn SecureRandom,
+    ) -> Result<[u8; SIGNATURE_LEN], Error> {
+        sign(message, context, secret_key, rng)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sha3::sha3_256;
+    use crate::test_helpers::*;
+
+    #[test]
+    fn test_kat() {
+        let mut seed = [0; SEED_LEN];
+        for (i, byte) in seed.iter_mut().enumerate() {
+            *byte = i as u8;
+        }
+        let (secret_key, public_key) = derive_key_pair(&seed);
+        // Checked against OpenSSL 3.5
+        let expected = "202122232425262728292a2b2c2d2e2f990ce6298792b128846a8e4a3a68954c";
+        assert_eq!(h2b(expected), public_key);
+        assert_eq!(seed[..], secret_key[..SEED_LEN]);
+        assert_eq!(public_key[..], secret_key[SEED_LEN - N..]);
+
+        let signature = sign_deterministic(b"message", b"context", &secret_key).unwrap();
+        let expected = "a103df0fb4e86ace9568d3e139e434fbde7855a51be5ce1873a506c802a2d132";
+        assert_eq!(h2b(expected), sha3_256(&signature));
+        assert!(verify(b"message", b"context", &signature, &public_key));
+        let short_key = &public_key[1..];
+        assert!(!verify(b"message", b"context", &signature, short_key));
+        let long_key = &[&public_key[..], &[0]].concat();
+        assert!(!verify(b"message", b"context", &signature, long_key));
+    }
+
+    #[test]
+    fn test_invalid_lengths() {
+        let (secret_key, _) = derive_key_pair(&[0; SEED_LEN]);
+        let rng = &|buffer: &mut [u8]| buffer.fill(0x42);
+        let error = Err(Error::InvalidLength);
+        assert_eq!(
+            error,
+            sign(b"message", &[0; 256], &secret_key, rng).map(|_| ())
+        );
+        let signature = sign_deterministic(b"message", &[0; 256], &secret_key);
+        assert_eq!(error, signature.map(|_| ()));
+        let error = Err(Error::InvalidKey);
+        assert_eq!(
+            error,
+            sign(b"message", b"", &secret_key[1..], rng).map(|_| ())
+        );
+        let signature = sign_deterministic(b"message", b"", &secret_key[1..]);
+        assert_eq!(error, signature.map(|_| ()));
+    }
+}
@@ -0,0 +1,376 @@
+//! Module for the SPAKE2 password-authenticated key exchange (RFC 9382) with the
+//! SPAKE2-P256-SHA256-HKDF-HMAC cipher suite.
+//!
+//! Both parties know a password scalar w, derived from the password with a memory-hard function
+//! such as `argon2` or `scrypt` and reduced with `password_scalar`. Each sends a share, after
+//! which each can compute the shared key and a confirmation message, and must verify the other's
+//! confirmation before using the key. Party A's share is pA = x G + w M, and party B's is
+//! pB = y G + w N, with the fixed points M and N of RFC 9382.
+//!
+//! # Examples
+//!
+//! ```
+//! use crypto_pure::rand::SystemRandom;
+//! use crypto_pure::spake2::{self, Role, Spake2};
+//! # let mhf_output = &[0x42; 64];
+//! let rng = &SystemRandom::new();
+//! let w = &spake2::password_scalar(mhf_output);
+//! let alice = Spake2::new(Role::A, w, rng)?;
+//! let bob = Spake2::new(Role::B, w, rng)?;
+//! let alice_share = *alice.share();
+//! let alice = alice.finish(bob.share(), b"alice", b"bob", b"")?;
+//! let bob = bob.finish(&alice_share, b"alice", b"bob", b"")?;
+//! alice.verify(bob.confirmation())?;
+//! bob.verify(alice.confirmation())?;
+//! assert_eq!(alice.key(), bob.key());
+//! # Ok::<(), crypto_pure::error::Error>(())
+//! ```
+use crate::ct;
+use crate::error::Error;
+use crate::hkdf;
+use crate::hmac::Hmac;
+use crate::p256::PARAMS;
+use crate::rand::SecureRandom;
+use crate::secret::SecretKey;
+use crate::sha2::{HashFunction, Sha256};
+use crate::weierstrass::Curve;
+
+/// The length of a share, an uncompressed P-256 point.
+pub const SHARE_LEN: usize = 65;
+
+/// The length of the shared key Ke.
+pub const KEY_LEN: usize = 16;
+
+/// The length of a confirmation message.
+pub const CONFIRMATION_LEN: usize = 32;
+
+const SCALAR_LEN: usize = 32;
+
+/// The role of a party, which determines the point that blinds its share.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Role {
+    /// Party A, usually the client.
+    A,
+    /// Party B, usually the server.
+    B,
+}
+
+impl Role {
+    fn blinding_point(self) -> &'static [u8; SHARE_LEN] {
+        match self {
+            Role::A => &M,
+            Role::B => &N,
+        }
+    }
+
+    fn other(self) -> Self {
+        match self {
+            Role::A => Role::B,
+            Role::B => Role::A,
+        }
+    }
+}
+
+/// Reduces the output of a memory-hard function modulo the group order to give the password
+/// scalar w. RFC 9382 recommends an output at least 128 bits longer than the order, so 48 bytes.
+///
+/// # Panics
+///
+/// Panics if `mhf_output.len()` is more than 64.
+pub fn password_scalar(mhf_output: &[u8]) -> [u8; SCALAR_LEN] {
+    assert!(mhf_output.len() <= 2 * SCALAR_LEN);
+    let mut w = [0; SCALAR_LEN];
+    Curve::new(&PARAMS).reduce_scalar(mhf_output, &mut w);
+    w
+}
+
+/// A party that has computed its share.
+pub struct Spake2 {
+    role: Role,
+    w: SecretKey<[u8; SCALAR_LEN]>,
+    x: SecretKey<[u8; SCALAR_LEN]>,
+    share: [u8; SHARE_LEN],
+}
+
+impl Spake2 {
+    /// Computes the share of a party given the password scalar, with a secret scalar drawn from
+    /// `rng`.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::InvalidKey` if `w` is not below the group order.
+    pub fn new(role: Role, w: &[u8; SCALAR_LEN], rng: &dyn SecureRandom) -> Result<Self, Error> {
+        let curve = Curve::new(&PARAMS);
+        let mut x = SecretKey::new([0; SCALAR_LEN]);
+        let mut share = [0; SHARE_LEN];
+        loop {
+            rng.fill(&mut *x);
+            if curve.gen_pk(&*x, &mut share) {
+                break;
+            }
+        }
+        if !curve.mul_base_add(&*x, w, role.blinding_point(), &mut share) {
+            return Err(Error::InvalidKey);
+        }
+        Ok(Self {
+            role,
+            w: SecretKey::new(*w),
+            x,
+            share,
+        })
+    }
+
+    /// Outputs the share to send to the other party.
+    pub fn share(&self) -> &[u8; SHARE_LEN] {
+        &self.share
+    }
+
+    /// Processes the other party's share, computing the shared key and confirmation messages.
+    /// Both parties must use the same identities of A and B, which may be empty, and the same
+    /// associated data, which is bound to the confirmation keys.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::InvalidKey` if `peer_share` is not a valid point or the shared point is
+    /// the point at infinity.
+    pub fn finish(
+        self,
+        peer_share: &[u8],
+        identity_a: &[u8],
+        identity_b: &[u8],
+        aad: &[u8],
+    ) -> Result<Session, Error> {
+        let curve = Curve::new(&PARAMS);
+        let mut k = [0; SHARE_LEN];
+        let peer_point = self.role.other().blinding_point();
+        if !curve.mul_difference(&*self.x, peer_share, &*self.w, peer_point, &mut k) {
+            return Err(Error::InvalidKey);
+        }
+        let (share_a, share_b) = match self.role {
+            Role::A => (&self.share[..], peer_share),
+            Role::B => (peer_share, &self.share[..]),
+        };
+        let w = &*self.w;
+        let transcript = [identity_a, identity_b, share_a, share_b, &k, w];
+        let mut hash_function = Sha256::default();
+        write_transcript(&transcript, |part| hash_function.update(part));
+        let mut hash = SecretKey::new([0; Sha256::DIGEST_SIZE]);
+        hash_function.write_digest(&mut *hash);
+        let (ke, ka) = hash.split_at(KEY_LEN);
+        // HKDF-Expand with the info "ConfirmationKeys" || aad, which takes a single block
+        let mut prk = SecretKey::new([0; Sha256::DIGEST_SIZE]);
+        hkdf::extract::<Sha256>(b"", ka, &mut *prk).expect("PRK is the digest size");
+        let mut hmac = Hmac::<Sha256>::new(&*prk);
+        hmac.update(b"ConfirmationKeys");
+        hmac.update(aad);
+        hmac.update(&[1]);
+        let confirmation_keys = hmac.tag();
+        let (key_a, key_b) = confirmation_keys.split_at(KEY_LEN);
+        let confirmation_a = confirm(key_a, &transcript);
+        let confirmation_b = confirm(key_b, &transcript);
+        let (confirmation, peer_confirmation) = match self.role {
+            Role::A => (confirmation_a, confirmation_b),
+            Role::B => (confirmation_b, confirmation_a),
+        };
+        let mut key = SecretKey::new([0; KEY_LEN]);
+        key.copy_from_slice(ke);
+        Ok(Session {
+            key,
+            confirmation,
+            peer_confirmation,
+        })
+    }
+}
+
+/// Feeds the length-prefixed parts of the transcript TT to `update`.
+fn write_transcript<F: FnMut(&[u8])>(transcript: &[&[u8]], mut update: F) {
+    for part in transcript {
+        update(&(part.len() as u64).to_le_bytes());
+        update(part);
+    }
+}
+
+/// Computes a confirmation message, the MAC of the transcript under a confirmation key.
+fn confirm(key: &[u8], transcript: &[&[u8]]) -> [u8; CONFIRMATION_LEN] {
+    let mut hmac = Hmac::<Sha256>::new(key);
+    write_transcript(transcript, |part| hmac.update(part));
+    let mut confirmation = [0; CONFIRMATION_LEN];
+    confirmation.copy_from_slice(&hmac.tag());
+    confirmation
+}
+
+/// A party's session after processing the other party's share.
+pub struct Session {
+    key: SecretKey<[u8; KEY_LEN]>,
+    confirmation: [u8; CONFIRMATION_LEN],
+    peer_confirmation: [u8; CONFIRMATION_LEN],
+}
+
+impl Session {
+    /// Outputs the confirmation message to send to the other party.
+    pub fn confirmation(&self) -> &[u8; CONFIRMATION_LEN] {
+        &self.confirmation
+    }
+
+    /// Verifies the other party's confirmation message, which shows that it used the same
+    /// password.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::TagMismatch` if verification fails.
+    pub fn verify(&self, peer_confirmation: &[u8]) -> Result<(), Error> {
+        if !ct::constant_time_eq(peer_confirmation, &self.peer_confirmation) {
+            return Err(Error::TagMismatch);
+        }
+        Ok(())
+    }
+
+    /// Outputs the shared key Ke, which should only be used once the other party's confirmation
+    /// message is verified.
+    pub fn key(&self) -> &[u8; KEY_LEN] {
+        &self.key
+    }
+}
+
+// The fixed points M and N for P-256, from RFC 9382, Section 6
+const M: [u8; 65] = [
+    0x04, 0x88, 0x6e, 0x2f, 0x97, 0xac, 0xe4, 0x6e, 0x55, 0xba, 0x9d, 0xd7, 0x24, 0x25, 0x79, 0xf2,
+    0x99, 0x3b, 0x64, 0xe1, 0x6e, 0xf3, 0xdc, 0xab, 0x95, 0xaf, 0xd4, 0x97, 0x33, 0x3d, 0x8f, 0xa1,
+    0x2f, 0x5f, 0xf3, 0x55, 0x16, 0x3e, 0x43, 0xce, 0x22, 0x4e, 0x0b, 0x0e, 0x65, 0xff, 0x02, 0xac,
+    0x8e, 0x5c, 0x7b, 0xe0, 0x94, 0x19, 0xc7, 0x85, 0xe0, 0xca, 0x54, 0x7d, 0x55, 0xa1, 0x2e, 0x2d,
+    0x20,
+];
+
+const N: [u8; 65] = [
+    0x04, 0xd8, 0xbb, 0xd6, 0xc6, 0x39, 0xc6, 0x29, 0x37, 0xb0, 0x4d, 0x99, 0x7f, 0x38, 0xc3, 0x77,
+    0x07, 0x19, 0xc6, 0x29, 0xd7, 0x01, 0x4d, 0x49, 0xa2, 0x4b, 0x4f, 0x98, 0xba, 0xa1, 0x29, 0x2b,
+    0x49, 0x07, 0xd6, 0x0a, 0xa6, 0xbf, 0xad, 0xe4, 0x50, 0x08, 0xa6, 0x36, 0x33, 0x7f, 0x51, 0x68,
+    0xc6, 0x4d, 0x9b, 0xd3, 0x60, 0x34, 0x80, 0x8c, 0xd5, 0x64, 0x49, 0x0b, 0x1e, 0x65, 0x6e, 0xdb,
+    0xe7,
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::*;
+
+    const W: &str = "2ee57912099d31560b3a44b1184b9b4866e904c49d12ac5042c97dca461b1a5f";
+    const X: &str = "43dd0fd7215bdcb482879fca3220c6a968e66d70b1356cac18bb26c84a78d729";
+    const Y: &str = "dcb60106f276b02606d8ef0a328c02e4b629f84f89786af5befb0bc75b6e66be";
+
+    fn fixed_rng(scalar: &str) -> impl Fn(&mut [u8]) {
+        let scalar = h2b(scalar);
+        move |dest: &mut [u8]| dest.copy_from_slice(&scalar)
+    }
+
+    fn password(hex: &str) -> [u8; SCALAR_LEN] {
+        let mut w = [0; SCALAR_LEN];
+        w.copy_from_slice(&h2b(hex));
+        w
+    }
+
+    // RFC 9382, Appendix B, with the first vector's identities A = "server" and B = "client"
+    #[test]
+    fn test_vector() {
+        let w = &password(W);
+        let a = Spake2::new(Role::A, w, &fixed_rng(X)).unwrap();
+        let b = Spake2::new(Role::B, w, &fixed_rng(Y)).unwrap();
+        assert_eq!(
+            &h2b(
+                "04a56fa807caaa53a4d28dbb9853b9815c61a411118a6fe516a8798434751470f9\
+                 010153ac33d0d5f2047ffdb1a3e42c9b4e6be662766e1eeb4116988ede5f912c"
+            )[..],
+            &a.share()[..]
+        );
+        assert_eq!(
+            &h2b(
+                "0406557e482bd03097ad0cbaa5df82115460d951e3451962f1eaf4367a420676d0\
+                 9857ccbc522686c83d1852abfa8ed6e4a1155cf8f1543ceca528afb591a1e0b7"
+            )[..],
+            &b.share()[..]
+        );
+        let share_a = *a.share();
+        let a = a.finish(b.share(), b"server", b"client", b"").unwrap();
+        let b = b.finish(&share_a, b"server", b"client", b"").unwrap();
+        assert_eq!(&h2b("0e0672dc86f8e45565d338b0540abe69")[..], a.key());
+        assert_eq!(a.key(), b.key());
+        let confirmation_a =
+            h2b("58ad4aa88e0b60d5061eb6b5dd93e80d9c4f00d127c65b3b35b1b5281fee38f0");
+        let confirmation_b =
+            h2b("d3e2e547f1ae04f2dbdbf0fc4b79f8ecff2dff314b5d32fe9fcef2fb26dc459b");
+        assert_eq!(&confirmation_a[..], a.confirmation());
+        assert_eq!(&confirmation_b[..], b.confirmation());
+        a.verify(&confirmation_b).unwrap();
+        b.verify(&confirmation_a).unwrap();
+    }
+
+    #[test]
+    fn test_wrong_password() {
+        let a = Spake2::new(Role::A, &password(W), &fixed_rng(X)).unwrap();
+        let b = Spake2::new(Role::B, &password(X), &fixed_rng(Y)).unwrap();
+        let share_a = *a.share();
+        let a = a.finish(b.share(), b"a", b"b", b"").unwrap();
+        let b = b.finish(&share_a, b"a", b"b", b"").unwrap();
+        assert_ne!(a.key(), b.key());
+        assert_eq!(Err(Error::TagMismatch), a.verify(b.confirmation()));
+        assert_eq!(Err(Error::TagMismatch), b.verify(a.confirmation()));
+    }
+
+    #[test]
+    fn test_mismatched_context() {
+        let w = &password(W);
+        let check = |identity_b: &[u8], aad: &[u8]| {
+            let a = Spake2::new(Role::A, w, &fixed_rng(X)).unwrap();
+            let b = Spake2::new(Role::B, w, &fixed_rng(Y)).unwrap();
+            let share_a = *a.share();
+            let a = a.finish(b.share(), b"a", b"b", b"aad").unwrap();
+            let b = b.finish(&share_a, b"a", identity_b, aad).unwrap();
+            assert_eq!(Err(Error::TagMismatch), a.verify(b.confirmation()));
+        };
+        check(b"c", b"aad");
+        check(b"b", b"");
+    }
+
+    #[test]
+    fn test_invalid_share() {
+        let w = &password(W);
+        let mut share = *Spake2::new(Role::B, w, &fixed_rng(Y)).unwrap().share();
+        share[64] ^= 1;
+        let a = Spake2::new(Role::A, w, &fixed_rng(X)).unwrap();
+        assert!(a.finish(&share, b"", b"", b"").is_err());
+        let a = Spake2::new(Role::A, w, &fixed_rng(X)).unwrap();
+        assert!(a.finish(&share[..64], b"", b"", b"").is_err());
+        // With w = 1, a share of N gives the point at infinity.
+        let one = &mut [0; SCALAR_LEN];
+        one[31] = 1;
+        let a = Spake2::new(Role::A, one, &fixed_rng(X)).unwrap();
+        assert_eq!(
+            Err(Error::InvalidKey),
+            a.finish(&N, b"", b"", b"").map(|_| ())
+        );
+    }
+
+    #[test]
+    fn test_invalid_password() {
+        let w = &[0xff; SCALAR_LEN];
+        assert!(Spake2::new(Role::A, w, &fixed_rng(X)).is_err());
+    }
+
+    #[test]
+    fn test_password_scalar() {
+        assert_eq!(password(W), password_scalar(&h2b(W)));
+        // n + 1 reduces to 1
+        let mut expected = [0; SCALAR_LEN];
+        expected[31] = 1;
+        let n_plus_one = h2b("ffffffff00000000ffffffffffffffffbce6faada7179e84f3b9cac2fc632552");
+        assert_eq!(expected, password_scalar(&n_plus_one));
+        // 2^256 * 2 + 3 = 2 (2^256 - n) + 3 mod n
+        let mut wide = [0; 64];
+        wide[31] = 2;
+        wide[63] = 3;
+        assert_eq!(
+            &h2b("00000001fffffffe000000000000000086320aa4b1d0c2f6188c6a7a0739b561")[..],
+            &password_scalar(&wide)[..]
+        );
+    }
+}
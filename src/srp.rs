@@ -0,0 +1,702 @@
+//! Module for the SRP-6a password-authenticated key exchange (RFC 5054), with the groups of RFC
+//! 5054 and a hash function chosen by its `HashFunction` type.
+//!
+//! At registration, the server stores a salt and a verifier computed from the user's identity and
+//! password. To log in, the client and server exchange public keys, after which each derives the
+//! session key K = H(S) and the proofs M1 = H(H(N) xor H(g) | H(I) | s | A | B | K), sent by the
+//! client, and M2 = H(A | M1 | K), sent by the server once it has verified M1. Values in hashes
+//! and public keys are big-endian integers as long as N, as in RFC 5054.
+//!
+//! # Examples
+//!
+//! ```
+//! use crypto_pure::rand::SystemRandom;
+//! use crypto_pure::sha2::Sha256;
+//! use crypto_pure::srp::{self, Client, Group, Server};
+//! # let salt = &[0x42; 16];
+//! let rng = &SystemRandom::new();
+//! let verifier = srp::verifier::<Sha256>(Group::Srp2048, b"alice", b"password", salt);
+//!
+//! let client = Client::<Sha256>::new(Group::Srp2048, rng);
+//! let server = Server::<Sha256>::new(Group::Srp2048, &verifier, rng)?;
+//! let client = client.process(b"alice", b"password", salt, server.public_key())?;
+//! let server = server.process(b"alice", salt, client.public_key())?;
+//! let server_proof = server.verify_client(client.proof())?;
+//! client.verify_server(server_proof)?;
+//! assert_eq!(client.key(), server.key());
+//! # Ok::<(), crypto_pure::error::Error>(())
+//! ```
+use crate::bigint::{self, from_be_bytes, is_zero, resize, to_be_bytes, Modulus};
+use crate::ct;
+use crate::error::Error;
+use crate::rand::SecureRandom;
+use crate::secret::SecretKey;
+use crate::sha2::{HashFunction, MAX_DIGEST_SIZE};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::marker::PhantomData;
+
+const EXPONENT_LEN: usize = 32;
+
+/// A group from RFC 5054.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Group {
+    /// The 1024-bit group, which is too small for new uses.
+    Srp1024,
+    Srp2048,
+    Srp3072,
+    Srp4096,
+}
+
+impl Group {
+    /// Outputs the length of N, public keys and verifiers in bytes.
+    pub fn size(self) -> usize {
+        self.prime().len()
+    }
+
+    fn prime(self) -> &'static [u8] {
+        match self {
+            Group::Srp1024 => &SRP1024,
+            Group::Srp2048 => &SRP2048,
+            Group::Srp3072 => &SRP3072,
+            Group::Srp4096 => &SRP4096,
+        }
+    }
+
+    fn generator(self) -> u8 {
+        match self {
+            Group::Srp1024 | Group::Srp2048 => 2,
+            Group::Srp3072 | Group::Srp4096 => 5,
+        }
+    }
+
+    /// Outputs N, g in Montgomery form, and the multiplier k = H(N | PAD(g)) in Montgomery form.
+    fn parameters<H: HashFunction>(self) -> (Modulus, Vec<u64>, Vec<u64>) {
+        let n = Modulus::new(from_be_bytes(self.prime()));
+        let mut g = vec![0; n.len()];
+        g[0] = u64::from(self.generator());
+        let mut padded_g = vec![0; self.size()];
+        padded_g[self.size() - 1] = self.generator();
+        let k = hash::<H>(&[self.prime(), &padded_g]);
+        let k = n.to_montgomery(&resize(&from_be_bytes(&k[..H::DIGEST_SIZE]), n.len()));
+        let g = n.to_montgomery(&g);
+        (n, g, k)
+    }
+}
+
+/// Computes the verifier v = g^x, with x = H(s | H(I | ":" | P)), that the server stores for a user
+/// with identity I, password P and salt s.
+pub fn verifier<H: HashFunction>(
+    group: Group,
+    identity: &[u8],
+    password: &[u8],
+    salt: &[u8],
+) -> Vec<u8> {
+    let (n, g, _) = group.parameters::<H>();
+    let x = private_key::<H>(identity, password, salt);
+    let mut verifier = vec![0; group.size()];
+    to_be_bytes(&n.to_normal(&n.pow(&g, &x)), &mut verifier);
+    verifier
+}
+
+/// The client before it receives the server's public key.
+pub struct Client<H> {
+    group: Group,
+    exponent: Vec<u64>,
+    public_key: Vec<u8>,
+    hash: PhantomData<H>,
+}
+
+impl<H: HashFunction> Client<H> {
+    /// Starts a login with a secret exponent a drawn from `rng`, computing the public key
+    /// A = g^a.
+    pub fn new(group: Group, rng: &dyn SecureRandom) -> Self {
+        let (n, g, _) = group.parameters::<H>();
+        let exponent = random_exponent(rng);
+        let mut public_key = vec![0; group.size()];
+        to_be_bytes(&n.to_normal(&n.pow(&g, &exponent)), &mut public_key);
+        Self {
+            group,
+            exponent,
+            public_key,
+            hash: PhantomData,
+        }
+    }
+
+    /// Outputs the public key A to send to the server.
+    pub fn public_key(&self) -> &[u8] {
+        &self.public_key
+    }
+
+    /// Processes the server's public key B, computing the session key and proofs from the user's
+    /// identity, password and salt.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::InvalidKey` if `server_public_key` is not as long as N, is not below N,
+    /// or is 0, or if the scrambling parameter u = H(A | B) is 0.
+    pub fn process(
+        &self,
+        identity: &[u8],
+        password: &[u8],
+        salt: &[u8],
+        server_public_key: &[u8],
+    ) -> Result<ClientSession<H>, Error> {
+        let (n, g, k) = self.group.parameters::<H>();
+        let b = parse_public_key(&n, self.group, server_public_key)?;
+        let u = scrambler::<H>(&self.public_key, server_public_key)?;
+        let x = private_key::<H>(identity, password, salt);
+        // S = (B - k g^x)^(a + u x)
+        let base = n.sub(&n.to_montgomery(&b), &n.mul(&k, &n.pow(&g, &x)));
+        let ux = bigint::mul(&u, &x);
+        let len = ux.len().max(self.exponent.len()) + 1;
+        let exponent = bigint::add_with_carry(&resize(&self.exponent, len), &resize(&ux, len)).0;
+        let s = n.to_normal(&n.pow(&base, &exponent));
+        let proofs = Proofs::<H>::new(
+            self.group,
+            identity,
+            salt,
+            &self.public_key,
+            server_public_key,
+            &s,
+        );
+        Ok(ClientSession {
+            public_key: self.public_key.clone(),
+            proofs,
+        })
+    }
+}
+
+/// The client's session after processing the server's public key.
+pub struct ClientSession<H> {
+    public_key: Vec<u8>,
+    proofs: Proofs<H>,
+}
+
+impl<H: HashFunction> ClientSession<H> {
+    /// Outputs the public key A to send to the server.
+    pub fn public_key(&self) -> &[u8] {
+        &self.public_key
+    }
+
+    /// Outputs the proof M1 to send to the server.
+    pub fn proof(&self) -> &[u8] {
+        &self.proofs.client[..H::DIGEST_SIZE]
+    }
+
+    /// Verifies the server's proof M2, which shows that the server knows the verifier.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::TagMismatch` if verification fails.
+    pub fn verify_server(&self, proof: &[u8]) -> Result<(), Error> {
+        if !ct::constant_time_eq(proof, &self.proofs.server[..H::DIGEST_SIZE]) {
+            return Err(Error::TagMismatch);
+        }
+        Ok(())
+    }
+
+    /// Outputs the session key K, which should only be used once the server's proof is verified.
+    pub fn key(&self) -> &[u8] {
+        &self.proofs.key[..H::DIGEST_SIZE]
+    }
+}
+
+/// The server before it receives the client's public key.
+pub struct Server<H> {
+    group: Group,
+    exponent: Vec<u64>,
+    verifier: Vec<u64>,
+    public_key: Vec<u8>,
+    hash: PhantomData<H>,
+}
+
+impl<H: HashFunction> Server<H> {
+    /// Starts a login for a user with a stored verifier, with a secret exponent b drawn from
+    /// `rng`, computing the public key B = k v + g^b.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::InvalidLength` if `verifier` is not as long as N.
+    pub fn new(group: Group, verifier: &[u8], rng: &dyn SecureRandom) -> Result<Self, Error> {
+        if verifier.len() != group.size() {
+            return Err(Error::InvalidLength);
+        }
+        let (n, g, k) = group.parameters::<H>();
+        let verifier = n.to_montgomery(&n.reduce(&from_be_bytes(verifier)));
+        let exponent = random_exponent(rng);
+        let b = n.add(&n.mul(&k, &verifier), &n.pow(&g, &exponent));
+        let mut public_key = vec![0; group.size()];
+        to_be_bytes(&n.to_normal(&b), &mut public_key);
+        Ok(Self {
+            group,
+            exponent,
+            verifier,
+            public_key,
+            hash: PhantomData,
+        })
+    }
+
+    /// Outputs the public key B to send to the client along with the salt.
+    pub fn public_key(&self) -> &[u8] {
+        &self.public_key
+    }
+
+    /// Processes the client's public key A, computing the session key and proofs from the user's
+    /// identity and salt.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::InvalidKey` if `client_public_key` is not as long as N, is not below N,
+    /// or is 0, or if the scrambling parameter u = H(A | B) is 0.
+    pub fn process(
+        &self,
+        identity: &[u8],
+        salt: &[u8],
+        client_public_key: &[u8],
+    ) -> Result<ServerSession<H>, Error> {
+        let (n, _, _) = self.group.parameters::<H>();
+        let a = parse_public_key(&n, self.group, client_public_key)?;
+        let u = scrambler::<H>(client_public_key, &self.public_key)?;
+        // S = (A v^u)^b
+        let base = n.mul(&n.to_montgomery(&a), &n.pow(&self.verifier, &u));
+        let s = n.to_normal(&n.pow(&base, &self.exponent));
+        let proofs = Proofs::<H>::new(
+            self.group,
+            identity,
+            salt,
+            client_public_key,
+            &self.public_key,
+            &s,
+        );
+        Ok(ServerSession { proofs })
+    }
+}
+
+/// The server's session after processing the client's public key.
+pub struct ServerSession<H> {
+    proofs: Proofs<H>,
+}
+
+impl<H: HashFunction> ServerSession<H> {
+    /// Verifies the client's proof M1, which shows that the client knows the password, and
+    /// outputs the proof M2 to send to the client.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::TagMismatch` if verification fails, in which case the login must be
+    /// rejected without sending anything derived from the session key.
+    pub fn verify_client(&self, proof: &[u8]) -> Result<&[u8], Error> {
+        if !ct::constant_time_eq(proof, &self.proofs.client[..H::DIGEST_SIZE]) {
+            return Err(Error::TagMismatch);
+        }
+        Ok(&self.proofs.server[..H::DIGEST_SIZE])
+    }
+
+    /// Outputs the session key K, which should only be used once the client's proof is verified.
+    pub fn key(&self) -> &[u8] {
+        &self.proofs.key[..H::DIGEST_SIZE]
+    }
+}
+
+/// The session key and both proofs.
+struct Proofs<H> {
+    key: SecretKey<[u8; MAX_DIGEST_SIZE]>,
+    client: [u8; MAX_DIGEST_SIZE],
+    server: [u8; MAX_DIGEST_SIZE],
+    hash: PhantomData<H>,
+}
+
+impl<H: HashFunction> Proofs<H> {
+    fn new(
+        group: Group,
+        identity: &[u8],
+        salt: &[u8],
+        client_public_key: &[u8],
+        server_public_key: &[u8],
+        s: &[u64],
+    ) -> Self {
+        let len = H::DIGEST_SIZE;
+        let mut s_bytes = SecretKey::new(vec![0; group.size()]);
+        to_be_bytes(s, &mut s_bytes);
+        let key = SecretKey::new(hash::<H>(&[&s_bytes]));
+        let mut padded_g = vec![0; group.size()];
+        padded_g[group.size() - 1] = group.generator();
+        let mut group_hash = hash::<H>(&[group.prime()]);
+        for (x, y) in group_hash.iter_mut().zip(&hash::<H>(&[&padded_g])) {
+            *x ^= y;
+        }
+        let client = hash::<H>(&[
+            &group_hash[..len],
+            &hash::<H>(&[identity])[..len],
+            salt,
+            client_public_key,
+            server_public_key,
+            &key[..len],
+        ]);
+        let server = hash::<H>(&[client_public_key, &client[..len], &key[..len]]);
+        Self {
+            key,
+            client,
+            server,
+            hash: PhantomData,
+        }
+    }
+}
+
+fn hash<H: HashFunction>(parts: &[&[u8]]) -> [u8; MAX_DIGEST_SIZE] {
+    let mut hash = H::default();
+    for part in parts {
+        hash.update(part);
+    }
+    let mut digest = [0; MAX_DIGEST_SIZE];
+    hash.write_digest(&mut digest[..H::DIGEST_SIZE]);
+    digest
+}
+
+/// Computes x = H(s | H(I | ":" | P)).
+fn private_key<H: HashFunction>(identity: &[u8], password: &[u8], salt: &[u8]) -> Vec<u64> {
+    let inner = SecretKey::new(hash::<H>(&[identity, b":", password]));
+    let x = SecretKey::new(hash::<H>(&[salt, &inner[..H::DIGEST_SIZE]]));
+    from_be_bytes(&x[..H::DIGEST_SIZE])
+}
+
+/// Computes u = H(PAD(A) | PAD(B)), which must not be 0.
+fn scrambler<H: HashFunction>(
+    client_public_key: &[u8],
+    server_public_key: &[u8],
+) -> Result<Vec<u64>, Error> {
+    let u = hash::<H>(&[client_public_key, server_public_key]);
+    let u = from_be_bytes(&u[..H::DIGEST_SIZE]);
+    if is_zero(&u) {
+        return Err(Error::InvalidKey);
+    }
+    Ok(u)
+}
+
+/// Parses a public key, which must be a nonzero value below N.
+fn parse_public_key(n: &Modulus, group: Group, public_key: &[u8]) -> Result<Vec<u64>, Error> {
+    if public_key.len() != group.size() {
+        return Err(Error::InvalidKey);
+    }
+    let value = resize(&from_be_bytes(public_key), n.len());
+    if !n.contains(&value) || is_zero(&value) {
+        return Err(Error::InvalidKey);
+    }
+    Ok(value)
+}
+
+/// Draws a nonzero secret exponent of 256 bits, as RFC 5054 recommends at least.
+fn random_exponent(rng: &dyn SecureRandom) -> Vec<u64> {
+    let mut bytes = SecretKey::new([0; EXPONENT_LEN]);
+    loop {
+        rng.fill(&mut *bytes);
+        let exponent = from_be_bytes(&*bytes);
+        if !is_zero(&exponent) {
+            return exponent;
+        }
+    }
+}
+
+const SRP1024: [u8; 128] = [
+    0xee, 0xaf, 0x0a, 0xb9, 0xad, 0xb3, 0x8d, 0xd6, 0x9c, 0x33, 0xf8, 0x0a, 0xfa, 0x8f, 0xc5, 0xe8,
+    0x60, 0x72, 0x61, 0x87, 0x75, 0xff, 0x3c, 0x0b, 0x9e, 0xa2, 0x31, 0x4c, 0x9c, 0x25, 0x65, 0x76,
+    0xd6, 0x74, 0xdf, 0x74, 0x96, 0xea, 0x81, 0xd3, 0x38, 0x3b, 0x48, 0x13, 0xd6, 0x92, 0xc6, 0xe0,
+    0xe0, 0xd5, 0xd8, 0xe2, 0x50, 0xb9, 0x8b, 0xe4, 0x8e, 0x49, 0x5c, 0x1d, 0x60, 0x89, 0xda, 0xd1,
+    0x5d, 0xc7, 0xd7, 0xb4, 0x61, 0x54, 0xd6, 0xb6, 0xce, 0x8e, 0xf4, 0xad, 0x69, 0xb1, 0x5d, 0x49,
+    0x82, 0x55, 0x9b, 0x29, 0x7b, 0xcf, 0x18, 0x85, 0xc5, 0x29, 0xf5, 0x66, 0x66, 0x0e, 0x57, 0xec,
+    0x68, 0xed, 0xbc, 0x3c, 0x05, 0x72, 0x6c, 0xc0, 0x2f, 0xd4, 0xcb, 0xf4, 0x97, 0x6e, 0xaa, 0x9a,
+    0xfd, 0x51, 0x38, 0xfe, 0x83, 0x76, 0x43, 0x5b, 0x9f, 0xc6, 0x1d, 0x2f, 0xc0, 0xeb, 0x06, 0xe3,
+];
+
+const SRP2048: [u8; 256] = [
+    0xac, 0x6b, 0xdb, 0x41, 0x32, 0x4a, 0x9a, 0x9b, 0xf1, 0x66, 0xde, 0x5e, 0x13, 0x89, 0x58, 0x2f,
+    0xaf, 0x72, 0xb6, 0x65, 0x19, 0x87, 0xee, 0x07, 0xfc, 0x31, 0x92, 0x94, 0x3d, 0xb5, 0x60, 0x50,
+    0xa3, 0x73, 0x29, 0xcb, 0xb4, 0xa0, 0x99, 0xed, 0x81, 0x93, 0xe0, 0x75, 0x77, 0x67, 0xa1, 0x3d,
+    0xd5, 0x23, 0x12, 0xab, 0x4b, 0x03, 0x31, 0x0d, 0xcd, 0x7f, 0x48, 0xa9, 0xda, 0x04, 0xfd, 0x50,
+    0xe8, 0x08, 0x39, 0x69, 0xed, 0xb7, 0x67, 0xb0, 0xcf, 0x60, 0x95, 0x17, 0x9a, 0x16, 0x3a, 0xb3,
+    0x66, 0x1a, 0x05, 0xfb, 0xd5, 0xfa, 0xaa, 0xe8, 0x29, 0x18, 0xa9, 0x96, 0x2f, 0x0b, 0x93, 0xb8,
+    0x55, 0xf9, 0x79, 0x93, 0xec, 0x97, 0x5e, 0xea, 0xa8, 0x0d, 0x74, 0x0a, 0xdb, 0xf4, 0xff, 0x74,
+    0x73, 0x59, 0xd0, 0x41, 0xd5, 0xc3, 0x3e, 0xa7, 0x1d, 0x28, 0x1e, 0x44, 0x6b, 0x14, 0x77, 0x3b,
+    0xca, 0x97, 0xb4, 0x3a, 0x23, 0xfb, 0x80, 0x16, 0x76, 0xbd, 0x20, 0x7a, 0x43, 0x6c, 0x64, 0x81,
+    0xf1, 0xd2, 0xb9, 0x07, 0x87, 0x17, 0x46, 0x1a, 0x5b, 0x9d, 0x32, 0xe6, 0x88, 0xf8, 0x77, 0x48,
+    0x54, 0x45, 0x23, 0xb5, 0x24, 0xb0, 0xd5, 0x7d, 0x5e, 0xa7, 0x7a, 0x27, 0x75, 0xd2, 0xec, 0xfa,
+    0x03, 0x2c, 0xfb, 0xdb, 0xf5, 0x2f, 0xb3, 0x78, 0x61, 0x60, 0x27, 0x90, 0x04, 0xe5, 0x7a, 0xe6,
+    0xaf, 0x87, 0x4e, 0x73, 0x03, 0xce, 0x53, 0x29, 0x9c, 0xcc, 0x04, 0x1c, 0x7b, 0xc3, 0x08, 0xd8,
+    0x2a, 0x56, 0x98, 0xf3, 0xa8, 0xd0, 0xc3, 0x82, 0x71, 0xae, 0x35, 0xf8, 0xe9, 0xdb, 0xfb, 0xb6,
+    0x94, 0xb5, 0xc8, 0x03, 0xd8, 0x9f, 0x7a, 0xe4, 0x35, 0xde, 0x23, 0x6d, 0x52, 0x5f, 0x54, 0x75,
+    0x9b, 0x65, 0xe3, 0x72, 0xfc, 0xd6, 0x8e, 0xf2, 0x0f, 0xa7, 0x11, 0x1f, 0x9e, 0x4a, 0xff, 0x73,
+];
+
+const SRP3072: [u8; 384] = [
+    0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xc9, 0x0f, 0xda, 0xa2, 0x21, 0x68, 0xc2, 0x34,
+    0xc4, 0xc6, 0x62, 0x8b, 0x80, 0xdc, 0x1c, 0xd1, 0x29, 0x02, 0x4e, 0x08, 0x8a, 0x67, 0xcc, 0x74,
+    0x02, 0x0b, 0xbe, 0xa6, 0x3b, 0x13, 0x9b, 0x22, 0x51, 0x4a, 0x08, 0x79, 0x8e, 0x34, 0x04, 0xdd,
+    0xef, 0x95, 0x19, 0xb3, 0xcd, 0x3a, 0x43, 0x1b, 0x30, 0x2b, 0x0a, 0x6d, 0xf2, 0x5f, 0x14, 0x37,
+    0x4f, 0xe1, 0x35, 0x6d, 0x6d, 0x51, 0xc2, 0x45, 0xe4, 0x85, 0xb5, 0x76, 0x62, 0x5e, 0x7e, 0xc6,
+    0xf4, 0x4c, 0x42, 0xe9, 0xa6, 0x37, 0xed, 0x6b, 0x0b, 0xff, 0x5c, 0xb6, 0xf4, 0x06, 0xb7, 0xed,
+    0xee, 0x38, 0x6b, 0xfb, 0x5a, 0x89, 0x9f, 0xa5, 0xae, 0x9f, 0x24, 0x11, 0x7c, 0x4b, 0x1f, 0xe6,
+    0x49, 0x28, 0x66, 0x51, 0xec, 0xe4, 0x5b, 0x3d, 0xc2, 0x00, 0x7c, 0xb8, 0xa1, 0x63, 0xbf, 0x05,
+    0x98, 0xda, 0x48, 0x36, 0x1c, 0x55, 0xd3, 0x9a, 0x69, 0x16, 0x3f, 0xa8, 0xfd, 0x24, 0xcf, 0x5f,
+    0x83, 0x65, 0x5d, 0x23, 0xdc, 0xa3, 0xad, 0x96, 0x1c, 0x62, 0xf3, 0x56, 0x20, 0x85, 0x52, 0xbb,
+    0x9e, 0xd5, 0x29, 0x07, 0x70, 0x96, 0x96, 0x6d, 0x67, 0x0c, 0x35, 0x4e, 0x4a, 0xbc, 0x98, 0x04,
+    0xf1, 0x74, 0x6c, 0x08, 0xca, 0x18, 0x21, 0x7c, 0x32, 0x90, 0x5e, 0x46, 0x2e, 0x36, 0xce, 0x3b,
+    0xe3, 0x9e, 0x77, 0x2c, 0x18, 0x0e, 0x86, 0x03, 0x9b, 0x27, 0x83, 0xa2, 0xec, 0x07, 0xa2, 0x8f,
+    0xb5, 0xc5, 0x5d, 0xf0, 0x6f, 0x4c, 0x52, 0xc9, 0xde, 0x2b, 0xcb, 0xf6, 0x95, 0x58, 0x17, 0x18,
+    0x39, 0x95, 0x49, 0x7c, 0xea, 0x95, 0x6a, 0xe5, 0x15, 0xd2, 0x26, 0x18, 0x98, 0xfa, 0x05, 0x10,
+    0x15, 0x72, 0x8e, 0x5a, 0x8a, 0xaa, 0xc4, 0x2d, 0xad, 0x33, 0x17, 0x0d, 0x04, 0x50, 0x7a, 0x33,
+    0xa8, 0x55, 0x21, 0xab, 0xdf, 0x1c, 0xba, 0x64, 0xec, 0xfb, 0x85, 0x04, 0x58, 0xdb, 0xef, 0x0a,
+    0x8a, 0xea, 0x71, 0x57, 0x5d, 0x06, 0x0c, 0x7d, 0xb3, 0x97, 0x0f, 0x85, 0xa6, 0xe1, 0xe4, 0xc7,
+    0xab, 0xf5, 0xae, 0x8c, 0xdb, 0x09, 0x33, 0xd7, 0x1e, 0x8c, 0x94, 0xe0, 0x4a, 0x25, 0x61, 0x9d,
+    0xce, 0xe3, 0xd2, 0x26, 0x1a, 0xd2, 0xee, 0x6b, 0xf1, 0x2f, 0xfa, 0x06, 0xd9, 0x8a, 0x08, 0x64,
+    0xd8, 0x76, 0x02, 0x73, 0x3e, 0xc8, 0x6a, 0x64, 0x52, 0x1f, 0x2b, 0x18, 0x17, 0x7b, 0x20, 0x0c,
+    0xbb, 0xe1, 0x17, 0x57, 0x7a, 0x61, 0x5d, 0x6c, 0x77, 0x09, 0x88, 0xc0, 0xba, 0xd9, 0x46, 0xe2,
+    0x08, 0xe2, 0x4f, 0xa0, 0x74, 0xe5, 0xab, 0x31, 0x43, 0xdb, 0x5b, 0xfc, 0xe0, 0xfd, 0x10, 0x8e,
+    0x4b, 0x82, 0xd1, 0x20, 0xa9, 0x3a, 0xd2, 0xca, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+];
+
+const SRP4096: [u8; 512] = [
+    0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xc9, 0x0f, 0xda, 0xa2, 0x21, 0x68, 0xc2, 0x34,
+    0xc4, 0xc6, 0x62, 0x8b, 0x80, 0xdc, 0x1c, 0xd1, 0x29, 0x02, 0x4e, 0x08, 0x8a, 0x67, 0xcc, 0x74,
+    0x02, 0x0b, 0xbe, 0xa6, 0x3b, 0x13, 0x9b, 0x22, 0x51, 0x4a, 0x08, 0x79, 0x8e, 0x34, 0x04, 0xdd,
+    0xef, 0x95, 0x19, 0xb3, 0xcd, 0x3a, 0x43, 0x1b, 0x30, 0x2b, 0x0a, 0x6d, 0xf2, 0x5f, 0x14, 0x37,
+    0x4f, 0xe1, 0x35, 0x6d, 0x6d, 0x51, 0xc2, 0x45, 0xe4, 0x85, 0xb5, 0x76, 0x62, 0x5e, 0x7e, 0xc6,
+    0xf4, 0x4c, 0x42, 0xe9, 0xa6, 0x37, 0xed, 0x6b, 0x0b, 0xff, 0x5c, 0xb6, 0xf4, 0x06, 0xb7, 0xed,
+    0xee, 0x38, 0x6b, 0xfb, 0x5a, 0x89, 0x9f, 0xa5, 0xae, 0x9f, 0x24, 0x11, 0x7c, 0x4b, 0x1f, 0xe6,
+    0x49, 0x28, 0x66, 0x51, 0xec, 0xe4, 0x5b, 0x3d, 0xc2, 0x00, 0x7c, 0xb8, 0xa1, 0x63, 0xbf, 0x05,
+    0x98, 0xda, 0x48, 0x36, 0x1c, 0x55, 0xd3, 0x9a, 0x69, 0x16, 0x3f, 0xa8, 0xfd, 0x24, 0xcf, 0x5f,
+    0x83, 0x65, 0x5d, 0x23, 0xdc, 0xa3, 0xad, 0x96, 0x1c, 0x62, 0xf3, 0x56, 0x20, 0x85, 0x52, 0xbb,
+    0x9e, 0xd5, 0x29, 0x07, 0x70, 0x96, 0x96, 0x6d, 0x67, 0x0c, 0x35, 0x4e, 0x4a, 0xbc, 0x98, 0x04,
+    0xf1, 0x74, 0x6c, 0x08, 0xca, 0x18, 0x21, 0x7c, 0x32, 0x90, 0x5e, 0x46, 0x2e, 0x36, 0xce, 0x3b,
+    0xe3, 0x9e, 0x77, 0x2c, 0x18, 0x0e, 0x86, 0x03, 0x9b, 0x27, 0x83, 0xa2, 0xec, 0x07, 0xa2, 0x8f,
+    0xb5, 0xc5, 0x5d, 0xf0, 0x6f, 0x4c, 0x52, 0xc9, 0xde, 0x2b, 0xcb, 0xf6, 0x95, 0x58, 0x17, 0x18,
+    0x39, 0x95, 0x49, 0x7c, 0xea, 0x95, 0x6a, 0xe5, 0x15, 0xd2, 0x26, 0x18, 0x98, 0xfa, 0x05, 0x10,
+    0x15, 0x72, 0x8e, 0x5a, 0x8a, 0xaa, 0xc4, 0x2d, 0xad, 0x33, 0x17, 0x0d, 0x04, 0x50, 0x7a, 0x33,
+    0xa8, 0x55, 0x21, 0xab, 0xdf, 0x1c, 0xba, 0x64, 0xec, 0xfb, 0x85, 0x04, 0x58, 0xdb, 0xef, 0x0a,
+    0x8a, 0xea, 0x71, 0x57, 0x5d, 0x06, 0x0c, 0x7d, 0xb3, 0x97, 0x0f, 0x85, 0xa6, 0xe1, 0xe4, 0xc7,
+    0xab, 0xf5, 0xae, 0x8c, 0xdb, 0x09, 0x33, 0xd7, 0x1e, 0x8c, 0x94, 0xe0, 0x4a, 0x25, 0x61, 0x9d,
+    0xce, 0xe3, 0xd2, 0x26, 0x1a, 0xd2, 0xee, 0x6b, 0xf1, 0x2f, 0xfa, 0x06, 0xd9, 0x8a, 0x08, 0x64,
+    0xd8, 0x76, 0x02, 0x73, 0x3e, 0xc8, 0x6a, 0x64, 0x52, 0x1f, 0x2b, 0x18, 0x17, 0x7b, 0x20, 0x0c,
+    0xbb, 0xe1, 0x17, 0x57, 0x7a, 0x61, 0x5d, 0x6c, 0x77, 0x09, 0x88, 0xc0, 0xba, 0xd9, 0x46, 0xe2,
+    0x08, 0xe2, 0x4f, 0xa0, 0x74, 0xe5, 0xab, 0x31, 0x43, 0xdb, 0x5b, 0xfc, 0xe0, 0xfd, 0x10, 0x8e,
+    0x4b, 0x82, 0xd1, 0x20, 0xa9, 0x21, 0x08, 0x01, 0x1a, 0x72, 0x3c, 0x12, 0xa7, 0x87, 0xe6, 0xd7,
+    0x88, 0x71, 0x9a, 0x10, 0xbd, 0xba, 0x5b, 0x26, 0x99, 0xc3, 0x27, 0x18, 0x6a, 0xf4, 0xe2, 0x3c,
+    0x1a, 0x94, 0x68, 0x34, 0xb6, 0x15, 0x0b, 0xda, 0x25, 0x83, 0xe9, 0xca, 0x2a, 0xd4, 0x4c, 0xe8,
+    0xdb, 0xbb, 0xc2, 0xdb, 0x04, 0xde, 0x8e, 0xf9, 0x2e, 0x8e, 0xfc, 0x14, 0x1f, 0xbe, 0xca, 0xa6,
+    0x28, 0x7c, 0x59, 0x47, 0x4e, 0x6b, 0xc0, 0x5d, 0x99, 0xb2, 0x96, 0x4f, 0xa0, 0x90, 0xc3, 0xa2,
+    0x23, 0x3b, 0xa1, 0x86, 0x51, 0x5b, 0xe7, 0xed, 0x1f, 0x61, 0x29, 0x70, 0xce, 0xe2, 0xd7, 0xaf,
+    0xb8, 0x1b, 0xdd, 0x76, 0x21, 0x70, 0x48, 0x1c, 0xd0, 0x06, 0x91, 0x27, 0xd5, 0xb0, 0x5a, 0xa9,
+    0x93, 0xb4, 0xea, 0x98, 0x8d, 0x8f, 0xdd, 0xc1, 0x86, 0xff, 0xb7, 0xdc, 0x90, 0xa6, 0xc0, 0x8f,
+    0x4d, 0xf4, 0x35, 0xc9, 0x34, 0x06, 0x31, 0x99, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "legacy-hashes")]
+    use crate::sha1::InsecureSha1;
+    use crate::sha2::{Sha256, Sha512};
+    use crate::test_helpers::*;
+
+    const SALT: &str = "beb25379d1a8581eb5a727673a2441ee";
+    const A: &str = "60975527035cf2ad1989806f0407210bc81edc04e2762a56afd529ddda2d4393";
+    const B: &str = "e487cb59d31ac550471e81f00f6928e01dda08e974a004f49e61f5d105284d20";
+
+    fn fixed_rng(exponent: &str) -> impl Fn(&mut [u8]) {
+        let exponent = h2b(exponent);
+        move |dest: &mut [u8]| dest.copy_from_slice(&exponent)
+    }
+
+    fn check<H: HashFunction>(group: Group, expected: &[&str; 6]) {
+        let salt = &h2b(SALT);
+        let verifier = verifier::<H>(group, b"alice", b"password123", salt);
+        assert_eq!(h2b(expected[0]), verifier);
+        let client = Client::<H>::new(group, &fixed_rng(A));
+        assert_eq!(&h2b(expected[1])[..], client.public_key());
+        let server = Server::<H>::new(group, &verifier, &fixed_rng(B)).unwrap();
+        assert_eq!(&h2b(expected[2])[..], server.public_key());
+
+        let client = client
+            .process(b"alice", b"password123", salt, server.public_key())
+            .unwrap();
+        let server = server.process(b"alice", salt, client.public_key()).unwrap();
+        assert_eq!(&h2b(expected[3])[..], client.key());
+        assert_eq!(&h2b(expected[3])[..], server.key());
+        assert_eq!(&h2b(expected[4])[..], client.proof());
+        let server_proof = server.verify_client(client.proof()).unwrap();
+        assert_eq!(&h2b(expected[5])[..], server_proof);
+        client.verify_server(server_proof).unwrap();
+    }
+
+    // RFC 5054, Appendix B, with the key and proofs generated with Python
+    #[cfg(feature = "legacy-hashes")]
+    #[test]
+    fn test_rfc5054() {
+        check::<InsecureSha1>(
+            Group::Srp1024,
+            &[
+                "7e273de8696ffc4f4e337d05b4b375beb0dde1569e8fa00a9886d8129bada1f1\
+                 822223ca1a605b530e379ba4729fdc59f105b4787e5186f5c671085a1447b52a\
+                 48cf1970b4fb6f8400bbf4cebfbb168152e08ab5ea53d15c1aff87b2b9da6e04\
+                 e058ad51cc72bfc9033b564e26480d78e955a5e29e7ab245db2be315e2099afb",
+                "61d5e490f6f1b79547b0704c436f523dd0e560f0c64115bb72557ec44352e890\
+                 3211c04692272d8b2d1a5358a2cf1b6e0bfcf99f921530ec8e39356179eae45e\
+                 42ba92aeaced825171e1e8b9af6d9c03e1327f44be087ef06530e69f66615261\
+                 eef54073ca11cf5858f0edfdfe15efeab349ef5d76988a3672fac47b0769447b",
+                "bd0c61512c692c0cb6d041fa01bb152d4916a1e77af46ae105393011baf38964\
+                 dc46a0670dd125b95a981652236f99d9b681cbf87837ec996c6da04453728610\
+                 d0c6ddb58b318885d7d82c7f8deb75ce7bd4fbaa37089e6f9c6059f388838e7a\
+                 00030b331eb76840910440b1b27aaeaeeb4012b7d7665238a8e3fb004b117b58",
+                "017eefa1cefc5c2e626e21598987f31e0f1b11bb",
+                "62c71b289cb22a034b405667e1541202ce5d8e03",
+                "b475d7f2d75ce9537748005483e5d326048b59e9",
+            ],
+        );
+    }
+
+    // Generated with Python
+    #[test]
+    fn test_vectors() {
+        check::<Sha256>(
+            Group::Srp2048,
+            &[
+                "400272a61e185e23784e28a16a149dc60a3790fd45856f79a7070c44f7da1ca2\
+                 2f711cd5bc3592171a875c7812472916de2dcfafc22f7dead8f578f197054793\
+                 6f9eec686bb3df66ff57f724f6b907e83530812b4ffdbf614153e9fbfed4fc6d\
+                 972da70bb23f6ccd36ad08b72567fe6bcd2bacb713f2cdb9dc8f81f897f489bb\
+                 393067d66237a3e061902e72096d5ac1cd1d06c1cd648f7e56da5ec6e0094c1b\
+                 448c5d63ad2addec1e3d9a3aa7118a0410e53434ddbffc60eef5b82548bda5a2\
+                 f513209484d3221982ca74668a4d37330cc9cfe3b10f0db368293e43026e3a01\
+                 440ac732bc1cfb983b512d10296f6951ec5e567329af8e58d7c21ea6c778b0bd",
+                "4b700f8d48e69c9aae40c684ac7c7c03121e2b7602eb4c3514804ccada0ed401\
+                 9193a351ecc65a6f854ede91eb096e721b22d701c7adc64e9cedacd75f2e26bb\
+                 2f5e45dd53dc8dbeafffe82aa49fca0573444691212537a73cf80e2503925820\
+                 5a7edf4749b30adaf25877c62fcd09d6613598bcd4baf2a9727a53706a278148\
+                 992b2abb23ad5d512d269e16ca11bc0895b5a3b5ec4721cde40a8c39c796e94f\
+                 0be86dbbeb33da7037018983921aba3f5053195d5ac1da4e567e3c0e75d9e060\
+                 9f92e850657b2be4771f415b9cacc5c1ecedc30133bf6474f5022c6519d78076\
+                 0ca4d8d3b966b034bd73877c1b3b33f474b9c3c5299a1968f3e6cd3bfe84445a",
+                "410813e3063f3b4532f2d36413749f39c26c5ceeb1346d3995003c74544c30cb\
+                 a318f981281607ae68dbdc3bee9f0544ada6b13d8ac33217b670973152cf03ef\
+                 03797615e81dd305342c2e3bb035321d1fd717952e702b09682102d0a5aa25dc\
+                 ee01784a32b0684f75626ca3bf8aec874f2dc11f8926944b06f9948e8ad76490\
+                 25a58cd9dccdb6b210de00e2283e72baaf93a39b0417dfd1888f841f43d7d41c\
+                 75b58f654ccb2e8b9c875c42edc34fd3796200312f2abd19b7e2c54b5702cd1a\
+                 7f4d79fdf73bc418c96466ba122d45474ab6db553417715617f6c3b4a8764279\
+                 f086acc655e396f85812c90f6f932ce0586168c5deccc9f8beb6891ad13f7caf",
+                "899f35b485d44d577957e87cfdd48343d97ea2e0c3e8620594e0b8da9ce5da98",
+                "cf5fe5db823c3a24dd41b96945d07ea310e4c5e3430b2b53b2a912c1a37a6fb0",
+                "a2148a9fb1b29f2f7f6ce9555243d783d382a5c3778a8515387cec9d782c8abb",
+            ],
+        );
+        check::<Sha256>(
+            Group::Srp3072,
+            &[
+                "ebe52c3cac712e0be1ba08d4dae9d38c960c3f092ed0ee375b12173b1cbe8c2d\
+                 3380eb4b38f0c529b3c5b207226c2b4c7741249d965a1437abe480b1bb331584\
+                 673e6e0b83daaf3b2fa370f10dc3b8d599b419133bce88a42cc2675543563faa\
+                 999d939e90c1c8e7024b906811faa9034512890e21b07135a1ad306380aa5c94\
+                 62a21e0d635a20b076091145f8f1c0166efde224af4d0ce5ffb9857ddac93875\
+                 758e4f191131f5837e3dd5bbf8c3242879d0bd7bc9f75a0e492804c69b4fbac8\
+                 ec02844729a565b859f176e8fff9348deb0ff1ee429c2fffc14af6764dc0c457\
+                 8d41cd3907e21cde6db94aa64fb79113dde08144c77b404d15addb04d001579d\
+                 19bfa16830da366a7476a29aadcdef47423ca652f773eb0457c0816683913b63\
+                 f15a4b17d2bbf3da2dbf976fe5bf66db1cd5a02f0f4765bc636ebe151d95031c\
+                 6a3fc285c3df130be7cc7281f3664fecacb66be80571551d15a71b0599ef76b8\
+                 793f1e75eb611f33dd33a046d9c476df84c76c5fa85b6627e6c38756c84b271a",
+                "fab6f5d2615d1e323512e7991cc37443f487da604ca8c9230fcb04e541dce628\
+                 0b27ca4680b0374f179dc3bdc7553fe62459798c701ad864a91390a28c93b644\
+                 adbf9c00745b942b79f9012a21b9b78782319d83a1f8362866fbd6f46bfc0ddb\
+                 2e1ab6e4b45a9906b82e37f05d6f97f6a3eb6e182079759c4f6847837b62321a\
+                 c1b4fa68641fcb4bb98dd697a0c73641385f4bab25b793584cc39fc8d48d4bd8\
+                 67a9a3c10f8ea12170268e34fe3bbe6ff89998d60da2f3e4283cbec1393d52af\
+                 724a57230c604e9fbce583d7613e6bffd67596ad121a8707eec4694495703368\
+                 6a155f644d5c5863b48f61bdbf19a53eab6dad0a186b8c152e5f5d8cad4b0ef8\
+                 aa4ea5008834c3cd342e5e0f167ad04592cd8bd279639398ef9e114dfaaab919\
+                 e14e850989224ddd98576d79385d2210902e9f9b1f2d86cfa47ee244635465f7\
+                 1058421a0184be51dd10cc9d079e6f1604e7aa9b7cf7883c7d4ce12b06ebe160\
+                 81e23f27a231d18432d7d1bb55c28ae21ffcf005f57528d15a88881bb3bbb7fe",
+                "e4e338879ef55928d8c6242187fc514f5118675749ecbdd76cbd01759ff6f943\
+                 99e655fe92d656bedfebfe5f4444be01c74ef747f11a7deb67ac99ef9837bc37\
+                 e5a2c94ccbac1b1b12a1e9be0eb0ae5e0f74b314e3a7625b2e11364dfba043ee\
+                 facc9da0bb0aa14f3806b079fadb55abb1362cc8090924fa13e30f4198dc3fcf\
+                 012d69c8eb32e4a93126a830f37709c07dfdf8e66c0fa6d00ad4efc3f03956cb\
+                 b06f7d2739c14f8bf057ca9282117659114dc265535f7a49db4fefe558a36d80\
+                 b96bddb96feba4bfc62be99b19b7bf363d59c0e47d1465ad768df7b0f4c4a3be\
+                 2ab58c9f60591c485b96a0d02481a58c7df3926d4df09a80b80f197cf3c4e20c\
+                 b8bd6fb4951fe3289c1774a96581e2489f19f0f8671e0fd7547c212fa328b7b9\
+                 3039fec06e55fa7bb2cc373ba1c5f3abaf10f2a42dbe741421757709c9d23440\
+                 ff2a256f9909b04ebacabf95efcd5676c74bb4f7f4a188643511cb64fa5f0080\
+                 9dea47998fba13c8eb5de902e89870ec1ea189de5e5d6a990391fd324644f83f",
+                "468f4bb304eb97c9c5141ba81e44369a929c3aa7d695078cc7ed7761915d0396",
+                "37c7b1e4db76afa5b974c3aa24694f4391ae83136e1c0753d5f2333e5b39b8ed",
+                "b9f63124468019f12bc0f4a6b9ec742b22895c96c2ec4e9ff616ca5fbf85e912",
+            ],
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let salt = &[0x42; 16];
+        let rng = &fixed_rng(A);
+        for &group in &[Group::Srp1024, Group::Srp2048, Group::Srp4096] {
+            let verifier = verifier::<Sha512>(group, b"alice", b"password", salt);
+            let client = Client::<Sha512>::new(group, rng);
+            let server = Server::<Sha512>::new(group, &verifier, &fixed_rng(B)).unwrap();
+            let client = client
+                .process(b"alice", b"password", salt, server.public_key())
+                .unwrap();
+            let server = server.process(b"alice", salt, client.public_key()).unwrap();
+            let server_proof = server.verify_client(client.proof()).unwrap();
+            client.verify_server(server_proof).unwrap();
+            assert_eq!(client.key(), server.key());
+            assert_eq!(Sha512::DIGEST_SIZE, client.key().len());
+        }
+    }
+
+    #[test]
+    fn test_invalid() {
+        let group = Group::Srp2048;
+        let salt = &[0x42; 16];
+        let verifier = verifier::<Sha256>(group, b"alice", b"password", salt);
+        let client = Client::<Sha256>::new(group, &fixed_rng(A));
+        let server = Server::<Sha256>::new(group, &verifier, &fixed_rng(B)).unwrap();
+
+        let wrong = client
+            .process(b"alice", b"wrong", salt, server.public_key())
+            .unwrap();
+        let session = server.process(b"alice", salt, wrong.public_key()).unwrap();
+        assert_eq!(
+            Err(Error::TagMismatch),
+            session.verify_client(wrong.proof())
+        );
+        let other = server.process(b"bob", salt, client.public_key()).unwrap();
+        let client = client
+            .process(b"alice", b"password", salt, server.public_key())
+            .unwrap();
+        assert_eq!(Err(Error::TagMismatch), other.verify_client(client.proof()));
+        assert_eq!(
+            Err(Error::TagMismatch),
+            client.verify_server(client.proof())
+        );
+
+        let n = group.prime();
+        let mut n_plus_one = n.to_vec();
+        n_plus_one[n.len() - 1] += 1;
+        for public_key in &[&[0; 256][..], &[0; 255], &[1; 257], n, &n_plus_one] {
+            let result = server.process(b"alice", salt, public_key);
+            assert_eq!(Err(Error::InvalidKey), result.map(|_| ()));
+            let result = Client::<Sha256>::new(group, &fixed_rng(A)).process(
+                b"alice",
+                b"password",
+                salt,
+                public_key,
+            );
+            assert_eq!(Err(Error::InvalidKey), result.map(|_| ()));
+        }
+        for verifier in &[&verifier[1..], &[0; 257]] {
+            let result = Server::<Sha256>::new(group, verifier, &fixed_rng(B));
+            assert_eq!(Err(Error::InvalidLength), result.map(|_| ()));
+        }
+    }
+}
@@ -0,0 +1,571 @@
+//! Module for the TLS 1.2 pseudorandom function and key derivation (RFC 5246), and record
+//! protection for AES-GCM cipher suites (RFC 5288).
+//!
+//! `prf` is the TLS 1.2 PRF built on HMAC with the hash function of the cipher suite. A
+//! `MasterSecret` is derived from the pre-master secret, either from both randoms or, with the
+//! extended master secret extension (RFC 7627), from the session hash. It computes the Finished
+//! messages' verify data and expands into a `KeyBlock` holding the write keys and implicit nonces
+//! for each direction, which protect records with `RecordProtection`.
+//!
+//! These are meant for checking the crate's primitives against captured TLS 1.2 traffic, for
+//! example with a master secret from an SSLKEYLOGFILE. A cipher suite is chosen by its type,
+//! which must implement `CipherSuite`.
+//!
+//! # Examples
+//!
+//! ```
+//! use crypto_pure::tls12::{EcdheRsaAes128GcmSha256, MasterSecret};
+//! use crypto_pure::tls13::record::ContentType;
+//! # let pre_master_secret = &[0x42; 32];
+//! # let client_random = &[1; 32];
+//! # let server_random = &[2; 32];
+//! let master_secret = MasterSecret::<EcdheRsaAes128GcmSha256>::new(
+//!     pre_master_secret,
+//!     client_random,
+//!     server_random,
+//! );
+//! let key_block = master_secret.key_block(client_random, server_random);
+//! let mut sender = key_block.client_protection();
+//! let mut receiver = key_block.client_protection();
+//! let record = &mut [0; 64];
+//! let len = sender.protect(ContentType::ApplicationData, b"message", record)?;
+//! let (content_type, content) = receiver.unprotect(&mut record[..len])?;
+//! assert_eq!(ContentType::ApplicationData, content_type);
+//! assert_eq!(b"message", content);
+//! # Ok::<(), crypto_pure::error::Error>(())
+//! ```
+use crate::aead::Aead;
+use crate::aes::{Aes128, Aes256};
+use crate::error::Error;
+use crate::gcm::Gcm;
+use crate::hmac::Hmac;
+use crate::secret::SecretKey;
+use crate::sha2::{HashFunction, Sha256, Sha384};
+use crate::tls13::record::{parse_header, ContentType, HEADER_LEN, MAX_CONTENT_LEN};
+use byteorder::{BigEndian, ByteOrder as _};
+use core::marker::PhantomData;
+
+/// The length of a master secret.
+pub const MASTER_SECRET_LEN: usize = 48;
+
+/// The length of the verify data in a Finished message.
+pub const VERIFY_DATA_LEN: usize = 12;
+
+const RANDOM_LEN: usize = 32;
+const MAX_KEY_LEN: usize = 32;
+const FIXED_IV_LEN: usize = 4;
+const EXPLICIT_NONCE_LEN: usize = 8;
+
+/// A TLS 1.2 cipher suite with an AEAD taking a 4-byte implicit and an 8-byte explicit nonce.
+pub trait CipherSuite {
+    /// The identifier of the cipher suite.
+    const ID: u16;
+    /// The hash function for the PRF and the transcript.
+    type Hash: HashFunction;
+    /// The AEAD for record protection, which must take 12-byte nonces.
+    type Aead: Aead;
+}
+
+/// The TLS_ECDHE_RSA_WITH_AES_128_GCM_SHA256 cipher suite.
+pub struct EcdheRsaAes128GcmSha256;
+
+/// The TLS_ECDHE_RSA_WITH_AES_256_GCM_SHA384 cipher suite.
+pub struct EcdheRsaAes256GcmSha384;
+
+impl CipherSuite for EcdheRsaAes128GcmSha256 {
+    const ID: u16 = 0xc02f;
+    type Hash = Sha256;
+    type Aead = Gcm<Aes128>;
+}
+
+impl CipherSuite for EcdheRsaAes256GcmSha384 {
+    const ID: u16 = 0xc030;
+    type Hash = Sha384;
+    type Aead = Gcm<Aes256>;
+}
+
+/// Fills `output` with the TLS 1.2 PRF of `secret`, `label` and `seed`, which is P_hash with
+/// `label || seed` as the seed.
+pub fn prf<H: HashFunction>(secret: &[u8], label: &[u8], seed: &[u8], output: &mut [u8]) {
+    let hmac = Hmac::<H>::new(secret);
+    // A(1) = HMAC(secret, label || seed) and A(i + 1) = HMAC(secret, A(i))
+    let mut a = hmac.clone();
+    a.update(label);
+    a.update(seed);
+    let mut a = a.tag();
+    for chunk in output.chunks_mut(H::DIGEST_SIZE) {
+        let mut block = hmac.clone();
+        block.update(&a);
+        block.update(label);
+        block.update(seed);
+        chunk.copy_from_slice(&block.tag()[..chunk.len()]);
+        let mut next = hmac.clone();
+        next.update(&a);
+        a = next.tag();
+    }
+}
+
+/// The master secret of a connection.
+pub struct MasterSecret<S> {
+    secret: SecretKey<[u8; MASTER_SECRET_LEN]>,
+    cipher_suite: PhantomData<S>,
+}
+
+impl<S: CipherSuite> MasterSecret<S> {
+    /// Derives the master secret from the pre-master secret and the randoms of the hellos.
+    pub fn new(
+        pre_master_secret: &[u8],
+        client_random: &[u8; RANDOM_LEN],
+        server_random: &[u8; RANDOM_LEN],
+    ) -> Self {
+        let mut seed = [0; 2 * RANDOM_LEN];
+        seed[..RANDOM_LEN].copy_from_slice(client_random);
+        seed[RANDOM_LEN..].copy_from_slice(server_random);
+        Self::derive(pre_master_secret, b"master secret", &seed)
+    }
+
+    /// Derives the master secret with the extended master secret extension, given the hash of
+    /// the handshake messages up to and including the ClientKeyExchange.
+    pub fn extended(pre_master_secret: &[u8], session_hash: &[u8]) -> Self {
+        Self::derive(pre_master_secret, b"extended master secret", session_hash)
+    }
+
+    /// Uses a master secret obtained otherwise, such as from a key log.
+    pub fn from_bytes(secret: &[u8; MASTER_SECRET_LEN]) -> Self {
+        Self {
+            secret: SecretKey::new(*secret),
+            cipher_suite: PhantomData,
+        }
+    }
+
+    /// Outputs the master secret itself.
+    pub fn secret(&self) -> &[u8] {
+        &*self.secret
+    }
+
+    /// Computes the verify data of the client's Finished message given the hash of the
+    /// handshake messages before it.
+    pub fn client_verify_data(&self, handshake_hash: &[u8]) -> [u8; VERIFY_DATA_LEN] {
+        self.verify_data(b"client finished", handshake_hash)
+    }
+
+    /// Computes the verify data of the server's Finished message given the hash of the
+    /// handshake messages before it.
+    pub fn server_verify_data(&self, handshake_hash: &[u8]) -> [u8; VERIFY_DATA_LEN] {
+        self.verify_data(b"server finished", handshake_hash)
+    }
+
+    /// Expands the master secret into the key block, given the randoms of the hellos.
+    pub fn key_block(
+        &self,
+        client_random: &[u8; RANDOM_LEN],
+        server_random: &[u8; RANDOM_LEN],
+    ) -> KeyBlock<S> {
+        // The randoms are in the opposite order from the master secret's derivation.
+        let mut seed = [0; 2 * RANDOM_LEN];
+        seed[..RANDOM_LEN].copy_from_slice(server_random);
+        seed[RANDOM_LEN..].copy_from_slice(client_random);
+        let mut buffer = SecretKey::new([0; 2 * (MAX_KEY_LEN + FIXED_IV_LEN)]);
+        let len = 2 * (S::Aead::KEY_LEN + FIXED_IV_LEN);
+        prf::<S::Hash>(&*self.secret, b"key expansion", &seed, &mut buffer[..len]);
+        KeyBlock {
+            buffer,
+            cipher_suite: PhantomData,
+        }
+    }
+
+    fn derive(pre_master_secret: &[u8], label: &[u8], seed: &[u8]) -> Self {
+        let mut secret = SecretKey::new([0; MASTER_SECRET_LEN]);
+        prf::<S::Hash>(pre_master_secret, label, seed, &mut *secret);
+        Self {
+            secret,
+            cipher_suite: PhantomData,
+        }
+    }
+
+    fn verify_data(&self, label: &[u8], handshake_hash: &[u8]) -> [u8; VERIFY_DATA_LEN] {
+        let mut verify_data = [0; VERIFY_DATA_LEN];
+        prf::<S::Hash>(&*self.secret, label, handshake_hash, &mut verify_data);
+        verify_data
+    }
+}
+
+/// The keys and implicit nonces expanded from a master secret.
+pub struct KeyBlock<S> {
+    buffer: SecretKey<[u8; 2 * (MAX_KEY_LEN + FIXED_IV_LEN)]>,
+    cipher_suite: PhantomData<S>,
+}
+
+impl<S: CipherSuite> KeyBlock<S> {
+    /// Outputs the key protecting the client's records.
+    pub fn client_write_key(&self) -> &[u8] {
+        &self.buffer[..S::Aead::KEY_LEN]
+    }
+
+    /// Outputs the key protecting the server's records.
+    pub fn server_write_key(&self) -> &[u8] {
+        &self.buffer[S::Aead::KEY_LEN..2 * S::Aead::KEY_LEN]
+    }
+
+    /// Outputs the implicit part of the nonces of the client's records.
+    pub fn client_write_iv(&self) -> &[u8] {
+        let start = 2 * S::Aead::KEY_LEN;
+        &self.buffer[start..start + FIXED_IV_LEN]
+    }
+
+    /// Outputs the implicit part of the nonces of the server's records.
+    pub fn server_write_iv(&self) -> &[u8] {
+        let start = 2 * S::Aead::KEY_LEN + FIXED_IV_LEN;
+        &self.buffer[start..start + FIXED_IV_LEN]
+    }
+
+    /// Outputs the protection of the client's records, starting from sequence number 0.
+    pub fn client_protection(&self) -> RecordProtection<S> {
+        RecordProtection::new(self.client_write_key(), self.client_write_iv())
+    }
+
+    /// Outputs the protection of the server's records, starting from sequence number 0.
+    pub fn server_protection(&self) -> RecordProtection<S> {
+        RecordProtection::new(self.server_write_key(), self.server_write_iv())
+    }
+}
+
+/// The protection of records in one direction.
+///
+/// The explicit part of each record's nonce is its sequence number.
+pub struct RecordProtection<S: CipherSuite> {
+    aead: S::Aead,
+    fixed_iv: [u8; FIXED_IV_LEN],
+    sequence: u64,
+}
+
+impl<S: CipherSuite> RecordProtection<S> {
+    fn new(key: &[u8], fixed_iv: &[u8]) -> Self {
+        let mut iv = [0; FIXED_IV_LEN];
+        iv.copy_from_slice(fixed_iv);
+        Self {
+            aead: S::Aead::new(key).expect("invalid key length"),
+            fixed_iv: iv,
+            sequence: 0,
+        }
+    }
+
+    /// Outputs the sequence number of the next record.
+    pub fn sequence(&self) -> u64 {
+        self.sequence
+    }
+
+    /// Outputs the length of the record protecting content of length `len`.
+    pub fn record_len(len: usize) -> usize {
+        HEADER_LEN + EXPLICIT_NONCE_LEN + len + S::Aead::TAG_LEN
+    }
+
+    /// Protects `content` of type `content_type`, writing the record, including its header, into
+    /// the start of `output` and outputting its length.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::InvalidLength` if `content` is longer than `MAX_CONTENT_LEN` or
+    /// `output` is too short, or with `Error::NonceExhausted` if 2^64 - 1 records have been
+    /// protected.
+    pub fn protect(
+        &mut self,
+        content_type: ContentType,
+        content: &[u8],
+        output: &mut [u8],
+    ) -> Result<usize, Error> {
+        let len = Self::record_len(content.len());
+        if content.len() > MAX_CONTENT_LEN || output.len() < len {
+            return Err(Error::InvalidLength);
+        }
+        let (nonce, explicit_nonce) = self.nonce(None)?;
+        let (header, payload) = output[..len].split_at_mut(HEADER_LEN);
+        header[..3].copy_from_slice(&[content_type.to_u8(), 3, 3]);
+        BigEndian::write_u16(&mut header[3..], payload.len() as u16);
+        let (explicit, buffer) = payload.split_at_mut(EXPLICIT_NONCE_LEN);
+        explicit.copy_from_slice(&explicit_nonce);
+        buffer[..content.len()].copy_from_slice(content);
+        let data = self.additional_data(content_type, content.len());
+        self.aead.seal_in_place(&nonce, &data, buffer)?;
+        self.sequence += 1;
+        Ok(len)
+    }
+
+    /// Unprotects a record, including its header, in place, outputting the content type and
+    /// the content.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::InvalidEncoding` if the header is not valid, with
+    /// `Error::InvalidLength` if the record's length is not valid, with `Error::NonceExhausted`
+    /// if 2^64 - 1 records have been unprotected, or with `Error::TagMismatch` if the record fails
+    /// to decrypt.
+    pub fn unprotect<'a>(
+        &mut self,
+        record: &'a mut [u8],
+    ) -> Result<(ContentType, &'a mut [u8]), Error> {
+        if record.len() < HEADER_LEN {
+            return Err(Error::InvalidLength);
+        }
+        let (header, payload) = record.split_at_mut(HEADER_LEN);
+        let mut header_array = [0; HEADER_LEN];
+        header_array.copy_from_slice(header);
+        let (content_type, len) = parse_header(&header_array)?;
+        if header[2] != 3 {
+            return Err(Error::InvalidEncoding);
+        }
+        if len != payload.len()
+            || len < EXPLICIT_NONCE_LEN + S::Aead::TAG_LEN
+            || len > EXPLICIT_NONCE_LEN + MAX_CONTENT_LEN + S::Aead::TAG_LEN
+        {
+            return Err(Error::InvalidLength);
+        }
+        let (explicit, buffer) = payload.split_at_mut(EXPLICIT_NONCE_LEN);
+        let (nonce, _) = self.nonce(Some(explicit))?;
+        let data = self.additional_data(content_type, buffer.len() - S::Aead::TAG_LEN);
+        let content = self.aead.open_in_place(&nonce, &data, buffer)?;
+        self.sequence += 1;
+        Ok((content_type, content))
+    }
+
+    /// Builds the nonce from the explicit part of a received record, or from the sequence
+    /// number when sending, outputting both.
+    fn nonce(
+        &self,
+        explicit: Option<&[u8]>,
+    ) -> Result<
+        (
+            [u8; FIXED_IV_LEN + EXPLICIT_NONCE_LEN],
+            [u8; EXPLICIT_NONCE_LEN],
+        ),
+        Error,
+    > {
+        if self.sequence == u64::MAX {
+            return Err(Error::NonceExhausted);
+        }
+        let mut explicit_nonce = self.sequence.to_be_bytes();
+        if let Some(explicit) = explicit {
+            explicit_nonce.copy_from_slice(explicit);
+        }
+        let mut nonce = [0; FIXED_IV_LEN + EXPLICIT_NONCE_LEN];
+        nonce[..FIXED_IV_LEN].copy_from_slice(&self.fixed_iv);
+        nonce[FIXED_IV_LEN..].copy_from_slice(&explicit_nonce);
+        Ok((nonce, explicit_nonce))
+    }
+
+    fn additional_data(&self, content_type: ContentType, len: usize) -> [u8; 13] {
+        let mut data = [0; 13];
+        BigEndian::write_u64(&mut data, self.sequence);
+        data[8..11].copy_from_slice(&[content_type.to_u8(), 3, 3]);
+        BigEndian::write_u16(&mut data[11..], len as u16);
+        data
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sha2::Digest;
+    use crate::test_helpers::*;
+    use std::vec::Vec;
+
+    // Captured between OpenSSL's s_client and s_server, with the master secret from the key log
+    const SERVER: &str = "16030300410200003d0303694bb3f6d030177bb4ccbe16aff58db33b928cc559\
+        74db5d6119cee57f17073800c02f000015ff01000100000b0004030001020023\
+        00000017000016030302530b00024f00024c00024930820245308201eba00302\
+        0102020103300a06082a8648ce3d04030230123110300e06035504030c075465\
+        73742043413020170d3236313031353130323130385a180f3231323630393231\
+        3130323130385a30143112301006035504030c096c6f63616c686f7374308201\
+        22300d06092a864886f70d01010105000382010f003082010a0282010100a44a\
+        686316008cc46e2432c52ccf9ec9e4f7e99de80e5399ee72d6369b66961c579c\
+        e7a7c571ddbacbe7bbfa710e9d82765154e36170752fb9243bb2705400762b4b\
+        2ca004bb2e1d8e0a523523f39a7bcb52fc11f5745cb4fbb80b3107e9163dc373\
+        d4cc39c5b84942c71feab9fe87baec2f56d6309bcc0377ad79d0b80e954dba39\
+        28737d6acd7d3ed9726b9c92c76f85bdf72533e1a774585e71fbd062c27f4f1a\
+        7376a72578d29444fa4afab94e1bc559e71d1bd34f12ea29d6c97ea90b04b2df\
+        88e80de6ece0fd3a549d0ca2d60e1fa84d0e003b1588b46b1ca0c32c4c592b07\
+        fb64a42de177d4df8119063e646768a1e94d09f4907eb5518aaddf0bda0f0203\
+        010001a363306130140603551d11040d300b82096c6f63616c686f7374300906\
+        03551d1304023000301d0603551d0e041604141b34710cde2b2723cd7e796be5\
+        28f0c523da5189301f0603551d23041830168014728f4c5b90197ee2a912f99a\
+        2b7c553b7943ad71300a06082a8648ce3d040302034800304502203b476331ea\
+        5615e4fb4c7e6877282cae5ecc477e93cfe458e00843e18fffb605022100935e\
+        ed492b3857f6bcffebcda68baa3f1f9a7ac7f5f24729ebb3d3c92d7cb4151603\
+        03012c0c00012803001d20a739ec783f853f9bae2217be9c53b0fc5013ab7db5\
+        b8572507f825938e19740608040100244376148fa259c6c5ced8cc994b963a5d\
+        7be6853d4402883fe194c94f561bcd188726a71f03f9606caff7a45fbda2cc75\
+        5fef01610c1bf389744647f087ffc47f57d76831f93cedf8b5b04e3b7220592f\
+        c4384f73e578ccdb982442e8b4a5b961c88ea99781fb446ce03276410ce7d217\
+        db76ba4a94b59c5c286727d9680c58b8dfbe9fd4b0450f60e8bd0ca721592ff2\
+        3155aed0a2f2c932c971141112afac7a3b49bba8513bf9acef199f31df258fda\
+        91656a25c4d2dbb874ce229c567b317aa8cde86fbc220a28e0202c5357a542e7\
+        116db8bc43bbd2451e02beb0481e9673ef2f0f43a63d79ddad32b70afc8eef47\
+        d9b7b2e45d0ab4a5a77ac379148e4616030300040e00000016030300ba040000\
+        b600001c2000b01f725d48fd20de2b50bff650b77c7fd490c54315ef359a19b2\
+        af3b3fc7c6c0cacb4eb87b4ea4904f8e29caf869bb577b59465f82c0ce74cb2f\
+        009b6ac531d7fbc54fcd04931ad02502791363c1ccc3fcbbbc20f7d8d080a132\
+        7fe4708fe9da6dfa38fb29bb4a5c26e464c97f192fbe074946ce54998766f0f8\
+        1b8457d6138a4166b9fc1e81c6bf43025a51bd468f6269dfaf38816c1c275082\
+        b1535fed0edfab02170f45d89b55232b7939a23c74c448140303000101160303\
+        0028e63affd5d39180d8e589d3b94ca665be066b000dce656d0d24cdc865e3b1\
+        683137f316459c034521170303001ee63affd5d39180d9ba5c0bb471e9aeded2\
+        3a023337ba747f247d02ba4ad4";
+    const CLIENT: &str = "16030100860100008203038e66c6f8833dd66b3a77205699d7763f1cfad499de\
+        3dc2b3cad700ca08248054000002c02f01000057ff01000100000b0004030001\
+        02000a000c000a001d0017001e00180019002300000016000000170000000d00\
+        2a0028040305030603080708080809080a080b08040805080604010501060103\
+        030301030204020502060216030300251000002120eae88b5e5d2c53d1a88b8e\
+        06b39c4b4b2585045d4108756e62385c875431d9231403030001011603030028\
+        656908e159f196a561045f16cc120a9aead9cde1871d4ff2df23ee9851249beb\
+        add42f134c5effcc170303001e656908e159f196a6104b53a9f00b85064cb764\
+        f4afee2bd826d2a08951f8";
+    const MASTER_SECRET: &str = "53b55a4bacd991f39a987c98474ad7234b33821f265c1efa\
+        28199452366d5f2b94d23cdaa91ef08c76d31263f6b8af07";
+
+    fn records(mut bytes: &mut [u8]) -> Vec<&mut [u8]> {
+        let mut records = Vec::new();
+        while !bytes.is_empty() {
+            let len = HEADER_LEN + BigEndian::read_u16(&bytes[3..]) as usize;
+            let (record, rest) = bytes.split_at_mut(len);
+            records.push(record);
+            bytes = rest;
+        }
+        records
+    }
+
+    fn random(record: &[u8]) -> [u8; RANDOM_LEN] {
+        let mut random = [0; RANDOM_LEN];
+        random.copy_from_slice(&record[HEADER_LEN + 6..][..RANDOM_LEN]);
+        random
+    }
+
+    #[test]
+    fn test_capture() {
+        let client = &mut h2b(CLIENT);
+        let server = &mut h2b(SERVER);
+        let mut client_records = records(client);
+        let mut server_records = records(server);
+        assert_eq!(5, client_records.len());
+        assert_eq!(8, server_records.len());
+        let client_random = &random(client_records[0]);
+        let server_random = &random(server_records[0]);
+        let master_secret = &mut [0; MASTER_SECRET_LEN];
+        master_secret.copy_from_slice(&h2b(MASTER_SECRET));
+        let master_secret = MasterSecret::<EcdheRsaAes128GcmSha256>::from_bytes(master_secret);
+        let key_block = master_secret.key_block(client_random, server_random);
+        let mut client_protection = key_block.client_protection();
+        let mut server_protection = key_block.server_protection();
+
+        // ClientHello, ServerHello, Certificate, ServerKeyExchange, ServerHelloDone and
+        // ClientKeyExchange, then the client's Finished after its ChangeCipherSpec
+        let mut transcript = Sha256::default();
+        transcript.update(&client_records[0][HEADER_LEN..]);
+        for record in server_records[..4].iter().chain(&client_records[1..2]) {
+            transcript.update(&record[HEADER_LEN..]);
+        }
+        let (content_type, finished) = client_protection.unprotect(client_records[3]).unwrap();
+        assert_eq!(ContentType::Handshake, content_type);
+        let verify_data = master_secret.client_verify_data(&Digest::new(transcript.clone()));
+        assert_eq!(&[20, 0, 0, 12], &finished[..4]);
+        assert_eq!(&verify_data[..], &finished[4..]);
+        transcript.update(finished);
+
+        // NewSessionTicket, then the server's Finished after its ChangeCipherSpec
+        transcript.update(&server_records[4][HEADER_LEN..]);
+        let (content_type, finished) = server_protection.unprotect(server_records[6]).unwrap();
+        assert_eq!(ContentType::Handshake, content_type);
+        let verify_data = master_secret.server_verify_data(&Digest::new(transcript));
+        assert_eq!(&verify_data[..], &finished[4..]);
+
+        // s_server -rev reverses each line
+        let (content_type, content) = client_protection.unprotect(client_records[4]).unwrap();
+        assert_eq!(ContentType::ApplicationData, content_type);
+        assert_eq!(b"hello\n", content);
+        let (_, content) = server_protection.unprotect(server_records[7]).unwrap();
+        assert_eq!(b"olleh\n", content);
+        assert_eq!(2, server_protection.sequence());
+    }
+
+    // Generated by OpenSSL's TLS1-PRF KDF
+    #[test]
+    fn test_prf() {
+        let output = &mut [0; 100];
+        prf::<Sha256>(
+            &h2b("9bbe436ba940f017b17652849a71db35"),
+            b"test label",
+            &h2b("a0ba9f936cda311827a6f796ffd5198c"),
+            output,
+        );
+        assert_eq!(
+            &h2b(
+                "e3f229ba727be17b8d122620557cd453c2aab21d07c3d495329b52d4e61edb5a\
+                 6b301791e90d35c9c9a46b4e14baf9af0fa022f7077def17abfd3797c0564bab\
+                 4fbc91666e9def9b97fce34f796789baa48082d122ee42c5a72e5a5110fff701\
+                 87347b66"
+            )[..],
+            &output[..]
+        );
+        let output = &mut [0; 148];
+        prf::<Sha384>(
+            &h2b("b80b733d6ceefcdc71566ea48e5567df"),
+            b"test label",
+            &h2b("cd665cf6a8447dd6ff8b27555edb7465"),
+            output,
+        );
+        assert_eq!(
+            &h2b(
+                "7b0c18e9ced410ed1804f2cfa34a336a1c14dffb4900bb5fd7942107e81c83cd\
+                 e9ca0faa60be9fe34f82b1233c9146a0e534cb400fed2700884f9dc236f80edd\
+                 8bfa961144c9e8d792eca722a7b32fc3d416d473ebc2c5fd4abfdad05d918425\
+                 9b5bf8cd4d90fa0d31e2dec479e4f1a26066f2eea9a69236a3e52655c9e9aee6\
+                 91c8f3a26854308d5eaa3be85e0990703d73e56f"
+            )[..],
+            &output[..]
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let master_secret =
+            MasterSecret::<EcdheRsaAes256GcmSha384>::new(&[0x42; 32], &[1; 32], &[2; 32]);
+        let key_block = master_secret.key_block(&[1; 32], &[2; 32]);
+        assert_eq!(32, key_block.client_write_key().len());
+        assert_ne!(key_block.client_write_iv(), key_block.server_write_iv());
+        let mut sender = key_block.server_protection();
+        let mut receiver = key_block.server_protection();
+        let record = &mut [0; 64];
+        assert_eq!(
+            Err(Error::InvalidLength),
+            sender.protect(ContentType::Alert, &[1, 0], &mut record[..30])
+        );
+        let len = sender.protect(ContentType::Alert, &[1, 0], record).unwrap();
+        assert_eq!(
+            RecordProtection::<EcdheRsaAes256GcmSha384>::record_len(2),
+            len
+        );
+        assert_eq!(&[21, 3, 3, 0, 26, 0, 0, 0, 0, 0, 0, 0, 0], &record[..13]);
+
+        // The content type is authenticated
+        record[0] = ContentType::Handshake.to_u8();
+        assert_eq!(
+            Err(Error::TagMismatch),
+            receiver.unprotect(&mut record[..len]).map(|_| ())
+        );
+        record[0] = ContentType::Alert.to_u8();
+        assert_eq!(
+            Err(Error::InvalidLength),
+            receiver.unprotect(&mut record[..len - 1]).map(|_| ())
+        );
+        let protected = *record;
+        let (content_type, content) = receiver.unprotect(&mut record[..len]).unwrap();
+        assert_eq!(ContentType::Alert, content_type);
+        assert_eq!(&[1, 0], content);
+        *record = protected;
+
+        // The sequence number is authenticated even though the explicit nonce is sent
+        assert_eq!(
+            Err(Error::TagMismatch),
+            receiver.unprotect(&mut record[..len]).map(|_| ())
+        );
+    }
+}
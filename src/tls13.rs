@@ -0,0 +1,451 @@
+//! Module for the TLS 1.3 key schedule (RFC 8446, section 7).
+//!
+//! The schedule moves through three stages, each represented by its own type: `EarlySecret`,
+//! `HandshakeSecret` and `MasterSecret`. Traffic secrets are derived from a stage given the hash
+//! of the handshake transcript so far, and are then turned into record protection keys with
+//! `traffic_key_and_iv`. Deriving a secret fails with `Error::InvalidLength` if the transcript
+//! hash is longer than 255 bytes.
+//!
+//! A cipher suite pairs the hash function of the key schedule with the AEAD for record
+//! protection, and is chosen by its type, which must implement `CipherSuite`. The `record`
+//! module protects records with the traffic keys, and the `client` module builds a client
+//! handshake on top of both.
+//!
+//! # Examples
+//!
+//! ```
+//! use crypto_pure::sha2::{sha256, Sha256};
+//! use crypto_pure::tls13::{traffic_key_and_iv, EarlySecret};
+//! # let shared_secret = &[0x42; 32];
+//! # let hello_hash = &sha256(b"ClientHello...ServerHello");
+//! let early_secret = EarlySecret::<Sha256>::new(None);
+//! let handshake_secret = early_secret.handshake_secret(shared_secret);
+//! let server_secret = handshake_secret.server_handshake_traffic_secret(hello_hash)?;
+//! let key = &mut [0; 16];
+//! let iv = &mut [0; 12];
+//! traffic_key_and_iv::<Sha256>(&server_secret, key, iv)?;
+//! # Ok::<(), crypto_pure::error::Error>(())
+//! ```
+use crate::aead::Aead;
+use crate::aes::{Aes128, Aes256};
+use crate::error::Error;
+use crate::gcm::Gcm;
+use crate::hkdf;
+use crate::hmac::Hmac;
+use crate::poly1305::ChaCha20Poly1305;
+use crate::secret::SecretKey;
+use crate::sha2::{Digest, HashFunction, Sha256, Sha384, MAX_DIGEST_SIZE};
+use core::marker::PhantomData;
+use core::ops::Deref;
+
+#[cfg(feature = "std")]
+pub mod client;
+pub mod record;
+
+/// A TLS 1.3 cipher suite.
+pub trait CipherSuite {
+    /// The identifier of the cipher suite.
+    const ID: u16;
+    /// The hash function for the key schedule and the transcript.
+    type Hash: HashFunction;
+    /// The AEAD for record protection, which must take 12-byte nonces.
+    type Aead: Aead;
+}
+
+/// The TLS_AES_128_GCM_SHA256 cipher suite.
+pub struct Aes128GcmSha256;
+
+/// The TLS_AES_256_GCM_SHA384 cipher suite.
+pub struct Aes256GcmSha384;
+
+/// The TLS_CHACHA20_POLY1305_SHA256 cipher suite.
+pub struct ChaCha20Poly1305Sha256;
+
+impl CipherSuite for Aes128GcmSha256 {
+    const ID: u16 = 0x1301;
+    type Hash = Sha256;
+    type Aead = Gcm<Aes128>;
+}
+
+impl CipherSuite for Aes256GcmSha384 {
+    const ID: u16 = 0x1302;
+    type Hash = Sha384;
+    type Aead = Gcm<Aes256>;
+}
+
+impl CipherSuite for ChaCha20Poly1305Sha256 {
+    const ID: u16 = 0x1303;
+    type Hash = Sha256;
+    type Aead = ChaCha20Poly1305;
+}
+
+/// A secret produced by the key schedule that derefs into a slice of bytes.
+#[derive(Clone)]
+pub struct Secret {
+    buffer: SecretKey<[u8; MAX_DIGEST_SIZE]>,
+    size: usize,
+}
+
+impl Secret {
+    fn expand_label<H: HashFunction>(
+        secret: &[u8],
+        label: &[u8],
+        context: &[u8],
+    ) -> Result<Self, Error> {
+        let mut buffer = SecretKey::new([0; MAX_DIGEST_SIZE]);
+        hkdf_expand_label::<H>(secret, label, context, &mut buffer[..H::DIGEST_SIZE])?;
+        Ok(Self {
+            buffer,
+            size: H::DIGEST_SIZE,
+        })
+    }
+
+    fn extract<H: HashFunction>(salt: &[u8], ikm: &[u8]) -> Self {
+        let mut hmac = Hmac::<H>::new(salt);
+        hmac.update(ikm);
+        let mut buffer = SecretKey::new([0; MAX_DIGEST_SIZE]);
+        buffer[..H::DIGEST_SIZE].copy_from_slice(&hmac.tag());
+        Self {
+            buffer,
+            size: H::DIGEST_SIZE,
+        }
+    }
+}
+
+impl Deref for Secret {
+    type Target = [u8];
+
+    fn deref(&self) -> &Self::Target {
+        &self.buffer[..self.size]
+    }
+}
+
+impl AsRef<[u8]> for Secret {
+    fn as_ref(&self) -> &[u8] {
+        self
+    }
+}
+
+/// Expands a secret into `output` using a TLS 1.3 `HkdfLabel` built from `label` and `context`.
+///
+/// The "tls13 " prefix is added to `label` by this function.
+///
+/// # Errors
+///
+/// Fails with `Error::InvalidLength` if `label.len()` is more than 249, `context.len()` is more
+/// than 255, `secret.len()` is less than the digest size for `H`, or `output.len()` is more than
+/// 255 times the digest size.
+pub fn hkdf_expand_label<H: HashFunction>(
+    secret: &[u8],
+    label: &[u8],
+    context: &[u8],
+    output: &mut [u8],
+) -> Result<(), Error> {
+    const PREFIX: &[u8] = b"tls13 ";
+    if label.len() > 255 - PREFIX.len() || context.len() > 255 {
+        return Err(Error::InvalidLength);
+    }
+    let info = &mut [0; 2 + 1 + 255 + 1 + 255];
+    info[..2].copy_from_slice(&(output.len() as u16).to_be_bytes());
+    info[2] = (PREFIX.len() + label.len()) as u8;
+    let mut offset = 3;
+    for part in &[PREFIX, label] {
+        info[offset..offset + part.len()].copy_from_slice(part);
+        offset += part.len();
+    }
+    info[offset] = context.len() as u8;
+    offset += 1;
+    info[offset..offset + context.len()].copy_from_slice(context);
+    offset += context.len();
+    hkdf::expand::<H>(secret, &info[..offset], output)
+}
+
+/// Derives a secret from `secret` for a label given the hash of the transcript so far.
+///
+/// # Errors
+///
+/// Fails with `Error::InvalidLength` if `label.len()` is more than 249, `transcript_hash.len()`
+/// is more than 255, or `secret.len()` is less than the digest size for `H`.
+pub fn derive_secret<H: HashFunction>(
+    secret: &[u8],
+    label: &[u8],
+    transcript_hash: &[u8],
+) -> Result<Secret, Error> {
+    Secret::expand_label::<H>(secret, label, transcript_hash)
+}
+
+/// Derives the record protection key and IV from a traffic secret.
+///
+/// # Errors
+///
+/// Fails with `Error::InvalidLength` if `traffic_secret.len()` is less than the digest size for
+/// `H`, or `key.len()` or `iv.len()` is more than 255 times the digest size.
+pub fn traffic_key_and_iv<H: HashFunction>(
+    traffic_secret: &[u8],
+    key: &mut [u8],
+    iv: &mut [u8],
+) -> Result<(), Error> {
+    hkdf_expand_label::<H>(traffic_secret, b"key", b"", key)?;
+    hkdf_expand_label::<H>(traffic_secret, b"iv", b"", iv)
+}
+
+/// Derives the key used to compute the Finished message from a handshake traffic secret.
+///
+/// # Errors
+///
+/// Fails with `Error::InvalidLength` if `traffic_secret.len()` is less than the digest size for
+/// `H`.
+pub fn finished_key<H: HashFunction>(traffic_secret: &[u8]) -> Result<Secret, Error> {
+    Secret::expand_label::<H>(traffic_secret, b"finished", b"")
+}
+
+/// Derives the next application traffic secret from the current one, for a key update.
+///
+/// # Errors
+///
+/// Fails with `Error::InvalidLength` if `traffic_secret.len()` is less than the digest size for
+/// `H`.
+pub fn next_traffic_secret<H: HashFunction>(traffic_secret: &[u8]) -> Result<Secret, Error> {
+    Secret::expand_label::<H>(traffic_secret, b"traffic upd", b"")
+}
+
+/// The first stage of the key schedule, extracted from an optional pre-shared key.
+pub struct EarlySecret<H> {
+    secret: Secret,
+    hash_function: PhantomData<H>,
+}
+
+/// The second stage of the key schedule, which mixes in the (EC)DHE shared secret.
+pub struct HandshakeSecret<H> {
+    secret: Secret,
+    hash_function: PhantomData<H>,
+}
+
+/// The final stage of the key schedule, from which application traffic secrets are derived.
+pub struct MasterSecret<H> {
+    secret: Secret,
+    hash_function: PhantomData<H>,
+}
+
+impl<H: HashFunction> EarlySecret<H> {
+    /// Initializes the key schedule given a pre-shared key, if any.
+    pub fn new(psk: Option<&[u8]>) -> Self {
+        let zeros = &[0; MAX_DIGEST_SIZE][..H::DIGEST_SIZE];
+        Self {
+            secret: Secret::extract::<H>(zeros, psk.unwrap_or(zeros)),
+            hash_function: PhantomData,
+        }
+    }
+
+    /// Outputs the early secret itself.
+    pub fn secret(&self) -> &[u8] {
+        &self.secret
+    }
+
+    /// Derives the binder key for an external PSK if `external` is true, or for a resumption PSK
+    /// otherwise.
+    pub fn binder_key(&self, external: bool) -> Secret {
+        let label: &[u8] = if external {
+            b"ext binder"
+        } else {
+            b"res binder"
+        };
+        self.derive(label, &empty_hash::<H>())
+            .expect("invalid secret length")
+    }
+
+    /// Derives the client early traffic secret given the hash of the ClientHello.
+    pub fn client_early_traffic_secret(&self, transcript_hash: &[u8]) -> Result<Secret, Error> {
+        self.derive(b"c e traffic", transcript_hash)
+    }
+
+    /// Derives the early exporter master secret given the hash of the ClientHello.
+    pub fn early_exporter_master_secret(&self, transcript_hash: &[u8]) -> Result<Secret, Error> {
+        self.derive(b"e exp master", transcript_hash)
+    }
+
+    /// Advances the key schedule given the (EC)DHE shared secret.
+    pub fn handshake_secret(&self, shared_secret: &[u8]) -> HandshakeSecret<H> {
+        let salt = self
+            .derive(b"derived", &empty_hash::<H>())
+            .expect("invalid secret length");
+        HandshakeSecret {
+            secret: Secret::extract::<H>(&salt, shared_secret),
+            hash_function: PhantomData,
+        }
+    }
+
+    fn derive(&self, label: &[u8], transcript_hash: &[u8]) -> Result<Secret, Error> {
+        derive_secret::<H>(&self.secret, label, transcript_hash)
+    }
+}
+
+impl<H: HashFunction> HandshakeSecret<H> {
+    /// Outputs the handshake secret itself.
+    pub fn secret(&self) -> &[u8] {
+        &self.secret
+    }
+
+    /// Derives the client handshake traffic secret given the hash of ClientHello...ServerHello.
+    pub fn client_handshake_traffic_secret(&self, transcript_hash: &[u8]) -> Result<Secret, Error> {
+        self.derive(b"c hs traffic", transcript_hash)
+    }
+
+    /// Derives the server handshake traffic secret given the hash of ClientHello...ServerHello.
+    pub fn server_handshake_traffic_secret(&self, transcript_hash: &[u8]) -> Result<Secret, Error> {
+        self.derive(b"s hs traffic", transcript_hash)
+    }
+
+    /// Advances the key schedule to the master secret.
+    pub fn master_secret(&self) -> MasterSecret<H> {
+        let salt = self
+            .derive(b"derived", &empty_hash::<H>())
+            .expect("invalid secret length");
+        let zeros = &[0; MAX_DIGEST_SIZE][..H::DIGEST_SIZE];
+        MasterSecret {
+            secret: Secret::extract::<H>(&salt, zeros),
+            hash_function: PhantomData,
+        }
+    }
+
+    fn derive(&self, label: &[u8], transcript_hash: &[u8]) -> Result<Secret, Error> {
+        derive_secret::<H>(&self.secret, label, transcript_hash)
+    }
+}
+
+impl<H: HashFunction> MasterSecret<H> {
+    /// Outputs the master secret itself.
+    pub fn secret(&self) -> &[u8] {
+        &self.secret
+    }
+
+    /// Derives the first client application traffic secret given the hash of
+    /// ClientHello...server Finished.
+    pub fn client_application_traffic_secret(
+        &self,
+        transcript_hash: &[u8],
+    ) -> Result<Secret, Error> {
+        self.derive(b"c ap traffic", transcript_hash)
+    }
+
+    /// Derives the first server application traffic secret given the hash of
+    /// ClientHello...server Finished.
+    pub fn server_application_traffic_secret(
+        &self,
+        transcript_hash: &[u8],
+    ) -> Result<Secret, Error> {
+        self.derive(b"s ap traffic", transcript_hash)
+    }
+
+    /// Derives the exporter master secret given the hash of ClientHello...server Finished.
+    pub fn exporter_master_secret(&self, transcript_hash: &[u8]) -> Result<Secret, Error> {
+        self.derive(b"exp master", transcript_hash)
+    }
+
+    /// Derives the resumption master secret given the hash of ClientHello...client Finished.
+    pub fn resumption_master_secret(&self, transcript_hash: &[u8]) -> Result<Secret, Error> {
+        self.derive(b"res master", transcript_hash)
+    }
+
+    fn derive(&self, label: &[u8], transcript_hash: &[u8]) -> Result<Secret, Error> {
+        derive_secret::<H>(&self.secret, label, transcript_hash)
+    }
+}
+
+fn empty_hash<H: HashFunction>() -> Digest {
+    Digest::new(H::default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sha2::Sha256;
+    use crate::test_helpers::*;
+
+    // From the simple 1-RTT handshake in RFC 8448, section 3.
+    const SHARED_SECRET: &str = "8bd4054fb55b9d63fdfbacf9f04b9f0d35e6d63f537563efd46272900f89492d";
+    const HELLO_HASH: &str = "860c06edc07858ee8e78f0e7428c58edd6b43f2ca3e6e95f02ed063cf0e1cad8";
+
+    #[test]
+    fn test_key_schedule() {
+        let early_secret = EarlySecret::<Sha256>::new(None);
+        assert_eq!(
+            h2b("33ad0a1c607ec03b09e6cd9893680ce210adf300aa1f2660e1b22e10f170f92a"),
+            early_secret.secret()
+        );
+
+        let handshake_secret = early_secret.handshake_secret(&h2b(SHARED_SECRET));
+        assert_eq!(
+            h2b("1dc826e93606aa6fdc0aadc12f741b01046aa6b99f691ed221a9f0ca043fbeac"),
+            handshake_secret.secret()
+        );
+
+        let hello_hash = &h2b(HELLO_HASH);
+        let client_secret = handshake_secret
+            .client_handshake_traffic_secret(hello_hash)
+            .unwrap();
+        assert_eq!(
+            h2b("b3eddb126e067f35a780b3abf45e2d8f3b1a950738f52e9600746a0e27a55a21"),
+            &client_secret[..]
+        );
+        let server_secret = handshake_secret
+            .server_handshake_traffic_secret(hello_hash)
+            .unwrap();
+        assert_eq!(
+            h2b("b67b7d690cc16c4e75e54213cb2d37b4e9c912bcded9105d42befd59d391ad38"),
+            &server_secret[..]
+        );
+
+        let master_secret = handshake_secret.master_secret();
+        assert_eq!(
+            h2b("18df06843d13a08bf2a449844c5f8a478001bc4d4c627984d5a41da8d0402919"),
+            master_secret.secret()
+        );
+    }
+
+    #[test]
+    fn test_traffic_key_and_iv() {
+        let key = &mut [0; 16];
+        let iv = &mut [0; 12];
+        let server_secret = h2b("b67b7d690cc16c4e75e54213cb2d37b4e9c912bcded9105d42befd59d391ad38");
+        traffic_key_and_iv::<Sha256>(&server_secret, key, iv).unwrap();
+        assert_eq!(h2b("3fce516009c21727d0f2e4e86ee403bc"), key.to_vec());
+        assert_eq!(h2b("5d313eb2671276ee13000b30"), iv.to_vec());
+
+        let client_secret = h2b("b3eddb126e067f35a780b3abf45e2d8f3b1a950738f52e9600746a0e27a55a21");
+        traffic_key_and_iv::<Sha256>(&client_secret, key, iv).unwrap();
+        assert_eq!(h2b("dbfaa693d1762c5b666af5d950258d01"), key.to_vec());
+        assert_eq!(h2b("5bd3c71b836e0b76bb73265f"), iv.to_vec());
+    }
+
+    #[test]
+    fn test_derive_secret() {
+        let early_secret = h2b("33ad0a1c607ec03b09e6cd9893680ce210adf300aa1f2660e1b22e10f170f92a");
+        let empty_hash = empty_hash::<Sha256>();
+        let derived = derive_secret::<Sha256>(&early_secret, b"derived", &empty_hash).unwrap();
+        assert_eq!(
+            h2b("6f2615a108c702c5678f54fc9dbab69716c076189c48250cebeac3576c3611ba"),
+            &derived[..]
+        );
+    }
+
+    #[test]
+    fn test_invalid_lengths() {
+        let secret = &[0x42; 32];
+        let error = Some(Error::InvalidLength);
+        let result = derive_secret::<Sha256>(secret, &[b'a'; 250], b"");
+        assert_eq!(error, result.err());
+        let result = derive_secret::<Sha256>(secret, b"derived", &[0; 256]);
+        assert_eq!(error, result.err());
+        assert_eq!(error, finished_key::<Sha256>(&secret[1..]).err());
+        assert_eq!(error, next_traffic_secret::<Sha256>(&secret[1..]).err());
+        let result = hkdf_expand_label::<Sha256>(secret, b"key", b"", &mut [0; 255 * 32 + 1]);
+        assert_eq!(Err(Error::InvalidLength), result);
+        let result = traffic_key_and_iv::<Sha256>(&secret[1..], &mut [0; 16], &mut [0; 12]);
+        assert_eq!(Err(Error::InvalidLength), result);
+
+        let handshake_secret = EarlySecret::<Sha256>::new(None).handshake_secret(secret);
+        let result = handshake_secret.client_handshake_traffic_secret(&[0; 256]);
+        assert_eq!(error, result.err());
+    }
+}
@@ -0,0 +1,1347 @@
+//! Module for a TLS 1.3 client (RFC 8446) that performs a full 1-RTT handshake over a stream.
+//!
+//! The client offers a single cipher suite, chosen by its type, and key shares for both the
+//! X25519MLKEM768 hybrid KEM and X25519, letting the server pick either for key exchange. The
+//! server's certificate chain must lead to one of the given trust anchors and name the server
+//! in its subject alternative names. The server may sign the handshake with ECDSA with P-256 or
+//! P-384, RSA-PSS or Ed25519. Name constraints and the key usage of the server's own certificate
+//! are not checked, and client certificates, pre-shared keys, early data and HelloRetryRequest
+//! are not supported. Session tickets are ignored, and the server's KeyUpdate messages are
+//! handled with the `record` module.
+//!
+//! Once connected, the client implements `Read` and `Write` for application data. Errors other
+//! than I/O errors are reported with `io::ErrorKind::InvalidData` and an `Error`, or with
+//! `io::ErrorKind::ConnectionAborted` if the server sends an alert.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use crypto_pure::encoding::pem::{self, Mode};
+//! use crypto_pure::rand::SystemRandom;
+//! use crypto_pure::tls13::client::Client;
+//! use crypto_pure::tls13::Aes128GcmSha256;
+//! use crypto_pure::x509::Certificate;
+//! use std::io::{Read, Write};
+//! use std::net::TcpStream;
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! # let root_pem = "";
+//! let root = pem::parse(root_pem, Mode::Strict)?;
+//! let trust_anchors = &[Certificate::from_der(root.contents())?];
+//! let stream = TcpStream::connect("localhost:4433")?;
+//! let rng = &SystemRandom::new();
+//! let mut client = Client::<Aes128GcmSha256, _>::connect(stream, "localhost", trust_anchors, rng)?;
+//! client.write_all(b"hello\n")?;
+//! let mut response = Vec::new();
+//! client.read_to_end(&mut response)?;
+//! # Ok(())
+//! # }
+//! ```
+use super::record::{self, ContentType, KeyUpdate, RecordProtection, HEADER_LEN};
+use super::{finished_key, CipherSuite, EarlySecret};
+use crate::curve25519;
+use crate::ed25519::Ed25519;
+use crate::error::Error;
+use crate::hmac::Hmac;
+use crate::hybrid::{self, X25519MlKem768};
+use crate::kem::Kem;
+use crate::p256::{self, EcdsaP256};
+use crate::p384::{self, EcdsaP384};
+use crate::pkcs8::PublicKey;
+use crate::rand::SecureRandom;
+use crate::rsa::Pss;
+use crate::secret::SecretKey;
+use crate::sha2::{Digest, HashFunction, Sha256, Sha384, Sha512};
+use crate::sig::Verifier;
+use crate::x509::{Certificate, GeneralName, KeyUsage};
+use byteorder::{BigEndian, ByteOrder as _};
+use std::io::{self, Read, Write};
+use std::mem;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const CLIENT_HELLO: u8 = 1;
+const SERVER_HELLO: u8 = 2;
+const NEW_SESSION_TICKET: u8 = 4;
+const ENCRYPTED_EXTENSIONS: u8 = 8;
+const CERTIFICATE: u8 = 11;
+const CERTIFICATE_REQUEST: u8 = 13;
+const CERTIFICATE_VERIFY: u8 = 15;
+const FINISHED: u8 = 20;
+const KEY_UPDATE: u8 = 24;
+
+const SERVER_NAME: u16 = 0;
+const SUPPORTED_GROUPS: u16 = 10;
+const SIGNATURE_ALGORITHMS: u16 = 13;
+const SUPPORTED_VERSIONS: u16 = 43;
+const KEY_SHARE: u16 = 51;
+
+const ECDSA_SECP256R1_SHA256: u16 = 0x0403;
+const ECDSA_SECP384R1_SHA384: u16 = 0x0503;
+const RSA_PSS_RSAE_SHA256: u16 = 0x0804;
+const RSA_PSS_RSAE_SHA384: u16 = 0x0805;
+const RSA_PSS_RSAE_SHA512: u16 = 0x0806;
+const ED25519: u16 = 0x0807;
+// RSASSA-PKCS1-v1_5 is only offered for signatures in certificates.
+const SIGNATURE_SCHEMES: &[u16] = &[
+    ECDSA_SECP256R1_SHA256,
+    ECDSA_SECP384R1_SHA384,
+    RSA_PSS_RSAE_SHA256,
+    RSA_PSS_RSAE_SHA384,
+    RSA_PSS_RSAE_SHA512,
+    ED25519,
+    0x0401,
+    0x0501,
+    0x0601,
+];
+
+const X25519: u16 = 0x001d;
+const X25519_MLKEM768: u16 = 0x11ec;
+const LEGACY_VERSION: u16 = 0x0303;
+const TLS13: u16 = 0x0304;
+const CLOSE_NOTIFY: u8 = 0;
+
+const MAX_HANDSHAKE_LEN: usize = 1 << 16;
+
+// The SHA-256 digest of "HelloRetryRequest", which a HelloRetryRequest has as its random.
+const HELLO_RETRY_REQUEST: [u8; 32] = [
+    0xcf, 0x21, 0xad, 0x74, 0xe5, 0x9a, 0x61, 0x11, 0xbe, 0x1d, 0x8c, 0x02, 0x1e, 0x65, 0xb8, 0x91,
+    0xc2, 0xa2, 0x11, 0x16, 0x7a, 0xbb, 0x8c, 0x5e, 0x07, 0x9e, 0x09, 0xe2, 0xc8, 0xa8, 0x33, 0x9c,
+];
+
+/// A connection to a server.
+pub struct Client<S: CipherSuite, T> {
+    stream: T,
+    reading: RecordProtection<S>,
+    writing: RecordProtection<S>,
+    handshake: Vec<u8>,
+    plaintext: Vec<u8>,
+    offset: usize,
+    connected: bool,
+    closed: bool,
+    eof: bool,
+    certificates: Vec<Vec<u8>>,
+}
+
+impl<S: CipherSuite, T: Read + Write> Client<S, T> {
+    /// Performs a handshake over `stream` with the server named `server_name`, which must
+    /// present a certificate chain leading to one of `trust_anchors`. The ephemeral key and the
+    /// client random are drawn from `rng`.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::InvalidCertificate` if the certificate chain is not valid, with
+    /// `Error::InvalidSignature` if the server's signature is not valid, with
+    /// `Error::TagMismatch` if the server's Finished message or a record is not valid, or with
+    /// `Error::InvalidEncoding` or `Error::InvalidKey` if the server misbehaves otherwise, all
+    /// wrapped in an `io::Error`. Also fails with the errors of `stream`.
+    pub fn connect(
+        mut stream: T,
+        server_name: &str,
+        trust_anchors: &[Certificate],
+        rng: &dyn SecureRandom,
+    ) -> io::Result<Self> {
+        if server_name.is_empty()
+            || server_name.len() > 255
+            || !server_name.bytes().all(|byte| byte.is_ascii_graphic())
+        {
+            return Err(Error::InvalidEncoding.into());
+        }
+        let (decapsulation_key, encapsulation_key) = X25519MlKem768::generate(rng);
+        let mut secret_key = SecretKey::new([0; 32]);
+        rng.fill(&mut *secret_key);
+        let mut random = [0; 32];
+        rng.fill(&mut random);
+        let client_hello = client_hello::<S>(
+            server_name,
+            &random,
+            &encapsulation_key,
+            &curve25519::gen_pk(&*secret_key)?,
+        );
+        let mut transcript = S::Hash::default();
+        transcript.update(&client_hello);
+        let mut record = Vec::with_capacity(HEADER_LEN + client_hello.len());
+        record.extend_from_slice(&[ContentType::Handshake.to_u8(), 3, 1]);
+        record.extend_from_slice(&(client_hello.len() as u16).to_be_bytes());
+        record.extend_from_slice(&client_hello);
+        stream.write_all(&record)?;
+        stream.flush()?;
+
+        let server_hello = read_server_hello(&mut stream)?;
+        transcript.update(&server_hello);
+        let (group, server_share) = parse_server_hello::<S>(&server_hello[4..])?;
+        let early_secret = EarlySecret::<S::Hash>::new(None);
+        let handshake_secret = if group == X25519_MLKEM768 {
+            let shared_secret = SecretKey::new(X25519MlKem768::decapsulate(
+                &decapsulation_key,
+                server_share,
+            )?);
+            early_secret.handshake_secret(&*shared_secret)
+        } else {
+            let shared_secret = SecretKey::new(curve25519::x25519(&*secret_key, server_share)?);
+            early_secret.handshake_secret(&*shared_secret)
+        };
+        let transcript_hash = Digest::new(transcript.clone());
+        let client_secret = handshake_secret.client_handshake_traffic_secret(&transcript_hash)?;
+        let server_secret = handshake_secret.server_handshake_traffic_secret(&transcript_hash)?;
+        let mut client = Self {
+            stream,
+            reading: RecordProtection::new(&server_secret)?,
+            writing: RecordProtection::new(&client_secret)?,
+            handshake: Vec::new(),
+            plaintext: Vec::new(),
+            offset: 0,
+            connected: false,
+            closed: false,
+            eof: false,
+            certificates: Vec::new(),
+        };
+
+        let message = client.read_handshake_message()?;
+        if message[0] != ENCRYPTED_EXTENSIONS {
+            return Err(Error::InvalidEncoding.into());
+        }
+        let mut reader = Reader(&message[4..]);
+        parse_extensions(reader.vec(2)?)?;
+        reader.finish()?;
+        transcript.update(&message);
+
+        let message = client.read_handshake_message()?;
+        if message[0] == CERTIFICATE_REQUEST {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "client authentication is not supported",
+            ));
+        } else if message[0] != CERTIFICATE {
+            return Err(Error::InvalidEncoding.into());
+        }
+        let certificates = parse_certificate(&message[4..])?;
+        let chain = certificates
+            .iter()
+            .map(|der| Certificate::from_der(der))
+            .collect::<Result<Vec<_>, _>>()?;
+        verify_chain(&chain, server_name, trust_anchors, now())?;
+        transcript.update(&message);
+
+        let message = client.read_handshake_message()?;
+        if message[0] != CERTIFICATE_VERIFY {
+            return Err(Error::InvalidEncoding.into());
+        }
+        let mut reader = Reader(&message[4..]);
+        let scheme = reader.u16()?;
+        let signature = reader.vec(2)?;
+        reader.finish()?;
+        let transcript_hash = Digest::new(transcript.clone());
+        verify_certificate_verify(scheme, signature, &chain[0].public_key()?, &transcript_hash)?;
+        transcript.update(&message);
+
+        let message = client.read_handshake_message()?;
+        if message[0] != FINISHED {
+            return Err(Error::InvalidEncoding.into());
+        }
+        let mut hmac = Hmac::<S::Hash>::new(&finished_key::<S::Hash>(&server_secret)?);
+        hmac.update(&Digest::new(transcript.clone()));
+        hmac.verify(&message[4..]).map_err(Error::from)?;
+        transcript.update(&message);
+        // The server must change keys at a record boundary.
+        if !client.handshake.is_empty() {
+            return Err(Error::InvalidEncoding.into());
+        }
+
+        let transcript_hash = Digest::new(transcript.clone());
+        let master_secret = handshake_secret.master_secret();
+        let mut hmac = Hmac::<S::Hash>::new(&finished_key::<S::Hash>(&client_secret)?);
+        hmac.update(&transcript_hash);
+        let verify_data = hmac.tag();
+        let mut finished = vec![FINISHED, 0, 0, verify_data.len() as u8];
+        finished.extend_from_slice(&verify_data);
+        client.write_record(ContentType::Handshake, &finished)?;
+        client.stream.flush()?;
+
+        client.reading = RecordProtection::new(
+            &master_secret.server_application_traffic_secret(&transcript_hash)?,
+        )?;
+        client.writing = RecordProtection::new(
+            &master_secret.client_application_traffic_secret(&transcript_hash)?,
+        )?;
+        client.connected = true;
+        client.certificates = certificates;
+        Ok(client)
+    }
+
+    /// Outputs the DER encodings of the server's certificate chain, starting with its own.
+    pub fn peer_certificates(&self) -> &[Vec<u8>] {
+        &self.certificates
+    }
+
+    /// Sends a close_notify alert, after which no more data can be written.
+    pub fn close(&mut self) -> io::Result<()> {
+        if !self.closed {
+            self.write_record(ContentType::Alert, &[1, CLOSE_NOTIFY])?;
+            self.closed = true;
+        }
+        self.stream.flush()
+    }
+
+    /// Sends a KeyUpdate message and updates the traffic secret for writing. With
+    /// `KeyUpdate::UpdateRequested`, the server updates its own traffic secret in response.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `io::ErrorKind::BrokenPipe` after `close`, and with the errors of the stream.
+    pub fn update_keys(&mut self, key_update: KeyUpdate) -> io::Result<()> {
+        if self.closed {
+            return Err(io::ErrorKind::BrokenPipe.into());
+        }
+        self.write_record(ContentType::Handshake, &key_update.encode())?;
+        self.writing.update_traffic_secret();
+        self.stream.flush()
+    }
+
+    fn write_record(&mut self, content_type: ContentType, data: &[u8]) -> io::Result<()> {
+        let max_len = data.len().min(record::MAX_CONTENT_LEN);
+        let mut record = vec![0; RecordProtection::<S>::record_len(max_len, 0)];
+        for chunk in data.chunks(record::MAX_CONTENT_LEN) {
+            let len = self.writing.protect(content_type, chunk, 0, &mut record)?;
+            self.stream.write_all(&record[..len])?;
+        }
+        Ok(())
+    }
+
+    /// Reads and decrypts a record, skipping the ChangeCipherSpec records servers may send
+    /// during the handshake for compatibility, and outputs its content type and content.
+    fn read_record(&mut self) -> io::Result<(ContentType, Vec<u8>)> {
+        loop {
+            let (content_type, mut record) = read_raw_record(&mut self.stream)?;
+            match content_type {
+                ContentType::ApplicationData => {
+                    let (content_type, content) = self.reading.unprotect(&mut record)?;
+                    return Ok((content_type, content.to_vec()));
+                }
+                ContentType::ChangeCipherSpec if !self.connected && record[HEADER_LEN..] == [1] => {
+                }
+                _ => return Err(Error::InvalidEncoding.into()),
+            }
+        }
+    }
+
+    fn read_handshake_message(&mut self) -> io::Result<Vec<u8>> {
+        loop {
+            if let Some(message) = take_handshake_message(&mut self.handshake)? {
+                return Ok(message);
+            }
+            let (content_type, content) = self.read_record()?;
+            match content_type {
+                ContentType::Handshake if !content.is_empty() => {
+                    self.handshake.extend_from_slice(&content)
+                }
+                ContentType::Alert => return Err(alert_error(&content)),
+                _ => return Err(Error::InvalidEncoding.into()),
+            }
+        }
+    }
+
+    fn handle_post_handshake_messages(&mut self) -> io::Result<()> {
+        while let Some(message) = take_handshake_message(&mut self.handshake)? {
+            match message[0] {
+                NEW_SESSION_TICKET => {}
+                KEY_UPDATE => {
+                    let key_update = KeyUpdate::decode(&message)?;
+                    // The server must change keys at a record boundary.
+                    if !self.handshake.is_empty() {
+                        return Err(Error::InvalidEncoding.into());
+                    }
+                    self.reading.update_traffic_secret();
+                    // Nothing more can be sent after close_notify.
+                    if key_update == KeyUpdate::UpdateRequested && !self.closed {
+                        self.update_keys(KeyUpdate::UpdateNotRequested)?;
+                    }
+                }
+                _ => return Err(Error::InvalidEncoding.into()),
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<S: CipherSuite, T: Read + Write> Read for Client<S, T> {
+    /// Reads application data, outputting 0 once the server has sent a close_notify alert.
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.offset == self.plaintext.len() {
+            if self.eof {
+                return Ok(0);
+            }
+            let (content_type, content) = self.read_record()?;
+            match content_type {
+                ContentType::ApplicationData => {
+                    self.plaintext = content;
+                    self.offset = 0;
+                }
+                ContentType::Handshake if !content.is_empty() => {
+                    self.handshake.extend_from_slice(&content);
+                    self.handle_post_handshake_messages()?;
+                }
+                ContentType::Alert if content.get(1) == Some(&CLOSE_NOTIFY) => self.eof = true,
+                ContentType::Alert => return Err(alert_error(&content)),
+                _ => return Err(Error::InvalidEncoding.into()),
+            }
+        }
+        let len = buf.len().min(self.plaintext.len() - self.offset);
+        buf[..len].copy_from_slice(&self.plaintext[self.offset..self.offset + len]);
+        self.offset += len;
+        Ok(len)
+    }
+}
+
+impl<S: CipherSuite, T: Read + Write> Write for Client<S, T> {
+    /// Writes application data in a single record.
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.closed {
+            return Err(io::ErrorKind::BrokenPipe.into());
+        }
+        let len = buf.len().min(record::MAX_CONTENT_LEN);
+        self.write_record(ContentType::ApplicationData, &buf[..len])?;
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.stream.flush()
+    }
+}
+
+/// A reader for the big-endian integers and length-prefixed vectors of TLS messages.
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], Error> {
+        if self.0.len() < len {
+            return Err(Error::InvalidEncoding);
+        }
+        let (bytes, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, Error> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, Error> {
+        Ok(BigEndian::read_u16(self.bytes(2)?))
+    }
+
+    /// Reads a vector whose length is given in `len_size` bytes.
+    fn vec(&mut self, len_size: usize) -> Result<&'a [u8], Error> {
+        let len = BigEndian::read_uint(self.bytes(len_size)?, len_size);
+        self.bytes(len as usize)
+    }
+
+    fn finish(&self) -> Result<(), Error> {
+        if self.0.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::InvalidEncoding)
+        }
+    }
+}
+
+/// Appends the bytes written by `f` to `output`, prefixed by their length in `len_size` bytes.
+fn with_len(output: &mut Vec<u8>, len_size: usize, f: impl FnOnce(&mut Vec<u8>)) {
+    let start = output.len();
+    output.resize(start + len_size, 0);
+    f(output);
+    let len = output.len() - start - len_size;
+    BigEndian::write_uint(&mut output[start..start + len_size], len as u64, len_size);
+}
+
+fn extension(output: &mut Vec<u8>, extension_type: u16, f: impl FnOnce(&mut Vec<u8>)) {
+    output.extend_from_slice(&extension_type.to_be_bytes());
+    with_len(output, 2, f);
+}
+
+fn client_hello<S: CipherSuite>(
+    server_name: &str,
+    random: &[u8; 32],
+    encapsulation_key: &[u8; hybrid::ENCAPSULATION_KEY_LEN],
+    public_key: &[u8; 32],
+) -> Vec<u8> {
+    let mut message = vec![CLIENT_HELLO];
+    with_len(&mut message, 3, |body| {
+        body.extend_from_slice(&LEGACY_VERSION.to_be_bytes());
+        body.extend_from_slice(random);
+        // The legacy session ID is empty, and the only compression method is null.
+        body.push(0);
+        with_len(body, 2, |suites| {
+            suites.extend_from_slice(&S::ID.to_be_bytes())
+        });
+        body.extend_from_slice(&[1, 0]);
+        with_len(body, 2, |extensions| {
+            extension(extensions, SERVER_NAME, |data| {
+                with_len(data, 2, |names| {
+                    names.push(0);
+                    with_len(names, 2, |name| {
+                        name.extend_from_slice(server_name.as_bytes())
+                    });
+                })
+            });
+            extension(extensions, SUPPORTED_GROUPS, |data| {
+                with_len(data, 2, |groups| {
+                    groups.extend_from_slice(&X25519_MLKEM768.to_be_bytes());
+                    groups.extend_from_slice(&X25519.to_be_bytes());
+                })
+            });
+            extension(extensions, SIGNATURE_ALGORITHMS, |data| {
+                with_len(data, 2, |schemes| {
+                    for scheme in SIGNATURE_SCHEMES {
+                        schemes.extend_from_slice(&scheme.to_be_bytes());
+                    }
+                })
+            });
+            extension(extensions, SUPPORTED_VERSIONS, |data| {
+                with_len(data, 1, |versions| {
+                    versions.extend_from_slice(&TLS13.to_be_bytes())
+                })
+            });
+            extension(extensions, KEY_SHARE, |data| {
+                with_len(data, 2, |shares| {
+                    shares.extend_from_slice(&X25519_MLKEM768.to_be_bytes());
+                    with_len(shares, 2, |key| key.extend_from_slice(encapsulation_key));
+                    shares.extend_from_slice(&X25519.to_be_bytes());
+                    with_len(shares, 2, |key| key.extend_from_slice(public_key));
+                })
+            });
+        });
+    });
+    message
+}
+
+/// Reads the plaintext records holding the ServerHello.
+fn read_server_hello(stream: &mut impl Read) -> io::Result<Vec<u8>> {
+    let mut buffer = Vec::new();
+    loop {
+        let (content_type, record) = read_raw_record(stream)?;
+        let payload = &record[HEADER_LEN..];
+        match content_type {
+            ContentType::Handshake if !payload.is_empty() => buffer.extend_from_slice(payload),
+            ContentType::Alert => return Err(alert_error(payload)),
+            _ => return Err(Error::InvalidEncoding.into()),
+        }
+        if let Some(message) = take_handshake_message(&mut buffer)? {
+            // The server must change keys at a record boundary.
+            if message[0] != SERVER_HELLO || !buffer.is_empty() {
+                return Err(Error::InvalidEncoding.into());
+            }
+            return Ok(message);
+        }
+    }
+}
+
+/// Parses a ServerHello, outputting the group chosen by the server and its key share: an
+/// X25519MLKEM768 ciphertext or an X25519 public key.
+fn parse_server_hello<S: CipherSuite>(body: &[u8]) -> io::Result<(u16, &[u8])> {
+    let mut reader = Reader(body);
+    if reader.u16()? != LEGACY_VERSION {
+        return Err(unsupported_version());
+    }
+    if reader.bytes(32)? == HELLO_RETRY_REQUEST {
+        return Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "HelloRetryRequest is not supported",
+        ));
+    }
+    if !reader.vec(1)?.is_empty() || reader.u16()? != S::ID || reader.u8()? != 0 {
+        return Err(Error::InvalidEncoding.into());
+    }
+    let extensions = parse_extensions(reader.vec(2)?)?;
+    reader.finish()?;
+    let mut version = None;
+    let mut server_share = None;
+    for (extension_type, data) in extensions {
+        let mut reader = Reader(data);
+        match extension_type {
+            SUPPORTED_VERSIONS => version = Some(reader.u16()?),
+            KEY_SHARE => {
+                let group = reader.u16()?;
+                let share = reader.vec(2)?;
+                let share_len = match group {
+                    X25519_MLKEM768 => X25519MlKem768::CIPHERTEXT_LEN,
+                    X25519 => 32,
+                    _ => return Err(Error::InvalidKey.into()),
+                };
+                if share.len() != share_len {
+                    return Err(Error::InvalidKey.into());
+                }
+                server_share = Some((group, share));
+            }
+            _ => return Err(Error::InvalidEncoding.into()),
+        }
+        reader.finish()?;
+    }
+    if version != Some(TLS13) {
+        return Err(unsupported_version());
+    }
+    Ok(server_share.ok_or(Error::InvalidEncoding)?)
+}
+
+/// Parses a list of extensions into their types and data, rejecting duplicates.
+fn parse_extensions(data: &[u8]) -> Result<Vec<(u16, &[u8])>, Error> {
+    let mut reader = Reader(data);
+    let mut extensions: Vec<(u16, &[u8])> = Vec::new();
+    while !reader.0.is_empty() {
+        let extension_type = reader.u16()?;
+        let data = reader.vec(2)?;
+        if extensions.iter().any(|(t, _)| *t == extension_type) {
+            return Err(Error::InvalidEncoding);
+        }
+        extensions.push((extension_type, data));
+    }
+    Ok(extensions)
+}
+
+/// Parses a Certificate message, outputting the DER encodings of the certificates.
+fn parse_certificate(body: &[u8]) -> Result<Vec<Vec<u8>>, Error> {
+    let mut reader = Reader(body);
+    if !reader.vec(1)?.is_empty() {
+        return Err(Error::InvalidEncoding);
+    }
+    let mut list = Reader(reader.vec(3)?);
+    reader.finish()?;
+    let mut certificates = Vec::new();
+    while !list.0.is_empty() {
+        certificates.push(list.vec(3)?.to_vec());
+        parse_extensions(list.vec(2)?)?;
+    }
+    if certificates.is_empty() {
+        return Err(Error::InvalidCertificate);
+    }
+    Ok(certificates)
+}
+
+/// Verifies that `chain` starts with a certificate for `server_name` and leads to one of
+/// `trust_anchors`, with every certificate and the trust anchor valid at `time`. Certificates
+/// with unrecognized critical extensions are rejected, and every issuer must be allowed to sign
+/// certificates by its key usage and path length constraint.
+fn verify_chain(
+    chain: &[Certificate],
+    server_name: &str,
+    trust_anchors: &[Certificate],
+    time: i64,
+) -> Result<(), Error> {
+    let names_server = chain[0].subject_alt_names().any(|name| match name {
+        GeneralName::DnsName(name) => name_matches(name, server_name),
+        _ => false,
+    });
+    if !names_server {
+        return Err(Error::InvalidCertificate);
+    }
+    for (i, certificate) in chain.iter().enumerate() {
+        if !certificate.is_valid_at(time)
+            || certificate.has_unrecognized_critical_extension()
+            || (i > 0 && !certificate.basic_constraints().is_some_and(|c| c.is_ca()))
+        {
+            return Err(Error::InvalidCertificate);
+        }
+        // The issuer is followed by the `i` intermediate CAs from `certificate` down to the leaf.
+        let is_issued_by = |issuer: &Certificate| {
+            may_issue(issuer, i)
+                && issuer.subject() == certificate.issuer()
+                && issuer
+                    .public_key()
+                    .and_then(|key| certificate.verify_signature(&key))
+                    .is_ok()
+        };
+        if trust_anchors.iter().any(|anchor| {
+            anchor.is_valid_at(time)
+                && ((anchor.subject() == certificate.subject()
+                    && anchor.subject_public_key_info() == certificate.subject_public_key_info())
+                    || is_issued_by(anchor))
+        }) {
+            return Ok(());
+        }
+        match chain.get(i + 1) {
+            Some(issuer) if is_issued_by(issuer) => {}
+            _ => return Err(Error::InvalidCertificate),
+        }
+    }
+    unreachable!()
+}
+
+/// Checks whether `issuer` may sign certificates, and be followed by `intermediates` intermediate
+/// CAs in a path if it has a path length constraint.
+fn may_issue(issuer: &Certificate, intermediates: usize) -> bool {
+    issuer
+        .key_usage()
+        .is_none_or(|usage| usage.contains(KeyUsage::KEY_CERT_SIGN))
+        && issuer
+            .basic_constraints()
+            .and_then(|constraints| constraints.path_len())
+            .is_none_or(|path_len| path_len >= intermediates as u64)
+}
+
+/// Matches a DNS name from a certificate, which may start with a `*` label matching exactly one
+/// label, against a server name.
+fn name_matches(name: &str, server_name: &str) -> bool {
+    match name.strip_prefix("*.") {
+        Some(suffix) => server_name
+            .split_once('.')
+            .is_some_and(|(label, rest)| !label.is_empty() && rest.eq_ignore_ascii_case(suffix)),
+        None => name.eq_ignore_ascii_case(server_name),
+    }
+}
+
+fn verify_certificate_verify(
+    scheme: u16,
+    signature: &[u8],
+    public_key: &PublicKey,
+    transcript_hash: &[u8],
+) -> Result<(), Error> {
+    let message = &[
+        &[0x20; 64][..],
+        b"TLS 1.3, server CertificateVerify\0",
+        transcript_hash,
+    ]
+    .concat();
+    match (scheme, public_key) {
+        (ECDSA_SECP256R1_SHA256, PublicKey::P256(public_key)) => {
+            let signature =
+                p256::signature_from_der(signature).map_err(|_| Error::InvalidSignature)?;
+            EcdsaP256::verify(message, b"", &signature, public_key)
+        }
+        (ECDSA_SECP384R1_SHA384, PublicKey::P384(public_key)) => {
+            let signature =
+                p384::signature_from_der(signature).map_err(|_| Error::InvalidSignature)?;
+            EcdsaP384::verify(message, b"", &signature, public_key)
+        }
+        (RSA_PSS_RSAE_SHA256, PublicKey::Rsa(public_key)) => {
+            Pss::<Sha256>::verify(message, b"", signature, public_key)
+        }
+        (RSA_PSS_RSAE_SHA384, PublicKey::Rsa(public_key)) => {
+            Pss::<Sha384>::verify(message, b"", signature, public_key)
+        }
+        (RSA_PSS_RSAE_SHA512, PublicKey::Rsa(public_key)) => {
+            Pss::<Sha512>::verify(message, b"", signature, public_key)
+        }
+        (ED25519, PublicKey::Ed25519(public_key)) => {
+            Ed25519::verify(message, b"", signature, public_key)
+        }
+        _ => Err(Error::InvalidSignature),
+    }
+}
+
+/// Reads a record, outputting its content type and the record including its header.
+fn read_raw_record(stream: &mut impl Read) -> io::Result<(ContentType, Vec<u8>)> {
+    let mut header = [0; HEADER_LEN];
+    stream.read_exact(&mut header)?;
+    let (content_type, len) = record::parse_header(&header)?;
+    let mut record = vec![0; HEADER_LEN + len];
+    record[..HEADER_LEN].copy_from_slice(&header);
+    stream.read_exact(&mut record[HEADER_LEN..])?;
+    Ok((content_type, record))
+}
+
+/// Removes a complete handshake message from the start of `buffer`, if there is one.
+fn take_handshake_message(buffer: &mut Vec<u8>) -> Result<Option<Vec<u8>>, Error> {
+    if buffer.len() < 4 {
+        return Ok(None);
+    }
+    let len = 4 + BigEndian::read_u24(&buffer[1..4]) as usize;
+    if len > MAX_HANDSHAKE_LEN {
+        return Err(Error::InvalidLength);
+    }
+    if buffer.len() < len {
+        return Ok(None);
+    }
+    let rest = buffer.split_off(len);
+    Ok(Some(mem::replace(buffer, rest)))
+}
+
+fn alert_error(content: &[u8]) -> io::Error {
+    match content {
+        [_, CLOSE_NOTIFY] => io::ErrorKind::UnexpectedEof.into(),
+        [_, description] => io::Error::new(
+            io::ErrorKind::ConnectionAborted,
+            format!("received alert {}", description),
+        ),
+        _ => Error::InvalidEncoding.into(),
+    }
+}
+
+fn unsupported_version() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::Unsupported,
+        "the server doesn't support TLS 1.3",
+    )
+}
+
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs() as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encoding::pem::{self, Mode};
+    use crate::test_helpers::*;
+    use crate::tls13::Aes128GcmSha256;
+    use std::slice;
+
+    // Generated by OpenSSL
+    const TRUST_ANCHOR: &str = "-----BEGIN CERTIFICATE-----
+MIIBizCCATGgAwIBAgIURM19ZwDAVxwaNKUi8WVt9eOdJu4wCgYIKoZIzj0EAwIw
+EjEQMA4GA1UEAwwHVGVzdCBDQTAgFw0yNjEwMTUxMDIxMDhaGA8yMTI2MDkyMTEw
+MjEwOFowEjEQMA4GA1UEAwwHVGVzdCBDQTBZMBMGByqGSM49AgEGCCqGSM49AwEH
+A0IABLF6CcmjTZ+y1YdTHPLDsQdGBJRks52EyMzpXUKn6EuyeqkX08RM1gYuNFNP
+XR7pOhrJ0MFp+nUoGaulHHPd6MWjYzBhMB0GA1UdDgQWBBRyj0xbkBl+4qkS+Zor
+fFU7eUOtcTAfBgNVHSMEGDAWgBRyj0xbkBl+4qkS+ZorfFU7eUOtcTAPBgNVHRMB
+Af8EBTADAQH/MA4GA1UdDwEB/wQEAwICBDAKBggqhkjOPQQDAgNIADBFAiEA9pEu
+EdE5W+o7PEFlsQ4IoaPDmPVfnUUmD4Le8uvi48ICIB6ciTNAIvtRu/hxsUdODm5C
+PBhFqd53yS5edGeeIiRb
+-----END CERTIFICATE-----";
+
+    // Recorded from OpenSSL 3.5's s_server -rev with an Ed25519 certificate for localhost issued
+    // by the trust anchor, with every random byte of the client set to 0x42. The server picks
+    // X25519MLKEM768, reverses the line "hello\n" and closes the connection. SERVER_X25519 is
+    // recorded the same way with -groups X25519.
+    const SERVER: &str = "160303049a020004960303b0912732454ef9bc735dc071497862b86d223f789f\
+        a11fa4fbcae8681511102800130100046e002b000203040033046411ec04609f\
+        a6c498052b7351a52b0a2fcb10eb17676a6dc6abaaf99804091d1cf8aba83148\
+        6f95e74309e4a15eae84c1d6c9adaad016b8c3594d8326c5205726c834baa8c5\
+        aeb158bf6ed2b14ee7ba01cff6b487bc9db4f3ffd34aa37a0be8fad3653ba2c5\
+        50f4c1fda07aef7b8622d18f8f53fc264826a9387bd486b12a13d2b3055e176c\
+        3bbe57d2ccb52fb179df33360732feda6076261bd91c1aef6c396c9ba40e19cf\
+        7e6c992a69c30dd4b33e702f72f8a32a65c63246c4fbf71481068e4a7d95be9a\
+        9a99fbdaea0309936bb66e45ca1f296645ab10844e7e6220fc2de445e8350a61\
+        f4912ab04dace9a5a7e38fa53750f382578c046b79e28e9b01c511504a387310\
+        296f409fe1d1be0001eb1d045cf3570caadc7b1e59a8ccec3b097e40d743818c\
+        8c5dcfbf2c1925aa6147ff1d44568787902fc1aaba62d1bd8f3738df53dd8104\
+        6c33468a096adbc12f0ecd8caa142f6b8e18686fe268c314b0f425ee15f59a26\
+        e6dce73f232739ca320b29b830e169c07822ffe032a90d5d80f3aa154fc99d03\
+        52f29f88406590f1b3c033f325c41ab4ef31b7eaff8050fb28e7ac45ed84be83\
+        71f3aa649391be5b140af6b0d22a68eecd406f1c6675a09bc26affcc1ae0cf39\
+        c88d6936c72eb483fc0579ab0c7d3101f792134a4ef1f4c2ade59d58e4800fa6\
+        6fb0e5ba9a3efa0c53478cc92f86dfd8feeb4b6a060a12a819dd0924190854b3\
+        6ab3bcae3ca31f4c12029ce1300c1975174c82d5a525dc8b582d70a2f0d8a656\
+        7984a560ec30ba664e607775792ebde44faf39859d46a6bd3d04a0e3aea6aae2\
+        7797cb91b4d70b129c7ab968fe4ef09664da8b591c4f7fab59a0f838d2206e8c\
+        c2a5e94336a18861214653cca10ddd721511e6e9422efc94ee3a0b5c32478b96\
+        5b87157b8cbc1c526a69ecab35e679be9e11b09c16e60ba9b7789dbc6a318046\
+        1649b25b2d18838984d958241f1722d8ec633a899be6124cd038f670034cd6e0\
+        fb7eb059cb4dd7a63ea8ee911d3802c7228b5cdb9568d500309eaea01f9845eb\
+        2d98a9ab5801488d6ef290d257194dea9d2bad31df45dfbfb56529c533cd6c87\
+        c047dbac11b1ff114ec8c189f4d941e03485889ec2516555d38d0e480f419ca6\
+        88ed0eaaf6100b49278cbfd4c8753741a7fe94f23f38b42e192807c507a07e12\
+        29ef78c8792be388498e679df19ecc60059703c7a5d34ffb618dcd45b5366d0d\
+        c8645bce31c3726b3eebab15394790c9348d5ca79b7e30a63b2546c42390df80\
+        d0f9774109ea1acf6fe555be496e9e33aa629f9f478cb11420887d34a533be3f\
+        0b393c73d9a470d8220cb864066af9be57ad9460fea208e6de102dcda623b138\
+        af2bfea68d2260cc7e0ac47791afd302d9c041dafbcd781bc76c550be542936a\
+        e4fb952fb291812788975739a31f5f79ecded0f50595dbe34e483de68ec2022e\
+        b90fdd3b5d45f44f65cd25230af72e4988a5a61b65ef62ed1c488e71725d535b\
+        9a76d6bda5f8aae2c7deb7736bb27c3f020c2c70785973d1858e00cc386330ae\
+        649792453dcef9ca480da8d6989a195628081f30753bf7692b96f8c02ff64914\
+        0303000101170303001719a1b1c6f9a33c522e7fa2baa6d9c6e0374108795833\
+        df170303016b1c736782779bd0bbe683e5b4ed039cf5f40b6794c77fb7806fa2\
+        31691c0e9c94709039df2b9dd8c54d5477cf4466d158f33dee2c24b52af6060f\
+        6a9001644c9318e338c6b48bb10a54cf7867902ac0b562aed1e89ae8cea5cb77\
+        0d09d2c1f9740329fa6069763d790a53128c26a1c7763d532bb01deb50f36879\
+        db0e7809030077ac239fa9e2a6e76cf3a9943883019a462f17b492aed5970b6f\
+        4da3de17e3ba749aa970c96579f8f7d4ea5b56658011b877283948c9cb8dcb2d\
+        4435079d7e32472c3a87cd3b9a84f0e61ec8397758249f6c7f6a6cdb5d32f70b\
+        a41ad4e0035fe78e5af479a92a830688e886edd4814beeacb095764a2c976438\
+        b77b5fe98f58bd2a5d1bd2e6ff7f930753d6ab749dd7fc26092244ddae2fc7ec\
+        6730ac0e5dffef49161bf21f6452594d95f17b6319fbfb47a55c2ed25d7fb367\
+        3a1e65c79787e21c027c5bc504e4eb60d93949d6519671bf47f7cdb1e8b2b4ec\
+        7178eb4943b5595f3d9f9663a74b4bb7d617030300592415de1479e991154354\
+        f357f0760a725152f953b627369bab7920fddf1f9ad423744d91ada8c8bc35c7\
+        3f07d92a3c78da6580bff94879d86297f764a6046e3096155d946d041a34b001\
+        882458083d715513be701426a0921a1703030035019da296ed67e3c6804fb43b\
+        c0e0892cc4ccf7ea4031b2c929c31f7a9fe236b12a9ed9f507fe1d26b4c0c5a6\
+        f59273ff74e9637fb817030300ead7dc6f158bcac03a9fdf8f27cae35a03ad32\
+        493a211594d53c59d6efcf379957f351525e303a358d66e529c909ef8363d146\
+        35b766cc2b877c7321124c1cf261383c648f469ba18a7b45ceb9b44d920ddae1\
+        4b27bf3431b19b22de516b3d24f60824885b19b98d08b22593c8af8a69b142b4\
+        b8c59ebdca1c420f0381fdee9fe651880c4e98f6a1e55efc25c3f91363f4c7c4\
+        f1dc745b0a4242d040563d446cea912131f0b7c2711b9f4b46aea2d24ae52f3c\
+        a81baf12e9f86e3012baddc912d593e7c4ae4440dca5a31e6d49ba276d99b885\
+        15ad325a674210dafea9524db026fcaec732fdb96bb8524c17030300eaceb5d3\
+        9e40f5d24f381eec0f481ac314f76c943e9095ee0b15f51c7dc8aae42f8dcba4\
+        0e8a3329a8f66d770e8cd2fd5010425cd1c7d2c15ffc7193097d0d0baa62be97\
+        06065cdd3e87e10bce12bb2b3a2c70249f79d3f278d1f6e6cba04d959c645302\
+        668d2c5ad991ab20639244d28ff33f5dc7210a787946a076a8b06126b68566f3\
+        a287e608dcc8c3910098e2918409e4fe5ab2e5ecff36f38b1b0916b0e2904d74\
+        9e1cc58198de945249ff97d774f87cf4a0b99048b8ad04bd3aa2c4a424f98a60\
+        7a0c44b6103b525577f061e05f81f56a9334ac2330a14e902d9ad4fcc7595fce\
+        f43e3aafd8b9d0170303001784c7b6b728260acc157dcb5ffb2d09fde0159ab4\
+        5c4104170303001307bf0d9dfb127686351b3ca5f9389d70e92720";
+    const CLIENT: &str = "1603010558010005540303424242424242424242424242424242424242424242\
+        42424242424242424242420000021301010005290000000e000c0000096c6f63\
+        616c686f7374000a0006000411ec001d000d0014001204030503080408050806\
+        0807040105010601002b0003020304003304ea04e811ec04c03e87bcbb8575a2\
+        376486154a3d639d01869f8284cbc7b206f5e0940220112705975090637e2041\
+        32da7424271e7e93baf7766be51c25aad78e9b50aec4a457ba9a473883695cc3\
+        67c72155b3f7a4ecbabab11353465b2da86918fe48768c0bb8fea946788b4e07\
+        1703915623c0c79a0d024ff7206544f14a72720a2fbc55abcc0dd2028935dab3\
+        775c6b953367b958a23828936bfc1ae5a80c038a8c96218e393b4908fc336605\
+        936e9c32edb19bb3e1c937ca3789ccbe9315c454e94b01b62058387cbae7c97a\
+        529d8b3357a674841f367cd4347291079d360b6c938b58bb80772560324ce9ce\
+        c8c726c6819445b32aa2e8a5d021807417a32e9bc39268718649447d735e834b\
+        6e01db9b418900fd8244ab870e3fd50e09f69a7d5649554163273869c8a74aad\
+        09ae29998cb54590adfacb1a4b1f43555a75e6cf6aa99fea2cb396ec3c87120c\
+        4da0b3818762930ccea2022bc4ba4d20da653bd431ffa77279b12f48c392dbd5\
+        828c890899039ab8c92b99d2943d174e7f5983cd4304e360ca69407542b76fdb\
+        49238da64b0a051577b492eed72947fa0a50bb6f65366cf3b7489ab8953a5199\
+        9f1391dc39ccc85cb5ec76bc1f1a168267aa25e1844f674f105bb4db371db8d9\
+        75cc669c76e58e24a2a690124fe5f453ee198be21b1392c63e43579e32475116\
+        f2a88eb0c689147fc8022fa4c28f87e41a6248c3dc3666a502377a8b10198755\
+        9d0c8eefb65f56ec16c140a518f7b0fe93abff6ac68f181370e51710fcb35447\
+        752ecc2f0d412f3ab1cbef19c4a508b3c4f7ac25c98e84630036ecaf439c6b46\
+        0294ffd9a213895f6c497cebc51113aa75bb5cae080428207767306a8bc636a8\
+        12b0c7c1c93e3a599dab123ddcfb57306346859cca1e77b144b6046a5c057e19\
+        22587339b364c099ec74cba31bbf321d3f179a2270cdb94656e00b9d77f9c7b3\
+        e5c3f61a29d6a3c5792a1f9e26374fc5763800338060ba45f28cd048a5d31b52\
+        072178eb3a32ad516a6cc999c0390bfb6c94b1a5278df775f24b544849332e65\
+        8bcc9b6da65c039fbcc917e69a612aaccadc5ac0f103bc6993819b58c4da3e17\
+        4603b76c13277221a2ca9420b71cd06bbf05445953b31195da8f4c62a9af96c3\
+        bd326c1e413f638154f58486de972cc7a50f1d5b0654e18e89f35cfa53ad9935\
+        1bd97773cf520ac25b5ee50cb7c3d51c5d00603d2cb65e66767f76563430cfaa\
+        2ba11f6248b20020e670cb6d8a7ee92b8888e92afaaac566c2883cb28ddf022a\
+        4ee47ed9332fe834a8aef6bd965395e9f95d008118a3f697f3421fee8908de17\
+        0f8525673ba559427bb0ee83c8aa3b6d505216fab501120381bc290939c5203e\
+        895ced259b1876b7cfa2372cc4764b265bd57123ee08644233954b0a2c944006\
+        635540045ab797b560ae977cd1326e453c97ce72bbcb85c443842726ccbb5b06\
+        9f2ea1a63a452087ac2d2ec30b8a00a794598bc7bc3c70d61fecaa3de678b90a\
+        682c6380570d9a0671010983697208955501fa391604681a945432e757aa122c\
+        ddb90a6642c850b276ffe74f79082c5b0885ad46b645e6b8137b502d62aed573\
+        295beac276dc46d0b71141a80be5905500cbb7c645040cbb929ddc4bcf836bbc\
+        f25cb73aef430bbafa2e30bc61a6df068d4106d3cb8b4c3604132c442be010fb\
+        d57e72603328aa76e71fccc1503aae219327d14d9c9993f472001d0020132c44\
+        2be010fbd57e72603328aa76e71fccc1503aae219327d14d9c9993f472170303\
+        00350c213b3460335473a6ffebe863a79292d1bf7e2f54539fedbf49e2041083\
+        7e5dbac31e33a31470333898bb6703eb6c53d75a4188c0170303001781caf719\
+        96500cca62cb4adb0d5ed3458fad8ab518a1ea170303001371e9c6948654afb6\
+        d42fc0a73c0c87fb63bbd8";
+    const SERVER_X25519: &str = "160303005a0200005603038ded1f6aee1cc1dd5840f3f6d2bb956dbd1980d0ee\
+        a30f29bd14251ebcbe10cf00130100002e002b0002030400330024001d00200b\
+        da4ebe9089ee855f9c451fd2657ceff336f8143e102e2f7616836e796d045714\
+        0303000101170303001737ee95b810eaa1bd3fad737049ed5fbb6c797bea9e17\
+        b4170303016b364bf63e378e7c817315dd62f3993af19d2df96828cf58b8a6cb\
+        8168cbf036f26c9a1c4a3eaa71513e4e8ea170e93fac6f1c90c31dae3f2d9f43\
+        a4a0884da8460d462aac5a2378b6cd512409b74f41f9e7138a77b630da3d3da4\
+        78ab2e56ebd88d40c8deb30944828edb04d075a73713e4191e000e1a11ccf291\
+        fc910ed804f975a70cc247e96f4afb31c9eee6f6f8f5658ac3cc7f6bd239335b\
+        a60a8acaf184b951922d600ff82d81e8d5630c0e96100265e6e7983cab0fef33\
+        8da3076d31c15afc461d5b9041ea16c3ac7598cd3723ae06273fb00605377e50\
+        e5085cdffe932908a989cafa253e28807a1c1aa33b79359afa11614fed01ade2\
+        4c4f191f935892866b290362e3ac550afa4fda280c450b7908b73e8c358f2485\
+        3b2b90dc00ca6a0ae62e2d9cd9a2baa1d0ff3039f65c53ab5c10683c20794dd8\
+        6d13517f8abce9a7464ea191575c0ddfe894a4128489715cfd68158aaf7135fb\
+        5163ea4ebad5a72f6e6a95e5799307539a17030300595e13565935c8d00e503d\
+        a35d795561885893560adde9f94de35e3cb0d28abedf9024d365de926e01a62b\
+        7773a67d8c1498d39e5c6cf591123a5f5e0de97283ca5d2d445e17f41b98c30c\
+        971338d55e488d5d7a7dc232ea994d1703030035b744e8f26aa18f41e364e923\
+        3e42780b6defd63d5f84f87afdd2d7140a2eb4dc6e04e6b337a477a1d4328824\
+        0598cf844b38630c5617030300ea5c1876e6bca10548e7284414e9b40e4ffe3a\
+        ac1027fa0c5e19e22105f42d8938a26a878f6d3be04c5857a28b2ecfdf056bd1\
+        6b0dd7b18a1b2274f8a8c52e9980132bf22033e7d1839021fb5f330c9851e84e\
+        e5a986cb8a61b5d354048dff2f672ed369937d6370928b69241748c4519ad375\
+        4c8dffe77ad8e68dc66f861f3438be57d9e0237ee019320b1538eff7d658bc39\
+        f8d9f57d9e2cd74ab45833bb5dad33744d06d5e495b23bfdc35ebcff7adbee56\
+        d0680071e8db9193dbe63d8a9148726274cbd67b092173731c4d01b15aca5176\
+        98e74b61c732a641d5d0bf1440e949521a921ece0d85c5a117030300ea8a07d1\
+        3097810c348ce95c344eb25c77c46e5cb65331d0a5f475c412d52a29067d6442\
+        5be055781a21ea7c86fbbf29c259090b2c0bed29d76cd14f2ad6a25aa78a2978\
+        af800b6b37dbda41a5e67cfd0d85ce35070ad55aa887052d0de79f06232289fc\
+        b5ef9c7c78ed14e12f15e60483ae5efef14246ff2937f8148754161e0ffaad2b\
+        8f93be931064de5053b2ee7238088603ac0d508020d1c67fbf5517a2a7e8cf8e\
+        2dc400e5b4d45611f65825b779655453e719cce0cd4de847ac5a38d6a78dfb72\
+        847719a1b9b36561071ce99c2a1279ac4c5b0a87156f8fad990487768eb32ff4\
+        98c064162bd8d4170303001723a05a90cb0c405022516186d2c142ddea0deb3a\
+        1d7e061703030013e22ed0b00f69a5a0d17f96c88f679192c7c218";
+
+    // The length of the server's records up to its Finished message
+    const HANDSHAKE_LEN: usize = 1737;
+
+    struct Stream<'a> {
+        input: &'a [u8],
+        output: Vec<u8>,
+    }
+
+    impl Read for Stream<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.input.read(buf)
+        }
+    }
+
+    impl Write for Stream<'_> {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.output.write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn connect<'a>(
+        server: &'a [u8],
+        server_name: &str,
+        trust_anchor: &str,
+    ) -> io::Result<Client<Aes128GcmSha256, Stream<'a>>> {
+        let der = pem::parse(trust_anchor, Mode::Strict).unwrap();
+        let trust_anchors = &[Certificate::from_der(der.contents()).unwrap()];
+        let stream = Stream {
+            input: server,
+            output: Vec::new(),
+        };
+        let rng = &|dest: &mut [u8]| dest.iter_mut().for_each(|byte| *byte = 0x42);
+        Client::connect(stream, server_name, trust_anchors, rng)
+    }
+
+    fn error<T>(result: io::Result<T>) -> Error {
+        let error = result.err().unwrap();
+        assert_eq!(io::ErrorKind::InvalidData, error.kind());
+        *error.get_ref().unwrap().downcast_ref::<Error>().unwrap()
+    }
+
+    #[test]
+    fn test_replay() {
+        let server = &h2b(SERVER);
+        let mut client = connect(server, "localhost", TRUST_ANCHOR).unwrap();
+        assert_eq!(1, client.peer_certificates().len());
+        client.write_all(b"hello\n").unwrap();
+        let response = &mut Vec::new();
+        client.read_to_end(response).unwrap();
+        assert_eq!(b"olleh\n", &response[..]);
+        client.close().unwrap();
+        assert_eq!(h2b(CLIENT), client.stream.output);
+        assert_eq!(
+            io::ErrorKind::BrokenPipe,
+            client.write(b"more").unwrap_err().kind()
+        );
+    }
+
+    #[test]
+    fn test_x25519() {
+        let server = &h2b(SERVER_X25519);
+        let mut client = connect(server, "localhost", TRUST_ANCHOR).unwrap();
+        client.write_all(b"hello\n").unwrap();
+        let response = &mut Vec::new();
+        client.read_to_end(response).unwrap();
+        assert_eq!(b"olleh\n", &response[..]);
+    }
+
+    #[test]
+    fn test_invalid() {
+        let server = &mut h2b(SERVER);
+        assert_eq!(
+            Error::InvalidEncoding,
+            error(connect(server, "", TRUST_ANCHOR))
+        );
+
+        // A self-signed certificate from the x509 module's examples
+        let other_anchor = "-----BEGIN CERTIFICATE-----
+MIIBLzCB4qADAgECAgEDMAUGAytlcDAXMRUwEwYDVQQDDAxFZDI1NTE5IFRlc3Qw
+HhcNMjQwMjI5MTIwMDAwWhcNMzQwMjI4MTIwMDAwWjAXMRUwEwYDVQQDDAxFZDI1
+NTE5IFRlc3QwKjAFBgMrZXADIQBC0Yry9BEWRJAuDhao81aq9HydfTYezENPSfCv
+lPmCiKNTMFEwHQYDVR0OBBYEFN1ThD5jGupeR7cAzO5ujXAV+nu3MB8GA1UdIwQY
+MBaAFN1ThD5jGupeR7cAzO5ujXAV+nu3MA8GA1UdEwEB/wQFMAMBAf8wBQYDK2Vw
+A0EAJV7ofz3YNL6mXGbI1hBtrm96zxORv+RqIUcWcgmRcIvksXg8Kb0cWgrzV5q1
+QgItdfAgUyDYWsKwQOtMLZN/BQ==
+-----END CERTIFICATE-----";
+        assert_eq!(
+            Error::InvalidCertificate,
+            error(connect(server, "localhost", other_anchor))
+        );
+
+        // The ServerHello's random, which changes the keys
+        server[20] ^= 1;
+        assert_eq!(
+            Error::TagMismatch,
+            error(connect(server, "localhost", TRUST_ANCHOR))
+        );
+        server[20] ^= 1;
+
+        // The ML-KEM-768 ciphertext in the server's key share, which is implicitly rejected
+        server[100] ^= 1;
+        assert_eq!(
+            Error::TagMismatch,
+            error(connect(server, "localhost", TRUST_ANCHOR))
+        );
+        server[100] ^= 1;
+
+        // The server's first encrypted record
+        server[1200] ^= 1;
+        assert_eq!(
+            Error::TagMismatch,
+            error(connect(server, "localhost", TRUST_ANCHOR))
+        );
+        server[1200] ^= 1;
+        let len = server.len();
+
+        // The connection is cut off during the handshake or before close_notify
+        assert_eq!(
+            io::ErrorKind::UnexpectedEof,
+            connect(&server[..len / 2], "localhost", TRUST_ANCHOR)
+                .err()
+                .unwrap()
+                .kind()
+        );
+        let mut client = connect(&server[..len - 24], "localhost", TRUST_ANCHOR).unwrap();
+        assert_eq!(
+            io::ErrorKind::UnexpectedEof,
+            client.read_to_end(&mut Vec::new()).unwrap_err().kind()
+        );
+    }
+
+    fn protect(
+        sender: &mut RecordProtection<Aes128GcmSha256>,
+        content_type: ContentType,
+        content: &[u8],
+    ) -> Vec<u8> {
+        let mut record = vec![0; RecordProtection::<Aes128GcmSha256>::record_len(content.len(), 0)];
+        sender
+            .protect(content_type, content, 0, &mut record)
+            .unwrap();
+        record
+    }
+
+    fn unprotect(receiver: &mut RecordProtection<Aes128GcmSha256>, stream: &mut &[u8]) -> Vec<u8> {
+        let (content_type, mut record) = read_raw_record(stream).unwrap();
+        assert_eq!(ContentType::ApplicationData, content_type);
+        let (content_type, content) = receiver.unprotect(&mut record).unwrap();
+        assert_eq!(ContentType::Handshake, content_type);
+        content.to_vec()
+    }
+
+    #[test]
+    fn test_key_update() {
+        let client_secret = &[1; 32];
+        let server_secret = &[2; 32];
+        let mut server_writing = RecordProtection::new(server_secret).unwrap();
+        let mut server_reading = RecordProtection::new(client_secret).unwrap();
+        let mut input = Vec::new();
+        let key_update = KeyUpdate::UpdateRequested.encode();
+        input.extend(protect(
+            &mut server_writing,
+            ContentType::Handshake,
+            &key_update,
+        ));
+        server_writing.update_traffic_secret();
+        input.extend(protect(
+            &mut server_writing,
+            ContentType::ApplicationData,
+            b"hello",
+        ));
+        input.extend(protect(&mut server_writing, ContentType::Alert, &[1, 0]));
+
+        // Replace the application traffic secrets after the handshake
+        let server = &h2b(SERVER);
+        let mut client = connect(&server[..HANDSHAKE_LEN], "localhost", TRUST_ANCHOR).unwrap();
+        client.reading = RecordProtection::new(server_secret).unwrap();
+        client.writing = RecordProtection::new(client_secret).unwrap();
+        client.stream.input = &input;
+        client.stream.output.clear();
+        let response = &mut Vec::new();
+        client.read_to_end(response).unwrap();
+        assert_eq!(b"hello", &response[..]);
+        client.update_keys(KeyUpdate::UpdateRequested).unwrap();
+        client.write_all(b"hello").unwrap();
+
+        let output = &mut &client.stream.output[..];
+        let expected = KeyUpdate::UpdateNotRequested.encode();
+        assert_eq!(&expected[..], &unprotect(&mut server_reading, output)[..]);
+        server_reading.update_traffic_secret();
+        assert_eq!(&key_update[..], &unprotect(&mut server_reading, output)[..]);
+        server_reading.update_traffic_secret();
+        let (_, mut record) = read_raw_record(output).unwrap();
+        let (content_type, content) = server_reading.unprotect(&mut record).unwrap();
+        assert_eq!(ContentType::ApplicationData, content_type);
+        assert_eq!(b"hello", content);
+        assert!(output.is_empty());
+
+        // The server must update its keys at a record boundary
+        let mut server_writing = RecordProtection::new(server_secret).unwrap();
+        let mut content = key_update.to_vec();
+        content.extend_from_slice(&key_update);
+        let input = protect(&mut server_writing, ContentType::Handshake, &content);
+        let mut client = connect(&server[..HANDSHAKE_LEN], "localhost", TRUST_ANCHOR).unwrap();
+        client.reading = RecordProtection::new(server_secret).unwrap();
+        client.stream.input = &input;
+        assert_eq!(Error::InvalidEncoding, error(client.read(&mut [0; 5])));
+        client.close().unwrap();
+        assert_eq!(
+            io::ErrorKind::BrokenPipe,
+            client
+                .update_keys(KeyUpdate::UpdateNotRequested)
+                .unwrap_err()
+                .kind()
+        );
+    }
+
+    #[test]
+    fn test_verify_chain() {
+        let server = &h2b(SERVER);
+        let client = connect(server, "localhost", TRUST_ANCHOR).unwrap();
+        let leaf = Certificate::from_der(&client.peer_certificates()[0]).unwrap();
+        let der = pem::parse(TRUST_ANCHOR, Mode::Strict).unwrap();
+        let trust_anchor = Certificate::from_der(der.contents()).unwrap();
+        let chain = slice::from_ref(&leaf);
+        let trust_anchors = slice::from_ref(&trust_anchor);
+        let time = 1_800_000_000;
+        assert_eq!(
+            Ok(()),
+            verify_chain(chain, "localhost", trust_anchors, time)
+        );
+        assert_eq!(
+            Ok(()),
+            verify_chain(
+                &[leaf.clone(), trust_anchor.clone()],
+                "localhost",
+                trust_anchors,
+                time
+            )
+        );
+        assert_eq!(
+            Err(Error::InvalidCertificate),
+            verify_chain(chain, "example.com", trust_anchors, time)
+        );
+        assert_eq!(
+            Err(Error::InvalidCertificate),
+            verify_chain(chain, "localhost", trust_anchors, 5_000_000_000)
+        );
+        assert_eq!(
+            Err(Error::InvalidCertificate),
+            verify_chain(chain, "localhost", &[], time)
+        );
+    }
+
+    // Generated by OpenSSL with Ed25519 keys. The roots share a key and subject, and differ in
+    // their key usage, path length constraint or validity period. Intermediate A is issued by the
+    // root with a path length constraint of 0 and issues intermediate B, leaf A and the leaf with
+    // an unrecognized critical extension. Intermediate B issues leaf B.
+    const ROOT: &str = "-----BEGIN CERTIFICATE-----
+MIIBHDCBz6ADAgECAgMCgpUwBQYDK2VwMBQxEjAQBgNVBAMMCVRlc3QgUm9vdDAg
+Fw0yNjAxMDEwMDAwMDBaGA8yMTI2MDEwMTAwMDAwMFowFDESMBAGA1UEAwwJVGVz
+dCBSb290MCowBQYDK2VwAyEAxX3zugqK6mwLFm1bzg/TrlWNSVypP37t4x7s4DRC
+83ijQjBAMA8GA1UdEwEB/wQFMAMBAf8wDgYDVR0PAQH/BAQDAgIEMB0GA1UdDgQW
+BBQjk7i7wEbThYhfGSLnheyLLLcSZTAFBgMrZXADQQC8DmhvSLqar1U0tZ90gJZ5
+Tja4WhMugkU+JnPkv9WSveFqZhy615c6mEnHcQz1JSJvqFkyLC46fxKDej6AALAL
+-----END CERTIFICATE-----";
+    const ROOT_WITHOUT_CERT_SIGN: &str = "-----BEGIN CERTIFICATE-----
+MIIBGzCBzqADAgECAgI2lDAFBgMrZXAwFDESMBAGA1UEAwwJVGVzdCBSb290MCAX
+DTI2MDEwMTAwMDAwMFoYDzIxMjYwMTAxMDAwMDAwWjAUMRIwEAYDVQQDDAlUZXN0
+IFJvb3QwKjAFBgMrZXADIQDFffO6CorqbAsWbVvOD9OuVY1JXKk/fu3jHuzgNELz
+eKNCMEAwDwYDVR0TAQH/BAUwAwEB/zAOBgNVHQ8BAf8EBAMCB4AwHQYDVR0OBBYE
+FCOTuLvARtOFiF8ZIueF7IsstxJlMAUGAytlcANBAHVQboQu/8H8buwYXXmYaVwD
+QXY00W2j17WKcVWOtKkA9w4Vp7b8clh4+TtE7saKgdziJVooHsgloS/TxVXB4w8=
+-----END CERTIFICATE-----";
+    const ROOT_WITH_PATH_LEN: &str = "-----BEGIN CERTIFICATE-----
+MIIBHzCB0qADAgECAgMBUYIwBQYDK2VwMBQxEjAQBgNVBAMMCVRlc3QgUm9vdDAg
+Fw0yNjAxMDEwMDAwMDBaGA8yMTI2MDEwMTAwMDAwMFowFDESMBAGA1UEAwwJVGVz
+dCBSb290MCowBQYDK2VwAyEAxX3zugqK6mwLFm1bzg/TrlWNSVypP37t4x7s4DRC
+83ijRTBDMBIGA1UdEwEB/wQIMAYBAf8CAQAwDgYDVR0PAQH/BAQDAgIEMB0GA1Ud
+DgQWBBQjk7i7wEbThYhfGSLnheyLLLcSZTAFBgMrZXADQQA1aEDPNOqq0ZIIsJ+z
+R3S4bQZMlWqRPGnEFJE1J/558N0yc4uG8FS9nac5vpEJ8RHX9+dQOsLeYN7/2gOo
+hWMF
+-----END CERTIFICATE-----";
+    const EXPIRED_ROOT: &str = "-----BEGIN CERTIFICATE-----
+MIIBGjCBzaADAgECAgMCYZkwBQYDK2VwMBQxEjAQBgNVBAMMCVRlc3QgUm9vdDAe
+Fw0yMDAxMDEwMDAwMDBaFw0yMTAxMDEwMDAwMDBaMBQxEjAQBgNVBAMMCVRlc3Qg
+Um9vdDAqMAUGAytlcAMhAMV987oKiupsCxZtW84P065VjUlcqT9+7eMe7OA0QvN4
+o0IwQDAPBgNVHRMBAf8EBTADAQH/MA4GA1UdDwEB/wQEAwICBDAdBgNVHQ4EFgQU
+I5O4u8BG04WIXxki54Xsiyy3EmUwBQYDK2VwA0EADO1ZG6S+U5WRR439tvp5LJwJ
+00X4o4Ni02OqjFgNe+qGTgIJTmzRDGZd2/vxPntpsi1Yu0ItqPSPvJMjfuMTCg==
+-----END CERTIFICATE-----";
+    const INTERMEDIATE_A: &str = "-----BEGIN CERTIFICATE-----
+MIIBRTCB+KADAgECAgMDFHUwBQYDK2VwMBQxEjAQBgNVBAMMCVRlc3QgUm9vdDAg
+Fw0yNjAxMDEwMDAwMDBaGA8yMTI2MDEwMTAwMDAwMFowGTEXMBUGA1UEAwwOSW50
+ZXJtZWRpYXRlIEEwKjAFBgMrZXADIQAxPJ1epyDX7nPxqiG6fhwbCPY2UjdFTu58
+sy2KnuPHC6NmMGQwEgYDVR0TAQH/BAgwBgEB/wIBADAOBgNVHQ8BAf8EBAMCAgQw
+HQYDVR0OBBYEFCf/rbdSvedmla8Iq+Xl1VRCFqh0MB8GA1UdIwQYMBaAFCOTuLvA
+RtOFiF8ZIueF7IsstxJlMAUGAytlcANBAHH0OGRAYjy149iDIoernbrFgE09ET/p
+1ped+vng6MKxo5D1OaVZAwvdczHKHTOH9ZCSCxogydco5ygN3hCJrAk=
+-----END CERTIFICATE-----";
+    const INTERMEDIATE_B: &str = "-----BEGIN CERTIFICATE-----
+MIIBRjCB+aADAgECAgJpgzAFBgMrZXAwGTEXMBUGA1UEAwwOSW50ZXJtZWRpYXRl
+IEEwIBcNMjYwMTAxMDAwMDAwWhgPMjEyNjAxMDEwMDAwMDBaMBkxFzAVBgNVBAMM
+DkludGVybWVkaWF0ZSBCMCowBQYDK2VwAyEAfJZap12YCkM+Zl5yjtXtORZ5AHHZ
+ece75fYwTY0q8oSjYzBhMA8GA1UdEwEB/wQFMAMBAf8wDgYDVR0PAQH/BAQDAgIE
+MB0GA1UdDgQWBBRpQOilgjAUiH/BOqfLlbbG6/9f9DAfBgNVHSMEGDAWgBQn/623
+Ur3nZpWvCKvl5dVUQhaodDAFBgMrZXADQQAkCczsrnY65vxp5IyY9DkdcYUAXHzM
+xZ7oDrPy0rOgCRM/S/P+m63juSuMJvl4BUMlBTD3HnJdzPQjmIU57bgP
+-----END CERTIFICATE-----";
+    const LEAF_A: &str = "-----BEGIN CERTIFICATE-----
+MIIBNzCB6qADAgECAgMBdpAwBQYDK2VwMBkxFzAVBgNVBAMMDkludGVybWVkaWF0
+ZSBBMCAXDTI2MDEwMTAwMDAwMFoYDzIxMjYwMTAxMDAwMDAwWjAUMRIwEAYDVQQD
+DAlsb2NhbGhvc3QwKjAFBgMrZXADIQDfLB1/rm1tTsKf8L1J/Q7ESY69yX+48u0Z
+GB/WYzt04aNYMFYwFAYDVR0RBA0wC4IJbG9jYWxob3N0MB0GA1UdDgQWBBRigLHL
+q29ZXKQzDYYVmX1+mWY35jAfBgNVHSMEGDAWgBQn/623Ur3nZpWvCKvl5dVUQhao
+dDAFBgMrZXADQQCwSXtymGY7EzuRx2SVofye2XyVPgGS0xk6852t7I1Uty6A4MK+
+MdB5g1WLl05B+e0NvSjdbj6/WJgX8+vSWlUF
+-----END CERTIFICATE-----";
+    const LEAF_B: &str = "-----BEGIN CERTIFICATE-----
+MIIBNzCB6qADAgECAgMChXgwBQYDK2VwMBkxFzAVBgNVBAMMDkludGVybWVkaWF0
+ZSBCMCAXDTI2MDEwMTAwMDAwMFoYDzIxMjYwMTAxMDAwMDAwWjAUMRIwEAYDVQQD
+DAlsb2NhbGhvc3QwKjAFBgMrZXADIQDfLB1/rm1tTsKf8L1J/Q7ESY69yX+48u0Z
+GB/WYzt04aNYMFYwFAYDVR0RBA0wC4IJbG9jYWxob3N0MB0GA1UdDgQWBBRigLHL
+q29ZXKQzDYYVmX1+mWY35jAfBgNVHSMEGDAWgBRpQOilgjAUiH/BOqfLlbbG6/9f
+9DAFBgMrZXADQQDHUHQAbj7E2PSOwl+bqrE2iShsONebncE6tapw+Jh7DZxgBMjc
+0DzpIKoAGUssnfqSmHkMVx/tnZXCCdFFRVIO
+-----END CERTIFICATE-----";
+    const LEAF_WITH_CRITICAL_EXTENSION: &str = "-----BEGIN CERTIFICATE-----
+MIIBRTCB+KADAgECAgMBJiIwBQYDK2VwMBkxFzAVBgNVBAMMDkludGVybWVkaWF0
+ZSBBMCAXDTI2MDEwMTAwMDAwMFoYDzIxMjYwMTAxMDAwMDAwWjAUMRIwEAYDVQQD
+DAlsb2NhbGhvc3QwKjAFBgMrZXADIQDfLB1/rm1tTsKf8L1J/Q7ESY69yX+48u0Z
+GB/WYzt04aNmMGQwFAYDVR0RBA0wC4IJbG9jYWxob3N0MAwGAyoDBAEB/wQCBQAw
+HQYDVR0OBBYEFGKAscurb1lcpDMNhhWZfX6ZZjfmMB8GA1UdIwQYMBaAFCf/rbdS
+vedmla8Iq+Xl1VRCFqh0MAUGAytlcANBAOu0IpN03Ya3UOSfzHbrk512xPkzv6tG
+8ASAC5y7syjMCl16CpHxDS86JmBg5uJ0sgQsscHckEZ9u83ijT3cPQo=
+-----END CERTIFICATE-----";
+
+    fn check_chain(chain: &[&str], trust_anchor: &str) -> Result<(), Error> {
+        let ders: Vec<_> = chain
+            .iter()
+            .chain([&trust_anchor])
+            .map(|pem| pem::parse(pem, Mode::Strict).unwrap())
+            .collect();
+        let certificates: Vec<_> = ders
+            .iter()
+            .map(|der| Certificate::from_der(der.contents()).unwrap())
+            .collect();
+        let (trust_anchor, chain) = certificates.split_last().unwrap();
+        verify_chain(
+            chain,
+            "localhost",
+            slice::from_ref(trust_anchor),
+            1_800_000_000,
+        )
+    }
+
+    #[test]
+    fn test_verify_chain_constraints() {
+        let chain = &[LEAF_A, INTERMEDIATE_A];
+        let error = Err(Error::InvalidCertificate);
+        assert_eq!(Ok(()), check_chain(chain, ROOT));
+        assert_eq!(error, check_chain(chain, ROOT_WITHOUT_CERT_SIGN));
+        assert_eq!(error, check_chain(chain, ROOT_WITH_PATH_LEN));
+        assert_eq!(error, check_chain(chain, EXPIRED_ROOT));
+        let chain = &[LEAF_B, INTERMEDIATE_B, INTERMEDIATE_A];
+        assert_eq!(error, check_chain(chain, ROOT));
+        let chain = &[LEAF_WITH_CRITICAL_EXTENSION, INTERMEDIATE_A];
+        assert_eq!(error, check_chain(chain, ROOT));
+
+        let der = pem::parse(LEAF_WITH_CRITICAL_EXTENSION, Mode::Strict).unwrap();
+        let leaf = Certificate::from_der(der.contents()).unwrap();
+        assert!(leaf.has_unrecognized_critical_extension());
+    }
+
+    #[test]
+    fn test_name_matches() {
+        assert!(name_matches("localhost", "LocalHost"));
+        assert!(name_matches("*.example.com", "www.example.com"));
+        assert!(!name_matches("*.example.com", "example.com"));
+        assert!(!name_matches("*.example.com", "a.b.example.com"));
+        assert!(!name_matches("*.example.com", ".example.com"));
+        assert!(!name_matches("www.example.com", "example.com"));
+    }
+}
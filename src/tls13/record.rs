@@ -0,0 +1,476 @@
+//! Module for the TLS 1.3 record layer (RFC 8446, section 5).
+//!
+//! `RecordProtection` protects records in one direction with the traffic keys derived from a
+//! traffic secret: the content and its type are framed as a `TLSInnerPlaintext` with optional
+//! padding, and encrypted with the AEAD of the cipher suite under a nonce built from the record's
+//! sequence number. Records are protected and unprotected in caller-provided buffers, so no
+//! allocation is needed.
+//!
+//! Traffic secrets are updated with `update_traffic_secret` after sending or receiving a
+//! `KeyUpdate` message, which resets the sequence number.
+//!
+//! # Examples
+//!
+//! ```
+//! use crypto_pure::tls13::record::{ContentType, RecordProtection};
+//! use crypto_pure::tls13::Aes128GcmSha256;
+//! # let traffic_secret = &[0x42; 32];
+//! let mut sender = RecordProtection::<Aes128GcmSha256>::new(traffic_secret)?;
+//! let mut receiver = RecordProtection::<Aes128GcmSha256>::new(traffic_secret)?;
+//! let record = &mut [0; 64];
+//! let len = sender.protect(ContentType::ApplicationData, b"message", 10, record)?;
+//! let (content_type, content) = receiver.unprotect(&mut record[..len])?;
+//! assert_eq!(ContentType::ApplicationData, content_type);
+//! assert_eq!(b"message", content);
+//! # Ok::<(), crypto_pure::error::Error>(())
+//! ```
+use super::{next_traffic_secret, traffic_key_and_iv, CipherSuite, Secret};
+use crate::aead::Aead;
+use crate::error::Error;
+use crate::secret::SecretKey;
+use crate::sha2::{HashFunction, MAX_DIGEST_SIZE};
+use byteorder::{BigEndian, ByteOrder as _};
+
+/// The length of a record header.
+pub const HEADER_LEN: usize = 5;
+
+/// The maximum length of the content of a record.
+pub const MAX_CONTENT_LEN: usize = 1 << 14;
+
+/// The maximum length of the protected payload of a record after its header.
+pub const MAX_PAYLOAD_LEN: usize = MAX_CONTENT_LEN + 256;
+
+const NONCE_LEN: usize = 12;
+const KEY_UPDATE: u8 = 24;
+
+/// The type of the content of a record.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ContentType {
+    /// A ChangeCipherSpec record, which is only sent unprotected for compatibility
+    ChangeCipherSpec,
+    /// An alert
+    Alert,
+    /// Handshake messages
+    Handshake,
+    /// Application data, and the outer type of all protected records
+    ApplicationData,
+}
+
+impl ContentType {
+    /// Outputs the encoding of the content type.
+    pub fn to_u8(self) -> u8 {
+        match self {
+            ContentType::ChangeCipherSpec => 20,
+            ContentType::Alert => 21,
+            ContentType::Handshake => 22,
+            ContentType::ApplicationData => 23,
+        }
+    }
+
+    /// Decodes a content type, or outputs `None` if it is not known.
+    pub fn from_u8(content_type: u8) -> Option<Self> {
+        match content_type {
+            20 => Some(ContentType::ChangeCipherSpec),
+            21 => Some(ContentType::Alert),
+            22 => Some(ContentType::Handshake),
+            23 => Some(ContentType::ApplicationData),
+            _ => None,
+        }
+    }
+}
+
+/// A KeyUpdate handshake message, which announces that the sender's traffic secret is updated.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KeyUpdate {
+    /// The receiver need not update its own traffic secret.
+    UpdateNotRequested,
+    /// The receiver must update its own traffic secret in response.
+    UpdateRequested,
+}
+
+impl KeyUpdate {
+    /// Outputs the encoding of the message, including its handshake header.
+    pub fn encode(self) -> [u8; 5] {
+        let request_update = match self {
+            KeyUpdate::UpdateNotRequested => 0,
+            KeyUpdate::UpdateRequested => 1,
+        };
+        [KEY_UPDATE, 0, 0, 1, request_update]
+    }
+
+    /// Decodes a message including its handshake header.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::InvalidEncoding` if `message` is not a KeyUpdate message.
+    pub fn decode(message: &[u8]) -> Result<Self, Error> {
+        match message {
+            [KEY_UPDATE, 0, 0, 1, 0] => Ok(KeyUpdate::UpdateNotRequested),
+            [KEY_UPDATE, 0, 0, 1, 1] => Ok(KeyUpdate::UpdateRequested),
+            _ => Err(Error::InvalidEncoding),
+        }
+    }
+}
+
+/// Parses a record header, outputting the content type and the length of the payload after it.
+///
+/// # Errors
+///
+/// Fails with `Error::InvalidEncoding` if the content type or the version is not valid, or with
+/// `Error::InvalidLength` if the length is more than `MAX_PAYLOAD_LEN`.
+pub fn parse_header(header: &[u8; HEADER_LEN]) -> Result<(ContentType, usize), Error> {
+    let content_type = ContentType::from_u8(header[0]).ok_or(Error::InvalidEncoding)?;
+    // The legacy version is 0x0301 in an initial ClientHello and 0x0303 otherwise.
+    if header[1] != 3 || !(header[2] == 1 || header[2] == 3) {
+        return Err(Error::InvalidEncoding);
+    }
+    let len = BigEndian::read_u16(&header[3..]) as usize;
+    if len > MAX_PAYLOAD_LEN {
+        return Err(Error::InvalidLength);
+    }
+    Ok((content_type, len))
+}
+
+/// The protection of records in one direction.
+pub struct RecordProtection<S: CipherSuite> {
+    aead: S::Aead,
+    iv: [u8; NONCE_LEN],
+    sequence: u64,
+    traffic_secret: Secret,
+}
+
+impl<S: CipherSuite> RecordProtection<S> {
+    /// Derives the traffic keys from a traffic secret.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::InvalidLength` if `traffic_secret.len()` is not the digest size for the
+    /// cipher suite's hash function.
+    pub fn new(traffic_secret: &[u8]) -> Result<Self, Error> {
+        if traffic_secret.len() != S::Hash::DIGEST_SIZE {
+            return Err(Error::InvalidLength);
+        }
+        let mut buffer = SecretKey::new([0; MAX_DIGEST_SIZE]);
+        buffer[..traffic_secret.len()].copy_from_slice(traffic_secret);
+        let traffic_secret = Secret {
+            buffer,
+            size: traffic_secret.len(),
+        };
+        let (aead, iv) = Self::keys(&traffic_secret);
+        Ok(Self {
+            aead,
+            iv,
+            sequence: 0,
+            traffic_secret,
+        })
+    }
+
+    /// Outputs the sequence number of the next record.
+    pub fn sequence(&self) -> u64 {
+        self.sequence
+    }
+
+    /// Outputs the length of the record protecting content of length `len` with `padding` bytes
+    /// of padding.
+    pub fn record_len(len: usize, padding: usize) -> usize {
+        HEADER_LEN + len + 1 + padding + S::Aead::TAG_LEN
+    }
+
+    /// Protects `content` of type `content_type` with `padding` zero bytes of padding, writing
+    /// the record, including its header, into the start of `output` and outputting its length.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::InvalidLength` if the content, its type and the padding together are
+    /// longer than `MAX_CONTENT_LEN + 1` bytes or `output` is too short, or with
+    /// `Error::NonceExhausted` if 2^64 - 1 records have been protected.
+    pub fn protect(
+        &mut self,
+        content_type: ContentType,
+        content: &[u8],
+        padding: usize,
+        output: &mut [u8],
+    ) -> Result<usize, Error> {
+        let inner_len = content
+            .len()
+            .checked_add(padding)
+            .ok_or(Error::InvalidLength)?;
+        if inner_len > MAX_CONTENT_LEN {
+            return Err(Error::InvalidLength);
+        }
+        let len = Self::record_len(content.len(), padding);
+        if len - HEADER_LEN > MAX_PAYLOAD_LEN || output.len() < len {
+            return Err(Error::InvalidLength);
+        }
+        let nonce = self.nonce()?;
+        let (header, payload) = output[..len].split_at_mut(HEADER_LEN);
+        header[..3].copy_from_slice(&[ContentType::ApplicationData.to_u8(), 3, 3]);
+        BigEndian::write_u16(&mut header[3..], payload.len() as u16);
+        let (inner_plaintext, _) = payload.split_at_mut(payload.len() - S::Aead::TAG_LEN);
+        inner_plaintext[..content.len()].copy_from_slice(content);
+        inner_plaintext[content.len()] = content_type.to_u8();
+        for byte in &mut inner_plaintext[content.len() + 1..] {
+            *byte = 0;
+        }
+        self.aead.seal_in_place(&nonce, header, payload)?;
+        self.sequence += 1;
+        Ok(len)
+    }
+
+    /// Unprotects a record, including its header, in place, outputting the content type and
+    /// the content with the padding removed.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::InvalidEncoding` if the header is not valid for a protected record or
+    /// the content type is not valid, with `Error::InvalidLength` if the record's length is not
+    /// valid, with `Error::NonceExhausted` if 2^64 - 1 records have been unprotected, or with
+    /// `Error::TagMismatch` if the record fails to decrypt.
+    pub fn unprotect<'a>(
+        &mut self,
+        record: &'a mut [u8],
+    ) -> Result<(ContentType, &'a mut [u8]), Error> {
+        if record.len() < HEADER_LEN {
+            return Err(Error::InvalidLength);
+        }
+        let (header, payload) = record.split_at_mut(HEADER_LEN);
+        let mut header_array = [0; HEADER_LEN];
+        header_array.copy_from_slice(header);
+        let (outer_type, len) = parse_header(&header_array)?;
+        if outer_type != ContentType::ApplicationData || header[2] != 3 {
+            return Err(Error::InvalidEncoding);
+        }
+        if len != payload.len() || len > MAX_CONTENT_LEN + 1 + S::Aead::TAG_LEN {
+            return Err(Error::InvalidLength);
+        }
+        let nonce = self.nonce()?;
+        let inner_plaintext = self.aead.open_in_place(&nonce, header, payload)?;
+        self.sequence += 1;
+        let len = inner_plaintext
+            .iter()
+            .rposition(|byte| *byte != 0)
+            .ok_or(Error::InvalidEncoding)?;
+        let (content, rest) = inner_plaintext.split_at_mut(len);
+        let content_type = ContentType::from_u8(rest[0]).ok_or(Error::InvalidEncoding)?;
+        Ok((content_type, content))
+    }
+
+    /// Updates the traffic secret, for after a KeyUpdate message is sent or received.
+    pub fn update_traffic_secret(&mut self) {
+        self.traffic_secret = next_traffic_secret::<S::Hash>(&self.traffic_secret)
+            .expect("invalid traffic secret length");
+        let (aead, iv) = Self::keys(&self.traffic_secret);
+        self.aead = aead;
+        self.iv = iv;
+        self.sequence = 0;
+    }
+
+    fn keys(traffic_secret: &[u8]) -> (S::Aead, [u8; NONCE_LEN]) {
+        let mut key = SecretKey::new([0; 32]);
+        let key = &mut key[..S::Aead::KEY_LEN];
+        let mut iv = [0; NONCE_LEN];
+        traffic_key_and_iv::<S::Hash>(traffic_secret, key, &mut iv)
+            .expect("invalid traffic secret length");
+        (S::Aead::new(key).expect("invalid key length"), iv)
+    }
+
+    fn nonce(&self) -> Result<[u8; NONCE_LEN], Error> {
+        if self.sequence == u64::MAX {
+            return Err(Error::NonceExhausted);
+        }
+        let mut nonce = self.iv;
+        for (byte, s) in nonce[NONCE_LEN - 8..]
+            .iter_mut()
+            .zip(&self.sequence.to_be_bytes())
+        {
+            *byte ^= s;
+        }
+        Ok(nonce)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::*;
+    use crate::tls13::{Aes128GcmSha256, Aes256GcmSha384};
+
+    fn check<S: CipherSuite>(expected: [&str; 3]) {
+        let mut traffic_secret = [0; MAX_DIGEST_SIZE];
+        for (i, byte) in traffic_secret.iter_mut().enumerate() {
+            *byte = i as u8;
+        }
+        let traffic_secret = &traffic_secret[..S::Hash::DIGEST_SIZE];
+        let mut sender = RecordProtection::<S>::new(traffic_secret).unwrap();
+        let mut receiver = RecordProtection::<S>::new(traffic_secret).unwrap();
+        let record = &mut [0; 64];
+        let messages = [
+            (ContentType::ApplicationData, &b"hello"[..], 0),
+            (ContentType::Alert, &[1, 0][..], 5),
+            (
+                ContentType::Handshake,
+                &KeyUpdate::UpdateRequested.encode()[..],
+                0,
+            ),
+        ];
+        for (i, ((content_type, content, padding), expected)) in
+            messages.iter().zip(&expected).enumerate()
+        {
+            if i == 2 {
+                sender.update_traffic_secret();
+                receiver.update_traffic_secret();
+            }
+            let len = sender
+                .protect(*content_type, content, *padding, record)
+                .unwrap();
+            assert_eq!(
+                RecordProtection::<S>::record_len(content.len(), *padding),
+                len
+            );
+            assert_eq!(&h2b(expected)[..], &record[..len]);
+            let (actual_type, actual) = receiver.unprotect(&mut record[..len]).unwrap();
+            assert_eq!(*content_type, actual_type);
+            assert_eq!(*content, &actual[..]);
+        }
+        assert_eq!(1, sender.sequence());
+        assert_eq!(1, receiver.sequence());
+    }
+
+    #[test]
+    fn test_vectors() {
+        // Generated with Python's hmac and cryptography, following the specification
+        check::<Aes128GcmSha256>([
+            "17030300164d6d8b52c4219f73abf497337bed8c6d80d86f23fcba",
+            "170303001854fd2b1fad622993f7e6680752782d6e64b45b6ad3f8f29d",
+            "1703030016c2346f671824a22839f2a38f2637b838a284a4f0c9f8",
+        ]);
+        check::<Aes256GcmSha384>([
+            "1703030016205213507a1ab2a128abae5928a72bee1710dead4c7b",
+            "17030300189c116cf0c44e0d29e47bc32addc4e9b5df0808fd8c82804c",
+            "17030300163495a38686106432678f2edc64cf420d4c9b1b9ee8cc",
+        ]);
+    }
+
+    #[test]
+    fn test_key_update() {
+        assert_eq!(
+            Ok(KeyUpdate::UpdateNotRequested),
+            KeyUpdate::decode(&KeyUpdate::UpdateNotRequested.encode())
+        );
+        assert_eq!(
+            Err(Error::InvalidEncoding),
+            KeyUpdate::decode(&[24, 0, 0, 1, 2])
+        );
+        assert_eq!(
+            Err(Error::InvalidEncoding),
+            KeyUpdate::decode(&[24, 0, 0, 1])
+        );
+        assert_eq!(
+            Err(Error::InvalidEncoding),
+            KeyUpdate::decode(&[20, 0, 0, 1, 0])
+        );
+    }
+
+    #[test]
+    fn test_parse_header() {
+        assert_eq!(
+            Ok((ContentType::Handshake, 512)),
+            parse_header(&[22, 3, 1, 2, 0])
+        );
+        assert_eq!(
+            Ok((ContentType::ApplicationData, MAX_PAYLOAD_LEN)),
+            parse_header(&[23, 3, 3, 0x41, 0])
+        );
+        assert_eq!(
+            Err(Error::InvalidLength),
+            parse_header(&[23, 3, 3, 0x41, 1])
+        );
+        assert_eq!(Err(Error::InvalidEncoding), parse_header(&[24, 3, 3, 0, 1]));
+        assert_eq!(Err(Error::InvalidEncoding), parse_header(&[23, 3, 4, 0, 1]));
+    }
+
+    #[test]
+    fn test_invalid() {
+        let traffic_secret = &[0x42; 32];
+        assert!(RecordProtection::<Aes128GcmSha256>::new(&traffic_secret[1..]).is_err());
+        assert!(RecordProtection::<Aes256GcmSha384>::new(traffic_secret).is_err());
+        let mut sender = RecordProtection::<Aes128GcmSha256>::new(traffic_secret).unwrap();
+        let mut receiver = RecordProtection::<Aes128GcmSha256>::new(traffic_secret).unwrap();
+        let record = &mut vec![0; HEADER_LEN + MAX_PAYLOAD_LEN];
+        let content = &[0x24; MAX_CONTENT_LEN + 1];
+        assert_eq!(
+            Err(Error::InvalidLength),
+            sender.protect(ContentType::ApplicationData, content, 0, record)
+        );
+        assert_eq!(
+            Err(Error::InvalidLength),
+            sender.protect(
+                ContentType::ApplicationData,
+                &content[..10],
+                MAX_CONTENT_LEN - 9,
+                record
+            )
+        );
+        assert_eq!(
+            Err(Error::InvalidLength),
+            sender.protect(ContentType::ApplicationData, b"hello", usize::MAX, record)
+        );
+        assert_eq!(
+            Err(Error::InvalidLength),
+            sender.protect(
+                ContentType::ApplicationData,
+                b"hello",
+                0,
+                &mut record[..5 + 5 + 1 + 15]
+            )
+        );
+        let len = sender
+            .protect(
+                ContentType::ApplicationData,
+                &content[..MAX_CONTENT_LEN],
+                0,
+                record,
+            )
+            .unwrap();
+        assert_eq!(HEADER_LEN + MAX_CONTENT_LEN + 1 + 16, len);
+
+        // The record is tampered with, truncated or has the wrong header
+        record[HEADER_LEN] ^= 1;
+        assert_eq!(
+            Err(Error::TagMismatch),
+            receiver.unprotect(&mut record[..len]).map(|_| ())
+        );
+        record[HEADER_LEN] ^= 1;
+        assert_eq!(
+            Err(Error::InvalidLength),
+            receiver.unprotect(&mut record[..len - 1]).map(|_| ())
+        );
+        record[0] = ContentType::Handshake.to_u8();
+        assert_eq!(
+            Err(Error::InvalidEncoding),
+            receiver.unprotect(&mut record[..len]).map(|_| ())
+        );
+        record[0] = ContentType::ApplicationData.to_u8();
+        assert_eq!(0, receiver.sequence());
+        receiver.unprotect(&mut record[..len]).unwrap();
+
+        // Replaying a record or skipping one fails
+        assert_eq!(
+            Err(Error::TagMismatch),
+            receiver.unprotect(&mut record[..len]).map(|_| ())
+        );
+
+        // The inner plaintext has no content type
+        let sender = RecordProtection::<Aes128GcmSha256>::new(traffic_secret).unwrap();
+        let mut receiver = RecordProtection::<Aes128GcmSha256>::new(traffic_secret).unwrap();
+        let record = &mut [0; HEADER_LEN + 1 + 16];
+        record[..HEADER_LEN].copy_from_slice(&[23, 3, 3, 0, 1 + 16]);
+        let (header, payload) = record.split_at_mut(HEADER_LEN);
+        let nonce = sender.nonce().unwrap();
+        sender.aead.seal_in_place(&nonce, header, payload).unwrap();
+        assert_eq!(
+            Err(Error::InvalidEncoding),
+            receiver.unprotect(record).map(|_| ())
+        );
+    }
+}
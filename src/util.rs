@@ -1,15 +1,9 @@
 //! Module for utility functions.
+use crate::ct;
 
 /// Verifies equality between an array of length 16 and a slice of unknown length.
 pub fn verify_16(x: &[u8; 16], y: &[u8]) -> bool {
-    x.len() == y.len() && verify_inner(x, y) == 0
-}
-
-#[inline(never)]
-pub(crate) fn verify_inner(x: &impl AsRef<[u8]>, y: &[u8]) -> u8 {
-    let x = x.as_ref();
-    assert_eq!(x.len(), y.len());
-    x.iter().zip(y).fold(0, |acc, (x, y)| acc | (x ^ y))
+    ct::constant_time_eq(x, y)
 }
 
 #[cfg(test)]
@@ -30,14 +24,4 @@ mod tests {
         assert!(!verify_16(&[0x80; 16], &[0xff; 16]));
         assert!(!verify_16(&[0x80; 16], &[0xff; 4]));
     }
-
-    #[test]
-    fn test_verify_inner() {
-        assert_eq!(0, verify_inner(b"", b""));
-        assert_eq!(0, verify_inner(b"Hello!", b"Hello!"));
-        assert_eq!(0, verify_inner(&[0x80, 0xff], &[0x80, 0xff]));
-
-        assert_ne!(0, verify_inner(b"ok", b"ko"));
-        assert_ne!(0, verify_inner(&[0x80, 0xff], &[0xff, 0x80]));
-    }
 }
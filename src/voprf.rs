@@ -0,0 +1,1284 @@
+//! Module for oblivious pseudorandom functions (RFC 9497) in the OPRF and VOPRF modes, with the
+//! ristretto255-SHA512 and P256-SHA256 suites.
+//!
+//! An OPRF lets a client learn the output of a pseudorandom function keyed by a server's secret
+//! key on an input of its choice, without the server learning the input or the output. The client
+//! blinds its input, the server evaluates the blinded element with its key, and the client
+//! finalizes the evaluated element into the output. In the VOPRF mode, the server also proves
+//! that it used the key of a known public key, so the client can be sure every output comes from
+//! the same key. Servers can also evaluate inputs directly, such as to compare outputs with those
+//! of clients.
+//!
+//! # Examples
+//!
+//! ```
+//! use crypto_pure::rand::SystemRandom;
+//! use crypto_pure::voprf::{P256Sha256, VoprfClient, VoprfServer};
+//! let rng = &SystemRandom::new();
+//! let server = VoprfServer::<P256Sha256>::generate(rng);
+//! let client = VoprfClient::<P256Sha256>::new(server.public_key())?;
+//! let blind = client.blind(b"input", rng)?;
+//! let (evaluated, proof) = server.blind_evaluate(blind.blinded_element(), rng)?;
+//! let output = client.finalize(b"input", &blind, &evaluated, &proof)?;
+//! assert_eq!(&output[..], &server.evaluate(b"input")?[..]);
+//! # Ok::<(), crypto_pure::error::Error>(())
+//! ```
+use crate::ct;
+use crate::error::Error;
+use crate::hash_to_curve::expand_message_xmd_concat;
+use crate::p256::{gen_pk, PARAMS};
+use crate::rand::SecureRandom;
+use crate::ristretto255::{Point, Scalar};
+use crate::secret::SecretKey;
+use crate::sha2::{Digest, HashFunction, Sha256, Sha512};
+use crate::weierstrass::Curve;
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+use core::marker::PhantomData;
+use core::ops::Deref;
+
+/// The length of a serialized scalar, such as a secret key, for both suites.
+pub const SCALAR_LEN: usize = 32;
+
+/// The length of a proof c || s.
+pub const PROOF_LEN: usize = 2 * SCALAR_LEN;
+
+/// The longest serialized element, a compressed P-256 point.
+const MAX_ELEMENT_LEN: usize = 33;
+
+const MODE_OPRF: u8 = 0;
+const MODE_VOPRF: u8 = 1;
+
+/// An OPRF suite, a prime-order group with a hash function.
+pub trait Suite: private::Group {}
+
+/// The ristretto255-SHA512 suite.
+pub struct Ristretto255Sha512;
+
+/// The P256-SHA256 suite, with points serialized in compressed form.
+pub struct P256Sha256;
+
+impl Suite for Ristretto255Sha512 {}
+
+impl Suite for P256Sha256 {}
+
+mod private {
+    use super::SCALAR_LEN;
+    use crate::ct;
+    use crate::rand::SecureRandom;
+    use crate::secret::SecretKey;
+    use crate::sha2::HashFunction;
+
+    /// The operations of a suite's group, with scalars in their serialized form.
+    pub trait Group {
+        /// The identifier of the suite in the context string.
+        const ID: &'static [u8];
+        /// The length of a serialized element.
+        const ELEMENT_LEN: usize;
+        /// The hash function of the suite.
+        type Hash: HashFunction;
+        /// An element, possibly the identity.
+        type Element: Copy;
+
+        fn identity() -> Self::Element;
+        fn generator() -> Self::Element;
+        fn is_identity(element: &Self::Element) -> bool;
+        fn add(a: &Self::Element, b: &Self::Element) -> Self::Element;
+        fn mul(scalar: &[u8; SCALAR_LEN], element: &Self::Element) -> Self::Element;
+        /// Writes the serialization of an element, or zeros for the identity, which has none.
+        fn serialize(element: &Self::Element, output: &mut [u8]);
+        /// Deserializes an element, rejecting the identity.
+        fn deserialize(bytes: &[u8]) -> Option<Self::Element>;
+        fn hash_to_group(input: &[u8], dst: &[u8]) -> Self::Element;
+        /// Hashes the concatenation of several parts of a message to a scalar.
+        fn hash_to_scalar(message: &[&[u8]], dst: &[u8]) -> [u8; SCALAR_LEN];
+        /// Checks whether bytes are the serialization of a scalar.
+        fn is_canonical(bytes: &[u8]) -> bool;
+        /// Draws a uniformly random nonzero scalar.
+        fn random_scalar(rng: &dyn SecureRandom) -> SecretKey<[u8; SCALAR_LEN]>;
+        /// Outputs the inverse of a nonzero scalar.
+        fn invert(scalar: &[u8; SCALAR_LEN]) -> [u8; SCALAR_LEN];
+        /// Outputs r - c k.
+        fn mul_sub(
+            r: &[u8; SCALAR_LEN],
+            c: &[u8; SCALAR_LEN],
+            k: &[u8; SCALAR_LEN],
+        ) -> [u8; SCALAR_LEN];
+
+        /// Checks whether bytes are the serialization of a nonzero scalar.
+        fn is_valid_key(bytes: &[u8]) -> bool {
+            Self::is_canonical(bytes) && !ct::constant_time_eq(bytes, &[0; SCALAR_LEN])
+        }
+    }
+}
+
+use private::Group;
+
+impl Ristretto255Sha512 {
+    fn scalar(bytes: &[u8; SCALAR_LEN]) -> Scalar {
+        Scalar::from_bytes(bytes).expect("non-canonical scalar")
+    }
+}
+
+impl Group for Ristretto255Sha512 {
+    const ID: &'static [u8] = b"ristretto255-SHA512";
+    const ELEMENT_LEN: usize = 32;
+    type Hash = Sha512;
+    type Element = Point;
+
+    fn identity() -> Point {
+        Point::identity()
+    }
+
+    fn generator() -> Point {
+        Point::generator()
+    }
+
+    fn is_identity(element: &Point) -> bool {
+        element.is_identity().into()
+    }
+
+    fn add(a: &Point, b: &Point) -> Point {
+        *a + *b
+    }
+
+    fn mul(scalar: &[u8; SCALAR_LEN], element: &Point) -> Point {
+        *element * Self::scalar(scalar)
+    }
+
+    fn serialize(element: &Point, output: &mut [u8]) {
+        output.copy_from_slice(&element.to_bytes());
+    }
+
+    fn deserialize(bytes: &[u8]) -> Option<Point> {
+        Point::from_bytes(bytes).filter(|point| !Self::is_identity(point))
+    }
+
+    fn hash_to_group(input: &[u8], dst: &[u8]) -> Point {
+        Point::hash_to_group(input, dst).expect("domain separation tag too long")
+    }
+
+    fn hash_to_scalar(message: &[&[u8]], dst: &[u8]) -> [u8; SCALAR_LEN] {
+        let mut uniform_bytes = [0; 64];
+        expand_message_xmd_concat::<Sha512>(message, dst, &mut uniform_bytes);
+        Scalar::from_uniform_bytes(&uniform_bytes).to_bytes()
+    }
+
+    fn is_canonical(bytes: &[u8]) -> bool {
+        Scalar::from_bytes(bytes).is_some()
+    }
+
+    fn random_scalar(rng: &dyn SecureRandom) -> SecretKey<[u8; SCALAR_LEN]> {
+        let mut scalar = SecretKey::new([0; SCALAR_LEN]);
+        loop {
+            rng.fill(&mut scalar[..]);
+            // L is just above 2^252, so keeping the low 253 bits accepts about half the draws.
+            scalar[SCALAR_LEN - 1] &= 0x1f;
+            if Self::is_valid_key(&scalar[..]) {
+                return scalar;
+            }
+        }
+    }
+
+    fn invert(scalar: &[u8; SCALAR_LEN]) -> [u8; SCALAR_LEN] {
+        Self::scalar(scalar).invert().to_bytes()
+    }
+
+    fn mul_sub(
+        r: &[u8; SCALAR_LEN],
+        c: &[u8; SCALAR_LEN],
+        k: &[u8; SCALAR_LEN],
+    ) -> [u8; SCALAR_LEN] {
+        (Self::scalar(r) - Self::scalar(c) * Self::scalar(k)).to_bytes()
+    }
+}
+
+impl Group for P256Sha256 {
+    const ID: &'static [u8] = b"P256-SHA256";
+    const ELEMENT_LEN: usize = 33;
+    type Hash = Sha256;
+    /// The uncompressed encoding of a point, or `None` for the point at infinity.
+    type Element = Option<[u8; 65]>;
+
+    fn identity() -> Self::Element {
+        None
+    }
+
+    fn generator() -> Self::Element {
+        let mut one = [0; SCALAR_LEN];
+        one[SCALAR_LEN - 1] = 1;
+        gen_pk(&one).ok()
+    }
+
+    fn is_identity(element: &Self::Element) -> bool {
+        element.is_none()
+    }
+
+    fn add(a: &Self::Element, b: &Self::Element) -> Self::Element {
+        let (a, b) = match (a, b) {
+            (Some(a), Some(b)) => (a, b),
+            (None, _) => return *b,
+            (_, None) => return *a,
+        };
+        let mut sum = [0; 65];
+        if Curve::new(&PARAMS).add_points(a, b, &mut sum) {
+            Some(sum)
+        } else {
+            None
+        }
+    }
+
+    fn mul(scalar: &[u8; SCALAR_LEN], element: &Self::Element) -> Self::Element {
+        let mut product = [0; 65];
+        match element {
+            Some(point) if Curve::new(&PARAMS).mul(scalar, point, &mut product) => Some(product),
+            _ => None,
+        }
+    }
+
+    fn serialize(element: &Self::Element, output: &mut [u8]) {
+        match element {
+            Some(point) => Curve::new(&PARAMS).compress_point(point, output),
+            None => output.iter_mut().for_each(|byte| *byte = 0),
+        }
+    }
+
+    fn deserialize(bytes: &[u8]) -> Option<Self::Element> {
+        let mut point = [0; 65];
+        if Curve::new(&PARAMS).decompress_point(bytes, &mut point) {
+            Some(Some(point))
+        } else {
+            None
+        }
+    }
+
+    fn hash_to_group(input: &[u8], dst: &[u8]) -> Self::Element {
+        let mut point = [0; 65];
+        if Curve::new(&PARAMS).hash_to_curve::<Sha256>(input, dst, 10, &mut point) {
+            Some(point)
+        } else {
+            None
+        }
+    }
+
+    fn hash_to_scalar(message: &[&[u8]], dst: &[u8]) -> [u8; SCALAR_LEN] {
+        let mut uniform_bytes = [0; 48];
+        expand_message_xmd_concat::<Sha256>(message, dst, &mut uniform_bytes);
+        let mut scalar = [0; SCALAR_LEN];
+        Curve::new(&PARAMS).reduce_scalar(&uniform_bytes, &mut scalar);
+        scalar
+    }
+
+    fn is_canonical(bytes: &[u8]) -> bool {
+        if bytes.len() != SCALAR_LEN {
+            return false;
+        }
+        // A scalar is below n exactly when reducing it leaves it unchanged.
+        let reduced = &mut [0; SCALAR_LEN];
+        Curve::new(&PARAMS).reduce_scalar(bytes, reduced);
+        ct::constant_time_eq(reduced, bytes)
+    }
+
+    fn random_scalar(rng: &dyn SecureRandom) -> SecretKey<[u8; SCALAR_LEN]> {
+        let mut scalar = SecretKey::new([0; SCALAR_LEN]);
+        loop {
+            rng.fill(&mut scalar[..]);
+            if Self::is_valid_key(&scalar[..]) {
+                return scalar;
+            }
+        }
+    }
+
+    fn invert(scalar: &[u8; SCALAR_LEN]) -> [u8; SCALAR_LEN] {
+        let mut inverse = [0; SCALAR_LEN];
+        Curve::new(&PARAMS).invert_scalar(scalar, &mut inverse);
+        inverse
+    }
+
+    fn mul_sub(
+        r: &[u8; SCALAR_LEN],
+        c: &[u8; SCALAR_LEN],
+        k: &[u8; SCALAR_LEN],
+    ) -> [u8; SCALAR_LEN] {
+        let curve = Curve::new(&PARAMS);
+        let mut minus_c = *c;
+        curve.negate_scalar(&mut minus_c);
+        let mut s = [0; SCALAR_LEN];
+        curve.scalar_muladd(&minus_c, k, r, &mut s);
+        s
+    }
+}
+
+/// A serialized element, such as a blinded or evaluated element, that derefs into a slice of
+/// bytes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Element {
+    buffer: [u8; MAX_ELEMENT_LEN],
+    len: usize,
+}
+
+impl Element {
+    fn new<S: Suite>(element: &S::Element) -> Self {
+        let mut buffer = [0; MAX_ELEMENT_LEN];
+        S::serialize(element, &mut buffer[..S::ELEMENT_LEN]);
+        Self {
+            buffer,
+            len: S::ELEMENT_LEN,
+        }
+    }
+}
+
+impl Deref for Element {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.buffer[..self.len]
+    }
+}
+
+/// A client's blinded input, holding the secret blind needed to finalize its evaluation.
+pub struct Blind<S: Suite> {
+    blind: SecretKey<[u8; SCALAR_LEN]>,
+    blinded: S::Element,
+    blinded_element: Element,
+}
+
+impl<S: Suite> Blind<S> {
+    /// Outputs the blinded element to send to the server.
+    pub fn blinded_element(&self) -> &Element {
+        &self.blinded_element
+    }
+
+    /// Outputs the output for an element evaluated from this blind.
+    fn finalize(&self, input: &[u8], evaluated_element: &S::Element) -> Digest {
+        let inverse = SecretKey::new(S::invert(&self.blind));
+        output::<S>(input, &S::mul(&inverse, evaluated_element))
+    }
+}
+
+/// The client in the OPRF mode.
+pub struct OprfClient<S>(PhantomData<S>);
+
+impl<S: Suite> OprfClient<S> {
+    /// Creates a client.
+    pub fn new() -> Self {
+        Self(PhantomData)
+    }
+
+    /// Blinds an input with a blind drawn from `rng`.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::InvalidLength` if `input.len()` is 2^16 or more.
+    pub fn blind(&self, input: &[u8], rng: &dyn SecureRandom) -> Result<Blind<S>, Error> {
+        blind(MODE_OPRF, input, rng)
+    }
+
+    /// Outputs the OPRF output for an input, given its blind and the server's evaluated element.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::InvalidLength` if `input.len()` is 2^16 or more, or with
+    /// `Error::InvalidEncoding` if the evaluated element is not the serialization of a group
+    /// element other than the identity.
+    pub fn finalize(
+        &self,
+        input: &[u8],
+        blind: &Blind<S>,
+        evaluated_element: &[u8],
+    ) -> Result<Digest, Error> {
+        check_len(input)?;
+        let evaluated_element = S::deserialize(evaluated_element).ok_or(Error::InvalidEncoding)?;
+        Ok(blind.finalize(input, &evaluated_element))
+    }
+}
+
+impl<S: Suite> Default for OprfClient<S> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The client in the VOPRF mode, which checks the server's proofs against its public key.
+pub struct VoprfClient<S: Suite> {
+    public_key: S::Element,
+}
+
+impl<S: Suite> VoprfClient<S> {
+    /// Creates a client for a server's public key.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::InvalidKey` if the public key is not the serialization of a group
+    /// element other than the identity.
+    pub fn new(public_key: &[u8]) -> Result<Self, Error> {
+        let public_key = S::deserialize(public_key).ok_or(Error::InvalidKey)?;
+        Ok(Self { public_key })
+    }
+
+    /// Blinds an input with a blind drawn from `rng`.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::InvalidLength` if `input.len()` is 2^16 or more.
+    pub fn blind(&self, input: &[u8], rng: &dyn SecureRandom) -> Result<Blind<S>, Error> {
+        blind(MODE_VOPRF, input, rng)
+    }
+
+    /// Verifies the server's proof for an evaluated element and outputs the VOPRF output for an
+    /// input, given its blind.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::InvalidLength` if `input.len()` is 2^16 or more, with
+    /// `Error::InvalidEncoding` if the evaluated element is not the serialization of a group
+    /// element other than the identity, or with `Error::InvalidProof` if the proof fails
+    /// verification.
+    pub fn finalize(
+        &self,
+        input: &[u8],
+        blind: &Blind<S>,
+        evaluated_element: &[u8],
+        proof: &[u8],
+    ) -> Result<Digest, Error> {
+        check_len(input)?;
+        let evaluated_element = S::deserialize(evaluated_element).ok_or(Error::InvalidEncoding)?;
+        let (blinded, evaluated) = (&[blind.blinded], &[evaluated_element]);
+        verify_proof::<S>(&self.public_key, blinded, evaluated, proof)?;
+        Ok(blind.finalize(input, &evaluated_element))
+    }
+
+    /// Verifies the server's proof for a batch of evaluated elements and outputs the VOPRF
+    /// outputs for the inputs, given their blinds.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::InvalidLength` if the numbers of inputs, blinds and evaluated elements
+    /// differ or are more than 2^16, or if an input has 2^16 or more bytes, with
+    /// `Error::InvalidEncoding` if an evaluated element is not the serialization of
+    /// a group element other than the identity, or with `Error::InvalidProof` if the proof fails
+    /// verification.
+    #[cfg(feature = "alloc")]
+    pub fn finalize_batch(
+        &self,
+        inputs: &[&[u8]],
+        blinds: &[Blind<S>],
+        evaluated_elements: &[&[u8]],
+        proof: &[u8],
+    ) -> Result<Vec<Digest>, Error> {
+        if inputs.len() != blinds.len() || blinds.len() != evaluated_elements.len() {
+            return Err(Error::InvalidLength);
+        }
+        check_batch_len(inputs)?;
+        inputs.iter().try_for_each(|input| check_len(input))?;
+        let blinded: Vec<_> = blinds.iter().map(|blind| blind.blinded).collect();
+        let evaluated = evaluated_elements
+            .iter()
+            .map(|element| S::deserialize(element).ok_or(Error::InvalidEncoding))
+            .collect::<Result<Vec<_>, _>>()?;
+        verify_proof::<S>(&self.public_key, &blinded, &evaluated, proof)?;
+        let outputs = inputs.iter().zip(blinds).zip(&evaluated);
+        Ok(outputs
+            .map(|((input, blind), element)| blind.finalize(input, element))
+            .collect())
+    }
+}
+
+/// The server in the OPRF mode.
+pub struct OprfServer<S> {
+    secret_key: SecretKey<[u8; SCALAR_LEN]>,
+    suite: PhantomData<S>,
+}
+
+impl<S: Suite> OprfServer<S> {
+    /// Creates a server with a serialized secret key.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::InvalidKey` if the secret key is not the serialization of a nonzero
+    /// scalar.
+    pub fn new(secret_key: &[u8]) -> Result<Self, Error> {
+        Ok(Self::with_key(new_key::<S>(secret_key)?))
+    }
+
+    /// Creates a server with a random secret key drawn from `rng`.
+    pub fn generate(rng: &dyn SecureRandom) -> Self {
+        Self::with_key(S::random_scalar(rng))
+    }
+
+    /// Creates a server with a secret key derived from a 32-byte seed and an info string, as in
+    /// RFC 9497, Section 3.2.1.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::InvalidLength` if `seed.len()` is not equal to 32 or `info.len()` is
+    /// 2^16 or more.
+    pub fn derive(seed: &[u8], info: &[u8]) -> Result<Self, Error> {
+        Ok(Self::with_key(derive_key::<S>(MODE_OPRF, seed, info)?))
+    }
+
+    fn with_key(secret_key: SecretKey<[u8; SCALAR_LEN]>) -> Self {
+        Self {
+            secret_key,
+            suite: PhantomData,
+        }
+    }
+
+    /// Outputs the serialized secret key.
+    pub fn secret_key(&self) -> &[u8] {
+        &self.secret_key[..]
+    }
+
+    /// Evaluates a client's blinded element.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::InvalidEncoding` if the blinded element is not the serialization of a
+    /// group element other than the identity.
+    pub fn blind_evaluate(&self, blinded_element: &[u8]) -> Result<Element, Error> {
+        let blinded_element = S::deserialize(blinded_element).ok_or(Error::InvalidEncoding)?;
+        Ok(Element::new::<S>(&S::mul(
+            &self.secret_key,
+            &blinded_element,
+        )))
+    }
+
+    /// Evaluates an input directly, giving the output a client would get for it.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::InvalidLength` if `input.len()` is 2^16 or more.
+    pub fn evaluate(&self, input: &[u8]) -> Result<Digest, Error> {
+        evaluate::<S>(MODE_OPRF, &self.secret_key, input)
+    }
+}
+
+/// The server in the VOPRF mode, which proves that it evaluates with the key of its public key.
+pub struct VoprfServer<S: Suite> {
+    secret_key: SecretKey<[u8; SCALAR_LEN]>,
+    public_key: S::Element,
+    serialized_public_key: Element,
+}
+
+impl<S: Suite> VoprfServer<S> {
+    /// Creates a server with a serialized secret key.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::InvalidKey` if the secret key is not the serialization of a nonzero
+    /// scalar.
+    pub fn new(secret_key: &[u8]) -> Result<Self, Error> {
+        Ok(Self::with_key(new_key::<S>(secret_key)?))
+    }
+
+    /// Creates a server with a random secret key drawn from `rng`.
+    pub fn generate(rng: &dyn SecureRandom) -> Self {
+        Self::with_key(S::random_scalar(rng))
+    }
+
+    /// Creates a server with a secret key derived from a 32-byte seed and an info string, as in
+    /// RFC 9497, Section 3.2.1.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::InvalidLength` if `seed.len()` is not equal to 32 or `info.len()` is
+    /// 2^16 or more.
+    pub fn derive(seed: &[u8], info: &[u8]) -> Result<Self, Error> {
+        Ok(Self::with_key(derive_key::<S>(MODE_VOPRF, seed, info)?))
+    }
+
+    fn with_key(secret_key: SecretKey<[u8; SCALAR_LEN]>) -> Self {
+        let public_key = S::mul(&secret_key, &S::generator());
+        Self {
+            secret_key,
+            public_key,
+            serialized_public_key: Element::new::<S>(&public_key),
+        }
+    }
+
+    /// Outputs the serialized secret key.
+    pub fn secret_key(&self) -> &[u8] {
+        &self.secret_key[..]
+    }
+
+    /// Outputs the serialized public key that clients check proofs against.
+    pub fn public_key(&self) -> &Element {
+        &self.serialized_public_key
+    }
+
+    /// Evaluates a client's blinded element, outputting it with a proof whose randomness is
+    /// drawn from `rng`.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::InvalidEncoding` if the blinded element is not the serialization of a
+    /// group element other than the identity.
+    pub fn blind_evaluate(
+        &self,
+        blinded_element: &[u8],
+        rng: &dyn SecureRandom,
+    ) -> Result<(Element, [u8; PROOF_LEN]), Error> {
+        let blinded_element = S::deserialize(blinded_element).ok_or(Error::InvalidEncoding)?;
+        let evaluated_element = S::mul(&self.secret_key, &blinded_element);
+        let proof = self.prove(&[blinded_element], &[evaluated_element], rng);
+        Ok((Element::new::<S>(&evaluated_element), proof))
+    }
+
+    /// Evaluates a batch of blinded elements, outputting them with a single proof whose
+    /// randomness is drawn from `rng`.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::InvalidLength` if there are more than 2^16 blinded elements, or with
+    /// `Error::InvalidEncoding` if a blinded element is not the serialization of a group element
+    /// other than the identity.
+    #[cfg(feature = "alloc")]
+    pub fn blind_evaluate_batch(
+        &self,
+        blinded_elements: &[&[u8]],
+        rng: &dyn SecureRandom,
+    ) -> Result<(Vec<Element>, [u8; PROOF_LEN]), Error> {
+        check_batch_len(blinded_elements)?;
+        let blinded = blinded_elements
+            .iter()
+            .map(|element| S::deserialize(element).ok_or(Error::InvalidEncoding))
+            .collect::<Result<Vec<_>, _>>()?;
+        let evaluated: Vec<_> = blinded
+            .iter()
+            .map(|element| S::mul(&self.secret_key, element))
+            .collect();
+        let proof = self.prove(&blinded, &evaluated, rng);
+        let evaluated = evaluated.iter().map(Element::new::<S>).collect();
+        Ok((evaluated, proof))
+    }
+
+    /// Evaluates an input directly, giving the output a client would get for it.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::InvalidLength` if `input.len()` is 2^16 or more.
+    pub fn evaluate(&self, input: &[u8]) -> Result<Digest, Error> {
+        evaluate::<S>(MODE_VOPRF, &self.secret_key, input)
+    }
+
+    /// Proves that the evaluated elements are the blinded elements times the secret key, as in
+    /// RFC 9497, Section 2.2.1.
+    fn prove(
+        &self,
+        blinded: &[S::Element],
+        evaluated: &[S::Element],
+        rng: &dyn SecureRandom,
+    ) -> [u8; PROOF_LEN] {
+        let public_key = &self.public_key;
+        let (m, z) = composites::<S>(Some(&self.secret_key), public_key, blinded, evaluated);
+        let r = S::random_scalar(rng);
+        let t_2 = S::mul(&r, &S::generator());
+        let t_3 = S::mul(&r, &m);
+        let c = challenge::<S>(public_key, &m, &z, &t_2, &t_3);
+        let mut proof = [0; PROOF_LEN];
+        proof[..SCALAR_LEN].copy_from_slice(&c);
+        proof[SCALAR_LEN..].copy_from_slice(&S::mul_sub(&r, &c, &self.secret_key));
+        proof
+    }
+}
+
+fn new_key<S: Suite>(secret_key: &[u8]) -> Result<SecretKey<[u8; SCALAR_LEN]>, Error> {
+    if !S::is_valid_key(secret_key) {
+        return Err(Error::InvalidKey);
+    }
+    let mut key = SecretKey::new([0; SCALAR_LEN]);
+    key.copy_from_slice(secret_key);
+    Ok(key)
+}
+
+/// Writes "<prefix>OPRFV1-<mode>-<suite ID>", a prefix followed by the context string, to a
+/// buffer, outputting the written part.
+fn dst<'a, S: Suite>(prefix: &[u8], mode: u8, buffer: &'a mut [u8; 48]) -> &'a [u8] {
+    let parts: [&[u8]; 5] = [prefix, b"OPRFV1-", &[mode], b"-", S::ID];
+    let mut len = 0;
+    for part in &parts {
+        buffer[len..len + part.len()].copy_from_slice(part);
+        len += part.len();
+    }
+    &buffer[..len]
+}
+
+/// Checks that an input or info string is short enough for its two-byte length prefix.
+fn check_len(bytes: &[u8]) -> Result<(), Error> {
+    if bytes.len() > usize::from(u16::MAX) {
+        return Err(Error::InvalidLength);
+    }
+    Ok(())
+}
+
+/// Checks that every element of a batch can be numbered with two bytes.
+#[cfg(feature = "alloc")]
+fn check_batch_len<T>(batch: &[T]) -> Result<(), Error> {
+    if batch.len() > 1 << 16 {
+        return Err(Error::InvalidLength);
+    }
+    Ok(())
+}
+
+fn length_prefix(bytes: &[u8]) -> [u8; 2] {
+    u16::try_from(bytes.len())
+        .expect("length exceeds 2^16 - 1")
+        .to_be_bytes()
+}
+
+fn derive_key<S: Suite>(
+    mode: u8,
+    seed: &[u8],
+    info: &[u8],
+) -> Result<SecretKey<[u8; SCALAR_LEN]>, Error> {
+    if seed.len() != 32 {
+        return Err(Error::InvalidLength);
+    }
+    check_len(info)?;
+    let buffer = &mut [0; 48];
+    let dst = dst::<S>(b"DeriveKeyPair", mode, buffer);
+    for counter in 0..=255 {
+        let message: [&[u8]; 4] = [seed, &length_prefix(info), info, &[counter]];
+        let secret_key = SecretKey::new(S::hash_to_scalar(&message, dst));
+        if S::is_valid_key(&secret_key[..]) {
+            return Ok(secret_key);
+        }
+    }
+    panic!("derived only zero keys");
+}
+
+fn hash_to_group<S: Suite>(mode: u8, input: &[u8]) -> S::Element {
+    let buffer = &mut [0; 48];
+    let element = S::hash_to_group(input, dst::<S>(b"HashToGroup-", mode, buffer));
+    // Hashing to the identity would take finding a preimage of the hash.
+    assert!(!S::is_identity(&element), "hashed to the identity");
+    element
+}
+
+fn blind<S: Suite>(mode: u8, input: &[u8], rng: &dyn SecureRandom) -> Result<Blind<S>, Error> {
+    check_len(input)?;
+    let input_element = hash_to_group::<S>(mode, input);
+    let blind = S::random_scalar(rng);
+    let blinded = S::mul(&blind, &input_element);
+    Ok(Blind {
+        blind,
+        blinded,
+        blinded_element: Element::new::<S>(&blinded),
+    })
+}
+
+fn evaluate<S: Suite>(
+    mode: u8,
+    secret_key: &[u8; SCALAR_LEN],
+    input: &[u8],
+) -> Result<Digest, Error> {
+    check_len(input)?;
+    let input_element = hash_to_group::<S>(mode, input);
+    Ok(output::<S>(input, &S::mul(secret_key, &input_element)))
+}
+
+/// Hashes an input with its unblinded evaluated element to the output.
+fn output<S: Suite>(input: &[u8], element: &S::Element) -> Digest {
+    let element = Element::new::<S>(element);
+    let mut hash_function = S::Hash::default();
+    hash_function.update(&length_prefix(input));
+    hash_function.update(input);
+    hash_function.update(&length_prefix(&element));
+    hash_function.update(&element);
+    hash_function.update(b"Finalize");
+    Digest::new(hash_function)
+}
+
+/// Combines blinded elements C_i and evaluated elements D_i into M and Z, as in RFC 9497,
+/// Section 2.2.1. With the secret key k, Z is k M, and otherwise it is combined from the D_i.
+fn composites<S: Suite>(
+    secret_key: Option<&[u8; SCALAR_LEN]>,
+    public_key: &S::Element,
+    blinded: &[S::Element],
+    evaluated: &[S::Element],
+) -> (S::Element, S::Element) {
+    let public_key = Element::new::<S>(public_key);
+    let buffer = &mut [0; 48];
+    let seed_dst = dst::<S>(b"Seed-", MODE_VOPRF, buffer);
+    let mut hash_function = S::Hash::default();
+    hash_function.update(&length_prefix(&public_key));
+    hash_function.update(&public_key);
+    hash_function.update(&length_prefix(seed_dst));
+    hash_function.update(seed_dst);
+    let seed = &Digest::new(hash_function);
+
+    let buffer = &mut [0; 48];
+    let dst = dst::<S>(b"HashToScalar-", MODE_VOPRF, buffer);
+    let mut m = S::identity();
+    let mut z = S::identity();
+    for (i, (c, d)) in blinded.iter().zip(evaluated).enumerate() {
+        let i = u16::try_from(i).expect("batch exceeds 2^16 - 1 elements");
+        let (c_i, d_i) = (&Element::new::<S>(c), &Element::new::<S>(d));
+        let transcript: [&[u8]; 8] = [
+            &length_prefix(seed),
+            seed,
+            &i.to_be_bytes(),
+            &length_prefix(c_i),
+            c_i,
+            &length_prefix(d_i),
+            d_i,
+            b"Composite",
+        ];
+        let di = S::hash_to_scalar(&transcript, dst);
+        m = S::add(&S::mul(&di, c), &m);
+        if secret_key.is_none() {
+            z = S::add(&S::mul(&di, d), &z);
+        }
+    }
+    if let Some(secret_key) = secret_key {
+        z = S::mul(secret_key, &m);
+    }
+    (m, z)
+}
+
+/// Hashes the public key, the composite elements and the commitments of a proof to its
+/// challenge.
+fn challenge<S: Suite>(
+    public_key: &S::Element,
+    m: &S::Element,
+    z: &S::Element,
+    t_2: &S::Element,
+    t_3: &S::Element,
+) -> [u8; SCALAR_LEN] {
+    let elements = [public_key, m, z, t_2, t_3].map(Element::new::<S>);
+    let prefixes = elements.map(|element| length_prefix(&element));
+    let mut transcript = [&b"Challenge"[..]; 11];
+    for (i, (prefix, element)) in prefixes.iter().zip(&elements).enumerate() {
+        transcript[2 * i] = prefix;
+        transcript[2 * i + 1] = element;
+    }
+    let buffer = &mut [0; 48];
+    S::hash_to_scalar(&transcript, dst::<S>(b"HashToScalar-", MODE_VOPRF, buffer))
+}
+
+fn verify_proof<S: Suite>(
+    public_key: &S::Element,
+    blinded: &[S::Element],
+    evaluated: &[S::Element],
+    proof: &[u8],
+) -> Result<(), Error> {
+    if proof.len() != PROOF_LEN {
+        return Err(Error::InvalidProof);
+    }
+    let mut c = [0; SCALAR_LEN];
+    let mut s = [0; SCALAR_LEN];
+    c.copy_from_slice(&proof[..SCALAR_LEN]);
+    s.copy_from_slice(&proof[SCALAR_LEN..]);
+    if !S::is_canonical(&c) || !S::is_canonical(&s) {
+        return Err(Error::InvalidProof);
+    }
+    let (m, z) = composites::<S>(None, public_key, blinded, evaluated);
+    let t_2 = S::add(&S::mul(&s, &S::generator()), &S::mul(&c, public_key));
+    let t_3 = S::add(&S::mul(&s, &m), &S::mul(&c, &z));
+    let expected = challenge::<S>(public_key, &m, &z, &t_2, &t_3);
+    if ct::constant_time_eq(&expected, &c) {
+        Ok(())
+    } else {
+        Err(Error::InvalidProof)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rand::SystemRandom;
+    use crate::test_helpers::*;
+
+    // RFC 9497, Appendix A, where every key is derived from this seed and info
+    const SEED: &[u8] = &[0xa3; 32];
+    const INFO: &[u8] = b"test key";
+    const INPUT_1: &[u8] = &[0];
+    const INPUT_2: &[u8] = &[0x5a; 17];
+
+    fn fixed(bytes: &str) -> impl Fn(&mut [u8]) {
+        let bytes = h2b(bytes);
+        move |buffer: &mut [u8]| buffer.copy_from_slice(&bytes)
+    }
+
+    fn check_oprf<S: Suite>(
+        input: &[u8],
+        blind: &str,
+        blinded: &str,
+        evaluated: &str,
+        output: &str,
+    ) {
+        let server = OprfServer::<S>::derive(SEED, INFO).unwrap();
+        let client = OprfClient::<S>::new();
+        let blind = client.blind(input, &fixed(blind)).unwrap();
+        assert_eq!(h2b(blinded), &blind.blinded_element()[..]);
+        let evaluated_element = server.blind_evaluate(blind.blinded_element()).unwrap();
+        assert_eq!(h2b(evaluated), &evaluated_element[..]);
+        let finalized = client.finalize(input, &blind, &evaluated_element).unwrap();
+        assert_eq!(h2b(output), &finalized[..]);
+        assert_eq!(&finalized[..], &server.evaluate(input).unwrap()[..]);
+    }
+
+    fn check_voprf<S: Suite>(
+        input: &[u8],
+        blind: &str,
+        blinded: &str,
+        evaluated: &str,
+        proof_random: &str,
+        proof: &str,
+        output: &str,
+    ) {
+        let server = VoprfServer::<S>::derive(SEED, INFO).unwrap();
+        let client = VoprfClient::<S>::new(server.public_key()).unwrap();
+        let blind = client.blind(input, &fixed(blind)).unwrap();
+        assert_eq!(h2b(blinded), &blind.blinded_element()[..]);
+        let (evaluated_element, evaluated_proof) = server
+            .blind_evaluate(blind.blinded_element(), &fixed(proof_random))
+            .unwrap();
+        assert_eq!(h2b(evaluated), &evaluated_element[..]);
+        assert_eq!(h2b(proof), &evaluated_proof[..]);
+        let finalized = client
+            .finalize(input, &blind, &evaluated_element, &evaluated_proof)
+            .unwrap();
+        assert_eq!(h2b(output), &finalized[..]);
+        assert_eq!(&finalized[..], &server.evaluate(input).unwrap()[..]);
+
+        for &i in &[0, 31, 32, 63] {
+            let proof = &mut evaluated_proof.clone();
+            proof[i] ^= 1;
+            let finalized = client.finalize(input, &blind, &evaluated_element, proof);
+            assert_eq!(Err(Error::InvalidProof), finalized.map(|_| ()));
+        }
+        let finalized = client.finalize(input, &blind, &evaluated_element, &evaluated_proof[1..]);
+        assert_eq!(Err(Error::InvalidProof), finalized.map(|_| ()));
+        let finalized = client.finalize(b"other", &blind, &evaluated_element, &evaluated_proof);
+        assert_ne!(h2b(output), &finalized.unwrap()[..]);
+        let other = VoprfServer::<S>::derive(SEED, b"other key").unwrap();
+        let client = VoprfClient::<S>::new(other.public_key()).unwrap();
+        let finalized = client.finalize(input, &blind, &evaluated_element, &evaluated_proof);
+        assert_eq!(Err(Error::InvalidProof), finalized.map(|_| ()));
+    }
+
+    #[test]
+    fn test_ristretto255_oprf() {
+        let server = OprfServer::<Ristretto255Sha512>::derive(SEED, INFO).unwrap();
+        assert_eq!(
+            h2b("5ebcea5ee37023ccb9fc2d2019f9d7737be85591ae8652ffa9ef0f4d37063b0e"),
+            server.secret_key()
+        );
+        let blind = "64d37aed22a27f5191de1c1d69fadb899d8862b58eb4220029e036ec4c1f6706";
+        check_oprf::<Ristretto255Sha512>(
+            INPUT_1,
+            blind,
+            "609a0ae68c15a3cf6903766461307e5c8bb2f95e7e6550e1ffa2dc99e412803c",
+            "7ec6578ae5120958eb2db1745758ff379e77cb64fe77b0b2d8cc917ea0869c7e",
+            "527759c3d9366f277d8c6020418d96bb393ba2afb20ff90df23fb7708264e2f3\
+             ab9135e3bd69955851de4b1f9fe8a0973396719b7912ba9ee8aa7d0b5e24bcf6",
+        );
+        check_oprf::<Ristretto255Sha512>(
+            INPUT_2,
+            blind,
+            "da27ef466870f5f15296299850aa088629945a17d1f5b7f5ff043f76b3c06418",
+            "b4cbf5a4f1eeda5a63ce7b77c7d23f461db3fcab0dd28e4e17cecb5c90d02c25",
+            "f4a74c9c592497375e796aa837e907b1a045d34306a749db9f34221f7e750cb4\
+             f2a6413a6bf6fa5e19ba6348eb673934a722a7ede2e7621306d18951e7cf2c73",
+        );
+    }
+
+    #[test]
+    fn test_ristretto255_voprf() {
+        let server = VoprfServer::<Ristretto255Sha512>::derive(SEED, INFO).unwrap();
+        assert_eq!(
+            h2b("e6f73f344b79b379f1a0dd37e07ff62e38d9f71345ce62ae3a9bc60b04ccd909"),
+            server.secret_key()
+        );
+        assert_eq!(
+            h2b("c803e2cc6b05fc15064549b5920659ca4a77b2cca6f04f6b357009335476ad4e"),
+            &server.public_key()[..]
+        );
+        let blind = "64d37aed22a27f5191de1c1d69fadb899d8862b58eb4220029e036ec4c1f6706";
+        let proof_random = "222a5e897cf59db8145db8d16e597e8facb80ae7d4e26d9881aa6f61d645fc0e";
+        check_voprf::<Ristretto255Sha512>(
+            INPUT_1,
+            blind,
+            "863f330cc1a1259ed5a5998a23acfd37fb4351a793a5b3c090b642ddc439b945",
+            "aa8fa048764d5623868679402ff6108d2521884fa138cd7f9c7669a9a014267e",
+            proof_random,
+            "ddef93772692e535d1a53903db24367355cc2cc78de93b3be5a8ffcc6985dd06\
+             6d4346421d17bf5117a2a1ff0fcb2a759f58a539dfbe857a40bce4cf49ec600d",
+            "b58cfbe118e0cb94d79b5fd6a6dafb98764dff49c14e1770b566e42402da1a7d\
+             a4d8527693914139caee5bd03903af43a491351d23b430948dd50cde10d32b3c",
+        );
+        check_voprf::<Ristretto255Sha512>(
+            INPUT_2,
+            blind,
+            "cc0b2a350101881d8a4cba4c80241d74fb7dcbfde4a61fde2f91443c2bf9ef0c",
+            "60a59a57208d48aca71e9e850d22674b611f752bed48b36f7a91b372bd7ad468",
+            proof_random,
+            "401a0da6264f8cf45bb2f5264bc31e109155600babb3cd4e5af7d181a2c9dc0a\
+             67154fabf031fd936051dec80b0b6ae29c9503493dde7393b722eafdf5a50b02",
+            "8a9a2f3c7f085b65933594309041fc1898d42d0858e59f90814ae90571a6df60\
+             356f4610bf816f27afdd84f47719e480906d27ecd994985890e5f539e7ea74b6",
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn test_ristretto255_voprf_batch() {
+        let server = VoprfServer::<Ristretto255Sha512>::derive(SEED, INFO).unwrap();
+        let client = VoprfClient::<Ristretto255Sha512>::new(server.public_key()).unwrap();
+        let blinds = [
+            client
+                .blind(
+                    INPUT_1,
+                    &fixed("64d37aed22a27f5191de1c1d69fadb899d8862b58eb4220029e036ec4c1f6706"),
+                )
+                .unwrap(),
+            client
+                .blind(
+                    INPUT_2,
+                    &fixed("222a5e897cf59db8145db8d16e597e8facb80ae7d4e26d9881aa6f61d645fc0e"),
+                )
+                .unwrap(),
+        ];
+        let blinded: Vec<&[u8]> = blinds
+            .iter()
+            .map(|blind| &blind.blinded_element()[..])
+            .collect();
+        let proof_random =
+            fixed("419c4f4f5052c53c45f3da494d2b67b220d02118e0857cdbcf037f9ea84bbe0c");
+        let (evaluated, proof) = server
+            .blind_evaluate_batch(&blinded, &proof_random)
+            .unwrap();
+        assert_eq!(
+            h2b("aa8fa048764d5623868679402ff6108d2521884fa138cd7f9c7669a9a014267e"),
+            &evaluated[0][..]
+        );
+        assert_eq!(
+            h2b("cc5ac221950a49ceaa73c8db41b82c20372a4c8d63e5dded2db920b7eee36a2a"),
+            &evaluated[1][..]
+        );
+        let expected = "cc203910175d786927eeb44ea847328047892ddf8590e723c37205cb74600b0a\
+                        5ab5337c8eb4ceae0494c2cf89529dcf94572ed267473d567aeed6ab873dee08";
+        assert_eq!(h2b(expected), &proof[..]);
+        let evaluated: Vec<&[u8]> = evaluated.iter().map(|element| &element[..]).collect();
+        let inputs = [INPUT_1, INPUT_2];
+        let outputs = client
+            .finalize_batch(&inputs, &blinds, &evaluated, &proof)
+            .unwrap();
+        for (input, output) in inputs.iter().zip(&outputs) {
+            assert_eq!(&server.evaluate(input).unwrap()[..], &output[..]);
+        }
+
+        let swapped = [evaluated[1], evaluated[0]];
+        let finalized = client.finalize_batch(&inputs, &blinds, &swapped, &proof);
+        assert_eq!(Err(Error::InvalidProof), finalized.map(|_| ()));
+        let finalized = client.finalize_batch(&inputs, &blinds, &evaluated[..1], &proof);
+        assert_eq!(Err(Error::InvalidLength), finalized.map(|_| ()));
+    }
+
+    #[test]
+    fn test_p256_oprf() {
+        let server = OprfServer::<P256Sha256>::derive(SEED, INFO).unwrap();
+        assert_eq!(
+            h2b("159749d750713afe245d2d39ccfaae8381c53ce92d098a9375ee70739c7ac0bf"),
+            server.secret_key()
+        );
+        let blind = "3338fa65ec36e0290022b48eb562889d89dbfa691d1cde91517fa222ed7ad364";
+        check_oprf::<P256Sha256>(
+            INPUT_1,
+            blind,
+            "03723a1e5c09b8b9c18d1dcbca29e8007e95f14f4732d9346d490ffc195110368d",
+            "030de02ffec47a1fd53efcdd1c6faf5bdc270912b8749e783c7ca75bb412958832",
+            "a0b34de5fa4c5b6da07e72af73cc507cceeb48981b97b7285fc375345fe495dd",
+        );
+        check_oprf::<P256Sha256>(
+            INPUT_2,
+            blind,
+            "03cc1df781f1c2240a64d1c297b3f3d16262ef5d4cf102734882675c26231b0838",
+            "03a0395fe3828f2476ffcd1f4fe540e5a8489322d398be3c4e5a869db7fcb7c52c",
+            "c748ca6dd327f0ce85f4ae3a8cd6d4d5390bbb804c9e12dcf94f853fece3dcce",
+        );
+    }
+
+    #[test]
+    fn test_p256_voprf() {
+        let server = VoprfServer::<P256Sha256>::derive(SEED, INFO).unwrap();
+        assert_eq!(
+            h2b("ca5d94c8807817669a51b196c34c1b7f8442fde4334a7121ae4736364312fca6"),
+            server.secret_key()
+        );
+        assert_eq!(
+            h2b("03e17e70604bcabe198882c0a1f27a92441e774224ed9c702e51dd17038b102462"),
+            &server.public_key()[..]
+        );
+        let blind = "3338fa65ec36e0290022b48eb562889d89dbfa691d1cde91517fa222ed7ad364";
+        let proof_random = "f9db001266677f62c095021db018cd8cbb55941d4073698ce45c405d1348b7b1";
+        check_voprf::<P256Sha256>(
+            INPUT_1,
+            blind,
+            "02dd05901038bb31a6fae01828fd8d0e49e35a486b5c5d4b4994013648c01277da",
+            "0209f33cab60cf8fe69239b0afbcfcd261af4c1c5632624f2e9ba29b90ae83e4a2",
+            proof_random,
+            "e7c2b3c5c954c035949f1f74e6bce2ed539a3be267d1481e9ddb178533df4c26\
+             64f69d065c604a4fd953e100b856ad83804eb3845189babfa5a702090d6fc5fa",
+            "0412e8f78b02c415ab3a288e228978376f99927767ff37c5718d420010a645a1",
+        );
+        check_voprf::<P256Sha256>(
+            INPUT_2,
+            blind,
+            "03cd0f033e791c4d79dfa9c6ed750f2ac009ec46cd4195ca6fd3800d1e9b887dbd",
+            "030d2985865c693bf7af47ba4d3a3813176576383d19aff003ef7b0784a0d83cf1",
+            proof_random,
+            "2787d729c57e3d9512d3aa9e8708ad226bc48e0f1750b0767aaff73482c44b8d\
+             2873d74ec88aebd3504961acea16790a05c542d9fbff4fe269a77510db00abab",
+            "771e10dcd6bcd3664e23b8f2a710cfaaa8357747c4a8cbba03133967b5c24f18",
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn test_p256_voprf_batch() {
+        let server = VoprfServer::<P256Sha256>::derive(SEED, INFO).unwrap();
+        let client = VoprfClient::<P256Sha256>::new(server.public_key()).unwrap();
+        let blinds = [
+            client
+                .blind(
+                    INPUT_1,
+                    &fixed("3338fa65ec36e0290022b48eb562889d89dbfa691d1cde91517fa222ed7ad364"),
+                )
+                .unwrap(),
+            client
+                .blind(
+                    INPUT_2,
+                    &fixed("f9db001266677f62c095021db018cd8cbb55941d4073698ce45c405d1348b7b1"),
+                )
+                .unwrap(),
+        ];
+        let blinded: Vec<&[u8]> = blinds
+            .iter()
+            .map(|blind| &blind.blinded_element()[..])
+            .collect();
+        let proof_random =
+            fixed("350e8040f828bf6ceca27405420cdf3d63cb3aef005f40ba51943c8026877963");
+        let (evaluated, proof) = server
+            .blind_evaluate_batch(&blinded, &proof_random)
+            .unwrap();
+        assert_eq!(
+            h2b("02bb24f4d838414aef052a8f044a6771230ca69c0a5677540fff738dd31bb69771"),
+            &evaluated[1][..]
+        );
+        let expected = "bdcc351707d02a72ce49511c7db990566d29d6153ad6f8982fad2b435d6ce4d6\
+                        0da1e6b3fa740811bde34dd4fe0aa1b5fe6600d0440c9ddee95ea7fad7a60cf2";
+        assert_eq!(h2b(expected), &proof[..]);
+        let evaluated: Vec<&[u8]> = evaluated.iter().map(|element| &element[..]).collect();
+        let inputs = [INPUT_1, INPUT_2];
+        let outputs = client
+            .finalize_batch(&inputs, &blinds, &evaluated, &proof)
+            .unwrap();
+        assert_eq!(
+            h2b("771e10dcd6bcd3664e23b8f2a710cfaaa8357747c4a8cbba03133967b5c24f18"),
+            &outputs[1][..]
+        );
+    }
+
+    #[test]
+    fn test_invalid_elements() {
+        let server = OprfServer::<Ristretto255Sha512>::derive(SEED, INFO).unwrap();
+        // The identity, a non-canonical encoding and a non-square
+        for element in &[[0; 32], [0xff; 32], [0x42; 32]] {
+            assert_eq!(Err(Error::InvalidEncoding), server.blind_evaluate(element));
+            assert!(VoprfClient::<Ristretto255Sha512>::new(element).is_err());
+        }
+        assert_eq!(
+            Err(Error::InvalidEncoding),
+            server.blind_evaluate(&[0x42; 31])
+        );
+
+        let server = OprfServer::<P256Sha256>::derive(SEED, INFO).unwrap();
+        let client = OprfClient::<P256Sha256>::new();
+        let blind = client.blind(INPUT_1, &SystemRandom::new()).unwrap();
+        let uncompressed = &mut [4; 65];
+        Curve::new(&PARAMS).decompress_point(blind.blinded_element(), uncompressed);
+        assert!(server.blind_evaluate(uncompressed).is_err());
+        let element = &mut blind.blinded_element().to_vec();
+        // The x-coordinate p is not a field element.
+        element[1..].copy_from_slice(&h2b(
+            "ffffffff00000001000000000000000000000000ffffffffffffffffffffffff",
+        ));
+        assert_eq!(Err(Error::InvalidEncoding), server.blind_evaluate(element));
+        assert!(client.finalize(INPUT_1, &blind, element).is_err());
+        element[0] = 4;
+        assert_eq!(Err(Error::InvalidEncoding), server.blind_evaluate(element));
+    }
+
+    #[test]
+    fn test_invalid_keys() {
+        let l = "edd3f55c1a631258d69cf7a2def9de1400000000000000000000000000000010";
+        let n = "ffffffff00000000ffffffffffffffffbce6faada7179e84f3b9cac2fc632551";
+        for key in &[[0; 32].to_vec(), h2b(l), vec![1; 31]] {
+            assert!(OprfServer::<Ristretto255Sha512>::new(key).is_err());
+            assert!(VoprfServer::<Ristretto255Sha512>::new(key).is_err());
+        }
+        for key in &[[0; 32].to_vec(), h2b(n), vec![1; 31]] {
+            assert!(OprfServer::<P256Sha256>::new(key).is_err());
+            assert!(VoprfServer::<P256Sha256>::new(key).is_err());
+        }
+        let server = VoprfServer::<P256Sha256>::derive(SEED, INFO).unwrap();
+        let restored = VoprfServer::<P256Sha256>::new(server.secret_key()).unwrap();
+        assert_eq!(server.public_key(), restored.public_key());
+    }
+
+    #[test]
+    fn test_invalid_lengths() {
+        let error = Some(Error::InvalidLength);
+        let long = &vec![0; 1 << 16];
+        assert_eq!(
+            error,
+            OprfServer::<P256Sha256>::derive(&SEED[1..], INFO).err()
+        );
+        assert_eq!(error, VoprfServer::<P256Sha256>::derive(SEED, long).err());
+        assert!(VoprfServer::<P256Sha256>::derive(SEED, &long[1..]).is_ok());
+
+        let rng = &SystemRandom::new();
+        let server = OprfServer::<P256Sha256>::derive(SEED, INFO).unwrap();
+        let client = OprfClient::<P256Sha256>::new();
+        assert_eq!(error, client.blind(long, rng).err());
+        assert_eq!(error, server.evaluate(long).err());
+        let blind = client.blind(INPUT_1, rng).unwrap();
+        let evaluated = server.blind_evaluate(blind.blinded_element()).unwrap();
+        assert_eq!(error, client.finalize(long, &blind, &evaluated).err());
+        let server = VoprfServer::<P256Sha256>::derive(SEED, INFO).unwrap();
+        let client = VoprfClient::<P256Sha256>::new(server.public_key()).unwrap();
+        assert_eq!(error, client.blind(long, rng).err());
+        assert_eq!(error, server.evaluate(long).err());
+    }
+
+    fn check_random<S: Suite>() {
+        let rng = &SystemRandom::new();
+        let server = OprfServer::<S>::generate(rng);
+        let client = OprfClient::<S>::new();
+        let blind = client.blind(b"input", rng).unwrap();
+        let evaluated = server.blind_evaluate(blind.blinded_element()).unwrap();
+        let output = client.finalize(b"input", &blind, &evaluated).unwrap();
+        assert_eq!(&server.evaluate(b"input").unwrap()[..], &output[..]);
+
+        let server = VoprfServer::<S>::generate(rng);
+        let client = VoprfClient::<S>::new(server.public_key()).unwrap();
+        let blind = client.blind(b"input", rng).unwrap();
+        let (evaluated, proof) = server.blind_evaluate(blind.blinded_element(), rng).unwrap();
+        let output = client
+            .finalize(b"input", &blind, &evaluated, &proof)
+            .unwrap();
+        assert_eq!(&server.evaluate(b"input").unwrap()[..], &output[..]);
+        // The modes use different context strings.
+        let server = OprfServer::<S>::new(server.secret_key()).unwrap();
+        assert_ne!(&server.evaluate(b"input").unwrap()[..], &output[..]);
+    }
+
+    #[test]
+    fn test_random() {
+        check_random::<Ristretto255Sha512>();
+        check_random::<P256Sha256>();
+    }
+}
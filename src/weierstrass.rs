@@ -0,0 +1,711 @@
+//! Module for ECDH and ECDSA on short Weierstrass curves y^2 = x^3 + a x + b over prime fields,
+//! shared by the specific curves.
+//!
+//! Field and scalar arithmetic use the `field` module, and points use the complete projective
+//! formulas of Renes, Costello and Batina ("Complete addition formulas for prime order elliptic
+//! curves"), so that scalar multiplication needs no special cases and runs in constant time.
+use crate::asn1;
+#[cfg(feature = "alloc")]
+use crate::asn1::Writer;
+use crate::error::Error;
+use crate::field::{from_be_bytes, is_zero, select, sub_with_borrow, to_be_bytes, Modulus};
+use crate::hash_to_curve::expand_message_xmd_concat;
+use crate::hmac_drbg::HmacDrbg;
+use crate::rand::SecureRandom;
+use crate::sha2::HashFunction;
+#[cfg(feature = "alloc")]
+use alloc::{vec, vec::Vec};
+
+/// The parameters of a curve of prime order `n`, with integers in little-endian limbs.
+pub(crate) struct Params<const N: usize> {
+    pub(crate) p: [u64; N],
+    pub(crate) n: [u64; N],
+    pub(crate) a: [u64; N],
+    pub(crate) b: [u64; N],
+    pub(crate) gx: [u64; N],
+    pub(crate) gy: [u64; N],
+}
+
+/// A point in projective coordinates (X : Y : Z), representing (X / Z, Y / Z), or the point at
+/// infinity if Z is zero. The coordinates are in Montgomery form.
+#[derive(Clone, Copy)]
+struct Point<const N: usize> {
+    x: [u64; N],
+    y: [u64; N],
+    z: [u64; N],
+}
+
+/// A curve with the constants needed for its arithmetic.
+pub(crate) struct Curve<const N: usize> {
+    field: Modulus<N>,
+    scalars: Modulus<N>,
+    a: [u64; N],
+    b: [u64; N],
+    b3: [u64; N],
+    generator: Point<N>,
+}
+
+impl<const N: usize> Curve<N> {
+    /// The length of an encoded field element or scalar.
+    const LEN: usize = 8 * N;
+
+    pub(crate) fn new(params: &Params<N>) -> Self {
+        let field = Modulus::new(params.p);
+        let b = field.to_montgomery(&params.b);
+        let generator = Point {
+            x: field.to_montgomery(&params.gx),
+            y: field.to_montgomery(&params.gy),
+            z: field.one(),
+        };
+        Self {
+            scalars: Modulus::new(params.n),
+            a: field.to_montgomery(&params.a),
+            b,
+            b3: field.add(&field.add(&b, &b), &b),
+            generator,
+            field,
+        }
+    }
+
+    /// Writes the uncompressed encoding of the public key for a big-endian secret key, failing if
+    /// the secret key is not between 1 and n - 1.
+    pub(crate) fn gen_pk(&self, secret_key: &[u8], public_key: &mut [u8]) -> bool {
+        let d = match self.secret_scalar(secret_key) {
+            Some(d) => d,
+            None => return false,
+        };
+        let (x, y) = self.to_affine(&self.scalarmult(&self.generator, &d));
+        public_key[0] = 4;
+        to_be_bytes(&x, &mut public_key[1..=Self::LEN]);
+        to_be_bytes(&y, &mut public_key[1 + Self::LEN..]);
+        true
+    }
+
+    /// Checks that a public key is an uncompressed encoding of a point on the curve.
+    #[cfg(feature = "alloc")]
+    pub(crate) fn is_valid_public_key(&self, public_key: &[u8]) -> bool {
+        self.decode_point(public_key).is_some()
+    }
+
+    /// Writes the x-coordinate of the shared point, failing if the secret key is invalid or the
+    /// public key is not an uncompressed encoding of a point on the curve.
+    pub(crate) fn ecdh(&self, secret_key: &[u8], public_key: &[u8], shared: &mut [u8]) -> bool {
+        let d = match self.secret_scalar(secret_key) {
+            Some(d) => d,
+            None => return false,
+        };
+        let q = match self.decode_point(public_key) {
+            Some(q) => q,
+            None => return false,
+        };
+        // The curve has prime order, so the product of a valid point is never infinity.
+        let (x, _) = self.to_affine(&self.scalarmult(&q, &d));
+        to_be_bytes(&x, shared);
+        true
+    }
+
+    /// Writes the uncompressed encoding of a G + b Q for big-endian scalars a and b below n and
+    /// an encoded point Q, failing if a scalar or Q is invalid or the result is infinity.
+    pub(crate) fn mul_base_add(&self, a: &[u8], b: &[u8], q: &[u8], output: &mut [u8]) -> bool {
+        let (a, b, q) = match (self.scalar(a), self.scalar(b), self.decode_point(q)) {
+            (Some(a), Some(b), Some(q)) => (a, b, q),
+            _ => return false,
+        };
+        let point = self.add(
+            &self.scalarmult(&self.generator, &a),
+            &self.scalarmult(&q, &b),
+        );
+        self.encode_point(&point, output)
+    }
+
+    /// Writes the uncompressed encoding of a (P - b Q) for big-endian scalars a and b below n and
+    /// encoded points P and Q, failing if a scalar or point is invalid or the result is infinity.
+    pub(crate) fn mul_difference(
+        &self,
+        a: &[u8],
+        p: &[u8],
+        b: &[u8],
+        q: &[u8],
+        output: &mut [u8],
+    ) -> bool {
+        let scalars = (self.scalar(a), self.scalar(b));
+        let points = (self.decode_point(p), self.decode_point(q));
+        let (a, b, p, q) = match (scalars, points) {
+            ((Some(a), Some(b)), (Some(p), Some(q))) => (a, b, p, q),
+            _ => return false,
+        };
+        let mut bq = self.scalarmult(&q, &b);
+        bq.y = self.field.sub(&[0; N], &bq.y);
+        let point = self.scalarmult(&self.add(&p, &bq), &a);
+        self.encode_point(&point, output)
+    }
+
+    /// Reduces a big-endian integer of up to twice the length of n modulo n.
+    pub(crate) fn reduce_scalar(&self, bytes: &[u8], output: &mut [u8]) {
+        to_be_bytes(&reduce_wide(&self.scalars, bytes), output);
+    }
+
+    /// Writes the ECDSA signature r || s of a message digest, drawing nonces from `rng`, failing
+    /// if the secret key is not between 1 and n - 1.
+    pub(crate) fn sign(
+        &self,
+        digest: &[u8],
+        secret_key: &[u8],
+        rng: &dyn SecureRandom,
+        signature: &mut [u8],
+    ) -> bool {
+        self.sign_with_nonces(digest, secret_key, |buffer| rng.fill(buffer), signature)
+    }
+
+    /// Writes the ECDSA signature r || s of a message digest with nonces from HMAC_DRBG with `H`
+    /// as in RFC 6979, which is deterministic if `extra` is empty. Otherwise `extra` is the
+    /// additional data k' of RFC 6979, Section 3.6, such as fresh randomness for hedged signing.
+    /// Fails if the secret key is not between 1 and n - 1.
+    pub(crate) fn sign_deterministic<H: HashFunction>(
+        &self,
+        digest: &[u8],
+        secret_key: &[u8],
+        extra: &[u8],
+        signature: &mut [u8],
+    ) -> bool {
+        if self.secret_scalar(secret_key).is_none() {
+            return false;
+        }
+        let h = &mut [0; 72][..Self::LEN];
+        to_be_bytes(&self.digest_scalar(digest), h);
+        // Secret keys are at least 32 bytes for the supported curves.
+        let mut drbg = HmacDrbg::<H>::new(secret_key, h, extra).expect("secret key too short");
+        // The order's bit length is a multiple of 8 for the supported curves, so bits2int of the
+        // DRBG output is the output itself.
+        let nonces = |buffer: &mut [u8]| drbg.generate(buffer, b"").expect("reseed required");
+        self.sign_with_nonces(digest, secret_key, nonces, signature)
+    }
+
+    fn sign_with_nonces<F: FnMut(&mut [u8])>(
+        &self,
+        digest: &[u8],
+        secret_key: &[u8],
+        mut nonces: F,
+        signature: &mut [u8],
+    ) -> bool {
+        let d = match self.secret_scalar(secret_key) {
+            Some(d) => d,
+            None => return false,
+        };
+        let scalars = &self.scalars;
+        let e = scalars.to_montgomery(&self.digest_scalar(digest));
+        let d = scalars.to_montgomery(&d);
+        let buffer = &mut [0; 72][..Self::LEN];
+        loop {
+            nonces(buffer);
+            let k = match self.secret_scalar(buffer) {
+                Some(k) => k,
+                None => continue,
+            };
+            let (x, _) = self.to_affine(&self.scalarmult(&self.generator, &k));
+            let r = scalars.reduce_once(&x);
+            let k_inverse = scalars.invert(&scalars.to_montgomery(&k));
+            let rd = scalars.mul(&scalars.to_montgomery(&r), &d);
+            let s = scalars.to_normal(&scalars.mul(&k_inverse, &scalars.add(&e, &rd)));
+            if is_zero(&r) || is_zero(&s) {
+                continue;
+            }
+            to_be_bytes(&r, &mut signature[..Self::LEN]);
+            to_be_bytes(&s, &mut signature[Self::LEN..]);
+            return true;
+        }
+    }
+
+    /// Verifies an ECDSA signature r || s of a message digest.
+    pub(crate) fn verify(&self, digest: &[u8], signature: &[u8], public_key: &[u8]) -> bool {
+        if signature.len() != 2 * Self::LEN {
+            return false;
+        }
+        let scalars = &self.scalars;
+        let r = from_be_bytes(&signature[..Self::LEN]);
+        let s = from_be_bytes(&signature[Self::LEN..]);
+        if !scalars.contains(&r) || !scalars.contains(&s) || is_zero(&r) || is_zero(&s) {
+            return false;
+        }
+        let q = match self.decode_point(public_key) {
+            Some(q) => q,
+            None => return false,
+        };
+        let e = scalars.to_montgomery(&self.digest_scalar(digest));
+        let w = scalars.invert(&scalars.to_montgomery(&s));
+        let u_1 = scalars.to_normal(&scalars.mul(&e, &w));
+        let u_2 = scalars.to_normal(&scalars.mul(&scalars.to_montgomery(&r), &w));
+        let point = self.add(
+            &self.scalarmult(&self.generator, &u_1),
+            &self.scalarmult(&q, &u_2),
+        );
+        if is_zero(&point.z) {
+            return false;
+        }
+        let (x, _) = self.to_affine(&point);
+        let x = scalars.reduce_once(&x);
+        x == r
+    }
+
+    /// Checks whether the s half of a signature r || s is greater than (n - 1) / 2, in which case
+    /// r || (n - s) is an equally valid signature.
+    pub(crate) fn is_high_s(&self, signature: &[u8]) -> bool {
+        let s = from_be_bytes(&signature[Self::LEN..]);
+        let n = self.scalars.modulus();
+        let mut half = [0; N];
+        for (i, limb) in half.iter_mut().enumerate() {
+            let high = n.get(i + 1).map_or(0, |next| next << 63);
+            *limb = (n[i] >> 1) | high;
+        }
+        sub_with_borrow(&half, &s).1 == 1
+    }
+
+    /// Replaces s with n - s in a signature r || s if s is high, outputting whether it did.
+    pub(crate) fn normalize_s(&self, signature: &mut [u8]) -> bool {
+        if !self.is_high_s(signature) {
+            return false;
+        }
+        let s = from_be_bytes(&signature[Self::LEN..]);
+        let (s, _) = sub_with_borrow(self.scalars.modulus(), &s);
+        to_be_bytes(&s, &mut signature[Self::LEN..]);
+        true
+    }
+
+    /// Writes the uncompressed encoding of the point with a big-endian x-coordinate and an even
+    /// y-coordinate, as in BIP 340, failing if there is no such point. Square roots are computed
+    /// as powers, so p must be 3 mod 4.
+    pub(crate) fn lift_x(&self, x: &[u8], output: &mut [u8]) -> bool {
+        x.len() == Self::LEN && self.decompress(x, 0, output)
+    }
+
+    /// Writes the uncompressed encoding of a compressed point 0x02 || x or 0x03 || x, failing if
+    /// it is not a point on the curve. As with `lift_x`, p must be 3 mod 4.
+    pub(crate) fn decompress_point(&self, bytes: &[u8], output: &mut [u8]) -> bool {
+        if bytes.len() != 1 + Self::LEN || (bytes[0] != 2 && bytes[0] != 3) {
+            return false;
+        }
+        self.decompress(&bytes[1..], bytes[0] & 1, output)
+    }
+
+    /// Writes the compressed encoding of an uncompressed point.
+    pub(crate) fn compress_point(&self, point: &[u8], output: &mut [u8]) {
+        output[0] = 2 | (point[2 * Self::LEN] & 1);
+        output[1..].copy_from_slice(&point[1..=Self::LEN]);
+    }
+
+    /// Writes the uncompressed encoding of a P for a big-endian scalar a below n and an encoded
+    /// point P, failing if a or P is invalid or the result is infinity.
+    pub(crate) fn mul(&self, a: &[u8], p: &[u8], output: &mut [u8]) -> bool {
+        match (self.scalar(a), self.decode_point(p)) {
+            (Some(a), Some(p)) => self.encode_point(&self.scalarmult(&p, &a), output),
+            _ => false,
+        }
+    }
+
+    /// Writes the uncompressed encoding of P + Q for encoded points P and Q, failing if a point
+    /// is invalid or the sum is infinity.
+    pub(crate) fn add_points(&self, p: &[u8], q: &[u8], output: &mut [u8]) -> bool {
+        match (self.decode_point(p), self.decode_point(q)) {
+            (Some(p), Some(q)) => self.encode_point(&self.add(&p, &q), output),
+            _ => false,
+        }
+    }
+
+    /// Writes the inverse mod n of a big-endian scalar below n, where the inverse of zero is zero.
+    pub(crate) fn invert_scalar(&self, a: &[u8], output: &mut [u8]) {
+        let scalars = &self.scalars;
+        let inverse = scalars.invert(&scalars.to_montgomery(&from_be_bytes(a)));
+        to_be_bytes(&scalars.to_normal(&inverse), output);
+    }
+
+    /// Writes the uncompressed encoding of a point hashed from a message with `H` and the
+    /// simplified SWU map for the constant Z = -`minus_z`, as in the hash_to_curve random oracle
+    /// encoding of RFC 9380, failing in the negligibly likely case that the point is infinity.
+    /// As with `lift_x`, p must be 3 mod 4, and the curve must have prime order and a and b both
+    /// nonzero. The caller must check that `dst.len()` is at most 255.
+    pub(crate) fn hash_to_curve<H: HashFunction>(
+        &self,
+        message: &[u8],
+        dst: &[u8],
+        minus_z: u64,
+        output: &mut [u8],
+    ) -> bool {
+        // Each field element is reduced from k / 8 more bytes than its length for a security
+        // level of k bits, which is half the length for these curves.
+        let len = Self::LEN + Self::LEN / 2;
+        let uniform_bytes = &mut [0; 144][..2 * len];
+        expand_message_xmd_concat::<H>(&[message], dst, uniform_bytes);
+        let field = &self.field;
+        let mut z = [0; N];
+        z[0] = minus_z;
+        let z = field.sub(&[0; N], &field.to_montgomery(&z));
+        let u_0 = field.to_montgomery(&reduce_wide(field, &uniform_bytes[..len]));
+        let u_1 = field.to_montgomery(&reduce_wide(field, &uniform_bytes[len..]));
+        let point = self.add(&self.map_to_curve(&u_0, &z), &self.map_to_curve(&u_1, &z));
+        self.encode_point(&point, output)
+    }
+
+    /// Writes a b + c mod n for big-endian scalars a, b and c below n.
+    pub(crate) fn scalar_muladd(&self, a: &[u8], b: &[u8], c: &[u8], output: &mut [u8]) {
+        let scalars = &self.scalars;
+        // The Montgomery product of a R and b is a b.
+        let ab = scalars.mul(&scalars.to_montgomery(&from_be_bytes(a)), &from_be_bytes(b));
+        to_be_bytes(&scalars.add(&ab, &from_be_bytes(c)), output);
+    }
+
+    /// Replaces a big-endian scalar below n with its negation mod n.
+    pub(crate) fn negate_scalar(&self, scalar: &mut [u8]) {
+        let negation = self.scalars.sub(&[0; N], &from_be_bytes(scalar));
+        to_be_bytes(&negation, scalar);
+    }
+
+    /// Checks whether a G + sum(b_i Q_i) is the point at infinity for big-endian scalars a and
+    /// b_i below n and encoded points Q_i, failing if a scalar or point is invalid. This
+    /// interleaves signed sliding windows over every scalar, so it runs in variable time and is
+    /// only for public values, as in batch verification.
+    #[cfg(feature = "alloc")]
+    pub(crate) fn is_infinity_vartime(&self, a: &[u8], terms: &[(&[u8], &[u8])]) -> bool {
+        let a = match self.scalar(a) {
+            Some(a) => a,
+            None => return false,
+        };
+        let mut digits = Vec::with_capacity(terms.len() + 1);
+        let mut tables = Vec::with_capacity(terms.len() + 1);
+        digits.push(signed_digits(&a));
+        tables.push(self.odd_multiples(&self.generator));
+        for (b, q) in terms {
+            match (self.scalar(b), self.decode_point(q)) {
+                (Some(b), Some(q)) => {
+                    digits.push(signed_digits(&b));
+                    tables.push(self.odd_multiples(&q));
+                }
+                _ => return false,
+            }
+        }
+
+        let mut result = Point {
+            x: [0; N],
+            y: self.field.one(),
+            z: [0; N],
+        };
+        for i in (0..=64 * N).rev() {
+            result = self.add(&result, &result);
+            for (digits, table) in digits.iter().zip(&tables) {
+                let digit = digits[i];
+                if digit == 0 {
+                    continue;
+                }
+                let mut point = table[usize::from(digit.unsigned_abs() / 2)];
+                if digit < 0 {
+                    point.y = self.field.sub(&[0; N], &point.y);
+                }
+                result = self.add(&result, &point);
+            }
+        }
+        is_zero(&result.z)
+    }
+
+    fn secret_scalar(&self, bytes: &[u8]) -> Option<[u64; N]> {
+        if bytes.len() != Self::LEN {
+            return None;
+        }
+        let d = from_be_bytes(bytes);
+        if self.scalars.contains(&d) && !is_zero(&d) {
+            Some(d)
+        } else {
+            None
+        }
+    }
+
+    fn scalar(&self, bytes: &[u8]) -> Option<[u64; N]> {
+        if bytes.len() != Self::LEN {
+            return None;
+        }
+        let scalar = from_be_bytes(bytes);
+        if self.scalars.contains(&scalar) {
+            Some(scalar)
+        } else {
+            None
+        }
+    }
+
+    /// Converts the leftmost bits of a digest to an integer modulo n, assuming n has 64 N bits.
+    fn digest_scalar(&self, digest: &[u8]) -> [u64; N] {
+        let mut bytes = [0; 72];
+        let len = digest.len().min(Self::LEN);
+        bytes[Self::LEN - len..Self::LEN].copy_from_slice(&digest[..len]);
+        self.scalars
+            .reduce_once(&from_be_bytes(&bytes[..Self::LEN]))
+    }
+
+    /// Decodes an uncompressed point 0x04 || x || y, checking that it is on the curve.
+    fn decode_point(&self, bytes: &[u8]) -> Option<Point<N>> {
+        if bytes.len() != 1 + 2 * Self::LEN || bytes[0] != 4 {
+            return None;
+        }
+        let x = from_be_bytes(&bytes[1..=Self::LEN]);
+        let y = from_be_bytes(&bytes[1 + Self::LEN..]);
+        let field = &self.field;
+        if !field.contains(&x) || !field.contains(&y) {
+            return None;
+        }
+        let x = field.to_montgomery(&x);
+        let y = field.to_montgomery(&y);
+        if field.square(&y) != self.curve_rhs(&x) {
+            return None;
+        }
+        Some(Point {
+            x,
+            y,
+            z: field.one(),
+        })
+    }
+
+    /// Writes the uncompressed encoding of the point with a big-endian x-coordinate whose
+    /// y-coordinate has the parity `odd`, failing if there is no such point.
+    fn decompress(&self, x: &[u8], odd: u8, output: &mut [u8]) -> bool {
+        let x = from_be_bytes(x);
+        let field = &self.field;
+        if !field.contains(&x) {
+            return false;
+        }
+        let (y, is_square) = self.sqrt(&self.curve_rhs(&field.to_montgomery(&x)));
+        if !is_square {
+            return false;
+        }
+        let mut y = field.to_normal(&y);
+        if y[0] & 1 != u64::from(odd) {
+            y = sub_with_borrow(field.modulus(), &y).0;
+        }
+        output[0] = 4;
+        to_be_bytes(&x, &mut output[1..=Self::LEN]);
+        to_be_bytes(&y, &mut output[1 + Self::LEN..]);
+        true
+    }
+
+    /// Outputs a square root of a Montgomery-form c for p = 3 mod 4, and whether it is one, which
+    /// it is exactly when c is a square.
+    fn sqrt(&self, c: &[u64; N]) -> ([u64; N], bool) {
+        // c^((p + 1) / 4) is a square root of c if there is one, and (p + 1) / 4 is
+        // (p - 3) / 4 + 1.
+        let field = &self.field;
+        let mut three = [0; N];
+        three[0] = 3;
+        let (p_minus_three, _) = sub_with_borrow(field.modulus(), &three);
+        let mut exponent = [0; N];
+        for (i, limb) in exponent.iter_mut().enumerate() {
+            let high = p_minus_three.get(i + 1).map_or(0, |next| next << 62);
+            *limb = (p_minus_three[i] >> 2) | high;
+        }
+        let y = field.mul(&field.pow(c, &exponent), c);
+        (y, field.square(&y) == *c)
+    }
+
+    /// Maps a Montgomery-form field element u to a point with the simplified SWU map of
+    /// RFC 9380, Section 6.6.2, for a Montgomery-form constant Z, selecting in constant time.
+    fn map_to_curve(&self, u: &[u64; N], z: &[u64; N]) -> Point<N> {
+        let field = &self.field;
+        let zero = &[0; N];
+        let z_u2 = field.mul(z, &field.square(u));
+        let tv_1 = field.invert(&field.add(&field.square(&z_u2), &z_u2));
+        // x_1 is -b / a (1 + tv_1), or b / (Z a) in the exceptional case that tv_1 is zero.
+        let minus_b_over_a = field.sub(zero, &field.mul(&self.b, &field.invert(&self.a)));
+        let x_1 = field.mul(&minus_b_over_a, &field.add(&field.one(), &tv_1));
+        let exceptional = field.mul(&self.b, &field.invert(&field.mul(z, &self.a)));
+        let x_1 = select(&exceptional, &x_1, u64::from(is_zero(&tv_1)).wrapping_neg());
+        let x_2 = field.mul(&z_u2, &x_1);
+        let (y_1, is_square) = self.sqrt(&self.curve_rhs(&x_1));
+        let (y_2, _) = self.sqrt(&self.curve_rhs(&x_2));
+        let mask = u64::from(is_square).wrapping_neg();
+        let x = select(&x_1, &x_2, mask);
+        let y = select(&y_1, &y_2, mask);
+        // The sign of y, its parity, must match that of u.
+        let flip = ((field.to_normal(u)[0] ^ field.to_normal(&y)[0]) & 1).wrapping_neg();
+        Point {
+            x,
+            y: select(&field.sub(zero, &y), &y, flip),
+            z: field.one(),
+        }
+    }
+
+    /// Computes x^3 + a x + b for a Montgomery-form x.
+    fn curve_rhs(&self, x: &[u64; N]) -> [u64; N] {
+        let field = &self.field;
+        let ax_b = field.add(&field.mul(&self.a, x), &self.b);
+        field.add(&field.mul(&field.square(x), x), &ax_b)
+    }
+
+    /// Writes the uncompressed encoding of a point, failing if it is the point at infinity.
+    fn encode_point(&self, point: &Point<N>, output: &mut [u8]) -> bool {
+        if is_zero(&point.z) {
+            return false;
+        }
+        let (x, y) = self.to_affine(point);
+        output[0] = 4;
+        to_be_bytes(&x, &mut output[1..=Self::LEN]);
+        to_be_bytes(&y, &mut output[1 + Self::LEN..]);
+        true
+    }
+
+    /// Outputs the affine coordinates of a point in normal form, where the point at infinity
+    /// gives (0, 0).
+    fn to_affine(&self, point: &Point<N>) -> ([u64; N], [u64; N]) {
+        let field = &self.field;
+        let z_inverse = field.invert(&point.z);
+        (
+            field.to_normal(&field.mul(&point.x, &z_inverse)),
+            field.to_normal(&field.mul(&point.y, &z_inverse)),
+        )
+    }
+
+    /// Computes `scalar` times a point by doubling and adding at every bit, selecting the result
+    /// in constant time.
+    fn scalarmult(&self, point: &Point<N>, scalar: &[u64; N]) -> Point<N> {
+        let mut result = Point {
+            x: [0; N],
+            y: self.field.one(),
+            z: [0; N],
+        };
+        for i in (0..64 * N).rev() {
+            result = self.add(&result, &result);
+            let sum = self.add(&result, point);
+            let mask = ((scalar[i / 64] >> (i % 64)) & 1).wrapping_neg();
+            result = Point {
+                x: select(&sum.x, &result.x, mask),
+                y: select(&sum.y, &result.y, mask),
+                z: select(&sum.z, &result.z, mask),
+            };
+        }
+        result
+    }
+
+    /// Outputs P, 3 P, 5 P, ..., 15 P.
+    #[cfg(feature = "alloc")]
+    fn odd_multiples(&self, point: &Point<N>) -> [Point<N>; 8] {
+        let double = self.add(point, point);
+        let mut table = [*point; 8];
+        for i in 1..8 {
+            table[i] = self.add(&table[i - 1], &double);
+        }
+        table
+    }
+
+    /// Adds two points using Algorithm 1 of Renes, Costello and Batina, which is complete for
+    /// any a, so it also handles doubling and the point at infinity.
+    fn add(&self, p: &Point<N>, q: &Point<N>) -> Point<N> {
+        let f = &self.field;
+        let mut t0 = f.mul(&p.x, &q.x);
+        let mut t1 = f.mul(&p.y, &q.y);
+        let mut t2 = f.mul(&p.z, &q.z);
+        let mut t3 = f.mul(&f.add(&p.x, &p.y), &f.add(&q.x, &q.y));
+        let mut t4 = f.add(&t0, &t1);
+        t3 = f.sub(&t3, &t4);
+        t4 = f.mul(&f.add(&p.x, &p.z), &f.add(&q.x, &q.z));
+        let mut t5 = f.add(&t0, &t2);
+        t4 = f.sub(&t4, &t5);
+        t5 = f.mul(&f.add(&p.y, &p.z), &f.add(&q.y, &q.z));
+        let mut x3 = f.add(&t1, &t2);
+        t5 = f.sub(&t5, &x3);
+        let mut z3 = f.mul(&self.a, &t4);
+        x3 = f.mul(&self.b3, &t2);
+        z3 = f.add(&x3, &z3);
+        x3 = f.sub(&t1, &z3);
+        z3 = f.add(&t1, &z3);
+        let mut y3 = f.mul(&x3, &z3);
+        t1 = f.add(&f.add(&t0, &t0), &t0);
+        t2 = f.mul(&self.a, &t2);
+        t4 = f.mul(&self.b3, &t4);
+        t1 = f.add(&t1, &t2);
+        t2 = f.mul(&self.a, &f.sub(&t0, &t2));
+        t4 = f.add(&t4, &t2);
+        t0 = f.mul(&t1, &t4);
+        y3 = f.add(&y3, &t0);
+        t0 = f.mul(&t5, &t4);
+        x3 = f.sub(&f.mul(&t3, &x3), &t0);
+        t0 = f.mul(&t3, &t1);
+        z3 = f.add(&f.mul(&t5, &z3), &t0);
+        Point {
+            x: x3,
+            y: y3,
+            z: z3,
+        }
+    }
+}
+
+/// Reduces a big-endian integer of up to twice the length of the modulus, outputting it in normal
+/// form.
+fn reduce_wide<const N: usize>(modulus: &Modulus<N>, bytes: &[u8]) -> [u64; N] {
+    let len = 8 * N;
+    let mut wide = [0; 144];
+    let wide = &mut wide[..2 * len];
+    wide[2 * len - bytes.len()..].copy_from_slice(bytes);
+    let high = modulus.reduce_once(&from_be_bytes(&wide[..len]));
+    let low = modulus.reduce_once(&from_be_bytes(&wide[len..]));
+    // The Montgomery form of the high half is high 2^(64 N) mod m.
+    modulus.add(&modulus.to_montgomery(&high), &low)
+}
+
+/// Recodes a scalar k as digits d_i with k = sum(d_i 2^i), where each digit is zero or odd with
+/// |d_i| < 16, and at most one of any five consecutive digits is nonzero.
+#[cfg(feature = "alloc")]
+fn signed_digits<const N: usize>(scalar: &[u64; N]) -> Vec<i8> {
+    let mut digits = vec![0; 64 * N + 1];
+    let mut k = *scalar;
+    // The bit above the top limb, for when adding to k carries out of it
+    let mut high = 0;
+    for digit in digits.iter_mut() {
+        if k[0] & 1 == 1 {
+            let window = (k[0] & 31) as i8;
+            *digit = if window >= 16 { window - 32 } else { window };
+            // Subtracting the digit clears the low five bits of k.
+            if *digit > 0 {
+                k[0] -= *digit as u64;
+            } else {
+                let mut carry = u64::from(digit.unsigned_abs());
+                for limb in k.iter_mut() {
+                    let (value, overflow) = limb.overflowing_add(carry);
+                    *limb = value;
+                    carry = u64::from(overflow);
+                }
+                high += carry;
+            }
+        }
+        for i in 0..N {
+            let next = k.get(i + 1).map_or(high, |next| *next);
+            k[i] = (k[i] >> 1) | (next << 63);
+        }
+        high = 0;
+    }
+    digits
+}
+
+/// Converts a signature r || s to a DER SEQUENCE of the INTEGERs r and s.
+#[cfg(feature = "alloc")]
+pub(crate) fn signature_to_der(signature: &[u8]) -> Vec<u8> {
+    let (r, s) = signature.split_at(signature.len() / 2);
+    let mut writer = Writer::new();
+    writer.write_sequence(|sequence| {
+        sequence.write_unsigned_integer(r);
+        sequence.write_unsigned_integer(s);
+    });
+    writer.into_vec()
+}
+
+/// Converts a DER SEQUENCE of the INTEGERs r and s to a signature r || s, failing if either is
+/// negative or too large for half of `signature`. The reader rejects any encoding that isn't
+/// DER, such as integers with redundant leading bytes.
+pub(crate) fn signature_from_der(der: &[u8], signature: &mut [u8]) -> Result<(), Error> {
+    let len = signature.len() / 2;
+    asn1::parse(der, |reader| {
+        let mut sequence = reader.read_sequence()?;
+        for half in signature.chunks_mut(len) {
+            let integer = sequence.read_unsigned_integer()?;
+            if integer.len() > len {
+                return Err(Error::InvalidEncoding);
+            }
+            half.fill(0);
+            half[len - integer.len()..].copy_from_slice(integer);
+        }
+        sequence.finish()
+    })
+}
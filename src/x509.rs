@@ -0,0 +1,943 @@
+//! Module for parsing X.509 certificates (RFC 5280) and verifying their signatures.
+//!
+//! `Certificate` borrows from its DER encoding. Parsing checks the structure of every field and
+//! fully decodes the validity period and the subject alternative name, key usage and basic
+//! constraints extensions. Signatures can be verified with RSASSA-PKCS1-v1_5 and SHA-2, ECDSA
+//! with P-256 and SHA-256 or P-384 and SHA-384, and Ed25519.
+//!
+//! Only single signatures are verified: building and validating a path, including checking names,
+//! validity periods and key usages, is left to the caller. Critical extensions other than those
+//! three are recorded while parsing, so that the caller can reject them.
+//!
+//! # Examples
+//!
+//! ```
+//! use crypto_pure::encoding::pem::{self, Mode};
+//! use crypto_pure::x509::Certificate;
+//! let pem = pem::parse(
+//!     "-----BEGIN CERTIFICATE-----
+//! MIIBLzCB4qADAgECAgEDMAUGAytlcDAXMRUwEwYDVQQDDAxFZDI1NTE5IFRlc3Qw
+//! HhcNMjQwMjI5MTIwMDAwWhcNMzQwMjI4MTIwMDAwWjAXMRUwEwYDVQQDDAxFZDI1
+//! NTE5IFRlc3QwKjAFBgMrZXADIQBC0Yry9BEWRJAuDhao81aq9HydfTYezENPSfCv
+//! lPmCiKNTMFEwHQYDVR0OBBYEFN1ThD5jGupeR7cAzO5ujXAV+nu3MB8GA1UdIwQY
+//! MBaAFN1ThD5jGupeR7cAzO5ujXAV+nu3MA8GA1UdEwEB/wQFMAMBAf8wBQYDK2Vw
+//! A0EAJV7ofz3YNL6mXGbI1hBtrm96zxORv+RqIUcWcgmRcIvksXg8Kb0cWgrzV5q1
+//! QgItdfAgUyDYWsKwQOtMLZN/BQ==
+//! -----END CERTIFICATE-----",
+//!     Mode::Strict,
+//! )?;
+//! let certificate = Certificate::from_der(pem.contents())?;
+//! assert_eq!(Some("Ed25519 Test"), certificate.subject().common_name());
+//! assert!(certificate.is_valid_at(1_750_000_000));
+//! // The certificate is self-signed.
+//! certificate.verify_signature(&certificate.public_key()?)?;
+//! # Ok::<(), crypto_pure::error::Error>(())
+//! ```
+use crate::asn1::{self, BitString, Oid, Reader};
+use crate::ed25519::Ed25519;
+use crate::error::Error;
+use crate::p256::{self, EcdsaP256};
+use crate::p384::{self, EcdsaP384};
+use crate::pkcs8::{self, PublicKey};
+use crate::rsa::Pkcs1v15;
+use crate::sha2::{Sha256, Sha384, Sha512};
+use crate::sig::Verifier;
+use core::iter;
+use core::str;
+
+// sha256WithRSAEncryption, sha384WithRSAEncryption and sha512WithRSAEncryption
+// (1.2.840.113549.1.1.11 to 13)
+const SHA256_WITH_RSA: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x0b];
+const SHA384_WITH_RSA: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x0c];
+const SHA512_WITH_RSA: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x0d];
+// ecdsa-with-SHA256 and ecdsa-with-SHA384 (1.2.840.10045.4.3.2 and 3)
+const ECDSA_WITH_SHA256: &[u8] = &[0x2a, 0x86, 0x48, 0xce, 0x3d, 0x04, 0x03, 0x02];
+const ECDSA_WITH_SHA384: &[u8] = &[0x2a, 0x86, 0x48, 0xce, 0x3d, 0x04, 0x03, 0x03];
+// id-Ed25519 (1.3.101.112)
+const ED25519: &[u8] = &[0x2b, 0x65, 0x70];
+
+// id-at-commonName (2.5.4.3)
+const COMMON_NAME: &[u8] = &[0x55, 0x04, 0x03];
+// id-ce-keyUsage, id-ce-subjectAltName and id-ce-basicConstraints (2.5.29.15, 17 and 19)
+const KEY_USAGE: &[u8] = &[0x55, 0x1d, 0x0f];
+const SUBJECT_ALT_NAME: &[u8] = &[0x55, 0x1d, 0x11];
+const BASIC_CONSTRAINTS: &[u8] = &[0x55, 0x1d, 0x13];
+
+const SECONDS_PER_DAY: i64 = 86400;
+
+/// A signature algorithm that certificates can be verified with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SignatureAlgorithm {
+    /// RSASSA-PKCS1-v1_5 with SHA-256
+    RsaPkcs1Sha256,
+    /// RSASSA-PKCS1-v1_5 with SHA-384
+    RsaPkcs1Sha384,
+    /// RSASSA-PKCS1-v1_5 with SHA-512
+    RsaPkcs1Sha512,
+    /// ECDSA with P-256 and SHA-256
+    EcdsaP256Sha256,
+    /// ECDSA with P-384 and SHA-384
+    EcdsaP384Sha384,
+    /// Ed25519
+    Ed25519,
+}
+
+/// A parsed X.509 certificate.
+#[derive(Clone, Debug)]
+pub struct Certificate<'a> {
+    tbs_certificate: &'a [u8],
+    version: u8,
+    serial_number: &'a [u8],
+    signature_algorithm: &'a [u8],
+    issuer: Name<'a>,
+    not_before: i64,
+    not_after: i64,
+    subject: Name<'a>,
+    subject_public_key_info: &'a [u8],
+    extensions: &'a [u8],
+    subject_alt_names: Option<&'a [u8]>,
+    key_usage: Option<KeyUsage>,
+    basic_constraints: Option<BasicConstraints>,
+    unrecognized_critical_extension: bool,
+    signature: &'a [u8],
+}
+
+impl<'a> Certificate<'a> {
+    /// Parses the DER encoding of a certificate, such as the contents of a `CERTIFICATE` PEM
+    /// block.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::InvalidEncoding` if `der` is malformed, including if the signature
+    /// algorithms inside and outside the signed part differ or an extension appears twice.
+    pub fn from_der(der: &'a [u8]) -> Result<Self, Error> {
+        asn1::parse(der, |reader| {
+            let mut certificate = reader.read_sequence()?;
+            let tbs_certificate = certificate.read_raw(asn1::SEQUENCE)?;
+            let signature_algorithm = certificate.read_raw(asn1::SEQUENCE)?;
+            let signature = certificate.read_bit_string()?.octets()?;
+            certificate.finish()?;
+            let mut parsed = asn1::parse(tbs_certificate, |reader| {
+                read_tbs_certificate(reader.read_sequence()?, signature_algorithm)
+            })?;
+            parsed.tbs_certificate = tbs_certificate;
+            parsed.signature = signature;
+            Ok(parsed)
+        })
+    }
+
+    /// Gets the version, which is 1, 2 or 3.
+    pub fn version(&self) -> u8 {
+        self.version
+    }
+
+    /// Gets the big-endian two's complement serial number.
+    pub fn serial_number(&self) -> &'a [u8] {
+        self.serial_number
+    }
+
+    /// Gets the signature algorithm, or `None` if it is not supported.
+    pub fn signature_algorithm(&self) -> Option<SignatureAlgorithm> {
+        asn1::parse(self.signature_algorithm, |reader| {
+            let mut identifier = reader.read_sequence()?;
+            let oid = identifier.read_oid()?;
+            // RFC 4055 requires NULL parameters for RSA, and RFC 5758 and RFC 8410 require no
+            // parameters for ECDSA and Ed25519.
+            let algorithm = match oid.as_bytes() {
+                SHA256_WITH_RSA | SHA384_WITH_RSA | SHA512_WITH_RSA => {
+                    identifier.read_null()?;
+                    match oid.as_bytes() {
+                        SHA256_WITH_RSA => SignatureAlgorithm::RsaPkcs1Sha256,
+                        SHA384_WITH_RSA => SignatureAlgorithm::RsaPkcs1Sha384,
+                        _ => SignatureAlgorithm::RsaPkcs1Sha512,
+                    }
+                }
+                ECDSA_WITH_SHA256 => SignatureAlgorithm::EcdsaP256Sha256,
+                ECDSA_WITH_SHA384 => SignatureAlgorithm::EcdsaP384Sha384,
+                ED25519 => SignatureAlgorithm::Ed25519,
+                _ => return Err(Error::InvalidKey),
+            };
+            identifier.finish()?;
+            Ok(algorithm)
+        })
+        .ok()
+    }
+
+    /// Gets the name of the issuer.
+    pub fn issuer(&self) -> Name<'a> {
+        self.issuer
+    }
+
+    /// Gets the start of the validity period in seconds since the Unix epoch.
+    pub fn not_before(&self) -> i64 {
+        self.not_before
+    }
+
+    /// Gets the end of the validity period in seconds since the Unix epoch. The certificate is
+    /// still valid during this second.
+    pub fn not_after(&self) -> i64 {
+        self.not_after
+    }
+
+    /// Checks whether `time`, in seconds since the Unix epoch, is in the validity period.
+    pub fn is_valid_at(&self, time: i64) -> bool {
+        self.not_before <= time && time <= self.not_after
+    }
+
+    /// Gets the name of the subject.
+    pub fn subject(&self) -> Name<'a> {
+        self.subject
+    }
+
+    /// Gets the DER encoding of the SubjectPublicKeyInfo.
+    pub fn subject_public_key_info(&self) -> &'a [u8] {
+        self.subject_public_key_info
+    }
+
+    /// Decodes the subject's public key.
+    ///
+    /// # Errors
+    ///
+    /// Fails like `pkcs8::decode_public_key`, such as if the key type is not supported.
+    pub fn public_key(&self) -> Result<PublicKey, Error> {
+        pkcs8::decode_public_key(self.subject_public_key_info)
+    }
+
+    /// Outputs the extensions in order.
+    pub fn extensions(&self) -> impl Iterator<Item = Extension<'a>> + 'a {
+        let mut reader = Reader::new(self.extensions);
+        iter::from_fn(move || {
+            if reader.is_empty() {
+                return None;
+            }
+            // The extensions were checked when the certificate was parsed.
+            Some(read_extension(&mut reader).expect("invalid extension"))
+        })
+    }
+
+    /// Outputs the names of the subject alternative name extension, which is empty if the
+    /// extension is absent.
+    pub fn subject_alt_names(&self) -> impl Iterator<Item = GeneralName<'a>> + 'a {
+        let mut reader = Reader::new(self.subject_alt_names.unwrap_or(&[]));
+        iter::from_fn(move || {
+            if reader.is_empty() {
+                return None;
+            }
+            // The names were checked when the certificate was parsed.
+            Some(read_general_name(&mut reader).expect("invalid general name"))
+        })
+    }
+
+    /// Gets the key usage extension, if present.
+    pub fn key_usage(&self) -> Option<KeyUsage> {
+        self.key_usage
+    }
+
+    /// Gets the basic constraints extension, if present.
+    pub fn basic_constraints(&self) -> Option<BasicConstraints> {
+        self.basic_constraints
+    }
+
+    /// Checks whether a critical extension other than the subject alternative name, key usage and
+    /// basic constraints extensions is present, in which case the certificate must be rejected
+    /// unless the caller recognizes the extension.
+    pub fn has_unrecognized_critical_extension(&self) -> bool {
+        self.unrecognized_critical_extension
+    }
+
+    /// Verifies the signature of the certificate under `public_key`, usually that of the issuer.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::InvalidKey` if the signature algorithm is not supported or needs a
+    /// different type of key, or with `Error::InvalidSignature` if the signature is not valid.
+    pub fn verify_signature(&self, public_key: &PublicKey) -> Result<(), Error> {
+        let algorithm = self.signature_algorithm().ok_or(Error::InvalidKey)?;
+        let (message, signature) = (self.tbs_certificate, self.signature);
+        match (algorithm, public_key) {
+            (SignatureAlgorithm::RsaPkcs1Sha256, PublicKey::Rsa(public_key)) => {
+                Pkcs1v15::<Sha256>::verify(message, b"", signature, public_key)
+            }
+            (SignatureAlgorithm::RsaPkcs1Sha384, PublicKey::Rsa(public_key)) => {
+                Pkcs1v15::<Sha384>::verify(message, b"", signature, public_key)
+            }
+            (SignatureAlgorithm::RsaPkcs1Sha512, PublicKey::Rsa(public_key)) => {
+                Pkcs1v15::<Sha512>::verify(message, b"", signature, public_key)
+            }
+            (SignatureAlgorithm::EcdsaP256Sha256, PublicKey::P256(public_key)) => {
+                let signature =
+                    p256::signature_from_der(signature).map_err(|_| Error::InvalidSignature)?;
+                EcdsaP256::verify(message, b"", &signature, public_key)
+            }
+            (SignatureAlgorithm::EcdsaP384Sha384, PublicKey::P384(public_key)) => {
+                let signature =
+                    p384::signature_from_der(signature).map_err(|_| Error::InvalidSignature)?;
+                EcdsaP384::verify(message, b"", &signature, public_key)
+            }
+            (SignatureAlgorithm::Ed25519, PublicKey::Ed25519(public_key)) => {
+                Ed25519::verify(message, b"", signature, public_key)
+            }
+            _ => Err(Error::InvalidKey),
+        }
+    }
+}
+
+/// A distinguished name, such as the subject of a certificate.
+///
+/// Names are equal if their encodings are, which is how issuers are usually matched in practice.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Name<'a>(&'a [u8]);
+
+impl<'a> Name<'a> {
+    /// Gets the contents of the DER encoding of the name.
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.0
+    }
+
+    /// Outputs the type, string tag and string contents of each attribute in order, such as
+    /// `2.5.4.3`, `asn1::UTF8_STRING` and `example.com` for the common name `example.com`.
+    pub fn attributes(&self) -> impl Iterator<Item = (Oid<'a>, u8, &'a [u8])> + 'a {
+        let mut names = Reader::new(self.0);
+        let mut attributes = Reader::new(&[]);
+        iter::from_fn(move || {
+            // The name was checked when the certificate was parsed.
+            while attributes.is_empty() {
+                if names.is_empty() {
+                    return None;
+                }
+                attributes = names.read_set().expect("invalid name");
+            }
+            Some(read_attribute(&mut attributes).expect("invalid name"))
+        })
+    }
+
+    /// Gets the first common name, if it is a UTF8String or PrintableString.
+    pub fn common_name(&self) -> Option<&'a str> {
+        self.attributes()
+            .find(|(oid, _, _)| oid.as_bytes() == COMMON_NAME)
+            .and_then(|(_, tag, value)| match tag {
+                asn1::UTF8_STRING | asn1::PRINTABLE_STRING => str::from_utf8(value).ok(),
+                _ => None,
+            })
+    }
+}
+
+/// A certificate extension.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Extension<'a> {
+    oid: Oid<'a>,
+    critical: bool,
+    value: &'a [u8],
+}
+
+impl<'a> Extension<'a> {
+    /// Gets the type of the extension.
+    pub fn oid(&self) -> Oid<'a> {
+        self.oid
+    }
+
+    /// Checks whether the extension is critical, so that a certificate must be rejected by a
+    /// verifier that doesn't recognize it.
+    pub fn is_critical(&self) -> bool {
+        self.critical
+    }
+
+    /// Gets the DER encoding held by the extension.
+    pub fn value(&self) -> &'a [u8] {
+        self.value
+    }
+}
+
+/// A name in the subject alternative name extension.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GeneralName<'a> {
+    /// An email address
+    Email(&'a str),
+    /// A DNS name, which may start with a `*` label
+    DnsName(&'a str),
+    /// A URI
+    Uri(&'a str),
+    /// A 4-byte IPv4 or 16-byte IPv6 address
+    IpAddress(&'a [u8]),
+    /// Another type of name, given by its tag and contents
+    Other(u8, &'a [u8]),
+}
+
+/// The key usage extension, which restricts what the public key can be used for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KeyUsage(u16);
+
+impl KeyUsage {
+    /// Signatures other than on certificates and CRLs
+    pub const DIGITAL_SIGNATURE: Self = Self(1);
+    /// Signatures for non-repudiation
+    pub const CONTENT_COMMITMENT: Self = Self(1 << 1);
+    /// Encryption of keys, such as with RSAES-OAEP
+    pub const KEY_ENCIPHERMENT: Self = Self(1 << 2);
+    /// Encryption of data other than keys
+    pub const DATA_ENCIPHERMENT: Self = Self(1 << 3);
+    /// Key agreement, such as ECDH
+    pub const KEY_AGREEMENT: Self = Self(1 << 4);
+    /// Signatures on certificates
+    pub const KEY_CERT_SIGN: Self = Self(1 << 5);
+    /// Signatures on certificate revocation lists
+    pub const CRL_SIGN: Self = Self(1 << 6);
+    /// Only encryption during key agreement
+    pub const ENCIPHER_ONLY: Self = Self(1 << 7);
+    /// Only decryption during key agreement
+    pub const DECIPHER_ONLY: Self = Self(1 << 8);
+
+    /// Checks whether all of the usages in `usage` are allowed.
+    pub fn contains(self, usage: Self) -> bool {
+        self.0 & usage.0 == usage.0
+    }
+}
+
+/// The basic constraints extension, which says whether the subject is a CA.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BasicConstraints {
+    ca: bool,
+    path_len: Option<u64>,
+}
+
+impl BasicConstraints {
+    /// Checks whether the subject is a CA, so that the public key can verify certificates.
+    pub fn is_ca(&self) -> bool {
+        self.ca
+    }
+
+    /// Gets the maximum number of intermediate CAs that may follow in a path, if limited.
+    pub fn path_len(&self) -> Option<u64> {
+        self.path_len
+    }
+}
+
+fn read_tbs_certificate<'a>(
+    mut tbs: Reader<'a>,
+    signature_algorithm: &'a [u8],
+) -> Result<Certificate<'a>, Error> {
+    // The version defaults to v1, so DER requires it to be absent then.
+    let version = match tbs.read_explicit(0)? {
+        Some(mut version) => {
+            let number = version.read_u64()?;
+            version.finish()?;
+            match number {
+                1 | 2 => number as u8 + 1,
+                _ => return Err(Error::InvalidEncoding),
+            }
+        }
+        None => 1,
+    };
+    let serial_number = tbs.read_integer()?;
+    if tbs.read_raw(asn1::SEQUENCE)? != signature_algorithm {
+        return Err(Error::InvalidEncoding);
+    }
+    let issuer = read_name(&mut tbs)?;
+    let mut validity = tbs.read_sequence()?;
+    let not_before = read_time(&mut validity)?;
+    let not_after = read_time(&mut validity)?;
+    validity.finish()?;
+    let subject = read_name(&mut tbs)?;
+    let subject_public_key_info = tbs.read_raw(asn1::SEQUENCE)?;
+    // The unique identifiers are implicitly tagged BIT STRINGs, only allowed from v2.
+    for number in 1..=2 {
+        if let Some(unique_id) = tbs.read_optional(asn1::implicit(number))? {
+            if version < 2 {
+                return Err(Error::InvalidEncoding);
+            }
+            BitString::from_contents(unique_id)?;
+        }
+    }
+
+    let mut certificate = Certificate {
+        tbs_certificate: &[],
+        version,
+        serial_number,
+        signature_algorithm,
+        issuer,
+        not_before,
+        not_after,
+        subject,
+        subject_public_key_info,
+        extensions: &[],
+        subject_alt_names: None,
+        key_usage: None,
+        basic_constraints: None,
+        unrecognized_critical_extension: false,
+        signature: &[],
+    };
+    if let Some(mut explicit) = tbs.read_explicit(3)? {
+        if version < 3 {
+            return Err(Error::InvalidEncoding);
+        }
+        certificate.extensions = explicit.read(asn1::SEQUENCE)?;
+        explicit.finish()?;
+        read_extensions(&mut certificate)?;
+    }
+    tbs.finish()?;
+    Ok(certificate)
+}
+
+/// Checks the extensions of `certificate`, decoding those that are supported.
+fn read_extensions(certificate: &mut Certificate) -> Result<(), Error> {
+    let mut extensions = Reader::new(certificate.extensions);
+    // RFC 5280 requires at least one extension if the field is present.
+    if extensions.is_empty() {
+        return Err(Error::InvalidEncoding);
+    }
+    let mut count = 0;
+    while !extensions.is_empty() {
+        let extension = read_extension(&mut extensions)?;
+        // Each extension may appear only once.
+        if certificate
+            .extensions()
+            .take(count)
+            .any(|e| e.oid == extension.oid)
+        {
+            return Err(Error::InvalidEncoding);
+        }
+        count += 1;
+        let value = extension.value;
+        match extension.oid.as_bytes() {
+            SUBJECT_ALT_NAME => {
+                let names = asn1::parse(value, |reader| reader.read(asn1::SEQUENCE))?;
+                let mut reader = Reader::new(names);
+                // The extension must have at least one name.
+                if reader.is_empty() {
+                    return Err(Error::InvalidEncoding);
+                }
+                while !reader.is_empty() {
+                    read_general_name(&mut reader)?;
+                }
+                certificate.subject_alt_names = Some(names);
+            }
+            KEY_USAGE => certificate.key_usage = Some(asn1::parse(value, read_key_usage)?),
+            BASIC_CONSTRAINTS => {
+                certificate.basic_constraints = Some(asn1::parse(value, read_basic_constraints)?)
+            }
+            _ if extension.critical => certificate.unrecognized_critical_extension = true,
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+fn read_extension<'a>(reader: &mut Reader<'a>) -> Result<Extension<'a>, Error> {
+    let mut extension = reader.read_sequence()?;
+    let oid = extension.read_oid()?;
+    // Criticality defaults to false, so DER requires it to be absent then.
+    let critical = match extension.peek_tag() {
+        Some(asn1::BOOLEAN) if extension.read_boolean()? => true,
+        Some(asn1::BOOLEAN) => return Err(Error::InvalidEncoding),
+        _ => false,
+    };
+    let value = extension.read_octet_string()?;
+    extension.finish()?;
+    Ok(Extension {
+        oid,
+        critical,
+        value,
+    })
+}
+
+fn read_general_name<'a>(reader: &mut Reader<'a>) -> Result<GeneralName<'a>, Error> {
+    let ascii = |contents: &'a [u8]| match str::from_utf8(contents) {
+        Ok(s) if contents.is_ascii() => Ok(s),
+        _ => Err(Error::InvalidEncoding),
+    };
+    let (tag, contents) = reader.read_any()?;
+    Ok(match tag {
+        0x81 => GeneralName::Email(ascii(contents)?),
+        0x82 => GeneralName::DnsName(ascii(contents)?),
+        0x86 => GeneralName::Uri(ascii(contents)?),
+        0x87 if contents.len() == 4 || contents.len() == 16 => GeneralName::IpAddress(contents),
+        0x87 => return Err(Error::InvalidEncoding),
+        _ => GeneralName::Other(tag, contents),
+    })
+}
+
+fn read_key_usage(reader: &mut Reader) -> Result<KeyUsage, Error> {
+    let bits = reader.read_bit_string()?;
+    // DER requires named bits to have no trailing zeros, so the last bit is set.
+    let bit_len = 8 * bits.bytes().len() - usize::from(bits.unused_bits());
+    if bit_len > 9 || (bit_len > 0 && !bits.is_set(bit_len - 1)) {
+        return Err(Error::InvalidEncoding);
+    }
+    let usage = (0..bit_len)
+        .filter(|&i| bits.is_set(i))
+        .fold(0, |usage, i| usage | 1 << i);
+    Ok(KeyUsage(usage))
+}
+
+fn read_basic_constraints(reader: &mut Reader) -> Result<BasicConstraints, Error> {
+    let mut constraints = reader.read_sequence()?;
+    // cA defaults to false, so DER requires it to be absent then.
+    let ca = match constraints.peek_tag() {
+        Some(asn1::BOOLEAN) if constraints.read_boolean()? => true,
+        Some(asn1::BOOLEAN) => return Err(Error::InvalidEncoding),
+        _ => false,
+    };
+    let path_len = match constraints.peek_tag() {
+        Some(asn1::INTEGER) => Some(constraints.read_u64()?),
+        _ => None,
+    };
+    constraints.finish()?;
+    Ok(BasicConstraints { ca, path_len })
+}
+
+fn read_name<'a>(reader: &mut Reader<'a>) -> Result<Name<'a>, Error> {
+    let name = reader.read(asn1::SEQUENCE)?;
+    let mut names = Reader::new(name);
+    while !names.is_empty() {
+        let mut attributes = names.read_set()?;
+        // Each relative distinguished name has at least one attribute.
+        if attributes.is_empty() {
+            return Err(Error::InvalidEncoding);
+        }
+        while !attributes.is_empty() {
+            read_attribute(&mut attributes)?;
+        }
+    }
+    Ok(Name(name))
+}
+
+fn read_attribute<'a>(reader: &mut Reader<'a>) -> Result<(Oid<'a>, u8, &'a [u8]), Error> {
+    let mut attribute = reader.read_sequence()?;
+    let oid = attribute.read_oid()?;
+    let (tag, value) = attribute.read_any()?;
+    attribute.finish()?;
+    Ok((oid, tag, value))
+}
+
+/// Reads a UTCTime or GeneralizedTime in the form RFC 5280 requires, YYMMDDHHMMSSZ or
+/// YYYYMMDDHHMMSSZ, outputting seconds since the Unix epoch.
+fn read_time(reader: &mut Reader) -> Result<i64, Error> {
+    let (year, rest) = match reader.read_any()? {
+        (asn1::UTC_TIME, contents) if contents.len() == 13 => {
+            // Two-digit years from 50 are in the 20th century.
+            let year = digits(&contents[..2])?;
+            (if year >= 50 { 1900 } else { 2000 } + year, &contents[2..])
+        }
+        (asn1::GENERALIZED_TIME, contents) if contents.len() == 15 => {
+            (digits(&contents[..4])?, &contents[4..])
+        }
+        _ => return Err(Error::InvalidEncoding),
+    };
+    let month = digits(&rest[0..2])?;
+    let day = digits(&rest[2..4])?;
+    let hour = digits(&rest[4..6])?;
+    let minute = digits(&rest[6..8])?;
+    let second = digits(&rest[8..10])?;
+    if rest[10] != b'Z'
+        || !(1..=12).contains(&month)
+        || !(1..=days_in_month(year, month)).contains(&day)
+        || hour > 23
+        || minute > 59
+        || second > 59
+    {
+        return Err(Error::InvalidEncoding);
+    }
+    Ok(days_from_civil(year, month, day) * SECONDS_PER_DAY + hour * 3600 + minute * 60 + second)
+}
+
+fn digits(bytes: &[u8]) -> Result<i64, Error> {
+    bytes.iter().try_fold(0, |value, &byte| match byte {
+        b'0'..=b'9' => Ok(10 * value + i64::from(byte - b'0')),
+        _ => Err(Error::InvalidEncoding),
+    })
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Outputs the number of days from 1970-01-01 to a date in the proleptic Gregorian calendar, for
+/// years from 0.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    // Count from 0000-03-01, so that leap days are at the ends of years.
+    let (year, month) = if month <= 2 {
+        (year - 1, month + 9)
+    } else {
+        (year, month - 3)
+    };
+    let day_of_year = (153 * month + 2) / 5 + day - 1;
+    let days = 365 * year + year.div_euclid(4) - year.div_euclid(100) + year.div_euclid(400);
+    days + day_of_year - 719_468
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encoding::pem::{self, Mode};
+    use crate::test_helpers::*;
+    use std::string::ToString;
+    use std::vec::Vec;
+
+    // Generated by OpenSSL: a P-256 CA, a P-384 leaf it issued, and self-signed RSA and Ed25519
+    // certificates
+    const CA: &str = "-----BEGIN CERTIFICATE-----
+MIIBxjCCAW2gAwIBAgIBATAKBggqhkjOPQQDAjA5MQswCQYDVQQGEwJVUzEQMA4G
+A1UECgwHRXhhbXBsZTEYMBYGA1UEAwwPRXhhbXBsZSBSb290IENBMB4XDTI0MDEw
+MTAwMDAwMFoXDTQ0MDEwMTAwMDAwMFowOTELMAkGA1UEBhMCVVMxEDAOBgNVBAoM
+B0V4YW1wbGUxGDAWBgNVBAMMD0V4YW1wbGUgUm9vdCBDQTBZMBMGByqGSM49AgEG
+CCqGSM49AwEHA0IABL6C5SOSZdrng2K27xcjI0QUOM59vrTjA/90XMckjcrCkN0/
+Si5BywDMduZiWh3QkaxahUviRRVXtTZGksltA+OjZjBkMB0GA1UdDgQWBBQb4Lwv
+akY7QyP1yul7vMEl1L36NTAfBgNVHSMEGDAWgBQb4LwvakY7QyP1yul7vMEl1L36
+NTASBgNVHRMBAf8ECDAGAQH/AgEAMA4GA1UdDwEB/wQEAwIBBjAKBggqhkjOPQQD
+AgNHADBEAiBmZS//ubDEeTcdYYoCiPdRPgus7O0kUNBriqQ1A9vdfwIgTEEA7XC4
++x9mQMYDO1mhKAhAh0cZIVioFccIzQ4X7Gs=
+-----END CERTIFICATE-----";
+    const LEAF: &str = "-----BEGIN CERTIFICATE-----
+MIICBzCCAa2gAwIBAgIJASNFZ4mrze8BMAoGCCqGSM49BAMCMDkxCzAJBgNVBAYT
+AlVTMRAwDgYDVQQKDAdFeGFtcGxlMRgwFgYDVQQDDA9FeGFtcGxlIFJvb3QgQ0Ew
+IBcNMjUwMTAxMDAwMDAwWhgPMjA1MDAxMDEwMDAwMDBaMBYxFDASBgNVBAMMC2V4
+YW1wbGUuY29tMHYwEAYHKoZIzj0CAQYFK4EEACIDYgAEBZF/57+E3M6zfgkFgkvn
+TQDAqW/qDduYehT/C77+Bw8QIn9xDWBloK6AVtk69Dfvk1ahZIhUrcZxSBTzQcUa
+PRs83i7VPuCz2uNG8MarQdhSb8Ybr82vBlliIDGWCAgGo4GhMIGeMAwGA1UdEwEB
+/wQCMAAwDgYDVR0PAQH/BAQDAgeAMD4GA1UdEQQ3MDWCC2V4YW1wbGUuY29tgg0q
+LmV4YW1wbGUuY29thwTAAAIBgRFhZG1pbkBleGFtcGxlLmNvbTAdBgNVHQ4EFgQU
+imCtcg+SHB0t6KEomewaHZ9f81IwHwYDVR0jBBgwFoAUG+C8L2pGO0Mj9crpe7zB
+JdS9+jUwCgYIKoZIzj0EAwIDSAAwRQIhAO89lbQJBjSO/fpX2RSXRJT/0GQ5xdNQ
+pdueQXDJfkVYAiAbhkFQVUxMtVSbXZWItI63EiKn+QYNRh+SQYj/nr9R/A==
+-----END CERTIFICATE-----";
+    const RSA: &str = "-----BEGIN CERTIFICATE-----
+MIIB7zCCAVigAwIBAgIBAjANBgkqhkiG9w0BAQsFADATMREwDwYDVQQDDAhSU0Eg
+VGVzdDAeFw05OTEyMzEyMzU5NTlaFw00OTEyMzEyMzU5NTlaMBMxETAPBgNVBAMM
+CFJTQSBUZXN0MIGfMA0GCSqGSIb3DQEBAQUAA4GNADCBiQKBgQCzEkRww+ty8oxl
+nkkv1c3cCqKG1gXU79YSKrF0YGofHhc04REPuHsz6i3TyUE/6WnKbjtBPtckw4ON
+1oIwly6VWYu9pYAa1lAf5WjRxDjF7LF1Hlb/sL+lfXBTnznvBdPOmd6vB1B7y5H1
+h6CDCA3YDazjlr4Xp3T0UhUqFxiV9QIDAQABo1MwUTAdBgNVHQ4EFgQUklqVKmwk
+2Gpa5diMwvJ8/CjUdhIwHwYDVR0jBBgwFoAUklqVKmwk2Gpa5diMwvJ8/CjUdhIw
+DwYDVR0TAQH/BAUwAwEB/zANBgkqhkiG9w0BAQsFAAOBgQCuXhx1Kv+/OVvUz+Dc
+CwWqVYtnohjhIoKRdnA2yY/I7VH7xNAfmaWCqKkEK6umtJTjFgAK+tCTqZRp9Zig
+GVLg+gChw6LflhlSQqFFVVqxvvB14JU3tQWUodRE/6i3n4u8OLsORLgpA6d92t+0
+nnCBJ4aOGNRALzF2d1MaSd/YCw==
+-----END CERTIFICATE-----";
+    const ED25519: &str = "-----BEGIN CERTIFICATE-----
+MIIBLzCB4qADAgECAgEDMAUGAytlcDAXMRUwEwYDVQQDDAxFZDI1NTE5IFRlc3Qw
+HhcNMjQwMjI5MTIwMDAwWhcNMzQwMjI4MTIwMDAwWjAXMRUwEwYDVQQDDAxFZDI1
+NTE5IFRlc3QwKjAFBgMrZXADIQBC0Yry9BEWRJAuDhao81aq9HydfTYezENPSfCv
+lPmCiKNTMFEwHQYDVR0OBBYEFN1ThD5jGupeR7cAzO5ujXAV+nu3MB8GA1UdIwQY
+MBaAFN1ThD5jGupeR7cAzO5ujXAV+nu3MA8GA1UdEwEB/wQFMAMBAf8wBQYDK2Vw
+A0EAJV7ofz3YNL6mXGbI1hBtrm96zxORv+RqIUcWcgmRcIvksXg8Kb0cWgrzV5q1
+QgItdfAgUyDYWsKwQOtMLZN/BQ==
+-----END CERTIFICATE-----";
+
+    fn der(pem: &str) -> Vec<u8> {
+        pem::parse(pem, Mode::Strict).unwrap().contents().to_vec()
+    }
+
+    #[test]
+    fn test_ca() {
+        let der = der(CA);
+        let ca = Certificate::from_der(&der).unwrap();
+        assert_eq!(3, ca.version());
+        assert_eq!(&[1], ca.serial_number());
+        assert_eq!(
+            Some(SignatureAlgorithm::EcdsaP256Sha256),
+            ca.signature_algorithm()
+        );
+        assert_eq!(ca.issuer(), ca.subject());
+        assert_eq!(Some("Example Root CA"), ca.subject().common_name());
+        let attributes: Vec<_> = ca
+            .subject()
+            .attributes()
+            .map(|(oid, tag, value)| (oid.as_bytes(), tag, value))
+            .collect();
+        assert_eq!(
+            vec![
+                (&[0x55, 0x04, 0x06][..], asn1::PRINTABLE_STRING, &b"US"[..]),
+                (&[0x55, 0x04, 0x0a], asn1::UTF8_STRING, b"Example"),
+                (COMMON_NAME, asn1::UTF8_STRING, b"Example Root CA"),
+            ],
+            attributes
+        );
+        assert_eq!(1_704_067_200, ca.not_before());
+        assert_eq!(2_335_219_200, ca.not_after());
+        assert!(!ca.is_valid_at(1_704_067_199));
+        assert!(ca.is_valid_at(1_704_067_200));
+        assert!(ca.is_valid_at(2_335_219_200));
+        assert!(!ca.is_valid_at(2_335_219_201));
+
+        let constraints = ca.basic_constraints().unwrap();
+        assert!(constraints.is_ca());
+        assert_eq!(Some(0), constraints.path_len());
+        let usage = ca.key_usage().unwrap();
+        assert!(usage.contains(KeyUsage(KeyUsage::KEY_CERT_SIGN.0 | KeyUsage::CRL_SIGN.0)));
+        assert!(!usage.contains(KeyUsage::DIGITAL_SIGNATURE));
+        assert_eq!(0, ca.subject_alt_names().count());
+        let critical: Vec<_> = ca.extensions().map(|e| e.is_critical()).collect();
+        assert_eq!(vec![false, false, true, true], critical);
+        assert!(!ca.has_unrecognized_critical_extension());
+
+        let public_key = ca.public_key().unwrap();
+        assert_eq!(
+            ca.subject_public_key_info(),
+            &pkcs8::encode_public_key(&public_key)[..]
+        );
+        assert_eq!(Ok(()), ca.verify_signature(&public_key));
+    }
+
+    #[test]
+    fn test_leaf() {
+        let (ca_der, leaf_der) = (der(CA), der(LEAF));
+        let ca = Certificate::from_der(&ca_der).unwrap();
+        let leaf = Certificate::from_der(&leaf_der).unwrap();
+        assert_eq!(h2b("0123456789abcdef01"), leaf.serial_number());
+        assert_eq!(ca.subject(), leaf.issuer());
+        assert_ne!(leaf.issuer(), leaf.subject());
+        assert_eq!(Some("example.com"), leaf.subject().common_name());
+        // The end of the validity period is a GeneralizedTime.
+        assert_eq!(1_735_689_600, leaf.not_before());
+        assert_eq!(2_524_608_000, leaf.not_after());
+        assert_eq!(Some(KeyUsage::DIGITAL_SIGNATURE), leaf.key_usage());
+        let constraints = leaf.basic_constraints().unwrap();
+        assert!(!constraints.is_ca());
+        assert_eq!(None, constraints.path_len());
+        let names: Vec<_> = leaf.subject_alt_names().collect();
+        assert_eq!(
+            vec![
+                GeneralName::DnsName("example.com"),
+                GeneralName::DnsName("*.example.com"),
+                GeneralName::IpAddress(&[192, 0, 2, 1]),
+                GeneralName::Email("admin@example.com"),
+            ],
+            names
+        );
+        let oids: Vec<_> = leaf.extensions().map(|e| e.oid().to_string()).collect();
+        assert_eq!(
+            vec![
+                "2.5.29.19",
+                "2.5.29.15",
+                "2.5.29.17",
+                "2.5.29.14",
+                "2.5.29.35"
+            ],
+            oids
+        );
+
+        assert_eq!(Ok(()), leaf.verify_signature(&ca.public_key().unwrap()));
+        let leaf_key = leaf.public_key().unwrap();
+        assert!(matches!(leaf_key, PublicKey::P384(_)));
+        assert_eq!(Err(Error::InvalidKey), leaf.verify_signature(&leaf_key));
+    }
+
+    #[test]
+    fn test_self_signed() {
+        for &(pem, algorithm, not_before, not_after) in &[
+            (
+                RSA,
+                SignatureAlgorithm::RsaPkcs1Sha256,
+                946_684_799,
+                2_524_607_999,
+            ),
+            (
+                ED25519,
+                SignatureAlgorithm::Ed25519,
+                1_709_208_000,
+                2_024_740_800,
+            ),
+        ] {
+            let der = der(pem);
+            let certificate = Certificate::from_der(&der).unwrap();
+            assert_eq!(Some(algorithm), certificate.signature_algorithm());
+            assert_eq!(not_before, certificate.not_before());
+            assert_eq!(not_after, certificate.not_after());
+            assert_eq!(None, certificate.key_usage());
+            assert!(certificate.basic_constraints().unwrap().is_ca());
+            let public_key = certificate.public_key().unwrap();
+            assert_eq!(Ok(()), certificate.verify_signature(&public_key));
+
+            // Changing the signed part or the signature invalidates the signature.
+            let name = der.windows(4).position(|w| w == b"Test").unwrap();
+            for &offset in &[name, der.len() - 5] {
+                let mut tampered = der.clone();
+                tampered[offset] ^= 1;
+                let certificate = Certificate::from_der(&tampered).unwrap();
+                let error = Err(Error::InvalidSignature);
+                assert_eq!(error, certificate.verify_signature(&public_key));
+            }
+        }
+    }
+
+    #[test]
+    fn test_invalid_certificates() {
+        let der = der(CA);
+        for len in 0..der.len() {
+            assert!(Certificate::from_der(&der[..len]).is_err());
+        }
+        let replace = |offset: usize, byte: u8| {
+            let mut der = der.clone();
+            der[offset] = byte;
+            Certificate::from_der(&der).map(|_| ())
+        };
+        let error = Err(Error::InvalidEncoding);
+        // Version 4
+        assert_eq!(error, replace(12, 3));
+        // Different inner signature algorithm
+        assert_eq!(error, replace(27, 3));
+        // Invalid month of notBefore
+        assert_eq!(error, replace(93, b'3'));
+        // Non-critical extension with an explicit FALSE
+        assert_eq!(error, replace(346, 0x00));
+        // Key usage with a trailing zero bit
+        assert_eq!(error, replace(371, 0x00));
+        // Duplicate extension: the subject key identifier becomes a second authority key
+        // identifier.
+        assert_eq!(error, replace(279, 0x23));
+
+        let ca = Certificate::from_der(&der).unwrap();
+        let mut other = PublicKey::Ed25519([0; 32]);
+        assert_eq!(Err(Error::InvalidKey), ca.verify_signature(&other));
+        if let Ok(PublicKey::P256(mut public_key)) = ca.public_key() {
+            public_key[64] ^= 1;
+            other = PublicKey::P256(public_key);
+        }
+        assert_eq!(Err(Error::InvalidSignature), ca.verify_signature(&other));
+    }
+
+    #[test]
+    fn test_times() {
+        let valid: &[(u8, &[u8], i64)] = &[
+            (asn1::UTC_TIME, b"700101000000Z", 0),
+            (asn1::UTC_TIME, b"500101000000Z", -631_152_000),
+            (asn1::UTC_TIME, b"491231235959Z", 2_524_607_999),
+            (asn1::UTC_TIME, b"000229000000Z", 951_782_400),
+            (asn1::GENERALIZED_TIME, b"19691231235959Z", -1),
+            (asn1::GENERALIZED_TIME, b"20380119031408Z", 2_147_483_648),
+            (asn1::GENERALIZED_TIME, b"99991231235959Z", 253_402_300_799),
+        ];
+        for &(tag, contents, expected) in valid {
+            let input = [&[tag, contents.len() as u8], contents].concat();
+            assert_eq!(Ok(expected), asn1::parse(&input, read_time));
+        }
+        let invalid: &[(u8, &[u8])] = &[
+            (asn1::UTC_TIME, b"240230000000Z"),
+            (asn1::UTC_TIME, b"230229000000Z"),
+            (asn1::UTC_TIME, b"241301000000Z"),
+            (asn1::UTC_TIME, b"240001000000Z"),
+            (asn1::UTC_TIME, b"240100000000Z"),
+            (asn1::UTC_TIME, b"240101240000Z"),
+            (asn1::UTC_TIME, b"240101006000Z"),
+            (asn1::UTC_TIME, b"240101000060Z"),
+            (asn1::UTC_TIME, b"2401010000Z"),
+            (asn1::UTC_TIME, b"240101000000+0000"),
+            (asn1::UTC_TIME, b"24010100000 Z"),
+            (asn1::UTC_TIME, b"20240101000000Z"),
+            (asn1::GENERALIZED_TIME, b"240101000000Z"),
+            (asn1::GENERALIZED_TIME, b"21000229000000Z"),
+            (asn1::GENERALIZED_TIME, b"20240101000000.5Z"),
+            (asn1::UTF8_STRING, b"240101000000Z"),
+        ];
+        for &(tag, contents) in invalid {
+            let input = [&[tag, contents.len() as u8], contents].concat();
+            assert_eq!(Err(Error::InvalidEncoding), asn1::parse(&input, read_time));
+        }
+    }
+}
@@ -0,0 +1,81 @@
+//! Module for the ANSI X9.63 key derivation function (SEC 1, Section 3.6.1), with a hash function
+//! chosen by its `HashFunction` type.
+//!
+//! Each block of output is H(Z || counter || SharedInfo), with a 4-byte big-endian counter
+//! starting at 1. It turns an ECDH shared secret Z into keys for ECIES as used by Apple's
+//! `SecKey` and other X9.63 consumers, which usually put the ephemeral public key in SharedInfo.
+//!
+//! # Examples
+//!
+//! ```
+//! use crypto_pure::sha2::Sha256;
+//! use crypto_pure::x963;
+//! # let shared_secret = &[0x42; 32];
+//! # let ephemeral_public_key = &[0x04; 65];
+//! let output = &mut [0; 32];
+//! x963::derive::<Sha256>(shared_secret, ephemeral_public_key, output);
+//! let (key, iv) = output.split_at(16);
+//! ```
+use crate::sha2::{Digest, HashFunction};
+
+/// Derives keying material into `output` from a shared secret and shared information.
+///
+/// # Panics
+///
+/// Panics if `output.len()` is more than (2^32 - 1) times the digest size for `H`.
+pub fn derive<H: HashFunction>(shared_secret: &[u8], shared_info: &[u8], output: &mut [u8]) {
+    let digest_size = H::DIGEST_SIZE;
+    assert!((output.len() as u64).div_ceil(digest_size as u64) <= u64::from(u32::MAX));
+    for (counter, chunk) in (1u32..).zip(output.chunks_mut(digest_size)) {
+        let mut hash_function = H::default();
+        hash_function.update(shared_secret);
+        hash_function.update(&counter.to_be_bytes());
+        hash_function.update(shared_info);
+        let digest = Digest::new(hash_function);
+        chunk.copy_from_slice(&digest[..chunk.len()]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sha2::{Sha256, Sha512};
+    use crate::test_helpers::*;
+    use std::vec;
+
+    fn check<H: HashFunction>(shared_secret: &[u8], shared_info: &[u8], expected: &str) {
+        let expected = h2b(expected);
+        let output = &mut vec![0; expected.len()];
+        derive::<H>(shared_secret, shared_info, output);
+        assert_eq!(&expected, output);
+    }
+
+    // NIST CAVP, ANS X9.63-2001 with SHA-256
+    #[test]
+    fn test_vector() {
+        check::<Sha256>(
+            &h2b("96c05619d56c328ab95fe84b18264b08725b85e33fd34f08"),
+            b"",
+            "443024c3dae66b95e6f5670601558f71",
+        );
+    }
+
+    // Generated with Python's cryptography
+    #[test]
+    fn test_shared_info() {
+        let shared_secret = &(0..32).collect::<vec::Vec<u8>>();
+        check::<Sha256>(
+            shared_secret,
+            b"shared info",
+            "a2ba25392f91013464ed530b7260876d7d85630fd24cd7894a49600ab4c7a8c3\
+             3354555992b455ebe41e722867504954a4df484c1d3c0c1bdf6ecefb467afb36\
+             a3c7188e88fda97d332a145bdf22011d44d591cb579b49ac234d950bfb1ee52a\
+             0fc7d3cc",
+        );
+        check::<Sha512>(
+            shared_secret,
+            &[&[4][..], &[1; 64]].concat(),
+            "c45fe0c8b3cd68da70d5112742cd5711c9d7a8d27b2b1719cd0267b91c620a3c",
+        );
+    }
+}
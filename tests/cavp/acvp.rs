@@ -0,0 +1,74 @@
+//! A loader for the JSON vector sets of the NIST Automated Cryptographic Validation Protocol
+//! (ACVP), built on the crate's own JSON parser.
+//!
+//! Only `internalProjection.json` files are read, since they hold both the prompts and the
+//! expected results of a vector set.
+use crate::rsp;
+use crypto_pure::encoding::json::{self, Value};
+use std::fs;
+use std::path::Path;
+
+/// A JSON object of a vector set, such as a test group or a test.
+#[derive(Clone, Copy)]
+pub struct Object<'a>(&'a Value);
+
+impl<'a> Object<'a> {
+    /// Gets a member that is an array of objects, such as the tests of a group.
+    pub fn array(&self, name: &str) -> impl Iterator<Item = Object<'a>> {
+        let array = self
+            .member(name)
+            .as_array()
+            .unwrap_or_else(|| panic!("{} is not an array", name));
+        array.iter().map(Object)
+    }
+
+    /// Gets a member, if there is one.
+    pub fn get(&self, name: &str) -> Option<&'a Value> {
+        self.0.get(name)
+    }
+
+    /// Gets a string member.
+    pub fn str(&self, name: &str) -> &'a str {
+        self.member(name)
+            .as_str()
+            .unwrap_or_else(|| panic!("{} is not a string", name))
+    }
+
+    /// Decodes a hexadecimal string member.
+    pub fn hex(&self, name: &str) -> Vec<u8> {
+        rsp::decode(self.str(name))
+    }
+
+    /// Gets a member that is a non-negative integer.
+    pub fn usize(&self, name: &str) -> usize {
+        let number = self
+            .member(name)
+            .as_f64()
+            .unwrap_or_else(|| panic!("{} is not a number", name));
+        number as usize
+    }
+
+    /// Gets a boolean member, or `false` if it is missing.
+    pub fn bool(&self, name: &str) -> bool {
+        self.get(name).is_some_and(|value| value.as_bool().unwrap())
+    }
+
+    fn member(&self, name: &str) -> &'a Value {
+        self.get(name)
+            .unwrap_or_else(|| panic!("missing member {}", name))
+    }
+}
+
+/// Reads a vector set and runs `f` on it. Files downloaded from an ACVP server wrap the vector
+/// set in an array after an object with the protocol version, so both forms are accepted.
+pub fn read<T>(path: &Path, f: impl FnOnce(Object) -> T) -> T {
+    let file = json::parse(&fs::read_to_string(path).unwrap()).unwrap();
+    let vector_set = match file.as_array() {
+        Some(array) => array
+            .iter()
+            .find(|value| value.get("testGroups").is_some())
+            .unwrap(),
+        None => &file,
+    };
+    f(Object(vector_set))
+}
@@ -0,0 +1,694 @@
+//! Runs the official NIST vector sets for SHA-1, SHA-2, SHA-3, HMAC, AES-GCM, HMAC_DRBG and
+//! CTR_DRBG, from CAVP response files or ACVP vector sets.
+//!
+//! The files are too large to keep in the repository, so they are read from `vectors/` next to
+//! this file, or from the directory named by the `CAVP_VECTORS` environment variable, and tests
+//! whose files are missing are skipped. See `vectors/README.md` for where to download them. Only
+//! small samples of each file format are kept in `vectors/samples/`, so that the parsers run
+//! without a download.
+//! Tests with parameters the crate doesn't support, such as GCM nonces that are not 96 bits
+//! long, are skipped too.
+#![cfg(feature = "cavp")]
+
+mod acvp;
+mod rsp;
+
+use acvp::Object;
+use crypto_pure::aead::Aead;
+use crypto_pure::aes::{Aes128, Aes192, Aes256, BlockCipher};
+use crypto_pure::ctr_drbg::CtrDrbg;
+use crypto_pure::gcm::Gcm;
+use crypto_pure::hmac::Hmac;
+use crypto_pure::hmac_drbg::HmacDrbg;
+#[cfg(feature = "legacy-hashes")]
+use crypto_pure::sha1::InsecureSha1;
+use crypto_pure::sha2::{HashFunction, Sha224, Sha256, Sha384, Sha512};
+use crypto_pure::sha3::{Sha3_224, Sha3_256, Sha3_384, Sha3_512};
+use std::convert::TryInto;
+use std::env;
+use std::fmt::Display;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Gets the directory holding the vector files.
+fn vectors_dir() -> PathBuf {
+    env::var_os("CAVP_VECTORS").map_or_else(
+        || Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/cavp/vectors"),
+        PathBuf::from,
+    )
+}
+
+/// Finds the vector files whose names satisfy `predicate`, in any subdirectory of the vectors
+/// directory.
+fn find(predicate: impl Fn(&str) -> bool) -> Vec<PathBuf> {
+    fn visit(dir: &Path, predicate: &dyn Fn(&str) -> bool, paths: &mut Vec<PathBuf>) {
+        let entries = match fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(_) => return,
+        };
+        for path in entries.map(|entry| entry.unwrap().path()) {
+            if path.is_dir() {
+                visit(&path, predicate, paths);
+            } else if path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(predicate)
+            {
+                paths.push(path);
+            }
+        }
+    }
+    let dir = &vectors_dir();
+    let mut paths = Vec::new();
+    visit(dir, &predicate, &mut paths);
+    paths.sort();
+    paths
+}
+
+/// Runs `f` on each file found by `find`, or notes that there are none. Panics if
+/// `CAVP_VECTORS` names a directory without any files, which is more likely a mistake than a
+/// request to skip every test.
+fn run(description: &str, predicate: impl Fn(&str) -> bool, mut f: impl FnMut(&Path)) {
+    let paths = find(predicate);
+    if paths.is_empty() {
+        assert!(
+            env::var_os("CAVP_VECTORS").is_none() || !find(|_| true).is_empty(),
+            "CAVP_VECTORS is set, but {} has no vector files",
+            vectors_dir().display()
+        );
+        eprintln!(
+            "skipping {}: no vector files in {}",
+            description,
+            vectors_dir().display()
+        );
+    }
+    for path in &paths {
+        f(path);
+    }
+}
+
+/// The outcomes of the tests of a vector file.
+struct Results {
+    path: PathBuf,
+    count: usize,
+    failures: Vec<String>,
+}
+
+impl Results {
+    fn new(path: &Path) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            count: 0,
+            failures: Vec::new(),
+        }
+    }
+
+    /// Records whether a test passed, or nothing if the test is unsupported.
+    fn record(&mut self, id: impl Display, passed: Option<bool>) {
+        if let Some(passed) = passed {
+            self.count += 1;
+            if !passed {
+                self.failures.push(id.to_string());
+            }
+        }
+    }
+
+    /// Panics listing the failing tests, if there are any, or if no tests were supported.
+    fn finish(self) {
+        let path = self.path.display();
+        assert_ne!(0, self.count, "{}: no supported tests", path);
+        assert!(
+            self.failures.is_empty(),
+            "{}: failed tests {:?}",
+            path,
+            self.failures
+        );
+    }
+}
+
+/// The implementations built on a hash function.
+struct HashImpls {
+    digest: fn(&[u8]) -> Vec<u8>,
+    hmac: fn(&[u8], &[u8]) -> Vec<u8>,
+    hmac_drbg: RunDrbg,
+}
+
+impl HashImpls {
+    fn new<H: HashFunction>() -> Self {
+        Self {
+            digest: digest::<H>,
+            hmac: hmac::<H>,
+            hmac_drbg: run_drbg::<HmacDrbg<H>>,
+        }
+    }
+
+    /// Gets the implementations for a hash function named as in ACVP, such as `SHA2-256`.
+    fn get(name: &str) -> Option<Self> {
+        match name {
+            #[cfg(feature = "legacy-hashes")]
+            "SHA-1" => Some(Self::new::<InsecureSha1>()),
+            "SHA2-224" => Some(Self::new::<Sha224>()),
+            "SHA2-256" => Some(Self::new::<Sha256>()),
+            "SHA2-384" => Some(Self::new::<Sha384>()),
+            "SHA2-512" => Some(Self::new::<Sha512>()),
+            "SHA3-224" => Some(Self::new::<Sha3_224>()),
+            "SHA3-256" => Some(Self::new::<Sha3_256>()),
+            "SHA3-384" => Some(Self::new::<Sha3_384>()),
+            "SHA3-512" => Some(Self::new::<Sha3_512>()),
+            _ => None,
+        }
+    }
+}
+
+fn digest<H: HashFunction>(message: &[u8]) -> Vec<u8> {
+    let mut hash_function = H::default();
+    hash_function.update(message);
+    let mut digest = vec![0; H::DIGEST_SIZE];
+    hash_function.write_digest(&mut digest);
+    digest
+}
+
+fn hmac<H: HashFunction>(key: &[u8], message: &[u8]) -> Vec<u8> {
+    let mut hmac = Hmac::<H>::new(key);
+    hmac.update(message);
+    hmac.tag().to_vec()
+}
+
+/// Decodes a message of `len` bits, or outputs `None` if it isn't a whole number of bytes.
+fn message(hex: &str, len: usize) -> Option<Vec<u8>> {
+    if !len.is_multiple_of(8) {
+        return None;
+    }
+    let mut message = rsp::decode(hex);
+    message.truncate(len / 8);
+    Some(message)
+}
+
+/// Runs the Monte Carlo test of SHAVS or SHA3VS from a seed, outputting the digest after each
+/// of the 100 checkpoints.
+fn monte_carlo(name: &str, digest: fn(&[u8]) -> Vec<u8>, seed: &[u8]) -> Vec<Vec<u8>> {
+    let mut seed = seed.to_vec();
+    let mut checkpoints = Vec::new();
+    for _ in 0..100 {
+        if name.starts_with("SHA3") {
+            for _ in 0..1000 {
+                seed = digest(&seed);
+            }
+        } else {
+            let mut window = [seed.clone(), seed.clone(), seed];
+            for _ in 0..1000 {
+                let next = digest(&window.concat());
+                window = [window[1].clone(), window[2].clone(), next];
+            }
+            let [_, _, last] = window;
+            seed = last;
+        }
+        checkpoints.push(seed.clone());
+    }
+    checkpoints
+}
+
+/// Gets the ACVP name of the hash function of a CAVP file such as `SHA3_256ShortMsg.rsp`.
+fn hash_name(file_name: &str) -> String {
+    let stem = ["ShortMsg.rsp", "LongMsg.rsp", "Monte.rsp"]
+        .iter()
+        .find_map(|suffix| file_name.strip_suffix(suffix))
+        .unwrap();
+    match stem.strip_prefix("SHA3_") {
+        Some(size) => format!("SHA3-{}", size),
+        None if stem == "SHA1" => "SHA-1".to_string(),
+        None => format!("SHA2-{}", &stem[3..]),
+    }
+}
+
+#[test]
+fn test_sha() {
+    let predicate = |name: &str| {
+        name.starts_with("SHA")
+            && ["ShortMsg.rsp", "LongMsg.rsp", "Monte.rsp"]
+                .iter()
+                .any(|suffix| name.ends_with(suffix))
+    };
+    run("SHA", predicate, |path| {
+        let file_name = path.file_name().unwrap().to_str().unwrap();
+        let name = &hash_name(file_name);
+        let hash = match HashImpls::get(name) {
+            Some(hash) => hash,
+            None => {
+                eprintln!("skipping {}: {} is not supported", path.display(), name);
+                return;
+            }
+        };
+        let mut results = Results::new(path);
+        for section in rsp::read(path) {
+            if !file_name.ends_with("Monte.rsp") {
+                for record in &section.records {
+                    let message = message(record.str("Msg"), record.usize("Len"));
+                    let passed = message.map(|message| (hash.digest)(&message) == record.hex("MD"));
+                    results.record(format!("{} {}", section.id(), record.id()), passed);
+                }
+                continue;
+            }
+            // The first record holds the seed, which SHA3VS calls the message.
+            let (seed, records) = section.records.split_first().unwrap();
+            let seed = rsp::decode(seed.get("Seed").unwrap_or_else(|| seed.str("Msg")));
+            let checkpoints = monte_carlo(name, hash.digest, &seed);
+            for (record, checkpoint) in records.iter().zip(&checkpoints) {
+                let id = format!("{} {}", section.id(), record.id());
+                results.record(id, Some(*checkpoint == record.hex("MD")));
+            }
+        }
+        results.finish();
+    });
+}
+
+#[test]
+fn test_hmac() {
+    run(
+        "HMAC",
+        |name| name == "HMAC.rsp",
+        |path| {
+            let mut results = Results::new(path);
+            for section in rsp::read(path) {
+                let name = match section.usize("L") {
+                    20 => "SHA-1",
+                    28 => "SHA2-224",
+                    32 => "SHA2-256",
+                    48 => "SHA2-384",
+                    64 => "SHA2-512",
+                    len => panic!("unknown L {}", len),
+                };
+                let hash = match HashImpls::get(name) {
+                    Some(hash) => hash,
+                    None => continue,
+                };
+                for record in &section.records {
+                    let tag = (hash.hmac)(&record.hex("Key"), &record.hex("Msg"));
+                    let id = format!("{} {}", section.id(), record.id());
+                    results.record(id, Some(tag.starts_with(&record.hex("Mac"))));
+                }
+            }
+            results.finish();
+        },
+    );
+}
+
+/// A GCM test, where `plaintext` is `None` if decryption must fail.
+struct GcmTest {
+    key: Vec<u8>,
+    nonce: Vec<u8>,
+    data: Vec<u8>,
+    plaintext: Option<Vec<u8>>,
+    ciphertext: Vec<u8>,
+    tag: Vec<u8>,
+}
+
+impl GcmTest {
+    /// Checks encryption if `encrypt` is true, and otherwise decryption. Only 96-bit nonces are
+    /// supported, as are only 128-bit tags for decryption.
+    fn check(&self, encrypt: bool) -> Option<bool> {
+        match self.key.len() {
+            16 => self.check_with::<Aes128>(encrypt),
+            24 => self.check_with::<Aes192>(encrypt),
+            _ => self.check_with::<Aes256>(encrypt),
+        }
+    }
+
+    fn check_with<E: BlockCipher>(&self, encrypt: bool) -> Option<bool> {
+        if self.nonce.len() != Gcm::<E>::NONCE_LEN {
+            return None;
+        }
+        let gcm = Gcm::<E>::new(&self.key).unwrap();
+        if encrypt {
+            let mut buffer = self.plaintext.clone().unwrap();
+            let tag = gcm.seal_detached(&self.nonce, &self.data, &mut buffer);
+            return Some(buffer == self.ciphertext && tag.unwrap().starts_with(&self.tag));
+        }
+        let tag = self.tag[..].try_into().ok()?;
+        let mut buffer = self.ciphertext.clone();
+        let opened = gcm.open_detached(&self.nonce, &self.data, &mut buffer, tag);
+        Some(match &self.plaintext {
+            Some(plaintext) => opened.is_ok() && buffer == *plaintext,
+            None => opened.is_err(),
+        })
+    }
+}
+
+#[test]
+fn test_gcm() {
+    let predicate = |name: &str| {
+        (name.starts_with("gcmEncryptExtIV") || name.starts_with("gcmDecrypt"))
+            && name.ends_with(".rsp")
+    };
+    run("GCM", predicate, |path| {
+        let encrypt = path
+            .file_name()
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .starts_with("gcmEncrypt");
+        let mut results = Results::new(path);
+        for section in rsp::read(path) {
+            for record in &section.records {
+                let test = GcmTest {
+                    key: record.hex("Key"),
+                    nonce: record.hex("IV"),
+                    data: record.hex("AAD"),
+                    plaintext: record.get("PT").map(rsp::decode),
+                    ciphertext: record.hex("CT"),
+                    tag: record.hex("Tag"),
+                };
+                let id = format!("{} {}", section.id(), record.id());
+                results.record(id, test.check(encrypt));
+            }
+        }
+        results.finish();
+    });
+}
+
+/// A DRBG that can be tested.
+trait Drbg {
+    /// The minimum length of an entropy input.
+    const MIN_ENTROPY_LEN: usize;
+
+    fn new(entropy_input: &[u8], nonce: &[u8], personalization_string: &[u8]) -> Self;
+    fn reseed(&mut self, entropy_input: &[u8], additional_input: &[u8]);
+    fn generate(&mut self, output: &mut [u8], additional_input: &[u8]);
+}
+
+impl<H: HashFunction> Drbg for HmacDrbg<H> {
+    const MIN_ENTROPY_LEN: usize = if H::DIGEST_SIZE < 32 {
+        H::DIGEST_SIZE
+    } else {
+        32
+    };
+
+    fn new(entropy_input: &[u8], nonce: &[u8], personalization_string: &[u8]) -> Self {
+        Self::new(entropy_input, nonce, personalization_string).unwrap()
+    }
+
+    fn reseed(&mut self, entropy_input: &[u8], additional_input: &[u8]) {
+        self.reseed(entropy_input, additional_input).unwrap();
+    }
+
+    fn generate(&mut self, output: &mut [u8], additional_input: &[u8]) {
+        self.generate(output, additional_input).unwrap();
+    }
+}
+
+impl Drbg for CtrDrbg {
+    const MIN_ENTROPY_LEN: usize = 32;
+
+    fn new(entropy_input: &[u8], nonce: &[u8], personalization_string: &[u8]) -> Self {
+        Self::new(entropy_input, nonce, personalization_string).unwrap()
+    }
+
+    fn reseed(&mut self, entropy_input: &[u8], additional_input: &[u8]) {
+        self.reseed(entropy_input, additional_input).unwrap();
+    }
+
+    fn generate(&mut self, output: &mut [u8], additional_input: &[u8]) {
+        self.generate(output, additional_input).unwrap();
+    }
+}
+
+/// A function running a DRBG test, or outputting `None` if the DRBG doesn't support it.
+type RunDrbg = fn(&DrbgTest) -> Option<Vec<u8>>;
+
+/// A call to a DRBG after instantiation.
+enum DrbgStep {
+    Reseed {
+        entropy_input: Vec<u8>,
+        additional_input: Vec<u8>,
+    },
+    Generate {
+        additional_input: Vec<u8>,
+    },
+}
+
+/// A DRBG test, whose expected output is that of the last call to generate. With prediction
+/// resistance, each call to generate is preceded by a reseed with its additional input.
+struct DrbgTest {
+    entropy_input: Vec<u8>,
+    nonce: Vec<u8>,
+    personalization_string: Vec<u8>,
+    steps: Vec<DrbgStep>,
+    output_len: usize,
+}
+
+impl DrbgTest {
+    /// Adds a call to generate, which is made after a reseed if there is an entropy input for
+    /// prediction resistance.
+    fn generate(&mut self, additional_input: Vec<u8>, entropy_input: Option<Vec<u8>>) {
+        if let Some(entropy_input) = entropy_input {
+            self.steps.push(DrbgStep::Reseed {
+                entropy_input,
+                additional_input,
+            });
+            self.steps.push(DrbgStep::Generate {
+                additional_input: Vec::new(),
+            });
+        } else {
+            self.steps.push(DrbgStep::Generate { additional_input });
+        }
+    }
+}
+
+/// Runs a DRBG test, or outputs `None` if an entropy input is too short for the DRBG.
+fn run_drbg<D: Drbg>(test: &DrbgTest) -> Option<Vec<u8>> {
+    let too_short = |entropy_input: &[u8]| entropy_input.len() < D::MIN_ENTROPY_LEN;
+    if too_short(&test.entropy_input)
+        || test.steps.iter().any(|step| match step {
+            DrbgStep::Reseed { entropy_input, .. } => too_short(entropy_input),
+            DrbgStep::Generate { .. } => false,
+        })
+    {
+        return None;
+    }
+    let mut drbg = D::new(
+        &test.entropy_input,
+        &test.nonce,
+        &test.personalization_string,
+    );
+    let mut output = vec![0; test.output_len];
+    for step in &test.steps {
+        match step {
+            DrbgStep::Reseed {
+                entropy_input,
+                additional_input,
+            } => drbg.reseed(entropy_input, additional_input),
+            DrbgStep::Generate { additional_input } => drbg.generate(&mut output, additional_input),
+        }
+    }
+    Some(output)
+}
+
+/// Gets the function running tests of a DRBG named as in CAVP files, such as `SHA-256` or
+/// `AES-256 use df`.
+fn cavp_drbg(name: &str) -> Option<RunDrbg> {
+    if name == "AES-256 use df" {
+        return Some(run_drbg::<CtrDrbg>);
+    }
+    let name = match name.strip_prefix("SHA-") {
+        Some("1") => "SHA-1".to_string(),
+        Some(size) => format!("SHA2-{}", size),
+        None => return None,
+    };
+    HashImpls::get(&name).map(|hash| hash.hmac_drbg)
+}
+
+#[test]
+fn test_drbg() {
+    let predicate = |name: &str| name == "HMAC_DRBG.rsp" || name == "CTR_DRBG.rsp";
+    run("DRBG", predicate, |path| {
+        let mut results = Results::new(path);
+        for section in rsp::read(path) {
+            let run_drbg = match cavp_drbg(section.name()) {
+                Some(run_drbg) => run_drbg,
+                None => continue,
+            };
+            for record in &section.records {
+                let mut test = DrbgTest {
+                    entropy_input: record.hex("EntropyInput"),
+                    nonce: record.hex("Nonce"),
+                    personalization_string: record.hex("PersonalizationString"),
+                    steps: Vec::new(),
+                    output_len: section.usize("ReturnedBitsLen") / 8,
+                };
+                let mut entropy_input = Vec::new();
+                let mut additional_input = None;
+                for (name, value) in record.fields() {
+                    match name {
+                        "EntropyInputReseed" => entropy_input = rsp::decode(value),
+                        "AdditionalInputReseed" => test.steps.push(DrbgStep::Reseed {
+                            entropy_input: entropy_input.clone(),
+                            additional_input: rsp::decode(value),
+                        }),
+                        // With prediction resistance, each additional input is followed by the
+                        // entropy input of the reseed before the call to generate.
+                        "AdditionalInput"
+                            if section.param("PredictionResistance") == Some("True") =>
+                        {
+                            additional_input = Some(rsp::decode(value));
+                        }
+                        "AdditionalInput" => test.generate(rsp::decode(value), None),
+                        "EntropyInputPR" => {
+                            let additional_input = additional_input.take().unwrap();
+                            test.generate(additional_input, Some(rsp::decode(value)));
+                        }
+                        _ => {}
+                    }
+                }
+                let passed = run_drbg(&test).map(|output| output == record.hex("ReturnedBits"));
+                results.record(format!("{} {}", section.id(), record.id()), passed);
+            }
+        }
+        results.finish();
+    });
+}
+
+/// Checks the tests of an ACVP vector set for a hash function.
+fn check_acvp_hash(vector_set: Object, hash: &HashImpls, results: &mut Results) {
+    let name = vector_set.str("algorithm");
+    for group in vector_set.array("testGroups") {
+        let test_type = group.str("testType");
+        let standard = group
+            .get("mctVersion")
+            .is_none_or(|version| version.as_str() == Some("standard"));
+        for test in group.array("tests") {
+            let id = test.usize("tcId");
+            match test_type {
+                "AFT" => {
+                    let message = message(test.str("msg"), test.usize("len"));
+                    let passed = message.map(|message| (hash.digest)(&message) == test.hex("md"));
+                    results.record(id, passed);
+                }
+                "MCT" if standard => {
+                    let seed = test.hex("msg");
+                    let checkpoints = monte_carlo(name, hash.digest, &seed);
+                    let passed = test
+                        .array("resultsArray")
+                        .zip(&checkpoints)
+                        .all(|(result, checkpoint)| *checkpoint == result.hex("md"));
+                    results.record(id, Some(passed));
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Checks the tests of an ACVP vector set for HMAC.
+fn check_acvp_hmac(vector_set: Object, hash: &HashImpls, results: &mut Results) {
+    for group in vector_set.array("testGroups") {
+        for test in group.array("tests") {
+            let tag = (hash.hmac)(&test.hex("key"), &test.hex("msg"));
+            results.record(test.usize("tcId"), Some(tag.starts_with(&test.hex("mac"))));
+        }
+    }
+}
+
+/// Checks the tests of an ACVP vector set for AES-GCM.
+fn check_acvp_gcm(vector_set: Object, results: &mut Results) {
+    for group in vector_set.array("testGroups") {
+        let encrypt = group.str("direction") == "encrypt";
+        let supported = group.str("ivGen") == "external"
+            && group.usize("payloadLen").is_multiple_of(8)
+            && group.usize("aadLen").is_multiple_of(8);
+        for test in group.array("tests") {
+            let id = test.usize("tcId");
+            let test_passed = test
+                .get("testPassed")
+                .is_none_or(|passed| passed.as_bool().unwrap());
+            let test = GcmTest {
+                key: test.hex("key"),
+                nonce: test.hex("iv"),
+                data: test.hex("aad"),
+                plaintext: test_passed.then(|| test.hex("pt")),
+                ciphertext: test.hex("ct"),
+                tag: test.hex("tag"),
+            };
+            let passed = if supported { test.check(encrypt) } else { None };
+            results.record(id, passed);
+        }
+    }
+}
+
+/// Checks the tests of an ACVP vector set for a DRBG.
+fn check_acvp_drbg(vector_set: Object, results: &mut Results) {
+    let ctr = vector_set.str("algorithm") == "ctrDRBG";
+    for group in vector_set.array("testGroups") {
+        let mode = group.str("mode");
+        let run_drbg: RunDrbg = if ctr {
+            if mode != "AES-256" || !group.bool("derFunc") {
+                continue;
+            }
+            run_drbg::<CtrDrbg>
+        } else {
+            match HashImpls::get(mode) {
+                Some(hash) => hash.hmac_drbg,
+                None => continue,
+            }
+        };
+        let prediction_resistance = group.bool("predResistance");
+        for test in group.array("tests") {
+            let mut drbg_test = DrbgTest {
+                entropy_input: test.hex("entropyInput"),
+                nonce: test.hex("nonce"),
+                personalization_string: test.hex("persoString"),
+                steps: Vec::new(),
+                output_len: group.usize("returnedBitsLen") / 8,
+            };
+            for input in test.array("otherInput") {
+                let entropy_input = input.hex("entropyInput");
+                let additional_input = input.hex("additionalInput");
+                if input.str("intendedUse") == "reSeed" {
+                    drbg_test.steps.push(DrbgStep::Reseed {
+                        entropy_input,
+                        additional_input,
+                    });
+                } else {
+                    let entropy_input = prediction_resistance.then_some(entropy_input);
+                    drbg_test.generate(additional_input, entropy_input);
+                }
+            }
+            let passed = run_drbg(&drbg_test).map(|output| output == test.hex("returnedBits"));
+            results.record(test.usize("tcId"), passed);
+        }
+    }
+}
+
+#[test]
+fn test_acvp() {
+    run(
+        "ACVP",
+        |name| name == "internalProjection.json",
+        |path| {
+            let mut results = Results::new(path);
+            acvp::read(path, |vector_set| {
+                let algorithm = vector_set.str("algorithm");
+                match algorithm {
+                    "ACVP-AES-GCM" => check_acvp_gcm(vector_set, &mut results),
+                    "hmacDRBG" | "ctrDRBG" => check_acvp_drbg(vector_set, &mut results),
+                    _ => match algorithm.strip_prefix("HMAC-") {
+                        Some(name) => {
+                            if let Some(hash) = HashImpls::get(name) {
+                                check_acvp_hmac(vector_set, &hash, &mut results);
+                            }
+                        }
+                        None => {
+                            if let Some(hash) = HashImpls::get(algorithm) {
+                                check_acvp_hash(vector_set, &hash, &mut results);
+                            }
+                        }
+                    },
+                }
+            });
+            if results.count == 0 {
+                eprintln!("skipping {}: not supported", path.display());
+                return;
+            }
+            results.finish();
+        },
+    );
+}
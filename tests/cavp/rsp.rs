@@ -0,0 +1,157 @@
+//! A parser for the response (`.rsp`) files of the NIST Cryptographic Algorithm Validation
+//! Program (CAVP).
+//!
+//! A response file is a sequence of sections, each starting with bracketed parameters such as
+//! `[L = 32]` or `[SHA-256]` and followed by records of `Name = value` lines separated by blank
+//! lines. Lines starting with `#` are comments, and a line without `=`, such as `FAIL`, is a
+//! field with an empty value.
+use crypto_pure::encoding::hex;
+use std::fs;
+use std::path::Path;
+
+/// The parameters shared by a run of records, and the records themselves.
+pub struct Section {
+    params: Vec<(String, String)>,
+    pub records: Vec<Record>,
+}
+
+impl Section {
+    /// Gets a parameter, or the empty string for a bare parameter such as `[SHA-256]`.
+    pub fn param(&self, name: &str) -> Option<&str> {
+        find(&self.params, name)
+    }
+
+    /// Gets the name of the first parameter, which names the mechanism in DRBG files, as in
+    /// `[SHA-256]`.
+    pub fn name(&self) -> &str {
+        &self.params[0].0
+    }
+
+    /// Gets an identifier of the section for failure messages.
+    pub fn id(&self) -> String {
+        let params = self
+            .params
+            .iter()
+            .map(|(name, value)| match value.as_str() {
+                "" => format!("[{}]", name),
+                value => format!("[{} = {}]", name, value),
+            });
+        params.collect()
+    }
+
+    /// Gets a parameter that is a non-negative integer.
+    pub fn usize(&self, name: &str) -> usize {
+        let param = self
+            .param(name)
+            .unwrap_or_else(|| panic!("missing parameter {}", name));
+        param.parse().unwrap()
+    }
+}
+
+/// The fields of a record, in order. Some records repeat a field, such as `AdditionalInput` in
+/// DRBG files.
+pub struct Record(Vec<(String, String)>);
+
+impl Record {
+    /// Gets the fields in order.
+    pub fn fields(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.0
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+    }
+
+    /// Gets the first field with a name, if there is one.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        find(&self.0, name)
+    }
+
+    /// Gets the first field with a name.
+    pub fn str(&self, name: &str) -> &str {
+        self.get(name)
+            .unwrap_or_else(|| panic!("missing field {}", name))
+    }
+
+    /// Decodes the first field with a name as hexadecimal.
+    pub fn hex(&self, name: &str) -> Vec<u8> {
+        decode(self.str(name))
+    }
+
+    /// Gets the first field with a name as a non-negative integer.
+    pub fn usize(&self, name: &str) -> usize {
+        self.str(name).parse().unwrap()
+    }
+
+    /// Gets an identifier of the record for failure messages.
+    pub fn id(&self) -> String {
+        self.0
+            .first()
+            .map_or_else(String::new, |(name, value)| format!("{} = {}", name, value))
+    }
+}
+
+/// Decodes a hexadecimal value.
+pub fn decode(value: &str) -> Vec<u8> {
+    let mut bytes = vec![0; value.len() / 2];
+    hex::decode_to_slice(value, &mut bytes).unwrap();
+    bytes
+}
+
+/// Parses the contents of a response file.
+pub fn parse(contents: &str) -> Vec<Section> {
+    let mut sections = Vec::new();
+    let mut params = Vec::new();
+    let mut records = Vec::new();
+    let mut fields = Vec::new();
+    let mut in_params = false;
+    for line in contents.lines().map(str::trim) {
+        if let Some(param) = line.strip_prefix('[') {
+            if !fields.is_empty() {
+                records.push(Record(fields));
+                fields = Vec::new();
+            }
+            // A new run of parameters starts a new section, and sections without records are
+            // dropped.
+            if !in_params {
+                if !records.is_empty() {
+                    sections.push(Section { params, records });
+                    records = Vec::new();
+                }
+                params = Vec::new();
+            }
+            params.push(split(param.trim_end_matches(']')));
+            in_params = true;
+        } else if line.is_empty() || line.starts_with('#') {
+            if !fields.is_empty() {
+                records.push(Record(fields));
+                fields = Vec::new();
+            }
+        } else {
+            fields.push(split(line));
+            in_params = false;
+        }
+    }
+    if !fields.is_empty() {
+        records.push(Record(fields));
+    }
+    if !records.is_empty() {
+        sections.push(Section { params, records });
+    }
+    sections
+}
+
+/// Reads and parses a response file.
+pub fn read(path: &Path) -> Vec<Section> {
+    parse(&fs::read_to_string(path).unwrap())
+}
+
+fn split(line: &str) -> (String, String) {
+    let (name, value) = line.split_once('=').unwrap_or((line, ""));
+    (name.trim().to_string(), value.trim().to_string())
+}
+
+fn find<'a>(fields: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    fields
+        .iter()
+        .find(|(field, _)| field == name)
+        .map(|(_, value)| value.as_str())
+}
@@ -0,0 +1,184 @@
+//! Runs the `crypto-practice` binary on files in a temporary directory and checks its output
+//! against the library.
+#![cfg(feature = "std")]
+
+use crypto_pure::age::Identity;
+use crypto_pure::encoding::hex;
+use crypto_pure::encoding::pem::{self, Pem};
+use crypto_pure::hmac::hmac_sha256;
+use crypto_pure::pkcs8::{self, PrivateKey};
+use crypto_pure::rand::SystemRandom;
+use crypto_pure::secret::SecretKey;
+use crypto_pure::sha2::{sha256, sha512};
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Output, Stdio};
+
+/// A temporary directory that is removed when dropped.
+struct TempDir(PathBuf);
+
+impl TempDir {
+    fn new(name: &str) -> Self {
+        let dir =
+            std::env::temp_dir().join(format!("crypto-practice-{}-{}", name, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        TempDir(dir)
+    }
+
+    /// Writes a file into the directory, outputting its path.
+    fn write(&self, name: &str, contents: &[u8]) -> String {
+        let path = self.path(name);
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    fn path(&self, name: &str) -> String {
+        self.0.join(name).to_str().unwrap().to_string()
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+fn run(args: &[&str], stdin: &[u8]) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_crypto-practice"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(stdin).unwrap();
+    child.wait_with_output().unwrap()
+}
+
+fn stdout(args: &[&str], stdin: &[u8]) -> String {
+    let output = run(args, stdin);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn test_hash() {
+    let dir = TempDir::new("hash");
+    let first = &dir.write("first", b"first file");
+    let second = &dir.write("second", &[0x42; 200_000]);
+    let expected = format!(
+        "{}  {}\n{}  {}\n",
+        hex::encode(&sha256(b"first file")),
+        first,
+        hex::encode(&sha256(&[0x42; 200_000])),
+        second
+    );
+    assert_eq!(expected, stdout(&["hash", first, second], b""));
+
+    let expected = format!("{}  -\n", hex::encode(&sha512(b"from stdin")));
+    let args = &["hash", "--algorithm", "sha512"];
+    assert_eq!(expected, stdout(args, b"from stdin"));
+}
+
+#[test]
+fn test_hmac() {
+    let dir = TempDir::new("hmac");
+    let key = &dir.write("key", b"Jefe");
+    // RFC 4231, Test Case 2
+    let expected = "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843  -\n";
+    let message = b"what do ya want for nothing?";
+    assert_eq!(expected, stdout(&["hmac", "--key", "4a656665"], message));
+    assert_eq!(expected, stdout(&["hmac", "--key-file", key], message));
+    assert_eq!(
+        &hex::encode(&hmac_sha256(b"Jefe", message)),
+        &expected[..64]
+    );
+}
+
+#[test]
+fn test_encrypt_decrypt() {
+    let dir = TempDir::new("age");
+    let identity = Identity::generate(&SystemRandom::new());
+    let identities = &dir.write(
+        "identities",
+        format!("# created: now\n{}\n", identity.encode()).as_bytes(),
+    );
+    let message = &vec![0x42; 100_000];
+    let plaintext = &dir.write("plaintext", message);
+    let ciphertext = &dir.path("ciphertext");
+    let recipient = &identity.recipient().encode();
+    let args = &[
+        "encrypt",
+        "--recipient",
+        recipient,
+        "--output",
+        ciphertext,
+        plaintext,
+    ];
+    stdout(args, b"");
+    assert_ne!(message, &fs::read(ciphertext).unwrap());
+
+    let args = &["decrypt", "--identity", identities, ciphertext];
+    assert_eq!(message, &stdout(args, b"").into_bytes());
+
+    let other = &dir.write(
+        "other",
+        Identity::generate(&SystemRandom::new()).encode().as_bytes(),
+    );
+    let output = run(&["decrypt", "--identity", other, ciphertext], b"");
+    assert_eq!(Some(2), output.status.code());
+}
+
+#[test]
+fn test_sign_verify() {
+    let dir = TempDir::new("sig");
+    let keys = [
+        PrivateKey::Ed25519(SecretKey::new([0x42; 32])),
+        PrivateKey::P256(SecretKey::new([0x42; 32])),
+        PrivateKey::P384(SecretKey::new([0x42; 48])),
+    ];
+    let message = &dir.write("message", b"message");
+    let other = &dir.write("other", b"other message");
+    let signature = &dir.path("signature");
+    for key in &keys {
+        let private_key = pkcs8::encode_private_key(key).unwrap();
+        let public_key = pkcs8::encode_public_key(&key.public_key().unwrap());
+        let private_key = pem::encode(&Pem::new("PRIVATE KEY", private_key.to_vec()).unwrap());
+        let public_key = pem::encode(&Pem::new("PUBLIC KEY", public_key).unwrap());
+        let private_key = &dir.write("private_key.pem", private_key.as_bytes());
+        let public_key = &dir.write("public_key.pem", public_key.as_bytes());
+
+        stdout(
+            &["sign", "--key", private_key, "--output", signature, message],
+            b"",
+        );
+        let args = &["verify", "--key", public_key, "--signature", signature];
+        assert_eq!(
+            "Verified OK\n",
+            stdout(&[&args[..], &[message]].concat(), b"")
+        );
+        assert_eq!("Verified OK\n", stdout(args, b"message"));
+        let output = run(&[&args[..], &[other]].concat(), b"");
+        assert_eq!(Some(1), output.status.code());
+        assert_eq!(&b"Verification failure\n"[..], &output.stdout[..]);
+    }
+}
+
+#[test]
+fn test_usage() {
+    assert_eq!(Some(2), run(&[], b"").status.code());
+    assert_eq!(Some(2), run(&["unknown"], b"").status.code());
+    assert_eq!(Some(2), run(&["hash", "--key", "00"], b"").status.code());
+    assert_eq!(Some(2), run(&["hash", "-a", "sha512"], b"").status.code());
+    let output = run(&["hash", "--algorithm", "md4"], b"");
+    assert_eq!(Some(2), output.status.code());
+    assert_eq!(
+        "crypto-practice: unknown algorithm md4\n",
+        String::from_utf8(output.stderr).unwrap()
+    );
+}
@@ -0,0 +1,136 @@
+//! Compares SHA-2, HMAC, GHASH and AES against the RustCrypto implementations for random inputs
+//! fed in random chunks, with message lengths concentrated around the block and padding
+//! boundaries where streaming bugs hide, such as 111, 112 and 128 bytes for SHA-512.
+#![cfg(feature = "differential")]
+
+use aes::cipher::generic_array::GenericArray;
+use aes::cipher::{BlockDecrypt, BlockEncrypt, KeyInit};
+use crypto_pure::aes::{Aes128, Aes192, Aes256, BlockCipher};
+use crypto_pure::ghash::GHash;
+use crypto_pure::hmac::Hmac;
+use crypto_pure::sha2::{HashFunction, Sha224, Sha256, Sha384, Sha512};
+use ghash::universal_hash::UniversalHash;
+use hmac::Mac;
+use proptest::collection::vec;
+use proptest::prelude::*;
+use proptest::sample::select;
+use sha2::Digest;
+
+/// The lengths around which padding or block handling changes for some function: where the
+/// length no longer fits in the last block of SHA-256 or SHA-512, and multiples of the block
+/// sizes.
+const BOUNDARIES: [usize; 6] = [56, 64, 112, 128, 192, 256];
+
+/// Generates a message, usually with a length within 2 bytes of one of `BOUNDARIES`.
+fn message() -> impl Strategy<Value = Vec<u8>> {
+    let boundary_len =
+        (select(&BOUNDARIES[..]), 0..5usize).prop_map(|(len, offset)| len + offset - 2);
+    prop_oneof![
+        1 => vec(any::<u8>(), 0..300),
+        3 => boundary_len.prop_flat_map(|len| vec(any::<u8>(), len)),
+    ]
+}
+
+/// Generates the lengths of the chunks to feed a message in, after which the rest is fed.
+fn chunks() -> impl Strategy<Value = Vec<usize>> {
+    vec(0..150usize, 0..8)
+}
+
+/// Feeds `input` to `update` in chunks of the given lengths, and then the rest.
+fn feed(input: &[u8], chunks: &[usize], mut update: impl FnMut(&[u8])) {
+    let mut input = input;
+    for &len in chunks {
+        let (chunk, rest) = input.split_at(len.min(input.len()));
+        update(chunk);
+        input = rest;
+    }
+    update(input);
+}
+
+fn check_digest<H: HashFunction, R: Digest>(
+    message: &[u8],
+    chunks: &[usize],
+) -> Result<(), TestCaseError> {
+    let mut hash_function = H::default();
+    feed(message, chunks, |chunk| hash_function.update(chunk));
+    let digest = &mut [0; 64][..H::DIGEST_SIZE];
+    hash_function.write_digest(digest);
+    prop_assert_eq!(&R::digest(message)[..], &digest[..]);
+    Ok(())
+}
+
+fn check_hmac<H: HashFunction, R: Mac + KeyInit>(
+    key: &[u8],
+    message: &[u8],
+    chunks: &[usize],
+) -> Result<(), TestCaseError> {
+    let mut hmac = Hmac::<H>::new(key);
+    feed(message, chunks, |chunk| hmac.update(chunk));
+    let mut expected = <R as Mac>::new_from_slice(key).unwrap();
+    expected.update(message);
+    prop_assert_eq!(&expected.finalize().into_bytes()[..], &hmac.tag()[..]);
+    Ok(())
+}
+
+fn check_block_cipher<E: BlockCipher, R: BlockEncrypt + BlockDecrypt + KeyInit>(
+    key: &[u8],
+    block: &[u8; 16],
+) -> Result<(), TestCaseError> {
+    let cipher = E::new(&key[..E::KEY_LEN]).unwrap();
+    let expected = R::new_from_slice(&key[..E::KEY_LEN]).unwrap();
+    let mut expected_block = GenericArray::clone_from_slice(block);
+    expected.encrypt_block(&mut expected_block);
+    let encrypted = cipher.encrypt_block(block);
+    prop_assert_eq!(&expected_block[..], &encrypted[..]);
+    expected.decrypt_block(&mut expected_block);
+    prop_assert_eq!(&block[..], &expected_block[..]);
+    let mut expected_block = GenericArray::clone_from_slice(block);
+    expected.decrypt_block(&mut expected_block);
+    prop_assert_eq!(&expected_block[..], &cipher.decrypt_block(block)[..]);
+    Ok(())
+}
+
+proptest! {
+    #[test]
+    fn test_sha2(message in message(), chunks in chunks()) {
+        check_digest::<Sha224, sha2::Sha224>(&message, &chunks)?;
+        check_digest::<Sha256, sha2::Sha256>(&message, &chunks)?;
+        check_digest::<Sha384, sha2::Sha384>(&message, &chunks)?;
+        check_digest::<Sha512, sha2::Sha512>(&message, &chunks)?;
+    }
+
+    #[test]
+    fn test_hmac(key in message(), message in message(), chunks in chunks()) {
+        check_hmac::<Sha256, hmac::Hmac<sha2::Sha256>>(&key, &message, &chunks)?;
+        check_hmac::<Sha384, hmac::Hmac<sha2::Sha384>>(&key, &message, &chunks)?;
+        check_hmac::<Sha512, hmac::Hmac<sha2::Sha512>>(&key, &message, &chunks)?;
+    }
+
+    #[test]
+    fn test_ghash(
+        key in any::<[u8; 16]>(),
+        data in message(),
+        data_chunks in chunks(),
+        ciphertext in message(),
+        ciphertext_chunks in chunks(),
+    ) {
+        let mut ghash = GHash::new(&key);
+        feed(&data, &data_chunks, |chunk| ghash.update_aad(chunk));
+        feed(&ciphertext, &ciphertext_chunks, |chunk| ghash.update_ciphertext(chunk));
+
+        // GCM pads the data and the ciphertext to whole blocks, followed by their lengths in bits.
+        let mut expected = ghash::GHash::new(&key.into());
+        expected.update_padded(&data);
+        expected.update_padded(&ciphertext);
+        let lengths = ((8 * data.len() as u128) << 64) | (8 * ciphertext.len() as u128);
+        expected.update_padded(&lengths.to_be_bytes());
+        prop_assert_eq!(&expected.finalize()[..], &ghash.finalize()[..]);
+    }
+
+    #[test]
+    fn test_aes(key in any::<[u8; 32]>(), block in any::<[u8; 16]>()) {
+        check_block_cipher::<Aes128, aes::Aes128>(&key, &block)?;
+        check_block_cipher::<Aes192, aes::Aes192>(&key, &block)?;
+        check_block_cipher::<Aes256, aes::Aes256>(&key, &block)?;
+    }
+}
@@ -0,0 +1,196 @@
+//! Runs the Wycheproof test vectors (https://github.com/C2SP/wycheproof), which cover the edge
+//! cases, malleability and malformed inputs that hand-copied vectors tend to miss.
+//!
+//! The vector files are copied unmodified into `testvectors/`. To cover another primitive, copy
+//! its files there and add a test that runs them with `vectors::run`.
+#![cfg(feature = "std")]
+
+mod vectors;
+
+use crypto_pure::aead::Aead;
+use crypto_pure::aes::{Aes128, Aes192, Aes256};
+use crypto_pure::curve25519;
+use crypto_pure::ed25519;
+use crypto_pure::error::Error;
+use crypto_pure::gcm::Gcm;
+use crypto_pure::hkdf::Hkdf;
+use crypto_pure::hmac::{hmac_sha256, hmac_sha384, hmac_sha512};
+use crypto_pure::p256;
+use crypto_pure::p384;
+use crypto_pure::rand::SystemRandom;
+use crypto_pure::rsa::{PrivateKey, PublicKey};
+use crypto_pure::secp256k1;
+use crypto_pure::sha2::{HashFunction, Sha256, Sha384, Sha512};
+use vectors::{run, run_skipping, Object};
+
+#[test]
+fn test_aes_gcm() {
+    let skipped = run_skipping("aes_gcm_test.json", |group, test| {
+        let key = &test.hex("key");
+        let (nonce, data) = (&test.hex("iv"), &test.hex("aad"));
+        let sealed = &[test.hex("ct"), test.hex("tag")].concat();
+        let opened = match key.len() {
+            16 => Gcm::<Aes128>::new(key).unwrap().open(nonce, data, sealed),
+            24 => Gcm::<Aes192>::new(key).unwrap().open(nonce, data, sealed),
+            _ => Gcm::<Aes256>::new(key).unwrap().open(nonce, data, sealed),
+        };
+        // Only 96-bit nonces are supported, so the tests of other sizes are skipped, whether
+        // valid or not, after checking that the nonce is rejected.
+        if group.usize("ivSize") != 96 {
+            assert_eq!(Err(Error::InvalidLength), opened);
+            return None;
+        }
+        Some(opened.is_ok_and(|message| message == test.hex("msg")))
+    });
+    // 119 of the 316 tests have nonces of other sizes.
+    assert_eq!(119, skipped);
+}
+
+fn check_hmac(test: Object, tag: &[u8]) -> bool {
+    let expected = &test.hex("tag");
+    tag[..expected.len()] == expected[..]
+}
+
+#[test]
+fn test_hmac() {
+    run("hmac_sha256_test.json", |_, test| {
+        check_hmac(test, &hmac_sha256(&test.hex("key"), &test.hex("msg")))
+    });
+    run("hmac_sha384_test.json", |_, test| {
+        check_hmac(test, &hmac_sha384(&test.hex("key"), &test.hex("msg")))
+    });
+    run("hmac_sha512_test.json", |_, test| {
+        check_hmac(test, &hmac_sha512(&test.hex("key"), &test.hex("msg")))
+    });
+}
+
+fn check_hkdf<H: HashFunction>(test: Object) -> bool {
+    let size = test.usize("size");
+    let mut okm = vec![0; size];
+    let hkdf = Hkdf::<H>::extract(&test.hex("salt"), &test.hex("ikm"));
+    match hkdf.expand(&test.hex("info"), &mut okm) {
+        Ok(()) => okm == test.hex("okm"),
+        // Outputs longer than 255 blocks are not defined, and must be rejected as too long.
+        Err(error) => {
+            assert!(size > 255 * H::DIGEST_SIZE);
+            assert_eq!(Error::InvalidLength, error);
+            false
+        }
+    }
+}
+
+#[test]
+fn test_hkdf() {
+    run("hkdf_sha256_test.json", |_, test| {
+        check_hkdf::<Sha256>(test)
+    });
+    run("hkdf_sha384_test.json", |_, test| {
+        check_hkdf::<Sha384>(test)
+    });
+    run("hkdf_sha512_test.json", |_, test| {
+        check_hkdf::<Sha512>(test)
+    });
+}
+
+/// Gets the uncompressed public key of an ECDSA test group.
+fn ec_public_key(group: Object) -> Vec<u8> {
+    group.object("publicKey").hex("uncompressed")
+}
+
+#[test]
+fn test_ecdsa_p256() {
+    run("ecdsa_secp256r1_sha256_p1363_test.json", |group, test| {
+        p256::verify(&test.hex("msg"), &test.hex("sig"), &ec_public_key(group))
+    });
+    run("ecdsa_secp256r1_sha256_test.json", |group, test| {
+        p256::signature_from_der(&test.hex("sig")).is_ok_and(|signature| {
+            p256::verify(&test.hex("msg"), &signature, &ec_public_key(group))
+        })
+    });
+}
+
+#[test]
+fn test_ecdsa_p384() {
+    run("ecdsa_secp384r1_sha384_p1363_test.json", |group, test| {
+        p384::verify(&test.hex("msg"), &test.hex("sig"), &ec_public_key(group))
+    });
+    run("ecdsa_secp384r1_sha384_test.json", |group, test| {
+        p384::signature_from_der(&test.hex("sig")).is_ok_and(|signature| {
+            p384::verify(&test.hex("msg"), &signature, &ec_public_key(group))
+        })
+    });
+}
+
+/// Only the Bitcoin variant is run, since `verify` rejects signatures with a high S as Bitcoin
+/// does.
+#[test]
+fn test_ecdsa_secp256k1() {
+    run("ecdsa_secp256k1_sha256_bitcoin_test.json", |group, test| {
+        secp256k1::signature_from_der(&test.hex("sig")).is_ok_and(|signature| {
+            secp256k1::verify(&test.hex("msg"), &signature, &ec_public_key(group))
+        })
+    });
+}
+
+#[test]
+fn test_ed25519() {
+    run("ed25519_test.json", |group, test| {
+        let public_key = &group.object("publicKey").hex("pk");
+        ed25519::verify(&test.hex("msg"), &test.hex("sig"), public_key)
+    });
+}
+
+#[test]
+fn test_x25519() {
+    run("x25519_test.json", |_, test| {
+        // An all-zero shared secret, from a public key of low order, is rejected.
+        curve25519::x25519(&test.hex("private"), &test.hex("public"))
+            .is_ok_and(|shared_secret| shared_secret[..] == test.hex("shared")[..])
+    });
+}
+
+/// Gets the public key of an RSA test group.
+fn rsa_public_key(group: Object) -> PublicKey {
+    let public_key = group.object("publicKey");
+    PublicKey::new(
+        &public_key.hex("modulus"),
+        &public_key.hex("publicExponent"),
+    )
+    .unwrap()
+}
+
+#[test]
+fn test_rsa_pkcs1v15() {
+    run("rsa_signature_2048_sha256_test.json", |group, test| {
+        rsa_public_key(group).verify_pkcs1v15::<Sha256>(&test.hex("msg"), &test.hex("sig"))
+    });
+}
+
+#[test]
+fn test_rsa_pss() {
+    run("rsa_pss_2048_sha256_mgf1_32_test.json", |group, test| {
+        rsa_public_key(group).verify_pss::<Sha256>(&test.hex("msg"), &test.hex("sig"))
+    });
+}
+
+#[test]
+fn test_rsa_oaep() {
+    let rng = &SystemRandom::new();
+    run(
+        "rsa_oaep_2048_sha256_mgf1sha256_test.json",
+        |group, test| {
+            let key = group.object("privateKey");
+            let private_key = PrivateKey::from_components(
+                &key.hex("modulus"),
+                &key.hex("publicExponent"),
+                &key.hex("privateExponent"),
+                &key.hex("prime1"),
+                &key.hex("prime2"),
+            )
+            .unwrap();
+            private_key
+                .decrypt_oaep::<Sha256>(&test.hex("ct"), &test.hex("label"), rng)
+                .is_ok_and(|message| message == test.hex("msg"))
+        },
+    );
+}
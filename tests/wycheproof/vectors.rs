@@ -0,0 +1,113 @@
+//! A loader for Wycheproof test vector files, built on the crate's own JSON parser.
+use crypto_pure::encoding::hex;
+use crypto_pure::encoding::json::{self, Value};
+use std::fs;
+use std::path::Path;
+
+/// A JSON object of a test vector file, such as a test group or a test.
+#[derive(Clone, Copy)]
+pub struct Object<'a>(&'a Value);
+
+impl<'a> Object<'a> {
+    /// Gets a member that is itself an object, such as the public key of a group.
+    pub fn object(&self, name: &str) -> Object<'a> {
+        Object(self.member(name))
+    }
+
+    /// Gets a string member.
+    pub fn str(&self, name: &str) -> &'a str {
+        self.member(name)
+            .as_str()
+            .unwrap_or_else(|| panic!("{} is not a string", name))
+    }
+
+    /// Decodes a hexadecimal string member.
+    pub fn hex(&self, name: &str) -> Vec<u8> {
+        let string = self.str(name);
+        let mut bytes = vec![0; string.len() / 2];
+        hex::decode_to_slice(string, &mut bytes).unwrap();
+        bytes
+    }
+
+    /// Gets a member that is a non-negative integer, which some files encode as a string.
+    pub fn usize(&self, name: &str) -> usize {
+        match self.member(name) {
+            Value::Number(number) => *number as usize,
+            Value::String(string) => string.parse().unwrap(),
+            _ => panic!("{} is not a number", name),
+        }
+    }
+
+    fn member(&self, name: &str) -> &'a Value {
+        self.0
+            .get(name)
+            .unwrap_or_else(|| panic!("missing member {}", name))
+    }
+}
+
+/// Runs every test of the file `testvectors/<name>`. For each test, `check` is given the test
+/// group and the test, and outputs whether the implementation accepted the input and produced
+/// the expected output.
+///
+/// Tests with the result `valid` must be accepted and those with `invalid` must be rejected,
+/// while those with `acceptable` may be either, as long as `check` doesn't panic.
+///
+/// # Panics
+///
+/// Panics listing the IDs of the failing tests, if there are any, or if the file doesn't have
+/// the number of tests it declares.
+pub fn run(name: &str, mut check: impl FnMut(Object, Object) -> bool) {
+    let skipped = run_skipping(name, |group, test| Some(check(group, test)));
+    assert_eq!(0, skipped);
+}
+
+/// Runs the tests of the file `testvectors/<name>` like `run`, except that `check` outputs `None`
+/// for a test that covers an unsupported parameter, which is neither passed nor failed. The
+/// number of skipped tests is logged and output, so the caller can check that no more are
+/// skipped than expected.
+///
+/// # Panics
+///
+/// Panics in the same cases as `run`.
+pub fn run_skipping(name: &str, mut check: impl FnMut(Object, Object) -> Option<bool>) -> usize {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/wycheproof/testvectors")
+        .join(name);
+    let file = json::parse(&fs::read_to_string(path).unwrap()).unwrap();
+    let file = Object(&file);
+    let mut count = 0;
+    let mut skipped = 0;
+    let mut failures = Vec::new();
+    for group in file.member("testGroups").as_array().unwrap() {
+        let group = Object(group);
+        for test in group.member("tests").as_array().unwrap() {
+            let test = Object(test);
+            count += 1;
+            let accepted = match check(group, test) {
+                Some(accepted) => accepted,
+                None => {
+                    skipped += 1;
+                    continue;
+                }
+            };
+            let passed = match test.str("result") {
+                "valid" => accepted,
+                "invalid" => !accepted,
+                "acceptable" => true,
+                result => panic!("unknown result {}", result),
+            };
+            if !passed {
+                failures.push(test.usize("tcId"));
+            }
+        }
+    }
+    assert_eq!(file.usize("numberOfTests"), count, "{}", name);
+    assert!(failures.is_empty(), "{}: failed tests {:?}", name, failures);
+    if skipped > 0 {
+        eprintln!(
+            "{}: skipped {} of {} tests as unsupported",
+            name, skipped, count
+        );
+    }
+    skipped
+}